@@ -59,6 +59,7 @@ fn main() {
         endpoint: Some("postgresql://localhost:5432/octopus".to_string()),
         last_check: "2024-01-15 10:30:00".to_string(),
         consecutive_failures: 0,
+        ..Default::default()
     };
     let health_card = ui_components::health_check_card(&health_check);
     println!("{health_card}\n");
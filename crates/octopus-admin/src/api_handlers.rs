@@ -10,19 +10,22 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{Method, StatusCode},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::auth::AdminOperator;
 use crate::handlers::AppState;
 use crate::models::{
-    ActivityLogEntry, AnalyticsMetrics, ConfigItem, FarpServiceInfo, LatencyPercentiles, LogQuery,
-    PerformanceMetrics, RouteConfig, RouteInfo, RouteMetric, SecurityEvent, SystemInfo,
-    TimeSeriesPoint, UpstreamClusterInfo, UpstreamInstanceInfo,
+    ActivityLogEntry, AnalyticsMetrics, CircuitAction, CircuitActionRequest, ConfigItem,
+    DebugCaptureQuery, ExplainRequest, FarpServiceInfo, LatencyPercentiles, LogQuery,
+    PerformanceMetrics, RateLimitExemptionRequest, RateLimitKeyQuery, RouteConfig, RouteInfo,
+    RouteMetric, SecurityEvent, SystemInfo, TimeSeriesPoint, UpstreamClusterInfo,
+    UpstreamInstanceInfo,
 };
 
 /// Lazily-initialized system info provider for CPU/memory metrics
@@ -128,6 +131,15 @@ pub async fn api_realtime_metrics_handler(State(state): State<Arc<AppState>>) ->
     Json(stats)
 }
 
+/// Get per-plugin execution metrics: invocations, errors and latency
+/// GET /admin/api/metrics/plugins
+pub async fn api_plugin_metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let plugins = state.metrics.as_ref().map_or_else(Vec::new, |m| {
+        octopus_metrics::MetricsSnapshot::from_collector(m).plugins
+    });
+    Json(plugins)
+}
+
 /// Get time series data for charts
 /// GET /admin/api/metrics/timeseries?metric=requests&period=1h
 pub async fn api_timeseries_handler(
@@ -427,6 +439,22 @@ pub async fn api_plugin_toggle_handler(
     Json(serde_json::json!({"success": false, "error": "Plugin not found"}))
 }
 
+/// Get the JSON Schema of a plugin's configuration (null if it declares none)
+/// GET /admin/api/plugins/:id/schema
+pub async fn api_plugin_schema_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(ref pm) = state.plugin_manager else {
+        return Json(serde_json::json!({"error": "Plugin manager not available"}));
+    };
+
+    match pm.config_schema(&id) {
+        Ok(schema) => Json(serde_json::json!({"id": id, "schema": schema})),
+        Err(_) => Json(serde_json::json!({"error": "Plugin not found", "id": id})),
+    }
+}
+
 /// Update plugin configuration
 /// PUT /admin/api/plugins/:id/config
 pub async fn api_plugin_config_handler(
@@ -435,7 +463,14 @@ pub async fn api_plugin_config_handler(
     Json(config): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     if let Some(ref pm) = state.plugin_manager {
-        if let Err(e) = pm.reload(&id, config).await {
+        if let Err(e) = pm.reconfigure(&id, config).await {
+            if let octopus_plugin_runtime::PluginRuntimeError::InvalidConfig { errors, .. } = &e {
+                return Json(serde_json::json!({
+                    "success": false,
+                    "error": "Plugin configuration does not match its schema",
+                    "errors": errors,
+                }));
+            }
             return Json(
                 serde_json::json!({"success": false, "error": format!("Failed to reload plugin config: {}", e)}),
             );
@@ -490,7 +525,10 @@ pub async fn api_logs_handler(
                     "{} {} → {} ({:.1}ms)",
                     e.method, e.path, e.status, e.latency_ms
                 ),
-                details: Some(format!("Upstream: {}", e.upstream)),
+                details: Some(match e.note {
+                    Some(ref note) => format!("Upstream: {} ({note})", e.upstream),
+                    None => format!("Upstream: {}", e.upstream),
+                }),
                 source: Some("proxy".to_string()),
             })
             .collect()
@@ -621,6 +659,555 @@ pub async fn api_config_update_handler(
     }))
 }
 
+// ============================================================================
+// Maintenance Mode Endpoints
+// ============================================================================
+
+/// Get maintenance mode settings
+/// GET /admin/api/maintenance
+pub async fn api_maintenance_get_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.maintenance.settings())
+}
+
+/// Update maintenance mode; fields absent from the body keep their value,
+/// so `{"enabled": true}` just flips the switch.
+/// PUT /admin/api/maintenance
+pub async fn api_maintenance_update_handler(
+    State(state): State<Arc<AppState>>,
+    operator: Option<Extension<AdminOperator>>,
+    Json(update): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let serde_json::Value::Object(update) = update else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": "Expected a JSON object"})),
+        );
+    };
+
+    let current = state.maintenance.settings();
+    let mut merged = serde_json::to_value(&current).unwrap_or_else(|_| serde_json::json!({}));
+    if let Some(fields) = merged.as_object_mut() {
+        fields.extend(update);
+    }
+    let settings = match serde_json::from_value::<octopus_core::MaintenanceSettings>(merged) {
+        Ok(settings) => settings,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    serde_json::json!({"success": false, "error": format!("Invalid maintenance settings: {}", e)}),
+                ),
+            )
+        }
+    };
+    if let Err(e) = state.maintenance.set(settings.clone()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        );
+    }
+
+    let operator = operator.map_or_else(|| "anonymous".to_string(), |Extension(op)| op.0);
+    tracing::warn!(
+        target: octopus_core::AUDIT_LOG_TARGET,
+        operator = %operator,
+        enabled = settings.enabled,
+        routes = ?settings.routes,
+        "Maintenance mode updated"
+    );
+    if let Some(ref log) = state.activity_log {
+        let toggle = match (current.enabled, settings.enabled) {
+            (false, true) => "maintenance on",
+            (true, false) => "maintenance off",
+            _ => "maintenance settings updated",
+        };
+        log.add_entry(
+            octopus_metrics::ActivityEntry::new(
+                Method::PUT,
+                "/admin/api/maintenance".to_string(),
+                StatusCode::OK,
+                std::time::Duration::ZERO,
+                String::new(),
+            )
+            .with_note(format!("{toggle} by {operator}")),
+        );
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({"success": true, "maintenance": settings})),
+    )
+}
+
+// ============================================================================
+// Debug Tap Endpoints
+// ============================================================================
+
+/// The debug tap's capture log, or the 404 answered while no tap is configured
+fn debug_tap_log(
+    state: &AppState,
+) -> Result<Arc<octopus_metrics::DebugTapLog>, (StatusCode, Json<serde_json::Value>)> {
+    state
+        .debug_tap
+        .read()
+        .ok()
+        .and_then(|slot| slot.clone())
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"success": false, "error": "Debug tap is not enabled"})),
+            )
+        })
+}
+
+/// List debug-tapped exchanges, most recent first
+/// GET /admin/api/debug/captures?limit=20
+pub async fn api_debug_captures_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DebugCaptureQuery>,
+) -> impl IntoResponse {
+    match debug_tap_log(&state) {
+        Ok(log) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "captures": log.recent(query.limit.unwrap_or(20)),
+            })),
+        ),
+        Err(not_found) => not_found,
+    }
+}
+
+/// Get one debug-tapped exchange
+/// GET /admin/api/debug/captures/:id
+pub async fn api_debug_capture_get_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    let log = match debug_tap_log(&state) {
+        Ok(log) => log,
+        Err(not_found) => return not_found,
+    };
+    match log.get(id) {
+        Some(capture) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"success": true, "capture": capture})),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": format!("No capture {id}")})),
+        ),
+    }
+}
+
+/// Drop all debug-tapped exchanges
+/// DELETE /admin/api/debug/captures
+pub async fn api_debug_captures_clear_handler(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    match debug_tap_log(&state) {
+        Ok(log) => {
+            let cleared = log.count();
+            log.clear();
+            tracing::info!(cleared, "Debug tap captures cleared");
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({"success": true, "cleared": cleared})),
+            )
+        }
+        Err(not_found) => not_found,
+    }
+}
+
+// ============================================================================
+// Rate Limit Endpoints
+// ============================================================================
+
+/// The keyed rate limiter's per-key state, or the 404 answered while none
+/// is installed
+fn rate_limit_keys(
+    state: &AppState,
+) -> Result<Arc<dyn octopus_core::RateLimitKeys>, (StatusCode, Json<serde_json::Value>)> {
+    state
+        .rate_limits
+        .read()
+        .ok()
+        .and_then(|slot| slot.clone())
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(
+                    serde_json::json!({"success": false, "error": "Keyed rate limiting is not enabled"}),
+                ),
+            )
+        })
+}
+
+/// `key`'s buckets and exemption
+async fn rate_limit_key_state(
+    keys: &dyn octopus_core::RateLimitKeys,
+    key: &str,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let buckets = match keys.buckets(key).await {
+        Ok(buckets) => buckets,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": e.to_string()})),
+            )
+        }
+    };
+    let exempt_for = keys
+        .exemption(key)
+        .map(|left| humantime_serde::re::humantime::format_duration(left).to_string());
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "key": key,
+            "buckets": buckets,
+            "exempt_for": exempt_for,
+        })),
+    )
+}
+
+/// Record a manual rate-limit override in the audit log and activity feed
+fn audit_rate_limit_override(
+    state: &AppState,
+    method: Method,
+    path: String,
+    key: &str,
+    operator: &str,
+    action: &str,
+) {
+    tracing::warn!(
+        target: octopus_core::AUDIT_LOG_TARGET,
+        operator = %operator,
+        key = %key,
+        action,
+        "Rate limit manually overridden"
+    );
+    if let Some(ref log) = state.activity_log {
+        log.add_entry(
+            octopus_metrics::ActivityEntry::new(
+                method,
+                path,
+                StatusCode::OK,
+                std::time::Duration::ZERO,
+                String::new(),
+            )
+            .with_note(format!("rate limit {action} for {key} by {operator}")),
+        );
+    }
+}
+
+/// Get a key's remaining rate-limit budget; a key with no buckets hasn't
+/// been limited yet
+/// GET /admin/api/rate-limits?key=
+pub async fn api_rate_limit_get_handler(
+    State(state): State<Arc<AppState>>,
+    Query(RateLimitKeyQuery { key }): Query<RateLimitKeyQuery>,
+) -> impl IntoResponse {
+    match rate_limit_keys(&state) {
+        Ok(keys) => rate_limit_key_state(&*keys, &key).await,
+        Err(not_found) => not_found,
+    }
+}
+
+/// Refill a key's buckets, so its requests are let through again at once
+/// DELETE /admin/api/rate-limits?key=
+pub async fn api_rate_limit_reset_handler(
+    State(state): State<Arc<AppState>>,
+    Query(RateLimitKeyQuery { key }): Query<RateLimitKeyQuery>,
+    operator: Option<Extension<AdminOperator>>,
+) -> impl IntoResponse {
+    let keys = match rate_limit_keys(&state) {
+        Ok(keys) => keys,
+        Err(not_found) => return not_found,
+    };
+    let operator = operator.map_or_else(|| "anonymous".to_string(), |Extension(op)| op.0);
+    let reset = match keys.reset(&key).await {
+        Ok(reset) => reset,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": e.to_string()})),
+            )
+        }
+    };
+    audit_rate_limit_override(
+        &state,
+        Method::DELETE,
+        "/admin/api/rate-limits".to_string(),
+        &key,
+        &operator,
+        "reset",
+    );
+    let (status, Json(mut body)) = rate_limit_key_state(&*keys, &key).await;
+    body["reset"] = reset.into();
+    (status, Json(body))
+}
+
+/// Let a key through unlimited for a while, e.g. `{"duration": "15m"}`
+/// PUT /admin/api/rate-limits/exemption?key=
+pub async fn api_rate_limit_exempt_handler(
+    State(state): State<Arc<AppState>>,
+    Query(RateLimitKeyQuery { key }): Query<RateLimitKeyQuery>,
+    operator: Option<Extension<AdminOperator>>,
+    Json(req): Json<RateLimitExemptionRequest>,
+) -> impl IntoResponse {
+    let keys = match rate_limit_keys(&state) {
+        Ok(keys) => keys,
+        Err(not_found) => return not_found,
+    };
+    if req.duration.is_zero() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(
+                serde_json::json!({"success": false, "error": "Exemption duration must be positive"}),
+            ),
+        );
+    }
+    if let Err(e) = keys.exempt(&key, req.duration) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        );
+    }
+    let operator = operator.map_or_else(|| "anonymous".to_string(), |Extension(op)| op.0);
+    audit_rate_limit_override(
+        &state,
+        Method::PUT,
+        "/admin/api/rate-limits/exemption".to_string(),
+        &key,
+        &operator,
+        "exemption",
+    );
+    rate_limit_key_state(&*keys, &key).await
+}
+
+/// Lift a key's exemption before it expires
+/// DELETE /admin/api/rate-limits/exemption?key=
+pub async fn api_rate_limit_unexempt_handler(
+    State(state): State<Arc<AppState>>,
+    Query(RateLimitKeyQuery { key }): Query<RateLimitKeyQuery>,
+    operator: Option<Extension<AdminOperator>>,
+) -> impl IntoResponse {
+    let keys = match rate_limit_keys(&state) {
+        Ok(keys) => keys,
+        Err(not_found) => return not_found,
+    };
+    if !keys.unexempt(&key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": format!("{key} is not exempt")})),
+        );
+    }
+    let operator = operator.map_or_else(|| "anonymous".to_string(), |Extension(op)| op.0);
+    audit_rate_limit_override(
+        &state,
+        Method::DELETE,
+        "/admin/api/rate-limits/exemption".to_string(),
+        &key,
+        &operator,
+        "exemption lifted",
+    );
+    rate_limit_key_state(&*keys, &key).await
+}
+
+// ============================================================================
+// Request Explain Endpoint
+// ============================================================================
+
+/// Trace how a request would be handled, without proxying it: the route
+/// decision (and why other routes lost), extracted params, the upstream and
+/// its load-balancing strategy, the middleware chain and auth requirements.
+/// POST /admin/api/explain
+pub async fn api_explain_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ExplainRequest>,
+) -> impl IntoResponse {
+    let Some(router) = state.router.as_ref() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({"success": false, "error": "Router not available"})),
+        );
+    };
+    let Ok(method) = Method::from_bytes(req.method.trim().to_ascii_uppercase().as_bytes()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(
+                serde_json::json!({"success": false, "error": format!("Invalid method '{}'", req.method)}),
+            ),
+        );
+    };
+
+    let headers: HashMap<String, &str> = req
+        .headers
+        .iter()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.as_str()))
+        .collect();
+    let host = req
+        .host
+        .as_deref()
+        .or_else(|| headers.get("host").copied())
+        .map(octopus_core::strip_port)
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let path = req.path.split('?').next().unwrap_or_default();
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    };
+
+    let explanation = router.explain(&host, &method, &path);
+
+    let trace: Vec<serde_json::Value> = explanation
+        .routes
+        .iter()
+        .map(|t| {
+            serde_json::json!({
+                "method": t.route.method.as_str(),
+                "path": t.route.path,
+                "host": host_pattern(&t.route.host),
+                "upstream": t.route.upstream_name,
+                "priority": t.route.priority,
+                "verdict": t.verdict.as_str(),
+            })
+        })
+        .collect();
+
+    let (route, upstream, auth) = match &explanation.matched {
+        Some(m) => {
+            let r = &m.route;
+            let route = serde_json::json!({
+                "method": r.method.as_str(),
+                "path": r.path,
+                "host": host_pattern(&r.host),
+                "priority": r.priority,
+                "params": m.params,
+                "wildcard": m.wildcard,
+                "redirect_to": m.redirect_to,
+                "strip_prefix": r.strip_prefix,
+                "add_prefix": r.add_prefix,
+                "timeout_ms": r.timeout.map(|t| t.as_millis() as u64),
+                "rate_limit": r.rate_limit.map(|(requests, window)| serde_json::json!({
+                    "requests": requests,
+                    "window_secs": window.as_secs(),
+                })),
+                "cors_override": r.cors.is_some(),
+            });
+            let upstream = if r.convention.is_some() {
+                serde_json::json!({
+                    "name": serde_json::Value::Null,
+                    "derived_from_host": true,
+                })
+            } else {
+                let cluster = router.get_upstream(&r.upstream_name);
+                serde_json::json!({
+                    "name": r.upstream_name,
+                    "registered": cluster.is_some(),
+                    "strategy": cluster.as_ref().map(|c| format!("{:?}", c.strategy)),
+                    "instances": cluster.as_ref().map_or(0, |c| c.instances.len()),
+                    "healthy_instances": cluster.as_ref().map_or(0, |c| c.healthy_count()),
+                    "geo_upstreams": r.geo_upstreams,
+                    "fallback_upstream": r.fallback_upstream,
+                    "service": r.service,
+                })
+            };
+            let auth = explain_auth(&state, r, &method, &path, &headers);
+            (route, upstream, auth)
+        }
+        None => (
+            serde_json::Value::Null,
+            serde_json::Value::Null,
+            serde_json::Value::Null,
+        ),
+    };
+
+    let middleware = state
+        .middleware
+        .read()
+        .map(|names| names.clone())
+        .unwrap_or_default();
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "request": {"method": method.as_str(), "host": host, "path": path},
+            "matched": explanation.matched.is_some(),
+            "reason": explanation.reason,
+            "route": route,
+            "upstream": upstream,
+            "auth": auth,
+            "middleware": middleware,
+            "trace": trace,
+        })),
+    )
+}
+
+/// Auth decision for a matched route, mirroring the auth gateway's order:
+/// preflight, global skip paths, per-route skip, then provider resolution.
+fn explain_auth(
+    state: &AppState,
+    route: &octopus_router::Route,
+    method: &Method,
+    path: &str,
+    headers: &HashMap<String, &str>,
+) -> serde_json::Value {
+    let auth = state.config.as_ref().map(|c| &c.auth);
+    let skip_path = auth.is_some_and(|a| {
+        a.skip_paths.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => path == p,
+        })
+    });
+    let provider = route
+        .auth_provider
+        .clone()
+        .or_else(|| auth.and_then(|a| a.default_provider.clone()));
+    let global_enforce = auth.is_some_and(|a| a.global_enforce);
+
+    let (required, reason) = if *method == Method::OPTIONS {
+        (false, "CORS preflight requests skip authentication")
+    } else if skip_path {
+        (false, "path matches a global auth skip path")
+    } else if route.skip_auth {
+        (false, "route sets skip_auth")
+    } else if provider.is_some() {
+        (true, "route is authenticated by the provider")
+    } else if global_enforce {
+        (
+            true,
+            "auth is enforced globally but no provider is configured",
+        )
+    } else {
+        (false, "no auth provider applies and auth is not enforced")
+    };
+
+    serde_json::json!({
+        "required": required,
+        "reason": reason,
+        "provider": provider,
+        "require_roles": route.require_roles,
+        "require_scopes": route.require_scopes,
+        "authz_rule": route.authz_rule,
+        "credentials_present": headers.contains_key("authorization")
+            || headers.contains_key("x-api-key"),
+    })
+}
+
+/// Gateway-API-style rendering of a route's host scope.
+fn host_pattern(host: &octopus_router::HostMatch) -> String {
+    match host {
+        octopus_router::HostMatch::Any => "*".to_string(),
+        octopus_router::HostMatch::Wildcard(suffix) => format!("*{suffix}"),
+        octopus_router::HostMatch::Exact(host) => host.clone(),
+    }
+}
+
 // ============================================================================
 // System Information Endpoints
 // ============================================================================
@@ -800,6 +1387,77 @@ pub async fn api_circuits_list_handler(State(state): State<Arc<AppState>>) -> im
     Json(circuits)
 }
 
+/// Manually override the circuit breakers of an upstream's instances. `open`
+/// and `close` pin the state until a `reset` restores automatic behavior.
+/// POST /admin/api/circuit/:upstream
+pub async fn api_circuit_action_handler(
+    State(state): State<Arc<AppState>>,
+    Path(upstream): Path<String>,
+    operator: Option<Extension<AdminOperator>>,
+    Json(req): Json<CircuitActionRequest>,
+) -> impl IntoResponse {
+    let Some(ref cb) = state.circuit_breaker else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "Circuit breaker not available" })),
+        );
+    };
+    let Some(cluster) = state
+        .router
+        .as_ref()
+        .and_then(|router| router.get_upstream(&upstream))
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Upstream not found", "name": upstream })),
+        );
+    };
+    let operator = operator.map_or_else(|| "anonymous".to_string(), |Extension(op)| op.0);
+
+    let mut instances = Vec::with_capacity(cluster.instances.len());
+    for inst in &cluster.instances {
+        match req.action {
+            CircuitAction::Open => cb.force_open(&inst.id),
+            CircuitAction::Close => cb.force_close(&inst.id),
+            CircuitAction::Reset => cb.reset(&inst.id),
+        }
+        instances.push(serde_json::json!({
+            "id": inst.id,
+            "state": cb.get_state(&inst.id).to_string(),
+        }));
+    }
+
+    tracing::warn!(
+        operator = %operator,
+        upstream = %upstream,
+        action = req.action.as_str(),
+        "Audit: circuit breaker manually overridden"
+    );
+    if let Some(ref log) = state.activity_log {
+        log.add_entry(
+            octopus_metrics::ActivityEntry::new(
+                Method::POST,
+                format!("/admin/api/circuit/{upstream}"),
+                StatusCode::OK,
+                std::time::Duration::ZERO,
+                upstream.clone(),
+            )
+            .with_note(format!("circuit {} by {operator}", req.action.as_str())),
+        );
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "upstream": upstream,
+            "action": req.action,
+            "operator": operator,
+            "instances": instances,
+        })),
+    )
+}
+
 /// Get structured health check data
 /// GET /admin/api/health/checks
 pub async fn api_health_checks_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
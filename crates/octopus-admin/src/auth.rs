@@ -46,6 +46,11 @@ struct Claims {
     exp: usize,
 }
 
+/// Identity of the authenticated admin operator, attached as a request
+/// extension so write endpoints can audit-log who acted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminOperator(pub String);
+
 /// Self-contained admin authentication backend.
 #[derive(Clone)]
 pub struct AdminAuth {
@@ -286,12 +291,13 @@ pub async fn api_auth_me_handler(
 ///
 /// Pass-through when auth is disabled (`state.admin_auth` is `None`). Otherwise
 /// gates `/admin/api/*` (except `/admin/api/auth/*`) and `/admin/ws`, returning
-/// `401` with a JSON body when no valid session cookie is present. UI/static
+/// `401` with a JSON body when no valid session cookie is present, and attaches
+/// the session's [`AdminOperator`] otherwise. UI/static
 /// routes are intentionally left open so the login page can load; their data is
 /// gated at the API.
 pub async fn require_admin_session(
     State(state): State<Arc<AppState>>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Response {
     let Some(auth) = state.admin_auth.clone() else {
@@ -306,12 +312,12 @@ pub async fn require_admin_session(
         return next.run(req).await;
     }
 
-    let authorized = extract_cookie(req.headers(), SESSION_COOKIE)
+    let operator = extract_cookie(req.headers(), SESSION_COOKIE)
         .as_deref()
-        .and_then(|t| auth.verify_token(t))
-        .is_some();
+        .and_then(|t| auth.verify_token(t));
 
-    if authorized {
+    if let Some(operator) = operator {
+        req.extensions_mut().insert(AdminOperator(operator));
         next.run(req).await
     } else {
         (
@@ -39,6 +39,21 @@ pub struct AppState {
     pub farp_federation: Option<Arc<octopus_farp::SchemaFederation>>,
     /// Optional admin authentication (login/session). `None` = no auth enforced.
     pub admin_auth: Option<Arc<crate::auth::AdminAuth>>,
+    /// Maintenance mode switch shared with the request handler
+    pub maintenance: Arc<octopus_core::MaintenanceMode>,
+    /// Names of the gateway middleware chain, outermost first. Filled in by
+    /// the request handler once its chain is built.
+    pub middleware: Arc<std::sync::RwLock<Vec<String>>>,
+    /// Debug tap captures, when `gateway.debug_tap` is configured. Filled in
+    /// by the request handler once the tap is built.
+    pub debug_tap: Arc<std::sync::RwLock<Option<Arc<octopus_metrics::DebugTapLog>>>>,
+    /// Per-key state of the gateway's keyed rate limiter, when one is
+    /// installed. Filled in by whoever builds the limiter.
+    pub rate_limits: Arc<std::sync::RwLock<Option<Arc<dyn octopus_core::RateLimitKeys>>>>,
+    /// Shared state stores (e.g. Redis) the gateway depends on, by what they
+    /// back. Filled in by whoever builds the middleware that uses them.
+    pub state_backends:
+        Arc<std::sync::RwLock<Vec<(String, Arc<dyn octopus_state::BackendHealthSource>)>>>,
     /// Server start time for uptime calculation
     pub start_time: std::time::Instant,
 }
@@ -59,6 +74,11 @@ impl AppState {
             farp_registry: None,
             farp_federation: None,
             admin_auth: None,
+            maintenance: Arc::new(octopus_core::MaintenanceMode::default()),
+            middleware: Arc::default(),
+            debug_tap: Arc::default(),
+            rate_limits: Arc::default(),
+            state_backends: Arc::default(),
             start_time: std::time::Instant::now(),
         }
     }
@@ -208,6 +228,7 @@ pub struct HealthTemplate {
 #[template(path = "shadcn_plugins_enhanced.html")]
 pub struct PluginsTemplate {
     pub plugins: Vec<PluginInfo>,
+    pub plugin_metrics: Vec<octopus_metrics::PluginMetrics>,
     pub total_plugins: usize,
     pub active_plugins: usize,
     pub updates_available: usize,
@@ -342,8 +363,13 @@ pub async fn plugins_handler(State(state): State<Arc<AppState>>) -> impl IntoRes
     let plugins = build_plugins_from_state(&state);
     let active_plugins = plugins.iter().filter(|p| p.enabled).count();
 
+    let plugin_metrics = state.metrics.as_ref().map_or_else(Vec::new, |m| {
+        octopus_metrics::MetricsSnapshot::from_collector(m).plugins
+    });
+
     let template = PluginsTemplate {
         plugins: plugins.clone(),
+        plugin_metrics,
         total_plugins: plugins.len(),
         active_plugins,
         updates_available: 0,
@@ -605,73 +631,176 @@ pub(crate) fn route_to_info(
     }
 }
 
-/// Build health checks from health tracker + upstreams
+/// Build health checks from health tracker + upstreams, annotated with
+/// circuit breaker state and outlier (ejection) status per instance
 pub(crate) fn build_health_from_state(state: &AppState) -> Vec<HealthCheckInfo> {
     let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
+    // Instances marked unhealthy are skipped by load balancing (ejected)
+    let instance_health: std::collections::HashMap<String, bool> = state
+        .router
+        .as_ref()
+        .map(|router| {
+            router
+                .get_all_upstreams()
+                .iter()
+                .flat_map(|cluster| {
+                    cluster
+                        .instances
+                        .iter()
+                        .map(|inst| (inst.id.clone(), inst.is_healthy()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // (instance id, check) pairs so circuit state can be joined by id
+    let mut checks: Vec<(String, HealthCheckInfo)> = Vec::new();
+
     if let Some(ref ht) = state.health_tracker {
-        let snapshots = ht.get_all_snapshots();
-        if !snapshots.is_empty() {
-            return snapshots
-                .into_iter()
-                .map(|(id, snap)| {
-                    let status = if snap.error_rate > 0.5 {
-                        "critical"
-                    } else if snap.error_rate > 0.1 {
-                        "warning"
-                    } else {
-                        "passing"
-                    };
+        checks = ht
+            .get_all_snapshots()
+            .into_iter()
+            .map(|(id, snap)| {
+                let status = if snap.error_rate > 0.5 {
+                    "critical"
+                } else if snap.error_rate > 0.1 {
+                    "warning"
+                } else {
+                    "passing"
+                };
+
+                let consecutive_failures = snap.failed_requests as u32;
 
-                    let consecutive_failures = snap.failed_requests as u32;
+                let check = HealthCheckInfo {
+                    name: id.clone(),
+                    status: status.to_string(),
+                    response_time_ms: snap.avg_latency.as_millis() as u64,
+                    message: if status == "critical" {
+                        Some(format!("Error rate: {:.1}%", snap.error_rate * 100.0))
+                    } else {
+                        None
+                    },
+                    endpoint: Some(id.clone()),
+                    last_check: now.clone(),
+                    consecutive_failures,
+                    ..Default::default()
+                };
+                (id, check)
+            })
+            .collect();
+    }
 
-                    HealthCheckInfo {
-                        name: id.clone(),
-                        status: status.to_string(),
-                        response_time_ms: snap.avg_latency.as_millis() as u64,
-                        message: if status == "critical" {
-                            Some(format!("Error rate: {:.1}%", snap.error_rate * 100.0))
+    // Fall back to upstream instances if no health tracker data
+    if checks.is_empty() {
+        if let Some(ref router) = state.router {
+            for cluster in router.get_all_upstreams() {
+                for inst in &cluster.instances {
+                    let check = HealthCheckInfo {
+                        name: format!("{}/{}", cluster.name, inst.id),
+                        status: if inst.is_healthy() {
+                            "passing"
                         } else {
+                            "critical"
+                        }
+                        .to_string(),
+                        response_time_ms: 0,
+                        message: if inst.is_healthy() {
                             None
+                        } else {
+                            Some("Instance marked unhealthy".to_string())
                         },
-                        endpoint: Some(id),
+                        endpoint: Some(inst.base_url()),
                         last_check: now.clone(),
-                        consecutive_failures,
-                    }
-                })
-                .collect();
+                        consecutive_failures: u32::from(!inst.is_healthy()),
+                        ..Default::default()
+                    };
+                    checks.push((inst.id.clone(), check));
+                }
+            }
         }
     }
 
-    // Fall back to upstream instances if no health tracker data
-    if let Some(ref router) = state.router {
-        let mut checks = Vec::new();
-        for cluster in router.get_all_upstreams() {
-            for inst in &cluster.instances {
-                checks.push(HealthCheckInfo {
-                    name: format!("{}/{}", cluster.name, inst.id),
-                    status: if inst.is_healthy() {
-                        "passing"
-                    } else {
-                        "critical"
-                    }
-                    .to_string(),
-                    response_time_ms: 0,
-                    message: if inst.is_healthy() {
-                        None
-                    } else {
-                        Some("Instance marked unhealthy".to_string())
-                    },
-                    endpoint: Some(inst.base_url()),
-                    last_check: now.clone(),
-                    consecutive_failures: u32::from(!inst.is_healthy()),
-                });
+    if let Some(ref cb) = state.circuit_breaker {
+        let mut circuits: std::collections::BTreeMap<_, _> =
+            cb.get_all_metrics().into_iter().collect();
+        for (id, check) in &mut checks {
+            if let Some(metrics) = circuits.remove(id.as_str()) {
+                apply_circuit_state(check, &metrics);
             }
         }
-        return checks;
+        // Circuits for instances without any other health data
+        for (id, metrics) in circuits {
+            let mut check = HealthCheckInfo {
+                name: id.clone(),
+                status: "passing".to_string(),
+                endpoint: Some(id.clone()),
+                last_check: now.clone(),
+                consecutive_failures: metrics.failure_count as u32,
+                ..Default::default()
+            };
+            apply_circuit_state(&mut check, &metrics);
+            checks.push((id, check));
+        }
     }
 
-    vec![]
+    let mut checks: Vec<HealthCheckInfo> = checks
+        .into_iter()
+        .map(|(id, mut check)| {
+            check.ejected = instance_health.get(&id) == Some(&false);
+            check
+        })
+        .collect();
+
+    if let Ok(backends) = state.state_backends.read() {
+        for (name, backend) in backends.iter() {
+            let health = backend.health();
+            checks.push(HealthCheckInfo {
+                name: name.clone(),
+                status: match health.status {
+                    octopus_state::HealthStatus::Connected => "passing",
+                    octopus_state::HealthStatus::Degraded => "critical",
+                }
+                .to_string(),
+                message: health.last_error,
+                last_check: now.clone(),
+                consecutive_failures: health.consecutive_failures as u32,
+                ..Default::default()
+            });
+        }
+    }
+
+    checks
+}
+
+/// Fold a circuit breaker's state into a health check: an open circuit is
+/// critical, a half-open one at least a warning.
+fn apply_circuit_state(
+    check: &mut HealthCheckInfo,
+    metrics: &octopus_health::CircuitBreakerMetrics,
+) {
+    let changed_at = chrono::DateTime::<chrono::Utc>::from(metrics.last_transition)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+
+    match metrics.state {
+        octopus_health::CircuitState::Open => {
+            check.status = "critical".to_string();
+            check.message = Some(format!("Circuit open since {changed_at}"));
+        }
+        octopus_health::CircuitState::HalfOpen => {
+            if check.status == "passing" {
+                check.status = "warning".to_string();
+            }
+            check
+                .message
+                .get_or_insert_with(|| format!("Circuit half-open since {changed_at}"));
+        }
+        octopus_health::CircuitState::Closed => {}
+    }
+
+    check.circuit_state = Some(metrics.state.to_string());
+    check.circuit_changed_at = Some(changed_at);
 }
 
 /// Build plugin list from plugin manager (runtime)
@@ -720,6 +849,127 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    fn circuit_state() -> Arc<AppState> {
+        use octopus_core::{UpstreamCluster, UpstreamInstance};
+        use octopus_health::{CircuitBreaker, CircuitBreakerConfig};
+        use std::time::Duration;
+
+        let router = octopus_router::Router::new();
+        let mut cluster = UpstreamCluster::new("backend");
+        cluster.add_instance(UpstreamInstance::new("b1", "127.0.0.1", 9001));
+        let mut ejected = UpstreamInstance::new("b2", "127.0.0.1", 9002);
+        ejected.mark_unhealthy();
+        cluster.add_instance(ejected);
+        router.register_upstream(cluster);
+
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 0.5,
+            min_requests: 2,
+            open_timeout: Duration::ZERO,
+            half_open_max_requests: 1,
+            ..Default::default()
+        });
+
+        Arc::new(
+            AppState::new()
+                .with_router(Arc::new(router))
+                .with_circuit_breaker(Arc::new(breaker)),
+        )
+    }
+
+    async fn api_health(state: &Arc<AppState>) -> Vec<serde_json::Value> {
+        let response = api_health_handler(State(Arc::clone(state)))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn check<'a>(checks: &'a [serde_json::Value], name: &str) -> &'a serde_json::Value {
+        checks
+            .iter()
+            .find(|c| c["name"] == name)
+            .unwrap_or_else(|| panic!("no health check named {name}"))
+    }
+
+    #[tokio::test]
+    async fn test_api_health_reports_open_circuit_and_ejection() {
+        let state = circuit_state();
+        let cb = state.circuit_breaker.as_ref().unwrap();
+        cb.record_failure("b1");
+        cb.record_failure("b1");
+
+        let checks = api_health(&state).await;
+        let b1 = check(&checks, "backend/b1");
+        assert_eq!(b1["circuit_state"], "open");
+        assert_eq!(b1["status"], "critical");
+        assert!(b1["circuit_changed_at"].is_string());
+        assert!(b1["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Circuit open since"));
+        assert_eq!(b1["ejected"], false);
+
+        let b2 = check(&checks, "backend/b2");
+        assert_eq!(b2["ejected"], true);
+        assert!(b2["circuit_state"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_api_health_follows_circuit_transitions() {
+        let state = circuit_state();
+        let cb = state.circuit_breaker.as_ref().unwrap();
+        cb.record_failure("b1");
+        cb.record_failure("b1");
+        assert_eq!(
+            check(&api_health(&state).await, "backend/b1")["circuit_state"],
+            "open"
+        );
+
+        // Open timeout elapsed: the next request probes in half-open
+        assert!(cb.allow_request("b1"));
+        let b1 = check(&api_health(&state).await, "backend/b1").clone();
+        assert_eq!(b1["circuit_state"], "half-open");
+        assert_eq!(b1["status"], "warning");
+
+        cb.record_success("b1");
+        let b1 = check(&api_health(&state).await, "backend/b1").clone();
+        assert_eq!(b1["circuit_state"], "closed");
+        assert_eq!(b1["status"], "passing");
+        assert!(b1["message"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_api_health_reports_degraded_state_backend() {
+        #[derive(Debug)]
+        struct Unreachable;
+        impl octopus_state::BackendHealthSource for Unreachable {
+            fn health(&self) -> octopus_state::BackendHealth {
+                octopus_state::BackendHealth {
+                    status: octopus_state::HealthStatus::Degraded,
+                    consecutive_failures: 3,
+                    last_error: Some("connection refused".to_string()),
+                }
+            }
+        }
+
+        let state = Arc::new(AppState::new());
+        state
+            .state_backends
+            .write()
+            .unwrap()
+            .push(("idempotency_store".to_string(), Arc::new(Unreachable)));
+
+        let checks = api_health(&state).await;
+        let store = check(&checks, "idempotency_store");
+        assert_eq!(store["status"], "critical");
+        assert_eq!(store["message"], "connection refused");
+        assert_eq!(store["consecutive_failures"], 3);
+    }
+
     #[test]
     fn route_to_info_exposes_route_config() {
         use octopus_router::RouteBuilder;
@@ -138,7 +138,7 @@ pub struct RateLimitConfig {
 }
 
 /// Health check information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HealthCheckInfo {
     pub name: String,
     pub status: String, // "passing", "warning", "critical"
@@ -147,6 +147,74 @@ pub struct HealthCheckInfo {
     pub endpoint: Option<String>,
     pub last_check: String,
     pub consecutive_failures: u32,
+    /// Circuit breaker state ("closed", "open", "half-open"), if one is tracked
+    #[serde(default)]
+    pub circuit_state: Option<String>,
+    /// When the circuit last changed state
+    #[serde(default)]
+    pub circuit_changed_at: Option<String>,
+    /// Instance is marked unhealthy and skipped by load balancing
+    #[serde(default)]
+    pub ejected: bool,
+}
+
+/// Manual circuit breaker action for `POST /admin/api/circuit/:upstream`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CircuitAction {
+    /// Force open: requests fast-fail until reset
+    Open,
+    /// Force closed: requests pass regardless of failures until reset
+    Close,
+    /// Clear any override and restore automatic behavior
+    Reset,
+}
+
+impl CircuitAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CircuitAction::Open => "open",
+            CircuitAction::Close => "close",
+            CircuitAction::Reset => "reset",
+        }
+    }
+}
+
+/// Request body for a manual circuit breaker action
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitActionRequest {
+    pub action: CircuitAction,
+}
+
+/// Rate-limit key query parameter; a query rather than a path segment, so
+/// keys containing `/` need no escaping
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitKeyQuery {
+    /// The limiter key, e.g. a client address
+    pub key: String,
+}
+
+/// Request body for `PUT /admin/api/rate-limits/exemption?key=`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitExemptionRequest {
+    /// How long the key goes unlimited, e.g. `15m`
+    #[serde(with = "humantime_serde")]
+    pub duration: std::time::Duration,
+}
+
+/// Request body for `POST /admin/api/explain`: the request to trace
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExplainRequest {
+    /// HTTP method, e.g. `GET`
+    pub method: String,
+    /// Request path; a query string is ignored
+    pub path: String,
+    /// Request host; falls back to the `host` header, then to any host
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Request headers (used for the host and credential presence)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
 }
 
 /// Plugin information
@@ -181,6 +249,12 @@ pub struct LogQuery {
     pub search: Option<String>,
 }
 
+/// Debug capture query parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugCaptureQuery {
+    pub limit: Option<usize>,
+}
+
 /// System information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemInfo {
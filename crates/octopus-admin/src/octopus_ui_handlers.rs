@@ -152,6 +152,7 @@ pub async fn octopus_ui_dashboard_handler(
             endpoint: Some("postgresql://localhost:5432/octopus".to_string()),
             last_check: "2024-01-15 10:30:00".to_string(),
             consecutive_failures: 0,
+            ..Default::default()
         },
         HealthCheckInfo {
             name: "Redis Cache".to_string(),
@@ -161,6 +162,7 @@ pub async fn octopus_ui_dashboard_handler(
             endpoint: Some("redis://localhost:6379".to_string()),
             last_check: "2024-01-15 10:30:00".to_string(),
             consecutive_failures: 0,
+            ..Default::default()
         },
     ];
 
@@ -279,6 +281,7 @@ pub async fn octopus_ui_health_handler(State(_state): State<Arc<AppState>>) -> i
             endpoint: Some("postgresql://localhost:5432/octopus".to_string()),
             last_check: "2024-01-15 10:30:00".to_string(),
             consecutive_failures: 0,
+            ..Default::default()
         },
         HealthCheckInfo {
             name: "Redis Cache".to_string(),
@@ -288,6 +291,7 @@ pub async fn octopus_ui_health_handler(State(_state): State<Arc<AppState>>) -> i
             endpoint: Some("redis://localhost:6379".to_string()),
             last_check: "2024-01-15 10:30:00".to_string(),
             consecutive_failures: 0,
+            ..Default::default()
         },
     ];
 
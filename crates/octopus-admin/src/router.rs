@@ -9,15 +9,19 @@ use std::sync::Arc;
 use tower_http::services::{ServeDir, ServeFile};
 
 use crate::api_handlers::{
-    api_analytics_handler, api_circuits_list_handler, api_config_list_handler,
-    api_config_update_handler, api_farp_federated_openapi_handler, api_farp_service_detail_handler,
-    api_farp_services_handler, api_health_checks_handler, api_logs_handler, api_openapi_handler,
-    api_performance_metrics_handler, api_plugin_config_handler, api_plugin_get_handler,
-    api_plugin_toggle_handler, api_plugins_list_handler, api_realtime_metrics_handler,
-    api_route_create_handler, api_route_delete_handler, api_route_get_handler,
-    api_route_update_handler, api_routes_list_handler, api_security_events_handler,
-    api_services_list_handler, api_system_info_handler, api_timeseries_handler,
-    api_upstreams_list_handler,
+    api_analytics_handler, api_circuit_action_handler, api_circuits_list_handler,
+    api_config_list_handler, api_config_update_handler, api_debug_capture_get_handler,
+    api_debug_captures_clear_handler, api_debug_captures_handler, api_explain_handler,
+    api_farp_federated_openapi_handler, api_farp_service_detail_handler, api_farp_services_handler,
+    api_health_checks_handler, api_logs_handler, api_maintenance_get_handler,
+    api_maintenance_update_handler, api_openapi_handler, api_performance_metrics_handler,
+    api_plugin_config_handler, api_plugin_get_handler, api_plugin_metrics_handler,
+    api_plugin_schema_handler, api_plugin_toggle_handler, api_plugins_list_handler,
+    api_rate_limit_exempt_handler, api_rate_limit_get_handler, api_rate_limit_reset_handler,
+    api_rate_limit_unexempt_handler, api_realtime_metrics_handler, api_route_create_handler,
+    api_route_delete_handler, api_route_get_handler, api_route_update_handler,
+    api_routes_list_handler, api_security_events_handler, api_services_list_handler,
+    api_system_info_handler, api_timeseries_handler, api_upstreams_list_handler,
 };
 use crate::auth::{api_auth_login_handler, api_auth_logout_handler, api_auth_me_handler};
 use crate::handlers::{
@@ -72,6 +76,10 @@ impl DashboardRouter {
                 get(api_realtime_metrics_handler),
             )
             .route("/admin/api/metrics/timeseries", get(api_timeseries_handler))
+            .route(
+                "/admin/api/metrics/plugins",
+                get(api_plugin_metrics_handler),
+            )
             .route(
                 "/admin/api/metrics/performance",
                 get(api_performance_metrics_handler),
@@ -93,6 +101,10 @@ impl DashboardRouter {
                 "/admin/api/plugins/:id/config",
                 put(api_plugin_config_handler),
             )
+            .route(
+                "/admin/api/plugins/:id/schema",
+                get(api_plugin_schema_handler),
+            )
             // ===== Logs & Monitoring API =====
             .route("/admin/api/logs", get(api_logs_handler))
             .route("/admin/api/activity", get(api_activity_handler))
@@ -118,6 +130,10 @@ impl DashboardRouter {
             )
             .route("/admin/api/services", get(api_services_list_handler))
             .route("/admin/api/circuits", get(api_circuits_list_handler))
+            .route(
+                "/admin/api/circuit/:upstream",
+                post(api_circuit_action_handler),
+            )
             .route("/admin/api/health/checks", get(api_health_checks_handler))
             .route("/admin/api/openapi.json", get(api_openapi_handler))
             // ===== TLS / Certificates API =====
@@ -148,6 +164,31 @@ impl DashboardRouter {
                 "/admin/api/farp/schema/openapi",
                 get(api_farp_federated_openapi_handler),
             )
+            // ===== Maintenance Mode API =====
+            .route(
+                "/admin/api/maintenance",
+                get(api_maintenance_get_handler).put(api_maintenance_update_handler),
+            )
+            // ===== Debug Tap API =====
+            .route(
+                "/admin/api/debug/captures",
+                get(api_debug_captures_handler).delete(api_debug_captures_clear_handler),
+            )
+            .route(
+                "/admin/api/debug/captures/:id",
+                get(api_debug_capture_get_handler),
+            )
+            // ===== Rate Limit API =====
+            .route(
+                "/admin/api/rate-limits",
+                get(api_rate_limit_get_handler).delete(api_rate_limit_reset_handler),
+            )
+            .route(
+                "/admin/api/rate-limits/exemption",
+                put(api_rate_limit_exempt_handler).delete(api_rate_limit_unexempt_handler),
+            )
+            // ===== Request Explain API =====
+            .route("/admin/api/explain", post(api_explain_handler))
             // ===== System Information API =====
             .route("/admin/api/system/info", get(api_system_info_handler))
             // ===== Auth Configuration API =====
@@ -229,4 +270,402 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn maintenance_api_toggles_shared_state() {
+        let log = Arc::new(octopus_metrics::ActivityLog::new(10));
+        let state = Arc::new(AppState::new().with_activity_log(Arc::clone(&log)));
+        let app = DashboardRouter::build(Arc::clone(&state));
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("PUT")
+                    .uri("/admin/api/maintenance")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(
+                        r#"{"enabled": true, "retry_after": "2m"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let settings = state.maintenance.settings();
+        assert!(settings.enabled);
+        assert_eq!(settings.retry_after, std::time::Duration::from_secs(120));
+        // Fields absent from the body keep their value.
+        assert_eq!(
+            settings.message,
+            octopus_core::MaintenanceSettings::default().message
+        );
+        let entry = &log.recent_entries(1)[0];
+        assert_eq!(entry.note.as_deref(), Some("maintenance on by anonymous"));
+    }
+
+    #[tokio::test]
+    async fn maintenance_api_rejects_invalid_bypass_ips() {
+        let state = Arc::new(AppState::new());
+        let app = DashboardRouter::build(Arc::clone(&state));
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("PUT")
+                    .uri("/admin/api/maintenance")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(
+                        r#"{"enabled": true, "bypass_ips": ["10.0.0.1", "10.0.0.300"]}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(
+            body["error"].as_str().unwrap().contains("\"10.0.0.300\""),
+            "{body}"
+        );
+        // The rejected update leaves the current settings alone.
+        assert!(!state.maintenance.is_enabled());
+    }
+
+    #[tokio::test]
+    async fn debug_captures_api_serves_the_tap_log() {
+        let state = Arc::new(AppState::new());
+        let get = |uri: &str| {
+            axum::http::Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        // No tap configured
+        let app = DashboardRouter::build(Arc::clone(&state));
+        let response = app.oneshot(get("/admin/api/debug/captures")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let log = Arc::new(octopus_metrics::DebugTapLog::new(10));
+        let id = log.record(octopus_metrics::DebugCapture {
+            id: 0,
+            timestamp: 0,
+            subject: Some("support".to_string()),
+            method: "GET".to_string(),
+            uri: "/orders/7".to_string(),
+            status: 200,
+            request: Default::default(),
+            response: Default::default(),
+        });
+        *state.debug_tap.write().unwrap() = Some(log);
+
+        let app = DashboardRouter::build(Arc::clone(&state));
+        let response = app
+            .clone()
+            .oneshot(get(&format!("/admin/api/debug/captures/{id}")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["capture"]["uri"], "/orders/7");
+        assert_eq!(body["capture"]["subject"], "support");
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("DELETE")
+                    .uri("/admin/api/debug/captures")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.debug_tap.read().unwrap().as_ref().unwrap().count(), 0);
+    }
+
+    async fn explain(app: Router, body: &str) -> serde_json::Value {
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/admin/api/explain")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn explain_api_traces_parameterized_match_and_no_match() {
+        use octopus_core::{UpstreamCluster, UpstreamInstance};
+        use octopus_router::RouteBuilder;
+
+        let router = octopus_router::Router::new();
+        let mut cluster = UpstreamCluster::new("users");
+        cluster.add_instance(UpstreamInstance::new("u1", "127.0.0.1", 9001));
+        router.register_upstream(cluster);
+        for (method, path) in [
+            (http::Method::GET, "/users/:id"),
+            (http::Method::DELETE, "/users/:id"),
+        ] {
+            router
+                .add_route(
+                    RouteBuilder::new()
+                        .method(method)
+                        .path(path)
+                        .upstream_name("users")
+                        .require_roles(&["admin".to_string()])
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+        }
+        let state = Arc::new(AppState::new().with_router(Arc::new(router)));
+        *state.middleware.write().unwrap() = vec!["RequestId".into(), "AuthGateway".into()];
+        let app = DashboardRouter::build(Arc::clone(&state));
+
+        let body = explain(
+            app.clone(),
+            r#"{"method": "get", "path": "/users/42?x=1", "headers": {"Host": "API.example.com:8080"}}"#,
+        )
+        .await;
+        assert_eq!(body["matched"], true);
+        assert_eq!(body["request"]["host"], "api.example.com");
+        assert_eq!(body["route"]["path"], "/users/:id");
+        assert_eq!(body["route"]["params"]["id"], "42");
+        assert_eq!(body["upstream"]["name"], "users");
+        assert_eq!(body["upstream"]["strategy"], "RoundRobin");
+        assert_eq!(body["upstream"]["healthy_instances"], 1);
+        assert_eq!(body["auth"]["require_roles"][0], "admin");
+        assert_eq!(body["middleware"][1], "AuthGateway");
+        assert_eq!(body["trace"][0]["verdict"], "matched");
+        assert_eq!(body["trace"][1]["verdict"], "method_mismatch");
+
+        let body = explain(app, r#"{"method": "POST", "path": "/users/42"}"#).await;
+        assert_eq!(body["matched"], false);
+        assert!(body["route"].is_null());
+        assert_eq!(
+            body["reason"],
+            "path /users/42 is routed for DELETE, GET but not POST"
+        );
+    }
+
+    async fn circuit_action(app: Router, upstream: &str, action: &str) -> StatusCode {
+        app.oneshot(
+            axum::http::Request::builder()
+                .method("POST")
+                .uri(format!("/admin/api/circuit/{upstream}"))
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(format!(
+                    r#"{{"action": "{action}"}}"#
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn circuit_api_forces_and_resets_upstream_circuits() {
+        use octopus_core::{UpstreamCluster, UpstreamInstance};
+        use octopus_health::{CircuitBreaker, CircuitState};
+
+        let router = octopus_router::Router::new();
+        let mut cluster = UpstreamCluster::new("backend");
+        cluster.add_instance(UpstreamInstance::new("b1", "127.0.0.1", 9001));
+        router.register_upstream(cluster);
+        let breaker = Arc::new(CircuitBreaker::default_config());
+        let log = Arc::new(octopus_metrics::ActivityLog::new(10));
+        let state = Arc::new(
+            AppState::new()
+                .with_router(Arc::new(router))
+                .with_circuit_breaker(Arc::clone(&breaker))
+                .with_activity_log(Arc::clone(&log)),
+        );
+        let app = DashboardRouter::build(state);
+
+        assert_eq!(
+            circuit_action(app.clone(), "backend", "open").await,
+            StatusCode::OK
+        );
+        assert_eq!(breaker.get_state("b1"), CircuitState::Open);
+        assert!(!breaker.allow_request("b1"));
+        let entry = &log.recent_entries(1)[0];
+        assert_eq!(entry.note.as_deref(), Some("circuit open by anonymous"));
+
+        assert_eq!(
+            circuit_action(app.clone(), "backend", "reset").await,
+            StatusCode::OK
+        );
+        assert_eq!(breaker.get_state("b1"), CircuitState::Closed);
+        assert!(!breaker.get_metrics("b1").unwrap().manual);
+
+        assert_eq!(
+            circuit_action(app.clone(), "missing", "open").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            circuit_action(app, "backend", "explode").await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+
+    /// A keyed limiter holding one client whose single request has been spent
+    #[derive(Debug, Default)]
+    struct OneClient {
+        spent: std::sync::Mutex<bool>,
+        exemptions: octopus_core::RateLimitExemptions,
+    }
+
+    #[axum::async_trait]
+    impl octopus_core::RateLimitKeys for OneClient {
+        async fn buckets(
+            &self,
+            key: &str,
+        ) -> octopus_core::Result<Vec<octopus_core::RateLimitBucket>> {
+            if key != "10.0.0.1" {
+                return Ok(Vec::new());
+            }
+            let spent = *self.spent.lock().unwrap();
+            Ok(vec![octopus_core::RateLimitBucket {
+                route: None,
+                remaining: u32::from(!spent),
+                limit: 1,
+                reset_after: std::time::Duration::from_secs(if spent { 60 } else { 0 }),
+            }])
+        }
+
+        async fn reset(&self, key: &str) -> octopus_core::Result<usize> {
+            *self.spent.lock().unwrap() = false;
+            Ok(usize::from(key == "10.0.0.1"))
+        }
+
+        fn exempt(&self, key: &str, duration: std::time::Duration) -> octopus_core::Result<()> {
+            self.exemptions.grant(key, duration)
+        }
+
+        fn unexempt(&self, key: &str) -> bool {
+            self.exemptions.lift(key)
+        }
+
+        fn exemption(&self, key: &str) -> Option<std::time::Duration> {
+            // Whole seconds, as the limiters round their reports
+            self.exemptions
+                .remaining(key)
+                .map(|left| std::time::Duration::from_secs(left.as_secs() + 1))
+        }
+    }
+
+    async fn rate_limit_call(
+        app: Router,
+        method: &str,
+        uri: &str,
+        body: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn rate_limit_api_inspects_resets_and_exempts_keys() {
+        let log = Arc::new(octopus_metrics::ActivityLog::new(10));
+        let state = Arc::new(AppState::new().with_activity_log(Arc::clone(&log)));
+        let uri = "/admin/api/rate-limits?key=10.0.0.1";
+
+        // No keyed limiter installed
+        let app = DashboardRouter::build(Arc::clone(&state));
+        let (status, _) = rate_limit_call(app, "GET", uri, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let keys = OneClient {
+            spent: std::sync::Mutex::new(true),
+            ..Default::default()
+        };
+        *state.rate_limits.write().unwrap() = Some(Arc::new(keys));
+        let app = DashboardRouter::build(Arc::clone(&state));
+
+        let (status, body) = rate_limit_call(app.clone(), "GET", uri, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["buckets"][0]["remaining"], 0);
+        assert_eq!(body["buckets"][0]["reset_after"], "1m");
+        assert!(body["exempt_for"].is_null());
+
+        // Keys with `/` (e.g. a path) go in the query unescaped by routing
+        let (status, body) = rate_limit_call(
+            app.clone(),
+            "GET",
+            "/admin/api/rate-limits?key=%2Fapi%2Fusers",
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["key"], "/api/users");
+        assert_eq!(body["buckets"], serde_json::json!([]));
+
+        let (status, body) = rate_limit_call(app.clone(), "DELETE", uri, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["reset"], 1);
+        assert_eq!(body["buckets"][0]["remaining"], 1);
+        let entry = &log.recent_entries(1)[0];
+        assert_eq!(
+            entry.note.as_deref(),
+            Some("rate limit reset for 10.0.0.1 by anonymous")
+        );
+        assert!(entry.upstream.is_empty());
+
+        let exemption = "/admin/api/rate-limits/exemption?key=10.0.0.1";
+        let (status, body) =
+            rate_limit_call(app.clone(), "PUT", exemption, r#"{"duration": "15m"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["exempt_for"], "15m");
+        let (status, _) =
+            rate_limit_call(app.clone(), "PUT", exemption, r#"{"duration": "0s"}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = rate_limit_call(
+            app.clone(),
+            "PUT",
+            exemption,
+            r#"{"duration": "500000000000y"}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["success"], false);
+
+        let (status, body) = rate_limit_call(app.clone(), "DELETE", exemption, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["exempt_for"].is_null());
+        let (status, _) = rate_limit_call(app, "DELETE", exemption, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
 }
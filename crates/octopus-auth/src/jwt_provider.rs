@@ -32,6 +32,9 @@ pub struct Claims {
     /// Name (custom claim)
     #[serde(default)]
     pub name: Option<String>,
+    /// Any other claims (e.g. `tenant`), exposed as principal attributes
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 /// JWT auth provider
@@ -144,7 +147,7 @@ impl AuthProviderInstance for JwtProvider {
                     roles: claims.roles,
                     scopes,
                     provider: self.name.clone(),
-                    attributes: HashMap::new(),
+                    attributes: claims.extra,
                 }))
             }
             Err(e) => Ok(AuthResult::Failed(format!("Invalid JWT: {e}"))),
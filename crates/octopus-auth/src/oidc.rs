@@ -7,7 +7,6 @@ use dashmap::DashMap;
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 use octopus_config::types::OidcProviderConfig;
 use serde::Deserialize;
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -221,6 +220,14 @@ impl AuthProviderInstance for OidcProvider {
     fn provider_type(&self) -> &'static str {
         "oidc"
     }
+
+    async fn warm_up(&self) -> anyhow::Result<()> {
+        // A successful discovery at startup already cached the keys.
+        if self.cached_keys.read().await.is_some() {
+            return Ok(());
+        }
+        self.discover().await
+    }
 }
 
 fn validate_with_keys(
@@ -293,7 +300,7 @@ fn validate_with_keys(
                 roles: claims.roles,
                 scopes: token_scopes,
                 provider: provider_name.to_string(),
-                attributes: HashMap::new(),
+                attributes: claims.extra,
             }));
         }
     }
@@ -56,6 +56,11 @@ pub trait AuthProviderInstance: Send + Sync + fmt::Debug {
     fn name(&self) -> &str;
     /// Provider type (jwt, oidc, api_key, forward_auth, mtls)
     fn provider_type(&self) -> &str;
+    /// Fetch anything the provider would otherwise fetch on its first
+    /// request (signing keys, discovery documents). Defaults to a no-op.
+    async fn warm_up(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 /// Registry of named auth providers with token caching
@@ -102,6 +107,24 @@ impl AuthProviderRegistry {
         self.providers.iter().map(|e| e.key().clone()).collect()
     }
 
+    /// Warm up every registered provider, returning the name and error of
+    /// each one that failed
+    pub async fn warm_up(&self) -> Vec<(String, anyhow::Error)> {
+        let providers: Vec<_> = self
+            .providers
+            .iter()
+            .map(|e| (e.key().clone(), Arc::clone(e.value())))
+            .collect();
+        let mut failures = Vec::new();
+        for (name, provider) in providers {
+            if let Err(e) = provider.warm_up().await {
+                failures.push((name, e));
+            }
+        }
+        failures.sort_by(|a, b| a.0.cmp(&b.0));
+        failures
+    }
+
     /// Authenticate using a specific provider, with caching
     pub async fn authenticate(
         &self,
@@ -227,4 +250,43 @@ mod tests {
         assert!(registry.get("test").is_some());
         assert!(registry.get("nonexistent").is_none());
     }
+
+    #[derive(Debug)]
+    struct UnreachableKeys;
+
+    #[async_trait]
+    impl AuthProviderInstance for UnreachableKeys {
+        async fn authenticate(&self, _req: &AuthRequest<'_>) -> anyhow::Result<AuthResult> {
+            Ok(AuthResult::Unauthenticated)
+        }
+
+        fn name(&self) -> &'static str {
+            "idp"
+        }
+
+        fn provider_type(&self) -> &'static str {
+            "oidc"
+        }
+
+        async fn warm_up(&self) -> anyhow::Result<()> {
+            anyhow::bail!("JWKS endpoint unreachable")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_reports_failing_providers() {
+        let registry = AuthProviderRegistry::new(None, Duration::from_secs(60));
+        registry.register(
+            "test",
+            Arc::new(MockProvider {
+                name: "test".to_string(),
+            }),
+        );
+        registry.register("idp", Arc::new(UnreachableKeys));
+
+        let failures = registry.warm_up().await;
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "idp");
+        assert!(failures[0].1.to_string().contains("unreachable"));
+    }
 }
@@ -0,0 +1,251 @@
+//! Adaptive compression decisions
+//!
+//! [`RatioTracker`] samples the size reduction achieved per content type and
+//! reports when compression stops paying off, so the middleware can skip it
+//! for payloads that are already compressed or encrypted. Skipped content
+//! types are re-sampled periodically in case the traffic changes.
+//!
+//! [`CpuLoad`] reports the system load so the compression level can back off
+//! while the host is busy.
+
+use crate::config::AdaptiveConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Compression results seen for one content type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RatioStats {
+    /// Responses compressed while sampling
+    pub samples: u32,
+    /// Total uncompressed bytes of the samples
+    pub original_bytes: u64,
+    /// Total compressed bytes of the samples
+    pub compressed_bytes: u64,
+    /// Responses sent uncompressed since sampling finished
+    pub skipped: u32,
+}
+
+impl RatioStats {
+    /// Fraction of bytes saved by compression (0.0 = none, 1.0 = all)
+    pub fn savings(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 0.0;
+        }
+        1.0 - self.compressed_bytes as f64 / self.original_bytes as f64
+    }
+}
+
+/// Per-content-type compression ratio sampler
+#[derive(Debug)]
+pub struct RatioTracker {
+    config: AdaptiveConfig,
+    stats: Mutex<HashMap<String, RatioStats>>,
+}
+
+impl RatioTracker {
+    /// Create a tracker for `config`
+    pub fn new(config: AdaptiveConfig) -> Self {
+        Self {
+            config,
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a response of `content_type` should be compressed.
+    ///
+    /// Returns `true` while the content type is still being sampled or its
+    /// sampled savings reach `min_savings`. Otherwise the response is counted
+    /// as skipped; after `resample_after` skips the samples are cleared so the
+    /// next responses are measured again.
+    pub fn should_compress(&self, content_type: &str) -> bool {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(entry) = stats.get_mut(&content_type_key(content_type)) else {
+            return true;
+        };
+        if entry.samples < self.config.sample_size || entry.savings() >= self.config.min_savings {
+            return true;
+        }
+        entry.skipped += 1;
+        if self.config.resample_after > 0 && entry.skipped >= self.config.resample_after {
+            *entry = RatioStats::default();
+        }
+        false
+    }
+
+    /// Record one compression result for `content_type` while it is sampled.
+    pub fn record(&self, content_type: &str, original: usize, compressed: usize) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = stats.entry(content_type_key(content_type)).or_default();
+        if entry.samples < self.config.sample_size {
+            entry.samples += 1;
+            entry.original_bytes += original as u64;
+            // A larger result is sent uncompressed, so it saves nothing.
+            entry.compressed_bytes += compressed.min(original) as u64;
+        }
+    }
+
+    /// Current statistics for `content_type`
+    pub fn stats(&self, content_type: &str) -> Option<RatioStats> {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.get(&content_type_key(content_type)).copied()
+    }
+}
+
+/// Media type without parameters, lowercased (`text/html; charset=utf-8` →
+/// `text/html`)
+fn content_type_key(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Source of the current CPU load, as a fraction of the available cores
+/// (1.0 = every core busy).
+pub trait CpuLoad: Send + Sync + std::fmt::Debug {
+    /// Current load
+    fn load(&self) -> f64;
+}
+
+/// One-minute load average divided by the core count, read from
+/// `/proc/loadavg` at most once per second. Reports 0.0 where the load
+/// average is unavailable.
+///
+/// Inside a Tokio runtime the file is read on the blocking pool and
+/// [`load`](CpuLoad::load) returns the last value read, so a request never
+/// waits on the filesystem.
+#[derive(Debug)]
+pub struct LoadAverage {
+    cores: f64,
+    state: Arc<Mutex<LoadState>>,
+}
+
+/// Last load read, and whether a read is in flight
+#[derive(Debug, Default)]
+struct LoadState {
+    load: f64,
+    read_at: Option<Instant>,
+    refreshing: bool,
+}
+
+impl LoadAverage {
+    const REFRESH: Duration = Duration::from_secs(1);
+
+    /// Create a load-average probe
+    pub fn new() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            cores: cores as f64,
+            state: Arc::new(Mutex::new(LoadState::default())),
+        }
+    }
+
+    fn read(cores: f64) -> f64 {
+        std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| s.split_whitespace().next()?.parse::<f64>().ok())
+            .map_or(0.0, |avg| avg / cores)
+    }
+
+    fn store(state: &Mutex<LoadState>, load: f64) {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.load = load;
+        state.read_at = Some(Instant::now());
+        state.refreshing = false;
+    }
+}
+
+impl Default for LoadAverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CpuLoad for LoadAverage {
+    fn load(&self) -> f64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let fresh = state.read_at.is_some_and(|at| at.elapsed() < Self::REFRESH);
+        if fresh || state.refreshing {
+            return state.load;
+        }
+        let cores = self.cores;
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                state.refreshing = true;
+                let shared = Arc::clone(&self.state);
+                runtime.spawn_blocking(move || Self::store(&shared, Self::read(cores)));
+                state.load
+            }
+            Err(_) => {
+                drop(state);
+                let load = Self::read(cores);
+                Self::store(&self.state, load);
+                load
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> RatioTracker {
+        RatioTracker::new(AdaptiveConfig {
+            enabled: true,
+            sample_size: 2,
+            min_savings: 0.1,
+            resample_after: 3,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_skips_after_poor_samples_and_resamples() {
+        let tracker = tracker();
+        let ct = "application/x-encrypted; v=1";
+
+        assert!(tracker.should_compress(ct));
+        tracker.record(ct, 1000, 990);
+        assert!(tracker.should_compress(ct));
+        tracker.record(ct, 1000, 1010);
+
+        assert!(!tracker.should_compress("Application/X-Encrypted"));
+        assert!(!tracker.should_compress(ct));
+        assert_eq!(tracker.stats(ct).unwrap().skipped, 2);
+        // The third skip clears the samples, so the next response is measured.
+        assert!(!tracker.should_compress(ct));
+        assert!(tracker.should_compress(ct));
+    }
+
+    #[test]
+    fn test_keeps_compressing_when_it_helps() {
+        let tracker = tracker();
+        for _ in 0..5 {
+            assert!(tracker.should_compress("text/html"));
+            tracker.record("text/html", 1000, 200);
+        }
+        let stats = tracker.stats("text/html").unwrap();
+        assert_eq!(stats.samples, 2);
+        assert!((stats.savings() - 0.8).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_load_average_is_read_off_the_runtime() {
+        let probe = LoadAverage::new();
+
+        // The first call only starts a read on the blocking pool.
+        assert_eq!(probe.load(), 0.0);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while probe.state.lock().unwrap().read_at.is_none() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("load average never refreshed");
+        assert!(probe.load() >= 0.0);
+    }
+}
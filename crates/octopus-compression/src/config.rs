@@ -20,6 +20,51 @@ pub struct CompressionConfig {
     /// Preferred compression algorithms in order
     #[serde(default = "default_algorithms")]
     pub algorithms: Vec<String>,
+
+    /// Skip compression where it doesn't pay off
+    #[serde(default)]
+    pub adaptive: AdaptiveConfig,
+}
+
+/// Adaptive compression: sample the savings achieved per content type and
+/// stop compressing types that don't shrink, and optionally lower the level
+/// while the CPU is busy.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    /// Enable ratio sampling
+    pub enabled: bool,
+
+    /// Responses compressed per content type before deciding
+    pub sample_size: u32,
+
+    /// Minimum fraction of bytes saved (0.0-1.0) for a content type to keep
+    /// being compressed
+    pub min_savings: f64,
+
+    /// Skipped responses after which a content type is sampled again
+    /// (0 = never)
+    pub resample_after: u32,
+
+    /// Load per core (e.g. 0.8) above which `high_load_level` is used;
+    /// `None` keeps the configured level
+    pub cpu_high_watermark: Option<f64>,
+
+    /// Compression level used above `cpu_high_watermark`
+    pub high_load_level: u32,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_size: 16,
+            min_savings: 0.1,
+            resample_after: 1000,
+            cpu_high_watermark: None,
+            high_load_level: 1,
+        }
+    }
 }
 
 impl Default for CompressionConfig {
@@ -33,6 +78,7 @@ impl Default for CompressionConfig {
                 "zstd".to_string(), // zstd (fast)
                 "gzip".to_string(), // gzip (universal)
             ],
+            adaptive: AdaptiveConfig::default(),
         }
     }
 }
@@ -63,6 +109,12 @@ impl CompressionConfig {
     pub fn is_compressible_content_type(&self, content_type: &str) -> bool {
         let ct = content_type.to_lowercase();
 
+        // gRPC compresses per message (`grpc-encoding`); HTTP-level encoding
+        // would break the framing, even for `application/grpc+json`.
+        if ct.starts_with("application/grpc") {
+            return false;
+        }
+
         // Text-based content types that benefit from compression
         ct.starts_with("text/")
             || ct.contains("json")
@@ -112,5 +164,8 @@ mod tests {
         assert!(!config.is_compressible_content_type("image/png"));
         assert!(!config.is_compressible_content_type("video/mp4"));
         assert!(!config.is_compressible_content_type("application/octet-stream"));
+        assert!(!config.is_compressible_content_type("application/grpc"));
+        assert!(!config.is_compressible_content_type("application/grpc+json"));
+        assert!(!config.is_compressible_content_type("application/grpc-web+json"));
     }
 }
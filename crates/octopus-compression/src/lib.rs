@@ -10,12 +10,16 @@
 //! - Accept-Encoding negotiation
 //! - Configurable compression levels
 //! - Minimum size threshold
+//! - Adaptive mode: skips content types that don't shrink and lowers the
+//!   level under CPU load
 //! - Automatic Content-Encoding header handling
 
+pub mod adaptive;
 pub mod compressor;
 pub mod config;
 pub mod middleware;
 
+pub use adaptive::{CpuLoad, LoadAverage, RatioStats, RatioTracker};
 pub use compressor::{CompressionAlgorithm, Compressor};
-pub use config::CompressionConfig;
+pub use config::{AdaptiveConfig, CompressionConfig};
 pub use middleware::CompressionMiddleware;
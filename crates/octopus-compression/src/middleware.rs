@@ -1,12 +1,13 @@
 //! Compression middleware implementation
 
+use crate::adaptive::{CpuLoad, LoadAverage, RatioTracker};
 use crate::compressor::{CompressionAlgorithm, Compressor};
 use crate::config::CompressionConfig;
 use async_trait::async_trait;
 use http::{HeaderValue, Request, Response, StatusCode};
 use http_body_util::BodyExt;
 use octopus_core::middleware::{Body, Middleware, Next};
-use octopus_core::Result;
+use octopus_core::{Result, StreamedResponse};
 use std::sync::Arc;
 use tracing::{debug, warn};
 
@@ -14,13 +15,49 @@ use tracing::{debug, warn};
 #[derive(Debug)]
 pub struct CompressionMiddleware {
     config: Arc<CompressionConfig>,
+    /// Per-content-type savings, when adaptive mode is enabled
+    ratios: Option<Arc<RatioTracker>>,
+    /// Load probe, when a CPU watermark is configured
+    cpu_load: Option<Arc<dyn CpuLoad>>,
 }
 
 impl CompressionMiddleware {
     /// Create a new compression middleware
     pub fn new(config: CompressionConfig) -> Self {
+        let ratios = config
+            .adaptive
+            .enabled
+            .then(|| Arc::new(RatioTracker::new(config.adaptive.clone())));
+        let cpu_load = config
+            .adaptive
+            .cpu_high_watermark
+            .map(|_| Arc::new(LoadAverage::new()) as Arc<dyn CpuLoad>);
         Self {
             config: Arc::new(config),
+            ratios,
+            cpu_load,
+        }
+    }
+
+    /// Use `probe` instead of the system load average for level backoff
+    pub fn with_cpu_load(mut self, probe: Arc<dyn CpuLoad>) -> Self {
+        self.cpu_load = Some(probe);
+        self
+    }
+
+    /// Per-content-type savings sampled in adaptive mode
+    pub fn ratios(&self) -> Option<&Arc<RatioTracker>> {
+        self.ratios.as_ref()
+    }
+
+    /// Compression level for the current load
+    fn level(&self) -> u32 {
+        let adaptive = &self.config.adaptive;
+        match (adaptive.cpu_high_watermark, &self.cpu_load) {
+            (Some(watermark), Some(probe)) if probe.load() > watermark => {
+                adaptive.high_load_level.min(self.config.level)
+            }
+            _ => self.config.level,
         }
     }
 }
@@ -54,12 +91,29 @@ impl Middleware for CompressionMiddleware {
             return Ok(response);
         }
 
+        let content_type = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if let Some(ratios) = &self.ratios {
+            if !ratios.should_compress(&content_type) {
+                debug!(content_type = %content_type, "Skipping compression, content type doesn't shrink");
+                return Ok(response);
+            }
+        }
+
         // Compress the response
-        match compress_response(response, algo, self.config.level).await {
-            Ok(compressed) => {
+        let level = self.level();
+        match compress_response(response, algo, level, self.config.min_size).await {
+            Ok((compressed, sizes)) => {
+                if let (Some(ratios), Some((original, encoded))) = (&self.ratios, sizes) {
+                    ratios.record(&content_type, original, encoded);
+                }
                 debug!(
                     algorithm = algo.encoding_name(),
-                    "Response compressed successfully"
+                    level, "Response compressed successfully"
                 );
                 Ok(compressed)
             }
@@ -77,10 +131,11 @@ impl Middleware for CompressionMiddleware {
 
 /// Check if response should be compressed
 fn should_compress_response(response: &Response<Body>, config: &CompressionConfig) -> bool {
-    // Don't compress if already encoded
+    // Don't compress if already encoded, or streamed past the chain
     if response
         .headers()
         .contains_key(http::header::CONTENT_ENCODING)
+        || StreamedResponse::is_streamed(response)
     {
         return false;
     }
@@ -102,12 +157,14 @@ fn should_compress_response(response: &Response<Body>, config: &CompressionConfi
     true
 }
 
-/// Compress response body
+/// Compress response body. Bodies below `min_size` are returned unchanged;
+/// otherwise the original and compressed sizes are returned too.
 async fn compress_response(
     response: Response<Body>,
     algorithm: CompressionAlgorithm,
     level: u32,
-) -> Result<Response<Body>> {
+    min_size: usize,
+) -> Result<(Response<Body>, Option<(usize, usize)>)> {
     let (mut parts, body) = response.into_parts();
 
     // Collect the body
@@ -119,6 +176,9 @@ async fn compress_response(
 
     // Check if body is large enough to compress
     let original_size = body_bytes.len();
+    if original_size < min_size {
+        return Ok((Response::from_parts(parts, Body::from(body_bytes)), None));
+    }
 
     // Compress the body
     let compressed = Compressor::compress(&body_bytes, algorithm, level)
@@ -152,12 +212,13 @@ async fn compress_response(
     parts.headers.remove(http::header::TRANSFER_ENCODING);
 
     let response = Response::from_parts(parts, Body::from(final_body));
-    Ok(response)
+    Ok((response, Some((original_size, compressed_size))))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::AdaptiveConfig;
     use bytes::Bytes;
     use http::header::{ACCEPT_ENCODING, CONTENT_TYPE};
 
@@ -205,6 +266,139 @@ mod tests {
             .contains_key(http::header::CONTENT_ENCODING));
     }
 
+    /// Run `middleware` over a gzip-accepting request answered with `body`
+    async fn respond(
+        middleware: &CompressionMiddleware,
+        content_type: &'static str,
+        body: Vec<u8>,
+    ) -> Response<Body> {
+        let req = Request::builder()
+            .header(ACCEPT_ENCODING, "gzip")
+            .body(Body::from(Bytes::new()))
+            .unwrap();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([]);
+        let next = Next::with_handler(
+            stack,
+            Box::new(move |_req| {
+                let body = body.clone();
+                Box::pin(async move {
+                    Ok(Response::builder()
+                        .header(CONTENT_TYPE, content_type)
+                        .body(Body::from(body))
+                        .unwrap())
+                })
+            }),
+        );
+        middleware.call(req, next).await.unwrap()
+    }
+
+    /// Bytes from a xorshift generator: incompressible
+    fn noise(len: usize) -> Vec<u8> {
+        let mut x: u32 = 0x9e37_79b9;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect()
+    }
+
+    fn adaptive(sample_size: u32) -> CompressionMiddleware {
+        CompressionMiddleware::new(CompressionConfig {
+            algorithms: vec!["gzip".to_string()],
+            adaptive: AdaptiveConfig {
+                enabled: true,
+                sample_size,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_skips_incompressible_type_after_sampling() {
+        let middleware = adaptive(3);
+        let ct = "text/x-ciphertext";
+
+        for _ in 0..3 {
+            respond(&middleware, ct, noise(4096)).await;
+        }
+        let ratios = middleware.ratios().unwrap();
+        assert_eq!(ratios.stats(ct).unwrap().samples, 3);
+        assert_eq!(ratios.stats(ct).unwrap().skipped, 0);
+
+        let res = respond(&middleware, ct, noise(4096)).await;
+        assert!(!res.headers().contains_key(http::header::CONTENT_ENCODING));
+        assert_eq!(ratios.stats(ct).unwrap().skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_keeps_compressing_text() {
+        let middleware = adaptive(3);
+        let html = "<li>octopus gateway</li>".repeat(200).into_bytes();
+
+        for _ in 0..6 {
+            let res = respond(&middleware, "text/html; charset=utf-8", html.clone()).await;
+            assert_eq!(res.headers()[http::header::CONTENT_ENCODING], "gzip");
+        }
+        let stats = middleware.ratios().unwrap().stats("text/html").unwrap();
+        assert_eq!(stats.skipped, 0);
+        assert!(stats.savings() > 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_min_size_still_applies() {
+        let middleware = adaptive(3);
+        let small = "<p>hi</p>".repeat(10).into_bytes();
+
+        let res = respond(&middleware, "text/html", small.clone()).await;
+        assert!(!res.headers().contains_key(http::header::CONTENT_ENCODING));
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, small);
+        // Small bodies are not sampled either.
+        assert!(middleware.ratios().unwrap().stats("text/html").is_none());
+    }
+
+    #[derive(Debug)]
+    struct FixedLoad(f64);
+
+    impl CpuLoad for FixedLoad {
+        fn load(&self) -> f64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_level_backs_off_under_cpu_load() {
+        let config = CompressionConfig {
+            level: 9,
+            adaptive: AdaptiveConfig {
+                cpu_high_watermark: Some(0.8),
+                high_load_level: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let busy =
+            CompressionMiddleware::new(config.clone()).with_cpu_load(Arc::new(FixedLoad(0.95)));
+        assert_eq!(busy.level(), 2);
+        let idle = CompressionMiddleware::new(config).with_cpu_load(Arc::new(FixedLoad(0.3)));
+        assert_eq!(idle.level(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_responses_are_not_compressed() {
+        let middleware = CompressionMiddleware::new(CompressionConfig::default());
+        let body = br#"{"name":"octopus"}"#.repeat(200);
+
+        let res = respond(&middleware, "application/grpc+json", body.clone()).await;
+        assert!(!res.headers().contains_key(http::header::CONTENT_ENCODING));
+        let sent = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(sent, body);
+    }
+
     #[tokio::test]
     async fn test_should_compress_response() {
         let config = CompressionConfig::default();
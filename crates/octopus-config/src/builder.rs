@@ -26,6 +26,25 @@ impl ConfigBuilder {
         let gateway = self.gateway.get_or_insert_with(|| GatewayConfig {
             listen: addr,
             workers: 0,
+            worker_stack_size: None,
+            worker_cpus: Vec::new(),
+            acceptors: 0,
+            unix_socket: None,
+            proxy_protocol: Default::default(),
+            connection_limits: Default::default(),
+            concurrency: None,
+            debug_tap: None,
+            debug_headers: None,
+            request_id: None,
+            multipart: None,
+            internal_redirect: None,
+            tenancy: None,
+            response_body_limit: None,
+            idempotency: None,
+            request_signing: None,
+            qos: None,
+            startup_check: None,
+            warmup: Default::default(),
             request_timeout: std::time::Duration::from_secs(30),
             shutdown_timeout: std::time::Duration::from_secs(30),
             pre_stop_delay: std::time::Duration::from_secs(5),
@@ -34,8 +53,21 @@ impl ConfigBuilder {
             compression: crate::types::CompressionConfig::default(),
             internal_route_prefix: Some("__".to_string()),
             probes: crate::types::ProbeConfig::default(),
+            h2c: true,
             enforce_sni_check: true,
             security_headers: Default::default(),
+            trailing_slash: Default::default(),
+            path_normalization: Default::default(),
+            error_format: Default::default(),
+            header_policy: Default::default(),
+            forwarded: Default::default(),
+            maintenance: Default::default(),
+            experiments: Default::default(),
+            request_validation: Default::default(),
+            response_validation: Default::default(),
+            geoip: Default::default(),
+            upstream_retry_after: Default::default(),
+            unmatched: None,
         });
         gateway.listen = addr;
         self
@@ -51,6 +83,7 @@ impl ConfigBuilder {
             gateway,
             upstreams: Vec::new(),
             routes: Vec::new(),
+            route_groups: Vec::new(),
             plugins: Vec::new(),
             farp: crate::types::FarpConfig::default(),
             observability: ObservabilityConfig::default(),
@@ -61,6 +94,7 @@ impl ConfigBuilder {
             grpc: crate::types::GrpcConfig::default(),
             graphql: crate::types::GraphQLConfig::default(),
             kubernetes: crate::types::KubernetesConfig::default(),
+            events: crate::types::EventsConfig::default(),
         })
     }
 }
@@ -69,7 +69,7 @@ pub fn load_from_str(content: &str, format: ConfigFormat) -> Result<Config> {
     // Expand environment variables first
     let expanded_content = expand_env_vars(content)?;
 
-    let config = match format {
+    let mut config: Config = match format {
         ConfigFormat::Yaml => serde_yaml::from_str(&expanded_content)
             .map_err(|e| Error::Config(format!("Failed to parse YAML: {e}")))?,
         ConfigFormat::Toml => toml::from_str(&expanded_content)
@@ -77,6 +77,7 @@ pub fn load_from_str(content: &str, format: ConfigFormat) -> Result<Config> {
         ConfigFormat::Json => serde_json::from_str(&expanded_content)
             .map_err(|e| Error::Config(format!("Failed to parse JSON: {e}")))?,
     };
+    config.expand_route_groups()?;
 
     Ok(config)
 }
@@ -321,4 +322,88 @@ gateway:
         env::remove_var("DB_PORT");
         env::remove_var("DB_NAME");
     }
+
+    const GROUPED_CONFIG: &str = r#"
+gateway:
+  listen: "127.0.0.1:8080"
+
+route_groups:
+  - name: admin
+    prefix: /admin
+    upstream: admin-service
+    auth_provider: jwt
+    require_roles: [admin]
+    timeout: 5s
+    rate_limit:
+      requests_per_window: 10
+      window_size: 1m
+    metadata:
+      team: platform
+    transform:
+      request_headers:
+        set:
+          X-Admin: "{claim.sub}"
+    routes:
+      - path: /
+        methods: [GET]
+      - path: /users/:id
+        methods: [GET, DELETE]
+        auth_provider: oidc
+        require_roles: [superuser]
+        rate_limit:
+          requests_per_window: 1
+          window_size: 1s
+        metadata:
+          team: identity
+          audit: "true"
+      - path: /health
+        methods: [GET]
+        upstream: health-service
+        skip_auth: true
+"#;
+
+    #[test]
+    fn route_groups_expand_into_routes() {
+        let config = load_from_str(GROUPED_CONFIG, ConfigFormat::Yaml).unwrap();
+        assert!(config.route_groups.is_empty());
+        assert_eq!(config.routes.len(), 3);
+
+        let root = &config.routes[0];
+        assert_eq!(root.path, "/admin");
+        assert_eq!(root.upstream, "admin-service");
+        assert_eq!(root.auth_provider.as_deref(), Some("jwt"));
+        assert_eq!(root.require_roles, ["admin"]);
+        assert_eq!(root.timeout, Some(std::time::Duration::from_secs(5)));
+        assert_eq!(root.rate_limit.as_ref().unwrap().requests_per_window, 10);
+        assert_eq!(root.metadata["team"], "platform");
+        assert!(root.transform.is_some());
+
+        let health = &config.routes[2];
+        assert_eq!(health.path, "/admin/health");
+        assert_eq!(health.upstream, "health-service");
+        assert!(health.skip_auth);
+    }
+
+    #[test]
+    fn route_group_member_settings_win() {
+        let config = load_from_str(GROUPED_CONFIG, ConfigFormat::Yaml).unwrap();
+        let users = &config.routes[1];
+
+        assert_eq!(users.path, "/admin/users/:id");
+        assert_eq!(users.auth_provider.as_deref(), Some("oidc"));
+        assert_eq!(users.require_roles, ["superuser"]);
+        assert_eq!(users.rate_limit.as_ref().unwrap().requests_per_window, 1);
+        assert_eq!(users.metadata["team"], "identity");
+        assert_eq!(users.metadata["audit"], "true");
+        // Settings the member leaves unset still come from the group.
+        assert_eq!(users.upstream, "admin-service");
+        assert!(users.transform.is_some());
+    }
+
+    #[test]
+    fn route_group_prefix_must_be_absolute() {
+        let yaml = GROUPED_CONFIG.replace("prefix: /admin", "prefix: admin");
+        let err = load_from_str(&yaml, ConfigFormat::Yaml).unwrap_err();
+        assert!(err.to_string().contains("route group 'admin'"));
+    }
 }
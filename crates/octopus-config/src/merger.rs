@@ -55,6 +55,7 @@ fn merge_two_configs(mut base: Config, overlay: Config) -> Result<Config> {
 
     // Merge routes (by path, or append if new)
     base.routes = merge_routes(base.routes, overlay.routes);
+    base.route_groups.extend(overlay.route_groups);
 
     // Merge plugins (by name, or append if new)
     base.plugins = merge_plugins(base.plugins, overlay.plugins);
@@ -76,6 +77,33 @@ fn merge_gateway_config(base: GatewayConfig, overlay: GatewayConfig) -> GatewayC
         } else {
             base.workers
         },
+        worker_stack_size: overlay.worker_stack_size.or(base.worker_stack_size),
+        worker_cpus: if overlay.worker_cpus.is_empty() {
+            base.worker_cpus
+        } else {
+            overlay.worker_cpus
+        },
+        acceptors: if overlay.acceptors > 0 {
+            overlay.acceptors
+        } else {
+            base.acceptors
+        },
+        unix_socket: overlay.unix_socket.or(base.unix_socket),
+        proxy_protocol: overlay.proxy_protocol,
+        connection_limits: overlay.connection_limits,
+        concurrency: overlay.concurrency.or(base.concurrency),
+        debug_tap: overlay.debug_tap.or(base.debug_tap),
+        debug_headers: overlay.debug_headers.or(base.debug_headers),
+        request_id: overlay.request_id.or(base.request_id),
+        multipart: overlay.multipart.or(base.multipart),
+        internal_redirect: overlay.internal_redirect.or(base.internal_redirect),
+        tenancy: overlay.tenancy.or(base.tenancy),
+        response_body_limit: overlay.response_body_limit.or(base.response_body_limit),
+        idempotency: overlay.idempotency.or(base.idempotency),
+        request_signing: overlay.request_signing.or(base.request_signing),
+        qos: overlay.qos.or(base.qos),
+        startup_check: overlay.startup_check.or(base.startup_check),
+        warmup: overlay.warmup,
         request_timeout: overlay.request_timeout,
         shutdown_timeout: overlay.shutdown_timeout,
         pre_stop_delay: overlay.pre_stop_delay,
@@ -84,8 +112,21 @@ fn merge_gateway_config(base: GatewayConfig, overlay: GatewayConfig) -> GatewayC
         compression: overlay.compression,
         internal_route_prefix: overlay.internal_route_prefix.or(base.internal_route_prefix),
         probes: overlay.probes,
+        h2c: overlay.h2c,
         enforce_sni_check: overlay.enforce_sni_check,
         security_headers: overlay.security_headers,
+        trailing_slash: overlay.trailing_slash,
+        path_normalization: overlay.path_normalization,
+        error_format: overlay.error_format,
+        header_policy: overlay.header_policy,
+        forwarded: overlay.forwarded,
+        maintenance: overlay.maintenance,
+        experiments: overlay.experiments,
+        request_validation: overlay.request_validation,
+        response_validation: overlay.response_validation,
+        geoip: overlay.geoip,
+        upstream_retry_after: overlay.upstream_retry_after,
+        unmatched: overlay.unmatched.or(base.unmatched),
     }
 }
 
@@ -145,6 +186,25 @@ mod tests {
                     .parse::<SocketAddr>()
                     .unwrap(),
                 workers,
+                worker_stack_size: None,
+                worker_cpus: Vec::new(),
+                acceptors: 0,
+                unix_socket: None,
+                proxy_protocol: Default::default(),
+                connection_limits: Default::default(),
+                concurrency: None,
+                debug_tap: None,
+                debug_headers: None,
+                request_id: None,
+                multipart: None,
+                internal_redirect: None,
+                tenancy: None,
+                response_body_limit: None,
+                idempotency: None,
+                request_signing: None,
+                qos: None,
+                startup_check: None,
+                warmup: Default::default(),
                 request_timeout: Duration::from_secs(30),
                 shutdown_timeout: Duration::from_secs(10),
                 pre_stop_delay: Duration::from_secs(5),
@@ -153,11 +213,25 @@ mod tests {
                 compression: CompressionConfig::default(),
                 internal_route_prefix: None,
                 probes: crate::types::ProbeConfig::default(),
+                h2c: true,
                 enforce_sni_check: true,
                 security_headers: Default::default(),
+                trailing_slash: Default::default(),
+                path_normalization: Default::default(),
+                error_format: Default::default(),
+                header_policy: Default::default(),
+                forwarded: Default::default(),
+                maintenance: Default::default(),
+                experiments: Default::default(),
+                request_validation: Default::default(),
+                response_validation: Default::default(),
+                geoip: Default::default(),
+                upstream_retry_after: Default::default(),
+                unmatched: None,
             },
             upstreams: vec![],
             routes: vec![],
+            route_groups: vec![],
             plugins: vec![],
             farp: Default::default(),
             observability: ObservabilityConfig::default(),
@@ -168,6 +242,7 @@ mod tests {
             grpc: Default::default(),
             graphql: Default::default(),
             kubernetes: Default::default(),
+            events: Default::default(),
         }
     }
 
@@ -191,6 +266,8 @@ mod tests {
             lb_policy: "round_robin".to_string(),
             health_check: None,
             circuit_breaker: None,
+            openapi: None,
+            slow_start: None,
         };
 
         let upstream2 = UpstreamConfig {
@@ -199,6 +276,8 @@ mod tests {
             lb_policy: "round_robin".to_string(),
             health_check: None,
             circuit_breaker: None,
+            openapi: None,
+            slow_start: None,
         };
 
         let upstream1_override = UpstreamConfig {
@@ -207,6 +286,8 @@ mod tests {
             lb_policy: "least_conn".to_string(),
             health_check: None,
             circuit_breaker: None,
+            openapi: None,
+            slow_start: None,
         };
 
         let base = vec![upstream1];
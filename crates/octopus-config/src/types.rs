@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// Main configuration
@@ -19,6 +20,12 @@ pub struct Config {
     #[serde(default)]
     pub routes: Vec<RouteConfig>,
 
+    /// Route groups: a path prefix plus auth and middleware settings shared
+    /// by their member routes. Loading expands them into `routes` (see
+    /// [`Config::expand_route_groups`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub route_groups: Vec<RouteGroupConfig>,
+
     /// Plugins
     #[serde(default)]
     pub plugins: Vec<PluginConfig>,
@@ -58,6 +65,10 @@ pub struct Config {
     /// Kubernetes operator (Gateway API + Octopus CRDs)
     #[serde(default)]
     pub kubernetes: KubernetesConfig,
+
+    /// Gateway lifecycle and security event delivery
+    #[serde(default)]
+    pub events: EventsConfig,
 }
 
 /// Kubernetes operator configuration.
@@ -125,7 +136,110 @@ pub struct GatewayConfig {
     #[serde(default)]
     pub workers: usize,
 
-    /// Request timeout
+    /// Stack size of each worker thread in bytes (default: Tokio's 2 MiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_stack_size: Option<usize>,
+
+    /// CPUs to pin worker threads to, assigned round-robin in thread
+    /// start order. Each must be a CPU the process is allowed to run on.
+    /// Empty (the default) leaves scheduling to the OS.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub worker_cpus: Vec<usize>,
+
+    /// Listener sockets bound to `listen` with `SO_REUSEPORT`, each served
+    /// by its own accept loop (0 = one per worker on Linux, a single socket
+    /// elsewhere). Falls back to one socket where the option is unsupported.
+    #[serde(default)]
+    pub acceptors: usize,
+
+    /// Serve on a Unix domain socket instead of the TCP `listen` address,
+    /// e.g. behind a sidecar proxy. `listen` is not bound when this is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix_socket: Option<UnixSocketConfig>,
+
+    /// PROXY protocol (v1/v2) on accepted connections, for a gateway behind
+    /// an L4 load balancer. Off by default.
+    #[serde(default)]
+    pub proxy_protocol: ProxyProtocolConfig,
+
+    /// Limits on frontend connections that stall (trickled headers, idle
+    /// keep-alive) or carry too many requests. Separate from
+    /// `request_timeout`, which bounds the upstream exchange.
+    #[serde(default)]
+    pub connection_limits: ConnectionLimitsConfig,
+
+    /// Gateway-wide limit on requests handled at once. Requests beyond it
+    /// wait in a bounded queue or are rejected with 503.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<ConcurrencyConfig>,
+
+    /// Capture of full exchanges for requests carrying a signed debug
+    /// header, retrievable from the admin API. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_tap: Option<DebugTapConfig>,
+
+    /// Response headers describing how a request was handled (route,
+    /// upstream, cache, retries, timings), for trusted callers or every
+    /// request. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_headers: Option<DebugHeadersConfig>,
+
+    /// Request IDs: the header carrying them and how missing ones are
+    /// generated. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<RequestIdConfig>,
+
+    /// Multi-tenant routing: resolve each request's tenant, inject its id
+    /// and route it to the tenant's upstream. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenancy: Option<TenancyConfig>,
+
+    /// Largest upstream response body the gateway buffers, and what happens
+    /// past it; routes may set their own. Without it bodies over 100 MiB
+    /// fail.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body_limit: Option<ResponseBodyLimitConfig>,
+
+    /// Limits for `multipart/form-data` bodies (per part, in total, allowed
+    /// fields) and whether they stream to the upstream rather than being
+    /// buffered. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multipart: Option<MultipartConfig>,
+
+    /// Upstream response header naming a resource the gateway fetches and
+    /// returns in place of the response (`X-Accel-Redirect`). Off unless
+    /// configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal_redirect: Option<InternalRedirectConfig>,
+
+    /// `Idempotency-Key` handling: a keyed request runs once, and retries
+    /// get the stored response. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency: Option<IdempotencyConfig>,
+
+    /// HMAC request signing, with optional single-use nonces against
+    /// replays. Off unless configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_signing: Option<RequestSigningConfig>,
+
+    /// Request priorities for the concurrency limiter: which requests are
+    /// admitted first and which are shed when it is saturated. Off unless
+    /// configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qos: Option<QosConfig>,
+
+    /// Probe every upstream cluster once before reporting ready. Off unless
+    /// configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_check: Option<StartupCheckConfig>,
+
+    /// Work done at startup so the first requests don't pay for it:
+    /// compiling route patterns, compiling scripts, fetching signing keys.
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+
+    /// Request timeout, per upstream attempt and as the default total
+    /// budget across retries
     #[serde(default = "default_timeout", with = "humantime_serde")]
     pub request_timeout: Duration,
 
@@ -142,7 +256,9 @@ pub struct GatewayConfig {
     #[serde(default = "default_pre_stop_delay", with = "humantime_serde")]
     pub pre_stop_delay: Duration,
 
-    /// Max request body size (bytes)
+    /// Max request body size (bytes). Larger bodies are rejected with `413`;
+    /// a larger declared `Content-Length` is rejected before the body (or a
+    /// `100 Continue`) is sent.
     #[serde(default = "default_max_body_size")]
     pub max_body_size: usize,
 
@@ -150,6 +266,12 @@ pub struct GatewayConfig {
     #[serde(default)]
     pub tls: Option<TlsConfig>,
 
+    /// Accept HTTP/2 with prior knowledge (h2c) on a plaintext listener,
+    /// e.g. for gRPC clients without TLS. Over TLS the HTTP version is
+    /// negotiated through ALPN (`h2`, `http/1.1`) regardless. Default `true`.
+    #[serde(default = "default_true")]
+    pub h2c: bool,
+
     /// Compression configuration
     #[serde(default)]
     pub compression: CompressionConfig,
@@ -175,6 +297,85 @@ pub struct GatewayConfig {
     /// set `enabled: true` to add HSTS, CSP, `X-Frame-Options`, etc.
     #[serde(default)]
     pub security_headers: SecurityHeadersConfig,
+
+    /// How a trailing `/` on request paths is matched against routes.
+    #[serde(default)]
+    pub trailing_slash: TrailingSlashMode,
+
+    /// Request path normalization applied before routing and auth.
+    #[serde(default)]
+    pub path_normalization: PathNormalizationConfig,
+
+    /// Body format of gateway-generated error responses: RFC 7807
+    /// `problem` (default) or the `legacy` `{"error", "message"}` JSON.
+    #[serde(default)]
+    pub error_format: octopus_core::ErrorFormat,
+
+    /// Headers stripped from client requests and upstream responses, on top
+    /// of the hop-by-hop headers that are always removed.
+    #[serde(default)]
+    pub header_policy: HeaderPolicyConfig,
+
+    /// `X-Forwarded-*` / `Forwarded` header management.
+    #[serde(default)]
+    pub forwarded: ForwardedHeadersConfig,
+
+    /// Maintenance mode: answer data-plane requests with a 503 and
+    /// `Retry-After` while health, metrics and admin endpoints keep working.
+    /// Can also be toggled at runtime via `PUT /admin/api/maintenance`.
+    #[serde(default)]
+    pub maintenance: octopus_core::MaintenanceSettings,
+
+    /// A/B experiments: users are assigned a weighted variant per experiment,
+    /// sent upstream as `X-Experiment-{name}` and kept sticky by a cookie.
+    #[serde(default)]
+    pub experiments: ExperimentsConfig,
+
+    /// JSON Schema validation of request bodies on selected routes.
+    #[serde(default)]
+    pub request_validation: RequestValidationConfig,
+
+    /// Dev-mode validation of upstream responses against their
+    /// FARP-declared OpenAPI schemas. Off by default.
+    #[serde(default)]
+    pub response_validation: ResponseValidationConfig,
+
+    /// GeoIP client location (MaxMind mmdb): country blocking and
+    /// region-specific route upstreams. Needs the `geoip` build feature.
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
+
+    /// Handling of `Retry-After` on upstream 429/503 responses.
+    #[serde(default)]
+    pub upstream_retry_after: UpstreamRetryAfterConfig,
+
+    /// How requests that match no route are answered (default: the standard
+    /// `404` problem response).
+    #[serde(default)]
+    pub unmatched: Option<UnmatchedConfig>,
+}
+
+/// Trailing-slash matching mode (maps to [`octopus_router::TrailingSlashPolicy`]).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingSlashMode {
+    /// `/users` and `/users/` are different paths
+    Strict,
+    /// `/users` and `/users/` match the same route
+    #[default]
+    Merge,
+    /// Match either form, but answer the non-canonical one with a 308 redirect
+    Redirect,
+}
+
+impl From<TrailingSlashMode> for octopus_router::TrailingSlashPolicy {
+    fn from(mode: TrailingSlashMode) -> Self {
+        match mode {
+            TrailingSlashMode::Strict => Self::Strict,
+            TrailingSlashMode::Merge => Self::Merge,
+            TrailingSlashMode::Redirect => Self::RedirectToCanonical,
+        }
+    }
 }
 
 fn default_sni_check() -> bool {
@@ -185,6 +386,327 @@ fn default_internal_prefix() -> Option<String> {
     Some("__".to_string())
 }
 
+/// Request path normalization.
+///
+/// When enabled, request paths are canonicalized (dot segments resolved, `//`
+/// collapsed, unreserved percent-escapes decoded) before routing, so auth rules
+/// and route matching see the same path the upstream receives.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PathNormalizationConfig {
+    /// Normalize request paths before routing (default `true`).
+    pub enabled: bool,
+    /// How an encoded slash (`%2F`) inside a segment is handled.
+    pub encoded_slash: EncodedSlashMode,
+}
+
+impl Default for PathNormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            encoded_slash: EncodedSlashMode::default(),
+        }
+    }
+}
+
+/// Forwarded-header management. Incoming `X-Forwarded-*` and `Forwarded`
+/// values are appended to only when the connecting peer is a trusted proxy;
+/// from any other peer they are overwritten from the connection.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ForwardedHeadersConfig {
+    /// Set forwarded headers on proxied requests (default `true`).
+    pub enabled: bool,
+    /// Trusted proxy IPs/CIDRs/ranges (e.g. the load balancer's subnet).
+    pub trusted_proxies: Vec<String>,
+    /// Also emit the RFC 7239 `Forwarded` header (default `false`).
+    pub emit_forwarded: bool,
+}
+
+impl Default for ForwardedHeadersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            trusted_proxies: Vec::new(),
+            emit_forwarded: false,
+        }
+    }
+}
+
+/// A/B experiment assignment.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExperimentsConfig {
+    /// Experiments assigned on every data-plane request.
+    pub experiments: Vec<ExperimentConfig>,
+    /// Headers holding a stable user key, checked in order (e.g. the auth
+    /// principal header `x-user-id`).
+    pub key_headers: Vec<String>,
+    /// Cookies holding a stable user key, checked after `key_headers`.
+    /// Users with no key are bucketed at random.
+    pub key_cookies: Vec<String>,
+    /// Prefix of the assignment cookies (`{cookie_prefix}{experiment}`).
+    pub cookie_prefix: String,
+    /// Lifetime of the assignment cookies (default 30 days).
+    #[serde(with = "humantime_serde")]
+    pub cookie_max_age: Duration,
+}
+
+impl Default for ExperimentsConfig {
+    fn default() -> Self {
+        Self {
+            experiments: Vec::new(),
+            key_headers: Vec::new(),
+            key_cookies: Vec::new(),
+            cookie_prefix: "octopus_exp_".to_string(),
+            cookie_max_age: Duration::from_secs(30 * 24 * 3600),
+        }
+    }
+}
+
+/// One experiment and its weighted variants.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExperimentConfig {
+    /// Experiment name (letters, digits, `-`, `_`); used in the header and
+    /// cookie names.
+    pub name: String,
+    /// Variants; each gets `weight / sum(weights)` of new users.
+    pub variants: Vec<ExperimentVariantConfig>,
+}
+
+/// A weighted experiment variant.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExperimentVariantConfig {
+    /// Variant name sent upstream.
+    pub name: String,
+    /// Relative weight.
+    pub weight: u32,
+}
+
+/// JSON Schema request body validation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestValidationConfig {
+    /// Routes whose bodies are validated; the first matching rule wins.
+    pub rules: Vec<RequestValidationRule>,
+    /// Largest body accepted on a validated route, in bytes (default 1 MiB);
+    /// larger bodies are rejected with 413 before parsing.
+    pub max_body_size: usize,
+}
+
+impl Default for RequestValidationConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Dev-mode upstream response validation. Violations are logged and recorded
+/// in the activity log; responses are never altered beyond the optional
+/// header.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ResponseValidationConfig {
+    /// Check responses against the FARP-federated OpenAPI spec (needs FARP).
+    pub enabled: bool,
+    /// Flag violating responses with an `X-Schema-Violation` header.
+    pub violation_header: bool,
+    /// Larger response bodies are not validated, in bytes (default 1 MiB).
+    pub max_body_size: usize,
+}
+
+impl Default for ResponseValidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            violation_header: false,
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+/// GeoIP lookup of the client IP.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GeoIpConfig {
+    /// Resolve client locations.
+    pub enabled: bool,
+    /// Path to a GeoLite2/GeoIP2 Country or City database (`.mmdb`).
+    pub database: String,
+    /// How often the database file is checked for changes (default 5m).
+    #[serde(with = "humantime_serde")]
+    pub reload_interval: Duration,
+    /// ISO 3166-1 country codes whose clients are rejected.
+    pub blocked_countries: Vec<String>,
+    /// Status for blocked clients: 451 (default) or 403.
+    pub block_status: u16,
+}
+
+impl Default for GeoIpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database: String::new(),
+            reload_interval: Duration::from_secs(300),
+            blocked_countries: Vec::new(),
+            block_status: 451,
+        }
+    }
+}
+
+/// A route whose request bodies are validated.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestValidationRule {
+    /// Path pattern; `{name}` matches one segment and a trailing `/*` any
+    /// suffix.
+    pub path: String,
+    /// Methods to validate; empty = `POST`, `PUT` and `PATCH`.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Inline JSON Schema.
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
+    /// Use the request body schema of the matching operation in the
+    /// FARP-federated OpenAPI spec instead of an inline `schema`.
+    #[serde(default)]
+    pub from_farp: bool,
+}
+
+/// Handling of requests that match no route.
+///
+/// ```yaml
+/// unmatched:
+///   type: upstream        # forward to a catch-all upstream
+///   upstream: legacy-gateway
+/// # or
+/// unmatched:
+///   type: redirect
+///   url: https://legacy.example.com
+/// # or
+/// unmatched:
+///   type: respond
+///   content_type: text/html
+///   body: "<h1>Not found</h1>"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UnmatchedConfig {
+    /// Serve a custom body
+    Respond {
+        /// Response status (default 404)
+        #[serde(default = "default_unmatched_status")]
+        status: u16,
+        /// `Content-Type` of the body (default `application/json`)
+        #[serde(default = "default_unmatched_content_type")]
+        content_type: String,
+        /// Response body
+        body: String,
+    },
+    /// Redirect to another URL
+    Redirect {
+        /// Redirect target
+        url: String,
+        /// Redirect status (default 302)
+        #[serde(default = "default_unmatched_redirect_status")]
+        status: u16,
+        /// Append the request path and query to `url` (default true)
+        #[serde(default = "default_true")]
+        preserve_path: bool,
+    },
+    /// Proxy to a catch-all upstream, e.g. the legacy gateway while routes
+    /// are migrated
+    Upstream {
+        /// Name of a configured upstream
+        upstream: String,
+    },
+}
+
+fn default_unmatched_status() -> u16 {
+    404
+}
+
+fn default_unmatched_content_type() -> String {
+    "application/json".to_string()
+}
+
+fn default_unmatched_redirect_status() -> u16 {
+    302
+}
+
+/// Upstream `Retry-After` handling.
+///
+/// ```yaml
+/// upstream_retry_after:
+///   max_wait: 5s
+///   propagate: true
+/// ```
+///
+/// The instance that sent the header is passed over by load balancing until
+/// the delay elapses. A retry of the same request waits at least that long,
+/// and a delay above `max_wait` ends the retries.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UpstreamRetryAfterConfig {
+    /// Longest delay waited out before retrying the request
+    #[serde(with = "humantime_serde")]
+    pub max_wait: Duration,
+    /// Pass the upstream `Retry-After` header through to the client
+    pub propagate: bool,
+}
+
+impl Default for UpstreamRetryAfterConfig {
+    fn default() -> Self {
+        Self {
+            max_wait: Duration::from_secs(5),
+            propagate: true,
+        }
+    }
+}
+
+/// Header stripping policy. Patterns are case-insensitive header names; a
+/// trailing `*` matches by prefix (`x-internal-*`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct HeaderPolicyConfig {
+    /// Applied to client requests before auth and middleware, e.g. deny
+    /// `x-auth-*` so clients cannot forge identity headers.
+    pub request: HeaderFilterConfig,
+    /// Applied to upstream responses, e.g. deny `x-internal-*`.
+    pub response: HeaderFilterConfig,
+}
+
+/// Deny/allow header name lists; `allow` exempts headers from `deny`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct HeaderFilterConfig {
+    /// Header names or `prefix-*` patterns to strip
+    pub deny: Vec<String>,
+    /// Header names or `prefix-*` patterns kept even if denied
+    pub allow: Vec<String>,
+}
+
+/// Encoded-slash handling (maps to [`octopus_router::EncodedSlash`]).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EncodedSlashMode {
+    /// Reject the request with `400 Bad Request`
+    #[default]
+    Reject,
+    /// Keep `%2F` as literal segment data
+    Literal,
+}
+
+impl From<EncodedSlashMode> for octopus_router::EncodedSlash {
+    fn from(mode: EncodedSlashMode) -> Self {
+        match mode {
+            EncodedSlashMode::Reject => Self::Reject,
+            EncodedSlashMode::Literal => Self::Literal,
+        }
+    }
+}
+
 /// Health probe configuration.
 ///
 /// These endpoints are served on the gateway listen port and are intended for
@@ -196,51 +718,887 @@ pub struct ProbeConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
 
-    /// Liveness probe path (200 while the process is alive).
-    #[serde(default = "default_liveness_path")]
-    pub liveness_path: String,
+    /// Liveness probe path (200 while the process is alive).
+    #[serde(default = "default_liveness_path")]
+    pub liveness_path: String,
+
+    /// Readiness probe path (200 only when ready to serve new traffic).
+    #[serde(default = "default_readiness_path")]
+    pub readiness_path: String,
+
+    /// Startup probe path (200 once the listener has bound).
+    #[serde(default = "default_startup_path")]
+    pub startup_path: String,
+
+    /// When true, readiness waits for the first service-discovery sync to
+    /// complete before reporting ready (only applies when discovery is
+    /// configured).
+    #[serde(default = "default_true")]
+    pub require_discovery_sync: bool,
+
+    /// When true, readiness also requires at least one healthy upstream
+    /// instance (passes when no upstreams are registered).
+    #[serde(default = "default_true")]
+    pub require_healthy_upstream: bool,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            liveness_path: default_liveness_path(),
+            readiness_path: default_readiness_path(),
+            startup_path: default_startup_path(),
+            require_discovery_sync: true,
+            require_healthy_upstream: true,
+        }
+    }
+}
+
+fn default_liveness_path() -> String {
+    "/livez".to_string()
+}
+
+fn default_readiness_path() -> String {
+    "/readyz".to_string()
+}
+
+fn default_startup_path() -> String {
+    "/startupz".to_string()
+}
+
+fn default_pre_stop_delay() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Unix domain socket listener.
+///
+/// A socket file left at `path` by a previous run is removed on startup,
+/// unless another process still accepts connections on it. The file is
+/// removed again on shutdown.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UnixSocketConfig {
+    /// Socket file path
+    pub path: PathBuf,
+
+    /// Permission bits of the socket file in octal, e.g. `"0660"`
+    /// (default: from the process umask)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+
+    /// User id to own the socket file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+
+    /// Group id to own the socket file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+}
+
+impl UnixSocketConfig {
+    /// Permission bits parsed from `mode`; `None` when unset or not an
+    /// octal number no larger than `7777`.
+    pub fn mode_bits(&self) -> Option<u32> {
+        self.mode
+            .as_deref()
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+            .filter(|&bits| bits <= 0o7777)
+    }
+}
+
+/// PROXY protocol on accepted connections.
+///
+/// An L4 load balancer (AWS NLB, HAProxy in TCP mode) can prefix each
+/// connection with a header naming the client it came from. When enabled,
+/// connections from `trusted_sources` must start with such a header, and
+/// the address it carries becomes the connection's client address.
+/// Connections from other peers are served as they are.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProxyProtocolConfig {
+    /// Expect PROXY protocol headers (default `false`). Enable only when
+    /// every trusted source sends one.
+    pub enabled: bool,
+
+    /// Peer IPs/CIDRs/ranges whose headers are honoured, e.g. the load
+    /// balancer's subnet. Required when enabled.
+    pub trusted_sources: Vec<String>,
+
+    /// How long a trusted peer has to send its header
+    #[serde(with = "humantime_serde")]
+    pub header_timeout: Duration,
+}
+
+impl Default for ProxyProtocolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            trusted_sources: Vec::new(),
+            header_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Frontend connection hardening against clients that hold connections
+/// open without making progress (slow-loris).
+///
+/// A connection that misses a deadline is closed. A timeout of `0`
+/// disables it.
+///
+/// ```yaml
+/// gateway:
+///   connection_limits:
+///     first_byte_timeout: 10s
+///     header_read_timeout: 10s
+///     keep_alive_idle_timeout: 60s
+///     max_requests_per_connection: 1000
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ConnectionLimitsConfig {
+    /// Time from accepting a connection to the first byte of its first
+    /// request, TLS handshake included. For HTTP/2 it runs until the first
+    /// request.
+    #[serde(with = "humantime_serde")]
+    pub first_byte_timeout: Duration,
+
+    /// Time from the first byte of an HTTP/1.1 request to the end of its
+    /// headers
+    #[serde(with = "humantime_serde")]
+    pub header_read_timeout: Duration,
+
+    /// How long a connection may sit idle between requests
+    #[serde(with = "humantime_serde")]
+    pub keep_alive_idle_timeout: Duration,
+
+    /// Requests served on one connection before it is closed, after the
+    /// last response (unlimited unless set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_requests_per_connection: Option<u32>,
+}
+
+impl Default for ConnectionLimitsConfig {
+    fn default() -> Self {
+        Self {
+            first_byte_timeout: Duration::from_secs(10),
+            header_read_timeout: Duration::from_secs(10),
+            keep_alive_idle_timeout: Duration::from_secs(60),
+            max_requests_per_connection: None,
+        }
+    }
+}
+
+/// Limit on concurrently handled requests.
+///
+/// Up to `max_concurrent` requests run at once. Further requests wait for a
+/// slot, at most `queue_depth` of them and each for at most `queue_timeout`;
+/// any beyond that are rejected with `503 Service Unavailable`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConcurrencyConfig {
+    /// Requests handled at once
+    pub max_concurrent: usize,
+
+    /// Requests that may wait for a slot (default 0: reject immediately)
+    #[serde(default)]
+    pub queue_depth: usize,
+
+    /// Longest a request waits for a slot
+    #[serde(default = "default_queue_timeout", with = "humantime_serde")]
+    pub queue_timeout: Duration,
+}
+
+fn default_queue_timeout() -> Duration {
+    Duration::from_secs(1)
+}
+
+/// Request priorities for the concurrency limiter.
+///
+/// Each request gets the priority of the first rule it matches, or
+/// `default_priority`. While a limit is saturated its queue is served
+/// highest priority first; requests below `shed_below` are rejected with
+/// `503` instead of queueing, and a request arriving at a full queue takes
+/// the place of a lower-priority one waiting there, which is rejected.
+///
+/// ```yaml
+/// gateway:
+///   concurrency:
+///     max_concurrent: 1000
+///     queue_depth: 200
+///   qos:
+///     default_priority: normal
+///     shed_below: normal
+///     rules:
+///       - priority: high
+///         role: paid
+///       - priority: low
+///         path_prefix: /search
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QosConfig {
+    /// Priority of requests no rule matches
+    #[serde(default)]
+    pub default_priority: RequestPriority,
+
+    /// Requests below this priority are shed rather than queued while a
+    /// limit is saturated
+    #[serde(default)]
+    pub shed_below: RequestPriority,
+
+    /// Classification rules, first match wins
+    #[serde(default)]
+    pub rules: Vec<QosRule>,
+}
+
+/// One QoS classification rule; a request matches when it meets every
+/// condition the rule sets
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QosRule {
+    /// Priority given to matching requests
+    pub priority: RequestPriority,
+
+    /// Request path starts with this prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+
+    /// Request carries this header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+
+    /// ...with this value (requires `header`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_value: Option<String>,
+
+    /// Request belongs to this tenant (see `gateway.tenancy`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+
+    /// Authenticated principal has this role. Rules on roles make the
+    /// limiter run after authentication.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+impl QosRule {
+    /// Whether the rule sets any condition
+    pub fn has_conditions(&self) -> bool {
+        self.path_prefix.is_some()
+            || self.header.is_some()
+            || self.tenant.is_some()
+            || self.role.is_some()
+    }
+}
+
+/// Priority of a request under QoS scheduling, lowest first
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    /// Best effort; shed first
+    Low,
+    /// Ordinary traffic
+    #[default]
+    Normal,
+    /// Served ahead of normal traffic
+    High,
+    /// Served ahead of everything else
+    Critical,
+}
+
+/// Handling of `multipart/form-data` request bodies.
+///
+/// Multipart bodies are held to `max_total_size` instead of
+/// `gateway.max_body_size`, and each part to `max_part_size`; both are
+/// checked as the body is read, so an oversized part is rejected with `413`
+/// without reading the rest. With `allowed_fields`, a part named anything
+/// else is rejected with `400`. With `stream` the body is sent on to the
+/// upstream as it arrives instead of being buffered first.
+///
+/// ```yaml
+/// gateway:
+///   multipart:
+///     max_part_size: 104857600      # 100 MiB per file
+///     max_total_size: 262144000     # 250 MiB per request
+///     allowed_fields: [avatar, description]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MultipartConfig {
+    /// Largest single part (headers excluded), in bytes
+    pub max_part_size: usize,
+
+    /// Largest multipart body, in bytes
+    pub max_total_size: usize,
+
+    /// Stream the body to the upstream as it arrives (default `false`). The
+    /// request is not retried, and configuration with middleware that reads
+    /// request bodies is rejected: it would see an empty one.
+    #[serde(default)]
+    pub stream: bool,
+
+    /// Field names parts may have; empty allows any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_fields: Vec<String>,
+}
+
+/// Internal redirects requested by upstreams.
+///
+/// An upstream response carrying `header` is not returned: its value, a path
+/// with an optional query, is fetched with a `GET` routed like a client
+/// request, and that response is returned instead. The client's headers are
+/// sent along; the first response is discarded. A redirect answered by
+/// another redirect is followed up to `max_hops` times.
+///
+/// ```yaml
+/// gateway:
+///   internal_redirect:
+///     header: x-accel-redirect
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InternalRedirectConfig {
+    /// Response header carrying the path to fetch
+    #[serde(default = "default_internal_redirect_header")]
+    pub header: String,
+
+    /// Most redirects followed for one request
+    #[serde(default = "default_internal_redirect_max_hops")]
+    pub max_hops: u32,
+}
+
+fn default_internal_redirect_header() -> String {
+    "x-accel-redirect".to_string()
+}
+
+fn default_internal_redirect_max_hops() -> u32 {
+    3
+}
+
+/// Cap on the size of a buffered upstream response body.
+///
+/// The body is measured as it is read. Past `max_bytes` the request fails
+/// with `502 Bad Gateway` (`abort`, the default), or the body is cut at
+/// `max_bytes` and a warning logged (`truncate`). A compressed
+/// (`Content-Encoding`) body is never truncated, as the cut would corrupt it,
+/// and fails instead. An upstream that declares a larger `Content-Length` is
+/// aborted before its body is read.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseBodyLimitConfig {
+    /// Largest response body, in bytes
+    pub max_bytes: usize,
+
+    /// What to do with a larger body
+    #[serde(default)]
+    pub on_exceed: ResponseBodyOverflow,
+}
+
+impl ResponseBodyLimitConfig {
+    /// The limit as carried on requests to the proxy
+    pub fn limit(&self) -> octopus_core::ResponseBodyLimit {
+        octopus_core::ResponseBodyLimit {
+            max_bytes: self.max_bytes,
+            truncate: self.on_exceed == ResponseBodyOverflow::Truncate,
+        }
+    }
+}
+
+/// Idempotency keys for unsafe requests, such as payment `POST`s.
+///
+/// A request with one of `methods` that carries `header` runs once per key,
+/// method, route, principal and body. Its response is stored for `ttl` and
+/// replayed to retries, marked `Idempotent-Replayed: true`; a retry while
+/// the first request is still in flight gets `409 Conflict`. Server errors
+/// are not stored, so those requests can be retried. An in-flight request
+/// holds its key for at most `lock_timeout`. Keys live in-process unless
+/// `redis_url` points the gateway at a store shared across replicas.
+///
+/// ```yaml
+/// gateway:
+///   idempotency:
+///     header: Idempotency-Key
+///     methods: [POST, PATCH]
+///     ttl: 24h
+///     lock_timeout: 1m
+///     max_body_size: 1048576
+///     redis_url: redis://redis:6379
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdempotencyConfig {
+    /// Header carrying the client's idempotency key
+    #[serde(default = "default_idempotency_header")]
+    pub header: String,
+
+    /// Methods whose keyed requests are deduplicated
+    #[serde(default = "default_idempotency_methods")]
+    pub methods: Vec<String>,
+
+    /// How long a response is replayed for
+    #[serde(default = "default_idempotency_ttl", with = "humantime_serde")]
+    pub ttl: Duration,
+
+    /// Longest a key stays in flight, in case its request never completes
+    #[serde(default = "default_idempotency_lock_timeout", with = "humantime_serde")]
+    pub lock_timeout: Duration,
+
+    /// Responses the gateway's in-process store holds; once full, new ones
+    /// are not stored until stored ones expire
+    #[serde(default = "default_idempotency_max_entries")]
+    pub max_entries: usize,
+
+    /// Largest request or response body, in bytes, that is hashed or stored.
+    /// Larger requests get `413`; larger responses are returned but not stored.
+    #[serde(default = "default_idempotency_max_body_size")]
+    pub max_body_size: usize,
+
+    /// Redis URL of a store shared across replicas (needs the `redis`
+    /// feature); keys stay in-process when unset
+    #[serde(default)]
+    pub redis_url: Option<String>,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            header: default_idempotency_header(),
+            methods: default_idempotency_methods(),
+            ttl: default_idempotency_ttl(),
+            lock_timeout: default_idempotency_lock_timeout(),
+            max_entries: default_idempotency_max_entries(),
+            max_body_size: default_idempotency_max_body_size(),
+            redis_url: None,
+        }
+    }
+}
+
+fn default_idempotency_header() -> String {
+    "Idempotency-Key".to_string()
+}
+
+fn default_idempotency_methods() -> Vec<String> {
+    vec!["POST".to_string(), "PATCH".to_string()]
+}
+
+fn default_idempotency_ttl() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+fn default_idempotency_lock_timeout() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_idempotency_max_entries() -> usize {
+    100_000
+}
+
+fn default_idempotency_max_body_size() -> usize {
+    1024 * 1024
+}
+
+/// HMAC-SHA256 request signing.
+///
+/// Every request must name one of `keys` and carry a Unix timestamp within
+/// `max_skew` of the gateway clock, plus the signature of its method, path
+/// and query, timestamp, nonce and body hash. With `require_nonce`, each
+/// request also carries a nonce that is accepted once: it is remembered for
+/// twice `max_skew`, as long as a request dated up to `max_skew` ahead can
+/// pass the timestamp check, so a replay is rejected either way. Nonces live in-process unless `redis_url` points the gateway at a
+/// store shared across replicas.
+///
+/// ```yaml
+/// gateway:
+///   request_signing:
+///     keys:
+///       billing: ${BILLING_SIGNING_SECRET}
+///     max_skew: 5m
+///     require_nonce: true
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestSigningConfig {
+    /// Shared secrets by key id (at least 32 bytes each)
+    pub keys: HashMap<String, String>,
+
+    /// Largest accepted difference between a request's timestamp and the
+    /// gateway clock; nonces are remembered for twice this
+    #[serde(default = "default_signing_max_skew", with = "humantime_serde")]
+    pub max_skew: Duration,
+
+    /// Reject signed requests without a nonce
+    #[serde(default)]
+    pub require_nonce: bool,
+
+    /// Redis URL of a nonce store shared across replicas (needs the `redis`
+    /// feature); nonces stay in-process when unset
+    #[serde(default)]
+    pub redis_url: Option<String>,
+}
+
+impl Default for RequestSigningConfig {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::new(),
+            max_skew: default_signing_max_skew(),
+            require_nonce: false,
+            redis_url: None,
+        }
+    }
+}
+
+fn default_signing_max_skew() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+/// Startup self-check of upstream reachability.
+///
+/// Before readiness flips to true, the gateway opens one TCP connection per
+/// upstream cluster, trying its instances in turn until one accepts. A
+/// cluster none of whose instances accept within `timeout` is unreachable;
+/// `policy` decides whether that fails startup or is logged. Clusters with
+/// no instances, such as discovery-fed ones not yet populated, are skipped.
+///
+/// ```yaml
+/// gateway:
+///   startup_check:
+///     policy: strict
+///     timeout: 2s
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StartupCheckConfig {
+    /// What to do about unreachable clusters
+    #[serde(default)]
+    pub policy: StartupCheckPolicy,
+
+    /// How long each connection attempt may take
+    #[serde(default = "default_startup_check_timeout", with = "humantime_serde")]
+    pub timeout: Duration,
+}
+
+impl Default for StartupCheckConfig {
+    fn default() -> Self {
+        Self {
+            policy: StartupCheckPolicy::default(),
+            timeout: default_startup_check_timeout(),
+        }
+    }
+}
+
+/// Outcome of a startup check that finds unreachable clusters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupCheckPolicy {
+    /// Fail startup; the gateway never reports ready
+    Strict,
+    /// Log a warning per unreachable cluster and start anyway
+    #[default]
+    Warn,
+}
+
+fn default_startup_check_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+/// Startup warmup: one-off work done before the gateway serves traffic
+/// rather than on the first requests that need it.
+///
+/// Script middleware is compiled and, with `fetch_jwks`, auth providers
+/// fetch any signing keys they don't have yet. A failing step is logged and the work is left to happen
+/// lazily, unless `fail_on_error` makes it fail startup.
+///
+/// ```yaml
+/// gateway:
+///   warmup:
+///     enabled: true
+///     fetch_jwks: true
+///     fail_on_error: false
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WarmupConfig {
+    /// Whether to warm up at all
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Have auth providers fetch their signing keys (JWKS) up front
+    #[serde(default = "default_true")]
+    pub fetch_jwks: bool,
+
+    /// Fail startup if any warmup step fails instead of logging it
+    #[serde(default)]
+    pub fail_on_error: bool,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fetch_jwks: true,
+            fail_on_error: false,
+        }
+    }
+}
+
+/// Handling of a response body over [`ResponseBodyLimitConfig::max_bytes`]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseBodyOverflow {
+    /// Fail the request with `502 Bad Gateway`
+    #[default]
+    Abort,
+    /// Forward the first `max_bytes` bytes
+    Truncate,
+}
+
+/// Debug tap: full request/response capture for tagged requests.
+///
+/// A request is captured only when `header` carries an HS256 JWT signed with
+/// `secret`, with audience `octopus-debug-tap`, an `exp` and an `iat` at most
+/// `max_token_ttl` apart. The header is never forwarded upstream. Captures
+/// keep the last `capacity` exchanges, with `redact_headers`, the query
+/// parameters in `redact_query` and the JSON body fields at `redact_fields`
+/// masked.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DebugTapConfig {
+    /// Header carrying the debug token
+    #[serde(default = "default_debug_tap_header")]
+    pub header: String,
+
+    /// HMAC secret debug tokens are signed with (at least 32 bytes)
+    pub secret: String,
+
+    /// Longest lifetime (`exp - iat`) a debug token may have
+    #[serde(default = "default_debug_tap_token_ttl", with = "humantime_serde")]
+    pub max_token_ttl: Duration,
+
+    /// Exchanges kept for retrieval
+    #[serde(default = "default_debug_tap_capacity")]
+    pub capacity: usize,
+
+    /// Body bytes kept per captured request or response
+    #[serde(default = "default_debug_tap_body_bytes")]
+    pub max_body_bytes: usize,
+
+    /// Headers whose values are masked in captures, in addition to
+    /// credentials and cookies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact_headers: Vec<String>,
+
+    /// JSON body fields masked in captures (paths like `password` or
+    /// `$.card.number`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact_fields: Vec<String>,
+
+    /// Query parameters whose values are masked in captures, in addition to
+    /// common credential parameters such as `access_token`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact_query: Vec<String>,
+}
+
+fn default_debug_tap_header() -> String {
+    "x-octopus-debug".to_string()
+}
+
+fn default_debug_tap_token_ttl() -> Duration {
+    Duration::from_secs(3600)
+}
+
+fn default_debug_tap_capacity() -> usize {
+    100
+}
+
+fn default_debug_tap_body_bytes() -> usize {
+    64 * 1024
+}
+
+/// Debug response headers.
+///
+/// Each response gets the matched route (`X-Octopus-Route`), the upstream and
+/// instance that answered (`X-Octopus-Upstream`, `X-Octopus-Instance`),
+/// whether it came from cache (`X-Cache`), the retries made
+/// (`X-Octopus-Retries`) and auth, proxy and total time (`Server-Timing`).
+/// They expose internal addresses, so by default only requests whose
+/// `header` carries `secret` get them; `always` adds them to every response.
+/// The trigger header is never forwarded upstream.
+///
+/// ```yaml
+/// gateway:
+///   debug_headers:
+///     header: x-octopus-debug-headers
+///     secret: ${DEBUG_HEADERS_SECRET}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DebugHeadersConfig {
+    /// Add the headers to every response, not only triggered ones
+    #[serde(default)]
+    pub always: bool,
+
+    /// Header a request sets to `secret` to get the debug headers
+    #[serde(default = "default_debug_headers_header")]
+    pub header: String,
+
+    /// Shared secret the trigger header must carry (at least 32 bytes);
+    /// required unless `always` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+fn default_debug_headers_header() -> String {
+    "x-octopus-debug-headers".to_string()
+}
+
+/// Request IDs.
+///
+/// A request without `header` gets a generated ID in it, which is forwarded
+/// upstream, seen by plugins and, with `add_to_response`, echoed back. An ID
+/// the client sent is kept.
+///
+/// ```yaml
+/// gateway:
+///   request_id:
+///     generator: snowflake
+///     worker_id: 3
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RequestIdConfig {
+    /// Header carrying the request ID
+    #[serde(default = "default_request_id_header")]
+    pub header: String,
+
+    /// How missing IDs are generated
+    #[serde(default)]
+    pub generator: RequestIdGenerator,
+
+    /// Worker id stamped into `snowflake` IDs (`0..=1023`); give each gateway
+    /// instance its own so their IDs never collide
+    #[serde(default)]
+    pub worker_id: u16,
+
+    /// Echo the request ID in the response
+    #[serde(default = "default_true")]
+    pub add_to_response: bool,
+}
+
+fn default_request_id_header() -> String {
+    "x-request-id".to_string()
+}
+
+/// How request IDs are generated
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestIdGenerator {
+    /// Random UUID v4
+    #[default]
+    UuidV4,
+    /// Millisecond timestamp followed by random bits
+    Ulid,
+    /// Time-ordered UUID v7
+    UuidV7,
+    /// 64-bit snowflake IDs stamped with `worker_id`
+    Snowflake,
+}
+
+/// Multi-tenant routing.
+///
+/// Each request's tenant id is taken from `source`, checked against
+/// `tenants` and sent upstream in `header` (client-supplied values are
+/// dropped). A tenant with its own `upstream` has all its traffic proxied
+/// there instead of to the route's upstream. Requests for an unknown tenant,
+/// and when `required` those carrying none, get `unknown_status`.
+///
+/// ```yaml
+/// gateway:
+///   tenancy:
+///     source:
+///       type: subdomain          # acme.api.example.com
+///       base_domain: api.example.com
+///     # or: { type: path, prefix: /t }            /t/acme/orders → /orders
+///     # or: { type: header, name: X-Tenant }
+///     # or: { type: claim, claim: org.tenant }    bearer token claim
+///     tenants:
+///       acme:
+///         upstream: acme-api
+///         headers:
+///           X-Tenant-Plan: enterprise
+///       globex: {}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TenancyConfig {
+    /// Where the tenant id is read from
+    pub source: TenantSourceConfig,
 
-    /// Readiness probe path (200 only when ready to serve new traffic).
-    #[serde(default = "default_readiness_path")]
-    pub readiness_path: String,
+    /// Header carrying the resolved tenant id upstream
+    #[serde(default = "default_tenant_header")]
+    pub header: String,
 
-    /// Startup probe path (200 once the listener has bound).
-    #[serde(default = "default_startup_path")]
-    pub startup_path: String,
+    /// Status for unknown (or, when `required`, missing) tenants: 403 or 404
+    #[serde(default = "default_unknown_tenant_status")]
+    pub unknown_status: u16,
 
-    /// When true, readiness waits for the first service-discovery sync to
-    /// complete before reporting ready (only applies when discovery is
-    /// configured).
+    /// Reject requests no tenant can be read from (default `true`)
     #[serde(default = "default_true")]
-    pub require_discovery_sync: bool,
+    pub required: bool,
+
+    /// Known tenants by id (ids are matched case-insensitively)
+    #[serde(default)]
+    pub tenants: HashMap<String, TenantConfig>,
 }
 
-impl Default for ProbeConfig {
-    fn default() -> Self {
-        Self {
-            enabled: true,
-            liveness_path: default_liveness_path(),
-            readiness_path: default_readiness_path(),
-            startup_path: default_startup_path(),
-            require_discovery_sync: true,
-        }
-    }
+/// Where a request's tenant id comes from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TenantSourceConfig {
+    /// The host label directly left of `base_domain`
+    /// (`acme.api.example.com` → `acme`)
+    Subdomain {
+        /// Domain tenants are subdomains of
+        base_domain: String,
+    },
+    /// The path segment after `prefix` (`/t/acme/orders` → `acme`). The
+    /// prefix and tenant segment are removed before routing, so routes are
+    /// declared without them.
+    Path {
+        /// Path prefix ahead of the tenant segment (default `/t`)
+        #[serde(default = "default_tenant_path_prefix")]
+        prefix: String,
+    },
+    /// A request header
+    Header {
+        /// Header name
+        name: String,
+    },
+    /// A claim of the token auth verified (`tenant`, `org.id`), resolved
+    /// after the auth middleware. Requests no auth verified have no tenant.
+    Claim {
+        /// Claim path
+        claim: String,
+        /// Header carrying the token (default `Authorization`). Unused: the
+        /// claims come from the auth middleware, which reads its own header.
+        #[serde(default = "default_tenant_token_header")]
+        token_header: String,
+    },
 }
 
-fn default_liveness_path() -> String {
-    "/livez".to_string()
+/// Per-tenant settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TenantConfig {
+    /// Upstream serving this tenant in place of the route's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+
+    /// Headers added to this tenant's upstream requests
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
 }
 
-fn default_readiness_path() -> String {
-    "/readyz".to_string()
+fn default_tenant_header() -> String {
+    "X-Tenant-Id".to_string()
 }
 
-fn default_startup_path() -> String {
-    "/startupz".to_string()
+fn default_unknown_tenant_status() -> u16 {
+    404
 }
 
-fn default_pre_stop_delay() -> Duration {
-    Duration::from_secs(5)
+fn default_tenant_path_prefix() -> String {
+    "/t".to_string()
+}
+
+fn default_tenant_token_header() -> String {
+    "Authorization".to_string()
 }
 
 /// TLS configuration
@@ -291,6 +1649,42 @@ pub struct CompressionConfig {
     /// Preferred compression algorithms in order
     #[serde(default = "default_compression_algorithms")]
     pub algorithms: Vec<String>,
+
+    /// Adaptive mode: skip content types that don't shrink and lower the
+    /// level under CPU load
+    #[serde(default)]
+    pub adaptive: AdaptiveCompressionConfig,
+}
+
+/// Adaptive compression settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AdaptiveCompressionConfig {
+    /// Sample the savings per content type and skip types that don't shrink
+    pub enabled: bool,
+    /// Responses compressed per content type before deciding
+    pub sample_size: u32,
+    /// Minimum fraction of bytes saved (0.0-1.0) to keep compressing a type
+    pub min_savings: f64,
+    /// Skipped responses after which a type is sampled again (0 = never)
+    pub resample_after: u32,
+    /// Load per core above which `high_load_level` is used (e.g. 0.8)
+    pub cpu_high_watermark: Option<f64>,
+    /// Compression level used above `cpu_high_watermark`
+    pub high_load_level: u32,
+}
+
+impl Default for AdaptiveCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_size: 16,
+            min_savings: 0.1,
+            resample_after: 1000,
+            cpu_high_watermark: None,
+            high_load_level: 1,
+        }
+    }
 }
 
 impl Default for CompressionConfig {
@@ -304,6 +1698,7 @@ impl Default for CompressionConfig {
                 "zstd".to_string(), // zstd (fast)
                 "gzip".to_string(), // gzip (universal)
             ],
+            adaptive: AdaptiveCompressionConfig::default(),
         }
     }
 }
@@ -417,6 +1812,15 @@ pub struct FarpDiscoveryConfig {
     /// Discovery backends
     #[serde(default)]
     pub backends: Vec<DiscoveryBackendConfig>,
+
+    /// How long a route `service`'s resolved instances are reused before
+    /// the backends are asked again
+    #[serde(default = "default_resolve_cache_ttl", with = "humantime_serde")]
+    pub resolve_cache_ttl: Duration,
+}
+
+fn default_resolve_cache_ttl() -> Duration {
+    Duration::from_secs(5)
 }
 
 /// Individual discovery backend configuration
@@ -550,6 +1954,53 @@ pub struct UpstreamConfig {
     /// Circuit breaker configuration
     #[serde(default)]
     pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    /// Local OpenAPI spec to generate this upstream's routes from at startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openapi: Option<UpstreamOpenApiConfig>,
+
+    /// Ramp up new and recovered instances' traffic over a window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_start: Option<octopus_core::SlowStartConfig>,
+}
+
+/// Routes generated from a local OpenAPI file, without FARP registration.
+///
+/// Every operation in the spec becomes a route to the upstream, mounted under
+/// `prefix` like FARP-registered services: `GET /users/{id}` in the spec of
+/// upstream `users` is served at `/users/users/:id`, and the prefix is
+/// stripped before proxying.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpstreamOpenApiConfig {
+    /// Spec file (JSON or YAML), relative to the working directory
+    pub path: PathBuf,
+
+    /// Path prefix for the generated routes (default: `/{upstream name}`,
+    /// lowercased); `""` serves them at the spec's own paths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    /// Only generate operations carrying one of these tags (empty = all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Priority of the generated routes
+    #[serde(default = "default_openapi_priority")]
+    pub priority: i32,
+}
+
+fn default_openapi_priority() -> i32 {
+    100
+}
+
+impl UpstreamOpenApiConfig {
+    /// The prefix generated routes are mounted under for `upstream`.
+    pub fn prefix_for(&self, upstream: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => prefix.trim_end_matches('/').to_string(),
+            None => format!("/{}", upstream.to_lowercase()),
+        }
+    }
 }
 
 /// Instance configuration
@@ -602,6 +2053,21 @@ pub struct HealthCheckConfig {
 }
 
 /// Circuit breaker configuration
+///
+/// Applies to each of the upstream's instances. Which outcomes count as
+/// failures is configurable: a response whose status is in
+/// `failure_status_codes`, a connection error (refused, reset, DNS, TLS) and
+/// a timeout. Failures of the gateway's own, such as a response over the
+/// size limit, never count.
+///
+/// ```yaml
+/// circuit_breaker:
+///   error_threshold: 50.0
+///   min_requests: 20
+///   timeout: 30s
+///   failure_status_codes: [502, 503, 504]
+///   count_timeouts: false
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CircuitBreakerConfig {
     /// Error threshold percentage
@@ -613,6 +2079,23 @@ pub struct CircuitBreakerConfig {
     /// Open state timeout
     #[serde(with = "humantime_serde")]
     pub timeout: Duration,
+
+    /// Response statuses counted as failures (default: 500-599); any other
+    /// status counts as a success
+    #[serde(default = "default_failure_status_codes")]
+    pub failure_status_codes: Vec<u16>,
+
+    /// Whether connection errors count as failures; if not they are ignored
+    #[serde(default = "default_true")]
+    pub count_connection_errors: bool,
+
+    /// Whether timeouts count as failures; if not they are ignored
+    #[serde(default = "default_true")]
+    pub count_timeouts: bool,
+}
+
+fn default_failure_status_codes() -> Vec<u16> {
+    (500..=599).collect()
 }
 
 /// Route configuration
@@ -625,10 +2108,18 @@ pub struct RouteConfig {
     #[serde(default)]
     pub methods: Vec<String>,
 
-    /// Upstream name
+    /// Upstream name (may be omitted in a route group that sets one)
+    #[serde(default)]
     pub upstream: String,
 
-    /// Priority (higher = matched first)
+    /// Service resolved per request through the discovery backends
+    /// (`farp.discovery`), instead of a static `upstream`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+
+    /// Priority: breaks ties between routes that match a request equally
+    /// specifically (static segments beat parameters beat wildcards); higher
+    /// wins
     #[serde(default)]
     pub priority: i32,
 
@@ -664,7 +2155,8 @@ pub struct RouteConfig {
     #[serde(default)]
     pub authz_rule: Option<String>,
 
-    /// Per-route request timeout override
+    /// Per-route request timeout override: the total upstream time budget,
+    /// shared across retries (defaults to `gateway.request_timeout`)
     #[serde(default, with = "humantime_serde::option")]
     pub timeout: Option<Duration>,
 
@@ -672,6 +2164,10 @@ pub struct RouteConfig {
     #[serde(default)]
     pub rate_limit: Option<RouteRateLimitConfig>,
 
+    /// Per-route concurrency limit, applied after `gateway.concurrency`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<ConcurrencyConfig>,
+
     /// Per-route CORS override
     #[serde(default)]
     pub cors: Option<RouteCorsConfig>,
@@ -702,36 +2198,464 @@ pub struct RouteConfig {
     /// Set to `false` for self-signed or internal-CA certs.
     #[serde(default)]
     pub tls_verify: Option<bool>,
+
+    /// Response served instead of an error when the upstream has no healthy
+    /// instance or every retry failed.
+    #[serde(default)]
+    pub fallback: Option<RouteFallbackConfig>,
+
+    /// Upstream that takes the route's traffic while its own has no healthy
+    /// instance or every instance's circuit breaker is open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_upstream: Option<String>,
+
+    /// Region-specific upstreams keyed by client location: an ISO 3166-2
+    /// region (`US-CA`), country (`DE`) or continent (`EU`); the most
+    /// specific match wins. Needs `gateway.geoip`.
+    #[serde(default)]
+    pub geo_upstreams: HashMap<String, String>,
+
+    /// Response body limit for this route, in place of
+    /// `gateway.response_body_limit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body_limit: Option<ResponseBodyLimitConfig>,
+
+    /// Declarative JSON body transforms for this route.
+    #[serde(default)]
+    pub transform: Option<RouteTransformConfig>,
+}
+
+/// Per-route JSON body transforms, applied in order. Non-JSON bodies and
+/// bodies larger than `max_body_size` pass through untouched.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RouteTransformConfig {
+    /// Rules applied to the request body before it is proxied.
+    pub request: Vec<JsonTransformRule>,
+    /// Rules applied to the upstream response body.
+    pub response: Vec<JsonTransformRule>,
+    /// Largest body transformed, in bytes (default 1 MiB).
+    pub max_body_size: usize,
+    /// Header rules applied to the request before it is proxied.
+    pub request_headers: HeaderTemplateRules,
+    /// Header rules applied to the upstream response.
+    pub response_headers: HeaderTemplateRules,
+    /// Changes to the request's `Cookie` header.
+    pub request_cookies: RequestCookieRules,
+    /// Changes to the upstream response's `Set-Cookie` headers.
+    pub response_cookies: ResponseCookieRules,
+    /// Changes to the request's query parameters.
+    pub query: QueryRules,
+}
+
+impl RouteTransformConfig {
+    /// Whether any header, cookie or query rules are configured (everything
+    /// but the JSON body rules).
+    pub fn has_non_body_rules(&self) -> bool {
+        !self.request_headers.is_empty()
+            || !self.response_headers.is_empty()
+            || !self.request_cookies.is_empty()
+            || !self.response_cookies.is_empty()
+            || !self.query.is_empty()
+    }
+}
+
+/// Query parameter changes before proxying, applied in field order. The
+/// path is kept; the query is re-encoded. Repeated parameters are matched
+/// (and renamed or dropped) value by value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct QueryRules {
+    /// Keep only these parameters (by incoming name); empty = keep all
+    pub allow: Vec<String>,
+    /// Parameters to drop
+    pub remove: Vec<String>,
+    /// Parameters to rename (old name -> new name)
+    pub rename: HashMap<String, String>,
+    /// Parameters to append; combine with `remove` to replace a value
+    pub add: std::collections::BTreeMap<String, String>,
+}
+
+impl QueryRules {
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty()
+            && self.remove.is_empty()
+            && self.rename.is_empty()
+            && self.add.is_empty()
+    }
+}
+
+impl Default for RouteTransformConfig {
+    fn default() -> Self {
+        Self {
+            request: Vec::new(),
+            response: Vec::new(),
+            max_body_size: 1024 * 1024,
+            request_headers: HeaderTemplateRules::default(),
+            response_headers: HeaderTemplateRules::default(),
+            request_cookies: RequestCookieRules::default(),
+            response_cookies: ResponseCookieRules::default(),
+            query: QueryRules::default(),
+        }
+    }
+}
+
+/// Header changes for a route. Values in `set` and `add` are templates with
+/// `{variable}` placeholders (`{param.id}`, `{claim.sub}`, `{route_path}`,
+/// `{request_id}`, `{now}`, `{header.<name>}`; see
+/// [`octopus_core::Template`]); a header whose variables have no value on a
+/// request is left out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HeaderTemplateRules {
+    /// Headers to set, replacing existing values
+    pub set: HashMap<String, String>,
+    /// Headers to append
+    pub add: HashMap<String, String>,
+    /// Headers to remove (before `set`/`add`)
+    pub remove: Vec<String>,
+}
+
+impl HeaderTemplateRules {
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.add.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Changes to request cookies before proxying.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestCookieRules {
+    /// Cookies to set, replacing any with the same name
+    pub set: HashMap<String, String>,
+    /// Cookies to drop (e.g. sensitive ones the upstream must not see)
+    pub remove: Vec<String>,
+}
+
+impl RequestCookieRules {
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Changes to the upstream's `Set-Cookie` headers. Attribute changes apply
+/// to every cookie, or only to those listed in `cookies`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ResponseCookieRules {
+    /// Cookies to drop
+    pub remove: Vec<String>,
+    /// Extra `Set-Cookie` values to append, verbatim
+    pub add: Vec<String>,
+    /// Restrict attribute changes to these cookie names; empty = all
+    pub cookies: Vec<String>,
+    /// Set `SameSite`
+    pub same_site: Option<octopus_core::SameSite>,
+    /// Add (`true`) or strip (`false`) the `Secure` flag
+    pub secure: Option<bool>,
+    /// Add (`true`) or strip (`false`) the `HttpOnly` flag
+    pub http_only: Option<bool>,
+    /// Set `Domain`; an empty string removes it
+    pub domain: Option<String>,
+}
+
+impl ResponseCookieRules {
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.remove.is_empty()
+            && self.add.is_empty()
+            && self.same_site.is_none()
+            && self.secure.is_none()
+            && self.http_only.is_none()
+            && self.domain.is_none()
+    }
+}
+
+/// A JSON body transform. Paths are JSONPath-style (`$.user.name`,
+/// `$.items[0].id`); the leading `$.` is optional.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JsonTransformRule {
+    /// Move a field to another path
+    Rename {
+        /// Source path
+        from: String,
+        /// Destination path
+        to: String,
+    },
+    /// Drop a field
+    Remove {
+        /// Field path
+        path: String,
+    },
+    /// Set a field, replacing any existing value
+    Set {
+        /// Field path
+        path: String,
+        /// Value to set
+        value: serde_json::Value,
+    },
+    /// Set a field only when it is absent or null
+    Default {
+        /// Field path
+        path: String,
+        /// Default value
+        value: serde_json::Value,
+    },
+    /// Nest the whole body under a path (`$.data` turns `{..}` into
+    /// `{"data": {..}}`)
+    Wrap {
+        /// Path the body is moved to
+        path: String,
+    },
+    /// Replace the whole body with the value at a path
+    Unwrap {
+        /// Path of the new body
+        path: String,
+    },
+    /// Replace a field's value with `***REDACTED***`
+    Redact {
+        /// Field path
+        path: String,
+    },
+}
+
+/// Per-route graceful-degradation fallback (maps to
+/// [`octopus_router::RouteFallback`]).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RouteFallbackConfig {
+    /// Serve a canned JSON body
+    StaticJson {
+        /// Response status (default 200)
+        #[serde(default = "default_fallback_status")]
+        status: u16,
+        /// JSON body
+        body: serde_json::Value,
+    },
+    /// Respond with a bare status code
+    Status {
+        /// Response status
+        code: u16,
+    },
+    /// Redirect (302) to a URL
+    Redirect {
+        /// Redirect target
+        url: String,
+    },
+    /// Serve the last successful anonymous `GET` response for the same URL
+    LastGoodCache {
+        /// How long a captured response stays servable (default 5m)
+        #[serde(default = "default_last_good_max_age", with = "humantime_serde")]
+        max_age: Duration,
+    },
+}
+
+fn default_fallback_status() -> u16 {
+    200
+}
+
+fn default_last_good_max_age() -> Duration {
+    Duration::from_secs(300)
+}
+
+impl RouteConfig {
+    /// Build a [`octopus_router::ProxySpec`] if any proxy field is set;
+    /// returns `None` when no proxy fields are present so legacy routes are
+    /// unaffected.
+    pub fn proxy_spec(&self) -> Option<octopus_router::ProxySpec> {
+        let any = self.path_mode.is_some()
+            || self.upstream_origin.is_some()
+            || self.rewrite_redirects.is_some()
+            || self.rewrite_cookie_path.is_some();
+        if !any {
+            return None;
+        }
+        let origin = self.upstream_origin.as_ref().and_then(|u| {
+            octopus_router::UpstreamOrigin::parse(u, self.tls_verify.unwrap_or(true))
+        });
+        Some(octopus_router::ProxySpec {
+            origin,
+            path_mode: match self.path_mode.as_deref() {
+                Some("passthrough") => octopus_router::PathMode::Passthrough,
+                Some("strip") | None => octopus_router::PathMode::Strip,
+                Some(other) => {
+                    tracing::warn!(path_mode = %other, "unrecognized path-mode; defaulting to strip");
+                    octopus_router::PathMode::Strip
+                }
+            },
+            rewrite_redirects: self.rewrite_redirects.unwrap_or(false),
+            rewrite_cookie_path: self.rewrite_cookie_path.unwrap_or(false),
+        })
+    }
+
+    /// Build the [`octopus_router::RouteFallback`], or `None` when no fallback
+    /// is configured or its status code is invalid.
+    pub fn fallback_spec(&self) -> Option<octopus_router::RouteFallback> {
+        let status = |code: u16| {
+            octopus_core::StatusCode::from_u16(code)
+                .map_err(|_| tracing::warn!(route = %self.path, code, "invalid fallback status; ignoring fallback"))
+                .ok()
+        };
+        Some(match self.fallback.as_ref()? {
+            RouteFallbackConfig::StaticJson { status: code, body } => {
+                octopus_router::RouteFallback::StaticJson {
+                    status: status(*code)?,
+                    body: body.to_string(),
+                }
+            }
+            RouteFallbackConfig::Status { code } => {
+                octopus_router::RouteFallback::Status(status(*code)?)
+            }
+            RouteFallbackConfig::Redirect { url } => {
+                octopus_router::RouteFallback::Redirect(url.clone())
+            }
+            RouteFallbackConfig::LastGoodCache { max_age } => {
+                octopus_router::RouteFallback::LastGoodCache { max_age: *max_age }
+            }
+        })
+    }
+}
+
+/// A set of routes sharing a path prefix, auth and per-route middleware.
+///
+/// Each member is a regular route whose path is relative to `prefix`. A
+/// setting the member leaves unset is taken from the group; one it sets wins.
+/// `metadata` is merged (member keys win) and `skip_auth` on either makes the
+/// route public.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteGroupConfig {
+    /// Group name, for error messages
+    pub name: String,
+
+    /// Path prefix prepended to every member's path, e.g. `/api/v1`
+    pub prefix: String,
+
+    /// Upstream for members that don't name one
+    #[serde(default)]
+    pub upstream: Option<String>,
+
+    /// Strip prefix for members without their own
+    #[serde(default)]
+    pub strip_prefix: Option<String>,
+
+    /// Add prefix for members without their own
+    #[serde(default)]
+    pub add_prefix: Option<String>,
+
+    /// Metadata added to every member
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+
+    /// Auth provider name
+    #[serde(default)]
+    pub auth_provider: Option<String>,
+
+    /// Skip authentication for every member
+    #[serde(default)]
+    pub skip_auth: bool,
+
+    /// Required roles, for members that require none
+    #[serde(default)]
+    pub require_roles: Vec<String>,
+
+    /// Required scopes, for members that require none
+    #[serde(default)]
+    pub require_scopes: Vec<String>,
+
+    /// Custom authorization rule (Rhai expression)
+    #[serde(default)]
+    pub authz_rule: Option<String>,
+
+    /// Request timeout (upstream time budget)
+    #[serde(default, with = "humantime_serde::option")]
+    pub timeout: Option<Duration>,
+
+    /// Rate limit, applied per member route
+    #[serde(default)]
+    pub rate_limit: Option<RouteRateLimitConfig>,
+
+    /// Concurrency limit, applied per member route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<ConcurrencyConfig>,
+
+    /// CORS override
+    #[serde(default)]
+    pub cors: Option<RouteCorsConfig>,
+
+    /// Body, header, cookie and query transforms
+    #[serde(default)]
+    pub transform: Option<RouteTransformConfig>,
+
+    /// Member routes, with paths relative to `prefix`
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+}
+
+impl RouteGroupConfig {
+    /// The group's member routes with the group's prefix and settings
+    /// applied.
+    pub fn expand(&self) -> Vec<RouteConfig> {
+        self.routes.iter().map(|route| self.apply(route)).collect()
+    }
+
+    fn apply(&self, route: &RouteConfig) -> RouteConfig {
+        let or_group = |own: &Vec<String>, group: &Vec<String>| {
+            if own.is_empty() { group } else { own }.clone()
+        };
+        let mut metadata = self.metadata.clone();
+        metadata.extend(route.metadata.clone());
+
+        RouteConfig {
+            path: octopus_router::join_prefix(&self.prefix, &route.path),
+            upstream: match (&self.upstream, route.upstream.is_empty()) {
+                (Some(upstream), true) if route.service.is_none() => upstream.clone(),
+                _ => route.upstream.clone(),
+            },
+            strip_prefix: route
+                .strip_prefix
+                .clone()
+                .or_else(|| self.strip_prefix.clone()),
+            add_prefix: route.add_prefix.clone().or_else(|| self.add_prefix.clone()),
+            metadata,
+            auth_provider: route
+                .auth_provider
+                .clone()
+                .or_else(|| self.auth_provider.clone()),
+            skip_auth: route.skip_auth || self.skip_auth,
+            require_roles: or_group(&route.require_roles, &self.require_roles),
+            require_scopes: or_group(&route.require_scopes, &self.require_scopes),
+            authz_rule: route.authz_rule.clone().or_else(|| self.authz_rule.clone()),
+            timeout: route.timeout.or(self.timeout),
+            rate_limit: route.rate_limit.clone().or_else(|| self.rate_limit.clone()),
+            concurrency: route
+                .concurrency
+                .clone()
+                .or_else(|| self.concurrency.clone()),
+            cors: route.cors.clone().or_else(|| self.cors.clone()),
+            transform: route.transform.clone().or_else(|| self.transform.clone()),
+            ..route.clone()
+        }
+    }
 }
 
-impl RouteConfig {
-    /// Build a [`octopus_router::ProxySpec`] if any proxy field is set;
-    /// returns `None` when no proxy fields are present so legacy routes are
-    /// unaffected.
-    pub fn proxy_spec(&self) -> Option<octopus_router::ProxySpec> {
-        let any = self.path_mode.is_some()
-            || self.upstream_origin.is_some()
-            || self.rewrite_redirects.is_some()
-            || self.rewrite_cookie_path.is_some();
-        if !any {
-            return None;
+impl Config {
+    /// Move every route group's expanded members into `routes`, leaving
+    /// `route_groups` empty. A group prefix must start with `/`.
+    pub fn expand_route_groups(&mut self) -> octopus_core::Result<()> {
+        for group in std::mem::take(&mut self.route_groups) {
+            if !group.prefix.starts_with('/') {
+                return Err(octopus_core::Error::Config(format!(
+                    "route group '{}': prefix must start with '/'",
+                    group.name
+                )));
+            }
+            self.routes.extend(group.expand());
         }
-        let origin = self.upstream_origin.as_ref().and_then(|u| {
-            octopus_router::UpstreamOrigin::parse(u, self.tls_verify.unwrap_or(true))
-        });
-        Some(octopus_router::ProxySpec {
-            origin,
-            path_mode: match self.path_mode.as_deref() {
-                Some("passthrough") => octopus_router::PathMode::Passthrough,
-                Some("strip") | None => octopus_router::PathMode::Strip,
-                Some(other) => {
-                    tracing::warn!(path_mode = %other, "unrecognized path-mode; defaulting to strip");
-                    octopus_router::PathMode::Strip
-                }
-            },
-            rewrite_redirects: self.rewrite_redirects.unwrap_or(false),
-            rewrite_cookie_path: self.rewrite_cookie_path.unwrap_or(false),
-        })
+        Ok(())
     }
 }
 
@@ -756,6 +2680,65 @@ pub struct PluginConfig {
     /// Configuration
     #[serde(default)]
     pub config: HashMap<String, serde_json::Value>,
+
+    /// Isolation of the plugin's interceptor calls
+    #[serde(default)]
+    pub sandbox: PluginSandboxConfig,
+}
+
+/// Timeout, panic and crash handling for a plugin's interceptor calls.
+///
+/// ```yaml
+/// plugins:
+///   - name: geo-tagger
+///     sandbox:
+///       timeout: 200ms
+///       on_failure: open
+///       max_failures: 5
+///       disable_for: 1m
+/// ```
+///
+/// A call that panics or outlives `timeout` fails the plugin's step: with
+/// `on_failure: closed` the request fails, with `open` the step is skipped.
+/// After `max_failures` such failures in a row the plugin is not called for
+/// `disable_for`, its steps failing per `on_failure` meanwhile. A timed-out
+/// wasm guest is interrupted; synchronous work in a static plugin runs on
+/// until it returns.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PluginSandboxConfig {
+    /// Longest a single interceptor call may run
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    /// What a failed step does to the request
+    pub on_failure: PluginFailurePolicy,
+    /// Consecutive panics or timeouts that disable the plugin (0 = never)
+    pub max_failures: u32,
+    /// How long a disabled plugin stays disabled before it is tried again
+    #[serde(with = "humantime_serde")]
+    pub disable_for: Duration,
+}
+
+impl Default for PluginSandboxConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            on_failure: PluginFailurePolicy::Closed,
+            max_failures: 0,
+            disable_for: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Outcome of a plugin step that panicked, timed out or was disabled
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginFailurePolicy {
+    /// Fail the request
+    #[default]
+    Closed,
+    /// Skip the plugin's step and carry on with the request
+    Open,
 }
 
 /// Observability configuration
@@ -780,6 +2763,155 @@ pub struct LoggingConfig {
 
     /// Log format (json, text)
     pub format: String,
+
+    /// Separate outputs and formats for the access and audit logs, and for
+    /// the remaining application logs
+    #[serde(default, skip_serializing_if = "LogCategories::is_empty")]
+    pub categories: LogCategories,
+}
+
+/// Per-category log outputs.
+///
+/// Access logs are the events on the `octopus::access` target, audit logs
+/// those on `octopus::audit`, and app logs everything else. A category
+/// without a sink of its own is written with the app logs; without an `app`
+/// sink those go to stdout in [`LoggingConfig::format`].
+///
+/// ```yaml
+/// observability:
+///   logging:
+///     level: info
+///     format: text
+///     categories:
+///       access:
+///         output: /var/log/octopus/access.log
+///         format: message
+///         buffer:
+///           flush_interval: 1s
+///       audit:
+///         output: /var/log/octopus/audit.log
+///         format: json
+///       app:
+///         output: stderr
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogCategories {
+    /// Application logs: everything not in another configured category
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<LogSinkConfig>,
+
+    /// Per-request access logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<LogSinkConfig>,
+
+    /// Security audit logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<LogSinkConfig>,
+}
+
+impl LogCategories {
+    /// Whether no category has a sink of its own
+    pub fn is_empty(&self) -> bool {
+        self.app.is_none() && self.access.is_none() && self.audit.is_none()
+    }
+}
+
+/// Where and how one log category is written
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogSinkConfig {
+    /// `stdout`, `stderr`, or the path of a file to append to
+    #[serde(default = "default_log_output")]
+    pub output: String,
+
+    /// Line format; [`LoggingConfig::format`] when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<LogLineFormat>,
+
+    /// Write lines in batches from a background task, so requests never
+    /// wait on log I/O. Only the access log supports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer: Option<LogBufferConfig>,
+}
+
+impl Default for LogSinkConfig {
+    fn default() -> Self {
+        Self {
+            output: default_log_output(),
+            format: None,
+            buffer: None,
+        }
+    }
+}
+
+fn default_log_output() -> String {
+    "stdout".to_string()
+}
+
+/// Buffered, batched writing of a log category
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogBufferConfig {
+    /// Lines held between requests and the writer task
+    #[serde(default = "default_log_buffer_capacity")]
+    pub capacity: usize,
+
+    /// Lines written at once
+    #[serde(default = "default_log_batch_size")]
+    pub batch_size: usize,
+
+    /// Longest a line waits in a partial batch
+    #[serde(default = "default_log_flush_interval", with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// What happens to a line while the buffer is full
+    #[serde(default)]
+    pub overflow: LogOverflow,
+}
+
+impl Default for LogBufferConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_log_buffer_capacity(),
+            batch_size: default_log_batch_size(),
+            flush_interval: default_log_flush_interval(),
+            overflow: LogOverflow::default(),
+        }
+    }
+}
+
+fn default_log_buffer_capacity() -> usize {
+    8192
+}
+
+fn default_log_batch_size() -> usize {
+    256
+}
+
+fn default_log_flush_interval() -> Duration {
+    Duration::from_secs(1)
+}
+
+/// What a buffered log does with a line while its buffer is full
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogOverflow {
+    /// Drop the line and count it in `octopus_access_log_dropped_total`
+    #[default]
+    Drop,
+    /// Hold the request until there is room
+    Block,
+}
+
+/// How a log event is rendered into a line
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLineFormat {
+    /// Human-readable: timestamp, level, message and fields
+    Text,
+    /// One JSON object per event
+    Json,
+    /// The message alone, for lines rendered before logging, such as access
+    /// log or JSON-lines request logs and JSON audit events
+    Message,
 }
 
 /// Metrics configuration
@@ -790,6 +2922,99 @@ pub struct MetricsConfig {
 
     /// Metrics endpoint
     pub endpoint: String,
+
+    /// Per-route SLO tracking, exported with the metrics (unset = disabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slo: Option<SloConfig>,
+}
+
+/// Per-route SLO tracking
+///
+/// Routes are the request paths the per-route metrics are recorded under.
+/// Objectives are read at startup.
+///
+/// ```yaml
+/// observability:
+///   metrics:
+///     slo:
+///       default:
+///         latency_threshold: 200ms
+///         latency_target: 0.99
+///       routes:
+///         /api/checkout:
+///           success_target: 0.999
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SloConfig {
+    /// Objective for every route without its own (unset = only `routes` are
+    /// tracked)
+    #[serde(default)]
+    pub default: Option<SloObjectiveConfig>,
+
+    /// Objectives by route
+    #[serde(default)]
+    pub routes: HashMap<String, SloObjectiveConfig>,
+}
+
+/// A route's service-level objective
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SloObjectiveConfig {
+    /// Target ratio of successful requests
+    #[serde(default = "default_slo_target")]
+    pub success_target: f64,
+
+    /// Requests slower than this count against the latency objective
+    #[serde(default = "default_slo_latency_threshold", with = "humantime_serde")]
+    pub latency_threshold: Duration,
+
+    /// Target ratio of requests under `latency_threshold`
+    #[serde(default = "default_slo_target")]
+    pub latency_target: f64,
+
+    /// Rolling window the ratios are computed over
+    #[serde(default = "default_slo_window", with = "humantime_serde")]
+    pub window: Duration,
+
+    /// Error-budget burn rate at or above which a breach is reported
+    #[serde(default = "default_slo_burn_rate_threshold")]
+    pub burn_rate_threshold: f64,
+
+    /// Requests needed in the window before the objective is evaluated
+    #[serde(default = "default_slo_min_requests")]
+    pub min_requests: u64,
+}
+
+impl Default for SloObjectiveConfig {
+    fn default() -> Self {
+        Self {
+            success_target: default_slo_target(),
+            latency_threshold: default_slo_latency_threshold(),
+            latency_target: default_slo_target(),
+            window: default_slo_window(),
+            burn_rate_threshold: default_slo_burn_rate_threshold(),
+            min_requests: default_slo_min_requests(),
+        }
+    }
+}
+
+fn default_slo_target() -> f64 {
+    0.99
+}
+
+fn default_slo_latency_threshold() -> Duration {
+    Duration::from_millis(200)
+}
+
+fn default_slo_window() -> Duration {
+    Duration::from_secs(300)
+}
+
+fn default_slo_burn_rate_threshold() -> f64 {
+    2.0
+}
+
+fn default_slo_min_requests() -> u64 {
+    20
 }
 
 /// Tracing configuration
@@ -873,10 +3098,12 @@ impl Default for ObservabilityConfig {
             logging: LoggingConfig {
                 level: "info".to_string(),
                 format: "text".to_string(),
+                categories: LogCategories::default(),
             },
             metrics: MetricsConfig {
                 enabled: true,
                 endpoint: "/metrics".to_string(),
+                slo: None,
             },
             tracing: TracingConfig {
                 enabled: false,
@@ -1282,7 +3509,9 @@ pub enum AuthzAction {
 /// Global CORS configuration
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CorsGlobalConfig {
-    /// Allowed origins (use ["*"] carefully)
+    /// Allowed origins: exact (`https://app.example.com`), subdomain
+    /// wildcards (`https://*.example.com`, `*.example.com`), regexes
+    /// (`~^https://pr-\d+\.example\.com$`) or `*` (not with credentials)
     #[serde(default)]
     pub allowed_origins: Vec<String>,
     /// Allowed HTTP methods
@@ -1305,7 +3534,7 @@ pub struct CorsGlobalConfig {
 /// Per-route CORS override
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RouteCorsConfig {
-    /// Allowed origins
+    /// Allowed origins, in the same forms as [`CorsGlobalConfig::allowed_origins`]
     #[serde(default)]
     pub allowed_origins: Vec<String>,
     /// Allowed methods
@@ -1341,6 +3570,9 @@ pub struct AdminConfig {
     pub auth_provider: Option<String>,
     /// IP allowlist for admin access (empty = all allowed)
     pub allowed_ips: Vec<String>,
+    /// Serve the admin dashboard, metrics and FARP on this address instead
+    /// of `gateway.listen`, which then carries proxied traffic only
+    pub listen: Option<SocketAddr>,
 }
 
 // Auth config defaults
@@ -1424,11 +3656,18 @@ pub struct GrpcConfig {
     pub enable_grpc_web: bool,
     /// Propagate gRPC deadlines to upstreams
     pub deadline_propagation: bool,
-    /// Explicit gRPC service-to-upstream mapping
-    /// Key: fully qualified service name (e.g., "users.UserService")
+    /// Explicit gRPC service-to-upstream mapping, taking precedence over
+    /// path-prefix routes
+    /// Key: fully qualified service name (e.g., "users.UserService") or
+    /// method (e.g., "users.UserService/GetUser"); a method entry wins
     /// Value: upstream name
     #[serde(default)]
     pub services: HashMap<String, String>,
+    /// Upstream serving `grpc.reflection.*.ServerReflection` when
+    /// `enable_reflection` is set; defaults to the single upstream in
+    /// `services` if they all agree
+    #[serde(default)]
+    pub reflection_upstream: Option<String>,
 }
 
 impl Default for GrpcConfig {
@@ -1440,6 +3679,7 @@ impl Default for GrpcConfig {
             enable_grpc_web: false,
             deadline_propagation: true,
             services: HashMap::new(),
+            reflection_upstream: None,
         }
     }
 }
@@ -1468,6 +3708,9 @@ pub struct GraphQLConfig {
     pub max_depth: usize,
     /// Reject operations whose field count (approximate cost) exceeds this value.
     pub max_complexity: usize,
+    /// REST routes served by translating them into GraphQL operations against
+    /// a GraphQL-only upstream. Independent of `enabled`.
+    pub rest_mappings: Vec<RestGraphQLMapping>,
 }
 
 impl Default for GraphQLConfig {
@@ -1479,14 +3722,214 @@ impl Default for GraphQLConfig {
             introspection: true,
             max_depth: 15,
             max_complexity: 1000,
+            rest_mappings: Vec::new(),
+        }
+    }
+}
+
+/// Translates one REST route into a GraphQL operation.
+///
+/// ```yaml
+/// rest_path: /users/:id
+/// upstream: users-graphql
+/// operation: "query($id: ID!) { user(id: $id) { id name } }"
+/// result_field: user
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RestGraphQLMapping {
+    /// REST path pattern; `:name` segments capture path parameters.
+    pub rest_path: String,
+    /// HTTP method the REST route answers.
+    #[serde(default = "default_rest_method")]
+    pub method: String,
+    /// Upstream serving the GraphQL endpoint.
+    pub upstream: String,
+    /// GraphQL endpoint path on the upstream.
+    #[serde(default = "default_graphql_endpoint")]
+    pub endpoint: String,
+    /// GraphQL document (query or mutation) sent upstream.
+    pub operation: String,
+    /// GraphQL variable name -> request source: `path.<param>`,
+    /// `query.<param>`, `body` or `body.<field>`, optionally suffixed with
+    /// `:int`, `:float` or `:bool` to coerce string values. When empty, every
+    /// path parameter becomes a variable of the same name.
+    #[serde(default)]
+    pub variable_mapping: HashMap<String, String>,
+    /// Field of `data` returned as the REST body (default: all of `data`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_field: Option<String>,
+}
+
+fn default_rest_method() -> String {
+    "GET".to_string()
+}
+
+fn default_graphql_endpoint() -> String {
+    "/graphql".to_string()
+}
+
+// ============================================================================
+// Events Configuration
+// ============================================================================
+
+/// Gateway lifecycle and security events (config reloads, upstreams going
+/// down, circuits opening, auth failure spikes, plugin crashes) and where to
+/// deliver them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Webhook receivers; each gets a JSON `POST` per matching event.
+    pub webhooks: Vec<WebhookConfig>,
+    /// Number of 401/403 responses within `auth_failure_window` that raises
+    /// an `auth_failure_spike` event (0 disables).
+    pub auth_failure_threshold: u64,
+    /// Window over which auth failures are counted.
+    #[serde(with = "humantime_serde")]
+    pub auth_failure_window: Duration,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            auth_failure_threshold: 50,
+            auth_failure_window: Duration::from_secs(60),
         }
     }
 }
 
+/// A webhook receiving gateway events.
+///
+/// ```yaml
+/// url: https://hooks.example.com/octopus
+/// events: [upstream_down, circuit_opened]
+/// headers:
+///   Authorization: Bearer ${WEBHOOK_TOKEN}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookConfig {
+    /// `http://` or `https://` URL events are posted to.
+    pub url: String,
+    /// Event types to deliver (e.g. `config_reloaded`); empty means all.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Extra headers sent with every delivery.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Retries after a failed delivery (error or non-2xx response).
+    #[serde(default = "default_webhook_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on each further attempt.
+    #[serde(default = "default_webhook_backoff", with = "humantime_serde")]
+    pub initial_backoff: Duration,
+    /// Timeout for a single delivery attempt.
+    #[serde(default = "default_webhook_timeout", with = "humantime_serde")]
+    pub timeout: Duration,
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+fn default_webhook_backoff() -> Duration {
+    Duration::from_millis(500)
+}
+
+fn default_webhook_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn unmatched_config_parses_with_defaults() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  unmatched:\n    \
+            type: redirect\n    url: https://legacy.example.com\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            cfg.gateway.unmatched,
+            Some(UnmatchedConfig::Redirect {
+                url: "https://legacy.example.com".to_string(),
+                status: 302,
+                preserve_path: true,
+            })
+        );
+
+        let unmatched: UnmatchedConfig =
+            serde_yaml::from_str("type: respond\nbody: '{\"error\":\"gone\"}'").unwrap();
+        assert_eq!(
+            unmatched,
+            UnmatchedConfig::Respond {
+                status: 404,
+                content_type: "application/json".to_string(),
+                body: r#"{"error":"gone"}"#.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn unix_socket_config_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  unix_socket:\n    \
+            path: /run/octopus.sock\n    mode: \"0660\"\n    gid: 101\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let socket = cfg.gateway.unix_socket.unwrap();
+        assert_eq!(socket.path, PathBuf::from("/run/octopus.sock"));
+        assert_eq!(socket.mode_bits(), Some(0o660));
+        assert_eq!((socket.uid, socket.gid), (None, Some(101)));
+    }
+
+    #[test]
+    fn proxy_protocol_config_parses_with_defaults() {
+        let cfg: Config = serde_yaml::from_str("gateway:\n  listen: \"0.0.0.0:8080\"\n").unwrap();
+        assert!(!cfg.gateway.proxy_protocol.enabled);
+
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  proxy_protocol:\n    \
+            enabled: true\n    trusted_sources: [\"10.0.0.0/8\"]\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let proxy_protocol = cfg.gateway.proxy_protocol;
+        assert!(proxy_protocol.enabled);
+        assert_eq!(proxy_protocol.trusted_sources, vec!["10.0.0.0/8"]);
+        assert_eq!(proxy_protocol.header_timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn concurrency_config_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  concurrency:\n    \
+            max_concurrent: 100\n    queue_depth: 50\n    queue_timeout: 250ms\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            cfg.gateway.concurrency,
+            Some(ConcurrencyConfig {
+                max_concurrent: 100,
+                queue_depth: 50,
+                queue_timeout: Duration::from_millis(250),
+            })
+        );
+
+        let route: RouteConfig = serde_yaml::from_str(
+            "path: /export\nupstream: reports\nconcurrency:\n  max_concurrent: 2\n",
+        )
+        .unwrap();
+        let concurrency = route.concurrency.unwrap();
+        assert_eq!(concurrency.queue_depth, 0);
+        assert_eq!(concurrency.queue_timeout, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn upstream_slow_start_parses() {
+        let upstream: UpstreamConfig =
+            serde_yaml::from_str("name: api\ninstances: []\nslow_start:\n  window: 30s\n").unwrap();
+        assert_eq!(
+            upstream.slow_start,
+            Some(octopus_core::SlowStartConfig {
+                window: Duration::from_secs(30),
+                min_weight_percent: 10,
+            })
+        );
+    }
+
     #[test]
     fn route_config_parses_proxy_fields() {
         let yaml = r#"
@@ -1507,6 +3950,43 @@ upstream_origin: https://api.example.com:443
         assert_eq!(o.scheme, octopus_router::Scheme::Https);
     }
 
+    #[test]
+    fn route_config_parses_fallback_variants() {
+        let parse = |fallback: &str| {
+            let yaml = format!("path: /x\nupstream: x\nfallback: {fallback}\n");
+            serde_yaml::from_str::<RouteConfig>(&yaml)
+                .unwrap()
+                .fallback_spec()
+        };
+
+        assert_eq!(
+            parse(r#"{type: static_json, body: {items: []}}"#),
+            Some(octopus_router::RouteFallback::StaticJson {
+                status: octopus_core::StatusCode::OK,
+                body: r#"{"items":[]}"#.to_string(),
+            })
+        );
+        assert_eq!(
+            parse("{type: status, code: 204}"),
+            Some(octopus_router::RouteFallback::Status(
+                octopus_core::StatusCode::NO_CONTENT
+            ))
+        );
+        assert_eq!(
+            parse("{type: redirect, url: 'https://status.example.com'}"),
+            Some(octopus_router::RouteFallback::Redirect(
+                "https://status.example.com".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("{type: last_good_cache, max_age: 1m}"),
+            Some(octopus_router::RouteFallback::LastGoodCache {
+                max_age: Duration::from_secs(60)
+            })
+        );
+        assert_eq!(parse("{type: status, code: 1000}"), None);
+    }
+
     #[test]
     fn graphql_config_defaults() {
         let cfg = GraphQLConfig::default();
@@ -1545,6 +4025,273 @@ upstream_origin: https://api.example.com:443
         assert!(!config.tracing.enabled);
     }
 
+    #[test]
+    fn experiments_section_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  experiments:\n    \
+            key_headers: [x-user-id]\n    cookie_max_age: 7d\n    experiments:\n      \
+            - name: checkout\n        variants:\n          \
+            - { name: control, weight: 90 }\n          \
+            - { name: one-page, weight: 10 }\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let experiments = &cfg.gateway.experiments;
+        assert_eq!(experiments.key_headers, vec!["x-user-id"]);
+        assert_eq!(experiments.cookie_prefix, "octopus_exp_");
+        assert_eq!(
+            experiments.cookie_max_age,
+            Duration::from_secs(7 * 24 * 3600)
+        );
+        assert_eq!(experiments.experiments[0].name, "checkout");
+        assert_eq!(experiments.experiments[0].variants[1].weight, 10);
+    }
+
+    #[test]
+    fn request_validation_section_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  request_validation:\n    \
+            max_body_size: 65536\n    rules:\n      \
+            - path: /api/users\n        methods: [POST]\n        \
+            schema: { type: object, required: [name] }\n      \
+            - path: /api/orders/*\n        from_farp: true\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let validation = &cfg.gateway.request_validation;
+        assert_eq!(validation.max_body_size, 65536);
+        assert_eq!(
+            validation.rules[0].schema.as_ref().unwrap()["required"][0],
+            "name"
+        );
+        assert!(validation.rules[1].from_farp);
+        assert!(validation.rules[1].methods.is_empty());
+    }
+
+    #[test]
+    fn response_validation_defaults_off() {
+        let cfg: Config = serde_yaml::from_str("gateway:\n  listen: \"0.0.0.0:8080\"\n").unwrap();
+        assert!(!cfg.gateway.response_validation.enabled);
+        assert!(!cfg.gateway.response_validation.violation_header);
+    }
+
+    #[test]
+    fn geoip_section_and_route_geo_upstreams_parse() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  geoip:\n    \
+            enabled: true\n    database: /var/lib/geoip/GeoLite2-Country.mmdb\n    \
+            reload_interval: 1h\n    blocked_countries: [KP]\n\
+            routes:\n  - path: /api\n    methods: [GET]\n    upstream: global\n    \
+            geo_upstreams:\n      EU: api-eu\n      US-CA: api-us-west\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let geoip = &cfg.gateway.geoip;
+        assert!(geoip.enabled);
+        assert_eq!(geoip.reload_interval, Duration::from_secs(3600));
+        assert_eq!(geoip.blocked_countries, vec!["KP"]);
+        assert_eq!(geoip.block_status, 451);
+        assert_eq!(cfg.routes[0].geo_upstreams["US-CA"], "api-us-west");
+    }
+
+    #[test]
+    fn response_body_limit_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  response_body_limit:\n    \
+            max_bytes: 1048576\nroutes:\n  - path: /export\n    upstream: api\n    \
+            response_body_limit:\n      max_bytes: 4096\n      on_exceed: truncate\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            cfg.gateway.response_body_limit,
+            Some(ResponseBodyLimitConfig {
+                max_bytes: 1_048_576,
+                on_exceed: ResponseBodyOverflow::Abort,
+            })
+        );
+        let route = cfg.routes[0].response_body_limit.unwrap();
+        assert_eq!(
+            route.limit(),
+            octopus_core::ResponseBodyLimit {
+                max_bytes: 4096,
+                truncate: true,
+            }
+        );
+    }
+
+    #[test]
+    fn idempotency_section_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  idempotency:\n    \
+            methods: [POST]\n    ttl: 1h\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let idempotency = cfg.gateway.idempotency.unwrap();
+        assert_eq!(idempotency.header, "Idempotency-Key");
+        assert_eq!(idempotency.methods, ["POST"]);
+        assert_eq!(idempotency.ttl, Duration::from_secs(3600));
+        assert_eq!(idempotency.lock_timeout, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn log_categories_parse() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\nobservability:\n  logging:\n    \
+            level: info\n    format: text\n    categories:\n      access:\n        \
+            output: /var/log/octopus/access.log\n        format: message\n      audit:\n        \
+            format: json\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let categories = cfg.observability.logging.categories;
+        assert_eq!(
+            categories.access,
+            Some(LogSinkConfig {
+                output: "/var/log/octopus/access.log".to_string(),
+                format: Some(LogLineFormat::Message),
+                buffer: None,
+            })
+        );
+        let audit = categories.audit.unwrap();
+        assert_eq!(audit.output, "stdout");
+        assert_eq!(audit.format, Some(LogLineFormat::Json));
+        assert!(categories.app.is_none());
+    }
+
+    #[test]
+    fn startup_check_section_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  startup_check:\n    \
+            policy: strict\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let check = cfg.gateway.startup_check.unwrap();
+        assert_eq!(check.policy, StartupCheckPolicy::Strict);
+        assert_eq!(check.timeout, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn qos_section_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  qos:\n    shed_below: high\n    \
+            rules:\n      - priority: critical\n        path_prefix: /checkout\n      \
+            - priority: low\n        header: X-Batch\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let qos = cfg.gateway.qos.unwrap();
+        assert_eq!(qos.default_priority, RequestPriority::Normal);
+        assert_eq!(qos.shed_below, RequestPriority::High);
+        assert_eq!(qos.rules[0].priority, RequestPriority::Critical);
+        assert_eq!(qos.rules[1].header.as_deref(), Some("X-Batch"));
+        assert!(RequestPriority::Low < RequestPriority::Normal);
+    }
+
+    #[test]
+    fn warmup_is_on_by_default() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(cfg.gateway.warmup, WarmupConfig::default());
+        assert!(cfg.gateway.warmup.enabled);
+
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  warmup:\n    \
+            fetch_jwks: false\n    fail_on_error: true\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let warmup = cfg.gateway.warmup;
+        assert!(warmup.enabled);
+        assert!(!warmup.fetch_jwks);
+        assert!(warmup.fail_on_error);
+    }
+
+    #[test]
+    fn tenancy_section_parses() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n  tenancy:\n    \
+            source:\n      type: path\n    unknown_status: 403\n    tenants:\n      \
+            acme:\n        upstream: acme-api\n        headers:\n          \
+            X-Tenant-Plan: enterprise\n      globex: {}\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        let tenancy = cfg.gateway.tenancy.unwrap();
+        assert_eq!(
+            tenancy.source,
+            TenantSourceConfig::Path {
+                prefix: "/t".to_string()
+            }
+        );
+        assert_eq!(tenancy.header, "X-Tenant-Id");
+        assert_eq!(tenancy.unknown_status, 403);
+        assert!(tenancy.required);
+        assert_eq!(
+            tenancy.tenants["acme"].upstream.as_deref(),
+            Some("acme-api")
+        );
+        assert_eq!(
+            tenancy.tenants["acme"].headers["X-Tenant-Plan"],
+            "enterprise"
+        );
+        assert_eq!(tenancy.tenants["globex"], TenantConfig::default());
+    }
+
+    #[test]
+    fn route_transform_rules_parse() {
+        let yaml = r#"
+path: /api/users
+upstream: users
+transform:
+  request:
+    - op: rename
+      from: $.userName
+      to: $.user_name
+    - op: default
+      path: $.role
+      value: member
+  response:
+    - op: unwrap
+      path: $.data
+  request_headers:
+    set:
+      X-User: "{claim.sub}"
+    remove: [X-Debug]
+  response_cookies:
+    remove: [tracking]
+    same_site: lax
+    secure: false
+  query:
+    allow: [q, page]
+    add: { source: gateway }
+"#;
+        let route: RouteConfig = serde_yaml::from_str(yaml).unwrap();
+        let transform = route.transform.unwrap();
+        assert_eq!(transform.max_body_size, 1024 * 1024);
+        assert_eq!(
+            transform.request[0],
+            JsonTransformRule::Rename {
+                from: "$.userName".to_string(),
+                to: "$.user_name".to_string()
+            }
+        );
+        assert_eq!(
+            transform.request[1],
+            JsonTransformRule::Default {
+                path: "$.role".to_string(),
+                value: serde_json::json!("member")
+            }
+        );
+        assert_eq!(
+            transform.response,
+            vec![JsonTransformRule::Unwrap {
+                path: "$.data".to_string()
+            }]
+        );
+        assert_eq!(transform.request_headers.set["X-User"], "{claim.sub}");
+        assert_eq!(
+            transform.request_headers.remove,
+            vec!["X-Debug".to_string()]
+        );
+        assert!(transform.response_headers.is_empty());
+        assert_eq!(
+            transform.response_cookies.same_site,
+            Some(octopus_core::SameSite::Lax)
+        );
+        assert_eq!(transform.response_cookies.secure, Some(false));
+        assert!(transform.request_cookies.is_empty());
+        assert!(transform.has_non_body_rules());
+        assert_eq!(transform.query.allow, vec!["q", "page"]);
+        assert_eq!(transform.query.add["source"], "gateway");
+    }
+
+    #[test]
+    fn plugin_sandbox_parses_with_defaults() {
+        let yaml = "gateway:\n  listen: \"0.0.0.0:8080\"\n\
+            plugins:\n  - name: tagger\n  - name: geo\n    sandbox:\n      \
+            timeout: 200ms\n      on_failure: open\n      max_failures: 5\n";
+        let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(cfg.plugins[0].sandbox, PluginSandboxConfig::default());
+        let sandbox = &cfg.plugins[1].sandbox;
+        assert_eq!(sandbox.timeout, Duration::from_millis(200));
+        assert_eq!(sandbox.on_failure, PluginFailurePolicy::Open);
+        assert_eq!(sandbox.max_failures, 5);
+        assert_eq!(sandbox.disable_for, Duration::from_secs(30));
+    }
+
     #[test]
     fn kubernetes_section_defaults_off() {
         let cfg: Config = serde_yaml::from_str("gateway:\n  listen: \"0.0.0.0:8080\"\n").unwrap();
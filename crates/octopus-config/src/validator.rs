@@ -1,7 +1,14 @@
 //! Configuration validation
 
+use crate::types::{
+    ConcurrencyConfig, DebugHeadersConfig, DebugTapConfig, GatewayConfig, IdempotencyConfig,
+    InternalRedirectConfig, MultipartConfig, ProxyProtocolConfig, QosConfig, RequestIdConfig,
+    RequestIdGenerator, RequestSigningConfig, ResponseBodyLimitConfig, RouteConfig, TenancyConfig,
+    TenantSourceConfig, UnixSocketConfig,
+};
 use crate::Config;
 use octopus_core::{Error, Result};
+use std::net::IpAddr;
 
 /// Validate configuration
 pub fn validate_config(config: &Config) -> Result<()> {
@@ -14,12 +21,37 @@ pub fn validate_config(config: &Config) -> Result<()> {
     // Validate routes
     validate_routes(config)?;
 
+    // Validate the unmatched-request policy
+    validate_unmatched(config)?;
+
+    // Validate gRPC service mapping
+    validate_grpc(config)?;
+
+    // Validate REST-to-GraphQL mappings
+    validate_graphql(config)?;
+
+    // Validate event webhooks
+    validate_events(config)?;
+
+    // Validate CORS origin patterns
+    validate_cors(config)?;
+
     // Validate plugins
     validate_plugins(config)?;
 
+    // Validate per-category log outputs
+    validate_logging(config)?;
+    validate_slo(config)?;
+
     Ok(())
 }
 
+/// Upper bound on `gateway.workers`; anything above is a typo, not a sizing.
+const MAX_WORKERS: usize = 1024;
+
+/// Smallest accepted `gateway.worker_stack_size` (64 KiB).
+const MIN_WORKER_STACK_SIZE: usize = 64 * 1024;
+
 fn validate_gateway(config: &Config) -> Result<()> {
     // Check request timeout is reasonable
     if config.gateway.request_timeout.as_secs() == 0 {
@@ -35,6 +67,115 @@ fn validate_gateway(config: &Config) -> Result<()> {
         return Err(Error::Config("max_body_size must be > 0".to_string()));
     }
 
+    validate_workers(&config.gateway)?;
+
+    if let Some(socket) = &config.gateway.unix_socket {
+        validate_unix_socket(socket)?;
+    }
+
+    validate_proxy_protocol(&config.gateway.proxy_protocol)?;
+    validate_ip_patterns(
+        "maintenance.bypass_ips",
+        &config.gateway.maintenance.bypass_ips,
+    )?;
+
+    if let Some(concurrency) = &config.gateway.concurrency {
+        validate_concurrency("gateway.concurrency", concurrency)?;
+    }
+
+    if let Some(tap) = &config.gateway.debug_tap {
+        validate_debug_tap(tap)?;
+    }
+
+    if let Some(headers) = &config.gateway.debug_headers {
+        validate_debug_headers(headers)?;
+    }
+
+    if let Some(request_id) = &config.gateway.request_id {
+        validate_request_id(request_id)?;
+    }
+
+    if let Some(multipart) = &config.gateway.multipart {
+        validate_multipart(config, multipart)?;
+    }
+
+    if let Some(redirect) = &config.gateway.internal_redirect {
+        validate_internal_redirect(redirect)?;
+    }
+
+    if let Some(tenancy) = &config.gateway.tenancy {
+        validate_tenancy(config, tenancy)?;
+    }
+
+    if let Some(limit) = &config.gateway.response_body_limit {
+        validate_response_body_limit("gateway.response_body_limit", limit)?;
+    }
+
+    if let Some(idempotency) = &config.gateway.idempotency {
+        validate_idempotency(idempotency)?;
+    }
+
+    if let Some(signing) = &config.gateway.request_signing {
+        validate_request_signing(signing)?;
+    }
+
+    if let Some(qos) = &config.gateway.qos {
+        validate_qos(config, qos)?;
+    }
+
+    if let Some(check) = &config.gateway.startup_check {
+        if check.timeout.is_zero() {
+            return Err(Error::Config(
+                "startup_check.timeout must be > 0".to_string(),
+            ));
+        }
+    }
+
+    if let Some(admin) = config.admin.listen {
+        if admin == config.gateway.listen && admin.port() != 0 {
+            return Err(Error::Config(format!(
+                "admin.listen must differ from gateway.listen ({admin})"
+            )));
+        }
+    }
+
+    for rule in &config.gateway.request_validation.rules {
+        if rule.schema.is_some() == rule.from_farp {
+            return Err(Error::Config(format!(
+                "request_validation rule {} must set exactly one of schema or from_farp",
+                rule.path
+            )));
+        }
+    }
+
+    let adaptive = &config.gateway.compression.adaptive;
+    if !(0.0..=1.0).contains(&adaptive.min_savings) {
+        return Err(Error::Config(format!(
+            "compression.adaptive.min_savings must be between 0 and 1, got {}",
+            adaptive.min_savings
+        )));
+    }
+
+    let geoip = &config.gateway.geoip;
+    if geoip.enabled {
+        if geoip.database.is_empty() {
+            return Err(Error::Config(
+                "geoip.database is required when geoip is enabled".to_string(),
+            ));
+        }
+        if !matches!(geoip.block_status, 403 | 451) {
+            return Err(Error::Config(format!(
+                "geoip.block_status must be 403 or 451, got {}",
+                geoip.block_status
+            )));
+        }
+        if geoip.reload_interval.is_zero() {
+            return Err(Error::Config(
+                "geoip.reload_interval must be > 0".to_string(),
+            ));
+        }
+    }
+
     // Validate TLS configuration if present
     if let Some(ref tls) = config.gateway.tls {
         if tls.cert_file.is_empty() {
@@ -66,6 +207,403 @@ fn validate_gateway(config: &Config) -> Result<()> {
     Ok(())
 }
 
+fn validate_workers(gateway: &GatewayConfig) -> Result<()> {
+    if gateway.workers > MAX_WORKERS {
+        return Err(Error::Config(format!(
+            "workers must be at most {MAX_WORKERS}, got {}",
+            gateway.workers
+        )));
+    }
+
+    if gateway.acceptors > MAX_WORKERS {
+        return Err(Error::Config(format!(
+            "acceptors must be at most {MAX_WORKERS}, got {}",
+            gateway.acceptors
+        )));
+    }
+
+    if let Some(stack_size) = gateway.worker_stack_size {
+        if stack_size < MIN_WORKER_STACK_SIZE {
+            return Err(Error::Config(format!(
+                "worker_stack_size must be at least {MIN_WORKER_STACK_SIZE} bytes, got {stack_size}"
+            )));
+        }
+    }
+
+    // Whether the CPUs exist is checked against the host when the runtime is
+    // built; here only the list itself is checked.
+    let mut seen = std::collections::HashSet::new();
+    for cpu in &gateway.worker_cpus {
+        if !seen.insert(cpu) {
+            return Err(Error::Config(format!(
+                "worker_cpus lists CPU {cpu} more than once"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_unix_socket(socket: &UnixSocketConfig) -> Result<()> {
+    if socket.path.as_os_str().is_empty() {
+        return Err(Error::Config(
+            "unix_socket.path cannot be empty".to_string(),
+        ));
+    }
+
+    match &socket.mode {
+        Some(mode) if socket.mode_bits().is_none() => Err(Error::Config(format!(
+            "unix_socket.mode must be octal permission bits like \"0660\", got {mode:?}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `pattern` is an IP, CIDR (`10.0.0.0/8`) or range
+/// (`10.0.0.1-10.0.0.9`), the forms IP rules accept
+fn is_ip_pattern(pattern: &str) -> bool {
+    if let Some((base, prefix)) = pattern.split_once('/') {
+        let max_prefix = match base.parse::<IpAddr>() {
+            Ok(IpAddr::V4(_)) => 32,
+            Ok(IpAddr::V6(_)) => 128,
+            Err(_) => return false,
+        };
+        prefix.parse::<u8>().is_ok_and(|len| len <= max_prefix)
+    } else if let Some((start, end)) = pattern.split_once('-') {
+        start.parse::<IpAddr>().is_ok() && end.parse::<IpAddr>().is_ok()
+    } else {
+        pattern.parse::<IpAddr>().is_ok()
+    }
+}
+
+fn validate_ip_patterns(context: &str, patterns: &[String]) -> Result<()> {
+    match patterns.iter().find(|p| !is_ip_pattern(p)) {
+        Some(pattern) => Err(Error::Config(format!(
+            "{context} entry {pattern:?} is not an IP, CIDR or IP range"
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn validate_proxy_protocol(proxy_protocol: &ProxyProtocolConfig) -> Result<()> {
+    if !proxy_protocol.enabled {
+        return Ok(());
+    }
+    // Whoever may send a PROXY header chooses its client address
+    if proxy_protocol.trusted_sources.is_empty() {
+        return Err(Error::Config(
+            "proxy_protocol.trusted_sources must list the balancers when PROXY protocol is enabled"
+                .to_string(),
+        ));
+    }
+    validate_ip_patterns("proxy_protocol.trusted_sources", &proxy_protocol.trusted_sources)
+}
+
+fn validate_qos(config: &Config, qos: &QosConfig) -> Result<()> {
+    let limited = config.gateway.concurrency.is_some()
+        || config.routes.iter().any(|r| r.concurrency.is_some());
+    if !limited {
+        return Err(Error::Config(
+            "gateway.qos needs gateway.concurrency or a route concurrency limit to schedule"
+                .to_string(),
+        ));
+    }
+    for (i, rule) in qos.rules.iter().enumerate() {
+        if !rule.has_conditions() {
+            return Err(Error::Config(format!(
+                "gateway.qos rule {i} must set path_prefix, header, tenant or role"
+            )));
+        }
+        if rule.header_value.is_some() && rule.header.is_none() {
+            return Err(Error::Config(format!(
+                "gateway.qos rule {i} sets header_value without header"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_concurrency(context: &str, concurrency: &ConcurrencyConfig) -> Result<()> {
+    if concurrency.max_concurrent == 0 {
+        return Err(Error::Config(format!(
+            "{context}.max_concurrent must be > 0"
+        )));
+    }
+    if concurrency.queue_depth > 0 && concurrency.queue_timeout.is_zero() {
+        return Err(Error::Config(format!(
+            "{context}.queue_timeout must be > 0 when queue_depth is set"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_response_body_limit(context: &str, limit: &ResponseBodyLimitConfig) -> Result<()> {
+    if limit.max_bytes == 0 {
+        return Err(Error::Config(format!("{context}.max_bytes must be > 0")));
+    }
+    Ok(())
+}
+
+fn validate_idempotency(idempotency: &IdempotencyConfig) -> Result<()> {
+    if !is_header_name(&idempotency.header) {
+        return Err(Error::Config(format!(
+            "idempotency.header is not a valid header name: {:?}",
+            idempotency.header
+        )));
+    }
+    if idempotency.methods.is_empty() {
+        return Err(Error::Config(
+            "idempotency.methods must list at least one method".to_string(),
+        ));
+    }
+    for method in &idempotency.methods {
+        if method.parse::<octopus_core::Method>().is_err() {
+            return Err(Error::Config(format!(
+                "idempotency.methods has an invalid method: {method:?}"
+            )));
+        }
+    }
+    if idempotency.ttl.is_zero() || idempotency.lock_timeout.is_zero() {
+        return Err(Error::Config(
+            "idempotency.ttl and idempotency.lock_timeout must be > 0".to_string(),
+        ));
+    }
+    if idempotency.max_entries == 0 {
+        return Err(Error::Config(
+            "idempotency.max_entries must be > 0".to_string(),
+        ));
+    }
+    if idempotency.max_body_size == 0 {
+        return Err(Error::Config(
+            "idempotency.max_body_size must be > 0".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Shortest request signing secret accepted
+const MIN_SIGNING_SECRET_LEN: usize = 32;
+
+fn validate_request_signing(signing: &RequestSigningConfig) -> Result<()> {
+    if signing.keys.is_empty() {
+        return Err(Error::Config(
+            "request_signing.keys must list at least one key".to_string(),
+        ));
+    }
+    if let Some(id) = signing
+        .keys
+        .iter()
+        .find_map(|(id, secret)| (secret.len() < MIN_SIGNING_SECRET_LEN).then_some(id))
+    {
+        return Err(Error::Config(format!(
+            "request_signing.keys.{id} must be at least {MIN_SIGNING_SECRET_LEN} bytes"
+        )));
+    }
+    if signing.max_skew.as_secs() == 0 {
+        return Err(Error::Config(
+            "request_signing.max_skew must be at least 1s".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Shortest debug tap secret accepted; the tap exposes full bodies, so it
+/// must not be guessable.
+const MIN_DEBUG_TAP_SECRET_LEN: usize = 32;
+
+/// Whether `name` is usable as an HTTP header name
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn validate_debug_tap(tap: &DebugTapConfig) -> Result<()> {
+    if !is_header_name(&tap.header) {
+        return Err(Error::Config(format!(
+            "debug_tap.header is not a valid header name: {:?}",
+            tap.header
+        )));
+    }
+    if tap.secret.len() < MIN_DEBUG_TAP_SECRET_LEN {
+        return Err(Error::Config(format!(
+            "debug_tap.secret must be at least {MIN_DEBUG_TAP_SECRET_LEN} bytes"
+        )));
+    }
+    if tap.max_token_ttl.is_zero() {
+        return Err(Error::Config(
+            "debug_tap.max_token_ttl must be > 0".to_string(),
+        ));
+    }
+    if tap.capacity == 0 {
+        return Err(Error::Config("debug_tap.capacity must be > 0".to_string()));
+    }
+    Ok(())
+}
+
+fn validate_debug_headers(headers: &DebugHeadersConfig) -> Result<()> {
+    if !is_header_name(&headers.header) {
+        return Err(Error::Config(format!(
+            "debug_headers.header is not a valid header name: {:?}",
+            headers.header
+        )));
+    }
+    match &headers.secret {
+        Some(secret) if secret.len() < MIN_DEBUG_TAP_SECRET_LEN => Err(Error::Config(format!(
+            "debug_headers.secret must be at least {MIN_DEBUG_TAP_SECRET_LEN} bytes"
+        ))),
+        None if !headers.always => Err(Error::Config(
+            "debug_headers needs a secret unless always is set".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Snowflake IDs carry a 10-bit worker id
+const MAX_SNOWFLAKE_WORKER_ID: u16 = 1023;
+
+fn validate_request_id(request_id: &RequestIdConfig) -> Result<()> {
+    if !is_header_name(&request_id.header) {
+        return Err(Error::Config(format!(
+            "request_id.header is not a valid header name: {:?}",
+            request_id.header
+        )));
+    }
+    if request_id.generator == RequestIdGenerator::Snowflake
+        && request_id.worker_id > MAX_SNOWFLAKE_WORKER_ID
+    {
+        return Err(Error::Config(format!(
+            "request_id.worker_id must be at most {MAX_SNOWFLAKE_WORKER_ID}"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_multipart(config: &Config, multipart: &MultipartConfig) -> Result<()> {
+    if multipart.max_part_size == 0 || multipart.max_total_size == 0 {
+        return Err(Error::Config(
+            "multipart.max_part_size and multipart.max_total_size must be > 0".to_string(),
+        ));
+    }
+    if multipart.max_part_size > multipart.max_total_size {
+        return Err(Error::Config(
+            "multipart.max_part_size must not exceed multipart.max_total_size".to_string(),
+        ));
+    }
+    if multipart.stream {
+        if let Some(reader) = request_body_reader(config) {
+            return Err(Error::Config(format!(
+                "multipart.stream cannot be used with {reader}, which reads request bodies"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The first configured middleware that reads request bodies, which a
+/// streamed upload would pass with an empty one
+fn request_body_reader(config: &Config) -> Option<String> {
+    let gateway = &config.gateway;
+    if gateway.debug_tap.is_some() {
+        return Some("debug_tap".to_string());
+    }
+    if !gateway.request_validation.rules.is_empty() {
+        return Some("request_validation".to_string());
+    }
+    if gateway.idempotency.is_some() {
+        return Some("idempotency".to_string());
+    }
+    if config.graphql.enabled {
+        return Some("graphql".to_string());
+    }
+    if let Some(route) = config
+        .routes
+        .iter()
+        .find(|r| r.transform.as_ref().is_some_and(|t| !t.request.is_empty()))
+    {
+        return Some(format!("the request transform of route {}", route.path));
+    }
+    config
+        .plugins
+        .iter()
+        .find(|p| p.enabled && matches!(p.plugin_type.as_str(), "script" | "wasm"))
+        .map(|p| format!("plugin {}", p.name))
+}
+
+fn validate_internal_redirect(redirect: &InternalRedirectConfig) -> Result<()> {
+    if !is_header_name(&redirect.header) {
+        return Err(Error::Config(format!(
+            "internal_redirect.header is not a valid header name: {:?}",
+            redirect.header
+        )));
+    }
+    if redirect.max_hops == 0 {
+        return Err(Error::Config(
+            "internal_redirect.max_hops must be > 0".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_tenancy(config: &Config, tenancy: &TenancyConfig) -> Result<()> {
+    match &tenancy.source {
+        TenantSourceConfig::Subdomain { base_domain } if base_domain.is_empty() => {
+            return Err(Error::Config(
+                "tenancy.source.base_domain cannot be empty".to_string(),
+            ));
+        }
+        TenantSourceConfig::Path { prefix } if !prefix.starts_with('/') => {
+            return Err(Error::Config(format!(
+                "tenancy.source.prefix must start with '/', got {prefix:?}"
+            )));
+        }
+        TenantSourceConfig::Header { name } if !is_header_name(name) => {
+            return Err(Error::Config(format!(
+                "tenancy.source.name is not a valid header name: {name:?}"
+            )));
+        }
+        TenantSourceConfig::Claim { claim, .. } if claim.is_empty() => {
+            return Err(Error::Config(
+                "tenancy.source.claim cannot be empty".to_string(),
+            ));
+        }
+        _ => {}
+    }
+    if !is_header_name(&tenancy.header) {
+        return Err(Error::Config(format!(
+            "tenancy.header is not a valid header name: {:?}",
+            tenancy.header
+        )));
+    }
+    if !matches!(tenancy.unknown_status, 403 | 404) {
+        return Err(Error::Config(format!(
+            "tenancy.unknown_status must be 403 or 404, got {}",
+            tenancy.unknown_status
+        )));
+    }
+    if tenancy.tenants.is_empty() {
+        return Err(Error::Config(
+            "tenancy.tenants must list at least one tenant".to_string(),
+        ));
+    }
+    for (id, tenant) in &tenancy.tenants {
+        if let Some(upstream) = &tenant.upstream {
+            if !config.upstreams.iter().any(|u| &u.name == upstream) {
+                return Err(Error::Config(format!(
+                    "tenancy.tenants.{id} references non-existent upstream: {upstream}"
+                )));
+            }
+        }
+        if let Some(name) = tenant.headers.keys().find(|name| !is_header_name(name)) {
+            return Err(Error::Config(format!(
+                "tenancy.tenants.{id}.headers has an invalid header name: {name:?}"
+            )));
+        }
+    }
+    Ok(())
+}
+
 fn validate_upstreams(config: &Config) -> Result<()> {
     for upstream in &config.upstreams {
         if upstream.name.is_empty() {
@@ -93,11 +631,90 @@ fn validate_upstreams(config: &Config) -> Result<()> {
                 return Err(Error::Config("instance port must be > 0".to_string()));
             }
         }
+
+        if let Some(check) = &upstream.health_check {
+            if !matches!(check.check_type.as_str(), "http" | "tcp" | "grpc") {
+                return Err(Error::Config(format!(
+                    "upstream {} health_check.type must be http, tcp or grpc, got {:?}",
+                    upstream.name, check.check_type
+                )));
+            }
+            if check.interval.is_zero() || check.timeout.is_zero() {
+                return Err(Error::Config(format!(
+                    "upstream {} health_check interval and timeout must be > 0",
+                    upstream.name
+                )));
+            }
+            if check.healthy_threshold == 0 || check.unhealthy_threshold == 0 {
+                return Err(Error::Config(format!(
+                    "upstream {} health_check thresholds must be > 0",
+                    upstream.name
+                )));
+            }
+        }
+
+        if let Some(breaker) = &upstream.circuit_breaker {
+            if !(0.0..=100.0).contains(&breaker.error_threshold) {
+                return Err(Error::Config(format!(
+                    "upstream {} circuit_breaker.error_threshold must be a percentage, got {}",
+                    upstream.name, breaker.error_threshold
+                )));
+            }
+            if let Some(status) = breaker
+                .failure_status_codes
+                .iter()
+                .find(|status| !(100..=599).contains(*status))
+            {
+                return Err(Error::Config(format!(
+                    "upstream {} circuit_breaker.failure_status_codes has invalid status {status}",
+                    upstream.name
+                )));
+            }
+        }
+
+        if let Some(openapi) = &upstream.openapi {
+            let prefix = openapi.prefix_for(&upstream.name);
+            if !prefix.is_empty() && !prefix.starts_with('/') {
+                return Err(Error::Config(format!(
+                    "upstream {} openapi prefix must start with '/'",
+                    upstream.name
+                )));
+            }
+        }
     }
 
     Ok(())
 }
 
+/// A route's `service` replaces its `upstream` and needs discovery
+/// backends to be resolved through
+fn validate_route_service(config: &Config, route: &RouteConfig, service: &str) -> Result<()> {
+    if service.is_empty() {
+        return Err(Error::Config(format!(
+            "Route {} service cannot be empty",
+            route.path
+        )));
+    }
+    if !route.upstream.is_empty() {
+        return Err(Error::Config(format!(
+            "Route {} sets both upstream and service",
+            route.path
+        )));
+    }
+    if config
+        .farp
+        .discovery
+        .as_ref()
+        .map_or(true, |discovery| discovery.backends.is_empty())
+    {
+        return Err(Error::Config(format!(
+            "Route {} service {service} needs farp.discovery backends to resolve it",
+            route.path
+        )));
+    }
+    Ok(())
+}
+
 fn validate_routes(config: &Config) -> Result<()> {
     for route in &config.routes {
         if route.path.is_empty() {
@@ -108,38 +725,317 @@ fn validate_routes(config: &Config) -> Result<()> {
             return Err(Error::Config("route path must start with '/'".to_string()));
         }
 
-        if route.upstream.is_empty() {
-            return Err(Error::Config("route upstream cannot be empty".to_string()));
-        }
+        if let Some(service) = &route.service {
+            validate_route_service(config, route, service)?;
+        } else {
+            if route.upstream.is_empty() {
+                return Err(Error::Config("route upstream cannot be empty".to_string()));
+            }
 
-        // Check that upstream exists
-        if !config.upstreams.iter().any(|u| u.name == route.upstream) {
-            return Err(Error::Config(format!(
-                "Route references non-existent upstream: {}",
-                route.upstream
-            )));
+            // Check that upstream exists
+            if !config.upstreams.iter().any(|u| u.name == route.upstream) {
+                return Err(Error::Config(format!(
+                    "Route references non-existent upstream: {}",
+                    route.upstream
+                )));
+            }
         }
-    }
 
-    Ok(())
-}
+        if let Some(concurrency) = &route.concurrency {
+            validate_concurrency(&format!("Route {} concurrency", route.path), concurrency)?;
+        }
 
-fn validate_plugins(_config: &Config) -> Result<()> {
-    // TODO: Validate plugin configurations
-    Ok(())
-}
+        if let Some(limit) = &route.response_body_limit {
+            validate_response_body_limit(
+                &format!("Route {} response_body_limit", route.path),
+                limit,
+            )?;
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::*;
-    use std::time::Duration;
+        for (location, upstream) in &route.geo_upstreams {
+            if !config.upstreams.iter().any(|u| &u.name == upstream) {
+                return Err(Error::Config(format!(
+                    "Route {} geo_upstreams.{location} references non-existent upstream: {upstream}",
+                    route.path
+                )));
+            }
+        }
+        if !route.geo_upstreams.is_empty() && !config.gateway.geoip.enabled {
+            tracing::warn!(route = %route.path, "geo_upstreams has no effect without gateway.geoip");
+        }
 
-    fn minimal_config() -> Config {
+        if let Some(fallback) = &route.fallback_upstream {
+            if !config.upstreams.iter().any(|u| &u.name == fallback) {
+                return Err(Error::Config(format!(
+                    "Route {} fallback_upstream references non-existent upstream: {fallback}",
+                    route.path
+                )));
+            }
+            if fallback == &route.upstream {
+                return Err(Error::Config(format!(
+                    "Route {} fallback_upstream must differ from its upstream",
+                    route.path
+                )));
+            }
+        }
+
+        if let Some(transform) = &route.transform {
+            for rules in [&transform.request_headers, &transform.response_headers] {
+                for (name, value) in rules.set.iter().chain(&rules.add) {
+                    octopus_core::Template::parse(value).map_err(|e| {
+                        Error::Config(format!("Route {} transform header {name}: {e}", route.path))
+                    })?;
+                }
+            }
+            if let Some(bad) = transform
+                .response_cookies
+                .add
+                .iter()
+                .find(|v| octopus_core::SetCookie::parse(v).is_none())
+            {
+                return Err(Error::Config(format!(
+                    "Route {} transform response_cookies.add: '{bad}' is not a name=value cookie",
+                    route.path
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_unmatched(config: &Config) -> Result<()> {
+    use crate::types::UnmatchedConfig;
+
+    match &config.gateway.unmatched {
+        Some(UnmatchedConfig::Respond { status, .. }) if !(200..=599).contains(status) => {
+            Err(Error::Config(format!(
+                "unmatched.status must be between 200 and 599, got {status}"
+            )))
+        }
+        Some(UnmatchedConfig::Redirect { url, .. }) if url.is_empty() => {
+            Err(Error::Config("unmatched.url cannot be empty".to_string()))
+        }
+        Some(UnmatchedConfig::Redirect { status, .. }) if !(300..=399).contains(status) => {
+            Err(Error::Config(format!(
+                "unmatched redirect status must be 3xx, got {status}"
+            )))
+        }
+        Some(UnmatchedConfig::Upstream { upstream })
+            if !config.upstreams.iter().any(|u| &u.name == upstream) =>
+        {
+            Err(Error::Config(format!(
+                "unmatched references non-existent upstream: {upstream}"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_grpc(config: &Config) -> Result<()> {
+    let grpc = &config.grpc;
+    let mapped = grpc
+        .services
+        .iter()
+        .map(|(target, upstream)| (target.as_str(), upstream))
+        .chain(
+            grpc.reflection_upstream
+                .iter()
+                .map(|upstream| ("reflection_upstream", upstream)),
+        );
+    for (target, upstream) in mapped {
+        if !config.upstreams.iter().any(|u| &u.name == upstream) {
+            return Err(Error::Config(format!(
+                "grpc {target} references non-existent upstream: {upstream}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_graphql(config: &Config) -> Result<()> {
+    for mapping in &config.graphql.rest_mappings {
+        if !mapping.rest_path.starts_with('/') {
+            return Err(Error::Config(format!(
+                "graphql rest_path must start with '/': {}",
+                mapping.rest_path
+            )));
+        }
+        if mapping.operation.trim().is_empty() {
+            return Err(Error::Config(format!(
+                "graphql rest mapping {} has an empty operation",
+                mapping.rest_path
+            )));
+        }
+        if !config.upstreams.iter().any(|u| u.name == mapping.upstream) {
+            return Err(Error::Config(format!(
+                "graphql rest mapping {} references non-existent upstream: {}",
+                mapping.rest_path, mapping.upstream
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_events(config: &Config) -> Result<()> {
+    for webhook in &config.events.webhooks {
+        let valid = webhook
+            .url
+            .split_once("://")
+            .is_some_and(|(scheme, rest)| matches!(scheme, "http" | "https") && !rest.is_empty());
+        if !valid {
+            return Err(Error::Config(format!(
+                "events webhook url must be an http(s) URL: {}",
+                webhook.url
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_cors(config: &Config) -> Result<()> {
+    if let Some(cors) = &config.cors {
+        validate_cors_origins("cors", &cors.allowed_origins, cors.allow_credentials)?;
+    }
+    for route in &config.routes {
+        if let Some(cors) = &route.cors {
+            validate_cors_origins(
+                &format!("route {} cors", route.path),
+                &cors.allowed_origins,
+                cors.allow_credentials,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `*` with credentials is rejected (browsers refuse it, and reflecting every
+/// origin instead would hand credentials to any site); `~` patterns must be
+/// valid regexes.
+fn validate_cors_origins(context: &str, origins: &[String], allow_credentials: bool) -> Result<()> {
+    for origin in origins {
+        let origin = origin.trim();
+        if origin == "*" && allow_credentials {
+            return Err(Error::Config(format!(
+                "{context}: allowed_origins \"*\" cannot be combined with allow_credentials"
+            )));
+        }
+        if let Some(re) = origin.strip_prefix('~') {
+            regex::Regex::new(re).map_err(|e| {
+                Error::Config(format!("{context}: invalid origin regex {re:?}: {e}"))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_plugins(config: &Config) -> Result<()> {
+    for plugin in &config.plugins {
+        if plugin.sandbox.timeout.is_zero() {
+            return Err(Error::Config(format!(
+                "plugin '{}': sandbox timeout must be greater than 0",
+                plugin.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_logging(config: &Config) -> Result<()> {
+    let categories = &config.observability.logging.categories;
+    for (name, sink) in [
+        ("app", &categories.app),
+        ("access", &categories.access),
+        ("audit", &categories.audit),
+    ] {
+        if sink
+            .as_ref()
+            .is_some_and(|sink| sink.output.trim().is_empty())
+        {
+            return Err(Error::Config(format!(
+                "observability.logging.categories.{name}.output must not be empty"
+            )));
+        }
+        let Some(buffer) = sink.as_ref().and_then(|sink| sink.buffer.as_ref()) else {
+            continue;
+        };
+        if name != "access" {
+            return Err(Error::Config(format!(
+                "observability.logging.categories.{name}.buffer is only supported for the access log"
+            )));
+        }
+        if buffer.capacity == 0 || buffer.batch_size == 0 || buffer.flush_interval.is_zero() {
+            return Err(Error::Config(
+                "observability.logging.categories.access.buffer capacity, batch_size and flush_interval must be > 0"
+                    .to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_slo(config: &Config) -> Result<()> {
+    let Some(slo) = &config.observability.metrics.slo else {
+        return Ok(());
+    };
+    let objectives = slo
+        .default
+        .iter()
+        .map(|objective| ("default".to_string(), objective))
+        .chain(
+            slo.routes
+                .iter()
+                .map(|(route, objective)| (format!("routes.{route}"), objective)),
+        );
+    for (name, objective) in objectives {
+        let in_range = |target: f64| target > 0.0 && target <= 1.0;
+        if !in_range(objective.success_target) || !in_range(objective.latency_target) {
+            return Err(Error::Config(format!(
+                "observability.metrics.slo.{name} success_target and latency_target must be in (0, 1]"
+            )));
+        }
+        if objective.window.is_zero() || objective.burn_rate_threshold <= 0.0 {
+            return Err(Error::Config(format!(
+                "observability.metrics.slo.{name} window and burn_rate_threshold must be > 0"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+    use std::time::Duration;
+
+    fn minimal_config() -> Config {
         Config {
             gateway: GatewayConfig {
                 listen: "127.0.0.1:8080".parse().unwrap(),
                 workers: 0,
+                worker_stack_size: None,
+                worker_cpus: Vec::new(),
+                acceptors: 0,
+                unix_socket: None,
+                proxy_protocol: Default::default(),
+                connection_limits: Default::default(),
+                concurrency: None,
+                debug_tap: None,
+                debug_headers: None,
+                request_id: None,
+                multipart: None,
+                internal_redirect: None,
+                tenancy: None,
+                response_body_limit: None,
+                idempotency: None,
+                request_signing: None,
+                qos: None,
+                startup_check: None,
+                warmup: Default::default(),
                 request_timeout: Duration::from_secs(30),
                 shutdown_timeout: Duration::from_secs(30),
                 pre_stop_delay: Duration::from_secs(5),
@@ -148,11 +1044,25 @@ mod tests {
                 compression: CompressionConfig::default(),
                 internal_route_prefix: Some("__".to_string()),
                 probes: ProbeConfig::default(),
+                h2c: true,
                 enforce_sni_check: true,
                 security_headers: Default::default(),
+                trailing_slash: Default::default(),
+                path_normalization: Default::default(),
+                error_format: Default::default(),
+                header_policy: Default::default(),
+                forwarded: Default::default(),
+                maintenance: Default::default(),
+                experiments: Default::default(),
+                request_validation: Default::default(),
+                response_validation: Default::default(),
+                geoip: Default::default(),
+                upstream_retry_after: Default::default(),
+                unmatched: None,
             },
             upstreams: vec![],
             routes: vec![],
+            route_groups: vec![],
             plugins: vec![],
             farp: Default::default(),
             observability: ObservabilityConfig::default(),
@@ -163,6 +1073,7 @@ mod tests {
             grpc: Default::default(),
             graphql: Default::default(),
             kubernetes: Default::default(),
+            events: Default::default(),
         }
     }
 
@@ -180,6 +1091,110 @@ mod tests {
         assert!(validate_config(&config).is_err());
     }
 
+    #[test]
+    fn test_geoip_requires_database_and_valid_status() {
+        let mut config = minimal_config();
+        config.gateway.geoip.enabled = true;
+        assert!(validate_config(&config).is_err());
+
+        config.gateway.geoip.database = "/var/lib/geoip/country.mmdb".to_string();
+        assert!(validate_config(&config).is_ok());
+
+        config.gateway.geoip.block_status = 404;
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_unmatched_policy_validation() {
+        let mut config = minimal_config();
+        config.gateway.unmatched = Some(UnmatchedConfig::Upstream {
+            upstream: "legacy".to_string(),
+        });
+        assert!(validate_config(&config).is_err());
+
+        config.gateway.unmatched = Some(UnmatchedConfig::Redirect {
+            url: "https://legacy.example.com".to_string(),
+            status: 200,
+            preserve_path: true,
+        });
+        assert!(validate_config(&config).is_err());
+
+        config.gateway.unmatched = Some(UnmatchedConfig::Respond {
+            status: 404,
+            content_type: "text/html".to_string(),
+            body: "<h1>Not found</h1>".to_string(),
+        });
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_grpc_mapping_requires_known_upstreams() {
+        let mut config = minimal_config();
+        config
+            .grpc
+            .services
+            .insert("users.UserService/GetUser".to_string(), "users".to_string());
+        assert!(validate_config(&config).is_err());
+
+        config.grpc.services.clear();
+        config.grpc.reflection_upstream = Some("users".to_string());
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_event_webhooks_require_http_urls() {
+        let mut config = minimal_config();
+        config.events.webhooks.push(WebhookConfig {
+            url: "hooks.example.com/octopus".to_string(),
+            events: vec![],
+            headers: Default::default(),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(5),
+        });
+        assert!(validate_config(&config).is_err());
+
+        config.events.webhooks[0].url = "https://hooks.example.com/octopus".to_string();
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_cors_rejects_wildcard_with_credentials_and_bad_regex() {
+        let mut config = minimal_config();
+        config.cors = Some(CorsGlobalConfig {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: vec![],
+            allowed_headers: vec![],
+            exposed_headers: vec![],
+            max_age: 600,
+            allow_credentials: true,
+        });
+        assert!(validate_config(&config).is_err());
+
+        let cors = config.cors.as_mut().unwrap();
+        cors.allowed_origins = vec![
+            "https://*.example.com".to_string(),
+            r"~https://pr-\d+\.preview\.example\.com".to_string(),
+        ];
+        assert!(validate_config(&config).is_ok());
+
+        config.cors.as_mut().unwrap().allowed_origins = vec!["~(unclosed".to_string()];
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_zero_plugin_sandbox_timeout() {
+        let mut config = minimal_config();
+        config.plugins = vec![serde_json::from_value(serde_json::json!({
+            "name": "tagger",
+            "sandbox": {"timeout": "0s"},
+        }))
+        .unwrap()];
+
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("plugin 'tagger'"), "{err}");
+    }
+
     #[test]
     fn test_zero_body_size() {
         let mut config = minimal_config();
@@ -188,6 +1203,540 @@ mod tests {
         assert!(validate_config(&config).is_err());
     }
 
+    #[test]
+    fn test_worker_settings_are_bounded() {
+        let mut config = minimal_config();
+        config.gateway.workers = 8;
+        config.gateway.worker_stack_size = Some(4 * 1024 * 1024);
+        config.gateway.worker_cpus = vec![0, 1];
+        assert!(validate_config(&config).is_ok());
+
+        config.gateway.workers = MAX_WORKERS + 1;
+        assert!(validate_config(&config).is_err());
+        config.gateway.workers = 8;
+
+        config.gateway.acceptors = MAX_WORKERS + 1;
+        assert!(validate_config(&config).is_err());
+        config.gateway.acceptors = 0;
+
+        config.gateway.worker_stack_size = Some(4096);
+        assert!(validate_config(&config).is_err());
+        config.gateway.worker_stack_size = None;
+
+        config.gateway.worker_cpus = vec![0, 1, 0];
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("CPU 0 more than once"), "{err}");
+    }
+
+    #[test]
+    fn test_debug_tap_requires_strong_secret() {
+        let mut config = minimal_config();
+        let mut tap = DebugTapConfig {
+            header: "x-octopus-debug".to_string(),
+            secret: "s".repeat(32),
+            max_token_ttl: Duration::from_secs(600),
+            capacity: 10,
+            max_body_bytes: 1024,
+            redact_headers: vec![],
+            redact_fields: vec![],
+            redact_query: vec![],
+        };
+        config.gateway.debug_tap = Some(tap.clone());
+        assert!(validate_config(&config).is_ok());
+
+        tap.secret = "short".to_string();
+        config.gateway.debug_tap = Some(tap.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("debug_tap.secret"), "{err}");
+
+        tap.secret = "s".repeat(32);
+        tap.header = "x debug".to_string();
+        config.gateway.debug_tap = Some(tap);
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("debug_tap.header"), "{err}");
+    }
+
+    #[test]
+    fn test_debug_headers_need_a_secret_unless_always_on() {
+        let mut config = minimal_config();
+        let mut headers = DebugHeadersConfig {
+            always: false,
+            header: "x-octopus-debug-headers".to_string(),
+            secret: None,
+        };
+        config.gateway.debug_headers = Some(headers.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("needs a secret"), "{err}");
+
+        headers.secret = Some("short".to_string());
+        config.gateway.debug_headers = Some(headers.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("debug_headers.secret"), "{err}");
+
+        headers.secret = Some("s".repeat(32));
+        config.gateway.debug_headers = Some(headers.clone());
+        assert!(validate_config(&config).is_ok());
+
+        headers.secret = None;
+        headers.always = true;
+        config.gateway.debug_headers = Some(headers);
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_request_id_is_validated() {
+        let mut config = minimal_config();
+        let mut request_id = RequestIdConfig {
+            header: "x-request-id".to_string(),
+            generator: RequestIdGenerator::Snowflake,
+            worker_id: 1023,
+            add_to_response: true,
+        };
+        config.gateway.request_id = Some(request_id.clone());
+        assert!(validate_config(&config).is_ok());
+
+        request_id.worker_id = 1024;
+        config.gateway.request_id = Some(request_id.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("request_id.worker_id"), "{err}");
+
+        request_id.worker_id = 0;
+        request_id.header = "bad header".to_string();
+        config.gateway.request_id = Some(request_id);
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("request_id.header"), "{err}");
+    }
+
+    #[test]
+    fn test_internal_redirect_needs_a_header_and_hops() {
+        let mut config = minimal_config();
+        let mut redirect = InternalRedirectConfig {
+            header: "x-accel-redirect".to_string(),
+            max_hops: 3,
+        };
+        config.gateway.internal_redirect = Some(redirect.clone());
+        assert!(validate_config(&config).is_ok());
+
+        redirect.max_hops = 0;
+        config.gateway.internal_redirect = Some(redirect.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("max_hops"), "{err}");
+
+        redirect.max_hops = 1;
+        redirect.header = "bad header".to_string();
+        config.gateway.internal_redirect = Some(redirect);
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("internal_redirect.header"), "{err}");
+    }
+
+    #[test]
+    fn test_multipart_part_limit_fits_the_total() {
+        let mut config = minimal_config();
+        let mut multipart = MultipartConfig {
+            max_part_size: 1024,
+            max_total_size: 4096,
+            stream: true,
+            allowed_fields: vec![],
+        };
+        config.gateway.multipart = Some(multipart.clone());
+        assert!(validate_config(&config).is_ok());
+
+        multipart.max_part_size = 8192;
+        config.gateway.multipart = Some(multipart.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("must not exceed"), "{err}");
+
+        multipart.max_part_size = 0;
+        config.gateway.multipart = Some(multipart);
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_streamed_multipart_excludes_body_reading_middleware() {
+        let mut config = minimal_config();
+        config.gateway.multipart = Some(MultipartConfig {
+            max_part_size: 1024,
+            max_total_size: 4096,
+            stream: true,
+            allowed_fields: vec![],
+        });
+        assert!(validate_config(&config).is_ok());
+
+        config.gateway.idempotency = Some(IdempotencyConfig::default());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("idempotency"), "{err}");
+
+        // Buffered uploads go through the chain like any other body
+        config.gateway.multipart.as_mut().unwrap().stream = false;
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_tenancy_upstreams_must_exist() {
+        let mut config = minimal_config();
+        config.gateway.tenancy = Some(TenancyConfig {
+            source: TenantSourceConfig::Subdomain {
+                base_domain: "api.example.com".to_string(),
+            },
+            header: "X-Tenant-Id".to_string(),
+            unknown_status: 404,
+            required: true,
+            tenants: [(
+                "acme".to_string(),
+                crate::types::TenantConfig {
+                    upstream: Some("acme-api".to_string()),
+                    headers: Default::default(),
+                },
+            )]
+            .into(),
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("tenancy.tenants.acme"), "{err}");
+
+        config.upstreams.push(
+            serde_json::from_value(serde_json::json!({
+                "name": "acme-api",
+                "instances": [{"id": "acme-1", "host": "10.0.0.1", "port": 8080}],
+            }))
+            .unwrap(),
+        );
+        assert!(validate_config(&config).is_ok());
+
+        config.gateway.tenancy.as_mut().unwrap().unknown_status = 500;
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("tenancy.unknown_status"), "{err}");
+    }
+
+    #[test]
+    fn test_upstream_health_check_validation() {
+        let upstream = |check: serde_json::Value| {
+            serde_json::from_value(serde_json::json!({
+                "name": "orders",
+                "instances": [{"id": "orders-1", "host": "10.0.0.1", "port": 8080}],
+                "health_check": check,
+            }))
+            .unwrap()
+        };
+        let mut config = minimal_config();
+        config.upstreams = vec![upstream(serde_json::json!({
+            "type": "http", "path": "/healthz", "interval": "10s", "timeout": "2s",
+        }))];
+        assert!(validate_config(&config).is_ok());
+
+        config.upstreams = vec![upstream(serde_json::json!({
+            "type": "icmp", "interval": "10s", "timeout": "2s",
+        }))];
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("health_check.type"), "{err}");
+
+        config.upstreams = vec![upstream(serde_json::json!({
+            "type": "tcp", "interval": "0s", "timeout": "2s",
+        }))];
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_upstream_circuit_breaker_validation() {
+        let upstream = |breaker: serde_json::Value| {
+            serde_json::from_value(serde_json::json!({
+                "name": "orders",
+                "instances": [{"id": "orders-1", "host": "10.0.0.1", "port": 8080}],
+                "circuit_breaker": breaker,
+            }))
+            .unwrap()
+        };
+        let mut config = minimal_config();
+        config.upstreams = vec![upstream(serde_json::json!({
+            "error_threshold": 50.0, "min_requests": 20, "timeout": "30s",
+        }))];
+        assert!(validate_config(&config).is_ok());
+        let breaker = config.upstreams[0].circuit_breaker.as_ref().unwrap();
+        assert_eq!(breaker.failure_status_codes.len(), 100);
+        assert!(breaker.count_connection_errors && breaker.count_timeouts);
+
+        config.upstreams = vec![upstream(serde_json::json!({
+            "error_threshold": 50.0, "min_requests": 20, "timeout": "30s",
+            "failure_status_codes": [503, 999],
+        }))];
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("failure_status_codes"), "{err}");
+
+        config.upstreams = vec![upstream(serde_json::json!({
+            "error_threshold": 150.0, "min_requests": 20, "timeout": "30s",
+        }))];
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_concurrency_limit_must_admit_requests() {
+        let mut config = minimal_config();
+        let mut concurrency = ConcurrencyConfig {
+            max_concurrent: 10,
+            queue_depth: 5,
+            queue_timeout: Duration::from_millis(500),
+        };
+        config.gateway.concurrency = Some(concurrency.clone());
+        assert!(validate_config(&config).is_ok());
+
+        concurrency.queue_timeout = Duration::ZERO;
+        config.gateway.concurrency = Some(concurrency.clone());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("gateway.concurrency.queue_timeout"), "{err}");
+
+        concurrency.max_concurrent = 0;
+        config.gateway.concurrency = Some(concurrency);
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("gateway.concurrency.max_concurrent"), "{err}");
+    }
+
+    #[test]
+    fn test_response_body_limit_must_be_positive() {
+        let mut config = minimal_config();
+        config.gateway.response_body_limit = Some(ResponseBodyLimitConfig {
+            max_bytes: 0,
+            on_exceed: Default::default(),
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(
+            err.contains("gateway.response_body_limit.max_bytes"),
+            "{err}"
+        );
+
+        config
+            .gateway
+            .response_body_limit
+            .as_mut()
+            .unwrap()
+            .max_bytes = 1024;
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_idempotency_needs_a_header_and_methods() {
+        let mut config = minimal_config();
+        config.gateway.idempotency = Some(IdempotencyConfig::default());
+        assert!(validate_config(&config).is_ok());
+
+        config.gateway.idempotency.as_mut().unwrap().methods = vec!["P OST".to_string()];
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("idempotency.methods"), "{err}");
+
+        config.gateway.idempotency.as_mut().unwrap().header = String::new();
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("idempotency.header"), "{err}");
+    }
+
+    #[test]
+    fn test_request_signing_needs_long_enough_keys() {
+        let mut config = minimal_config();
+        config.gateway.request_signing = Some(RequestSigningConfig::default());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("request_signing.keys"), "{err}");
+
+        let signing = config.gateway.request_signing.as_mut().unwrap();
+        signing
+            .keys
+            .insert("billing".to_string(), "short".to_string());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("request_signing.keys.billing"), "{err}");
+
+        let signing = config.gateway.request_signing.as_mut().unwrap();
+        signing.keys.insert("billing".to_string(), "x".repeat(32));
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_log_category_output_must_be_set() {
+        let mut config = minimal_config();
+        config.observability.logging.categories.audit = Some(LogSinkConfig::default());
+        assert!(validate_config(&config).is_ok());
+
+        config.observability.logging.categories.audit = Some(LogSinkConfig {
+            output: " ".to_string(),
+            ..Default::default()
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("categories.audit.output"), "{err}");
+    }
+
+    #[test]
+    fn test_only_the_access_log_is_buffered() {
+        let mut config = minimal_config();
+        let buffered = |buffer: LogBufferConfig| {
+            Some(LogSinkConfig {
+                buffer: Some(buffer),
+                ..Default::default()
+            })
+        };
+        config.observability.logging.categories.access = buffered(LogBufferConfig::default());
+        assert!(validate_config(&config).is_ok());
+
+        config.observability.logging.categories.access = buffered(LogBufferConfig {
+            batch_size: 0,
+            ..Default::default()
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("access.buffer"), "{err}");
+
+        config.observability.logging.categories.access = None;
+        config.observability.logging.categories.audit = buffered(LogBufferConfig::default());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("audit.buffer"), "{err}");
+    }
+
+    #[test]
+    fn test_slo_objectives_are_validated() {
+        let mut config = minimal_config();
+        config.observability.metrics.slo = Some(SloConfig {
+            default: Some(SloObjectiveConfig::default()),
+            routes: std::collections::HashMap::from([(
+                "/api/checkout".to_string(),
+                SloObjectiveConfig {
+                    success_target: 0.999,
+                    ..Default::default()
+                },
+            )]),
+        });
+        assert!(validate_config(&config).is_ok());
+
+        config.observability.metrics.slo = Some(SloConfig {
+            routes: std::collections::HashMap::from([(
+                "/api/checkout".to_string(),
+                SloObjectiveConfig {
+                    success_target: 99.0,
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("slo.routes./api/checkout"), "{err}");
+
+        config.observability.metrics.slo = Some(SloConfig {
+            default: Some(SloObjectiveConfig {
+                window: Duration::ZERO,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("slo.default"), "{err}");
+    }
+
+    #[test]
+    fn test_qos_rules_need_a_condition_and_a_limit() {
+        let rule = QosRule {
+            priority: RequestPriority::High,
+            path_prefix: None,
+            header: Some("X-Tier".to_string()),
+            header_value: Some("paid".to_string()),
+            tenant: None,
+            role: None,
+        };
+        let mut config = minimal_config();
+        config.gateway.qos = Some(QosConfig {
+            default_priority: RequestPriority::Normal,
+            shed_below: RequestPriority::Normal,
+            rules: vec![rule.clone()],
+        });
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("gateway.concurrency"), "{err}");
+
+        config.gateway.concurrency = Some(ConcurrencyConfig {
+            max_concurrent: 100,
+            queue_depth: 10,
+            queue_timeout: Duration::from_secs(1),
+        });
+        assert!(validate_config(&config).is_ok());
+
+        let qos = config.gateway.qos.as_mut().unwrap();
+        qos.rules[0].header = None;
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("must set"), "{err}");
+
+        let qos = config.gateway.qos.as_mut().unwrap();
+        qos.rules[0].tenant = Some("acme".to_string());
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("header_value without header"), "{err}");
+    }
+
+    #[test]
+    fn test_admin_listen_must_differ_from_gateway_listen() {
+        let mut config = minimal_config();
+        config.admin.listen = Some("127.0.0.1:9090".parse().unwrap());
+        assert!(validate_config(&config).is_ok());
+
+        config.admin.listen = Some(config.gateway.listen);
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("admin.listen"), "{err}");
+    }
+
+    #[test]
+    fn test_startup_check_timeout_must_be_positive() {
+        let mut config = minimal_config();
+        config.gateway.startup_check = Some(StartupCheckConfig::default());
+        assert!(validate_config(&config).is_ok());
+
+        config.gateway.startup_check.as_mut().unwrap().timeout = Duration::ZERO;
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("startup_check.timeout"), "{err}");
+    }
+
+    #[test]
+    fn test_proxy_protocol_needs_valid_trusted_sources() {
+        let mut config = minimal_config();
+        config.gateway.proxy_protocol.enabled = true;
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("trusted_sources"), "{err}");
+
+        config.gateway.proxy_protocol.trusted_sources =
+            vec!["10.0.0.0/8".to_string(), "not-an-ip".to_string()];
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("not-an-ip"), "{err}");
+
+        config.gateway.proxy_protocol.trusted_sources =
+            vec!["10.0.0.0/8".to_string(), "192.0.2.7".to_string()];
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_maintenance_bypass_ips_are_validated() {
+        let mut config = minimal_config();
+        config.gateway.maintenance.bypass_ips =
+            vec!["10.1.2.0/24".to_string(), "office".to_string()];
+        let err = validate_config(&config).unwrap_err().to_string();
+        assert!(err.contains("maintenance.bypass_ips"), "{err}");
+        assert!(err.contains("office"), "{err}");
+
+        config.gateway.maintenance.bypass_ips = vec!["10.1.2.0/24".to_string()];
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_unix_socket_mode_must_be_octal() {
+        let mut config = minimal_config();
+        let mut socket = UnixSocketConfig {
+            path: "/run/octopus.sock".into(),
+            mode: Some("0660".to_string()),
+            uid: None,
+            gid: None,
+        };
+        config.gateway.unix_socket = Some(socket.clone());
+        assert!(validate_config(&config).is_ok());
+
+        for mode in ["rw-rw----", "0990", "17777"] {
+            socket.mode = Some(mode.to_string());
+            config.gateway.unix_socket = Some(socket.clone());
+            let err = validate_config(&config).unwrap_err().to_string();
+            assert!(err.contains("unix_socket.mode"), "{err}");
+        }
+
+        socket.mode = None;
+        socket.path = "".into();
+        config.gateway.unix_socket = Some(socket);
+        assert!(validate_config(&config).is_err());
+    }
+
     #[test]
     fn test_route_invalid_upstream() {
         let mut config = minimal_config();
@@ -195,6 +1744,7 @@ mod tests {
             path: "/test".to_string(),
             methods: vec!["GET".to_string()],
             upstream: "nonexistent".to_string(),
+            service: None,
             priority: 0,
             strip_prefix: None,
             add_prefix: None,
@@ -206,14 +1756,118 @@ mod tests {
             authz_rule: None,
             timeout: None,
             rate_limit: None,
+            concurrency: None,
             cors: None,
             path_mode: None,
             upstream_origin: None,
             rewrite_redirects: None,
             rewrite_cookie_path: None,
             tls_verify: None,
+            fallback: None,
+            fallback_upstream: None,
+            geo_upstreams: std::collections::HashMap::new(),
+            response_body_limit: None,
+            transform: None,
         });
 
         assert!(validate_config(&config).is_err());
     }
+
+    #[test]
+    fn test_route_header_template_variables_are_checked() {
+        let config = |value: &str| -> Config {
+            serde_yaml::from_str(&format!(
+                r#"
+gateway:
+  listen: "127.0.0.1:8080"
+upstreams:
+  - name: users
+    instances: [{{ id: u1, host: 127.0.0.1, port: 9000 }}]
+routes:
+  - path: /users/:id
+    methods: [GET]
+    upstream: users
+    transform:
+      request_headers:
+        set:
+          X-User: "{value}"
+"#
+            ))
+            .unwrap()
+        };
+
+        assert!(validate_config(&config("{param.id}")).is_ok());
+        let err = validate_config(&config("{secret}")).unwrap_err();
+        assert!(err.to_string().contains("X-User"));
+    }
+
+    #[test]
+    fn test_route_fallback_upstream_must_be_another_known_upstream() {
+        let config = |fallback: &str| -> Config {
+            serde_yaml::from_str(&format!(
+                r#"
+gateway:
+  listen: "127.0.0.1:8080"
+upstreams:
+  - name: orders
+    instances: [{{ id: o1, host: 127.0.0.1, port: 9000 }}]
+  - name: orders-dr
+    instances: [{{ id: d1, host: 127.0.0.1, port: 9001 }}]
+routes:
+  - path: /orders
+    methods: [GET]
+    upstream: orders
+    fallback_upstream: {fallback}
+"#
+            ))
+            .unwrap()
+        };
+
+        assert!(validate_config(&config("orders-dr")).is_ok());
+        let err = validate_config(&config("orders-eu"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("non-existent upstream: orders-eu"), "{err}");
+        let err = validate_config(&config("orders")).unwrap_err().to_string();
+        assert!(err.contains("must differ"), "{err}");
+    }
+
+    #[test]
+    fn test_route_service_replaces_upstream_and_needs_discovery() {
+        let config = |route: &str, discovery: &str| -> Config {
+            serde_yaml::from_str(&format!(
+                r#"
+gateway:
+  listen: "127.0.0.1:8080"
+farp:
+  {discovery}
+upstreams:
+  - name: orders
+    instances: [{{ id: o1, host: 127.0.0.1, port: 9000 }}]
+routes:
+  - path: /orders
+    methods: [GET]
+    {route}
+"#
+            ))
+            .unwrap()
+        };
+        let consul = "discovery: { backends: [{ type: consul, config: { address: 'http://consul:8500', datacenter: dc1, watch_interval: 10s } }] }";
+
+        let config_ok = config("service: orders-svc", consul);
+        assert!(validate_config(&config_ok).is_ok());
+        assert_eq!(
+            config_ok.farp.discovery.unwrap().resolve_cache_ttl,
+            std::time::Duration::from_secs(5)
+        );
+
+        let err = validate_config(&config("service: orders-svc", "enabled: false"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("needs farp.discovery"), "{err}");
+        let err = validate_config(&config("upstream: orders\n    service: orders-svc", consul))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("both upstream and service"), "{err}");
+    }
 }
@@ -0,0 +1,293 @@
+//! Cookie parsing and rewriting.
+//!
+//! Request cookies arrive as `name=value` pairs across one or more `Cookie`
+//! headers; response cookies as one `Set-Cookie` header each, with
+//! attributes (`Path`, `Domain`, `SameSite`, `Secure`, ...). The helpers here
+//! read and rewrite both without disturbing entries they don't touch.
+
+use http::{header, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The `SameSite` cookie attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    /// `SameSite=Strict`
+    Strict,
+    /// `SameSite=Lax`
+    Lax,
+    /// `SameSite=None` (browsers require `Secure` with it)
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        })
+    }
+}
+
+/// `(name, value)` pairs across every `Cookie` header, in order. A cookie
+/// without `=` is a name with an empty value, as browsers and most servers
+/// read it.
+pub fn cookie_pairs(headers: &HeaderMap) -> impl Iterator<Item = (&str, &str)> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .map(|(name, value)| (name.trim(), value.trim()))
+}
+
+/// The value of request cookie `name`, if present.
+pub fn get_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    cookie_pairs(headers).find_map(|(n, v)| (n == name).then_some(v))
+}
+
+/// Rewrite the request cookies: `f` edits the `(name, value)` list, which is
+/// then written back as a single `Cookie` header (removed when empty).
+pub fn rewrite_cookies(headers: &mut HeaderMap, f: impl FnOnce(&mut Vec<(String, String)>)) {
+    let mut cookies: Vec<(String, String)> = cookie_pairs(headers)
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect();
+    f(&mut cookies);
+
+    headers.remove(header::COOKIE);
+    if cookies.is_empty() {
+        return;
+    }
+    let joined = cookies
+        .iter()
+        .map(|(n, v)| format!("{n}={v}"))
+        .collect::<Vec<_>>()
+        .join("; ");
+    if let Ok(value) = HeaderValue::from_str(&joined) {
+        headers.insert(header::COOKIE, value);
+    }
+}
+
+/// A parsed `Set-Cookie` header value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetCookie {
+    /// Cookie name
+    pub name: String,
+    /// Cookie value
+    pub value: String,
+    /// Attributes in their original order and spelling; flags such as
+    /// `Secure` have no value
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl SetCookie {
+    /// A cookie with no attributes.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Parse a `Set-Cookie` value, or `None` without a `name=value` pair.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(';');
+        let (name, cookie_value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let attributes = parts
+            .map(str::trim)
+            .filter(|attr| !attr.is_empty())
+            .map(|attr| match attr.split_once('=') {
+                Some((k, v)) => (k.trim().to_string(), Some(v.trim().to_string())),
+                None => (attr.to_string(), None),
+            })
+            .collect();
+        Some(Self {
+            name: name.to_string(),
+            value: cookie_value.trim().to_string(),
+            attributes,
+        })
+    }
+
+    /// Attribute `name` (case-insensitive): `Some(None)` for a flag,
+    /// `Some(Some(v))` for a valued attribute, `None` when absent.
+    pub fn attribute(&self, name: &str) -> Option<Option<&str>> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_deref())
+    }
+
+    /// Set attribute `name` to `value` (`None` for a flag), replacing it in
+    /// place if present.
+    pub fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        let value = value.map(str::to_string);
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        {
+            Some(slot) => slot.1 = value,
+            None => self.attributes.push((name.to_string(), value)),
+        }
+    }
+
+    /// Remove attribute `name` (case-insensitive).
+    pub fn remove_attribute(&mut self, name: &str) {
+        self.attributes
+            .retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    }
+
+    /// Set `SameSite`.
+    pub fn set_same_site(&mut self, same_site: SameSite) {
+        self.set_attribute("SameSite", Some(&same_site.to_string()));
+    }
+
+    /// Add or drop the `Secure` flag.
+    pub fn set_secure(&mut self, secure: bool) {
+        if secure {
+            self.set_attribute("Secure", None);
+        } else {
+            self.remove_attribute("Secure");
+        }
+    }
+
+    /// Add or drop the `HttpOnly` flag.
+    pub fn set_http_only(&mut self, http_only: bool) {
+        if http_only {
+            self.set_attribute("HttpOnly", None);
+        } else {
+            self.remove_attribute("HttpOnly");
+        }
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        for (name, value) in &self.attributes {
+            match value {
+                Some(value) => write!(f, "; {name}={value}")?,
+                None => write!(f, "; {name}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rewrite every `Set-Cookie` header: `f` edits each parsed cookie and
+/// returns whether to keep it. Values that don't parse are kept verbatim.
+pub fn rewrite_set_cookies(headers: &mut HeaderMap, mut f: impl FnMut(&mut SetCookie) -> bool) {
+    let original: Vec<HeaderValue> = match headers.entry(header::SET_COOKIE) {
+        header::Entry::Occupied(entry) => entry.remove_entry_mult().1.collect(),
+        header::Entry::Vacant(_) => return,
+    };
+
+    for value in original {
+        let Some(mut cookie) = value.to_str().ok().and_then(SetCookie::parse) else {
+            headers.append(header::SET_COOKIE, value);
+            continue;
+        };
+        if !f(&mut cookie) {
+            continue;
+        }
+        match HeaderValue::from_str(&cookie.to_string()) {
+            Ok(rewritten) => headers.append(header::SET_COOKIE, rewritten),
+            Err(_) => headers.append(header::SET_COOKIE, value),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_cookies_across_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, HeaderValue::from_static("a=1; b=2"));
+        headers.append(header::COOKIE, HeaderValue::from_static("c=3"));
+
+        assert_eq!(get_cookie(&headers, "b"), Some("2"));
+        assert_eq!(get_cookie(&headers, "c"), Some("3"));
+        assert_eq!(get_cookie(&headers, "d"), None);
+    }
+
+    #[test]
+    fn cookies_without_a_value_are_kept() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            header::COOKIE,
+            HeaderValue::from_static("a=1; flag; ; b=2;"),
+        );
+
+        let pairs: Vec<_> = cookie_pairs(&headers).collect();
+        assert_eq!(pairs, [("a", "1"), ("flag", ""), ("b", "2")]);
+
+        rewrite_cookies(&mut headers, |cookies| cookies.retain(|(n, _)| n != "a"));
+        assert_eq!(headers[header::COOKIE], "flag=; b=2");
+    }
+
+    #[test]
+    fn rewrite_cookies_drops_named_and_keeps_others() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, HeaderValue::from_static("a=1; debug=x"));
+        headers.append(header::COOKIE, HeaderValue::from_static("c=3"));
+
+        rewrite_cookies(&mut headers, |cookies| {
+            cookies.retain(|(n, _)| n != "debug")
+        });
+        assert_eq!(headers.get_all(header::COOKIE).iter().count(), 1);
+        assert_eq!(headers[header::COOKIE], "a=1; c=3");
+
+        rewrite_cookies(&mut headers, Vec::clear);
+        assert!(!headers.contains_key(header::COOKIE));
+    }
+
+    #[test]
+    fn set_cookie_round_trips_and_edits_attributes() {
+        let mut cookie =
+            SetCookie::parse("sid=abc; Path=/; secure; samesite=None; HttpOnly").unwrap();
+        assert_eq!(cookie.attribute("Secure"), Some(None));
+        assert_eq!(cookie.attribute("SameSite"), Some(Some("None")));
+
+        cookie.set_same_site(SameSite::Lax);
+        cookie.set_secure(false);
+        cookie.set_attribute("Domain", Some("example.com"));
+        assert_eq!(
+            cookie.to_string(),
+            "sid=abc; Path=/; samesite=Lax; HttpOnly; Domain=example.com"
+        );
+
+        assert!(SetCookie::parse("no-pair").is_none());
+    }
+
+    #[test]
+    fn rewrite_set_cookies_handles_each_header() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::SET_COOKIE, HeaderValue::from_static("a=1; Path=/"));
+        headers.append(header::SET_COOKIE, HeaderValue::from_static("track=1"));
+        headers.append(header::SET_COOKIE, HeaderValue::from_static("garbage"));
+
+        rewrite_set_cookies(&mut headers, |cookie| {
+            cookie.set_same_site(SameSite::Strict);
+            cookie.name != "track"
+        });
+
+        let values: Vec<&str> = headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+        assert_eq!(values, vec!["a=1; Path=/; SameSite=Strict", "garbage"]);
+    }
+}
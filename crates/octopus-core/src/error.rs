@@ -22,14 +22,55 @@ pub enum Error {
     #[error("Failed to connect to upstream: {0}")]
     UpstreamConnection(String),
 
+    /// The upstream refused the connection
+    #[error("Upstream refused the connection: {0}")]
+    UpstreamRefused(String),
+
+    /// The upstream reset the connection
+    #[error("Upstream reset the connection: {0}")]
+    UpstreamReset(String),
+
+    /// The upstream closed the connection before the response was complete
+    #[error("Upstream response incomplete: {0}")]
+    UpstreamIncompleteResponse(String),
+
+    /// The upstream response body exceeded the configured size limit
+    #[error("Upstream response exceeded {0} bytes")]
+    UpstreamResponseTooLarge(usize),
+
+    /// The upstream host name did not resolve
+    #[error("Upstream DNS lookup failed: {0}")]
+    UpstreamDns(String),
+
+    /// The TLS handshake with the upstream failed
+    #[error("Upstream TLS handshake failed: {0}")]
+    UpstreamTls(String),
+
     /// Upstream timeout
     #[error("Upstream request timed out")]
     UpstreamTimeout,
 
+    /// The request's deadline passed before the upstream answered
+    #[error("Request deadline exceeded")]
+    DeadlineExceeded,
+
     /// Upstream unavailable
     #[error("No healthy upstream instances available")]
     NoHealthyUpstream,
 
+    /// The upstream answered, with an error status. Unlike the failures
+    /// above this is the upstream's own response, not the gateway's: it
+    /// reaches the client unchanged (see [`Error::upstream_response`]).
+    #[error("Upstream responded with {status}")]
+    Upstream {
+        /// Status of the upstream response
+        status: http::StatusCode,
+        /// Headers of the upstream response
+        headers: Box<http::HeaderMap>,
+        /// Body of the upstream response
+        body: bytes::Bytes,
+    },
+
     /// Configuration error
     #[error("Configuration error: {0}")]
     Config(String),
@@ -100,23 +141,298 @@ pub enum Error {
     Internal(String),
 }
 
-impl Error {
-    /// Convert error to HTTP status code
-    pub fn to_status_code(&self) -> http::StatusCode {
+/// Stable, machine-readable error code carried by every [`Error`] variant.
+///
+/// Codes are part of the client contract: they appear in error bodies and
+/// must not be renamed. [`ErrorCode::spec`] is the single code → status →
+/// client-safe message table; the client message never includes the
+/// variant's payload, which may hold internal details (addresses, upstream
+/// names, parser output) that belong in logs only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// Malformed or rejected request
+    InvalidRequest,
+    /// No route matches the request
+    RouteNotFound,
+    /// The upstream could not be reached
+    UpstreamUnavailable,
+    /// The upstream refused the connection
+    UpstreamRefused,
+    /// The upstream reset the connection
+    UpstreamReset,
+    /// The upstream response ended early
+    UpstreamIncompleteResponse,
+    /// The upstream response body was over the size limit
+    UpstreamResponseTooLarge,
+    /// The upstream host name did not resolve
+    UpstreamDnsFailure,
+    /// The TLS handshake with the upstream failed
+    UpstreamTlsFailure,
+    /// The upstream did not answer in time
+    UpstreamTimeout,
+    /// The request's total time budget ran out
+    DeadlineExceeded,
+    /// Every instance of the upstream is unhealthy
+    NoHealthyUpstream,
+    /// The upstream responded with an error status
+    UpstreamStatus,
+    /// Credentials missing or invalid
+    Unauthenticated,
+    /// Authenticated but not allowed
+    Forbidden,
+    /// Rate limit exceeded
+    RateLimited,
+    /// The upstream's circuit breaker is open
+    CircuitOpen,
+    /// Gateway configuration error
+    ConfigError,
+    /// Plugin failure
+    PluginError,
+    /// Middleware failure
+    MiddlewareError,
+    /// FARP protocol failure
+    FarpError,
+    /// Schema processing failure
+    SchemaError,
+    /// Service discovery failure
+    DiscoveryError,
+    /// Serialization failure
+    SerializationError,
+    /// I/O failure
+    IoError,
+    /// Any other internal failure
+    InternalError,
+}
+
+impl ErrorCode {
+    /// The code → (wire code, status, client-safe message) table.
+    pub const fn spec(self) -> (&'static str, http::StatusCode, &'static str) {
         use http::StatusCode;
         match self {
-            Error::Http(_) | Error::InvalidRequest(_) => StatusCode::BAD_REQUEST,
-            Error::RouteNotFound(_) => StatusCode::NOT_FOUND,
-            Error::UpstreamConnection(_) | Error::UpstreamTimeout => StatusCode::BAD_GATEWAY,
-            Error::NoHealthyUpstream => StatusCode::SERVICE_UNAVAILABLE,
-            Error::Authentication(_) => StatusCode::UNAUTHORIZED,
-            Error::Authorization(_) => StatusCode::FORBIDDEN,
-            Error::RateLimitExceeded => StatusCode::TOO_MANY_REQUESTS,
-            Error::CircuitBreakerOpen(_) => StatusCode::SERVICE_UNAVAILABLE,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidRequest => (
+                "INVALID_REQUEST",
+                StatusCode::BAD_REQUEST,
+                "Invalid request",
+            ),
+            Self::RouteNotFound => ("ROUTE_NOT_FOUND", StatusCode::NOT_FOUND, "Route not found"),
+            Self::UpstreamUnavailable => (
+                "UPSTREAM_UNAVAILABLE",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service unavailable",
+            ),
+            Self::UpstreamRefused => (
+                "UPSTREAM_REFUSED",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service refused the connection",
+            ),
+            Self::UpstreamReset => (
+                "UPSTREAM_RESET",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service closed the connection unexpectedly",
+            ),
+            Self::UpstreamIncompleteResponse => (
+                "UPSTREAM_INCOMPLETE_RESPONSE",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service sent an incomplete response",
+            ),
+            Self::UpstreamResponseTooLarge => (
+                "UPSTREAM_RESPONSE_TOO_LARGE",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service response is too large",
+            ),
+            Self::UpstreamDnsFailure => (
+                "UPSTREAM_DNS_FAILURE",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service address could not be resolved",
+            ),
+            Self::UpstreamTlsFailure => (
+                "UPSTREAM_TLS_FAILURE",
+                StatusCode::BAD_GATEWAY,
+                "Secure connection to upstream service failed",
+            ),
+            Self::UpstreamTimeout => (
+                "UPSTREAM_TIMEOUT",
+                StatusCode::GATEWAY_TIMEOUT,
+                "Upstream request timed out",
+            ),
+            Self::DeadlineExceeded => (
+                "DEADLINE_EXCEEDED",
+                StatusCode::GATEWAY_TIMEOUT,
+                "Request deadline exceeded",
+            ),
+            Self::NoHealthyUpstream => (
+                "NO_HEALTHY_UPSTREAM",
+                StatusCode::SERVICE_UNAVAILABLE,
+                "No healthy upstream available",
+            ),
+            Self::UpstreamStatus => (
+                "UPSTREAM_STATUS",
+                StatusCode::BAD_GATEWAY,
+                "Upstream service responded with an error",
+            ),
+            Self::Unauthenticated => (
+                "UNAUTHENTICATED",
+                StatusCode::UNAUTHORIZED,
+                "Authentication required",
+            ),
+            Self::Forbidden => ("FORBIDDEN", StatusCode::FORBIDDEN, "Access denied"),
+            Self::RateLimited => (
+                "RATE_LIMITED",
+                StatusCode::TOO_MANY_REQUESTS,
+                "Rate limit exceeded",
+            ),
+            Self::CircuitOpen => (
+                "CIRCUIT_OPEN",
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Upstream temporarily unavailable",
+            ),
+            Self::ConfigError => ("CONFIG_ERROR", StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+            Self::PluginError => ("PLUGIN_ERROR", StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+            Self::MiddlewareError => (
+                "MIDDLEWARE_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                INTERNAL,
+            ),
+            Self::FarpError => ("FARP_ERROR", StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+            Self::SchemaError => ("SCHEMA_ERROR", StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+            Self::DiscoveryError => (
+                "DISCOVERY_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                INTERNAL,
+            ),
+            Self::SerializationError => (
+                "SERIALIZATION_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                INTERNAL,
+            ),
+            Self::IoError => ("IO_ERROR", StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+            Self::InternalError => (
+                "INTERNAL_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                INTERNAL,
+            ),
+        }
+    }
+
+    /// Wire form of the code (e.g. `ROUTE_NOT_FOUND`)
+    pub const fn as_str(self) -> &'static str {
+        self.spec().0
+    }
+
+    /// HTTP status for the code
+    pub const fn status(self) -> http::StatusCode {
+        self.spec().1
+    }
+
+    /// Message safe to return to clients
+    pub const fn client_message(self) -> &'static str {
+        self.spec().2
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+const INTERNAL: &str = "Internal server error";
+
+impl Error {
+    /// Stable machine-readable code for this error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Http(_) | Error::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            Error::RouteNotFound(_) => ErrorCode::RouteNotFound,
+            Error::UpstreamConnection(_) => ErrorCode::UpstreamUnavailable,
+            Error::UpstreamRefused(_) => ErrorCode::UpstreamRefused,
+            Error::UpstreamReset(_) => ErrorCode::UpstreamReset,
+            Error::UpstreamIncompleteResponse(_) => ErrorCode::UpstreamIncompleteResponse,
+            Error::UpstreamResponseTooLarge(_) => ErrorCode::UpstreamResponseTooLarge,
+            Error::UpstreamDns(_) => ErrorCode::UpstreamDnsFailure,
+            Error::UpstreamTls(_) => ErrorCode::UpstreamTlsFailure,
+            Error::UpstreamTimeout => ErrorCode::UpstreamTimeout,
+            Error::DeadlineExceeded => ErrorCode::DeadlineExceeded,
+            Error::NoHealthyUpstream => ErrorCode::NoHealthyUpstream,
+            Error::Upstream { .. } => ErrorCode::UpstreamStatus,
+            Error::Config(_) => ErrorCode::ConfigError,
+            Error::Plugin { .. } => ErrorCode::PluginError,
+            Error::Middleware(_) => ErrorCode::MiddlewareError,
+            Error::Authentication(_) => ErrorCode::Unauthenticated,
+            Error::Authorization(_) => ErrorCode::Forbidden,
+            Error::RateLimitExceeded => ErrorCode::RateLimited,
+            Error::CircuitBreakerOpen(_) => ErrorCode::CircuitOpen,
+            Error::Farp(_) => ErrorCode::FarpError,
+            Error::Schema(_) => ErrorCode::SchemaError,
+            Error::Discovery(_) => ErrorCode::DiscoveryError,
+            Error::Serialization(_) => ErrorCode::SerializationError,
+            Error::Io(_) => ErrorCode::IoError,
+            Error::Runtime(_) | Error::HttpError(_) | Error::Generic(_) | Error::Internal(_) => {
+                ErrorCode::InternalError
+            }
         }
     }
 
+    /// Convert error to HTTP status code. An [`Error::Upstream`] rendered as
+    /// a gateway error is a 502 like any other upstream failure; its own
+    /// status only reaches the client through [`Error::upstream_response`].
+    pub fn to_status_code(&self) -> http::StatusCode {
+        self.code().status()
+    }
+
+    /// Client-safe message for this error. Unlike `Display`, it never includes
+    /// the variant's payload; log the error itself for the details.
+    pub fn client_message(&self) -> &'static str {
+        self.code().client_message()
+    }
+
+    /// Whether the upstream could not be reached or dropped the exchange
+    /// (connect, reset, DNS, TLS, truncated response or timeout). These are
+    /// the failures worth retrying on another attempt.
+    pub fn is_upstream_failure(&self) -> bool {
+        matches!(
+            self,
+            Error::UpstreamConnection(_)
+                | Error::UpstreamRefused(_)
+                | Error::UpstreamReset(_)
+                | Error::UpstreamIncompleteResponse(_)
+                | Error::UpstreamDns(_)
+                | Error::UpstreamTls(_)
+                | Error::UpstreamTimeout
+        )
+    }
+
+    /// An upstream's error response, passed through to the client
+    pub fn upstream(
+        status: http::StatusCode,
+        headers: http::HeaderMap,
+        body: impl Into<bytes::Bytes>,
+    ) -> Self {
+        Error::Upstream {
+            status,
+            headers: Box::new(headers),
+            body: body.into(),
+        }
+    }
+
+    /// The response to send for an [`Error::Upstream`]: the upstream's
+    /// status, headers and body, unchanged. `None` for every gateway error,
+    /// which answers with its [`ErrorResponse`](crate::ErrorResponse).
+    pub fn upstream_response(&self) -> Option<http::Response<http_body_util::Full<bytes::Bytes>>> {
+        let Error::Upstream {
+            status,
+            headers,
+            body,
+        } = self
+        else {
+            return None;
+        };
+        let mut response = http::Response::new(http_body_util::Full::new(body.clone()));
+        *response.status_mut() = *status;
+        *response.headers_mut() = (**headers).clone();
+        Some(response)
+    }
+
     /// Create a plugin error
     pub fn plugin(plugin: impl Into<String>, message: impl Into<String>) -> Self {
         Error::Plugin {
@@ -147,6 +463,208 @@ mod tests {
         );
     }
 
+    #[test]
+    fn every_variant_has_code_status_and_sanitized_message() {
+        // Every payload carries an internal detail that must stay out of the
+        // client message.
+        const SECRET: &str = "10.0.0.7:8080";
+        let io = std::io::Error::other(SECRET);
+        let serde = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let http = http::Response::builder()
+            .header("bad\nname", SECRET)
+            .body(())
+            .unwrap_err();
+
+        // `Error::Http` wraps a `hyper::Error`, which has no public
+        // constructor; it shares `InvalidRequest`'s row.
+        let cases = [
+            (
+                Error::InvalidRequest(SECRET.into()),
+                "INVALID_REQUEST",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Error::RouteNotFound(SECRET.into()),
+                "ROUTE_NOT_FOUND",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                Error::UpstreamConnection(SECRET.into()),
+                "UPSTREAM_UNAVAILABLE",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamRefused(SECRET.into()),
+                "UPSTREAM_REFUSED",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamReset(SECRET.into()),
+                "UPSTREAM_RESET",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamIncompleteResponse(SECRET.into()),
+                "UPSTREAM_INCOMPLETE_RESPONSE",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamResponseTooLarge(1 << 20),
+                "UPSTREAM_RESPONSE_TOO_LARGE",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamDns(SECRET.into()),
+                "UPSTREAM_DNS_FAILURE",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamTls(SECRET.into()),
+                "UPSTREAM_TLS_FAILURE",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::UpstreamTimeout,
+                "UPSTREAM_TIMEOUT",
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (
+                Error::DeadlineExceeded,
+                "DEADLINE_EXCEEDED",
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (
+                Error::NoHealthyUpstream,
+                "NO_HEALTHY_UPSTREAM",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                Error::upstream(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    http::HeaderMap::new(),
+                    SECRET,
+                ),
+                "UPSTREAM_STATUS",
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                Error::Config(SECRET.into()),
+                "CONFIG_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::plugin("jwt-auth", SECRET),
+                "PLUGIN_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Middleware(SECRET.into()),
+                "MIDDLEWARE_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Authentication(SECRET.into()),
+                "UNAUTHENTICATED",
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                Error::Authorization(SECRET.into()),
+                "FORBIDDEN",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Error::RateLimitExceeded,
+                "RATE_LIMITED",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                Error::CircuitBreakerOpen(SECRET.into()),
+                "CIRCUIT_OPEN",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                Error::Farp(SECRET.into()),
+                "FARP_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Schema(SECRET.into()),
+                "SCHEMA_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Discovery(SECRET.into()),
+                "DISCOVERY_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Runtime(SECRET.into()),
+                "INTERNAL_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Serialization(serde),
+                "SERIALIZATION_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (Error::Io(io), "IO_ERROR", StatusCode::INTERNAL_SERVER_ERROR),
+            (
+                Error::HttpError(http),
+                "INTERNAL_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Generic(SECRET.into()),
+                "INTERNAL_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Error::Internal(SECRET.into()),
+                "INTERNAL_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (err, code, status) in cases {
+            assert_eq!(err.code().as_str(), code, "{err:?}");
+            assert_eq!(err.to_status_code(), status, "{err:?}");
+            let message = err.client_message();
+            assert!(!message.is_empty());
+            assert!(!message.contains(SECRET), "{err:?} leaks its payload");
+            assert!(!message.contains("line 1"), "{err:?} leaks parser output");
+        }
+    }
+
+    #[test]
+    fn server_errors_share_a_generic_message() {
+        assert_eq!(
+            Error::Config("db password missing".into()).client_message(),
+            "Internal server error"
+        );
+        assert_eq!(ErrorCode::RouteNotFound.to_string(), "ROUTE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn upstream_error_status_passes_through_unchanged() {
+        use http_body_util::BodyExt;
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert("x-upstream-trace", "abc123".parse().unwrap());
+        let err = Error::upstream(StatusCode::UNAUTHORIZED, headers, "token expired");
+        assert!(!err.is_upstream_failure());
+
+        let response = err.upstream_response().unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["x-upstream-trace"], "abc123");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "token expired");
+
+        // Gateway failures have no upstream response to pass on
+        assert!(Error::UpstreamRefused("10.0.0.7:8080".into())
+            .upstream_response()
+            .is_none());
+    }
+
     #[test]
     fn test_plugin_error() {
         let err = Error::plugin("jwt-auth", "invalid signature");
@@ -0,0 +1,110 @@
+//! IP address patterns: exact addresses, CIDR blocks and inclusive ranges.
+//!
+//! Used wherever config lists client or proxy addresses (IP filter, trusted
+//! proxies, maintenance bypass). Parse once when the config is loaded and
+//! match the parsed patterns per request.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// IP pattern for matching
+#[derive(Debug, Clone)]
+pub enum IpPattern {
+    /// Exact IP address match
+    Exact(IpAddr),
+    /// CIDR range match (base address + prefix length)
+    Cidr(IpAddr, u8),
+    /// IP range match (inclusive start to inclusive end)
+    Range(IpAddr, IpAddr),
+}
+
+impl IpPattern {
+    /// Parse a string into an IpPattern.
+    ///
+    /// Supports:
+    /// - Exact: "192.168.1.1"
+    /// - CIDR: "192.168.1.0/24"
+    /// - Range: "192.168.1.1-192.168.1.254"
+    pub fn parse(s: &str) -> std::result::Result<Self, String> {
+        if let Some((base, prefix)) = s.split_once('/') {
+            let addr = IpAddr::from_str(base).map_err(|e| format!("invalid IP in CIDR: {e}"))?;
+            let prefix_len: u8 = prefix
+                .parse()
+                .map_err(|e| format!("invalid prefix length: {e}"))?;
+            let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+            if prefix_len > max_prefix {
+                return Err(format!(
+                    "prefix length {prefix_len} exceeds maximum {max_prefix}"
+                ));
+            }
+            Ok(IpPattern::Cidr(addr, prefix_len))
+        } else if let Some((start, end)) = s.split_once('-') {
+            let start_addr =
+                IpAddr::from_str(start).map_err(|e| format!("invalid start IP: {e}"))?;
+            let end_addr = IpAddr::from_str(end).map_err(|e| format!("invalid end IP: {e}"))?;
+            Ok(IpPattern::Range(start_addr, end_addr))
+        } else {
+            let addr = IpAddr::from_str(s).map_err(|e| format!("invalid IP address: {e}"))?;
+            Ok(IpPattern::Exact(addr))
+        }
+    }
+
+    /// Check if an IP address matches this pattern
+    pub fn matches(&self, ip: &IpAddr) -> bool {
+        match self {
+            IpPattern::Exact(pattern_ip) => ip == pattern_ip,
+            IpPattern::Cidr(base, prefix_len) => cidr_matches(base, *prefix_len, ip),
+            IpPattern::Range(start, end) => range_matches(start, end, ip),
+        }
+    }
+}
+
+/// Check if an IP is within a CIDR range
+fn cidr_matches(base: &IpAddr, prefix_len: u8, candidate: &IpAddr) -> bool {
+    match (base, candidate) {
+        (IpAddr::V4(base_v4), IpAddr::V4(cand_v4)) => {
+            if prefix_len == 0 {
+                return true;
+            }
+            if prefix_len >= 32 {
+                return base_v4 == cand_v4;
+            }
+            let base_bits = u32::from(*base_v4);
+            let cand_bits = u32::from(*cand_v4);
+            let mask = !0u32 << (32 - prefix_len);
+            (base_bits & mask) == (cand_bits & mask)
+        }
+        (IpAddr::V6(base_v6), IpAddr::V6(cand_v6)) => {
+            if prefix_len == 0 {
+                return true;
+            }
+            if prefix_len >= 128 {
+                return base_v6 == cand_v6;
+            }
+            let base_bits = u128::from(*base_v6);
+            let cand_bits = u128::from(*cand_v6);
+            let mask = !0u128 << (128 - prefix_len);
+            (base_bits & mask) == (cand_bits & mask)
+        }
+        _ => false, // IPv4 vs IPv6 mismatch
+    }
+}
+
+/// Check if an IP is within a range (inclusive)
+fn range_matches(start: &IpAddr, end: &IpAddr, candidate: &IpAddr) -> bool {
+    match (start, end, candidate) {
+        (IpAddr::V4(s), IpAddr::V4(e), IpAddr::V4(c)) => {
+            let s = u32::from(*s);
+            let e = u32::from(*e);
+            let c = u32::from(*c);
+            c >= s && c <= e
+        }
+        (IpAddr::V6(s), IpAddr::V6(e), IpAddr::V6(c)) => {
+            let s = u128::from(*s);
+            let e = u128::from(*e);
+            let c = u128::from(*c);
+            c >= s && c <= e
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,127 @@
+//! Paths into JSON values, and claims rendered as header values.
+//!
+//! A path is dot-separated (`realm_access.roles`) or JSONPath-style
+//! (`$.items[0].id`, `$['https://example.com/tenant']`). Body transforms,
+//! tenant resolution and claim forwarding all read values through [`get`],
+//! so a path means the same thing everywhere.
+
+use serde_json::Value;
+
+/// One step of a JSON path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Object key
+    Key(String),
+    /// Array index
+    Index(usize),
+}
+
+/// Split a path into segments. Accepts `a.b`, `$.a.b`, `$.items[0].id` and
+/// `$['a.b']`; `$` alone is the root (no segments).
+pub fn segments(path: &str) -> Vec<Segment> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let Some(end) = tail.find(']') else {
+                segments.push(Segment::Key(rest.to_string()));
+                break;
+            };
+            let inner = &tail[..end];
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+            match (quoted, inner.parse::<usize>()) {
+                (Some(key), _) => segments.push(Segment::Key(key.to_string())),
+                (None, Ok(index)) => segments.push(Segment::Index(index)),
+                (None, Err(_)) => segments.push(Segment::Key(inner.to_string())),
+            }
+            rest = &tail[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            segments.push(Segment::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+    }
+    segments
+}
+
+/// The value at `path` in `root`
+pub fn get<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    segments(path)
+        .iter()
+        .try_fold(root, |current, segment| match segment {
+            Segment::Key(key) => current.get(key.as_str()),
+            Segment::Index(index) => current.get(*index),
+        })
+}
+
+/// The claim at `path` in `claims` as a header value: scalars as text and
+/// arrays of scalars joined with `separator`. Absent claims, objects, nulls
+/// and arrays without scalars have none.
+pub fn claim_header_value(claims: &Value, path: &str, separator: &str) -> Option<String> {
+    fn scalar(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+    match get(claims, path)? {
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().filter_map(scalar).collect();
+            (!items.is_empty()).then(|| items.join(separator))
+        }
+        other => scalar(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_segments_jsonpath() {
+        assert_eq!(
+            segments("$.items[2]['a.b'].id"),
+            vec![
+                Segment::Key("items".to_string()),
+                Segment::Index(2),
+                Segment::Key("a.b".to_string()),
+                Segment::Key("id".to_string()),
+            ]
+        );
+        assert_eq!(segments("a.b"), segments("$.a.b"));
+        assert!(segments("$").is_empty());
+    }
+
+    #[test]
+    fn test_claim_header_value() {
+        let claims = json!({
+            "sub": "user-42",
+            "org": { "id": 7, "admin": true },
+            "realm_access": { "roles": ["admin", 3, { "nested": 1 }, null] },
+            "https://example.com/tenant": "acme",
+            "empty": [],
+        });
+        let value = |path| claim_header_value(&claims, path, ",");
+
+        assert_eq!(value("sub").as_deref(), Some("user-42"));
+        assert_eq!(value("org.id").as_deref(), Some("7"));
+        assert_eq!(value("$.org.admin").as_deref(), Some("true"));
+        assert_eq!(value("realm_access.roles").as_deref(), Some("admin,3"));
+        assert_eq!(
+            value("$['https://example.com/tenant']").as_deref(),
+            Some("acme")
+        );
+        assert_eq!(value("org"), None);
+        assert_eq!(value("empty"), None);
+        assert_eq!(value("missing"), None);
+    }
+}
@@ -17,29 +17,51 @@
 )]
 
 pub mod backend;
+pub mod cookie;
 pub mod error;
+pub mod ip_pattern;
+pub mod json_path;
+pub mod maintenance;
 pub mod middleware;
+pub mod problem;
+pub mod query;
+pub mod rate_limit;
 pub mod request;
+pub mod resolver;
 pub mod response;
+pub mod template;
 pub mod types;
 pub mod upstream;
 
 pub use backend::BackendWatcher;
-pub use error::{Error, Result};
-pub use middleware::{Body, Middleware, Next};
-pub use request::RequestContext;
-pub use response::ResponseBuilder;
+pub use cookie::{SameSite, SetCookie};
+pub use error::{Error, ErrorCode, Result};
+pub use ip_pattern::IpPattern;
+pub use maintenance::{MaintenanceMode, MaintenanceSettings};
+pub use middleware::{Body, Flow, Middleware, Next};
+pub use problem::{error_format, set_error_format, ErrorFormat, ErrorResponse, PROBLEM_JSON};
+pub use rate_limit::{RateLimitBucket, RateLimitExemptions, RateLimitKeys};
+pub use request::{
+    strip_port, AuthContext, Deadline, PathParams, RequestContext, ResponseBodyLimit,
+};
+pub use resolver::{CachedResolver, UpstreamResolver};
+pub use response::{ResponseBuilder, StreamEvents, StreamFormat, StreamedResponse};
+pub use template::Template;
 pub use types::*;
-pub use upstream::{UpstreamCluster, UpstreamInstance};
+pub use upstream::{UpstreamCluster, UpstreamInstance, UpstreamSelection};
 
 // Re-export commonly used HTTP types
 pub use bytes::Bytes;
 pub use http::{Method, Request, Response, StatusCode};
 
+/// `tracing` target of audit events: operator actions and audit logs
+pub const AUDIT_LOG_TARGET: &str = "octopus::audit";
+
 /// Re-export commonly used types
 pub mod prelude {
-    pub use crate::error::{Error, Result};
+    pub use crate::error::{Error, ErrorCode, Result};
     pub use crate::middleware::{Middleware, Next};
+    pub use crate::problem::ErrorResponse;
     pub use crate::request::RequestContext;
     pub use crate::response::ResponseBuilder;
     pub use crate::types::*;
@@ -0,0 +1,222 @@
+//! Runtime-toggleable maintenance mode.
+//!
+//! [`MaintenanceMode`] is shared between the request handler and the admin
+//! API: while it is enabled, data-plane requests to the covered paths are
+//! answered with a 503 and `Retry-After` instead of being proxied. Control
+//! plane endpoints (health probes, metrics, admin) are never covered.
+
+use crate::{Error, ErrorResponse, IpPattern, Result};
+use bytes::Bytes;
+use http::{header, Response, StatusCode};
+use http_body_util::Full;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Maintenance mode settings (`gateway.maintenance` and the admin API body).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct MaintenanceSettings {
+    /// Whether maintenance mode is on
+    pub enabled: bool,
+    /// Message returned in the 503 body
+    pub message: String,
+    /// Value of the `Retry-After` header
+    #[serde(with = "humantime_serde")]
+    pub retry_after: Duration,
+    /// Path prefixes in maintenance; empty = every data-plane path
+    pub routes: Vec<String>,
+    /// Client IPs/CIDRs/ranges that bypass maintenance (e.g. smoke tests)
+    pub bypass_ips: Vec<String>,
+    /// Path prefixes that bypass maintenance
+    pub bypass_paths: Vec<String>,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: "The service is down for maintenance. Please try again shortly.".to_string(),
+            retry_after: Duration::from_secs(300),
+            routes: Vec::new(),
+            bypass_ips: Vec::new(),
+            bypass_paths: Vec::new(),
+        }
+    }
+}
+
+impl MaintenanceSettings {
+    /// Whether a request to `path` is put in maintenance, ignoring the client
+    /// IP bypass (see [`MaintenanceMode::bypasses`]).
+    pub fn covers(&self, path: &str) -> bool {
+        self.enabled
+            && (self.routes.is_empty() || self.routes.iter().any(|p| path_has_prefix(path, p)))
+            && !self.bypass_paths.iter().any(|p| path_has_prefix(path, p))
+    }
+
+    /// The 503 maintenance response.
+    pub fn response(&self) -> Response<Full<Bytes>> {
+        ErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "maintenance")
+            .detail(self.message.clone())
+            .header(header::RETRY_AFTER, self.retry_after.as_secs().to_string())
+            .into_response()
+    }
+
+    /// Parse [`Self::bypass_ips`], failing on the first entry that isn't an
+    /// IP, CIDR or IP range
+    pub fn bypass_patterns(&self) -> Result<Vec<IpPattern>> {
+        self.bypass_ips
+            .iter()
+            .map(|pattern| {
+                IpPattern::parse(pattern).map_err(|_| {
+                    Error::Config(format!(
+                        "maintenance.bypass_ips entry {pattern:?} is not an IP, CIDR or IP range"
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Whether `path` is `prefix` or lies below it (`/api` covers `/api/users`
+/// but not `/apis`).
+fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Shared maintenance-mode switch
+#[derive(Debug, Default)]
+pub struct MaintenanceMode {
+    state: RwLock<State>,
+}
+
+/// The settings together with their bypass IPs, parsed when stored
+#[derive(Debug, Default)]
+struct State {
+    settings: MaintenanceSettings,
+    bypass_ips: Vec<IpPattern>,
+}
+
+impl MaintenanceMode {
+    /// Create with initial settings
+    pub fn new(settings: MaintenanceSettings) -> Result<Self> {
+        let mode = Self::default();
+        mode.set(settings)?;
+        Ok(mode)
+    }
+
+    /// Whether maintenance mode is on
+    pub fn is_enabled(&self) -> bool {
+        self.read().settings.enabled
+    }
+
+    /// Snapshot of the current settings
+    pub fn settings(&self) -> MaintenanceSettings {
+        self.read().settings.clone()
+    }
+
+    /// Whether requests from `ip` bypass maintenance
+    pub fn bypasses(&self, ip: &IpAddr) -> bool {
+        self.read().bypass_ips.iter().any(|p| p.matches(ip))
+    }
+
+    /// Replace the settings; settings with an invalid bypass IP are rejected
+    /// and the current ones kept
+    pub fn set(&self, settings: MaintenanceSettings) -> Result<()> {
+        let bypass_ips = settings.bypass_patterns()?;
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = State {
+            settings,
+            bypass_ips,
+        };
+        Ok(())
+    }
+
+    /// Turn maintenance mode on or off, keeping the other settings
+    pub fn set_enabled(&self, enabled: bool) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .settings
+            .enabled = enabled;
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_every_path_unless_scoped_or_bypassed() {
+        let mut settings = MaintenanceSettings::default();
+        assert!(!settings.covers("/api/users"));
+
+        settings.enabled = true;
+        assert!(settings.covers("/api/users"));
+
+        settings.routes = vec!["/api/orders/".to_string()];
+        settings.bypass_paths = vec!["/api/orders/smoke".to_string()];
+        assert!(settings.covers("/api/orders"));
+        assert!(settings.covers("/api/orders/42"));
+        assert!(!settings.covers("/api/ordersx"));
+        assert!(!settings.covers("/api/users"));
+        assert!(!settings.covers("/api/orders/smoke/check"));
+    }
+
+    #[test]
+    fn response_is_503_with_retry_after() {
+        let settings = MaintenanceSettings {
+            retry_after: Duration::from_secs(120),
+            ..Default::default()
+        };
+
+        let response = settings.response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "120");
+    }
+
+    #[test]
+    fn toggle_keeps_settings() {
+        let mode = MaintenanceMode::new(MaintenanceSettings {
+            message: "Back at 10:00 UTC".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        mode.set_enabled(true);
+
+        assert!(mode.is_enabled());
+        assert_eq!(mode.settings().message, "Back at 10:00 UTC");
+    }
+
+    #[test]
+    fn invalid_bypass_ip_keeps_current_settings() {
+        let mode = MaintenanceMode::new(MaintenanceSettings {
+            bypass_ips: vec!["10.1.2.0/24".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let err = mode
+            .set(MaintenanceSettings {
+                enabled: true,
+                bypass_ips: vec!["10.0.0.1".to_string(), "10.0.0.0/33".to_string()],
+                ..Default::default()
+            })
+            .unwrap_err();
+
+        assert!(err.to_string().contains("\"10.0.0.0/33\""), "{err}");
+        assert!(!mode.is_enabled());
+        assert!(mode.bypasses(&"10.1.2.3".parse().unwrap()));
+        assert!(!mode.bypasses(&"10.0.0.1".parse().unwrap()));
+    }
+}
@@ -12,6 +12,45 @@ use std::sync::Arc;
 pub type Body = Full<Bytes>;
 
 /// Middleware trait for request/response processing
+///
+/// A middleware sees the request on the way in and the response on the way
+/// out:
+///
+/// - **Short-circuit**: return a response without calling
+///   [`Next::run`]. Nothing after this middleware in the chain runs, and
+///   neither does the final handler (the proxy), so no upstream request is
+///   made. Rejections such as 401/403/429 and cached responses work this way.
+///   [`Flow`] expresses the decision as a value.
+/// - **Post-process**: `await` [`Next::run`] and modify the returned
+///   response (headers, status, body) before returning it. The response may
+///   come from the proxy or from a later middleware that short-circuited;
+///   errors from downstream can be handled or propagated with `?`.
+///
+/// ```
+/// use async_trait::async_trait;
+/// use http::{Request, Response, StatusCode};
+/// use octopus_core::middleware::{Body, Flow, Middleware, Next};
+/// use octopus_core::Result;
+///
+/// #[derive(Debug)]
+/// struct RequireApiKey;
+///
+/// #[async_trait]
+/// impl Middleware for RequireApiKey {
+///     async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+///         let flow = if req.headers().contains_key("x-api-key") {
+///             Flow::Continue(req)
+///         } else {
+///             let mut res = Response::new(Body::from("missing API key"));
+///             *res.status_mut() = StatusCode::UNAUTHORIZED;
+///             Flow::Respond(res)
+///         };
+///         let mut res = flow.run(next).await?;
+///         res.headers_mut().insert("x-checked-by", "api-key".parse().unwrap());
+///         Ok(res)
+///     }
+/// }
+/// ```
 #[async_trait]
 pub trait Middleware: Send + Sync + fmt::Debug {
     /// Process a request
@@ -25,6 +64,47 @@ pub trait Middleware: Send + Sync + fmt::Debug {
     ///
     /// Returns the HTTP response or an error
     async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>>;
+
+    /// Short name for introspection (e.g. the admin explain endpoint).
+    ///
+    /// Defaults to the implementing type's name without its module path or
+    /// generic parameters.
+    fn name(&self) -> &'static str {
+        let full = std::any::type_name::<Self>();
+        let base = full.split('<').next().unwrap_or(full);
+        base.rsplit("::").next().unwrap_or(base)
+    }
+
+    /// Do any expensive one-off preparation (compiling scripts, fetching
+    /// keys) ahead of the first request.
+    ///
+    /// Called once at startup when warmup is enabled. The default does
+    /// nothing; an error is logged and, unless warmup is configured to fail
+    /// on errors, startup carries on and the work happens lazily instead.
+    async fn warm_up(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A middleware's decision about a request before the rest of the chain.
+#[derive(Debug)]
+pub enum Flow {
+    /// Pass the (possibly modified) request to the next middleware
+    Continue(Request<Body>),
+    /// Short-circuit with this response; later middleware and the final
+    /// handler do not run
+    Respond(Response<Body>),
+}
+
+impl Flow {
+    /// Carry out the decision: run the rest of the chain, or return the
+    /// response as is.
+    pub async fn run(self, next: Next) -> Result<Response<Body>> {
+        match self {
+            Self::Continue(req) => next.run(req).await,
+            Self::Respond(res) => Ok(res),
+        }
+    }
 }
 
 /// Type alias for the final handler function
@@ -64,6 +144,9 @@ impl Next {
     }
 
     /// Run the next middleware or final handler
+    ///
+    /// Returns the downstream response for the caller to post-process. Not
+    /// calling this at all short-circuits the rest of the chain.
     pub async fn run(self, req: Request<Body>) -> Result<Response<Body>> {
         if let Some(middleware) = self.middleware_stack.get(self.index) {
             let next = Self {
@@ -136,6 +219,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_default_name_is_short_type_name() {
+        let mw: Arc<dyn Middleware> = Arc::new(TestMiddleware {
+            name: "first".to_string(),
+        });
+        assert_eq!(mw.name(), "TestMiddleware");
+    }
+
     #[tokio::test]
     async fn test_middleware_chain() {
         let middleware1 = Arc::new(TestMiddleware {
@@ -157,4 +248,101 @@ mod tests {
         let result = next.run(req).await;
         assert!(result.is_err()); // Should error at end of chain
     }
+
+    /// Records its name on the way in and appends it to `x-trace` on the
+    /// way out.
+    #[derive(Debug)]
+    struct Trace {
+        name: &'static str,
+        seen: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Trace {
+        async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+            self.seen.lock().unwrap().push(self.name);
+            let mut res = next.run(req).await?;
+            let trace = match res.headers().get("x-trace") {
+                Some(v) => format!("{},{}", v.to_str().unwrap(), self.name),
+                None => self.name.to_string(),
+            };
+            res.headers_mut().insert("x-trace", trace.parse().unwrap());
+            Ok(res)
+        }
+    }
+
+    /// Rejects requests without `x-allow` with a 403.
+    #[derive(Debug)]
+    struct Gate;
+
+    #[async_trait]
+    impl Middleware for Gate {
+        async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+            let flow = if req.headers().contains_key("x-allow") {
+                Flow::Continue(req)
+            } else {
+                let mut res = Response::new(Body::from("forbidden"));
+                *res.status_mut() = http::StatusCode::FORBIDDEN;
+                Flow::Respond(res)
+            };
+            flow.run(next).await
+        }
+    }
+
+    type Seen = Arc<std::sync::Mutex<Vec<&'static str>>>;
+
+    /// `outer -> Gate -> inner -> handler`, recording what ran.
+    fn gated_chain() -> (Next, Seen, Arc<std::sync::atomic::AtomicUsize>) {
+        let seen: Seen = Arc::default();
+        let proxied = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(Trace {
+                name: "outer",
+                seen: Arc::clone(&seen),
+            }) as Arc<dyn Middleware>,
+            Arc::new(Gate),
+            Arc::new(Trace {
+                name: "inner",
+                seen: Arc::clone(&seen),
+            }),
+        ]);
+        let counter = Arc::clone(&proxied);
+        let handler: HandlerFn = Box::new(move |_req| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async { Ok(Response::new(Body::from("upstream"))) })
+        });
+        (Next::with_handler(stack, handler), seen, proxied)
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit_skips_rest_of_chain_and_handler() {
+        let (next, seen, proxied) = gated_chain();
+        let req = Request::builder().uri("/").body(Body::from("")).unwrap();
+
+        let res = next.run(req).await.unwrap();
+
+        assert_eq!(res.status(), http::StatusCode::FORBIDDEN);
+        assert_eq!(*seen.lock().unwrap(), ["outer"]);
+        assert_eq!(proxied.load(std::sync::atomic::Ordering::SeqCst), 0);
+        // Middleware before the short-circuit still post-processes it.
+        assert_eq!(res.headers()["x-trace"], "outer");
+    }
+
+    #[tokio::test]
+    async fn test_post_process_sees_downstream_response() {
+        let (next, seen, proxied) = gated_chain();
+        let req = Request::builder()
+            .uri("/")
+            .header("x-allow", "1")
+            .body(Body::from(""))
+            .unwrap();
+
+        let res = next.run(req).await.unwrap();
+
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(*seen.lock().unwrap(), ["outer", "inner"]);
+        assert_eq!(proxied.load(std::sync::atomic::Ordering::SeqCst), 1);
+        // Responses unwind innermost first.
+        assert_eq!(res.headers()["x-trace"], "inner,outer");
+    }
 }
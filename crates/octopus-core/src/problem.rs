@@ -0,0 +1,346 @@
+//! RFC 7807 problem-details error responses.
+//!
+//! [`ErrorResponse`] is the single error body emitted by gateway components
+//! (handler, rate limiter, auth, FARP), so clients see one shape everywhere:
+//!
+//! ```json
+//! {
+//!   "type": "https://octopus.io/problems/rate-limit-exceeded",
+//!   "title": "Too Many Requests",
+//!   "status": 429,
+//!   "detail": "Rate limit exceeded",
+//!   "instance": "/api/users"
+//! }
+//! ```
+//!
+//! served as `application/problem+json`. Deployments with clients that parse
+//! the older `{"error": ..., "message": ...}` body can switch back with
+//! [`set_error_format`]`(`[`ErrorFormat::Legacy`]`)`.
+
+use crate::Error;
+use bytes::Bytes;
+use http::{header, HeaderName, Response, StatusCode};
+use http_body_util::Full;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Media type of RFC 7807 problem documents.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Base URI of the gateway's problem types; a problem's `type` is this base
+/// followed by its code in kebab-case.
+pub const PROBLEM_TYPE_BASE: &str = "https://octopus.io/problems/";
+
+/// Wire format of gateway-generated error bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorFormat {
+    /// RFC 7807 `application/problem+json` (the default).
+    #[default]
+    Problem,
+    /// The pre-RFC 7807 `{"error": ..., "message": ...}` JSON body.
+    Legacy,
+}
+
+static ERROR_FORMAT: AtomicU8 = AtomicU8::new(0);
+
+/// Set the process-wide error body format (`gateway.error_format`).
+pub fn set_error_format(format: ErrorFormat) {
+    let raw = match format {
+        ErrorFormat::Problem => 0,
+        ErrorFormat::Legacy => 1,
+    };
+    ERROR_FORMAT.store(raw, Ordering::Relaxed);
+}
+
+/// The process-wide error body format used by [`ErrorResponse::into_response`].
+pub fn error_format() -> ErrorFormat {
+    match ERROR_FORMAT.load(Ordering::Relaxed) {
+        1 => ErrorFormat::Legacy,
+        _ => ErrorFormat::Problem,
+    }
+}
+
+/// Builder for an RFC 7807 problem document and the response carrying it.
+///
+/// `code` is a stable snake_case identifier (e.g. `rate_limit_exceeded`): it
+/// names the problem `type` URI and is the `error` field of the legacy body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorResponse {
+    status: StatusCode,
+    code: String,
+    title: String,
+    detail: Option<String>,
+    instance: Option<String>,
+    extensions: Map<String, Value>,
+    headers: Vec<(HeaderName, String)>,
+}
+
+impl ErrorResponse {
+    /// Create a problem with `status` and `code`; the title defaults to the
+    /// status' canonical reason phrase.
+    pub fn new(status: StatusCode, code: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: code.into(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Map a gateway [`Error`] to its problem via the [`ErrorCode`](crate::ErrorCode) table.
+    ///
+    /// The `detail` is the code's client-safe message and the wire code is
+    /// added as a `code` extension; the error's own payload is never exposed,
+    /// so callers should log `err` for the details.
+    pub fn from_error(err: &Error) -> Self {
+        let code = err.code();
+        Self::new(code.status(), code.as_str().to_ascii_lowercase())
+            .detail(code.client_message())
+            .extension("code", code.as_str())
+    }
+
+    /// Set the short, human-readable summary of the problem type.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Set the explanation specific to this occurrence.
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Set the URI reference identifying this occurrence (usually the path).
+    pub fn instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Add an extension member (e.g. `retry_after`).
+    pub fn extension(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extensions.insert(key.into(), value.into());
+        self
+    }
+
+    /// Add a response header (e.g. `Retry-After`, `WWW-Authenticate`).
+    pub fn header(mut self, name: HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// HTTP status of the problem
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Stable snake_case problem code
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The problem `type` URI
+    pub fn type_uri(&self) -> String {
+        format!("{PROBLEM_TYPE_BASE}{}", self.code.replace('_', "-"))
+    }
+
+    /// Render the RFC 7807 problem document.
+    pub fn to_problem_json(&self) -> Value {
+        let mut doc = Map::new();
+        doc.insert("type".into(), self.type_uri().into());
+        doc.insert("title".into(), self.title.clone().into());
+        doc.insert("status".into(), self.status.as_u16().into());
+        if let Some(detail) = &self.detail {
+            doc.insert("detail".into(), detail.clone().into());
+        }
+        if let Some(instance) = &self.instance {
+            doc.insert("instance".into(), instance.clone().into());
+        }
+        for (key, value) in &self.extensions {
+            doc.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        Value::Object(doc)
+    }
+
+    /// Render the legacy `{"error": code, "message": ...}` body.
+    pub fn to_legacy_json(&self) -> Value {
+        let mut doc = Map::new();
+        doc.insert("error".into(), self.code.clone().into());
+        let message = self.detail.as_ref().unwrap_or(&self.title);
+        doc.insert("message".into(), message.clone().into());
+        for (key, value) in &self.extensions {
+            doc.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        Value::Object(doc)
+    }
+
+    /// Build the response in the process-wide [`error_format`].
+    pub fn into_response(self) -> Response<Full<Bytes>> {
+        self.into_response_with(error_format())
+    }
+
+    /// Build the response in an explicit `format`.
+    pub fn into_response_with(self, format: ErrorFormat) -> Response<Full<Bytes>> {
+        let (content_type, body) = match format {
+            ErrorFormat::Problem => (PROBLEM_JSON, self.to_problem_json()),
+            ErrorFormat::Legacy => ("application/json", self.to_legacy_json()),
+        };
+
+        let mut response = Response::builder()
+            .status(self.status)
+            .header(header::CONTENT_TYPE, content_type);
+        for (name, value) in &self.headers {
+            response = response.header(name, value.as_str());
+        }
+
+        response
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap_or_else(|_| {
+                // Only reachable with an invalid extra header value.
+                let mut fallback = Response::new(Full::new(Bytes::new()));
+                *fallback.status_mut() = self.status;
+                fallback
+            })
+    }
+}
+
+impl From<&Error> for ErrorResponse {
+    fn from(err: &Error) -> Self {
+        Self::from_error(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    async fn body_json(response: Response<Full<Bytes>>) -> Value {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn route_not_found_maps_to_404_problem() {
+        let err = Error::RouteNotFound("/missing".to_string());
+        let response = ErrorResponse::from(&err)
+            .instance("/missing")
+            .into_response_with(ErrorFormat::Problem);
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+        let doc = body_json(response).await;
+        assert_eq!(doc["type"], "https://octopus.io/problems/route-not-found");
+        assert_eq!(doc["title"], "Not Found");
+        assert_eq!(doc["status"], 404);
+        assert_eq!(doc["detail"], "Route not found");
+        assert_eq!(doc["code"], "ROUTE_NOT_FOUND");
+        assert_eq!(doc["instance"], "/missing");
+    }
+
+    #[test]
+    fn error_variants_map_to_stable_types_and_statuses() {
+        let cases = [
+            (
+                Error::InvalidRequest("bad".into()),
+                StatusCode::BAD_REQUEST,
+                "invalid-request",
+            ),
+            (
+                Error::Authentication("expired".into()),
+                StatusCode::UNAUTHORIZED,
+                "unauthenticated",
+            ),
+            (
+                Error::Authorization("denied".into()),
+                StatusCode::FORBIDDEN,
+                "forbidden",
+            ),
+            (
+                Error::RateLimitExceeded,
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate-limited",
+            ),
+            (
+                Error::NoHealthyUpstream,
+                StatusCode::SERVICE_UNAVAILABLE,
+                "no-healthy-upstream",
+            ),
+            (
+                Error::CircuitBreakerOpen("users".into()),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "circuit-open",
+            ),
+            (
+                Error::UpstreamConnection("10.0.0.7:8080 refused".into()),
+                StatusCode::BAD_GATEWAY,
+                "upstream-unavailable",
+            ),
+            (
+                Error::Config("missing key".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "config-error",
+            ),
+        ];
+
+        for (err, status, slug) in cases {
+            let doc = ErrorResponse::from(&err).to_problem_json();
+            assert_eq!(doc["status"], status.as_u16(), "{err:?}");
+            assert_eq!(doc["type"], format!("{PROBLEM_TYPE_BASE}{slug}"), "{err:?}");
+        }
+    }
+
+    #[test]
+    fn errors_never_expose_their_payload() {
+        for err in [
+            Error::UpstreamConnection("10.0.0.7:8080 refused".into()),
+            Error::Authentication("kid 10.0.0.7:8080 not in JWKS".into()),
+            Error::Internal("lock poisoned at 10.0.0.7:8080".into()),
+        ] {
+            let doc = ErrorResponse::from(&err).to_problem_json();
+            assert_eq!(doc["detail"], err.client_message(), "{err:?}");
+            assert!(!doc.to_string().contains("10.0.0.7"), "{err:?} leaked");
+        }
+    }
+
+    #[tokio::test]
+    async fn extensions_and_headers_are_emitted() {
+        let response = ErrorResponse::from(&Error::RateLimitExceeded)
+            .extension("retry_after", 30)
+            .header(header::RETRY_AFTER, "30")
+            .into_response_with(ErrorFormat::Problem);
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+        let doc = body_json(response).await;
+        assert_eq!(doc["retry_after"], 30);
+    }
+
+    #[tokio::test]
+    async fn legacy_format_keeps_error_and_message() {
+        let response = ErrorResponse::new(StatusCode::TOO_MANY_REQUESTS, "rate_limit_exceeded")
+            .detail("Slow down")
+            .extension("retry_after", 60)
+            .into_response_with(ErrorFormat::Legacy);
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let doc = body_json(response).await;
+        assert_eq!(doc["error"], "rate_limit_exceeded");
+        assert_eq!(doc["message"], "Slow down");
+        assert_eq!(doc["retry_after"], 60);
+        assert!(doc.get("type").is_none());
+    }
+
+    #[test]
+    fn extensions_cannot_override_standard_members() {
+        let doc = ErrorResponse::new(StatusCode::NOT_FOUND, "not_found")
+            .extension("status", 200)
+            .to_problem_json();
+        assert_eq!(doc["status"], 404);
+    }
+}
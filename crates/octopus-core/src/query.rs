@@ -0,0 +1,121 @@
+//! Query string parsing and rewriting.
+//!
+//! Parameters are handled as an ordered list of decoded `(name, value)`
+//! pairs, so repeated parameters (`?tag=a&tag=b`) keep every value and their
+//! order. A rewrite keeps the client's bytes for every parameter it leaves
+//! alone, so signed URLs survive; only added or changed parameters are
+//! encoded as `application/x-www-form-urlencoded`.
+
+use crate::{Error, Result};
+use http::uri::{PathAndQuery, Uri};
+
+/// Decoded `(name, value)` pairs of `query`, in order.
+pub fn query_pairs(query: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+/// Encode `pairs` as a query string (without the leading `?`).
+pub fn encode_query<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(pairs)
+        .finish()
+}
+
+/// Rewrite the query of `uri`: `f` edits the decoded pairs. Pairs `f` left
+/// untouched keep their original encoding, new or changed ones are encoded,
+/// and a query `f` did not change is returned as is. The path is kept as is
+/// and an empty result drops the `?`.
+pub fn rewrite_query(uri: &Uri, f: impl FnOnce(&mut Vec<(String, String)>)) -> Result<Uri> {
+    let mut original: Vec<(&str, (String, String))> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|raw| Some((raw, query_pairs(raw).pop()?)))
+        .collect();
+    let mut pairs: Vec<_> = original.iter().map(|(_, pair)| pair.clone()).collect();
+    f(&mut pairs);
+    if pairs.len() == original.len() && pairs.iter().zip(&original).all(|(a, (_, b))| a == b) {
+        return Ok(uri.clone());
+    }
+
+    let query = pairs
+        .iter()
+        .map(|pair| match original.iter().position(|(_, p)| p == pair) {
+            Some(i) => original.remove(i).0.to_string(),
+            None => encode_query([(pair.0.as_str(), pair.1.as_str())]),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    let path_and_query = if query.is_empty() {
+        uri.path().to_string()
+    } else {
+        format!("{}?{query}", uri.path())
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(
+        PathAndQuery::try_from(path_and_query)
+            .map_err(|e| Error::InvalidRequest(format!("invalid rewritten query: {e}")))?,
+    );
+    Uri::from_parts(parts).map_err(|e| Error::InvalidRequest(format!("invalid rewritten URI: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repeated_and_encoded_params() {
+        assert_eq!(
+            query_pairs("tag=a&tag=b&q=hello%20world&flag"),
+            vec![
+                ("tag".to_string(), "a".to_string()),
+                ("tag".to_string(), "b".to_string()),
+                ("q".to_string(), "hello world".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrite_keeps_path_and_encodes_new_params() {
+        let uri: Uri = "/api/search?q=a%26b&debug=1".parse().unwrap();
+
+        let rewritten = rewrite_query(&uri, |pairs| {
+            pairs.retain(|(n, _)| n != "debug");
+            pairs.push(("lang".to_string(), "en us".to_string()));
+        })
+        .unwrap();
+        assert_eq!(rewritten.path(), "/api/search");
+        assert_eq!(rewritten.query(), Some("q=a%26b&lang=en+us"));
+
+        let cleared = rewrite_query(&uri, Vec::clear).unwrap();
+        assert_eq!(cleared.to_string(), "/api/search");
+    }
+
+    #[test]
+    fn rewrite_keeps_scheme_and_authority() {
+        let uri: Uri = "http://example.com/x?a=1".parse().unwrap();
+        let rewritten = rewrite_query(&uri, |pairs| pairs[0].1 = "2".to_string()).unwrap();
+        assert_eq!(rewritten.to_string(), "http://example.com/x?a=2");
+    }
+
+    #[test]
+    fn rewrite_keeps_raw_bytes_of_untouched_params() {
+        let uri: Uri = "/dl?sig=a%20b%2Fc&path=x+y&debug=1&sig=a%20b%2Fc"
+            .parse()
+            .unwrap();
+
+        let rewritten = rewrite_query(&uri, |pairs| pairs.retain(|(n, _)| n != "debug")).unwrap();
+        assert_eq!(
+            rewritten.query(),
+            Some("sig=a%20b%2Fc&path=x+y&sig=a%20b%2Fc")
+        );
+
+        let unchanged: Uri = "/dl?a=%7E&&b".parse().unwrap();
+        let same = rewrite_query(&unchanged, |_| {}).unwrap();
+        assert_eq!(same, unchanged);
+    }
+}
@@ -0,0 +1,116 @@
+//! Per-key rate-limit state, shared between keyed limiters and the admin API.
+//!
+//! A keyed rate limiter implements [`RateLimitKeys`] so operators can see how
+//! much of a key's budget is left and, during incidents, refill it or let
+//! the key through unlimited for a while.
+
+use crate::{Error, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// One bucket a keyed limiter holds for a key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RateLimitBucket {
+    /// Route with its own limit, or `None` for the limiter-wide bucket
+    pub route: Option<String>,
+    /// Requests that would be let through right now
+    pub remaining: u32,
+    /// Bucket capacity (requests allowed per window or back-to-back)
+    pub limit: u32,
+    /// Time until the bucket is full again
+    #[serde(with = "humantime_serde")]
+    pub reset_after: Duration,
+}
+
+/// Inspect and override the per-key state of a keyed rate limiter
+#[async_trait]
+pub trait RateLimitKeys: Send + Sync + fmt::Debug {
+    /// The buckets held for `key`; empty when no request with that key has
+    /// been limited yet (its budget is untouched).
+    async fn buckets(&self, key: &str) -> Result<Vec<RateLimitBucket>>;
+
+    /// Refill every bucket of `key`, returning how many there were
+    async fn reset(&self, key: &str) -> Result<usize>;
+
+    /// Let requests with `key` through unlimited for `duration`. Fails with
+    /// [`Error::InvalidRequest`] when `duration` is too long to represent.
+    fn exempt(&self, key: &str, duration: Duration) -> Result<()>;
+
+    /// Lift `key`'s exemption, returning whether it had one
+    fn unexempt(&self, key: &str) -> bool;
+
+    /// Time left on `key`'s exemption, if it has one
+    fn exemption(&self, key: &str) -> Option<Duration>;
+}
+
+/// Keys let through unlimited until a deadline, for [`RateLimitKeys`]
+/// implementations
+#[derive(Debug, Default)]
+pub struct RateLimitExemptions {
+    until: RwLock<HashMap<String, Instant>>,
+}
+
+impl RateLimitExemptions {
+    /// Exempt `key` for `duration` from now
+    pub fn grant(&self, key: &str, duration: Duration) -> Result<()> {
+        let until = Instant::now().checked_add(duration).ok_or_else(|| {
+            Error::InvalidRequest(format!("Exemption of {duration:?} is too long"))
+        })?;
+        self.until
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_string(), until);
+        Ok(())
+    }
+
+    /// Lift `key`'s exemption, returning whether it had one
+    pub fn lift(&self, key: &str) -> bool {
+        self.until
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key)
+            .is_some()
+    }
+
+    /// Time left on `key`'s exemption, dropping it once expired
+    pub fn remaining(&self, key: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let until = *self
+            .until
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)?;
+        if until > now {
+            return Some(until - now);
+        }
+        let mut exemptions = self.until.write().unwrap_or_else(|e| e.into_inner());
+        if exemptions.get(key).is_some_and(|until| *until <= now) {
+            exemptions.remove(key);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exemption_too_long_to_represent_is_rejected() {
+        let exemptions = RateLimitExemptions::default();
+        assert!(matches!(
+            exemptions.grant("k", Duration::MAX),
+            Err(Error::InvalidRequest(_))
+        ));
+        assert_eq!(exemptions.remaining("k"), None);
+
+        exemptions.grant("k", Duration::from_secs(60)).unwrap();
+        assert!(exemptions.remaining("k").unwrap() > Duration::from_secs(59));
+        assert!(exemptions.lift("k"));
+        assert!(!exemptions.lift("k"));
+    }
+}
@@ -1,7 +1,9 @@
 //! Request context and utilities
 
+use http::Extensions;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 /// Context attached to each request
@@ -24,6 +26,9 @@ pub struct RequestContext {
 
     /// Authentication context (if authenticated)
     pub auth: Option<AuthContext>,
+
+    /// Typed values stages hand to later stages, one per type
+    pub extensions: Extensions,
 }
 
 impl RequestContext {
@@ -36,6 +41,7 @@ impl RequestContext {
             upstream: None,
             metadata: Arc::new(HashMap::new()),
             auth: None,
+            extensions: Extensions::new(),
         }
     }
 
@@ -60,6 +66,26 @@ impl RequestContext {
         metadata.insert(key.into(), value);
         self.metadata = Arc::new(metadata);
     }
+
+    /// Store a typed value, returning the previous value of that type
+    pub fn insert<T: Clone + Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.extensions.insert(value)
+    }
+
+    /// Get the value of type `T`, if one was stored
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    /// Get a mutable reference to the value of type `T`
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.extensions.get_mut()
+    }
+
+    /// Remove and return the value of type `T`
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.extensions.remove()
+    }
 }
 
 impl Default for RequestContext {
@@ -100,7 +126,13 @@ pub struct UpstreamInfo {
     pub weight: u32,
 }
 
-/// Authentication context
+/// Path parameters of the matched route (`/users/:id` → `id`), inserted
+/// into request extensions before the middleware chain runs.
+#[derive(Debug, Clone, Default)]
+pub struct PathParams(pub HashMap<String, String>);
+
+/// Authentication context, inserted into request extensions by the auth
+/// gateway once a request is authenticated.
 #[derive(Debug, Clone)]
 pub struct AuthContext {
     /// Subject (user ID, service account, etc.)
@@ -109,6 +141,9 @@ pub struct AuthContext {
     /// Authentication provider name
     pub provider: String,
 
+    /// Assigned roles
+    pub roles: Vec<String>,
+
     /// Scopes/permissions
     pub scopes: Vec<String>,
 
@@ -133,10 +168,72 @@ impl AuthContext {
     }
 }
 
+/// Deadline for the whole upstream exchange of a request, set when the
+/// request is received and shared by every attempt (retries included), so
+/// retrying never stretches a request past its timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// A deadline at `instant`.
+    pub fn at(instant: Instant) -> Self {
+        Self(instant)
+    }
+
+    /// A deadline `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    /// The instant the deadline passes.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Time left, or `None` once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_none()
+    }
+}
+
+/// Largest upstream response body buffered for a request, and what happens
+/// to a larger one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseBodyLimit {
+    /// Limit in bytes
+    pub max_bytes: usize,
+    /// Cut the body at the limit instead of failing the request with a 502;
+    /// compressed bodies fail regardless
+    pub truncate: bool,
+}
+
+/// `host[:port]` without the port; bracketed IPv6 literals keep their colons.
+pub fn strip_port(host: &str) -> &str {
+    match host.rfind(':') {
+        Some(pos) if !host[pos..].contains(']') => &host[..pos],
+        _ => host,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_strip_port() {
+        assert_eq!(strip_port("api.example.com:8443"), "api.example.com");
+        assert_eq!(strip_port("api.example.com"), "api.example.com");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+    }
+
     #[test]
     fn test_request_context() {
         let mut ctx = RequestContext::new();
@@ -146,11 +243,50 @@ mod tests {
         assert_eq!(ctx.param("user_id"), Some("123"));
     }
 
+    #[test]
+    fn test_typed_extensions() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Bucket(&'static str);
+        #[derive(Debug, Clone, PartialEq)]
+        struct Attempt(u32);
+
+        let mut ctx = RequestContext::new();
+        assert!(ctx.get::<Bucket>().is_none());
+
+        ctx.insert(Bucket("control"));
+        ctx.insert(Attempt(1));
+        // Same underlying representation, distinct type: no collision.
+        ctx.insert(7u32);
+
+        assert_eq!(ctx.get::<Bucket>(), Some(&Bucket("control")));
+        assert_eq!(ctx.get::<Attempt>(), Some(&Attempt(1)));
+        assert_eq!(ctx.get::<u32>(), Some(&7));
+
+        ctx.get_mut::<Attempt>().unwrap().0 += 1;
+        assert_eq!(ctx.insert(Bucket("variant")), Some(Bucket("control")));
+        assert_eq!(ctx.get::<Attempt>(), Some(&Attempt(2)));
+
+        // Clones carry the values along.
+        let cloned = ctx.clone();
+        assert_eq!(cloned.get::<Bucket>(), Some(&Bucket("variant")));
+
+        assert_eq!(ctx.remove::<u32>(), Some(7));
+        assert!(ctx.get::<u32>().is_none());
+        assert_eq!(ctx.get::<Attempt>(), Some(&Attempt(2)));
+    }
+
+    #[test]
+    fn test_request_context_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RequestContext>();
+    }
+
     #[test]
     fn test_auth_context_scopes() {
         let auth = AuthContext {
             subject: "user-123".to_string(),
             provider: "jwt".to_string(),
+            roles: Vec::new(),
             scopes: vec!["read".to_string(), "write".to_string()],
             claims: HashMap::new(),
         };
@@ -161,4 +297,16 @@ mod tests {
         assert!(auth.has_scopes(&["read", "write"]));
         assert!(!auth.has_scopes(&["read", "admin"]));
     }
+
+    #[test]
+    fn deadline_counts_down_to_expiry() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+        let remaining = deadline.remaining().unwrap();
+        assert!(remaining > Duration::from_secs(59) && remaining <= Duration::from_secs(60));
+        assert!(!deadline.is_expired());
+
+        let past = Deadline::at(Instant::now() - Duration::from_millis(1));
+        assert_eq!(past.remaining(), None);
+        assert!(past.is_expired());
+    }
 }
@@ -0,0 +1,218 @@
+//! Abstraction for resolving a logical service name to upstream instances.
+
+use crate::{Result, UpstreamInstance};
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Resolves the current instances of a service at request time, for routes
+/// that name a service instead of a statically registered upstream.
+///
+/// Discovery providers implement this (see `octopus_discovery`); wrap them
+/// in a [`CachedResolver`] so the data plane doesn't query the backend on
+/// every request.
+#[async_trait]
+pub trait UpstreamResolver: Send + Sync + fmt::Debug {
+    /// Current instances of `service`. An empty list means the service has
+    /// no available instances.
+    async fn resolve(&self, service: &str) -> Result<Vec<UpstreamInstance>>;
+}
+
+/// Caches another resolver's answers per service for `ttl`.
+///
+/// Concurrent lookups of a service that isn't cached share one query to the
+/// inner resolver. When a refresh fails the previous answer keeps being
+/// served (and is retried after another `ttl`), so a discovery backend
+/// outage doesn't take down services that were resolvable before it.
+pub struct CachedResolver<R> {
+    inner: R,
+    ttl: Duration,
+    entries: RwLock<HashMap<String, (Instant, Vec<UpstreamInstance>)>>,
+    /// Per-service lock held while its answer is refreshed
+    refreshing: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl<R> CachedResolver<R> {
+    /// Cache `inner`'s answers for `ttl`
+    pub fn new(inner: R, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            entries: RwLock::default(),
+            refreshing: Mutex::default(),
+        }
+    }
+
+    /// Drop the cached answer for `service`, so the next lookup resolves it
+    pub fn invalidate(&self, service: &str) {
+        self.entries.write().remove(service);
+    }
+
+    fn fresh(&self, service: &str) -> Option<Vec<UpstreamInstance>> {
+        let entries = self.entries.read();
+        let (resolved_at, instances) = entries.get(service)?;
+        (resolved_at.elapsed() < self.ttl).then(|| instances.clone())
+    }
+
+    fn store(&self, service: &str, instances: Vec<UpstreamInstance>) {
+        self.entries
+            .write()
+            .insert(service.to_string(), (Instant::now(), instances));
+    }
+
+    /// The lock serializing refreshes of `service`
+    fn refresh_lock(&self, service: &str) -> Arc<tokio::sync::Mutex<()>> {
+        Arc::clone(
+            self.refreshing
+                .lock()
+                .entry(service.to_string())
+                .or_default(),
+        )
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for CachedResolver<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedResolver")
+            .field("inner", &self.inner)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<R: UpstreamResolver> UpstreamResolver for CachedResolver<R> {
+    async fn resolve(&self, service: &str) -> Result<Vec<UpstreamInstance>> {
+        if let Some(instances) = self.fresh(service) {
+            return Ok(instances);
+        }
+        // One lookup per service at a time; whoever waited for it reads the
+        // answer it stored.
+        let lock = self.refresh_lock(service);
+        let _refreshing = lock.lock().await;
+        if let Some(instances) = self.fresh(service) {
+            return Ok(instances);
+        }
+        match self.inner.resolve(service).await {
+            Ok(instances) => {
+                self.store(service, instances.clone());
+                Ok(instances)
+            }
+            Err(e) => {
+                let stale = self
+                    .entries
+                    .read()
+                    .get(service)
+                    .map(|(_, instances)| instances.clone());
+                let Some(instances) = stale else {
+                    return Err(e);
+                };
+                tracing::warn!(service, error = %e, "Service resolution failed, serving cached instances");
+                self.store(service, instances.clone());
+                Ok(instances)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Answers from a list the test edits, counting lookups
+    #[derive(Debug, Default)]
+    struct MockResolver {
+        ports: Mutex<Option<Vec<u16>>>,
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl UpstreamResolver for MockResolver {
+        async fn resolve(&self, service: &str) -> Result<Vec<UpstreamInstance>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            let ports = self.ports.lock().unwrap().clone();
+            let ports = ports.ok_or_else(|| Error::Discovery("backend down".to_string()))?;
+            Ok(ports
+                .into_iter()
+                .map(|port| UpstreamInstance::new(format!("{service}-{port}"), "127.0.0.1", port))
+                .collect())
+        }
+    }
+
+    fn ports(instances: &[UpstreamInstance]) -> Vec<u16> {
+        instances.iter().map(|i| i.port).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn answers_are_cached_for_the_ttl() {
+        let mock = MockResolver::default();
+        *mock.ports.lock().unwrap() = Some(vec![8080]);
+        let cached = CachedResolver::new(mock, Duration::from_secs(5));
+
+        assert_eq!(ports(&cached.resolve("orders").await.unwrap()), [8080]);
+        *cached.inner.ports.lock().unwrap() = Some(vec![8080, 8081]);
+        assert_eq!(ports(&cached.resolve("orders").await.unwrap()), [8080]);
+        assert_eq!(cached.inner.lookups.load(Ordering::SeqCst), 1);
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(
+            ports(&cached.resolve("orders").await.unwrap()),
+            [8080, 8081]
+        );
+        assert_eq!(cached.inner.lookups.load(Ordering::SeqCst), 2);
+
+        *cached.inner.ports.lock().unwrap() = Some(vec![9090]);
+        cached.invalidate("orders");
+        assert_eq!(ports(&cached.resolve("orders").await.unwrap()), [9090]);
+    }
+
+    /// Answers after a delay, so concurrent lookups overlap
+    #[derive(Debug, Default)]
+    struct SlowResolver(MockResolver);
+
+    #[async_trait]
+    impl UpstreamResolver for SlowResolver {
+        async fn resolve(&self, service: &str) -> Result<Vec<UpstreamInstance>> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.0.resolve(service).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_misses_share_one_lookup() {
+        let slow = SlowResolver::default();
+        *slow.0.ports.lock().unwrap() = Some(vec![8080]);
+        let cached = Arc::new(CachedResolver::new(slow, Duration::from_secs(5)));
+
+        let lookups: Vec<_> = (0..8)
+            .map(|_| {
+                let cached = Arc::clone(&cached);
+                tokio::spawn(async move { cached.resolve("orders").await })
+            })
+            .collect();
+        for lookup in lookups {
+            assert_eq!(ports(&lookup.await.unwrap().unwrap()), [8080]);
+        }
+        assert_eq!(cached.inner.0.lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_refresh_serves_the_previous_answer() {
+        let cached = CachedResolver::new(MockResolver::default(), Duration::from_secs(5));
+        assert!(cached.resolve("orders").await.is_err());
+
+        *cached.inner.ports.lock().unwrap() = Some(vec![8080]);
+        assert_eq!(ports(&cached.resolve("orders").await.unwrap()), [8080]);
+
+        *cached.inner.ports.lock().unwrap() = None;
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(ports(&cached.resolve("orders").await.unwrap()), [8080]);
+    }
+}
@@ -1,10 +1,15 @@
 //! Response builder and utilities
 
-use crate::Result;
+use crate::problem::ErrorResponse;
+use crate::{Error, Result};
 use bytes::Bytes;
-use http::{header, Response, StatusCode};
+use http::{header, HeaderMap, Response, StatusCode};
+use http_body_util::combinators::BoxBody;
 use http_body_util::Full;
+use parking_lot::Mutex;
 use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
 
 /// Body type alias
 pub type Body = Full<Bytes>;
@@ -32,6 +37,7 @@ impl ResponseBuilder {
     }
 
     /// Set content type to JSON
+    #[deprecated(note = "use `json_body`, which sets the content type")]
     pub fn json(self) -> Self {
         self.header(header::CONTENT_TYPE, "application/json")
     }
@@ -48,7 +54,7 @@ impl ResponseBuilder {
     }
 
     /// Build response with text body
-    pub fn text(self, body: impl Into<String>) -> Result<Response<Body>> {
+    pub fn text_body(self, body: impl Into<String>) -> Result<Response<Body>> {
         let mut response = Response::builder().status(self.status);
 
         response = response.header(header::CONTENT_TYPE, "text/plain; charset=utf-8");
@@ -60,6 +66,12 @@ impl ResponseBuilder {
         Ok(response.body(Full::new(Bytes::from(body.into())))?)
     }
 
+    /// Build response with text body
+    #[deprecated(note = "use `text_body` or `ResponseBuilder::text_response`")]
+    pub fn text(self, body: impl Into<String>) -> Result<Response<Body>> {
+        self.text_body(body)
+    }
+
     /// Build response with JSON body
     pub fn json_body<T: Serialize>(self, body: &T) -> Result<Response<Body>> {
         let json = serde_json::to_string(body)?;
@@ -74,6 +86,46 @@ impl ResponseBuilder {
 
         Ok(response.body(Full::new(Bytes::from(json)))?)
     }
+
+    /// `status` response with `body` serialized as JSON. A body that fails
+    /// to serialize yields a `500` problem response instead (the serializer
+    /// error is logged, not returned to the client).
+    pub fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Result<Response<Body>> {
+        match Self::new(status).json_body(body) {
+            Err(Error::Serialization(e)) => {
+                tracing::error!(error = %e, "Failed to serialize JSON response body");
+                Ok(ErrorResponse::from_error(&Error::Serialization(e)).into_response())
+            }
+            other => other,
+        }
+    }
+
+    /// `text/plain` response with `body`
+    pub fn text_response(status: StatusCode, body: impl Into<String>) -> Result<Response<Body>> {
+        Self::new(status).text_body(body)
+    }
+
+    /// Redirect to `location`; `status` must be a 3xx code.
+    pub fn redirect(status: StatusCode, location: impl Into<String>) -> Result<Response<Body>> {
+        if !status.is_redirection() {
+            return Err(Error::Internal(format!(
+                "redirect status must be 3xx, got {status}"
+            )));
+        }
+        Self::new(status).header(header::LOCATION, location).build()
+    }
+
+    /// Problem-details error response (see [`ErrorResponse`]) with a stable
+    /// snake_case `code` and a client-facing `detail`.
+    pub fn problem(
+        status: StatusCode,
+        code: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Result<Response<Body>> {
+        Ok(ErrorResponse::new(status, code)
+            .detail(detail)
+            .into_response())
+    }
 }
 
 /// Convenience functions for common responses
@@ -97,42 +149,124 @@ pub mod responses {
 
     /// 400 Bad Request
     pub fn bad_request(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::BAD_REQUEST).text(message)
+        ResponseBuilder::text_response(StatusCode::BAD_REQUEST, message)
     }
 
     /// 401 Unauthorized
     pub fn unauthorized(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::UNAUTHORIZED).text(message)
+        ResponseBuilder::text_response(StatusCode::UNAUTHORIZED, message)
     }
 
     /// 403 Forbidden
     pub fn forbidden(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::FORBIDDEN).text(message)
+        ResponseBuilder::text_response(StatusCode::FORBIDDEN, message)
     }
 
     /// 404 Not Found
     pub fn not_found(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::NOT_FOUND).text(message)
+        ResponseBuilder::text_response(StatusCode::NOT_FOUND, message)
     }
 
     /// 429 Too Many Requests
     pub fn too_many_requests() -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::TOO_MANY_REQUESTS).text("Rate limit exceeded")
+        ResponseBuilder::text_response(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded")
     }
 
     /// 500 Internal Server Error
     pub fn internal_error(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).text(message)
+        ResponseBuilder::text_response(StatusCode::INTERNAL_SERVER_ERROR, message)
     }
 
     /// 502 Bad Gateway
     pub fn bad_gateway(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::BAD_GATEWAY).text(message)
+        ResponseBuilder::text_response(StatusCode::BAD_GATEWAY, message)
     }
 
     /// 503 Service Unavailable
     pub fn service_unavailable(message: impl Into<String>) -> Result<Response<Body>> {
-        ResponseBuilder::new(StatusCode::SERVICE_UNAVAILABLE).text(message)
+        ResponseBuilder::text_response(StatusCode::SERVICE_UNAVAILABLE, message)
+    }
+}
+
+/// Content type of Server-Sent Events streams
+pub const SSE_CONTENT_TYPE: &str = "text/event-stream";
+
+/// Content type of NDJSON streams
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// How a streamed body is split into events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// Server-Sent Events: blocks separated by a blank line, JSON in `data:`
+    EventStream,
+    /// Newline-delimited JSON: one value per line
+    Ndjson,
+}
+
+impl StreamFormat {
+    /// The format of a response with these headers, if it is a stream of
+    /// events
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let essence = content_type.split(';').next()?.trim();
+        if essence.eq_ignore_ascii_case(SSE_CONTENT_TYPE) {
+            Some(Self::EventStream)
+        } else if essence.eq_ignore_ascii_case(NDJSON_CONTENT_TYPE)
+            || essence.eq_ignore_ascii_case("application/ndjson")
+        {
+            Some(Self::Ndjson)
+        } else {
+            None
+        }
+    }
+}
+
+/// Body of a [`StreamedResponse`]
+pub type StreamingBody = BoxBody<Bytes, hyper::Error>;
+
+/// Request extension letting the proxy hand back an event-stream response
+/// (see [`StreamFormat`]) unread, as a [`StreamedResponse`], instead of
+/// buffering it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamEvents;
+
+/// Response extension holding an upstream body that streams to the client
+/// as it arrives.
+///
+/// The response itself carries an empty body through the middleware chain;
+/// middleware that stores or rewrites whole bodies passes such a response
+/// through untouched (see [`StreamedResponse::is_streamed`]).
+#[derive(Clone)]
+pub struct StreamedResponse(Arc<Mutex<Option<StreamingBody>>>);
+
+impl StreamedResponse {
+    /// Park `body` for the client
+    pub fn new(body: StreamingBody) -> Self {
+        Self(Arc::new(Mutex::new(Some(body))))
+    }
+
+    /// Take the body; `None` once taken
+    pub fn take(&self) -> Option<StreamingBody> {
+        self.0.lock().take()
+    }
+
+    /// Replace the parked body with `f` applied to it
+    pub fn map(&self, f: impl FnOnce(StreamingBody) -> StreamingBody) {
+        let mut body = self.0.lock();
+        *body = body.take().map(f);
+    }
+
+    /// Whether `response`'s body is streamed rather than buffered
+    pub fn is_streamed<B>(response: &Response<B>) -> bool {
+        response.extensions().get::<Self>().is_some()
+    }
+}
+
+impl fmt::Debug for StreamedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamedResponse")
+            .field("taken", &self.0.lock().is_none())
+            .finish()
     }
 }
 
@@ -141,6 +275,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(deprecated)]
     fn test_response_builder() {
         let response = ResponseBuilder::new(StatusCode::OK)
             .header(header::HeaderName::from_static("x-custom"), "value")
@@ -169,4 +304,78 @@ mod tests {
             "application/json"
         );
     }
+
+    async fn body_string(response: Response<Body>) -> String {
+        use http_body_util::BodyExt;
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn json_helper_serializes_body() {
+        let response =
+            ResponseBuilder::json_response(StatusCode::CREATED, &serde_json::json!({"id": 7}))
+                .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(body_string(response).await, r#"{"id":7}"#);
+    }
+
+    #[tokio::test]
+    async fn json_helper_maps_serialization_failure_to_500() {
+        // Maps with non-string keys cannot be serialized to JSON.
+        let body: std::collections::HashMap<(u8, u8), u8> = [((1, 2), 3)].into();
+
+        let response = ResponseBuilder::json_response(StatusCode::OK, &body).unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let text = body_string(response).await;
+        assert!(text.contains("SERIALIZATION_ERROR"), "{text}");
+        assert!(!text.contains("key must be a string"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn text_helper_sets_plain_content_type() {
+        let response = ResponseBuilder::text_response(StatusCode::BAD_REQUEST, "nope").unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(body_string(response).await, "nope");
+    }
+
+    #[test]
+    fn redirect_helper_sets_location_and_rejects_non_3xx() {
+        let response =
+            ResponseBuilder::redirect(StatusCode::PERMANENT_REDIRECT, "/users/").unwrap();
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], "/users/");
+
+        let err = ResponseBuilder::redirect(StatusCode::OK, "/users/").unwrap_err();
+        assert!(matches!(err, Error::Internal(_)));
+    }
+
+    #[test]
+    fn redirect_helper_rejects_invalid_location() {
+        let err = ResponseBuilder::redirect(StatusCode::FOUND, "/bad\nlocation").unwrap_err();
+        assert_eq!(err.to_status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn problem_helper_builds_problem_document() {
+        let response = ResponseBuilder::problem(
+            StatusCode::CONFLICT,
+            "route_exists",
+            "Route already registered",
+        )
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let text = body_string(response).await;
+        assert!(text.contains("route-exists"), "{text}");
+        assert!(text.contains("Route already registered"), "{text}");
+    }
 }
@@ -0,0 +1,255 @@
+//! Header value templates.
+//!
+//! A template is literal text with `{variable}` placeholders filled from the
+//! request: `"user={claim.sub} route={route_path}"`. Only the variables below
+//! exist, so a template can read request context but never run code. `{{` and
+//! `}}` produce literal braces.
+//!
+//! | Variable         | Value                                            |
+//! |------------------|--------------------------------------------------|
+//! | `now`            | Current time, Unix milliseconds                  |
+//! | `request_id`     | The `X-Request-ID` header                        |
+//! | `method`         | Request method                                   |
+//! | `path`           | Request path                                     |
+//! | `route_path`     | Matched route pattern ([`RouteInfo`])            |
+//! | `param.<name>`   | Captured path parameter ([`PathParams`])         |
+//! | `claim.<name>`   | Claim of the authenticated caller ([`AuthContext`]) |
+//! | `header.<name>`  | Another request header                           |
+//!
+//! [`RouteInfo`]: crate::request::RouteInfo
+
+use crate::request::{AuthContext, PathParams, RouteInfo};
+use crate::{Error, Result};
+use http::Request;
+
+/// A parsed header value template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Var(Var),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Var {
+    Now,
+    RequestId,
+    Method,
+    Path,
+    RoutePath,
+    Param(String),
+    Claim(String),
+    Header(String),
+}
+
+impl Var {
+    fn parse(name: &str) -> Result<Self> {
+        let named = |prefix: &str| {
+            name.strip_prefix(prefix)
+                .filter(|rest| !rest.is_empty())
+                .map(str::to_string)
+        };
+        Ok(match name {
+            "now" => Self::Now,
+            "request_id" => Self::RequestId,
+            "method" => Self::Method,
+            "path" => Self::Path,
+            "route_path" => Self::RoutePath,
+            _ => {
+                if let Some(param) = named("param.") {
+                    Self::Param(param)
+                } else if let Some(claim) = named("claim.") {
+                    Self::Claim(claim)
+                } else if let Some(header) = named("header.") {
+                    Self::Header(header.to_ascii_lowercase())
+                } else {
+                    return Err(Error::Config(format!(
+                        "unknown template variable '{{{name}}}'"
+                    )));
+                }
+            }
+        })
+    }
+
+    fn resolve<B>(&self, req: &Request<B>) -> Option<String> {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        match self {
+            Self::Now => std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_millis().to_string()),
+            Self::RequestId => header("x-request-id"),
+            Self::Method => Some(req.method().to_string()),
+            Self::Path => Some(req.uri().path().to_string()),
+            Self::RoutePath => req.extensions().get::<RouteInfo>().map(|r| r.path.clone()),
+            Self::Param(name) => req
+                .extensions()
+                .get::<PathParams>()
+                .and_then(|p| p.0.get(name).cloned()),
+            Self::Claim(name) => {
+                let auth = req.extensions().get::<AuthContext>()?;
+                match auth.get_claim(name) {
+                    Some(serde_json::Value::String(s)) => Some(s.clone()),
+                    Some(serde_json::Value::Null) => None,
+                    Some(other) => Some(other.to_string()),
+                    None if name == "sub" => Some(auth.subject.clone()),
+                    None => None,
+                }
+            }
+            Self::Header(name) => header(name),
+        }
+    }
+}
+
+impl Template {
+    /// Parse `source`, rejecting unknown variables and unbalanced braces.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(Error::Config(format!(
+                            "unclosed '{{' in template '{source}'"
+                        )));
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Var(Var::parse(name.trim())?));
+                }
+                '}' => {
+                    return Err(Error::Config(format!(
+                        "unmatched '}}' in template '{source}'"
+                    )));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Whether the template has no placeholders.
+    pub fn is_static(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|s| matches!(s, Segment::Literal(_)))
+    }
+
+    /// Fill the template from `req`, or `None` when a variable has no value
+    /// (e.g. `{claim.sub}` on an unauthenticated request).
+    pub fn render<B>(&self, req: &Request<B>) -> Option<String> {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Var(var) => out.push_str(&var.resolve(req)?),
+            }
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request() -> Request<()> {
+        let mut req = Request::builder()
+            .method("DELETE")
+            .uri("/users/42?x=1")
+            .header("X-Request-ID", "req-1")
+            .header("X-Tenant", "acme")
+            .body(())
+            .unwrap();
+        req.extensions_mut().insert(PathParams(HashMap::from([(
+            "id".to_string(),
+            "42".to_string(),
+        )])));
+        req.extensions_mut().insert(RouteInfo {
+            path: "/users/:id".to_string(),
+            method: "DELETE".to_string(),
+            operation_id: None,
+            tags: Vec::new(),
+        });
+        req
+    }
+
+    #[test]
+    fn renders_request_variables() {
+        let template = Template::parse(
+            "{method} {path} {route_path} id={param.id} tenant={header.X-Tenant} rid={request_id}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.render(&request()).unwrap(),
+            "DELETE /users/42 /users/:id id=42 tenant=acme rid=req-1"
+        );
+        assert!(!template.is_static());
+    }
+
+    #[test]
+    fn claims_fall_back_to_subject_and_missing_values_yield_none() {
+        let template = Template::parse("{claim.sub}/{claim.org}").unwrap();
+        assert_eq!(template.render(&request()), None);
+
+        let mut req = request();
+        req.extensions_mut().insert(AuthContext {
+            subject: "alice".to_string(),
+            provider: "jwt".to_string(),
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            claims: HashMap::from([("org".to_string(), serde_json::json!(7))]),
+        });
+        assert_eq!(template.render(&req).unwrap(), "alice/7");
+    }
+
+    #[test]
+    fn escapes_and_rejects_bad_templates() {
+        let template = Template::parse("{{literal}} {now}").unwrap();
+        let rendered = template.render(&request()).unwrap();
+        assert!(rendered.starts_with("{literal} "));
+        assert!(rendered[10..].parse::<u128>().is_ok());
+
+        assert!(Template::parse("plain").unwrap().is_static());
+        assert!(Template::parse("{env.HOME}").is_err());
+        assert!(Template::parse("{param.}").is_err());
+        assert!(Template::parse("{now").is_err());
+        assert!(Template::parse("now}").is_err());
+    }
+}
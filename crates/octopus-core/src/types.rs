@@ -21,6 +21,35 @@ pub enum LoadBalanceStrategy {
     IpHash,
 }
 
+/// Slow start: a newly added or recovered instance's share of traffic ramps
+/// up from `min_weight_percent` of its weight to all of it over `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowStartConfig {
+    /// How long the ramp-up takes
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+
+    /// Share of its weight an instance starts with, in percent
+    #[serde(default = "default_min_weight_percent")]
+    pub min_weight_percent: u32,
+}
+
+fn default_min_weight_percent() -> u32 {
+    10
+}
+
+impl SlowStartConfig {
+    /// Fraction of its weight (`0.0..=1.0`) an instance gets `elapsed`
+    /// after it became available; rises linearly over the window.
+    pub fn weight_factor(&self, elapsed: Duration) -> f64 {
+        if elapsed >= self.window {
+            return 1.0;
+        }
+        let min = f64::from(self.min_weight_percent.min(100)) / 100.0;
+        (elapsed.as_secs_f64() / self.window.as_secs_f64()).max(min)
+    }
+}
+
 /// Health check configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthCheckConfig {
@@ -162,6 +191,19 @@ impl Default for RetryPolicy {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_slow_start_weight_factor_ramps_linearly() {
+        let slow_start = SlowStartConfig {
+            window: Duration::from_secs(100),
+            min_weight_percent: 10,
+        };
+        assert_eq!(slow_start.weight_factor(Duration::ZERO), 0.1);
+        assert_eq!(slow_start.weight_factor(Duration::from_secs(5)), 0.1);
+        assert_eq!(slow_start.weight_factor(Duration::from_secs(50)), 0.5);
+        assert_eq!(slow_start.weight_factor(Duration::from_secs(100)), 1.0);
+        assert_eq!(slow_start.weight_factor(Duration::from_secs(500)), 1.0);
+    }
+
     #[test]
     fn test_load_balance_strategy_serde() {
         let strategy = LoadBalanceStrategy::RoundRobin;
@@ -1,9 +1,12 @@
 //! Upstream service definitions
 
-use crate::types::{CircuitBreakerConfig, HealthCheckConfig, LoadBalanceStrategy, TimeoutConfig};
+use crate::types::{
+    CircuitBreakerConfig, HealthCheckConfig, LoadBalanceStrategy, SlowStartConfig, TimeoutConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 
 /// Upstream service cluster
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +29,10 @@ pub struct UpstreamCluster {
 
     /// Timeout configuration
     pub timeout: TimeoutConfig,
+
+    /// Ramp-up of new and recovered instances' traffic
+    #[serde(default)]
+    pub slow_start: Option<SlowStartConfig>,
 }
 
 impl UpstreamCluster {
@@ -38,6 +45,7 @@ impl UpstreamCluster {
             health_check: HealthCheckConfig::default(),
             circuit_breaker: Some(CircuitBreakerConfig::default()),
             timeout: TimeoutConfig::default(),
+            slow_start: None,
         }
     }
 
@@ -194,6 +202,73 @@ impl UpstreamInstance {
     }
 }
 
+/// Which upstream served a request and how the upstream call went.
+///
+/// Inserted into response extensions by the proxy so logging and debugging
+/// layers can report it; a response served from cache carries one with
+/// `cache_hit` set and no instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpstreamSelection {
+    /// Upstream (cluster) name; empty if not known where it was recorded
+    pub upstream: String,
+    /// Id of the instance that answered
+    pub instance_id: String,
+    /// `host:port` of the instance that answered
+    pub address: String,
+    /// Time spent on the upstream call, including retries and backoff
+    pub latency: Duration,
+    /// Attempts made after the first one
+    pub retries: u32,
+    /// The response came from a cache, not the upstream
+    pub cache_hit: bool,
+    /// Upstream the request failed over from, when it had no instance to
+    /// take it
+    pub failover_from: Option<String>,
+}
+
+impl UpstreamSelection {
+    /// Selection of `instance`, answering after `latency` and `retries`.
+    pub fn new(instance: &UpstreamInstance, latency: Duration, retries: u32) -> Self {
+        Self {
+            upstream: String::new(),
+            instance_id: instance.id.clone(),
+            address: format!("{}:{}", instance.address, instance.port),
+            latency,
+            retries,
+            cache_hit: false,
+            failover_from: None,
+        }
+    }
+
+    /// A response served from cache.
+    pub fn cache_hit() -> Self {
+        Self {
+            cache_hit: true,
+            ..Self::default()
+        }
+    }
+
+    /// Compact form for an `X-Upstream` debug header, e.g.
+    /// `users; instance=users-1; addr=10.0.0.5:8080; upstream_ms=12; retries=0`.
+    pub fn header_value(&self) -> String {
+        if self.cache_hit {
+            return "cache".to_string();
+        }
+        let name = if self.upstream.is_empty() {
+            "-"
+        } else {
+            &self.upstream
+        };
+        format!(
+            "{name}; instance={}; addr={}; upstream_ms={}; retries={}",
+            self.instance_id,
+            self.address,
+            self.latency.as_millis(),
+            self.retries
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,4 +319,17 @@ mod tests {
         assert_eq!(i.base_url(), "http://127.0.0.1:8080");
         assert!(!i.is_tls());
     }
+
+    #[test]
+    fn upstream_selection_header_value() {
+        let instance = UpstreamInstance::new("users-1", "10.0.0.5", 8080);
+        let mut selection = UpstreamSelection::new(&instance, Duration::from_millis(12), 1);
+        selection.upstream = "users".to_string();
+
+        assert_eq!(
+            selection.header_value(),
+            "users; instance=users-1; addr=10.0.0.5:8080; upstream_ms=12; retries=1"
+        );
+        assert_eq!(UpstreamSelection::cache_hit().header_value(), "cache");
+    }
 }
@@ -21,11 +21,13 @@ pub mod kubernetes;
 #[cfg(feature = "mdns")]
 pub mod mdns;
 pub mod provider;
+pub mod resolver;
 
 pub use provider::{
     DiscoveryEvent, DiscoveryProvider, ServiceEndpoint, ServiceHealth, ServiceInstance,
     ServiceMetadata,
 };
+pub use resolver::DiscoveryResolver;
 
 #[cfg(feature = "consul")]
 pub use consul::ConsulDiscovery;
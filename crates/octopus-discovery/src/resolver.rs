@@ -0,0 +1,151 @@
+//! Request-time service resolution backed by discovery providers
+
+use crate::provider::{DiscoveryProvider, ServiceHealth, ServiceInstance};
+use async_trait::async_trait;
+use octopus_core::{Result, UpstreamInstance, UpstreamResolver};
+use std::sync::Arc;
+
+/// Resolves a service name to its instances through discovery providers.
+///
+/// Providers are asked in order and the first that knows the service
+/// answers; unhealthy instances are left out. Wrap it in an
+/// [`octopus_core::CachedResolver`] to avoid a lookup per request.
+#[derive(Debug, Clone)]
+pub struct DiscoveryResolver {
+    providers: Vec<Arc<dyn DiscoveryProvider>>,
+}
+
+impl DiscoveryResolver {
+    /// Resolve through `providers`, in order
+    pub fn new(providers: Vec<Arc<dyn DiscoveryProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+fn upstream_instance(instance: &ServiceInstance) -> UpstreamInstance {
+    let mut upstream = UpstreamInstance::new(&instance.id, &instance.address, instance.port);
+    upstream.metadata = instance.metadata.custom.clone();
+    if let Some(weight) = instance
+        .metadata
+        .custom
+        .get("weight")
+        .and_then(|w| w.parse().ok())
+    {
+        upstream.weight = weight;
+    }
+    upstream
+}
+
+#[async_trait]
+impl UpstreamResolver for DiscoveryResolver {
+    async fn resolve(&self, service: &str) -> Result<Vec<UpstreamInstance>> {
+        let mut failure = None;
+        for provider in &self.providers {
+            match provider.discover_service(service).await {
+                Ok(instances) if !instances.is_empty() => {
+                    return Ok(instances
+                        .iter()
+                        .filter(|i| i.health != ServiceHealth::Unhealthy)
+                        .map(upstream_instance)
+                        .collect());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::debug!(provider = provider.name(), service, error = %e, "Service lookup failed");
+                    failure = Some(e);
+                }
+            }
+        }
+        // Nothing found: an error if a provider couldn't answer, so a cache
+        // can keep serving what it had
+        failure.map_or_else(|| Ok(Vec::new()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{DiscoveryEvent, ServiceMetadata};
+    use octopus_core::Error;
+
+    #[derive(Debug)]
+    struct StaticProvider {
+        instances: Option<Vec<ServiceInstance>>,
+    }
+
+    #[async_trait]
+    impl DiscoveryProvider for StaticProvider {
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        async fn discover_services(&self) -> Result<Vec<ServiceInstance>> {
+            Ok(self.instances.clone().unwrap_or_default())
+        }
+
+        async fn discover_service(&self, service_name: &str) -> Result<Vec<ServiceInstance>> {
+            let instances = self
+                .instances
+                .clone()
+                .ok_or_else(|| Error::Discovery("unreachable".to_string()))?;
+            Ok(instances
+                .into_iter()
+                .filter(|i| i.name == service_name)
+                .collect())
+        }
+
+        async fn watch_services(
+            &self,
+            _callback: Box<dyn Fn(DiscoveryEvent) + Send + Sync>,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn instance(id: &str, port: u16, health: ServiceHealth) -> ServiceInstance {
+        let mut metadata = ServiceMetadata::default();
+        metadata
+            .custom
+            .insert("weight".to_string(), "3".to_string());
+        ServiceInstance {
+            id: id.to_string(),
+            name: "orders".to_string(),
+            address: "10.0.0.1".to_string(),
+            port,
+            health,
+            metadata,
+            endpoints: vec![],
+        }
+    }
+
+    fn provider(instances: Option<Vec<ServiceInstance>>) -> Arc<dyn DiscoveryProvider> {
+        Arc::new(StaticProvider { instances })
+    }
+
+    #[tokio::test]
+    async fn first_provider_knowing_the_service_answers() {
+        let resolver = DiscoveryResolver::new(vec![
+            provider(None),
+            provider(Some(vec![])),
+            provider(Some(vec![
+                instance("orders-1", 8080, ServiceHealth::Healthy),
+                instance("orders-2", 8081, ServiceHealth::Unhealthy),
+                instance("orders-3", 8082, ServiceHealth::Unknown),
+            ])),
+        ]);
+
+        let instances = resolver.resolve("orders").await.unwrap();
+        let ids: Vec<_> = instances.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, ["orders-1", "orders-3"]);
+        assert_eq!(instances[0].weight, 3);
+    }
+
+    #[tokio::test]
+    async fn unknown_service_is_empty_unless_a_provider_failed() {
+        let resolver = DiscoveryResolver::new(vec![provider(Some(vec![]))]);
+        assert!(resolver.resolve("orders").await.unwrap().is_empty());
+
+        let resolver = DiscoveryResolver::new(vec![provider(None), provider(Some(vec![]))]);
+        assert!(resolver.resolve("orders").await.is_err());
+    }
+}
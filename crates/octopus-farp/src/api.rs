@@ -10,7 +10,7 @@ use crate::validation::ManifestValidator;
 use bytes::Bytes;
 use http::{Method, Request, Response, StatusCode};
 use http_body_util::{BodyExt, Full};
-use octopus_core::{Error, Result};
+use octopus_core::{Error, ErrorResponse, ResponseBuilder, Result};
 use octopus_router::Router;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -638,14 +638,10 @@ impl FarpApiHandler {
         // return 404 with a re-register hint so the service pushes its full manifest.
         if self.registry.heartbeat(instance_id).is_err() {
             debug!(instance_id = %instance_id, "Heartbeat for unknown instance, requesting re-registration");
-            return self.json_response(
-                StatusCode::NOT_FOUND,
-                &serde_json::json!({
-                    "error": "instance not found",
-                    "action": "re-register",
-                    "message": "Gateway has no record of this instance. Please re-register with full manifest."
-                }),
-            );
+            return Ok(ErrorResponse::new(StatusCode::NOT_FOUND, "instance_not_found")
+                .detail("Gateway has no record of this instance. Please re-register with full manifest.")
+                .extension("action", "re-register")
+                .into_response());
         }
 
         // §17.4.1 Reconciliation: if service sent a checksum and it doesn't
@@ -760,9 +756,10 @@ impl FarpApiHandler {
                 &serde_json::json!({"status": "deregistered"}),
             )
         } else {
-            self.json_response(
-                StatusCode::NOT_FOUND,
-                &serde_json::json!({"error": "instance not found"}),
+            Ok(
+                ErrorResponse::new(StatusCode::NOT_FOUND, "instance_not_found")
+                    .detail(format!("Instance not found: {instance_id}"))
+                    .into_response(),
             )
         }
     }
@@ -1108,12 +1105,10 @@ impl FarpApiHandler {
             "graphql" => SchemaFormat::GraphQL,
             "grpc" => SchemaFormat::Grpc,
             _ => {
-                return self.json_response(
-                    StatusCode::BAD_REQUEST,
-                    &serde_json::json!({
-                        "error": "invalid_format",
-                        "message": format!("Unknown format: {}", format_str)
-                    }),
+                return Ok(
+                    ErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_format")
+                        .detail(format!("Unknown format: {format_str}"))
+                        .into_response(),
                 );
             }
         };
@@ -1131,13 +1126,9 @@ impl FarpApiHandler {
                     .body(Full::new(Bytes::from(schema.content)))
                     .map_err(|e| Error::Internal(format!("Failed to build response: {e}")))
             }
-            Err(e) => self.json_response(
-                StatusCode::NOT_FOUND,
-                &serde_json::json!({
-                    "error": "not_found",
-                    "message": e.to_string()
-                }),
-            ),
+            Err(e) => Ok(ErrorResponse::new(StatusCode::NOT_FOUND, "not_found")
+                .detail(e.to_string())
+                .into_response()),
         }
     }
 
@@ -1264,12 +1255,9 @@ impl FarpApiHandler {
 
     /// Return a 404 Not Found response
     fn not_found(&self) -> Result<Response<Full<Bytes>>> {
-        let response = serde_json::json!({
-            "error": "not_found",
-            "message": "Endpoint not found"
-        });
-
-        self.json_response(StatusCode::NOT_FOUND, &response)
+        Ok(ErrorResponse::new(StatusCode::NOT_FOUND, "not_found")
+            .detail("Endpoint not found")
+            .into_response())
     }
 
     /// Create a JSON response
@@ -1278,14 +1266,7 @@ impl FarpApiHandler {
         status: StatusCode,
         body: &T,
     ) -> Result<Response<Full<Bytes>>> {
-        let json = serde_json::to_string(body)
-            .map_err(|e| Error::Internal(format!("Failed to serialize response: {e}")))?;
-
-        Response::builder()
-            .status(status)
-            .header("content-type", "application/json")
-            .body(Full::new(Bytes::from(json)))
-            .map_err(|e| Error::Internal(format!("Failed to build response: {e}")))
+        ResponseBuilder::json_response(status, body)
     }
 }
 
@@ -1580,7 +1561,8 @@ mod tests {
 
         let body = res.into_body().collect().await.unwrap().to_bytes();
         let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
-        assert_eq!(json["error"], "invalid_format");
+        assert_eq!(json["type"], "https://octopus.io/problems/invalid-format");
+        assert_eq!(json["status"], 400);
     }
 
     #[tokio::test]
@@ -421,6 +421,13 @@ impl SchemaFederation {
             .ok_or_else(|| Error::Farp(format!("No federated schema for format: {format:?}")))
     }
 
+    /// When the federated schema for a format was last rebuilt, without
+    /// cloning its content
+    #[must_use]
+    pub fn updated_at(&self, format: &SchemaFormat) -> Option<std::time::SystemTime> {
+        self.federated.get(format).map(|f| f.updated_at)
+    }
+
     /// List all federated formats
     #[must_use]
     pub fn list_formats(&self) -> Vec<SchemaFormat> {
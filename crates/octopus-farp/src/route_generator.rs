@@ -4,6 +4,7 @@ use crate::schema::{SchemaDescriptor, SchemaFormat};
 use octopus_core::{Error, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::path::Path;
 
 /// Route generator for creating routes from schemas
 #[derive(Debug, Clone)]
@@ -108,11 +109,44 @@ impl RouteGenerator {
         }
     }
 
+    /// Generate routes for `upstream` from a local `OpenAPI` spec file (JSON
+    /// or YAML).
+    ///
+    /// Unlike schema-based generation, path templates are converted to router
+    /// parameters (`/users/{id}` becomes `/users/:id`), so the routes can be
+    /// registered as they are.
+    pub fn generate_from_openapi_file(
+        &self,
+        path: &Path,
+        upstream: &str,
+    ) -> Result<Vec<GeneratedRoute>> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            Error::Config(format!(
+                "failed to read OpenAPI file {}: {e}",
+                path.display()
+            ))
+        })?;
+        // YAML is a superset of JSON, so one parser reads both formats.
+        let spec: Value = serde_yaml::from_str(&content)
+            .map_err(|e| Error::Config(format!("invalid OpenAPI file {}: {e}", path.display())))?;
+
+        let mut routes = self.routes_from_openapi(&spec, upstream);
+        for route in &mut routes {
+            route.path = openapi_path_to_pattern(&route.path);
+        }
+        Ok(routes)
+    }
+
     /// Generate routes from `OpenAPI` schema
     fn generate_from_openapi(&self, schema: &SchemaDescriptor) -> Result<Vec<GeneratedRoute>> {
         let spec: Value = serde_json::from_str(&schema.content)
             .map_err(|e| Error::Farp(format!("Invalid OpenAPI schema: {e}")))?;
 
+        Ok(self.routes_from_openapi(&spec, &schema.service))
+    }
+
+    /// One route per operation under the spec's `paths`
+    fn routes_from_openapi(&self, spec: &Value, upstream: &str) -> Vec<GeneratedRoute> {
         let mut routes = Vec::new();
 
         if let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) {
@@ -131,7 +165,7 @@ impl RouteGenerator {
                         routes.push(GeneratedRoute {
                             method: method.to_uppercase(),
                             path: path.clone(),
-                            upstream: schema.service.clone(),
+                            upstream: upstream.to_string(),
                             metadata,
                         });
                     }
@@ -139,7 +173,7 @@ impl RouteGenerator {
             }
         }
 
-        Ok(routes)
+        routes
     }
 
     /// Extract metadata from `OpenAPI` operation
@@ -227,13 +261,13 @@ impl RouteGenerator {
         ])
     }
 
-    /// Apply prefix to all routes
+    /// Apply prefix to all routes (see [`octopus_router::join_prefix`])
     #[must_use]
     pub fn apply_prefix(routes: Vec<GeneratedRoute>, prefix: &str) -> Vec<GeneratedRoute> {
         routes
             .into_iter()
             .map(|mut route| {
-                route.path = format!("{}{}", prefix, route.path);
+                route.path = octopus_router::join_prefix(prefix, &route.path);
                 route
             })
             .collect()
@@ -249,6 +283,21 @@ impl RouteGenerator {
     }
 }
 
+/// Convert `OpenAPI` path templates to router parameters: `/users/{id}`
+/// becomes `/users/:id`. Segments that only partly template (`/{name}.json`)
+/// are kept as they are.
+fn openapi_path_to_pattern(path: &str) -> String {
+    path.split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) if !name.is_empty() && !name.contains(['{', '}']) => format!(":{name}"),
+                _ => segment.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 impl Default for RouteGenerator {
     fn default() -> Self {
         Self::new()
@@ -293,6 +342,54 @@ mod tests {
         assert!(routes[0].method == "GET" || routes[0].method == "POST");
     }
 
+    #[test]
+    fn test_openapi_file_route_generation() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/petstore.yaml");
+        let routes = RouteGenerator::new()
+            .generate_from_openapi_file(&fixture, "pets")
+            .unwrap();
+
+        let mut found: Vec<(&str, &str)> = routes
+            .iter()
+            .map(|r| (r.method.as_str(), r.path.as_str()))
+            .collect();
+        found.sort_unstable();
+        assert_eq!(
+            found,
+            vec![
+                ("DELETE", "/pets/:petId"),
+                ("GET", "/pets"),
+                ("GET", "/pets/:petId"),
+                ("GET", "/pets/{petId}.json"),
+                ("POST", "/pets"),
+            ]
+        );
+        assert!(routes.iter().all(|r| r.upstream == "pets"));
+
+        let create = routes.iter().find(|r| r.method == "POST").unwrap();
+        assert_eq!(create.metadata.operation_id.as_deref(), Some("createPet"));
+        assert_eq!(create.metadata.tags, vec!["admin"]);
+        assert!(create.metadata.requires_auth);
+    }
+
+    #[test]
+    fn test_openapi_file_errors() {
+        let generator = RouteGenerator::new();
+        assert!(generator
+            .generate_from_openapi_file(Path::new("/nonexistent/openapi.yaml"), "svc")
+            .is_err());
+    }
+
+    #[test]
+    fn test_openapi_path_to_pattern() {
+        assert_eq!(openapi_path_to_pattern("/a/{id}/b/{x_y}"), "/a/:id/b/:x_y");
+        assert_eq!(
+            openapi_path_to_pattern("/files/{name}.json"),
+            "/files/{name}.json"
+        );
+        assert_eq!(openapi_path_to_pattern("/"), "/");
+    }
+
     #[test]
     fn test_apply_prefix() {
         let routes = vec![GeneratedRoute {
@@ -304,6 +401,14 @@ mod tests {
 
         let prefixed = RouteGenerator::apply_prefix(routes, "/api/v1");
         assert_eq!(prefixed[0].path, "/api/v1/users");
+
+        let root = vec![GeneratedRoute {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            upstream: "test".to_string(),
+            metadata: RouteMetadata::default(),
+        }];
+        assert_eq!(RouteGenerator::apply_prefix(root, "/api/")[0].path, "/api");
     }
 
     /// Helper to create a minimal `RouteDescriptor` for tests
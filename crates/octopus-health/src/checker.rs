@@ -492,6 +492,13 @@ impl HealthChecker {
     }
 }
 
+#[async_trait]
+impl HealthCheck for HealthChecker {
+    async fn check(&self, address: &str, port: u16) -> HealthCheckResult {
+        self.checker.check(address, port).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
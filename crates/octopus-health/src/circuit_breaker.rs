@@ -1,9 +1,11 @@
 //! Circuit breaker pattern implementation
 
 use dashmap::DashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use octopus_core::Error;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, info, warn};
 
 /// Circuit breaker state
@@ -17,6 +19,17 @@ pub enum CircuitState {
     HalfOpen,
 }
 
+impl CircuitState {
+    /// Numeric value for metrics gauges: 0 closed, 1 open, 2 half-open
+    pub fn as_gauge(self) -> u8 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::Open => 1,
+            CircuitState::HalfOpen => 2,
+        }
+    }
+}
+
 impl std::fmt::Display for CircuitState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -28,7 +41,7 @@ impl std::fmt::Display for CircuitState {
 }
 
 /// Circuit breaker configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CircuitBreakerConfig {
     /// Failure threshold (0.0 to 1.0) to open the circuit
     pub failure_threshold: f64,
@@ -38,6 +51,27 @@ pub struct CircuitBreakerConfig {
     pub open_timeout: Duration,
     /// Maximum number of requests allowed in half-open state
     pub half_open_max_requests: u32,
+    /// Response status codes counted as failures; any other status counts
+    /// as a success, so client errors such as 404 don't trip the circuit
+    pub failure_status_codes: Vec<u16>,
+    /// Whether connection errors (refused, reset, DNS, TLS) count as
+    /// failures; if not they are ignored
+    pub count_connection_errors: bool,
+    /// Whether timeouts count as failures; if not they are ignored
+    pub count_timeouts: bool,
+}
+
+impl CircuitBreakerConfig {
+    /// Whether `outcome` counts as a success or a failure, or `None` if it
+    /// is ignored
+    pub fn classify(&self, outcome: Outcome) -> Option<bool> {
+        match outcome {
+            Outcome::Status(status) => Some(!self.failure_status_codes.contains(&status)),
+            Outcome::ConnectionError => self.count_connection_errors.then_some(false),
+            Outcome::Timeout => self.count_timeouts.then_some(false),
+            Outcome::Local => None,
+        }
+    }
 }
 
 impl Default for CircuitBreakerConfig {
@@ -47,6 +81,42 @@ impl Default for CircuitBreakerConfig {
             min_requests: 10,
             open_timeout: Duration::from_secs(30),
             half_open_max_requests: 5,
+            failure_status_codes: (500..=599).collect(),
+            count_connection_errors: true,
+            count_timeouts: true,
+        }
+    }
+}
+
+/// Result of a request to an upstream instance, as seen by the breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The upstream responded with this status code
+    Status(u16),
+    /// The connection failed or broke before a response arrived
+    ConnectionError,
+    /// The upstream did not respond in time
+    Timeout,
+    /// The gateway failed the request for a reason of its own, such as a
+    /// response over the size limit or an open circuit; never counted
+    Local,
+}
+
+impl Outcome {
+    /// The outcome of a request that failed with `error`
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::UpstreamTimeout | Error::DeadlineExceeded => Self::Timeout,
+            Error::Upstream { status, .. } => Self::Status(status.as_u16()),
+            Error::Http(_)
+            | Error::Io(_)
+            | Error::UpstreamConnection(_)
+            | Error::UpstreamRefused(_)
+            | Error::UpstreamReset(_)
+            | Error::UpstreamIncompleteResponse(_)
+            | Error::UpstreamDns(_)
+            | Error::UpstreamTls(_) => Self::ConnectionError,
+            _ => Self::Local,
         }
     }
 }
@@ -61,6 +131,22 @@ struct CircuitBreakerInstance {
     total_count: AtomicU64,
     half_open_requests: AtomicU64,
     state_change_time: parking_lot::Mutex<Instant>,
+    /// Wall-clock time of the last state change, for display
+    last_transition: parking_lot::Mutex<SystemTime>,
+    /// Set while an operator has pinned the state; cleared by `reset`
+    manual: AtomicBool,
+    /// Times the circuit opened, over the breaker's lifetime
+    trips: AtomicU64,
+    /// Successes and failures over the breaker's lifetime; unlike the
+    /// counts above, never reset
+    lifetime_successes: AtomicU64,
+    lifetime_failures: AtomicU64,
+    /// State changes not yet passed to the listeners, queued while the
+    /// state's write lock is held so they are in the order they happened
+    transitions: parking_lot::Mutex<Vec<CircuitState>>,
+    /// Held while listeners are notified, so concurrent callers report
+    /// transitions one batch at a time, in order
+    notifying: parking_lot::Mutex<()>,
 }
 
 impl CircuitBreakerInstance {
@@ -74,6 +160,13 @@ impl CircuitBreakerInstance {
             total_count: AtomicU64::new(0),
             half_open_requests: AtomicU64::new(0),
             state_change_time: parking_lot::Mutex::new(Instant::now()),
+            last_transition: parking_lot::Mutex::new(SystemTime::now()),
+            manual: AtomicBool::new(false),
+            trips: AtomicU64::new(0),
+            lifetime_successes: AtomicU64::new(0),
+            lifetime_failures: AtomicU64::new(0),
+            transitions: parking_lot::Mutex::default(),
+            notifying: parking_lot::Mutex::default(),
         }
     }
 
@@ -84,12 +177,13 @@ impl CircuitBreakerInstance {
 
     /// Record a successful request
     fn record_success(&self) {
+        self.lifetime_successes.fetch_add(1, Ordering::Relaxed);
         self.success_count.fetch_add(1, Ordering::Relaxed);
         self.total_count.fetch_add(1, Ordering::Relaxed);
 
         let current_state = self.state();
 
-        if current_state == CircuitState::HalfOpen {
+        if current_state == CircuitState::HalfOpen && !self.is_manual() {
             let success = self.success_count.load(Ordering::Relaxed);
             let total = self.total_count.load(Ordering::Relaxed);
 
@@ -112,27 +206,25 @@ impl CircuitBreakerInstance {
 
     /// Record a failed request
     fn record_failure(&self) {
+        self.lifetime_failures.fetch_add(1, Ordering::Relaxed);
         self.failure_count.fetch_add(1, Ordering::Relaxed);
         self.total_count.fetch_add(1, Ordering::Relaxed);
 
         let current_state = self.state();
         let total = self.total_count.load(Ordering::Relaxed);
 
-        // Check if we should open the circuit
-        if total >= self.config.min_requests {
-            let failure_rate = self.failure_count.load(Ordering::Relaxed) as f64 / total as f64;
-
-            if failure_rate >= self.config.failure_threshold {
-                match current_state {
-                    CircuitState::Closed => {
-                        self.transition_to_open();
-                    }
-                    CircuitState::HalfOpen => {
-                        // If failures occur in half-open, go back to open
+        if !self.is_manual() {
+            match current_state {
+                // A failed probe reopens the circuit, however few were sent
+                CircuitState::HalfOpen => self.transition_to_open(),
+                CircuitState::Closed if total >= self.config.min_requests => {
+                    let failure_rate =
+                        self.failure_count.load(Ordering::Relaxed) as f64 / total as f64;
+                    if failure_rate >= self.config.failure_threshold {
                         self.transition_to_open();
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
 
@@ -148,6 +240,10 @@ impl CircuitBreakerInstance {
     fn allow_request(&self) -> bool {
         let current_state = self.state();
 
+        if self.is_manual() {
+            return current_state != CircuitState::Open;
+        }
+
         match current_state {
             CircuitState::Closed => true,
             CircuitState::Open => {
@@ -155,35 +251,91 @@ impl CircuitBreakerInstance {
                 let time_since_open = self.state_change_time.lock().elapsed();
                 if time_since_open >= self.config.open_timeout {
                     self.transition_to_half_open();
-                    true
+                    self.take_probe()
                 } else {
                     false
                 }
             }
+            // Allow limited requests in half-open state
+            CircuitState::HalfOpen => self.take_probe(),
+        }
+    }
+
+    /// Take a half-open probe slot, if one is left
+    fn take_probe(&self) -> bool {
+        let max = u64::from(self.config.half_open_max_requests);
+        self.half_open_requests
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .is_ok()
+    }
+
+    /// Give back the probe slot of a half-open request whose outcome is
+    /// ignored, so it neither uses up a probe nor holds the circuit half-open
+    fn release_probe(&self) {
+        if self.state() == CircuitState::HalfOpen && !self.is_manual() {
+            let _ =
+                self.half_open_requests
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        }
+    }
+
+    /// Whether requests are being rejected: open, and (unless pinned) still
+    /// within the open timeout. Unlike `allow_request`, never moves the
+    /// circuit to half-open.
+    fn rejects(&self) -> bool {
+        self.state() == CircuitState::Open
+            && (self.is_manual()
+                || self.state_change_time.lock().elapsed() < self.config.open_timeout)
+    }
+
+    /// Whether `allow_request` would let a request through now, without
+    /// counting one: closed, due to probe, or half-open with a probe slot
+    /// left
+    fn admits(&self) -> bool {
+        let state = self.state();
+        if self.is_manual() {
+            return state != CircuitState::Open;
+        }
+        match state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                self.config.half_open_max_requests > 0
+                    && self.state_change_time.lock().elapsed() >= self.config.open_timeout
+            }
             CircuitState::HalfOpen => {
-                // Allow limited requests in half-open state
-                let current_requests = self.half_open_requests.fetch_add(1, Ordering::Relaxed);
-                current_requests < self.config.half_open_max_requests as u64
+                self.half_open_requests.load(Ordering::Relaxed)
+                    < u64::from(self.config.half_open_max_requests)
             }
         }
     }
 
+    /// Stamp the time of a change to `state` and queue it for the
+    /// listeners; called with the state's write lock held
+    fn mark_transition(&self, state: CircuitState) {
+        *self.state_change_time.lock() = Instant::now();
+        *self.last_transition.lock() = SystemTime::now();
+        self.transitions.lock().push(state);
+    }
+
     /// Transition to open state
     fn transition_to_open(&self) {
         let mut state = self.state.write();
         if *state != CircuitState::Open {
             *state = CircuitState::Open;
-            *self.state_change_time.lock() = Instant::now();
+            self.mark_transition(CircuitState::Open);
+            self.trips.fetch_add(1, Ordering::Relaxed);
             warn!("Circuit breaker transitioned to OPEN");
         }
     }
 
-    /// Transition to half-open state
+    /// Transition to half-open state, if still open
     fn transition_to_half_open(&self) {
         let mut state = self.state.write();
-        if *state != CircuitState::HalfOpen {
+        if *state == CircuitState::Open {
             *state = CircuitState::HalfOpen;
-            *self.state_change_time.lock() = Instant::now();
+            self.mark_transition(CircuitState::HalfOpen);
             self.half_open_requests.store(0, Ordering::Relaxed);
             // Reset counters for half-open testing
             self.success_count.store(0, Ordering::Relaxed);
@@ -193,12 +345,12 @@ impl CircuitBreakerInstance {
         }
     }
 
-    /// Transition to closed state
+    /// Transition to closed state, if still half-open
     fn transition_to_closed(&self) {
         let mut state = self.state.write();
-        if *state != CircuitState::Closed {
+        if *state == CircuitState::HalfOpen {
             *state = CircuitState::Closed;
-            *self.state_change_time.lock() = Instant::now();
+            self.mark_transition(CircuitState::Closed);
             // Reset counters
             self.success_count.store(0, Ordering::Relaxed);
             self.failure_count.store(0, Ordering::Relaxed);
@@ -208,10 +360,43 @@ impl CircuitBreakerInstance {
         }
     }
 
+    /// Whether the state is pinned by a manual override
+    fn is_manual(&self) -> bool {
+        self.manual.load(Ordering::Relaxed)
+    }
+
+    /// Pin the circuit to `target` until `reset`, ignoring automatic transitions
+    fn force(&self, target: CircuitState) {
+        let mut state = self.state.write();
+        self.manual.store(true, Ordering::Relaxed);
+        if *state != target {
+            *state = target;
+            self.mark_transition(target);
+            if target == CircuitState::Open {
+                self.trips.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.success_count.store(0, Ordering::Relaxed);
+        self.failure_count.store(0, Ordering::Relaxed);
+        self.total_count.store(0, Ordering::Relaxed);
+        self.half_open_requests.store(0, Ordering::Relaxed);
+        warn!(state = %target, "Circuit breaker manually forced");
+    }
+
     /// Reset the circuit breaker
     fn reset(&self) {
-        *self.state.write() = CircuitState::Closed;
-        *self.state_change_time.lock() = Instant::now();
+        self.manual.store(false, Ordering::Relaxed);
+        {
+            let mut state = self.state.write();
+            if *state != CircuitState::Closed {
+                *state = CircuitState::Closed;
+                self.mark_transition(CircuitState::Closed);
+            } else {
+                // Still closed: only the closed period starts over.
+                *self.state_change_time.lock() = Instant::now();
+                *self.last_transition.lock() = SystemTime::now();
+            }
+        }
         self.success_count.store(0, Ordering::Relaxed);
         self.failure_count.store(0, Ordering::Relaxed);
         self.total_count.store(0, Ordering::Relaxed);
@@ -223,6 +408,8 @@ impl CircuitBreakerInstance {
     fn metrics(&self) -> CircuitBreakerMetrics {
         CircuitBreakerMetrics {
             state: self.state(),
+            last_transition: *self.last_transition.lock(),
+            manual: self.is_manual(),
             success_count: self.success_count.load(Ordering::Relaxed),
             failure_count: self.failure_count.load(Ordering::Relaxed),
             total_count: self.total_count.load(Ordering::Relaxed),
@@ -232,6 +419,9 @@ impl CircuitBreakerInstance {
             } else {
                 0.0
             },
+            trips: self.trips.load(Ordering::Relaxed),
+            lifetime_successes: self.lifetime_successes.load(Ordering::Relaxed),
+            lifetime_failures: self.lifetime_failures.load(Ordering::Relaxed),
         }
     }
 }
@@ -241,6 +431,10 @@ impl CircuitBreakerInstance {
 pub struct CircuitBreakerMetrics {
     /// Current state
     pub state: CircuitState,
+    /// When the circuit last changed state (or was created/reset)
+    pub last_transition: SystemTime,
+    /// Whether the state is pinned by an operator override
+    pub manual: bool,
     /// Number of successful requests
     pub success_count: u64,
     /// Number of failed requests
@@ -249,13 +443,37 @@ pub struct CircuitBreakerMetrics {
     pub total_count: u64,
     /// Failure rate (0.0 to 1.0)
     pub failure_rate: f64,
+    /// Times the circuit has opened
+    pub trips: u64,
+    /// Successful requests since the breaker was created
+    pub lifetime_successes: u64,
+    /// Failed requests since the breaker was created
+    pub lifetime_failures: u64,
+}
+
+/// Callback invoked with the instance id and new state whenever a circuit
+/// changes state
+pub type TransitionListener = Arc<dyn Fn(&str, CircuitState) + Send + Sync>;
+
+#[derive(Default)]
+struct Listeners(parking_lot::RwLock<Vec<TransitionListener>>);
+
+impl std::fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Listeners")
+            .field("count", &self.0.read().len())
+            .finish()
+    }
 }
 
 /// Circuit breaker manager for multiple upstream instances
 #[derive(Debug, Clone)]
 pub struct CircuitBreaker {
     config: CircuitBreakerConfig,
+    /// Per-instance configs used instead of `config`
+    instance_configs: Arc<DashMap<String, CircuitBreakerConfig>>,
     instances: Arc<DashMap<String, Arc<CircuitBreakerInstance>>>,
+    listeners: Arc<Listeners>,
 }
 
 impl CircuitBreaker {
@@ -263,7 +481,9 @@ impl CircuitBreaker {
     pub fn new(config: CircuitBreakerConfig) -> Self {
         Self {
             config,
+            instance_configs: Arc::default(),
             instances: Arc::new(DashMap::new()),
+            listeners: Arc::default(),
         }
     }
 
@@ -272,30 +492,123 @@ impl CircuitBreaker {
         Self::new(CircuitBreakerConfig::default())
     }
 
+    /// Replace the per-instance configs used instead of the breaker-wide
+    /// one. An instance whose config changes starts over, closed.
+    pub fn set_instance_configs(&self, configs: HashMap<String, CircuitBreakerConfig>) {
+        self.instance_configs.retain(|instance_id, config| {
+            let keep = configs.get(instance_id) == Some(config);
+            if !keep {
+                self.instances.remove(instance_id);
+            }
+            keep
+        });
+        for (instance_id, config) in configs {
+            if !self.instance_configs.contains_key(&instance_id) {
+                self.instances.remove(&instance_id);
+                self.instance_configs.insert(instance_id, config);
+            }
+        }
+    }
+
+    /// The config `instance_id` is judged by
+    fn config_for(&self, instance_id: &str) -> CircuitBreakerConfig {
+        self.instance_configs
+            .get(instance_id)
+            .map_or_else(|| self.config.clone(), |config| config.clone())
+    }
+
+    /// Register a listener notified of every state change
+    pub fn on_transition(&self, listener: TransitionListener) {
+        self.listeners.0.write().push(listener);
+    }
+
+    /// Run `op` against an instance's breaker, then notify listeners of
+    /// every state change not yet reported. Changes are queued as they are
+    /// made, so each is reported once and in order even when concurrent
+    /// calls race on the same breaker.
+    fn observe<T>(
+        &self,
+        instance_id: &str,
+        instance: &CircuitBreakerInstance,
+        op: impl FnOnce(&CircuitBreakerInstance) -> T,
+    ) -> T {
+        let result = op(instance);
+        let _notifying = instance.notifying.lock();
+        let transitions = std::mem::take(&mut *instance.transitions.lock());
+        if !transitions.is_empty() {
+            let listeners = self.listeners.0.read();
+            for state in transitions {
+                for listener in listeners.iter() {
+                    listener(instance_id, state);
+                }
+            }
+        }
+        result
+    }
+
     /// Get or create circuit breaker for an instance
     fn get_or_create(&self, instance_id: &str) -> Arc<CircuitBreakerInstance> {
         self.instances
             .entry(instance_id.to_string())
-            .or_insert_with(|| Arc::new(CircuitBreakerInstance::new(self.config.clone())))
+            .or_insert_with(|| Arc::new(CircuitBreakerInstance::new(self.config_for(instance_id))))
             .clone()
     }
 
     /// Check if a request to an instance should be allowed
     pub fn allow_request(&self, instance_id: &str) -> bool {
         let instance = self.get_or_create(instance_id);
-        instance.allow_request()
+        self.observe(
+            instance_id,
+            &instance,
+            CircuitBreakerInstance::allow_request,
+        )
     }
 
     /// Record a successful request
     pub fn record_success(&self, instance_id: &str) {
         let instance = self.get_or_create(instance_id);
-        instance.record_success();
+        self.observe(
+            instance_id,
+            &instance,
+            CircuitBreakerInstance::record_success,
+        );
     }
 
     /// Record a failed request
     pub fn record_failure(&self, instance_id: &str) {
         let instance = self.get_or_create(instance_id);
-        instance.record_failure();
+        self.observe(
+            instance_id,
+            &instance,
+            CircuitBreakerInstance::record_failure,
+        );
+    }
+
+    /// Record a request's outcome as a success or failure according to the
+    /// configured failure classes. Ignored outcomes are not recorded; a
+    /// half-open circuit gets the probe slot back.
+    pub fn record(&self, instance_id: &str, outcome: Outcome) {
+        let verdict = match self.instances.get(instance_id) {
+            Some(instance) => instance.config.classify(outcome),
+            None => match self.instance_configs.get(instance_id) {
+                Some(config) => config.classify(outcome),
+                None => self.config.classify(outcome),
+            },
+        };
+        match verdict {
+            Some(true) => self.record_success(instance_id),
+            Some(false) => self.record_failure(instance_id),
+            None => {
+                if let Some(instance) = self.instances.get(instance_id) {
+                    instance.release_probe();
+                }
+                debug!(
+                    instance = instance_id,
+                    ?outcome,
+                    "Outcome ignored by circuit breaker"
+                );
+            }
+        }
     }
 
     /// Get the state of a circuit breaker
@@ -306,6 +619,24 @@ impl CircuitBreaker {
             .unwrap_or(CircuitState::Closed)
     }
 
+    /// Whether requests to an instance are currently rejected. Reading it
+    /// doesn't count as a request: a circuit due to probe the instance is
+    /// not open by this measure.
+    pub fn is_open(&self, instance_id: &str) -> bool {
+        self.instances
+            .get(instance_id)
+            .is_some_and(|inst| inst.rejects())
+    }
+
+    /// Whether a request to an instance would be let through now. Like
+    /// `is_open`, reading it doesn't count as a request, but a half-open
+    /// circuit whose probe slots are taken doesn't admit one.
+    pub fn admits(&self, instance_id: &str) -> bool {
+        self.instances
+            .get(instance_id)
+            .map_or(true, |inst| inst.admits())
+    }
+
     /// Get metrics for an instance
     pub fn get_metrics(&self, instance_id: &str) -> Option<CircuitBreakerMetrics> {
         self.instances.get(instance_id).map(|inst| inst.metrics())
@@ -319,17 +650,37 @@ impl CircuitBreaker {
             .collect()
     }
 
-    /// Reset a circuit breaker
+    /// Force an instance's circuit open: requests fast-fail until `reset`
+    pub fn force_open(&self, instance_id: &str) {
+        let instance = self.get_or_create(instance_id);
+        self.observe(instance_id, &instance, |i| i.force(CircuitState::Open));
+    }
+
+    /// Force an instance's circuit closed: requests pass regardless of
+    /// failures until `reset`
+    pub fn force_close(&self, instance_id: &str) {
+        let instance = self.get_or_create(instance_id);
+        self.observe(instance_id, &instance, |i| i.force(CircuitState::Closed));
+    }
+
+    /// Reset a circuit breaker, clearing any manual override
     pub fn reset(&self, instance_id: &str) {
-        if let Some(instance) = self.instances.get(instance_id) {
-            instance.reset();
+        // Release the map guard before listeners run; they may query states.
+        let instance = self.instances.get(instance_id).map(|i| Arc::clone(&i));
+        if let Some(instance) = instance {
+            self.observe(instance_id, &instance, CircuitBreakerInstance::reset);
         }
     }
 
     /// Reset all circuit breakers
     pub fn reset_all(&self) {
-        for entry in self.instances.iter() {
-            entry.value().reset();
+        let instances: Vec<_> = self
+            .instances
+            .iter()
+            .map(|entry| (entry.key().clone(), Arc::clone(entry.value())))
+            .collect();
+        for (instance_id, instance) in instances {
+            self.observe(&instance_id, &instance, CircuitBreakerInstance::reset);
         }
         info!("All circuit breakers reset");
     }
@@ -347,6 +698,7 @@ mod tests {
             min_requests: 5,
             open_timeout: Duration::from_secs(1),
             half_open_max_requests: 3,
+            ..Default::default()
         };
 
         let breaker = CircuitBreaker::new(config);
@@ -375,6 +727,7 @@ mod tests {
             min_requests: 5,
             open_timeout: Duration::from_millis(100),
             half_open_max_requests: 3,
+            ..Default::default()
         };
 
         let breaker = CircuitBreaker::new(config);
@@ -386,12 +739,26 @@ mod tests {
         }
         assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
 
+        assert!(breaker.is_open(instance_id));
+
         // Wait for open timeout
         sleep(Duration::from_millis(150));
+        assert!(!breaker.is_open(instance_id));
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
 
         // Should allow request and transition to half-open
         assert!(breaker.allow_request(instance_id));
         assert_eq!(breaker.get_state(instance_id), CircuitState::HalfOpen);
+
+        // Half-open admits only while a probe slot is left; the request
+        // that moved it there took the first.
+        for _ in 0..2 {
+            assert!(breaker.admits(instance_id));
+            assert!(breaker.allow_request(instance_id));
+        }
+        assert!(!breaker.is_open(instance_id));
+        assert!(!breaker.admits(instance_id));
+        assert!(!breaker.allow_request(instance_id));
     }
 
     #[test]
@@ -401,6 +768,7 @@ mod tests {
             min_requests: 3,
             open_timeout: Duration::from_millis(100),
             half_open_max_requests: 3,
+            ..Default::default()
         };
 
         let breaker = CircuitBreaker::new(config);
@@ -425,6 +793,57 @@ mod tests {
         assert_eq!(breaker.get_state(instance_id), CircuitState::Closed);
     }
 
+    #[test]
+    fn test_half_open_ignored_outcomes_release_probes() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            open_timeout: Duration::from_millis(50),
+            count_timeouts: false,
+            ..Default::default()
+        });
+        let instance_id = "test-instance";
+        for _ in 0..10 {
+            breaker.record_failure(instance_id);
+        }
+        sleep(Duration::from_millis(80));
+
+        // Probes ending in ignored outcomes give their slot back.
+        for _ in 0..10 {
+            assert!(breaker.allow_request(instance_id));
+            breaker.record(instance_id, Outcome::Local);
+            assert!(breaker.allow_request(instance_id));
+            breaker.record(instance_id, Outcome::Timeout);
+        }
+        assert_eq!(breaker.get_state(instance_id), CircuitState::HalfOpen);
+        assert!(breaker.admits(instance_id));
+
+        for _ in 0..5 {
+            assert!(breaker.allow_request(instance_id));
+            breaker.record(instance_id, Outcome::Status(200));
+        }
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_failed_half_open_probe_reopens() {
+        // Fewer probes (5) than `min_requests` (10)
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            open_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let instance_id = "test-instance";
+        for _ in 0..10 {
+            breaker.record_failure(instance_id);
+        }
+        sleep(Duration::from_millis(80));
+
+        assert!(breaker.allow_request(instance_id));
+        assert!(breaker.allow_request(instance_id));
+        breaker.record(instance_id, Outcome::Status(200));
+        breaker.record(instance_id, Outcome::Status(503));
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
+        assert!(breaker.is_open(instance_id));
+    }
+
     #[test]
     fn test_circuit_breaker_metrics() {
         let breaker = CircuitBreaker::default_config();
@@ -441,6 +860,136 @@ mod tests {
         assert_eq!(metrics.failure_rate, 1.0 / 3.0);
     }
 
+    #[test]
+    fn test_client_errors_do_not_trip() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 5,
+            ..Default::default()
+        });
+
+        for _ in 0..20 {
+            breaker.record("api-1", Outcome::Status(404));
+            breaker.record("api-1", Outcome::Status(400));
+        }
+        assert_eq!(breaker.get_state("api-1"), CircuitState::Closed);
+
+        for _ in 0..5 {
+            breaker.record("api-2", Outcome::Status(503));
+        }
+        assert_eq!(breaker.get_state("api-2"), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_failure_classes_are_configurable() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 2,
+            failure_status_codes: vec![502, 503],
+            count_timeouts: false,
+            ..Default::default()
+        });
+
+        breaker.record("api-1", Outcome::Status(500));
+        breaker.record("api-1", Outcome::Status(500));
+        assert_eq!(breaker.get_state("api-1"), CircuitState::Closed);
+
+        // Ignored outcomes aren't counted at all.
+        breaker.record("api-2", Outcome::Timeout);
+        breaker.record("api-2", Outcome::Timeout);
+        assert!(breaker.get_metrics("api-2").is_none());
+
+        breaker.record("api-2", Outcome::ConnectionError);
+        breaker.record("api-2", Outcome::Status(502));
+        assert_eq!(breaker.get_state("api-2"), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_outcome_from_error() {
+        assert_eq!(
+            Outcome::from_error(&Error::UpstreamTimeout),
+            Outcome::Timeout
+        );
+        assert_eq!(
+            Outcome::from_error(&Error::UpstreamRefused("api-1".into())),
+            Outcome::ConnectionError
+        );
+        assert_eq!(
+            Outcome::from_error(&Error::UpstreamTls("bad certificate".into())),
+            Outcome::ConnectionError
+        );
+        assert_eq!(
+            Outcome::from_error(&Error::Upstream {
+                status: http::StatusCode::BAD_GATEWAY,
+                headers: Default::default(),
+                body: Default::default(),
+            }),
+            Outcome::Status(502)
+        );
+        // The gateway's own failures say nothing about the upstream.
+        for local in [
+            Error::UpstreamResponseTooLarge(1024),
+            Error::CircuitBreakerOpen("api-1".into()),
+            Error::Internal("bug".into()),
+        ] {
+            assert_eq!(Outcome::from_error(&local), Outcome::Local);
+        }
+    }
+
+    #[test]
+    fn test_instance_configs_override_the_default() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 2,
+            ..Default::default()
+        });
+        let lenient = CircuitBreakerConfig {
+            min_requests: 2,
+            count_connection_errors: false,
+            ..Default::default()
+        };
+        breaker.record("strict", Outcome::ConnectionError);
+        breaker.record("lenient", Outcome::ConnectionError);
+        breaker.set_instance_configs(HashMap::from([("lenient".to_string(), lenient.clone())]));
+
+        // A changed config starts the instance over; an unchanged one doesn't.
+        assert!(breaker.get_metrics("lenient").is_none());
+        breaker.record("lenient", Outcome::ConnectionError);
+        breaker.record("lenient", Outcome::ConnectionError);
+        assert!(breaker.get_metrics("lenient").is_none());
+        breaker.record("strict", Outcome::ConnectionError);
+        assert_eq!(breaker.get_state("strict"), CircuitState::Open);
+
+        breaker.record("lenient", Outcome::Status(500));
+        breaker.set_instance_configs(HashMap::from([("lenient".to_string(), lenient)]));
+        assert_eq!(breaker.get_metrics("lenient").unwrap().failure_count, 1);
+
+        // Dropping the override falls back to the default.
+        breaker.set_instance_configs(HashMap::new());
+        breaker.record("lenient", Outcome::ConnectionError);
+        breaker.record("lenient", Outcome::ConnectionError);
+        assert_eq!(breaker.get_state("lenient"), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_lifetime_counts_survive_transitions() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 2,
+            ..Default::default()
+        });
+        let instance_id = "test-instance";
+
+        breaker.record_success(instance_id);
+        breaker.record_failure(instance_id);
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
+        breaker.reset(instance_id);
+        breaker.force_open(instance_id);
+        assert!(breaker.is_open(instance_id));
+
+        let metrics = breaker.get_metrics(instance_id).unwrap();
+        assert_eq!(metrics.trips, 2);
+        assert_eq!(metrics.total_count, 0);
+        assert_eq!(metrics.lifetime_successes, 1);
+        assert_eq!(metrics.lifetime_failures, 1);
+    }
+
     #[test]
     fn test_circuit_breaker_reset() {
         let breaker = CircuitBreaker::default_config();
@@ -456,6 +1005,157 @@ mod tests {
         assert_eq!(breaker.get_state(instance_id), CircuitState::Closed);
     }
 
+    #[test]
+    fn test_metrics_track_last_transition() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 0.5,
+            min_requests: 2,
+            open_timeout: Duration::from_secs(30),
+            half_open_max_requests: 1,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::new(config);
+        let instance_id = "test-instance";
+
+        breaker.record_success(instance_id);
+        let created = breaker.get_metrics(instance_id).unwrap().last_transition;
+
+        sleep(Duration::from_millis(5));
+        breaker.record_failure(instance_id);
+        breaker.record_failure(instance_id);
+        let opened = breaker.get_metrics(instance_id).unwrap();
+        assert_eq!(opened.state, CircuitState::Open);
+        assert!(opened.last_transition > created);
+
+        // Staying open does not move the timestamp
+        breaker.record_failure(instance_id);
+        let still_open = breaker.get_metrics(instance_id).unwrap();
+        assert_eq!(still_open.last_transition, opened.last_transition);
+
+        sleep(Duration::from_millis(5));
+        breaker.reset(instance_id);
+        assert!(breaker.get_metrics(instance_id).unwrap().last_transition > opened.last_transition);
+    }
+
+    #[test]
+    fn test_forced_open_holds_until_reset() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 0.5,
+            min_requests: 2,
+            open_timeout: Duration::ZERO,
+            half_open_max_requests: 1,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::new(config);
+        let instance_id = "test-instance";
+
+        breaker.force_open(instance_id);
+        assert!(breaker.get_metrics(instance_id).unwrap().manual);
+        // The open timeout would normally let a probe through
+        assert!(!breaker.allow_request(instance_id));
+        breaker.record_success(instance_id);
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
+
+        // Reset restores automatic behavior
+        breaker.reset(instance_id);
+        assert!(!breaker.get_metrics(instance_id).unwrap().manual);
+        assert!(breaker.allow_request(instance_id));
+        breaker.record_failure(instance_id);
+        breaker.record_failure(instance_id);
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_forced_close_ignores_failures() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 0.5,
+            min_requests: 2,
+            open_timeout: Duration::from_secs(30),
+            half_open_max_requests: 1,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::new(config);
+        let instance_id = "test-instance";
+
+        breaker.record_failure(instance_id);
+        breaker.record_failure(instance_id);
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Open);
+
+        breaker.force_close(instance_id);
+        for _ in 0..5 {
+            assert!(breaker.allow_request(instance_id));
+            breaker.record_failure(instance_id);
+        }
+        assert_eq!(breaker.get_state(instance_id), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_transition_listener_sees_state_changes() {
+        let cb = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 0.5,
+            min_requests: 2,
+            open_timeout: Duration::from_secs(30),
+            half_open_max_requests: 1,
+            ..Default::default()
+        });
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        cb.on_transition(Arc::new(move |id, state| {
+            sink.lock().push((id.to_string(), state));
+        }));
+
+        cb.record_failure("i1");
+        cb.record_failure("i1");
+        cb.record_failure("i1");
+        cb.reset("i1");
+
+        assert_eq!(
+            *seen.lock(),
+            vec![
+                ("i1".to_string(), CircuitState::Open),
+                ("i1".to_string(), CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_transitions_are_each_reported_once() {
+        let cb = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 0.5,
+            min_requests: 1,
+            open_timeout: Duration::ZERO,
+            half_open_max_requests: 1,
+            ..Default::default()
+        });
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        cb.on_transition(Arc::new(move |_, state| sink.lock().push(state)));
+
+        let threads: Vec<_> = (0..8)
+            .map(|t| {
+                let cb = cb.clone();
+                std::thread::spawn(move || {
+                    for i in 0..5000 {
+                        if cb.allow_request("i1") && (i + t) % 3 == 0 {
+                            cb.record_success("i1");
+                        } else {
+                            cb.record_failure("i1");
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let seen = seen.lock();
+        let trips = seen.iter().filter(|s| **s == CircuitState::Open).count() as u64;
+        assert_eq!(trips, cb.get_metrics("i1").unwrap().trips);
+        assert!(seen.windows(2).all(|pair| pair[0] != pair[1]), "{seen:?}");
+        assert_eq!(seen.last().copied(), Some(cb.get_state("i1")));
+    }
+
     #[test]
     fn test_circuit_state_display() {
         assert_eq!(format!("{}", CircuitState::Closed), "closed");
@@ -2,6 +2,7 @@
 //!
 //! Health checking and circuit breaker with:
 //! - Active health checks (HTTP, TCP, gRPC)
+//! - Jittered check scheduling with a cap on checks in flight
 //! - Passive health checks (request success/failure tracking)
 //! - Circuit breaker pattern
 //! - Health state tracking
@@ -17,6 +18,7 @@
 
 pub mod checker;
 pub mod circuit_breaker;
+pub mod scheduler;
 pub mod tracker;
 
 pub use checker::{
@@ -24,8 +26,10 @@ pub use checker::{
     HealthStatus, HttpHealthCheck, TcpHealthCheck,
 };
 pub use circuit_breaker::{
-    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics, CircuitState,
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics, CircuitState, Outcome,
+    TransitionListener,
 };
+pub use scheduler::{CheckTarget, HealthCheckScheduler, ResultSink, ScheduleConfig};
 pub use tracker::{HealthMetrics, HealthSnapshot, HealthTracker, HealthTrackerConfig};
 
 /// Re-export commonly used types
@@ -35,7 +39,8 @@ pub mod prelude {
         HealthStatus, HttpHealthCheck, TcpHealthCheck,
     };
     pub use crate::circuit_breaker::{
-        CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics, CircuitState,
+        CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics, CircuitState, Outcome,
     };
+    pub use crate::scheduler::{CheckTarget, HealthCheckScheduler, ScheduleConfig};
     pub use crate::tracker::{HealthMetrics, HealthSnapshot, HealthTracker, HealthTrackerConfig};
 }
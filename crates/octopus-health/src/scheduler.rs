@@ -0,0 +1,332 @@
+//! Scheduling of active health checks across clusters
+//!
+//! Checking every instance on the same tick sends a burst of probes to the
+//! backends at the start of each interval. [`HealthCheckScheduler`] instead
+//! gives each instance a fixed offset within the interval, derived from its
+//! cluster and id, so checks are spread across the round and each instance
+//! is checked at the same phase every time. At most
+//! [`ScheduleConfig::max_concurrent`] checks are in flight at once, so a slow
+//! backend holding checks open cannot pile more of them up. Clusters can be
+//! disabled and re-enabled at runtime.
+
+use crate::checker::{HealthCheck, HealthCheckResult};
+use parking_lot::RwLock;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::MissedTickBehavior;
+use tracing::debug;
+
+/// Timing of health check rounds
+#[derive(Debug, Clone)]
+pub struct ScheduleConfig {
+    /// Length of a round: every enabled instance is checked once per interval
+    pub interval: Duration,
+    /// Share of the interval over which check start times are spread, from
+    /// `0.0` (all at the start of the round) to `1.0` (the whole interval)
+    pub jitter: f64,
+    /// Checks in flight at once across all clusters
+    pub max_concurrent: usize,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            jitter: 1.0,
+            max_concurrent: 32,
+        }
+    }
+}
+
+/// An instance to health check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckTarget {
+    /// Instance id, passed back with its result
+    pub instance_id: String,
+    /// Host or IP address
+    pub address: String,
+    /// Port
+    pub port: u16,
+}
+
+impl CheckTarget {
+    /// Create a check target
+    pub fn new(instance_id: impl Into<String>, address: impl Into<String>, port: u16) -> Self {
+        Self {
+            instance_id: instance_id.into(),
+            address: address.into(),
+            port,
+        }
+    }
+}
+
+/// Receives each check result with the cluster and instance it belongs to
+pub type ResultSink = Arc<dyn Fn(&str, &CheckTarget, &HealthCheckResult) + Send + Sync>;
+
+struct Cluster {
+    checker: Arc<dyn HealthCheck>,
+    targets: Vec<CheckTarget>,
+    enabled: bool,
+}
+
+/// Runs jittered, concurrency-capped health check rounds
+pub struct HealthCheckScheduler {
+    config: ScheduleConfig,
+    clusters: RwLock<HashMap<String, Cluster>>,
+    permits: Arc<Semaphore>,
+}
+
+impl HealthCheckScheduler {
+    /// Create a scheduler with no clusters
+    pub fn new(config: ScheduleConfig) -> Self {
+        let permits = Arc::new(Semaphore::new(config.max_concurrent.max(1)));
+        Self {
+            config,
+            clusters: RwLock::new(HashMap::new()),
+            permits,
+        }
+    }
+
+    /// Check `targets` with `checker`, replacing any cluster of that name.
+    /// New clusters are enabled.
+    pub fn set_cluster(
+        &self,
+        name: impl Into<String>,
+        checker: Arc<dyn HealthCheck>,
+        targets: Vec<CheckTarget>,
+    ) {
+        self.clusters.write().insert(
+            name.into(),
+            Cluster {
+                checker,
+                targets,
+                enabled: true,
+            },
+        );
+    }
+
+    /// Stop checking a cluster
+    pub fn remove_cluster(&self, name: &str) {
+        self.clusters.write().remove(name);
+    }
+
+    /// Enable or disable checks for a cluster; `false` if it isn't known
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        match self.clusters.write().get_mut(name) {
+            Some(cluster) => {
+                cluster.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a cluster is known and enabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.clusters.read().get(name).is_some_and(|c| c.enabled)
+    }
+
+    /// Offset of an instance's check from the start of each round
+    pub fn offset(&self, cluster: &str, instance_id: &str) -> Duration {
+        let window = self
+            .config
+            .interval
+            .mul_f64(self.config.jitter.clamp(0.0, 1.0));
+        if window.is_zero() {
+            return Duration::ZERO;
+        }
+        let mut hasher = DefaultHasher::new();
+        (cluster, instance_id).hash(&mut hasher);
+        let nanos = window.as_nanos().min(u64::MAX as u128) as u64;
+        Duration::from_nanos(hasher.finish() % nanos)
+    }
+
+    /// Run one round: check every instance of every enabled cluster at its
+    /// offset, reporting each result to `sink`. Returns once all checks are
+    /// done.
+    pub async fn run_round(&self, sink: &ResultSink) {
+        let mut checks = JoinSet::new();
+        for (name, cluster) in self.clusters.read().iter() {
+            if !cluster.enabled {
+                debug!(cluster = %name, "Health checks disabled, skipping");
+                continue;
+            }
+            for target in &cluster.targets {
+                let offset = self.offset(name, &target.instance_id);
+                let name = name.clone();
+                let target = target.clone();
+                let checker = Arc::clone(&cluster.checker);
+                let permits = Arc::clone(&self.permits);
+                let sink = Arc::clone(sink);
+                checks.spawn(async move {
+                    tokio::time::sleep(offset).await;
+                    let Ok(_permit) = permits.acquire_owned().await else {
+                        return;
+                    };
+                    let result = checker.check(&target.address, target.port).await;
+                    sink(&name, &target, &result);
+                });
+            }
+        }
+        while checks.join_next().await.is_some() {}
+    }
+
+    /// Run rounds every interval until the future is dropped, which also
+    /// cancels the checks in flight. A round that overruns the interval
+    /// delays the next one rather than overlapping it.
+    pub async fn run(&self, sink: ResultSink) {
+        let mut ticks = tokio::time::interval(self.config.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            self.run_round(&sink).await;
+        }
+    }
+
+    /// [`run`](Self::run) on a task of its own, until it is aborted
+    pub fn spawn(self: Arc<Self>, sink: ResultSink) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run(sink).await })
+    }
+}
+
+impl fmt::Debug for HealthCheckScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthCheckScheduler")
+            .field("config", &self.config)
+            .field("clusters", &self.clusters.read().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::time::Instant;
+
+    /// Records when each check starts and how many overlap
+    #[derive(Debug, Default)]
+    struct Probe {
+        latency: Duration,
+        started: Mutex<Vec<Instant>>,
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HealthCheck for Probe {
+        async fn check(&self, _address: &str, _port: u16) -> HealthCheckResult {
+            self.started.lock().push(Instant::now());
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(self.latency).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            HealthCheckResult::healthy(self.latency)
+        }
+    }
+
+    fn targets(n: usize) -> Vec<CheckTarget> {
+        (0..n)
+            .map(|i| CheckTarget::new(format!("api-{i}"), "10.0.0.1", 8000 + i as u16))
+            .collect()
+    }
+
+    fn counting_sink() -> (ResultSink, Arc<AtomicUsize>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let sink: ResultSink = Arc::new({
+            let count = Arc::clone(&count);
+            move |_, _, _| {
+                count.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        (sink, count)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn checks_are_spread_across_the_interval() {
+        let interval = Duration::from_secs(10);
+        let scheduler = HealthCheckScheduler::new(ScheduleConfig {
+            interval,
+            ..Default::default()
+        });
+        let probe = Arc::new(Probe::default());
+        scheduler.set_cluster("api", probe.clone(), targets(20));
+        let (sink, checked) = counting_sink();
+
+        let start = Instant::now();
+        scheduler.run_round(&sink).await;
+
+        assert_eq!(checked.load(Ordering::SeqCst), 20);
+        let offsets: Vec<Duration> = probe.started.lock().iter().map(|t| *t - start).collect();
+        assert!(offsets.iter().all(|o| *o < interval), "{offsets:?}");
+        let at_start = offsets.iter().filter(|o| o.is_zero()).count();
+        assert!(at_start <= 1, "{offsets:?}");
+        // Both halves of the interval get checks.
+        let early = offsets.iter().filter(|o| **o < interval / 2).count();
+        assert!(early > 0 && early < 20, "{offsets:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn zero_jitter_checks_everything_at_the_start() {
+        let scheduler = HealthCheckScheduler::new(ScheduleConfig {
+            jitter: 0.0,
+            ..Default::default()
+        });
+        let probe = Arc::new(Probe::default());
+        scheduler.set_cluster("api", probe.clone(), targets(5));
+        let (sink, _) = counting_sink();
+
+        let start = Instant::now();
+        scheduler.run_round(&sink).await;
+
+        assert!(probe.started.lock().iter().all(|t| *t == start));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_checks_are_capped() {
+        let scheduler = HealthCheckScheduler::new(ScheduleConfig {
+            jitter: 0.0,
+            max_concurrent: 3,
+            ..Default::default()
+        });
+        let slow = Arc::new(Probe {
+            latency: Duration::from_secs(2),
+            ..Default::default()
+        });
+        scheduler.set_cluster("slow", slow.clone(), targets(10));
+        let (sink, checked) = counting_sink();
+
+        scheduler.run_round(&sink).await;
+
+        assert_eq!(checked.load(Ordering::SeqCst), 10);
+        assert_eq!(slow.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disabled_clusters_are_skipped() {
+        let scheduler = HealthCheckScheduler::new(ScheduleConfig::default());
+        let api = Arc::new(Probe::default());
+        let batch = Arc::new(Probe::default());
+        scheduler.set_cluster("api", api.clone(), targets(2));
+        scheduler.set_cluster("batch", batch.clone(), targets(3));
+        let (sink, _) = counting_sink();
+
+        assert!(scheduler.set_enabled("batch", false));
+        assert!(!scheduler.set_enabled("missing", false));
+        scheduler.run_round(&sink).await;
+        assert_eq!(api.started.lock().len(), 2);
+        assert!(batch.started.lock().is_empty());
+
+        scheduler.set_enabled("batch", true);
+        scheduler.run_round(&sink).await;
+        assert_eq!(batch.started.lock().len(), 3);
+    }
+}
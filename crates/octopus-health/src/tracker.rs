@@ -253,13 +253,17 @@ impl HealthTracker {
 
     /// Start background cleanup task
     pub fn start_cleanup_task(self: Arc<Self>) {
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(self.config.cleanup_interval);
-            loop {
-                interval.tick().await;
-                self.cleanup_old_metrics();
-            }
-        });
+        tokio::spawn(self.run_cleanup());
+    }
+
+    /// Clean up old metrics every `cleanup_interval`, forever; for callers
+    /// that own the task, such as the runtime's shutdown-cancelled tasks
+    pub async fn run_cleanup(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.cleanup_interval);
+        loop {
+            interval.tick().await;
+            self.cleanup_old_metrics();
+        }
     }
 
     /// Clean up metrics for instances that haven't been accessed recently
@@ -19,6 +19,8 @@ pub struct ActivityEntry {
     pub latency_ms: f64,
     /// Upstream service name
     pub upstream: String,
+    /// Extra detail, e.g. a response schema violation
+    pub note: Option<String>,
 }
 
 impl ActivityEntry {
@@ -37,9 +39,16 @@ impl ActivityEntry {
             status: status.as_u16(),
             latency_ms: format_duration_ms(latency),
             upstream,
+            note: None,
         }
     }
 
+    /// Attach extra detail to the entry
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
     /// Get formatted timestamp
     pub fn formatted_time(&self) -> String {
         // Convert to human-readable time
@@ -1,7 +1,10 @@
 //! Metrics collector for tracking gateway performance
 
 use super::*;
+use octopus_core::ErrorCode;
+use octopus_health::{CircuitBreaker, CircuitBreakerMetrics};
 use std::collections::VecDeque;
+use std::fmt;
 
 /// Per-route metrics tracking
 #[derive(Debug)]
@@ -10,6 +13,11 @@ pub struct RouteStats {
     pub request_count: AtomicU64,
     /// Total errors for this route
     pub error_count: AtomicU64,
+    /// Total request body bytes received on this route
+    pub request_bytes: AtomicU64,
+    /// Total response body bytes sent on this route, as sent to the client
+    /// (after compression)
+    pub response_bytes: AtomicU64,
     /// Total latency in nanoseconds
     total_latency_ns: AtomicU64,
     /// Minimum latency in nanoseconds
@@ -26,6 +34,8 @@ impl RouteStats {
         Self {
             request_count: AtomicU64::new(0),
             error_count: AtomicU64::new(0),
+            request_bytes: AtomicU64::new(0),
+            response_bytes: AtomicU64::new(0),
             total_latency_ns: AtomicU64::new(0),
             min_latency_ns: AtomicU64::new(u64::MAX),
             max_latency_ns: AtomicU64::new(0),
@@ -81,6 +91,14 @@ impl RouteStats {
         latencies.push_back(latency_ns);
     }
 
+    /// Add body sizes of one request/response exchange
+    pub fn record_bytes(&self, request_bytes: u64, response_bytes: u64) {
+        self.request_bytes
+            .fetch_add(request_bytes, Ordering::Relaxed);
+        self.response_bytes
+            .fetch_add(response_bytes, Ordering::Relaxed);
+    }
+
     /// Get average latency in milliseconds
     pub fn avg_latency_ms(&self) -> f64 {
         let count = self.request_count.load(Ordering::Relaxed);
@@ -138,6 +156,138 @@ impl Default for RouteStats {
     }
 }
 
+/// Upper bounds, in seconds, of the plugin latency histogram buckets
+pub const PLUGIN_LATENCY_BUCKETS: [f64; 13] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Per-plugin execution metrics
+///
+/// Latency is the time spent inside the plugin's interceptors for one
+/// request, excluding the rest of the pipeline.
+#[derive(Debug)]
+pub struct PluginStats {
+    /// Requests the plugin ran on
+    pub invocations: AtomicU64,
+    /// Invocations that failed or aborted the request with an error
+    pub errors: AtomicU64,
+    /// Total execution time in nanoseconds
+    total_ns: AtomicU64,
+    /// Slowest execution in nanoseconds
+    max_ns: AtomicU64,
+    /// Non-cumulative counts per [`PLUGIN_LATENCY_BUCKETS`] bound; the last
+    /// slot counts executions above the largest bound
+    buckets: [AtomicU64; PLUGIN_LATENCY_BUCKETS.len() + 1],
+}
+
+impl PluginStats {
+    /// Create empty plugin stats
+    pub fn new() -> Self {
+        Self {
+            invocations: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            total_ns: AtomicU64::new(0),
+            max_ns: AtomicU64::new(0),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    /// Record one execution
+    pub fn record(&self, duration: Duration, ok: bool) {
+        let ns = duration.as_nanos() as u64;
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_ns.fetch_add(ns, Ordering::Relaxed);
+        self.max_ns.fetch_max(ns, Ordering::Relaxed);
+
+        let secs = duration.as_secs_f64();
+        let slot = PLUGIN_LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(PLUGIN_LATENCY_BUCKETS.len());
+        self.buckets[slot].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Total execution time in seconds
+    pub fn total_seconds(&self) -> f64 {
+        self.total_ns.load(Ordering::Relaxed) as f64 / 1_000_000_000.0
+    }
+
+    /// Average execution time in milliseconds
+    pub fn avg_latency_ms(&self) -> f64 {
+        let count = self.invocations.load(Ordering::Relaxed);
+        if count == 0 {
+            return 0.0;
+        }
+        self.total_ns.load(Ordering::Relaxed) as f64 / count as f64 / 1_000_000.0
+    }
+
+    /// Slowest execution in milliseconds
+    pub fn max_latency_ms(&self) -> f64 {
+        self.max_ns.load(Ordering::Relaxed) as f64 / 1_000_000.0
+    }
+
+    /// Cumulative execution counts for each [`PLUGIN_LATENCY_BUCKETS`] bound
+    pub fn cumulative_buckets(&self) -> Vec<(f64, u64)> {
+        let mut cumulative = 0;
+        PLUGIN_LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (bound, cumulative)
+            })
+            .collect()
+    }
+}
+
+impl Default for PluginStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Load on one concurrency limit: requests holding a slot, requests
+/// waiting for one, and requests turned away
+#[derive(Debug, Default)]
+pub struct ConcurrencyStats {
+    /// Requests currently holding a slot
+    pub in_flight: AtomicUsize,
+    /// Requests currently waiting for a slot
+    pub queued: AtomicUsize,
+    /// Requests rejected because the queue was full or the wait timed out
+    pub rejected: AtomicU64,
+}
+
+/// Maps an upstream instance id to the name of its upstream
+pub type UpstreamLookup = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Circuit breaker whose per-instance state is exported with the metrics
+#[derive(Clone)]
+struct CircuitSource {
+    breaker: Arc<CircuitBreaker>,
+    upstream_of: UpstreamLookup,
+}
+
+impl fmt::Debug for CircuitSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitSource").finish_non_exhaustive()
+    }
+}
+
+/// Circuit breaker metrics of one upstream instance
+#[derive(Debug, Clone)]
+pub struct CircuitReading {
+    /// Upstream the instance belongs to, `unknown` if it can't be resolved
+    pub upstream: String,
+    /// Instance id
+    pub instance: String,
+    /// State and counters of the instance's circuit
+    pub metrics: CircuitBreakerMetrics,
+}
+
 /// Main metrics collector for the gateway
 #[derive(Debug, Clone)]
 pub struct MetricsCollector {
@@ -145,12 +295,30 @@ pub struct MetricsCollector {
     total_requests: Arc<AtomicU64>,
     /// Global error counter
     total_errors: Arc<AtomicU64>,
+    /// Global request body byte counter
+    total_request_bytes: Arc<AtomicU64>,
+    /// Global response body byte counter
+    total_response_bytes: Arc<AtomicU64>,
     /// Per-route statistics
     route_stats: Arc<DashMap<String, Arc<RouteStats>>>,
     /// Active connections
     active_connections: Arc<AtomicUsize>,
+    /// Upstream failures by error code (refused, reset, DNS, TLS, ...)
+    upstream_errors: Arc<DashMap<ErrorCode, AtomicU64>>,
+    /// Requests failed over, by primary and fallback upstream
+    failovers: Arc<DashMap<(String, String), AtomicU64>>,
+    /// Per-plugin execution statistics
+    plugin_stats: Arc<DashMap<String, Arc<PluginStats>>>,
+    /// Concurrency limit load, by limit scope (`global` or a route)
+    concurrency: Arc<DashMap<String, Arc<ConcurrencyStats>>>,
+    /// Access log lines dropped because the log writer's buffer was full
+    access_log_dropped: Arc<AtomicU64>,
     /// Start time of the collector
     start_time: Arc<AtomicU64>,
+    /// Per-route SLO tracking (None = disabled)
+    slo: Option<Arc<crate::slo::SloTracker>>,
+    /// Circuit breaker state source (None = not exported)
+    circuits: Option<CircuitSource>,
 }
 
 impl MetricsCollector {
@@ -159,12 +327,66 @@ impl MetricsCollector {
         Self {
             total_requests: Arc::new(AtomicU64::new(0)),
             total_errors: Arc::new(AtomicU64::new(0)),
+            total_request_bytes: Arc::new(AtomicU64::new(0)),
+            total_response_bytes: Arc::new(AtomicU64::new(0)),
             route_stats: Arc::new(DashMap::new()),
             active_connections: Arc::new(AtomicUsize::new(0)),
+            upstream_errors: Arc::new(DashMap::new()),
+            failovers: Arc::new(DashMap::new()),
+            plugin_stats: Arc::new(DashMap::new()),
+            concurrency: Arc::new(DashMap::new()),
+            access_log_dropped: Arc::new(AtomicU64::new(0)),
             start_time: Arc::new(AtomicU64::new(current_timestamp_ms())),
+            slo: None,
+            circuits: None,
         }
     }
 
+    /// Feed every recorded request to an SLO tracker
+    pub fn with_slo(mut self, tracker: Arc<crate::slo::SloTracker>) -> Self {
+        self.slo = Some(tracker);
+        self
+    }
+
+    /// The SLO tracker, if enabled
+    pub fn slo(&self) -> Option<&Arc<crate::slo::SloTracker>> {
+        self.slo.as_ref()
+    }
+
+    /// Export the per-instance state of a circuit breaker, labelled with the
+    /// upstream `upstream_of` resolves each instance to
+    pub fn with_circuit_breaker(
+        mut self,
+        breaker: Arc<CircuitBreaker>,
+        upstream_of: UpstreamLookup,
+    ) -> Self {
+        self.circuits = Some(CircuitSource {
+            breaker,
+            upstream_of,
+        });
+        self
+    }
+
+    /// Circuit breaker metrics of every tracked instance, sorted by upstream
+    /// and instance. Empty unless a breaker was attached.
+    pub fn circuit_readings(&self) -> Vec<CircuitReading> {
+        let Some(source) = &self.circuits else {
+            return Vec::new();
+        };
+        let mut readings: Vec<_> = source
+            .breaker
+            .get_all_metrics()
+            .into_iter()
+            .map(|(instance, metrics)| CircuitReading {
+                upstream: (source.upstream_of)(&instance).unwrap_or_else(|| "unknown".to_string()),
+                instance,
+                metrics,
+            })
+            .collect();
+        readings.sort_by(|a, b| (&a.upstream, &a.instance).cmp(&(&b.upstream, &b.instance)));
+        readings
+    }
+
     /// Record a request
     pub fn record_request(&self, route: &str, latency: Duration, outcome: RequestOutcome) {
         // Update global counters
@@ -174,13 +396,131 @@ impl MetricsCollector {
         }
 
         // Update route-specific stats
-        let stats = self
-            .route_stats
+        self.route_entry(route)
+            .record_request(latency.as_nanos() as u64, outcome);
+
+        if let Some(slo) = &self.slo {
+            slo.record(route, latency, outcome);
+        }
+    }
+
+    /// Record the request and response body sizes of one exchange on `route`.
+    ///
+    /// `response_bytes` should be what was sent to the client, i.e. the
+    /// compressed size when the response was compressed.
+    pub fn record_bytes(&self, route: &str, request_bytes: u64, response_bytes: u64) {
+        self.total_request_bytes
+            .fetch_add(request_bytes, Ordering::Relaxed);
+        self.total_response_bytes
+            .fetch_add(response_bytes, Ordering::Relaxed);
+        self.route_entry(route)
+            .record_bytes(request_bytes, response_bytes);
+    }
+
+    fn route_entry(&self, route: &str) -> Arc<RouteStats> {
+        self.route_stats
             .entry(route.to_string())
             .or_insert_with(|| Arc::new(RouteStats::new()))
-            .clone();
+            .clone()
+    }
+
+    /// Count an upstream failure under its error code
+    pub fn record_upstream_error(&self, code: ErrorCode) {
+        self.upstream_errors
+            .entry(code)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of upstream failures recorded under `code`
+    pub fn upstream_error_count(&self, code: ErrorCode) -> u64 {
+        self.upstream_errors
+            .get(&code)
+            .map_or(0, |count| count.load(Ordering::Relaxed))
+    }
+
+    /// Upstream failure counts by error code, sorted by code
+    pub fn upstream_error_counts(&self) -> Vec<(ErrorCode, u64)> {
+        let mut counts: Vec<_> = self
+            .upstream_errors
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().load(Ordering::Relaxed)))
+            .collect();
+        counts.sort_by_key(|(code, _)| code.as_str());
+        counts
+    }
+
+    /// Count a request sent to `fallback` because `upstream` was unavailable
+    pub fn record_failover(&self, upstream: &str, fallback: &str) {
+        self.failovers
+            .entry((upstream.to_string(), fallback.to_string()))
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
 
-        stats.record_request(latency.as_nanos() as u64, outcome);
+    /// Failover counts by primary and fallback upstream, sorted
+    pub fn failover_counts(&self) -> Vec<((String, String), u64)> {
+        let mut counts: Vec<_> = self
+            .failovers
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    /// Record one execution of a plugin's interceptors
+    pub fn record_plugin(&self, plugin: &str, duration: Duration, ok: bool) {
+        if let Some(stats) = self.plugin_stats.get(plugin) {
+            stats.record(duration, ok);
+            return;
+        }
+        self.plugin_stats
+            .entry(plugin.to_string())
+            .or_insert_with(|| Arc::new(PluginStats::new()))
+            .record(duration, ok);
+    }
+
+    /// Get stats for a specific plugin
+    pub fn plugin_stats(&self, plugin: &str) -> Option<Arc<PluginStats>> {
+        self.plugin_stats.get(plugin).map(|entry| entry.clone())
+    }
+
+    /// Names of all plugins with recorded executions, sorted
+    pub fn plugin_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .plugin_stats
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Load counters for the concurrency limit `scope`, created on first
+    /// use. The limiter updates them in place.
+    pub fn concurrency_stats(&self, scope: &str) -> Arc<ConcurrencyStats> {
+        self.concurrency
+            .entry(scope.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Concurrency limit scopes with their counters, sorted by scope
+    pub fn concurrency_scopes(&self) -> Vec<(String, Arc<ConcurrencyStats>)> {
+        let mut scopes: Vec<_> = self
+            .concurrency
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        scopes.sort_by(|a, b| a.0.cmp(&b.0));
+        scopes
+    }
+
+    /// Counter of access log lines dropped on a full buffer, shared with
+    /// the access log writer, which counts into it
+    pub fn access_log_dropped(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.access_log_dropped)
     }
 
     /// Increment active connections
@@ -203,6 +543,16 @@ impl MetricsCollector {
         self.total_errors.load(Ordering::Relaxed)
     }
 
+    /// Get total request body bytes
+    pub fn total_request_bytes(&self) -> u64 {
+        self.total_request_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Get total response body bytes
+    pub fn total_response_bytes(&self) -> u64 {
+        self.total_response_bytes.load(Ordering::Relaxed)
+    }
+
     /// Get active connections count
     pub fn active_connections(&self) -> usize {
         self.active_connections.load(Ordering::Relaxed)
@@ -304,6 +654,94 @@ mod tests {
         assert_eq!(collector.route_count(), 2);
     }
 
+    #[test]
+    fn test_record_bytes_per_route_and_global() {
+        let collector = MetricsCollector::new();
+        collector.record_bytes("/upload", 1024, 12);
+        collector.record_bytes("/upload", 2048, 12);
+        collector.record_bytes("/download", 0, 4096);
+
+        assert_eq!(collector.total_request_bytes(), 3072);
+        assert_eq!(collector.total_response_bytes(), 4120);
+        let upload = collector.route_stats("/upload").unwrap();
+        assert_eq!(upload.request_bytes.load(Ordering::Relaxed), 3072);
+        assert_eq!(upload.response_bytes.load(Ordering::Relaxed), 24);
+        // Byte counts alone don't count as requests.
+        assert_eq!(collector.total_requests(), 0);
+    }
+
+    #[test]
+    fn test_collector_feeds_slo_tracker() {
+        let tracker = Arc::new(
+            crate::slo::SloTracker::new()
+                .with_objective("/users", crate::slo::SloObjective::default()),
+        );
+        let collector = MetricsCollector::new().with_slo(Arc::clone(&tracker));
+        collector.record_request(
+            "/users",
+            Duration::from_millis(500),
+            RequestOutcome::Success,
+        );
+        collector.record_request("/users", Duration::from_millis(5), RequestOutcome::Error);
+
+        let status = tracker.status("/users").unwrap();
+        assert_eq!(status.requests, 2);
+        assert_eq!(status.success_ratio, 0.5);
+        assert_eq!(status.latency_attainment, 0.5);
+    }
+
+    #[test]
+    fn test_upstream_errors_are_counted_by_code() {
+        let collector = MetricsCollector::new();
+        collector.record_upstream_error(ErrorCode::UpstreamReset);
+        collector.record_upstream_error(ErrorCode::UpstreamReset);
+        collector.record_upstream_error(ErrorCode::UpstreamDnsFailure);
+
+        assert_eq!(collector.upstream_error_count(ErrorCode::UpstreamReset), 2);
+        assert_eq!(
+            collector.upstream_error_count(ErrorCode::UpstreamTimeout),
+            0
+        );
+        assert_eq!(
+            collector.upstream_error_counts(),
+            vec![
+                (ErrorCode::UpstreamDnsFailure, 1),
+                (ErrorCode::UpstreamReset, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plugin_executions_are_tracked_per_plugin() {
+        let collector = MetricsCollector::new();
+        collector.record_plugin("auth", Duration::from_micros(200), true);
+        collector.record_plugin("auth", Duration::from_micros(600), false);
+        collector.record_plugin("cors", Duration::from_micros(50), true);
+
+        assert_eq!(collector.plugin_names(), vec!["auth", "cors"]);
+        let auth = collector.plugin_stats("auth").unwrap();
+        assert_eq!(auth.invocations.load(Ordering::Relaxed), 2);
+        assert_eq!(auth.errors.load(Ordering::Relaxed), 1);
+        assert!((auth.avg_latency_ms() - 0.4).abs() < 1e-9);
+        assert!((auth.max_latency_ms() - 0.6).abs() < 1e-9);
+        assert!(collector.plugin_stats("missing").is_none());
+    }
+
+    #[test]
+    fn test_plugin_latency_buckets_are_cumulative() {
+        let stats = PluginStats::new();
+        stats.record(Duration::from_micros(80), true);
+        stats.record(Duration::from_millis(3), true);
+        stats.record(Duration::from_secs(2), true);
+
+        let buckets = stats.cumulative_buckets();
+        assert_eq!(buckets[0], (0.0001, 1));
+        assert_eq!(buckets.iter().find(|(le, _)| *le == 0.005).unwrap().1, 2);
+        // The 2s execution is only counted in +Inf.
+        assert_eq!(buckets.last().unwrap(), &(1.0, 2));
+        assert_eq!(stats.invocations.load(Ordering::Relaxed), 3);
+    }
+
     #[test]
     fn test_active_connections() {
         let collector = MetricsCollector::new();
@@ -0,0 +1,134 @@
+//! Captured exchanges of debug-tapped requests
+
+use super::*;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// One side of a captured exchange, already redacted
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CapturedMessage {
+    /// Headers in order, sensitive values masked
+    pub headers: Vec<(String, String)>,
+    /// Body, lossily decoded as UTF-8 and cut at the capture limit
+    pub body: String,
+    /// Whether the body was cut
+    pub body_truncated: bool,
+}
+
+/// A request and its response, captured because the request carried a
+/// valid debug token
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugCapture {
+    /// Sequence number, assigned when recorded
+    pub id: u64,
+    /// Timestamp in milliseconds
+    pub timestamp: u64,
+    /// Subject of the debug token, i.e. who asked for the capture
+    pub subject: Option<String>,
+    /// HTTP method
+    pub method: String,
+    /// Request URI
+    pub uri: String,
+    /// Response status code
+    pub status: u16,
+    /// The request as received
+    pub request: CapturedMessage,
+    /// The response as returned to the client
+    pub response: CapturedMessage,
+}
+
+/// Bounded buffer of the most recent debug captures
+#[derive(Debug, Clone)]
+pub struct DebugTapLog {
+    entries: Arc<parking_lot::Mutex<VecDeque<DebugCapture>>>,
+    next_id: Arc<AtomicU64>,
+    max_entries: usize,
+}
+
+impl DebugTapLog {
+    /// Create a log keeping the last `max_entries` captures
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(parking_lot::Mutex::new(VecDeque::with_capacity(
+                max_entries,
+            ))),
+            next_id: Arc::new(AtomicU64::new(1)),
+            max_entries,
+        }
+    }
+
+    /// Record a capture, evicting the oldest when full. Returns its id.
+    pub fn record(&self, mut capture: DebugCapture) -> u64 {
+        capture.id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let id = capture.id;
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(capture);
+        id
+    }
+
+    /// Most recent captures first
+    pub fn recent(&self, limit: usize) -> Vec<DebugCapture> {
+        let entries = self.entries.lock();
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// The capture with `id`, if still kept
+    pub fn get(&self, id: u64) -> Option<DebugCapture> {
+        let entries = self.entries.lock();
+        entries.iter().find(|c| c.id == id).cloned()
+    }
+
+    /// Drop all captures
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// Number of captures kept
+    pub fn count(&self) -> usize {
+        self.entries.lock().len()
+    }
+}
+
+impl Default for DebugTapLog {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(uri: &str) -> DebugCapture {
+        DebugCapture {
+            id: 0,
+            timestamp: current_timestamp_ms(),
+            subject: None,
+            method: "GET".to_string(),
+            uri: uri.to_string(),
+            status: 200,
+            request: CapturedMessage::default(),
+            response: CapturedMessage::default(),
+        }
+    }
+
+    #[test]
+    fn test_debug_tap_log_keeps_most_recent() {
+        let log = DebugTapLog::new(2);
+        assert_eq!(log.record(capture("/a")), 1);
+        log.record(capture("/b"));
+        let id = log.record(capture("/c"));
+
+        assert_eq!(log.count(), 2);
+        let recent: Vec<_> = log.recent(10).into_iter().map(|c| c.uri).collect();
+        assert_eq!(recent, ["/c", "/b"]);
+        assert_eq!(log.get(id).unwrap().uri, "/c");
+        assert!(log.get(1).is_none());
+
+        log.clear();
+        assert_eq!(log.count(), 0);
+    }
+}
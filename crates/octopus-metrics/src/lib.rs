@@ -4,8 +4,14 @@
 //! - Request counts (total and per-route)
 //! - Latency tracking (min, max, avg, p50, p95, p99)
 //! - Error rates and counts
+//! - Request and response body bytes (total and per-route)
 //! - Active connections
+//! - Per-plugin execution time and error counts
+//! - In-flight, queued and rejected requests per concurrency limit
+//! - Circuit breaker state, trips and outcomes per upstream instance
 //! - Activity logs for recent requests
+//! - Captured exchanges of debug-tapped requests
+//! - Per-route SLO attainment and error-budget burn rate
 
 use dashmap::DashMap;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -14,13 +20,20 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub mod activity;
 pub mod collector;
+pub mod debug_tap;
 pub mod prometheus;
+pub mod slo;
 pub mod snapshot;
 
 pub use activity::{ActivityEntry, ActivityLog};
-pub use collector::MetricsCollector;
+pub use collector::{
+    CircuitReading, ConcurrencyStats, MetricsCollector, PluginStats, UpstreamLookup,
+    PLUGIN_LATENCY_BUCKETS,
+};
+pub use debug_tap::{CapturedMessage, DebugCapture, DebugTapLog};
 pub use prometheus::PrometheusExporter;
-pub use snapshot::{MetricsSnapshot, RouteMetrics};
+pub use slo::{SloHook, SloIndicator, SloObjective, SloStatus, SloTracker};
+pub use snapshot::{MetricsSnapshot, PluginMetrics, RouteMetrics};
 
 /// Request outcome
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
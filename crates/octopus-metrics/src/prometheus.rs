@@ -1,7 +1,9 @@
 //! Prometheus metrics exporter
 
-use crate::collector::MetricsCollector;
+use crate::collector::{CircuitReading, ConcurrencyStats, MetricsCollector, RouteStats};
 use std::fmt::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 /// Prometheus metrics exporter
 pub struct PrometheusExporter;
@@ -20,6 +22,28 @@ impl PrometheusExporter {
         // Per-route metrics
         Self::write_route_metrics(&mut output, collector);
 
+        // Per-route request/response body bytes
+        Self::write_byte_metrics(&mut output, collector);
+
+        // Upstream failures by kind
+        Self::write_upstream_error_metrics(&mut output, collector);
+        Self::write_failover_metrics(&mut output, collector);
+
+        // Per-plugin execution time and errors
+        Self::write_plugin_metrics(&mut output, collector);
+
+        // Concurrency limit load
+        Self::write_concurrency_metrics(&mut output, collector);
+
+        // Access log lines lost to a full buffer
+        Self::write_access_log_metrics(&mut output, collector);
+
+        // Circuit breaker state per upstream instance
+        Self::write_circuit_metrics(&mut output, collector);
+
+        // Per-route SLO attainment
+        Self::write_slo_metrics(&mut output, collector);
+
         output
     }
 
@@ -143,7 +167,354 @@ impl PrometheusExporter {
         writeln!(output, "# Per-route metrics (count: {route_count})").unwrap();
     }
 
-    #[allow(dead_code)]
+    fn write_byte_metrics(output: &mut String, collector: &MetricsCollector) {
+        let mut routes = collector.route_names();
+        routes.sort();
+
+        Self::write_byte_counter(
+            output,
+            collector,
+            &routes,
+            "octopus_request_bytes_total",
+            "Request body bytes received per route",
+            |s| s.request_bytes.load(Ordering::Relaxed),
+        );
+        Self::write_byte_counter(
+            output,
+            collector,
+            &routes,
+            "octopus_response_bytes_total",
+            "Response body bytes sent per route, after compression",
+            |s| s.response_bytes.load(Ordering::Relaxed),
+        );
+    }
+
+    fn write_byte_counter(
+        output: &mut String,
+        collector: &MetricsCollector,
+        routes: &[String],
+        name: &str,
+        help: &str,
+        bytes: impl Fn(&RouteStats) -> u64,
+    ) {
+        writeln!(output, "# HELP {name} {help}").unwrap();
+        writeln!(output, "# TYPE {name} counter").unwrap();
+        for route in routes {
+            if let Some(stats) = collector.route_stats(route) {
+                writeln!(
+                    output,
+                    "{name}{{route=\"{}\"}} {}",
+                    Self::sanitize_label(route),
+                    bytes(&stats)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn write_upstream_error_metrics(output: &mut String, collector: &MetricsCollector) {
+        writeln!(
+            output,
+            "# HELP octopus_upstream_errors_total Upstream failures by error code"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_upstream_errors_total counter").unwrap();
+        for (code, count) in collector.upstream_error_counts() {
+            writeln!(
+                output,
+                "octopus_upstream_errors_total{{code=\"{code}\"}} {count}"
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_failover_metrics(output: &mut String, collector: &MetricsCollector) {
+        writeln!(
+            output,
+            "# HELP octopus_upstream_failovers_total Requests sent to a route's fallback upstream"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_upstream_failovers_total counter").unwrap();
+        for ((upstream, fallback), count) in collector.failover_counts() {
+            writeln!(
+                output,
+                "octopus_upstream_failovers_total{{upstream=\"{}\",fallback=\"{}\"}} {count}",
+                Self::sanitize_label(&upstream),
+                Self::sanitize_label(&fallback)
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_plugin_metrics(output: &mut String, collector: &MetricsCollector) {
+        let plugins: Vec<_> = collector
+            .plugin_names()
+            .into_iter()
+            .filter_map(|name| Some((Self::sanitize_label(&name), collector.plugin_stats(&name)?)))
+            .collect();
+
+        writeln!(
+            output,
+            "# HELP octopus_plugin_duration_seconds Time spent in each plugin's interceptors per request"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_plugin_duration_seconds histogram").unwrap();
+        for (plugin, stats) in &plugins {
+            for (le, count) in stats.cumulative_buckets() {
+                writeln!(
+                    output,
+                    "octopus_plugin_duration_seconds_bucket{{plugin=\"{plugin}\",le=\"{le}\"}} {count}"
+                )
+                .unwrap();
+            }
+            let invocations = stats.invocations.load(Ordering::Relaxed);
+            writeln!(
+                output,
+                "octopus_plugin_duration_seconds_bucket{{plugin=\"{plugin}\",le=\"+Inf\"}} {invocations}"
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "octopus_plugin_duration_seconds_sum{{plugin=\"{plugin}\"}} {:.6}",
+                stats.total_seconds()
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "octopus_plugin_duration_seconds_count{{plugin=\"{plugin}\"}} {invocations}"
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "# HELP octopus_plugin_errors_total Plugin executions that failed or aborted the request"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_plugin_errors_total counter").unwrap();
+        for (plugin, stats) in &plugins {
+            writeln!(
+                output,
+                "octopus_plugin_errors_total{{plugin=\"{plugin}\"}} {}",
+                stats.errors.load(Ordering::Relaxed)
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_concurrency_metrics(output: &mut String, collector: &MetricsCollector) {
+        let scopes = collector.concurrency_scopes();
+        if scopes.is_empty() {
+            return;
+        }
+
+        Self::write_concurrency_metric(
+            output,
+            &scopes,
+            "octopus_concurrency_in_flight",
+            "gauge",
+            "Requests holding a concurrency slot",
+            |s| s.in_flight.load(Ordering::Relaxed) as u64,
+        );
+        Self::write_concurrency_metric(
+            output,
+            &scopes,
+            "octopus_concurrency_queued",
+            "gauge",
+            "Requests waiting for a concurrency slot",
+            |s| s.queued.load(Ordering::Relaxed) as u64,
+        );
+        Self::write_concurrency_metric(
+            output,
+            &scopes,
+            "octopus_concurrency_rejected_total",
+            "counter",
+            "Requests rejected by a concurrency limit",
+            |s| s.rejected.load(Ordering::Relaxed),
+        );
+    }
+
+    fn write_access_log_metrics(output: &mut String, collector: &MetricsCollector) {
+        writeln!(
+            output,
+            "# HELP octopus_access_log_dropped_total Access log lines dropped because the log buffer was full"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_access_log_dropped_total counter").unwrap();
+        writeln!(
+            output,
+            "octopus_access_log_dropped_total {}",
+            collector.access_log_dropped().load(Ordering::Relaxed)
+        )
+        .unwrap();
+    }
+
+    fn write_concurrency_metric(
+        output: &mut String,
+        scopes: &[(String, Arc<ConcurrencyStats>)],
+        name: &str,
+        kind: &str,
+        help: &str,
+        value: impl Fn(&ConcurrencyStats) -> u64,
+    ) {
+        writeln!(output, "# HELP {name} {help}").unwrap();
+        writeln!(output, "# TYPE {name} {kind}").unwrap();
+        for (scope, stats) in scopes {
+            writeln!(
+                output,
+                "{name}{{scope=\"{}\"}} {}",
+                Self::sanitize_label(scope),
+                value(stats)
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_circuit_metrics(output: &mut String, collector: &MetricsCollector) {
+        let readings = collector.circuit_readings();
+        if readings.is_empty() {
+            return;
+        }
+
+        Self::write_circuit_metric(
+            output,
+            &readings,
+            "octopus_circuit_breaker_state",
+            "gauge",
+            "Circuit breaker state (0 = closed, 1 = open, 2 = half-open)",
+            |r| r.metrics.state.as_gauge().into(),
+        );
+        Self::write_circuit_metric(
+            output,
+            &readings,
+            "octopus_circuit_breaker_trips_total",
+            "counter",
+            "Times the circuit breaker has opened",
+            |r| r.metrics.trips,
+        );
+        Self::write_circuit_metric(
+            output,
+            &readings,
+            "octopus_circuit_breaker_successes_total",
+            "counter",
+            "Successful requests recorded by the circuit breaker",
+            |r| r.metrics.lifetime_successes,
+        );
+        Self::write_circuit_metric(
+            output,
+            &readings,
+            "octopus_circuit_breaker_failures_total",
+            "counter",
+            "Failed requests recorded by the circuit breaker",
+            |r| r.metrics.lifetime_failures,
+        );
+    }
+
+    fn write_circuit_metric(
+        output: &mut String,
+        readings: &[CircuitReading],
+        name: &str,
+        kind: &str,
+        help: &str,
+        value: impl Fn(&CircuitReading) -> u64,
+    ) {
+        writeln!(output, "# HELP {name} {help}").unwrap();
+        writeln!(output, "# TYPE {name} {kind}").unwrap();
+        for reading in readings {
+            writeln!(
+                output,
+                "{name}{{upstream=\"{}\",instance=\"{}\"}} {}",
+                Self::sanitize_label(&reading.upstream),
+                Self::sanitize_label(&reading.instance),
+                value(reading)
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_slo_metrics(output: &mut String, collector: &MetricsCollector) {
+        let Some(slo) = collector.slo() else {
+            return;
+        };
+        let statuses = slo.statuses();
+
+        writeln!(
+            output,
+            "# HELP octopus_slo_success_ratio Ratio of successful requests in the SLO window"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_slo_success_ratio gauge").unwrap();
+        for status in &statuses {
+            writeln!(
+                output,
+                "octopus_slo_success_ratio{{route=\"{}\"}} {}",
+                Self::sanitize_label(&status.route),
+                Self::format_float(status.success_ratio)
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "# HELP octopus_slo_latency_attainment_ratio Ratio of requests under the latency threshold in the SLO window"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_slo_latency_attainment_ratio gauge").unwrap();
+        for status in &statuses {
+            writeln!(
+                output,
+                "octopus_slo_latency_attainment_ratio{{route=\"{}\"}} {}",
+                Self::sanitize_label(&status.route),
+                Self::format_float(status.latency_attainment)
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "# HELP octopus_slo_burn_rate Error budget burn rate (1 = budget spent exactly over the window)"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_slo_burn_rate gauge").unwrap();
+        for status in &statuses {
+            writeln!(
+                output,
+                "octopus_slo_burn_rate{{route=\"{}\"}} {}",
+                Self::sanitize_label(&status.route),
+                Self::format_float(status.burn_rate)
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "# HELP octopus_slo_breaches_total SLO breaches reported to the breach hooks"
+        )
+        .unwrap();
+        writeln!(output, "# TYPE octopus_slo_breaches_total counter").unwrap();
+        for status in &statuses {
+            writeln!(
+                output,
+                "octopus_slo_breaches_total{{route=\"{}\"}} {}",
+                Self::sanitize_label(&status.route),
+                status.breaches
+            )
+            .unwrap();
+        }
+    }
+
+    /// A gauge value in the text format, which spells infinities `+Inf` and
+    /// `-Inf`
+    fn format_float(value: f64) -> String {
+        if value == f64::INFINITY {
+            "+Inf".to_string()
+        } else if value == f64::NEG_INFINITY {
+            "-Inf".to_string()
+        } else {
+            format!("{value:.6}")
+        }
+    }
+
     fn sanitize_label(label: &str) -> String {
         // Replace characters that might cause issues in Prometheus labels
         label
@@ -180,6 +551,165 @@ mod tests {
         assert!(output.contains("octopus_requests_total {} 0"));
     }
 
+    #[test]
+    fn test_export_slo_metrics() {
+        use crate::slo::{SloObjective, SloTracker};
+        use crate::RequestOutcome;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let tracker = Arc::new(SloTracker::new().with_objective("/users", SloObjective::default()));
+        let collector = MetricsCollector::new().with_slo(tracker);
+        collector.record_request("/users", Duration::from_millis(5), RequestOutcome::Success);
+        collector.record_request("/users", Duration::from_millis(5), RequestOutcome::Error);
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("octopus_slo_success_ratio{route=\"/users\"} 0.500000"));
+        assert!(output.contains("octopus_slo_burn_rate{route=\"/users\"} 50.000000"));
+        assert!(output.contains("octopus_slo_breaches_total{route=\"/users\"} 0"));
+    }
+
+    #[test]
+    fn test_export_unbounded_slo_burn_rate() {
+        use crate::slo::{SloObjective, SloTracker};
+        use crate::RequestOutcome;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        // A 100% target leaves no error budget: any error burns it infinitely fast.
+        let objective = SloObjective {
+            success_target: 1.0,
+            ..SloObjective::default()
+        };
+        let tracker = Arc::new(SloTracker::new().with_objective("/users", objective));
+        let collector = MetricsCollector::new().with_slo(tracker);
+        collector.record_request("/users", Duration::from_millis(5), RequestOutcome::Error);
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("octopus_slo_burn_rate{route=\"/users\"} +Inf"));
+    }
+
+    #[test]
+    fn test_export_byte_metrics() {
+        let collector = MetricsCollector::new();
+        collector.record_bytes("/upload", 4096, 17);
+        collector.record_bytes("/upload", 1024, 17);
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_request_bytes_total counter"));
+        assert!(output.contains("octopus_request_bytes_total{route=\"/upload\"} 5120"));
+        assert!(output.contains("octopus_response_bytes_total{route=\"/upload\"} 34"));
+    }
+
+    #[test]
+    fn test_export_upstream_error_metrics() {
+        let collector = MetricsCollector::new();
+        collector.record_upstream_error(octopus_core::ErrorCode::UpstreamRefused);
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_upstream_errors_total counter"));
+        assert!(output.contains("octopus_upstream_errors_total{code=\"UPSTREAM_REFUSED\"} 1"));
+    }
+
+    #[test]
+    fn test_export_failover_metrics() {
+        let collector = MetricsCollector::new();
+        collector.record_failover("orders", "orders-dr");
+        collector.record_failover("orders", "orders-dr");
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_upstream_failovers_total counter"));
+        assert!(output.contains(
+            "octopus_upstream_failovers_total{upstream=\"orders\",fallback=\"orders-dr\"} 2"
+        ));
+    }
+
+    #[test]
+    fn test_export_plugin_metrics() {
+        use std::time::Duration;
+
+        let collector = MetricsCollector::new();
+        collector.record_plugin("auth", Duration::from_millis(2), true);
+        collector.record_plugin("auth", Duration::from_millis(40), false);
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_plugin_duration_seconds histogram"));
+        assert!(output
+            .contains("octopus_plugin_duration_seconds_bucket{plugin=\"auth\",le=\"0.0025\"} 1"));
+        assert!(output
+            .contains("octopus_plugin_duration_seconds_bucket{plugin=\"auth\",le=\"0.05\"} 2"));
+        assert!(output
+            .contains("octopus_plugin_duration_seconds_bucket{plugin=\"auth\",le=\"+Inf\"} 2"));
+        assert!(output.contains("octopus_plugin_duration_seconds_sum{plugin=\"auth\"} 0.042000"));
+        assert!(output.contains("octopus_plugin_duration_seconds_count{plugin=\"auth\"} 2"));
+        assert!(output.contains("octopus_plugin_errors_total{plugin=\"auth\"} 1"));
+    }
+
+    #[test]
+    fn test_export_concurrency_metrics() {
+        let collector = MetricsCollector::new();
+        assert!(!PrometheusExporter::export(&collector).contains("octopus_concurrency"));
+
+        let stats = collector.concurrency_stats("GET /users");
+        stats.in_flight.store(4, Ordering::Relaxed);
+        stats.queued.store(2, Ordering::Relaxed);
+        stats.rejected.store(7, Ordering::Relaxed);
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_concurrency_in_flight gauge"));
+        assert!(output.contains("octopus_concurrency_in_flight{scope=\"GET /users\"} 4"));
+        assert!(output.contains("octopus_concurrency_queued{scope=\"GET /users\"} 2"));
+        assert!(output.contains("# TYPE octopus_concurrency_rejected_total counter"));
+        assert!(output.contains("octopus_concurrency_rejected_total{scope=\"GET /users\"} 7"));
+    }
+
+    #[test]
+    fn test_export_access_log_drops() {
+        let collector = MetricsCollector::new();
+        collector
+            .access_log_dropped()
+            .fetch_add(3, Ordering::Relaxed);
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_access_log_dropped_total counter"));
+        assert!(output.contains("octopus_access_log_dropped_total 3"));
+    }
+
+    #[test]
+    fn test_export_circuit_breaker_metrics() {
+        use octopus_health::{CircuitBreaker, CircuitBreakerConfig};
+
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 2,
+            ..Default::default()
+        }));
+        let collector = MetricsCollector::new().with_circuit_breaker(
+            Arc::clone(&breaker),
+            Arc::new(|instance: &str| instance.starts_with("users-").then(|| "users".to_string())),
+        );
+        assert!(!PrometheusExporter::export(&collector).contains("octopus_circuit"));
+
+        breaker.record_success("users-1");
+        breaker.record_failure("users-1");
+        breaker.record_success("orders-1");
+
+        let output = PrometheusExporter::export(&collector);
+        assert!(output.contains("# TYPE octopus_circuit_breaker_state gauge"));
+        assert!(output
+            .contains("octopus_circuit_breaker_state{upstream=\"users\",instance=\"users-1\"} 1"));
+        assert!(output.contains(
+            "octopus_circuit_breaker_trips_total{upstream=\"users\",instance=\"users-1\"} 1"
+        ));
+        assert!(output.contains(
+            "octopus_circuit_breaker_failures_total{upstream=\"users\",instance=\"users-1\"} 1"
+        ));
+        assert!(output.contains(
+            "octopus_circuit_breaker_successes_total{upstream=\"users\",instance=\"users-1\"} 1"
+        ));
+        assert!(output.contains(
+            "octopus_circuit_breaker_state{upstream=\"unknown\",instance=\"orders-1\"} 0"
+        ));
+    }
+
     #[test]
     fn test_export_format() {
         let collector = MetricsCollector::new();
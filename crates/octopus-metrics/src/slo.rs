@@ -0,0 +1,429 @@
+//! Per-route SLO tracking
+//!
+//! Tracks, over a rolling window, the share of successful requests and of
+//! requests under a latency threshold for each route with an objective, and
+//! derives the error-budget burn rate: the observed bad-request ratio divided
+//! by the ratio the objective allows (`1 - target`). A burn rate of 1 spends
+//! the budget exactly over the window; above `burn_rate_threshold` the
+//! registered [`SloHook`]s fire once, re-arming when the rate recovers.
+
+use super::*;
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+/// Number of buckets a rolling window is split into
+const WINDOW_BUCKETS: u32 = 60;
+
+/// A route's service-level objective
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloObjective {
+    /// Target ratio of successful requests (e.g. `0.99`)
+    pub success_target: f64,
+    /// Requests slower than this count against the latency objective
+    pub latency_threshold: Duration,
+    /// Target ratio of requests under `latency_threshold` (e.g. `0.99`)
+    pub latency_target: f64,
+    /// Rolling window the ratios are computed over
+    pub window: Duration,
+    /// Burn rate at or above which the breach hooks fire
+    pub burn_rate_threshold: f64,
+    /// Requests needed in the window before the SLO is evaluated
+    pub min_requests: u64,
+}
+
+impl Default for SloObjective {
+    fn default() -> Self {
+        Self {
+            success_target: 0.99,
+            latency_threshold: Duration::from_millis(200),
+            latency_target: 0.99,
+            window: Duration::from_secs(300),
+            burn_rate_threshold: 2.0,
+            min_requests: 20,
+        }
+    }
+}
+
+/// Which objective a burn rate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SloIndicator {
+    /// Success-rate objective
+    Availability,
+    /// Latency objective
+    Latency,
+}
+
+/// Current SLO attainment of a route
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloStatus {
+    /// Route the status is for
+    pub route: String,
+    /// Requests in the window
+    pub requests: u64,
+    /// Ratio of successful requests in the window
+    pub success_ratio: f64,
+    /// Ratio of requests under the latency threshold in the window
+    pub latency_attainment: f64,
+    /// Highest burn rate of the two objectives
+    pub burn_rate: f64,
+    /// Objective with the highest burn rate
+    pub indicator: SloIndicator,
+    /// Breach hooks fired since the tracker started
+    pub breaches: u64,
+}
+
+/// Receives SLO breaches
+pub trait SloHook: Send + Sync {
+    /// Called once when a route's burn rate reaches its threshold; not called
+    /// again for the route until the burn rate drops below it
+    fn on_slo_breach(&self, breach: &SloStatus);
+}
+
+impl<F> SloHook for F
+where
+    F: Fn(&SloStatus) + Send + Sync,
+{
+    fn on_slo_breach(&self, breach: &SloStatus) {
+        self(breach)
+    }
+}
+
+/// Requests recorded in one slice of the window
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: Instant,
+    requests: u64,
+    errors: u64,
+    slow: u64,
+}
+
+/// Rolling window state of one route
+#[derive(Debug, Default)]
+struct RouteWindow {
+    buckets: VecDeque<Bucket>,
+    breached: bool,
+    breaches: u64,
+}
+
+impl RouteWindow {
+    fn record(&mut self, now: Instant, objective: &SloObjective, error: bool, slow: bool) {
+        let width = objective.window / WINDOW_BUCKETS;
+        match self.buckets.back_mut() {
+            Some(bucket) if now.saturating_duration_since(bucket.start) < width => {
+                bucket.requests += 1;
+                bucket.errors += u64::from(error);
+                bucket.slow += u64::from(slow);
+            }
+            _ => self.buckets.push_back(Bucket {
+                start: now,
+                requests: 1,
+                errors: u64::from(error),
+                slow: u64::from(slow),
+            }),
+        }
+        while self
+            .buckets
+            .front()
+            .is_some_and(|b| now.saturating_duration_since(b.start) >= objective.window)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn status(&self, route: &str, objective: &SloObjective) -> SloStatus {
+        let (requests, errors, slow) = self.buckets.iter().fold((0, 0, 0), |acc, b| {
+            (acc.0 + b.requests, acc.1 + b.errors, acc.2 + b.slow)
+        });
+        let ratio = |bad: u64| {
+            if requests == 0 {
+                0.0
+            } else {
+                bad as f64 / requests as f64
+            }
+        };
+        let burn = |bad_ratio: f64, target: f64| {
+            let budget = 1.0 - target;
+            if budget <= 0.0 {
+                if bad_ratio > 0.0 {
+                    f64::INFINITY
+                } else {
+                    0.0
+                }
+            } else {
+                bad_ratio / budget
+            }
+        };
+        let availability_burn = burn(ratio(errors), objective.success_target);
+        let latency_burn = burn(ratio(slow), objective.latency_target);
+        let (burn_rate, indicator) = if latency_burn > availability_burn {
+            (latency_burn, SloIndicator::Latency)
+        } else {
+            (availability_burn, SloIndicator::Availability)
+        };
+
+        SloStatus {
+            route: route.to_string(),
+            requests,
+            success_ratio: 1.0 - ratio(errors),
+            latency_attainment: 1.0 - ratio(slow),
+            burn_rate,
+            indicator,
+            breaches: self.breaches,
+        }
+    }
+}
+
+/// Per-route SLO tracker, fed by [`MetricsCollector::record_request`]
+pub struct SloTracker {
+    /// Objective for routes without their own (`None` = untracked)
+    default_objective: Option<SloObjective>,
+    /// Objectives by route
+    objectives: HashMap<String, SloObjective>,
+    windows: DashMap<String, parking_lot::Mutex<RouteWindow>>,
+    hooks: Vec<Arc<dyn SloHook>>,
+}
+
+impl SloTracker {
+    /// Create a tracker; only routes given an objective are tracked
+    pub fn new() -> Self {
+        Self {
+            default_objective: None,
+            objectives: HashMap::new(),
+            windows: DashMap::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Track every route without its own objective against `objective`
+    pub fn with_default_objective(mut self, objective: SloObjective) -> Self {
+        self.default_objective = Some(objective);
+        self
+    }
+
+    /// Set the objective of a route
+    pub fn with_objective(mut self, route: impl Into<String>, objective: SloObjective) -> Self {
+        self.objectives.insert(route.into(), objective);
+        self
+    }
+
+    /// Register a breach hook
+    pub fn with_hook(mut self, hook: Arc<dyn SloHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// The objective a route is tracked against, if any
+    pub fn objective(&self, route: &str) -> Option<&SloObjective> {
+        self.objectives
+            .get(route)
+            .or(self.default_objective.as_ref())
+    }
+
+    /// Record a request
+    pub fn record(&self, route: &str, latency: Duration, outcome: RequestOutcome) {
+        self.record_at(route, latency, outcome, Instant::now());
+    }
+
+    fn record_at(&self, route: &str, latency: Duration, outcome: RequestOutcome, now: Instant) {
+        let Some(objective) = self.objective(route) else {
+            return;
+        };
+        let error = outcome != RequestOutcome::Success;
+        let slow = latency > objective.latency_threshold;
+
+        let breach = {
+            let window = self.windows.entry(route.to_string()).or_default();
+            let mut window = window.lock();
+            window.record(now, objective, error, slow);
+            let status = window.status(route, objective);
+            if status.requests < objective.min_requests
+                || status.burn_rate < objective.burn_rate_threshold
+            {
+                window.breached = false;
+                None
+            } else if window.breached {
+                None
+            } else {
+                window.breached = true;
+                window.breaches += 1;
+                Some(SloStatus {
+                    breaches: window.breaches,
+                    ..status
+                })
+            }
+        };
+
+        if let Some(breach) = breach {
+            tracing::warn!(
+                route = %breach.route,
+                burn_rate = breach.burn_rate,
+                indicator = ?breach.indicator,
+                success_ratio = breach.success_ratio,
+                latency_attainment = breach.latency_attainment,
+                "SLO error budget burning too fast"
+            );
+            for hook in &self.hooks {
+                hook.on_slo_breach(&breach);
+            }
+        }
+    }
+
+    /// Current status of a tracked route that has seen requests
+    pub fn status(&self, route: &str) -> Option<SloStatus> {
+        let objective = self.objective(route)?;
+        let window = self.windows.get(route)?;
+        let status = window.lock().status(route, objective);
+        Some(status)
+    }
+
+    /// Current status of every tracked route that has seen requests, sorted
+    /// by route
+    pub fn statuses(&self) -> Vec<SloStatus> {
+        let mut statuses: Vec<SloStatus> = self
+            .windows
+            .iter()
+            .filter_map(|entry| {
+                let objective = self.objective(entry.key())?;
+                Some(entry.value().lock().status(entry.key(), objective))
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.route.cmp(&b.route));
+        statuses
+    }
+}
+
+impl Default for SloTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for SloTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SloTracker")
+            .field("default_objective", &self.default_objective)
+            .field("objectives", &self.objectives.len())
+            .field("routes", &self.windows.len())
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capturing_tracker(
+        objective: SloObjective,
+    ) -> (SloTracker, Arc<parking_lot::Mutex<Vec<SloStatus>>>) {
+        let breaches = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&breaches);
+        let tracker = SloTracker::new()
+            .with_objective("/api/users", objective)
+            .with_hook(Arc::new(move |b: &SloStatus| sink.lock().push(b.clone())));
+        (tracker, breaches)
+    }
+
+    #[test]
+    fn test_latency_breach_fires_hook_with_route_and_burn_rate() {
+        let (tracker, breaches) = capturing_tracker(SloObjective::default());
+        let now = Instant::now();
+
+        // 10% of requests over 200ms against a 99% objective: burn rate 10.
+        for i in 0..100 {
+            let latency = if i % 10 == 0 { 500 } else { 50 };
+            tracker.record_at(
+                "/api/users",
+                Duration::from_millis(latency),
+                RequestOutcome::Success,
+                now,
+            );
+        }
+
+        let breaches = breaches.lock();
+        assert_eq!(breaches.len(), 1, "hook fires once per breach");
+        let breach = &breaches[0];
+        assert_eq!(breach.route, "/api/users");
+        assert_eq!(breach.indicator, SloIndicator::Latency);
+        assert!(breach.burn_rate >= 2.0);
+
+        let status = tracker.status("/api/users").unwrap();
+        assert_eq!(status.requests, 100);
+        assert!((status.latency_attainment - 0.9).abs() < 1e-9);
+        assert!((status.burn_rate - 10.0).abs() < 1e-6);
+        assert_eq!(status.breaches, 1);
+    }
+
+    #[test]
+    fn test_error_breach_reports_availability_burn_rate() {
+        let (tracker, breaches) = capturing_tracker(SloObjective {
+            min_requests: 50,
+            ..SloObjective::default()
+        });
+        let now = Instant::now();
+
+        for i in 0..100 {
+            let outcome = if i % 20 == 0 {
+                RequestOutcome::Error
+            } else {
+                RequestOutcome::Success
+            };
+            tracker.record_at("/api/users", Duration::from_millis(10), outcome, now);
+        }
+
+        let breaches = breaches.lock();
+        assert_eq!(breaches.len(), 1);
+        let breach = &breaches[0];
+        assert_eq!(breach.route, "/api/users");
+        assert_eq!(breach.indicator, SloIndicator::Availability);
+        // Fired on the 50th request, with 3 errors (0, 20, 40): 6% / 1%.
+        assert_eq!(breach.requests, 50);
+        assert!((breach.burn_rate - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_no_breach_within_budget_or_below_min_requests() {
+        let (tracker, breaches) = capturing_tracker(SloObjective::default());
+        let now = Instant::now();
+
+        // Every request fails, but fewer than min_requests.
+        for _ in 0..19 {
+            tracker.record_at("/api/users", Duration::ZERO, RequestOutcome::Error, now);
+        }
+        // Untracked routes are ignored.
+        for _ in 0..100 {
+            tracker.record_at("/other", Duration::ZERO, RequestOutcome::Error, now);
+        }
+
+        assert!(breaches.lock().is_empty());
+        assert!(tracker.status("/other").is_none());
+    }
+
+    #[test]
+    fn test_breach_rearms_after_window_rolls_over() {
+        let objective = SloObjective {
+            window: Duration::from_secs(60),
+            ..SloObjective::default()
+        };
+        let (tracker, breaches) = capturing_tracker(objective);
+        let start = Instant::now();
+
+        for _ in 0..20 {
+            tracker.record_at("/api/users", Duration::ZERO, RequestOutcome::Error, start);
+        }
+        assert_eq!(breaches.lock().len(), 1);
+
+        // The failures age out; healthy traffic recovers the SLO.
+        let later = start + Duration::from_secs(61);
+        for _ in 0..20 {
+            tracker.record_at("/api/users", Duration::ZERO, RequestOutcome::Success, later);
+        }
+        assert_eq!(tracker.status("/api/users").unwrap().burn_rate, 0.0);
+
+        let again = later + Duration::from_secs(61);
+        for _ in 0..20 {
+            tracker.record_at("/api/users", Duration::ZERO, RequestOutcome::Error, again);
+        }
+        assert_eq!(breaches.lock().len(), 2);
+    }
+}
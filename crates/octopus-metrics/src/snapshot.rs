@@ -12,6 +12,12 @@ pub struct RouteMetrics {
     pub request_count: u64,
     /// Error count
     pub error_count: u64,
+    /// Request body bytes received
+    #[serde(default)]
+    pub request_bytes: u64,
+    /// Response body bytes sent (after compression)
+    #[serde(default)]
+    pub response_bytes: u64,
     /// Average latency in milliseconds
     pub avg_latency_ms: f64,
     /// Minimum latency in milliseconds
@@ -28,6 +34,21 @@ pub struct RouteMetrics {
     pub error_rate: f64,
 }
 
+/// Snapshot of one plugin's execution metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginMetrics {
+    /// Plugin name
+    pub name: String,
+    /// Requests the plugin ran on
+    pub invocations: u64,
+    /// Executions that failed or aborted the request
+    pub errors: u64,
+    /// Average execution time in milliseconds
+    pub avg_latency_ms: f64,
+    /// Slowest execution in milliseconds
+    pub max_latency_ms: f64,
+}
+
 /// Complete metrics snapshot
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricsSnapshot {
@@ -37,6 +58,12 @@ pub struct MetricsSnapshot {
     pub total_requests: u64,
     /// Total errors across all routes
     pub total_errors: u64,
+    /// Request body bytes received across all routes
+    #[serde(default)]
+    pub total_request_bytes: u64,
+    /// Response body bytes sent across all routes (after compression)
+    #[serde(default)]
+    pub total_response_bytes: u64,
     /// Number of active connections
     pub active_connections: usize,
     /// Number of unique routes
@@ -49,6 +76,9 @@ pub struct MetricsSnapshot {
     pub uptime_seconds: u64,
     /// Per-route metrics
     pub routes: Vec<RouteMetrics>,
+    /// Per-plugin execution metrics, sorted by name
+    #[serde(default)]
+    pub plugins: Vec<PluginMetrics>,
 }
 
 impl MetricsSnapshot {
@@ -57,6 +87,8 @@ impl MetricsSnapshot {
         let timestamp = current_timestamp_ms();
         let total_requests = collector.total_requests();
         let total_errors = collector.total_errors();
+        let total_request_bytes = collector.total_request_bytes();
+        let total_response_bytes = collector.total_response_bytes();
         let active_connections = collector.active_connections();
         let route_count = collector.route_count();
         let global_avg_latency_ms = collector.global_avg_latency_ms();
@@ -71,6 +103,8 @@ impl MetricsSnapshot {
                     path: route_name,
                     request_count: stats.request_count.load(Ordering::Relaxed),
                     error_count: stats.error_count.load(Ordering::Relaxed),
+                    request_bytes: stats.request_bytes.load(Ordering::Relaxed),
+                    response_bytes: stats.response_bytes.load(Ordering::Relaxed),
                     avg_latency_ms: stats.avg_latency_ms(),
                     min_latency_ms: stats.min_latency_ms(),
                     max_latency_ms: stats.max_latency_ms(),
@@ -85,16 +119,34 @@ impl MetricsSnapshot {
         // Sort routes by request count (descending)
         routes.sort_by_key(|b| std::cmp::Reverse(b.request_count));
 
+        let plugins = collector
+            .plugin_names()
+            .into_iter()
+            .filter_map(|name| {
+                let stats = collector.plugin_stats(&name)?;
+                Some(PluginMetrics {
+                    invocations: stats.invocations.load(Ordering::Relaxed),
+                    errors: stats.errors.load(Ordering::Relaxed),
+                    avg_latency_ms: stats.avg_latency_ms(),
+                    max_latency_ms: stats.max_latency_ms(),
+                    name,
+                })
+            })
+            .collect();
+
         Self {
             timestamp,
             total_requests,
             total_errors,
+            total_request_bytes,
+            total_response_bytes,
             active_connections,
             route_count,
             global_avg_latency_ms,
             global_error_rate,
             uptime_seconds,
             routes,
+            plugins,
         }
     }
 
@@ -145,18 +197,49 @@ mod tests {
         assert_eq!(snapshot.routes.len(), 2);
     }
 
+    #[test]
+    fn test_snapshot_includes_bytes() {
+        let collector = MetricsCollector::new();
+        collector.record_request("/users", Duration::from_millis(5), RequestOutcome::Success);
+        collector.record_bytes("/users", 100, 2000);
+
+        let snapshot = MetricsSnapshot::from_collector(&collector);
+
+        assert_eq!(snapshot.total_request_bytes, 100);
+        assert_eq!(snapshot.total_response_bytes, 2000);
+        assert_eq!(snapshot.routes[0].request_bytes, 100);
+        assert_eq!(snapshot.routes[0].response_bytes, 2000);
+    }
+
+    #[test]
+    fn test_snapshot_includes_plugins() {
+        let collector = MetricsCollector::new();
+        collector.record_plugin("auth", Duration::from_millis(4), false);
+
+        let snapshot = MetricsSnapshot::from_collector(&collector);
+
+        assert_eq!(snapshot.plugins.len(), 1);
+        assert_eq!(snapshot.plugins[0].name, "auth");
+        assert_eq!(snapshot.plugins[0].invocations, 1);
+        assert_eq!(snapshot.plugins[0].errors, 1);
+        assert_eq!(snapshot.plugins[0].max_latency_ms, 4.0);
+    }
+
     #[test]
     fn test_formatted_uptime() {
         let snapshot = MetricsSnapshot {
             timestamp: 0,
             total_requests: 0,
             total_errors: 0,
+            total_request_bytes: 0,
+            total_response_bytes: 0,
             active_connections: 0,
             route_count: 0,
             global_avg_latency_ms: 0.0,
             global_error_rate: 0.0,
             uptime_seconds: 3665, // 1h 1m 5s
             routes: vec![],
+            plugins: vec![],
         };
 
         let uptime = snapshot.formatted_uptime();
@@ -171,6 +254,8 @@ mod tests {
                 path: "/users".to_string(),
                 request_count: 100,
                 error_count: 0,
+                request_bytes: 0,
+                response_bytes: 0,
                 avg_latency_ms: 50.0,
                 min_latency_ms: 10.0,
                 max_latency_ms: 100.0,
@@ -183,6 +268,8 @@ mod tests {
                 path: "/posts".to_string(),
                 request_count: 50,
                 error_count: 0,
+                request_bytes: 0,
+                response_bytes: 0,
                 avg_latency_ms: 30.0,
                 min_latency_ms: 10.0,
                 max_latency_ms: 50.0,
@@ -197,12 +284,15 @@ mod tests {
             timestamp: 0,
             total_requests: 150,
             total_errors: 0,
+            total_request_bytes: 0,
+            total_response_bytes: 0,
             active_connections: 0,
             route_count: 2,
             global_avg_latency_ms: 40.0,
             global_error_rate: 0.0,
             uptime_seconds: 100,
             routes,
+            plugins: vec![],
         };
 
         let top = snapshot.top_routes(1);
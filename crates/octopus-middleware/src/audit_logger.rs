@@ -7,6 +7,7 @@
 //! - Rate limiting violations
 //! - Suspicious activity
 //! - Admin actions
+//! - Upstream schema violations (dev-mode response validation)
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -41,6 +42,8 @@ pub enum AuditEventType {
     SuspiciousActivity,
     /// Request blocked by security policy
     RequestBlocked,
+    /// Upstream response did not match its declared schema
+    SchemaViolation,
 }
 
 /// Audit event
@@ -67,7 +70,7 @@ pub struct AuditEvent {
 }
 
 impl AuditEvent {
-    fn new(event_type: AuditEventType) -> Self {
+    pub(crate) fn new(event_type: AuditEventType) -> Self {
         Self {
             timestamp: Utc::now(),
             event_type,
@@ -82,6 +85,9 @@ impl AuditEvent {
     }
 }
 
+/// `tracing` target of audit events
+pub use octopus_core::AUDIT_LOG_TARGET;
+
 /// Audit output destination
 #[derive(Debug, Clone)]
 pub enum AuditOutput {
@@ -238,6 +244,7 @@ impl AuditLogger {
             AuditEventType::ConfigChange => self.config.log_config_change,
             AuditEventType::SuspiciousActivity => self.config.log_suspicious,
             AuditEventType::RequestBlocked => true, // Always log blocked requests
+            AuditEventType::SchemaViolation => true, // Only emitted when validation is on
         };
 
         if !should_log {
@@ -18,7 +18,7 @@ use octopus_auth::{
 };
 use octopus_config::types::AuthConfig;
 use octopus_core::middleware::{Middleware, Next};
-use octopus_core::Result;
+use octopus_core::{ErrorResponse, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{debug, warn};
@@ -115,7 +115,7 @@ impl AuthGatewayMiddleware {
         false
     }
 
-    /// Build a structured JSON error response
+    /// Build a structured (problem+json) error response
     fn error_response(
         &self,
         status: StatusCode,
@@ -124,28 +124,17 @@ impl AuthGatewayMiddleware {
         provider: Option<&str>,
         extra: Option<serde_json::Value>,
     ) -> Response<Full<Bytes>> {
-        let mut body = serde_json::json!({
-            "error": error,
-            "message": message,
-        });
+        let mut problem = ErrorResponse::new(status, error).detail(message);
 
         if let Some(p) = provider {
-            body["provider"] = serde_json::json!(p);
+            problem = problem.extension("provider", p);
         }
-        if let Some(extra) = extra {
-            if let Some(obj) = extra.as_object() {
-                for (k, v) in obj {
-                    body[k] = v.clone();
-                }
+        if let Some(serde_json::Value::Object(obj)) = extra {
+            for (k, v) in obj {
+                problem = problem.extension(k, v);
             }
         }
 
-        let body_bytes = serde_json::to_vec(&body).unwrap_or_default();
-
-        let mut builder = Response::builder()
-            .status(status)
-            .header("Content-Type", "application/json");
-
         // Only add WWW-Authenticate for 401 with Bearer-based providers
         if status == StatusCode::UNAUTHORIZED {
             let challenge = match provider.and_then(|p| self.registry.get(p)) {
@@ -158,18 +147,11 @@ impl AuthGatewayMiddleware {
                 None => Some("Bearer"), // default
             };
             if let Some(c) = challenge {
-                builder = builder.header("WWW-Authenticate", c);
+                problem = problem.header(http::header::WWW_AUTHENTICATE, c);
             }
         }
 
-        builder
-            .body(Full::new(Bytes::from(body_bytes)))
-            .unwrap_or_else(|_| {
-                Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Full::new(Bytes::new()))
-                    .unwrap()
-            })
+        problem.into_response()
     }
 }
 
@@ -342,7 +324,16 @@ impl Middleware for AuthGatewayMiddleware {
                     }
                 }
 
-                // Store principal in request extensions
+                // Store principal in request extensions, plus the crate-neutral
+                // `AuthContext` for consumers that cannot depend on octopus-auth
+                // (e.g. authorization scripts).
+                req.extensions_mut().insert(octopus_core::AuthContext {
+                    subject: principal.id.clone(),
+                    provider: principal.provider.clone(),
+                    roles: principal.roles.clone(),
+                    scopes: principal.scopes.clone(),
+                    claims: principal.attributes.clone(),
+                });
                 req.extensions_mut().insert(principal.clone());
 
                 // Set rate limit key by identity
@@ -1,27 +1,80 @@
 //! JSON body transformation middleware
 //!
-//! Applies field-level transformations (remove, rename, set, redact) to
-//! JSON request and response bodies. Only operates on payloads with
-//! `Content-Type: application/json`.
+//! Applies field-level transformations (remove, rename, set, default, wrap,
+//! unwrap, redact) to JSON request and response bodies. Only operates on
+//! payloads with `Content-Type: application/json`; bodies larger than
+//! `max_body_size` pass through untouched.
+//!
+//! Paths are dot-separated (`user.name`) or JSONPath-style (`$.items[0].id`).
+//! Rules come from [`BodyTransformConfig`], or per route from a
+//! [`MatchedRouteTransform`] request extension, which takes precedence.
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use http::{header, Request, Response};
 use http_body_util::{BodyExt, Full};
+use octopus_config::types::JsonTransformRule;
+use octopus_core::json_path::{self, Segment};
 use octopus_core::{Error, Middleware, Next, Result};
 use serde_json::Value;
 use std::fmt;
+use std::sync::Arc;
 
 /// Body type alias
 pub type Body = Full<Bytes>;
 
+/// Default largest body transformed (1 MiB)
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
 /// JSON body transformation configuration
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct BodyTransformConfig {
     /// Rules applied to the request body before forwarding
     pub request_rules: Vec<BodyRule>,
     /// Rules applied to the response body before returning to the client
     pub response_rules: Vec<BodyRule>,
+    /// Largest body transformed (bytes); larger bodies pass through untouched
+    pub max_body_size: usize,
+}
+
+impl Default for BodyTransformConfig {
+    fn default() -> Self {
+        Self {
+            request_rules: Vec::new(),
+            response_rules: Vec::new(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+}
+
+/// Per-route transform rules, injected as a request extension by the handler
+/// for the matched route. Overrides the middleware's configured rules.
+#[derive(Debug, Clone)]
+pub struct MatchedRouteTransform {
+    /// Rules applied to the request body
+    pub request_rules: Arc<[BodyRule]>,
+    /// Rules applied to the response body
+    pub response_rules: Arc<[BodyRule]>,
+    /// Largest body transformed (bytes)
+    pub max_body_size: usize,
+}
+
+impl From<&octopus_config::types::RouteTransformConfig> for MatchedRouteTransform {
+    fn from(config: &octopus_config::types::RouteTransformConfig) -> Self {
+        Self {
+            request_rules: config.request.iter().map(BodyRule::from).collect(),
+            response_rules: config.response.iter().map(BodyRule::from).collect(),
+            max_body_size: config.max_body_size,
+        }
+    }
+}
+
+impl MatchedRouteTransform {
+    /// Apply the response rules to one JSON value, such as an event of a
+    /// streamed response
+    pub fn transform_response(&self, value: Value) -> Value {
+        BodyTransform::apply_rules(value, &self.response_rules)
+    }
 }
 
 /// A single body transformation rule
@@ -43,10 +96,45 @@ pub enum BodyRule {
         /// Value to set
         value: Value,
     },
+    /// Set a field at the given path when it is absent or null
+    DefaultField {
+        /// Dot-separated path
+        path: String,
+        /// Default value
+        value: Value,
+    },
+    /// Nest the whole body under the given path
+    Wrap(String),
+    /// Replace the whole body with the value at the given path (no-op when
+    /// the path is absent)
+    Unwrap(String),
     /// Replace the value at the given path with "***REDACTED***"
     RedactField(String),
 }
 
+impl From<&JsonTransformRule> for BodyRule {
+    fn from(rule: &JsonTransformRule) -> Self {
+        match rule {
+            JsonTransformRule::Rename { from, to } => Self::RenameField {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            JsonTransformRule::Remove { path } => Self::RemoveField(path.clone()),
+            JsonTransformRule::Set { path, value } => Self::SetField {
+                path: path.clone(),
+                value: value.clone(),
+            },
+            JsonTransformRule::Default { path, value } => Self::DefaultField {
+                path: path.clone(),
+                value: value.clone(),
+            },
+            JsonTransformRule::Wrap { path } => Self::Wrap(path.clone()),
+            JsonTransformRule::Unwrap { path } => Self::Unwrap(path.clone()),
+            JsonTransformRule::Redact { path } => Self::RedactField(path.clone()),
+        }
+    }
+}
+
 /// JSON body transformation middleware
 #[derive(Clone)]
 pub struct BodyTransform {
@@ -88,8 +176,25 @@ impl BodyTransform {
                 BodyRule::SetField { path, value: val } => {
                     set_at_path(&mut value, path, val.clone());
                 }
+                BodyRule::DefaultField { path, value: val } => {
+                    if json_path::get(&value, path).map_or(true, Value::is_null) {
+                        set_at_path(&mut value, path, val.clone());
+                    }
+                }
+                BodyRule::Wrap(path) => {
+                    if !json_path::segments(path).is_empty() {
+                        let mut wrapped = Value::Object(serde_json::Map::new());
+                        set_at_path(&mut wrapped, path, value);
+                        value = wrapped;
+                    }
+                }
+                BodyRule::Unwrap(path) => {
+                    if let Some(inner) = get_at_path_mut(&mut value, path).map(Value::take) {
+                        value = inner;
+                    }
+                }
                 BodyRule::RedactField(path) => {
-                    if get_at_path(&value, path).is_some() {
+                    if json_path::get(&value, path).is_some() {
                         set_at_path(
                             &mut value,
                             path,
@@ -101,6 +206,30 @@ impl BodyTransform {
         }
         value
     }
+
+    /// Transform a JSON body. Returns `None` when it is left untouched
+    /// (too large or not valid JSON).
+    fn transform_body(
+        bytes: &[u8],
+        rules: &[BodyRule],
+        max_body_size: usize,
+    ) -> Result<Option<Bytes>> {
+        if bytes.len() > max_body_size {
+            tracing::debug!(
+                size = bytes.len(),
+                max_body_size,
+                "Body too large to transform"
+            );
+            return Ok(None);
+        }
+        let Ok(json) = serde_json::from_slice::<Value>(bytes) else {
+            return Ok(None);
+        };
+        let transformed = Self::apply_rules(json, rules);
+        serde_json::to_vec(&transformed)
+            .map(|v| Some(Bytes::from(v)))
+            .map_err(|e| Error::Internal(format!("JSON serialization failed: {e}")))
+    }
 }
 
 impl Default for BodyTransform {
@@ -114,69 +243,88 @@ impl fmt::Debug for BodyTransform {
         f.debug_struct("BodyTransform")
             .field("request_rules", &self.config.request_rules.len())
             .field("response_rules", &self.config.response_rules.len())
+            .field("max_body_size", &self.config.max_body_size)
             .finish()
     }
 }
 
 // ---------------------------------------------------------------------------
-// JSON path helpers (dot-separated)
+// JSON path helpers (dot-separated or JSONPath-style; see
+// `octopus_core::json_path`)
 // ---------------------------------------------------------------------------
 
-/// Split a dot-separated path into segments.
-fn path_segments(path: &str) -> Vec<&str> {
-    path.split('.').filter(|s| !s.is_empty()).collect()
-}
-
-/// Navigate to the parent of the target key and return a mutable reference to
-/// the parent object together with the final key segment.
-fn navigate_to_parent_mut<'a, 'b>(
-    root: &'a mut Value,
-    segments: &'b [&'b str],
-) -> Option<(&'a mut Value, &'b str)> {
-    if segments.is_empty() {
-        return None;
-    }
-    if segments.len() == 1 {
-        return Some((root, segments[0]));
+fn child_mut<'a>(value: &'a mut Value, segment: &Segment) -> Option<&'a mut Value> {
+    match segment {
+        Segment::Key(key) => value.get_mut(key.as_str()),
+        Segment::Index(index) => value.get_mut(*index),
     }
-    let mut current = root;
-    for &seg in &segments[..segments.len() - 1] {
-        current = current.get_mut(seg)?;
-    }
-    Some((current, segments[segments.len() - 1]))
 }
 
-/// Remove the value at a dot-separated path, returning the removed value.
+/// Remove the value at a path, returning the removed value.
 fn remove_at_path(root: &mut Value, path: &str) -> Option<Value> {
-    let segs = path_segments(path);
-    let (parent, key) = navigate_to_parent_mut(root, &segs)?;
-    parent.as_object_mut()?.remove(key)
+    let segs = json_path::segments(path);
+    let (last, parents) = segs.split_last()?;
+    let mut parent = root;
+    for seg in parents {
+        parent = child_mut(parent, seg)?;
+    }
+    match last {
+        Segment::Key(key) => parent.as_object_mut()?.remove(key),
+        Segment::Index(index) => {
+            let array = parent.as_array_mut()?;
+            (*index < array.len()).then(|| array.remove(*index))
+        }
+    }
 }
 
-/// Set a value at a dot-separated path, creating intermediate objects as needed.
-fn set_at_path(root: &mut Value, path: &str, val: Value) {
-    let segs = path_segments(path);
-    if segs.is_empty() {
+/// Set a value at a path, creating intermediate objects as needed. Array
+/// elements must already exist; paths through non-container values are left
+/// alone.
+pub(crate) fn set_at_path(root: &mut Value, path: &str, val: Value) {
+    let segs = json_path::segments(path);
+    let Some((last, parents)) = segs.split_last() else {
         return;
-    }
+    };
     let mut current = root;
-    for &seg in &segs[..segs.len() - 1] {
-        if !current.get(seg).map(|v| v.is_object()).unwrap_or(false) {
-            current[seg] = Value::Object(serde_json::Map::new());
+    for seg in parents {
+        if let Segment::Key(key) = seg {
+            if current.is_null() {
+                *current = Value::Object(serde_json::Map::new());
+            }
+            if let Some(object) = current.as_object_mut() {
+                let entry = object.entry(key.clone()).or_insert(Value::Null);
+                if !entry.is_object() && !entry.is_array() {
+                    *entry = Value::Object(serde_json::Map::new());
+                }
+            }
+        }
+        let Some(next) = child_mut(current, seg) else {
+            return;
+        };
+        current = next;
+    }
+    match last {
+        Segment::Key(key) => {
+            if current.is_null() {
+                *current = Value::Object(serde_json::Map::new());
+            }
+            if let Some(object) = current.as_object_mut() {
+                object.insert(key.clone(), val);
+            }
+        }
+        Segment::Index(_) => {
+            if let Some(slot) = child_mut(current, last) {
+                *slot = val;
+            }
         }
-        current = current.get_mut(seg).unwrap();
     }
-    current[segs[segs.len() - 1]] = val;
 }
 
-/// Get a reference to the value at a dot-separated path.
-fn get_at_path<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
-    let segs = path_segments(path);
-    let mut current = root;
-    for seg in segs {
-        current = current.get(seg)?;
-    }
-    Some(current)
+/// Get a mutable reference to the value at a path.
+fn get_at_path_mut<'a>(root: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    json_path::segments(path)
+        .iter()
+        .try_fold(root, |current, seg| child_mut(current, seg))
 }
 
 // ---------------------------------------------------------------------------
@@ -186,35 +334,39 @@ fn get_at_path<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
 #[async_trait]
 impl Middleware for BodyTransform {
     async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
-        let has_request_rules = !self.config.request_rules.is_empty();
-        let has_response_rules = !self.config.response_rules.is_empty();
+        let route = req.extensions().get::<MatchedRouteTransform>().cloned();
+        let (request_rules, response_rules, max_body_size) = match &route {
+            Some(route) => (
+                &route.request_rules[..],
+                &route.response_rules[..],
+                route.max_body_size,
+            ),
+            None => (
+                &self.config.request_rules[..],
+                &self.config.response_rules[..],
+                self.config.max_body_size,
+            ),
+        };
 
         // --- Transform request body ---
-        let req = if has_request_rules && Self::is_json_content_type(req.headers()) {
-            let (parts, body) = req.into_parts();
+        let req = if !request_rules.is_empty() && Self::is_json_content_type(req.headers()) {
+            let (mut parts, body) = req.into_parts();
             let body_bytes = body
                 .collect()
                 .await
                 .map(|c| c.to_bytes())
                 .unwrap_or_default();
 
-            match serde_json::from_slice::<Value>(&body_bytes) {
-                Ok(json) => {
-                    let transformed = Self::apply_rules(json, &self.config.request_rules);
-                    let new_bytes = serde_json::to_vec(&transformed)
-                        .map_err(|e| Error::Internal(format!("JSON serialization failed: {e}")))?;
-                    let len = new_bytes.len();
-                    let mut new_req = Request::from_parts(parts, Full::new(Bytes::from(new_bytes)));
-                    new_req.headers_mut().insert(
+            match Self::transform_body(&body_bytes, request_rules, max_body_size)? {
+                Some(new_bytes) => {
+                    parts.headers.insert(
                         header::CONTENT_LENGTH,
-                        http::HeaderValue::from_str(&len.to_string()).unwrap(),
+                        http::HeaderValue::from(new_bytes.len()),
                     );
-                    new_req
-                }
-                Err(_) => {
-                    // Not valid JSON -- pass through unchanged
-                    Request::from_parts(parts, Full::new(body_bytes))
+                    Request::from_parts(parts, Full::new(new_bytes))
                 }
+                // Not transformable -- pass through unchanged
+                None => Request::from_parts(parts, Full::new(body_bytes)),
             }
         } else {
             req
@@ -224,29 +376,23 @@ impl Middleware for BodyTransform {
         let response = next.run(req).await?;
 
         // --- Transform response body ---
-        if has_response_rules && Self::is_json_content_type(response.headers()) {
-            let (parts, body) = response.into_parts();
+        if !response_rules.is_empty() && Self::is_json_content_type(response.headers()) {
+            let (mut parts, body) = response.into_parts();
             let body_bytes = body
                 .collect()
                 .await
                 .map(|c| c.to_bytes())
                 .unwrap_or_default();
 
-            match serde_json::from_slice::<Value>(&body_bytes) {
-                Ok(json) => {
-                    let transformed = Self::apply_rules(json, &self.config.response_rules);
-                    let new_bytes = serde_json::to_vec(&transformed)
-                        .map_err(|e| Error::Internal(format!("JSON serialization failed: {e}")))?;
-                    let len = new_bytes.len();
-                    let mut new_resp =
-                        Response::from_parts(parts, Full::new(Bytes::from(new_bytes)));
-                    new_resp.headers_mut().insert(
+            match Self::transform_body(&body_bytes, response_rules, max_body_size)? {
+                Some(new_bytes) => {
+                    parts.headers.insert(
                         header::CONTENT_LENGTH,
-                        http::HeaderValue::from_str(&len.to_string()).unwrap(),
+                        http::HeaderValue::from(new_bytes.len()),
                     );
-                    Ok(new_resp)
+                    Ok(Response::from_parts(parts, Full::new(new_bytes)))
                 }
-                Err(_) => Ok(Response::from_parts(parts, Full::new(body_bytes))),
+                None => Ok(Response::from_parts(parts, Full::new(body_bytes))),
             }
         } else {
             Ok(response)
@@ -309,6 +455,7 @@ mod tests {
         let config = BodyTransformConfig {
             request_rules: vec![BodyRule::RemoveField("user.secret".to_string())],
             response_rules: vec![],
+            ..Default::default()
         };
         let transform = BodyTransform::with_config(config);
         let handler = EchoHandler;
@@ -342,6 +489,7 @@ mod tests {
         let config = BodyTransformConfig {
             request_rules: vec![],
             response_rules: vec![BodyRule::RedactField("data.email".to_string())],
+            ..Default::default()
         };
         let transform = BodyTransform::with_config(config);
         let handler = JsonResponseHandler {
@@ -377,6 +525,7 @@ mod tests {
                 value: Value::String("v2".to_string()),
             }],
             response_rules: vec![],
+            ..Default::default()
         };
         let transform = BodyTransform::with_config(config);
         let handler = EchoHandler;
@@ -408,6 +557,7 @@ mod tests {
                 to: "new_name".to_string(),
             }],
             response_rules: vec![],
+            ..Default::default()
         };
         let transform = BodyTransform::with_config(config);
         let handler = EchoHandler;
@@ -437,6 +587,7 @@ mod tests {
         let config = BodyTransformConfig {
             request_rules: vec![BodyRule::RemoveField("secret".to_string())],
             response_rules: vec![],
+            ..Default::default()
         };
         let transform = BodyTransform::with_config(config);
         let handler = EchoHandler;
@@ -468,6 +619,7 @@ mod tests {
                 },
             ],
             response_rules: vec![],
+            ..Default::default()
         };
         let transform = BodyTransform::with_config(config);
         let handler = EchoHandler;
@@ -499,13 +651,118 @@ mod tests {
         assert_eq!(json["processed"], true);
     }
 
+    fn route_transform(rules: Vec<JsonTransformRule>) -> MatchedRouteTransform {
+        MatchedRouteTransform::from(&octopus_config::types::RouteTransformConfig {
+            response: rules,
+            ..Default::default()
+        })
+    }
+
+    /// Run a JSON response through a route's response rules
+    async fn transform_route_response(route: MatchedRouteTransform, json: Value) -> Value {
+        let transform = BodyTransform::new();
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(transform), Arc::new(JsonResponseHandler { json })]);
+        let mut req = Request::builder()
+            .uri("/test")
+            .body(Body::from(""))
+            .unwrap();
+        req.extensions_mut().insert(route);
+
+        let response = Next::new(stack).run(req).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        parse_response_json(&body)
+    }
+
+    #[tokio::test]
+    async fn test_route_rules_rename_and_drop_fields() {
+        let route = route_transform(vec![
+            JsonTransformRule::Rename {
+                from: "$.user.fullName".to_string(),
+                to: "$.user.name".to_string(),
+            },
+            JsonTransformRule::Remove {
+                path: "$.user.password_hash".to_string(),
+            },
+            JsonTransformRule::Default {
+                path: "$.user.role".to_string(),
+                value: Value::from("member"),
+            },
+        ]);
+        let json = transform_route_response(
+            route,
+            serde_json::json!({
+                "user": { "fullName": "Ada Lovelace", "password_hash": "x", "role": null }
+            }),
+        )
+        .await;
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "user": { "name": "Ada Lovelace", "role": "member" } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_route_rules_unwrap_nested_object() {
+        let route = route_transform(vec![
+            JsonTransformRule::Unwrap {
+                path: "$.data.items[0]".to_string(),
+            },
+            JsonTransformRule::Wrap {
+                path: "$.item".to_string(),
+            },
+        ]);
+        let json = transform_route_response(
+            route,
+            serde_json::json!({
+                "data": { "items": [{ "id": 1, "name": "first" }, { "id": 2 }] },
+                "meta": { "page": 1 }
+            }),
+        )
+        .await;
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "item": { "id": 1, "name": "first" } })
+        );
+    }
+
+    #[test]
+    fn test_route_response_rules_apply_to_single_values() {
+        let route = route_transform(vec![JsonTransformRule::Redact {
+            path: "user.email".to_string(),
+        }]);
+        let event = route.transform_response(serde_json::json!({
+            "user": { "email": "a@example.com", "id": 1 }
+        }));
+        assert_eq!(
+            event,
+            serde_json::json!({ "user": { "email": "***REDACTED***", "id": 1 } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_body_over_size_cap_is_untouched() {
+        let mut route = route_transform(vec![JsonTransformRule::Remove {
+            path: "secret".to_string(),
+        }]);
+        route.max_body_size = 8;
+        let json = transform_route_response(route, serde_json::json!({ "secret": "s3cret" })).await;
+
+        assert_eq!(json["secret"], "s3cret");
+    }
+
     // Unit tests for path helpers
     #[test]
     fn test_get_at_path() {
         let v = serde_json::json!({ "a": { "b": { "c": 42 } } });
-        assert_eq!(get_at_path(&v, "a.b.c"), Some(&Value::from(42)));
-        assert_eq!(get_at_path(&v, "a.b"), Some(&serde_json::json!({"c": 42})));
-        assert!(get_at_path(&v, "a.x").is_none());
+        assert_eq!(json_path::get(&v, "a.b.c"), Some(&Value::from(42)));
+        assert_eq!(
+            json_path::get(&v, "a.b"),
+            Some(&serde_json::json!({"c": 42}))
+        );
+        assert!(json_path::get(&v, "a.x").is_none());
     }
 
     #[test]
@@ -0,0 +1,719 @@
+//! JSON Schema request body validation middleware
+//!
+//! For configured routes, validates the JSON request body against a JSON
+//! Schema before the request reaches the upstream, and answers `400` with the
+//! list of violations when it does not conform. A rule's schema is either
+//! given inline or looked up per operation in an OpenAPI document (the
+//! FARP-federated spec) through a [`SchemaResolver`]. Schemas are compiled
+//! once and cached. Only `$ref`s within a schema resolve: an external
+//! reference (an `http:` or `file:` URL) is never fetched, and a body checked
+//! against one fails validation.
+//!
+//! Bodies larger than `max_body_size` are rejected with `413` before they are
+//! parsed.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use dashmap::DashMap;
+use http::{header, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use jsonschema::JSONSchema;
+use octopus_core::{ErrorResponse, Middleware, Next, Result};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use url::Url;
+
+/// Body type alias
+pub type Body = Full<Bytes>;
+
+/// Where a rule's schema comes from
+#[derive(Debug, Clone)]
+pub enum RuleSchema {
+    /// An inline JSON Schema
+    Inline(Value),
+    /// The request body schema of the matching operation, from the
+    /// [`SchemaResolver`]
+    Resolved,
+}
+
+/// A route whose request bodies are validated
+#[derive(Debug, Clone)]
+pub struct ValidationRule {
+    /// Path pattern; `{name}` matches one segment and a trailing `/*` any
+    /// suffix (e.g. `/api/users/{id}`)
+    pub path: String,
+    /// Methods to validate (case-insensitive); empty = `POST`, `PUT`, `PATCH`
+    pub methods: Vec<String>,
+    /// Schema source
+    pub schema: RuleSchema,
+}
+
+impl ValidationRule {
+    fn applies(&self, method: &Method, path: &str) -> bool {
+        let method_matches = if self.methods.is_empty() {
+            matches!(*method, Method::POST | Method::PUT | Method::PATCH)
+        } else {
+            self.methods
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(method.as_str()))
+        };
+        method_matches && path_matches(&self.path, path)
+    }
+}
+
+/// Request body validation configuration
+#[derive(Debug, Clone)]
+pub struct RequestValidationConfig {
+    /// Routes to validate; the first matching rule wins
+    pub rules: Vec<ValidationRule>,
+    /// Largest body accepted on a validated route (bytes); larger bodies are
+    /// rejected with `413` before parsing
+    pub max_body_size: usize,
+}
+
+impl Default for RequestValidationConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+/// A request body schema found by a [`SchemaResolver`]
+#[derive(Debug, Clone)]
+pub struct ResolvedSchema {
+    /// Identity of the operation (e.g. `POST /users/{id}`); compiled schemas
+    /// are cached under it
+    pub key: String,
+    /// Version of the source document; a cached schema compiled from another
+    /// version is recompiled
+    pub version: u64,
+    /// The JSON Schema
+    pub schema: Arc<Value>,
+}
+
+/// What an operation declares for a response status
+#[derive(Debug, Clone)]
+pub enum DeclaredResponse {
+    /// The status is not among the operation's declared responses
+    Undeclared,
+    /// The status is declared, with its JSON body schema if it has one
+    Declared(Option<ResolvedSchema>),
+}
+
+/// Looks up request (and response) body schemas by operation
+pub trait SchemaResolver: Send + Sync + fmt::Debug {
+    /// The request body schema of the operation serving `method` `path`, or
+    /// `None` if there is no such operation or it takes no JSON body
+    fn resolve(&self, method: &Method, path: &str) -> Option<ResolvedSchema>;
+
+    /// What the operation serving `method` `path` declares for `status`, or
+    /// `None` if there is no such operation
+    fn resolve_response(
+        &self,
+        _method: &Method,
+        _path: &str,
+        _status: StatusCode,
+    ) -> Option<DeclaredResponse> {
+        None
+    }
+}
+
+/// Body schemas of every operation in an OpenAPI 3 document
+#[derive(Debug, Clone, Default)]
+pub struct OpenApiSchemas {
+    /// Version of the source document, stamped on every [`ResolvedSchema`]
+    version: u64,
+    /// Operations, literal path templates first
+    operations: Vec<Operation>,
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    method: Method,
+    template: String,
+    /// JSON request body schema
+    request: Option<Arc<Value>>,
+    /// `(status, JSON body schema)`, where status is e.g. `200`, `2XX` or
+    /// `default`
+    responses: Vec<(String, Option<Arc<Value>>)>,
+}
+
+impl Operation {
+    fn resolved(&self, suffix: Option<&str>, schema: &Arc<Value>, version: u64) -> ResolvedSchema {
+        let mut key = format!("{} {}", self.method, self.template);
+        if let Some(suffix) = suffix {
+            key = format!("{key} {suffix}");
+        }
+        ResolvedSchema {
+            key,
+            version,
+            schema: Arc::clone(schema),
+        }
+    }
+}
+
+impl OpenApiSchemas {
+    /// Extract the `application/json` request and response body schemas of
+    /// each operation; `version` identifies the document.
+    ///
+    /// Schemas are wrapped with the document's `components`, so local
+    /// `#/components/...` references still resolve.
+    pub fn from_document(doc: &Value, version: u64) -> Self {
+        let mut operations = Vec::new();
+        for (template, item) in doc
+            .get("paths")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            for (method, operation) in item.as_object().into_iter().flatten() {
+                let Ok(method) = Method::from_bytes(method.to_ascii_uppercase().as_bytes()) else {
+                    continue;
+                };
+                if !operation.is_object() {
+                    continue;
+                }
+                let request = operation
+                    .get("requestBody")
+                    .and_then(|body| json_body_schema(doc, body));
+                let responses = operation
+                    .get("responses")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .map(|(status, response)| (status.clone(), json_body_schema(doc, response)))
+                    .collect();
+                operations.push(Operation {
+                    method,
+                    template: template.clone(),
+                    request,
+                    responses,
+                });
+            }
+        }
+        operations.sort_by_key(|op| op.template.matches('{').count());
+        Self {
+            version,
+            operations,
+        }
+    }
+
+    fn operation(&self, method: &Method, path: &str) -> Option<&Operation> {
+        self.operations
+            .iter()
+            .find(|op| op.method == method && path_matches(&op.template, path))
+    }
+
+    /// The request body schema of the operation serving `method` `path`
+    pub fn find(&self, method: &Method, path: &str) -> Option<ResolvedSchema> {
+        let op = self.operation(method, path)?;
+        Some(op.resolved(None, op.request.as_ref()?, self.version))
+    }
+
+    /// What the operation serving `method` `path` declares for `status`:
+    /// the exact code wins over its `NXX` range, which wins over `default`.
+    /// An operation without declared responses accepts any status.
+    pub fn find_response(
+        &self,
+        method: &Method,
+        path: &str,
+        status: StatusCode,
+    ) -> Option<DeclaredResponse> {
+        let op = self.operation(method, path)?;
+        if op.responses.is_empty() {
+            return Some(DeclaredResponse::Declared(None));
+        }
+        let code = status.as_str();
+        let range = format!("{}XX", &code[..1]);
+        let declared = [code, range.as_str(), "default"]
+            .into_iter()
+            .find_map(|key| {
+                op.responses
+                    .iter()
+                    .find(|(status, _)| status.eq_ignore_ascii_case(key))
+            });
+        Some(match declared {
+            None => DeclaredResponse::Undeclared,
+            Some((status, schema)) => DeclaredResponse::Declared(
+                schema
+                    .as_ref()
+                    .map(|schema| op.resolved(Some(status), schema, self.version)),
+            ),
+        })
+    }
+
+    /// Version of the source document
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of operations
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Whether the document has no operations
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+/// The JSON body schema of a request body or response object, wrapped with
+/// the document's `components`
+fn json_body_schema(doc: &Value, holder: &Value) -> Option<Arc<Value>> {
+    let schema = resolve_ref(doc, holder)
+        .get("content")?
+        .as_object()?
+        .iter()
+        .find(|(media, _)| media.contains("json"))?
+        .1
+        .get("schema")?;
+    Some(Arc::new(match doc.get("components") {
+        Some(components) => json!({ "allOf": [schema], "components": components }),
+        None => schema.clone(),
+    }))
+}
+
+/// Follow a local `$ref` (e.g. `#/components/requestBodies/User`)
+fn resolve_ref<'a>(doc: &'a Value, value: &'a Value) -> &'a Value {
+    value
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|r| r.strip_prefix('#'))
+        .and_then(|pointer| doc.pointer(pointer))
+        .unwrap_or(value)
+}
+
+/// Whether `path` matches `pattern`, where a `{name}` segment matches any one
+/// segment and a trailing `/*` any suffix
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, wildcard) = match pattern.strip_suffix("/*") {
+        Some(prefix) => (prefix, true),
+        None => (pattern, false),
+    };
+    let mut expected = pattern
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty());
+    let mut actual = path.trim_matches('/').split('/').filter(|s| !s.is_empty());
+    loop {
+        match (expected.next(), actual.next()) {
+            (None, None) => return true,
+            (None, Some(_)) => return wildcard,
+            (Some(_), None) => return false,
+            (Some(e), Some(a)) => {
+                if !(e == a || (e.starts_with('{') && e.ends_with('}'))) {
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+/// Refuses every external `$ref`, so a schema can't make the gateway fetch a
+/// URL or read a local file
+struct LocalRefsOnly;
+
+impl jsonschema::SchemaResolver for LocalRefsOnly {
+    fn resolve(
+        &self,
+        _root_schema: &Value,
+        url: &Url,
+        _original_reference: &str,
+    ) -> std::result::Result<Arc<Value>, jsonschema::SchemaResolverError> {
+        Err(anyhow::anyhow!(
+            "external schema reference {url} is not resolved"
+        ))
+    }
+}
+
+/// Compile `schema`, resolving only the references within it
+pub(crate) fn compile(schema: &Value) -> std::result::Result<JSONSchema, String> {
+    JSONSchema::options()
+        .with_resolver(LocalRefsOnly)
+        .compile(schema)
+        .map_err(|e| e.to_string())
+}
+
+/// A compiled schema; `None` when there is none to validate against
+pub(crate) type CompiledSchema = Option<Arc<JSONSchema>>;
+
+/// Compiled resolved schemas by key, recompiled when their version changes
+#[derive(Default)]
+pub(crate) struct SchemaCache(DashMap<String, (u64, CompiledSchema)>);
+
+impl SchemaCache {
+    /// The compiled `resolved` schema; `None` (cached too) if it does not
+    /// compile
+    pub(crate) fn get(&self, resolved: ResolvedSchema) -> CompiledSchema {
+        if let Some(entry) = self.0.get(&resolved.key) {
+            if entry.0 == resolved.version {
+                return entry.1.clone();
+            }
+        }
+        let compiled = match compile(&resolved.schema) {
+            Ok(compiled) => Some(Arc::new(compiled)),
+            Err(e) => {
+                tracing::warn!(operation = %resolved.key, error = %e, "Schema does not compile; not validating");
+                None
+            }
+        };
+        self.0
+            .insert(resolved.key, (resolved.version, compiled.clone()));
+        compiled
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// JSON Schema request body validation middleware
+#[derive(Clone)]
+pub struct RequestValidation {
+    rules: Arc<[(ValidationRule, CompiledSchema)]>,
+    max_body_size: usize,
+    resolver: Option<Arc<dyn SchemaResolver>>,
+    compiled: Arc<SchemaCache>,
+}
+
+impl RequestValidation {
+    /// Create from config. Rules with an inline schema that does not compile
+    /// are skipped with a warning.
+    pub fn new(config: RequestValidationConfig) -> Self {
+        let rules = config
+            .rules
+            .into_iter()
+            .filter_map(|rule| {
+                let compiled = match &rule.schema {
+                    RuleSchema::Inline(schema) => match compile(schema) {
+                        Ok(compiled) => Some(Arc::new(compiled)),
+                        Err(e) => {
+                            tracing::warn!(path = %rule.path, error = %e, "Ignoring validation rule with invalid schema");
+                            return None;
+                        }
+                    },
+                    RuleSchema::Resolved => None,
+                };
+                Some((rule, compiled))
+            })
+            .collect();
+        Self {
+            rules,
+            max_body_size: config.max_body_size,
+            resolver: None,
+            compiled: Arc::default(),
+        }
+    }
+
+    /// Look up [`RuleSchema::Resolved`] schemas with `resolver`
+    pub fn with_resolver(mut self, resolver: Arc<dyn SchemaResolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// The schema for a request matching `rule`, or `None` to skip validation
+    fn schema_for(
+        &self,
+        inline: Option<&Arc<JSONSchema>>,
+        method: &Method,
+        path: &str,
+    ) -> CompiledSchema {
+        if let Some(schema) = inline {
+            return Some(Arc::clone(schema));
+        }
+        let resolved = self.resolver.as_ref()?.resolve(method, path)?;
+        self.compiled.get(resolved)
+    }
+
+    fn too_large(&self, path: &str) -> Response<Body> {
+        ErrorResponse::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large")
+            .detail(format!("Request body exceeds {} bytes", self.max_body_size))
+            .instance(path)
+            .into_response()
+    }
+}
+
+impl fmt::Debug for RequestValidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestValidation")
+            .field("rules", &self.rules.len())
+            .field("max_body_size", &self.max_body_size)
+            .field("resolver", &self.resolver)
+            .field("compiled", &self.compiled.len())
+            .finish()
+    }
+}
+
+/// The 400 listing every violation as `{"path", "message"}`
+fn invalid_body(path: &str, errors: Vec<Value>) -> Response<Body> {
+    ErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_request_body")
+        .detail("Request body failed schema validation")
+        .instance(path)
+        .extension("errors", errors)
+        .into_response()
+}
+
+#[async_trait]
+impl Middleware for RequestValidation {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let path = req.uri().path().to_string();
+        let Some((_, inline)) = self
+            .rules
+            .iter()
+            .find(|(rule, _)| rule.applies(req.method(), &path))
+        else {
+            return next.run(req).await;
+        };
+        let Some(schema) = self.schema_for(inline.as_ref(), req.method(), &path) else {
+            return next.run(req).await;
+        };
+
+        let declared_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        if declared_length.is_some_and(|len| len > self.max_body_size) {
+            return Ok(self.too_large(&path));
+        }
+
+        let (parts, body) = req.into_parts();
+        let bytes = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        if bytes.len() > self.max_body_size {
+            return Ok(self.too_large(&path));
+        }
+
+        let instance: Value = match serde_json::from_slice(&bytes) {
+            Ok(value) => value,
+            Err(e) => {
+                let error = json!({ "path": "", "message": format!("invalid JSON: {e}") });
+                return Ok(invalid_body(&path, vec![error]));
+            }
+        };
+        if let Err(errors) = schema.validate(&instance) {
+            let errors: Vec<Value> = errors
+                .map(|e| json!({ "path": e.instance_path.to_string(), "message": e.to_string() }))
+                .collect();
+            tracing::debug!(path = %path, errors = errors.len(), "Request body failed validation");
+            return Ok(invalid_body(&path, errors));
+        }
+
+        next.run(Request::from_parts(parts, Full::new(bytes))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_core::Error;
+
+    /// Answers 200 with the request body it received.
+    #[derive(Debug)]
+    struct Echo;
+
+    #[async_trait]
+    impl Middleware for Echo {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(req.into_body())
+                .map_err(|e| Error::Internal(e.to_string()))
+        }
+    }
+
+    fn user_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["name", "age"],
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": { "type": "integer", "minimum": 0 }
+            }
+        })
+    }
+
+    fn stack(validation: RequestValidation) -> Arc<[Arc<dyn Middleware>]> {
+        Arc::new([
+            Arc::new(validation) as Arc<dyn Middleware>,
+            Arc::new(Echo) as Arc<dyn Middleware>,
+        ])
+    }
+
+    fn inline(max_body_size: usize) -> RequestValidation {
+        RequestValidation::new(RequestValidationConfig {
+            rules: vec![ValidationRule {
+                path: "/api/users".to_string(),
+                methods: Vec::new(),
+                schema: RuleSchema::Inline(user_schema()),
+            }],
+            max_body_size,
+        })
+    }
+
+    fn post(path: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::POST)
+            .uri(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    async fn run(stack: &Arc<[Arc<dyn Middleware>]>, req: Request<Body>) -> (StatusCode, Value) {
+        let response = Next::new(stack.clone()).run(req).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn external_refs_are_not_resolved() {
+        // Accepts anything, were it ever read
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"{}").unwrap();
+        let url = Url::from_file_path(file.path()).unwrap();
+        let stack = stack(RequestValidation::new(RequestValidationConfig {
+            rules: vec![ValidationRule {
+                path: "/api/users".to_string(),
+                methods: Vec::new(),
+                schema: RuleSchema::Inline(json!({
+                    "type": "object",
+                    "properties": { "name": { "$ref": url.as_str() } }
+                })),
+            }],
+            max_body_size: 1024,
+        }));
+
+        let (status, _) = run(&stack, post("/api/users", r#"{"name":"Ada"}"#)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn valid_body_passes_through() {
+        let stack = stack(inline(1024));
+
+        let (status, body) = run(&stack, post("/api/users", r#"{"name":"Ada","age":36}"#)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "name": "Ada", "age": 36 }));
+    }
+
+    #[tokio::test]
+    async fn invalid_body_returns_structured_errors() {
+        let stack = stack(inline(1024));
+
+        let (status, body) = run(&stack, post("/api/users", r#"{"name":"","age":-1}"#)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["status"], 400);
+        let paths: Vec<&str> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&"/name"));
+        assert!(paths.contains(&"/age"));
+
+        let (status, body) = run(&stack, post("/api/users", "not json")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid JSON"));
+    }
+
+    #[tokio::test]
+    async fn oversized_body_rejected_before_parsing() {
+        let stack = stack(inline(16));
+
+        // Not JSON at all: a 413 (not a 400) shows it was never parsed.
+        let (status, _) = run(&stack, post("/api/users", &"x".repeat(17))).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut req = post("/api/users", "{}");
+        req.headers_mut()
+            .insert(header::CONTENT_LENGTH, "1048576".parse().unwrap());
+        let (status, _) = run(&stack, req).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn unmatched_routes_and_methods_are_not_validated() {
+        let stack = stack(inline(16));
+
+        let (status, _) = run(&stack, post("/api/orders", "not json")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let mut req = post("/api/users", "not json");
+        *req.method_mut() = Method::GET;
+        let (status, _) = run(&stack, req).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[derive(Debug)]
+    struct Spec(OpenApiSchemas);
+
+    impl SchemaResolver for Spec {
+        fn resolve(&self, method: &Method, path: &str) -> Option<ResolvedSchema> {
+            self.0.find(method, path)
+        }
+    }
+
+    #[tokio::test]
+    async fn resolved_schema_from_openapi_document() {
+        let doc = json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/users/{id}": {
+                    "put": {
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/User" }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": { "schemas": { "User": user_schema() } }
+        });
+        let schemas = OpenApiSchemas::from_document(&doc, 1);
+        assert_eq!(schemas.len(), 1);
+
+        let validation = RequestValidation::new(RequestValidationConfig {
+            rules: vec![ValidationRule {
+                path: "/users/*".to_string(),
+                methods: Vec::new(),
+                schema: RuleSchema::Resolved,
+            }],
+            ..Default::default()
+        })
+        .with_resolver(Arc::new(Spec(schemas)));
+        let stack = stack(validation);
+
+        let mut req = post("/users/42", r#"{"name":"Ada","age":36}"#);
+        *req.method_mut() = Method::PUT;
+        assert_eq!(run(&stack, req).await.0, StatusCode::OK);
+
+        let mut req = post("/users/42", r#"{"name":"Ada"}"#);
+        *req.method_mut() = Method::PUT;
+        assert_eq!(run(&stack, req).await.0, StatusCode::BAD_REQUEST);
+
+        // No operation for POST: nothing to validate against.
+        assert_eq!(
+            run(&stack, post("/users/42", "not json")).await.0,
+            StatusCode::OK
+        );
+    }
+}
@@ -1,16 +1,32 @@
 //! Bot detection/blocking middleware based on User-Agent patterns
+//!
+//! User-Agents are matched against exact, substring or regex patterns; a
+//! matching bot is blocked, throttled to a stricter rate limit, tagged with a
+//! header for downstream handling, or only logged, depending on [`BotMode`].
 
+use crate::forwarded::ClientIp;
+use crate::rate_limit::{too_many_requests, window_quota};
 use async_trait::async_trait;
 use bytes::Bytes;
-use http::{Request, Response, StatusCode};
+use governor::{clock::DefaultClock, state::keyed::DefaultKeyedStateStore, RateLimiter};
+use http::{HeaderName, HeaderValue, Request, Response, StatusCode};
 use http_body_util::Full;
 use octopus_core::{Middleware, Next, Result};
 use regex::Regex;
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Body type alias
 pub type Body = Full<Bytes>;
 
+/// Pattern reported for a missing User-Agent treated as a bot
+const EMPTY_UA: &str = "(empty)";
+
+/// Throttle checks between sweeps of idle clients from the limiter
+const SWEEP_EVERY: u64 = 1024;
+
 /// Bot detection mode
 #[derive(Debug, Clone)]
 pub enum BotMode {
@@ -20,6 +36,48 @@ pub enum BotMode {
     Allow,
     /// Log but don't block
     LogOnly,
+    /// Rate-limit matching bots per client to `throttle_requests` per
+    /// `throttle_window`
+    Throttle,
+    /// Pass matching bots through with `tag_header` set to the matched pattern
+    Tag,
+}
+
+/// A User-Agent pattern
+#[derive(Debug, Clone)]
+pub enum UaPattern {
+    /// The whole User-Agent (case-insensitive)
+    Exact(String),
+    /// A substring of the User-Agent (case-insensitive)
+    Contains(String),
+    /// A regular expression
+    Regex(Regex),
+}
+
+impl UaPattern {
+    /// Parse `exact:<ua>`, `contains:<text>` or `regex:<re>`; a pattern
+    /// without a prefix is a regex
+    pub fn parse(s: &str) -> std::result::Result<Self, String> {
+        if let Some(ua) = s.strip_prefix("exact:") {
+            return Ok(Self::Exact(ua.to_lowercase()));
+        }
+        if let Some(text) = s.strip_prefix("contains:") {
+            return Ok(Self::Contains(text.to_lowercase()));
+        }
+        let re = s.strip_prefix("regex:").unwrap_or(s);
+        Regex::new(re)
+            .map(Self::Regex)
+            .map_err(|e| format!("Invalid User-Agent regex '{re}': {e}"))
+    }
+
+    /// Check if `ua` matches this pattern
+    pub fn matches(&self, ua: &str) -> bool {
+        match self {
+            Self::Exact(exact) => ua.eq_ignore_ascii_case(exact),
+            Self::Contains(text) => ua.to_lowercase().contains(text.as_str()),
+            Self::Regex(re) => re.is_match(ua),
+        }
+    }
 }
 
 /// Bot detection configuration
@@ -27,16 +85,25 @@ pub enum BotMode {
 pub struct BotDetectionConfig {
     /// Detection mode
     pub mode: BotMode,
-    /// Regex patterns to block
+    /// Patterns to block (see [`UaPattern::parse`])
     pub block_patterns: Vec<String>,
     /// Always allow (override block)
     pub allow_patterns: Vec<String>,
     /// Block empty User-Agent (default: false)
     pub block_empty_ua: bool,
+    /// Treat an empty User-Agent as a bot match, handled per `mode`
+    /// (default: false)
+    pub empty_ua_is_bot: bool,
     /// HTTP status code for blocked requests (default: 403)
     pub response_status: u16,
     /// Response body message for blocked requests (default: "Forbidden")
     pub response_message: String,
+    /// Requests a throttled bot may make per window (default: 10)
+    pub throttle_requests: u32,
+    /// Throttle window (default: 1 minute)
+    pub throttle_window: Duration,
+    /// Header set on tagged requests (default: "x-bot-detected")
+    pub tag_header: String,
 }
 
 impl Default for BotDetectionConfig {
@@ -46,8 +113,12 @@ impl Default for BotDetectionConfig {
             block_patterns: Vec::new(),
             allow_patterns: Vec::new(),
             block_empty_ua: false,
+            empty_ua_is_bot: false,
             response_status: 403,
             response_message: "Forbidden".to_string(),
+            throttle_requests: 10,
+            throttle_window: Duration::from_secs(60),
+            tag_header: "x-bot-detected".to_string(),
         }
     }
 }
@@ -61,43 +132,55 @@ impl BotDetectionConfig {
                 r"(?i)(bot|crawler|spider|scraper|curl|wget|python-requests|go-http-client|java/|libwww)"
                     .to_string(),
             ],
-            allow_patterns: Vec::new(),
-            block_empty_ua: false,
-            response_status: 403,
-            response_message: "Forbidden".to_string(),
+            ..Default::default()
         }
     }
 }
 
+/// Per-client limiter for throttled bots
+type BotLimiter = RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>;
+
 /// Bot detection middleware
 ///
-/// Inspects the User-Agent header and blocks, allows, or logs
-/// requests based on configurable regex patterns.
+/// Inspects the User-Agent header and blocks, allows, throttles, tags or
+/// logs requests based on configurable patterns.
 #[derive(Clone)]
 pub struct BotDetection {
     config: BotDetectionConfig,
-    block_regexes: Vec<Regex>,
-    allow_regexes: Vec<Regex>,
+    block_patterns: Vec<(String, UaPattern)>,
+    allow_patterns: Vec<UaPattern>,
+    limiter: Arc<BotLimiter>,
+    throttle_checks: Arc<AtomicU64>,
+    tag_header: HeaderName,
 }
 
 impl BotDetection {
     /// Create a new BotDetection middleware with the given config.
-    /// Pre-compiles all regex patterns.
+    /// Pre-compiles all patterns; invalid ones are skipped with a warning.
     pub fn new(config: BotDetectionConfig) -> Self {
-        let block_regexes = config
+        let block_patterns = config
             .block_patterns
             .iter()
-            .filter_map(|p| Regex::new(p).ok())
+            .filter_map(|p| Some((p.clone(), compile(p)?)))
             .collect();
-        let allow_regexes = config
+        let allow_patterns = config
             .allow_patterns
             .iter()
-            .filter_map(|p| Regex::new(p).ok())
+            .filter_map(|p| compile(p))
             .collect();
+        let limiter = Arc::new(RateLimiter::keyed(window_quota(
+            config.throttle_requests,
+            config.throttle_window,
+        )));
+        let tag_header = HeaderName::from_bytes(config.tag_header.as_bytes())
+            .unwrap_or_else(|_| HeaderName::from_static("x-bot-detected"));
         Self {
             config,
-            block_regexes,
-            allow_regexes,
+            block_patterns,
+            allow_patterns,
+            limiter,
+            throttle_checks: Arc::new(AtomicU64::new(0)),
+            tag_header,
         }
     }
 
@@ -106,6 +189,18 @@ impl BotDetection {
         Self::new(BotDetectionConfig::with_common_bots())
     }
 
+    /// Count a throttled bot request from `client`, returning whether it is
+    /// over the limit. Every [`SWEEP_EVERY`] checks, clients whose limit has
+    /// fully replenished are dropped from the limiter.
+    fn throttle(&self, client: &String) -> bool {
+        let limited = self.limiter.check_key(client).is_err();
+        if self.throttle_checks.fetch_add(1, Ordering::Relaxed) % SWEEP_EVERY == SWEEP_EVERY - 1 {
+            self.limiter.retain_recent();
+            self.limiter.shrink_to_fit();
+        }
+        limited
+    }
+
     /// Build the blocked response
     fn blocked_response(&self) -> Response<Body> {
         let status =
@@ -119,15 +214,24 @@ impl BotDetection {
 
     /// Check if user agent matches any allow pattern
     fn matches_allow(&self, ua: &str) -> bool {
-        self.allow_regexes.iter().any(|r| r.is_match(ua))
+        self.allow_patterns.iter().any(|p| p.matches(ua))
     }
 
-    /// Check if user agent matches any block pattern
-    fn matches_block(&self, ua: &str) -> bool {
-        self.block_regexes.iter().any(|r| r.is_match(ua))
+    /// The first block pattern matching the user agent
+    fn matched_block(&self, ua: &str) -> Option<&str> {
+        self.block_patterns
+            .iter()
+            .find(|(_, p)| p.matches(ua))
+            .map(|(source, _)| source.as_str())
     }
 }
 
+fn compile(pattern: &str) -> Option<UaPattern> {
+    UaPattern::parse(pattern)
+        .map_err(|e| tracing::warn!(error = %e, "Ignoring bot detection pattern"))
+        .ok()
+}
+
 impl fmt::Debug for BotDetection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BotDetection")
@@ -135,18 +239,20 @@ impl fmt::Debug for BotDetection {
             .field("block_patterns", &self.config.block_patterns)
             .field("allow_patterns", &self.config.allow_patterns)
             .field("block_empty_ua", &self.config.block_empty_ua)
+            .field("empty_ua_is_bot", &self.config.empty_ua_is_bot)
             .finish()
     }
 }
 
 #[async_trait]
 impl Middleware for BotDetection {
-    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+    async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
         let ua = req
             .headers()
             .get(http::header::USER_AGENT)
             .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
+            .unwrap_or("")
+            .to_string();
 
         // Block empty User-Agent if configured
         if ua.is_empty() && self.config.block_empty_ua {
@@ -155,27 +261,59 @@ impl Middleware for BotDetection {
         }
 
         // Allow patterns always take priority
-        if !ua.is_empty() && self.matches_allow(ua) {
+        if !ua.is_empty() && self.matches_allow(&ua) {
             return next.run(req).await;
         }
 
+        let matched = if ua.is_empty() {
+            self.config.empty_ua_is_bot.then_some(EMPTY_UA)
+        } else {
+            self.matched_block(&ua)
+        };
+
         match self.config.mode {
             BotMode::Block => {
-                if !ua.is_empty() && self.matches_block(ua) {
-                    tracing::warn!(uri = %req.uri(), user_agent = %ua, "Blocked bot request");
+                if let Some(pattern) = matched {
+                    tracing::warn!(uri = %req.uri(), user_agent = %ua, pattern, "Blocked bot request");
                     return Ok(self.blocked_response());
                 }
             }
             BotMode::Allow => {
                 // In Allow mode, if no allow pattern matched (checked above), block
-                if !ua.is_empty() && !self.matches_allow(ua) {
+                if !ua.is_empty() || matched.is_some() {
                     tracing::warn!(uri = %req.uri(), user_agent = %ua, "Blocked non-allowed User-Agent");
                     return Ok(self.blocked_response());
                 }
             }
             BotMode::LogOnly => {
-                if !ua.is_empty() && self.matches_block(ua) {
-                    tracing::info!(uri = %req.uri(), user_agent = %ua, "Detected bot request (log only)");
+                if let Some(pattern) = matched {
+                    tracing::info!(uri = %req.uri(), user_agent = %ua, pattern, "Detected bot request (log only)");
+                }
+            }
+            BotMode::Throttle => {
+                if let Some(pattern) = matched {
+                    // Per client as the server resolved it; the User-Agent
+                    // stands in when the client is unknown.
+                    let key = req
+                        .extensions()
+                        .get::<ClientIp>()
+                        .map(|ClientIp(ip)| ip.to_string())
+                        .unwrap_or_else(|| ua.clone());
+                    if self.throttle(&key) {
+                        tracing::warn!(uri = %req.uri(), user_agent = %ua, pattern, client = %key, "Throttled bot request");
+                        return Ok(too_many_requests(
+                            self.config.throttle_window,
+                            self.config.throttle_requests,
+                            "Rate limit exceeded",
+                        ));
+                    }
+                }
+            }
+            BotMode::Tag => {
+                if let Some(pattern) = matched {
+                    let value = HeaderValue::from_str(pattern)
+                        .unwrap_or_else(|_| HeaderValue::from_static("true"));
+                    req.headers_mut().insert(self.tag_header.clone(), value);
                 }
             }
         }
@@ -307,6 +445,147 @@ mod tests {
         assert_eq!(response.status(), StatusCode::FORBIDDEN);
     }
 
+    fn ua_request(ua: Option<&str>) -> Request<Body> {
+        let mut req = Request::builder().uri("/test");
+        if let Some(ua) = ua {
+            req = req.header("User-Agent", ua);
+        }
+        let mut req = req.body(Body::from("")).unwrap();
+        req.extensions_mut()
+            .insert(ClientIp("203.0.113.9".parse().unwrap()));
+        req
+    }
+
+    #[test]
+    fn test_ua_pattern_kinds() {
+        let exact = UaPattern::parse("exact:BadScraper/1.0").unwrap();
+        assert!(exact.matches("badscraper/1.0"));
+        assert!(!exact.matches("BadScraper/1.0 extra"));
+
+        let contains = UaPattern::parse("contains:HeadlessChrome").unwrap();
+        assert!(contains.matches("Mozilla/5.0 HeadlessChrome/120.0"));
+        assert!(!contains.matches("Mozilla/5.0 Chrome/120.0"));
+
+        let regex = UaPattern::parse(r"regex:^python-requests/\d").unwrap();
+        assert!(regex.matches("python-requests/2.31"));
+        assert!(UaPattern::parse("(unclosed").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_throttle_mode_limits_bots_only() {
+        let config = BotDetectionConfig {
+            mode: BotMode::Throttle,
+            block_patterns: vec!["contains:scrapy".to_string()],
+            throttle_requests: 2,
+            ..Default::default()
+        };
+        let stack = make_stack(BotDetection::new(config));
+
+        for _ in 0..2 {
+            let response = Next::new(stack.clone())
+                .run(ua_request(Some("Scrapy/2.11")))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = Next::new(stack.clone())
+            .run(ua_request(Some("Scrapy/2.11")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+
+        // A forwarded address the client wrote itself doesn't reset its limit.
+        let mut spoofed = ua_request(Some("Scrapy/2.11"));
+        spoofed
+            .headers_mut()
+            .insert("x-forwarded-for", HeaderValue::from_static("198.51.100.7"));
+        let response = Next::new(stack.clone()).run(spoofed).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Browsers from the same client are not throttled.
+        let response = Next::new(stack)
+            .run(ua_request(Some("Mozilla/5.0 Firefox/121.0")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_idle_throttled_clients_are_evicted() {
+        let bot = BotDetection::new(BotDetectionConfig {
+            mode: BotMode::Throttle,
+            throttle_requests: 1,
+            throttle_window: Duration::from_millis(1),
+            ..Default::default()
+        });
+        for i in 0..SWEEP_EVERY - 1 {
+            bot.throttle(&format!("client-{i}"));
+        }
+        assert_eq!(bot.limiter.len() as u64, SWEEP_EVERY - 1);
+
+        // The sweep drops every client whose limit has replenished.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        bot.throttle(&"client-last".to_string());
+        assert_eq!(bot.limiter.len(), 1);
+    }
+
+    /// Echoes the bot tag header as the body.
+    #[derive(Debug)]
+    struct TagEcho;
+
+    #[async_trait]
+    impl Middleware for TagEcho {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            let tag = req
+                .headers()
+                .get("x-bot-detected")
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            Ok(Response::new(Full::new(Bytes::from(tag))))
+        }
+    }
+
+    async fn tag_for(bot: &BotDetection, ua: Option<&str>) -> Bytes {
+        use http_body_util::BodyExt;
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(bot.clone()) as Arc<dyn Middleware>,
+            Arc::new(TagEcho) as Arc<dyn Middleware>,
+        ]);
+        let response = Next::new(stack).run(ua_request(ua)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response.into_body().collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_tag_mode_flags_matching_requests() {
+        let bot = BotDetection::new(BotDetectionConfig {
+            mode: BotMode::Tag,
+            block_patterns: vec![r"(?i)bot\b".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(tag_for(&bot, Some("AhrefsBot 7.0")).await, r"(?i)bot\b");
+        assert_eq!(tag_for(&bot, Some("Mozilla/5.0")).await, "");
+    }
+
+    #[tokio::test]
+    async fn test_empty_ua_rule_uses_mode_action() {
+        let tagging = BotDetection::new(BotDetectionConfig {
+            mode: BotMode::Tag,
+            empty_ua_is_bot: true,
+            ..Default::default()
+        });
+        assert_eq!(tag_for(&tagging, None).await, EMPTY_UA);
+
+        // Off by default: a missing User-Agent passes untagged.
+        let default = BotDetection::new(BotDetectionConfig {
+            mode: BotMode::Tag,
+            ..Default::default()
+        });
+        assert_eq!(tag_for(&default, None).await, "");
+    }
+
     #[tokio::test]
     async fn test_log_only_mode_passes_through() {
         let config = BotDetectionConfig {
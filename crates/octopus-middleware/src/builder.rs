@@ -1,15 +1,59 @@
 //! Middleware chain builder
 //!
 //! This module provides a builder pattern for constructing middleware chains.
+//! Every middleware belongs to a [`Phase`]; the built chain runs the phases
+//! in order (pre-auth, auth, post-auth, pre-proxy) and keeps declaration
+//! order within a phase, so e.g. compression can never end up ahead of auth
+//! just because it was added first.
 
 use crate::*;
+use octopus_core::{Error, Result};
+use std::fmt;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Stage of the request pipeline a middleware runs in, outermost first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    /// Before authentication: request ids, logging, timeouts, IP filtering,
+    /// CORS preflights
+    PreAuth,
+    /// Authentication
+    Auth,
+    /// After authentication: rate limiting per principal, caching,
+    /// authorization
+    PostAuth,
+    /// Closest to the proxy: response encoding and body rewriting
+    PreProxy,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PreAuth => "pre-auth",
+            Self::Auth => "auth",
+            Self::PostAuth => "post-auth",
+            Self::PreProxy => "pre-proxy",
+        })
+    }
+}
+
+/// A middleware with the phase it was declared in.
+#[derive(Debug)]
+struct Entry {
+    name: &'static str,
+    phase: Phase,
+    /// Phases the middleware may be moved to with
+    /// [`MiddlewareBuilder::in_phase`]
+    allowed: RangeInclusive<Phase>,
+    middleware: Arc<dyn Middleware>,
+}
+
 /// Middleware chain builder
 #[derive(Debug, Default)]
 pub struct MiddlewareBuilder {
-    middlewares: Vec<Arc<dyn Middleware>>,
+    middlewares: Vec<Entry>,
 }
 
 impl MiddlewareBuilder {
@@ -21,164 +65,294 @@ impl MiddlewareBuilder {
         }
     }
 
+    fn push(
+        mut self,
+        name: &'static str,
+        phase: Phase,
+        allowed: RangeInclusive<Phase>,
+        middleware: Arc<dyn Middleware>,
+    ) -> Self {
+        self.middlewares.push(Entry {
+            name,
+            phase,
+            allowed,
+            middleware,
+        });
+        self
+    }
+
     /// Add Request ID middleware
     #[must_use]
-    pub fn with_request_id(mut self) -> Self {
-        self.middlewares.push(Arc::new(RequestId::new()));
-        self
+    pub fn with_request_id(self) -> Self {
+        self.push(
+            "request_id",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PreAuth,
+            Arc::new(RequestId::new()),
+        )
     }
 
     /// Add Request ID middleware with custom configuration
     #[must_use]
-    pub fn with_request_id_config(mut self, config: RequestIdConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(RequestId::with_config(config)));
-        self
+    pub fn with_request_id_config(self, config: RequestIdConfig) -> Self {
+        self.push(
+            "request_id",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PreAuth,
+            Arc::new(RequestId::with_config(config)),
+        )
     }
 
     /// Add Timeout middleware with default config (30s timeout)
     #[must_use]
-    pub fn with_timeout(mut self) -> Self {
-        self.middlewares.push(Arc::new(Timeout::new()));
-        self
+    pub fn with_timeout(self) -> Self {
+        self.push(
+            "timeout",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PostAuth,
+            Arc::new(Timeout::new()),
+        )
     }
 
     /// Add Timeout middleware with custom duration
     #[must_use]
-    pub fn with_timeout_duration(mut self, timeout: Duration) -> Self {
+    pub fn with_timeout_duration(self, timeout: Duration) -> Self {
         let config = TimeoutConfig {
             request_timeout: timeout,
             custom_error_message: None,
         };
-        self.middlewares
-            .push(Arc::new(Timeout::with_config(config)));
-        self
+        self.push(
+            "timeout",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PostAuth,
+            Arc::new(Timeout::with_config(config)),
+        )
     }
 
     /// Add Timeout middleware with custom configuration
     #[must_use]
-    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(Timeout::with_config(config)));
-        self
+    pub fn with_timeout_config(self, config: TimeoutConfig) -> Self {
+        self.push(
+            "timeout",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PostAuth,
+            Arc::new(Timeout::with_config(config)),
+        )
     }
 
     /// Add Logging middleware
     #[must_use]
-    pub fn with_logging(mut self) -> Self {
-        self.middlewares.push(Arc::new(RequestLogger::new()));
-        self
+    pub fn with_logging(self) -> Self {
+        self.push(
+            "logging",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PreAuth,
+            Arc::new(RequestLogger::new()),
+        )
     }
 
     /// Add Logging middleware with custom configuration
     #[must_use]
-    pub fn with_logging_config(mut self, config: LoggingConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(RequestLogger::with_config(config)));
-        self
+    pub fn with_logging_config(self, config: LoggingConfig) -> Self {
+        self.push(
+            "logging",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PreAuth,
+            Arc::new(RequestLogger::with_config(config)),
+        )
     }
 
     /// Add Rate Limiting middleware with default config
     #[must_use]
-    pub fn with_rate_limit(mut self) -> Self {
-        self.middlewares.push(Arc::new(RateLimit::new()));
-        self
+    pub fn with_rate_limit(self) -> Self {
+        self.push(
+            "rate_limit",
+            Phase::PostAuth,
+            Phase::PreAuth..=Phase::PostAuth,
+            Arc::new(RateLimit::new()),
+        )
     }
 
     /// Add Rate Limiting middleware with specific limits
     #[must_use]
-    pub fn with_rate_limit_params(mut self, requests_per_window: u32, window: Duration) -> Self {
+    pub fn with_rate_limit_params(self, requests_per_window: u32, window: Duration) -> Self {
         let config = RateLimitConfig {
             requests_per_window,
             window_size: window,
             ..Default::default()
         };
-        self.middlewares
-            .push(Arc::new(RateLimit::with_config(config)));
-        self
+        self.push(
+            "rate_limit",
+            Phase::PostAuth,
+            Phase::PreAuth..=Phase::PostAuth,
+            Arc::new(RateLimit::with_config(config)),
+        )
     }
 
     /// Add Rate Limiting middleware with custom configuration
     #[must_use]
-    pub fn with_rate_limit_config(mut self, config: RateLimitConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(RateLimit::with_config(config)));
-        self
+    pub fn with_rate_limit_config(self, config: RateLimitConfig) -> Self {
+        self.push(
+            "rate_limit",
+            Phase::PostAuth,
+            Phase::PreAuth..=Phase::PostAuth,
+            Arc::new(RateLimit::with_config(config)),
+        )
     }
 
     /// Add CORS middleware
     #[must_use]
-    pub fn with_cors(mut self) -> Self {
-        self.middlewares.push(Arc::new(Cors::new()));
-        self
+    pub fn with_cors(self) -> Self {
+        self.push(
+            "cors",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PreAuth,
+            Arc::new(Cors::new()),
+        )
     }
 
     /// Add CORS middleware with custom configuration
     #[must_use]
-    pub fn with_cors_config(mut self, config: CorsConfig) -> Self {
-        self.middlewares.push(Arc::new(Cors::with_config(config)));
-        self
+    pub fn with_cors_config(self, config: CorsConfig) -> Self {
+        self.push(
+            "cors",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::PreAuth,
+            Arc::new(Cors::with_config(config)),
+        )
     }
 
     /// Add Compression middleware
     #[must_use]
-    pub fn with_compression(mut self) -> Self {
-        self.middlewares.push(Arc::new(Compression::new()));
-        self
+    pub fn with_compression(self) -> Self {
+        self.push(
+            "compression",
+            Phase::PreProxy,
+            Phase::PreProxy..=Phase::PreProxy,
+            Arc::new(Compression::new()),
+        )
     }
 
     /// Add Compression middleware with custom configuration
     #[must_use]
-    pub fn with_compression_config(mut self, config: CompressionConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(Compression::with_config(config)));
-        self
+    pub fn with_compression_config(self, config: CompressionConfig) -> Self {
+        self.push(
+            "compression",
+            Phase::PreProxy,
+            Phase::PreProxy..=Phase::PreProxy,
+            Arc::new(Compression::with_config(config)),
+        )
     }
 
     /// Add IP Filter middleware with custom configuration
     #[must_use]
-    pub fn with_ip_filter(mut self, config: crate::IpFilterConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(crate::IpFilter::with_config(config)));
-        self
+    pub fn with_ip_filter(self, config: crate::IpFilterConfig) -> Self {
+        self.push(
+            "ip_filter",
+            Phase::PreAuth,
+            Phase::PreAuth..=Phase::Auth,
+            Arc::new(crate::IpFilter::with_config(config)),
+        )
     }
 
     /// Add Forward Auth middleware with custom configuration
     #[must_use]
-    pub fn with_forward_auth(mut self, config: crate::ForwardAuthConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(crate::ForwardAuth::with_config(config)));
-        self
+    pub fn with_forward_auth(self, config: crate::ForwardAuthConfig) -> Self {
+        self.push(
+            "forward_auth",
+            Phase::Auth,
+            Phase::Auth..=Phase::Auth,
+            Arc::new(crate::ForwardAuth::with_config(config)),
+        )
     }
 
     /// Add Response Caching middleware with default configuration
     #[must_use]
-    pub fn with_caching(mut self) -> Self {
-        self.middlewares.push(Arc::new(crate::Caching::new()));
-        self
+    pub fn with_caching(self) -> Self {
+        self.push(
+            "caching",
+            Phase::PostAuth,
+            Phase::PostAuth..=Phase::PreProxy,
+            Arc::new(crate::Caching::new()),
+        )
     }
 
     /// Add Response Caching middleware with custom configuration
     #[must_use]
-    pub fn with_caching_config(mut self, config: crate::CachingConfig) -> Self {
-        self.middlewares
-            .push(Arc::new(crate::Caching::with_config(config)));
-        self
+    pub fn with_caching_config(self, config: crate::CachingConfig) -> Self {
+        self.push(
+            "caching",
+            Phase::PostAuth,
+            Phase::PostAuth..=Phase::PreProxy,
+            Arc::new(crate::Caching::with_config(config)),
+        )
+    }
+
+    /// Add custom middleware in the [`Phase::PostAuth`] phase
+    #[must_use]
+    pub fn with_middleware(self, middleware: Arc<dyn Middleware>) -> Self {
+        self.with_middleware_in(Phase::PostAuth, middleware)
+    }
+
+    /// Add custom middleware in the given phase
+    #[must_use]
+    pub fn with_middleware_in(self, phase: Phase, middleware: Arc<dyn Middleware>) -> Self {
+        self.push("custom", phase, phase..=phase, middleware)
     }
 
-    /// Add custom middleware
+    /// Add several custom middlewares, in order, in the given phase
+    #[must_use]
+    pub fn with_middlewares_in(
+        self,
+        phase: Phase,
+        middlewares: impl IntoIterator<Item = Arc<dyn Middleware>>,
+    ) -> Self {
+        middlewares
+            .into_iter()
+            .fold(self, |builder, mw| builder.with_middleware_in(phase, mw))
+    }
+
+    /// Move the most recently added middleware to `phase`
+    ///
+    /// Built-in middleware only accepts phases where it is safe (CORS must
+    /// answer preflights before auth, compression and caching must not run
+    /// ahead of it, ...); [`build`](Self::build) rejects anything else.
     #[must_use]
-    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
-        self.middlewares.push(middleware);
+    pub fn in_phase(mut self, phase: Phase) -> Self {
+        if let Some(entry) = self.middlewares.last_mut() {
+            entry.phase = phase;
+            if entry.name == "custom" {
+                entry.allowed = phase..=phase;
+            }
+        }
         self
     }
 
     /// Build the middleware chain
     ///
-    /// Returns an `Arc<[Arc<dyn Middleware>]>` for efficient sharing.
-    #[must_use]
-    pub fn build(self) -> Arc<[Arc<dyn Middleware>]> {
-        self.middlewares.into()
+    /// Orders the middleware by phase (stable within a phase) and returns an
+    /// `Arc<[Arc<dyn Middleware>]>` for efficient sharing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when a built-in middleware was placed in a
+    /// phase it must not run in.
+    pub fn build(mut self) -> Result<Arc<[Arc<dyn Middleware>]>> {
+        if let Some(entry) = self
+            .middlewares
+            .iter()
+            .find(|e| !e.allowed.contains(&e.phase))
+        {
+            return Err(Error::Config(format!(
+                "{} middleware cannot run in the {} phase (allowed: {} to {})",
+                entry.name,
+                entry.phase,
+                entry.allowed.start(),
+                entry.allowed.end()
+            )));
+        }
+        self.middlewares.sort_by_key(|e| e.phase);
+        Ok(self.middlewares.into_iter().map(|e| e.middleware).collect())
     }
 
     /// Get the number of middlewares in the chain
@@ -200,13 +374,13 @@ mod tests {
 
     #[test]
     fn test_builder_empty() {
-        let chain = MiddlewareBuilder::new().build();
+        let chain = MiddlewareBuilder::new().build().unwrap();
         assert!(chain.is_empty());
     }
 
     #[test]
     fn test_builder_single_middleware() {
-        let chain = MiddlewareBuilder::new().with_request_id().build();
+        let chain = MiddlewareBuilder::new().with_request_id().build().unwrap();
         assert_eq!(chain.len(), 1);
     }
 
@@ -219,7 +393,8 @@ mod tests {
             .with_rate_limit()
             .with_cors()
             .with_compression()
-            .build();
+            .build()
+            .unwrap();
         assert_eq!(chain.len(), 6);
     }
 
@@ -232,7 +407,8 @@ mod tests {
 
         let chain = MiddlewareBuilder::new()
             .with_request_id_config(request_id_config)
-            .build();
+            .build()
+            .unwrap();
         assert_eq!(chain.len(), 1);
     }
 
@@ -246,4 +422,93 @@ mod tests {
         assert_eq!(builder.len(), 1);
         assert!(!builder.is_empty());
     }
+
+    /// Records its label, then passes the request on.
+    #[derive(Debug)]
+    struct Record(&'static str, Arc<parking_lot::Mutex<Vec<&'static str>>>);
+
+    #[async_trait::async_trait]
+    impl Middleware for Record {
+        async fn call(
+            &self,
+            req: http::Request<octopus_core::middleware::Body>,
+            next: Next,
+        ) -> Result<http::Response<octopus_core::middleware::Body>> {
+            self.1.lock().push(self.0);
+            next.run(req).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_executes_in_phase_order() {
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let record = |label| Arc::new(Record(label, Arc::clone(&seen))) as Arc<dyn Middleware>;
+
+        let chain = MiddlewareBuilder::new()
+            .with_middleware_in(Phase::PreProxy, record("pre-proxy"))
+            .with_middleware(record("post-auth"))
+            .with_middleware_in(Phase::Auth, record("auth"))
+            .with_middleware_in(Phase::PreAuth, record("pre-auth-1"))
+            .with_middleware_in(Phase::PreAuth, record("pre-auth-2"))
+            .build()
+            .unwrap();
+
+        let req = http::Request::builder()
+            .uri("/")
+            .body(octopus_core::middleware::Body::from(""))
+            .unwrap();
+        let handler: octopus_core::middleware::HandlerFn = Box::new(|_req| {
+            Box::pin(async {
+                Ok(http::Response::new(octopus_core::middleware::Body::from(
+                    "",
+                )))
+            })
+        });
+        Next::with_handler(chain, handler).run(req).await.unwrap();
+
+        assert_eq!(
+            *seen.lock(),
+            ["pre-auth-1", "pre-auth-2", "auth", "post-auth", "pre-proxy"]
+        );
+    }
+
+    #[test]
+    fn test_builtins_use_canonical_order() {
+        let chain = MiddlewareBuilder::new()
+            .with_compression()
+            .with_rate_limit()
+            .with_cors()
+            .with_request_id()
+            .build()
+            .unwrap();
+
+        let names: Vec<String> = chain.iter().map(|mw| format!("{mw:?}")).collect();
+        assert!(names[0].starts_with("Cors"));
+        assert!(names[1].starts_with("RequestId"));
+        assert!(names[2].starts_with("RateLimit"));
+        assert!(names[3].starts_with("Compression"));
+    }
+
+    #[test]
+    fn test_phase_violation_is_rejected() {
+        let err = MiddlewareBuilder::new()
+            .with_compression()
+            .in_phase(Phase::PreAuth)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("compression"));
+
+        assert!(MiddlewareBuilder::new()
+            .with_cors()
+            .in_phase(Phase::PostAuth)
+            .build()
+            .is_err());
+
+        // Moving within the allowed range is fine.
+        assert!(MiddlewareBuilder::new()
+            .with_rate_limit()
+            .in_phase(Phase::PreAuth)
+            .build()
+            .is_ok());
+    }
 }
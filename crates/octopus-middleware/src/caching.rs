@@ -2,19 +2,42 @@
 //!
 //! Caches HTTP responses based on method, path, query, and configurable headers.
 //! Supports in-memory storage with TTL-based expiration and FIFO eviction.
+//!
+//! ## Content negotiation
+//!
+//! - The upstream's `Vary` header is honored: the request headers it names
+//!   become part of the cache key for that resource (`Vary: *` is never
+//!   cached).
+//! - Compressed upstream responses (`gzip`, `deflate`, `br`, `zstd`) are
+//!   stored decoded, together with the original encoded bytes. On a hit the
+//!   body is re-encoded for the requesting client from its `Accept-Encoding`,
+//!   so a client that did not ask for compression never receives it.
+//!   Responses in an encoding the cache cannot decode are keyed on the
+//!   client's `Accept-Encoding` instead.
+//!
+//! ## Request coalescing
+//!
+//! Concurrent misses for the same cache key are collapsed into one upstream
+//! call (single flight): the first request leads, the others wait for it —
+//! at most [`CachingConfig::coalesce_timeout`] — and are then served from the
+//! entry it stored. Followers whose leader produced nothing cacheable, or who
+//! time out, go upstream themselves.
 
+use crate::compression::{Compression, CompressionAlgorithm, CompressionConfig};
 use async_trait::async_trait;
 use bytes::Bytes;
 use dashmap::DashMap;
-use http::header::HeaderMap;
+use http::header::{self, HeaderMap, HeaderValue};
 use http::{Method, Request, Response, StatusCode};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_core::{Middleware, Next, Result, StreamedResponse};
 use sha2::{Digest, Sha256};
 use std::collections::VecDeque;
 use std::fmt;
+use std::io::Read;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::watch;
 
 /// Body type alias
 pub type Body = Full<Bytes>;
@@ -36,6 +59,11 @@ pub struct CachingConfig {
     pub cacheable_status_max: u16,
     /// Headers to include in cache key generation (Vary)
     pub vary_by_headers: Vec<String>,
+    /// Collapse concurrent misses for the same key into one upstream call
+    pub coalesce_requests: bool,
+    /// How long a coalesced request waits for the in-flight leader before
+    /// going upstream itself
+    pub coalesce_timeout: Duration,
 }
 
 impl Default for CachingConfig {
@@ -48,6 +76,8 @@ impl Default for CachingConfig {
             cacheable_status_min: 200,
             cacheable_status_max: 399,
             vary_by_headers: Vec::new(),
+            coalesce_requests: true,
+            coalesce_timeout: Duration::from_secs(5),
         }
     }
 }
@@ -57,10 +87,13 @@ impl Default for CachingConfig {
 pub struct CachedResponse {
     /// HTTP status code
     pub status: StatusCode,
-    /// Response headers
+    /// Response headers (without `Content-Encoding` when `encoded` is set)
     pub headers: HeaderMap,
-    /// Response body
+    /// Response body (the identity representation when `encoded` is set)
     pub body: Bytes,
+    /// The upstream's original `Content-Encoding` and encoded body, when the
+    /// stored `body` was decoded from it
+    pub encoded: Option<(String, Bytes)>,
     /// When this entry was cached
     pub cached_at: Instant,
     /// TTL for this entry
@@ -155,6 +188,100 @@ impl CacheStore for InMemoryCacheStore {
 pub struct Caching {
     config: CachingConfig,
     store: Arc<dyn CacheStore>,
+    /// Request headers the upstream said each resource varies on
+    vary_index: Arc<VaryIndex>,
+    /// Cache keys with an upstream call in flight; followers subscribe and
+    /// are woken when the leader's [`FlightGuard`] drops the sender
+    in_flight: Arc<DashMap<String, watch::Sender<()>>>,
+}
+
+/// Base cache key → request headers (lowercased, sorted) the upstream said
+/// the resource varies on.
+///
+/// Bounded like the store: a record lasts as long as the newest variant
+/// stored under it, and at most `max_entries` are kept, oldest out first.
+#[derive(Debug)]
+struct VaryIndex {
+    /// Vary headers and when the record expires
+    entries: DashMap<String, (Vec<String>, Instant)>,
+    insertion_order: parking_lot::Mutex<VecDeque<String>>,
+    max_entries: usize,
+}
+
+impl VaryIndex {
+    fn new(max_entries: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            insertion_order: parking_lot::Mutex::new(VecDeque::new()),
+            max_entries,
+        }
+    }
+
+    /// The headers recorded for `base_key`; none once its record expired.
+    /// Expired records stay queued until the next store overwrites them or
+    /// they are evicted.
+    fn get(&self, base_key: &str) -> Vec<String> {
+        self.entries
+            .get(base_key)
+            .filter(|entry| entry.1 > Instant::now())
+            .map(|entry| entry.0.clone())
+            .unwrap_or_default()
+    }
+
+    /// Record `vary` for `base_key` until at least `expires_at`
+    fn insert(&self, base_key: String, vary: Vec<String>, expires_at: Instant) {
+        use dashmap::mapref::entry::Entry;
+        if vary.is_empty() {
+            self.entries.remove(&base_key);
+            return;
+        }
+        match self.entries.entry(base_key) {
+            Entry::Occupied(mut entry) => {
+                let (headers, expiry) = entry.get_mut();
+                *headers = vary;
+                *expiry = (*expiry).max(expires_at);
+                return;
+            }
+            Entry::Vacant(slot) => {
+                self.insertion_order.lock().push_back(slot.key().clone());
+                slot.insert((vary, expires_at));
+            }
+        }
+
+        let mut order = self.insertion_order.lock();
+        while self.entries.len() > self.max_entries {
+            let Some(oldest) = order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        // Records dropped for an empty `Vary` stay queued; trim them before
+        // the queue outgrows the index.
+        if order.len() > self.max_entries.saturating_mul(2) {
+            order.retain(|key| self.entries.contains_key(key));
+        }
+    }
+}
+
+/// Role of a request in single-flight coalescing
+enum Flight {
+    /// First miss for the key: performs the upstream call
+    Leader(FlightGuard),
+    /// A call for the key is already in flight: wait for it
+    Follower(watch::Receiver<()>),
+}
+
+/// Marks a key in flight until dropped (on success, error or cancellation)
+struct FlightGuard {
+    key: String,
+    in_flight: Arc<DashMap<String, watch::Sender<()>>>,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        // Dropping the sender wakes every follower.
+        self.in_flight.remove(&self.key);
+    }
 }
 
 impl Caching {
@@ -166,12 +293,17 @@ impl Caching {
     /// Create with custom config and default in-memory store
     pub fn with_config(config: CachingConfig) -> Self {
         let store = Arc::new(InMemoryCacheStore::new(config.max_entries));
-        Self { config, store }
+        Self::with_store(config, store)
     }
 
     /// Create with custom config and store
     pub fn with_store(config: CachingConfig, store: Arc<dyn CacheStore>) -> Self {
-        Self { config, store }
+        Self {
+            vary_index: Arc::new(VaryIndex::new(config.max_entries)),
+            config,
+            store,
+            in_flight: Arc::new(DashMap::new()),
+        }
     }
 
     /// Generate a cache key from the request
@@ -210,6 +342,108 @@ impl Caching {
         hex::encode(hasher.finalize())
     }
 
+    /// Extend a base key with the request's values for the `vary` headers
+    fn variant_key(base: &str, vary: &[String], headers: &HeaderMap) -> String {
+        if vary.is_empty() {
+            return base.to_string();
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(base.as_bytes());
+        for name in vary {
+            hasher.update(b"|");
+            hasher.update(name.as_bytes());
+            hasher.update(b"=");
+            // Repeated headers are joined so their order is significant.
+            for value in headers.get_all(name.as_str()) {
+                hasher.update(value.as_bytes());
+                hasher.update(b",");
+            }
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// Key of the variant of `base_key` matching `headers`, per the `Vary`
+    /// last seen for the resource
+    fn current_key(&self, base_key: &str, headers: &HeaderMap) -> String {
+        Self::variant_key(base_key, &self.vary_index.get(base_key), headers)
+    }
+
+    /// Look up the variant of `base_key` matching `headers`
+    async fn lookup(&self, base_key: &str, headers: &HeaderMap) -> Option<CachedResponse> {
+        self.store.get(&self.current_key(base_key, headers)).await
+    }
+
+    /// Lead or follow the in-flight upstream call for `key`
+    fn join_flight(&self, key: &str) -> Flight {
+        use dashmap::mapref::entry::Entry;
+        match self.in_flight.entry(key.to_string()) {
+            Entry::Occupied(flight) => Flight::Follower(flight.get().subscribe()),
+            Entry::Vacant(slot) => {
+                slot.insert(watch::channel(()).0);
+                Flight::Leader(FlightGuard {
+                    key: key.to_string(),
+                    in_flight: Arc::clone(&self.in_flight),
+                })
+            }
+        }
+    }
+
+    /// Header names from the response's `Vary`, lowercased; `None` for `Vary: *`
+    fn response_vary(headers: &HeaderMap) -> Option<Vec<String>> {
+        let mut names = Vec::new();
+        for value in headers.get_all(header::VARY) {
+            for name in value.to_str().unwrap_or_default().split(',') {
+                let name = name.trim().to_ascii_lowercase();
+                if name == "*" {
+                    return None;
+                }
+                if !name.is_empty() {
+                    names.push(name);
+                }
+            }
+        }
+        Some(names)
+    }
+
+    /// Build the response for a cache hit, encoding the body for this client
+    fn cached_response(cached: CachedResponse, accept_encoding: Option<&str>) -> Response<Body> {
+        let mut headers = cached.headers;
+        let mut body = cached.body;
+
+        if let Some((encoding, encoded_body)) = cached.encoded {
+            if accepts_encoding(accept_encoding, &encoding) {
+                body = encoded_body;
+                set_content_encoding(&mut headers, &encoding);
+            } else if encoding != "gzip" && accepts_encoding(accept_encoding, "gzip") {
+                let gzip = Compression::with_config(CompressionConfig {
+                    min_size: 0,
+                    ..Default::default()
+                });
+                if let Ok(compressed) = gzip.compress_body(body.clone(), CompressionAlgorithm::Gzip)
+                {
+                    body = compressed;
+                    set_content_encoding(&mut headers, "gzip");
+                }
+            }
+            // The representation now depends on the client's Accept-Encoding.
+            let varies_on_encoding = headers.get_all(header::VARY).iter().any(|v| {
+                v.to_str()
+                    .unwrap_or_default()
+                    .split(',')
+                    .any(|n| n.trim().eq_ignore_ascii_case("accept-encoding"))
+            });
+            if !varies_on_encoding {
+                headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+            }
+        }
+
+        let mut resp = Response::new(Full::new(body));
+        *resp.status_mut() = cached.status;
+        *resp.headers_mut() = headers;
+        resp
+    }
+
     /// Check if a method is cacheable
     fn is_cacheable_method(&self, method: &Method) -> bool {
         self.config.cacheable_methods.contains(method)
@@ -296,64 +530,167 @@ impl Middleware for Caching {
             return Ok(resp);
         }
 
-        let key = self.cache_key(&req);
+        let base_key = self.cache_key(&req);
+        let accept_encoding = req
+            .headers()
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
 
         // Try cache lookup
-        if let Some(cached) = self.store.get(&key).await {
-            // Build response from cache
-            let mut builder = Response::builder().status(cached.status);
-            for (name, value) in cached.headers.iter() {
-                builder = builder.header(name, value);
-            }
-            let mut resp = builder
-                .body(Full::new(cached.body))
-                .expect("Failed to build cached response");
+        if let Some(cached) = self.lookup(&base_key, req.headers()).await {
+            let mut resp = Self::cached_response(cached, accept_encoding.as_deref());
             resp.headers_mut()
                 .insert("X-Cache", http::header::HeaderValue::from_static("HIT"));
+            resp.extensions_mut()
+                .insert(octopus_core::UpstreamSelection::cache_hit());
             return Ok(resp);
         }
 
+        // Single flight: one upstream call per cache key. A resource whose
+        // Vary is not known yet coalesces all its requests on the base key.
+        let _flight = if self.config.coalesce_requests {
+            match self.join_flight(&self.current_key(&base_key, req.headers())) {
+                Flight::Leader(guard) => Some(guard),
+                Flight::Follower(mut leader) => {
+                    let _ =
+                        tokio::time::timeout(self.config.coalesce_timeout, leader.changed()).await;
+                    if let Some(cached) = self.lookup(&base_key, req.headers()).await {
+                        let mut resp = Self::cached_response(cached, accept_encoding.as_deref());
+                        resp.headers_mut()
+                            .insert("X-Cache", http::header::HeaderValue::from_static("HIT"));
+                        resp.extensions_mut()
+                            .insert(octopus_core::UpstreamSelection::cache_hit());
+                        return Ok(resp);
+                    }
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // Cache miss — forward request
+        let req_headers = req.headers().clone();
         let resp = next.run(req).await?;
 
-        // Check if response is cacheable
-        if self.is_cacheable_status(resp.status()) {
-            if let Some(ttl) = self.extract_ttl(resp.headers()) {
-                // Collect response body for caching
-                use http_body_util::BodyExt;
-                let status = resp.status();
-                let headers = resp.headers().clone();
-                let body_bytes = resp
-                    .into_body()
-                    .collect()
-                    .await
-                    .map(|c| c.to_bytes())
-                    .unwrap_or_default();
-
-                let cached = CachedResponse {
-                    status,
-                    headers: headers.clone(),
-                    body: body_bytes.clone(),
-                    cached_at: Instant::now(),
-                    ttl,
-                };
-                self.store.set(&key, cached).await;
+        // Check if response is cacheable; a streamed body is never stored
+        if !self.is_cacheable_status(resp.status()) || StreamedResponse::is_streamed(&resp) {
+            return Ok(resp);
+        }
+        let Some(ttl) = self.extract_ttl(resp.headers()) else {
+            return Ok(resp);
+        };
+        let Some(mut vary) = Self::response_vary(resp.headers()) else {
+            // `Vary: *` — every request is a distinct representation.
+            return Ok(resp);
+        };
 
-                // Rebuild response with MISS header
-                let mut builder = Response::builder().status(status);
-                for (name, value) in headers.iter() {
-                    builder = builder.header(name, value);
+        // Collect response body for caching
+        use http_body_util::BodyExt;
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body_bytes = resp
+            .into_body()
+            .collect()
+            .await
+            .map(|c| c.to_bytes())
+            .unwrap_or_default();
+
+        // Store the identity representation so any client can be served.
+        let content_encoding = headers
+            .get(header::CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| v != "identity");
+        let mut stored_headers = headers.clone();
+        let (stored_body, encoded) = match content_encoding {
+            None => (body_bytes.clone(), None),
+            Some(encoding) => match decode_body(&encoding, &body_bytes) {
+                Some(decoded) => {
+                    stored_headers.remove(header::CONTENT_ENCODING);
+                    stored_headers.remove(header::CONTENT_LENGTH);
+                    // Encoding is negotiated per client on every hit.
+                    vary.retain(|name| name != "accept-encoding");
+                    (decoded, Some((encoding, body_bytes.clone())))
                 }
-                let mut resp = builder
-                    .body(Full::new(body_bytes))
-                    .expect("Failed to build response");
-                resp.headers_mut()
-                    .insert("X-Cache", http::header::HeaderValue::from_static("MISS"));
-                return Ok(resp);
-            }
+                None => {
+                    // Opaque encoding: only serve it to clients that
+                    // negotiated the same way.
+                    vary.push("accept-encoding".to_string());
+                    (body_bytes.clone(), None)
+                }
+            },
+        };
+        vary.sort();
+        vary.dedup();
+
+        let key = Self::variant_key(&base_key, &vary, &req_headers);
+        self.vary_index.insert(base_key, vary, Instant::now() + ttl);
+
+        let cached = CachedResponse {
+            status,
+            headers: stored_headers,
+            body: stored_body,
+            encoded,
+            cached_at: Instant::now(),
+            ttl,
+        };
+        self.store.set(&key, cached).await;
+
+        // Return the upstream's response as-is (already negotiated for this client)
+        let mut resp = Response::new(Full::new(body_bytes));
+        *resp.status_mut() = status;
+        *resp.headers_mut() = headers;
+        resp.headers_mut()
+            .insert("X-Cache", http::header::HeaderValue::from_static("MISS"));
+        Ok(resp)
+    }
+}
+
+/// Whether the client's `Accept-Encoding` allows `encoding` (RFC 9110 §12.5.3)
+fn accepts_encoding(accept_encoding: Option<&str>, encoding: &str) -> bool {
+    let Some(accept) = accept_encoding else {
+        return false;
+    };
+
+    let mut wildcard = None;
+    for entry in accept.split(',') {
+        let mut parts = entry.split(';');
+        let coding = parts.next().unwrap_or_default().trim();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if coding.eq_ignore_ascii_case(encoding)
+            || (encoding == "gzip" && coding.eq_ignore_ascii_case("x-gzip"))
+        {
+            return q > 0.0;
+        }
+        if coding == "*" {
+            wildcard = Some(q > 0.0);
         }
+    }
+    wildcard.unwrap_or(false)
+}
 
-        Ok(resp)
+/// Decode a `Content-Encoding`d body; `None` for unknown codings or corrupt data
+fn decode_body(encoding: &str, body: &[u8]) -> Option<Bytes> {
+    let mut decoded = Vec::with_capacity(body.len() * 2);
+    let result = match encoding {
+        "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body).read_to_end(&mut decoded),
+        "deflate" => flate2::read::ZlibDecoder::new(body).read_to_end(&mut decoded),
+        "br" => brotli::Decompressor::new(body, 4096).read_to_end(&mut decoded),
+        "zstd" => zstd::stream::read::Decoder::new(body)
+            .and_then(|mut decoder| decoder.read_to_end(&mut decoded)),
+        _ => return None,
+    };
+    result.ok().map(|_| Bytes::from(decoded))
+}
+
+fn set_content_encoding(headers: &mut HeaderMap, encoding: &str) {
+    if let Ok(value) = HeaderValue::from_str(encoding) {
+        headers.insert(header::CONTENT_ENCODING, value);
     }
 }
 
@@ -368,6 +705,7 @@ mod tests {
         call_count: Arc<AtomicU32>,
         status: StatusCode,
         cache_control: Option<String>,
+        delay: Duration,
     }
 
     impl CountingHandler {
@@ -376,9 +714,15 @@ mod tests {
                 call_count: Arc::new(AtomicU32::new(0)),
                 status: StatusCode::OK,
                 cache_control: None,
+                delay: Duration::ZERO,
             }
         }
 
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
         fn with_status(mut self, status: StatusCode) -> Self {
             self.status = status;
             self
@@ -394,6 +738,7 @@ mod tests {
     impl Middleware for CountingHandler {
         async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
             let count = self.call_count.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
             let mut builder = Response::builder().status(self.status);
             if let Some(ref cc) = self.cache_control {
                 builder = builder.header("Cache-Control", cc.as_str());
@@ -660,6 +1005,7 @@ mod tests {
                     status: StatusCode::OK,
                     headers: HeaderMap::new(),
                     body: Bytes::from(format!("body-{i}")),
+                    encoded: None,
                     cached_at: Instant::now(),
                     ttl: Duration::from_secs(60),
                 };
@@ -672,4 +1018,340 @@ mod tests {
         assert!(results.iter().all(|r| r.is_ok()));
         assert_eq!(store.len().await, 10);
     }
+
+    /// Upstream that serves a fixed body in `encoding` with the given `Vary`,
+    /// echoing the request's `Accept-Language` in the body.
+    #[derive(Debug)]
+    struct NegotiatingHandler {
+        call_count: Arc<AtomicU32>,
+        encoding: Option<CompressionAlgorithm>,
+        vary: &'static str,
+    }
+
+    impl NegotiatingHandler {
+        fn new(encoding: Option<CompressionAlgorithm>, vary: &'static str) -> Self {
+            Self {
+                call_count: Arc::new(AtomicU32::new(0)),
+                encoding,
+                vary,
+            }
+        }
+    }
+
+    const PAYLOAD: &str = "{\"users\":[\"ada\",\"grace\",\"linus\"]}";
+
+    #[async_trait]
+    impl Middleware for NegotiatingHandler {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            self.call_count.fetch_add(1, Ordering::SeqCst);
+            let lang = req
+                .headers()
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("en");
+            let body = Bytes::from(format!("{lang}:{PAYLOAD}"));
+            let mut builder = Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "application/json");
+            if !self.vary.is_empty() {
+                builder = builder.header(header::VARY, self.vary);
+            }
+            let body = match self.encoding {
+                Some(algorithm) => {
+                    builder = builder.header(header::CONTENT_ENCODING, algorithm.as_str());
+                    compress(body, algorithm)
+                }
+                None => body,
+            };
+            builder
+                .body(Full::new(body))
+                .map_err(|e| Error::Internal(e.to_string()))
+        }
+    }
+
+    fn compress(body: Bytes, algorithm: CompressionAlgorithm) -> Bytes {
+        Compression::with_config(CompressionConfig {
+            min_size: 0,
+            ..Default::default()
+        })
+        .compress_body(body, algorithm)
+        .unwrap()
+    }
+
+    fn negotiated_req(accept_encoding: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().method("GET").uri("/users");
+        if let Some(ae) = accept_encoding {
+            builder = builder.header(header::ACCEPT_ENCODING, ae);
+        }
+        builder.body(Body::from("")).unwrap()
+    }
+
+    /// Decode a response body per its `Content-Encoding`
+    async fn decoded_body(resp: Response<Body>) -> String {
+        use http_body_util::BodyExt;
+        let encoding = resp
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|v| v.to_str().unwrap().to_string());
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let bytes = match encoding {
+            Some(encoding) => decode_body(&encoding, &bytes).expect("body must decode"),
+            None => bytes,
+        };
+        String::from_utf8(bytes.to_vec()).expect("identity body must be plain text")
+    }
+
+    #[tokio::test]
+    async fn clients_with_different_accept_encoding_get_matching_bodies() {
+        let handler = NegotiatingHandler::new(Some(CompressionAlgorithm::Gzip), "Accept-Encoding");
+        let count = handler.call_count.clone();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(Caching::new()) as Arc<dyn Middleware>,
+            Arc::new(handler) as Arc<dyn Middleware>,
+        ]);
+
+        // gzip-capable client populates the cache.
+        let resp = Next::new(stack.clone())
+            .run(negotiated_req(Some("gzip")))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["X-Cache"], "MISS");
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(decoded_body(resp).await, format!("en:{PAYLOAD}"));
+
+        // A client without Accept-Encoding gets the identity body from cache.
+        let resp = Next::new(stack.clone())
+            .run(negotiated_req(None))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["X-Cache"], "HIT");
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(resp.headers()[header::VARY], "Accept-Encoding");
+        assert_eq!(decoded_body(resp).await, format!("en:{PAYLOAD}"));
+
+        // A client that refuses gzip explicitly also gets identity.
+        let resp = Next::new(stack.clone())
+            .run(negotiated_req(Some("gzip;q=0, identity")))
+            .await
+            .unwrap();
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+
+        // A gzip client hitting the cache gets gzip again.
+        let resp = Next::new(stack)
+            .run(negotiated_req(Some("br;q=0.5, gzip")))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["X-Cache"], "HIT");
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(decoded_body(resp).await, format!("en:{PAYLOAD}"));
+
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn cached_brotli_is_recompressed_for_gzip_only_clients() {
+        let handler = NegotiatingHandler::new(Some(CompressionAlgorithm::Brotli), "");
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(Caching::new()) as Arc<dyn Middleware>,
+            Arc::new(handler) as Arc<dyn Middleware>,
+        ]);
+
+        let _ = Next::new(stack.clone())
+            .run(negotiated_req(Some("br")))
+            .await
+            .unwrap();
+
+        let resp = Next::new(stack)
+            .run(negotiated_req(Some("gzip")))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["X-Cache"], "HIT");
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding");
+        assert_eq!(decoded_body(resp).await, format!("en:{PAYLOAD}"));
+    }
+
+    #[tokio::test]
+    async fn upstream_vary_header_splits_cache_entries() {
+        let handler = NegotiatingHandler::new(None, "Accept-Language");
+        let count = handler.call_count.clone();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(Caching::new()) as Arc<dyn Middleware>,
+            Arc::new(handler) as Arc<dyn Middleware>,
+        ]);
+        let lang_req = |lang: &str| {
+            Request::builder()
+                .uri("/users")
+                .header(header::ACCEPT_LANGUAGE, lang)
+                .body(Body::from(""))
+                .unwrap()
+        };
+
+        let resp = Next::new(stack.clone()).run(lang_req("en")).await.unwrap();
+        assert_eq!(decoded_body(resp).await, format!("en:{PAYLOAD}"));
+
+        // Different language → different variant, not the English entry.
+        let resp = Next::new(stack.clone()).run(lang_req("fr")).await.unwrap();
+        assert_eq!(resp.headers()["X-Cache"], "MISS");
+        assert_eq!(decoded_body(resp).await, format!("fr:{PAYLOAD}"));
+
+        let resp = Next::new(stack).run(lang_req("en")).await.unwrap();
+        assert_eq!(resp.headers()["X-Cache"], "HIT");
+        assert_eq!(decoded_body(resp).await, format!("en:{PAYLOAD}"));
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn vary_index_is_bounded_and_expires_with_entries() {
+        let caching = Caching::with_config(CachingConfig {
+            max_entries: 2,
+            default_ttl: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(caching.clone()) as Arc<dyn Middleware>,
+            Arc::new(NegotiatingHandler::new(None, "Accept-Language")) as Arc<dyn Middleware>,
+        ]);
+
+        for i in 0..10 {
+            let _ = Next::new(stack.clone())
+                .run(get_req(&format!("/users/{i}")))
+                .await
+                .unwrap();
+        }
+        assert_eq!(caching.vary_index.entries.len(), 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        for i in 8..10 {
+            let resp = Next::new(stack.clone())
+                .run(get_req(&format!("/users/{i}")))
+                .await
+                .unwrap();
+            assert_eq!(resp.headers()["X-Cache"], "MISS");
+        }
+        assert_eq!(caching.vary_index.entries.len(), 2);
+        assert_eq!(
+            caching.vary_index.insertion_order.lock().len(),
+            2,
+            "refreshed records are not queued twice"
+        );
+    }
+
+    #[tokio::test]
+    async fn vary_star_is_not_cached() {
+        let handler = NegotiatingHandler::new(None, "*");
+        let count = handler.call_count.clone();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(Caching::new()) as Arc<dyn Middleware>,
+            Arc::new(handler) as Arc<dyn Middleware>,
+        ]);
+
+        let _ = Next::new(stack.clone())
+            .run(get_req("/users"))
+            .await
+            .unwrap();
+        let _ = Next::new(stack).run(get_req("/users")).await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    async fn run_concurrently(stack: Arc<[Arc<dyn Middleware>]>, n: usize) -> Vec<Response<Body>> {
+        let handles: Vec<_> = (0..n)
+            .map(|_| {
+                let stack = stack.clone();
+                tokio::spawn(async move { Next::new(stack).run(get_req("/hot")).await.unwrap() })
+            })
+            .collect();
+        futures::future::join_all(handles)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_misses_hit_upstream_once() {
+        let handler = CountingHandler::new().with_delay(Duration::from_millis(50));
+        let count = handler.call_count.clone();
+        let stack = make_stack(Caching::new(), handler);
+
+        let responses = run_concurrently(stack, 50).await;
+
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        let misses = responses
+            .iter()
+            .filter(|r| r.headers()["X-Cache"] == "MISS")
+            .count();
+        assert_eq!(misses, 1, "only the leader misses");
+        for resp in responses {
+            use http_body_util::BodyExt;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = resp.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "response-0");
+        }
+    }
+
+    #[tokio::test]
+    async fn followers_of_uncacheable_leader_go_upstream() {
+        let handler = CountingHandler::new()
+            .with_delay(Duration::from_millis(20))
+            .with_cache_control("no-store");
+        let count = handler.call_count.clone();
+        let stack = make_stack(Caching::new(), handler);
+
+        run_concurrently(stack, 5).await;
+
+        // Nothing was stored, so followers must not share the leader's response.
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stuck_leader_does_not_block_followers_past_timeout() {
+        let config = CachingConfig {
+            coalesce_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let handler = CountingHandler::new().with_delay(Duration::from_secs(60));
+        let count = handler.call_count.clone();
+        let stack = make_stack(Caching::with_config(config), handler);
+
+        let leader = tokio::spawn({
+            let stack = stack.clone();
+            async move { Next::new(stack).run(get_req("/hot")).await }
+        });
+        tokio::task::yield_now().await;
+
+        // The follower gives up on the leader after 100ms and goes upstream
+        // itself instead of waiting the leader's full 60s.
+        let follower = tokio::spawn(async move { Next::new(stack).run(get_req("/hot")).await });
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        leader.abort();
+        follower.abort();
+    }
+
+    #[tokio::test]
+    async fn coalescing_can_be_disabled() {
+        let config = CachingConfig {
+            coalesce_requests: false,
+            ..Default::default()
+        };
+        let handler = CountingHandler::new().with_delay(Duration::from_millis(20));
+        let count = handler.call_count.clone();
+        let stack = make_stack(Caching::with_config(config), handler);
+
+        run_concurrently(stack, 5).await;
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn accept_encoding_honors_q_values_and_wildcards() {
+        assert!(accepts_encoding(Some("gzip, deflate"), "gzip"));
+        assert!(accepts_encoding(Some("x-gzip"), "gzip"));
+        assert!(!accepts_encoding(Some("gzip;q=0"), "gzip"));
+        assert!(accepts_encoding(Some("*"), "br"));
+        assert!(!accepts_encoding(Some("*, br;q=0"), "br"));
+        assert!(!accepts_encoding(Some("identity"), "gzip"));
+        assert!(!accepts_encoding(None, "gzip"));
+    }
 }
@@ -72,21 +72,10 @@ impl Canary {
 
     /// Check if a cookie with the given name has a truthy value ("true" or "1")
     fn cookie_is_truthy(req: &Request<Body>, cookie_name: &str) -> bool {
-        req.headers()
-            .get(http::header::COOKIE)
-            .and_then(|v| v.to_str().ok())
-            .map(|cookie_header| {
-                cookie_header.split(';').any(|pair| {
-                    let pair = pair.trim();
-                    if let Some((name, value)) = pair.split_once('=') {
-                        name.trim() == cookie_name
-                            && (value.trim() == "true" || value.trim() == "1")
-                    } else {
-                        false
-                    }
-                })
-            })
-            .unwrap_or(false)
+        matches!(
+            octopus_core::cookie::get_cookie(req.headers(), cookie_name),
+            Some("true" | "1")
+        )
     }
 }
 
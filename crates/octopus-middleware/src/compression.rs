@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use http::{header, HeaderValue, Request, Response};
 use http_body_util::{BodyExt, Full};
-use octopus_core::{Error, Middleware, Next, Result};
+use octopus_core::{Error, Middleware, Next, Result, StreamedResponse};
 use std::fmt;
 use std::io::Write;
 
@@ -115,8 +115,10 @@ impl Compression {
 
     /// Check if response should be compressed
     fn should_compress(&self, response: &Response<Body>) -> bool {
-        // Check if already compressed
-        if response.headers().contains_key(header::CONTENT_ENCODING) {
+        // Check if already compressed, or streamed past the chain
+        if response.headers().contains_key(header::CONTENT_ENCODING)
+            || StreamedResponse::is_streamed(response)
+        {
             return false;
         }
 
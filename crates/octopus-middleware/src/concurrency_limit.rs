@@ -0,0 +1,666 @@
+//! Concurrency limiting middleware
+//!
+//! Caps how many requests are handled at once, gateway-wide and per route.
+//! A request over a limit waits in a bounded queue for a slot; when the queue
+//! is full, or no slot frees up within `queue_timeout`, it is rejected with
+//! `503 Service Unavailable`. Each limit reports its in-flight, queued and
+//! rejected counts to the [`MetricsCollector`].
+//!
+//! With a [`QosClassifier`] the queue is served by priority: a freed slot
+//! goes to the highest-priority waiter, requests the classifier sheds are
+//! rejected rather than queued, and a request arriving at a full queue
+//! displaces a lower-priority waiter.
+
+use crate::qos::QosClassifier;
+use async_trait::async_trait;
+use http::{Method, Request, Response, StatusCode};
+use octopus_config::types::{ConcurrencyConfig, RequestPriority};
+use octopus_core::request::RouteInfo;
+use octopus_core::{Body, ErrorResponse, Middleware, Next, Result};
+use octopus_metrics::{ConcurrencyStats, MetricsCollector};
+use parking_lot::{Mutex, RwLock};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+/// Metrics scope of the gateway-wide limit
+const GLOBAL_SCOPE: &str = "global";
+
+/// Queue position: highest priority first, then arrival order
+type Ticket = (Reverse<RequestPriority>, u64);
+
+/// Route limits by method and path pattern
+type RouteLimits = HashMap<(Method, String), Arc<ConcurrencyLimiter>>;
+
+/// Free slots of one limit and the requests waiting for them
+#[derive(Debug)]
+struct Slots {
+    state: Mutex<SlotState>,
+    stats: Arc<ConcurrencyStats>,
+}
+
+#[derive(Debug)]
+struct SlotState {
+    free: usize,
+    /// Sending hands the waiter a slot; dropping the sender sheds it
+    waiting: BTreeMap<Ticket, oneshot::Sender<()>>,
+    next_ticket: u64,
+}
+
+impl Slots {
+    /// Hand a slot to the first waiter still waiting, or free it
+    fn release(&self) {
+        let mut state = self.state.lock();
+        while let Some((_, waiter)) = state.waiting.pop_first() {
+            if waiter.send(()).is_ok() {
+                self.sync_queued(&state);
+                return;
+            }
+        }
+        self.sync_queued(&state);
+        state.free += 1;
+    }
+
+    fn sync_queued(&self, state: &SlotState) {
+        self.stats
+            .queued
+            .store(state.waiting.len(), Ordering::Relaxed);
+    }
+}
+
+/// One concurrency limit with its waiting queue
+#[derive(Debug)]
+pub struct ConcurrencyLimiter {
+    config: ConcurrencyConfig,
+    slots: Arc<Slots>,
+}
+
+impl ConcurrencyLimiter {
+    /// Create a limiter counting its load in `stats`
+    pub fn new(config: ConcurrencyConfig, stats: Arc<ConcurrencyStats>) -> Self {
+        Self {
+            slots: Arc::new(Slots {
+                state: Mutex::new(SlotState {
+                    free: config.max_concurrent,
+                    waiting: BTreeMap::new(),
+                    next_ticket: 0,
+                }),
+                stats,
+            }),
+            config,
+        }
+    }
+
+    /// Take a slot, waiting in the queue if none is free. `None` when the
+    /// queue is full or the wait timed out.
+    pub async fn acquire(&self) -> Option<ConcurrencyPermit> {
+        self.acquire_as(RequestPriority::default(), true).await
+    }
+
+    /// Take a slot for a request of `priority`. When none is free the
+    /// request waits behind higher-priority ones, or with `may_queue` unset
+    /// is rejected at once. `None` when rejected, displaced from the queue
+    /// by a higher-priority request, or timed out.
+    pub async fn acquire_as(
+        &self,
+        priority: RequestPriority,
+        may_queue: bool,
+    ) -> Option<ConcurrencyPermit> {
+        let mut waiting = {
+            let mut state = self.slots.state.lock();
+            if state.free > 0 {
+                state.free -= 1;
+                return Some(self.admit());
+            }
+            if !may_queue {
+                return self.reject();
+            }
+            if state.waiting.len() >= self.config.queue_depth {
+                // Make room by shedding the lowest-priority waiter, if it
+                // ranks below this request.
+                match state.waiting.last_key_value() {
+                    Some(((Reverse(lowest), _), _)) if *lowest < priority => {
+                        state.waiting.pop_last();
+                    }
+                    _ => return self.reject(),
+                }
+            }
+            let ticket = (Reverse(priority), state.next_ticket);
+            state.next_ticket += 1;
+            let (tx, rx) = oneshot::channel();
+            state.waiting.insert(ticket, tx);
+            self.slots.sync_queued(&state);
+            Waiting {
+                slots: &self.slots,
+                ticket,
+                slot: rx,
+            }
+        };
+
+        let got_slot =
+            match tokio::time::timeout(self.config.queue_timeout, &mut waiting.slot).await {
+                Ok(handed) => handed.is_ok(),
+                Err(_) => waiting.give_up(),
+            };
+        if got_slot {
+            Some(self.admit())
+        } else {
+            self.reject()
+        }
+    }
+
+    /// Load counters of this limit
+    pub fn stats(&self) -> &ConcurrencyStats {
+        &self.slots.stats
+    }
+
+    fn admit(&self) -> ConcurrencyPermit {
+        self.slots.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        ConcurrencyPermit {
+            slots: Arc::clone(&self.slots),
+        }
+    }
+
+    fn reject(&self) -> Option<ConcurrencyPermit> {
+        self.slots.stats.rejected.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+/// A request's place in the queue. Dropped while still queued (the request
+/// went away) it leaves the queue, and a slot handed to it is passed on.
+struct Waiting<'a> {
+    slots: &'a Slots,
+    ticket: Ticket,
+    slot: oneshot::Receiver<()>,
+}
+
+impl Waiting<'_> {
+    /// Stop waiting; `true` if a slot was handed over in the meantime
+    fn give_up(&mut self) -> bool {
+        let mut state = self.slots.state.lock();
+        if state.waiting.remove(&self.ticket).is_some() {
+            self.slots.sync_queued(&state);
+            return false;
+        }
+        // Already handed a slot or shed; the lock orders that before us.
+        self.slot.try_recv().is_ok()
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if self.give_up() {
+            self.slots.release();
+        }
+    }
+}
+
+/// A held slot, released when dropped
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    slots: Arc<Slots>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.slots.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.slots.release();
+    }
+}
+
+/// Concurrency limiting middleware
+///
+/// The route limit, looked up by the matched route's method and path in
+/// [`RouteInfo`], is taken first and the gateway-wide limit second, so
+/// requests queued for a busy route do not hold gateway-wide slots while
+/// they wait. Route limits can be replaced at runtime with
+/// [`set_routes`](Self::set_routes), through any clone.
+///
+/// # Example
+///
+/// ```
+/// use octopus_config::types::ConcurrencyConfig;
+/// use octopus_metrics::MetricsCollector;
+/// use octopus_middleware::ConcurrencyLimit;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let limit = ConcurrencyLimit::new(Arc::new(MetricsCollector::new()))
+///     .global(ConcurrencyConfig {
+///         max_concurrent: 1000,
+///         queue_depth: 500,
+///         queue_timeout: Duration::from_secs(1),
+///     })
+///     .route(
+///         http::Method::GET,
+///         "/reports/export",
+///         ConcurrencyConfig {
+///             max_concurrent: 4,
+///             queue_depth: 0,
+///             queue_timeout: Duration::from_secs(1),
+///         },
+///     );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    metrics: Arc<MetricsCollector>,
+    global: Option<Arc<ConcurrencyLimiter>>,
+    routes: Arc<RwLock<RouteLimits>>,
+    qos: Option<Arc<QosClassifier>>,
+}
+
+impl ConcurrencyLimit {
+    /// Create a middleware without limits, reporting to `metrics`
+    pub fn new(metrics: Arc<MetricsCollector>) -> Self {
+        Self {
+            metrics,
+            global: None,
+            routes: Arc::default(),
+            qos: None,
+        }
+    }
+
+    /// Limit requests across the whole gateway
+    pub fn global(mut self, config: ConcurrencyConfig) -> Self {
+        self.global = Some(self.limiter(GLOBAL_SCOPE, config));
+        self
+    }
+
+    /// Limit `method` requests to the route with path pattern `path`
+    pub fn route(self, method: Method, path: &str, config: ConcurrencyConfig) -> Self {
+        let limiter = self.limiter(&format!("{method} {path}"), config);
+        self.routes
+            .write()
+            .insert((method, path.to_string()), limiter);
+        self
+    }
+
+    /// Replace the route limits, as on a config reload. A route whose limit
+    /// is unchanged keeps its limiter, so requests holding or waiting for
+    /// its slots stay counted.
+    pub fn set_routes(
+        &self,
+        routes: impl IntoIterator<Item = (Method, String, ConcurrencyConfig)>,
+    ) {
+        let mut current = self.routes.write();
+        let mut previous = std::mem::take(&mut *current);
+        for (method, path, config) in routes {
+            let key = (method, path);
+            let limiter = match previous.remove(&key) {
+                Some(limiter) if limiter.config == config => limiter,
+                _ => self.limiter(&format!("{} {}", key.0, key.1), config),
+            };
+            current.insert(key, limiter);
+        }
+    }
+
+    /// Queue and shed requests by the priority `qos` gives them
+    pub fn qos(mut self, qos: QosClassifier) -> Self {
+        self.qos = Some(Arc::new(qos));
+        self
+    }
+
+    /// Whether any limit is configured
+    pub fn is_empty(&self) -> bool {
+        self.global.is_none() && self.routes.read().is_empty()
+    }
+
+    fn limiter(&self, scope: &str, config: ConcurrencyConfig) -> Arc<ConcurrencyLimiter> {
+        Arc::new(ConcurrencyLimiter::new(
+            config,
+            self.metrics.concurrency_stats(scope),
+        ))
+    }
+}
+
+fn service_unavailable(scope: &str) -> Response<Body> {
+    ErrorResponse::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "concurrency_limit_exceeded",
+    )
+    .detail(format!("Too many concurrent requests ({scope})"))
+    .into_response()
+}
+
+#[async_trait]
+impl Middleware for ConcurrencyLimit {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let route = req.extensions().get::<RouteInfo>().and_then(|info| {
+            let method = info.method.parse::<Method>().ok()?;
+            let key = (method, info.path.clone());
+            let limiter = self.routes.read().get(&key).cloned()?;
+            Some((key, limiter))
+        });
+        let priority = self
+            .qos
+            .as_ref()
+            .map_or(RequestPriority::default(), |qos| qos.classify(&req));
+        let may_queue = !self.qos.as_ref().is_some_and(|qos| qos.sheds(priority));
+
+        let _route_permit = match route {
+            Some(((method, path), limiter)) => {
+                match limiter.acquire_as(priority, may_queue).await {
+                    Some(permit) => Some(permit),
+                    None => {
+                        tracing::debug!(%method, route = %path, ?priority, "Route concurrency limit exceeded");
+                        return Ok(service_unavailable("route"));
+                    }
+                }
+            }
+            None => None,
+        };
+        let _global_permit = match &self.global {
+            Some(limiter) => match limiter.acquire_as(priority, may_queue).await {
+                Some(permit) => Some(permit),
+                None => {
+                    tracing::debug!(?priority, "Gateway concurrency limit exceeded");
+                    return Ok(service_unavailable(GLOBAL_SCOPE));
+                }
+            },
+            None => None,
+        };
+
+        next.run(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_config::types::{QosConfig, QosRule};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    /// Handler that holds each request until the test releases it
+    #[derive(Debug)]
+    struct Held(Arc<Semaphore>);
+
+    #[async_trait]
+    impl Middleware for Held {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            self.0.acquire().await.unwrap().forget();
+            Ok(Response::new(Body::from("ok")))
+        }
+    }
+
+    fn config(max_concurrent: usize, queue_depth: usize) -> ConcurrencyConfig {
+        ConcurrencyConfig {
+            max_concurrent,
+            queue_depth,
+            queue_timeout: Duration::from_secs(1),
+        }
+    }
+
+    fn stack(limit: ConcurrencyLimit) -> (Arc<[Arc<dyn Middleware>]>, Arc<Semaphore>) {
+        let release = Arc::new(Semaphore::new(0));
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(limit), Arc::new(Held(release.clone()))]);
+        (stack, release)
+    }
+
+    fn request(method: Method, route: &str) -> Request<Body> {
+        let mut req = Request::builder()
+            .method(method.clone())
+            .uri(route)
+            .body(Body::from(""))
+            .unwrap();
+        req.extensions_mut().insert(RouteInfo {
+            path: route.to_string(),
+            method: method.to_string(),
+            operation_id: None,
+            tags: Vec::new(),
+        });
+        req
+    }
+
+    fn send(
+        stack: &Arc<[Arc<dyn Middleware>]>,
+        route: &str,
+    ) -> tokio::task::JoinHandle<StatusCode> {
+        send_with(stack, Method::GET, route)
+    }
+
+    /// Like [`send`], with `method`
+    fn send_with(
+        stack: &Arc<[Arc<dyn Middleware>]>,
+        method: Method,
+        route: &str,
+    ) -> tokio::task::JoinHandle<StatusCode> {
+        let next = Next::new(stack.clone());
+        let req = request(method, route);
+        tokio::spawn(async move { next.run(req).await.unwrap().status() })
+    }
+
+    /// Like [`send`], marked with an `X-Priority` header
+    fn send_as(
+        stack: &Arc<[Arc<dyn Middleware>]>,
+        priority: &str,
+    ) -> tokio::task::JoinHandle<StatusCode> {
+        let next = Next::new(stack.clone());
+        let mut req = request(Method::GET, "/a");
+        req.headers_mut()
+            .insert("x-priority", priority.parse().unwrap());
+        tokio::spawn(async move { next.run(req).await.unwrap().status() })
+    }
+
+    /// Prioritizes by `X-Priority: high|low`, shedding low
+    fn qos() -> QosClassifier {
+        let rule = |value: &str, priority| QosRule {
+            priority,
+            path_prefix: None,
+            header: Some("X-Priority".to_string()),
+            header_value: Some(value.to_string()),
+            tenant: None,
+            role: None,
+        };
+        QosClassifier::new(QosConfig {
+            default_priority: RequestPriority::Normal,
+            shed_below: RequestPriority::Normal,
+            rules: vec![
+                rule("high", RequestPriority::High),
+                rule("low", RequestPriority::Low),
+            ],
+        })
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_beyond_limit_queue_then_proceed() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let (stack, release) = stack(ConcurrencyLimit::new(metrics.clone()).global(config(2, 2)));
+
+        let requests: Vec<_> = (0..4).map(|_| send(&stack, "/a")).collect();
+        settle().await;
+        let stats = metrics.concurrency_stats(GLOBAL_SCOPE);
+        assert_eq!(stats.in_flight.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.queued.load(Ordering::Relaxed), 2);
+
+        release.add_permits(4);
+        for request in requests {
+            assert_eq!(request.await.unwrap(), StatusCode::OK);
+        }
+        assert_eq!(stats.in_flight.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.queued.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.rejected.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_full_queue_rejects_with_503() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let (stack, release) = stack(ConcurrencyLimit::new(metrics.clone()).global(config(1, 1)));
+
+        let running = send(&stack, "/a");
+        let queued = send(&stack, "/a");
+        settle().await;
+        assert_eq!(
+            send(&stack, "/a").await.unwrap(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            metrics
+                .concurrency_stats(GLOBAL_SCOPE)
+                .rejected
+                .load(Ordering::Relaxed),
+            1
+        );
+
+        release.add_permits(2);
+        assert_eq!(running.await.unwrap(), StatusCode::OK);
+        assert_eq!(queued.await.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queue_timeout_rejects_with_503() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let (stack, release) = stack(ConcurrencyLimit::new(metrics.clone()).global(config(1, 5)));
+
+        let running = send(&stack, "/a");
+        settle().await;
+        let waited = tokio::time::Instant::now();
+        assert_eq!(
+            send(&stack, "/a").await.unwrap(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(waited.elapsed(), Duration::from_secs(1));
+        let stats = metrics.concurrency_stats(GLOBAL_SCOPE);
+        assert_eq!(stats.queued.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.rejected.load(Ordering::Relaxed), 1);
+
+        release.add_permits(1);
+        assert_eq!(running.await.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_route_limit_applies_to_its_route_only() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let (stack, release) = stack(ConcurrencyLimit::new(metrics.clone()).route(
+            Method::GET,
+            "/export",
+            config(1, 0),
+        ));
+
+        let export = send(&stack, "/export");
+        settle().await;
+        assert_eq!(
+            send(&stack, "/export").await.unwrap(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        let other = send(&stack, "/users");
+        let post = send_with(&stack, Method::POST, "/export");
+        settle().await;
+        assert_eq!(
+            metrics
+                .concurrency_stats("GET /export")
+                .in_flight
+                .load(Ordering::Relaxed),
+            1
+        );
+
+        release.add_permits(3);
+        assert_eq!(export.await.unwrap(), StatusCode::OK);
+        assert_eq!(other.await.unwrap(), StatusCode::OK);
+        assert_eq!(post.await.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_routes_replaces_route_limits() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let limit = ConcurrencyLimit::new(metrics.clone())
+            .route(Method::GET, "/export", config(1, 0))
+            .route(Method::GET, "/import", config(1, 0));
+        let (stack, release) = stack(limit.clone());
+
+        let export = send(&stack, "/export");
+        settle().await;
+        limit.set_routes([
+            (Method::GET, "/export".to_string(), config(1, 0)),
+            (Method::POST, "/users".to_string(), config(1, 0)),
+        ]);
+
+        // The unchanged limit still counts the request in flight.
+        assert_eq!(
+            send(&stack, "/export").await.unwrap(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        let import = send(&stack, "/import");
+        let import_too = send(&stack, "/import");
+        let users = send_with(&stack, Method::POST, "/users");
+        settle().await;
+        assert_eq!(
+            send_with(&stack, Method::POST, "/users").await.unwrap(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        release.add_permits(4);
+        for request in [export, import, import_too, users] {
+            assert_eq!(request.await.unwrap(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_saturated_limit_sheds_low_priority_and_admits_high() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let (stack, release) = stack(
+            ConcurrencyLimit::new(metrics.clone())
+                .global(config(1, 1))
+                .qos(qos()),
+        );
+
+        let running = send(&stack, "/a");
+        settle().await;
+        // Saturated: a low-priority request is shed without queueing.
+        assert_eq!(
+            send_as(&stack, "low").await.unwrap(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // A high-priority request takes a normal one's place in the full queue.
+        let normal = send(&stack, "/a");
+        settle().await;
+        let high = send_as(&stack, "high");
+        assert_eq!(normal.await.unwrap(), StatusCode::SERVICE_UNAVAILABLE);
+
+        release.add_permits(2);
+        assert_eq!(running.await.unwrap(), StatusCode::OK);
+        assert_eq!(high.await.unwrap(), StatusCode::OK);
+        let stats = metrics.concurrency_stats(GLOBAL_SCOPE);
+        assert_eq!(stats.rejected.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.in_flight.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_freed_slot_goes_to_the_highest_priority_waiter() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let (stack, release) = stack(
+            ConcurrencyLimit::new(metrics.clone())
+                .global(config(1, 2))
+                .qos(qos()),
+        );
+
+        let running = send(&stack, "/a");
+        settle().await;
+        let normal = send(&stack, "/a");
+        settle().await;
+        let high = send_as(&stack, "high");
+        settle().await;
+        let stats = metrics.concurrency_stats(GLOBAL_SCOPE);
+        assert_eq!(stats.queued.load(Ordering::Relaxed), 2);
+
+        // One slot frees up: the later, higher-priority request gets it.
+        release.add_permits(1);
+        assert_eq!(running.await.unwrap(), StatusCode::OK);
+        settle().await;
+        assert_eq!(stats.queued.load(Ordering::Relaxed), 1);
+        assert!(!normal.is_finished());
+
+        release.add_permits(2);
+        assert_eq!(high.await.unwrap(), StatusCode::OK);
+        assert_eq!(normal.await.unwrap(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,251 @@
+//! Conditional middleware
+//!
+//! [`When`] wraps another middleware and only invokes it for requests
+//! matching a [`RequestPredicate`]; every other request passes straight
+//! through to the rest of the chain. This lets one configured middleware
+//! apply to a subset of traffic, e.g. JWT only under `/api` or CORS only for
+//! requests carrying an `Origin` header.
+//!
+//! # Example
+//!
+//! ```
+//! use octopus_middleware::{Cors, RequestPredicate, When};
+//! use std::sync::Arc;
+//!
+//! let cors = When::new(
+//!     RequestPredicate::header_present("origin"),
+//!     Arc::new(Cors::new()),
+//! );
+//! ```
+
+use async_trait::async_trait;
+use http::header::HOST;
+use http::{HeaderName, Method, Request, Response};
+use octopus_core::{Body, Middleware, Next, Result};
+use std::fmt;
+use std::sync::Arc;
+
+/// Custom request check for [`RequestPredicate::Custom`].
+pub type PredicateFn = Arc<dyn Fn(&Request<Body>) -> bool + Send + Sync>;
+
+/// Request matcher used by [`When`].
+#[derive(Clone)]
+pub enum RequestPredicate {
+    /// Path equals the prefix or continues it with a `/` segment
+    /// (`/api` matches `/api` and `/api/users`, not `/apis`)
+    PathPrefix(String),
+    /// Request method is one of these
+    Method(Vec<Method>),
+    /// The header is present, whatever its value
+    HeaderPresent(HeaderName),
+    /// Host (from the `Host` header or the URI authority, port ignored)
+    /// equals this name, case-insensitively
+    Host(String),
+    /// Every predicate matches
+    All(Vec<RequestPredicate>),
+    /// At least one predicate matches
+    Any(Vec<RequestPredicate>),
+    /// The predicate does not match
+    Not(Box<RequestPredicate>),
+    /// Arbitrary check
+    Custom(PredicateFn),
+}
+
+impl RequestPredicate {
+    /// Match requests under `prefix`.
+    pub fn path_prefix(prefix: impl Into<String>) -> Self {
+        Self::PathPrefix(prefix.into())
+    }
+
+    /// Match requests using `method`.
+    pub fn method(method: Method) -> Self {
+        Self::Method(vec![method])
+    }
+
+    /// Match requests carrying `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is not a valid header name.
+    pub fn header_present(name: &str) -> Self {
+        Self::HeaderPresent(HeaderName::from_bytes(name.as_bytes()).expect("valid header name"))
+    }
+
+    /// Match requests addressed to `host`.
+    pub fn host(host: impl Into<String>) -> Self {
+        Self::Host(host.into())
+    }
+
+    /// Match requests for which `f` returns `true`.
+    pub fn custom(f: impl Fn(&Request<Body>) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Whether `req` matches.
+    pub fn matches(&self, req: &Request<Body>) -> bool {
+        match self {
+            Self::PathPrefix(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+                match req.uri().path().strip_prefix(prefix) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                    None => false,
+                }
+            }
+            Self::Method(methods) => methods.contains(req.method()),
+            Self::HeaderPresent(name) => req.headers().contains_key(name),
+            Self::Host(host) => request_host(req).is_some_and(|h| h.eq_ignore_ascii_case(host)),
+            Self::All(predicates) => predicates.iter().all(|p| p.matches(req)),
+            Self::Any(predicates) => predicates.iter().any(|p| p.matches(req)),
+            Self::Not(predicate) => !predicate.matches(req),
+            Self::Custom(f) => f(req),
+        }
+    }
+}
+
+impl fmt::Debug for RequestPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PathPrefix(prefix) => f.debug_tuple("PathPrefix").field(prefix).finish(),
+            Self::Method(methods) => f.debug_tuple("Method").field(methods).finish(),
+            Self::HeaderPresent(name) => f.debug_tuple("HeaderPresent").field(name).finish(),
+            Self::Host(host) => f.debug_tuple("Host").field(host).finish(),
+            Self::All(predicates) => f.debug_tuple("All").field(predicates).finish(),
+            Self::Any(predicates) => f.debug_tuple("Any").field(predicates).finish(),
+            Self::Not(predicate) => f.debug_tuple("Not").field(predicate).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Host the request is addressed to, without the port.
+fn request_host(req: &Request<Body>) -> Option<&str> {
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| req.uri().host())?;
+    Some(octopus_core::strip_port(host))
+}
+
+/// Runs the wrapped middleware only for requests matching a predicate.
+#[derive(Debug, Clone)]
+pub struct When {
+    predicate: RequestPredicate,
+    inner: Arc<dyn Middleware>,
+}
+
+impl When {
+    /// Wrap `inner` so it only sees requests matching `predicate`.
+    pub fn new(predicate: RequestPredicate, inner: Arc<dyn Middleware>) -> Self {
+        Self { predicate, inner }
+    }
+
+    /// The predicate gating the wrapped middleware.
+    pub fn predicate(&self) -> &RequestPredicate {
+        &self.predicate
+    }
+}
+
+#[async_trait]
+impl Middleware for When {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        if self.predicate.matches(&req) {
+            self.inner.call(req, next).await
+        } else {
+            next.run(req).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{HeaderValue, StatusCode};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts calls and tags the response, then continues the chain.
+    #[derive(Debug, Default)]
+    struct Tag(AtomicUsize);
+
+    #[async_trait]
+    impl Middleware for Tag {
+        async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let mut res = next.run(req).await?;
+            res.headers_mut()
+                .insert("x-tagged", HeaderValue::from_static("1"));
+            Ok(res)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Ok200;
+
+    #[async_trait]
+    impl Middleware for Ok200 {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::from("ok"))
+                .unwrap())
+        }
+    }
+
+    async fn run(predicate: RequestPredicate, req: Request<Body>) -> (usize, Response<Body>) {
+        let tag = Arc::new(Tag::default());
+        let when = When::new(predicate, tag.clone());
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([Arc::new(when), Arc::new(Ok200)]);
+        let res = Next::new(stack).run(req).await.unwrap();
+        (tag.0.load(Ordering::SeqCst), res)
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::builder().uri(uri).body(Body::from("")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_runs_wrapped_middleware_for_matching_requests() {
+        let (calls, res) = run(RequestPredicate::path_prefix("/api"), get("/api/users")).await;
+
+        assert_eq!(calls, 1);
+        assert_eq!(res.headers()["x-tagged"], "1");
+    }
+
+    #[tokio::test]
+    async fn test_passes_through_non_matching_requests() {
+        let (calls, res) = run(RequestPredicate::path_prefix("/api"), get("/apis/users")).await;
+
+        assert_eq!(calls, 0);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get("x-tagged").is_none());
+    }
+
+    #[test]
+    fn test_predicates() {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/api")
+            .header("host", "API.example.com:8443")
+            .header("origin", "https://app.example.com")
+            .body(Body::from(""))
+            .unwrap();
+
+        assert!(RequestPredicate::path_prefix("/api/").matches(&req));
+        assert!(RequestPredicate::method(Method::POST).matches(&req));
+        assert!(!RequestPredicate::method(Method::GET).matches(&req));
+        assert!(RequestPredicate::header_present("origin").matches(&req));
+        assert!(!RequestPredicate::header_present("authorization").matches(&req));
+        assert!(RequestPredicate::host("api.example.com").matches(&req));
+        assert!(!RequestPredicate::host("example.com").matches(&req));
+        assert!(RequestPredicate::All(vec![
+            RequestPredicate::path_prefix("/api"),
+            RequestPredicate::Not(Box::new(RequestPredicate::method(Method::GET))),
+        ])
+        .matches(&req));
+        assert!(RequestPredicate::Any(vec![
+            RequestPredicate::host("other.example.com"),
+            RequestPredicate::custom(|r| r.uri().path() == "/api"),
+        ])
+        .matches(&req));
+    }
+}
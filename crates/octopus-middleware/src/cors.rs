@@ -1,12 +1,27 @@
 //! CORS (Cross-Origin Resource Sharing) middleware
+//!
+//! Allowed origins are patterns (see [`OriginPattern`]):
+//!
+//! - `*` — any origin; only valid without credentials
+//! - `https://app.example.com` — exact origin
+//! - `https://*.example.com` / `*.example.com` — any subdomain, with or
+//!   without a fixed scheme
+//! - `~^https://pr-\d+\.preview\.example\.com$` — regex (after the `~`),
+//!   matched against the whole origin
+//!
+//! A matched origin is echoed back with `Vary: Origin`; `*` is only sent
+//! when any origin is allowed and credentials are not.
 
 use crate::auth_gateway::MatchedRouteCors;
 use async_trait::async_trait;
 use bytes::Bytes;
+use dashmap::DashMap;
 use http::{header, HeaderValue, Method, Request, Response, StatusCode};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_core::{Error, Middleware, Next, Result};
+use regex::Regex;
 use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Body type alias
@@ -15,7 +30,8 @@ pub type Body = Full<Bytes>;
 /// CORS configuration
 #[derive(Debug, Clone)]
 pub struct CorsConfig {
-    /// Allowed origins (e.g., "*", "https://example.com")
+    /// Allowed origin patterns (e.g., "*", "https://example.com",
+    /// "https://*.example.com", "~^https://pr-\d+\.example\.com$")
     pub allowed_origins: Vec<String>,
     /// Allowed HTTP methods
     pub allowed_methods: Vec<Method>,
@@ -29,6 +45,24 @@ pub struct CorsConfig {
     pub allow_credentials: bool,
 }
 
+impl CorsConfig {
+    /// Check the origin patterns: every pattern must parse, and `*` cannot
+    /// be combined with credentials (browsers reject that response, and
+    /// echoing every origin instead would hand credentials to any site).
+    pub fn validate(&self) -> Result<()> {
+        for pattern in &self.allowed_origins {
+            let pattern = OriginPattern::parse(pattern)?;
+            if self.allow_credentials && matches!(pattern, OriginPattern::Any) {
+                return Err(Error::Config(
+                    "CORS allowed_origins \"*\" cannot be combined with allow_credentials"
+                        .to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Default for CorsConfig {
     fn default() -> Self {
         Self {
@@ -52,6 +86,73 @@ impl Default for CorsConfig {
     }
 }
 
+/// A parsed allowed-origin pattern
+#[derive(Debug, Clone)]
+pub enum OriginPattern {
+    /// `*`: any origin
+    Any,
+    /// The exact origin, e.g. `https://app.example.com`
+    Exact(String),
+    /// A subdomain of `suffix` (stored with its leading dot), optionally
+    /// restricted to a scheme. The bare suffix domain does not match.
+    Wildcard {
+        /// Required scheme, e.g. `https`; `None` accepts any
+        scheme: Option<String>,
+        /// Domain suffix, e.g. `.example.com`
+        suffix: String,
+    },
+    /// `~<regex>`, matched against the whole origin
+    Regex(Regex),
+}
+
+impl OriginPattern {
+    /// Parse a pattern from configuration.
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim();
+        if pattern == "*" {
+            return Ok(Self::Any);
+        }
+        if let Some(re) = pattern.strip_prefix('~') {
+            return Regex::new(&format!("^(?:{re})$"))
+                .map(Self::Regex)
+                .map_err(|e| Error::Config(format!("invalid CORS origin regex {re:?}: {e}")));
+        }
+        let (scheme, host) = match pattern.split_once("://") {
+            Some((scheme, host)) => (Some(scheme), host),
+            None => (None, pattern),
+        };
+        if let Some(rest) = host.strip_prefix("*.") {
+            return Ok(Self::Wildcard {
+                scheme: scheme.map(str::to_ascii_lowercase),
+                suffix: format!(".{}", rest.to_ascii_lowercase()),
+            });
+        }
+        Ok(Self::Exact(pattern.to_string()))
+    }
+
+    /// Whether `origin` (the request's `Origin` header) matches.
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(exact) => exact.eq_ignore_ascii_case(origin),
+            Self::Wildcard { scheme, suffix } => {
+                let (origin_scheme, host) = origin.split_once("://").unwrap_or(("", origin));
+                if scheme
+                    .as_deref()
+                    .is_some_and(|s| !s.eq_ignore_ascii_case(origin_scheme))
+                {
+                    return false;
+                }
+                // Ignore the port, then require a label before the suffix.
+                let host = host.rsplit_once(':').map_or(host, |(h, _)| h);
+                host.len() > suffix.len()
+                    && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+            }
+            Self::Regex(re) => re.is_match(origin),
+        }
+    }
+}
+
 /// CORS middleware
 ///
 /// Handles Cross-Origin Resource Sharing (CORS) by:
@@ -61,6 +162,9 @@ impl Default for CorsConfig {
 #[derive(Clone)]
 pub struct Cors {
     config: CorsConfig,
+    origins: Arc<[OriginPattern]>,
+    /// Parsed per-route override patterns, keyed by the pattern string
+    override_origins: Arc<DashMap<String, Option<OriginPattern>>>,
 }
 
 impl Cors {
@@ -69,9 +173,33 @@ impl Cors {
         Self::with_config(CorsConfig::default())
     }
 
-    /// Create a new CORS middleware with custom config
+    /// Create a new CORS middleware with custom config. Origin patterns that
+    /// fail to parse are logged and ignored; use
+    /// [`try_with_config`](Self::try_with_config) to reject them instead.
     pub fn with_config(config: CorsConfig) -> Self {
-        Self { config }
+        let origins = config
+            .allowed_origins
+            .iter()
+            .filter_map(|p| match OriginPattern::parse(p) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Ignoring CORS origin pattern");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            config,
+            origins,
+            override_origins: Arc::default(),
+        }
+    }
+
+    /// Create a new CORS middleware, rejecting an invalid config
+    /// (see [`CorsConfig::validate`]).
+    pub fn try_with_config(config: CorsConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self::with_config(config))
     }
 
     /// Create a permissive CORS middleware (allow all)
@@ -89,9 +217,9 @@ impl Cors {
     }
 
     /// Resolve effective CORS config: per-route override or global default
-    fn effective_config(&self, req: &Request<Body>) -> CorsConfig {
+    fn effective_config(&self, req: &Request<Body>) -> (CorsConfig, Arc<[OriginPattern]>) {
         if let Some(route_cors) = req.extensions().get::<MatchedRouteCors>() {
-            CorsConfig {
+            let config = CorsConfig {
                 allowed_origins: route_cors.allowed_origins.clone(),
                 allowed_methods: route_cors
                     .allowed_methods
@@ -103,62 +231,64 @@ impl Cors {
                 max_age: Duration::from_secs(route_cors.max_age),
                 // Inherit exposed_headers from global config
                 exposed_headers: self.config.exposed_headers.clone(),
-            }
+            };
+            let origins = config
+                .allowed_origins
+                .iter()
+                .filter_map(|p| {
+                    self.override_origins
+                        .entry(p.clone())
+                        .or_insert_with(|| OriginPattern::parse(p).ok())
+                        .clone()
+                })
+                .collect();
+            (config, origins)
         } else {
-            self.config.clone()
+            (self.config.clone(), Arc::clone(&self.origins))
         }
     }
 
-    /// Check if origin is allowed. Supports "*", exact match, and a single
-    /// wildcard label — "<scheme>://*.suffix" matches any subdomain of suffix
-    /// (e.g. "https://*.example.cloud" → "https://acme.example.cloud"), so a
-    /// multi-tenant deployment can allow every tenant subdomain with one entry
-    /// while still reflecting the specific origin for credentialed requests.
-    fn is_origin_allowed(config: &CorsConfig, origin: &str) -> bool {
-        config
-            .allowed_origins
-            .iter()
-            .any(|pattern| Self::origin_matches(pattern, origin))
-    }
-
-    /// Match one allowed-origin pattern against a request origin.
-    fn origin_matches(pattern: &str, origin: &str) -> bool {
-        if pattern == "*" || pattern == origin {
-            return true;
-        }
-        // Wildcard subdomain: "<scheme>://*.suffix".
-        if let Some(star) = pattern.find("://*.") {
-            let scheme_prefix = &pattern[..star + 3]; // e.g. "https://"
-            let suffix = &pattern[star + 4..]; // e.g. ".example.cloud"
-            if let Some(host) = origin.strip_prefix(scheme_prefix) {
-                // Require a non-empty label before the suffix.
-                return host.len() > suffix.len() && host.ends_with(suffix);
-            }
+    /// Get the appropriate Access-Control-Allow-Origin value: `*` only when
+    /// any origin is allowed without credentials, otherwise the request
+    /// origin if a pattern matches it. With credentials `*` matches nothing:
+    /// a policy that slipped past validation (e.g. a per-route override)
+    /// must not hand credentials to every site.
+    fn get_allow_origin(
+        config: &CorsConfig,
+        origins: &[OriginPattern],
+        request_origin: Option<&str>,
+    ) -> Option<String> {
+        let any = origins.iter().any(|p| matches!(p, OriginPattern::Any));
+        if any && !config.allow_credentials {
+            return Some("*".to_string());
         }
-        false
+        let origin = request_origin?;
+        origins
+            .iter()
+            .filter(|p| !matches!(p, OriginPattern::Any))
+            .any(|p| p.matches(origin))
+            .then(|| origin.to_string())
     }
 
-    /// Get the appropriate Access-Control-Allow-Origin value
-    fn get_allow_origin(config: &CorsConfig, request_origin: Option<&str>) -> Option<String> {
-        if config.allowed_origins.contains(&"*".to_string()) {
-            if config.allow_credentials {
-                request_origin.map(|s| s.to_string())
-            } else {
-                Some("*".to_string())
-            }
-        } else if let Some(origin) = request_origin {
-            if Self::is_origin_allowed(config, origin) {
-                Some(origin.to_string())
-            } else {
-                None
-            }
-        } else {
-            None
+    /// Set the allow-origin header, with `Vary: Origin` when it depends on
+    /// the request
+    fn set_allow_origin(response: &mut Response<Body>, allow_origin: &str) {
+        let Ok(value) = HeaderValue::from_str(allow_origin) else {
+            return;
+        };
+        let headers = response.headers_mut();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+        if allow_origin != "*" {
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
         }
     }
 
     /// Handle preflight OPTIONS request
-    fn handle_preflight(config: &CorsConfig, req: &Request<Body>) -> Response<Body> {
+    fn handle_preflight(
+        config: &CorsConfig,
+        origins: &[OriginPattern],
+        req: &Request<Body>,
+    ) -> Response<Body> {
         let origin = req
             .headers()
             .get(header::ORIGIN)
@@ -166,13 +296,6 @@ impl Cors {
 
         let mut response = Response::builder().status(StatusCode::NO_CONTENT);
 
-        if let Some(allow_origin) = Self::get_allow_origin(config, origin) {
-            response = response.header(
-                header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                HeaderValue::from_str(&allow_origin).unwrap(),
-            );
-        }
-
         let methods = config
             .allowed_methods
             .iter()
@@ -193,14 +316,19 @@ impl Cors {
             response = response.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
         }
 
-        response
+        let mut response = response
             .body(Full::new(Bytes::new()))
-            .expect("Failed to build CORS preflight response")
+            .expect("Failed to build CORS preflight response");
+        if let Some(allow_origin) = Self::get_allow_origin(config, origins, origin) {
+            Self::set_allow_origin(&mut response, &allow_origin);
+        }
+        response
     }
 
     /// Add CORS headers to response
     fn add_cors_headers(
         config: &CorsConfig,
+        origins: &[OriginPattern],
         req: &Request<Body>,
         mut response: Response<Body>,
     ) -> Response<Body> {
@@ -209,11 +337,8 @@ impl Cors {
             .get(header::ORIGIN)
             .and_then(|v| v.to_str().ok());
 
-        if let Some(allow_origin) = Self::get_allow_origin(config, origin) {
-            response.headers_mut().insert(
-                header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                HeaderValue::from_str(&allow_origin).unwrap(),
-            );
+        if let Some(allow_origin) = Self::get_allow_origin(config, origins, origin) {
+            Self::set_allow_origin(&mut response, &allow_origin);
         }
 
         if !config.exposed_headers.is_empty() {
@@ -254,16 +379,16 @@ impl fmt::Debug for Cors {
 impl Middleware for Cors {
     async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
         // Resolve effective CORS config (per-route override or global)
-        let effective = self.effective_config(&req);
+        let (effective, origins) = self.effective_config(&req);
 
         // Handle preflight OPTIONS request
         if req.method() == Method::OPTIONS {
-            return Ok(Self::handle_preflight(&effective, &req));
+            return Ok(Self::handle_preflight(&effective, &origins, &req));
         }
 
         // For actual requests, call next and add CORS headers
         let response = next.run(req.clone()).await?;
-        Ok(Self::add_cors_headers(&effective, &req, response))
+        Ok(Self::add_cors_headers(&effective, &origins, &req, response))
     }
 }
 
@@ -272,6 +397,12 @@ mod tests {
     use super::*;
     use octopus_core::Error;
 
+    /// Whether `config` allows `origin`, and the allow-origin value sent back.
+    fn allow(config: &CorsConfig, origin: &str) -> Option<String> {
+        let cors = Cors::with_config(config.clone());
+        Cors::get_allow_origin(&cors.config, &cors.origins, Some(origin))
+    }
+
     #[test]
     fn wildcard_subdomain_origin_matching() {
         let config = CorsConfig {
@@ -280,30 +411,84 @@ mod tests {
             ..Default::default()
         };
         // Any tenant subdomain matches.
-        assert!(Cors::is_origin_allowed(
-            &config,
-            "https://acme.example.cloud"
-        ));
-        assert!(Cors::is_origin_allowed(
-            &config,
-            "https://acme.api.example.cloud"
-        ));
+        assert!(allow(&config, "https://acme.example.cloud").is_some());
+        assert!(allow(&config, "https://acme.api.example.cloud").is_some());
+        assert!(allow(&config, "https://acme.example.cloud:8443").is_some());
         // Spoofed / non-matching origins are rejected.
-        assert!(!Cors::is_origin_allowed(&config, "https://evil.com"));
-        assert!(!Cors::is_origin_allowed(&config, "https://example.cloud")); // apex needs its own entry
-        assert!(!Cors::is_origin_allowed(
-            &config,
-            "http://acme.example.cloud"
-        )); // scheme mismatch
-        assert!(!Cors::is_origin_allowed(
-            &config,
-            "https://acmeexample.cloud"
-        )); // missing separator
-            // The specific origin is reflected (required with credentials).
+        assert!(allow(&config, "https://evil.com").is_none());
+        assert!(allow(&config, "https://example.cloud").is_none()); // apex needs its own entry
+        assert!(allow(&config, "http://acme.example.cloud").is_none()); // scheme mismatch
+        assert!(allow(&config, "https://acmeexample.cloud").is_none()); // missing separator
+        assert!(allow(&config, "https://example.cloud.evil.com").is_none());
+        // The specific origin is reflected (required with credentials).
         assert_eq!(
-            Cors::get_allow_origin(&config, Some("https://acme.example.cloud")),
+            allow(&config, "https://acme.example.cloud"),
             Some("https://acme.example.cloud".to_string())
         );
+
+        // Without a scheme any scheme matches.
+        let config = CorsConfig {
+            allowed_origins: vec!["*.example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(allow(&config, "http://a.example.com").is_some());
+        assert!(allow(&config, "https://b.a.example.com").is_some());
+        assert!(allow(&config, "https://example.com").is_none());
+    }
+
+    #[test]
+    fn regex_origin_matching() {
+        let config = CorsConfig {
+            allowed_origins: vec![r"~https://pr-\d+\.preview\.example\.com".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            allow(&config, "https://pr-42.preview.example.com"),
+            Some("https://pr-42.preview.example.com".to_string())
+        );
+        // The regex must match the whole origin.
+        assert!(allow(&config, "https://pr-42.preview.example.com.evil.com").is_none());
+        assert!(allow(&config, "https://pr-x.preview.example.com").is_none());
+
+        let bad = CorsConfig {
+            allowed_origins: vec!["~(unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+        assert!(Cors::try_with_config(bad).is_err());
+    }
+
+    #[test]
+    fn credentials_echo_origin_and_reject_any() {
+        // Without credentials "*" is sent as-is.
+        let config = CorsConfig::default();
+        assert_eq!(allow(&config, "https://a.com"), Some("*".to_string()));
+
+        // With credentials the matched origin is echoed, never "*".
+        let config = CorsConfig {
+            allowed_origins: vec!["https://a.com".to_string(), "*.b.com".to_string()],
+            allow_credentials: true,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            allow(&config, "https://a.com"),
+            Some("https://a.com".to_string())
+        );
+        assert_eq!(
+            allow(&config, "https://x.b.com"),
+            Some("https://x.b.com".to_string())
+        );
+
+        // "*" with credentials is rejected up front.
+        let config = CorsConfig {
+            allow_credentials: true,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(Cors::try_with_config(config.clone()).is_err());
+        // ...and never reflects an origin if it gets through anyway
+        assert_eq!(allow(&config, "https://a.com"), None);
     }
 
     #[derive(Debug)]
@@ -379,6 +564,44 @@ mod tests {
             .contains_key(header::ACCESS_CONTROL_MAX_AGE));
     }
 
+    #[tokio::test]
+    async fn test_preflight_sends_configured_policy_and_max_age() {
+        let config = CorsConfig {
+            allowed_origins: vec!["https://*.example.com".to_string()],
+            allowed_methods: vec![Method::GET, Method::PATCH],
+            allowed_headers: vec!["Content-Type".to_string(), "X-Tenant".to_string()],
+            max_age: Duration::from_secs(600),
+            allow_credentials: true,
+            ..Default::default()
+        };
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(Cors::with_config(config)), Arc::new(TestHandler)]);
+
+        let req = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/test")
+            .header(header::ORIGIN, "https://tenant.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .body(Body::from(""))
+            .unwrap();
+        let response = Next::new(stack).run(req).await.unwrap();
+
+        let headers = response.headers();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://tenant.example.com"
+        );
+        assert_eq!(headers[header::VARY], "Origin");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, PATCH");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "Content-Type, X-Tenant"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
     #[tokio::test]
     async fn test_cors_specific_origins() {
         let cors = Cors::for_origins(vec!["https://allowed.com".to_string()]);
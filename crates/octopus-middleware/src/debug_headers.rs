@@ -0,0 +1,383 @@
+//! Debug response headers
+//!
+//! Tells a caller how the gateway handled their request: the matched route,
+//! the upstream and instance that answered, the upstream it failed over
+//! from, whether the response came from cache, how many retries it took and
+//! where the time went, as
+//! `Server-Timing` entries for auth, the upstream call and the whole request.
+//!
+//! The values come from what earlier stages already record: the
+//! [`RouteInfo`] the handler puts in request extensions and the
+//! [`UpstreamSelection`] the proxy (or cache) puts in response extensions.
+//! Auth time is measured between two [`DebugTimingMark`]s placed around the
+//! auth middleware.
+//!
+//! The headers expose internal addresses, so unless configured for every
+//! request they are only added when the request carries the trigger header
+//! with the configured secret. The trigger header never reaches the upstream.
+
+use async_trait::async_trait;
+use http::{HeaderName, HeaderValue, Request, Response};
+use octopus_config::types::DebugHeadersConfig;
+use octopus_core::request::RouteInfo;
+use octopus_core::{Body, Error, Middleware, Next, Result, UpstreamSelection};
+use std::fmt::{self, Write as _};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Matched route, as `METHOD /pattern`
+pub const ROUTE_HEADER: &str = "x-octopus-route";
+/// Upstream (cluster) that served the request
+pub const UPSTREAM_HEADER: &str = "x-octopus-upstream";
+/// Instance that answered, with its address
+pub const INSTANCE_HEADER: &str = "x-octopus-instance";
+/// Attempts made after the first one
+pub const RETRIES_HEADER: &str = "x-octopus-retries";
+/// Upstream the request failed over from
+pub const FAILOVER_HEADER: &str = "x-octopus-failover";
+/// Per-phase durations, in milliseconds
+const SERVER_TIMING: &str = "server-timing";
+
+/// Timestamps of one request, shared through request extensions by
+/// [`DebugHeaders`] and the [`DebugTimingMark`]s
+#[derive(Debug)]
+struct Timer {
+    received: Instant,
+    auth_started: OnceLock<Instant>,
+    auth_finished: OnceLock<Instant>,
+}
+
+impl Timer {
+    /// Time spent in auth; a request rejected there spent the rest of
+    /// `total` in it
+    fn auth(&self, total: Duration) -> Option<Duration> {
+        let started = self.auth_started.get()?;
+        let finished = self
+            .auth_finished
+            .get()
+            .copied()
+            .unwrap_or(self.received + total);
+        Some(finished.saturating_duration_since(*started))
+    }
+}
+
+/// Debug response header middleware
+#[derive(Clone)]
+pub struct DebugHeaders {
+    always: bool,
+    header: HeaderName,
+    secret: Option<Arc<[u8]>>,
+}
+
+impl DebugHeaders {
+    /// Create the middleware from the `gateway.debug_headers` section
+    pub fn new(config: &DebugHeadersConfig) -> Result<Self> {
+        let header = HeaderName::from_bytes(config.header.as_bytes()).map_err(|e| {
+            Error::Config(format!(
+                "invalid debug_headers.header {:?}: {e}",
+                config.header
+            ))
+        })?;
+        Ok(Self {
+            always: config.always,
+            header,
+            secret: config.secret.as_deref().map(|s| s.as_bytes().into()),
+        })
+    }
+
+    /// Whether `req` gets the debug headers
+    fn triggered(&self, req: &Request<Body>) -> bool {
+        if self.always {
+            return true;
+        }
+        let (Some(secret), Some(value)) = (&self.secret, req.headers().get(&self.header)) else {
+            return false;
+        };
+        constant_time_eq(value.as_bytes(), secret)
+    }
+}
+
+impl fmt::Debug for DebugHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugHeaders")
+            .field("always", &self.always)
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Middleware for DebugHeaders {
+    async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let triggered = self.triggered(&req);
+        req.headers_mut().remove(&self.header);
+        if !triggered {
+            return next.run(req).await;
+        }
+
+        let timer = Arc::new(Timer {
+            received: Instant::now(),
+            auth_started: OnceLock::new(),
+            auth_finished: OnceLock::new(),
+        });
+        req.extensions_mut().insert(Arc::clone(&timer));
+        let route = req.extensions().get::<RouteInfo>().cloned();
+
+        let mut response = next.run(req).await?;
+        let total = timer.received.elapsed();
+        let selection = response.extensions().get::<UpstreamSelection>().cloned();
+        let headers = response.headers_mut();
+
+        if let Some(route) = route {
+            insert(
+                headers,
+                ROUTE_HEADER,
+                &format!("{} {}", route.method, route.path),
+            );
+        }
+        if let Some(selection) = &selection {
+            if !selection.upstream.is_empty() {
+                insert(headers, UPSTREAM_HEADER, &selection.upstream);
+            }
+            if !selection.cache_hit {
+                insert(
+                    headers,
+                    INSTANCE_HEADER,
+                    &format!("{}; addr={}", selection.instance_id, selection.address),
+                );
+                insert(headers, RETRIES_HEADER, &selection.retries.to_string());
+            }
+            if let Some(from) = &selection.failover_from {
+                insert(headers, FAILOVER_HEADER, from);
+            }
+            // The cache layer's own verdict (which may be BYPASS) wins.
+            if !headers.contains_key("x-cache") {
+                let verdict = if selection.cache_hit { "HIT" } else { "MISS" };
+                headers.insert("x-cache", HeaderValue::from_static(verdict));
+            }
+        }
+
+        let mut timing = String::new();
+        if let Some(auth) = timer.auth(total) {
+            let _ = write!(timing, "auth;dur={}, ", millis(auth));
+        }
+        if let Some(proxy) = selection.as_ref().filter(|s| !s.cache_hit) {
+            let _ = write!(timing, "proxy;dur={}, ", millis(proxy.latency));
+        }
+        let _ = write!(timing, "total;dur={}", millis(total));
+        if let Ok(value) = HeaderValue::from_str(&timing) {
+            headers.append(SERVER_TIMING, value);
+        }
+
+        Ok(response)
+    }
+}
+
+/// Records when a request enters and leaves authentication, for the `auth`
+/// entry of [`DebugHeaders`]' `Server-Timing`. Place [`auth_start`] just
+/// before the auth middleware and [`auth_end`] just after it; both do
+/// nothing for requests that don't get debug headers.
+///
+/// [`auth_start`]: Self::auth_start
+/// [`auth_end`]: Self::auth_end
+#[derive(Debug, Clone, Copy)]
+pub struct DebugTimingMark {
+    end: bool,
+}
+
+impl DebugTimingMark {
+    /// Mark placed before authentication
+    pub fn auth_start() -> Self {
+        Self { end: false }
+    }
+
+    /// Mark placed after authentication
+    pub fn auth_end() -> Self {
+        Self { end: true }
+    }
+}
+
+#[async_trait]
+impl Middleware for DebugTimingMark {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        if let Some(timer) = req.extensions().get::<Arc<Timer>>() {
+            let mark = if self.end {
+                &timer.auth_finished
+            } else {
+                &timer.auth_started
+            };
+            let _ = mark.set(Instant::now());
+        }
+        next.run(req).await
+    }
+}
+
+fn insert(headers: &mut http::HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+/// `duration` in milliseconds, to the microsecond
+fn millis(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+/// Compare without exiting at the first differing byte, so the time taken
+/// says nothing about how much of the secret a guess got right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use http::StatusCode;
+    use http_body_util::Full;
+    use octopus_core::UpstreamInstance;
+    use std::sync::Mutex;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    /// Stands in for the proxy: answers as instance `users-1` after one retry
+    /// and remembers the headers it was sent
+    #[derive(Debug, Default)]
+    struct Upstream {
+        seen: Mutex<Option<http::HeaderMap>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Upstream {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            *self.seen.lock().unwrap() = Some(req.headers().clone());
+            let instance = UpstreamInstance::new("users-1", "10.0.0.5", 8080);
+            let mut selection = UpstreamSelection::new(&instance, Duration::from_millis(12), 1);
+            selection.upstream = "users".to_string();
+            selection.failover_from = Some("users-eu".to_string());
+            let mut response = Response::builder()
+                .status(StatusCode::OK)
+                .body(Full::new(Bytes::from("ok")))
+                .unwrap();
+            response.extensions_mut().insert(selection);
+            Ok(response)
+        }
+    }
+
+    fn config(always: bool) -> DebugHeadersConfig {
+        DebugHeadersConfig {
+            always,
+            header: "x-octopus-debug-headers".to_string(),
+            secret: Some(SECRET.to_string()),
+        }
+    }
+
+    fn request(trigger: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/users/42");
+        if let Some(value) = trigger {
+            builder = builder.header("x-octopus-debug-headers", value);
+        }
+        let mut req = builder.body(Full::new(Bytes::new())).unwrap();
+        req.extensions_mut().insert(RouteInfo {
+            path: "/users/:id".to_string(),
+            method: "GET".to_string(),
+            operation_id: None,
+            tags: Vec::new(),
+        });
+        req
+    }
+
+    async fn run(
+        config: &DebugHeadersConfig,
+        req: Request<Body>,
+    ) -> (Response<Body>, Arc<Upstream>) {
+        let upstream = Arc::new(Upstream::default());
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(DebugHeaders::new(config).unwrap()) as Arc<dyn Middleware>,
+            Arc::new(DebugTimingMark::auth_start()),
+            Arc::new(DebugTimingMark::auth_end()),
+            Arc::clone(&upstream) as Arc<dyn Middleware>,
+        ]);
+        let response = Next::new(stack).run(req).await.unwrap();
+        (response, upstream)
+    }
+
+    #[tokio::test]
+    async fn triggered_requests_get_the_debug_headers() {
+        let (response, upstream) = run(&config(false), request(Some(SECRET))).await;
+        let headers = response.headers();
+        assert_eq!(headers[ROUTE_HEADER], "GET /users/:id");
+        assert_eq!(headers[UPSTREAM_HEADER], "users");
+        assert_eq!(headers[INSTANCE_HEADER], "users-1; addr=10.0.0.5:8080");
+        assert_eq!(headers[RETRIES_HEADER], "1");
+        assert_eq!(headers[FAILOVER_HEADER], "users-eu");
+        assert_eq!(headers["x-cache"], "MISS");
+
+        let timing = headers[SERVER_TIMING].to_str().unwrap();
+        let names: Vec<&str> = timing
+            .split(", ")
+            .map(|entry| entry.split(';').next().unwrap())
+            .collect();
+        assert_eq!(names, ["auth", "proxy", "total"], "{timing}");
+        assert!(timing.contains("proxy;dur=12.000"), "{timing}");
+
+        // The trigger is the gateway's business, not the upstream's.
+        let seen = upstream.seen.lock().unwrap().clone().unwrap();
+        assert!(!seen.contains_key("x-octopus-debug-headers"));
+    }
+
+    #[tokio::test]
+    async fn headers_are_absent_without_a_valid_trigger() {
+        for trigger in [None, Some("wrong"), Some(&SECRET[1..])] {
+            let (response, upstream) = run(&config(false), request(trigger)).await;
+            let headers = response.headers();
+            for name in [
+                ROUTE_HEADER,
+                UPSTREAM_HEADER,
+                INSTANCE_HEADER,
+                RETRIES_HEADER,
+                FAILOVER_HEADER,
+            ] {
+                assert!(!headers.contains_key(name), "{name} for {trigger:?}");
+            }
+            assert!(!headers.contains_key(SERVER_TIMING));
+            assert!(!headers.contains_key("x-cache"));
+            let seen = upstream.seen.lock().unwrap().clone().unwrap();
+            assert!(!seen.contains_key("x-octopus-debug-headers"));
+        }
+    }
+
+    #[tokio::test]
+    async fn always_on_needs_no_trigger() {
+        let (response, _) = run(&config(true), request(None)).await;
+        assert_eq!(response.headers()[ROUTE_HEADER], "GET /users/:id");
+    }
+
+    #[tokio::test]
+    async fn cached_responses_report_a_hit_without_an_instance() {
+        #[derive(Debug)]
+        struct Cache;
+
+        #[async_trait]
+        impl Middleware for Cache {
+            async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+                let mut response = Response::new(Full::new(Bytes::from("cached")));
+                response
+                    .extensions_mut()
+                    .insert(UpstreamSelection::cache_hit());
+                Ok(response)
+            }
+        }
+
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(DebugHeaders::new(&config(true)).unwrap()) as Arc<dyn Middleware>,
+            Arc::new(Cache),
+        ]);
+        let response = Next::new(stack).run(request(None)).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers["x-cache"], "HIT");
+        assert!(!headers.contains_key(INSTANCE_HEADER));
+        let timing = headers[SERVER_TIMING].to_str().unwrap();
+        assert!(timing.starts_with("total;dur="), "{timing}");
+    }
+}
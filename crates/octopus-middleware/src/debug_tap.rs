@@ -0,0 +1,474 @@
+//! Debug tap middleware
+//!
+//! Captures the full request and response, headers and bodies, of requests
+//! that carry a valid debug token, for reproducing a customer's problem
+//! without turning on body logging for everyone. Captures go to a bounded
+//! [`DebugTapLog`] served by the admin API.
+//!
+//! The token is an HS256 JWT signed with the tap's own secret, with audience
+//! `octopus-debug-tap`, and `iat` and `exp` at most `max_token_ttl` apart. A
+//! missing, malformed or expired token leaves the request untouched apart
+//! from removing the debug header, which never reaches the upstream.
+//! Credentials, cookies and the configured headers, query parameters and
+//! JSON body fields are masked before a capture is stored. A body over
+//! `max_body_bytes` is only parsed for redaction up to that size: with
+//! `redact_fields` set, a larger one is left out of the capture, since a
+//! partial document can't be redacted.
+
+use crate::body_transform::set_at_path;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, Request, Response, Uri};
+use http_body_util::{BodyExt, Full};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use octopus_config::types::DebugTapConfig;
+use octopus_core::{json_path, Body, Error, Middleware, Next, Result};
+use octopus_metrics::{current_timestamp_ms, CapturedMessage, DebugCapture, DebugTapLog};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Audience a debug token must be issued for, so tokens minted for anything
+/// else never enable a capture
+pub const DEBUG_TAP_AUDIENCE: &str = "octopus-debug-tap";
+
+/// Headers masked in every capture
+const ALWAYS_REDACTED: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Query parameters masked in every capture
+const ALWAYS_REDACTED_PARAMS: &[&str] = &[
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "token",
+    "api_key",
+    "apikey",
+    "key",
+    "password",
+    "secret",
+    "client_secret",
+    "signature",
+    "sig",
+    "code",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Claims of a debug token
+#[derive(Debug, Deserialize)]
+struct DebugClaims {
+    /// Who asked for the capture
+    sub: Option<String>,
+    exp: u64,
+    iat: u64,
+}
+
+/// Debug tap middleware
+#[derive(Clone)]
+pub struct DebugTap {
+    header: HeaderName,
+    decoding_key: Arc<DecodingKey>,
+    validation: Validation,
+    max_token_ttl: Duration,
+    max_body_bytes: usize,
+    redact_headers: HashSet<String>,
+    redact_query: HashSet<String>,
+    redact_fields: Vec<String>,
+    log: Arc<DebugTapLog>,
+}
+
+impl DebugTap {
+    /// Create a debug tap recording into `log`
+    pub fn new(config: &DebugTapConfig, log: Arc<DebugTapLog>) -> Result<Self> {
+        let header = HeaderName::from_bytes(config.header.as_bytes()).map_err(|e| {
+            Error::Config(format!("invalid debug_tap.header {:?}: {e}", config.header))
+        })?;
+
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[DEBUG_TAP_AUDIENCE]);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+
+        let redact_headers = ALWAYS_REDACTED
+            .iter()
+            .map(|h| h.to_string())
+            .chain(config.redact_headers.iter().map(|h| h.to_ascii_lowercase()))
+            .collect();
+        let redact_query = ALWAYS_REDACTED_PARAMS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(config.redact_query.iter().map(|p| p.to_ascii_lowercase()))
+            .collect();
+
+        Ok(Self {
+            header,
+            decoding_key: Arc::new(DecodingKey::from_secret(config.secret.as_bytes())),
+            validation,
+            max_token_ttl: config.max_token_ttl,
+            max_body_bytes: config.max_body_bytes,
+            redact_headers,
+            redact_query,
+            redact_fields: config.redact_fields.clone(),
+            log,
+        })
+    }
+
+    /// The log captures are recorded into
+    pub fn log(&self) -> &Arc<DebugTapLog> {
+        &self.log
+    }
+
+    /// Claims of `token` if it authorizes a capture
+    fn authorize(&self, token: &str) -> Option<DebugClaims> {
+        let claims = match decode::<DebugClaims>(token, &self.decoding_key, &self.validation) {
+            Ok(data) => data.claims,
+            Err(e) => {
+                tracing::warn!(error = %e, "Rejected debug tap token");
+                return None;
+            }
+        };
+        if claims.exp.saturating_sub(claims.iat) > self.max_token_ttl.as_secs() {
+            tracing::warn!(
+                subject = ?claims.sub,
+                "Rejected debug tap token: lifetime exceeds max_token_ttl"
+            );
+            return None;
+        }
+        Some(claims)
+    }
+
+    fn capture_headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redact_headers.contains(name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// `uri` with the values of redacted query parameters masked
+    fn capture_uri(&self, uri: &Uri) -> String {
+        let Some(query) = uri.query() else {
+            return uri.to_string();
+        };
+        let query = query
+            .split('&')
+            .map(|pair| {
+                let name = url::form_urlencoded::parse(pair.as_bytes())
+                    .next()
+                    .map(|(name, _)| name.to_ascii_lowercase());
+                match name {
+                    Some(name) if self.redact_query.contains(&name) => {
+                        let raw = pair.split_once('=').map_or(pair, |(raw, _)| raw);
+                        format!("{raw}={REDACTED}")
+                    }
+                    _ => pair.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let path = uri.path_and_query().map_or("", |pq| pq.path());
+        match uri.authority() {
+            Some(authority) => format!(
+                "{}://{authority}{path}?{query}",
+                uri.scheme_str().unwrap_or("http")
+            ),
+            None => format!("{path}?{query}"),
+        }
+    }
+
+    fn capture_body(&self, body: &[u8]) -> (String, bool) {
+        // A partial document can't be redacted, so a body too large to
+        // keep whole is not parsed and, when fields must be masked, not kept.
+        if body.len() > self.max_body_bytes && !self.redact_fields.is_empty() {
+            return (String::new(), true);
+        }
+        let redacted = self.redact_body(body);
+        let bytes = redacted.as_deref().unwrap_or(body);
+        let truncated = bytes.len() > self.max_body_bytes;
+        let kept = &bytes[..bytes.len().min(self.max_body_bytes)];
+        (String::from_utf8_lossy(kept).into_owned(), truncated)
+    }
+
+    /// `body` with the redacted fields masked, when it is JSON containing any
+    fn redact_body(&self, body: &[u8]) -> Option<Vec<u8>> {
+        if self.redact_fields.is_empty() {
+            return None;
+        }
+        let mut json: Value = serde_json::from_slice(body).ok()?;
+        let mut changed = false;
+        for path in &self.redact_fields {
+            if json_path::get(&json, path).is_some() {
+                set_at_path(&mut json, path, Value::String(REDACTED.to_string()));
+                changed = true;
+            }
+        }
+        changed.then(|| serde_json::to_vec(&json).ok()).flatten()
+    }
+
+    fn capture_message(&self, headers: &HeaderMap, body: &[u8]) -> CapturedMessage {
+        let (body, body_truncated) = self.capture_body(body);
+        CapturedMessage {
+            headers: self.capture_headers(headers),
+            body,
+            body_truncated,
+        }
+    }
+}
+
+impl fmt::Debug for DebugTap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugTap")
+            .field("header", &self.header)
+            .field("max_token_ttl", &self.max_token_ttl)
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("redact_headers", &self.redact_headers)
+            .field("redact_query", &self.redact_query)
+            .field("redact_fields", &self.redact_fields)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Middleware for DebugTap {
+    async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let Some(token) = req.headers_mut().remove(&self.header) else {
+            return next.run(req).await;
+        };
+        let Some(claims) = token.to_str().ok().and_then(|t| self.authorize(t)) else {
+            return next.run(req).await;
+        };
+
+        let (parts, body) = req.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        let request = self.capture_message(&parts.headers, &body);
+        let method = parts.method.to_string();
+        let uri = self.capture_uri(&parts.uri);
+
+        let response = next
+            .run(Request::from_parts(parts, Full::new(body)))
+            .await?;
+
+        let (parts, body) = response.into_parts();
+        let body: Bytes = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        let id = self.log.record(DebugCapture {
+            id: 0,
+            timestamp: current_timestamp_ms(),
+            subject: claims.sub,
+            method,
+            uri,
+            status: parts.status.as_u16(),
+            request,
+            response: self.capture_message(&parts.headers, &body),
+        });
+        tracing::info!(capture = id, "Captured debug-tapped exchange");
+
+        Ok(Response::from_parts(parts, Full::new(body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    /// Handler answering with a JSON body holding a secret, and recording
+    /// the headers it received
+    #[derive(Debug, Default)]
+    struct Upstream {
+        seen: parking_lot::Mutex<Vec<HeaderMap>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Upstream {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            self.seen.lock().push(req.headers().clone());
+            Ok(Response::builder()
+                .status(StatusCode::CREATED)
+                .header("set-cookie", "session=abc")
+                .body(Body::from(r#"{"id":7,"token":"t-123"}"#))
+                .unwrap())
+        }
+    }
+
+    fn config() -> DebugTapConfig {
+        DebugTapConfig {
+            header: "x-octopus-debug".to_string(),
+            secret: SECRET.to_string(),
+            max_token_ttl: Duration::from_secs(600),
+            capacity: 10,
+            max_body_bytes: 1024,
+            redact_headers: vec!["X-Api-Key".to_string()],
+            redact_fields: vec!["password".to_string(), "token".to_string()],
+            redact_query: vec!["Session".to_string()],
+        }
+    }
+
+    fn token(secret: &str, aud: &str, ttl: u64) -> String {
+        let now = current_timestamp_ms() / 1000;
+        let claims = json!({ "sub": "support", "aud": aud, "iat": now, "exp": now + ttl });
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    async fn send(tap: DebugTap, upstream: Arc<Upstream>, debug: Option<String>) -> Response<Body> {
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([Arc::new(tap), upstream]);
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/login?next=/home&access_token=abc&session=s-1")
+            .header("authorization", "Bearer user-token")
+            .header("x-api-key", "k-1")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"user":"ada","password":"hunter2"}"#))
+            .unwrap();
+        if let Some(debug) = debug {
+            req.headers_mut()
+                .insert("x-octopus-debug", debug.parse().unwrap());
+        }
+        Next::new(stack).run(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tagged_request_is_captured_with_redaction() {
+        let log = Arc::new(DebugTapLog::new(10));
+        let tap = DebugTap::new(&config(), log.clone()).unwrap();
+        let upstream = Arc::new(Upstream::default());
+
+        let response = send(
+            tap,
+            upstream.clone(),
+            Some(token(SECRET, DEBUG_TAP_AUDIENCE, 60)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            body, r#"{"id":7,"token":"t-123"}"#,
+            "client gets the real body"
+        );
+        assert!(upstream.seen.lock()[0].get("x-octopus-debug").is_none());
+
+        let captures = log.recent(10);
+        assert_eq!(captures.len(), 1);
+        let capture = &captures[0];
+        assert_eq!(capture.subject.as_deref(), Some("support"));
+        assert_eq!(capture.method, "POST");
+        assert_eq!(
+            capture.uri,
+            "/login?next=/home&access_token=[REDACTED]&session=[REDACTED]"
+        );
+        assert_eq!(capture.status, 201);
+
+        let header = |message: &CapturedMessage, name: &str| {
+            message
+                .headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(header(&capture.request, "authorization").unwrap(), REDACTED);
+        assert_eq!(header(&capture.request, "x-api-key").unwrap(), REDACTED);
+        assert_eq!(
+            header(&capture.request, "content-type").unwrap(),
+            "application/json"
+        );
+        assert_eq!(header(&capture.response, "set-cookie").unwrap(), REDACTED);
+
+        let request_body: Value = serde_json::from_str(&capture.request.body).unwrap();
+        assert_eq!(request_body, json!({ "user": "ada", "password": REDACTED }));
+        let response_body: Value = serde_json::from_str(&capture.response.body).unwrap();
+        assert_eq!(response_body, json!({ "id": 7, "token": REDACTED }));
+    }
+
+    #[tokio::test]
+    async fn test_untagged_and_invalid_requests_are_not_captured() {
+        let log = Arc::new(DebugTapLog::new(10));
+        let tap = DebugTap::new(&config(), log.clone()).unwrap();
+        let upstream = Arc::new(Upstream::default());
+
+        let attempts = [
+            None,
+            Some("not-a-jwt".to_string()),
+            Some(token(
+                "another-secret-another-secret-00",
+                DEBUG_TAP_AUDIENCE,
+                60,
+            )),
+            Some(token(SECRET, "some-api", 60)),
+            // Longer-lived than max_token_ttl allows
+            Some(token(SECRET, DEBUG_TAP_AUDIENCE, 86_400)),
+        ];
+        for debug in attempts {
+            let response = send(tap.clone(), upstream.clone(), debug).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        assert_eq!(log.count(), 0);
+        for headers in upstream.seen.lock().iter() {
+            assert!(headers.get("x-octopus-debug").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_captured_body_is_truncated() {
+        let log = Arc::new(DebugTapLog::new(10));
+        let mut config = config();
+        config.max_body_bytes = 8;
+        config.redact_fields.clear();
+        let tap = DebugTap::new(&config, log.clone()).unwrap();
+
+        send(
+            tap,
+            Arc::new(Upstream::default()),
+            Some(token(SECRET, DEBUG_TAP_AUDIENCE, 60)),
+        )
+        .await;
+        let capture = &log.recent(1)[0];
+        assert_eq!(capture.request.body, r#"{"user":"#);
+        assert!(capture.request.body_truncated);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_with_redacted_fields_is_not_captured() {
+        let log = Arc::new(DebugTapLog::new(10));
+        let mut config = config();
+        config.max_body_bytes = 8;
+        let tap = DebugTap::new(&config, log.clone()).unwrap();
+
+        send(
+            tap,
+            Arc::new(Upstream::default()),
+            Some(token(SECRET, DEBUG_TAP_AUDIENCE, 60)),
+        )
+        .await;
+        let capture = &log.recent(1)[0];
+        assert_eq!(capture.request.body, "");
+        assert!(capture.request.body_truncated);
+    }
+}
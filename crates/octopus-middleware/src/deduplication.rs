@@ -5,7 +5,7 @@ use bytes::Bytes;
 use dashmap::DashMap;
 use http::{Request, Response, StatusCode};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_core::{Middleware, Next, Result, StreamedResponse};
 use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
@@ -178,6 +178,10 @@ impl Middleware for Deduplication {
 
         // Execute the actual request
         let response = next.run(req).await?;
+        // A streamed body can only be sent once
+        if StreamedResponse::is_streamed(&response) {
+            return Ok(response);
+        }
 
         // Cache the response
         let status = response.status().as_u16();
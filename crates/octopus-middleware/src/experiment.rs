@@ -0,0 +1,378 @@
+//! A/B experiment assignment middleware
+//!
+//! Assigns each user to a weighted variant of every configured experiment and
+//! tells upstreams via an `X-Experiment-{name}: {variant}` request header.
+//!
+//! Assignment is deterministic: the variant is picked from a hash of the
+//! experiment name and a stable user key (a header such as the authenticated
+//! principal, or a cookie), so the same user lands in the same bucket on every
+//! replica. The result is also persisted in a `{cookie_prefix}{name}` cookie,
+//! which wins on later requests, so a returning user keeps their variant even
+//! if the weights change. Users without a key are bucketed at random and kept
+//! sticky by the cookie alone.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, HeaderName, HeaderValue, Request, Response};
+use http_body_util::Full;
+use octopus_core::{Middleware, Next, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Body type alias
+pub type Body = Full<Bytes>;
+
+/// A weighted experiment variant
+#[derive(Debug, Clone)]
+pub struct ExperimentVariant {
+    /// Variant name sent to upstreams (e.g. "control")
+    pub name: String,
+    /// Relative weight; a variant's share is `weight / sum(weights)`
+    pub weight: u32,
+}
+
+/// An experiment and its variants
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    /// Experiment name, used in the header and cookie names
+    pub name: String,
+    /// Variants in bucket order
+    pub variants: Vec<ExperimentVariant>,
+}
+
+impl Experiment {
+    /// The variant for a user with stable `key`
+    pub fn assign(&self, key: &str) -> Option<&str> {
+        let digest = Sha256::new()
+            .chain_update(self.name.as_bytes())
+            .chain_update([0])
+            .chain_update(key.as_bytes())
+            .finalize();
+        let mut point = [0u8; 8];
+        point.copy_from_slice(&digest[..8]);
+        self.pick(u64::from_be_bytes(point))
+    }
+
+    /// Map `point` onto the cumulative variant weights
+    fn pick(&self, point: u64) -> Option<&str> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut point = point % total;
+        for variant in &self.variants {
+            let weight = u64::from(variant.weight);
+            if point < weight {
+                return Some(&variant.name);
+            }
+            point -= weight;
+        }
+        None
+    }
+
+    /// Whether `name` is still a configured variant (even at weight 0, so
+    /// ramping a variant down does not move users already in it)
+    fn has_variant(&self, name: &str) -> bool {
+        self.variants.iter().any(|v| v.name == name)
+    }
+}
+
+/// Experiment assignment configuration
+#[derive(Debug, Clone)]
+pub struct ExperimentsConfig {
+    /// Experiments to assign on every request
+    pub experiments: Vec<Experiment>,
+    /// Headers holding a stable user key, checked in order (e.g. `x-user-id`)
+    pub key_headers: Vec<String>,
+    /// Cookies holding a stable user key, checked after `key_headers`
+    pub key_cookies: Vec<String>,
+    /// Prefix of the assignment cookies (`{cookie_prefix}{experiment}`)
+    pub cookie_prefix: String,
+    /// Lifetime of the assignment cookies
+    pub cookie_max_age: Duration,
+}
+
+impl Default for ExperimentsConfig {
+    fn default() -> Self {
+        Self {
+            experiments: Vec::new(),
+            key_headers: Vec::new(),
+            key_cookies: Vec::new(),
+            cookie_prefix: "octopus_exp_".to_string(),
+            cookie_max_age: Duration::from_secs(30 * 24 * 3600),
+        }
+    }
+}
+
+/// Experiment assignment middleware
+#[derive(Debug, Clone)]
+pub struct Experiments {
+    config: ExperimentsConfig,
+    /// `x-experiment-{name}` per experiment, parallel to `config.experiments`
+    header_names: Vec<HeaderName>,
+}
+
+impl Experiments {
+    /// Create from config. Experiments whose name is not a valid header or
+    /// cookie token, or that have no weighted variant, are skipped with a
+    /// warning.
+    pub fn new(mut config: ExperimentsConfig) -> Self {
+        let mut header_names = Vec::new();
+        config.experiments.retain(|exp| {
+            let valid_name = !exp.name.is_empty()
+                && exp
+                    .name
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+            let header = HeaderName::from_bytes(
+                format!("x-experiment-{}", exp.name.to_ascii_lowercase()).as_bytes(),
+            );
+            match header {
+                Ok(header) if valid_name && exp.pick(0).is_some() => {
+                    header_names.push(header);
+                    true
+                }
+                _ => {
+                    tracing::warn!(experiment = %exp.name, "Ignoring invalid experiment");
+                    false
+                }
+            }
+        });
+        Self {
+            config,
+            header_names,
+        }
+    }
+
+    /// The first stable user key found in the request, if any
+    fn user_key(&self, req: &Request<Body>, cookies: &HashMap<&str, &str>) -> Option<String> {
+        let from_header = self.config.key_headers.iter().find_map(|name| {
+            req.headers()
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| !v.is_empty())
+        });
+        let from_cookie = || {
+            self.config
+                .key_cookies
+                .iter()
+                .find_map(|name| cookies.get(name.as_str()).copied())
+                .filter(|v| !v.is_empty())
+        };
+        from_header.or_else(from_cookie).map(str::to_string)
+    }
+
+    fn set_cookie(&self, experiment: &str, variant: &str) -> Option<HeaderValue> {
+        HeaderValue::from_str(&format!(
+            "{}{experiment}={variant}; Path=/; Max-Age={}; SameSite=Lax",
+            self.config.cookie_prefix,
+            self.config.cookie_max_age.as_secs()
+        ))
+        .ok()
+    }
+}
+
+impl Default for Experiments {
+    fn default() -> Self {
+        Self::new(ExperimentsConfig::default())
+    }
+}
+
+/// Cookie name → value across every `Cookie` header
+fn parse_cookies(headers: &http::HeaderMap) -> HashMap<&str, &str> {
+    octopus_core::cookie::cookie_pairs(headers).collect()
+}
+
+#[async_trait]
+impl Middleware for Experiments {
+    async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        if self.config.experiments.is_empty() {
+            return next.run(req).await;
+        }
+
+        let mut assignments = Vec::with_capacity(self.config.experiments.len());
+        let mut set_cookies = Vec::new();
+        {
+            let cookies = parse_cookies(req.headers());
+            let key = self.user_key(&req, &cookies);
+            for exp in &self.config.experiments {
+                let cookie_name = format!("{}{}", self.config.cookie_prefix, exp.name);
+                let persisted = cookies
+                    .get(cookie_name.as_str())
+                    .copied()
+                    .filter(|v| exp.has_variant(v));
+                let variant = match persisted {
+                    Some(variant) => variant.to_string(),
+                    None => {
+                        let variant = match &key {
+                            Some(key) => exp.assign(key),
+                            None => exp.pick(rand::random()),
+                        }
+                        .unwrap_or_default()
+                        .to_string();
+                        set_cookies.extend(self.set_cookie(&exp.name, &variant));
+                        variant
+                    }
+                };
+                assignments.push(variant);
+            }
+        }
+
+        // Overwrites any client-supplied value for the same experiment.
+        for (name, variant) in self.header_names.iter().zip(&assignments) {
+            if let Ok(value) = HeaderValue::from_str(variant) {
+                req.headers_mut().insert(name.clone(), value);
+            }
+        }
+
+        let mut response = next.run(req).await?;
+        for cookie in set_cookies {
+            response.headers_mut().append(header::SET_COOKIE, cookie);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+    use octopus_core::Error;
+    use std::sync::Arc;
+
+    /// Echoes the assigned `checkout` variant as the response body.
+    #[derive(Debug)]
+    struct EchoVariant;
+
+    #[async_trait]
+    impl Middleware for EchoVariant {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            let variant = req
+                .headers()
+                .get("x-experiment-checkout")
+                .map(|v| v.as_bytes().to_vec())
+                .unwrap_or_default();
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(Full::new(Bytes::from(variant)))
+                .map_err(|e| Error::Internal(e.to_string()))
+        }
+    }
+
+    fn checkout(control: u32, treatment: u32) -> Experiment {
+        Experiment {
+            name: "checkout".to_string(),
+            variants: vec![
+                ExperimentVariant {
+                    name: "control".to_string(),
+                    weight: control,
+                },
+                ExperimentVariant {
+                    name: "treatment".to_string(),
+                    weight: treatment,
+                },
+            ],
+        }
+    }
+
+    fn stack(control: u32, treatment: u32) -> Arc<[Arc<dyn Middleware>]> {
+        let experiments = Experiments::new(ExperimentsConfig {
+            experiments: vec![checkout(control, treatment)],
+            key_headers: vec!["x-user-id".to_string()],
+            ..Default::default()
+        });
+        Arc::new([
+            Arc::new(experiments) as Arc<dyn Middleware>,
+            Arc::new(EchoVariant) as Arc<dyn Middleware>,
+        ])
+    }
+
+    async fn run(stack: &Arc<[Arc<dyn Middleware>]>, req: Request<Body>) -> (String, Vec<String>) {
+        let response = Next::new(stack.clone()).run(req).await.unwrap();
+        let cookies = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap().to_string())
+            .collect();
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        (String::from_utf8(body.to_vec()).unwrap(), cookies)
+    }
+
+    #[test]
+    fn weighted_distribution_over_many_users() {
+        let exp = checkout(80, 20);
+        let users = 10_000;
+        let treatment = (0..users)
+            .filter(|i| exp.assign(&format!("user-{i}")) == Some("treatment"))
+            .count();
+
+        let share = treatment as f64 / users as f64;
+        assert!((0.18..0.22).contains(&share), "treatment share {share}");
+    }
+
+    #[test]
+    fn assignment_is_deterministic_per_user_and_experiment() {
+        let exp = checkout(50, 50);
+        assert_eq!(exp.assign("user-42"), exp.assign("user-42"));
+        assert_eq!(checkout(100, 0).assign("user-42"), Some("control"));
+        assert_eq!(checkout(0, 1).assign("user-42"), Some("treatment"));
+        assert_eq!(checkout(0, 0).assign("user-42"), None);
+    }
+
+    #[tokio::test]
+    async fn first_visit_sets_sticky_cookie() {
+        let stack = stack(0, 100);
+        let req = Request::builder()
+            .uri("/cart")
+            .header("x-user-id", "user-42")
+            .body(Body::default())
+            .unwrap();
+
+        let (variant, cookies) = run(&stack, req).await;
+
+        assert_eq!(variant, "treatment");
+        assert_eq!(cookies.len(), 1);
+        assert!(cookies[0].starts_with("octopus_exp_checkout=treatment; Path=/; Max-Age="));
+    }
+
+    #[tokio::test]
+    async fn returning_user_keeps_cookie_assignment() {
+        // Weights now send everyone to control, but the cookie wins.
+        let stack = stack(100, 0);
+        let req = Request::builder()
+            .uri("/cart")
+            .header("x-user-id", "user-42")
+            .header(
+                header::COOKIE,
+                "session=abc; octopus_exp_checkout=treatment",
+            )
+            .header("x-experiment-checkout", "spoofed")
+            .body(Body::default())
+            .unwrap();
+
+        let (variant, cookies) = run(&stack, req).await;
+
+        assert_eq!(variant, "treatment");
+        assert!(cookies.is_empty(), "no new cookie for a returning user");
+    }
+
+    #[tokio::test]
+    async fn unknown_cookie_variant_is_reassigned() {
+        let stack = stack(100, 0);
+        let req = Request::builder()
+            .uri("/cart")
+            .header(header::COOKIE, "octopus_exp_checkout=retired")
+            .body(Body::default())
+            .unwrap();
+
+        let (variant, cookies) = run(&stack, req).await;
+
+        assert_eq!(variant, "control");
+        assert_eq!(cookies.len(), 1);
+    }
+}
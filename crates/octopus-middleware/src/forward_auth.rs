@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use http::{Request, Response, StatusCode};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_core::{ErrorResponse, Middleware, Next, Result};
 use std::fmt;
 use std::time::Duration;
 
@@ -158,17 +158,9 @@ impl ForwardAuth {
 
     /// Build an error response
     fn error_response(status: StatusCode, message: &str) -> Response<Body> {
-        Response::builder()
-            .status(status)
-            .header("Content-Type", "application/json")
-            .body(Full::new(Bytes::from(
-                serde_json::json!({
-                    "error": "authentication_failed",
-                    "message": message
-                })
-                .to_string(),
-            )))
-            .expect("Failed to build error response")
+        ErrorResponse::new(status, "authentication_failed")
+            .detail(message)
+            .into_response()
     }
 }
 
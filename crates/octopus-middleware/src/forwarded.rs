@@ -0,0 +1,305 @@
+//! Forwarded-header management
+//!
+//! Rewrites `X-Forwarded-For`, `X-Forwarded-Proto`, `X-Forwarded-Host`,
+//! `X-Real-IP` and (optionally) the RFC 7239 `Forwarded` header on every
+//! inbound request, so upstreams see accurate client information.
+//!
+//! Client-supplied values are only kept when the connecting peer is a
+//! trusted proxy; otherwise they are overwritten from the connection itself,
+//! so a client cannot claim an arbitrary IP or scheme.
+
+use crate::ip_filter::IpPattern;
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+
+/// The client behind any trusted proxies, resolved by the server from the
+/// connection peer (see [`ForwardedHeaders::client_ip`]) and inserted into
+/// request extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Forwarded-header configuration
+#[derive(Debug, Clone)]
+pub struct ForwardedConfig {
+    /// Whether forwarded headers are managed at all
+    pub enabled: bool,
+    /// Peers whose forwarded headers are trusted and appended to; headers from
+    /// any other peer are overwritten
+    pub trusted_proxies: Vec<IpPattern>,
+    /// Also emit the standardized RFC 7239 `Forwarded` header
+    pub emit_forwarded: bool,
+}
+
+impl Default for ForwardedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            trusted_proxies: Vec::new(),
+            emit_forwarded: false,
+        }
+    }
+}
+
+/// Applies [`ForwardedConfig`] to inbound request headers
+#[derive(Debug, Clone, Default)]
+pub struct ForwardedHeaders {
+    config: ForwardedConfig,
+}
+
+impl ForwardedHeaders {
+    /// Create from config
+    pub fn new(config: ForwardedConfig) -> Self {
+        Self { config }
+    }
+
+    /// Whether `ip` is a trusted proxy
+    pub fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.config.trusted_proxies.iter().any(|p| p.matches(ip))
+    }
+
+    /// The client behind `peer`: the rightmost `X-Forwarded-For` address not
+    /// added by a trusted proxy when `peer` is one, otherwise `peer` itself
+    pub fn client_ip(&self, headers: &HeaderMap, peer: IpAddr) -> IpAddr {
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+        rightmost_untrusted(headers, &self.config.trusted_proxies).unwrap_or(peer)
+    }
+
+    /// Rewrite the forwarded headers of a request received from `peer`;
+    /// `tls` is whether the listener accepted the connection over TLS.
+    ///
+    /// From a trusted peer, `peer` is appended to the incoming chain and the
+    /// incoming proto/host are kept; from anyone else the chain restarts at
+    /// `peer` and proto/host come from the connection and `Host` header.
+    pub fn apply(&self, headers: &mut HeaderMap, peer: IpAddr, tls: bool) {
+        if !self.config.enabled {
+            return;
+        }
+        let trusted = self.is_trusted(&peer);
+        let proto = if tls { "https" } else { "http" };
+        let client = self.client_ip(headers, peer);
+
+        // X-Forwarded-For: the proxy chain, client first
+        let mut chain: Vec<String> = if trusted {
+            list_values(headers, &X_FORWARDED_FOR).collect()
+        } else {
+            Vec::new()
+        };
+        chain.push(peer.to_string());
+        set(headers, X_FORWARDED_FOR, &chain.join(", "));
+
+        // X-Real-IP: the rightmost address not added by a trusted proxy
+        set(headers, X_REAL_IP, &client.to_string());
+
+        if !trusted || !headers.contains_key(&X_FORWARDED_PROTO) {
+            set(headers, X_FORWARDED_PROTO, proto);
+        }
+
+        let host = headers
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        if !trusted || !headers.contains_key(&X_FORWARDED_HOST) {
+            match &host {
+                Some(host) => set(headers, X_FORWARDED_HOST, host),
+                None => {
+                    headers.remove(&X_FORWARDED_HOST);
+                }
+            }
+        }
+
+        // Forwarded (RFC 7239): elements are appended like X-Forwarded-For
+        if self.config.emit_forwarded {
+            let mut element = format!("for={};proto={proto}", forwarded_node(peer));
+            if let Some(host) = &host {
+                element.push_str(&format!(";host=\"{host}\""));
+            }
+            let mut elements: Vec<String> = if trusted {
+                list_values(headers, &header::FORWARDED).collect()
+            } else {
+                Vec::new()
+            };
+            elements.push(element);
+            set(headers, header::FORWARDED, &elements.join(", "));
+        } else if !trusted {
+            headers.remove(header::FORWARDED);
+        }
+    }
+}
+
+/// The rightmost `X-Forwarded-For` address that isn't one of the `trusted`
+/// proxies; entries left of it may have been written by the client
+pub(crate) fn rightmost_untrusted(headers: &HeaderMap, trusted: &[IpPattern]) -> Option<IpAddr> {
+    let chain: Vec<String> = list_values(headers, &X_FORWARDED_FOR).collect();
+    chain
+        .iter()
+        .rev()
+        .filter_map(|entry| IpAddr::from_str(entry).ok())
+        .find(|ip| !trusted.iter().any(|p| p.matches(ip)))
+}
+
+/// Comma-separated entries across every occurrence of `name`
+fn list_values<'a>(headers: &'a HeaderMap, name: &HeaderName) -> impl Iterator<Item = String> + 'a {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+}
+
+/// Replace every occurrence of `name` with `value` (dropped if not a valid value)
+fn set(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    match HeaderValue::from_str(value) {
+        Ok(value) => {
+            headers.insert(name, value);
+        }
+        Err(_) => {
+            headers.remove(name);
+        }
+    }
+}
+
+/// RFC 7239 node: IPv6 addresses are bracketed and quoted
+fn forwarded_node(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => format!("\"[{v6}]\""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(trusted: &[&str], emit_forwarded: bool) -> ForwardedHeaders {
+        ForwardedHeaders::new(ForwardedConfig {
+            trusted_proxies: trusted
+                .iter()
+                .map(|p| IpPattern::parse(p).unwrap())
+                .collect(),
+            emit_forwarded,
+            ..Default::default()
+        })
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn spoofed_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("api.example.com"));
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        headers.insert("x-forwarded-host", HeaderValue::from_static("evil.example"));
+        headers.insert("x-real-ip", HeaderValue::from_static("1.2.3.4"));
+        headers.insert("forwarded", HeaderValue::from_static("for=1.2.3.4"));
+        headers
+    }
+
+    #[test]
+    fn untrusted_peer_overwrites_client_supplied_headers() {
+        let mut headers = spoofed_headers();
+
+        forwarded(&["10.0.0.0/8"], false).apply(&mut headers, ip("203.0.113.9"), false);
+
+        assert_eq!(headers["x-forwarded-for"], "203.0.113.9");
+        assert_eq!(headers["x-real-ip"], "203.0.113.9");
+        assert_eq!(headers["x-forwarded-proto"], "http");
+        assert_eq!(headers["x-forwarded-host"], "api.example.com");
+        assert!(!headers.contains_key("forwarded"));
+    }
+
+    #[test]
+    fn trusted_peer_appends_to_chain_and_keeps_proto() {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("internal:8080"));
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.7, 10.0.0.2"),
+        );
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        headers.insert(
+            "x-forwarded-host",
+            HeaderValue::from_static("api.example.com"),
+        );
+
+        forwarded(&["10.0.0.0/8"], false).apply(&mut headers, ip("10.0.0.5"), false);
+
+        assert_eq!(
+            headers["x-forwarded-for"],
+            "198.51.100.7, 10.0.0.2, 10.0.0.5"
+        );
+        assert_eq!(headers["x-real-ip"], "198.51.100.7");
+        assert_eq!(headers["x-forwarded-proto"], "https");
+        assert_eq!(headers["x-forwarded-host"], "api.example.com");
+    }
+
+    #[test]
+    fn real_ip_skips_only_trusted_hops() {
+        // The left-most entry was supplied by the client and is not trusted
+        // further than the first untrusted hop from the right.
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("6.6.6.6, 198.51.100.7, 10.0.0.2"),
+        );
+
+        forwarded(&["10.0.0.0/8"], false).apply(&mut headers, ip("10.0.0.5"), false);
+
+        assert_eq!(headers["x-real-ip"], "198.51.100.7");
+    }
+
+    #[test]
+    fn tls_listener_sets_https_proto() {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("api.example.com"));
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("http"));
+
+        forwarded(&[], true).apply(&mut headers, ip("203.0.113.9"), true);
+
+        assert_eq!(headers["x-forwarded-proto"], "https");
+        assert_eq!(
+            headers["forwarded"],
+            "for=203.0.113.9;proto=https;host=\"api.example.com\""
+        );
+    }
+
+    #[test]
+    fn forwarded_header_appends_from_trusted_peer() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "forwarded",
+            HeaderValue::from_static("for=198.51.100.7;proto=https"),
+        );
+
+        forwarded(&["::1"], true).apply(&mut headers, ip("::1"), false);
+
+        assert_eq!(
+            headers["forwarded"],
+            "for=198.51.100.7;proto=https, for=\"[::1]\";proto=http"
+        );
+    }
+
+    #[test]
+    fn disabled_leaves_headers_untouched() {
+        let mut headers = spoofed_headers();
+        let handler = ForwardedHeaders::new(ForwardedConfig {
+            enabled: false,
+            ..Default::default()
+        });
+
+        handler.apply(&mut headers, ip("203.0.113.9"), false);
+
+        assert_eq!(headers, spoofed_headers());
+    }
+}
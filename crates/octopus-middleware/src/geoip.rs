@@ -0,0 +1,502 @@
+//! GeoIP client location, blocking and routing
+//!
+//! [`GeoIp`] resolves the client IP (the rightmost `X-Forwarded-For` entry
+//! that isn't a trusted proxy, else `X-Real-IP`) against a MaxMind database (GeoLite2/GeoIP2 Country or City),
+//! rejects clients from blocked countries, and stores the location as a
+//! [`ClientGeo`] request extension so routes can pick a region-specific
+//! upstream. The database file is re-read when it changes on disk.
+//!
+//! The MaxMind reader is behind the `geoip` feature; [`ClientGeo`] is always
+//! available so the router can consume it.
+
+#[cfg(feature = "geoip")]
+pub use database::{GeoIp, GeoIpConfig, GeoIpDatabase};
+
+/// Client location resolved by [`GeoIp`], stored in request extensions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientGeo {
+    /// ISO 3166-1 country code (e.g. "DE")
+    pub country: Option<String>,
+    /// ISO 3166-2 subdivision code (e.g. "US-CA"); City databases only
+    pub region: Option<String>,
+    /// Continent code (e.g. "EU")
+    pub continent: Option<String>,
+}
+
+impl ClientGeo {
+    /// Location keys from most to least specific: region, country, continent
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        [&self.region, &self.country, &self.continent]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+}
+
+#[cfg(feature = "geoip")]
+mod database {
+    use super::ClientGeo;
+    use crate::ip_filter::{forwarded_client_ip, IpPattern};
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use http::{Request, Response, StatusCode};
+    use http_body_util::Full;
+    use maxminddb::{geoip2, MaxMindDBError, Reader};
+    use octopus_core::{Error, ErrorResponse, Middleware, Next, Result};
+    use parking_lot::RwLock;
+    use std::collections::HashSet;
+    use std::fmt;
+    use std::net::IpAddr;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    /// Body type alias
+    type Body = Full<Bytes>;
+
+    /// A MaxMind database file, reloadable in place
+    pub struct GeoIpDatabase {
+        path: PathBuf,
+        reader: RwLock<Arc<Reader<Vec<u8>>>>,
+        last_modified: RwLock<Option<SystemTime>>,
+    }
+
+    impl GeoIpDatabase {
+        /// Open the mmdb file at `path`
+        pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+            let path = path.into();
+            let reader = load(&path)?;
+            Ok(Self {
+                last_modified: RwLock::new(modified(&path)),
+                reader: RwLock::new(Arc::new(reader)),
+                path,
+            })
+        }
+
+        /// The database file
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// The location of `ip`, if the database knows it
+        pub fn lookup(&self, ip: IpAddr) -> Option<ClientGeo> {
+            let reader = Arc::clone(&self.reader.read());
+            let city: geoip2::City<'_> = match reader.lookup(ip) {
+                Ok(city) => city,
+                Err(MaxMindDBError::AddressNotFoundError(_)) => return None,
+                Err(e) => {
+                    tracing::debug!(ip = %ip, error = %e, "GeoIP lookup failed");
+                    return None;
+                }
+            };
+            let country = city
+                .country
+                .and_then(|c| c.iso_code)
+                .map(str::to_ascii_uppercase);
+            let region = city
+                .subdivisions
+                .as_ref()
+                .and_then(|s| s.first())
+                .and_then(|s| s.iso_code)
+                .zip(country.as_deref())
+                .map(|(subdivision, country)| {
+                    format!("{country}-{}", subdivision.to_ascii_uppercase())
+                });
+            let continent = city
+                .continent
+                .and_then(|c| c.code)
+                .map(str::to_ascii_uppercase);
+            Some(ClientGeo {
+                country,
+                region,
+                continent,
+            })
+        }
+
+        /// Re-read the database file
+        pub fn reload(&self) -> Result<()> {
+            let modified = modified(&self.path);
+            let reader = load(&self.path)?;
+            *self.reader.write() = Arc::new(reader);
+            *self.last_modified.write() = modified;
+            Ok(())
+        }
+
+        /// Reload if the file changed since it was last read; a broken file
+        /// keeps the current database
+        pub fn check_and_reload(&self) -> Result<bool> {
+            let current = modified(&self.path);
+            if current.is_none() || current <= *self.last_modified.read() {
+                return Ok(false);
+            }
+            tracing::info!(path = %self.path.display(), "GeoIP database modified, reloading...");
+            self.reload()?;
+            Ok(true)
+        }
+
+        /// Check for a new database file every `interval`
+        pub fn start_auto_reload(self: Arc<Self>, interval: Duration) {
+            tokio::spawn(self.run_auto_reload(interval));
+        }
+
+        /// Check for a new database file every `interval`, forever; for
+        /// callers that own the task
+        pub async fn run_auto_reload(self: Arc<Self>, interval: Duration) {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.check_and_reload() {
+                    tracing::warn!(error = %e, "Failed to reload GeoIP database, keeping current");
+                }
+            }
+        }
+    }
+
+    impl fmt::Debug for GeoIpDatabase {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let reader = self.reader.read();
+            f.debug_struct("GeoIpDatabase")
+                .field("path", &self.path)
+                .field("database_type", &reader.metadata.database_type)
+                .field("build_epoch", &reader.metadata.build_epoch)
+                .finish()
+        }
+    }
+
+    fn load(path: &Path) -> Result<Reader<Vec<u8>>> {
+        Reader::open_readfile(path).map_err(|e| {
+            Error::Config(format!(
+                "Failed to open GeoIP database {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// GeoIP middleware configuration
+    #[derive(Debug, Clone)]
+    pub struct GeoIpConfig {
+        /// ISO 3166-1 country codes whose clients are rejected
+        pub blocked_countries: HashSet<String>,
+        /// Status for blocked clients (451 or 403)
+        pub block_status: StatusCode,
+        /// Proxies in front of the gateway, skipped when reading the client
+        /// from `X-Forwarded-For`
+        pub trusted_proxies: Vec<IpPattern>,
+    }
+
+    impl Default for GeoIpConfig {
+        fn default() -> Self {
+            Self {
+                blocked_countries: HashSet::new(),
+                block_status: StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                trusted_proxies: Vec::new(),
+            }
+        }
+    }
+
+    /// GeoIP blocking middleware; also exposes [`ClientGeo`] to later layers
+    #[derive(Debug, Clone)]
+    pub struct GeoIp {
+        database: Arc<GeoIpDatabase>,
+        config: GeoIpConfig,
+    }
+
+    impl GeoIp {
+        /// Resolve clients against `database`
+        pub fn new(database: Arc<GeoIpDatabase>, mut config: GeoIpConfig) -> Self {
+            config.blocked_countries = config
+                .blocked_countries
+                .iter()
+                .map(|c| c.to_ascii_uppercase())
+                .collect();
+            Self { database, config }
+        }
+
+        fn blocked(&self, country: &str, path: &str) -> Response<Body> {
+            ErrorResponse::new(self.config.block_status, "geo_blocked")
+                .detail("Service is not available in your country")
+                .instance(path)
+                .extension("country", country)
+                .into_response()
+        }
+    }
+
+    #[async_trait]
+    impl Middleware for GeoIp {
+        async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+            let Some(geo) = forwarded_client_ip(&req, &self.config.trusted_proxies)
+                .and_then(|ip| self.database.lookup(ip))
+            else {
+                return next.run(req).await;
+            };
+
+            if let Some(country) = geo
+                .country
+                .as_deref()
+                .filter(|c| self.config.blocked_countries.contains(*c))
+            {
+                tracing::warn!(
+                    country = %country,
+                    uri = %req.uri(),
+                    "Request blocked by GeoIP country"
+                );
+                return Ok(self.blocked(country, req.uri().path()));
+            }
+
+            req.extensions_mut().insert(geo);
+            next.run(req).await
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use http_body_util::BodyExt;
+        use serde_json::{json, Value};
+        use std::io::Write;
+
+        /// Minimal MaxMind DB writer: IPv4 search tree with 24-bit records.
+        fn mmdb(networks: &[(&str, Value)]) -> Vec<u8> {
+            #[derive(Clone, Copy)]
+            enum Record {
+                Empty,
+                Node(usize),
+                Data(usize),
+            }
+
+            let mut nodes = vec![[Record::Empty; 2]];
+            let mut data = Vec::new();
+            for (cidr, value) in networks {
+                let (ip, len) = cidr.split_once('/').unwrap();
+                let ip = u32::from(ip.parse::<std::net::Ipv4Addr>().unwrap());
+                let len: usize = len.parse().unwrap();
+                let offset = data.len();
+                encode(value, &mut data);
+
+                let mut node = 0;
+                for depth in 0..len {
+                    let bit = ((ip >> (31 - depth)) & 1) as usize;
+                    if depth == len - 1 {
+                        nodes[node][bit] = Record::Data(offset);
+                    } else {
+                        node = match nodes[node][bit] {
+                            Record::Node(next) => next,
+                            _ => {
+                                nodes.push([Record::Empty; 2]);
+                                nodes[node][bit] = Record::Node(nodes.len() - 1);
+                                nodes.len() - 1
+                            }
+                        };
+                    }
+                }
+            }
+
+            let node_count = nodes.len();
+            let mut out = Vec::new();
+            for node in &nodes {
+                for record in node {
+                    let value = match *record {
+                        Record::Empty => node_count,
+                        Record::Node(next) => next,
+                        Record::Data(offset) => node_count + 16 + offset,
+                    };
+                    out.extend_from_slice(&(value as u32).to_be_bytes()[1..]);
+                }
+            }
+            out.extend_from_slice(&[0; 16]);
+            out.extend_from_slice(&data);
+            out.extend_from_slice(b"\xab\xcd\xefMaxMind.com");
+            encode(
+                &json!({
+                    "binary_format_major_version": 2,
+                    "binary_format_minor_version": 0,
+                    "build_epoch": 1_700_000_000,
+                    "database_type": "Octopus-Test-Country",
+                    "description": { "en": "test fixture" },
+                    "ip_version": 4,
+                    "languages": ["en"],
+                    "node_count": node_count,
+                    "record_size": 24
+                }),
+                &mut out,
+            );
+            out
+        }
+
+        fn encode(value: &Value, out: &mut Vec<u8>) {
+            // Control byte: type in the top 3 bits (0 = extended), size below.
+            fn control(out: &mut Vec<u8>, kind: u8, size: usize) {
+                assert!(size < 29, "fixture values must be small");
+                if kind > 7 {
+                    out.extend_from_slice(&[size as u8, kind - 7]);
+                } else {
+                    out.push((kind << 5) | size as u8);
+                }
+            }
+
+            match value {
+                Value::String(s) => {
+                    control(out, 2, s.len());
+                    out.extend_from_slice(s.as_bytes());
+                }
+                Value::Number(n) => {
+                    let bytes = (n.as_u64().unwrap() as u32).to_be_bytes();
+                    control(out, 6, 4);
+                    out.extend_from_slice(&bytes);
+                }
+                Value::Array(items) => {
+                    control(out, 11, items.len());
+                    items.iter().for_each(|item| encode(item, out));
+                }
+                Value::Object(map) => {
+                    control(out, 7, map.len());
+                    for (key, value) in map {
+                        encode(&Value::String(key.clone()), out);
+                        encode(value, out);
+                    }
+                }
+                other => panic!("unsupported fixture value {other}"),
+            }
+        }
+
+        fn location(country: &str, continent: &str) -> Value {
+            json!({
+                "continent": { "code": continent },
+                "country": { "iso_code": country }
+            })
+        }
+
+        fn fixture() -> Vec<u8> {
+            mmdb(&[
+                ("81.2.69.0/24", location("GB", "EU")),
+                ("89.160.20.0/24", location("SE", "EU")),
+                ("175.16.199.0/24", location("CN", "AS")),
+                (
+                    "216.160.83.0/24",
+                    json!({
+                        "continent": { "code": "NA" },
+                        "country": { "iso_code": "US" },
+                        "subdivisions": [{ "iso_code": "WA" }]
+                    }),
+                ),
+            ])
+        }
+
+        fn write_db(bytes: &[u8]) -> tempfile::NamedTempFile {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            file.write_all(bytes).unwrap();
+            file.flush().unwrap();
+            file
+        }
+
+        /// Records the location the handler saw.
+        #[derive(Debug)]
+        struct Echo;
+
+        #[async_trait]
+        impl Middleware for Echo {
+            async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+                let country = req
+                    .extensions()
+                    .get::<ClientGeo>()
+                    .and_then(|geo| geo.country.clone())
+                    .unwrap_or_default();
+                Ok(Response::new(Full::new(Bytes::from(country))))
+            }
+        }
+
+        async fn call(geoip: GeoIp, ip: &str) -> Response<Body> {
+            let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([Arc::new(geoip), Arc::new(Echo)]);
+            let req = Request::builder()
+                .uri("/content")
+                .header("x-forwarded-for", ip)
+                .body(Body::default())
+                .unwrap();
+            Next::new(stack).run(req).await.unwrap()
+        }
+
+        async fn body(response: Response<Body>) -> Bytes {
+            response.into_body().collect().await.unwrap().to_bytes()
+        }
+
+        #[test]
+        fn resolves_known_ips() {
+            let file = write_db(&fixture());
+            let db = GeoIpDatabase::open(file.path()).unwrap();
+
+            let gb = db.lookup("81.2.69.142".parse().unwrap()).unwrap();
+            assert_eq!(gb.country.as_deref(), Some("GB"));
+            assert_eq!(gb.continent.as_deref(), Some("EU"));
+            assert_eq!(gb.region, None);
+
+            let us = db.lookup("216.160.83.56".parse().unwrap()).unwrap();
+            assert_eq!(us.keys().collect::<Vec<_>>(), ["US-WA", "US", "NA"]);
+
+            assert!(db.lookup("10.0.0.1".parse().unwrap()).is_none());
+        }
+
+        #[tokio::test]
+        async fn blocked_country_gets_451() {
+            let file = write_db(&fixture());
+            let db = Arc::new(GeoIpDatabase::open(file.path()).unwrap());
+            let geoip = GeoIp::new(
+                db,
+                GeoIpConfig {
+                    blocked_countries: HashSet::from(["cn".to_string()]),
+                    ..Default::default()
+                },
+            );
+
+            let response = call(geoip.clone(), "175.16.199.10").await;
+            assert_eq!(response.status(), StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+
+            let response = call(geoip.clone(), "89.160.20.128").await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(body(response).await, "SE");
+
+            // Unknown addresses are let through without a location.
+            let response = call(geoip, "10.0.0.1").await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(body(response).await, "");
+        }
+
+        #[tokio::test]
+        async fn block_status_is_configurable() {
+            let file = write_db(&fixture());
+            let geoip = GeoIp::new(
+                Arc::new(GeoIpDatabase::open(file.path()).unwrap()),
+                GeoIpConfig {
+                    blocked_countries: HashSet::from(["GB".to_string()]),
+                    block_status: StatusCode::FORBIDDEN,
+                    ..Default::default()
+                },
+            );
+            let response = call(geoip, "81.2.69.1").await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+
+        #[test]
+        fn reload_picks_up_new_database() {
+            let mut file = write_db(&fixture());
+            let db = GeoIpDatabase::open(file.path()).unwrap();
+            let ip = "81.2.69.142".parse().unwrap();
+            assert_eq!(db.lookup(ip).unwrap().country.as_deref(), Some("GB"));
+
+            // The range moved to another country in the new release.
+            let updated = mmdb(&[("81.2.69.0/24", location("IE", "EU"))]);
+            file.as_file_mut().set_len(0).unwrap();
+            std::fs::write(file.path(), updated).unwrap();
+            db.reload().unwrap();
+            assert_eq!(db.lookup(ip).unwrap().country.as_deref(), Some("IE"));
+
+            // A broken file keeps the current database.
+            std::fs::write(file.path(), b"not an mmdb").unwrap();
+            assert!(db.reload().is_err());
+            assert_eq!(db.lookup(ip).unwrap().country.as_deref(), Some("IE"));
+        }
+    }
+}
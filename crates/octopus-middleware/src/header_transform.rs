@@ -1,13 +1,22 @@
 //! Header transformation middleware
 //!
 //! Add, set, remove, or rename headers on requests and responses.
+//!
+//! Rules come from [`HeaderTransformConfig`]; a route's templated header,
+//! cookie and query rules (`routes[].transform.request_headers`,
+//! `response_cookies`, `query`, ...) arrive as a [`MatchedRouteHeaders`]
+//! request extension and are applied after them.
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use http::{header::HeaderName, HeaderValue, Request, Response};
+use http::{header::HeaderName, HeaderMap, HeaderValue, Request, Response};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_config::types::{
+    HeaderTemplateRules, QueryRules, RequestCookieRules, ResponseCookieRules, RouteTransformConfig,
+};
+use octopus_core::{cookie, query, Middleware, Next, Result, Template};
 use std::fmt;
+use std::sync::Arc;
 
 /// Body type alias
 pub type Body = Full<Bytes>;
@@ -54,6 +63,189 @@ impl fmt::Debug for HeaderTransformConfig {
     }
 }
 
+/// Header rules whose values are [`Template`]s filled from the request.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRules {
+    /// Set/replace header
+    pub set: Vec<(HeaderName, Template)>,
+    /// Append header
+    pub add: Vec<(HeaderName, Template)>,
+    /// Remove headers by name (before `set`/`add`)
+    pub remove: Vec<HeaderName>,
+}
+
+impl From<&HeaderTemplateRules> for TemplateRules {
+    /// Invalid names and templates are skipped; config validation reports them.
+    fn from(config: &HeaderTemplateRules) -> Self {
+        let parse = |rules: &std::collections::HashMap<String, String>| {
+            rules
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.parse().ok()?, Template::parse(value).ok()?))
+                })
+                .collect()
+        };
+        Self {
+            set: parse(&config.set),
+            add: parse(&config.add),
+            remove: config
+                .remove
+                .iter()
+                .filter_map(|n| n.parse().ok())
+                .collect(),
+        }
+    }
+}
+
+impl TemplateRules {
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.add.is_empty() && self.remove.is_empty()
+    }
+
+    /// Fill the templates from `req`. Headers whose templates reference a
+    /// variable without a value, or render to an invalid value, are dropped.
+    fn render<B>(&self, req: &Request<B>) -> RenderedRules {
+        let render = |rules: &[(HeaderName, Template)]| {
+            rules
+                .iter()
+                .filter_map(|(name, template)| {
+                    let value = HeaderValue::from_str(&template.render(req)?).ok()?;
+                    Some((name.clone(), value))
+                })
+                .collect()
+        };
+        RenderedRules {
+            set: render(&self.set),
+            add: render(&self.add),
+            remove: self.remove.clone(),
+        }
+    }
+}
+
+/// [`TemplateRules`] filled in for one request
+#[derive(Debug, Default)]
+struct RenderedRules {
+    set: Vec<(HeaderName, HeaderValue)>,
+    add: Vec<(HeaderName, HeaderValue)>,
+    remove: Vec<HeaderName>,
+}
+
+impl RenderedRules {
+    fn apply(self, headers: &mut HeaderMap) {
+        for name in &self.remove {
+            headers.remove(name);
+        }
+        for (name, value) in self.set {
+            headers.insert(name, value);
+        }
+        for (name, value) in self.add {
+            headers.append(name, value);
+        }
+    }
+}
+
+/// Per-route templated header, cookie and query rules, injected as a
+/// request extension by the handler for the matched route.
+#[derive(Debug, Clone, Default)]
+pub struct MatchedRouteHeaders {
+    /// Rules applied to the request
+    pub request: Arc<TemplateRules>,
+    /// Rules applied to the response
+    pub response: Arc<TemplateRules>,
+    /// Changes to the request `Cookie` header
+    pub request_cookies: Arc<RequestCookieRules>,
+    /// Changes to the response `Set-Cookie` headers
+    pub response_cookies: Arc<ResponseCookieRules>,
+    /// Changes to the request query parameters
+    pub query: Arc<QueryRules>,
+}
+
+impl From<&RouteTransformConfig> for MatchedRouteHeaders {
+    fn from(config: &RouteTransformConfig) -> Self {
+        Self {
+            request: Arc::new(TemplateRules::from(&config.request_headers)),
+            response: Arc::new(TemplateRules::from(&config.response_headers)),
+            request_cookies: Arc::new(config.request_cookies.clone()),
+            response_cookies: Arc::new(config.response_cookies.clone()),
+            query: Arc::new(config.query.clone()),
+        }
+    }
+}
+
+/// Drop and set request cookies; the rest keep their order.
+fn apply_request_cookies(headers: &mut HeaderMap, rules: &RequestCookieRules) {
+    if rules.is_empty() {
+        return;
+    }
+    cookie::rewrite_cookies(headers, |cookies| {
+        cookies.retain(|(name, _)| !rules.remove.contains(name));
+        for (name, value) in &rules.set {
+            match cookies.iter_mut().find(|(n, _)| n == name) {
+                Some(slot) => slot.1 = value.clone(),
+                None => cookies.push((name.clone(), value.clone())),
+            }
+        }
+    });
+}
+
+/// Filter, drop, rename and append query parameters, keeping the path.
+fn apply_query(req: &mut Request<Body>, rules: &QueryRules) {
+    if rules.is_empty() {
+        return;
+    }
+    let rewritten = query::rewrite_query(req.uri(), |params| {
+        if !rules.allow.is_empty() {
+            params.retain(|(name, _)| rules.allow.contains(name));
+        }
+        params.retain(|(name, _)| !rules.remove.contains(name));
+        for (name, _) in params.iter_mut() {
+            if let Some(new_name) = rules.rename.get(name.as_str()) {
+                *name = new_name.clone();
+            }
+        }
+        params.extend(rules.add.iter().map(|(n, v)| (n.clone(), v.clone())));
+    });
+    match rewritten {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(e) => tracing::warn!(error = %e, "Query transform produced an invalid URI; skipped"),
+    }
+}
+
+/// Drop `Set-Cookie` entries, adjust their attributes, and append new ones.
+fn apply_response_cookies(headers: &mut HeaderMap, rules: &ResponseCookieRules) {
+    if rules.is_empty() {
+        return;
+    }
+    cookie::rewrite_set_cookies(headers, |cookie| {
+        if rules.remove.contains(&cookie.name) {
+            return false;
+        }
+        if rules.cookies.is_empty() || rules.cookies.contains(&cookie.name) {
+            if let Some(same_site) = rules.same_site {
+                cookie.set_same_site(same_site);
+            }
+            if let Some(secure) = rules.secure {
+                cookie.set_secure(secure);
+            }
+            if let Some(http_only) = rules.http_only {
+                cookie.set_http_only(http_only);
+            }
+            match rules.domain.as_deref() {
+                Some("") => cookie.remove_attribute("Domain"),
+                Some(domain) => cookie.set_attribute("Domain", Some(domain)),
+                None => {}
+            }
+        }
+        true
+    });
+    for value in &rules.add {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.append(http::header::SET_COOKIE, value);
+        }
+    }
+}
+
 /// Header transformation middleware
 ///
 /// Applies add/set/remove/rename rules to request and response headers.
@@ -120,14 +312,31 @@ impl fmt::Debug for HeaderTransform {
 #[async_trait]
 impl Middleware for HeaderTransform {
     async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        // Render the route's templates from the request as received
+        let route = req.extensions().get::<MatchedRouteHeaders>().map(|route| {
+            let request = route.request.render(&req);
+            let response = route.response.render(&req);
+            (route.clone(), request, response)
+        });
+
         // Apply request rules
         Self::apply_rules(req.headers_mut(), &self.config.request);
+        let route = route.map(|(route, request, response)| {
+            request.apply(req.headers_mut());
+            apply_request_cookies(req.headers_mut(), &route.request_cookies);
+            apply_query(&mut req, &route.query);
+            (route, response)
+        });
 
         // Call next middleware
         let mut response = next.run(req).await?;
 
         // Apply response rules
         Self::apply_rules(response.headers_mut(), &self.config.response);
+        if let Some((route, rules)) = route {
+            rules.apply(response.headers_mut());
+            apply_response_cookies(response.headers_mut(), &route.response_cookies);
+        }
 
         Ok(response)
     }
@@ -137,7 +346,7 @@ impl Middleware for HeaderTransform {
 mod tests {
     use super::*;
     use http::StatusCode;
-    use octopus_core::Error;
+    use octopus_core::{AuthContext, Error, PathParams};
 
     #[derive(Debug)]
     struct EchoHandler;
@@ -303,4 +512,245 @@ mod tests {
         assert!(values.contains(&"from-handler"));
         assert!(values.contains(&"appended"));
     }
+
+    fn route_headers(request: &[(&str, &str)], response: &[(&str, &str)]) -> MatchedRouteHeaders {
+        let rules = |set: &[(&str, &str)]| HeaderTemplateRules {
+            set: set
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        MatchedRouteHeaders::from(&RouteTransformConfig {
+            request_headers: rules(request),
+            response_headers: rules(response),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_route_templates_substitute_path_param_and_claim() {
+        let next = Next::new(make_stack(HeaderTransform::default()));
+
+        let mut req = Request::builder()
+            .uri("/orders/42")
+            .body(Body::from(""))
+            .unwrap();
+        req.extensions_mut().insert(route_headers(
+            &[("x-order", "order-{param.id}"), ("x-user", "{claim.sub}")],
+            &[("x-served-for", "{claim.tenant}/{param.id}")],
+        ));
+        req.extensions_mut().insert(PathParams(
+            [("id".to_string(), "42".to_string())].into_iter().collect(),
+        ));
+        req.extensions_mut().insert(AuthContext {
+            subject: "alice".to_string(),
+            provider: "jwt".to_string(),
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            claims: [("tenant".to_string(), serde_json::json!("acme"))]
+                .into_iter()
+                .collect(),
+        });
+
+        let resp = next.run(req).await.unwrap();
+        assert_eq!(resp.headers()["x-echo-x-order"], "order-42");
+        assert_eq!(resp.headers()["x-echo-x-user"], "alice");
+        assert_eq!(resp.headers()["x-served-for"], "acme/42");
+    }
+
+    #[tokio::test]
+    async fn test_route_template_without_value_is_skipped() {
+        let next = Next::new(make_stack(HeaderTransform::default()));
+
+        let mut req = Request::builder()
+            .uri("/orders/42")
+            .header("x-tenant", "acme")
+            .body(Body::from(""))
+            .unwrap();
+        req.extensions_mut().insert(route_headers(
+            &[("x-user", "{claim.sub}"), ("x-copied", "{header.x-tenant}")],
+            &[],
+        ));
+
+        let resp = next.run(req).await.unwrap();
+        assert!(resp.headers().get("x-echo-x-user").is_none());
+        assert_eq!(resp.headers()["x-echo-x-copied"], "acme");
+    }
+
+    #[derive(Debug)]
+    struct SetCookieHandler;
+
+    #[async_trait]
+    impl Middleware for SetCookieHandler {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            let cookies = req
+                .headers()
+                .get(http::header::COOKIE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+            Response::builder()
+                .header("x-echo-cookie", cookies)
+                .header(http::header::SET_COOKIE, "session=abc; Path=/; Secure")
+                .header(http::header::SET_COOKIE, "tracking=1; Path=/")
+                .header(http::header::SET_COOKIE, "theme=dark")
+                .body(Full::new(Bytes::from("ok")))
+                .map_err(|e| Error::Internal(e.to_string()))
+        }
+    }
+
+    async fn run_with_cookies(config: RouteTransformConfig, cookie: &str) -> Response<Body> {
+        let stack: std::sync::Arc<[std::sync::Arc<dyn Middleware>]> = std::sync::Arc::new([
+            std::sync::Arc::new(HeaderTransform::default()) as std::sync::Arc<dyn Middleware>,
+            std::sync::Arc::new(SetCookieHandler),
+        ]);
+        let mut req = Request::builder()
+            .uri("/")
+            .header(http::header::COOKIE, cookie)
+            .body(Body::from(""))
+            .unwrap();
+        req.extensions_mut()
+            .insert(MatchedRouteHeaders::from(&config));
+        Next::new(stack).run(req).await.unwrap()
+    }
+
+    fn set_cookies(resp: &Response<Body>) -> Vec<&str> {
+        resp.headers()
+            .get_all(http::header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_route_strips_named_request_cookie_and_keeps_others() {
+        let config = RouteTransformConfig {
+            request_cookies: RequestCookieRules {
+                remove: vec!["internal_token".to_string()],
+                set: [("region".to_string(), "eu".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+            ..Default::default()
+        };
+
+        let resp = run_with_cookies(config, "a=1; internal_token=secret; b=2").await;
+        assert_eq!(resp.headers()["x-echo-cookie"], "a=1; b=2; region=eu");
+    }
+
+    #[tokio::test]
+    async fn test_route_adjusts_set_cookie_attributes() {
+        let config = RouteTransformConfig {
+            response_cookies: ResponseCookieRules {
+                remove: vec!["tracking".to_string()],
+                cookies: vec!["session".to_string()],
+                same_site: Some(octopus_core::SameSite::Lax),
+                secure: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let resp = run_with_cookies(config, "a=1").await;
+        assert_eq!(
+            set_cookies(&resp),
+            vec!["session=abc; Path=/; SameSite=Lax", "theme=dark"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_route_adds_same_site_to_every_set_cookie() {
+        let config = RouteTransformConfig {
+            response_cookies: ResponseCookieRules {
+                same_site: Some(octopus_core::SameSite::Lax),
+                add: vec!["consent=1; Path=/".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let resp = run_with_cookies(config, "a=1").await;
+        assert_eq!(
+            set_cookies(&resp),
+            vec![
+                "session=abc; Path=/; Secure; SameSite=Lax",
+                "tracking=1; Path=/; SameSite=Lax",
+                "theme=dark; SameSite=Lax",
+                "consent=1; Path=/",
+            ]
+        );
+    }
+
+    #[derive(Debug)]
+    struct EchoUri;
+
+    #[async_trait]
+    impl Middleware for EchoUri {
+        async fn call(&self, req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            Ok(Response::new(Full::new(Bytes::from(req.uri().to_string()))))
+        }
+    }
+
+    async fn forwarded_uri(query: QueryRules, uri: &str) -> String {
+        use http_body_util::BodyExt;
+
+        let stack: std::sync::Arc<[std::sync::Arc<dyn Middleware>]> = std::sync::Arc::new([
+            std::sync::Arc::new(HeaderTransform::default()) as std::sync::Arc<dyn Middleware>,
+            std::sync::Arc::new(EchoUri),
+        ]);
+        let mut req = Request::builder().uri(uri).body(Body::from("")).unwrap();
+        req.extensions_mut()
+            .insert(MatchedRouteHeaders::from(&RouteTransformConfig {
+                query,
+                ..Default::default()
+            }));
+        let resp = Next::new(stack).run(req).await.unwrap();
+        String::from_utf8(
+            resp.into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_query_add_remove_rename() {
+        let rules = QueryRules {
+            remove: vec!["debug".to_string()],
+            rename: [("q".to_string(), "search".to_string())]
+                .into_iter()
+                .collect(),
+            add: [("source".to_string(), "gateway api".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            forwarded_uri(rules, "/items/7?q=a%26b&debug=1&tag=x&debug=2&q=c").await,
+            "/items/7?search=a%26b&tag=x&search=c&source=gateway+api"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_allowlist_drops_unlisted_params() {
+        let rules = QueryRules {
+            allow: vec!["page".to_string(), "tag".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            forwarded_uri(
+                rules.clone(),
+                "/items?utm_source=x&tag=a&page=2&tag=b&_=123"
+            )
+            .await,
+            "/items?tag=a&page=2&tag=b"
+        );
+        assert_eq!(forwarded_uri(rules, "/items?utm_source=x").await, "/items");
+    }
 }
@@ -0,0 +1,513 @@
+//! Idempotency keys backed by a shared state store
+//!
+//! [`Idempotency`] lets a client retry an unsafe request, such as a payment
+//! `POST`, without it being processed twice. The first request carrying an
+//! `Idempotency-Key` takes the key in the [`StateBackend`] and runs; its
+//! response is stored and replayed to later requests with the same key. A
+//! request arriving while the first is still in flight gets `409 Conflict`.
+//!
+//! Keys are scoped to the method, the matched route, the authenticated
+//! principal and a hash of the body, so the same key sent with a different
+//! payload, or by another client, is a different request. Because the state
+//! lives in the backend, a shared backend holds keys across replicas.
+//!
+//! In-flight keys are taken with a set-if-absent, so a retry never extends
+//! them. They can live in a backend of their own
+//! ([`with_locks`](Idempotency::with_locks)), so a size-capped response
+//! store never evicts a key that is still held. Bodies over `max_body_size`
+//! are not buffered for replay: such requests get `413`, and such responses
+//! are returned without being stored.
+
+use crate::auth_gateway::AuthRateLimitKey;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, HeaderName, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use octopus_config::types::IdempotencyConfig;
+use octopus_core::request::RouteInfo;
+use octopus_core::{Error, ErrorResponse, Middleware, Next, Result, StreamedResponse};
+use octopus_state::StateBackend;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::Duration;
+
+/// Body type alias
+pub type Body = Full<Bytes>;
+
+/// Header marking a response replayed from the store
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Response headers tied to one exchange, not stored for replay
+const UNSTORED_HEADERS: [&str; 3] = ["date", "set-cookie", "x-request-id"];
+
+/// A completed response as kept in the backend
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Hex-encoded body
+    body: String,
+}
+
+impl StoredResponse {
+    fn capture(response: &Response<Body>, body: &Bytes) -> Self {
+        Self {
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .filter(|(name, _)| !UNSTORED_HEADERS.contains(&name.as_str()))
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: hex::encode(body),
+        }
+    }
+
+    fn replay(&self) -> Result<Response<Body>> {
+        let body = hex::decode(&self.body)
+            .map_err(|e| Error::Internal(format!("Corrupt idempotent response: {e}")))?;
+        let mut builder =
+            Response::builder().status(StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK));
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        builder
+            .header(IDEMPOTENT_REPLAYED_HEADER, "true")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| Error::Internal(format!("Failed to build idempotent response: {e}")))
+    }
+}
+
+/// Idempotency-key middleware over a [`StateBackend`]
+#[derive(Clone)]
+pub struct Idempotency<B: StateBackend> {
+    header: HeaderName,
+    methods: Vec<Method>,
+    ttl: Duration,
+    lock_timeout: Duration,
+    max_body_size: usize,
+    key_prefix: String,
+    backend: B,
+    locks: B,
+}
+
+impl<B: StateBackend> Idempotency<B> {
+    /// Create the middleware from `config`, keeping responses and in-flight
+    /// keys in `backend`.
+    pub fn new(config: &IdempotencyConfig, backend: B) -> Result<Self> {
+        let header = HeaderName::from_bytes(config.header.as_bytes()).map_err(|e| {
+            Error::Config(format!(
+                "Invalid idempotency header {:?}: {e}",
+                config.header
+            ))
+        })?;
+        let methods = config
+            .methods
+            .iter()
+            .map(|m| {
+                m.parse()
+                    .map_err(|_| Error::Config(format!("Invalid idempotency method: {m}")))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            header,
+            methods,
+            ttl: config.ttl,
+            lock_timeout: config.lock_timeout,
+            max_body_size: config.max_body_size,
+            key_prefix: "octopus:idem".to_string(),
+            locks: backend.clone(),
+            backend,
+        })
+    }
+
+    /// Keep in-flight keys in `locks` instead of the response store.
+    pub fn with_locks(mut self, locks: B) -> Self {
+        self.locks = locks;
+        self
+    }
+
+    /// Backend key for this request: the client key scoped to the method,
+    /// route, principal and body
+    fn scoped_key(&self, req: &Request<Body>, key: &str, body: &Bytes) -> String {
+        let route = req
+            .extensions()
+            .get::<RouteInfo>()
+            .map_or(req.uri().path(), |r| r.path.as_str());
+        let principal = req
+            .extensions()
+            .get::<AuthRateLimitKey>()
+            .map_or("", |p| p.0.as_str());
+
+        let mut hasher = Sha256::new();
+        for part in [req.method().as_str(), route, principal, key] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(Sha256::digest(body));
+        format!("{}:{}", self.key_prefix, hex::encode(hasher.finalize()))
+    }
+
+    async fn stored(&self, key: &str) -> Result<Option<StoredResponse>> {
+        let Some(raw) = self.backend.get(key).await.map_err(backend_error)? else {
+            return Ok(None);
+        };
+        match serde_json::from_slice(&raw) {
+            Ok(stored) => Ok(Some(stored)),
+            Err(e) => {
+                tracing::warn!(error = %e, "Discarding unreadable idempotent response");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Take the key for this request; `false` when another holds it
+    async fn acquire(&self, lock: &str) -> Result<bool> {
+        self.locks
+            .set_if_absent(lock, b"1".to_vec(), Some(self.lock_timeout))
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn release(&self, lock: &str) {
+        if let Err(e) = self.locks.delete(lock).await {
+            tracing::warn!(error = %e, "Failed to release idempotency key; it expires on its own");
+        }
+    }
+
+    fn in_flight_response(&self) -> Response<Body> {
+        ErrorResponse::new(StatusCode::CONFLICT, "idempotency_key_in_use")
+            .detail("A request with this idempotency key is still being processed")
+            .header(header::RETRY_AFTER, "1")
+            .into_response()
+    }
+
+    fn too_large(&self, path: &str) -> Response<Body> {
+        ErrorResponse::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large")
+            .detail(format!("Request body exceeds {} bytes", self.max_body_size))
+            .instance(path)
+            .into_response()
+    }
+}
+
+fn backend_error(e: octopus_state::Error) -> Error {
+    Error::Internal(format!("State backend error: {e}"))
+}
+
+impl<B: StateBackend> fmt::Debug for Idempotency<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Idempotency")
+            .field("header", &self.header)
+            .field("methods", &self.methods)
+            .field("ttl", &self.ttl)
+            .field("lock_timeout", &self.lock_timeout)
+            .field("max_body_size", &self.max_body_size)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<B: StateBackend> Middleware for Idempotency<B> {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        if !self.methods.contains(req.method()) {
+            return next.run(req).await;
+        }
+        let Some(key) = req
+            .headers()
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok())
+            .filter(|k| !k.is_empty())
+            .map(str::to_string)
+        else {
+            return next.run(req).await;
+        };
+
+        let (parts, body) = req.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        if body.len() > self.max_body_size {
+            return Ok(self.too_large(parts.uri.path()));
+        }
+        let req = Request::from_parts(parts, Full::new(body.clone()));
+        let scoped = self.scoped_key(&req, &key, &body);
+        let lock = format!("{scoped}:lock");
+
+        if let Some(stored) = self.stored(&scoped).await? {
+            tracing::debug!(idempotency_key = %key, "Replaying stored response");
+            return stored.replay();
+        }
+        if !self.acquire(&lock).await? {
+            tracing::debug!(idempotency_key = %key, "Idempotency key already in flight");
+            return Ok(self.in_flight_response());
+        }
+        // The first request may have finished between the lookup and the
+        // acquire.
+        if let Some(stored) = self.stored(&scoped).await? {
+            self.release(&lock).await;
+            return stored.replay();
+        }
+
+        let response = match next.run(req).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.release(&lock).await;
+                return Err(e);
+            }
+        };
+        // A streamed body can't be stored for replay
+        if StreamedResponse::is_streamed(&response) {
+            self.release(&lock).await;
+            return Ok(response);
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        let response = Response::from_parts(parts, Full::new(body.clone()));
+
+        // Server errors are not kept, so the client can retry them.
+        if body.len() > self.max_body_size {
+            tracing::warn!(
+                idempotency_key = %key,
+                size = body.len(),
+                "Response too large to store for idempotent replay"
+            );
+        } else if !response.status().is_server_error() {
+            let stored = StoredResponse::capture(&response, &body);
+            match serde_json::to_vec(&stored) {
+                Ok(value) => {
+                    if let Err(e) = self.backend.set(&scoped, value, Some(self.ttl)).await {
+                        tracing::warn!(idempotency_key = %key, error = %e, "Failed to store idempotent response");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to encode idempotent response"),
+            }
+        }
+        self.release(&lock).await;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_state::InMemoryBackend;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+
+    /// Terminal handler counting calls; waits for `gate` when set
+    #[derive(Debug, Default)]
+    struct Upstream {
+        calls: AtomicU64,
+        gate: Option<Arc<Notify>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Upstream {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if let Some(gate) = &self.gate {
+                gate.notified().await;
+            }
+            Ok(Response::builder()
+                .status(StatusCode::CREATED)
+                .header("content-type", "application/json")
+                .header("x-request-id", format!("req-{n}"))
+                .body(Full::new(Bytes::from(format!("{{\"charge\":{n}}}"))))
+                .unwrap())
+        }
+    }
+
+    fn stack(upstream: Arc<Upstream>) -> Arc<[Arc<dyn Middleware>]> {
+        let idempotency =
+            Idempotency::new(&IdempotencyConfig::default(), InMemoryBackend::new()).unwrap();
+        Arc::new([Arc::new(idempotency) as Arc<dyn Middleware>, upstream])
+    }
+
+    fn charge(key: &str, body: &'static str) -> Request<Body> {
+        Request::post("/charges")
+            .header("idempotency-key", key)
+            .body(Full::new(Bytes::from(body)))
+            .unwrap()
+    }
+
+    async fn body(response: Response<Body>) -> Bytes {
+        response.into_body().collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn replay_returns_the_stored_response() {
+        let upstream = Arc::new(Upstream::default());
+        let stack = stack(Arc::clone(&upstream));
+
+        let first = Next::new(stack.clone())
+            .run(charge("k-1", "{\"amount\":5}"))
+            .await
+            .unwrap();
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(!first.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert_eq!(body(first).await, "{\"charge\":1}");
+
+        let replay = Next::new(stack.clone())
+            .run(charge("k-1", "{\"amount\":5}"))
+            .await
+            .unwrap();
+        assert_eq!(replay.status(), StatusCode::CREATED);
+        assert_eq!(replay.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(replay.headers()["content-type"], "application/json");
+        assert!(!replay.headers().contains_key("x-request-id"));
+        assert_eq!(body(replay).await, "{\"charge\":1}");
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 1);
+
+        // The same key with another body is a different request.
+        let other = Next::new(stack.clone())
+            .run(charge("k-1", "{\"amount\":9}"))
+            .await
+            .unwrap();
+        assert_eq!(body(other).await, "{\"charge\":2}");
+    }
+
+    #[tokio::test]
+    async fn concurrent_request_with_the_key_in_flight_conflicts() {
+        let gate = Arc::new(Notify::new());
+        let upstream = Arc::new(Upstream {
+            gate: Some(Arc::clone(&gate)),
+            ..Default::default()
+        });
+        let stack = stack(Arc::clone(&upstream));
+
+        let first = tokio::spawn({
+            let stack = stack.clone();
+            async move { Next::new(stack).run(charge("k-2", "{}")).await }
+        });
+        while upstream.calls.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let second = Next::new(stack.clone())
+            .run(charge("k-2", "{}"))
+            .await
+            .unwrap();
+        assert_eq!(second.status(), StatusCode::CONFLICT);
+        assert_eq!(second.headers()[header::RETRY_AFTER], "1");
+
+        gate.notify_one();
+        let first = first.await.unwrap().unwrap();
+        assert_eq!(first.status(), StatusCode::CREATED);
+
+        // Once the first completes, retries get its response.
+        let retry = Next::new(stack.clone())
+            .run(charge("k-2", "{}"))
+            .await
+            .unwrap();
+        assert_eq!(retry.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn held_keys_are_not_extended_by_retries() {
+        let gate = Arc::new(Notify::new());
+        let upstream = Arc::new(Upstream {
+            gate: Some(Arc::clone(&gate)),
+            ..Default::default()
+        });
+        let config = IdempotencyConfig {
+            lock_timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let idempotency = Idempotency::new(&config, InMemoryBackend::new()).unwrap();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(idempotency) as Arc<dyn Middleware>,
+            upstream.clone(),
+        ]);
+
+        let _stuck = tokio::spawn({
+            let stack = stack.clone();
+            async move { Next::new(stack).run(charge("k-4", "{}")).await }
+        });
+        while upstream.calls.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Retries inside the timeout conflict without pushing it back, so
+        // the key frees up once `lock_timeout` has passed since it was taken.
+        for _ in 0..4 {
+            let retry = Next::new(stack.clone())
+                .run(charge("k-4", "{}"))
+                .await
+                .unwrap();
+            assert_eq!(retry.status(), StatusCode::CONFLICT);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let retry = tokio::spawn({
+            let stack = stack.clone();
+            async move { Next::new(stack).run(charge("k-4", "{}")).await }
+        });
+        while upstream.calls.load(Ordering::SeqCst) < 2 {
+            tokio::task::yield_now().await;
+        }
+        // One permit each for the stuck request and the retry
+        gate.notify_one();
+        gate.notify_one();
+        assert_eq!(retry.await.unwrap().unwrap().status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_not_stored() {
+        let upstream = Arc::new(Upstream::default());
+        let config = IdempotencyConfig {
+            max_body_size: 8,
+            ..Default::default()
+        };
+        let idempotency = Idempotency::new(&config, InMemoryBackend::new()).unwrap();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(idempotency) as Arc<dyn Middleware>,
+            upstream.clone(),
+        ]);
+
+        let rejected = Next::new(stack.clone())
+            .run(charge("k-5", "{\"amount\":500}"))
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 0);
+
+        // `{"charge":n}` is over the limit, so each retry reaches the upstream.
+        for n in 1..=2 {
+            let response = Next::new(stack.clone())
+                .run(charge("k-5", "{}"))
+                .await
+                .unwrap();
+            assert!(!response.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+            assert_eq!(body(response).await, format!("{{\"charge\":{n}}}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_without_a_key_or_with_safe_methods_pass_through() {
+        let upstream = Arc::new(Upstream::default());
+        let stack = stack(Arc::clone(&upstream));
+
+        for _ in 0..2 {
+            let req = Request::post("/charges")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            Next::new(stack.clone()).run(req).await.unwrap();
+            let req = Request::get("/charges")
+                .header("idempotency-key", "k-3")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            Next::new(stack.clone()).run(req).await.unwrap();
+        }
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 4);
+    }
+}
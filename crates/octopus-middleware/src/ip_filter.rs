@@ -9,111 +9,11 @@ use std::fmt;
 use std::net::IpAddr;
 use std::str::FromStr;
 
+pub use octopus_core::IpPattern;
+
 /// Body type alias
 pub type Body = Full<Bytes>;
 
-/// IP pattern for matching
-#[derive(Debug, Clone)]
-pub enum IpPattern {
-    /// Exact IP address match
-    Exact(IpAddr),
-    /// CIDR range match (base address + prefix length)
-    Cidr(IpAddr, u8),
-    /// IP range match (inclusive start to inclusive end)
-    Range(IpAddr, IpAddr),
-}
-
-impl IpPattern {
-    /// Parse a string into an IpPattern.
-    ///
-    /// Supports:
-    /// - Exact: "192.168.1.1"
-    /// - CIDR: "192.168.1.0/24"
-    /// - Range: "192.168.1.1-192.168.1.254"
-    pub fn parse(s: &str) -> std::result::Result<Self, String> {
-        if let Some((base, prefix)) = s.split_once('/') {
-            let addr = IpAddr::from_str(base).map_err(|e| format!("invalid IP in CIDR: {e}"))?;
-            let prefix_len: u8 = prefix
-                .parse()
-                .map_err(|e| format!("invalid prefix length: {e}"))?;
-            let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
-            if prefix_len > max_prefix {
-                return Err(format!(
-                    "prefix length {prefix_len} exceeds maximum {max_prefix}"
-                ));
-            }
-            Ok(IpPattern::Cidr(addr, prefix_len))
-        } else if let Some((start, end)) = s.split_once('-') {
-            let start_addr =
-                IpAddr::from_str(start).map_err(|e| format!("invalid start IP: {e}"))?;
-            let end_addr = IpAddr::from_str(end).map_err(|e| format!("invalid end IP: {e}"))?;
-            Ok(IpPattern::Range(start_addr, end_addr))
-        } else {
-            let addr = IpAddr::from_str(s).map_err(|e| format!("invalid IP address: {e}"))?;
-            Ok(IpPattern::Exact(addr))
-        }
-    }
-
-    /// Check if an IP address matches this pattern
-    pub fn matches(&self, ip: &IpAddr) -> bool {
-        match self {
-            IpPattern::Exact(pattern_ip) => ip == pattern_ip,
-            IpPattern::Cidr(base, prefix_len) => cidr_matches(base, *prefix_len, ip),
-            IpPattern::Range(start, end) => range_matches(start, end, ip),
-        }
-    }
-}
-
-/// Check if an IP is within a CIDR range
-fn cidr_matches(base: &IpAddr, prefix_len: u8, candidate: &IpAddr) -> bool {
-    match (base, candidate) {
-        (IpAddr::V4(base_v4), IpAddr::V4(cand_v4)) => {
-            if prefix_len == 0 {
-                return true;
-            }
-            if prefix_len >= 32 {
-                return base_v4 == cand_v4;
-            }
-            let base_bits = u32::from(*base_v4);
-            let cand_bits = u32::from(*cand_v4);
-            let mask = !0u32 << (32 - prefix_len);
-            (base_bits & mask) == (cand_bits & mask)
-        }
-        (IpAddr::V6(base_v6), IpAddr::V6(cand_v6)) => {
-            if prefix_len == 0 {
-                return true;
-            }
-            if prefix_len >= 128 {
-                return base_v6 == cand_v6;
-            }
-            let base_bits = u128::from(*base_v6);
-            let cand_bits = u128::from(*cand_v6);
-            let mask = !0u128 << (128 - prefix_len);
-            (base_bits & mask) == (cand_bits & mask)
-        }
-        _ => false, // IPv4 vs IPv6 mismatch
-    }
-}
-
-/// Check if an IP is within a range (inclusive)
-fn range_matches(start: &IpAddr, end: &IpAddr, candidate: &IpAddr) -> bool {
-    match (start, end, candidate) {
-        (IpAddr::V4(s), IpAddr::V4(e), IpAddr::V4(c)) => {
-            let s = u32::from(*s);
-            let e = u32::from(*e);
-            let c = u32::from(*c);
-            c >= s && c <= e
-        }
-        (IpAddr::V6(s), IpAddr::V6(e), IpAddr::V6(c)) => {
-            let s = u128::from(*s);
-            let e = u128::from(*e);
-            let c = u128::from(*c);
-            c >= s && c <= e
-        }
-        _ => false,
-    }
-}
-
 /// IP filter configuration
 #[derive(Debug, Clone)]
 pub struct IpFilterConfig {
@@ -125,6 +25,9 @@ pub struct IpFilterConfig {
     pub deny_ips: Vec<IpPattern>,
     /// Whether to trust X-Forwarded-For header for client IP extraction
     pub trust_forwarded_for: bool,
+    /// Proxies in front of the gateway, skipped when reading the client
+    /// from `X-Forwarded-For`
+    pub trusted_proxies: Vec<IpPattern>,
     /// Custom rejection message
     pub rejection_message: Option<String>,
 }
@@ -136,6 +39,7 @@ impl Default for IpFilterConfig {
             allow_ips: Vec::new(),
             deny_ips: Vec::new(),
             trust_forwarded_for: true,
+            trusted_proxies: Vec::new(),
             rejection_message: None,
         }
     }
@@ -145,7 +49,8 @@ impl Default for IpFilterConfig {
 ///
 /// Filters requests based on client IP address using allowlists and blocklists.
 /// Deny rules take precedence over allow rules.
-/// Extracts client IP from `X-Forwarded-For` header or falls back to `X-Real-IP`.
+/// Extracts client IP from `X-Forwarded-For` header (the rightmost entry that
+/// isn't a trusted proxy) or falls back to `X-Real-IP`.
 #[derive(Clone)]
 pub struct IpFilter {
     config: IpFilterConfig,
@@ -167,28 +72,10 @@ impl IpFilter {
     /// Extract client IP from request headers or connection info
     fn extract_client_ip<B>(&self, req: &Request<B>) -> Option<IpAddr> {
         if self.config.trust_forwarded_for {
-            // Try X-Forwarded-For first (first entry is the original client)
-            if let Some(xff) = req.headers().get("x-forwarded-for") {
-                if let Ok(xff_str) = xff.to_str() {
-                    if let Some(first_ip) = xff_str.split(',').next() {
-                        if let Ok(ip) = IpAddr::from_str(first_ip.trim()) {
-                            return Some(ip);
-                        }
-                    }
-                }
-            }
-
-            // Try X-Real-IP
-            if let Some(real_ip) = req.headers().get("x-real-ip") {
-                if let Ok(ip_str) = real_ip.to_str() {
-                    if let Ok(ip) = IpAddr::from_str(ip_str.trim()) {
-                        return Some(ip);
-                    }
-                }
-            }
+            forwarded_client_ip(req, &self.config.trusted_proxies)
+        } else {
+            None
         }
-
-        None
     }
 
     /// Check if an IP is allowed by the filter rules
@@ -235,6 +122,28 @@ impl IpFilter {
     }
 }
 
+/// The client IP from `X-Forwarded-For`, falling back to `X-Real-IP`
+///
+/// The chain is walked from the right, past the `trusted` proxies: the first
+/// other address is the client as the nearest trusted hop saw it, while
+/// entries further left are whatever the client chose to send.
+pub(crate) fn forwarded_client_ip<B>(req: &Request<B>, trusted: &[IpPattern]) -> Option<IpAddr> {
+    if let Some(ip) = crate::forwarded::rightmost_untrusted(req.headers(), trusted) {
+        return Some(ip);
+    }
+
+    // Try X-Real-IP
+    if let Some(real_ip) = req.headers().get("x-real-ip") {
+        if let Ok(ip_str) = real_ip.to_str() {
+            if let Ok(ip) = IpAddr::from_str(ip_str.trim()) {
+                return Some(ip);
+            }
+        }
+    }
+
+    None
+}
+
 impl Default for IpFilter {
     fn default() -> Self {
         Self::new()
@@ -435,13 +344,32 @@ mod tests {
         let stack = make_stack(IpFilter::with_config(config));
         let next = Next::new(stack);
 
-        // X-Forwarded-For with multiple entries: first one is client
+        // X-Forwarded-For with multiple entries: the rightmost one is the
+        // client, anything left of it may be spoofed
         let req = Request::builder()
             .uri("/test")
             .header("X-Forwarded-For", "1.2.3.4, 5.6.7.8")
             .body(Body::from(""))
             .unwrap();
         let resp = next.run(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_skips_trusted_proxies_in_x_forwarded_for() {
+        let config = IpFilterConfig {
+            deny_ips: vec![IpPattern::Exact(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)))],
+            trusted_proxies: vec![IpPattern::parse("10.0.0.0/8").unwrap()],
+            ..Default::default()
+        };
+        let stack = make_stack(IpFilter::with_config(config));
+
+        // Spoofed entry, client, then two trusted hops
+        let next = Next::new(stack);
+        let resp = next
+            .run(req_with_ip("9.9.9.9, 1.2.3.4, 10.0.0.7, 10.0.0.1"))
+            .await
+            .unwrap();
         assert_eq!(resp.status(), StatusCode::FORBIDDEN);
     }
 
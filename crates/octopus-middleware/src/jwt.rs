@@ -2,20 +2,42 @@
 //!
 //! Validates JSON Web Tokens (JWT) for authentication and authorization.
 //! Supports RS256, HS256, and other standard JWT algorithms.
-
+//!
+//! Claims of a valid token can be forwarded to the upstream as request
+//! headers: `claim_headers` maps a claim path (`sub`, `realm_access.roles`,
+//! `$['https://example.com/tenant']`) to a header name, and array claims are
+//! joined with `array_separator`. Mapped headers sent by the client are
+//! always removed, so an upstream can trust them.
+//!
+//! Rejections carry an RFC 6750 `WWW-Authenticate` challenge: a bare
+//! `Bearer` when no token was sent, `error="invalid_token"` for expired,
+//! malformed or otherwise invalid tokens, and `error="insufficient_scope"`
+//! (with 403) when the token lacks one of the matched route's
+//! `require_scopes` ([`MatchedRouteAuth`]). With
+//! `expiry_hint` set, successful responses carry `X-Token-Expires-In` so
+//! clients can refresh before the token runs out.
+
+use crate::auth_gateway::MatchedRouteAuth;
 use async_trait::async_trait;
 use bytes::Bytes;
-use http::{header, Request, Response, StatusCode};
+use http::{header, HeaderName, HeaderValue, Request, Response, StatusCode};
 use http_body_util::Full;
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
-use octopus_core::{Middleware, Next, Result as CoreResult};
+use octopus_core::json_path::claim_header_value;
+use octopus_core::{ErrorResponse, Middleware, Next, Result as CoreResult};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Body type alias
 pub type Body = Full<Bytes>;
 
+/// Response header carrying the seconds until the token expires
+pub const TOKEN_EXPIRES_IN_HEADER: &str = "x-token-expires-in";
+
 /// JWT Configuration
 #[derive(Clone)]
 pub struct JwtConfig {
@@ -42,6 +64,19 @@ pub struct JwtConfig {
 
     /// Skip paths that don't require authentication
     pub skip_paths: Vec<String>,
+
+    /// Claims forwarded upstream: claim path → header name
+    pub claim_headers: BTreeMap<String, String>,
+
+    /// Separator joining the elements of an array claim (default: ",")
+    pub array_separator: String,
+
+    /// Remove the token header before forwarding, so the upstream only sees
+    /// the mapped claims
+    pub strip_authorization: bool,
+
+    /// Add `X-Token-Expires-In` to successful responses
+    pub expiry_hint: bool,
 }
 
 impl Default for JwtConfig {
@@ -55,6 +90,10 @@ impl Default for JwtConfig {
             audience: None,
             issuer: None,
             skip_paths: vec![],
+            claim_headers: BTreeMap::new(),
+            array_separator: ",".to_string(),
+            strip_authorization: false,
+            expiry_hint: false,
         }
     }
 }
@@ -70,6 +109,9 @@ impl fmt::Debug for JwtConfig {
             .field("has_secret", &self.secret.is_some())
             .field("has_public_key", &self.public_key.is_some())
             .field("skip_paths", &self.skip_paths)
+            .field("claim_headers", &self.claim_headers)
+            .field("strip_authorization", &self.strip_authorization)
+            .field("expiry_hint", &self.expiry_hint)
             .finish()
     }
 }
@@ -106,6 +148,8 @@ pub struct JwtAuth {
     config: Arc<JwtConfig>,
     validation: Validation,
     decoding_key: Arc<DecodingKey>,
+    /// Parsed `claim_headers`
+    claim_headers: Arc<[(String, HeaderName)]>,
 }
 
 impl JwtAuth {
@@ -125,6 +169,7 @@ impl JwtAuth {
             config: Arc::new(config),
             validation,
             decoding_key: Arc::new(decoding_key),
+            claim_headers: Arc::new([]),
         }
     }
 
@@ -168,13 +213,55 @@ impl JwtAuth {
             validation.set_issuer(&[iss]);
         }
 
+        let claim_headers = config
+            .claim_headers
+            .iter()
+            .map(|(claim, name)| {
+                HeaderName::from_bytes(name.as_bytes())
+                    .map(|header| (claim.clone(), header))
+                    .map_err(|e| {
+                        octopus_core::Error::Config(format!(
+                            "Invalid header name {name:?} for claim {claim:?}: {e}"
+                        ))
+                    })
+            })
+            .collect::<CoreResult<_>>()?;
+
         Ok(Self {
             config: Arc::new(config),
             validation,
             decoding_key: Arc::new(decoding_key),
+            claim_headers,
         })
     }
 
+    /// Set the mapped headers from `claims`, skipping claims that are absent
+    /// or can't be a header value
+    fn forward_claims(&self, claims: &Claims, headers: &mut http::HeaderMap) {
+        if self.claim_headers.is_empty() {
+            return;
+        }
+        let Ok(claims) = serde_json::to_value(claims) else {
+            return;
+        };
+        for (claim, header) in self.claim_headers.iter() {
+            let Some(value) = claim_header_value(&claims, claim, &self.config.array_separator)
+            else {
+                continue;
+            };
+            match HeaderValue::from_str(&value) {
+                Ok(value) => {
+                    headers.insert(header.clone(), value);
+                }
+                Err(_) => tracing::warn!(
+                    claim = %claim,
+                    header = %header,
+                    "Claim is not a valid header value; not forwarded"
+                ),
+            }
+        }
+    }
+
     /// Extract token from request
     fn extract_token(&self, req: &Request<Body>) -> Option<String> {
         req.headers()
@@ -202,22 +289,91 @@ impl JwtAuth {
         })
     }
 
-    /// Build unauthorized response
-    fn unauthorized_response(&self, message: &str) -> Response<Body> {
-        let body = serde_json::json!({
-            "error": "unauthorized",
-            "message": message
-        });
+    /// Scopes in `required` the token doesn't grant
+    fn missing_scopes<'a>(&self, required: &'a [String], claims: &Claims) -> Vec<&'a str> {
+        if required.is_empty() {
+            return vec![];
+        }
+        let granted = token_scopes(&claims.custom);
+        required
+            .iter()
+            .map(String::as_str)
+            .filter(|scope| !granted.contains(scope))
+            .collect()
+    }
 
-        Response::builder()
-            .status(StatusCode::UNAUTHORIZED)
-            .header(header::CONTENT_TYPE, "application/json")
+    /// Build unauthorized response for a request without a token. Per
+    /// RFC 6750 §3.1 the challenge carries no error code.
+    fn missing_token_response(&self) -> Response<Body> {
+        ErrorResponse::new(StatusCode::UNAUTHORIZED, "unauthorized")
+            .detail("Missing authentication token")
             .header(header::WWW_AUTHENTICATE, "Bearer")
-            .body(Full::new(Bytes::from(body.to_string())))
-            .expect("Failed to build unauthorized response")
+            .into_response()
+    }
+
+    /// Build unauthorized response for a token that failed validation
+    fn invalid_token_response(&self, message: &str) -> Response<Body> {
+        ErrorResponse::new(StatusCode::UNAUTHORIZED, "unauthorized")
+            .detail(message)
+            .header(
+                header::WWW_AUTHENTICATE,
+                bearer_challenge("invalid_token", message, None),
+            )
+            .into_response()
+    }
+
+    /// Build forbidden response for a valid token lacking some of the
+    /// `required` scopes
+    fn insufficient_scope_response(&self, required: &[String], missing: &[&str]) -> Response<Body> {
+        let message = format!("Token lacks required scope: {}", missing.join(" "));
+        let scope = required.join(" ");
+        ErrorResponse::new(StatusCode::FORBIDDEN, "forbidden")
+            .detail(message.clone())
+            .header(
+                header::WWW_AUTHENTICATE,
+                bearer_challenge("insufficient_scope", &message, Some(&scope)),
+            )
+            .into_response()
     }
 }
 
+/// `WWW-Authenticate` value for an RFC 6750 error. Quotes and backslashes
+/// aren't allowed in the attribute values, so they are dropped.
+fn bearer_challenge(error: &str, description: &str, scope: Option<&str>) -> String {
+    let quoted = |value: &str| value.replace(['"', '\\'], "");
+    let mut challenge = format!(
+        "Bearer error=\"{error}\", error_description=\"{}\"",
+        quoted(description)
+    );
+    if let Some(scope) = scope {
+        challenge.push_str(&format!(", scope=\"{}\"", quoted(scope)));
+    }
+    challenge
+}
+
+/// Scopes granted by a token: the space-separated `scope` claim (RFC 8693)
+/// or the `scp`/`scopes` claims, as a string or an array
+fn token_scopes(custom: &Value) -> Vec<&str> {
+    ["scope", "scp", "scopes"]
+        .iter()
+        .filter_map(|claim| custom.get(claim))
+        .flat_map(|value| match value {
+            Value::String(s) => s.split_whitespace().collect(),
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// Whole seconds from now until `exp`, zero once passed
+fn seconds_until(exp: usize) -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    (exp as u64).saturating_sub(now)
+}
+
 impl fmt::Debug for JwtAuth {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("JwtAuth")
@@ -228,11 +384,17 @@ impl fmt::Debug for JwtAuth {
 
 #[async_trait]
 impl Middleware for JwtAuth {
-    async fn call(&self, req: Request<Body>, next: Next) -> CoreResult<Response<Body>> {
-        let path = req.uri().path();
+    async fn call(&self, mut req: Request<Body>, next: Next) -> CoreResult<Response<Body>> {
+        // Only this middleware sets the mapped headers; never pass on a
+        // client's own.
+        for (_, header) in self.claim_headers.iter() {
+            req.headers_mut().remove(header);
+        }
+
+        let path = req.uri().path().to_string();
 
         // Skip authentication for configured paths
-        if self.should_skip(path) {
+        if self.should_skip(&path) {
             return next.run(req).await;
         }
 
@@ -241,7 +403,7 @@ impl Middleware for JwtAuth {
             Some(token) => token,
             None => {
                 tracing::warn!(path = %path, "Missing authentication token");
-                return Ok(self.unauthorized_response("Missing authentication token"));
+                return Ok(self.missing_token_response());
             }
         };
 
@@ -254,21 +416,56 @@ impl Middleware for JwtAuth {
                     "Authentication successful"
                 );
 
-                // TODO: Add claims to request extensions for downstream middleware
-                next.run(req).await
+                // Scopes come from the matched route's `require_scopes`.
+                let required = req
+                    .extensions()
+                    .get::<MatchedRouteAuth>()
+                    .map(|route| route.require_scopes.clone())
+                    .unwrap_or_default();
+                let missing = self.missing_scopes(&required, &token_data.claims);
+                if !missing.is_empty() {
+                    tracing::warn!(
+                        path = %path,
+                        sub = %token_data.claims.sub,
+                        missing = ?missing,
+                        "Token lacks required scopes"
+                    );
+                    return Ok(self.insufficient_scope_response(&required, &missing));
+                }
+
+                let exp = token_data.claims.exp;
+                self.forward_claims(&token_data.claims, req.headers_mut());
+                if self.config.strip_authorization {
+                    req.headers_mut().remove(self.config.header_name.as_str());
+                }
+                req.extensions_mut().insert(token_data.claims);
+
+                let mut response = next.run(req).await?;
+                if self.config.expiry_hint {
+                    response.headers_mut().insert(
+                        HeaderName::from_static(TOKEN_EXPIRES_IN_HEADER),
+                        HeaderValue::from(seconds_until(exp)),
+                    );
+                }
+                Ok(response)
             }
             Err(e) => {
                 tracing::warn!(path = %path, error = %e, "Token validation failed");
+                use jsonwebtoken::errors::ErrorKind;
                 let message = match e.kind() {
-                    jsonwebtoken::errors::ErrorKind::ExpiredSignature => "Token has expired",
-                    jsonwebtoken::errors::ErrorKind::InvalidToken => "Invalid token format",
-                    jsonwebtoken::errors::ErrorKind::InvalidSignature => "Invalid token signature",
-                    jsonwebtoken::errors::ErrorKind::InvalidIssuer => "Invalid token issuer",
-                    jsonwebtoken::errors::ErrorKind::InvalidAudience => "Invalid token audience",
+                    ErrorKind::ExpiredSignature => "Token has expired",
+                    ErrorKind::ImmatureSignature => "Token is not yet valid",
+                    ErrorKind::InvalidToken
+                    | ErrorKind::Base64(_)
+                    | ErrorKind::Json(_)
+                    | ErrorKind::Utf8(_) => "Malformed token",
+                    ErrorKind::InvalidSignature => "Invalid token signature",
+                    ErrorKind::InvalidIssuer => "Invalid token issuer",
+                    ErrorKind::InvalidAudience => "Invalid token audience",
                     _ => "Token validation failed",
                 };
 
-                Ok(self.unauthorized_response(message))
+                Ok(self.invalid_token_response(message))
             }
         }
     }
@@ -354,7 +551,8 @@ mod tests {
 
         let response = next.run(req).await.unwrap();
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
-        assert!(response.headers().contains_key("WWW-Authenticate"));
+        // No error code when no credentials were sent (RFC 6750 §3.1)
+        assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
     }
 
     #[tokio::test]
@@ -395,6 +593,10 @@ mod tests {
 
         let response = next.run(req).await.unwrap();
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()["WWW-Authenticate"],
+            r#"Bearer error="invalid_token", error_description="Token has expired""#
+        );
     }
 
     #[tokio::test]
@@ -429,6 +631,232 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    /// Handler answering with the headers it received
+    #[derive(Debug)]
+    struct EchoHeaders;
+
+    #[async_trait]
+    impl Middleware for EchoHeaders {
+        async fn call(&self, req: Request<Body>, _next: Next) -> CoreResult<Response<Body>> {
+            let mut response = Response::new(Body::from(""));
+            *response.headers_mut() = req.headers().clone();
+            Ok(response)
+        }
+    }
+
+    fn token_with(secret: &str, custom: serde_json::Value) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as usize;
+        let claims = Claims {
+            sub: "user-42".to_string(),
+            exp: now + 3600,
+            iat: Some(now),
+            iss: None,
+            aud: None,
+            custom,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn mapping_config(strip_authorization: bool) -> JwtConfig {
+        JwtConfig {
+            secret: Some("test-secret".to_string()),
+            claim_headers: [
+                ("sub", "X-User-Id"),
+                ("org.id", "X-Org-Id"),
+                ("realm_access.roles", "X-Roles"),
+                ("missing", "X-Missing"),
+            ]
+            .into_iter()
+            .map(|(claim, header)| (claim.to_string(), header.to_string()))
+            .collect(),
+            strip_authorization,
+            ..Default::default()
+        }
+    }
+
+    async fn forwarded(config: JwtConfig, req: Request<Body>) -> http::HeaderMap {
+        let jwt_auth = JwtAuth::with_config(config).unwrap();
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(jwt_auth), Arc::new(EchoHeaders)]);
+        let response = Next::new(stack).run(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response.headers().clone()
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_maps_claims_to_headers() {
+        let token = token_with(
+            "test-secret",
+            serde_json::json!({
+                "org": { "id": 7 },
+                "realm_access": { "roles": ["admin", "billing"] },
+            }),
+        );
+        let req = Request::builder()
+            .uri("/protected")
+            .header("Authorization", format!("Bearer {token}"))
+            // Spoofed by the client
+            .header("X-Missing", "root")
+            .header("X-User-Id", "someone-else")
+            .body(Body::from(""))
+            .unwrap();
+
+        let headers = forwarded(mapping_config(false), req).await;
+        assert_eq!(headers["x-user-id"], "user-42");
+        assert_eq!(headers["x-org-id"], "7");
+        assert_eq!(headers["x-roles"], "admin,billing");
+        assert!(!headers.contains_key("x-missing"));
+        assert!(headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_strips_authorization() {
+        let token = token_with("test-secret", serde_json::json!({}));
+        let req = Request::builder()
+            .uri("/protected")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(""))
+            .unwrap();
+
+        let headers = forwarded(mapping_config(true), req).await;
+        assert_eq!(headers["x-user-id"], "user-42");
+        assert!(!headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_removes_mapped_headers_on_skipped_paths() {
+        let mut config = mapping_config(false);
+        config.skip_paths = vec!["/public/*".to_string()];
+        let req = Request::builder()
+            .uri("/public/docs")
+            .header("X-User-Id", "admin")
+            .body(Body::from(""))
+            .unwrap();
+
+        let headers = forwarded(config, req).await;
+        assert!(!headers.contains_key("x-user-id"));
+    }
+
+    #[test]
+    fn test_jwt_auth_rejects_invalid_header_names() {
+        let mut config = mapping_config(false);
+        config
+            .claim_headers
+            .insert("sub".to_string(), "X User".to_string());
+        assert!(JwtAuth::with_config(config).is_err());
+    }
+
+    fn scoped_request(token: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/protected")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(""))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_malformed_token_challenge() {
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(JwtAuth::new("test-secret")), Arc::new(TestHandler)]);
+        let response = Next::new(stack)
+            .run(scoped_request("not.a.jwt"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()["WWW-Authenticate"],
+            r#"Bearer error="invalid_token", error_description="Malformed token""#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_expiry_hint() {
+        let config = JwtConfig {
+            secret: Some("test-secret".to_string()),
+            expiry_hint: true,
+            ..Default::default()
+        };
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(JwtAuth::with_config(config).unwrap()),
+            Arc::new(TestHandler),
+        ]);
+        let token = create_test_token("test-secret", 3600);
+        let response = Next::new(stack).run(scoped_request(&token)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let expires_in: u64 = response.headers()[TOKEN_EXPIRES_IN_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((3590..=3600).contains(&expires_in), "{expires_in}");
+
+        // Off by default
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(JwtAuth::new("test-secret")), Arc::new(TestHandler)]);
+        let response = Next::new(stack).run(scoped_request(&token)).await.unwrap();
+        assert!(!response.headers().contains_key(TOKEN_EXPIRES_IN_HEADER));
+    }
+
+    /// `scoped_request` on a route requiring `orders:read orders:write`
+    fn orders_request(token: &str) -> Request<Body> {
+        let mut req = scoped_request(token);
+        req.extensions_mut().insert(MatchedRouteAuth {
+            auth_provider: None,
+            skip_auth: false,
+            require_roles: vec![],
+            require_scopes: vec!["orders:read".to_string(), "orders:write".to_string()],
+            authz_rule: None,
+            upstream: "orders".to_string(),
+            metadata: Default::default(),
+        });
+        req
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_route_required_scopes() {
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(JwtAuth::new("test-secret")), Arc::new(TestHandler)]);
+
+        let token = token_with(
+            "test-secret",
+            serde_json::json!({ "scope": "profile orders:read" }),
+        );
+        let response = Next::new(stack.clone())
+            .run(orders_request(&token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers()["WWW-Authenticate"],
+            r#"Bearer error="insufficient_scope", error_description="Token lacks required scope: orders:write", scope="orders:read orders:write""#
+        );
+
+        let token = token_with(
+            "test-secret",
+            serde_json::json!({ "scp": ["orders:read", "orders:write"] }),
+        );
+        let response = Next::new(stack.clone())
+            .run(orders_request(&token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Routes without `require_scopes` accept any valid token.
+        let token = token_with("test-secret", serde_json::json!({ "scope": "profile" }));
+        let response = Next::new(stack).run(scoped_request(&token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn test_jwt_auth_wrong_secret() {
         let jwt_auth = JwtAuth::new("correct-secret");
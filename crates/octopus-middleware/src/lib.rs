@@ -3,10 +3,17 @@
 //! Built-in middleware collection with:
 //! - CORS (Cross-Origin Resource Sharing)
 //! - Compression (gzip, brotli, zstd)
-//! - Request logging
+//! - Request logging, with buffered asynchronous access-log writing
 //! - Rate limiting
 //! - Timeout enforcement
+//! - Concurrency limits with a bounded waiting queue, served by QoS priority
+//! - Debug tap capture of requests carrying a signed debug header
 //! - Request ID injection
+//! - JSON Schema request body validation
+//! - GeoIP blocking and client location (`geoip` feature)
+//! - Multi-tenant resolution (subdomain, path prefix, header or token claim)
+//! - Idempotency keys over a shared state backend (`distributed` feature)
+//! - HMAC request signing with single-use nonces (`distributed` feature)
 
 #![forbid(unsafe_code)]
 #![warn(
@@ -19,76 +26,125 @@
 pub mod audit_logger;
 pub mod auth_gateway;
 pub mod body_transform;
+pub mod body_validation;
 pub mod bot_detection;
 pub mod builder;
 pub mod caching;
 pub mod canary;
 pub mod circuit_breaker;
 pub mod compression;
+pub mod concurrency_limit;
+pub mod conditional;
 pub mod connection_limits;
 pub mod cors;
+pub mod debug_headers;
+pub mod debug_tap;
 pub mod deduplication;
+pub mod experiment;
 pub mod forward_auth;
+pub mod forwarded;
+pub mod geoip;
 pub mod header_transform;
+#[cfg(feature = "distributed")]
+pub mod idempotency;
 pub mod ip_filter;
 pub mod jwt;
+pub mod log_format;
+pub mod log_writer;
 pub mod logging;
+pub mod qos;
 pub mod rate_limit;
 pub mod redirect;
 pub mod request_id;
 pub mod request_limits;
+#[cfg(feature = "distributed")]
+pub mod request_signing;
+pub mod response_validation;
 pub mod retry;
 pub mod security_headers;
+pub mod tenant;
 pub mod timeout;
 pub mod waf;
 
 pub use audit_logger::{
     AuditEvent, AuditEventType, AuditHandler, AuditLogger, AuditLoggerConfig, AuditOutput,
+    AUDIT_LOG_TARGET,
 };
 pub use auth_gateway::{
     AuthGatewayMiddleware, AuthRateLimitKey, MatchedRouteAuth, MatchedRouteCors, ResolvedGateway,
 };
-pub use body_transform::{BodyRule, BodyTransform, BodyTransformConfig};
-pub use bot_detection::{BotDetection, BotDetectionConfig, BotMode};
-pub use builder::MiddlewareBuilder;
+pub use body_transform::{BodyRule, BodyTransform, BodyTransformConfig, MatchedRouteTransform};
+pub use body_validation::{
+    DeclaredResponse, OpenApiSchemas, RequestValidation, RequestValidationConfig, ResolvedSchema,
+    RuleSchema, SchemaResolver, ValidationRule,
+};
+pub use bot_detection::{BotDetection, BotDetectionConfig, BotMode, UaPattern};
+pub use builder::{MiddlewareBuilder, Phase};
 pub use caching::{CacheStore, CachedResponse, Caching, CachingConfig, InMemoryCacheStore};
 pub use canary::{Canary, CanaryConfig, CanaryRule, CanaryUpstream};
 pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
 pub use compression::{Compression, CompressionAlgorithm, CompressionConfig};
+pub use concurrency_limit::{ConcurrencyLimit, ConcurrencyLimiter, ConcurrencyPermit};
+pub use conditional::{PredicateFn, RequestPredicate, When};
 pub use connection_limits::{ConnectionLimits, ConnectionLimitsConfig};
-pub use cors::{Cors, CorsConfig};
+pub use cors::{Cors, CorsConfig, OriginPattern};
+pub use debug_headers::{DebugHeaders, DebugTimingMark};
+pub use debug_tap::{DebugTap, DEBUG_TAP_AUDIENCE};
 pub use deduplication::{Deduplication, DeduplicationConfig};
+pub use experiment::{Experiment, ExperimentVariant, Experiments, ExperimentsConfig};
 pub use forward_auth::{ForwardAuth, ForwardAuthConfig};
-pub use header_transform::{HeaderRules, HeaderTransform, HeaderTransformConfig};
+pub use forwarded::{ClientIp, ForwardedConfig, ForwardedHeaders};
+pub use geoip::ClientGeo;
+pub use header_transform::{
+    HeaderRules, HeaderTransform, HeaderTransformConfig, MatchedRouteHeaders, TemplateRules,
+};
 pub use ip_filter::{IpFilter, IpFilterConfig, IpPattern};
-pub use jwt::{Claims, JwtAuth, JwtConfig};
-pub use logging::{LoggingConfig, RequestLogger};
+pub use jwt::{Claims, JwtAuth, JwtConfig, TOKEN_EXPIRES_IN_HEADER};
+pub use log_format::{
+    AccessLogEvent, AccessLogFormatter, JsonLinesFormatter, LogFormatter, LogfmtFormatter,
+    COMBINED_LOG_FORMAT,
+};
+pub use log_writer::{AccessLogWriter, LogSink, LogWriterConfig, OverflowPolicy, WriterSink};
+pub use logging::{LogFormat, LoggingConfig, RequestLogger, ACCESS_LOG_TARGET};
+pub use qos::QosClassifier;
 pub use rate_limit::{
-    KeyExtractor, MatchedRouteRateLimit, RateLimit, RateLimitConfig, RateLimitStrategy,
-    RouteRateLimit,
+    KeyExtractor, KeyFn, KeyedBuckets, MatchedRouteRateLimit, RateLimit, RateLimitConfig,
+    RateLimitStrategy, RouteRateLimit,
 };
 pub use redirect::{Redirect, RedirectConfig, RedirectRule, TrailingSlash};
-pub use request_id::{IdGenerator, RequestId, RequestIdConfig};
+pub use request_id::{GenerateId, IdGenerator, RequestId, RequestIdConfig, Snowflake};
 pub use request_limits::{RequestLimits, RequestLimitsConfig};
+pub use response_validation::{
+    ResponseValidation, ResponseValidationConfig, SCHEMA_VIOLATION_HEADER,
+};
 pub use retry::{Retry, RetryConfig};
 pub use security_headers::{SecurityHeaders, SecurityHeadersConfig};
+pub use tenant::{ResolvedTenant, TenantResolver};
 pub use timeout::{Timeout, TimeoutConfig};
 pub use waf::{Waf, WafConfig, WafMode, WafRule, WafTarget};
 
+#[cfg(feature = "distributed")]
+pub use idempotency::{Idempotency, IDEMPOTENT_REPLAYED_HEADER};
 #[cfg(feature = "distributed")]
 pub use rate_limit::{DistributedRateLimit, DistributedRateLimitConfig, RouteRateLimiter};
+#[cfg(feature = "distributed")]
+pub use request_signing::RequestSigning;
+
+#[cfg(feature = "geoip")]
+pub use geoip::{GeoIp, GeoIpConfig, GeoIpDatabase};
 
 // Re-export core middleware types from octopus-core
 pub use octopus_core::middleware::{Middleware, Next};
 
 /// Re-export commonly used types
 pub mod prelude {
-    pub use crate::builder::MiddlewareBuilder;
+    pub use crate::builder::{MiddlewareBuilder, Phase};
     pub use crate::compression::{Compression, CompressionAlgorithm, CompressionConfig};
     pub use crate::cors::{Cors, CorsConfig};
-    pub use crate::logging::{LoggingConfig, RequestLogger};
+    pub use crate::log_format::LogFormatter;
+    pub use crate::logging::{LogFormat, LoggingConfig, RequestLogger};
     pub use crate::rate_limit::{KeyExtractor, RateLimit, RateLimitConfig, RateLimitStrategy};
-    pub use crate::request_id::{IdGenerator, RequestId, RequestIdConfig};
+    pub use crate::request_id::{GenerateId, IdGenerator, RequestId, RequestIdConfig, Snowflake};
     pub use crate::timeout::{Timeout, TimeoutConfig};
     pub use octopus_core::middleware::{Middleware, Next};
 }
@@ -0,0 +1,432 @@
+//! Request log formatters
+//!
+//! [`RequestLogger`](crate::RequestLogger) describes each completed request
+//! as an [`AccessLogEvent`] and renders it with a [`LogFormatter`]: JSON
+//! lines, logfmt, or an NGINX-style access log template. Implement
+//! [`LogFormatter`] for any other pipeline format.
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::fmt::{self, Write as _};
+use std::time::Duration;
+
+/// NGINX/Apache "combined" access log format.
+pub const COMBINED_LOG_FORMAT: &str = r#"$remote_addr - - [$time_local] "$request" $status $body_bytes_sent "$http_referer" "$http_user_agent""#;
+
+/// A completed request, as handed to a [`LogFormatter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogEvent {
+    /// When the request completed
+    pub timestamp: DateTime<Utc>,
+    /// `X-Request-Id`, if present
+    pub request_id: Option<String>,
+    /// Client address (first `X-Forwarded-For` hop)
+    pub client_ip: Option<String>,
+    /// HTTP method
+    pub method: String,
+    /// Request URI (path and query)
+    pub uri: String,
+    /// Protocol version, e.g. `HTTP/1.1`
+    pub protocol: String,
+    /// Matched route path pattern
+    pub route: Option<String>,
+    /// Upstream the route targets
+    pub upstream: Option<String>,
+    /// Id of the upstream instance that answered
+    pub upstream_instance: Option<String>,
+    /// `host:port` of the upstream instance that answered
+    pub upstream_addr: Option<String>,
+    /// Time spent on the upstream call, including retries
+    pub upstream_duration: Option<Duration>,
+    /// Upstream attempts made after the first
+    pub retries: u32,
+    /// The response was served from cache
+    pub cache_hit: bool,
+    /// Response status; `None` when the request failed without a response
+    pub status: Option<u16>,
+    /// Response body size in bytes
+    pub bytes: u64,
+    /// Time spent handling the request
+    pub duration: Duration,
+    /// `User-Agent` header
+    pub user_agent: Option<String>,
+    /// `Referer` header
+    pub referer: Option<String>,
+    /// Error message when the request failed
+    pub error: Option<String>,
+}
+
+/// Renders an [`AccessLogEvent`] as one log line.
+pub trait LogFormatter: Send + Sync + fmt::Debug {
+    /// Format `event` (without a trailing newline).
+    fn format(&self, event: &AccessLogEvent) -> String;
+}
+
+/// One JSON object per line; absent fields are omitted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonLinesFormatter;
+
+impl LogFormatter for JsonLinesFormatter {
+    fn format(&self, event: &AccessLogEvent) -> String {
+        let mut map = Map::new();
+        let mut put = |key: &str, value: Value| {
+            map.insert(key.to_string(), value);
+        };
+        put("timestamp", Value::from(event.timestamp.to_rfc3339()));
+        put("method", Value::from(event.method.as_str()));
+        put("uri", Value::from(event.uri.as_str()));
+        put("protocol", Value::from(event.protocol.as_str()));
+        put("bytes", Value::from(event.bytes));
+        put("duration_ms", Value::from(duration_ms(event.duration)));
+        if let Some(status) = event.status {
+            put("status", Value::from(status));
+        }
+        if let Some(upstream) = event.upstream_duration {
+            put("upstream_ms", Value::from(duration_ms(upstream)));
+        }
+        put("retries", Value::from(event.retries));
+        put("cache_hit", Value::from(event.cache_hit));
+        for (key, value) in optional_fields(event) {
+            if let Some(value) = value {
+                put(key, Value::from(value));
+            }
+        }
+        Value::Object(map).to_string()
+    }
+}
+
+/// `key=value` pairs; values containing spaces, quotes or `=` are quoted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogfmtFormatter;
+
+impl LogFormatter for LogfmtFormatter {
+    fn format(&self, event: &AccessLogEvent) -> String {
+        let mut line = String::new();
+        let mut pair = |key: &str, value: &str| {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(key);
+            line.push('=');
+            push_logfmt_value(&mut line, value);
+        };
+        pair("ts", &event.timestamp.to_rfc3339());
+        pair("method", &event.method);
+        pair("uri", &event.uri);
+        pair("proto", &event.protocol);
+        if let Some(status) = event.status {
+            pair("status", &status.to_string());
+        }
+        pair("bytes", &event.bytes.to_string());
+        pair("duration_ms", &duration_ms(event.duration).to_string());
+        if let Some(upstream) = event.upstream_duration {
+            pair("upstream_ms", &duration_ms(upstream).to_string());
+        }
+        pair("retries", &event.retries.to_string());
+        pair("cache_hit", if event.cache_hit { "true" } else { "false" });
+        for (key, value) in optional_fields(event) {
+            if let Some(value) = value {
+                pair(key, value);
+            }
+        }
+        line
+    }
+}
+
+/// Access log rendered from an NGINX-style template of `$variable`s.
+///
+/// Supported variables: `$remote_addr`, `$time_local`, `$time_iso8601`,
+/// `$request`, `$request_method`, `$request_uri`, `$server_protocol`,
+/// `$status`, `$body_bytes_sent`, `$request_time`, `$http_referer`,
+/// `$http_user_agent`, `$request_id`, `$route`, `$upstream`,
+/// `$upstream_addr`, `$upstream_response_time` and `$upstream_cache_status`
+/// (`HIT` for cached responses). Missing values render as `-`; quotes and control characters in values are escaped
+/// as `\xHH` so a field cannot break out of its quotes. Unknown variables
+/// are kept literally.
+#[derive(Debug, Clone)]
+pub struct AccessLogFormatter {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Variable(Variable),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Variable {
+    RemoteAddr,
+    TimeLocal,
+    TimeIso8601,
+    Request,
+    RequestMethod,
+    RequestUri,
+    ServerProtocol,
+    Status,
+    BodyBytesSent,
+    RequestTime,
+    HttpReferer,
+    HttpUserAgent,
+    RequestId,
+    Route,
+    Upstream,
+    UpstreamAddr,
+    UpstreamResponseTime,
+    UpstreamCacheStatus,
+}
+
+impl Variable {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "remote_addr" => Self::RemoteAddr,
+            "time_local" => Self::TimeLocal,
+            "time_iso8601" => Self::TimeIso8601,
+            "request" => Self::Request,
+            "request_method" => Self::RequestMethod,
+            "request_uri" => Self::RequestUri,
+            "server_protocol" => Self::ServerProtocol,
+            "status" => Self::Status,
+            "body_bytes_sent" => Self::BodyBytesSent,
+            "request_time" => Self::RequestTime,
+            "http_referer" => Self::HttpReferer,
+            "http_user_agent" => Self::HttpUserAgent,
+            "request_id" => Self::RequestId,
+            "route" => Self::Route,
+            "upstream" => Self::Upstream,
+            "upstream_addr" => Self::UpstreamAddr,
+            "upstream_response_time" => Self::UpstreamResponseTime,
+            "upstream_cache_status" => Self::UpstreamCacheStatus,
+            _ => return None,
+        })
+    }
+}
+
+impl AccessLogFormatter {
+    /// Compile `template`.
+    pub fn new(template: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(pos) = rest.find('$') {
+            literal.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            match Variable::parse(&after[..len]) {
+                Some(variable) => {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Variable(variable));
+                }
+                None => {
+                    literal.push('$');
+                    literal.push_str(&after[..len]);
+                }
+            }
+            rest = &after[len..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Self { segments }
+    }
+
+    /// The NGINX/Apache combined format.
+    pub fn combined() -> Self {
+        Self::new(COMBINED_LOG_FORMAT)
+    }
+}
+
+impl Default for AccessLogFormatter {
+    fn default() -> Self {
+        Self::combined()
+    }
+}
+
+impl LogFormatter for AccessLogFormatter {
+    fn format(&self, event: &AccessLogEvent) -> String {
+        let mut line = String::new();
+        for segment in &self.segments {
+            let variable = match segment {
+                Segment::Literal(text) => {
+                    line.push_str(text);
+                    continue;
+                }
+                Segment::Variable(variable) => *variable,
+            };
+            let value = match variable {
+                Variable::RemoteAddr => event.client_ip.clone(),
+                Variable::TimeLocal => {
+                    Some(event.timestamp.format("%d/%b/%Y:%H:%M:%S %z").to_string())
+                }
+                Variable::TimeIso8601 => Some(event.timestamp.to_rfc3339()),
+                Variable::Request => {
+                    Some(format!("{} {} {}", event.method, event.uri, event.protocol))
+                }
+                Variable::RequestMethod => Some(event.method.clone()),
+                Variable::RequestUri => Some(event.uri.clone()),
+                Variable::ServerProtocol => Some(event.protocol.clone()),
+                Variable::Status => event.status.map(|s| s.to_string()),
+                Variable::BodyBytesSent => Some(event.bytes.to_string()),
+                Variable::RequestTime => Some(format!("{:.3}", event.duration.as_secs_f64())),
+                Variable::HttpReferer => event.referer.clone(),
+                Variable::HttpUserAgent => event.user_agent.clone(),
+                Variable::RequestId => event.request_id.clone(),
+                Variable::Route => event.route.clone(),
+                Variable::Upstream => event.upstream.clone(),
+                Variable::UpstreamAddr => event.upstream_addr.clone(),
+                Variable::UpstreamResponseTime => event
+                    .upstream_duration
+                    .map(|d| format!("{:.3}", d.as_secs_f64())),
+                Variable::UpstreamCacheStatus => event.cache_hit.then(|| "HIT".to_string()),
+            };
+            match value {
+                Some(value) if !value.is_empty() => push_access_log_value(&mut line, &value),
+                _ => line.push('-'),
+            }
+        }
+        line
+    }
+}
+
+fn optional_fields(event: &AccessLogEvent) -> [(&'static str, Option<&str>); 9] {
+    [
+        ("request_id", event.request_id.as_deref()),
+        ("client_ip", event.client_ip.as_deref()),
+        ("route", event.route.as_deref()),
+        ("upstream", event.upstream.as_deref()),
+        ("upstream_instance", event.upstream_instance.as_deref()),
+        ("upstream_addr", event.upstream_addr.as_deref()),
+        ("user_agent", event.user_agent.as_deref()),
+        ("referer", event.referer.as_deref()),
+        ("error", event.error.as_deref()),
+    ]
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn push_logfmt_value(line: &mut String, value: &str) {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c == ' ' || c == '=' || c == '"' || c.is_control());
+    if !needs_quotes {
+        line.push_str(value);
+        return;
+    }
+    line.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c => line.push(c),
+        }
+    }
+    line.push('"');
+}
+
+fn push_access_log_value(line: &mut String, value: &str) {
+    for c in value.chars() {
+        if c == '"' || c == '\\' || c.is_control() {
+            let _ = write!(line, "\\x{:02X}", u32::from(c));
+        } else {
+            line.push(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn event() -> AccessLogEvent {
+        AccessLogEvent {
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 9, 14, 5, 7).unwrap(),
+            request_id: Some("req-42".to_string()),
+            client_ip: Some("203.0.113.9".to_string()),
+            method: "GET".to_string(),
+            uri: "/api/users?page=2".to_string(),
+            protocol: "HTTP/1.1".to_string(),
+            route: Some("/api/users".to_string()),
+            upstream: Some("users".to_string()),
+            upstream_instance: Some("users-1".to_string()),
+            upstream_addr: Some("10.0.0.5:8080".to_string()),
+            upstream_duration: Some(Duration::from_millis(30)),
+            retries: 1,
+            cache_hit: false,
+            status: Some(200),
+            bytes: 512,
+            duration: Duration::from_millis(37),
+            user_agent: Some(r#"Mozilla/5.0 (X11; "Linux")"#.to_string()),
+            referer: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_json_lines_format() {
+        let line = JsonLinesFormatter.format(&event());
+        let json: Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(json["method"], "GET");
+        assert_eq!(json["uri"], "/api/users?page=2");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["bytes"], 512);
+        assert_eq!(json["duration_ms"], 37);
+        assert_eq!(json["request_id"], "req-42");
+        assert_eq!(json["upstream_instance"], "users-1");
+        assert_eq!(json["upstream_ms"], 30);
+        assert_eq!(json["retries"], 1);
+        assert_eq!(json["cache_hit"], false);
+        assert_eq!(json["user_agent"], r#"Mozilla/5.0 (X11; "Linux")"#);
+        assert!(json.get("referer").is_none());
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn test_logfmt_format_quotes_and_escapes() {
+        let line = LogfmtFormatter.format(&event());
+
+        assert!(line.starts_with("ts=2024-03-09T14:05:07+00:00 method=GET "));
+        assert!(line.contains(r#" uri="/api/users?page=2" "#));
+        assert!(line.contains(" status=200 bytes=512 duration_ms=37 upstream_ms=30 retries=1 "));
+        assert!(line.contains(" upstream_addr=10.0.0.5:8080 "));
+        assert!(line.contains(r#" user_agent="Mozilla/5.0 (X11; \"Linux\")""#));
+        assert!(!line.contains("referer="));
+    }
+
+    #[test]
+    fn test_combined_access_log_format() {
+        let line = AccessLogFormatter::combined().format(&event());
+
+        assert_eq!(
+            line,
+            r#"203.0.113.9 - - [09/Mar/2024:14:05:07 +0000] "GET /api/users?page=2 HTTP/1.1" 200 512 "-" "Mozilla/5.0 (X11; \x22Linux\x22)""#
+        );
+    }
+
+    #[test]
+    fn test_custom_access_log_template() {
+        let formatter = AccessLogFormatter::new(
+            "$request_id $route->$upstream $status $request_time \
+                 $upstream_addr $upstream_response_time $upstream_cache_status $unknown",
+        );
+        let mut event = event();
+        event.status = None;
+
+        assert_eq!(
+            formatter.format(&event),
+            "req-42 /api/users->users - 0.037 10.0.0.5:8080 0.030 - $unknown"
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Buffered, asynchronous access-log writing
+//!
+//! An [`AccessLogWriter`] hands formatted log lines to a background task
+//! through a bounded channel, so request handling never waits on log I/O.
+//! The task collects lines into batches and writes a batch to its
+//! [`LogSink`] once it is full or the flush interval passes. When the channel
+//! is full the [`OverflowPolicy`] decides whether lines are dropped (and
+//! counted) or the request waits for room.
+
+use async_trait::async_trait;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+
+/// What to do with a line when the writer's buffer is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the line and count it in [`AccessLogWriter::dropped`]
+    #[default]
+    Drop,
+    /// Wait until the background task makes room
+    Block,
+}
+
+/// Buffering of an [`AccessLogWriter`]
+#[derive(Debug, Clone)]
+pub struct LogWriterConfig {
+    /// Lines buffered between requests and the background task
+    pub capacity: usize,
+    /// Lines written to the sink at once
+    pub batch_size: usize,
+    /// Longest a line waits in a partial batch
+    pub flush_interval: Duration,
+    /// Behaviour when `capacity` lines are waiting
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for LogWriterConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 8192,
+            batch_size: 256,
+            flush_interval: Duration::from_secs(1),
+            overflow: OverflowPolicy::Drop,
+        }
+    }
+}
+
+/// Destination of batched log lines
+#[async_trait]
+pub trait LogSink: Send + 'static {
+    /// Write one batch of lines, in order
+    async fn write_batch(&mut self, lines: &[String]) -> io::Result<()>;
+}
+
+/// Sink writing newline-terminated lines to an [`AsyncWrite`], e.g. a file
+/// or stdout
+#[derive(Debug)]
+pub struct WriterSink<W> {
+    writer: W,
+    buf: Vec<u8>,
+}
+
+impl<W> WriterSink<W> {
+    /// Create a sink writing to `writer`
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buf: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Unpin + Send + 'static> LogSink for WriterSink<W> {
+    async fn write_batch(&mut self, lines: &[String]) -> io::Result<()> {
+        self.buf.clear();
+        for line in lines {
+            self.buf.extend_from_slice(line.as_bytes());
+            self.buf.push(b'\n');
+        }
+        self.writer.write_all(&self.buf).await?;
+        self.writer.flush().await
+    }
+}
+
+/// Handle queueing lines for a background log-writing task
+///
+/// Clones share the task. It writes what is left and stops once every
+/// handle is dropped.
+#[derive(Debug, Clone)]
+pub struct AccessLogWriter {
+    tx: mpsc::Sender<String>,
+    overflow: OverflowPolicy,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLogWriter {
+    /// Spawn the background task writing to `sink`. Must be called from
+    /// within a Tokio runtime.
+    pub fn spawn<S: LogSink>(sink: S, config: LogWriterConfig) -> Self {
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let overflow = config.overflow;
+        tokio::spawn(run(sink, rx, config));
+        Self {
+            tx,
+            overflow,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Queue `line`, applying the overflow policy if the buffer is full
+    pub async fn write(&self, line: String) {
+        match self.overflow {
+            OverflowPolicy::Drop => {
+                if let Err(mpsc::error::TrySendError::Full(_)) = self.tx.try_send(line) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            OverflowPolicy::Block => {
+                // Only fails once the task is gone, which it never is while
+                // a handle exists.
+                let _ = self.tx.send(line).await;
+            }
+        }
+    }
+
+    /// Count dropped lines in `counter` instead, e.g. one exported as a
+    /// metric
+    pub fn count_drops_in(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.dropped = counter;
+        self
+    }
+
+    /// Lines dropped because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+async fn run<S: LogSink>(mut sink: S, mut rx: mpsc::Receiver<String>, config: LogWriterConfig) {
+    let batch_size = config.batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    // The first tick is one interval out; an immediate one would flush a
+    // partial batch before it had a chance to fill.
+    let mut ticker = tokio::time::interval_at(
+        tokio::time::Instant::now() + config.flush_interval,
+        config.flush_interval,
+    );
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            line = rx.recv() => match line {
+                Some(line) => {
+                    batch.push(line);
+                    if batch.len() >= batch_size {
+                        flush(&mut sink, &mut batch).await;
+                    }
+                }
+                None => break,
+            },
+            _ = ticker.tick() => flush(&mut sink, &mut batch).await,
+        }
+    }
+    flush(&mut sink, &mut batch).await;
+}
+
+async fn flush<S: LogSink>(sink: &mut S, batch: &mut Vec<String>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = sink.write_batch(batch).await {
+        tracing::warn!(error = %e, lines = batch.len(), "Failed to write access log batch");
+    }
+    batch.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Semaphore;
+
+    /// Sink recording its batches, optionally waiting for a permit per batch
+    #[derive(Debug, Clone, Default)]
+    struct Recording {
+        batches: Arc<parking_lot::Mutex<Vec<Vec<String>>>>,
+        gate: Option<Arc<Semaphore>>,
+    }
+
+    impl Recording {
+        fn gated() -> (Self, Arc<Semaphore>) {
+            let gate = Arc::new(Semaphore::new(0));
+            let sink = Self {
+                gate: Some(gate.clone()),
+                ..Self::default()
+            };
+            (sink, gate)
+        }
+
+        fn batches(&self) -> Vec<Vec<String>> {
+            self.batches.lock().clone()
+        }
+    }
+
+    #[async_trait]
+    impl LogSink for Recording {
+        async fn write_batch(&mut self, lines: &[String]) -> io::Result<()> {
+            if let Some(gate) = &self.gate {
+                gate.acquire().await.unwrap().forget();
+            }
+            self.batches.lock().push(lines.to_vec());
+            Ok(())
+        }
+    }
+
+    fn lines(range: std::ops::Range<u32>) -> Vec<String> {
+        range.map(|i| format!("line {i}")).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batches_full_and_flushes_partial_on_interval() {
+        let sink = Recording::default();
+        let writer = AccessLogWriter::spawn(
+            sink.clone(),
+            LogWriterConfig {
+                batch_size: 3,
+                flush_interval: Duration::from_secs(1),
+                ..Default::default()
+            },
+        );
+
+        for line in lines(0..7) {
+            writer.write(line).await;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(sink.batches(), vec![lines(0..3), lines(3..6)]);
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(sink.batches(), vec![lines(0..3), lines(3..6), lines(6..7)]);
+        assert_eq!(writer.dropped(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drop_policy_counts_dropped_lines() {
+        let (sink, gate) = Recording::gated();
+        let counter = Arc::new(AtomicU64::new(0));
+        let writer = AccessLogWriter::spawn(
+            sink.clone(),
+            LogWriterConfig {
+                capacity: 2,
+                batch_size: 1,
+                overflow: OverflowPolicy::Drop,
+                ..Default::default()
+            },
+        )
+        .count_drops_in(Arc::clone(&counter));
+
+        // The first line is taken by the task, which then waits on the sink.
+        writer.write("line 0".to_string()).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        for line in lines(1..6) {
+            writer.write(line).await;
+        }
+        assert_eq!(writer.dropped(), 3);
+        assert_eq!(counter.load(Ordering::Relaxed), 3);
+
+        gate.add_permits(10);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(sink.batches(), vec![lines(0..1), lines(1..2), lines(2..3)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_block_policy_waits_for_room() {
+        let (sink, gate) = Recording::gated();
+        let writer = AccessLogWriter::spawn(
+            sink.clone(),
+            LogWriterConfig {
+                capacity: 1,
+                batch_size: 1,
+                overflow: OverflowPolicy::Block,
+                ..Default::default()
+            },
+        );
+
+        writer.write("line 0".to_string()).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        writer.write("line 1".to_string()).await;
+
+        let blocked = tokio::spawn({
+            let writer = writer.clone();
+            async move { writer.write("line 2".to_string()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!blocked.is_finished());
+
+        gate.add_permits(10);
+        blocked.await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(sink.batches(), vec![lines(0..1), lines(1..2), lines(2..3)]);
+        assert_eq!(writer.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_writer_sink_writes_remaining_lines_on_drop() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let out = tokio::fs::File::create(file.path()).await.unwrap();
+        let writer = AccessLogWriter::spawn(WriterSink::new(out), LogWriterConfig::default());
+
+        writer.write("first".to_string()).await;
+        writer.write("second".to_string()).await;
+        drop(writer);
+
+        let mut content = String::new();
+        for _ in 0..100 {
+            content = tokio::fs::read_to_string(file.path()).await.unwrap();
+            if !content.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(content, "first\nsecond\n");
+    }
+}
@@ -1,17 +1,34 @@
 //! Request/Response logging middleware
-
+//!
+//! Completed requests are logged as structured `tracing` events, or rendered
+//! by a [`LogFormatter`] (JSON lines, logfmt, access log) chosen with
+//! [`LogFormat`]. Rendered lines go through `tracing` on
+//! [`ACCESS_LOG_TARGET`], or to an [`AccessLogWriter`] that writes them in
+//! batches off the request path.
+
+use crate::log_format::{
+    AccessLogEvent, AccessLogFormatter, JsonLinesFormatter, LogFormatter, LogfmtFormatter,
+};
+use crate::log_writer::AccessLogWriter;
 use async_trait::async_trait;
 use bytes::Bytes;
-use http::{Request, Response};
+use http::{HeaderValue, Request, Response};
+use http_body::Body as _;
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_core::request::RouteInfo;
+use octopus_core::{Middleware, Next, Result, UpstreamSelection};
 use std::fmt;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{info, warn, Level};
 
 /// Body type alias
 pub type Body = Full<Bytes>;
 
+/// `tracing` target of every event [`RequestLogger`] emits
+pub const ACCESS_LOG_TARGET: &str = "octopus::access";
+
 /// Logging configuration
 #[derive(Debug, Clone)]
 pub struct LoggingConfig {
@@ -27,6 +44,51 @@ pub struct LoggingConfig {
     pub sensitive_headers: Vec<String>,
     /// Whether to log response status
     pub log_response: bool,
+    /// Requests taking at least this long are always logged, at WARN
+    pub slow_threshold: Option<Duration>,
+    /// Always log failed requests (handler errors and 5xx responses)
+    pub always_log_errors: bool,
+    /// Fraction (0.0-1.0) of the remaining requests to log
+    pub sample_rate: f64,
+    /// Output format of the completion log
+    pub format: LogFormat,
+    /// Add an `X-Upstream` response header naming the upstream instance,
+    /// its latency and retries. A debugging aid: it exposes internal
+    /// addresses, so keep it off in production.
+    pub upstream_header: bool,
+    /// Buffered writer for lines rendered by `format`. Without one they are
+    /// emitted as `tracing` events on [`ACCESS_LOG_TARGET`].
+    pub writer: Option<AccessLogWriter>,
+}
+
+/// Output format of the per-request completion log
+#[derive(Debug, Clone, Default)]
+pub enum LogFormat {
+    /// Structured `tracing` events, one field per attribute
+    #[default]
+    Tracing,
+    /// One JSON object per line
+    JsonLines,
+    /// logfmt `key=value` pairs
+    Logfmt,
+    /// Access log rendered from an NGINX-style `$variable` template, e.g.
+    /// [`COMBINED_LOG_FORMAT`](crate::log_format::COMBINED_LOG_FORMAT)
+    AccessLog(String),
+    /// A user-supplied formatter
+    Custom(Arc<dyn LogFormatter>),
+}
+
+impl LogFormat {
+    /// The formatter rendering completion lines; `None` for `Tracing`.
+    fn formatter(&self) -> Option<Arc<dyn LogFormatter>> {
+        match self {
+            Self::Tracing => None,
+            Self::JsonLines => Some(Arc::new(JsonLinesFormatter)),
+            Self::Logfmt => Some(Arc::new(LogfmtFormatter)),
+            Self::AccessLog(template) => Some(Arc::new(AccessLogFormatter::new(template))),
+            Self::Custom(formatter) => Some(Arc::clone(formatter)),
+        }
+    }
 }
 
 impl Default for LoggingConfig {
@@ -43,17 +105,42 @@ impl Default for LoggingConfig {
                 "x-api-key".to_string(),
             ],
             log_response: true,
+            slow_threshold: None,
+            always_log_errors: true,
+            sample_rate: 1.0,
+            format: LogFormat::Tracing,
+            upstream_header: false,
+            writer: None,
         }
     }
 }
 
+/// Why a completed request is logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogReason {
+    /// Took at least `slow_threshold`
+    Slow,
+    /// Errored or returned 5xx
+    Failed,
+    /// Picked by sampling
+    Sampled,
+}
+
 /// Request/Response logging middleware
 ///
 /// Logs requests and responses with structured logging using tracing.
 /// Supports redacting sensitive headers and body content.
+///
+/// Slow and failed requests are always logged; the rest are sampled at
+/// `sample_rate`. Sampling is deterministic (every `1/rate`-th request), so
+/// the logged fraction is exact rather than probabilistic.
 #[derive(Clone)]
 pub struct RequestLogger {
     config: LoggingConfig,
+    /// Requests seen by the sampler
+    sampled: Arc<AtomicU64>,
+    /// Compiled from `config.format`
+    formatter: Option<Arc<dyn LogFormatter>>,
 }
 
 impl RequestLogger {
@@ -64,7 +151,41 @@ impl RequestLogger {
 
     /// Create a new RequestLogger with custom config
     pub fn with_config(config: LoggingConfig) -> Self {
-        Self { config }
+        Self {
+            formatter: config.format.formatter(),
+            config,
+            sampled: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Whether this request falls in the sample
+    fn sample(&self) -> bool {
+        let rate = self.config.sample_rate;
+        if rate >= 1.0 {
+            return true;
+        }
+        if rate <= 0.0 || rate.is_nan() {
+            return false;
+        }
+        let n = self.sampled.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * rate).floor() > (n * rate).floor()
+    }
+
+    /// Decide whether a completed request is logged, and why
+    fn log_reason(&self, failed: bool, duration: Duration, sampled: bool) -> Option<LogReason> {
+        if self
+            .config
+            .slow_threshold
+            .is_some_and(|threshold| duration >= threshold)
+        {
+            Some(LogReason::Slow)
+        } else if failed && self.config.always_log_errors {
+            Some(LogReason::Failed)
+        } else if sampled {
+            Some(LogReason::Sampled)
+        } else {
+            None
+        }
     }
 
     /// Check if a header should be redacted
@@ -97,6 +218,9 @@ impl fmt::Debug for RequestLogger {
             .field("log_level", &self.config.log_level)
             .field("log_headers", &self.config.log_headers)
             .field("log_body", &self.config.log_body)
+            .field("slow_threshold", &self.config.slow_threshold)
+            .field("sample_rate", &self.config.sample_rate)
+            .field("format", &self.config.format)
             .finish()
     }
 }
@@ -107,88 +231,118 @@ impl Middleware for RequestLogger {
         let method = req.method().clone();
         let uri = req.uri().clone();
         let version = req.version();
-
-        // Log request
-        if self.config.log_headers {
-            let headers: Vec<String> = req
-                .headers()
-                .iter()
-                .map(|(name, value)| {
-                    let value_str = value.to_str().unwrap_or("[invalid UTF-8]");
-                    let redacted = self.redact_value(name.as_str(), value_str);
-                    format!("{name}: {redacted}")
-                })
-                .collect();
-
-            match self.config.log_level {
-                Level::TRACE => tracing::trace!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    headers = ?headers,
-                    "Incoming request"
-                ),
-                Level::DEBUG => tracing::debug!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    headers = ?headers,
-                    "Incoming request"
-                ),
-                Level::INFO => tracing::info!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    headers = ?headers,
-                    "Incoming request"
-                ),
-                Level::WARN => tracing::warn!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    headers = ?headers,
-                    "Incoming request"
-                ),
-                Level::ERROR => tracing::error!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    headers = ?headers,
-                    "Incoming request"
-                ),
-            }
-        } else {
-            match self.config.log_level {
-                Level::TRACE => tracing::trace!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    "Incoming request"
-                ),
-                Level::DEBUG => tracing::debug!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    "Incoming request"
-                ),
-                Level::INFO => tracing::info!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    "Incoming request"
-                ),
-                Level::WARN => tracing::warn!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    "Incoming request"
-                ),
-                Level::ERROR => tracing::error!(
-                    method = %method,
-                    uri = %uri,
-                    version = ?version,
-                    "Incoming request"
-                ),
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let request_id = header("x-request-id");
+        let user_agent = header("user-agent");
+        let referer = header("referer");
+        let client_ip = header("x-forwarded-for")
+            .and_then(|v| v.split(',').next().map(|ip| ip.trim().to_string()));
+        let route = req.extensions().get::<RouteInfo>().map(|r| r.path.clone());
+        let upstream = req
+            .extensions()
+            .get::<crate::MatchedRouteAuth>()
+            .map(|r| r.upstream.clone());
+        let sampled = self.sample();
+
+        // Log request (only sampled ones; slow or failed requests are
+        // logged on completion). Formatted logs are one line per request.
+        if sampled && self.formatter.is_none() {
+            if self.config.log_headers {
+                let headers: Vec<String> = req
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value_str = value.to_str().unwrap_or("[invalid UTF-8]");
+                        let redacted = self.redact_value(name.as_str(), value_str);
+                        format!("{name}: {redacted}")
+                    })
+                    .collect();
+
+                match self.config.log_level {
+                    Level::TRACE => tracing::trace!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        headers = ?headers,
+                        "Incoming request"
+                    ),
+                    Level::DEBUG => tracing::debug!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        headers = ?headers,
+                        "Incoming request"
+                    ),
+                    Level::INFO => tracing::info!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        headers = ?headers,
+                        "Incoming request"
+                    ),
+                    Level::WARN => tracing::warn!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        headers = ?headers,
+                        "Incoming request"
+                    ),
+                    Level::ERROR => tracing::error!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        headers = ?headers,
+                        "Incoming request"
+                    ),
+                }
+            } else {
+                match self.config.log_level {
+                    Level::TRACE => tracing::trace!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        "Incoming request"
+                    ),
+                    Level::DEBUG => tracing::debug!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        "Incoming request"
+                    ),
+                    Level::INFO => tracing::info!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        "Incoming request"
+                    ),
+                    Level::WARN => tracing::warn!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        "Incoming request"
+                    ),
+                    Level::ERROR => tracing::error!(
+                        target: ACCESS_LOG_TARGET,
+                        method = %method,
+                        uri = %uri,
+                        version = ?version,
+                        "Incoming request"
+                    ),
+                }
             }
         }
 
@@ -196,28 +350,148 @@ impl Middleware for RequestLogger {
         let start = Instant::now();
 
         // Call next middleware
-        let response = next.run(req).await;
+        let mut response = next.run(req).await;
 
         // Calculate duration
         let duration = start.elapsed();
 
+        // Which upstream served it, as recorded by the proxy (or cache)
+        let selection = response
+            .as_ref()
+            .ok()
+            .and_then(|r| r.extensions().get::<UpstreamSelection>().cloned());
+        if let (Ok(resp), Some(selection)) = (response.as_mut(), &selection) {
+            if self.config.upstream_header {
+                if let Ok(value) = HeaderValue::from_str(&selection.header_value()) {
+                    resp.headers_mut().insert("x-upstream", value);
+                }
+            }
+        }
+        let upstream = match &selection {
+            Some(s) if !s.upstream.is_empty() => Some(s.upstream.clone()),
+            _ => upstream,
+        };
+        // Instance details only when the upstream actually answered
+        let proxied = selection.as_ref().filter(|s| !s.cache_hit);
+        let retries = selection.as_ref().map_or(0, |s| s.retries);
+        let cache_hit = selection.as_ref().is_some_and(|s| s.cache_hit);
+
         // Log response
-        match &response {
-            Ok(resp) => {
-                if self.config.log_response {
-                    info!(
+        let failed = response
+            .as_ref()
+            .map_or(true, |resp| resp.status().is_server_error());
+        let Some(reason) = self.log_reason(failed, duration, sampled) else {
+            return response;
+        };
+        if reason == LogReason::Sampled && !self.config.log_response {
+            return response;
+        }
+
+        if let Some(formatter) = &self.formatter {
+            let event = AccessLogEvent {
+                timestamp: chrono::Utc::now(),
+                request_id,
+                client_ip,
+                method: method.to_string(),
+                uri: uri.to_string(),
+                protocol: format!("{version:?}"),
+                route,
+                upstream,
+                upstream_instance: proxied.map(|s| s.instance_id.clone()),
+                upstream_addr: proxied.map(|s| s.address.clone()),
+                upstream_duration: proxied.map(|s| s.latency),
+                retries,
+                cache_hit,
+                status: response.as_ref().ok().map(|r| r.status().as_u16()),
+                bytes: response
+                    .as_ref()
+                    .ok()
+                    .and_then(|r| r.body().size_hint().exact())
+                    .unwrap_or(0),
+                duration,
+                user_agent,
+                referer,
+                error: response.as_ref().err().map(ToString::to_string),
+            };
+            let line = formatter.format(&event);
+            if let Some(writer) = &self.config.writer {
+                writer.write(line).await;
+            } else if reason == LogReason::Sampled {
+                info!(target: ACCESS_LOG_TARGET, "{line}");
+            } else {
+                warn!(target: ACCESS_LOG_TARGET, "{line}");
+            }
+            return response;
+        }
+
+        let request_id = request_id.as_deref().unwrap_or("-");
+        let route = route.as_deref().unwrap_or("-");
+        let upstream = upstream.as_deref().unwrap_or("-");
+        let upstream_addr = proxied.map_or("-", |s| s.address.as_str());
+        let upstream_ms = proxied.map(|s| s.latency.as_millis());
+        match (&response, reason) {
+            (Ok(resp), reason) => {
+                let status = resp.status().as_u16();
+                let bytes = resp.body().size_hint().exact().unwrap_or(0);
+                match reason {
+                    LogReason::Slow => warn!(
+                        target: ACCESS_LOG_TARGET,
+                        request_id = %request_id,
+                        method = %method,
+                        uri = %uri,
+                        route = %route,
+                        upstream = %upstream,
+                        upstream_addr = %upstream_addr,
+                        upstream_ms,
+                        retries,
+                        cache_hit,
+                        status,
+                        bytes,
+                        duration_ms = duration.as_millis(),
+                        "Slow request"
+                    ),
+                    LogReason::Failed => warn!(
+                        target: ACCESS_LOG_TARGET,
+                        request_id = %request_id,
+                        method = %method,
+                        uri = %uri,
+                        route = %route,
+                        upstream = %upstream,
+                        upstream_addr = %upstream_addr,
+                        upstream_ms,
+                        retries,
+                        cache_hit,
+                        status,
+                        bytes,
+                        duration_ms = duration.as_millis(),
+                        "Request completed with server error"
+                    ),
+                    LogReason::Sampled => info!(
+                        target: ACCESS_LOG_TARGET,
+                        request_id = %request_id,
                         method = %method,
                         uri = %uri,
-                        status = resp.status().as_u16(),
+                        route = %route,
+                        upstream = %upstream,
+                        upstream_addr = %upstream_addr,
+                        upstream_ms,
+                        retries,
+                        cache_hit,
+                        status,
+                        bytes,
                         duration_ms = duration.as_millis(),
                         "Request completed"
-                    );
+                    ),
                 }
             }
-            Err(e) => {
+            (Err(e), _) => {
                 warn!(
+                    target: ACCESS_LOG_TARGET,
+                    request_id = %request_id,
                     method = %method,
                     uri = %uri,
+                    route = %route,
+                    upstream = %upstream,
                     error = %e,
                     duration_ms = duration.as_millis(),
                     "Request failed"
@@ -298,6 +572,7 @@ mod tests {
             max_body_size: 1024,
             sensitive_headers: vec!["X-Custom-Token".to_string()],
             log_response: true,
+            ..Default::default()
         };
 
         let logger = RequestLogger::with_config(config.clone());
@@ -351,4 +626,217 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    fn sampled_logger(sample_rate: f64) -> RequestLogger {
+        RequestLogger::with_config(LoggingConfig {
+            slow_threshold: Some(Duration::from_millis(500)),
+            sample_rate,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_slow_request_always_logged() {
+        let logger = sampled_logger(0.0);
+
+        assert_eq!(
+            logger.log_reason(false, Duration::from_millis(750), logger.sample()),
+            Some(LogReason::Slow)
+        );
+        assert_eq!(
+            logger.log_reason(false, Duration::from_millis(20), logger.sample()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_error_always_logged() {
+        let logger = sampled_logger(0.0);
+        assert_eq!(
+            logger.log_reason(true, Duration::from_millis(20), logger.sample()),
+            Some(LogReason::Failed)
+        );
+
+        let logger = RequestLogger::with_config(LoggingConfig {
+            always_log_errors: false,
+            sample_rate: 0.0,
+            ..Default::default()
+        });
+        assert_eq!(
+            logger.log_reason(true, Duration::from_millis(20), logger.sample()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fast_successes_sampled_at_rate() {
+        let logger = sampled_logger(0.1);
+        let logged = (0..1000)
+            .filter(|_| {
+                logger
+                    .log_reason(false, Duration::from_millis(5), logger.sample())
+                    .is_some()
+            })
+            .count();
+        assert_eq!(logged, 100);
+
+        let logger = sampled_logger(1.0);
+        assert!((0..100).all(|_| logger.sample()));
+    }
+
+    #[tokio::test]
+    async fn test_sampled_out_request_still_served() {
+        let logger = sampled_logger(0.0);
+        let handler = TestHandler {
+            status: StatusCode::OK,
+        };
+        let stack: std::sync::Arc<[std::sync::Arc<dyn Middleware>]> =
+            std::sync::Arc::new([std::sync::Arc::new(logger), std::sync::Arc::new(handler)]);
+
+        let req = Request::builder()
+            .uri("/test")
+            .header("x-request-id", "req-1")
+            .body(Body::from(""))
+            .unwrap();
+        let response = Next::new(stack).run(req).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// Formatter recording the events it renders.
+    #[derive(Debug, Default)]
+    struct Capture(parking_lot::Mutex<Vec<AccessLogEvent>>);
+
+    impl LogFormatter for Capture {
+        fn format(&self, event: &AccessLogEvent) -> String {
+            self.0.lock().push(event.clone());
+            String::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_formatter_receives_request_event() {
+        let capture = Arc::new(Capture::default());
+        let logger = RequestLogger::with_config(LoggingConfig {
+            format: LogFormat::Custom(capture.clone()),
+            ..Default::default()
+        });
+        let handler = TestHandler {
+            status: StatusCode::CREATED,
+        };
+        let stack: std::sync::Arc<[std::sync::Arc<dyn Middleware>]> =
+            std::sync::Arc::new([std::sync::Arc::new(logger), std::sync::Arc::new(handler)]);
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/orders?draft=1")
+            .header("x-request-id", "req-7")
+            .header("user-agent", "curl/8.5.0")
+            .header("x-forwarded-for", "203.0.113.9, 10.0.0.1")
+            .body(Body::from(""))
+            .unwrap();
+        Next::new(stack).run(req).await.unwrap();
+
+        let events = capture.0.lock();
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.method, "POST");
+        assert_eq!(event.uri, "/orders?draft=1");
+        assert_eq!(event.status, Some(201));
+        assert_eq!(event.bytes, "test response".len() as u64);
+        assert_eq!(event.request_id.as_deref(), Some("req-7"));
+        assert_eq!(event.user_agent.as_deref(), Some("curl/8.5.0"));
+        assert_eq!(event.client_ip.as_deref(), Some("203.0.113.9"));
+    }
+
+    /// Answers like the proxy does, recording the instance it used.
+    #[derive(Debug)]
+    struct ProxiedHandler;
+
+    #[async_trait]
+    impl Middleware for ProxiedHandler {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            let instance = octopus_core::UpstreamInstance::new("users-1", "10.0.0.5", 8080);
+            let mut selection = UpstreamSelection::new(&instance, Duration::from_millis(12), 1);
+            selection.upstream = "users".to_string();
+            let mut res = Response::new(Full::new(Bytes::from("ok")));
+            res.extensions_mut().insert(selection);
+            Ok(res)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upstream_selection_in_log_fields_and_debug_header() {
+        let capture = Arc::new(Capture::default());
+        let logger = RequestLogger::with_config(LoggingConfig {
+            format: LogFormat::Custom(capture.clone()),
+            upstream_header: true,
+            ..Default::default()
+        });
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(logger), Arc::new(ProxiedHandler)]);
+
+        let req = Request::builder()
+            .uri("/users/7")
+            .body(Body::from(""))
+            .unwrap();
+        let res = Next::new(stack).run(req).await.unwrap();
+
+        assert_eq!(
+            res.headers()["x-upstream"],
+            "users; instance=users-1; addr=10.0.0.5:8080; upstream_ms=12; retries=1"
+        );
+        let events = capture.0.lock();
+        let event = &events[0];
+        assert_eq!(event.upstream.as_deref(), Some("users"));
+        assert_eq!(event.upstream_instance.as_deref(), Some("users-1"));
+        assert_eq!(event.upstream_addr.as_deref(), Some("10.0.0.5:8080"));
+        assert_eq!(event.upstream_duration, Some(Duration::from_millis(12)));
+        assert_eq!(event.retries, 1);
+        assert!(!event.cache_hit);
+    }
+
+    /// Sink collecting written lines
+    #[derive(Debug, Clone, Default)]
+    struct Lines(Arc<parking_lot::Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl crate::log_writer::LogSink for Lines {
+        async fn write_batch(&mut self, lines: &[String]) -> std::io::Result<()> {
+            self.0.lock().extend_from_slice(lines);
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_formatted_lines_go_to_writer() {
+        let sink = Lines::default();
+        let writer = AccessLogWriter::spawn(sink.clone(), Default::default());
+        let logger = RequestLogger::with_config(LoggingConfig {
+            format: LogFormat::AccessLog("$request_method $request_uri $status".to_string()),
+            writer: Some(writer),
+            ..Default::default()
+        });
+        let handler = TestHandler {
+            status: StatusCode::OK,
+        };
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([Arc::new(logger), Arc::new(handler)]);
+
+        let req = Request::builder().uri("/a").body(Body::from("")).unwrap();
+        Next::new(stack).run(req).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        assert_eq!(*sink.0.lock(), vec!["GET /a 200".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_header_is_off_by_default() {
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(RequestLogger::new()), Arc::new(ProxiedHandler)]);
+
+        let req = Request::builder().uri("/").body(Body::from("")).unwrap();
+        let res = Next::new(stack).run(req).await.unwrap();
+
+        assert!(res.headers().get("x-upstream").is_none());
+    }
 }
@@ -0,0 +1,175 @@
+//! Request classification for QoS scheduling
+//!
+//! [`QosClassifier`] gives each request a [`RequestPriority`] from the
+//! `gateway.qos` rules: by path prefix, header, tenant or the roles of the
+//! authenticated principal. The [`ConcurrencyLimit`](crate::ConcurrencyLimit)
+//! uses it to decide, while saturated, which waiting requests are admitted
+//! first and which are shed.
+
+use crate::tenant::ResolvedTenant;
+use http::Request;
+use octopus_config::types::{QosConfig, QosRule, RequestPriority};
+use octopus_core::AuthContext;
+
+/// Assigns priorities to requests
+#[derive(Debug, Clone)]
+pub struct QosClassifier {
+    config: QosConfig,
+}
+
+impl QosClassifier {
+    /// Create a classifier from the `gateway.qos` section
+    pub fn new(config: QosConfig) -> Self {
+        Self { config }
+    }
+
+    /// Priority of `req`: that of the first matching rule, else the default
+    pub fn classify<B>(&self, req: &Request<B>) -> RequestPriority {
+        self.config
+            .rules
+            .iter()
+            .find(|rule| matches(rule, req))
+            .map_or(self.config.default_priority, |rule| rule.priority)
+    }
+
+    /// Whether requests of `priority` are shed rather than queued while a
+    /// limit is saturated
+    pub fn sheds(&self, priority: RequestPriority) -> bool {
+        priority < self.config.shed_below
+    }
+
+    /// Whether any rule looks at the authenticated principal, so requests
+    /// must be classified after authentication
+    pub fn needs_auth(&self) -> bool {
+        self.config.rules.iter().any(|rule| rule.role.is_some())
+    }
+}
+
+fn matches<B>(rule: &QosRule, req: &Request<B>) -> bool {
+    if let Some(prefix) = &rule.path_prefix {
+        if !req.uri().path().starts_with(prefix.as_str()) {
+            return false;
+        }
+    }
+    if let Some(name) = &rule.header {
+        let Some(value) = req.headers().get(name.as_str()) else {
+            return false;
+        };
+        if let Some(expected) = &rule.header_value {
+            if value.as_bytes() != expected.as_bytes() {
+                return false;
+            }
+        }
+    }
+    if let Some(tenant) = &rule.tenant {
+        let resolved = req.extensions().get::<ResolvedTenant>();
+        if !resolved.is_some_and(|t| t.id.eq_ignore_ascii_case(tenant)) {
+            return false;
+        }
+    }
+    if let Some(role) = &rule.role {
+        let auth = req.extensions().get::<AuthContext>();
+        if !auth.is_some_and(|a| a.roles.iter().any(|r| r == role)) {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(priority: RequestPriority) -> QosRule {
+        QosRule {
+            priority,
+            path_prefix: None,
+            header: None,
+            header_value: None,
+            tenant: None,
+            role: None,
+        }
+    }
+
+    fn classifier() -> QosClassifier {
+        QosClassifier::new(QosConfig {
+            default_priority: RequestPriority::Normal,
+            shed_below: RequestPriority::Normal,
+            rules: vec![
+                QosRule {
+                    role: Some("paid".to_string()),
+                    ..rule(RequestPriority::High)
+                },
+                QosRule {
+                    header: Some("X-Tier".to_string()),
+                    header_value: Some("batch".to_string()),
+                    ..rule(RequestPriority::Low)
+                },
+                QosRule {
+                    tenant: Some("acme".to_string()),
+                    path_prefix: Some("/checkout".to_string()),
+                    ..rule(RequestPriority::Critical)
+                },
+            ],
+        })
+    }
+
+    fn request(path: &str) -> Request<()> {
+        Request::builder().uri(path).body(()).unwrap()
+    }
+
+    #[test]
+    fn first_matching_rule_sets_the_priority() {
+        let qos = classifier();
+        assert_eq!(qos.classify(&request("/orders")), RequestPriority::Normal);
+
+        let mut req = request("/orders");
+        req.extensions_mut().insert(AuthContext {
+            subject: "u1".to_string(),
+            provider: "jwt".to_string(),
+            roles: vec!["paid".to_string()],
+            scopes: Vec::new(),
+            claims: Default::default(),
+        });
+        req.headers_mut().insert("x-tier", "batch".parse().unwrap());
+        assert_eq!(qos.classify(&req), RequestPriority::High);
+
+        let mut req = request("/orders");
+        req.headers_mut().insert("x-tier", "batch".parse().unwrap());
+        assert_eq!(qos.classify(&req), RequestPriority::Low);
+        req.headers_mut()
+            .insert("x-tier", "interactive".parse().unwrap());
+        assert_eq!(qos.classify(&req), RequestPriority::Normal);
+    }
+
+    #[test]
+    fn every_condition_of_a_rule_must_hold() {
+        let qos = classifier();
+        let tenant = |req: &mut Request<()>| {
+            req.extensions_mut().insert(ResolvedTenant {
+                id: "acme".to_string(),
+                upstream: None,
+            });
+        };
+
+        let mut req = request("/checkout/cart");
+        tenant(&mut req);
+        assert_eq!(qos.classify(&req), RequestPriority::Critical);
+
+        let mut req = request("/orders");
+        tenant(&mut req);
+        assert_eq!(qos.classify(&req), RequestPriority::Normal);
+        assert_eq!(
+            qos.classify(&request("/checkout/cart")),
+            RequestPriority::Normal
+        );
+    }
+
+    #[test]
+    fn priorities_below_the_threshold_are_shed() {
+        let qos = classifier();
+        assert!(qos.sheds(RequestPriority::Low));
+        assert!(!qos.sheds(RequestPriority::Normal));
+        assert!(qos.needs_auth());
+    }
+}
@@ -9,35 +9,46 @@ use governor::{
     state::{InMemoryState, NotKeyed},
     Quota, RateLimiter as GovernorRateLimiter,
 };
-use http::{Request, Response, StatusCode};
+use http::{header, HeaderName, Request, Response, StatusCode};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
-use serde_json;
+use octopus_core::{
+    ErrorResponse, Middleware, Next, RateLimitBucket, RateLimitExemptions, RateLimitKeys, Result,
+};
 use std::collections::HashMap;
 use std::fmt;
 use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Body type alias
 pub type Body = Full<Bytes>;
 
+/// Request limit per window (the sustained rate with burst tiers).
+const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+
+/// Requests allowed back-to-back, with burst tiers.
+const X_RATELIMIT_BURST: HeaderName = HeaderName::from_static("x-ratelimit-burst");
+
 /// A shared, unkeyed in-memory governor rate limiter.
 type SharedLimiter = Arc<GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>;
 
-/// A map of keys (path or identity) to their dedicated limiters.
+/// A map of route paths to their dedicated limiters.
 type KeyedLimiters = Arc<DashMap<String, SharedLimiter>>;
 
 /// Per-route rate-limit hint attached to a request after route matching.
 ///
 /// The runtime inserts this (from `routes[].rate_limit`) once a route is matched,
 /// so the route-aware rate limiter can enforce a per-route window without
-/// re-matching. The `key` is the route's path pattern, giving a route-wide cap
-/// regardless of the concrete request path (so wildcard routes share one window).
+/// re-matching. The `key` is the route's path pattern, so a client's requests
+/// to any concrete path of a wildcard route share one window.
 #[derive(Debug, Clone)]
 pub struct MatchedRouteRateLimit {
     /// Stable key identifying the route (its path pattern).
     pub key: String,
+    /// The client the window is kept for (its address behind any trusted
+    /// proxies).
+    pub client: String,
     /// Maximum requests allowed per window for this route.
     pub requests_per_window: u32,
     /// Window duration.
@@ -54,7 +65,11 @@ pub enum RateLimitStrategy {
 }
 
 /// Key extraction strategy
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Each distinct key gets its own bucket. Dimensions can be combined into one
+/// key with [`KeyExtractor::and`] (e.g. IP + path), and [`KeyExtractor::custom`]
+/// derives the key with arbitrary logic (e.g. a tenant from a header).
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KeyExtractor {
     /// Extract key from IP address
     Ip,
@@ -66,6 +81,92 @@ pub enum KeyExtractor {
     Global,
     /// Per-identity rate limit (uses AuthRateLimitKey from request extensions)
     Identity,
+    /// All parts' keys joined into one bucket key
+    Composite(Vec<KeyExtractor>),
+    /// Key computed by a custom function
+    Custom(KeyFn),
+}
+
+/// A request → bucket key function.
+type KeyFnInner = dyn Fn(&Request<Body>) -> String + Send + Sync;
+
+/// Custom key function for [`KeyExtractor::Custom`]
+#[derive(Clone)]
+pub struct KeyFn(Arc<KeyFnInner>);
+
+impl KeyFn {
+    /// Wrap a key function
+    pub fn new(f: impl Fn(&Request<Body>) -> String + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for KeyFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KeyFn(..)")
+    }
+}
+
+/// Two key functions are equal only if they are the same function instance.
+impl PartialEq for KeyFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for KeyFn {}
+
+impl KeyExtractor {
+    /// A custom extractor computing the key with `f`
+    pub fn custom(f: impl Fn(&Request<Body>) -> String + Send + Sync + 'static) -> Self {
+        Self::Custom(KeyFn::new(f))
+    }
+
+    /// Combine with `other` into one composite key (`Ip.and(Path)` buckets
+    /// each IP separately per path)
+    pub fn and(self, other: KeyExtractor) -> Self {
+        let mut parts = match self {
+            Self::Composite(parts) => parts,
+            single => vec![single],
+        };
+        match other {
+            Self::Composite(more) => parts.extend(more),
+            single => parts.push(single),
+        }
+        Self::Composite(parts)
+    }
+
+    /// The bucket key for `req`; `header_name` is used by [`KeyExtractor::Header`]
+    pub fn key(&self, req: &Request<Body>, header_name: Option<&str>) -> String {
+        match self {
+            Self::Ip => {
+                // Try X-Forwarded-For first, fall back to "unknown"
+                req.headers()
+                    .get("x-forwarded-for")
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.split(',').next().unwrap_or("unknown").trim().to_string())
+                    .unwrap_or_else(|| "unknown".to_string())
+            }
+            Self::Header => header_name
+                .and_then(|name| req.headers().get(name))
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("unknown")
+                .to_string(),
+            Self::Identity => req
+                .extensions()
+                .get::<AuthRateLimitKey>()
+                .map(|k| k.0.clone())
+                .unwrap_or_else(|| "anonymous".to_string()),
+            Self::Path => req.uri().path().to_string(),
+            Self::Global => "global".to_string(),
+            Self::Composite(parts) => parts
+                .iter()
+                .map(|part| part.key(req, header_name))
+                .collect::<Vec<_>>()
+                .join("|"),
+            Self::Custom(f) => (f.0)(req),
+        }
+    }
 }
 
 /// Rate limiting configuration
@@ -85,6 +186,13 @@ pub struct RateLimitConfig {
     pub error_message: Option<String>,
     /// Per-route rate limits (path -> config)
     pub per_route_limits: Option<HashMap<String, RouteRateLimit>>,
+    /// Sustained rate: requests per `window_size` once the burst is spent.
+    /// When set, tokens refill evenly (one every `window_size /
+    /// sustained_per_window`) and this replaces `requests_per_window`.
+    pub sustained_per_window: Option<u32>,
+    /// Requests allowed back-to-back before throttling to the sustained rate
+    /// (defaults to `sustained_per_window`)
+    pub burst: Option<u32>,
 }
 
 /// Per-route rate limit configuration
@@ -108,8 +216,43 @@ impl Default for RateLimitConfig {
             header_name: None,
             error_message: None,
             per_route_limits: None,
+            sustained_per_window: None,
+            burst: None,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// The quota for the global and per-key limiters
+    fn quota(&self) -> Quota {
+        match self.sustained_per_window {
+            Some(sustained) => {
+                let sustained = NonZeroU32::new(sustained).unwrap_or(NonZeroU32::MIN);
+                let burst = self.burst.and_then(NonZeroU32::new).unwrap_or(sustained);
+                let period = (self.window_size / sustained.get()).max(Duration::from_nanos(1));
+                Quota::with_period(period).unwrap().allow_burst(burst)
+            }
+            None => window_quota(self.requests_per_window, self.window_size),
         }
     }
+
+    /// The limit reported in `X-RateLimit-Limit`
+    fn reported_limit(&self) -> u32 {
+        self.sustained_per_window
+            .unwrap_or(self.requests_per_window)
+    }
+
+    /// The burst reported in `X-RateLimit-Burst`, with sustained tiers only
+    fn reported_burst(&self) -> Option<u32> {
+        self.sustained_per_window
+            .map(|sustained| self.burst.unwrap_or(sustained))
+    }
+}
+
+/// `requests` tokens, refilled one per `window`
+pub(crate) fn window_quota(requests: u32, window: Duration) -> Quota {
+    let requests = NonZeroU32::new(requests).unwrap_or(NonZeroU32::MIN);
+    Quota::with_period(window).unwrap().allow_burst(requests)
 }
 
 impl RouteRateLimit {
@@ -142,7 +285,8 @@ impl RouteRateLimit {
 /// Rate limiting middleware
 ///
 /// Limits the rate of requests using a token bucket algorithm.
-/// Can rate limit globally, per IP, per API key, per path, or per identity.
+/// Can rate limit globally, per IP, per API key, per path, per identity, or
+/// per composite/custom key (see [`KeyExtractor`]).
 /// Supports per-route rate limits with different limits for different endpoints.
 #[derive(Clone)]
 pub struct RateLimit {
@@ -151,8 +295,8 @@ pub struct RateLimit {
     limiter: SharedLimiter,
     /// Per-route rate limiters (path -> limiter)
     route_limiters: KeyedLimiters,
-    /// Per-identity rate limiters (identity key -> limiter)
-    identity_limiters: KeyedLimiters,
+    /// Per-key buckets (extracted key -> buckets)
+    keys: Arc<KeyedBuckets>,
 }
 
 impl RateLimit {
@@ -163,26 +307,14 @@ impl RateLimit {
 
     /// Create a new RateLimit middleware with custom config
     pub fn with_config(config: RateLimitConfig) -> Self {
-        // Create quota from config
-        let requests = NonZeroU32::new(config.requests_per_window)
-            .unwrap_or_else(|| NonZeroU32::new(1).unwrap());
-
-        let quota = Quota::with_period(config.window_size)
-            .unwrap()
-            .allow_burst(requests);
-
-        let limiter = Arc::new(GovernorRateLimiter::direct(quota));
+        let limiter = Arc::new(GovernorRateLimiter::direct(config.quota()));
 
         // Create per-route limiters if configured
         let route_limiters = Arc::new(DashMap::new());
         if let Some(ref per_route) = config.per_route_limits {
             for (path, route_config) in per_route {
-                let route_requests = NonZeroU32::new(route_config.requests_per_window)
-                    .unwrap_or_else(|| NonZeroU32::new(1).unwrap());
-
-                let route_quota = Quota::with_period(route_config.window_size)
-                    .unwrap()
-                    .allow_burst(route_requests);
+                let route_quota =
+                    window_quota(route_config.requests_per_window, route_config.window_size);
 
                 let route_limiter = Arc::new(GovernorRateLimiter::direct(route_quota));
                 route_limiters.insert(path.clone(), route_limiter);
@@ -193,10 +325,16 @@ impl RateLimit {
             config,
             limiter,
             route_limiters,
-            identity_limiters: Arc::new(DashMap::new()),
+            keys: Arc::default(),
         }
     }
 
+    /// The per-key buckets, for inspecting and overriding a client's limit
+    /// (see [`RateLimitKeys`]). Empty unless a keyed extractor is configured.
+    pub fn keys(&self) -> Arc<KeyedBuckets> {
+        Arc::clone(&self.keys)
+    }
+
     /// Create a rate limiter with specific requests per second
     pub fn per_second(requests: u32) -> Self {
         let config = RateLimitConfig {
@@ -227,25 +365,13 @@ impl RateLimit {
             .or(self.config.error_message.as_deref())
             .unwrap_or("Rate limit exceeded");
 
-        Response::builder()
-            .status(StatusCode::TOO_MANY_REQUESTS)
-            .header("Content-Type", "application/json")
-            .header("Retry-After", window_size.as_secs().to_string())
-            .header(
-                "X-RateLimit-Limit",
-                self.config.requests_per_window.to_string(),
-            )
-            .header("X-RateLimit-Remaining", "0")
-            .header("X-RateLimit-Reset", window_size.as_secs().to_string())
-            .body(Full::new(Bytes::from(
-                serde_json::json!({
-                    "error": "rate_limit_exceeded",
-                    "message": message,
-                    "retry_after": window_size.as_secs()
-                })
-                .to_string(),
-            )))
-            .expect("Failed to build rate limit response")
+        let mut response = too_many_requests(window_size, self.config.reported_limit(), message);
+        if let Some(burst) = self.config.reported_burst() {
+            response
+                .headers_mut()
+                .insert(X_RATELIMIT_BURST, burst.into());
+        }
+        response
     }
 
     /// Get the appropriate rate limiter for a request
@@ -296,57 +422,194 @@ impl Middleware for RateLimit {
         // Get the appropriate limiter for this request
         let (limiter, window_size, custom_message) = self.get_limiter_for_request(&path);
 
-        // For identity-based rate limiting, use per-identity limiter if available
-        let effective_limiter = if self.config.key_extractor == KeyExtractor::Identity {
-            if let Some(auth_key) = req.extensions().get::<AuthRateLimitKey>() {
-                let key = &auth_key.0;
-                self.identity_limiters
-                    .entry(key.clone())
-                    .or_insert_with(|| {
-                        let requests = NonZeroU32::new(self.config.requests_per_window)
-                            .unwrap_or_else(|| NonZeroU32::new(1).unwrap());
-                        let quota = Quota::with_period(self.config.window_size)
-                            .unwrap()
-                            .allow_burst(requests);
-                        Arc::new(GovernorRateLimiter::direct(quota))
-                    })
-                    .clone()
-            } else {
-                // No identity key — fall back to global limiter
-                limiter
+        // Keyed extractors get a limiter per key; an identity extractor without
+        // an identity key falls back to the global limiter.
+        let request_key = match self.config.key_extractor {
+            KeyExtractor::Global => None,
+            KeyExtractor::Identity if req.extensions().get::<AuthRateLimitKey>().is_none() => None,
+            ref extractor => Some(extractor.key(&req, self.config.header_name.as_deref())),
+        };
+        let allowed = match &request_key {
+            Some(key) => {
+                // A route with its own limit gets a bucket per key within it.
+                let route = self
+                    .config
+                    .per_route_limits
+                    .as_ref()
+                    .and_then(|limits| limits.get(&path));
+                match route {
+                    Some(route) => self.keys.check(
+                        key,
+                        Some(&path),
+                        window_quota(route.requests_per_window, route.window_size),
+                    ),
+                    None => self.keys.check(key, None, self.config.quota()),
+                }
             }
-        } else {
-            limiter
+            None => limiter.check().is_ok(),
         };
 
         // Check rate limit
-        match effective_limiter.check() {
-            Ok(_) => {
-                // Request allowed, proceed
-                next.run(req).await
-            }
-            Err(_) => {
-                // Rate limit exceeded
-                let identity_info = if self.config.key_extractor == KeyExtractor::Identity {
-                    req.extensions()
-                        .get::<AuthRateLimitKey>()
-                        .map(|k| k.0.clone())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
-                };
-                tracing::warn!(
-                    uri = %req.uri(),
-                    path = %path,
-                    identity = %identity_info,
-                    "Rate limit exceeded"
-                );
-                Ok(self.rate_limit_response(window_size, custom_message.as_deref()))
+        if allowed {
+            // Request allowed, proceed
+            let mut response = next.run(req).await?;
+            if let Some(burst) = self.config.reported_burst() {
+                let headers = response.headers_mut();
+                headers.insert(X_RATELIMIT_LIMIT, self.config.reported_limit().into());
+                headers.insert(X_RATELIMIT_BURST, burst.into());
             }
+            Ok(response)
+        } else {
+            // Rate limit exceeded
+            tracing::warn!(
+                uri = %req.uri(),
+                path = %path,
+                key = %request_key.unwrap_or_default(),
+                "Rate limit exceeded"
+            );
+            Ok(self.rate_limit_response(window_size, custom_message.as_deref()))
         }
     }
 }
 
+/// A GCRA token bucket: the same algorithm governor uses, with state that
+/// can be read without spending a token.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Time for one token to refill
+    interval: Duration,
+    /// Capacity
+    burst: u32,
+    /// When the bucket is full again (GCRA's theoretical arrival time)
+    full_at: Instant,
+}
+
+impl Bucket {
+    fn new(quota: Quota, now: Instant) -> Self {
+        Self {
+            interval: quota.replenish_interval(),
+            burst: quota.burst_size().get(),
+            full_at: now,
+        }
+    }
+
+    /// Spend a token, or return `false` when the bucket is empty
+    fn take(&mut self, now: Instant) -> bool {
+        let full_at = self.full_at.max(now) + self.interval;
+        if full_at - now > self.interval * self.burst {
+            return false;
+        }
+        self.full_at = full_at;
+        true
+    }
+
+    /// Tokens that can be spent at `now`
+    fn remaining(&self, now: Instant) -> u32 {
+        let spent = self
+            .full_at
+            .saturating_duration_since(now)
+            .as_nanos()
+            .div_ceil(self.interval.as_nanos().max(1));
+        self.burst
+            .saturating_sub(u32::try_from(spent).unwrap_or(u32::MAX))
+    }
+}
+
+/// Bucket checks between sweeps for idle keys
+const SWEEP_EVERY: u64 = 1024;
+
+/// The per-key buckets of a keyed [`RateLimit`].
+///
+/// Shared with the admin API (see [`RateLimit::keys`]), so operators can
+/// look up a client's remaining budget, refill it, or exempt the client for
+/// a while. Governor keeps its limiter state private, so keyed buckets are
+/// tracked here instead. Every [`SWEEP_EVERY`] checks, buckets that have
+/// refilled are dropped: a full bucket is what a new key starts with, so
+/// idle keys are forgotten without changing any limit.
+#[derive(Debug, Default)]
+pub struct KeyedBuckets {
+    /// Extracted key -> (route with its own limit, or `None`) -> bucket
+    buckets: DashMap<String, HashMap<Option<String>, Bucket>>,
+    /// Keys let through unlimited
+    exemptions: RateLimitExemptions,
+    /// Checks so far, counting towards the next sweep
+    checks: AtomicU64,
+}
+
+impl KeyedBuckets {
+    /// Spend a token from `key`'s bucket for `route`, creating the bucket
+    /// with `quota` on first use. Exempt keys always pass.
+    fn check(&self, key: &str, route: Option<&str>, quota: Quota) -> bool {
+        if self.exemptions.remaining(key).is_some() {
+            return true;
+        }
+        let now = Instant::now();
+        if self.checks.fetch_add(1, Ordering::Relaxed) % SWEEP_EVERY == SWEEP_EVERY - 1 {
+            self.evict_idle(now);
+        }
+        self.buckets
+            .entry(key.to_string())
+            .or_default()
+            .entry(route.map(str::to_string))
+            .or_insert_with(|| Bucket::new(quota, now))
+            .take(now)
+    }
+
+    /// Drop the buckets that are full again at `now`, and keys left without
+    /// any
+    fn evict_idle(&self, now: Instant) {
+        self.buckets.retain(|_, buckets| {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+            !buckets.is_empty()
+        });
+    }
+}
+
+/// `duration` rounded up to whole milliseconds, for reporting
+fn whole_millis(duration: Duration) -> Duration {
+    Duration::from_millis(duration.as_nanos().div_ceil(1_000_000) as u64)
+}
+
+#[async_trait]
+impl RateLimitKeys for KeyedBuckets {
+    async fn buckets(&self, key: &str) -> Result<Vec<RateLimitBucket>> {
+        let now = Instant::now();
+        let Some(buckets) = self.buckets.get(key) else {
+            return Ok(Vec::new());
+        };
+        let mut buckets: Vec<_> = buckets
+            .iter()
+            .map(|(route, bucket)| RateLimitBucket {
+                route: route.clone(),
+                remaining: bucket.remaining(now),
+                limit: bucket.burst,
+                reset_after: whole_millis(bucket.full_at.saturating_duration_since(now)),
+            })
+            .collect();
+        buckets.sort_by(|a, b| a.route.cmp(&b.route));
+        Ok(buckets)
+    }
+
+    async fn reset(&self, key: &str) -> Result<usize> {
+        Ok(self
+            .buckets
+            .remove(key)
+            .map_or(0, |(_, buckets)| buckets.len()))
+    }
+
+    fn exempt(&self, key: &str, duration: Duration) -> Result<()> {
+        self.exemptions.grant(key, duration)
+    }
+
+    fn unexempt(&self, key: &str) -> bool {
+        self.exemptions.lift(key)
+    }
+
+    fn exemption(&self, key: &str) -> Option<Duration> {
+        self.exemptions.remaining(key).map(whole_millis)
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Distributed rate limiting (feature-gated behind "distributed")
 // ---------------------------------------------------------------------------
@@ -419,34 +682,9 @@ impl<B: octopus_state::StateBackend> DistributedRateLimit<B> {
 
     /// Extract the rate-limit key component from the request.
     fn extract_key(&self, req: &Request<Body>) -> String {
-        match self.config.key_extractor {
-            KeyExtractor::Ip => {
-                // Try X-Forwarded-For first, fall back to "unknown"
-                req.headers()
-                    .get("x-forwarded-for")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|v| v.split(',').next().unwrap_or("unknown").trim().to_string())
-                    .unwrap_or_else(|| "unknown".to_string())
-            }
-            KeyExtractor::Header => {
-                if let Some(ref header_name) = self.config.header_name {
-                    req.headers()
-                        .get(header_name.as_str())
-                        .and_then(|v| v.to_str().ok())
-                        .unwrap_or("unknown")
-                        .to_string()
-                } else {
-                    "unknown".to_string()
-                }
-            }
-            KeyExtractor::Identity => req
-                .extensions()
-                .get::<AuthRateLimitKey>()
-                .map(|k| k.0.clone())
-                .unwrap_or_else(|| "anonymous".to_string()),
-            KeyExtractor::Path => req.uri().path().to_string(),
-            KeyExtractor::Global => "global".to_string(),
-        }
+        self.config
+            .key_extractor
+            .key(req, self.config.header_name.as_deref())
     }
 
     /// Build a rate-limit error response.
@@ -457,28 +695,11 @@ impl<B: octopus_state::StateBackend> DistributedRateLimit<B> {
             .as_deref()
             .unwrap_or("Rate limit exceeded");
 
-        Response::builder()
-            .status(StatusCode::TOO_MANY_REQUESTS)
-            .header("Content-Type", "application/json")
-            .header("Retry-After", self.config.window_size.as_secs().to_string())
-            .header(
-                "X-RateLimit-Limit",
-                self.config.requests_per_window.to_string(),
-            )
-            .header("X-RateLimit-Remaining", "0")
-            .header(
-                "X-RateLimit-Reset",
-                self.config.window_size.as_secs().to_string(),
-            )
-            .body(Full::new(Bytes::from(
-                serde_json::json!({
-                    "error": "rate_limit_exceeded",
-                    "message": message,
-                    "retry_after": self.config.window_size.as_secs()
-                })
-                .to_string(),
-            )))
-            .expect("Failed to build rate limit response")
+        too_many_requests(
+            self.config.window_size,
+            self.config.requests_per_window,
+            message,
+        )
     }
 }
 
@@ -528,14 +749,21 @@ impl<B: octopus_state::StateBackend> Middleware for DistributedRateLimit<B> {
 /// Per-route distributed rate limiter.
 ///
 /// Reads [`MatchedRouteRateLimit`] from the request (attached by the runtime from
-/// `routes[].rate_limit`) and enforces a fixed window per route using a
-/// [`octopus_state::StateBackend`], so the limit holds across replicas. Requests
-/// for routes without a rate limit pass through untouched.
+/// `routes[].rate_limit`) and enforces a fixed window per route and client using
+/// a [`octopus_state::StateBackend`], so the limit holds across replicas.
+/// Requests for routes without a rate limit pass through untouched.
+///
+/// Its windows can be inspected, reset and exempted per client through
+/// [`RateLimitKeys`], with one bucket per limited route.
 #[cfg(feature = "distributed")]
 #[derive(Clone)]
 pub struct RouteRateLimiter<B: octopus_state::StateBackend> {
     backend: B,
     key_prefix: String,
+    /// Route key -> (requests per window, window) last enforced for it
+    limits: Arc<DashMap<String, (u32, Duration)>>,
+    /// Clients let through unlimited
+    exemptions: Arc<RateLimitExemptions>,
 }
 
 #[cfg(feature = "distributed")]
@@ -545,30 +773,78 @@ impl<B: octopus_state::StateBackend> RouteRateLimiter<B> {
         Self {
             backend,
             key_prefix: "octopus:rrl".to_string(),
+            limits: Arc::default(),
+            exemptions: Arc::default(),
         }
     }
 
     /// Build the `429 Too Many Requests` response.
     fn limited_response(window: Duration, limit: u32) -> Response<Body> {
-        Response::builder()
-            .status(StatusCode::TOO_MANY_REQUESTS)
-            .header("Content-Type", "application/json")
-            .header("Retry-After", window.as_secs().to_string())
-            .header("X-RateLimit-Limit", limit.to_string())
-            .header("X-RateLimit-Remaining", "0")
-            .header("X-RateLimit-Reset", window.as_secs().to_string())
-            .body(Full::new(Bytes::from(
-                serde_json::json!({
-                    "error": "rate_limit_exceeded",
-                    "message": "Rate limit exceeded",
-                    "retry_after": window.as_secs()
-                })
-                .to_string(),
-            )))
-            .expect("Failed to build rate limit response")
+        too_many_requests(window, limit, "Rate limit exceeded")
+    }
+
+    /// The backend counter of `client`'s current window on `route`, and the
+    /// time until that window ends
+    fn current_window(&self, route: &str, client: &str, window: Duration) -> (String, Duration) {
+        let window_secs = window.as_secs().max(1);
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        (
+            format!(
+                "{}:{}|{}:{}",
+                self.key_prefix,
+                route,
+                client,
+                now / window_secs
+            ),
+            Duration::from_secs(window_secs - now % window_secs),
+        )
+    }
+
+    /// `client`'s live windows, by route
+    async fn windows(&self, client: &str) -> Result<Vec<ClientWindow>> {
+        let limits: Vec<(String, (u32, Duration))> = self
+            .limits
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        let mut windows = Vec::new();
+        for (route, (limit, window)) in limits {
+            let (counter, reset_after) = self.current_window(&route, client, window);
+            let count = self
+                .backend
+                .get(&counter)
+                .await
+                .map_err(|e| octopus_core::Error::Internal(format!("State backend error: {e}")))?
+                .and_then(|value| String::from_utf8(value).ok()?.parse::<u64>().ok());
+            if let Some(count) = count {
+                windows.push(ClientWindow {
+                    route,
+                    limit,
+                    counter,
+                    reset_after,
+                    count,
+                });
+            }
+        }
+        windows.sort_by(|a, b| a.route.cmp(&b.route));
+        Ok(windows)
     }
 }
 
+/// A client's current window on one route of a [`RouteRateLimiter`]
+#[cfg(feature = "distributed")]
+struct ClientWindow {
+    route: String,
+    limit: u32,
+    /// Backend counter holding `count`
+    counter: String,
+    reset_after: Duration,
+    count: u64,
+}
+
 #[cfg(feature = "distributed")]
 impl<B: octopus_state::StateBackend> fmt::Debug for RouteRateLimiter<B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -583,24 +859,36 @@ impl<B: octopus_state::StateBackend> fmt::Debug for RouteRateLimiter<B> {
 impl<B: octopus_state::StateBackend> Middleware for RouteRateLimiter<B> {
     async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
         if let Some(rl) = req.extensions().get::<MatchedRouteRateLimit>().cloned() {
-            let window_secs = rl.window_size.as_secs().max(1);
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            let window_id = now / window_secs;
-            let key = format!("{}:{}:{}", self.key_prefix, rl.key, window_id);
+            self.limits
+                .insert(rl.key.clone(), (rl.requests_per_window, rl.window_size));
+            if self.exemptions.remaining(&rl.client).is_some() {
+                return next.run(req).await;
+            }
+            let (key, _) = self.current_window(&rl.key, &rl.client, rl.window_size);
             let ttl = rl.window_size + Duration::from_secs(5);
 
-            let count = self
-                .backend
-                .increment(&key, 1, Some(ttl))
-                .await
-                .map_err(|e| octopus_core::Error::Internal(format!("State backend error: {e}")))?;
+            let count = match self.backend.increment(&key, 1, Some(ttl)).await {
+                Ok(count) => count,
+                // Live windows are never dropped to admit new clients, so
+                // while the store is full new clients are limited.
+                Err(octopus_state::Error::CapacityExceeded(e)) => {
+                    tracing::warn!(route = %rl.key, client = %rl.client, error = %e, "Rate limit store full; limiting new client");
+                    return Ok(Self::limited_response(
+                        rl.window_size,
+                        rl.requests_per_window,
+                    ));
+                }
+                Err(e) => {
+                    return Err(octopus_core::Error::Internal(format!(
+                        "State backend error: {e}"
+                    )))
+                }
+            };
 
             if count > rl.requests_per_window as i64 {
                 tracing::warn!(
                     route = %rl.key,
+                    client = %rl.client,
                     count,
                     limit = rl.requests_per_window,
                     "Per-route rate limit exceeded"
@@ -615,6 +903,66 @@ impl<B: octopus_state::StateBackend> Middleware for RouteRateLimiter<B> {
     }
 }
 
+#[cfg(feature = "distributed")]
+#[async_trait]
+impl<B: octopus_state::StateBackend> RateLimitKeys for RouteRateLimiter<B> {
+    async fn buckets(&self, key: &str) -> Result<Vec<RateLimitBucket>> {
+        Ok(self
+            .windows(key)
+            .await?
+            .into_iter()
+            .map(|window| RateLimitBucket {
+                route: Some(window.route),
+                remaining: window
+                    .limit
+                    .saturating_sub(u32::try_from(window.count).unwrap_or(u32::MAX)),
+                limit: window.limit,
+                reset_after: window.reset_after,
+            })
+            .collect())
+    }
+
+    async fn reset(&self, key: &str) -> Result<usize> {
+        let windows = self.windows(key).await?;
+        for window in &windows {
+            self.backend
+                .delete(&window.counter)
+                .await
+                .map_err(|e| octopus_core::Error::Internal(format!("State backend error: {e}")))?;
+        }
+        Ok(windows.len())
+    }
+
+    fn exempt(&self, key: &str, duration: Duration) -> Result<()> {
+        self.exemptions.grant(key, duration)
+    }
+
+    fn unexempt(&self, key: &str) -> bool {
+        self.exemptions.lift(key)
+    }
+
+    fn exemption(&self, key: &str) -> Option<Duration> {
+        self.exemptions.remaining(key).map(whole_millis)
+    }
+}
+
+/// Build the `429 Too Many Requests` problem response with the rate-limit
+/// headers and a `retry_after` extension (seconds).
+pub(crate) fn too_many_requests(window: Duration, limit: u32, message: &str) -> Response<Body> {
+    let window_secs = window.as_secs();
+    ErrorResponse::new(StatusCode::TOO_MANY_REQUESTS, "rate_limit_exceeded")
+        .detail(message)
+        .extension("retry_after", window_secs)
+        .header(header::RETRY_AFTER, window_secs.to_string())
+        .header(X_RATELIMIT_LIMIT, limit.to_string())
+        .header(HeaderName::from_static("x-ratelimit-remaining"), "0")
+        .header(
+            HeaderName::from_static("x-ratelimit-reset"),
+            window_secs.to_string(),
+        )
+        .into_response()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -853,7 +1201,7 @@ mod tests {
         assert!(response.headers().contains_key("X-RateLimit-Reset"));
         assert_eq!(
             response.headers().get("Content-Type").unwrap(),
-            "application/json"
+            octopus_core::PROBLEM_JSON
         );
     }
 
@@ -895,7 +1243,7 @@ mod tests {
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
         let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
-        assert_eq!(json["message"], "Premium API rate limit exceeded");
+        assert_eq!(json["detail"], "Premium API rate limit exceeded");
     }
 
     #[tokio::test]
@@ -934,6 +1282,281 @@ mod tests {
         assert!(response.headers().contains_key("Retry-After"));
     }
 
+    fn keyed_stack(key_extractor: KeyExtractor) -> Arc<[Arc<dyn Middleware>]> {
+        let rate_limit = RateLimit::with_config(RateLimitConfig {
+            requests_per_window: 1,
+            window_size: Duration::from_secs(60),
+            key_extractor,
+            ..Default::default()
+        });
+        Arc::new([Arc::new(rate_limit), Arc::new(TestHandler)])
+    }
+
+    async fn status_for(
+        stack: &Arc<[Arc<dyn Middleware>]>,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> StatusCode {
+        let mut req = Request::builder().uri(path);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let req = req.body(Body::from("")).unwrap();
+        Next::new(stack.clone()).run(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_composite_ip_and_path_buckets_paths_separately() {
+        let stack = keyed_stack(KeyExtractor::Ip.and(KeyExtractor::Path));
+        let ip = [("x-forwarded-for", "10.0.0.1")];
+
+        assert_eq!(status_for(&stack, "/orders", &ip).await, StatusCode::OK);
+        assert_eq!(status_for(&stack, "/users", &ip).await, StatusCode::OK);
+        assert_eq!(
+            status_for(&stack, "/orders", &ip).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        // Same path, different IP: its own bucket.
+        let other = [("x-forwarded-for", "10.0.0.2")];
+        assert_eq!(status_for(&stack, "/orders", &other).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_custom_extractor_buckets_by_tenant() {
+        // Tenant is the first label of a `{tenant}.api.example.com` host.
+        let stack = keyed_stack(KeyExtractor::custom(|req| {
+            req.headers()
+                .get(header::HOST)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|host| host.split('.').next())
+                .unwrap_or("none")
+                .to_string()
+        }));
+        let acme = [("host", "acme.api.example.com")];
+        let globex = [("host", "globex.api.example.com")];
+
+        assert_eq!(status_for(&stack, "/a", &acme).await, StatusCode::OK);
+        assert_eq!(
+            status_for(&stack, "/b", &acme).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(status_for(&stack, "/a", &globex).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_composite_key_joins_parts() {
+        let extractor = KeyExtractor::Header
+            .and(KeyExtractor::Path)
+            .and(KeyExtractor::custom(|_| "v2".to_string()));
+        let req = Request::builder()
+            .uri("/orders")
+            .header("x-api-key", "k1")
+            .body(Body::from(""))
+            .unwrap();
+
+        assert_eq!(extractor.key(&req, Some("x-api-key")), "k1|/orders|v2");
+        assert!(matches!(extractor, KeyExtractor::Composite(ref parts) if parts.len() == 3));
+    }
+
+    fn tiered_stack(key_extractor: KeyExtractor) -> Arc<[Arc<dyn Middleware>]> {
+        // 10/s sustained (one token every 100ms), bursts of 5.
+        let rate_limit = RateLimit::with_config(RateLimitConfig {
+            window_size: Duration::from_secs(1),
+            sustained_per_window: Some(10),
+            burst: Some(5),
+            key_extractor,
+            ..Default::default()
+        });
+        Arc::new([Arc::new(rate_limit), Arc::new(TestHandler)])
+    }
+
+    #[tokio::test]
+    async fn test_burst_allowed_then_throttled_to_sustained_rate() {
+        let stack = tiered_stack(KeyExtractor::Global);
+        let run = |stack: &Arc<[Arc<dyn Middleware>]>| {
+            let req = Request::builder()
+                .uri("/test")
+                .body(Body::from(""))
+                .unwrap();
+            Next::new(stack.clone()).run(req)
+        };
+
+        for _ in 0..5 {
+            let response = run(&stack).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["x-ratelimit-limit"], "10");
+            assert_eq!(response.headers()["x-ratelimit-burst"], "5");
+        }
+
+        let response = run(&stack).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["x-ratelimit-limit"], "10");
+        assert_eq!(response.headers()["x-ratelimit-burst"], "5");
+
+        // One sustained interval refills a single token, not the burst.
+        sleep(Duration::from_millis(120)).await;
+        assert_eq!(run(&stack).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            run(&stack).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_burst_tier_applies_per_key() {
+        let stack = tiered_stack(KeyExtractor::Ip);
+        let first = [("x-forwarded-for", "10.0.0.1")];
+        let second = [("x-forwarded-for", "10.0.0.2")];
+
+        for _ in 0..5 {
+            assert_eq!(status_for(&stack, "/a", &first).await, StatusCode::OK);
+        }
+        assert_eq!(
+            status_for(&stack, "/a", &first).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(status_for(&stack, "/a", &second).await, StatusCode::OK);
+    }
+
+    fn keyed_limit(requests_per_window: u32) -> (Arc<KeyedBuckets>, Arc<[Arc<dyn Middleware>]>) {
+        let rate_limit = RateLimit::with_config(RateLimitConfig {
+            requests_per_window,
+            window_size: Duration::from_secs(60),
+            key_extractor: KeyExtractor::Ip,
+            per_route_limits: Some(HashMap::from([(
+                "/login".to_string(),
+                RouteRateLimit::per_minute(1),
+            )])),
+            ..Default::default()
+        });
+        let keys = rate_limit.keys();
+        (
+            keys,
+            Arc::new([Arc::new(rate_limit), Arc::new(TestHandler)]),
+        )
+    }
+
+    #[test]
+    fn test_idle_keys_are_evicted() {
+        let keys = KeyedBuckets::default();
+        let slow = window_quota(1, Duration::from_secs(60));
+        let fast = window_quota(1000, Duration::from_millis(1));
+        assert!(keys.check("busy", None, slow));
+        for i in 0..SWEEP_EVERY - 2 {
+            assert!(keys.check(&format!("idle-{i}"), None, fast));
+        }
+        assert_eq!(keys.buckets.len() as u64, SWEEP_EVERY - 1);
+
+        // The next check sweeps: only the key still refilling stays.
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(keys.check("new", None, fast));
+        let mut left: Vec<String> = keys.buckets.iter().map(|e| e.key().clone()).collect();
+        left.sort();
+        assert_eq!(left, ["busy", "new"]);
+        assert!(!keys.check("busy", None, slow));
+    }
+
+    #[tokio::test]
+    async fn test_throttled_key_reports_zero_remaining_until_reset() {
+        let (keys, stack) = keyed_limit(2);
+        let client = [("x-forwarded-for", "10.0.0.1")];
+        assert!(keys.buckets("10.0.0.1").await.unwrap().is_empty());
+
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        let buckets = keys.buckets("10.0.0.1").await.unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!((buckets[0].remaining, buckets[0].limit), (1, 2));
+
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        assert_eq!(
+            status_for(&stack, "/a", &client).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        let buckets = keys.buckets("10.0.0.1").await.unwrap();
+        assert_eq!(buckets[0].route, None);
+        assert_eq!(buckets[0].remaining, 0);
+        assert!(buckets[0].reset_after > Duration::from_secs(60));
+
+        assert_eq!(keys.reset("10.0.0.1").await.unwrap(), 1);
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        assert_eq!(keys.buckets("10.0.0.1").await.unwrap()[0].remaining, 1);
+        // Other clients are untouched by a reset.
+        assert_eq!(keys.reset("10.0.0.2").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_route_limits_are_reported_per_route() {
+        let (keys, stack) = keyed_limit(5);
+        let client = [("x-forwarded-for", "10.0.0.1")];
+
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        assert_eq!(status_for(&stack, "/login", &client).await, StatusCode::OK);
+
+        let buckets = keys.buckets("10.0.0.1").await.unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].route, None);
+        assert_eq!(buckets[0].remaining, 4);
+        assert_eq!(buckets[1].route.as_deref(), Some("/login"));
+        assert_eq!((buckets[1].remaining, buckets[1].limit), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_exempt_key_bypasses_its_limit_until_lifted() {
+        let (keys, stack) = keyed_limit(1);
+        let client = [("x-forwarded-for", "10.0.0.1")];
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+
+        keys.exempt("10.0.0.1", Duration::from_secs(300)).unwrap();
+        assert!(keys.exemption("10.0.0.1").unwrap() > Duration::from_secs(290));
+        for _ in 0..3 {
+            assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        }
+        // Other clients are still limited.
+        let other = [("x-forwarded-for", "10.0.0.2")];
+        assert_eq!(status_for(&stack, "/a", &other).await, StatusCode::OK);
+        assert_eq!(
+            status_for(&stack, "/a", &other).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        assert!(keys.unexempt("10.0.0.1"));
+        assert!(!keys.unexempt("10.0.0.1"));
+        assert_eq!(
+            status_for(&stack, "/a", &client).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exemption_expires() {
+        let (keys, stack) = keyed_limit(1);
+        let client = [("x-forwarded-for", "10.0.0.1")];
+        keys.exempt("10.0.0.1", Duration::from_millis(50)).unwrap();
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+
+        sleep(Duration::from_millis(80)).await;
+        assert_eq!(keys.exemption("10.0.0.1"), None);
+        assert_eq!(status_for(&stack, "/a", &client).await, StatusCode::OK);
+        assert_eq!(
+            status_for(&stack, "/a", &client).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[test]
+    fn test_legacy_config_reports_no_burst() {
+        let config = RateLimitConfig::default();
+        assert_eq!(config.reported_limit(), config.requests_per_window);
+        assert_eq!(config.reported_burst(), None);
+
+        let sustained_only = RateLimitConfig {
+            sustained_per_window: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(sustained_only.reported_burst(), Some(20));
+    }
+
     // -----------------------------------------------------------------------
     // Distributed rate limit tests (use in-memory backend)
     // -----------------------------------------------------------------------
@@ -1066,14 +1689,24 @@ mod tests {
 
             let body = response.into_body().collect().await.unwrap().to_bytes();
             let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
-            assert_eq!(json["message"], "Custom limit hit");
+            assert_eq!(json["detail"], "Custom limit hit");
         }
 
         use crate::rate_limit::{MatchedRouteRateLimit, RouteRateLimiter};
 
         fn rl_ext(key: &str, limit: u32, window: Duration) -> MatchedRouteRateLimit {
+            client_ext(key, "10.0.0.1", limit, window)
+        }
+
+        fn client_ext(
+            key: &str,
+            client: &str,
+            limit: u32,
+            window: Duration,
+        ) -> MatchedRouteRateLimit {
             MatchedRouteRateLimit {
                 key: key.to_string(),
+                client: client.to_string(),
                 requests_per_window: limit,
                 window_size: window,
             }
@@ -1105,6 +1738,48 @@ mod tests {
             assert!(resp.headers().contains_key("Retry-After"));
         }
 
+        #[tokio::test]
+        async fn test_route_rate_limit_keeps_live_windows_when_full() {
+            let backend =
+                InMemoryBackend::new().with_max_entries(1, octopus_state::EvictionPolicy::Lru);
+            let rl = RouteRateLimiter::new(backend);
+            let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([Arc::new(rl), Arc::new(TestHandler)]);
+            let status = |client: &str| {
+                let next = Next::new(stack.clone());
+                let mut req = Request::builder().uri("/api").body(Body::from("")).unwrap();
+                req.extensions_mut()
+                    .insert(client_ext("/api", client, 2, Duration::from_secs(60)));
+                async move { next.run(req).await.unwrap().status() }
+            };
+
+            assert_eq!(status("10.0.0.1").await, StatusCode::OK);
+            // No room for a second client's window...
+            assert_eq!(status("10.0.0.2").await, StatusCode::TOO_MANY_REQUESTS);
+            // ...and the first client's window was not reset to make some.
+            assert_eq!(status("10.0.0.1").await, StatusCode::OK);
+            assert_eq!(status("10.0.0.1").await, StatusCode::TOO_MANY_REQUESTS);
+        }
+
+        #[tokio::test]
+        async fn test_route_rate_limit_keeps_a_window_per_client() {
+            let rl = RouteRateLimiter::new(InMemoryBackend::new());
+            let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([Arc::new(rl), Arc::new(TestHandler)]);
+            let run = |client: &str| {
+                let mut req = Request::builder().uri("/a").body(Body::from("")).unwrap();
+                req.extensions_mut()
+                    .insert(client_ext("/a", client, 1, Duration::from_secs(60)));
+                Next::new(stack.clone()).run(req)
+            };
+
+            assert_eq!(run("10.0.0.1").await.unwrap().status(), StatusCode::OK);
+            assert_eq!(
+                run("10.0.0.1").await.unwrap().status(),
+                StatusCode::TOO_MANY_REQUESTS
+            );
+            // Another client is not throttled by the first one's traffic
+            assert_eq!(run("10.0.0.2").await.unwrap().status(), StatusCode::OK);
+        }
+
         #[tokio::test]
         async fn test_route_rate_limit_passes_through_without_extension() {
             let rl = RouteRateLimiter::new(InMemoryBackend::new());
@@ -1138,5 +1813,37 @@ mod tests {
                 .insert(rl_ext("/b", 1, Duration::from_secs(60)));
             assert_eq!(next.run(req).await.unwrap().status(), StatusCode::OK);
         }
+
+        #[tokio::test]
+        async fn test_route_rate_limit_window_can_be_inspected_and_reset() {
+            let rl = Arc::new(RouteRateLimiter::new(InMemoryBackend::new()));
+            let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([rl.clone(), Arc::new(TestHandler)]);
+            let run = || {
+                let mut req = Request::builder().uri("/a").body(Body::from("")).unwrap();
+                req.extensions_mut()
+                    .insert(rl_ext("/a", 1, Duration::from_secs(60)));
+                Next::new(stack.clone()).run(req)
+            };
+            assert!(rl.buckets("10.0.0.1").await.unwrap().is_empty());
+
+            assert_eq!(run().await.unwrap().status(), StatusCode::OK);
+            assert_eq!(run().await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+            let buckets = rl.buckets("10.0.0.1").await.unwrap();
+            assert_eq!(buckets[0].route.as_deref(), Some("/a"));
+            assert_eq!((buckets[0].remaining, buckets[0].limit), (0, 1));
+            assert!(buckets[0].reset_after <= Duration::from_secs(60));
+            assert!(rl.buckets("10.0.0.2").await.unwrap().is_empty());
+
+            assert_eq!(rl.reset("10.0.0.1").await.unwrap(), 1);
+            assert!(rl.buckets("10.0.0.1").await.unwrap().is_empty());
+            assert_eq!(run().await.unwrap().status(), StatusCode::OK);
+            assert_eq!(rl.reset("10.0.0.2").await.unwrap(), 0);
+
+            rl.exempt("10.0.0.1", Duration::from_secs(300)).unwrap();
+            assert_eq!(run().await.unwrap().status(), StatusCode::OK);
+            assert!(rl.unexempt("10.0.0.1"));
+            assert_eq!(run().await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+            assert!(rl.exempt("10.0.0.1", Duration::MAX).is_err());
+        }
     }
 }
@@ -4,13 +4,22 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use http::{header::HeaderName, Request, Response};
 use http_body_util::Full;
-use octopus_core::{Middleware, Next, Result};
+use octopus_core::{Error, Middleware, Next, Result};
+use parking_lot::Mutex;
 use std::fmt;
+use std::sync::{Arc, OnceLock};
 use uuid::Uuid;
 
 /// Body type alias
 pub type Body = Full<Bytes>;
 
+/// A source of request IDs, for plugging a custom scheme into a
+/// [`RequestId`] middleware (see [`RequestId::with_custom_generator`]).
+pub trait GenerateId: Send + Sync + fmt::Debug {
+    /// Generate a new ID
+    fn generate(&self) -> String;
+}
+
 /// Request ID generator strategy
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IdGenerator {
@@ -18,9 +27,26 @@ pub enum IdGenerator {
     UuidV4,
     /// Generate ULID (Universally Unique Lexicographically Sortable Identifier)
     Ulid,
+    /// Generate UUID v7: time-ordered, so IDs sort by creation time
+    UuidV7,
+    /// Generate snowflake-style 64-bit IDs (see [`Snowflake`]) stamped with
+    /// `worker_id`. Every generator in the process with the same worker id
+    /// shares one sequence, so their IDs never collide. Ids above
+    /// [`Snowflake::MAX_WORKER_ID`] keep only their low 10 bits; build the
+    /// variant with [`IdGenerator::snowflake`] to reject them.
+    Snowflake {
+        /// Worker id (`0..=1023`)
+        worker_id: u16,
+    },
 }
 
 impl IdGenerator {
+    /// Snowflake IDs stamped with `worker_id` (`0..=1023`)
+    pub fn snowflake(worker_id: u16) -> Result<Self> {
+        Snowflake::check_worker_id(worker_id)?;
+        Ok(Self::Snowflake { worker_id })
+    }
+
     /// Generate a new ID
     pub fn generate(&self) -> String {
         match self {
@@ -33,10 +59,95 @@ impl IdGenerator {
                     .as_millis();
                 format!("{:016x}{}", now, Uuid::new_v4().simple())
             }
+            IdGenerator::UuidV7 => Uuid::now_v7().to_string(),
+            IdGenerator::Snowflake { worker_id } => {
+                Snowflake::shared(*worker_id).next_id().to_string()
+            }
         }
     }
 }
 
+/// Snowflake-style ID generator.
+///
+/// Each ID packs 41 bits of milliseconds since 2020-01-01, a 10-bit worker id
+/// and a 12-bit per-millisecond sequence, so IDs from one generator strictly
+/// increase and IDs from distinct workers never collide. When the sequence is
+/// exhausted, or the clock steps back, the timestamp is advanced logically
+/// rather than waiting for the wall clock.
+#[derive(Debug)]
+pub struct Snowflake {
+    worker_id: u64,
+    /// `(last timestamp, sequence)` of the previously issued ID
+    state: Mutex<(u64, u64)>,
+}
+
+impl Snowflake {
+    /// Largest accepted worker id
+    pub const MAX_WORKER_ID: u16 = (1 << 10) - 1;
+
+    const EPOCH_MS: u64 = 1_577_836_800_000;
+    const SEQUENCE_BITS: u32 = 12;
+    const WORKER_BITS: u32 = 10;
+
+    /// Create a generator for `worker_id` (`0..=1023`)
+    pub fn new(worker_id: u16) -> Result<Self> {
+        Self::check_worker_id(worker_id)?;
+        Ok(Self::unchecked(worker_id))
+    }
+
+    fn unchecked(worker_id: u16) -> Self {
+        Self {
+            worker_id: u64::from(worker_id & Self::MAX_WORKER_ID),
+            state: Mutex::new((0, 0)),
+        }
+    }
+
+    fn check_worker_id(worker_id: u16) -> Result<()> {
+        if worker_id > Self::MAX_WORKER_ID {
+            return Err(Error::Config(format!(
+                "snowflake worker id {worker_id} exceeds {}",
+                Self::MAX_WORKER_ID
+            )));
+        }
+        Ok(())
+    }
+
+    /// The process-wide generator for `worker_id`, behind
+    /// [`IdGenerator::Snowflake`]
+    fn shared(worker_id: u16) -> &'static Self {
+        static SHARED: OnceLock<Vec<Snowflake>> = OnceLock::new();
+
+        let shared =
+            SHARED.get_or_init(|| (0..=Self::MAX_WORKER_ID).map(Self::unchecked).collect());
+        &shared[usize::from(worker_id & Self::MAX_WORKER_ID)]
+    }
+
+    /// The next ID
+    pub fn next_id(&self) -> u64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+            .saturating_sub(Self::EPOCH_MS);
+
+        let mut state = self.state.lock();
+        let (last, sequence) = &mut *state;
+        if now > *last {
+            *last = now;
+            *sequence = 0;
+        } else {
+            *sequence = (*sequence + 1) & ((1 << Self::SEQUENCE_BITS) - 1);
+            if *sequence == 0 {
+                *last += 1;
+            }
+        }
+
+        (*last << (Self::WORKER_BITS + Self::SEQUENCE_BITS))
+            | (self.worker_id << Self::SEQUENCE_BITS)
+            | *sequence
+    }
+}
+
 /// Configuration for Request ID middleware
 #[derive(Debug, Clone)]
 pub struct RequestIdConfig {
@@ -66,6 +177,8 @@ impl Default for RequestIdConfig {
 pub struct RequestId {
     config: RequestIdConfig,
     header_name: HeaderName,
+    /// Replaces `config.generator` when set
+    custom: Option<Arc<dyn GenerateId>>,
 }
 
 impl RequestId {
@@ -82,12 +195,22 @@ impl RequestId {
         Self {
             config,
             header_name,
+            custom: None,
         }
     }
 
+    /// Generate IDs with `generator` instead of the configured strategy
+    pub fn with_custom_generator(mut self, generator: impl GenerateId + 'static) -> Self {
+        self.custom = Some(Arc::new(generator));
+        self
+    }
+
     /// Generate a new request ID
     fn generate_id(&self) -> String {
-        self.config.generator.generate()
+        match &self.custom {
+            Some(generator) => generator.generate(),
+            None => self.config.generator.generate(),
+        }
     }
 }
 
@@ -102,6 +225,7 @@ impl fmt::Debug for RequestId {
         f.debug_struct("RequestId")
             .field("header_name", &self.config.header_name)
             .field("generator", &self.config.generator)
+            .field("custom", &self.custom)
             .finish()
     }
 }
@@ -144,7 +268,7 @@ impl Middleware for RequestId {
 mod tests {
     use super::*;
     use http::StatusCode;
-    use octopus_core::Error;
+    use std::collections::HashSet;
 
     #[derive(Debug)]
     struct TestHandler;
@@ -231,4 +355,78 @@ mod tests {
         // ULID should be longer than UUID
         assert!(id1.len() > 36);
     }
+
+    #[test]
+    fn test_uuid_v7_ids_sort_by_creation_time() {
+        let generator = IdGenerator::UuidV7;
+        let ids: Vec<String> = (0..1000).map(|_| generator.generate()).collect();
+
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        assert!(ids
+            .iter()
+            .all(|id| Uuid::parse_str(id).unwrap().get_version_num() == 7));
+    }
+
+    #[test]
+    fn test_snowflake_ids_unique_and_monotonic_per_worker() {
+        let snowflake = Snowflake::new(7).unwrap();
+        let ids: Vec<u64> = (0..10_000).map(|_| snowflake.next_id()).collect();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ids.iter().all(|id| (id >> 12) & 0x3ff == 7));
+
+        let other = Snowflake::new(8).unwrap();
+        let seen: HashSet<u64> = ids.into_iter().collect();
+        assert!((0..10_000).all(|_| !seen.contains(&other.next_id())));
+
+        assert!(Snowflake::new(Snowflake::MAX_WORKER_ID + 1).is_err());
+    }
+
+    #[test]
+    fn test_snowflake_unique_across_threads() {
+        let generator = IdGenerator::snowflake(1).unwrap();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    (0..5_000).map(|_| generator.generate()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let ids: HashSet<String> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 20_000);
+        assert!(IdGenerator::snowflake(Snowflake::MAX_WORKER_ID + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_custom_generator() {
+        #[derive(Debug, Default)]
+        struct Counter(std::sync::atomic::AtomicU64);
+
+        impl GenerateId for Counter {
+            fn generate(&self) -> String {
+                let n = self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                format!("req-{n}")
+            }
+        }
+
+        let middleware = RequestId::new().with_custom_generator(Counter::default());
+        let stack: std::sync::Arc<[std::sync::Arc<dyn Middleware>]> = std::sync::Arc::new([
+            std::sync::Arc::new(middleware),
+            std::sync::Arc::new(TestHandler),
+        ]);
+
+        let req = Request::builder()
+            .uri("/test")
+            .body(Body::from(""))
+            .unwrap();
+        let response = Next::new(stack).run(req).await.unwrap();
+
+        assert_eq!(response.headers()["X-Request-ID"], "req-0");
+    }
 }
@@ -0,0 +1,341 @@
+//! HMAC request signing with single-use nonces
+//!
+//! [`RequestSigning`] admits only requests signed with one of the configured
+//! keys. A client sends the key id, a Unix timestamp, optionally a nonce, and
+//! the hex HMAC-SHA256 of the canonical string
+//!
+//! ```text
+//! METHOD \n path?query \n timestamp \n nonce \n hex(sha256(body))
+//! ```
+//!
+//! Requests whose timestamp is more than `max_skew` away from the gateway
+//! clock are rejected. With `require_nonce`, every request must carry a
+//! nonce, which is recorded in a [`NonceCache`] for twice `max_skew`: a
+//! request may be dated up to `max_skew` ahead, so its timestamp is accepted
+//! for that long after it was first seen. An identical replay is rejected
+//! while the nonce is remembered, and fails the timestamp check after.
+//! Rejections are `401` problem responses.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use http::{Request, Response};
+use http_body_util::{BodyExt, Full};
+use octopus_config::types::RequestSigningConfig;
+use octopus_core::{Error, ErrorResponse, Middleware, Next, Result};
+use octopus_state::{NonceCache, StateBackend, MAX_NONCE_LEN};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Body type alias
+pub type Body = Full<Bytes>;
+
+/// Header naming the signing key
+pub const SIGNATURE_KEY_ID_HEADER: &str = "x-signature-key-id";
+
+/// Header carrying the request's Unix timestamp, in seconds
+pub const SIGNATURE_TIMESTAMP_HEADER: &str = "x-signature-timestamp";
+
+/// Header carrying the request's single-use nonce
+pub const SIGNATURE_NONCE_HEADER: &str = "x-signature-nonce";
+
+/// Header carrying the hex HMAC-SHA256 signature
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// Verifies HMAC-signed requests, rejecting replays of their nonces
+pub struct RequestSigning<B: StateBackend> {
+    keys: HashMap<String, Vec<u8>>,
+    max_skew: Duration,
+    require_nonce: bool,
+    nonces: NonceCache<B>,
+}
+
+impl<B: StateBackend> RequestSigning<B> {
+    /// Create the middleware from `config`, recording nonces in `backend`
+    pub fn new(config: &RequestSigningConfig, backend: B) -> Result<Self> {
+        if config.keys.is_empty() {
+            return Err(Error::Config(
+                "request_signing.keys must not be empty".to_string(),
+            ));
+        }
+        Ok(Self {
+            keys: config
+                .keys
+                .iter()
+                .map(|(id, secret)| (id.clone(), secret.as_bytes().to_vec()))
+                .collect(),
+            max_skew: config.max_skew,
+            require_nonce: config.require_nonce,
+            nonces: NonceCache::new(backend, config.max_skew * 2),
+        })
+    }
+
+    /// The string a client signs for this request
+    pub fn canonical_string(
+        req: &Request<Body>,
+        timestamp: &str,
+        nonce: &str,
+        body: &[u8],
+    ) -> String {
+        let target = req
+            .uri()
+            .path_and_query()
+            .map_or(req.uri().path(), |pq| pq.as_str());
+        format!(
+            "{}\n{target}\n{timestamp}\n{nonce}\n{}",
+            req.method(),
+            hex::encode(Sha256::digest(body))
+        )
+    }
+
+    /// Check the signature headers against `body`; the rejection reason
+    /// when they don't verify
+    async fn verify(&self, req: &Request<Body>, body: &[u8]) -> Result<(), String> {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        let (Some(key_id), Some(timestamp), Some(signature)) = (
+            header(SIGNATURE_KEY_ID_HEADER),
+            header(SIGNATURE_TIMESTAMP_HEADER),
+            header(SIGNATURE_HEADER),
+        ) else {
+            return Err("Missing request signature".to_string());
+        };
+        let nonce = header(SIGNATURE_NONCE_HEADER).unwrap_or("");
+        if self.require_nonce && nonce.is_empty() {
+            return Err("Missing request nonce".to_string());
+        }
+        if nonce.len() > MAX_NONCE_LEN {
+            return Err(format!("Request nonce exceeds {MAX_NONCE_LEN} bytes"));
+        }
+
+        let Some(secret) = self.keys.get(key_id) else {
+            return Err("Unknown signing key".to_string());
+        };
+        let sent_at = timestamp
+            .parse::<u64>()
+            .map_err(|_| "Invalid signature timestamp".to_string())?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if now.abs_diff(sent_at) > self.max_skew.as_secs() {
+            return Err("Signature timestamp outside the accepted window".to_string());
+        }
+
+        let signature =
+            hex::decode(signature).map_err(|_| "Malformed request signature".to_string())?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+            .map_err(|_| "Unusable signing key".to_string())?;
+        mac.update(Self::canonical_string(req, timestamp, nonce, body).as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| "Invalid request signature".to_string())?;
+
+        // Recorded only once the signature holds, so forged requests can't
+        // burn a client's nonces.
+        if !nonce.is_empty() {
+            match self.nonces.check_and_record(key_id, nonce).await {
+                Ok(true) => {}
+                Ok(false) => return Err("Request nonce already used".to_string()),
+                Err(e) => {
+                    tracing::error!(error = %e, "Nonce store unavailable; rejecting signed request");
+                    return Err("Request nonce could not be verified".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: StateBackend> fmt::Debug for RequestSigning<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestSigning")
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .field("max_skew", &self.max_skew)
+            .field("require_nonce", &self.require_nonce)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<B: StateBackend> Middleware for RequestSigning<B> {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let (parts, body) = req.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        let req = Request::from_parts(parts, Full::new(body.clone()));
+
+        if let Err(reason) = self.verify(&req, &body).await {
+            tracing::debug!(path = %req.uri().path(), reason = %reason, "Rejected signed request");
+            return Ok(
+                ErrorResponse::from_error(&Error::Authentication(reason.clone()))
+                    .detail(reason)
+                    .instance(req.uri().path())
+                    .into_response(),
+            );
+        }
+        next.run(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+    use octopus_state::InMemoryBackend;
+    use std::sync::Arc;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    #[derive(Debug)]
+    struct Upstream;
+
+    #[async_trait]
+    impl Middleware for Upstream {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            Ok(Response::new(Full::new(Bytes::from_static(b"ok"))))
+        }
+    }
+
+    fn stack(require_nonce: bool) -> Arc<[Arc<dyn Middleware>]> {
+        stack_with_skew(require_nonce, RequestSigningConfig::default().max_skew)
+    }
+
+    fn stack_with_skew(require_nonce: bool, max_skew: Duration) -> Arc<[Arc<dyn Middleware>]> {
+        let config = RequestSigningConfig {
+            keys: HashMap::from([("client-1".to_string(), SECRET.to_string())]),
+            max_skew,
+            require_nonce,
+            ..Default::default()
+        };
+        let signing = RequestSigning::new(&config, InMemoryBackend::new()).unwrap();
+        Arc::new([
+            Arc::new(signing) as Arc<dyn Middleware>,
+            Arc::new(Upstream) as Arc<dyn Middleware>,
+        ])
+    }
+
+    fn signed(nonce: &str, secret: &str) -> Request<Body> {
+        signed_at(nonce, secret, 0)
+    }
+
+    /// A request signed with a timestamp `ahead` seconds in the future
+    fn signed_at(nonce: &str, secret: &str, ahead: u64) -> Request<Body> {
+        let timestamp = (SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + ahead)
+            .to_string();
+        let body = Bytes::from_static(b"{\"amount\":5}");
+        let unsigned = Request::post("/charges?currency=eur")
+            .body(Full::new(body.clone()))
+            .unwrap();
+        let canonical = RequestSigning::<InMemoryBackend>::canonical_string(
+            &unsigned, &timestamp, nonce, &body,
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(canonical.as_bytes());
+
+        let (mut parts, body) = unsigned.into_parts();
+        for (name, value) in [
+            (SIGNATURE_KEY_ID_HEADER, "client-1".to_string()),
+            (SIGNATURE_TIMESTAMP_HEADER, timestamp),
+            (SIGNATURE_NONCE_HEADER, nonce.to_string()),
+            (SIGNATURE_HEADER, hex::encode(mac.finalize().into_bytes())),
+        ] {
+            parts.headers.insert(name, value.parse().unwrap());
+        }
+        Request::from_parts(parts, body)
+    }
+
+    #[tokio::test]
+    async fn replayed_nonce_is_rejected_with_401() {
+        let stack = stack(true);
+
+        let first = Next::new(stack.clone())
+            .run(signed("n-1", SECRET))
+            .await
+            .unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+
+        let replay = Next::new(stack.clone())
+            .run(signed("n-1", SECRET))
+            .await
+            .unwrap();
+        assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+        let body = replay.into_body().collect().await.unwrap().to_bytes();
+        assert!(
+            String::from_utf8_lossy(&body).contains("nonce already used"),
+            "{body:?}"
+        );
+
+        let fresh = Next::new(stack).run(signed("n-2", SECRET)).await.unwrap();
+        assert_eq!(fresh.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn future_dated_replay_is_rejected_after_max_skew() {
+        let stack = stack_with_skew(true, Duration::from_secs(1));
+
+        let first = Next::new(stack.clone())
+            .run(signed_at("n-1", SECRET, 1))
+            .await
+            .unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+
+        // Past `max_skew` after first use, the timestamp is still accepted,
+        // and so the nonce must still be remembered.
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let replay = Next::new(stack)
+            .run(signed_at("n-1", SECRET, 0))
+            .await
+            .unwrap();
+        assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+        let body = replay.into_body().collect().await.unwrap().to_bytes();
+        assert!(
+            String::from_utf8_lossy(&body).contains("nonce already used"),
+            "{body:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn forged_request_is_rejected_without_burning_the_nonce() {
+        let stack = stack(true);
+
+        let forged = Next::new(stack.clone())
+            .run(signed("n-1", "not-the-shared-secret-at-all!!!!"))
+            .await
+            .unwrap();
+        assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+
+        let genuine = Next::new(stack).run(signed("n-1", SECRET)).await.unwrap();
+        assert_eq!(genuine.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn nonce_is_required_when_configured() {
+        let response = Next::new(stack(true))
+            .run(signed("", SECRET))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = Next::new(stack(false))
+            .run(signed("", SECRET))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unsigned_request_is_rejected() {
+        let request = Request::get("/charges")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let response = Next::new(stack(false)).run(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
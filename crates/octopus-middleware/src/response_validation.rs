@@ -0,0 +1,329 @@
+//! Upstream response validation (dev mode)
+//!
+//! Checks each upstream response against the operation's declared OpenAPI
+//! contract, looked up through a [`SchemaResolver`]: the status must be a
+//! declared response, and a JSON body must match that response's schema.
+//! Mismatches never change the response; they are reported as
+//! [`AuditEventType::SchemaViolation`] events and, optionally, flagged with an
+//! `X-Schema-Violation` header. Meant for staging, to catch contract drift.
+
+use crate::audit_logger::{AuditEvent, AuditEventType, AuditHandler};
+use crate::body_validation::{DeclaredResponse, ResolvedSchema, SchemaCache, SchemaResolver};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderValue, Request, Response};
+use http_body_util::{BodyExt, Full};
+use octopus_core::{Middleware, Next, Result, StreamedResponse};
+use std::fmt;
+use std::sync::Arc;
+
+/// Body type alias
+pub type Body = Full<Bytes>;
+
+/// Header flagging a response that violated its declared schema
+pub const SCHEMA_VIOLATION_HEADER: &str = "x-schema-violation";
+
+/// Response validation configuration
+#[derive(Debug, Clone)]
+pub struct ResponseValidationConfig {
+    /// Add `X-Schema-Violation` to responses that do not match the contract
+    pub violation_header: bool,
+    /// Larger response bodies are not validated (bytes)
+    pub max_body_size: usize,
+}
+
+impl Default for ResponseValidationConfig {
+    fn default() -> Self {
+        Self {
+            violation_header: false,
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Upstream response validation middleware
+#[derive(Clone)]
+pub struct ResponseValidation {
+    config: ResponseValidationConfig,
+    resolver: Arc<dyn SchemaResolver>,
+    sink: Option<Arc<dyn AuditHandler>>,
+    compiled: Arc<SchemaCache>,
+}
+
+impl ResponseValidation {
+    /// Validate responses against the contracts found by `resolver`
+    pub fn new(config: ResponseValidationConfig, resolver: Arc<dyn SchemaResolver>) -> Self {
+        Self {
+            config,
+            resolver,
+            sink: None,
+            compiled: Arc::default(),
+        }
+    }
+
+    /// Record violations with `sink` (they are always logged as warnings)
+    pub fn with_sink(mut self, sink: Arc<dyn AuditHandler>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Violations of `body` against the declared response schema, as messages
+    fn body_violations(&self, schema: Option<ResolvedSchema>, body: &[u8]) -> Vec<String> {
+        let Some(schema) = schema.and_then(|resolved| self.compiled.get(resolved)) else {
+            return Vec::new();
+        };
+        let instance: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(value) => value,
+            Err(e) => return vec![format!("body is not JSON: {e}")],
+        };
+        let violations = match schema.validate(&instance) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|e| format!("body {}: {e}", e.instance_path))
+                .collect(),
+        };
+        violations
+    }
+}
+
+impl fmt::Debug for ResponseValidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseValidation")
+            .field("config", &self.config)
+            .field("resolver", &self.resolver)
+            .field("sink", &self.sink)
+            .field("compiled", &self.compiled.len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Middleware for ResponseValidation {
+    async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        let request_id = req
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        let response = next.run(req).await?;
+        // A streamed body isn't read here
+        if StreamedResponse::is_streamed(&response) {
+            return Ok(response);
+        }
+        let status = response.status();
+        let Some(declared) = self.resolver.resolve_response(&method, &path, status) else {
+            return Ok(response);
+        };
+
+        let (mut parts, body) = response.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        let violations = match declared {
+            DeclaredResponse::Undeclared => {
+                vec![format!(
+                    "status {} is not a declared response",
+                    status.as_u16()
+                )]
+            }
+            DeclaredResponse::Declared(_)
+                if body.is_empty() || body.len() > self.config.max_body_size =>
+            {
+                Vec::new()
+            }
+            DeclaredResponse::Declared(schema) => self.body_violations(schema, &body),
+        };
+
+        if !violations.is_empty() {
+            let details = violations.join("; ");
+            tracing::warn!(
+                method = %method,
+                path = %path,
+                status = status.as_u16(),
+                violations = %details,
+                "Upstream response violates its declared schema"
+            );
+            if let Some(sink) = &self.sink {
+                let mut event = AuditEvent::new(AuditEventType::SchemaViolation);
+                event.method = method.to_string();
+                event.path = path;
+                event.status_code = status.as_u16();
+                event.details = Some(details);
+                event.request_id = request_id;
+                sink.log(&event);
+            }
+            if self.config.violation_header {
+                let value = HeaderValue::from_str(&violations[0])
+                    .unwrap_or_else(|_| HeaderValue::from(violations.len()));
+                parts.headers.insert(SCHEMA_VIOLATION_HEADER, value);
+            }
+        }
+        Ok(Response::from_parts(parts, Full::new(body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body_validation::OpenApiSchemas;
+    use http::{header, Method, StatusCode};
+    use octopus_core::Error;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    struct Spec(OpenApiSchemas);
+
+    impl SchemaResolver for Spec {
+        fn resolve(&self, method: &Method, path: &str) -> Option<ResolvedSchema> {
+            self.0.find(method, path)
+        }
+
+        fn resolve_response(
+            &self,
+            method: &Method,
+            path: &str,
+            status: StatusCode,
+        ) -> Option<DeclaredResponse> {
+            self.0.find_response(method, path, status)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Recorder(Mutex<Vec<AuditEvent>>);
+
+    impl AuditHandler for Recorder {
+        fn log(&self, event: &AuditEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    /// Upstream answering with a fixed status and body.
+    #[derive(Debug)]
+    struct Upstream(StatusCode, &'static str);
+
+    #[async_trait]
+    impl Middleware for Upstream {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            Response::builder()
+                .status(self.0)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Full::new(Bytes::from_static(self.1.as_bytes())))
+                .map_err(|e| Error::Internal(e.to_string()))
+        }
+    }
+
+    fn spec() -> OpenApiSchemas {
+        let doc = json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/users/{id}": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "content": {
+                                    "application/json": {
+                                        "schema": { "$ref": "#/components/schemas/User" }
+                                    }
+                                }
+                            },
+                            "404": { "description": "Not found" }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "User": {
+                        "type": "object",
+                        "required": ["id", "email"],
+                        "properties": {
+                            "id": { "type": "integer" },
+                            "email": { "type": "string" }
+                        }
+                    }
+                }
+            }
+        });
+        OpenApiSchemas::from_document(&doc, 1)
+    }
+
+    async fn run(upstream: Upstream, violation_header: bool) -> (Response<Body>, Vec<AuditEvent>) {
+        let recorder = Arc::new(Recorder::default());
+        let validation = ResponseValidation::new(
+            ResponseValidationConfig {
+                violation_header,
+                ..Default::default()
+            },
+            Arc::new(Spec(spec())),
+        )
+        .with_sink(recorder.clone());
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([
+            Arc::new(validation) as Arc<dyn Middleware>,
+            Arc::new(upstream) as Arc<dyn Middleware>,
+        ]);
+        let req = Request::builder()
+            .uri("/users/7")
+            .header("x-request-id", "req-1")
+            .body(Body::default())
+            .unwrap();
+        let response = Next::new(stack).run(req).await.unwrap();
+        let events = recorder.0.lock().unwrap().clone();
+        (response, events)
+    }
+
+    #[tokio::test]
+    async fn missing_required_field_is_recorded() {
+        let (response, events) = run(Upstream(StatusCode::OK, r#"{"id":7}"#), true).await;
+
+        // The response itself is passed through untouched.
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[SCHEMA_VIOLATION_HEADER]
+            .to_str()
+            .unwrap()
+            .contains("email"));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], br#"{"id":7}"#);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, AuditEventType::SchemaViolation);
+        assert_eq!(events[0].path, "/users/7");
+        assert_eq!(events[0].status_code, 200);
+        assert_eq!(events[0].request_id.as_deref(), Some("req-1"));
+        assert!(events[0].details.as_deref().unwrap().contains("email"));
+    }
+
+    #[tokio::test]
+    async fn conforming_response_records_nothing() {
+        let (response, events) = run(
+            Upstream(StatusCode::OK, r#"{"id":7,"email":"ada@example.com"}"#),
+            true,
+        )
+        .await;
+
+        assert!(response.headers().get(SCHEMA_VIOLATION_HEADER).is_none());
+        assert!(events.is_empty());
+
+        // Declared status without a body schema.
+        let (_, events) = run(Upstream(StatusCode::NOT_FOUND, ""), true).await;
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn undeclared_status_is_recorded_without_header_by_default() {
+        let (response, events) = run(Upstream(StatusCode::IM_A_TEAPOT, "{}"), false).await;
+
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        assert!(response.headers().get(SCHEMA_VIOLATION_HEADER).is_none());
+        assert_eq!(events.len(), 1);
+        assert!(events[0]
+            .details
+            .as_deref()
+            .unwrap()
+            .contains("status 418 is not a declared response"));
+    }
+}
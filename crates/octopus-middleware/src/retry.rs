@@ -181,11 +181,8 @@ impl Middleware for Retry {
                 }
                 Err(err) => {
                     let should_retry = match &err {
-                        octopus_core::Error::UpstreamTimeout if self.config.retry_on_timeout => {
-                            true
-                        }
-                        octopus_core::Error::UpstreamConnection(_) => true,
-                        _ => false,
+                        octopus_core::Error::UpstreamTimeout => self.config.retry_on_timeout,
+                        other => other.is_upstream_failure(),
                     };
 
                     if should_retry && attempt < max - 1 {
@@ -0,0 +1,445 @@
+//! Multi-tenant request resolution
+//!
+//! [`TenantResolver`] reads a tenant id from the request (a host subdomain,
+//! a path prefix segment, a header or a verified token claim), rejects
+//! tenants that aren't configured, and sends the id upstream in a header (by
+//! default `X-Tenant-Id`, never trusted from the client). The tenant is
+//! stored as a [`ResolvedTenant`] request extension so the proxy can send it
+//! to the tenant's own upstream.
+//!
+//! The handler applies it before route matching rather than as a chain
+//! layer: a path-prefix tenant (`/t/acme/orders`) is removed from the path,
+//! so routes are declared once (`/orders`) for every tenant. A claim tenant
+//! can only be read once auth has verified the token, so for that source
+//! [`TenantResolver::apply`] only drops the client's tenant header and
+//! [`TenantResolver::apply_verified`] resolves it after the middleware chain.
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode, Uri};
+use http_body_util::Full;
+use octopus_config::types::{TenancyConfig, TenantSourceConfig};
+use octopus_core::{json_path, AuthContext, Error, ErrorResponse, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Body type alias
+pub type Body = Full<Bytes>;
+
+/// The tenant a request belongs to, stored in request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTenant {
+    /// Tenant id (lowercase)
+    pub id: String,
+    /// Upstream serving this tenant in place of the route's own
+    pub upstream: Option<String>,
+}
+
+/// Where the tenant id is read from
+#[derive(Debug, Clone)]
+enum TenantSource {
+    /// Host label in front of `.{base_domain}`; holds the dotted suffix
+    Subdomain(String),
+    /// Path segment after the prefix; holds the prefix without a trailing `/`
+    Path(String),
+    Header(HeaderName),
+    /// Claim path in the verified token claims
+    Claim(String),
+}
+
+#[derive(Debug, Clone)]
+struct Tenant {
+    upstream: Option<String>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// Resolves and checks the tenant of each request
+#[derive(Debug, Clone)]
+pub struct TenantResolver {
+    source: TenantSource,
+    header: HeaderName,
+    unknown_status: StatusCode,
+    required: bool,
+    tenants: HashMap<String, Tenant>,
+}
+
+impl TenantResolver {
+    /// Build from the `gateway.tenancy` config section
+    pub fn new(config: &TenancyConfig) -> Result<Self> {
+        let source = match &config.source {
+            TenantSourceConfig::Subdomain { base_domain } => TenantSource::Subdomain(format!(
+                ".{}",
+                base_domain.trim_matches('.').to_ascii_lowercase()
+            )),
+            TenantSourceConfig::Path { prefix } => {
+                TenantSource::Path(prefix.trim_end_matches('/').to_string())
+            }
+            TenantSourceConfig::Header { name } => TenantSource::Header(header_name(name)?),
+            TenantSourceConfig::Claim { claim, .. } => TenantSource::Claim(claim.clone()),
+        };
+        let tenants = config
+            .tenants
+            .iter()
+            .map(|(id, tenant)| {
+                let headers = tenant
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = HeaderValue::from_str(value).map_err(|e| {
+                            Error::Config(format!("Invalid header value for {name:?}: {e}"))
+                        })?;
+                        Ok((header_name(name)?, value))
+                    })
+                    .collect::<Result<_>>()?;
+                let tenant = Tenant {
+                    upstream: tenant.upstream.clone(),
+                    headers,
+                };
+                Ok((id.to_ascii_lowercase(), tenant))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            source,
+            header: header_name(&config.header)?,
+            unknown_status: StatusCode::from_u16(config.unknown_status)
+                .map_err(|e| Error::Config(format!("Invalid tenancy.unknown_status: {e}")))?,
+            required: config.required,
+            tenants,
+        })
+    }
+
+    /// Resolve the tenant of `req`, whose lowercased host is `host`.
+    ///
+    /// On success the tenant header and the tenant's own headers are set, a
+    /// path-prefix tenant is removed from the path, and a [`ResolvedTenant`]
+    /// is stored in the extensions. Returns the rejection for an unknown
+    /// tenant, or a missing one when tenants are required.
+    ///
+    /// A claim tenant is left to [`apply_verified`](Self::apply_verified);
+    /// here only the client's tenant header is removed.
+    pub fn apply<B>(&self, req: &mut Request<B>, host: &str) -> Option<Response<Body>> {
+        if matches!(self.source, TenantSource::Claim(_)) {
+            req.headers_mut().remove(&self.header);
+            return None;
+        }
+        let extracted = self.extract(req, host);
+        self.admit(req, extracted)
+    }
+
+    /// Resolve a claim tenant from the claims auth verified for `req` (the
+    /// JWT middleware's [`Claims`](crate::jwt::Claims) or an
+    /// [`AuthContext`]). A request nobody authenticated has no tenant. No-op
+    /// for the other sources, which [`apply`](Self::apply) resolves.
+    pub fn apply_verified<B>(&self, req: &mut Request<B>) -> Option<Response<Body>> {
+        let TenantSource::Claim(path) = &self.source else {
+            return None;
+        };
+        let extracted = verified_claims(req)
+            .and_then(|claims| claim_value(&claims, path))
+            .filter(|id| !id.is_empty())
+            .map(|id| (id.to_ascii_lowercase(), None));
+        self.admit(req, extracted)
+    }
+
+    /// Apply an extracted tenant (see [`apply`](Self::apply))
+    fn admit<B>(
+        &self,
+        req: &mut Request<B>,
+        extracted: Option<(String, Option<String>)>,
+    ) -> Option<Response<Body>> {
+        req.headers_mut().remove(&self.header);
+
+        let Some((id, rest)) = extracted else {
+            if self.required {
+                tracing::debug!(path = %req.uri().path(), "No tenant in request");
+                return Some(self.reject("No tenant in request"));
+            }
+            return None;
+        };
+        let Some(tenant) = self.tenants.get(&id) else {
+            tracing::debug!(tenant = %id, "Unknown tenant");
+            return Some(self.reject(&format!("Unknown tenant '{id}'")));
+        };
+
+        if let Some(rest) = rest {
+            set_path(req, &rest);
+        }
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            req.headers_mut().insert(self.header.clone(), value);
+        }
+        for (name, value) in &tenant.headers {
+            req.headers_mut().insert(name.clone(), value.clone());
+        }
+        req.extensions_mut().insert(ResolvedTenant {
+            id,
+            upstream: tenant.upstream.clone(),
+        });
+        None
+    }
+
+    /// The lowercased tenant id and, for a path tenant, the path without it
+    fn extract<B>(&self, req: &Request<B>, host: &str) -> Option<(String, Option<String>)> {
+        let id = match &self.source {
+            TenantSource::Subdomain(suffix) => {
+                let label = host.strip_suffix(suffix.as_str())?;
+                label.rsplit('.').next().map(str::to_string)
+            }
+            TenantSource::Path(prefix) => {
+                let path = req.uri().path();
+                let tail = path.strip_prefix(prefix.as_str())?.strip_prefix('/')?;
+                let (id, rest) = match tail.split_once('/') {
+                    Some((id, rest)) => (id, format!("/{rest}")),
+                    None => (tail, "/".to_string()),
+                };
+                return (!id.is_empty()).then(|| (id.to_ascii_lowercase(), Some(rest)));
+            }
+            TenantSource::Header(name) => header_str(req.headers(), name).map(str::to_string),
+            TenantSource::Claim(_) => None,
+        };
+        id.filter(|id| !id.is_empty())
+            .map(|id| (id.to_ascii_lowercase(), None))
+    }
+
+    fn reject(&self, detail: &str) -> Response<Body> {
+        ErrorResponse::new(self.unknown_status, "unknown_tenant")
+            .detail(detail)
+            .into_response()
+    }
+}
+
+fn header_name(name: &str) -> Result<HeaderName> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| Error::Config(format!("Invalid header name {name:?}: {e}")))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+/// Claims verified by the auth middleware that ran for `req`
+fn verified_claims<B>(req: &Request<B>) -> Option<Value> {
+    if let Some(claims) = req.extensions().get::<crate::jwt::Claims>() {
+        return serde_json::to_value(claims).ok();
+    }
+    let auth = req.extensions().get::<AuthContext>()?;
+    let mut claims: serde_json::Map<String, Value> = auth
+        .claims
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    claims
+        .entry("sub")
+        .or_insert_with(|| Value::String(auth.subject.clone()));
+    Some(Value::Object(claims))
+}
+
+/// String or number claim at `path`
+fn claim_value(claims: &Value, path: &str) -> Option<String> {
+    match json_path::get(claims, path)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Replace the request path, keeping the scheme, authority and query
+fn set_path<B>(req: &mut Request<B>, path: &str) {
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path.to_string(),
+    };
+    let mut parts = req.uri().clone().into_parts();
+    if let Ok(path_and_query) = path_and_query.parse() {
+        parts.path_and_query = Some(path_and_query);
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *req.uri_mut() = uri;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_config::types::TenantConfig;
+
+    fn resolver(source: TenantSourceConfig) -> TenantResolver {
+        let tenant = |upstream: &str| TenantConfig {
+            upstream: Some(upstream.to_string()),
+            headers: [("X-Tenant-Plan".to_string(), "enterprise".to_string())].into(),
+        };
+        TenantResolver::new(&TenancyConfig {
+            source,
+            header: "X-Tenant-Id".to_string(),
+            unknown_status: 404,
+            required: true,
+            tenants: [
+                ("acme".to_string(), tenant("acme-api")),
+                ("globex".to_string(), tenant("globex-api")),
+            ]
+            .into(),
+        })
+        .unwrap()
+    }
+
+    fn request(uri: &str) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            // Spoofed by the client
+            .header("x-tenant-id", "globex")
+            .body(Body::default())
+            .unwrap()
+    }
+
+    fn tenant(req: &Request<Body>) -> Option<&ResolvedTenant> {
+        req.extensions().get::<ResolvedTenant>()
+    }
+
+    #[test]
+    fn test_tenant_from_subdomain() {
+        let resolver = resolver(TenantSourceConfig::Subdomain {
+            base_domain: "api.example.com".to_string(),
+        });
+
+        let mut req = request("/orders");
+        assert!(resolver.apply(&mut req, "acme.api.example.com").is_none());
+        assert_eq!(
+            tenant(&req),
+            Some(&ResolvedTenant {
+                id: "acme".to_string(),
+                upstream: Some("acme-api".to_string()),
+            })
+        );
+        assert_eq!(req.headers()["x-tenant-id"], "acme");
+        assert_eq!(req.headers()["x-tenant-plan"], "enterprise");
+        assert_eq!(req.uri().path(), "/orders");
+
+        // The bare base domain names no tenant
+        let mut req = request("/orders");
+        let resp = resolver.apply(&mut req, "api.example.com").unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_tenant_from_path_prefix() {
+        let resolver = resolver(TenantSourceConfig::Path {
+            prefix: "/t/".to_string(),
+        });
+
+        let mut req = request("/t/Globex/orders/7?expand=items");
+        assert!(resolver.apply(&mut req, "api.example.com").is_none());
+        assert_eq!(tenant(&req).unwrap().id, "globex");
+        assert_eq!(
+            tenant(&req).unwrap().upstream.as_deref(),
+            Some("globex-api")
+        );
+        assert_eq!(req.uri(), "/orders/7?expand=items");
+
+        let mut req = request("/t/acme");
+        assert!(resolver.apply(&mut req, "api.example.com").is_none());
+        assert_eq!(tenant(&req).unwrap().upstream.as_deref(), Some("acme-api"));
+        assert_eq!(req.uri().path(), "/");
+
+        // `/tenants/...` is not under the `/t` prefix
+        let mut req = request("/tenants/acme");
+        assert!(resolver.apply(&mut req, "api.example.com").is_some());
+    }
+
+    #[test]
+    fn test_unknown_tenant_is_rejected() {
+        let mut config = TenancyConfig {
+            source: TenantSourceConfig::Subdomain {
+                base_domain: "api.example.com".to_string(),
+            },
+            header: "X-Tenant-Id".to_string(),
+            unknown_status: 403,
+            required: false,
+            tenants: [("acme".to_string(), TenantConfig::default())].into(),
+        };
+        let resolver = TenantResolver::new(&config).unwrap();
+
+        let mut req = request("/orders");
+        let resp = resolver.apply(&mut req, "initech.api.example.com").unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(tenant(&req).is_none());
+
+        // Not required: requests without a tenant pass, minus the spoofed id
+        let mut req = request("/orders");
+        assert!(resolver.apply(&mut req, "api.example.com").is_none());
+        assert!(tenant(&req).is_none());
+        assert!(!req.headers().contains_key("x-tenant-id"));
+
+        config.required = true;
+        let resolver = TenantResolver::new(&config).unwrap();
+        let mut req = request("/orders");
+        let resp = resolver.apply(&mut req, "api.example.com").unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_tenant_from_header_and_claim() {
+        let by_header = resolver(TenantSourceConfig::Header {
+            name: "X-Tenant".to_string(),
+        });
+        let mut req = request("/orders");
+        req.headers_mut()
+            .insert("x-tenant", HeaderValue::from_static("acme"));
+        assert!(by_header.apply(&mut req, "api.example.com").is_none());
+        assert_eq!(tenant(&req).unwrap().id, "acme");
+
+        let by_claim = resolver(TenantSourceConfig::Claim {
+            claim: "org.tenant".to_string(),
+            token_header: "Authorization".to_string(),
+        });
+        let claims = crate::jwt::Claims {
+            sub: "user-1".to_string(),
+            exp: 0,
+            iat: None,
+            iss: None,
+            aud: None,
+            custom: serde_json::json!({ "org": { "tenant": "globex" } }),
+        };
+        let mut req = request("/orders");
+        // Before auth only the spoofed header goes
+        assert!(by_claim.apply(&mut req, "api.example.com").is_none());
+        assert!(tenant(&req).is_none());
+        assert!(!req.headers().contains_key("x-tenant-id"));
+
+        req.extensions_mut().insert(claims);
+        assert!(by_claim.apply_verified(&mut req).is_none());
+        assert_eq!(tenant(&req).unwrap().id, "globex");
+        assert_eq!(req.headers()["x-tenant-id"], "globex");
+    }
+
+    #[test]
+    fn test_claim_tenant_needs_verified_claims() {
+        let by_claim = resolver(TenantSourceConfig::Claim {
+            claim: "tenant".to_string(),
+            token_header: "Authorization".to_string(),
+        });
+
+        // A token nobody verified names no tenant, whatever it claims
+        let mut req = request("/orders");
+        req.headers_mut().insert(
+            "authorization",
+            HeaderValue::from_static("Bearer eyJhbGciOiJub25lIn0.eyJ0ZW5hbnQiOiJhY21lIn0."),
+        );
+        assert!(by_claim.apply(&mut req, "api.example.com").is_none());
+        let resp = by_claim.apply_verified(&mut req).unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(tenant(&req).is_none());
+
+        let mut req = request("/orders");
+        req.extensions_mut().insert(AuthContext {
+            subject: "svc".to_string(),
+            provider: "oidc".to_string(),
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            claims: [("tenant".to_string(), serde_json::json!("ACME"))].into(),
+        });
+        assert!(by_claim.apply_verified(&mut req).is_none());
+        assert_eq!(tenant(&req).unwrap().id, "acme");
+    }
+}
@@ -36,6 +36,15 @@ pub trait Plugin: Send + Sync + fmt::Debug {
         vec![]
     }
 
+    /// JSON Schema of the configuration passed to [`init`](Self::init)
+    ///
+    /// When declared, the runtime validates the configuration against it
+    /// before calling `init` or `reload` and rejects it with per-field errors,
+    /// and the admin dashboard uses it to validate config edits.
+    fn config_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+
     /// Initialize plugin with configuration
     ///
     /// This is called once when the plugin is loaded.
@@ -68,6 +77,15 @@ pub trait Plugin: Send + Sync + fmt::Debug {
         self.start().await
     }
 
+    /// Apply a new configuration to the running plugin
+    ///
+    /// Called for live config edits (e.g. from the admin dashboard). Override
+    /// to apply changes in place, without dropping in-flight work or state.
+    /// The default falls back to [`reload`](Self::reload), i.e. a restart.
+    async fn reconfigure(&mut self, config: serde_json::Value) -> Result<()> {
+        self.reload(config).await
+    }
+
     /// Get plugin metadata
     fn metadata(&self) -> PluginMetadata {
         PluginMetadata {
@@ -91,6 +91,11 @@ impl<P: Plugin> PluginTestHarness<P> {
         self.plugin.reload(config).await
     }
 
+    /// Reconfigure the running plugin
+    pub async fn reconfigure(&mut self, config: serde_json::Value) -> Result<(), PluginError> {
+        self.plugin.reconfigure(config).await
+    }
+
     /// Check plugin health
     pub async fn health_check(&self) -> Result<HealthStatus, PluginError> {
         self.plugin.health_check().await
@@ -5,7 +5,12 @@
 pub mod builders;
 pub mod helpers;
 pub mod mocks;
+pub mod pipeline;
 
 pub use builders::{RequestBuilder, ResponseBuilder};
 pub use helpers::{PluginTestHarness, TestContext};
 pub use mocks::{MockAuthProvider, MockInterceptor, MockPlugin};
+pub use pipeline::{
+    MockUpstream, PipelineHarness, PipelineOutcome, RecordedRequest, StageAction, StageEvent,
+    StagePhase,
+};
@@ -0,0 +1,646 @@
+//! Harness for running a request through a chain of interceptors
+//!
+//! [`PipelineHarness`] runs interceptors in the order they were added, the
+//! way the gateway chains plugins: request interceptors front to back, then a
+//! [`MockUpstream`], then response interceptors back to front. A request
+//! interceptor that returns a response short-circuits the rest of the chain,
+//! and only the response interceptors of the stages before it see that
+//! response. Each step is recorded in the [`PipelineOutcome`].
+
+use super::helpers::TestContext;
+use crate::interceptor::{Body, InterceptorAction, RequestInterceptor, ResponseInterceptor};
+use crate::PluginError;
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+use http_body_util::{BodyExt, Full};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// One plugin in the pipeline, with whichever interceptor roles it has
+struct Stage {
+    name: String,
+    request: Option<Arc<dyn RequestInterceptor>>,
+    response: Option<Arc<dyn ResponseInterceptor>>,
+}
+
+/// Runs requests through an ordered chain of interceptors and a mock upstream
+pub struct PipelineHarness {
+    stages: Vec<Stage>,
+    upstream: MockUpstream,
+    context: TestContext,
+}
+
+impl std::fmt::Debug for PipelineHarness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipelineHarness")
+            .field(
+                "stages",
+                &self.stages.iter().map(|s| &s.name).collect::<Vec<_>>(),
+            )
+            .field("upstream", &self.upstream)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PipelineHarness {
+    /// Create an empty pipeline whose upstream answers `200 OK`
+    pub fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+            upstream: MockUpstream::ok(),
+            context: TestContext::new(),
+        }
+    }
+
+    /// Append a plugin that intercepts requests
+    pub fn request_interceptor<P: RequestInterceptor + 'static>(mut self, plugin: P) -> Self {
+        self.stages.push(Stage {
+            name: plugin.name().to_string(),
+            request: Some(Arc::new(plugin)),
+            response: None,
+        });
+        self
+    }
+
+    /// Append a plugin that intercepts responses
+    pub fn response_interceptor<P: ResponseInterceptor + 'static>(mut self, plugin: P) -> Self {
+        self.stages.push(Stage {
+            name: plugin.name().to_string(),
+            request: None,
+            response: Some(Arc::new(plugin)),
+        });
+        self
+    }
+
+    /// Append a plugin that intercepts both requests and responses
+    pub fn interceptor<P>(mut self, plugin: P) -> Self
+    where
+        P: RequestInterceptor + ResponseInterceptor + 'static,
+    {
+        let plugin = Arc::new(plugin);
+        self.stages.push(Stage {
+            name: plugin.name().to_string(),
+            request: Some(Arc::clone(&plugin) as Arc<dyn RequestInterceptor>),
+            response: Some(plugin),
+        });
+        self
+    }
+
+    /// Answer requests that pass every request interceptor with `upstream`
+    pub fn upstream(mut self, upstream: MockUpstream) -> Self {
+        self.upstream = upstream;
+        self
+    }
+
+    /// Use `context` for the request and response contexts
+    pub fn context(mut self, context: TestContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Run `req` through the pipeline
+    pub async fn run(&self, mut req: Request<Body>) -> PipelineOutcome {
+        let started = Instant::now();
+        let ctx = self.context.build_request_context();
+        let mut trace = Vec::new();
+
+        // Stages whose request interceptors ran, and so wrap the response.
+        let mut entered = self.stages.len();
+        let mut response = None;
+        for (i, stage) in self.stages.iter().enumerate() {
+            let Some(interceptor) = &stage.request else {
+                continue;
+            };
+            let result = interceptor.intercept_request(&mut req, &ctx).await;
+            let (action, outcome) = StageAction::from_result(result);
+            trace.push(StageEvent {
+                stage: stage.name.clone(),
+                phase: StagePhase::Request,
+                action,
+                headers: req.headers().clone(),
+            });
+            match outcome {
+                Outcome::Continue => {}
+                Outcome::Respond(res) => {
+                    entered = i;
+                    response = Some(res);
+                    break;
+                }
+                Outcome::Fail(e) => return PipelineOutcome::new(Err(e), trace, None),
+            }
+        }
+
+        let upstream_request = if response.is_none() {
+            let recorded = RecordedRequest::of(&req);
+            response = Some(self.upstream.respond(&req, recorded.clone()));
+            Some(recorded)
+        } else {
+            None
+        };
+        let mut response = response.expect("set by a stage or the upstream");
+
+        for stage in self.stages[..entered].iter().rev() {
+            let Some(interceptor) = &stage.response else {
+                continue;
+            };
+            let res_ctx = self
+                .context
+                .build_response_context(started.elapsed(), response.status().as_u16());
+            let result = interceptor
+                .intercept_response(&mut response, &res_ctx)
+                .await;
+            let (action, outcome) = StageAction::from_result(result);
+            let failure = match outcome {
+                Outcome::Continue => None,
+                Outcome::Respond(replacement) => {
+                    response = replacement;
+                    None
+                }
+                Outcome::Fail(e) => Some(e),
+            };
+            trace.push(StageEvent {
+                stage: stage.name.clone(),
+                phase: StagePhase::Response,
+                action,
+                headers: response.headers().clone(),
+            });
+            if let Some(e) = failure {
+                return PipelineOutcome::new(Err(e), trace, upstream_request);
+            }
+        }
+
+        PipelineOutcome::new(Ok(response), trace, upstream_request)
+    }
+}
+
+impl Default for PipelineHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which interceptor of a stage ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StagePhase {
+    /// The request interceptor
+    Request,
+    /// The response interceptor
+    Response,
+}
+
+/// What an interceptor did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageAction {
+    /// Returned [`InterceptorAction::Continue`]
+    Continue,
+    /// Returned [`InterceptorAction::Return`]: a short-circuit on the request
+    /// side, a replacement on the response side
+    Return,
+    /// Returned [`InterceptorAction::Abort`]
+    Abort,
+    /// Returned an error
+    Error,
+}
+
+enum Outcome {
+    Continue,
+    Respond(Response<Body>),
+    Fail(PluginError),
+}
+
+impl StageAction {
+    fn from_result(result: Result<InterceptorAction, PluginError>) -> (Self, Outcome) {
+        match result {
+            Ok(InterceptorAction::Continue) => (Self::Continue, Outcome::Continue),
+            Ok(InterceptorAction::Return(res)) => (Self::Return, Outcome::Respond(res)),
+            Ok(InterceptorAction::Abort(e)) => (Self::Abort, Outcome::Fail(e)),
+            Err(e) => (Self::Error, Outcome::Fail(e)),
+        }
+    }
+}
+
+/// One interceptor call in a pipeline run
+#[derive(Debug, Clone)]
+pub struct StageEvent {
+    /// Name of the plugin
+    pub stage: String,
+    /// Which of its interceptors ran
+    pub phase: StagePhase,
+    /// What it did
+    pub action: StageAction,
+    /// Request headers after a request interceptor, response headers after a
+    /// response interceptor
+    pub headers: HeaderMap,
+}
+
+/// A request as the mock upstream received it
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// Request method
+    pub method: Method,
+    /// Request URI
+    pub uri: Uri,
+    /// Request headers
+    pub headers: HeaderMap,
+}
+
+impl RecordedRequest {
+    fn of(req: &Request<Body>) -> Self {
+        Self {
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            headers: req.headers().clone(),
+        }
+    }
+}
+
+type Respond = dyn Fn(&Request<Body>) -> Response<Body> + Send + Sync;
+
+/// Upstream stand-in answering requests that pass every request interceptor
+#[derive(Clone)]
+pub struct MockUpstream {
+    respond: Arc<Respond>,
+    received: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl std::fmt::Debug for MockUpstream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockUpstream")
+            .field("calls", &self.call_count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockUpstream {
+    /// An upstream answering every request with `respond`
+    pub fn new<F>(respond: F) -> Self
+    where
+        F: Fn(&Request<Body>) -> Response<Body> + Send + Sync + 'static,
+    {
+        Self {
+            respond: Arc::new(respond),
+            received: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// An upstream answering `200 OK` with an empty body
+    pub fn ok() -> Self {
+        Self::status(StatusCode::OK)
+    }
+
+    /// An upstream answering `status` with an empty body
+    pub fn status(status: StatusCode) -> Self {
+        Self::new(move |_| {
+            Response::builder()
+                .status(status)
+                .body(Full::new(Bytes::new()))
+                .unwrap()
+        })
+    }
+
+    /// Requests received so far
+    pub fn received(&self) -> Vec<RecordedRequest> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Number of requests received so far
+    pub fn call_count(&self) -> usize {
+        self.received.lock().unwrap().len()
+    }
+
+    fn respond(&self, req: &Request<Body>, recorded: RecordedRequest) -> Response<Body> {
+        self.received.lock().unwrap().push(recorded);
+        (self.respond)(req)
+    }
+}
+
+/// Result of a [`PipelineHarness::run`], with assertion helpers
+#[derive(Debug)]
+pub struct PipelineOutcome {
+    /// Final response, or the error that aborted the pipeline
+    pub result: Result<Response<Body>, PluginError>,
+    /// Every interceptor call, in the order they ran
+    pub trace: Vec<StageEvent>,
+    /// The request as the upstream received it; `None` if it never got there
+    pub upstream_request: Option<RecordedRequest>,
+}
+
+impl PipelineOutcome {
+    fn new(
+        result: Result<Response<Body>, PluginError>,
+        trace: Vec<StageEvent>,
+        upstream_request: Option<RecordedRequest>,
+    ) -> Self {
+        Self {
+            result,
+            trace,
+            upstream_request,
+        }
+    }
+
+    /// The final response; panics if the pipeline failed
+    pub fn response(&self) -> &Response<Body> {
+        match &self.result {
+            Ok(res) => res,
+            Err(e) => panic!("pipeline failed: {e}"),
+        }
+    }
+
+    /// The error that aborted the pipeline; panics if it succeeded
+    pub fn error(&self) -> &PluginError {
+        match &self.result {
+            Ok(res) => panic!("pipeline succeeded with {}", res.status()),
+            Err(e) => e,
+        }
+    }
+
+    /// The final response body; panics if the pipeline failed
+    pub async fn body(self) -> Bytes {
+        match self.result {
+            Ok(res) => res.into_body().collect().await.unwrap().to_bytes(),
+            Err(e) => panic!("pipeline failed: {e}"),
+        }
+    }
+
+    /// Name of the stage whose request interceptor answered the request
+    pub fn short_circuited_by(&self) -> Option<&str> {
+        self.trace
+            .iter()
+            .find(|e| e.phase == StagePhase::Request && e.action == StageAction::Return)
+            .map(|e| e.stage.as_str())
+    }
+
+    /// Whether the request reached the upstream
+    pub fn reached_upstream(&self) -> bool {
+        self.upstream_request.is_some()
+    }
+
+    /// The recorded call of `stage`'s interceptor for `phase`
+    pub fn event(&self, stage: &str, phase: StagePhase) -> Option<&StageEvent> {
+        self.trace
+            .iter()
+            .find(|e| e.stage == stage && e.phase == phase)
+    }
+
+    /// Names of the stages that ran for `phase`, in order
+    pub fn stages(&self, phase: StagePhase) -> Vec<&str> {
+        self.trace
+            .iter()
+            .filter(|e| e.phase == phase)
+            .map(|e| e.stage.as_str())
+            .collect()
+    }
+
+    /// Assert the final response has `status`
+    pub fn assert_status(&self, status: StatusCode) -> &Self {
+        assert_eq!(self.response().status(), status, "final response status");
+        self
+    }
+
+    /// Assert the final response has header `name` set to `value`
+    pub fn assert_header(&self, name: &str, value: &str) -> &Self {
+        assert_eq!(
+            header(self.response().headers(), name),
+            Some(value),
+            "final response header {name}"
+        );
+        self
+    }
+
+    /// Assert the upstream received header `name` set to `value`
+    pub fn assert_upstream_header(&self, name: &str, value: &str) -> &Self {
+        let req = self
+            .upstream_request
+            .as_ref()
+            .expect("request did not reach the upstream");
+        assert_eq!(
+            header(&req.headers, name),
+            Some(value),
+            "upstream request header {name}"
+        );
+        self
+    }
+
+    /// Assert header `name` was `value` right after `stage` ran for `phase`
+    pub fn assert_header_after(
+        &self,
+        stage: &str,
+        phase: StagePhase,
+        name: &str,
+        value: &str,
+    ) -> &Self {
+        let event = self
+            .event(stage, phase)
+            .unwrap_or_else(|| panic!("stage {stage} did not run for {phase:?}"));
+        assert_eq!(
+            header(&event.headers, name),
+            Some(value),
+            "header {name} after {stage} ({phase:?})"
+        );
+        self
+    }
+
+    /// Assert `stage`'s request interceptor answered the request, so the
+    /// upstream was not called
+    pub fn assert_short_circuited_by(&self, stage: &str) -> &Self {
+        assert_eq!(
+            self.short_circuited_by(),
+            Some(stage),
+            "short-circuiting stage"
+        );
+        assert!(
+            !self.reached_upstream(),
+            "short-circuited request reached the upstream"
+        );
+        self
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{RequestContext, ResponseContext};
+    use crate::plugin::Plugin;
+    use crate::testing::RequestBuilder;
+    use async_trait::async_trait;
+
+    /// Sets `x-tenant` on requests and `x-served-by` on responses.
+    #[derive(Debug)]
+    struct Tenant;
+
+    /// Answers `403` to requests for the `blocked` tenant, echoing the tenant.
+    #[derive(Debug)]
+    struct Gate;
+
+    /// Aborts every response.
+    #[derive(Debug)]
+    struct Breaker;
+
+    macro_rules! plugin {
+        ($ty:ty, $name:literal) => {
+            #[async_trait]
+            impl Plugin for $ty {
+                fn name(&self) -> &str {
+                    $name
+                }
+                fn version(&self) -> &str {
+                    "1.0.0"
+                }
+                async fn init(&mut self, _config: serde_json::Value) -> Result<(), PluginError> {
+                    Ok(())
+                }
+                async fn start(&mut self) -> Result<(), PluginError> {
+                    Ok(())
+                }
+                async fn stop(&mut self) -> Result<(), PluginError> {
+                    Ok(())
+                }
+            }
+        };
+    }
+
+    plugin!(Tenant, "tenant");
+    plugin!(Gate, "gate");
+    plugin!(Breaker, "breaker");
+
+    #[async_trait]
+    impl RequestInterceptor for Tenant {
+        async fn intercept_request(
+            &self,
+            req: &mut Request<Body>,
+            _ctx: &RequestContext,
+        ) -> Result<InterceptorAction, PluginError> {
+            let tenant = if req.uri().path().starts_with("/blocked") {
+                "blocked"
+            } else {
+                "acme"
+            };
+            req.headers_mut()
+                .insert("x-tenant", tenant.parse().unwrap());
+            Ok(InterceptorAction::Continue)
+        }
+    }
+
+    #[async_trait]
+    impl ResponseInterceptor for Tenant {
+        async fn intercept_response(
+            &self,
+            res: &mut Response<Body>,
+            _ctx: &ResponseContext,
+        ) -> Result<InterceptorAction, PluginError> {
+            res.headers_mut()
+                .insert("x-served-by", "tenant".parse().unwrap());
+            Ok(InterceptorAction::Continue)
+        }
+    }
+
+    #[async_trait]
+    impl RequestInterceptor for Gate {
+        async fn intercept_request(
+            &self,
+            req: &mut Request<Body>,
+            _ctx: &RequestContext,
+        ) -> Result<InterceptorAction, PluginError> {
+            let tenant = req.headers().get("x-tenant").cloned();
+            if tenant.as_ref().is_some_and(|t| t == "blocked") {
+                let res = Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .body(Full::new(Bytes::from(format!(
+                        "tenant {} is blocked",
+                        tenant.unwrap().to_str().unwrap()
+                    ))))
+                    .unwrap();
+                return Ok(InterceptorAction::Return(res));
+            }
+            Ok(InterceptorAction::Continue)
+        }
+    }
+
+    #[async_trait]
+    impl ResponseInterceptor for Breaker {
+        async fn intercept_response(
+            &self,
+            _res: &mut Response<Body>,
+            _ctx: &ResponseContext,
+        ) -> Result<InterceptorAction, PluginError> {
+            Ok(InterceptorAction::Abort(PluginError::runtime("broken")))
+        }
+    }
+
+    fn echo_tenant() -> MockUpstream {
+        MockUpstream::new(|req| {
+            let tenant = req.headers()["x-tenant"].to_str().unwrap().to_string();
+            Response::new(Full::new(Bytes::from(tenant)))
+        })
+    }
+
+    #[tokio::test]
+    async fn second_stage_short_circuits_on_the_first_stages_mutation() {
+        let upstream = echo_tenant();
+        let pipeline = PipelineHarness::new()
+            .interceptor(Tenant)
+            .request_interceptor(Gate)
+            .upstream(upstream.clone());
+
+        let outcome = pipeline
+            .run(RequestBuilder::get("/blocked/orders").build())
+            .await;
+
+        outcome
+            .assert_header_after("tenant", StagePhase::Request, "x-tenant", "blocked")
+            .assert_short_circuited_by("gate")
+            .assert_status(StatusCode::FORBIDDEN)
+            // The first stage wraps the short-circuit response.
+            .assert_header("x-served-by", "tenant");
+        assert_eq!(outcome.stages(StagePhase::Response), vec!["tenant"]);
+        assert_eq!(upstream.call_count(), 0);
+        assert_eq!(outcome.body().await, "tenant blocked is blocked");
+    }
+
+    #[tokio::test]
+    async fn request_passes_every_stage_to_the_upstream() {
+        let upstream = echo_tenant();
+        let pipeline = PipelineHarness::new()
+            .interceptor(Tenant)
+            .request_interceptor(Gate)
+            .upstream(upstream.clone());
+
+        let outcome = pipeline.run(RequestBuilder::get("/orders").build()).await;
+
+        outcome
+            .assert_upstream_header("x-tenant", "acme")
+            .assert_status(StatusCode::OK)
+            .assert_header("x-served-by", "tenant");
+        assert_eq!(outcome.short_circuited_by(), None);
+        assert_eq!(outcome.stages(StagePhase::Request), vec!["tenant", "gate"]);
+        assert_eq!(upstream.received()[0].uri.path(), "/orders");
+        assert_eq!(outcome.body().await, "acme");
+    }
+
+    #[tokio::test]
+    async fn response_interceptors_run_back_to_front_and_abort() {
+        let pipeline = PipelineHarness::new()
+            .response_interceptor(Breaker)
+            .interceptor(Tenant);
+
+        let outcome = pipeline.run(RequestBuilder::get("/orders").build()).await;
+
+        assert!(outcome.reached_upstream());
+        assert_eq!(
+            outcome.stages(StagePhase::Response),
+            vec!["tenant", "breaker"]
+        );
+        assert_eq!(
+            outcome
+                .event("breaker", StagePhase::Response)
+                .unwrap()
+                .action,
+            StageAction::Abort
+        );
+        assert!(outcome.error().to_string().contains("broken"));
+    }
+}
@@ -1,5 +1,6 @@
 //! Plugin runtime error types
 
+use crate::schema::ConfigFieldError;
 use octopus_plugin_api::PluginError;
 use std::fmt;
 
@@ -34,6 +35,15 @@ pub enum PluginRuntimeError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    /// Configuration rejected by the plugin's config schema
+    #[error("Invalid configuration for plugin {plugin}: {}", join_errors(.errors))]
+    InvalidConfig {
+        /// Plugin name
+        plugin: String,
+        /// One entry per violation
+        errors: Vec<ConfigFieldError>,
+    },
+
     /// I/O error
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
@@ -87,6 +97,14 @@ impl PluginRuntimeError {
     }
 }
 
+fn join_errors(errors: &[ConfigFieldError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
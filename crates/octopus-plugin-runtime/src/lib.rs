@@ -8,6 +8,8 @@
 //! - **Lifecycle Management**: Init, start, stop, reload
 //! - **Hot Reload**: Update plugins without gateway restart
 //! - **Health Monitoring**: Track plugin health status
+//! - **Sandboxing**: Timeouts, panic isolation and crash-disabling for plugin calls
+//! - **WASM Plugins** (`wasm` feature): Sandboxed WebAssembly interceptors
 //!
 //! ## Example
 //!
@@ -43,11 +45,19 @@ pub mod error;
 pub mod hot_reload;
 pub mod manager;
 pub mod registry;
+pub mod sandbox;
+pub mod schema;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use error::{PluginRuntimeError, Result};
 pub use hot_reload::{HotReloadWatcher, ReloadEvent};
 pub use manager::{PluginManager, PluginStats};
 pub use registry::{PluginEntry, PluginRegistry, PluginState as RegistryPluginState};
+pub use sandbox::{PluginSandbox, SandboxConfig, SandboxError};
+pub use schema::{ConfigFieldError, ConfigSchema};
+#[cfg(feature = "wasm")]
+pub use wasm::{WasmPlugin, WasmPluginConfig};
 
 // Re-export plugin API types for convenience
 pub use octopus_plugin_api::{
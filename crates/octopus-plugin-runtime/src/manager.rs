@@ -77,6 +77,11 @@ impl PluginManager {
         self.registry.reload(name, config).await
     }
 
+    /// Apply a new configuration to a live plugin
+    pub async fn reconfigure(&self, name: &str, config: serde_json::Value) -> Result<()> {
+        self.registry.reconfigure(name, config).await
+    }
+
     /// Start all plugins
     pub async fn start_all(&self) -> Result<()> {
         info!("Starting all plugins");
@@ -102,6 +107,11 @@ impl PluginManager {
         self.registry.get(name)
     }
 
+    /// Get the config schema a plugin declares
+    pub fn config_schema(&self, name: &str) -> Result<Option<serde_json::Value>> {
+        self.registry.config_schema(name)
+    }
+
     /// List all plugins
     pub fn list(&self) -> Vec<PluginInfo> {
         self.registry.list()
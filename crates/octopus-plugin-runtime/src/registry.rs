@@ -1,6 +1,7 @@
 //! Plugin registry for managing plugin lifecycle
 
 use crate::error::{PluginRuntimeError, Result};
+use crate::schema::ConfigSchema;
 use dashmap::DashMap;
 use octopus_plugin_api::{HealthStatus, Plugin, PluginDependency, PluginInfo, PluginMetadata};
 use std::sync::Arc;
@@ -35,6 +36,9 @@ pub struct PluginEntry {
     /// Configuration
     pub config: Arc<parking_lot::RwLock<serde_json::Value>>,
 
+    /// Schema the configuration is validated against, if the plugin declares one
+    pub config_schema: Option<Arc<ConfigSchema>>,
+
     /// When the plugin was registered
     pub registered_at: Instant,
 
@@ -52,6 +56,21 @@ impl std::fmt::Debug for PluginEntry {
     }
 }
 
+impl PluginEntry {
+    fn validate_config(&self, name: &str, config: &serde_json::Value) -> Result<()> {
+        let Some(schema) = &self.config_schema else {
+            return Ok(());
+        };
+        schema.validate(config).map_err(|errors| {
+            warn!(plugin = %name, errors = errors.len(), "Plugin configuration rejected");
+            PluginRuntimeError::InvalidConfig {
+                plugin: name.to_string(),
+                errors,
+            }
+        })
+    }
+}
+
 /// Plugin state
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PluginState {
@@ -104,11 +123,17 @@ impl PluginRegistry {
         // Check for dependency cycles
         self.check_dependency_cycle(&name, &dependencies)?;
 
+        let config_schema = plugin
+            .config_schema()
+            .map(|schema| ConfigSchema::compile(&name, schema).map(Arc::new))
+            .transpose()?;
+
         let entry = PluginEntry {
             plugin: Arc::new(tokio::sync::RwLock::new(plugin)),
             metadata: metadata.clone(),
             state: Arc::new(parking_lot::RwLock::new(PluginState::Registered)),
             config: Arc::new(parking_lot::RwLock::new(serde_json::Value::Null)),
+            config_schema,
             registered_at: Instant::now(),
             started_at: Arc::new(parking_lot::RwLock::new(None)),
         };
@@ -141,6 +166,8 @@ impl PluginRegistry {
             }
         }
 
+        entry.validate_config(name, &config)?;
+
         // Initialize plugin
         let mut plugin = entry.plugin.write().await;
         match plugin.init(config.clone()).await {
@@ -258,6 +285,9 @@ impl PluginRegistry {
             .get(name)
             .ok_or_else(|| PluginRuntimeError::not_found(name))?;
 
+        // A rejected config leaves the running plugin untouched
+        entry.validate_config(name, &config)?;
+
         let mut plugin = entry.plugin.write().await;
         match plugin.reload(config.clone()).await {
             Ok(()) => {
@@ -273,6 +303,47 @@ impl PluginRegistry {
         }
     }
 
+    /// Apply a new configuration to a live plugin
+    ///
+    /// A started plugin gets [`Plugin::reconfigure`]; one that is initialized
+    /// or stopped is re-initialized and keeps its state. The plugin's lock is
+    /// held throughout, so no request sees a half-applied configuration, and
+    /// the stored config only changes once the plugin has accepted it.
+    pub async fn reconfigure(&self, name: &str, config: serde_json::Value) -> Result<()> {
+        let entry = self
+            .plugins
+            .get(name)
+            .ok_or_else(|| PluginRuntimeError::not_found(name))?
+            .clone();
+
+        entry.validate_config(name, &config)?;
+
+        let mut plugin = entry.plugin.write().await;
+        let state = entry.state.read().clone();
+        let result = match state {
+            PluginState::Started => plugin.reconfigure(config.clone()).await,
+            PluginState::Initialized | PluginState::Stopped => plugin.init(config.clone()).await,
+            PluginState::Registered | PluginState::Failed(_) => {
+                return Err(PluginRuntimeError::invalid_state(format!(
+                    "Plugin {name} is not initialized"
+                )));
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                *entry.config.write() = config;
+                info!(plugin = %name, "Plugin reconfigured");
+                Ok(())
+            }
+            Err(e) => {
+                *entry.state.write() = PluginState::Failed(e.to_string());
+                error!(plugin = %name, error = %e, "Plugin reconfiguration failed");
+                Err(e.into())
+            }
+        }
+    }
+
     /// Get plugin health status
     pub async fn health_check(&self, name: &str) -> Result<HealthStatus> {
         let entry = self
@@ -315,6 +386,15 @@ impl PluginRegistry {
         self.plugins.get(name).map(|e| e.clone())
     }
 
+    /// Config schema declared by a plugin (`None` if it declares none)
+    pub fn config_schema(&self, name: &str) -> Result<Option<serde_json::Value>> {
+        let entry = self
+            .plugins
+            .get(name)
+            .ok_or_else(|| PluginRuntimeError::not_found(name))?;
+        Ok(entry.config_schema.as_ref().map(|s| s.schema().clone()))
+    }
+
     /// List all plugins
     pub fn list(&self) -> Vec<PluginInfo> {
         self.plugins
@@ -451,6 +531,325 @@ mod tests {
         }
     }
 
+    /// Plugin declaring a config schema
+    #[derive(Debug)]
+    struct SchemaPlugin {
+        init_calls: usize,
+    }
+
+    #[async_trait]
+    impl Plugin for SchemaPlugin {
+        fn name(&self) -> &str {
+            "limiter"
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        fn config_schema(&self) -> Option<serde_json::Value> {
+            Some(serde_json::json!({
+                "type": "object",
+                "required": ["limit"],
+                "properties": { "limit": { "type": "integer", "minimum": 1 } }
+            }))
+        }
+
+        async fn init(
+            &mut self,
+            _config: serde_json::Value,
+        ) -> std::result::Result<(), PluginError> {
+            self.init_calls += 1;
+            Ok(())
+        }
+
+        async fn start(&mut self) -> std::result::Result<(), PluginError> {
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> std::result::Result<(), PluginError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_is_rejected_before_init() {
+        let registry = PluginRegistry::new();
+        registry
+            .register("limiter", Box::new(SchemaPlugin { init_calls: 0 }))
+            .await
+            .unwrap();
+
+        let err = registry
+            .initialize("limiter", serde_json::json!({ "limit": "ten" }))
+            .await
+            .unwrap_err();
+        let PluginRuntimeError::InvalidConfig { plugin, errors } = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(plugin, "limiter");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/limit");
+        assert!(err.to_string().contains("/limit"), "{err}");
+
+        // Still registered and never initialized
+        let entry = registry.get("limiter").unwrap();
+        assert_eq!(*entry.state.read(), PluginState::Registered);
+        registry
+            .initialize("limiter", serde_json::json!({ "limit": 10 }))
+            .await
+            .unwrap();
+        assert_eq!(*entry.state.read(), PluginState::Initialized);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_reload_keeps_running_config() {
+        let registry = PluginRegistry::new();
+        registry
+            .register("limiter", Box::new(SchemaPlugin { init_calls: 0 }))
+            .await
+            .unwrap();
+        registry
+            .initialize("limiter", serde_json::json!({ "limit": 10 }))
+            .await
+            .unwrap();
+        registry.start("limiter").await.unwrap();
+
+        let err = registry
+            .reload("limiter", serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PluginRuntimeError::InvalidConfig { .. }));
+
+        let entry = registry.get("limiter").unwrap();
+        assert_eq!(*entry.state.read(), PluginState::Started);
+        assert_eq!(*entry.config.read(), serde_json::json!({ "limit": 10 }));
+        assert!(registry.config_schema("limiter").unwrap().is_some());
+        assert!(registry.config_schema("missing").is_err());
+    }
+
+    /// Lifecycle calls seen by a [`RatePlugin`] or [`CountingPlugin`]
+    #[derive(Debug, Default)]
+    struct Calls {
+        init: std::sync::atomic::AtomicUsize,
+        start: std::sync::atomic::AtomicUsize,
+        stop: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Calls {
+        fn get(&self) -> (usize, usize, usize) {
+            use std::sync::atomic::Ordering::SeqCst;
+            (
+                self.init.load(SeqCst),
+                self.start.load(SeqCst),
+                self.stop.load(SeqCst),
+            )
+        }
+    }
+
+    /// Plugin counting its lifecycle calls, with the default
+    /// [`Plugin::reload`] and [`Plugin::reconfigure`]
+    #[derive(Debug)]
+    struct CountingPlugin {
+        calls: Arc<Calls>,
+    }
+
+    #[async_trait]
+    impl Plugin for CountingPlugin {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        async fn init(
+            &mut self,
+            _config: serde_json::Value,
+        ) -> std::result::Result<(), PluginError> {
+            self.calls
+                .init
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn start(&mut self) -> std::result::Result<(), PluginError> {
+            self.calls
+                .start
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> std::result::Result<(), PluginError> {
+            self.calls
+                .stop
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Plugin whose behaviour is a `rate` read from its config
+    #[derive(Debug)]
+    struct RatePlugin {
+        rate: Arc<std::sync::atomic::AtomicU64>,
+        calls: Arc<Calls>,
+        in_place: bool,
+    }
+
+    impl RatePlugin {
+        fn apply(&self, config: &serde_json::Value) {
+            let rate = config["rate"].as_u64().unwrap_or(0);
+            self.rate.store(rate, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait]
+    impl Plugin for RatePlugin {
+        fn name(&self) -> &str {
+            "rate"
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        async fn init(
+            &mut self,
+            config: serde_json::Value,
+        ) -> std::result::Result<(), PluginError> {
+            self.calls
+                .init
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.apply(&config);
+            Ok(())
+        }
+
+        async fn start(&mut self) -> std::result::Result<(), PluginError> {
+            self.calls
+                .start
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> std::result::Result<(), PluginError> {
+            self.calls
+                .stop
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn reconfigure(
+            &mut self,
+            config: serde_json::Value,
+        ) -> std::result::Result<(), PluginError> {
+            if !self.in_place {
+                return self.reload(config).await;
+            }
+            self.apply(&config);
+            Ok(())
+        }
+    }
+
+    async fn started_rate_plugin(
+        in_place: bool,
+    ) -> (
+        PluginRegistry,
+        Arc<std::sync::atomic::AtomicU64>,
+        Arc<Calls>,
+    ) {
+        let rate = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let calls = Arc::new(Calls::default());
+        let registry = PluginRegistry::new();
+        registry
+            .register(
+                "rate",
+                Box::new(RatePlugin {
+                    rate: rate.clone(),
+                    calls: calls.clone(),
+                    in_place,
+                }),
+            )
+            .await
+            .unwrap();
+        registry
+            .initialize("rate", serde_json::json!({ "rate": 10 }))
+            .await
+            .unwrap();
+        registry.start("rate").await.unwrap();
+        (registry, rate, calls)
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_applies_in_place() {
+        let (registry, rate, calls) = started_rate_plugin(true).await;
+
+        registry
+            .reconfigure("rate", serde_json::json!({ "rate": 50 }))
+            .await
+            .unwrap();
+
+        assert_eq!(rate.load(std::sync::atomic::Ordering::SeqCst), 50);
+        // No restart: one init and one start, never stopped
+        assert_eq!(calls.get(), (1, 1, 0));
+        let entry = registry.get("rate").unwrap();
+        assert_eq!(*entry.state.read(), PluginState::Started);
+        assert_eq!(*entry.config.read(), serde_json::json!({ "rate": 50 }));
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_falls_back_to_restart() {
+        let (registry, rate, calls) = started_rate_plugin(false).await;
+
+        registry
+            .reconfigure("rate", serde_json::json!({ "rate": 50 }))
+            .await
+            .unwrap();
+
+        assert_eq!(rate.load(std::sync::atomic::Ordering::SeqCst), 50);
+        assert_eq!(calls.get(), (2, 2, 1));
+        let entry = registry.get("rate").unwrap();
+        assert_eq!(*entry.state.read(), PluginState::Started);
+    }
+
+    #[tokio::test]
+    async fn test_default_reconfigure_restarts() {
+        let calls = Arc::new(Calls::default());
+        let registry = PluginRegistry::new();
+        registry
+            .register(
+                "counting",
+                Box::new(CountingPlugin {
+                    calls: calls.clone(),
+                }),
+            )
+            .await
+            .unwrap();
+
+        // Not yet initialized
+        assert!(registry
+            .reconfigure("counting", serde_json::json!({}))
+            .await
+            .is_err());
+
+        registry
+            .initialize("counting", serde_json::json!({}))
+            .await
+            .unwrap();
+        registry.start("counting").await.unwrap();
+        assert_eq!(calls.get(), (1, 1, 0));
+        registry
+            .reconfigure("counting", serde_json::json!({ "verbose": true }))
+            .await
+            .unwrap();
+
+        // The default reconfigure reloads: stop, init and start once more
+        assert_eq!(calls.get(), (2, 2, 1));
+        let entry = registry.get("counting").unwrap();
+        assert_eq!(*entry.state.read(), PluginState::Started);
+        assert_eq!(*entry.config.read(), serde_json::json!({ "verbose": true }));
+    }
+
     #[tokio::test]
     async fn test_plugin_registration() {
         let registry = PluginRegistry::new();
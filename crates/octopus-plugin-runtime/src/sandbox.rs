@@ -0,0 +1,239 @@
+//! Isolation of plugin calls
+//!
+//! A [`PluginSandbox`] runs a plugin's calls under a timeout and catches their
+//! panics, so a buggy plugin fails its own step instead of the task it runs on.
+//!
+//! A timeout drops the call: async work stops at its next `.await` and a
+//! `WasmPlugin` guest, which runs on a blocking thread,
+//! is interrupted. Synchronous work inside a native plugin's call can't be
+//! interrupted; it keeps its worker thread until it returns, so native
+//! plugins should move CPU-bound work to `tokio::task::spawn_blocking`.
+//! Consecutive panics and timeouts can disable the plugin for a while, like a
+//! circuit breaker: once the cooldown is over the next call is let through,
+//! and another failure disables the plugin again.
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Limits applied to a sandboxed plugin's calls
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxConfig {
+    /// Longest a single call may run (see the module docs for what a
+    /// timeout can interrupt)
+    pub timeout: Duration,
+    /// Consecutive panics or timeouts that disable the plugin (0 = never)
+    pub max_failures: u32,
+    /// How long a disabled plugin stays disabled
+    pub disable_for: Duration,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_failures: 0,
+            disable_for: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Why a sandboxed call produced no result
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SandboxError {
+    /// The call panicked; holds the panic message
+    #[error("plugin panicked: {0}")]
+    Panicked(String),
+
+    /// The call did not finish within the timeout
+    #[error("plugin call timed out after {0:?}")]
+    TimedOut(Duration),
+
+    /// The plugin is disabled after repeated failures; the call was not made
+    #[error("plugin disabled after repeated failures")]
+    Disabled,
+}
+
+/// Runs one plugin's calls with a timeout and panic isolation.
+#[derive(Debug)]
+pub struct PluginSandbox {
+    config: SandboxConfig,
+    consecutive_failures: AtomicU32,
+    disabled_until: parking_lot::Mutex<Option<Instant>>,
+}
+
+impl PluginSandbox {
+    /// Create a sandbox applying `config`
+    pub fn new(config: SandboxConfig) -> Self {
+        Self {
+            config,
+            consecutive_failures: AtomicU32::new(0),
+            disabled_until: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// The limits this sandbox applies
+    pub fn config(&self) -> &SandboxConfig {
+        &self.config
+    }
+
+    /// Run `call`, failing if it panics, times out or the plugin is disabled.
+    ///
+    /// Only panics and timeouts count as failures; a call that returns, even
+    /// with the plugin's own error, resets the count.
+    pub async fn call<F: Future>(&self, call: F) -> Result<F::Output, SandboxError> {
+        if self.is_disabled() {
+            return Err(SandboxError::Disabled);
+        }
+
+        let error =
+            match tokio::time::timeout(self.config.timeout, CatchUnwind(Box::pin(call))).await {
+                Ok(Ok(output)) => {
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
+                    return Ok(output);
+                }
+                Ok(Err(payload)) => SandboxError::Panicked(panic_message(payload.as_ref())),
+                Err(_) => SandboxError::TimedOut(self.config.timeout),
+            };
+        self.record_failure();
+        Err(error)
+    }
+
+    /// Whether calls are currently refused after repeated failures
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_until
+            .lock()
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Panics and timeouts since the last call that returned
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.config.max_failures > 0 && failures >= self.config.max_failures {
+            *self.disabled_until.lock() = Some(Instant::now() + self.config.disable_for);
+            tracing::warn!(
+                failures,
+                disable_for = ?self.config.disable_for,
+                "Plugin disabled after repeated failures"
+            );
+        }
+    }
+}
+
+/// Polls the inner future inside `catch_unwind`, resolving to the panic
+/// payload if a poll panics.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        (*msg).to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(max_failures: u32) -> PluginSandbox {
+        PluginSandbox::new(SandboxConfig {
+            timeout: Duration::from_millis(100),
+            max_failures,
+            disable_for: Duration::from_secs(10),
+        })
+    }
+
+    async fn panics() -> u32 {
+        panic!("boom");
+    }
+
+    async fn hangs() -> u32 {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        0
+    }
+
+    #[tokio::test]
+    async fn returns_the_calls_output() {
+        let sandbox = sandbox(0);
+        assert_eq!(sandbox.call(async { 7 }).await, Ok(7));
+    }
+
+    #[tokio::test]
+    async fn catches_panics() {
+        let sandbox = sandbox(0);
+        assert_eq!(
+            sandbox.call(panics()).await,
+            Err(SandboxError::Panicked("boom".to_string()))
+        );
+        assert_eq!(sandbox.consecutive_failures(), 1);
+
+        // The sandbox stays usable.
+        assert_eq!(sandbox.call(async { 1 }).await, Ok(1));
+        assert_eq!(sandbox.consecutive_failures(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_hanging_calls() {
+        let sandbox = sandbox(0);
+        assert_eq!(
+            sandbox.call(hangs()).await,
+            Err(SandboxError::TimedOut(Duration::from_millis(100)))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repeated_failures_disable_until_the_cooldown_ends() {
+        let sandbox = sandbox(2);
+        sandbox.call(panics()).await.unwrap_err();
+        assert!(!sandbox.is_disabled());
+        sandbox.call(hangs()).await.unwrap_err();
+        assert!(sandbox.is_disabled());
+        assert_eq!(sandbox.call(async { 1 }).await, Err(SandboxError::Disabled));
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        // One more failure right after the cooldown disables it again...
+        sandbox.call(panics()).await.unwrap_err();
+        assert!(sandbox.is_disabled());
+
+        // ...while a successful call closes the breaker.
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(sandbox.call(async { 1 }).await, Ok(1));
+        sandbox.call(panics()).await.unwrap_err();
+        assert!(!sandbox.is_disabled());
+    }
+
+    #[tokio::test]
+    async fn never_disables_without_a_limit() {
+        let sandbox = sandbox(0);
+        for _ in 0..5 {
+            sandbox.call(panics()).await.unwrap_err();
+        }
+        assert!(!sandbox.is_disabled());
+        assert_eq!(sandbox.consecutive_failures(), 5);
+    }
+}
@@ -0,0 +1,133 @@
+//! Plugin configuration schemas
+//!
+//! A plugin may declare a JSON Schema for its configuration
+//! ([`Plugin::config_schema`](octopus_plugin_api::Plugin::config_schema)).
+//! The registry compiles it when the plugin is registered and validates every
+//! configuration against it before the plugin sees it.
+
+use crate::error::{PluginRuntimeError, Result};
+use jsonschema::JSONSchema;
+use serde::Serialize;
+use std::fmt;
+
+/// A plugin's compiled configuration schema
+pub struct ConfigSchema {
+    schema: serde_json::Value,
+    compiled: JSONSchema,
+}
+
+impl fmt::Debug for ConfigSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigSchema")
+            .field("schema", &self.schema)
+            .finish()
+    }
+}
+
+/// One violation of a configuration schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigFieldError {
+    /// JSON pointer to the offending value (`/` for the document root)
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl fmt::Display for ConfigFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl ConfigSchema {
+    /// Compile the schema declared by plugin `plugin`
+    pub fn compile(plugin: &str, schema: serde_json::Value) -> Result<Self> {
+        let compiled = JSONSchema::compile(&schema).map_err(|e| {
+            PluginRuntimeError::config(format!(
+                "plugin {plugin} declares an invalid config schema: {e}"
+            ))
+        })?;
+        Ok(Self { schema, compiled })
+    }
+
+    /// The schema as declared
+    pub fn schema(&self) -> &serde_json::Value {
+        &self.schema
+    }
+
+    /// Validate `config`, listing every violation
+    pub fn validate(
+        &self,
+        config: &serde_json::Value,
+    ) -> std::result::Result<(), Vec<ConfigFieldError>> {
+        self.compiled.validate(config).map_err(|errors| {
+            errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    ConfigFieldError {
+                        path: if path.is_empty() {
+                            "/".to_string()
+                        } else {
+                            path
+                        },
+                        message: e.to_string(),
+                    }
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> ConfigSchema {
+        ConfigSchema::compile(
+            "limiter",
+            json!({
+                "type": "object",
+                "required": ["limit"],
+                "properties": {
+                    "limit": { "type": "integer", "minimum": 1 },
+                    "mode": { "enum": ["local", "global"] }
+                },
+                "additionalProperties": false
+            }),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        assert!(schema()
+            .validate(&json!({ "limit": 10, "mode": "local" }))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_config_lists_field_errors() {
+        let errors = schema()
+            .validate(&json!({ "limit": 0, "mode": "cluster" }))
+            .unwrap_err();
+
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(paths.contains(&"/limit"));
+        assert!(paths.contains(&"/mode"));
+    }
+
+    #[test]
+    fn test_missing_field_is_reported_at_root() {
+        let errors = schema().validate(&json!({})).unwrap_err();
+        assert_eq!(errors[0].path, "/");
+        assert!(errors[0].message.contains("limit"), "{}", errors[0].message);
+    }
+
+    #[test]
+    fn test_invalid_schema_is_rejected() {
+        let err = ConfigSchema::compile("broken", json!({ "type": 5 })).unwrap_err();
+        assert!(err.to_string().contains("broken"), "{err}");
+    }
+}
@@ -0,0 +1,677 @@
+//! Sandboxed WebAssembly plugins (`wasm` feature).
+//!
+//! A [`WasmPlugin`] runs a `.wasm` module (or its `.wat` text form) as a
+//! request and response interceptor. The module gets no WASI and no imports
+//! besides the host functions below; every hook call runs on a fresh instance
+//! with a fuel budget, on a blocking thread so a slow guest doesn't stall the
+//! async workers, and its linear memory is capped. Dropping a call before it
+//! returns, as a [`PluginSandbox`](crate::PluginSandbox) timeout does,
+//! interrupts the guest.
+//!
+//! ## Guest interface
+//!
+//! The module exports `memory` and either or both hooks, each returning an
+//! action code:
+//!
+//! - `on_request() -> i32`
+//! - `on_response() -> i32`
+//!
+//! | Code  | Action                                                          |
+//! |-------|-----------------------------------------------------------------|
+//! | `0`   | Continue                                                        |
+//! | `1`   | Answer with the response set by `respond` (short-circuits a request, replaces a response) |
+//! | other | Abort with an error                                             |
+//!
+//! Host functions are imported from module `octopus`. Strings are
+//! `(ptr, len)` pairs in guest memory; `(out, out_cap)` is a buffer the host
+//! copies a value into, returning the value's length (the value is not
+//! copied when it doesn't fit, so the guest can retry with a bigger buffer).
+//!
+//! | Function                                       | Description                                   |
+//! |------------------------------------------------|-----------------------------------------------|
+//! | `get_header(name, name_len, out, out_cap) -> i32` | Header value length, or `-1` if absent     |
+//! | `set_header(name, name_len, value, value_len) -> i32` | `0`, or `-1` for an invalid name or value |
+//! | `remove_header(name, name_len)`                 | Remove a header                              |
+//! | `get_method(out, out_cap) -> i32`               | Request method                               |
+//! | `get_path(out, out_cap) -> i32`                 | Request path                                 |
+//! | `get_status() -> i32`                           | Response status (`0` in `on_request`)        |
+//! | `respond(status, body, body_len)`               | Set the response for action `1`              |
+//!
+//! Headers are the request's in `on_request` and the response's in
+//! `on_response`.
+
+use crate::context::{RequestContext, ResponseContext};
+use crate::interceptor::{Body, InterceptorAction, RequestInterceptor, ResponseInterceptor};
+use crate::{Plugin, PluginError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::{Request, Response, StatusCode};
+use http_body_util::Full;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use wasmtime::{
+    Caller, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, UpdateDeadline,
+};
+
+/// WASM plugin configuration, the plugin's `config` in the gateway config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmPluginConfig {
+    /// Path to the module (`.wasm`, or `.wat` text)
+    pub path: PathBuf,
+
+    /// Fuel per hook call; running out aborts the call (default: 10 million)
+    #[serde(default = "default_max_fuel")]
+    pub max_fuel: u64,
+
+    /// Linear memory cap in bytes (default: 16 MiB)
+    #[serde(default = "default_max_memory")]
+    pub max_memory: usize,
+}
+
+fn default_max_fuel() -> u64 {
+    10_000_000
+}
+
+fn default_max_memory() -> usize {
+    16 * 1024 * 1024
+}
+
+/// Per-call host state: what the guest can read and what it changed.
+struct HostState {
+    limits: StoreLimits,
+    method: String,
+    path: String,
+    status: u16,
+    headers: HeaderMap,
+    response: Option<(i32, Vec<u8>)>,
+}
+
+/// A compiled module with its engine and host functions.
+struct WasmRuntime {
+    config: WasmPluginConfig,
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+}
+
+impl WasmRuntime {
+    fn load(config: WasmPluginConfig) -> Result<Self, PluginError> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        engine_config.epoch_interruption(true);
+        let engine = Engine::new(&engine_config).map_err(PluginError::init)?;
+        let module = Module::from_file(&engine, &config.path)
+            .map_err(|e| PluginError::init(format!("{}: {e:#}", config.path.display())))?;
+        let linker = host_functions(&engine).map_err(PluginError::init)?;
+        Ok(Self {
+            config,
+            engine,
+            module,
+            linker,
+        })
+    }
+
+    /// Run `hook` on a fresh instance on a blocking thread, returning its
+    /// action code and the final state, or `None` when the module doesn't
+    /// export `hook`.
+    async fn call(
+        self: Arc<Self>,
+        hook: &'static str,
+        method: String,
+        path: String,
+        status: u16,
+        headers: HeaderMap,
+    ) -> Result<Option<(i32, HostState)>, PluginError> {
+        if self.module.get_export(hook).is_none() {
+            return Ok(None);
+        }
+        let mut interrupt = Interrupt::new(&self.engine);
+        let cancelled = Arc::clone(&interrupt.cancelled);
+        let result = tokio::task::spawn_blocking(move || {
+            self.run(hook, method, path, status, headers, cancelled)
+        })
+        .await;
+        interrupt.armed = false;
+        result
+            .map_err(|e| PluginError::runtime(format!("{hook}: {e}")))?
+            .map(Some)
+    }
+
+    fn run(
+        &self,
+        hook: &str,
+        method: String,
+        path: String,
+        status: u16,
+        headers: HeaderMap,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<(i32, HostState), PluginError> {
+        let failed = |e: wasmtime::Error| PluginError::runtime(format!("{hook}: {e:#}"));
+
+        let state = HostState {
+            limits: wasmtime::StoreLimitsBuilder::new()
+                .memory_size(self.config.max_memory)
+                .instances(1)
+                .build(),
+            method,
+            path,
+            status,
+            headers,
+            response: None,
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.config.max_fuel).map_err(failed)?;
+        // Every epoch tick checks whether this call was dropped; ticks only
+        // happen when some call is.
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(move |_| {
+            if cancelled.load(Ordering::Relaxed) {
+                Err(wasmtime::Error::msg("call cancelled"))
+            } else {
+                Ok(UpdateDeadline::Continue(1))
+            }
+        });
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .map_err(failed)?;
+        let func = instance
+            .get_typed_func::<(), i32>(&mut store, hook)
+            .map_err(failed)?;
+        let code = func.call(&mut store, ()).map_err(failed)?;
+        Ok((code, store.into_data()))
+    }
+}
+
+/// Interrupts the guest of a call dropped while it runs, unless disarmed
+/// once the call returned.
+struct Interrupt {
+    engine: Engine,
+    cancelled: Arc<AtomicBool>,
+    armed: bool,
+}
+
+impl Interrupt {
+    fn new(engine: &Engine) -> Self {
+        Self {
+            engine: engine.clone(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            armed: true,
+        }
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        if self.armed {
+            self.cancelled.store(true, Ordering::Relaxed);
+            self.engine.increment_epoch();
+        }
+    }
+}
+
+/// The guest's exported linear memory.
+fn memory(caller: &mut Caller<'_, HostState>) -> wasmtime::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("module exports no `memory`"))
+}
+
+fn range(ptr: i32, len: i32) -> wasmtime::Result<Range<usize>> {
+    let start = usize::try_from(ptr)?;
+    Ok(start..start + usize::try_from(len)?)
+}
+
+/// Copy `len` bytes at `ptr` out of guest memory.
+fn read(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> wasmtime::Result<Vec<u8>> {
+    let memory = memory(caller)?;
+    memory
+        .data(&caller)
+        .get(range(ptr, len)?)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| wasmtime::Error::msg("out-of-bounds memory read"))
+}
+
+/// Copy `value` into the guest buffer `out` if it fits in `cap` bytes;
+/// returns the value's length either way.
+fn write(
+    caller: &mut Caller<'_, HostState>,
+    value: &[u8],
+    out: i32,
+    cap: i32,
+) -> wasmtime::Result<i32> {
+    let len = i32::try_from(value.len())?;
+    if len <= cap {
+        let memory = memory(caller)?;
+        memory
+            .data_mut(caller)
+            .get_mut(range(out, len)?)
+            .ok_or_else(|| wasmtime::Error::msg("out-of-bounds memory write"))?
+            .copy_from_slice(value);
+    }
+    Ok(len)
+}
+
+fn host_functions(engine: &Engine) -> wasmtime::Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(
+        "octopus",
+        "get_header",
+        |mut caller: Caller<'_, HostState>, name: i32, name_len: i32, out: i32, cap: i32| {
+            let name = read(&mut caller, name, name_len)?;
+            let value = HeaderName::from_bytes(&name)
+                .ok()
+                .and_then(|name| caller.data().headers.get(name))
+                .map(|v| v.as_bytes().to_vec());
+            match value {
+                Some(value) => write(&mut caller, &value, out, cap),
+                None => Ok(-1),
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "octopus",
+        "set_header",
+        |mut caller: Caller<'_, HostState>,
+         name: i32,
+         name_len: i32,
+         value: i32,
+         value_len: i32| {
+            let name = read(&mut caller, name, name_len)?;
+            let value = read(&mut caller, value, value_len)?;
+            match (
+                HeaderName::from_bytes(&name),
+                HeaderValue::from_bytes(&value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    caller.data_mut().headers.insert(name, value);
+                    Ok(0)
+                }
+                _ => Ok(-1),
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "octopus",
+        "remove_header",
+        |mut caller: Caller<'_, HostState>, name: i32, name_len: i32| {
+            let name = read(&mut caller, name, name_len)?;
+            if let Ok(name) = HeaderName::from_bytes(&name) {
+                caller.data_mut().headers.remove(name);
+            }
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "octopus",
+        "get_method",
+        |mut caller: Caller<'_, HostState>, out: i32, cap: i32| {
+            let method = caller.data().method.clone();
+            write(&mut caller, method.as_bytes(), out, cap)
+        },
+    )?;
+
+    linker.func_wrap(
+        "octopus",
+        "get_path",
+        |mut caller: Caller<'_, HostState>, out: i32, cap: i32| {
+            let path = caller.data().path.clone();
+            write(&mut caller, path.as_bytes(), out, cap)
+        },
+    )?;
+
+    linker.func_wrap("octopus", "get_status", |caller: Caller<'_, HostState>| {
+        i32::from(caller.data().status)
+    })?;
+
+    linker.func_wrap(
+        "octopus",
+        "respond",
+        |mut caller: Caller<'_, HostState>, status: i32, body: i32, body_len: i32| {
+            let body = read(&mut caller, body, body_len)?;
+            caller.data_mut().response = Some((status, body));
+            Ok(())
+        },
+    )?;
+
+    Ok(linker)
+}
+
+/// Turn a hook's action code into an [`InterceptorAction`].
+fn action(
+    hook: &str,
+    code: i32,
+    response: Option<(i32, Vec<u8>)>,
+) -> Result<InterceptorAction, PluginError> {
+    match code {
+        0 => Ok(InterceptorAction::Continue),
+        1 => {
+            let (status, body) = response.ok_or_else(|| {
+                PluginError::runtime(format!("{hook} returned 1 without calling respond"))
+            })?;
+            let status = u16::try_from(status)
+                .ok()
+                .and_then(|s| StatusCode::from_u16(s).ok())
+                .ok_or_else(|| PluginError::runtime(format!("{hook}: invalid status {status}")))?;
+            let mut response = Response::new(Full::new(Bytes::from(body)));
+            *response.status_mut() = status;
+            Ok(InterceptorAction::Return(response))
+        }
+        other => Ok(InterceptorAction::Abort(PluginError::runtime(format!(
+            "{hook} aborted with code {other}"
+        )))),
+    }
+}
+
+/// A sandboxed WebAssembly module run as a request/response interceptor.
+///
+/// Configured through [`Plugin::init`] with a [`WasmPluginConfig`].
+pub struct WasmPlugin {
+    name: String,
+    runtime: Option<Arc<WasmRuntime>>,
+}
+
+impl WasmPlugin {
+    /// An uninitialized plugin named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            runtime: None,
+        }
+    }
+
+    fn runtime(&self) -> Result<Arc<WasmRuntime>, PluginError> {
+        self.runtime
+            .clone()
+            .ok_or_else(|| PluginError::InvalidState(format!("{} is not initialized", self.name)))
+    }
+}
+
+impl fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("name", &self.name)
+            .field("config", &self.runtime.as_ref().map(|r| &r.config))
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn description(&self) -> &str {
+        "Sandboxed WebAssembly interceptor"
+    }
+
+    async fn init(&mut self, config: serde_json::Value) -> Result<(), PluginError> {
+        let config: WasmPluginConfig =
+            serde_json::from_value(config).map_err(PluginError::config)?;
+        self.runtime = Some(Arc::new(WasmRuntime::load(config)?));
+        Ok(())
+    }
+
+    async fn start(&mut self) -> Result<(), PluginError> {
+        self.runtime().map(|_| ())
+    }
+
+    async fn stop(&mut self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RequestInterceptor for WasmPlugin {
+    async fn intercept_request(
+        &self,
+        req: &mut Request<Body>,
+        _ctx: &RequestContext,
+    ) -> Result<InterceptorAction, PluginError> {
+        let called = self
+            .runtime()?
+            .call(
+                "on_request",
+                req.method().to_string(),
+                req.uri().path().to_string(),
+                0,
+                req.headers().clone(),
+            )
+            .await?;
+        let Some((code, state)) = called else {
+            return Ok(InterceptorAction::Continue);
+        };
+        *req.headers_mut() = state.headers;
+        action("on_request", code, state.response)
+    }
+}
+
+#[async_trait]
+impl ResponseInterceptor for WasmPlugin {
+    async fn intercept_response(
+        &self,
+        res: &mut Response<Body>,
+        _ctx: &ResponseContext,
+    ) -> Result<InterceptorAction, PluginError> {
+        let called = self
+            .runtime()?
+            .call(
+                "on_response",
+                String::new(),
+                String::new(),
+                res.status().as_u16(),
+                res.headers().clone(),
+            )
+            .await?;
+        let Some((code, state)) = called else {
+            return Ok(InterceptorAction::Continue);
+        };
+        *res.headers_mut() = state.headers;
+        action("on_response", code, state.response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn plugin(fixture: &str, max_memory: usize) -> WasmPlugin {
+        plugin_with_fuel(fixture, max_memory, 1_000_000).await
+    }
+
+    async fn plugin_with_fuel(fixture: &str, max_memory: usize, max_fuel: u64) -> WasmPlugin {
+        let mut plugin = WasmPlugin::new("test");
+        plugin
+            .init(serde_json::json!({
+                "path": format!("{}/tests/fixtures/{fixture}", env!("CARGO_MANIFEST_DIR")),
+                "max_fuel": max_fuel,
+                "max_memory": max_memory,
+            }))
+            .await
+            .unwrap();
+        plugin
+    }
+
+    fn request(headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::builder().uri("/orders");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Full::new(Bytes::new())).unwrap()
+    }
+
+    fn request_ctx() -> RequestContext {
+        RequestContext::new("req-1".to_string(), ([127, 0, 0, 1], 0).into())
+    }
+
+    fn response_ctx() -> ResponseContext {
+        ResponseContext::new("req-1".to_string(), Duration::ZERO, 200)
+    }
+
+    #[tokio::test]
+    async fn module_adds_headers_to_requests_and_responses() {
+        let plugin = plugin("add_header.wat", 1 << 20).await;
+
+        let mut req = request(&[("accept", "*/*")]);
+        let action = plugin
+            .intercept_request(&mut req, &request_ctx())
+            .await
+            .unwrap();
+        assert!(action.is_continue());
+        assert_eq!(req.headers()["x-wasm"], "hello");
+        assert_eq!(req.headers()["accept"], "*/*");
+
+        let mut res = Response::new(Full::new(Bytes::new()));
+        let action = plugin
+            .intercept_response(&mut res, &response_ctx())
+            .await
+            .unwrap();
+        assert!(action.is_continue());
+        assert_eq!(res.headers()["x-wasm"], "hello");
+    }
+
+    #[tokio::test]
+    async fn module_short_circuits_with_its_own_response() {
+        let plugin = plugin("short_circuit.wat", 1 << 20).await;
+
+        let mut req = request(&[]);
+        let action = plugin
+            .intercept_request(&mut req, &request_ctx())
+            .await
+            .unwrap();
+        let InterceptorAction::Return(response) = action else {
+            panic!("expected a short-circuit, got {action:?}");
+        };
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "denied");
+
+        let mut req = request(&[("authorization", "Bearer t")]);
+        let action = plugin
+            .intercept_request(&mut req, &request_ctx())
+            .await
+            .unwrap();
+        assert!(action.is_continue());
+
+        // No `on_response` export: responses pass through.
+        let mut res = Response::new(Full::new(Bytes::new()));
+        let action = plugin
+            .intercept_response(&mut res, &response_ctx())
+            .await
+            .unwrap();
+        assert!(action.is_continue());
+    }
+
+    #[tokio::test]
+    async fn fuel_stops_a_runaway_module() {
+        let plugin = plugin("greedy.wat", 1 << 20).await;
+
+        let err = plugin
+            .intercept_request(&mut request(&[]), &request_ctx())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn a_busy_guest_leaves_the_executor_free() {
+        // A single-threaded runtime: the timer below only fires in time if
+        // the guest runs off it.
+        let plugin = plugin_with_fuel("greedy.wat", 1 << 20, 500_000_000).await;
+        let call = tokio::spawn(async move {
+            plugin
+                .intercept_request(&mut request(&[]), &request_ctx())
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(!call.is_finished());
+        let err = call.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{err}");
+    }
+
+    #[test]
+    fn a_timed_out_guest_is_interrupted() {
+        // One blocking thread: the second call only runs if the first guest
+        // stopped when its call was dropped.
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .max_blocking_threads(1)
+            .enable_time()
+            .build()
+            .unwrap();
+        let second_call = rt.block_on(async {
+            let plugin = plugin_with_fuel("greedy.wat", 128 << 20, u64::MAX).await;
+            let spinning = tokio::time::timeout(
+                Duration::from_millis(20),
+                plugin.intercept_request(&mut request(&[]), &request_ctx()),
+            )
+            .await;
+            assert!(spinning.is_err());
+
+            let mut res = Response::new(Full::new(Bytes::new()));
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                plugin.intercept_response(&mut res, &response_ctx()),
+            )
+            .await
+        });
+        // Don't wait for a guest that is still spinning.
+        rt.shutdown_background();
+
+        let action = second_call
+            .expect("the spinning guest still holds the blocking thread")
+            .unwrap();
+        assert!(action.is_continue());
+    }
+
+    #[tokio::test]
+    async fn memory_cap_blocks_growth() {
+        let mut res = Response::new(Full::new(Bytes::new()));
+
+        let capped = plugin("greedy.wat", 1 << 20).await;
+        let action = capped
+            .intercept_response(&mut res, &response_ctx())
+            .await
+            .unwrap();
+        assert!(action.is_abort());
+
+        let roomy = plugin("greedy.wat", 128 << 20).await;
+        let action = roomy
+            .intercept_response(&mut res, &response_ctx())
+            .await
+            .unwrap();
+        assert!(action.is_continue());
+    }
+
+    #[tokio::test]
+    async fn init_rejects_a_missing_module() {
+        let mut plugin = WasmPlugin::new("missing");
+        let err = plugin
+            .init(serde_json::json!({"path": "/nonexistent/plugin.wasm"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PluginError::InitError(_)));
+        assert!(plugin.start().await.is_err());
+    }
+}
@@ -0,0 +1,14 @@
+;; Tags requests and responses with `x-wasm: hello`.
+(module
+  (import "octopus" "set_header" (func $set_header (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "x-wasm")
+  (data (i32.const 16) "hello")
+
+  (func (export "on_request") (result i32)
+    (drop (call $set_header (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 5)))
+    (i32.const 0))
+
+  (func (export "on_response") (result i32)
+    (drop (call $set_header (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 5)))
+    (i32.const 0)))
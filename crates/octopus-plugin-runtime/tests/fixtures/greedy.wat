@@ -0,0 +1,13 @@
+;; Misbehaves: spins forever on requests, grabs 64 MiB on responses.
+(module
+  (memory (export "memory") 1)
+
+  (func (export "on_request") (result i32)
+    (loop $spin (br $spin))
+    (i32.const 0))
+
+  (func (export "on_response") (result i32)
+    (if (result i32)
+      (i32.lt_s (memory.grow (i32.const 1024)) (i32.const 0))
+      (then (i32.const -1))
+      (else (i32.const 0)))))
@@ -0,0 +1,17 @@
+;; Answers 401 itself unless the request carries an `authorization` header.
+(module
+  (import "octopus" "get_header" (func $get_header (param i32 i32 i32 i32) (result i32)))
+  (import "octopus" "respond" (func $respond (param i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "authorization")
+  (data (i32.const 32) "denied")
+
+  (func (export "on_request") (result i32)
+    (if (result i32)
+      (i32.lt_s
+        (call $get_header (i32.const 0) (i32.const 13) (i32.const 64) (i32.const 256))
+        (i32.const 0))
+      (then
+        (call $respond (i32.const 401) (i32.const 32) (i32.const 6))
+        (i32.const 1))
+      (else (i32.const 0)))))
@@ -0,0 +1,136 @@
+//! Protocol dispatch
+//!
+//! An ordered table of [`ProtocolHandler`]s. A request goes to the first
+//! handler whose [`ProtocolMatch`](crate::handler::ProtocolMatch)ers accept it;
+//! when none does, the caller falls back to plain HTTP proxying.
+
+use crate::handler::ProtocolHandler;
+use http::Request;
+use std::sync::Arc;
+
+/// Ordered protocol handler table; first match wins
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolDispatcher {
+    handlers: Arc<[Arc<dyn ProtocolHandler>]>,
+}
+
+impl ProtocolDispatcher {
+    /// Create a dispatcher over `handlers`, tried in order
+    pub fn new(handlers: Vec<Arc<dyn ProtocolHandler>>) -> Self {
+        Self {
+            handlers: handlers.into(),
+        }
+    }
+
+    /// The registered handlers, in dispatch order
+    pub fn handlers(&self) -> &[Arc<dyn ProtocolHandler>] {
+        &self.handlers
+    }
+
+    /// Number of registered handlers
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    /// Whether no handlers are registered
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// The first handler advertising a match for the request head, if any.
+    ///
+    /// Only headers and path are inspected, so this works on streaming and
+    /// buffered requests alike.
+    pub fn select<B>(&self, req: &Request<B>) -> Option<&Arc<dyn ProtocolHandler>> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.matchers().iter().any(|m| m.matches(req)))
+    }
+}
+
+impl From<Vec<Arc<dyn ProtocolHandler>>> for ProtocolDispatcher {
+    fn from(handlers: Vec<Arc<dyn ProtocolHandler>>) -> Self {
+        Self::new(handlers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::ProtocolType;
+    use crate::{GraphQLHandler, GrpcHandler, SseHandler, WebSocketHandler};
+
+    fn dispatcher() -> ProtocolDispatcher {
+        ProtocolDispatcher::new(vec![
+            Arc::new(WebSocketHandler),
+            Arc::new(SseHandler),
+            Arc::new(GrpcHandler::new()),
+            Arc::new(GraphQLHandler::new("/graphql")),
+        ])
+    }
+
+    fn selected(req: &Request<()>) -> Option<ProtocolType> {
+        dispatcher().select(req).map(|h| h.protocol_type())
+    }
+
+    #[test]
+    fn websocket_upgrade_dispatches_to_websocket() {
+        let req = Request::builder()
+            .uri("/graphql")
+            .header("upgrade", "websocket")
+            .header("connection", "Upgrade")
+            .body(())
+            .unwrap();
+        // Registered ahead of GraphQL, so subscriptions over WS upgrade
+        assert_eq!(selected(&req), Some(ProtocolType::WebSocket));
+    }
+
+    #[test]
+    fn grpc_content_type_dispatches_to_grpc() {
+        for content_type in [
+            "application/grpc",
+            "application/grpc+proto",
+            "application/grpc-web",
+        ] {
+            let req = Request::builder()
+                .method("POST")
+                .uri("/users.UserService/GetUser")
+                .header("content-type", content_type)
+                .body(())
+                .unwrap();
+            assert_eq!(selected(&req), Some(ProtocolType::Grpc), "{content_type}");
+        }
+    }
+
+    #[test]
+    fn graphql_path_dispatches_to_graphql() {
+        let req = Request::builder()
+            .method("POST")
+            .uri("/graphql")
+            .header("content-type", "application/json")
+            .body(())
+            .unwrap();
+        assert_eq!(selected(&req), Some(ProtocolType::GraphQL));
+    }
+
+    #[test]
+    fn sse_accept_dispatches_to_sse() {
+        let req = Request::builder()
+            .uri("/events")
+            .header("accept", "text/event-stream")
+            .body(())
+            .unwrap();
+        assert_eq!(selected(&req), Some(ProtocolType::Sse));
+    }
+
+    #[test]
+    fn plain_http_falls_through() {
+        let req = Request::builder()
+            .uri("/api/users")
+            .header("content-type", "application/json")
+            .body(())
+            .unwrap();
+        assert_eq!(selected(&req), None);
+        assert!(ProtocolDispatcher::default().select(&req).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 //! GraphQL protocol handler with federation support
 
-use crate::handler::{ProtocolHandler, ProtocolType};
+use crate::handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
 use async_trait::async_trait;
 use bytes::Bytes;
 use http::{header, Method, Request, Response, StatusCode};
@@ -243,8 +243,8 @@ impl ProtocolHandler for GraphQLHandler {
         ProtocolType::GraphQL
     }
 
-    fn can_handle(&self, req: &Request<Full<Bytes>>) -> bool {
-        Self::is_graphql_request(req, &self.endpoint)
+    fn matchers(&self) -> Vec<ProtocolMatch> {
+        vec![ProtocolMatch::Path(self.endpoint.clone())]
     }
 
     async fn handle(&self, req: Request<Full<Bytes>>) -> Result<Response<Full<Bytes>>> {
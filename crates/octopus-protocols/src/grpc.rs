@@ -7,14 +7,20 @@
 //! - Deadline/timeout propagation (grpc-timeout header)
 //! - gRPC-Web (HTTP/1.1 compatible variant)
 //! - Proper trailers (grpc-status in trailers)
+//! - Message compression (`grpc-encoding`/`grpc-accept-encoding`): `identity`
+//!   and `gzip` messages are passed through untouched, with only their
+//!   length-prefixed framing and size limits checked; other encodings are
+//!   refused, and upstreams are only offered encodings both the client and the
+//!   gateway accept
 
-use crate::handler::{ProtocolHandler, ProtocolType};
+use crate::handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
 use async_trait::async_trait;
 use bytes::Bytes;
 use http::{header, Method, Request, Response, StatusCode};
 use http_body_util::Full;
 use octopus_core::{Error, Result};
 use std::collections::HashMap;
+use std::io::Read;
 use std::time::Duration;
 use tracing::{debug, warn};
 
@@ -27,9 +33,11 @@ pub struct GrpcHandler {
     services: HashMap<String, String>,
 
     /// Enable gRPC reflection proxy
-    #[allow(dead_code)]
     enable_reflection: bool,
 
+    /// Upstream for reflection requests (see [`Self::resolve_path`])
+    reflection_upstream: Option<String>,
+
     /// Maximum message size in bytes
     max_message_size: usize,
 
@@ -53,6 +61,7 @@ impl GrpcHandler {
         Self {
             services: HashMap::new(),
             enable_reflection: false,
+            reflection_upstream: None,
             max_message_size: 4 * 1024 * 1024, // 4 MB
             deadline_propagation: true,
             enable_grpc_web: false,
@@ -65,6 +74,7 @@ impl GrpcHandler {
         Self {
             services: config.services.clone(),
             enable_reflection: config.enable_reflection,
+            reflection_upstream: config.reflection_upstream.clone(),
             max_message_size: config.max_message_size,
             deadline_propagation: config.deadline_propagation,
             enable_grpc_web: config.enable_grpc_web,
@@ -116,6 +126,94 @@ impl GrpcHandler {
         self.services.get(service).map(String::as_str)
     }
 
+    /// Whether `service` is the gRPC server reflection service
+    #[must_use]
+    pub fn is_reflection_service(service: &str) -> bool {
+        REFLECTION_SERVICES.contains(&service)
+    }
+
+    /// Upstream for a request path (`/package.Service/Method`) from the
+    /// explicit mapping: a method entry wins over its service entry. With
+    /// reflection enabled, unmapped reflection requests go to the reflection
+    /// upstream. `None` leaves the request to path-prefix routing.
+    pub fn resolve_path(&self, path: &str) -> Option<&str> {
+        let (service, method) = Self::parse_grpc_path(path)?;
+        if let Some(upstream) = self.services.get(&format!("{service}/{method}")) {
+            return Some(upstream);
+        }
+        if let Some(upstream) = self.resolve_upstream(&service) {
+            return Some(upstream);
+        }
+        if self.enable_reflection && Self::is_reflection_service(&service) {
+            return self.reflection_upstream();
+        }
+        None
+    }
+
+    /// The configured reflection upstream, else the one upstream every
+    /// mapped service shares
+    fn reflection_upstream(&self) -> Option<&str> {
+        if let Some(ref upstream) = self.reflection_upstream {
+            return Some(upstream);
+        }
+        let mut upstreams = self.services.values();
+        let first = upstreams.next()?;
+        upstreams.all(|u| u == first).then_some(first.as_str())
+    }
+
+    /// Check the framing and size of the messages in a unary request body.
+    ///
+    /// Messages are forwarded as they are, compressed or not. Each frame's
+    /// compressed flag must agree with the request's `grpc-encoding`, and no
+    /// message may exceed the configured maximum size; `gzip` messages are
+    /// also inflated (up to that limit) to check their decoded size. A
+    /// message in an encoding the gateway can't inspect is refused (see
+    /// [`negotiate_grpc_encoding`]). gRPC-Web text bodies are base64 and not
+    /// inspected.
+    pub fn check_request_messages(
+        &self,
+        headers: &http::HeaderMap,
+        body: &Bytes,
+    ) -> std::result::Result<(), GrpcStatus> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if content_type.starts_with("application/grpc-web-text") {
+            return Ok(());
+        }
+
+        let encoding = grpc_encoding(headers);
+        for frame in decode_grpc_frames(body)? {
+            if frame.message.len() > self.max_message_size {
+                return Err(GrpcStatus::new(
+                    status_codes::RESOURCE_EXHAUSTED,
+                    format!(
+                        "gRPC message larger than max ({} vs. {})",
+                        frame.message.len(),
+                        self.max_message_size
+                    ),
+                ));
+            }
+            if !frame.compressed {
+                continue;
+            }
+            match encoding {
+                None | Some("identity") => {
+                    return Err(GrpcStatus::new(
+                        status_codes::INTERNAL,
+                        "compressed gRPC message without grpc-encoding",
+                    ));
+                }
+                Some("gzip") => {
+                    gzip_decoded_len(&frame.message, self.max_message_size)?;
+                }
+                Some(other) => return Err(unsupported_encoding(other)),
+            }
+        }
+        Ok(())
+    }
+
     /// Get gRPC status code from response headers/trailers
     pub fn get_grpc_status(res: &Response<Full<Bytes>>) -> Option<i32> {
         res.headers()
@@ -191,6 +289,201 @@ impl GrpcHandler {
     }
 }
 
+/// Server reflection services (`grpcurl` and friends), proxied when
+/// reflection is enabled
+pub const REFLECTION_SERVICES: &[&str] = &[
+    "grpc.reflection.v1alpha.ServerReflection",
+    "grpc.reflection.v1.ServerReflection",
+];
+
+/// Message compression header of a gRPC request or response
+pub const GRPC_ENCODING: &str = "grpc-encoding";
+
+/// Message encodings the sender of a gRPC request or response accepts
+pub const GRPC_ACCEPT_ENCODING: &str = "grpc-accept-encoding";
+
+/// Message encodings the gateway can inspect, and so accepts
+pub const SUPPORTED_GRPC_ENCODINGS: &[&str] = &["identity", "gzip"];
+
+/// Bytes before each gRPC message: a compressed flag and a big-endian
+/// `u32` length
+const GRPC_FRAME_HEADER_LEN: usize = 5;
+
+/// A gRPC status produced by the gateway itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcStatus {
+    /// Status code (see [`status_codes`])
+    pub code: i32,
+    /// Human-readable message for `grpc-message`
+    pub message: String,
+}
+
+impl GrpcStatus {
+    /// A status with `code` and `message`
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The status as a gRPC error response, advertising the message
+    /// encodings the gateway accepts
+    pub fn into_response(self) -> Result<Response<Full<Bytes>>> {
+        let mut response = GrpcHandler::error_response(self.code, &self.message)?;
+        if let Ok(accepted) = http::HeaderValue::from_str(&SUPPORTED_GRPC_ENCODINGS.join(",")) {
+            response
+                .headers_mut()
+                .insert(GRPC_ACCEPT_ENCODING, accepted);
+        }
+        Ok(response)
+    }
+}
+
+/// One length-prefixed message of a gRPC body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcFrame {
+    /// Whether the message is compressed with the stream's `grpc-encoding`
+    pub compressed: bool,
+    /// The message bytes, still compressed if `compressed` is set
+    pub message: Bytes,
+}
+
+/// Split a gRPC body into its length-prefixed messages
+pub fn decode_grpc_frames(body: &Bytes) -> std::result::Result<Vec<GrpcFrame>, GrpcStatus> {
+    let malformed = |reason: &str| {
+        GrpcStatus::new(
+            status_codes::INTERNAL,
+            format!("malformed gRPC message: {reason}"),
+        )
+    };
+
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while offset < body.len() {
+        let header = body
+            .get(offset..offset + GRPC_FRAME_HEADER_LEN)
+            .ok_or_else(|| malformed("truncated frame header"))?;
+        let compressed = match header[0] {
+            0 => false,
+            1 => true,
+            flag => return Err(malformed(&format!("invalid compressed flag {flag}"))),
+        };
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let start = offset + GRPC_FRAME_HEADER_LEN;
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= body.len())
+            .ok_or_else(|| malformed("truncated message"))?;
+        frames.push(GrpcFrame {
+            compressed,
+            message: body.slice(start..end),
+        });
+        offset = end;
+    }
+    Ok(frames)
+}
+
+/// Frame `message` as one length-prefixed gRPC message; a message over
+/// 4 GiB can't be framed
+pub fn encode_grpc_frame(
+    compressed: bool,
+    message: &[u8],
+) -> std::result::Result<Bytes, GrpcStatus> {
+    let len = u32::try_from(message.len()).map_err(|_| {
+        GrpcStatus::new(
+            status_codes::RESOURCE_EXHAUSTED,
+            format!("gRPC message too large to frame ({} bytes)", message.len()),
+        )
+    })?;
+    let mut frame = Vec::with_capacity(GRPC_FRAME_HEADER_LEN + message.len());
+    frame.push(u8::from(compressed));
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(message);
+    Ok(frame.into())
+}
+
+/// Negotiate the message encodings of a request from its headers.
+///
+/// A request compressed with an encoding the gateway can't inspect is
+/// refused with `UNIMPLEMENTED`, whose response advertises the accepted ones
+/// (see [`GrpcStatus::into_response`]). Otherwise the request's
+/// `grpc-accept-encoding` is narrowed to the encodings the gateway also
+/// accepts, so the upstream only answers in one both sides can handle;
+/// without a common one, `identity` is left.
+pub fn negotiate_grpc_encoding(
+    headers: &mut http::HeaderMap,
+) -> std::result::Result<(), GrpcStatus> {
+    if let Some(encoding) = grpc_encoding(headers) {
+        if !SUPPORTED_GRPC_ENCODINGS.contains(&encoding) {
+            return Err(unsupported_encoding(encoding));
+        }
+    }
+
+    if !headers.contains_key(GRPC_ACCEPT_ENCODING) {
+        return Ok(());
+    }
+    let mut accepted: Vec<&str> = Vec::new();
+    for value in headers.get_all(GRPC_ACCEPT_ENCODING) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for encoding in value.split(',').map(str::trim) {
+            if let Some(supported) = SUPPORTED_GRPC_ENCODINGS
+                .iter()
+                .find(|supported| supported.eq_ignore_ascii_case(encoding))
+            {
+                if !accepted.contains(supported) {
+                    accepted.push(supported);
+                }
+            }
+        }
+    }
+    if accepted.is_empty() {
+        accepted.push("identity");
+    }
+    if let Ok(accepted) = http::HeaderValue::from_str(&accepted.join(",")) {
+        headers.insert(GRPC_ACCEPT_ENCODING, accepted);
+    }
+    Ok(())
+}
+
+/// The status refusing a message in `encoding`
+fn unsupported_encoding(encoding: &str) -> GrpcStatus {
+    GrpcStatus::new(
+        status_codes::UNIMPLEMENTED,
+        format!("unsupported grpc-encoding '{encoding}'"),
+    )
+}
+
+/// The `grpc-encoding` of a request or response, if set
+fn grpc_encoding(headers: &http::HeaderMap) -> Option<&str> {
+    headers
+        .get(GRPC_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+/// Decoded length of a gzip message, reading at most `max` bytes of it
+fn gzip_decoded_len(message: &[u8], max: usize) -> std::result::Result<usize, GrpcStatus> {
+    let limit = u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1);
+    let mut decoder = flate2::read::GzDecoder::new(message).take(limit);
+    let decoded = std::io::copy(&mut decoder, &mut std::io::sink()).map_err(|e| {
+        GrpcStatus::new(
+            status_codes::INTERNAL,
+            format!("invalid gzip gRPC message: {e}"),
+        )
+    })?;
+    let decoded = usize::try_from(decoded).unwrap_or(usize::MAX);
+    if decoded > max {
+        return Err(GrpcStatus::new(
+            status_codes::RESOURCE_EXHAUSTED,
+            format!("decompressed gRPC message larger than max ({max})"),
+        ));
+    }
+    Ok(decoded)
+}
+
 /// Percent-encode a gRPC message for the grpc-message header (RFC 3986)
 fn percent_encode_grpc_message(msg: &str) -> String {
     msg.chars()
@@ -227,8 +520,9 @@ pub fn build_grpc_upstream_headers(original_headers: &http::HeaderMap) -> http::
             continue;
         }
 
-        // Forward everything else (grpc-*, custom metadata, content-type, authorization, etc.)
-        headers.insert(key.clone(), value.clone());
+        // Forward everything else (grpc-*, custom metadata, content-type, authorization, etc.),
+        // keeping repeated values such as several grpc-accept-encoding headers
+        headers.append(key.clone(), value.clone());
     }
 
     // Ensure TE: trailers is set (required for gRPC over HTTP/2)
@@ -243,8 +537,13 @@ impl ProtocolHandler for GrpcHandler {
         ProtocolType::Grpc
     }
 
-    fn can_handle(&self, req: &Request<Full<Bytes>>) -> bool {
-        Self::is_grpc_request(req)
+    fn matchers(&self) -> Vec<ProtocolMatch> {
+        // Also covers gRPC-Web (`application/grpc-web*`)
+        vec![ProtocolMatch::ContentType("application/grpc".to_string())]
+    }
+
+    fn streaming(&self) -> bool {
+        true
     }
 
     async fn handle(&self, req: Request<Full<Bytes>>) -> Result<Response<Full<Bytes>>> {
@@ -382,6 +681,79 @@ mod tests {
         assert_eq!(GrpcHandler::parse_grpc_path("//"), None);
     }
 
+    fn mapped_handler(reflection_upstream: Option<&str>) -> GrpcHandler {
+        let config = octopus_config::types::GrpcConfig {
+            enable_reflection: true,
+            reflection_upstream: reflection_upstream.map(str::to_string),
+            services: HashMap::from([
+                ("users.UserService".to_string(), "users".to_string()),
+                ("orders.OrderService".to_string(), "orders".to_string()),
+                (
+                    "orders.OrderService/StreamOrders".to_string(),
+                    "orders-stream".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        };
+        GrpcHandler::from_config(&config)
+    }
+
+    #[test]
+    fn test_resolve_path_routes_services_and_methods() {
+        let handler = mapped_handler(None);
+        assert_eq!(
+            handler.resolve_path("/users.UserService/GetUser"),
+            Some("users")
+        );
+        assert_eq!(
+            handler.resolve_path("/orders.OrderService/GetOrder"),
+            Some("orders")
+        );
+        // Method-level entry wins over the service entry
+        assert_eq!(
+            handler.resolve_path("/orders.OrderService/StreamOrders"),
+            Some("orders-stream")
+        );
+        assert_eq!(handler.resolve_path("/billing.Billing/Charge"), None);
+        assert_eq!(handler.resolve_path("/not-grpc"), None);
+    }
+
+    #[test]
+    fn test_resolve_path_reflection() {
+        let path = "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo";
+
+        // Services disagree and no reflection upstream: left to routes
+        assert_eq!(mapped_handler(None).resolve_path(path), None);
+        assert_eq!(
+            mapped_handler(Some("users")).resolve_path(path),
+            Some("users")
+        );
+        assert_eq!(
+            mapped_handler(Some("users"))
+                .resolve_path("/grpc.reflection.v1.ServerReflection/ServerReflectionInfo"),
+            Some("users")
+        );
+
+        // One backing upstream answers reflection without extra config
+        let config = octopus_config::types::GrpcConfig {
+            enable_reflection: true,
+            services: HashMap::from([("users.UserService".to_string(), "users".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            GrpcHandler::from_config(&config).resolve_path(path),
+            Some("users")
+        );
+
+        // Disabled reflection is not special-cased
+        let config = octopus_config::types::GrpcConfig {
+            enable_reflection: false,
+            reflection_upstream: Some("users".to_string()),
+            ..config
+        };
+        assert_eq!(GrpcHandler::from_config(&config).resolve_path(path), None);
+    }
+
     #[test]
     fn test_parse_grpc_timeout() {
         assert_eq!(
@@ -474,4 +846,152 @@ mod tests {
         assert!(!upstream.contains_key("connection")); // Stripped
         assert_eq!(upstream.get("te").unwrap(), "trailers");
     }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn grpc_headers(encoding: Option<&str>) -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/grpc".parse().unwrap());
+        if let Some(encoding) = encoding {
+            headers.insert(GRPC_ENCODING, encoding.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_gzip_unary_message_passes_through_intact() {
+        let message = gzip(&b"\x0a\x05alice".repeat(50));
+        let body = encode_grpc_frame(true, &message).unwrap();
+
+        let mut headers = grpc_headers(Some("gzip"));
+        headers.append(GRPC_ACCEPT_ENCODING, "gzip".parse().unwrap());
+        headers.append(GRPC_ACCEPT_ENCODING, "identity".parse().unwrap());
+
+        GrpcHandler::new()
+            .check_request_messages(&headers, &body)
+            .unwrap();
+        let frames = decode_grpc_frames(&body).unwrap();
+        assert_eq!(
+            frames,
+            vec![GrpcFrame {
+                compressed: true,
+                message: Bytes::from(message),
+            }]
+        );
+
+        // Encoding headers reach the upstream unchanged, every value kept.
+        let upstream = build_grpc_upstream_headers(&headers);
+        assert_eq!(upstream[GRPC_ENCODING], "gzip");
+        let accepted: Vec<_> = upstream.get_all(GRPC_ACCEPT_ENCODING).iter().collect();
+        assert_eq!(accepted, ["gzip", "identity"]);
+    }
+
+    #[test]
+    fn test_frames_split_and_round_trip() {
+        let mut body = encode_grpc_frame(false, b"one").unwrap().to_vec();
+        body.extend_from_slice(&encode_grpc_frame(false, b"").unwrap());
+        body.extend_from_slice(&encode_grpc_frame(true, b"three").unwrap());
+        let frames = decode_grpc_frames(&Bytes::from(body)).unwrap();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].message, "one");
+        assert!(frames[1].message.is_empty());
+        assert!(frames[2].compressed);
+        assert!(decode_grpc_frames(&Bytes::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_malformed_frames_are_internal_errors() {
+        let frame = encode_grpc_frame(false, b"message").unwrap();
+        for body in [
+            frame.slice(..3),
+            frame.slice(..frame.len() - 1),
+            Bytes::from_static(b"\x02\x00\x00\x00\x00"),
+        ] {
+            let err = decode_grpc_frames(&body).unwrap_err();
+            assert_eq!(err.code, status_codes::INTERNAL);
+        }
+    }
+
+    #[test]
+    fn test_compressed_flag_needs_an_encoding() {
+        let body = encode_grpc_frame(true, &gzip(b"hello")).unwrap();
+        let handler = GrpcHandler::new();
+
+        for encoding in [None, Some("identity")] {
+            let err = handler
+                .check_request_messages(&grpc_headers(encoding), &body)
+                .unwrap_err();
+            assert_eq!(err.code, status_codes::INTERNAL);
+        }
+        // Messages the gateway can't inflate are refused.
+        let err = handler
+            .check_request_messages(&grpc_headers(Some("snappy")), &body)
+            .unwrap_err();
+        assert_eq!(err.code, status_codes::UNIMPLEMENTED);
+    }
+
+    #[test]
+    fn test_accept_encoding_is_negotiated() {
+        let mut headers = grpc_headers(Some("gzip"));
+        headers.append(GRPC_ACCEPT_ENCODING, "snappy, GZIP".parse().unwrap());
+        headers.append(GRPC_ACCEPT_ENCODING, "deflate,identity".parse().unwrap());
+        negotiate_grpc_encoding(&mut headers).unwrap();
+        let accepted: Vec<_> = headers.get_all(GRPC_ACCEPT_ENCODING).iter().collect();
+        assert_eq!(accepted, ["gzip,identity"]);
+
+        // Nothing in common: the upstream may only answer uncompressed.
+        let mut headers = grpc_headers(None);
+        headers.insert(GRPC_ACCEPT_ENCODING, "snappy".parse().unwrap());
+        negotiate_grpc_encoding(&mut headers).unwrap();
+        assert_eq!(headers[GRPC_ACCEPT_ENCODING], "identity");
+
+        // No preference stays none.
+        let mut headers = grpc_headers(None);
+        negotiate_grpc_encoding(&mut headers).unwrap();
+        assert!(!headers.contains_key(GRPC_ACCEPT_ENCODING));
+    }
+
+    #[test]
+    fn test_unsupported_request_encoding_advertises_accepted_ones() {
+        let mut headers = grpc_headers(Some("snappy"));
+        let status = negotiate_grpc_encoding(&mut headers).unwrap_err();
+        assert_eq!(status.code, status_codes::UNIMPLEMENTED);
+
+        let response = status.into_response().unwrap();
+        assert_eq!(response.headers()["grpc-status"], "12");
+        assert_eq!(response.headers()[GRPC_ACCEPT_ENCODING], "identity,gzip");
+    }
+
+    #[test]
+    fn test_message_size_limit_applies_before_and_after_inflating() {
+        let handler = GrpcHandler {
+            max_message_size: 64,
+            ..GrpcHandler::new()
+        };
+
+        let plain = encode_grpc_frame(false, &[0; 65]).unwrap();
+        let err = handler
+            .check_request_messages(&grpc_headers(None), &plain)
+            .unwrap_err();
+        assert_eq!(err.code, status_codes::RESOURCE_EXHAUSTED);
+
+        // Small on the wire, too large once inflated.
+        let bomb = encode_grpc_frame(true, &gzip(&[0; 4096])).unwrap();
+        let err = handler
+            .check_request_messages(&grpc_headers(Some("gzip")), &bomb)
+            .unwrap_err();
+        assert_eq!(err.code, status_codes::RESOURCE_EXHAUSTED);
+
+        let garbage = encode_grpc_frame(true, b"not gzip").unwrap();
+        let err = handler
+            .check_request_messages(&grpc_headers(Some("gzip")), &garbage)
+            .unwrap_err();
+        assert_eq!(err.code, status_codes::INTERNAL);
+    }
 }
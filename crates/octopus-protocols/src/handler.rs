@@ -34,14 +34,69 @@ impl fmt::Display for ProtocolType {
     }
 }
 
+/// A request trait a protocol handler advertises for dispatch.
+///
+/// Matchers only inspect the head (headers, path), so they can be evaluated
+/// before the body is buffered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolMatch {
+    /// `Upgrade: <token>` (case-insensitive) with `Connection: upgrade`
+    Upgrade(String),
+    /// `Content-Type` starting with the given prefix, e.g. `application/grpc`
+    ContentType(String),
+    /// `Accept` containing the given media type, e.g. `text/event-stream`
+    Accept(String),
+    /// Request path equal to, or nested under, the given path
+    Path(String),
+}
+
+impl ProtocolMatch {
+    /// Whether the request head satisfies this matcher
+    pub fn matches<B>(&self, req: &Request<B>) -> bool {
+        let header =
+            |name: http::header::HeaderName| req.headers().get(name).and_then(|v| v.to_str().ok());
+        match self {
+            Self::Upgrade(token) => {
+                header(http::header::UPGRADE).is_some_and(|v| v.eq_ignore_ascii_case(token))
+                    && header(http::header::CONNECTION).is_some_and(|v| {
+                        v.split(',')
+                            .any(|part| part.trim().eq_ignore_ascii_case("upgrade"))
+                    })
+            }
+            Self::ContentType(prefix) => {
+                header(http::header::CONTENT_TYPE).is_some_and(|v| v.starts_with(prefix.as_str()))
+            }
+            Self::Accept(media_type) => {
+                header(http::header::ACCEPT).is_some_and(|v| v.contains(media_type.as_str()))
+            }
+            Self::Path(path) => req
+                .uri()
+                .path()
+                .strip_prefix(path.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+        }
+    }
+}
+
 /// Protocol handler trait
 #[async_trait]
 pub trait ProtocolHandler: Send + Sync + fmt::Debug {
     /// Get the protocol type this handler supports
     fn protocol_type(&self) -> ProtocolType;
 
+    /// What requests this handler claims; any one matching is enough
+    fn matchers(&self) -> Vec<ProtocolMatch>;
+
+    /// Whether the runtime serves this protocol on its streaming path, before
+    /// the request body is buffered; `handle` is then only a fallback
+    fn streaming(&self) -> bool {
+        false
+    }
+
     /// Check if this handler can handle the given request
-    fn can_handle(&self, req: &Request<Full<Bytes>>) -> bool;
+    fn can_handle(&self, req: &Request<Full<Bytes>>) -> bool {
+        self.matchers().iter().any(|m| m.matches(req))
+    }
 
     /// Handle the protocol-specific request
     async fn handle(&self, req: Request<Full<Bytes>>) -> Result<Response<Full<Bytes>>>;
@@ -58,6 +113,39 @@ mod tests {
         assert_eq!(ProtocolType::Grpc.to_string(), "grpc");
     }
 
+    fn request(path: &str, headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder().uri(path);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_protocol_match_upgrade_needs_connection_upgrade() {
+        let m = ProtocolMatch::Upgrade("websocket".to_string());
+        assert!(m.matches(&request(
+            "/ws",
+            &[
+                ("upgrade", "WebSocket"),
+                ("connection", "keep-alive, Upgrade")
+            ]
+        )));
+        assert!(!m.matches(&request("/ws", &[("upgrade", "websocket")])));
+        assert!(!m.matches(&request(
+            "/ws",
+            &[("upgrade", "h2c"), ("connection", "upgrade")]
+        )));
+    }
+
+    #[test]
+    fn test_protocol_match_path_respects_segments() {
+        let m = ProtocolMatch::Path("/graphql".to_string());
+        assert!(m.matches(&request("/graphql", &[])));
+        assert!(m.matches(&request("/graphql/playground", &[])));
+        assert!(!m.matches(&request("/graphqlx", &[])));
+    }
+
     #[test]
     fn test_protocol_type_equality() {
         assert_eq!(ProtocolType::Http, ProtocolType::Http);
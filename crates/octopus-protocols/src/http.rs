@@ -1,6 +1,6 @@
 //! HTTP/REST protocol handler
 
-use crate::handler::{ProtocolHandler, ProtocolType};
+use crate::handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
 use async_trait::async_trait;
 use bytes::Bytes;
 use http::{Method, Request, Response, StatusCode};
@@ -77,6 +77,11 @@ impl ProtocolHandler for HttpHandler {
         ProtocolType::Http
     }
 
+    fn matchers(&self) -> Vec<ProtocolMatch> {
+        // Plain HTTP is the fallback, never dispatched to by request traits
+        Vec::new()
+    }
+
     fn can_handle(&self, req: &Request<Full<Bytes>>) -> bool {
         // Can handle all HTTP requests, but check if method is allowed
         req.method() == Method::OPTIONS || self.is_method_allowed(req.method())
@@ -90,9 +95,7 @@ impl ProtocolHandler for HttpHandler {
 
         // For non-OPTIONS, this is just a pass-through
         // The actual proxying happens elsewhere
-        ResponseBuilder::new(StatusCode::OK)
-            .json()
-            .json_body(&serde_json::json!({"status": "ok"}))
+        ResponseBuilder::json_response(StatusCode::OK, &serde_json::json!({"status": "ok"}))
     }
 }
 
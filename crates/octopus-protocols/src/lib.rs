@@ -48,31 +48,43 @@
     clippy::cargo_common_metadata
 )]
 
+pub mod dispatch;
 pub mod graphql;
 pub mod grpc;
 pub mod handler;
 pub mod http;
+pub mod rest_graphql;
 pub mod sse;
+pub mod stream_transform;
 pub mod websocket;
 pub mod ws_proxy;
 
+pub use dispatch::ProtocolDispatcher;
 pub use graphql::{GraphQLHandler, GraphQLRequest, GraphQLResponse};
 pub use grpc::GrpcHandler;
-pub use handler::{ProtocolHandler, ProtocolType};
-pub use sse::{format_comment, format_data, format_event, is_sse_request};
-pub use websocket::{build_upgrade_response, is_websocket_upgrade, WebSocketConfig};
+pub use handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
+pub use rest_graphql::{RestGraphQLMapper, RestGraphQLMatch};
+pub use sse::{format_comment, format_data, format_event, is_sse_request, SseHandler};
+pub use stream_transform::{EventTransform, StreamFormat, TransformedStream};
+pub use websocket::{
+    build_upgrade_response, is_websocket_upgrade, WebSocketConfig, WebSocketHandler,
+};
 pub use ws_proxy::{
     build_forwarded_headers, connect_upstream, proxy_websocket_connected, WebSocketSessionStats,
 };
 
 /// Re-export commonly used types
 pub mod prelude {
+    pub use crate::dispatch::ProtocolDispatcher;
     pub use crate::graphql::{GraphQLHandler, GraphQLRequest, GraphQLResponse};
     pub use crate::grpc::GrpcHandler;
-    pub use crate::handler::{ProtocolHandler, ProtocolType};
+    pub use crate::handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
     pub use crate::http::HttpHandler;
-    pub use crate::sse::{format_comment, format_data, format_event, is_sse_request};
-    pub use crate::websocket::{build_upgrade_response, is_websocket_upgrade, WebSocketConfig};
+    pub use crate::rest_graphql::{RestGraphQLMapper, RestGraphQLMatch};
+    pub use crate::sse::{format_comment, format_data, format_event, is_sse_request, SseHandler};
+    pub use crate::websocket::{
+        build_upgrade_response, is_websocket_upgrade, WebSocketConfig, WebSocketHandler,
+    };
     pub use crate::ws_proxy::{
         build_forwarded_headers, connect_upstream, proxy_websocket_connected,
     };
@@ -0,0 +1,350 @@
+//! REST-to-GraphQL translation
+//!
+//! Serves selected REST routes from a GraphQL-only upstream: the REST request
+//! is turned into a GraphQL operation (path, query and body values mapped to
+//! variables) and the `data` of the GraphQL response is returned as the REST
+//! body.
+
+use crate::graphql::GraphQLRequest;
+use bytes::Bytes;
+use http::{Method, StatusCode};
+use octopus_config::types::RestGraphQLMapping;
+use octopus_core::{Error, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Compiled set of REST-to-GraphQL mappings.
+#[derive(Debug, Clone, Default)]
+pub struct RestGraphQLMapper {
+    mappings: Vec<CompiledMapping>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledMapping {
+    method: Method,
+    segments: Vec<Segment>,
+    config: RestGraphQLMapping,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+/// A REST request matched against a mapping, with its captured path
+/// parameters.
+#[derive(Debug, Clone)]
+pub struct RestGraphQLMatch<'a> {
+    /// The matched mapping.
+    pub mapping: &'a RestGraphQLMapping,
+    /// Path parameters captured from `:name` segments.
+    pub params: HashMap<String, String>,
+}
+
+impl RestGraphQLMapper {
+    /// Compile mappings from config. Mappings with an unknown method are
+    /// skipped with a warning.
+    #[must_use]
+    pub fn from_config(mappings: &[RestGraphQLMapping]) -> Self {
+        let mappings = mappings
+            .iter()
+            .filter_map(|config| {
+                let Ok(method) = Method::from_bytes(config.method.to_uppercase().as_bytes()) else {
+                    tracing::warn!(
+                        rest_path = %config.rest_path,
+                        method = %config.method,
+                        "Ignoring REST-to-GraphQL mapping with invalid method"
+                    );
+                    return None;
+                };
+                let segments = split_path(&config.rest_path)
+                    .map(|s| match s.strip_prefix(':') {
+                        Some(name) => Segment::Param(name.to_string()),
+                        None => Segment::Literal(s.to_string()),
+                    })
+                    .collect();
+                Some(CompiledMapping {
+                    method,
+                    segments,
+                    config: config.clone(),
+                })
+            })
+            .collect();
+        Self { mappings }
+    }
+
+    /// Whether any mappings are configured.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Find the first mapping for `method` and `path`.
+    #[must_use]
+    pub fn find(&self, method: &Method, path: &str) -> Option<RestGraphQLMatch<'_>> {
+        self.mappings
+            .iter()
+            .filter(|m| m.method == method)
+            .find_map(|m| {
+                let mut params = HashMap::new();
+                let mut parts = split_path(path);
+                for segment in &m.segments {
+                    let part = parts.next()?;
+                    match segment {
+                        Segment::Literal(lit) if lit == part => {}
+                        Segment::Literal(_) => return None,
+                        Segment::Param(name) => {
+                            params.insert(name.clone(), percent_decode(part));
+                        }
+                    }
+                }
+                if parts.next().is_some() {
+                    return None;
+                }
+                Some(RestGraphQLMatch {
+                    mapping: &m.config,
+                    params,
+                })
+            })
+    }
+}
+
+impl RestGraphQLMatch<'_> {
+    /// Build the GraphQL request for this REST call from its query string and
+    /// (JSON) body.
+    pub fn build_request(&self, query: Option<&str>, body: &[u8]) -> Result<GraphQLRequest> {
+        let mut variables = Map::new();
+        if self.mapping.variable_mapping.is_empty() {
+            for (name, value) in &self.params {
+                variables.insert(name.clone(), Value::String(value.clone()));
+            }
+        } else {
+            let query_params: HashMap<String, String> = query
+                .map(|q| {
+                    url::form_urlencoded::parse(q.as_bytes())
+                        .into_owned()
+                        .collect()
+                })
+                .unwrap_or_default();
+            let body: Option<Value> = if body.is_empty() {
+                None
+            } else {
+                Some(serde_json::from_slice(body).map_err(|e| {
+                    Error::InvalidRequest(format!("Request body is not valid JSON: {e}"))
+                })?)
+            };
+
+            for (variable, source) in &self.mapping.variable_mapping {
+                let (source, coerce) = match source.rsplit_once(':') {
+                    Some((source, ty)) => (source, Some(ty)),
+                    None => (source.as_str(), None),
+                };
+                let value = match source.split_once('.') {
+                    Some(("path", name)) => self.params.get(name).cloned().map(Value::String),
+                    Some(("query", name)) => query_params.get(name).cloned().map(Value::String),
+                    Some(("body", field)) => body.as_ref().and_then(|b| b.get(field)).cloned(),
+                    None if source == "body" => body.clone(),
+                    _ => {
+                        return Err(Error::Config(format!(
+                            "Invalid variable source for ${variable}: {source}"
+                        )))
+                    }
+                };
+                // Absent sources are left out so GraphQL defaults apply.
+                let Some(value) = value else { continue };
+                let value = match coerce {
+                    Some(ty) => coerce_value(value, ty).ok_or_else(|| {
+                        Error::InvalidRequest(format!("${variable} is not a valid {ty}"))
+                    })?,
+                    None => value,
+                };
+                variables.insert(variable.clone(), value);
+            }
+        }
+
+        Ok(GraphQLRequest {
+            query: self.mapping.operation.clone(),
+            operation_name: None,
+            variables: (!variables.is_empty()).then_some(Value::Object(variables)),
+        })
+    }
+
+    /// Turn the upstream GraphQL response into the REST response.
+    ///
+    /// Non-2xx upstream responses pass through. A response with errors and no
+    /// data becomes `502` carrying the errors; a missing or null result
+    /// becomes `404`. Otherwise the body is `data` (or its `result_field`).
+    #[must_use]
+    pub fn unwrap_response(&self, status: StatusCode, body: &[u8]) -> (StatusCode, Bytes) {
+        if !status.is_success() {
+            return (status, Bytes::copy_from_slice(body));
+        }
+        let Ok(response) = serde_json::from_slice::<Value>(body) else {
+            return (
+                StatusCode::BAD_GATEWAY,
+                error_body("Upstream returned an invalid GraphQL response"),
+            );
+        };
+
+        let data = response.get("data").filter(|d| !d.is_null());
+        let errors = response
+            .get("errors")
+            .and_then(Value::as_array)
+            .filter(|e| !e.is_empty());
+        let Some(data) = data else {
+            return match errors {
+                Some(errors) => (
+                    StatusCode::BAD_GATEWAY,
+                    Bytes::from(serde_json::json!({ "errors": errors }).to_string()),
+                ),
+                None => (StatusCode::NOT_FOUND, error_body("Not found")),
+            };
+        };
+
+        let result = match &self.mapping.result_field {
+            Some(field) => data.get(field).filter(|v| !v.is_null()),
+            None => Some(data),
+        };
+        match result {
+            Some(result) => (StatusCode::OK, Bytes::from(result.to_string())),
+            None => (StatusCode::NOT_FOUND, error_body("Not found")),
+        }
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn coerce_value(value: Value, ty: &str) -> Option<Value> {
+    let Value::String(s) = value else {
+        return Some(value);
+    };
+    match ty {
+        "int" => s.parse::<i64>().ok().map(Value::from),
+        "float" => s.parse::<f64>().ok().map(Value::from),
+        "bool" => s.parse::<bool>().ok().map(Value::from),
+        _ => None,
+    }
+}
+
+fn error_body(message: &str) -> Bytes {
+    Bytes::from(serde_json::json!({ "error": message }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user_mapping() -> RestGraphQLMapping {
+        RestGraphQLMapping {
+            rest_path: "/users/:id".to_string(),
+            method: "GET".to_string(),
+            upstream: "users-graphql".to_string(),
+            endpoint: "/graphql".to_string(),
+            operation: "query($id: ID!) { user(id: $id) { id name } }".to_string(),
+            variable_mapping: HashMap::new(),
+            result_field: Some("user".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_get_user_maps_to_query_and_unwraps_data() {
+        let mapper = RestGraphQLMapper::from_config(&[user_mapping()]);
+        let matched = mapper.find(&Method::GET, "/users/42").unwrap();
+        assert_eq!(matched.mapping.upstream, "users-graphql");
+
+        let request = matched.build_request(None, b"").unwrap();
+        assert_eq!(
+            request.query,
+            "query($id: ID!) { user(id: $id) { id name } }"
+        );
+        assert_eq!(request.variables, Some(json!({ "id": "42" })));
+
+        let upstream = json!({ "data": { "user": { "id": "42", "name": "Ada" } } });
+        let (status, body) =
+            matched.unwrap_response(StatusCode::OK, upstream.to_string().as_bytes());
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({ "id": "42", "name": "Ada" }));
+    }
+
+    #[test]
+    fn test_find_requires_method_and_full_path() {
+        let mapper = RestGraphQLMapper::from_config(&[user_mapping()]);
+        assert!(mapper.find(&Method::POST, "/users/42").is_none());
+        assert!(mapper.find(&Method::GET, "/users").is_none());
+        assert!(mapper.find(&Method::GET, "/users/42/posts").is_none());
+        assert!(mapper.find(&Method::GET, "/accounts/42").is_none());
+    }
+
+    #[test]
+    fn test_variable_mapping_sources_and_coercion() {
+        let mut mapping = user_mapping();
+        mapping.rest_path = "/teams/:team/members".to_string();
+        mapping.method = "POST".to_string();
+        mapping.operation = "mutation($team: ID!, $limit: Int, $input: MemberInput!) { addMember(team: $team, input: $input) { id } }".to_string();
+        mapping.variable_mapping = HashMap::from([
+            ("team".to_string(), "path.team".to_string()),
+            ("limit".to_string(), "query.limit:int".to_string()),
+            ("input".to_string(), "body".to_string()),
+        ]);
+        let mapper = RestGraphQLMapper::from_config(&[mapping]);
+        let matched = mapper.find(&Method::POST, "/teams/core/members").unwrap();
+
+        let request = matched
+            .build_request(Some("limit=5"), br#"{"name":"Ada"}"#)
+            .unwrap();
+        assert_eq!(
+            request.variables,
+            Some(json!({ "team": "core", "limit": 5, "input": { "name": "Ada" } }))
+        );
+
+        assert!(matched.build_request(Some("limit=five"), b"{}").is_err());
+    }
+
+    #[test]
+    fn test_unwrap_response_errors_and_missing_results() {
+        let mapper = RestGraphQLMapper::from_config(&[user_mapping()]);
+        let matched = mapper.find(&Method::GET, "/users/a%20b").unwrap();
+        assert_eq!(matched.params["id"], "a b");
+
+        let missing = json!({ "data": { "user": null } }).to_string();
+        let (status, _) = matched.unwrap_response(StatusCode::OK, missing.as_bytes());
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let failed = json!({ "data": null, "errors": [{ "message": "boom" }] }).to_string();
+        let (status, body) = matched.unwrap_response(StatusCode::OK, failed.as_bytes());
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["errors"][0]["message"], "boom");
+
+        let (status, _) = matched.unwrap_response(StatusCode::SERVICE_UNAVAILABLE, b"down");
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
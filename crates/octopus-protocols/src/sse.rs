@@ -3,9 +3,14 @@
 //! Detection and formatting helpers for SSE streams.
 //! The actual SSE proxying is handled in `octopus-runtime/src/handler.rs`
 //! via `handle_sse_proxy()` which streams the upstream `Incoming` body
-//! directly to the client without buffering.
+//! directly to the client without buffering, transforming JSON events on
+//! the way when the route has response rules (see [`crate::stream_transform`]).
 
-use http::{header, Request};
+use crate::handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, Request, Response};
+use http_body_util::Full;
 
 /// Check if a request is for an SSE stream.
 ///
@@ -58,10 +63,38 @@ pub fn format_event_with_id(id: &str, event: &str, data: &str) -> String {
 }
 
 /// SSE-specific headers that should be set on streaming responses
-pub const SSE_CONTENT_TYPE: &str = "text/event-stream";
+pub use octopus_core::response::SSE_CONTENT_TYPE;
 /// Cache-Control value for SSE responses
 pub const SSE_CACHE_CONTROL: &str = "no-cache";
 
+/// Dispatch entry for SSE streams, which the runtime proxies unbuffered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SseHandler;
+
+#[async_trait]
+impl ProtocolHandler for SseHandler {
+    fn protocol_type(&self) -> ProtocolType {
+        ProtocolType::Sse
+    }
+
+    fn matchers(&self) -> Vec<ProtocolMatch> {
+        vec![ProtocolMatch::Accept(SSE_CONTENT_TYPE.to_string())]
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    async fn handle(
+        &self,
+        _req: Request<Full<Bytes>>,
+    ) -> octopus_core::Result<Response<Full<Bytes>>> {
+        Err(octopus_core::Error::InvalidRequest(
+            "SSE streams cannot be served from a buffered request".to_string(),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,457 @@
+//! Per-event transformation of streamed responses
+//!
+//! SSE (`text/event-stream`) and NDJSON (`application/x-ndjson`) responses
+//! are transformed one event at a time as they arrive: [`TransformedStream`]
+//! splits the upstream body into events, hands each JSON payload to an
+//! [`EventTransform`] and emits the result as its own frame. Nothing beyond
+//! the event in flight is buffered.
+//!
+//! Events whose payload isn't JSON pass through unchanged, as do SSE
+//! comments and events without `data`. Events larger than the size cap are
+//! dropped: they can't be transformed, and passing them on untransformed
+//! would leak what the rules redact.
+
+use bytes::{Buf, Bytes, BytesMut};
+use http_body::{Body, Frame, SizeHint};
+use pin_project::pin_project;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub use octopus_core::response::{StreamFormat, NDJSON_CONTENT_TYPE};
+
+/// Default largest event transformed (1 MiB)
+const DEFAULT_MAX_EVENT_SIZE: usize = 1024 * 1024;
+
+/// End of the first complete event in `buf`, including its terminator
+fn event_end(format: StreamFormat, buf: &[u8]) -> Option<usize> {
+    match format {
+        StreamFormat::Ndjson => buf.iter().position(|&b| b == b'\n').map(|i| i + 1),
+        StreamFormat::EventStream => {
+            let lf = find(buf, b"\n\n").map(|i| i + 2);
+            let crlf = find(buf, b"\r\n\r\n").map(|i| i + 4);
+            match (lf, crlf) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// A rule applied to each event of a stream
+pub trait EventTransform: Send + Sync {
+    /// Transform one event's JSON payload in place; `false` drops the event
+    fn transform(&self, event: &mut Value) -> bool;
+}
+
+impl<F> EventTransform for F
+where
+    F: Fn(&mut Value) -> bool + Send + Sync,
+{
+    fn transform(&self, event: &mut Value) -> bool {
+        self(event)
+    }
+}
+
+/// A response body transformed event by event
+#[pin_project]
+pub struct TransformedStream<B> {
+    #[pin]
+    inner: B,
+    format: StreamFormat,
+    transform: Arc<dyn EventTransform>,
+    max_event_size: usize,
+    /// Start of the event in flight
+    buffer: BytesMut,
+    /// Dropping an oversized event until its terminator
+    oversized: bool,
+    /// Frames ready to be returned, one per event
+    ready: VecDeque<Frame<Bytes>>,
+    done: bool,
+}
+
+impl<B> TransformedStream<B> {
+    /// Transform the events of `inner` with `transform`
+    pub fn new(inner: B, format: StreamFormat, transform: Arc<dyn EventTransform>) -> Self {
+        Self {
+            inner,
+            format,
+            transform,
+            max_event_size: DEFAULT_MAX_EVENT_SIZE,
+            buffer: BytesMut::new(),
+            oversized: false,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Largest event transformed; larger ones are dropped
+    pub fn max_event_size(mut self, max_event_size: usize) -> Self {
+        self.max_event_size = max_event_size;
+        self
+    }
+}
+
+impl<B> fmt::Debug for TransformedStream<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransformedStream")
+            .field("format", &self.format)
+            .field("max_event_size", &self.max_event_size)
+            .field("buffered", &self.buffer.len())
+            .finish()
+    }
+}
+
+/// The per-event state of a [`TransformedStream`], split from its pinned body
+struct Events<'a> {
+    format: StreamFormat,
+    transform: &'a dyn EventTransform,
+    max_event_size: usize,
+    buffer: &'a mut BytesMut,
+    oversized: &'a mut bool,
+    ready: &'a mut VecDeque<Frame<Bytes>>,
+}
+
+impl Events<'_> {
+    fn push(&mut self, data: Bytes) {
+        self.buffer.extend_from_slice(&data);
+
+        // Only the new bytes (and a partial terminator before them) can
+        // complete an event
+        let mut from = self.buffer.len().saturating_sub(data.len() + 3);
+        while let Some(end) = event_end(self.format, &self.buffer[from..]) {
+            let event = self.buffer.split_to(from + end).freeze();
+            // The first event after an oversized one is that event's tail
+            if !std::mem::take(self.oversized) {
+                self.event(event);
+            }
+            from = 0;
+        }
+        if *self.oversized {
+            self.keep_tail();
+        } else if self.buffer.len() > self.max_event_size {
+            tracing::warn!(
+                size = self.buffer.len(),
+                max_event_size = self.max_event_size,
+                "Streamed event too large to transform, dropping it"
+            );
+            self.keep_tail();
+            *self.oversized = true;
+        }
+    }
+
+    /// Discard a dropped event's bytes, keeping the last few in case they
+    /// start its terminator
+    fn keep_tail(&mut self) {
+        let tail = self.buffer.len().saturating_sub(3);
+        self.buffer.advance(tail);
+    }
+
+    /// Flush what is left when the stream ends
+    fn finish(&mut self) {
+        let rest = self.buffer.split().freeze();
+        if rest.is_empty() || std::mem::take(self.oversized) {
+            return;
+        }
+        self.event(rest);
+    }
+
+    fn event(&mut self, event: Bytes) {
+        let transformed = match self.format {
+            StreamFormat::Ndjson => ndjson_line(&event, self.transform),
+            StreamFormat::EventStream => sse_event(&event, self.transform),
+        };
+        match transformed {
+            Transformed::Unchanged => self.emit(event),
+            Transformed::Replaced(text) => self.emit(Bytes::from(text)),
+            Transformed::Dropped => {}
+        }
+    }
+
+    fn emit(&mut self, bytes: Bytes) {
+        if bytes.has_remaining() {
+            self.ready.push_back(Frame::data(bytes));
+        }
+    }
+}
+
+/// Outcome of transforming one event
+enum Transformed {
+    Unchanged,
+    Replaced(String),
+    Dropped,
+}
+
+/// Run `transform` on a JSON payload; the replacement is the serialized
+/// result
+fn apply(payload: &[u8], transform: &dyn EventTransform) -> Transformed {
+    let Ok(mut value) = serde_json::from_slice::<Value>(payload) else {
+        return Transformed::Unchanged;
+    };
+    if !transform.transform(&mut value) {
+        return Transformed::Dropped;
+    }
+    serde_json::to_string(&value).map_or(Transformed::Unchanged, Transformed::Replaced)
+}
+
+fn ndjson_line(line: &[u8], transform: &dyn EventTransform) -> Transformed {
+    let terminated = line.ends_with(b"\n");
+    let content = line.strip_suffix(b"\n").unwrap_or(line);
+    let content = content.strip_suffix(b"\r").unwrap_or(content);
+    if content.iter().all(u8::is_ascii_whitespace) {
+        return Transformed::Unchanged;
+    }
+    match apply(content, transform) {
+        Transformed::Replaced(mut json) => {
+            if terminated {
+                json.push('\n');
+            }
+            Transformed::Replaced(json)
+        }
+        other => other,
+    }
+}
+
+fn sse_event(event: &[u8], transform: &dyn EventTransform) -> Transformed {
+    let Ok(text) = std::str::from_utf8(event) else {
+        return Transformed::Unchanged;
+    };
+    let lines: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+    let data: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|value| value.strip_prefix(' ').unwrap_or(value))
+        .collect();
+    if data.is_empty() {
+        return Transformed::Unchanged;
+    }
+    let json = match apply(data.join("\n").as_bytes(), transform) {
+        Transformed::Replaced(json) => json,
+        other => return other,
+    };
+
+    // The other fields keep their place; the data lines become one
+    let mut out = String::with_capacity(json.len() + event.len());
+    let mut written = false;
+    for line in lines {
+        if line.starts_with("data:") {
+            if !written {
+                out.push_str("data: ");
+                out.push_str(&json);
+                out.push('\n');
+                written = true;
+            }
+        } else {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out.push('\n');
+    Transformed::Replaced(out)
+}
+
+impl<B> Body for TransformedStream<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let mut this = self.project();
+        loop {
+            if let Some(frame) = this.ready.pop_front() {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            if *this.done {
+                return Poll::Ready(None);
+            }
+            let mut events = Events {
+                format: *this.format,
+                transform: this.transform.as_ref(),
+                max_event_size: *this.max_event_size,
+                buffer: &mut *this.buffer,
+                oversized: &mut *this.oversized,
+                ready: &mut *this.ready,
+            };
+            match std::task::ready!(this.inner.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => events.push(data),
+                    Err(frame) => {
+                        events.finish();
+                        events.ready.push_back(frame);
+                    }
+                },
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    events.finish();
+                    *this.done = true;
+                }
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done && self.ready.is_empty()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::SinkExt;
+    use http_body_util::{BodyExt, StreamBody};
+    use std::convert::Infallible;
+
+    type Sender = mpsc::Sender<Result<Frame<Bytes>, Infallible>>;
+
+    fn redact_email() -> Arc<dyn EventTransform> {
+        Arc::new(|event: &mut Value| {
+            if let Some(email) = event.get_mut("email") {
+                *email = Value::from("***REDACTED***");
+            }
+            true
+        })
+    }
+
+    fn stream(
+        format: StreamFormat,
+        transform: Arc<dyn EventTransform>,
+    ) -> (
+        Sender,
+        TransformedStream<StreamBody<mpsc::Receiver<Result<Frame<Bytes>, Infallible>>>>,
+    ) {
+        let (tx, rx) = mpsc::channel(8);
+        (
+            tx,
+            TransformedStream::new(StreamBody::new(rx), format, transform),
+        )
+    }
+
+    async fn send(tx: &mut Sender, data: &'static str) {
+        tx.send(Ok(Frame::data(Bytes::from_static(data.as_bytes()))))
+            .await
+            .unwrap();
+    }
+
+    async fn next_data<B>(body: &mut B) -> String
+    where
+        B: Body<Data = Bytes> + Unpin,
+        B::Error: fmt::Debug,
+    {
+        let frame = body.frame().await.unwrap().unwrap();
+        String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn ndjson_lines_are_redacted_as_they_arrive() {
+        let (mut tx, mut body) = stream(StreamFormat::Ndjson, redact_email());
+
+        // Each line comes out before the next is sent, and before the
+        // stream ends: nothing waits for the rest of the body
+        send(&mut tx, "{\"email\":\"a@example.com\",\"id\":1}\n").await;
+        assert_eq!(
+            next_data(&mut body).await,
+            "{\"email\":\"***REDACTED***\",\"id\":1}\n"
+        );
+
+        // A line split across chunks is emitted once complete
+        send(&mut tx, "{\"em").await;
+        send(&mut tx, "ail\":\"b@example.com\",\"id\":2}\n{\"id\":3}\n").await;
+        assert_eq!(
+            next_data(&mut body).await,
+            "{\"email\":\"***REDACTED***\",\"id\":2}\n"
+        );
+        assert_eq!(next_data(&mut body).await, "{\"id\":3}\n");
+
+        send(&mut tx, "not json\n").await;
+        assert_eq!(next_data(&mut body).await, "not json\n");
+
+        drop(tx);
+        assert!(body.frame().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn sse_data_is_transformed_and_other_fields_kept() {
+        let (mut tx, mut body) = stream(StreamFormat::EventStream, redact_email());
+
+        send(&mut tx, ": keepalive\n\n").await;
+        assert_eq!(next_data(&mut body).await, ": keepalive\n\n");
+
+        send(
+            &mut tx,
+            "id: 7\nevent: signup\ndata: {\"email\":\"a@example.com\"}\n\n",
+        )
+        .await;
+        assert_eq!(
+            next_data(&mut body).await,
+            "id: 7\nevent: signup\ndata: {\"email\":\"***REDACTED***\"}\n\n"
+        );
+
+        send(&mut tx, "data: plain text\r\n\r\n").await;
+        assert_eq!(next_data(&mut body).await, "data: plain text\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn events_can_be_dropped() {
+        let drop_heartbeats: Arc<dyn EventTransform> =
+            Arc::new(|event: &mut Value| event["type"] != "heartbeat");
+        let (mut tx, body) = stream(StreamFormat::Ndjson, drop_heartbeats);
+
+        send(&mut tx, "{\"type\":\"heartbeat\"}\n{\"type\":\"order\"}\n").await;
+        send(&mut tx, "{\"type\":\"heartbeat\"}").await;
+        drop(tx);
+        let out = body.collect().await.unwrap().to_bytes();
+        assert_eq!(&out[..], b"{\"type\":\"order\"}\n");
+    }
+
+    #[tokio::test]
+    async fn oversized_events_are_dropped() {
+        let (mut tx, body) = stream(StreamFormat::Ndjson, redact_email());
+        let body = body.max_event_size(16);
+
+        send(&mut tx, "{\"email\":\"a@example.com\",").await;
+        send(&mut tx, "\"id\":1}\n{\"email\":\"b\"}\n").await;
+        // The terminator of an oversized event split across chunks
+        send(&mut tx, "{\"email\":\"c@example.com\"}\r").await;
+        send(&mut tx, "\n{\"id\":4}\n{\"email\":\"d@example.com\"").await;
+        drop(tx);
+        let out = body.collect().await.unwrap().to_bytes();
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            "{\"email\":\"***REDACTED***\"}\n{\"id\":4}\n"
+        );
+    }
+
+    #[test]
+    fn stream_format_from_content_type() {
+        use http::{header, HeaderMap};
+
+        let format = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, value.parse().unwrap());
+            StreamFormat::from_headers(&headers)
+        };
+        assert_eq!(
+            format("text/event-stream; charset=utf-8"),
+            Some(StreamFormat::EventStream)
+        );
+        assert_eq!(format("application/x-ndjson"), Some(StreamFormat::Ndjson));
+        assert_eq!(format("application/json"), None);
+    }
+}
@@ -9,6 +9,8 @@
 //! - Per-upstream connection tracking
 //! - Header injection (X-Forwarded-For, X-Real-IP, etc.)
 
+use crate::handler::{ProtocolHandler, ProtocolMatch, ProtocolType};
+use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine as _};
 use bytes::Bytes;
 use http::{header, Request, Response, StatusCode};
@@ -131,6 +133,38 @@ pub fn build_upgrade_response<B>(req: &Request<B>) -> Result<Response<Full<Bytes
         .map_err(|e| format!("Failed to build upgrade response: {e}"))
 }
 
+/// Dispatch entry for WebSocket upgrades.
+///
+/// The upgrade itself is proxied by the runtime before the body is buffered
+/// (the hyper `OnUpgrade` extension must still be on the request), so this
+/// handler only advertises what it matches.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebSocketHandler;
+
+#[async_trait]
+impl ProtocolHandler for WebSocketHandler {
+    fn protocol_type(&self) -> ProtocolType {
+        ProtocolType::WebSocket
+    }
+
+    fn matchers(&self) -> Vec<ProtocolMatch> {
+        vec![ProtocolMatch::Upgrade("websocket".to_string())]
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    async fn handle(
+        &self,
+        _req: Request<Full<Bytes>>,
+    ) -> octopus_core::Result<Response<Full<Bytes>>> {
+        Err(octopus_core::Error::InvalidRequest(
+            "WebSocket upgrade cannot be served from a buffered request".to_string(),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
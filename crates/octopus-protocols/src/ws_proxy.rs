@@ -14,10 +14,11 @@
 
 use crate::websocket::WebSocketConfig;
 use futures::{SinkExt, StreamExt};
+use octopus_core::{Error, Result};
 use std::time::{Duration, Instant};
 use tokio_tungstenite::{
     connect_async_with_config,
-    tungstenite::{client::IntoClientRequest, protocol::Message},
+    tungstenite::{client::IntoClientRequest, protocol::Message, Error as WsError},
     WebSocketStream,
 };
 use tracing::{debug, info, warn};
@@ -37,16 +38,22 @@ pub struct WebSocketSessionStats {
 
 /// Connect to an upstream WebSocket server with timeout and header forwarding.
 ///
-/// Returns the connected stream, or an error if connection fails/times out.
+/// Returns the connected stream. An upstream that answers the handshake
+/// with an HTTP response instead of upgrading is [`Error::Upstream`], so
+/// its status reaches the client (with the part of its body that arrived
+/// along with the head); failing to get an answer at all is a connection
+/// error or [`Error::UpstreamTimeout`].
 pub async fn connect_upstream(
     upstream_url: &str,
     forwarded_headers: &http::HeaderMap,
     config: &WebSocketConfig,
-) -> Result<WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>, String> {
+) -> Result<WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>> {
     // Build request with forwarded headers
-    let mut request = upstream_url
-        .into_client_request()
-        .map_err(|e| format!("Invalid upstream WebSocket URL '{upstream_url}': {e}"))?;
+    let mut request = upstream_url.into_client_request().map_err(|e| {
+        Error::UpstreamConnection(format!(
+            "Invalid upstream WebSocket URL '{upstream_url}': {e}"
+        ))
+    })?;
 
     // Inject forwarded headers
     for (key, value) in forwarded_headers {
@@ -60,18 +67,43 @@ pub async fn connect_upstream(
         connect_async_with_config(request, Some(ws_config), false).await
     })
     .await
-    .map_err(|_| {
-        format!(
-            "Upstream WebSocket connect timeout after {:?}",
-            config.connect_timeout
-        )
-    })?
-    .map_err(|e| format!("Upstream WebSocket connect failed: {e}"))?;
+    .map_err(|_| Error::UpstreamTimeout)?
+    .map_err(|e| match e {
+        WsError::Http(response) => {
+            let (parts, body) = response.into_parts();
+            refusal(parts.status, parts.headers, body.unwrap_or_default())
+        }
+        e => Error::UpstreamConnection(format!("Upstream WebSocket connect failed: {e}")),
+    })?;
 
     info!(upstream = %upstream_url, "Upstream WebSocket connected");
     Ok(stream)
 }
 
+/// The upstream's answer to a handshake it refused, to pass on to the
+/// client. Hop-by-hop headers are dropped and `Content-Length` is set from
+/// the body actually read, which may be shorter than the upstream announced.
+fn refusal(status: http::StatusCode, mut headers: http::HeaderMap, body: Vec<u8>) -> Error {
+    let connection_listed: Vec<http::HeaderName> = headers
+        .get_all(http::header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| http::HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in connection_listed {
+        headers.remove(name);
+    }
+    for name in octopus_proxy::HOP_BY_HOP {
+        headers.remove(*name);
+    }
+    headers.insert(
+        http::header::CONTENT_LENGTH,
+        http::HeaderValue::from(body.len()),
+    );
+    Error::upstream(status, headers, body)
+}
+
 /// Run a bidirectional WebSocket proxy between two already-connected streams.
 ///
 /// This is the core proxy loop. Call this after both client upgrade and
@@ -325,4 +357,71 @@ mod tests {
         let headers = build_forwarded_headers(&req);
         assert_eq!(headers.get("authorization").unwrap(), "Bearer tok123");
     }
+
+    #[tokio::test]
+    async fn rejected_handshake_is_the_upstream_response() {
+        use http_body_util::BodyExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            stream
+                .write_all(
+                    b"HTTP/1.1 403 Forbidden\r\ncontent-length: 6\r\nx-reason: origin\r\n\r\ndenied",
+                )
+                .await
+                .unwrap();
+        });
+
+        let url = format!("ws://127.0.0.1:{port}/ws");
+        let err = connect_upstream(&url, &http::HeaderMap::new(), &WebSocketConfig::default())
+            .await
+            .unwrap_err();
+        let response = err.upstream_response().expect("upstream response");
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()["x-reason"], "origin");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "denied");
+    }
+
+    #[tokio::test]
+    async fn rejected_handshake_drops_hop_by_hop_headers_and_fixes_length() {
+        use http_body_util::BodyExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            // Announces more body than it sends before closing
+            stream
+                .write_all(
+                    b"HTTP/1.1 401 Unauthorized\r\nconnection: close, x-hop\r\nupgrade: websocket\r\n\
+                      keep-alive: timeout=5\r\nx-hop: 1\r\nx-reason: token\r\ncontent-length: 100\r\n\r\nexpired",
+                )
+                .await
+                .unwrap();
+        });
+
+        let url = format!("ws://127.0.0.1:{port}/ws");
+        let err = connect_upstream(&url, &http::HeaderMap::new(), &WebSocketConfig::default())
+            .await
+            .unwrap_err();
+        let response = err.upstream_response().expect("upstream response");
+        assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+        let headers = response.headers();
+        for name in ["connection", "upgrade", "keep-alive", "x-hop"] {
+            assert!(!headers.contains_key(name), "{name} forwarded");
+        }
+        assert_eq!(headers["x-reason"], "token");
+        let length: usize = headers["content-length"].to_str().unwrap().parse().unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(length, body.len());
+    }
 }
@@ -1,7 +1,7 @@
 //! Comprehensive audit logging for security events
 
 use http::{Request, Response, StatusCode};
-use octopus_core::UpstreamInstance;
+use octopus_core::{UpstreamInstance, AUDIT_LOG_TARGET};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::time::{Duration, SystemTime};
@@ -274,6 +274,7 @@ impl AuditLogger {
 
             if event.is_security_critical() {
                 warn!(
+                    target: AUDIT_LOG_TARGET,
                     event_type = ?event.event_type,
                     event_id = %event.event_id,
                     audit_event = %event_json,
@@ -281,6 +282,7 @@ impl AuditLogger {
                 );
             } else if event.is_error() {
                 error!(
+                    target: AUDIT_LOG_TARGET,
                     event_type = ?event.event_type,
                     event_id = %event.event_id,
                     audit_event = %event_json,
@@ -288,6 +290,7 @@ impl AuditLogger {
                 );
             } else {
                 info!(
+                    target: AUDIT_LOG_TARGET,
                     event_type = ?event.event_type,
                     event_id = %event.event_id,
                     audit_event = %event_json,
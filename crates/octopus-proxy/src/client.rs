@@ -1,6 +1,7 @@
 //! HTTP client for making requests to upstream services using connection pooling
 
 use crate::pool::ConnectionPool;
+use crate::upstream_error;
 use bytes::Bytes;
 use http::{Request, Response};
 use http_body_util::Full;
@@ -93,7 +94,7 @@ impl HttpClient {
                     error = %e,
                     "Upstream request failed"
                 );
-                Err(Error::UpstreamConnection(e.to_string()))
+                Err(upstream_error::request_error(&e))
             }
             Err(_) => {
                 debug!(
@@ -114,6 +115,50 @@ impl HttpClient {
         response
     }
 
+    /// Send a request whose body streams to the upstream as it arrives,
+    /// over a connection of its own.
+    ///
+    /// Unlike [`send`](Self::send) this has no timeout: sending the body takes
+    /// as long as the client takes to upload it, so the caller bounds the
+    /// whole exchange instead.
+    pub async fn send_streaming<B>(
+        &self,
+        req: Request<B>,
+        upstream: &UpstreamInstance,
+    ) -> Result<Response<Incoming>>
+    where
+        B: hyper::body::Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        trace!(
+            upstream = %upstream.id,
+            method = %req.method(),
+            uri = %req.uri(),
+            "Streaming request to upstream"
+        );
+
+        let mut sender = self.pool.connect_unpooled(upstream).await?;
+        match sender.send_request(req).await {
+            Ok(resp) => {
+                debug!(
+                    upstream = %upstream.id,
+                    status = resp.status().as_u16(),
+                    "Received response from upstream"
+                );
+                Ok(resp)
+            }
+            Err(e) => {
+                debug!(
+                    upstream = %upstream.id,
+                    error = %e,
+                    "Streamed upstream request failed"
+                );
+                Err(upstream_error::request_error(&e))
+            }
+        }
+    }
+
     /// Send a request via HTTP/2 (for gRPC and HTTP/2 upstreams)
     pub async fn send_h2(
         &self,
@@ -146,7 +191,7 @@ impl HttpClient {
                     error = %e,
                     "HTTP/2 upstream request failed"
                 );
-                Err(Error::UpstreamConnection(e.to_string()))
+                Err(upstream_error::request_error(&e))
             }
             Err(_) => {
                 debug!(
@@ -5,6 +5,8 @@
 //! - Via header (RFC 7230)
 //! - X-Forwarded-* headers (de facto standard)
 //! - Trace context propagation
+//! - Hop-by-hop header removal (RFC 7230 §6.1) and configurable deny/allow
+//!   lists for custom headers ([`HeaderStripPolicy`])
 
 use http::{HeaderMap, HeaderName, HeaderValue, Request, Uri};
 use std::net::IpAddr;
@@ -88,6 +90,123 @@ impl Default for HeaderConfig {
     }
 }
 
+/// Hop-by-hop headers that are never forwarded (RFC 7230 §6.1), plus the
+/// non-standard `proxy-connection`.
+pub const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Remove hop-by-hop headers (RFC 7230 §6.1): the standard set plus every
+/// header named in `Connection`.
+///
+/// `TE: trailers` is kept: it is the one TE value a proxy may forward, and
+/// gRPC upstreams require it.
+pub fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let connection_listed: Vec<HeaderName> = headers
+        .get_all(http::header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    let te_trailers = headers
+        .get_all(http::header::TE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|t| {
+            t.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .eq_ignore_ascii_case("trailers")
+        });
+
+    for name in connection_listed {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP {
+        headers.remove(*name);
+    }
+    if te_trailers {
+        headers.insert(http::header::TE, HeaderValue::from_static("trailers"));
+    }
+}
+
+/// Deny/allow list of header names. Patterns are case-insensitive; a
+/// trailing `*` matches by prefix (`x-internal-*`).
+///
+/// A header is stripped when it matches a `deny` pattern and no `allow`
+/// pattern, so `allow` carves exceptions out of broad deny prefixes.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderFilter {
+    deny: Vec<String>,
+    allow: Vec<String>,
+}
+
+impl HeaderFilter {
+    /// Create a filter from deny and allow patterns
+    pub fn new(
+        deny: impl IntoIterator<Item = impl AsRef<str>>,
+        allow: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        let lower = |p: &str| p.trim().to_ascii_lowercase();
+        Self {
+            deny: deny.into_iter().map(|p| lower(p.as_ref())).collect(),
+            allow: allow.into_iter().map(|p| lower(p.as_ref())).collect(),
+        }
+    }
+
+    /// Whether the filter strips nothing
+    pub fn is_empty(&self) -> bool {
+        self.deny.is_empty()
+    }
+
+    /// Whether `name` (lowercase, as stored in a [`HeaderMap`]) is stripped
+    pub fn strips(&self, name: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        };
+        self.deny.iter().any(matches) && !self.allow.iter().any(matches)
+    }
+
+    /// Remove every stripped header from `headers`
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if self.is_empty() {
+            return;
+        }
+        let stripped: Vec<HeaderName> = headers
+            .keys()
+            .filter(|name| self.strips(name.as_str()))
+            .cloned()
+            .collect();
+        for name in stripped {
+            debug!(header = %name, "Stripping header per policy");
+            headers.remove(name);
+        }
+    }
+}
+
+/// Custom header stripping in each direction
+#[derive(Debug, Clone, Default)]
+pub struct HeaderStripPolicy {
+    /// Applied to client requests on ingress, before auth and middleware run
+    /// (e.g. drop client-supplied `X-Auth-*` that auth would otherwise trust)
+    pub request: HeaderFilter,
+    /// Applied to upstream responses before they reach the client
+    /// (e.g. drop `X-Internal-*`)
+    pub response: HeaderFilter,
+}
+
 /// Header processor for request/response transformation
 pub struct HeaderProcessor {
     config: HeaderConfig,
@@ -186,36 +305,7 @@ impl HeaderProcessor {
 
     /// Remove hop-by-hop headers as per RFC 7230
     fn remove_hop_by_hop_headers(&self, headers: &mut HeaderMap) {
-        // Standard hop-by-hop headers
-        const HOP_BY_HOP: &[&str] = &[
-            "connection",
-            "keep-alive",
-            "proxy-authenticate",
-            "proxy-authorization",
-            "te",
-            "trailer",
-            "transfer-encoding",
-            "upgrade",
-        ];
-
-        // Collect headers listed in Connection header before modifying
-        let connection_headers: Vec<String> = headers
-            .get("connection")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.split(',').map(|name| name.trim().to_string()).collect())
-            .unwrap_or_default();
-
-        // Remove standard hop-by-hop headers
-        for header in HOP_BY_HOP {
-            headers.remove(*header);
-        }
-
-        // Remove headers listed in Connection header
-        for name in connection_headers {
-            if let Ok(header_name) = HeaderName::from_bytes(name.as_bytes()) {
-                headers.remove(header_name);
-            }
-        }
+        strip_hop_by_hop(headers);
     }
 
     /// Add Forwarded header (RFC 7239)
@@ -426,6 +516,70 @@ mod tests {
         assert!(headers.contains_key("content-type"));
     }
 
+    #[test]
+    fn strip_hop_by_hop_removes_connection_listed_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            "connection",
+            HeaderValue::from_static("close, X-Session-Hint"),
+        );
+        headers.append("connection", HeaderValue::from_static("x-debug"));
+        headers.insert("x-session-hint", HeaderValue::from_static("abc"));
+        headers.insert("x-debug", HeaderValue::from_static("1"));
+        headers.insert("transfer-encoding", HeaderValue::from_static("chunked"));
+        headers.insert("upgrade", HeaderValue::from_static("h2c"));
+        headers.insert(
+            "proxy-authorization",
+            HeaderValue::from_static("Basic Zm9v"),
+        );
+        headers.insert("te", HeaderValue::from_static("gzip"));
+        headers.insert("x-request-id", HeaderValue::from_static("r-1"));
+
+        strip_hop_by_hop(&mut headers);
+
+        for name in [
+            "connection",
+            "x-session-hint",
+            "x-debug",
+            "transfer-encoding",
+            "upgrade",
+            "proxy-authorization",
+            "te",
+        ] {
+            assert!(!headers.contains_key(name), "{name} should be stripped");
+        }
+        assert_eq!(headers["x-request-id"], "r-1");
+    }
+
+    #[test]
+    fn strip_hop_by_hop_keeps_te_trailers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("te", HeaderValue::from_static("trailers, deflate;q=0.5"));
+
+        strip_hop_by_hop(&mut headers);
+
+        assert_eq!(headers["te"], "trailers");
+    }
+
+    #[test]
+    fn header_filter_matches_prefixes_and_allow_exceptions() {
+        let filter = HeaderFilter::new(["X-Internal-*", "server"], ["x-internal-trace-id"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-internal-node", HeaderValue::from_static("10.0.0.7"));
+        headers.insert("x-internal-trace-id", HeaderValue::from_static("t-1"));
+        headers.insert("server", HeaderValue::from_static("nginx"));
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+
+        filter.apply(&mut headers);
+
+        assert!(!headers.contains_key("x-internal-node"));
+        assert!(!headers.contains_key("server"));
+        assert!(headers.contains_key("x-internal-trace-id"));
+        assert!(headers.contains_key("content-type"));
+        assert!(HeaderFilter::default().is_empty());
+    }
+
     #[test]
     fn test_add_forwarded_header() {
         let config = HeaderConfig::default();
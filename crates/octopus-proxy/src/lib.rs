@@ -31,11 +31,14 @@ pub mod shutdown;
 pub mod timeout;
 pub mod tls;
 pub mod tracing_support;
+mod upstream_error;
 
 pub use audit::{AuditEvent, AuditEventType, AuditLogger};
 pub use bulkhead::{Bulkhead, BulkheadConfig, BulkheadError, BulkheadPermit};
 pub use client::HttpClient;
-pub use headers::{HeaderConfig, HeaderProcessor};
+pub use headers::{
+    strip_hop_by_hop, HeaderConfig, HeaderFilter, HeaderProcessor, HeaderStripPolicy, HOP_BY_HOP,
+};
 pub use limits::{LimitedBody, ProxyLimits};
 pub use metrics::{
     CircuitBreakerMetrics, PoolMetrics, ProxyMetrics, RequestTracker, RetryMetrics, TlsMetrics,
@@ -45,7 +48,7 @@ pub use proxy::{HttpProxy, ProxyConfig};
 pub use ratelimit::{
     InMemoryRateLimiter, RateLimitConfig, RateLimitKeyBuilder, RateLimitResult, RateLimiter,
 };
-pub use retry::{BackoffStrategy, RetryContext, RetryPolicy};
+pub use retry::{BackoffStrategy, RetryContext, RetryPolicy, UpstreamThrottle};
 pub use routing::{CanaryConfig, Router, RoutingConfig, RoutingStrategy, ShadowConfig};
 pub use shutdown::{ShutdownHandle, ShutdownSignal};
 pub use timeout::{TimeoutConfig, TimeoutContext, TimeoutOperation};
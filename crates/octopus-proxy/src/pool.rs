@@ -16,6 +16,7 @@ use tokio::time::timeout;
 use tracing::{debug, info, trace, warn};
 
 use crate::tls::TlsConfig;
+use crate::upstream_error;
 
 /// Cached verify-on TLS config. Building the root store is expensive, so we do
 /// it once and clone the cheap `Arc`-backed handle per connection.
@@ -41,12 +42,15 @@ fn shared_tls_config(verify: bool) -> Result<TlsConfig> {
 
 /// Drive a freshly handshaked HTTP/1.1 connection in the background. Shared by
 /// the plain and TLS paths so both return the same `SendRequest` type.
-async fn spawn_http1_handshake<I>(io: TokioIo<I>) -> Result<http1::SendRequest<Full<Bytes>>>
+async fn spawn_http1_handshake<I, B>(io: TokioIo<I>) -> Result<http1::SendRequest<B>>
 where
     I: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+    B: hyper::body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
     let (sender, conn) = http1::Builder::new()
-        .handshake::<_, Full<Bytes>>(io)
+        .handshake::<_, B>(io)
         .await
         .map_err(|e| Error::UpstreamConnection(format!("HTTP handshake failed: {e}")))?;
 
@@ -60,17 +64,27 @@ where
 }
 
 /// Plain (non-TLS) HTTP/1.1 handshake over a raw TCP stream.
-async fn handshake_plain(stream: TcpStream) -> Result<http1::SendRequest<Full<Bytes>>> {
+async fn handshake_plain<B>(stream: TcpStream) -> Result<http1::SendRequest<B>>
+where
+    B: hyper::body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
     spawn_http1_handshake(TokioIo::new(stream)).await
 }
 
 /// TLS-wrapped HTTP/1.1 handshake. Performs the rustls handshake against
 /// `domain` first, then the HTTP/1.1 handshake over the encrypted stream.
-async fn handshake_tls(
+async fn handshake_tls<B>(
     stream: TcpStream,
     domain: &str,
     verify: bool,
-) -> Result<http1::SendRequest<Full<Bytes>>> {
+) -> Result<http1::SendRequest<B>>
+where
+    B: hyper::body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
     let tls_config = shared_tls_config(verify)?;
     let tls_stream = tls_config.connect(stream, domain).await?;
     spawn_http1_handshake(TokioIo::new(tls_stream)).await
@@ -427,14 +441,17 @@ impl ConnectionPool {
 
         debug!(upstream = %addr, "Creating new connection");
 
-        // Connect with timeout
-        let stream = timeout(self.config.connect_timeout, TcpStream::connect(&addr))
-            .await
-            .map_err(|_| Error::UpstreamTimeout)?
-            .map_err(|e| {
-                pool.metrics.record_error();
-                Error::UpstreamConnection(format!("Failed to connect: {e}"))
-            })?;
+        // Resolve and connect with timeout
+        let stream = timeout(
+            self.config.connect_timeout,
+            upstream_error::connect(&instance.address, instance.port),
+        )
+        .await
+        .map_err(|_| Error::UpstreamTimeout)?
+        .map_err(|e| {
+            pool.metrics.record_error();
+            e
+        })?;
 
         // Configure TCP stream
         if let Err(e) = stream.set_nodelay(true) {
@@ -473,6 +490,43 @@ impl ConnectionPool {
         Ok(PooledConnection::new(sender, key.clone()))
     }
 
+    /// Open a connection to `instance` that is never pooled, for a request
+    /// whose body is not buffered (pooled connections only carry buffered
+    /// bodies). It closes once the request and response are done with.
+    pub async fn connect_unpooled<B>(
+        &self,
+        instance: &UpstreamInstance,
+    ) -> Result<http1::SendRequest<B>>
+    where
+        B: hyper::body::Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if !self.accepting.load(Ordering::Relaxed) {
+            return Err(Error::UpstreamConnection(
+                "Pool is shutting down".to_string(),
+            ));
+        }
+        let stream = timeout(
+            self.config.connect_timeout,
+            upstream_error::connect(&instance.address, instance.port),
+        )
+        .await
+        .map_err(|_| Error::UpstreamTimeout)??;
+        if let Err(e) = stream.set_nodelay(true) {
+            warn!("Failed to set TCP_NODELAY: {}", e);
+        }
+        if instance.is_tls() {
+            let domain = instance
+                .sni
+                .clone()
+                .unwrap_or_else(|| instance.address.clone());
+            handshake_tls(stream, &domain, instance.tls_verify).await
+        } else {
+            handshake_plain(stream).await
+        }
+    }
+
     /// Pop an idle connection if available
     async fn pop_idle_connection(
         &self,
@@ -719,10 +773,12 @@ impl Http2Pool {
         let addr = format!("{}:{}", instance.address, instance.port);
         debug!(upstream = %addr, "Creating new HTTP/2 connection");
 
-        let stream = timeout(self.config.connect_timeout, TcpStream::connect(&addr))
-            .await
-            .map_err(|_| Error::UpstreamTimeout)?
-            .map_err(|e| Error::UpstreamConnection(format!("Failed to connect: {e}")))?;
+        let stream = timeout(
+            self.config.connect_timeout,
+            upstream_error::connect(&instance.address, instance.port),
+        )
+        .await
+        .map_err(|_| Error::UpstreamTimeout)??;
 
         if let Err(e) = stream.set_nodelay(true) {
             warn!("Failed to set TCP_NODELAY: {}", e);
@@ -1,15 +1,24 @@
 //! HTTP proxy implementation with zero-copy streaming
 
 use crate::client::{Body, HttpClient};
+use crate::headers::{strip_hop_by_hop, HeaderStripPolicy};
+use crate::limits::ProxyLimits;
 use crate::pool::ConnectionPool;
-use crate::retry::{RetryContext, RetryPolicy};
-use bytes::Bytes;
-use http::{Request, Response, Uri};
+use crate::retry::{upstream_retry_after, RetryContext, RetryPolicy, UpstreamThrottle};
+use crate::upstream_error;
+use bytes::{Bytes, BytesMut};
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+use http::{HeaderMap, Request, Response, Uri};
 use http_body_util::{BodyExt, Full};
 use hyper::body::Incoming;
-use octopus_core::{Error, Result, UpstreamInstance};
-use octopus_health::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use octopus_core::{
+    Deadline, Error, ResponseBodyLimit, Result, StreamEvents, StreamFormat, StreamedResponse,
+    UpstreamInstance, UpstreamSelection,
+};
+use octopus_health::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, Outcome};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use tracing::{debug, instrument, warn};
 
@@ -30,6 +39,21 @@ pub struct ProxyConfig {
 
     /// Enable retry logic
     pub enable_retry: bool,
+
+    /// Custom request/response header stripping (hop-by-hop headers are
+    /// always removed)
+    pub header_policy: HeaderStripPolicy,
+
+    /// Pass an upstream `Retry-After` header through to the client
+    pub propagate_retry_after: bool,
+
+    /// Size limits; `max_response_body_size` caps buffered response bodies
+    /// of requests that carry no [`ResponseBodyLimit`] of their own
+    pub limits: ProxyLimits,
+
+    /// Cut a response body at `limits.max_response_body_size` instead of
+    /// failing the request
+    pub truncate_large_responses: bool,
 }
 
 impl Default for ProxyConfig {
@@ -40,6 +64,10 @@ impl Default for ProxyConfig {
             upstream_headers: Vec::new(),
             enable_circuit_breaker: true,
             enable_retry: true,
+            header_policy: HeaderStripPolicy::default(),
+            propagate_retry_after: true,
+            limits: ProxyLimits::default(),
+            truncate_large_responses: false,
         }
     }
 }
@@ -51,6 +79,7 @@ pub struct HttpProxy {
     config: ProxyConfig,
     circuit_breaker: Arc<CircuitBreaker>,
     retry_policy: Arc<RetryPolicy>,
+    throttle: Arc<UpstreamThrottle>,
 }
 
 impl HttpProxy {
@@ -61,6 +90,7 @@ impl HttpProxy {
             config,
             circuit_breaker: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default())),
             retry_policy: Arc::new(RetryPolicy::default()),
+            throttle: Arc::new(UpstreamThrottle::new()),
         }
     }
 
@@ -71,6 +101,7 @@ impl HttpProxy {
             config: ProxyConfig::default(),
             circuit_breaker: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default())),
             retry_policy: Arc::new(RetryPolicy::default()),
+            throttle: Arc::new(UpstreamThrottle::new()),
         }
     }
 
@@ -82,6 +113,7 @@ impl HttpProxy {
             config,
             circuit_breaker: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default())),
             retry_policy: Arc::new(RetryPolicy::default()),
+            throttle: Arc::new(UpstreamThrottle::new()),
         }
     }
 
@@ -97,6 +129,7 @@ impl HttpProxy {
             config,
             circuit_breaker,
             retry_policy,
+            throttle: Arc::new(UpstreamThrottle::new()),
         }
     }
 
@@ -133,10 +166,11 @@ impl HttpProxy {
 
         // Update circuit breaker based on result
         if self.config.enable_circuit_breaker {
-            match &result {
-                Ok(_) => self.circuit_breaker.record_success(&upstream.id),
-                Err(_) => self.circuit_breaker.record_failure(&upstream.id),
-            }
+            let outcome = match &result {
+                Ok(response) => Outcome::Status(response.status().as_u16()),
+                Err(e) => Outcome::from_error(e),
+            };
+            self.circuit_breaker.record(&upstream.id, outcome);
         }
 
         result
@@ -165,7 +199,12 @@ impl HttpProxy {
         self.transform_headers(&mut req, upstream)?;
 
         // Send request and stream response directly (zero-copy)
-        let response = self.client.send(req, upstream).await?;
+        let started = Instant::now();
+        let mut response = self.client.send(req, upstream).await?;
+        self.filter_response_headers(response.headers_mut());
+        response
+            .extensions_mut()
+            .insert(UpstreamSelection::new(upstream, started.elapsed(), 0));
 
         debug!(
             status = response.status().as_u16(),
@@ -185,27 +224,90 @@ impl HttpProxy {
         req: Request<Body>,
         upstream: &UpstreamInstance,
     ) -> Result<Response<Full<Bytes>>> {
-        use http_body_util::BodyExt;
+        let limit = self.response_body_limit(req.extensions());
 
         // Get streaming response
         let response = self.proxy(req, upstream).await?;
 
         // Collect body into bytes
-        let (parts, body) = response.into_parts();
-        let body_bytes = body
-            .collect()
-            .await
-            .map_err(|e| Error::UpstreamConnection(e.to_string()))?
-            .to_bytes();
+        let (mut parts, body) = response.into_parts();
+        let body_bytes = collect_limited(body, &mut parts.headers, limit, &upstream.id).await?;
 
         Ok(Response::from_parts(parts, Full::new(body_bytes)))
     }
 
+    /// Proxy a request whose body is streamed to the upstream as it arrives
+    ///
+    /// For bodies too large to buffer, such as file uploads. A streamed body
+    /// can only be sent once, so there are no retries, and it travels over a
+    /// connection of its own. The exchange is bounded by the request's
+    /// [`Deadline`] (else the client timeout) and the response is buffered
+    /// within its [`ResponseBodyLimit`], as with
+    /// [`proxy_with_retry`](Self::proxy_with_retry).
+    #[instrument(skip(self, req), fields(upstream = %upstream.id))]
+    pub async fn proxy_streaming<B>(
+        &self,
+        mut req: Request<B>,
+        upstream: &UpstreamInstance,
+    ) -> Result<Response<Full<Bytes>>>
+    where
+        B: hyper::body::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if self.config.enable_circuit_breaker && !self.circuit_breaker.allow_request(&upstream.id) {
+            warn!(upstream = %upstream.id, "Circuit breaker is OPEN, rejecting request");
+            return Err(Error::CircuitBreakerOpen(upstream.id.clone()));
+        }
+
+        let deadline = req
+            .extensions()
+            .get::<Deadline>()
+            .copied()
+            .unwrap_or_else(|| Deadline::after(self.client.timeout()));
+        let body_limit = self.response_body_limit(req.extensions());
+        *req.uri_mut() = self.build_upstream_uri(&req, upstream)?;
+        self.transform_headers(&mut req, upstream)?;
+
+        let started = Instant::now();
+        let result = within(Some(deadline), async {
+            let response = self.client.send_streaming(req, upstream).await?;
+            let (mut parts, body) = response.into_parts();
+            self.filter_response_headers(&mut parts.headers);
+            let body = collect_limited(body, &mut parts.headers, body_limit, &upstream.id).await?;
+            Ok(Response::from_parts(parts, Full::new(body)))
+        })
+        .await;
+
+        if self.config.enable_circuit_breaker {
+            let outcome = match &result {
+                Ok(response) => Outcome::Status(response.status().as_u16()),
+                Err(e) => Outcome::from_error(e),
+            };
+            self.circuit_breaker.record(&upstream.id, outcome);
+        }
+
+        let mut response = result?;
+        response
+            .extensions_mut()
+            .insert(UpstreamSelection::new(upstream, started.elapsed(), 0));
+        Ok(response)
+    }
+
     /// Proxy a pre-buffered request with retry logic and circuit breaker
     ///
     /// Takes a `Request<Full<Bytes>>` whose body is cheap to clone (Bytes is
     /// reference-counted), so we can rebuild the request on each retry attempt.
     /// Returns a fully buffered `Response<Full<Bytes>>`.
+    ///
+    /// A [`Deadline`] in the request extensions bounds all attempts together:
+    /// each attempt only gets the time left, and once it is spent no further
+    /// attempt starts and [`Error::DeadlineExceeded`] is returned. A
+    /// [`ResponseBodyLimit`] there caps the collected response body in place
+    /// of [`ProxyLimits::max_response_body_size`].
+    ///
+    /// With [`StreamEvents`] there, a successful event-stream response (SSE
+    /// or NDJSON) is not collected: its body is left unread in a
+    /// [`StreamedResponse`] extension and the returned body is empty.
     #[instrument(skip(self, req), fields(upstream = %upstream.id))]
     pub async fn proxy_with_retry(
         &self,
@@ -218,8 +320,30 @@ impl HttpProxy {
             return Err(Error::CircuitBreakerOpen(upstream.id.clone()));
         }
 
+        // However the attempts end, the breaker gets the last outcome
+        // observed; `Local` when there is none gives back a half-open probe.
+        let mut outcome = None;
+        let result = self.send_with_retry(req, upstream, &mut outcome).await;
+        if self.config.enable_circuit_breaker {
+            self.circuit_breaker
+                .record(&upstream.id, outcome.unwrap_or(Outcome::Local));
+        }
+        result
+    }
+
+    /// The attempts of [`proxy_with_retry`](Self::proxy_with_retry), each
+    /// leaving its outcome in `outcome`
+    async fn send_with_retry(
+        &self,
+        req: Request<Full<Bytes>>,
+        upstream: &UpstreamInstance,
+        outcome: &mut Option<Outcome>,
+    ) -> Result<Response<Full<Bytes>>> {
         // Save request parts for cloning across attempts
         let (parts, body) = req.into_parts();
+        let deadline = parts.extensions.get::<Deadline>().copied();
+        let body_limit = self.response_body_limit(&parts.extensions);
+        let stream_events = parts.extensions.get::<StreamEvents>().is_some();
         let method = parts.method.clone();
         let original_uri = parts.uri.clone();
         let headers = parts.headers.clone();
@@ -246,8 +370,17 @@ impl HttpProxy {
         };
         let mut retry_ctx = RetryContext::new();
         let mut last_result: Option<Result<Response<Full<Bytes>>>> = None;
+        let started = Instant::now();
 
         for attempt in 0..max_total_attempts {
+            if deadline.is_some_and(|d| d.is_expired()) {
+                warn!(
+                    attempt = attempt + 1,
+                    "Request deadline exceeded, not sending"
+                );
+                return Err(Error::DeadlineExceeded);
+            }
+
             // Build request from saved parts
             let mut new_req = Request::builder()
                 .method(method.clone())
@@ -270,31 +403,87 @@ impl HttpProxy {
                 attempt + 1
             );
 
-            // Send request
-            let send_result = self.client.send(new_req, upstream).await;
-
-            // Process result
-            match send_result {
+            // Send request and collect the response, within what is left of
+            // the deadline
+            let attempted = match within(deadline, self.client.send(new_req, upstream)).await {
                 Ok(response) => {
                     let status = response.status();
                     retry_ctx.record_status(status);
+                    *outcome = Some(Outcome::Status(status.as_u16()));
+
+                    // A 429/503 with `Retry-After` asks us to stay away from
+                    // this instance for a while.
+                    let retry_after = upstream_retry_after(status, response.headers());
+                    if let Some(delay) = retry_after {
+                        self.throttle.throttle(&upstream.id, delay);
+                    }
+
+                    if stream_events
+                        && status.is_success()
+                        && StreamFormat::from_headers(response.headers()).is_some()
+                    {
+                        debug!(status = status.as_u16(), "Streaming event-stream response");
+                        let (mut resp_parts, resp_body) = response.into_parts();
+                        self.filter_response_headers(&mut resp_parts.headers);
+                        resp_parts.extensions.insert(UpstreamSelection::new(
+                            upstream,
+                            started.elapsed(),
+                            attempt,
+                        ));
+                        resp_parts
+                            .extensions
+                            .insert(StreamedResponse::new(resp_body.boxed()));
+                        return Ok(Response::from_parts(resp_parts, Full::new(Bytes::new())));
+                    }
 
                     // Collect body into Full<Bytes>
-                    let (resp_parts, resp_body) = response.into_parts();
-                    let resp_bytes = resp_body
-                        .collect()
-                        .await
-                        .map_err(|e| Error::UpstreamConnection(e.to_string()))?
-                        .to_bytes();
-                    let buffered_resp = Response::from_parts(resp_parts, Full::new(resp_bytes));
+                    let (mut resp_parts, resp_body) = response.into_parts();
+                    self.filter_response_headers(&mut resp_parts.headers);
+                    within(
+                        deadline,
+                        collect_limited(
+                            resp_body,
+                            &mut resp_parts.headers,
+                            body_limit,
+                            &upstream.id,
+                        ),
+                    )
+                    .await
+                    .map(|resp_bytes| {
+                        resp_parts.extensions.insert(UpstreamSelection::new(
+                            upstream,
+                            started.elapsed(),
+                            attempt,
+                        ));
+                        (
+                            Response::from_parts(resp_parts, Full::new(resp_bytes)),
+                            retry_after,
+                        )
+                    })
+                }
+                Err(e) => Err(e),
+            };
+
+            // Process result
+            match attempted {
+                Ok((buffered_resp, retry_after)) => {
+                    let status = buffered_resp.status();
 
                     // Check if retryable
                     let is_retryable = self.config.enable_retry
                         && attempt < max_total_attempts - 1
                         && self.retry_policy.is_status_retryable(status)
                         && self.retry_policy.is_method_retryable(&method);
+                    let wait_too_long =
+                        retry_after.is_some_and(|d| d > self.retry_policy.max_retry_after);
 
-                    if is_retryable {
+                    if is_retryable && wait_too_long {
+                        debug!(
+                            status = status.as_u16(),
+                            retry_after_ms = retry_after.unwrap_or_default().as_millis() as u64,
+                            "Upstream Retry-After exceeds the maximum wait, not retrying"
+                        );
+                    } else if is_retryable {
                         warn!(
                             status = status.as_u16(),
                             attempt = attempt + 1,
@@ -304,20 +493,21 @@ impl HttpProxy {
                         retry_ctx.record_attempt();
                         last_result = Some(Ok(buffered_resp));
 
-                        let backoff = self.retry_policy.calculate_backoff(attempt);
-                        sleep(backoff).await;
+                        // Never retry sooner than the upstream asked for.
+                        let backoff = self
+                            .retry_policy
+                            .calculate_backoff(attempt)
+                            .max(retry_after.unwrap_or_default());
+                        sleep_within(deadline, backoff).await;
                         continue;
                     }
 
                     // Success or non-retryable status
                     debug!(status = status.as_u16(), "Received response from upstream");
-
-                    if self.config.enable_circuit_breaker {
-                        self.circuit_breaker.record_success(&upstream.id);
-                    }
                     return Ok(buffered_resp);
                 }
                 Err(e) => {
+                    *outcome = Some(Outcome::from_error(&e));
                     let is_retryable = self.config.enable_retry
                         && attempt < max_total_attempts - 1
                         && self.retry_policy.is_error_retryable(&e);
@@ -333,24 +523,17 @@ impl HttpProxy {
                         retry_ctx.record_error(e);
 
                         let backoff = self.retry_policy.calculate_backoff(attempt);
-                        sleep(backoff).await;
+                        sleep_within(deadline, backoff).await;
                         continue;
                     }
 
                     // Non-retryable error
-                    if self.config.enable_circuit_breaker {
-                        self.circuit_breaker.record_failure(&upstream.id);
-                    }
                     return Err(e);
                 }
             }
         }
 
         // All retries exhausted — return the last result
-        if self.config.enable_circuit_breaker {
-            self.circuit_breaker.record_failure(&upstream.id);
-        }
-
         match last_result {
             Some(result) => result,
             None => Err(Error::Internal(
@@ -359,8 +542,25 @@ impl HttpProxy {
         }
     }
 
+    /// Strip configured client-supplied headers from an inbound request.
+    ///
+    /// Call on ingress, before auth and middleware, so headers the gateway
+    /// itself sets (e.g. `X-Auth-*`) cannot be forged by the client.
+    pub fn strip_client_headers(&self, headers: &mut HeaderMap) {
+        self.config.header_policy.request.apply(headers);
+    }
+
+    /// Remove hop-by-hop and configured headers from an upstream response
+    fn filter_response_headers(&self, headers: &mut HeaderMap) {
+        strip_hop_by_hop(headers);
+        self.config.header_policy.response.apply(headers);
+        if !self.config.propagate_retry_after {
+            headers.remove(http::header::RETRY_AFTER);
+        }
+    }
+
     /// Build the upstream URI
-    fn build_upstream_uri(&self, req: &Request<Body>, upstream: &UpstreamInstance) -> Result<Uri> {
+    fn build_upstream_uri<B>(&self, req: &Request<B>, upstream: &UpstreamInstance) -> Result<Uri> {
         let path_and_query = req
             .uri()
             .path_and_query()
@@ -378,12 +578,13 @@ impl HttpProxy {
     }
 
     /// Transform request headers
-    fn transform_headers(
+    fn transform_headers<B>(
         &self,
-        req: &mut Request<Body>,
+        req: &mut Request<B>,
         upstream: &UpstreamInstance,
     ) -> Result<()> {
         let headers = req.headers_mut();
+        strip_hop_by_hop(headers);
 
         // Update Host header if not preserving
         if !self.config.preserve_host {
@@ -395,16 +596,12 @@ impl HttpProxy {
             );
         }
 
-        // Add X-Forwarded-* headers
+        // Default X-Forwarded-Proto; the gateway sets the full X-Forwarded-*
+        // set from the connection on ingress, which takes precedence.
         if self.config.add_forwarded_headers {
-            // X-Forwarded-Proto
-            headers.insert(
-                http::HeaderName::from_static("x-forwarded-proto"),
-                http::HeaderValue::from_static("http"),
-            );
-
-            // TODO: Add X-Forwarded-For when client IP is available
-            // TODO: Add X-Forwarded-Host
+            headers
+                .entry(http::HeaderName::from_static("x-forwarded-proto"))
+                .or_insert(http::HeaderValue::from_static("http"));
         }
 
         // Add custom headers
@@ -449,6 +646,7 @@ impl HttpProxy {
         upstream: &UpstreamInstance,
     ) -> Result<()> {
         let headers = req.headers_mut();
+        strip_hop_by_hop(headers);
 
         if !self.config.preserve_host {
             let host = format!("{}:{}", upstream.address, upstream.port);
@@ -460,10 +658,9 @@ impl HttpProxy {
         }
 
         if self.config.add_forwarded_headers {
-            headers.insert(
-                http::HeaderName::from_static("x-forwarded-proto"),
-                http::HeaderValue::from_static("http"),
-            );
+            headers
+                .entry(http::HeaderName::from_static("x-forwarded-proto"))
+                .or_insert(http::HeaderValue::from_static("http"));
         }
 
         for (name, value) in &self.config.upstream_headers {
@@ -488,6 +685,17 @@ impl HttpProxy {
         &self.config
     }
 
+    /// The request's [`ResponseBodyLimit`], else the proxy-wide one
+    fn response_body_limit(&self, extensions: &http::Extensions) -> ResponseBodyLimit {
+        extensions
+            .get::<ResponseBodyLimit>()
+            .copied()
+            .unwrap_or(ResponseBodyLimit {
+                max_bytes: self.config.limits.max_response_body_size,
+                truncate: self.config.truncate_large_responses,
+            })
+    }
+
     /// Get circuit breaker
     pub fn circuit_breaker(&self) -> &Arc<CircuitBreaker> {
         &self.circuit_breaker
@@ -497,6 +705,11 @@ impl HttpProxy {
     pub fn retry_policy(&self) -> &Arc<RetryPolicy> {
         &self.retry_policy
     }
+
+    /// Instances currently throttled by an upstream `Retry-After`
+    pub fn throttle(&self) -> &Arc<UpstreamThrottle> {
+        &self.throttle
+    }
 }
 
 impl std::fmt::Debug for HttpProxy {
@@ -510,6 +723,80 @@ impl std::fmt::Debug for HttpProxy {
     }
 }
 
+/// Run `fut`, failing with [`Error::DeadlineExceeded`] if `deadline` passes
+/// first.
+async fn within<T>(deadline: Option<Deadline>, fut: impl Future<Output = Result<T>>) -> Result<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.instant().into(), fut)
+            .await
+            .map_err(|_| Error::DeadlineExceeded)?,
+        None => fut.await,
+    }
+}
+
+/// Collect an upstream response body, enforcing `limit` as frames arrive.
+///
+/// A body past the limit fails with [`Error::UpstreamResponseTooLarge`], or,
+/// when the limit truncates, is cut at `max_bytes` with a warning and its
+/// `Content-Length` dropped. A `Content-Encoding`d body is never truncated,
+/// as a cut gzip or br stream can't be decoded, so it fails instead. A
+/// declared length over a failing limit fails before anything is read.
+async fn collect_limited(
+    mut body: Incoming,
+    headers: &mut HeaderMap,
+    limit: ResponseBodyLimit,
+    upstream_id: &str,
+) -> Result<Bytes> {
+    let encoded = headers
+        .get(CONTENT_ENCODING)
+        .is_some_and(|v| !v.as_bytes().eq_ignore_ascii_case(b"identity"));
+    let truncate = limit.truncate && !encoded;
+
+    let declared = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if !truncate && declared.is_some_and(|len| len > limit.max_bytes as u64) {
+        warn!(upstream = %upstream_id, max_bytes = limit.max_bytes, "Upstream response too large");
+        return Err(Error::UpstreamResponseTooLarge(limit.max_bytes));
+    }
+
+    let mut buf = BytesMut::new();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| upstream_error::body_error(&e))?;
+        // Trailers are not forwarded on buffered responses
+        let Ok(data) = frame.into_data() else {
+            continue;
+        };
+        if buf.len() + data.len() <= limit.max_bytes {
+            buf.extend_from_slice(&data);
+            continue;
+        }
+        warn!(
+            upstream = %upstream_id,
+            max_bytes = limit.max_bytes,
+            truncated = truncate,
+            "Upstream response exceeded the body limit"
+        );
+        if !truncate {
+            return Err(Error::UpstreamResponseTooLarge(limit.max_bytes));
+        }
+        buf.extend_from_slice(&data[..limit.max_bytes - buf.len()]);
+        headers.remove(CONTENT_LENGTH);
+        break;
+    }
+    Ok(buf.freeze())
+}
+
+/// Sleep for `backoff`, waking early if `deadline` passes first.
+async fn sleep_within(deadline: Option<Deadline>, backoff: Duration) {
+    let backoff = match deadline.map(|d| d.remaining()) {
+        Some(remaining) => backoff.min(remaining.unwrap_or_default()),
+        None => backoff,
+    };
+    sleep(backoff).await;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -538,6 +825,133 @@ mod tests {
         assert_eq!(uri.to_string(), "http://localhost:8080/test?foo=bar");
     }
 
+    fn policy_proxy() -> HttpProxy {
+        let config = ProxyConfig {
+            header_policy: HeaderStripPolicy {
+                request: crate::HeaderFilter::new(["x-auth-*"], Vec::<&str>::new()),
+                response: crate::HeaderFilter::new(["x-internal-*"], ["x-internal-trace-id"]),
+            },
+            ..Default::default()
+        };
+        HttpProxy::new(HttpClient::new(), config)
+    }
+
+    #[tokio::test]
+    async fn upstream_request_drops_hop_by_hop_headers() {
+        let proxy = policy_proxy();
+        let mut req = Request::builder()
+            .uri("/test")
+            .header("connection", "keep-alive, x-hop")
+            .header("keep-alive", "timeout=5")
+            .header("x-hop", "1")
+            .header("upgrade", "h2c")
+            .header("te", "trailers")
+            .header("accept", "application/json")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let upstream = UpstreamInstance::new("test", "localhost", 8080);
+
+        proxy.transform_headers_full(&mut req, &upstream).unwrap();
+
+        let headers = req.headers();
+        for name in ["connection", "keep-alive", "x-hop", "upgrade"] {
+            assert!(!headers.contains_key(name), "{name} should be stripped");
+        }
+        assert_eq!(headers["te"], "trailers");
+        assert_eq!(headers["accept"], "application/json");
+        assert_eq!(headers["host"], "localhost:8080");
+    }
+
+    #[tokio::test]
+    async fn upstream_request_keeps_ingress_forwarded_proto() {
+        let proxy = policy_proxy();
+        let upstream = UpstreamInstance::new("test", "localhost", 8080);
+
+        let mut req = Request::builder()
+            .uri("/test")
+            .header("x-forwarded-proto", "https")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        proxy.transform_headers_full(&mut req, &upstream).unwrap();
+        assert_eq!(req.headers()["x-forwarded-proto"], "https");
+
+        let mut req = Request::builder()
+            .uri("/test")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        proxy.transform_headers_full(&mut req, &upstream).unwrap();
+        assert_eq!(req.headers()["x-forwarded-proto"], "http");
+    }
+
+    #[tokio::test]
+    async fn client_supplied_auth_headers_are_stripped_on_ingress() {
+        let proxy = policy_proxy();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-auth-principal", "admin".parse().unwrap());
+        headers.insert("x-auth-roles", "superuser".parse().unwrap());
+        headers.insert("authorization", "Bearer t".parse().unwrap());
+
+        proxy.strip_client_headers(&mut headers);
+
+        assert!(!headers.contains_key("x-auth-principal"));
+        assert!(!headers.contains_key("x-auth-roles"));
+        assert!(headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn upstream_response_drops_hop_by_hop_and_internal_headers() {
+        let proxy = policy_proxy();
+        let mut headers = HeaderMap::new();
+        headers.insert("connection", "close".parse().unwrap());
+        headers.insert("transfer-encoding", "chunked".parse().unwrap());
+        headers.insert("proxy-authenticate", "Basic".parse().unwrap());
+        headers.insert("x-internal-node", "10.0.0.7".parse().unwrap());
+        headers.insert("x-internal-trace-id", "t-1".parse().unwrap());
+        headers.insert("content-type", "text/plain".parse().unwrap());
+
+        proxy.filter_response_headers(&mut headers);
+
+        for name in [
+            "connection",
+            "transfer-encoding",
+            "proxy-authenticate",
+            "x-internal-node",
+        ] {
+            assert!(!headers.contains_key(name), "{name} should be stripped");
+        }
+        assert!(headers.contains_key("x-internal-trace-id"));
+        assert!(headers.contains_key("content-type"));
+    }
+
+    #[tokio::test]
+    async fn forced_open_circuit_fast_fails_until_reset() {
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default()));
+        let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default())
+            .with_circuit_breaker(Arc::clone(&breaker));
+        // Nothing listens here; a real attempt would fail with a connect error
+        let upstream = UpstreamInstance::new("down", "127.0.0.1", 1);
+        let request = || {
+            Request::builder()
+                .uri("/test")
+                .body(Full::new(Bytes::new()))
+                .unwrap()
+        };
+
+        breaker.force_open(&upstream.id);
+        let err = proxy
+            .proxy_with_retry(request(), &upstream)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::CircuitBreakerOpen(ref id) if id == "down"));
+
+        breaker.reset(&upstream.id);
+        let err = proxy
+            .proxy_with_retry(request(), &upstream)
+            .await
+            .unwrap_err();
+        assert!(!matches!(err, Error::CircuitBreakerOpen(_)));
+    }
+
     #[tokio::test]
     async fn test_proxy_creation() {
         let pool = Arc::new(ConnectionPool::new(PoolConfig::default()));
@@ -1,10 +1,11 @@
 //! Retry logic with exponential backoff for transient failures
 
+use dashmap::DashMap;
 use http::{Method, Request, Response, StatusCode};
 use octopus_core::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
 /// Retry policy configuration
@@ -26,6 +27,15 @@ pub struct RetryPolicy {
     /// Timeout per attempt
     #[serde(with = "humantime_serde")]
     pub timeout_per_attempt: Duration,
+
+    /// Longest upstream `Retry-After` (on 429/503) waited out before
+    /// retrying; a longer delay ends the retries and is passed to the client
+    #[serde(default = "default_max_retry_after", with = "humantime_serde")]
+    pub max_retry_after: Duration,
+}
+
+fn default_max_retry_after() -> Duration {
+    Duration::from_secs(5)
 }
 
 impl Default for RetryPolicy {
@@ -51,6 +61,7 @@ impl Default for RetryPolicy {
             retryable_methods,
             retryable_status_codes,
             timeout_per_attempt: Duration::from_secs(30),
+            max_retry_after: default_max_retry_after(),
         }
     }
 }
@@ -79,6 +90,12 @@ impl RetryPolicy {
         self
     }
 
+    /// Set the longest upstream `Retry-After` waited out before retrying
+    pub fn with_max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
     /// Check if method is retryable
     pub fn is_method_retryable(&self, method: &Method) -> bool {
         self.retryable_methods.contains(method)
@@ -91,7 +108,7 @@ impl RetryPolicy {
 
     /// Check if error is retryable
     pub fn is_error_retryable(&self, error: &Error) -> bool {
-        matches!(error, Error::UpstreamTimeout | Error::UpstreamConnection(_))
+        error.is_upstream_failure()
     }
 
     /// Calculate backoff delay for attempt number
@@ -318,6 +335,62 @@ pub fn parse_retry_after(value: &str) -> Option<Duration> {
     None
 }
 
+/// `Retry-After` of a 429 or 503 upstream response, if it carries one
+pub fn upstream_retry_after(status: StatusCode, headers: &http::HeaderMap) -> Option<Duration> {
+    if !matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+    headers
+        .get(http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()
+        .and_then(|v| parse_retry_after(v.trim()))
+}
+
+/// Upstream instances that asked, via `Retry-After`, not to be sent requests
+/// for a while
+#[derive(Debug, Default)]
+pub struct UpstreamThrottle {
+    until: DashMap<String, Instant>,
+}
+
+impl UpstreamThrottle {
+    /// Create an empty throttle map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Avoid instance `id` for `delay`. A later deadline already recorded is
+    /// kept.
+    pub fn throttle(&self, id: &str, delay: Duration) {
+        let until = Instant::now() + delay;
+        self.until
+            .entry(id.to_string())
+            .and_modify(|t| *t = (*t).max(until))
+            .or_insert(until);
+    }
+
+    /// Time left before instance `id` may be used again (`None` if it is not
+    /// throttled)
+    pub fn remaining(&self, id: &str) -> Option<Duration> {
+        let until = *self.until.get(id)?;
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            self.until.remove_if(id, |_, t| *t <= Instant::now());
+            return None;
+        }
+        Some(remaining)
+    }
+
+    /// Whether instance `id` is currently throttled
+    pub fn is_throttled(&self, id: &str) -> bool {
+        self.remaining(id).is_some()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -390,6 +463,49 @@ mod tests {
 
         // Invalid format
         assert_eq!(parse_retry_after("invalid"), None);
+
+        // HTTP date in the future
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now() + Duration::from_secs(90));
+        let delay = parse_retry_after(&date).unwrap();
+        assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(90));
+    }
+
+    #[test]
+    fn test_upstream_retry_after_only_for_429_and_503() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::RETRY_AFTER, " 7 ".parse().unwrap());
+
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert_eq!(
+                upstream_retry_after(status, &headers),
+                Some(Duration::from_secs(7))
+            );
+        }
+        assert_eq!(
+            upstream_retry_after(StatusCode::BAD_GATEWAY, &headers),
+            None
+        );
+        assert_eq!(
+            upstream_retry_after(StatusCode::SERVICE_UNAVAILABLE, &http::HeaderMap::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_upstream_throttle() {
+        let throttle = UpstreamThrottle::new();
+        assert!(!throttle.is_throttled("a"));
+
+        throttle.throttle("a", Duration::from_secs(30));
+        throttle.throttle("a", Duration::from_secs(1));
+        assert!(throttle.remaining("a").unwrap() > Duration::from_secs(20));
+        assert!(!throttle.is_throttled("b"));
+
+        throttle.throttle("b", Duration::ZERO);
+        assert!(!throttle.is_throttled("b"));
     }
 
     #[test]
@@ -112,7 +112,7 @@ impl TlsConfig {
     /// Connect to a TLS server
     pub async fn connect(&self, stream: TcpStream, domain: &str) -> Result<TlsStream<TcpStream>> {
         let server_name = ServerName::try_from(domain.to_string())
-            .map_err(|e| Error::UpstreamConnection(format!("Invalid server name: {e}")))?;
+            .map_err(|e| Error::UpstreamTls(format!("Invalid server name: {e}")))?;
 
         debug!(
             domain = %domain,
@@ -123,7 +123,7 @@ impl TlsConfig {
         self.connector
             .connect(server_name, stream)
             .await
-            .map_err(|e| Error::UpstreamConnection(format!("TLS handshake failed: {e}")))
+            .map_err(|e| Error::UpstreamTls(e.to_string()))
     }
 
     /// Get the connector
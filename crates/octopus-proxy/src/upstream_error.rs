@@ -0,0 +1,228 @@
+//! Classification of upstream transport failures
+//!
+//! Maps the I/O and hyper errors of connecting to an upstream, exchanging a
+//! request with it and reading its response body onto the distinct upstream
+//! [`Error`] variants, so that clients get a specific status and message and
+//! metrics can tell the failure modes apart.
+
+use octopus_core::Error;
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+
+/// Resolve `host` and open a TCP connection to it.
+pub(crate) async fn connect(host: &str, port: u16) -> Result<TcpStream, Error> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| Error::UpstreamDns(format!("{host}: {e}")))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(Error::UpstreamDns(format!("{host}: no addresses")));
+    }
+    TcpStream::connect(addrs.as_slice())
+        .await
+        .map_err(connect_error)
+}
+
+/// A failed TCP connect
+pub(crate) fn connect_error(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused => Error::UpstreamRefused(e.to_string()),
+        io::ErrorKind::TimedOut => Error::UpstreamTimeout,
+        _ if is_reset(e.kind()) => Error::UpstreamReset(e.to_string()),
+        _ => Error::UpstreamConnection(format!("Failed to connect: {e}")),
+    }
+}
+
+/// A request that failed before the response head arrived
+pub(crate) fn request_error(e: &hyper::Error) -> Error {
+    if e.is_timeout() {
+        return Error::UpstreamTimeout;
+    }
+    match io_error_kind(e) {
+        Some(io::ErrorKind::TimedOut) => Error::UpstreamTimeout,
+        Some(kind) if is_reset(kind) => Error::UpstreamReset(e.to_string()),
+        _ if e.is_incomplete_message() || e.is_closed() => Error::UpstreamReset(e.to_string()),
+        _ => Error::UpstreamConnection(e.to_string()),
+    }
+}
+
+/// A response body that could not be read to its end
+pub(crate) fn body_error(e: &hyper::Error) -> Error {
+    match io_error_kind(e) {
+        Some(io::ErrorKind::TimedOut) => Error::UpstreamTimeout,
+        _ => Error::UpstreamIncompleteResponse(e.to_string()),
+    }
+}
+
+fn is_reset(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+/// Kind of the I/O error somewhere in `e`'s source chain
+fn io_error_kind(e: &hyper::Error) -> Option<io::ErrorKind> {
+    let mut source = std::error::Error::source(e);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<io::Error>() {
+            return Some(io.kind());
+        }
+        source = err.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::HttpClient;
+    use crate::proxy::{HttpProxy, ProxyConfig};
+    use bytes::Bytes;
+    use http::{Request, StatusCode};
+    use http_body_util::Full;
+    use octopus_core::{ErrorCode, UpstreamInstance};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Upstream that reads one request, then hands the socket to `respond`.
+    async fn mock_upstream<F, Fut>(respond: F) -> UpstreamInstance
+    where
+        F: FnOnce(TcpStream) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            respond(stream).await;
+        });
+        UpstreamInstance::new("mock-1", "127.0.0.1", port)
+    }
+
+    fn request() -> Request<Full<Bytes>> {
+        Request::builder()
+            .uri("/orders")
+            .body(Full::new(Bytes::new()))
+            .unwrap()
+    }
+
+    fn assert_maps(err: &Error, code: ErrorCode, status: StatusCode) {
+        assert_eq!(err.code(), code, "{err:?}");
+        assert_eq!(err.to_status_code(), status, "{err:?}");
+        assert!(err.is_upstream_failure(), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn refused_connection_is_502_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let instance = UpstreamInstance::new("gone-1", "127.0.0.1", port);
+        let err = HttpClient::new()
+            .send(request(), &instance)
+            .await
+            .unwrap_err();
+        assert_maps(&err, ErrorCode::UpstreamRefused, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn reset_before_response_is_502_reset() {
+        let instance = mock_upstream(|stream| async move {
+            // Linger 0 turns the close into an RST.
+            stream.set_linger(Some(Duration::ZERO)).unwrap();
+            drop(stream);
+        })
+        .await;
+
+        let err = HttpClient::new()
+            .send(request(), &instance)
+            .await
+            .unwrap_err();
+        assert_maps(&err, ErrorCode::UpstreamReset, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn eof_mid_body_is_502_incomplete() {
+        let instance = mock_upstream(|mut stream| async move {
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\npartial")
+                .await
+                .unwrap();
+        })
+        .await;
+
+        let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+        let err = proxy
+            .proxy_buffered(request(), &instance)
+            .await
+            .unwrap_err();
+        assert_maps(
+            &err,
+            ErrorCode::UpstreamIncompleteResponse,
+            StatusCode::BAD_GATEWAY,
+        );
+    }
+
+    #[tokio::test]
+    async fn unresolvable_host_is_502_dns() {
+        let instance = UpstreamInstance::new("dns-1", "upstream.invalid", 80);
+        let err = HttpClient::new()
+            .send(request(), &instance)
+            .await
+            .unwrap_err();
+        assert_maps(&err, ErrorCode::UpstreamDnsFailure, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn failed_tls_handshake_is_502_tls() {
+        let mut instance = mock_upstream(|mut stream| async move {
+            let _ = stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\n\r\n")
+                .await;
+        })
+        .await;
+        instance.set_tls(true, Some("localhost".to_string()), true);
+
+        let err = HttpClient::new()
+            .send(request(), &instance)
+            .await
+            .unwrap_err();
+        assert_maps(&err, ErrorCode::UpstreamTlsFailure, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn silent_upstream_is_504_timeout() {
+        let instance = mock_upstream(|stream| async move {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(stream);
+        })
+        .await;
+
+        let client = HttpClient::with_timeout(Duration::from_millis(100));
+        let err = client.send(request(), &instance).await.unwrap_err();
+        assert_maps(
+            &err,
+            ErrorCode::UpstreamTimeout,
+            StatusCode::GATEWAY_TIMEOUT,
+        );
+    }
+
+    #[test]
+    fn client_messages_do_not_leak_details() {
+        let err = connect_error(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "10.0.0.7:8080",
+        ));
+        assert!(matches!(err, Error::UpstreamRefused(_)));
+        assert!(!err.client_message().contains("10.0.0.7"));
+    }
+}
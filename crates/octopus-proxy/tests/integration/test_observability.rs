@@ -367,3 +367,81 @@ async fn test_audit_event_authorization() {
 
     logger.log(&granted);
 }
+
+#[tokio::test]
+async fn test_upstream_selection_recorded_on_response() {
+    use octopus_core::UpstreamSelection;
+    use octopus_proxy::{HttpClient, HttpProxy, ProxyConfig};
+    use std::time::Duration;
+
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    mock.set_config(MockConfig {
+        delay: Some(Duration::from_millis(20)),
+        ..Default::default()
+    })
+    .await;
+
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .id("users-1")
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let res = proxy
+        .proxy_with_retry(TestFixtures::request().build(), &upstream)
+        .await
+        .unwrap();
+
+    let selection = res.extensions().get::<UpstreamSelection>().unwrap();
+    assert_eq!(selection.instance_id, "users-1");
+    assert_eq!(
+        selection.address,
+        format!("127.0.0.1:{}", mock.addr().port())
+    );
+    assert!(selection.latency >= Duration::from_millis(20));
+    assert_eq!(selection.retries, 0);
+    assert!(!selection.cache_hit);
+}
+
+#[tokio::test]
+async fn test_upstream_selection_counts_retries() {
+    use octopus_core::UpstreamSelection;
+    use octopus_proxy::{BackoffStrategy, HttpClient, HttpProxy, ProxyConfig, RetryPolicy};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    mock.set_config(MockConfig {
+        status_code: http::StatusCode::SERVICE_UNAVAILABLE,
+        ..Default::default()
+    })
+    .await;
+
+    let policy = RetryPolicy {
+        max_attempts: 2,
+        backoff: BackoffStrategy::Fixed {
+            delay: Duration::from_millis(1),
+        },
+        ..Default::default()
+    };
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default())
+        .with_retry_policy(Arc::new(policy));
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let res = proxy
+        .proxy_with_retry(TestFixtures::request().build(), &upstream)
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        res.extensions().get::<UpstreamSelection>().unwrap().retries,
+        2
+    );
+}
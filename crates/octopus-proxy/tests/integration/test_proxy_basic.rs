@@ -260,6 +260,58 @@ async fn test_upstream_error_propagation() {
     assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
 }
 
+#[tokio::test]
+async fn test_upstream_error_response_is_passed_through() {
+    use http_body_util::BodyExt;
+
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    let addr = mock.addr();
+
+    let mut config = MockConfig::default();
+    config.status_code = StatusCode::INTERNAL_SERVER_ERROR;
+    config.body = Bytes::from("Internal Server Error");
+    config
+        .headers
+        .insert("X-Upstream-Trace".to_string(), "abc123".to_string());
+    mock.set_config(config).await;
+
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(addr.port())
+        .build();
+
+    // The upstream's answer, status, headers and body, is the response, not
+    // a gateway error
+    let req = TestFixtures::request().build();
+    let response = proxy.proxy_with_retry(req, &upstream).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.headers()["x-upstream-trace"], "abc123");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, Bytes::from("Internal Server Error"));
+}
+
+#[tokio::test]
+async fn test_unreachable_upstream_is_a_gateway_error() {
+    // A port nothing listens on
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(port)
+        .build();
+
+    let req = TestFixtures::request().build();
+    let err = proxy.proxy_with_retry(req, &upstream).await.unwrap_err();
+    assert!(err.is_upstream_failure(), "{err:?}");
+    assert!(err.upstream_response().is_none());
+    assert_eq!(err.to_status_code(), StatusCode::BAD_GATEWAY);
+}
+
 #[tokio::test]
 async fn test_route_specific_responses() {
     let mut mock = MockUpstream::new(0).await.unwrap();
@@ -303,3 +355,49 @@ async fn test_route_specific_responses() {
     let body = response.into_body().collect().await.unwrap().to_bytes();
     assert_eq!(body, Bytes::from("healthy"));
 }
+
+#[tokio::test]
+async fn test_streamed_request_body_reaches_upstream() {
+    use futures::StreamExt;
+    use http_body_util::{BodyExt, StreamBody};
+    use hyper::body::Frame;
+    use std::convert::Infallible;
+
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    mock.set_config(MockConfig {
+        echo_headers: true,
+        ..Default::default()
+    })
+    .await;
+
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .id("uploads-1")
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    // 64 chunks of 16 KiB, sent as they are produced.
+    let chunks = futures::stream::iter(0..64)
+        .map(|_| Ok::<_, Infallible>(Frame::data(Bytes::from(vec![b'x'; 16 * 1024]))));
+    let req = http::Request::builder()
+        .method(Method::POST)
+        .uri("/upload")
+        .header("content-length", (64 * 16 * 1024).to_string())
+        .body(StreamBody::new(chunks))
+        .unwrap();
+
+    let response = proxy.proxy_streaming(req, &upstream).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["x-request-body-size"],
+        (64 * 16 * 1024).to_string()
+    );
+    let selection = response
+        .extensions()
+        .get::<octopus_core::UpstreamSelection>()
+        .unwrap();
+    assert_eq!(selection.instance_id, "uploads-1");
+    assert_eq!(response.into_body().collect().await.unwrap().to_bytes(), "OK");
+}
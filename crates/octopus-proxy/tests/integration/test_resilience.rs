@@ -1,8 +1,11 @@
 //! Resilience features integration tests
 
 use super::*;
-use octopus_health::circuit_breaker::CircuitState;
-use octopus_proxy::{HttpClient, HttpProxy, ProxyConfig};
+use hyper::StatusCode;
+use octopus_core::{Deadline, Error, ResponseBodyLimit};
+use octopus_health::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use octopus_proxy::{BackoffStrategy, HttpClient, HttpProxy, ProxyConfig, RetryPolicy};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[tokio::test]
@@ -90,6 +93,54 @@ async fn test_circuit_breaker_opens_on_failures() {
     );
 }
 
+#[tokio::test]
+async fn test_client_errors_do_not_trip_circuit_breaker() {
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    let addr = mock.addr();
+    mock.set_config(MockConfig {
+        status_code: StatusCode::NOT_FOUND,
+        ..Default::default()
+    })
+    .await;
+
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .id("client-error-test")
+        .host("127.0.0.1")
+        .port(addr.port())
+        .build();
+
+    for _ in 0..15 {
+        let response = proxy
+            .proxy_resilient(TestFixtures::request().build(), &upstream)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    assert_eq!(
+        proxy.circuit_breaker().get_state(&upstream.id),
+        CircuitState::Closed,
+        "404s are client errors and must not trip the breaker"
+    );
+
+    mock.set_config(MockConfig {
+        status_code: StatusCode::SERVICE_UNAVAILABLE,
+        ..Default::default()
+    })
+    .await;
+    for _ in 0..20 {
+        let _ = proxy
+            .proxy_resilient(TestFixtures::request().build(), &upstream)
+            .await;
+    }
+    assert_eq!(
+        proxy.circuit_breaker().get_state(&upstream.id),
+        CircuitState::Open,
+        "503s count as failures"
+    );
+}
+
 #[tokio::test]
 async fn test_circuit_breaker_prevents_requests_when_open() {
     let mut mock = MockUpstream::new(0).await.unwrap();
@@ -331,3 +382,330 @@ async fn test_circuit_breaker_recovery() {
         "Circuit breaker should be closed after successes"
     );
 }
+
+async fn retry_after_upstream(retry_after: &str) -> MockUpstream {
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    mock.add_route(
+        "/test".to_string(),
+        MockResponse::new(StatusCode::SERVICE_UNAVAILABLE, "busy")
+            .with_header("Retry-After".to_string(), retry_after.to_string()),
+    )
+    .await;
+    mock
+}
+
+#[tokio::test]
+async fn test_long_retry_after_is_not_retried_and_is_propagated() {
+    let mock = retry_after_upstream("60").await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .id("throttled")
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let start = std::time::Instant::now();
+    let response = proxy
+        .proxy_with_retry(TestFixtures::request().uri("/test").build(), &upstream)
+        .await
+        .unwrap();
+
+    // The instance asked for 60s, longer than the 5s we wait: no retry.
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()["retry-after"], "60");
+    assert_eq!(mock.stats().await.requests_received, 1);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    let remaining = proxy.throttle().remaining("throttled").unwrap();
+    assert!(remaining > Duration::from_secs(50));
+}
+
+#[tokio::test]
+async fn test_short_retry_after_delays_the_retry() {
+    let mock = retry_after_upstream("1").await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default())
+        .with_retry_policy(Arc::new(RetryPolicy::new().with_max_attempts(1)));
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let start = std::time::Instant::now();
+    let response = proxy
+        .proxy_with_retry(TestFixtures::request().uri("/test").build(), &upstream)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(mock.stats().await.requests_received, 2);
+    assert!(
+        start.elapsed() >= Duration::from_secs(1),
+        "retried before the upstream's Retry-After elapsed"
+    );
+}
+
+#[tokio::test]
+async fn test_retry_after_can_be_withheld_from_clients() {
+    let mock = retry_after_upstream("60").await;
+    let mut proxy_config = ProxyConfig::default();
+    proxy_config.propagate_retry_after = false;
+    let proxy = HttpProxy::new(HttpClient::new(), proxy_config);
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let response = proxy
+        .proxy_with_retry(TestFixtures::request().uri("/test").build(), &upstream)
+        .await
+        .unwrap();
+
+    assert!(!response.headers().contains_key("retry-after"));
+    assert!(proxy.throttle().is_throttled(&upstream.id));
+}
+
+async fn slow_upstream(status: StatusCode, delay: Duration) -> MockUpstream {
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    mock.add_route(
+        "/slow".to_string(),
+        MockResponse::new(status, "slow").with_delay(delay),
+    )
+    .await;
+    mock
+}
+
+fn request_with_budget(budget: Duration) -> http::Request<http_body_util::Full<bytes::Bytes>> {
+    let mut req = TestFixtures::request().uri("/slow").build();
+    req.extensions_mut().insert(Deadline::after(budget));
+    req
+}
+
+#[tokio::test]
+async fn test_deadline_bounds_a_slow_attempt() {
+    let mock = slow_upstream(StatusCode::OK, Duration::from_secs(5)).await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let start = std::time::Instant::now();
+    let err = proxy
+        .proxy_with_retry(request_with_budget(Duration::from_millis(300)), &upstream)
+        .await
+        .unwrap_err();
+
+    // The attempt is cut at the deadline, not at the 30s client timeout, and
+    // the expired budget leaves no room for a retry.
+    assert!(matches!(err, Error::DeadlineExceeded), "{err:?}");
+    assert_eq!(err.to_status_code(), StatusCode::GATEWAY_TIMEOUT);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(mock.stats().await.requests_received, 1);
+}
+
+#[tokio::test]
+async fn test_no_retry_starts_after_the_deadline() {
+    // The first attempt takes most of the 400ms budget and fails with a
+    // retryable status; the backoff outlasts what is left.
+    let mock = slow_upstream(StatusCode::SERVICE_UNAVAILABLE, Duration::from_millis(300)).await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default()).with_retry_policy(
+        Arc::new(RetryPolicy::new().with_backoff(BackoffStrategy::Fixed {
+            delay: Duration::from_millis(200),
+        })),
+    );
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let start = std::time::Instant::now();
+    let err = proxy
+        .proxy_with_retry(request_with_budget(Duration::from_millis(400)), &upstream)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::DeadlineExceeded), "{err:?}");
+    assert!(start.elapsed() < Duration::from_millis(700));
+    assert_eq!(mock.stats().await.requests_received, 1);
+}
+
+#[tokio::test]
+async fn test_retries_share_the_remaining_budget() {
+    // Without a deadline the same upstream is retried to exhaustion.
+    let mock = slow_upstream(StatusCode::SERVICE_UNAVAILABLE, Duration::from_millis(100)).await;
+    let proxy =
+        HttpProxy::new(HttpClient::new(), ProxyConfig::default()).with_retry_policy(Arc::new(
+            RetryPolicy::new()
+                .with_max_attempts(5)
+                .with_backoff(BackoffStrategy::Fixed {
+                    delay: Duration::from_millis(10),
+                }),
+        ));
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let unbounded = proxy
+        .proxy_with_retry(TestFixtures::request().uri("/slow").build(), &upstream)
+        .await
+        .unwrap();
+    assert_eq!(unbounded.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(mock.stats().await.requests_received, 6);
+
+    // A 250ms budget fits at most three 100ms attempts.
+    mock.reset_stats().await;
+    let err = proxy
+        .proxy_with_retry(request_with_budget(Duration::from_millis(250)), &upstream)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::DeadlineExceeded), "{err:?}");
+    let attempts = mock.stats().await.requests_received;
+    assert!((2..=3).contains(&attempts), "{attempts} attempts");
+}
+
+/// A breaker that opens on one failure and probes once after 50ms
+fn quick_breaker(config: CircuitBreakerConfig) -> Arc<CircuitBreaker> {
+    Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+        min_requests: 1,
+        open_timeout: Duration::from_millis(50),
+        half_open_max_requests: 1,
+        ..config
+    }))
+}
+
+#[tokio::test]
+async fn test_half_open_probe_over_the_body_limit_gives_its_slot_back() {
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    let breaker = quick_breaker(CircuitBreakerConfig::default());
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default())
+        .with_circuit_breaker(Arc::clone(&breaker));
+    let upstream = TestFixtures::upstream()
+        .id("half-open-limit")
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+    breaker.record_failure(&upstream.id);
+    tokio::time::sleep(Duration::from_millis(80)).await;
+
+    // The probe fails on the gateway's own limit, which says nothing about
+    // the upstream.
+    let mut req = TestFixtures::request().build();
+    req.extensions_mut().insert(ResponseBodyLimit {
+        max_bytes: 1,
+        truncate: false,
+    });
+    let err = proxy.proxy_with_retry(req, &upstream).await.unwrap_err();
+    assert!(matches!(err, Error::UpstreamResponseTooLarge(_)), "{err:?}");
+    assert_eq!(breaker.get_state(&upstream.id), CircuitState::HalfOpen);
+    assert!(breaker.admits(&upstream.id));
+
+    let response = proxy
+        .proxy_with_retry(TestFixtures::request().build(), &upstream)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(breaker.get_state(&upstream.id), CircuitState::Closed);
+}
+
+/// Upstream answering its first connection with a body cut short and later
+/// ones in full; returns its port and the connections it accepted
+async fn truncating_upstream() -> (u16, Arc<std::sync::atomic::AtomicUsize>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let accepted = Arc::clone(&connections);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let n = accepted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let reply: &[u8] = if n == 0 {
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\nconnection: close\r\n\r\npartial"
+                } else {
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nOK"
+                };
+                let _ = stream.write_all(reply).await;
+            });
+        }
+    });
+    (port, connections)
+}
+
+#[tokio::test]
+async fn test_body_cut_short_is_retried() {
+    let (port, connections) = truncating_upstream().await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default()).with_retry_policy(
+        Arc::new(RetryPolicy::new().with_backoff(BackoffStrategy::Fixed {
+            delay: Duration::from_millis(10),
+        })),
+    );
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(port)
+        .build();
+
+    let response = proxy
+        .proxy_with_retry(TestFixtures::request().build(), &upstream)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_body_cut_short_is_recorded_by_the_breaker() {
+    let (port, _) = truncating_upstream().await;
+    let breaker = quick_breaker(CircuitBreakerConfig::default());
+    let mut proxy_config = ProxyConfig::default();
+    proxy_config.enable_retry = false;
+    let proxy =
+        HttpProxy::new(HttpClient::new(), proxy_config).with_circuit_breaker(Arc::clone(&breaker));
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(port)
+        .build();
+
+    let err = proxy
+        .proxy_with_retry(TestFixtures::request().build(), &upstream)
+        .await
+        .unwrap_err();
+    assert!(err.is_upstream_failure(), "{err:?}");
+    assert_eq!(breaker.get_state(&upstream.id), CircuitState::Open);
+}
+
+#[tokio::test]
+async fn test_deadline_after_a_retryable_status_records_the_status() {
+    // Timeouts are ignored, so only the observed 503 can trip the breaker.
+    let mock = slow_upstream(StatusCode::SERVICE_UNAVAILABLE, Duration::from_millis(300)).await;
+    let breaker = quick_breaker(CircuitBreakerConfig {
+        count_timeouts: false,
+        ..Default::default()
+    });
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default())
+        .with_circuit_breaker(Arc::clone(&breaker))
+        .with_retry_policy(Arc::new(RetryPolicy::new().with_backoff(
+            BackoffStrategy::Fixed {
+                delay: Duration::from_millis(200),
+            },
+        )));
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+
+    let err = proxy
+        .proxy_with_retry(request_with_budget(Duration::from_millis(400)), &upstream)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::DeadlineExceeded), "{err:?}");
+    let metrics = breaker.get_metrics(&upstream.id).unwrap();
+    assert_eq!(metrics.lifetime_failures, 1);
+    assert_eq!(breaker.get_state(&upstream.id), CircuitState::Open);
+}
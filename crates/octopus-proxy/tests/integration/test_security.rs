@@ -2,8 +2,13 @@
 
 use super::*;
 use bytes::Bytes;
-use http::{HeaderValue, Method};
-use octopus_proxy::{InMemoryRateLimiter, ProxyLimits, RateLimitConfig, RateLimitKeyBuilder};
+use http::{HeaderValue, Method, StatusCode};
+use http_body_util::BodyExt;
+use octopus_core::{Error, ResponseBodyLimit};
+use octopus_proxy::{
+    HttpClient, HttpProxy, InMemoryRateLimiter, ProxyConfig, ProxyLimits, RateLimitConfig,
+    RateLimitKeyBuilder,
+};
 use std::time::Duration;
 
 #[tokio::test]
@@ -330,3 +335,120 @@ async fn test_rate_limiter_check_tokens() {
     let result = limiter.check_tokens("test-key", 1);
     assert!(!result.is_allowed());
 }
+
+async fn upstream_returning(body: &'static str) -> (MockUpstream, octopus_core::UpstreamInstance) {
+    let mut mock = MockUpstream::new(0).await.unwrap();
+    mock.start().await.unwrap();
+    let mut config = MockConfig::default();
+    config.body = Bytes::from(body);
+    mock.set_config(config).await;
+    let upstream = TestFixtures::upstream()
+        .host("127.0.0.1")
+        .port(mock.addr().port())
+        .build();
+    (mock, upstream)
+}
+
+fn request_with_limit(
+    max_bytes: usize,
+    truncate: bool,
+) -> http::Request<http_body_util::Full<Bytes>> {
+    let mut req = TestFixtures::request().uri("/test").build();
+    req.extensions_mut().insert(ResponseBodyLimit {
+        max_bytes,
+        truncate,
+    });
+    req
+}
+
+#[tokio::test]
+async fn test_oversized_response_aborts_with_bad_gateway() {
+    let (_mock, upstream) = upstream_returning("0123456789abcdef").await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+
+    let err = proxy
+        .proxy_with_retry(request_with_limit(8, false), &upstream)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::UpstreamResponseTooLarge(8)), "{err:?}");
+    assert_eq!(err.to_status_code(), StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn test_oversized_response_is_truncated_when_configured() {
+    let (_mock, upstream) = upstream_returning("0123456789abcdef").await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+
+    let response = proxy
+        .proxy_with_retry(request_with_limit(8, true), &upstream)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response
+        .headers()
+        .contains_key(http::header::CONTENT_LENGTH));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, Bytes::from("01234567"));
+}
+
+#[tokio::test]
+async fn test_response_within_limit_passes_untouched() {
+    let (_mock, upstream) = upstream_returning("0123456789abcdef").await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+
+    for truncate in [false, true] {
+        let response = proxy
+            .proxy_with_retry(request_with_limit(16, truncate), &upstream)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "16");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from("0123456789abcdef"));
+    }
+}
+
+#[tokio::test]
+async fn test_proxy_limits_cap_responses_without_a_request_limit() {
+    let (_mock, upstream) = upstream_returning("0123456789abcdef").await;
+    let config = ProxyConfig {
+        limits: ProxyLimits::default().with_max_response_body_size(8),
+        ..Default::default()
+    };
+    let proxy = HttpProxy::new(HttpClient::new(), config);
+
+    let err = proxy
+        .proxy_with_retry(TestFixtures::request().uri("/test").build(), &upstream)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UpstreamResponseTooLarge(8)), "{err:?}");
+
+    // A request's own limit takes precedence.
+    let response = proxy
+        .proxy_with_retry(request_with_limit(16, false), &upstream)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_encoded_response_fails_instead_of_truncating() {
+    let (mock, upstream) = upstream_returning("0123456789abcdef").await;
+    let mut config = MockConfig::default();
+    config.body = Bytes::from("0123456789abcdef");
+    config
+        .headers
+        .insert("content-encoding".to_string(), "gzip".to_string());
+    mock.set_config(config).await;
+    let proxy = HttpProxy::new(HttpClient::new(), ProxyConfig::default());
+
+    let err = proxy
+        .proxy_with_retry(request_with_limit(8, true), &upstream)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::UpstreamResponseTooLarge(8)), "{err:?}");
+}
@@ -0,0 +1,102 @@
+//! Router lookup benchmarks
+//!
+//! Run with: cargo bench --package octopus-router
+//!
+//! Besides latency, each lookup kind reports its heap allocations per
+//! request (counted by a wrapping global allocator); static routes should
+//! report zero.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use http::Method;
+use octopus_router::{RouteBuilder, Router};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations, then defers to the system allocator.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Allocations made by one call of `f`.
+fn allocations<T>(f: impl Fn() -> T) -> usize {
+    black_box(f());
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// A table of 50 services, each with static, parameterized and wildcard
+/// routes, under a static `/internal` tree with no dynamic branches.
+fn router() -> Router {
+    let router = Router::new();
+    let add = |path: String| {
+        let route = RouteBuilder::new()
+            .method(Method::GET)
+            .path(path)
+            .upstream_name("bench-upstream")
+            .metadata("team", "platform")
+            .build()
+            .unwrap();
+        router.add_route(route).unwrap();
+    };
+    for i in 0..50 {
+        add(format!("/api/v1/service{i}/items"));
+        add(format!("/api/v1/service{i}/items/:id"));
+        add(format!("/api/v1/service{i}/files/*path"));
+        add(format!("/internal/service{i}/health"));
+    }
+    router
+}
+
+fn bench_lookups(c: &mut Criterion) {
+    let router = router();
+    let lookups = [
+        ("static", "/internal/service25/health"),
+        ("param", "/api/v1/service25/items/42"),
+        ("wildcard", "/api/v1/service25/files/css/site.css"),
+        ("miss", "/internal/service25/missing"),
+    ];
+
+    let mut group = c.benchmark_group("match_route");
+    for (name, path) in lookups {
+        let allocs = allocations(|| router.match_route("", &Method::GET, path));
+        println!("match_route/{name}: {allocs} allocation(s) per lookup");
+
+        group.bench_function(name, |b| {
+            b.iter(|| router.match_route(black_box(""), &Method::GET, black_box(path)))
+        });
+    }
+    group.finish();
+
+    c.bench_function("find_route/static", |b| {
+        b.iter(|| {
+            router.find_route(
+                black_box(""),
+                &Method::GET,
+                black_box("/internal/service25/health"),
+            )
+        })
+    });
+}
+
+criterion_group!(benches, bench_lookups);
+criterion_main!(benches);
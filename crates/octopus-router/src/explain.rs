@@ -0,0 +1,327 @@
+//! Dry-run route matching.
+//!
+//! [`Router::explain`] runs the same match as [`Router::match_route`] and, in
+//! addition, records why every other registered route was not chosen. It is
+//! side-effect free (no load balancer is consulted), which makes it suitable
+//! for the admin explain endpoint and for debugging routing tables.
+
+use crate::matcher::{Match, PathMatcher};
+use crate::route::Route;
+use crate::trailing_slash::{has_trailing_slash, TrailingSlashPolicy};
+use crate::Router;
+use http::Method;
+use std::fmt;
+
+/// Why a route was or was not selected for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteVerdict {
+    /// This route handles the request
+    Matched,
+    /// The path pattern does not match the request path
+    PathMismatch,
+    /// Path and host match, but the route serves another method
+    MethodMismatch,
+    /// Path and method match, but the route is scoped to another host
+    HostMismatch,
+    /// Only the trailing-slash form differs and the router is
+    /// [`TrailingSlashPolicy::Strict`]
+    TrailingSlashMismatch,
+    /// The route matches, but a route with a more specific host or path, or
+    /// a higher priority, won
+    Shadowed,
+}
+
+impl RouteVerdict {
+    /// Stable lowercase name, e.g. `path_mismatch`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Matched => "matched",
+            Self::PathMismatch => "path_mismatch",
+            Self::MethodMismatch => "method_mismatch",
+            Self::HostMismatch => "host_mismatch",
+            Self::TrailingSlashMismatch => "trailing_slash_mismatch",
+            Self::Shadowed => "shadowed",
+        }
+    }
+}
+
+impl fmt::Display for RouteVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One registered route and its verdict for the explained request.
+#[derive(Debug, Clone)]
+pub struct RouteTrace {
+    /// The registered route
+    pub route: Route,
+    /// Why it was or was not selected
+    pub verdict: RouteVerdict,
+}
+
+/// Full decision trace for a request, produced by [`Router::explain`].
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// The selected route, with extracted params, if any
+    pub matched: Option<Match>,
+    /// Every registered route, most relevant first: the match, then routes
+    /// that were shadowed or failed on method/host, then path mismatches
+    pub routes: Vec<RouteTrace>,
+    /// Why nothing matched (`None` when a route was selected)
+    pub reason: Option<String>,
+}
+
+impl Router {
+    /// Explain how a request would be routed without proxying it.
+    ///
+    /// `host` is lowercased like [`match_route`](Self::match_route) expects
+    /// the caller to do. The selected route is exactly what `match_route`
+    /// returns; the per-route verdicts are derived from each route's path
+    /// pattern, method and host.
+    pub fn explain(&self, host: &str, method: &Method, path: &str) -> Explanation {
+        let host = host.to_ascii_lowercase();
+        let matched = self.match_route(&host, method, path).ok();
+        let policy = self.trailing_slash();
+
+        let mut routes: Vec<RouteTrace> = self
+            .get_all_routes()
+            .into_iter()
+            .map(|route| {
+                let verdict = verdict(&route, matched.as_ref(), &host, method, path, policy);
+                RouteTrace { route, verdict }
+            })
+            .collect();
+        routes.sort_by_key(|t| match t.verdict {
+            RouteVerdict::Matched => 0,
+            RouteVerdict::PathMismatch => 2,
+            _ => 1,
+        });
+
+        let reason = matched
+            .is_none()
+            .then(|| no_match_reason(&routes, &host, method, path));
+
+        Explanation {
+            matched,
+            routes,
+            reason,
+        }
+    }
+}
+
+fn verdict(
+    route: &Route,
+    matched: Option<&Match>,
+    host: &str,
+    method: &Method,
+    path: &str,
+    policy: TrailingSlashPolicy,
+) -> RouteVerdict {
+    if matched.is_some_and(|m| {
+        m.route.method == route.method && m.route.path == route.path && m.route.host == route.host
+    }) {
+        return RouteVerdict::Matched;
+    }
+    // The trie ignores empty segments, so compare in the same form.
+    let matcher = PathMatcher::new(segment_form(&route.path));
+    if matcher.matches(&segment_form(path)).is_none() {
+        return RouteVerdict::PathMismatch;
+    }
+    if !route.host.matches(host) {
+        return RouteVerdict::HostMismatch;
+    }
+    if route.method != *method {
+        return RouteVerdict::MethodMismatch;
+    }
+    let wildcard = route.path.split('/').any(|s| s.starts_with('*'));
+    if policy == TrailingSlashPolicy::Strict
+        && !wildcard
+        && has_trailing_slash(&route.path) != has_trailing_slash(path)
+    {
+        return RouteVerdict::TrailingSlashMismatch;
+    }
+    RouteVerdict::Shadowed
+}
+
+fn segment_form(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    format!("/{}", segments.join("/"))
+}
+
+fn no_match_reason(routes: &[RouteTrace], host: &str, method: &Method, path: &str) -> String {
+    if routes.is_empty() {
+        return "no routes are registered".to_string();
+    }
+    let with = |verdict| {
+        let mut found: Vec<String> = routes
+            .iter()
+            .filter(|t| t.verdict == verdict)
+            .map(|t| match verdict {
+                RouteVerdict::MethodMismatch => t.route.method.to_string(),
+                _ => format!("{} {}", t.route.method, t.route.path),
+            })
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    };
+
+    let methods = with(RouteVerdict::MethodMismatch);
+    if !methods.is_empty() {
+        return format!(
+            "path {path} is routed for {} but not {method}",
+            methods.join(", ")
+        );
+    }
+    let slash = with(RouteVerdict::TrailingSlashMismatch);
+    if !slash.is_empty() {
+        return format!(
+            "path {path} only differs in its trailing slash from {} and trailing slashes are strict",
+            slash.join(", ")
+        );
+    }
+    let hosts = with(RouteVerdict::HostMismatch);
+    if !hosts.is_empty() {
+        let host = if host.is_empty() { "<none>" } else { host };
+        return format!(
+            "path {path} is routed only for other hosts ({}), not host {host}",
+            hosts.join(", ")
+        );
+    }
+    format!("no route pattern matches path {path}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HostMatch, RouteBuilder};
+
+    fn route(method: Method, path: &str, upstream: &str) -> Route {
+        RouteBuilder::new()
+            .method(method)
+            .path(path)
+            .upstream_name(upstream)
+            .build()
+            .unwrap()
+    }
+
+    fn router() -> Router {
+        let router = Router::new();
+        router
+            .add_route(route(Method::GET, "/users/:id", "users"))
+            .unwrap();
+        router
+            .add_route(route(Method::GET, "/users/:id/posts/:post_id", "posts"))
+            .unwrap();
+        router
+            .add_route(route(Method::DELETE, "/users/:id", "users-admin"))
+            .unwrap();
+        router
+            .add_route(
+                RouteBuilder::new()
+                    .method(Method::GET)
+                    .host(HostMatch::parse("internal.example.com"))
+                    .path("/metrics")
+                    .upstream_name("metrics")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        router
+    }
+
+    fn verdict_of(explanation: &Explanation, method: Method, path: &str) -> RouteVerdict {
+        explanation
+            .routes
+            .iter()
+            .find(|t| t.route.method == method && t.route.path == path)
+            .map(|t| t.verdict)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_explain_parameterized_match() {
+        let explanation = router().explain("api.example.com", &Method::GET, "/users/42");
+
+        let matched = explanation.matched.as_ref().unwrap();
+        assert_eq!(matched.route.path, "/users/:id");
+        assert_eq!(matched.route.upstream_name, "users");
+        assert_eq!(matched.params.get("id").map(String::as_str), Some("42"));
+        assert!(explanation.reason.is_none());
+
+        assert_eq!(explanation.routes[0].verdict, RouteVerdict::Matched);
+        assert_eq!(
+            verdict_of(&explanation, Method::DELETE, "/users/:id"),
+            RouteVerdict::MethodMismatch
+        );
+        assert_eq!(
+            verdict_of(&explanation, Method::GET, "/users/:id/posts/:post_id"),
+            RouteVerdict::PathMismatch
+        );
+    }
+
+    #[test]
+    fn test_explain_no_match_reports_reason() {
+        let router = router();
+
+        let explanation = router.explain("", &Method::POST, "/users/42");
+        assert!(explanation.matched.is_none());
+        assert_eq!(
+            explanation.reason.as_deref(),
+            Some("path /users/42 is routed for DELETE, GET but not POST")
+        );
+
+        let explanation = router.explain("Public.Example.com", &Method::GET, "/metrics");
+        assert!(explanation.matched.is_none());
+        assert_eq!(
+            verdict_of(&explanation, Method::GET, "/metrics"),
+            RouteVerdict::HostMismatch
+        );
+        assert!(explanation
+            .reason
+            .unwrap()
+            .contains("not host public.example.com"));
+
+        let explanation = router.explain("", &Method::GET, "/orders");
+        assert_eq!(
+            explanation.reason.as_deref(),
+            Some("no route pattern matches path /orders")
+        );
+    }
+
+    #[test]
+    fn test_explain_shadowed_and_strict_trailing_slash() {
+        let router = Router::new().with_trailing_slash(TrailingSlashPolicy::Strict);
+        router
+            .add_route(route(Method::GET, "/items/:id", "items"))
+            .unwrap();
+        router
+            .add_route(
+                RouteBuilder::new()
+                    .method(Method::GET)
+                    .host(HostMatch::parse("*.example.com"))
+                    .path("/items/:id")
+                    .upstream_name("tenant-items")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let explanation = router.explain("a.example.com", &Method::GET, "/items/1");
+        assert_eq!(
+            explanation.matched.unwrap().route.upstream_name,
+            "tenant-items"
+        );
+        assert!(explanation
+            .routes
+            .iter()
+            .any(|t| t.verdict == RouteVerdict::Shadowed));
+
+        let explanation = router.explain("", &Method::GET, "/items/1/");
+        assert!(explanation
+            .reason
+            .unwrap()
+            .contains("trailing slashes are strict"));
+    }
+}
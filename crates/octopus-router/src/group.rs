@@ -0,0 +1,155 @@
+//! Route groups: routes sharing a path prefix and settings
+//!
+//! A [`RouteGroup`] holds a prefix and a template [`RouteBuilder`] with the
+//! settings its members share (auth, timeout, rate limit, CORS, metadata,
+//! ...). [`RouteGroup::route`] starts each member from that template, so a
+//! setter called on the member overrides the group's value and `metadata`
+//! entries are added to the group's. The member's path is relative to the
+//! prefix.
+//!
+//! ```
+//! use http::Method;
+//! use octopus_router::RouteGroup;
+//!
+//! let admin = RouteGroup::new("/admin").defaults(|route| {
+//!     route
+//!         .upstream_name("admin-service")
+//!         .auth_provider(Some("jwt"))
+//!         .require_roles(&["admin".to_string()])
+//! });
+//!
+//! let users = admin.route().method(Method::GET).path("/users").build().unwrap();
+//! assert_eq!(users.path, "/admin/users");
+//! assert_eq!(users.require_roles, ["admin"]);
+//! ```
+
+use crate::route::RouteBuilder;
+
+/// A path prefix and route settings shared by member routes.
+#[derive(Debug, Clone)]
+pub struct RouteGroup {
+    prefix: String,
+    template: RouteBuilder,
+}
+
+impl RouteGroup {
+    /// A group of routes under `prefix` (e.g. `/api/v1`).
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            template: RouteBuilder::new(),
+        }
+    }
+
+    /// Set the settings members inherit, through the usual builder setters.
+    pub fn defaults(mut self, f: impl FnOnce(RouteBuilder) -> RouteBuilder) -> Self {
+        self.template = f(self.template);
+        self
+    }
+
+    /// The group's path prefix.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Start a member route with the group's settings; its path is joined
+    /// under the prefix when built.
+    pub fn route(&self) -> RouteBuilder {
+        let mut builder = self.template.clone();
+        builder.group_prefix = Some(self.prefix.clone());
+        builder
+    }
+}
+
+/// `path` under `prefix`: `/api` + `/users` is `/api/users`, `/api` + `/`
+/// or an empty path is `/api`, and a path without a leading slash gets one.
+pub fn join_prefix(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    match path {
+        "" | "/" if !prefix.is_empty() => prefix.to_string(),
+        _ if path.starts_with('/') => format!("{prefix}{path}"),
+        _ => format!("{prefix}/{path}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Method;
+    use std::time::Duration;
+
+    fn api() -> RouteGroup {
+        RouteGroup::new("/api/v1/").defaults(|route| {
+            route
+                .upstream_name("api")
+                .auth_provider(Some("jwt"))
+                .require_scopes(&["read".to_string()])
+                .timeout(Some(Duration::from_secs(5)))
+                .rate_limit(100, Duration::from_secs(60))
+                .metadata("team", "platform")
+        })
+    }
+
+    #[test]
+    fn members_inherit_prefix_and_settings() {
+        let group = api();
+        let route = group
+            .route()
+            .method(Method::GET)
+            .path("/orders/:id")
+            .build()
+            .unwrap();
+
+        assert_eq!(route.path, "/api/v1/orders/:id");
+        assert_eq!(route.upstream_name, "api");
+        assert_eq!(route.auth_provider.as_deref(), Some("jwt"));
+        assert_eq!(route.require_scopes, ["read"]);
+        assert_eq!(route.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(route.rate_limit, Some((100, Duration::from_secs(60))));
+        assert_eq!(route.metadata["team"], "platform");
+
+        let root = group.route().method(Method::GET).path("/").build().unwrap();
+        assert_eq!(root.path, "/api/v1");
+    }
+
+    #[test]
+    fn member_settings_override_the_group() {
+        let route = api()
+            .route()
+            .method(Method::POST)
+            .path("/login")
+            .upstream_name("auth")
+            .skip_auth(true)
+            .require_scopes(&[])
+            .timeout(Some(Duration::from_secs(30)))
+            .metadata("team", "identity")
+            .metadata("public", "yes")
+            .build()
+            .unwrap();
+
+        assert_eq!(route.path, "/api/v1/login");
+        assert_eq!(route.upstream_name, "auth");
+        assert!(route.skip_auth);
+        assert!(route.require_scopes.is_empty());
+        assert_eq!(route.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(route.metadata["team"], "identity");
+        assert_eq!(route.metadata["public"], "yes");
+        // Untouched group settings still apply.
+        assert_eq!(route.auth_provider.as_deref(), Some("jwt"));
+    }
+
+    #[test]
+    fn join_prefix_normalizes_slashes() {
+        assert_eq!(join_prefix("/api/v1/", "/orders"), "/api/v1/orders");
+        assert_eq!(join_prefix("/api", "orders"), "/api/orders");
+        assert_eq!(join_prefix("/api/", "/"), "/api");
+        assert_eq!(join_prefix("/api", ""), "/api");
+        assert_eq!(join_prefix("/", "/"), "/");
+    }
+
+    #[test]
+    fn member_paths_must_be_absolute() {
+        let err = api().route().method(Method::GET).path("orders").build();
+        assert!(err.is_err());
+    }
+}
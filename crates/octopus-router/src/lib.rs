@@ -4,15 +4,20 @@
 //! - Path parameter extraction (`/users/:id`)
 //! - Wildcard matching (`/static/*filepath`)
 //! - Method-based routing
-//! - Priority-based matching
+//! - Most-specific matching (static > parameter > wildcard), priority as tiebreaker
 //! - Dynamic route registration
+//! - Route groups sharing a path prefix and settings ([`RouteGroup`])
+//! - Configurable trailing-slash handling (strict, merge, redirect)
+//! - Path normalization (dot segments, `//`, percent-encoding) before matching
+//! - Dry-run explanation of routing decisions ([`Router::explain`])
 //!
 //! ## Performance
 //!
 //! - O(k) lookup time where k is the path length
 //! - Lock-free reads using DashMap
 //! - Pre-compiled regex for wildcards
-//! - Zero allocations for static routes
+//! - Zero allocations for static route lookups: matched routes are shared
+//!   (`Arc<Route>`), not copied (see `benches/router_benchmark.rs`)
 
 #![forbid(unsafe_code)]
 #![warn(
@@ -23,22 +28,32 @@
 )]
 
 pub mod convention;
+pub mod explain;
+pub mod group;
 pub mod host;
 pub mod load_balancer;
 pub mod matcher;
+pub mod normalize;
 mod proxy_spec;
 pub mod route;
+pub mod trailing_slash;
 pub mod trie;
 pub mod virtual_gateway;
 
 pub use convention::{
     BackendStrategy, Convention, ConventionRouteRule, ConventionTarget, LabelRole, PathRewrite,
 };
+pub use explain::{Explanation, RouteTrace, RouteVerdict};
+pub use group::{join_prefix, RouteGroup};
 pub use host::HostMatch;
-pub use load_balancer::{new_load_balancer, LoadBalancer};
+pub use load_balancer::{
+    load_balancer_for, new_load_balancer, seeded_load_balancer_for, LoadBalancer, Warmups,
+};
 pub use matcher::{Match, PathMatcher};
+pub use normalize::{normalize_path, EncodedSlash};
 pub use proxy_spec::{PathMode, ProxySpec, Scheme, UpstreamOrigin};
-pub use route::{Route, RouteBuilder, RouteCorsOverride};
+pub use route::{Route, RouteBuilder, RouteCorsOverride, RouteFallback};
+pub use trailing_slash::TrailingSlashPolicy;
 pub use trie::RouteTrie;
 pub use virtual_gateway::{
     gateway_scoped_upstream, GatewayEntry, GatewayPolicy, VirtualGatewayIndex,
@@ -47,7 +62,9 @@ pub use virtual_gateway::{
 use dashmap::DashMap;
 use http::Method;
 use octopus_core::{Error, LoadBalanceStrategy, Result, UpstreamCluster, UpstreamInstance};
+use parking_lot::RwLock;
 use std::sync::Arc;
+use std::time::Instant;
 
 /// Router for managing and matching routes
 #[derive(Debug, Clone)]
@@ -61,8 +78,17 @@ pub struct Router {
     /// Per-upstream load balancers (keyed by upstream name)
     load_balancers: Arc<DashMap<String, Arc<dyn LoadBalancer>>>,
 
+    /// Per-upstream slow-start ramps, kept across load balancer rebuilds
+    warmups: Arc<DashMap<String, Arc<Warmups>>>,
+
     /// Default load balancer (round-robin)
     default_lb: Arc<dyn LoadBalancer>,
+
+    /// How a trailing `/` on the request path is matched
+    trailing_slash: Arc<RwLock<TrailingSlashPolicy>>,
+
+    /// Seed for random load balancing (`None` = thread-local RNG)
+    rng_seed: Option<u64>,
 }
 
 impl Router {
@@ -72,10 +98,32 @@ impl Router {
             tries: Arc::new(DashMap::new()),
             upstreams: Arc::new(DashMap::new()),
             load_balancers: Arc::new(DashMap::new()),
+            warmups: Arc::new(DashMap::new()),
             default_lb: Arc::from(new_load_balancer(LoadBalanceStrategy::RoundRobin)),
+            trailing_slash: Arc::new(RwLock::new(TrailingSlashPolicy::default())),
+            rng_seed: None,
         }
     }
 
+    /// Set the trailing-slash policy used by [`match_route`](Self::match_route)
+    pub fn with_trailing_slash(self, policy: TrailingSlashPolicy) -> Self {
+        *self.trailing_slash.write() = policy;
+        self
+    }
+
+    /// Seed the RNG of random load balancing, for tests that assert exact
+    /// selections. Applies to upstreams registered afterwards; production
+    /// routers leave it unset.
+    pub fn with_rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = Some(seed);
+        self
+    }
+
+    /// Get the trailing-slash policy
+    pub fn trailing_slash(&self) -> TrailingSlashPolicy {
+        *self.trailing_slash.read()
+    }
+
     /// Add a route
     pub fn add_route(&self, route: Route) -> Result<()> {
         let method = route.method.clone();
@@ -104,13 +152,15 @@ impl Router {
     /// Match a request `host` + path. `host` must be lowercased by the caller;
     /// pass `""` (or any value) when host-scoping is irrelevant — host-agnostic
     /// routes match every host.
+    ///
+    /// A trailing `/` is handled per the router's [`TrailingSlashPolicy`].
     pub fn match_route(&self, host: &str, method: &Method, path: &str) -> Result<Match> {
         let trie = self
             .tries
             .get(method)
             .ok_or_else(|| Error::RouteNotFound(format!("No routes for method {method}")))?;
 
-        trie.match_path(host, path)
+        trie.match_path_with_policy(host, path, self.trailing_slash())
             .ok_or_else(|| Error::RouteNotFound(path.to_string()))
     }
 
@@ -118,10 +168,10 @@ impl Router {
     pub fn register_upstream(&self, cluster: UpstreamCluster) {
         let name = cluster.name.clone();
         let strategy = cluster.strategy;
-        self.upstreams.insert(name.clone(), cluster);
 
         // Create and cache the load balancer for this upstream's strategy
-        let lb = new_load_balancer(strategy);
+        let lb = self.load_balancer(&cluster);
+        self.upstreams.insert(name.clone(), cluster);
         self.load_balancers.insert(name.clone(), Arc::from(lb));
 
         tracing::debug!(upstream = %name, strategy = ?strategy, "Upstream registered");
@@ -137,22 +187,108 @@ impl Router {
         use dashmap::mapref::entry::Entry;
         if let Entry::Vacant(slot) = self.upstreams.entry(name.to_string()) {
             let cluster = build();
-            let lb = new_load_balancer(cluster.strategy);
+            let lb = self.load_balancer(&cluster);
             self.load_balancers.insert(name.to_string(), Arc::from(lb));
             slot.insert(cluster);
         }
     }
 
+    /// Point upstream `name` at `instances`, registering it if needed.
+    ///
+    /// Used for upstreams resolved at request time (discovery-backed
+    /// services): the cluster and its load balancer are kept while the
+    /// instance set is unchanged, and rebuilt when it differs. Returns
+    /// whether anything changed.
+    pub fn sync_upstream(&self, name: &str, instances: Vec<UpstreamInstance>) -> bool {
+        let same = |a: &UpstreamInstance, b: &UpstreamInstance| {
+            a.id == b.id && a.address == b.address && a.port == b.port && a.weight == b.weight
+        };
+        if let Some(cluster) = self.upstreams.get(name) {
+            if cluster.instances.len() == instances.len()
+                && cluster
+                    .instances
+                    .iter()
+                    .zip(&instances)
+                    .all(|(a, b)| same(a, b))
+            {
+                return false;
+            }
+        }
+
+        let mut cluster = self
+            .get_upstream(name)
+            .unwrap_or_else(|| UpstreamCluster::new(name));
+        cluster.instances = instances;
+        tracing::debug!(
+            upstream = %name,
+            instances = cluster.instances.len(),
+            "Upstream instances updated"
+        );
+        self.register_upstream(cluster);
+        true
+    }
+
+    /// Build the load balancer of `cluster`, handing it the cluster's
+    /// ramps with instances no longer in it forgotten
+    fn load_balancer(&self, cluster: &UpstreamCluster) -> Box<dyn LoadBalancer> {
+        let warmups = Arc::clone(
+            self.warmups
+                .entry(cluster.name.clone())
+                .or_default()
+                .value(),
+        );
+        warmups.retain(|id| cluster.instances.iter().any(|i| i.id == id));
+        match self.rng_seed {
+            Some(seed) => seeded_load_balancer_for(cluster, warmups, seed),
+            None => load_balancer_for(cluster, warmups),
+        }
+    }
+
+    /// Record a health transition of an instance, e.g. from an active health
+    /// check. An instance turning healthy again restarts its slow-start ramp.
+    /// Returns whether the instance's health changed.
+    pub fn set_instance_health(&self, upstream: &str, instance_id: &str, healthy: bool) -> bool {
+        let Some(mut cluster) = self.upstreams.get_mut(upstream) else {
+            return false;
+        };
+        let Some(instance) = cluster.instances.iter_mut().find(|i| i.id == instance_id) else {
+            return false;
+        };
+        if instance.is_healthy() == healthy {
+            return false;
+        }
+        if healthy {
+            instance.mark_healthy();
+            if let Some(warmups) = self.warmups.get(upstream) {
+                warmups.restart(instance_id, Instant::now());
+            }
+        } else {
+            instance.mark_unhealthy();
+        }
+        tracing::debug!(upstream = %upstream, instance = %instance_id, healthy, "Instance health changed");
+        true
+    }
+
     /// Get an upstream cluster
     pub fn get_upstream(&self, name: &str) -> Option<UpstreamCluster> {
         self.upstreams.get(name).map(|r| r.clone())
     }
 
+    /// Name of the upstream `instance_id` belongs to, without cloning any
+    /// cluster
+    pub fn upstream_of(&self, instance_id: &str) -> Option<String> {
+        self.upstreams
+            .iter()
+            .find(|entry| entry.instances.iter().any(|i| i.id == instance_id))
+            .map(|entry| entry.key().clone())
+    }
+
     /// Remove an upstream cluster
     pub fn remove_upstream(&self, name: &str) -> bool {
         let removed = self.upstreams.remove(name).is_some();
         if removed {
             self.load_balancers.remove(name);
+            self.warmups.remove(name);
             tracing::debug!(upstream = %name, "Upstream removed");
         }
         removed
@@ -173,14 +309,57 @@ impl Router {
         self.upstreams.len()
     }
 
+    /// Whether any registered upstream has at least one healthy instance
+    pub fn has_healthy_upstream(&self) -> bool {
+        self.upstreams
+            .iter()
+            .any(|entry| entry.value().healthy_count() > 0)
+    }
+
+    /// Whether `upstream` has a healthy instance matching `f`, without
+    /// cloning the cluster
+    pub fn has_healthy_instance(
+        &self,
+        upstream: &str,
+        f: impl Fn(&UpstreamInstance) -> bool,
+    ) -> bool {
+        self.upstreams.get(upstream).is_some_and(|cluster| {
+            cluster
+                .instances
+                .iter()
+                .any(|instance| instance.is_healthy() && f(instance))
+        })
+    }
+
     /// Clear all routes
     pub fn clear(&self) {
         self.tries.clear();
         tracing::debug!("All routes cleared");
     }
 
+    /// Replace all routes and the trailing-slash policy with those of `staged`
+    /// (e.g. a fully built reload candidate) and register its upstreams. Tries are swapped per method, so
+    /// lookups see either the old or the new table, never an empty one.
+    /// Upstreams absent from `staged` are kept, as discovery may own them.
+    pub fn replace_with(&self, staged: Router) {
+        let methods: Vec<Method> = staged.tries.iter().map(|e| e.key().clone()).collect();
+        for method in &methods {
+            if let Some((method, trie)) = staged.tries.remove(method) {
+                self.tries.insert(method, trie);
+            }
+        }
+        self.tries.retain(|method, _| methods.contains(method));
+        *self.trailing_slash.write() = staged.trailing_slash();
+
+        for cluster in staged.get_all_upstreams() {
+            self.register_upstream(cluster);
+        }
+
+        tracing::debug!("Routes replaced from staged router");
+    }
+
     /// Find a route for a given host, method and path (convenience for the handler)
-    pub fn find_route(&self, host: &str, method: &Method, path: &str) -> Result<Route> {
+    pub fn find_route(&self, host: &str, method: &Method, path: &str) -> Result<Arc<Route>> {
         let matched = self.match_route(host, method, path)?;
         Ok(matched.route)
     }
@@ -199,8 +378,44 @@ impl Router {
         })?;
 
         let healthy = cluster.healthy_instances();
+        self.select_from(upstream_name, &healthy, key)
+    }
+
+    /// Select an upstream instance from a cluster (convenience method, uses empty key).
+    pub fn select_instance(&self, upstream_name: &str) -> Result<UpstreamInstance> {
+        self.select_instance_with_key(upstream_name, "")
+    }
+
+    /// Select an upstream instance, passing over healthy instances for which
+    /// `avoid` returns `true` (e.g. ones that asked for a `Retry-After`
+    /// pause). When every healthy instance is avoided, all of them are
+    /// considered again.
+    pub fn select_instance_avoiding(
+        &self,
+        upstream_name: &str,
+        key: &str,
+        avoid: impl Fn(&UpstreamInstance) -> bool,
+    ) -> Result<UpstreamInstance> {
+        let cluster = self.get_upstream(upstream_name).ok_or_else(|| {
+            Error::UpstreamConnection(format!("Upstream '{upstream_name}' not found"))
+        })?;
+
+        let healthy = cluster.healthy_instances();
+        let preferred: Vec<&UpstreamInstance> =
+            healthy.iter().copied().filter(|i| !avoid(i)).collect();
+        if preferred.is_empty() {
+            return self.select_from(upstream_name, &healthy, key);
+        }
+        self.select_from(upstream_name, &preferred, key)
+    }
 
-        if healthy.is_empty() {
+    fn select_from(
+        &self,
+        upstream_name: &str,
+        instances: &[&UpstreamInstance],
+        key: &str,
+    ) -> Result<UpstreamInstance> {
+        if instances.is_empty() {
             return Err(Error::UpstreamConnection(format!(
                 "No healthy instances for upstream '{upstream_name}'"
             )));
@@ -212,13 +427,8 @@ impl Router {
             .map(|r| Arc::clone(r.value()))
             .unwrap_or_else(|| Arc::clone(&self.default_lb));
 
-        let index = lb.select(&healthy, key).unwrap_or(0);
-        Ok(healthy[index].clone())
-    }
-
-    /// Select an upstream instance from a cluster (convenience method, uses empty key).
-    pub fn select_instance(&self, upstream_name: &str) -> Result<UpstreamInstance> {
-        self.select_instance_with_key(upstream_name, "")
+        let index = lb.select(instances, key).unwrap_or(0);
+        Ok(instances[index].clone())
     }
 
     /// Get all routes across all methods
@@ -278,6 +488,36 @@ mod tests {
         assert_eq!(router.total_route_count(), 1);
     }
 
+    #[test]
+    fn test_replace_with_swaps_routes_and_keeps_other_upstreams() {
+        let route = |method: Method, path: &str, upstream: &str| {
+            RouteBuilder::new()
+                .path(path)
+                .method(method)
+                .upstream_name(upstream)
+                .build()
+                .unwrap()
+        };
+
+        let router = Router::new();
+        router.add_route(route(Method::GET, "/old", "a")).unwrap();
+        router.add_route(route(Method::POST, "/old", "a")).unwrap();
+        router.register_upstream(UpstreamCluster::new("discovered"));
+
+        let staged = Router::new().with_trailing_slash(TrailingSlashPolicy::Strict);
+        staged.add_route(route(Method::GET, "/new", "b")).unwrap();
+        staged.register_upstream(UpstreamCluster::new("b"));
+        router.replace_with(staged);
+
+        assert!(router.match_route("", &Method::GET, "/old").is_err());
+        assert!(router.match_route("", &Method::GET, "/new").is_ok());
+        assert!(router.match_route("", &Method::GET, "/new/").is_err());
+        assert_eq!(router.trailing_slash(), TrailingSlashPolicy::Strict);
+        assert_eq!(router.route_count(&Method::POST), 0);
+        assert!(router.get_upstream("b").is_some());
+        assert!(router.get_upstream("discovered").is_some());
+    }
+
     #[test]
     fn test_match_route() {
         let router = Router::new();
@@ -333,4 +573,140 @@ mod tests {
         assert!(router.remove_upstream("test-service"));
         assert_eq!(router.upstream_count(), 0);
     }
+
+    #[test]
+    fn test_has_healthy_upstream() {
+        let router = Router::new();
+        assert!(!router.has_healthy_upstream());
+
+        let mut down = UpstreamInstance::new("down-1", "10.0.0.1", 8080);
+        down.mark_unhealthy();
+        let mut cluster = UpstreamCluster::new("orders");
+        cluster.add_instance(down);
+        router.register_upstream(cluster);
+        assert!(!router.has_healthy_upstream());
+
+        let mut cluster = UpstreamCluster::new("users");
+        cluster.add_instance(UpstreamInstance::new("up-1", "10.0.0.2", 8080));
+        router.register_upstream(cluster);
+        assert!(router.has_healthy_upstream());
+
+        assert!(router.has_healthy_instance("users", |_| true));
+        assert!(!router.has_healthy_instance("users", |i| i.id != "up-1"));
+        assert!(!router.has_healthy_instance("orders", |_| true));
+        assert!(!router.has_healthy_instance("missing", |_| true));
+
+        assert_eq!(router.upstream_of("up-1").as_deref(), Some("users"));
+        assert_eq!(router.upstream_of("down-1").as_deref(), Some("orders"));
+        assert_eq!(router.upstream_of("missing-1"), None);
+    }
+
+    #[test]
+    fn test_seeded_router_replays_random_selections() {
+        let picks = |seed: u64| -> Vec<String> {
+            let router = Router::new().with_rng_seed(seed);
+            let mut cluster = UpstreamCluster::new("orders");
+            cluster.strategy = LoadBalanceStrategy::Random;
+            for (id, weight) in [("a", 1), ("b", 2), ("c", 7)] {
+                let mut instance = UpstreamInstance::new(id, "10.0.0.1", 8080);
+                instance.weight = weight;
+                cluster.add_instance(instance);
+            }
+            router.register_upstream(cluster);
+            (0..50)
+                .map(|_| router.select_instance("orders").unwrap().id)
+                .collect()
+        };
+
+        assert_eq!(picks(9), picks(9));
+        assert_ne!(picks(9), picks(10));
+    }
+
+    #[test]
+    fn test_sync_upstream_replaces_changed_instances_only() {
+        let router = Router::new();
+        let instances = |ports: &[u16]| -> Vec<UpstreamInstance> {
+            ports
+                .iter()
+                .map(|port| UpstreamInstance::new(format!("orders-{port}"), "10.0.0.1", *port))
+                .collect()
+        };
+
+        assert!(router.sync_upstream("orders", instances(&[8080])));
+        assert!(!router.sync_upstream("orders", instances(&[8080])));
+        assert_eq!(router.select_instance("orders").unwrap().port, 8080);
+
+        assert!(router.sync_upstream("orders", instances(&[8081])));
+        assert_eq!(router.select_instance("orders").unwrap().port, 8081);
+        assert_eq!(router.upstream_count(), 1);
+    }
+
+    #[test]
+    fn test_slow_start_ramps_survive_reregistration() {
+        let router = Router::new();
+        let cluster = |ids: &[&str]| {
+            let mut cluster = UpstreamCluster::new("orders");
+            cluster.slow_start = Some(octopus_core::SlowStartConfig {
+                window: std::time::Duration::from_secs(60),
+                min_weight_percent: 10,
+            });
+            for id in ids {
+                cluster.add_instance(UpstreamInstance::new(*id, "10.0.0.1", 8080));
+            }
+            cluster
+        };
+        let since = |id: &str| {
+            router
+                .warmups
+                .get("orders")
+                .unwrap()
+                .available_since(id, Instant::now())
+        };
+
+        router.register_upstream(cluster(&["a", "b"]));
+        router.select_instance("orders").unwrap();
+        router.select_instance("orders").unwrap();
+        let a = since("a");
+
+        // A re-registration, such as a discovery sync, keeps the ramps of
+        // the instances still there and forgets the ones that left.
+        router.register_upstream(cluster(&["a"]));
+        assert_eq!(since("a"), a);
+        assert_eq!(router.warmups.get("orders").unwrap().len(), 1);
+
+        // Recovery restarts the ramp; a repeated report changes nothing.
+        assert!(router.set_instance_health("orders", "a", false));
+        assert!(!router.set_instance_health("orders", "a", false));
+        assert!(router.select_instance("orders").is_err());
+        assert!(router.set_instance_health("orders", "a", true));
+        assert!(since("a") > a);
+        assert!(!router.set_instance_health("orders", "missing", true));
+
+        assert!(router.remove_upstream("orders"));
+        assert!(router.warmups.get("orders").is_none());
+    }
+
+    #[test]
+    fn test_select_instance_avoiding() {
+        let router = Router::new();
+        let mut cluster = UpstreamCluster::new("orders");
+        cluster.add_instance(UpstreamInstance::new("a", "10.0.0.1", 8080));
+        cluster.add_instance(UpstreamInstance::new("b", "10.0.0.2", 8080));
+        router.register_upstream(cluster);
+
+        for _ in 0..4 {
+            let selected = router
+                .select_instance_avoiding("orders", "", |i| i.id == "a")
+                .unwrap();
+            assert_eq!(selected.id, "b");
+        }
+
+        // Every instance avoided: fall back to all of them.
+        assert!(router
+            .select_instance_avoiding("orders", "", |_| true)
+            .is_ok());
+        assert!(router
+            .select_instance_avoiding("missing", "", |_| false)
+            .is_err());
+    }
 }
@@ -2,13 +2,22 @@
 //!
 //! Provides multiple load balancing algorithms:
 //! - **Round Robin**: Cycles through healthy instances sequentially
-//! - **Weighted Round Robin**: Distributes based on instance weights
-//! - **Random**: Random selection among healthy instances
+//! - **Weighted Round Robin**: Distributes based on instance weights, optionally
+//!   ramping up new and recovered instances (slow start)
+//! - **Random**: Weighted random selection among healthy instances, from a
+//!   thread-local RNG or, for reproducible tests, a seeded one
 //! - **Least Connections**: Selects instance with fewest active connections
 //! - **Consistent Hash (IP Hash)**: Deterministic selection based on a key (e.g., client IP)
 
-use octopus_core::{LoadBalanceStrategy, UpstreamInstance};
+use dashmap::DashMap;
+use octopus_core::{LoadBalanceStrategy, SlowStartConfig, UpstreamCluster, UpstreamInstance};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Weight granularity while instances warm up: a warming instance's weight
+/// is scaled to whole percent.
+const SLOW_START_SCALE: f64 = 100.0;
 
 /// Trait for load balancing algorithms.
 ///
@@ -28,12 +37,109 @@ pub fn new_load_balancer(strategy: LoadBalanceStrategy) -> Box<dyn LoadBalancer>
     match strategy {
         LoadBalanceStrategy::RoundRobin => Box::new(RoundRobinLB::new()),
         LoadBalanceStrategy::WeightedRoundRobin => Box::new(WeightedRoundRobinLB::new()),
-        LoadBalanceStrategy::Random => Box::new(RandomLB),
+        LoadBalanceStrategy::Random => Box::new(RandomLB::new()),
         LoadBalanceStrategy::LeastConnections => Box::new(LeastConnectionsLB),
         LoadBalanceStrategy::IpHash => Box::new(ConsistentHashLB),
     }
 }
 
+/// Create the load balancer for `cluster`: its strategy, with slow start
+/// when configured, ramping instances per `warmups`.
+///
+/// Slow start needs weights to ramp, so a round-robin cluster with slow start
+/// is balanced by weighted round robin, which is the same thing once every
+/// instance is warm. Other strategies ignore it.
+pub fn load_balancer_for(
+    cluster: &UpstreamCluster,
+    warmups: Arc<Warmups>,
+) -> Box<dyn LoadBalancer> {
+    match (cluster.strategy, cluster.slow_start) {
+        (
+            LoadBalanceStrategy::RoundRobin | LoadBalanceStrategy::WeightedRoundRobin,
+            Some(slow_start),
+        ) => Box::new(WeightedRoundRobinLB::with_slow_start(slow_start, warmups)),
+        (strategy, slow_start) => {
+            if slow_start.is_some() {
+                tracing::warn!(
+                    upstream = %cluster.name,
+                    strategy = ?strategy,
+                    "slow_start only applies to round-robin balancing; ignored"
+                );
+            }
+            new_load_balancer(strategy)
+        }
+    }
+}
+
+/// [`load_balancer_for`] with random choices drawn from an RNG seeded with
+/// `seed`, so a run of selections can be replayed exactly.
+pub fn seeded_load_balancer_for(
+    cluster: &UpstreamCluster,
+    warmups: Arc<Warmups>,
+    seed: u64,
+) -> Box<dyn LoadBalancer> {
+    match cluster.strategy {
+        LoadBalanceStrategy::Random => Box::new(RandomLB::seeded(seed)),
+        _ => load_balancer_for(cluster, warmups),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Slow start
+// ---------------------------------------------------------------------------
+
+/// When each instance of a cluster became available, keyed by instance id.
+///
+/// Balancers are rebuilt whenever their cluster is re-registered, so the
+/// [`Router`](crate::Router) keeps this per upstream and hands it to each
+/// new balancer; a ramp then runs its course across re-registrations. The
+/// router restarts an instance's ramp when it recovers and drops instances
+/// that leave the cluster.
+#[derive(Debug, Default)]
+pub struct Warmups {
+    since: DashMap<String, Instant>,
+}
+
+impl Warmups {
+    /// Create an empty set of ramps
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When `instance_id` became available; an instance not seen before
+    /// starts its ramp at `now`
+    pub fn available_since(&self, instance_id: &str, now: Instant) -> Instant {
+        if let Some(since) = self.since.get(instance_id) {
+            return *since;
+        }
+        *self
+            .since
+            .entry(instance_id.to_string())
+            .or_insert(now)
+            .value()
+    }
+
+    /// Restart the ramp of `instance_id` at `now`, e.g. once it recovers
+    pub fn restart(&self, instance_id: &str, now: Instant) {
+        self.since.insert(instance_id.to_string(), now);
+    }
+
+    /// Forget every instance for which `keep` returns `false`
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) {
+        self.since.retain(|id, _| keep(id));
+    }
+
+    /// Number of instances tracked
+    pub fn len(&self) -> usize {
+        self.since.len()
+    }
+
+    /// Whether no instance is tracked
+    pub fn is_empty(&self) -> bool {
+        self.since.is_empty()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Round Robin
 // ---------------------------------------------------------------------------
@@ -77,9 +183,14 @@ impl LoadBalancer for RoundRobinLB {
 ///
 /// Distributes traffic proportionally to instance weights.
 /// An instance with weight 3 receives 3x the traffic of weight 1.
+///
+/// With slow start, an instance's weight ramps up over the slow-start window
+/// from when it became available, as recorded in its [`Warmups`].
 #[derive(Debug)]
 pub struct WeightedRoundRobinLB {
     counter: AtomicU64,
+    slow_start: Option<SlowStartConfig>,
+    warmups: Arc<Warmups>,
 }
 
 impl Default for WeightedRoundRobinLB {
@@ -93,17 +204,66 @@ impl WeightedRoundRobinLB {
     pub fn new() -> Self {
         Self {
             counter: AtomicU64::new(0),
+            slow_start: None,
+            warmups: Arc::default(),
         }
     }
-}
 
-impl LoadBalancer for WeightedRoundRobinLB {
-    fn select(&self, instances: &[&UpstreamInstance], _key: &str) -> Option<usize> {
+    /// Create a weighted round-robin load balancer ramping up new and
+    /// recovered instances, as recorded in `warmups`.
+    pub fn with_slow_start(slow_start: SlowStartConfig, warmups: Arc<Warmups>) -> Self {
+        Self {
+            slow_start: Some(slow_start),
+            warmups,
+            ..Self::new()
+        }
+    }
+
+    /// [`select`](LoadBalancer::select) as of `now`
+    pub fn select_at(&self, instances: &[&UpstreamInstance], now: Instant) -> Option<usize> {
         if instances.is_empty() {
             return None;
         }
 
-        let total_weight: u64 = instances.iter().map(|i| i.weight as u64).sum();
+        match self.slow_start {
+            Some(slow_start) => {
+                let factors: Vec<f64> = instances
+                    .iter()
+                    .map(|inst| self.warmup_factor(&slow_start, inst, now))
+                    .collect();
+                if factors.iter().all(|&f| f >= 1.0) {
+                    self.pick(instances, |_, inst| inst.weight as u64)
+                } else {
+                    self.pick(instances, |i, inst| {
+                        (inst.weight as f64 * factors[i] * SLOW_START_SCALE).ceil() as u64
+                    })
+                }
+            }
+            None => self.pick(instances, |_, inst| inst.weight as u64),
+        }
+    }
+
+    /// Fraction of its weight `instance` gets at `now`
+    fn warmup_factor(
+        &self,
+        slow_start: &SlowStartConfig,
+        instance: &UpstreamInstance,
+        now: Instant,
+    ) -> f64 {
+        let available = self.warmups.available_since(&instance.id, now);
+        slow_start.weight_factor(now.saturating_duration_since(available))
+    }
+
+    fn pick(
+        &self,
+        instances: &[&UpstreamInstance],
+        weight: impl Fn(usize, &UpstreamInstance) -> u64,
+    ) -> Option<usize> {
+        let total_weight: u64 = instances
+            .iter()
+            .enumerate()
+            .map(|(i, inst)| weight(i, inst))
+            .sum();
         if total_weight == 0 {
             // Fallback to simple round-robin if all weights are zero
             let idx = self.counter.fetch_add(1, Ordering::Relaxed) as usize % instances.len();
@@ -114,7 +274,7 @@ impl LoadBalancer for WeightedRoundRobinLB {
         let mut cumulative: u64 = 0;
 
         for (i, inst) in instances.iter().enumerate() {
-            cumulative += inst.weight as u64;
+            cumulative += weight(i, inst);
             if pos < cumulative {
                 return Some(i);
             }
@@ -125,27 +285,85 @@ impl LoadBalancer for WeightedRoundRobinLB {
     }
 }
 
+impl LoadBalancer for WeightedRoundRobinLB {
+    fn select(&self, instances: &[&UpstreamInstance], _key: &str) -> Option<usize> {
+        self.select_at(instances, Instant::now())
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Random
 // ---------------------------------------------------------------------------
 
-/// Random load balancer.
-#[derive(Debug)]
-pub struct RandomLB;
+/// Weighted random load balancer.
+///
+/// Each instance is picked with probability proportional to its weight, or
+/// uniformly when every weight is zero.
+#[derive(Debug, Default)]
+pub struct RandomLB {
+    /// Seeded state, or `None` for the thread-local RNG
+    seeded: Option<AtomicU64>,
+}
+
+impl RandomLB {
+    /// Balancer drawing from a fast thread-local RNG
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Balancer drawing from an RNG seeded with `seed`: the same seed gives
+    /// the same sequence of selections over the same instances.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seeded: Some(AtomicU64::new(seed)),
+        }
+    }
+
+    fn next_u64(&self) -> u64 {
+        match &self.seeded {
+            Some(state) => splitmix64(state),
+            None => thread_rng_u64(),
+        }
+    }
+}
 
 impl LoadBalancer for RandomLB {
     fn select(&self, instances: &[&UpstreamInstance], _key: &str) -> Option<usize> {
         if instances.is_empty() {
             return None;
         }
-        // Simple fast random using thread-local RNG
-        Some(fastrand_index(instances.len()))
+        let total_weight: u64 = instances.iter().map(|inst| inst.weight as u64).sum();
+        if total_weight == 0 {
+            return Some((self.next_u64() % instances.len() as u64) as usize);
+        }
+
+        let pos = self.next_u64() % total_weight;
+        let mut cumulative: u64 = 0;
+        for (i, inst) in instances.iter().enumerate() {
+            cumulative += inst.weight as u64;
+            if pos < cumulative {
+                return Some(i);
+            }
+        }
+        Some(instances.len() - 1)
     }
 }
 
-/// Fast pseudo-random index using a simple xorshift on thread ID + counter.
-/// Avoids pulling in `rand` crate for a simple use case.
-fn fastrand_index(len: usize) -> usize {
+/// Next output of a SplitMix64 generator whose state is `state`. Advancing
+/// is a single atomic add, so a shared seeded balancer needs no lock.
+fn splitmix64(state: &AtomicU64) -> u64 {
+    const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut z = state
+        .fetch_add(GAMMA, Ordering::Relaxed)
+        .wrapping_add(GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Fast pseudo-random number from a thread-local xorshift seeded by the
+/// thread ID. Avoids pulling in `rand` crate for a simple use case.
+fn thread_rng_u64() -> u64 {
     use std::cell::Cell;
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
@@ -166,7 +384,7 @@ fn fastrand_index(len: usize) -> usize {
         s ^= s >> 7;
         s ^= s << 17;
         state.set(s);
-        (s as usize) % len
+        s
     })
 }
 
@@ -345,11 +563,119 @@ mod tests {
         }
     }
 
+    /// Share of `rounds` selections at `now` that go to instance `idx`
+    fn share_at(
+        lb: &WeightedRoundRobinLB,
+        r: &[&UpstreamInstance],
+        idx: usize,
+        now: Instant,
+        rounds: u32,
+    ) -> f64 {
+        let hits = (0..rounds)
+            .filter(|_| lb.select_at(r, now) == Some(idx))
+            .count();
+        hits as f64 / rounds as f64
+    }
+
+    fn slow_start(window_secs: u64) -> SlowStartConfig {
+        SlowStartConfig {
+            window: std::time::Duration::from_secs(window_secs),
+            min_weight_percent: 10,
+        }
+    }
+
+    #[test]
+    fn test_slow_start_ramps_new_instance_share() {
+        let lb = WeightedRoundRobinLB::with_slow_start(slow_start(100), Arc::default());
+        let instances = make_weighted_instances(&[1, 1]);
+        let start = Instant::now();
+        let secs = |s| start + std::time::Duration::from_secs(s);
+
+        // inst-0 has been serving for the whole window when inst-1 is added.
+        lb.select_at(&refs(&instances[..1]), start);
+        let added = secs(100);
+        let r = refs(&instances);
+
+        let mut shares = Vec::new();
+        for elapsed in [0, 25, 50, 75] {
+            shares.push(share_at(
+                &lb,
+                &r,
+                1,
+                added + std::time::Duration::from_secs(elapsed),
+                1000,
+            ));
+        }
+        assert!(shares[0] < 0.15, "starts near the minimum: {shares:?}");
+        assert!(
+            shares.windows(2).all(|w| w[0] < w[1]),
+            "share grows over the window: {shares:?}"
+        );
+        assert!(shares[3] < 0.5);
+
+        // Past the window both get their full weight.
+        assert_eq!(share_at(&lb, &r, 1, secs(200), 1000), 0.5);
+    }
+
+    #[test]
+    fn test_slow_start_ramps_recovered_instance() {
+        let warmups = Arc::new(Warmups::new());
+        let lb = WeightedRoundRobinLB::with_slow_start(slow_start(60), Arc::clone(&warmups));
+        let instances = make_weighted_instances(&[3, 1]);
+        // Both have been serving for longer than the window.
+        let start = Instant::now()
+            .checked_sub(std::time::Duration::from_secs(120))
+            .unwrap();
+        lb.select_at(&refs(&instances), start);
+        assert_eq!(
+            share_at(&lb, &refs(&instances), 0, Instant::now(), 400),
+            0.75
+        );
+
+        let recovered = Instant::now();
+        warmups.restart(&instances[0].id, recovered);
+        let early = share_at(&lb, &refs(&instances), 0, recovered, 1000);
+        let later = share_at(
+            &lb,
+            &refs(&instances),
+            0,
+            recovered + std::time::Duration::from_secs(45),
+            1000,
+        );
+        assert!(early < later && later < 0.75, "{early} then {later}");
+        assert_eq!(
+            share_at(
+                &lb,
+                &refs(&instances),
+                0,
+                recovered + std::time::Duration::from_secs(60),
+                400
+            ),
+            0.75
+        );
+    }
+
+    #[test]
+    fn test_load_balancer_for_applies_slow_start_to_round_robin() {
+        let mut cluster = UpstreamCluster::new("svc");
+        cluster.slow_start = Some(slow_start(30));
+        let warmups = Arc::new(Warmups::new());
+        let lb = load_balancer_for(&cluster, Arc::clone(&warmups));
+        let instances = make_weighted_instances(&[1, 1]);
+        assert!(lb.select(&refs(&instances), "").is_some());
+        assert_eq!(warmups.len(), 2);
+
+        cluster.strategy = LoadBalanceStrategy::IpHash;
+        let lb = load_balancer_for(&cluster, warmups);
+        let r = refs(&instances);
+        assert_eq!(lb.select(&r, "10.0.0.1"), lb.select(&r, "10.0.0.1"));
+    }
+
     // ---- Random ----
 
     #[test]
     fn test_random_selects_from_healthy() {
-        let lb = RandomLB;
+        let lb = RandomLB::new();
         let instances = make_instances(5);
         let r = refs(&instances);
 
@@ -361,11 +687,49 @@ mod tests {
 
     #[test]
     fn test_random_empty_returns_none() {
-        let lb = RandomLB;
+        let lb = RandomLB::new();
         let empty: Vec<&UpstreamInstance> = vec![];
         assert_eq!(lb.select(&empty, ""), None);
     }
 
+    #[test]
+    fn test_seeded_random_is_reproducible() {
+        let instances = make_weighted_instances(&[1, 1, 1, 1]);
+        let r = refs(&instances);
+        let run = |lb: &RandomLB| -> Vec<usize> {
+            (0..200).map(|_| lb.select(&r, "").unwrap()).collect()
+        };
+
+        let first = run(&RandomLB::seeded(42));
+        assert_eq!(first, run(&RandomLB::seeded(42)));
+        assert_ne!(first, run(&RandomLB::seeded(43)));
+    }
+
+    #[test]
+    fn test_seeded_random_follows_weights() {
+        let lb = RandomLB::seeded(7);
+        let instances = make_weighted_instances(&[1, 3, 6, 0]);
+        let r = refs(&instances);
+
+        let mut counts = [0u32; 4];
+        for _ in 0..10_000 {
+            counts[lb.select(&r, "").unwrap()] += 1;
+        }
+        // 10% / 30% / 60% / never, within a point and a half.
+        for (count, expected) in counts.iter().zip([1_000, 3_000, 6_000, 0]) {
+            assert!(count.abs_diff(expected) <= 150, "{counts:?}");
+        }
+    }
+
+    #[test]
+    fn test_random_zero_weights_fall_back_to_uniform() {
+        let lb = RandomLB::seeded(1);
+        let instances = make_weighted_instances(&[0, 0]);
+        let r = refs(&instances);
+        let picks: Vec<usize> = (0..100).map(|_| lb.select(&r, "").unwrap()).collect();
+        assert!(picks.contains(&0) && picks.contains(&1));
+    }
+
     // ---- Least Connections ----
 
     #[test]
@@ -3,18 +3,24 @@
 use crate::route::Route;
 use regex::Regex;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Result of a successful route match
 #[derive(Debug, Clone)]
 pub struct Match {
-    /// The matched route
-    pub route: Route,
+    /// The matched route, shared with the router (no per-request copy)
+    pub route: Arc<Route>,
 
     /// Extracted path parameters
     pub params: HashMap<String, String>,
 
     /// Wildcard match (if any)
     pub wildcard: Option<String>,
+
+    /// Canonical request path the client should be redirected to. Only set
+    /// under [`TrailingSlashPolicy::RedirectToCanonical`](crate::TrailingSlashPolicy::RedirectToCanonical)
+    /// when the request's trailing slash differs from the matched route's.
+    pub redirect_to: Option<String>,
 }
 
 /// Path pattern matcher
@@ -135,6 +141,19 @@ impl PathMatcher {
         }
     }
 
+    /// Whether `path` matches this pattern, without extracting parameters
+    pub fn is_match(&self, path: &str) -> bool {
+        match &self.regex {
+            Some(re) => re.is_match(path),
+            None => path == self.pattern,
+        }
+    }
+
+    /// Value `path` gives the parameter or wildcard `name`, if it matches
+    pub fn capture(&self, path: &str, name: &str) -> Option<String> {
+        self.matches(path)?.remove(name)
+    }
+
     /// Get the pattern
     pub fn pattern(&self) -> &str {
         &self.pattern
@@ -182,6 +201,22 @@ mod tests {
         assert!(matcher.matches("/users").is_none());
     }
 
+    #[test]
+    fn test_is_match_and_capture() {
+        let matcher = PathMatcher::new("/files/:dir/*path");
+        assert!(matcher.is_match("/files/docs/a/b.txt"));
+        assert!(!matcher.is_match("/files"));
+        assert_eq!(
+            matcher.capture("/files/docs/a/b.txt", "path").as_deref(),
+            Some("a/b.txt")
+        );
+        assert_eq!(matcher.capture("/files/docs/a", "missing"), None);
+
+        let matcher = PathMatcher::new("/health");
+        assert!(matcher.is_match("/health"));
+        assert!(!matcher.is_match("/health/live"));
+    }
+
     #[test]
     fn test_multiple_params() {
         let matcher = PathMatcher::new("/users/:user_id/posts/:post_id");
@@ -0,0 +1,205 @@
+//! Request path normalization.
+//!
+//! The trie matches raw path segments, so two spellings of the same resource
+//! (`/admin`, `//admin`, `/x/../admin`, `/%61dmin`) would otherwise take
+//! different routes — and path-based auth rules could be bypassed by picking
+//! the spelling that misses them. [`normalize_path`] reduces a request path to
+//! one canonical form before routing so matching, auth and proxying all see
+//! the same path:
+//!
+//! 1. Percent-encoded unreserved characters (`A-Z a-z 0-9 - . _ ~`) are
+//!    decoded; every other escape is kept with uppercase hex digits.
+//! 2. An encoded slash (`%2F`) is rejected or kept literally, per
+//!    [`EncodedSlash`] — it never becomes a segment separator.
+//! 3. Empty segments are dropped (`//` collapses to `/`).
+//! 4. `.` and `..` segments are resolved (RFC 3986 §5.2.4); `..` never climbs
+//!    above the root.
+//!
+//! A trailing slash is preserved so the
+//! [`TrailingSlashPolicy`](crate::TrailingSlashPolicy) still applies.
+
+use octopus_core::{Error, Result};
+use std::borrow::Cow;
+
+/// How an encoded slash (`%2F`) inside a path segment is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EncodedSlash {
+    /// Reject the request as invalid (the default).
+    #[default]
+    Reject,
+    /// Keep `%2F` as literal segment data; it is never decoded into a separator.
+    Literal,
+}
+
+/// Normalize a request path for routing (see the [module docs](self)).
+///
+/// Returns the path borrowed when it is already canonical. Fails with
+/// [`Error::InvalidRequest`] on a malformed percent-escape, or on `%2F` under
+/// [`EncodedSlash::Reject`]. Paths that do not start with `/` (e.g. the
+/// `OPTIONS *` target) are returned unchanged.
+pub fn normalize_path(path: &str, encoded_slash: EncodedSlash) -> Result<Cow<'_, str>> {
+    if !path.starts_with('/') || is_canonical(path) {
+        return Ok(Cow::Borrowed(path));
+    }
+
+    let decoded = decode_unreserved(path, encoded_slash)?;
+
+    let mut segments: Vec<&str> = Vec::new();
+    let mut trailing_slash = false;
+    for segment in decoded[1..].split('/') {
+        // A path ending in an empty, `.` or `..` segment names a directory.
+        trailing_slash = matches!(segment, "" | "." | "..");
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut normalized = String::with_capacity(decoded.len());
+    for segment in &segments {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    if normalized.is_empty() || trailing_slash {
+        normalized.push('/');
+    }
+    Ok(Cow::Owned(normalized))
+}
+
+/// Cheap pre-check: no escapes, empty segments or dot segments to rewrite.
+fn is_canonical(path: &str) -> bool {
+    !path.contains('%')
+        && !path.contains("//")
+        && !path
+            .split('/')
+            .any(|segment| segment == "." || segment == "..")
+}
+
+/// Decode percent-escaped unreserved characters, keeping (and uppercasing)
+/// every other escape.
+fn decode_unreserved(path: &str, encoded_slash: EncodedSlash) -> Result<String> {
+    let bytes = path.as_bytes();
+    let mut out = String::with_capacity(path.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'%' {
+            let run = path[i..].find('%').map_or(path.len(), |n| i + n);
+            out.push_str(&path[i..run]);
+            i = run;
+            continue;
+        }
+
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+            .ok_or_else(|| {
+                Error::InvalidRequest(format!("malformed percent-encoding in path: {path}"))
+            })?;
+
+        match hex {
+            b'/' if encoded_slash == EncodedSlash::Reject => {
+                return Err(Error::InvalidRequest(format!(
+                    "encoded slash (%2F) not allowed in path: {path}"
+                )));
+            }
+            b if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') => {
+                out.push(b as char);
+            }
+            b => {
+                out.push_str(&format!("%{b:02X}"));
+            }
+        }
+        i += 3;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn norm(path: &str) -> String {
+        normalize_path(path, EncodedSlash::Reject)
+            .unwrap()
+            .into_owned()
+    }
+
+    #[test]
+    fn canonical_paths_are_borrowed() {
+        for path in ["/", "/users", "/users/42/", "/.well-known/jwks.json"] {
+            let out = normalize_path(path, EncodedSlash::Reject).unwrap();
+            assert!(matches!(out, Cow::Borrowed(_)), "{path} should borrow");
+            assert_eq!(out, path);
+        }
+    }
+
+    #[test]
+    fn resolves_dot_segments() {
+        assert_eq!(norm("/a/../b"), "/b");
+        assert_eq!(norm("/a/./b"), "/a/b");
+        assert_eq!(norm("/a/b/.."), "/a/");
+        assert_eq!(norm("/a/b/."), "/a/b/");
+        assert_eq!(norm("/public/../admin/users"), "/admin/users");
+        // `..` cannot climb above the root.
+        assert_eq!(norm("/../../etc/passwd"), "/etc/passwd");
+        assert_eq!(norm("/.."), "/");
+    }
+
+    #[test]
+    fn collapses_double_slashes() {
+        assert_eq!(norm("//admin"), "/admin");
+        assert_eq!(norm("/api//users///42"), "/api/users/42");
+        assert_eq!(norm("/api/users//"), "/api/users/");
+        assert_eq!(norm("//"), "/");
+    }
+
+    #[test]
+    fn decodes_only_unreserved_escapes() {
+        assert_eq!(norm("/%61dmin"), "/admin");
+        assert_eq!(norm("/files/a%7Eb%2D%5F"), "/files/a~b-_");
+        // Reserved / non-ASCII escapes stay encoded, with uppercase hex.
+        assert_eq!(norm("/search/a%3fb"), "/search/a%3Fb");
+        assert_eq!(norm("/caf%c3%a9"), "/caf%C3%A9");
+        assert_eq!(norm("/100%25"), "/100%25");
+    }
+
+    #[test]
+    fn encoded_dot_segments_are_resolved() {
+        assert_eq!(norm("/public/%2e%2e/admin"), "/admin");
+        assert_eq!(norm("/a/%2E/b"), "/a/b");
+    }
+
+    #[test]
+    fn encoded_slash_rejected_by_default() {
+        let err = normalize_path("/files/a%2Fb", EncodedSlash::Reject).unwrap_err();
+        assert!(matches!(err, Error::InvalidRequest(_)));
+        assert!(normalize_path("/files/a%2fb", EncodedSlash::default()).is_err());
+    }
+
+    #[test]
+    fn encoded_slash_kept_literally_when_configured() {
+        let out = normalize_path("/files/a%2fb/../c", EncodedSlash::Literal).unwrap();
+        assert_eq!(out, "/files/c");
+        let out = normalize_path("/files/a%2fb", EncodedSlash::Literal).unwrap();
+        assert_eq!(out, "/files/a%2Fb");
+        // The literal escape does not split the segment.
+        assert_eq!(out.split('/').count(), 3);
+    }
+
+    #[test]
+    fn malformed_escapes_are_rejected() {
+        for path in ["/a%", "/a%2", "/a%zz", "/%G0"] {
+            let err = normalize_path(path, EncodedSlash::Literal).unwrap_err();
+            assert!(matches!(err, Error::InvalidRequest(_)), "{path}");
+        }
+    }
+
+    #[test]
+    fn non_origin_form_targets_are_untouched() {
+        assert_eq!(normalize_path("*", EncodedSlash::Reject).unwrap(), "*");
+    }
+}
@@ -3,8 +3,8 @@
 use crate::convention::Convention;
 use crate::host::HostMatch;
 use crate::proxy_spec::ProxySpec;
-use http::Method;
-use octopus_core::{Error, Result};
+use http::{Method, StatusCode};
+use octopus_core::{Error, ResponseBodyLimit, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -24,7 +24,12 @@ pub struct Route {
     /// Upstream cluster name
     pub upstream_name: String,
 
-    /// Priority (higher = matched first)
+    /// Logical service resolved per request through discovery, taking the
+    /// place of `upstream_name`
+    pub service: Option<String>,
+
+    /// Priority: among matches equally specific by host and path, the
+    /// higher wins
     pub priority: i32,
 
     /// Route metadata
@@ -72,6 +77,20 @@ pub struct Route {
 
     /// Reverse-proxy configuration. `None` = legacy in-cluster strip-only route.
     pub proxy: Option<ProxySpec>,
+
+    /// Response served instead of an error when the upstream is unavailable
+    pub fallback: Option<RouteFallback>,
+
+    /// Upstream taking the route's traffic while `upstream_name` has no
+    /// available instance
+    pub fallback_upstream: Option<String>,
+
+    /// Upstreams by client location (region `US-CA`, country `DE` or
+    /// continent `EU`), overriding `upstream_name` for matching clients
+    pub geo_upstreams: HashMap<String, String>,
+
+    /// Per-route response body limit override
+    pub response_body_limit: Option<ResponseBodyLimit>,
 }
 
 /// Per-route CORS override configuration
@@ -89,16 +108,64 @@ pub struct RouteCorsOverride {
     pub max_age: u64,
 }
 
+/// Graceful-degradation response for a route whose upstream is unavailable
+/// (no healthy instance, or every retry failed).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteFallback {
+    /// A canned JSON body with the given status
+    StaticJson {
+        /// Response status
+        status: StatusCode,
+        /// JSON body, served verbatim
+        body: String,
+    },
+    /// An empty response (problem body for error statuses) with this status
+    Status(StatusCode),
+    /// A `302 Found` redirect to this URL
+    Redirect(String),
+    /// The last successful anonymous `GET` response for the same URL, if one
+    /// was seen within `max_age`; otherwise the usual error
+    LastGoodCache {
+        /// How long a captured response stays servable
+        max_age: Duration,
+    },
+}
+
+impl RouteFallback {
+    /// Stable snake_case name of the fallback kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::StaticJson { .. } => "static_json",
+            Self::Status(_) => "status",
+            Self::Redirect(_) => "redirect",
+            Self::LastGoodCache { .. } => "last_good_cache",
+        }
+    }
+}
+
 impl Route {
     /// Create a new route builder
     pub fn builder() -> RouteBuilder {
         RouteBuilder::new()
     }
+
+    /// The region-specific upstream for the first location key (most
+    /// specific first) that has one
+    pub fn geo_upstream<'a>(&self, keys: impl IntoIterator<Item = &'a str>) -> Option<&str> {
+        if self.geo_upstreams.is_empty() {
+            return None;
+        }
+        keys.into_iter()
+            .find_map(|key| self.geo_upstreams.get(key))
+            .map(String::as_str)
+    }
 }
 
 /// Builder for constructing routes
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct RouteBuilder {
+    /// Prefix of the [`RouteGroup`](crate::RouteGroup) this route belongs to
+    pub(crate) group_prefix: Option<String>,
     method: Option<Method>,
     host: HostMatch,
     path: Option<String>,
@@ -118,6 +185,11 @@ pub struct RouteBuilder {
     convention: Option<Convention>,
     gateway_id: Option<Arc<str>>,
     proxy: Option<ProxySpec>,
+    fallback: Option<RouteFallback>,
+    fallback_upstream: Option<String>,
+    service: Option<String>,
+    geo_upstreams: HashMap<String, String>,
+    response_body_limit: Option<ResponseBodyLimit>,
 }
 
 impl RouteBuilder {
@@ -240,6 +312,36 @@ impl RouteBuilder {
         self
     }
 
+    /// Set the upstream-unavailable fallback (`None` = return the error).
+    pub fn fallback(mut self, fallback: Option<RouteFallback>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Set the upstream used while the route's own is unavailable.
+    pub fn fallback_upstream(mut self, upstream: Option<String>) -> Self {
+        self.fallback_upstream = upstream;
+        self
+    }
+
+    /// Set the discovery-resolved service (`None` = use `upstream_name`).
+    pub fn service(mut self, service: Option<String>) -> Self {
+        self.service = service;
+        self
+    }
+
+    /// Set the region-specific upstreams, keyed by location.
+    pub fn geo_upstreams(mut self, upstreams: HashMap<String, String>) -> Self {
+        self.geo_upstreams = upstreams;
+        self
+    }
+
+    /// Set the response body limit (`None` = the gateway default).
+    pub fn response_body_limit(mut self, limit: Option<ResponseBodyLimit>) -> Self {
+        self.response_body_limit = limit;
+        self
+    }
+
     /// Build the route
     pub fn build(self) -> Result<Route> {
         let method = self
@@ -258,12 +360,17 @@ impl RouteBuilder {
         if !path.starts_with('/') {
             return Err(Error::Config("path must start with '/'".to_string()));
         }
+        let path = match self.group_prefix {
+            Some(prefix) => crate::group::join_prefix(&prefix, &path),
+            None => path,
+        };
 
         Ok(Route {
             method,
             host: self.host,
             path,
             upstream_name,
+            service: self.service,
             priority: self.priority,
             metadata: self.metadata,
             strip_prefix: self.strip_prefix,
@@ -279,6 +386,10 @@ impl RouteBuilder {
             convention: self.convention,
             gateway_id: self.gateway_id,
             proxy: self.proxy,
+            fallback: self.fallback,
+            fallback_upstream: self.fallback_upstream,
+            geo_upstreams: self.geo_upstreams,
+            response_body_limit: self.response_body_limit,
         })
     }
 }
@@ -365,6 +476,26 @@ mod tests {
         assert_eq!(route.host, HostMatch::Exact("a.com".into()));
     }
 
+    #[test]
+    fn geo_upstream_prefers_most_specific_key() {
+        let route = RouteBuilder::new()
+            .method(Method::GET)
+            .path("/x")
+            .upstream_name("global")
+            .geo_upstreams(HashMap::from([
+                ("US-CA".to_string(), "us-west".to_string()),
+                ("US".to_string(), "us-east".to_string()),
+                ("EU".to_string(), "eu".to_string()),
+            ]))
+            .build()
+            .unwrap();
+
+        assert_eq!(route.geo_upstream(["US-CA", "US", "NA"]), Some("us-west"));
+        assert_eq!(route.geo_upstream(["US-WA", "US", "NA"]), Some("us-east"));
+        assert_eq!(route.geo_upstream(["DE", "EU"]), Some("eu"));
+        assert_eq!(route.geo_upstream(["JP", "AS"]), None);
+    }
+
     #[test]
     fn test_route_builder() {
         let route = RouteBuilder::new()
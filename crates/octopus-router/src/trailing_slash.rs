@@ -0,0 +1,67 @@
+//! Trailing-slash handling for route matching.
+//!
+//! The trie walks non-empty path segments, so `/users` and `/users/` always
+//! reach the same node. [`TrailingSlashPolicy`] decides what happens next:
+//! treat the two forms as distinct routes, treat them as the same route, or
+//! match them as the same route but ask the caller to redirect the client to
+//! the form the route was registered with.
+//!
+//! Wildcard routes (`/static/*filepath`) are exempt: the trailing slash is
+//! part of the captured tail, so they match and never redirect under every
+//! policy.
+
+/// How a trailing `/` on the request path is treated when matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TrailingSlashPolicy {
+    /// `/users` and `/users/` are different paths: a request only matches a
+    /// route registered with the same trailing-slash form.
+    Strict,
+    /// `/users` and `/users/` match the same route (the default). When both
+    /// forms are registered, the one matching the request form wins.
+    #[default]
+    Merge,
+    /// Match like [`Merge`](Self::Merge), but when the request form differs
+    /// from the matched route's, report the canonical path in
+    /// [`Match::redirect_to`](crate::Match::redirect_to) so the caller can
+    /// answer with `308 Permanent Redirect`.
+    RedirectToCanonical,
+}
+
+/// Whether `path` ends with a `/` that is not the root path itself.
+pub(crate) fn has_trailing_slash(path: &str) -> bool {
+    path.len() > 1 && path.ends_with('/')
+}
+
+/// Rewrite `path` into the requested trailing-slash form. The root path is
+/// returned unchanged.
+pub(crate) fn with_trailing_slash(path: &str, slash: bool) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return "/".to_string();
+    }
+    if slash {
+        format!("{trimmed}/")
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_slash_detection_ignores_root() {
+        assert!(!has_trailing_slash("/"));
+        assert!(!has_trailing_slash("/users"));
+        assert!(has_trailing_slash("/users/"));
+    }
+
+    #[test]
+    fn with_trailing_slash_rewrites_form() {
+        assert_eq!(with_trailing_slash("/users", true), "/users/");
+        assert_eq!(with_trailing_slash("/users/", false), "/users");
+        assert_eq!(with_trailing_slash("/users//", true), "/users/");
+        assert_eq!(with_trailing_slash("/", true), "/");
+    }
+}
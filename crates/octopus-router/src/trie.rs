@@ -2,8 +2,11 @@
 
 use crate::matcher::{Match, PathMatcher};
 use crate::route::Route;
+use crate::trailing_slash::{has_trailing_slash, with_trailing_slash, TrailingSlashPolicy};
 use octopus_core::{Error, Result};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Node in the route trie
 #[derive(Debug)]
@@ -18,11 +21,13 @@ struct TrieNode {
     wildcard_child: Option<Box<TrieNode>>,
 
     /// Routes at this node (terminal). Multiple routes may share a method+path
-    /// when they are scoped to different hosts; selection picks the most
-    /// specific host at match time.
-    routes: Vec<Route>,
+    /// when they are scoped to different hosts (or differ only by a trailing
+    /// slash); selection picks the most specific host at match time. Shared
+    /// with every [`Match`] that selects them.
+    routes: Vec<Arc<Route>>,
 
-    /// Path matcher for this node (shared by all routes here — same path)
+    /// Path matcher for this node (shared by all routes here — same path,
+    /// compiled without any trailing slash)
     matcher: Option<PathMatcher>,
 }
 
@@ -38,6 +43,40 @@ impl TrieNode {
     }
 }
 
+/// A route reached by a trie walk, before selection.
+struct Candidate<'a> {
+    route: &'a Arc<Route>,
+    params: HashMap<String, String>,
+    wildcard: Option<String>,
+}
+
+/// Compare how specifically two route patterns match the same request path:
+/// segment by segment, a static segment beats a parameter, which beats a
+/// wildcard, and the first segment that differs decides.
+fn path_specificity(a: &str, b: &str) -> Ordering {
+    fn ranks(pattern: &str) -> impl Iterator<Item = u8> + '_ {
+        pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|segment| match segment.as_bytes()[0] {
+                b'*' => 0,
+                b':' => 1,
+                _ => 2,
+            })
+    }
+    ranks(a).cmp(ranks(b))
+}
+
+/// Outcome of [`RouteTrie::static_walk`].
+enum StaticWalk<'a> {
+    /// Every segment matched a static child; only this node's routes can match
+    Terminal(&'a TrieNode),
+    /// A segment matched nothing and no dynamic branch was passed
+    NoMatch,
+    /// A parameter or wildcard branch was passed; the full walk is needed
+    Dynamic,
+}
+
 /// Trie for storing and matching routes
 #[derive(Debug)]
 pub struct RouteTrie {
@@ -54,6 +93,15 @@ impl RouteTrie {
         }
     }
 
+    /// Build a trie holding `routes`, failing on the first duplicate
+    pub fn from_routes(routes: impl IntoIterator<Item = Route>) -> Result<Self> {
+        let mut trie = Self::new();
+        for route in routes {
+            trie.insert(route)?;
+        }
+        Ok(trie)
+    }
+
     /// Insert a route into the trie
     pub fn insert(&mut self, route: Route) -> Result<()> {
         let segments: Vec<&str> = route.path.split('/').filter(|s| !s.is_empty()).collect();
@@ -84,8 +132,14 @@ impl RouteTrie {
         }
 
         // Store route and matcher at terminal node. The same path may host
-        // several routes (one per host), but a given (path, host) is unique.
-        if current.routes.iter().any(|r| r.host == route.host) {
+        // several routes (one per host, and `/users` next to `/users/`), but a
+        // given (path, host) is unique.
+        let slash = has_trailing_slash(&route.path);
+        if current
+            .routes
+            .iter()
+            .any(|r| r.host == route.host && has_trailing_slash(&r.path) == slash)
+        {
             return Err(Error::Config(format!(
                 "Route already exists: {} (host {:?})",
                 route.path, route.host
@@ -93,19 +147,23 @@ impl RouteTrie {
         }
 
         if current.matcher.is_none() {
-            current.matcher = Some(PathMatcher::new(route.path.clone()));
+            // Matching runs against the segment-joined path, which never
+            // carries a trailing slash, so compile the pattern without one.
+            current.matcher = Some(PathMatcher::new(with_trailing_slash(&route.path, false)));
         }
-        current.routes.push(route);
+        current.routes.push(Arc::new(route));
         self.count += 1;
 
         Ok(())
     }
 
-    /// Remove a route from the trie
+    /// Remove a route from the trie. Only routes registered with the same
+    /// trailing-slash form as `path` are removed.
     pub fn remove(&mut self, path: &str) -> Result<()> {
         let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
 
-        let removed = Self::remove_recursive(&mut self.root, &segments, 0);
+        let slash = has_trailing_slash(path);
+        let removed = Self::remove_recursive(&mut self.root, &segments, 0, slash);
         if removed > 0 {
             self.count -= removed;
             Ok(())
@@ -115,30 +173,36 @@ impl RouteTrie {
     }
 
     /// Returns the number of routes removed at the matched terminal node.
-    fn remove_recursive(node: &mut TrieNode, segments: &[&str], index: usize) -> usize {
+    fn remove_recursive(
+        node: &mut TrieNode,
+        segments: &[&str],
+        index: usize,
+        slash: bool,
+    ) -> usize {
         if index == segments.len() {
-            // Reached end of path — drop every route registered here.
-            if !node.routes.is_empty() {
-                let removed = node.routes.len();
-                node.routes.clear();
+            // Reached end of path — drop every route registered here in the
+            // same trailing-slash form.
+            let before = node.routes.len();
+            node.routes.retain(|r| has_trailing_slash(&r.path) != slash);
+            let removed = before - node.routes.len();
+            if node.routes.is_empty() {
                 node.matcher = None;
-                return removed;
             }
-            return 0;
+            return removed;
         }
 
         let segment = segments[index];
 
         if segment.starts_with(':') {
             if let Some(ref mut child) = node.param_child {
-                return Self::remove_recursive(child, segments, index + 1);
+                return Self::remove_recursive(child, segments, index + 1, slash);
             }
         } else if segment.starts_with('*') {
             if let Some(ref mut child) = node.wildcard_child {
-                return Self::remove_recursive(child, segments, index + 1);
+                return Self::remove_recursive(child, segments, index + 1, slash);
             }
         } else if let Some(child) = node.children.get_mut(segment) {
-            return Self::remove_recursive(child, segments, index + 1);
+            return Self::remove_recursive(child, segments, index + 1, slash);
         }
 
         0
@@ -146,32 +210,121 @@ impl RouteTrie {
 
     /// Match a request `host` + `path` against routes in the trie.
     ///
-    /// Only routes whose host matches are considered; among those, the most
-    /// specific host wins (exact > wildcard > any), then higher priority.
-    /// `host` must be lowercased by the caller.
+    /// Every route whose path and host match is a candidate; the most
+    /// specific host wins (exact > wildcard > any), then the most specific
+    /// path (static segments over parameters over wildcards, compared
+    /// segment by segment), then higher priority.
+    /// `host` must be lowercased by the caller. A trailing slash is handled
+    /// with [`TrailingSlashPolicy::Merge`].
     pub fn match_path(&self, host: &str, path: &str) -> Option<Match> {
+        self.match_path_with_policy(host, path, TrailingSlashPolicy::Merge)
+    }
+
+    /// Match `path` for a request without a host: only routes not scoped to
+    /// a host are considered.
+    pub fn lookup(&self, path: &str) -> Option<Match> {
+        self.match_path("", path)
+    }
+
+    /// Match like [`match_path`](Self::match_path), treating a trailing slash
+    /// on `path` according to `policy`.
+    pub fn match_path_with_policy(
+        &self,
+        host: &str,
+        path: &str,
+        policy: TrailingSlashPolicy,
+    ) -> Option<Match> {
+        // Fast path: a path that walks static segments only, with no
+        // parameter or wildcard branch along the way, can only match the
+        // routes at the end of that walk. Nothing is allocated for it.
+        match self.static_walk(path) {
+            StaticWalk::Dynamic => {}
+            StaticWalk::NoMatch => return None,
+            StaticWalk::Terminal(node) => {
+                let candidates = node
+                    .routes
+                    .iter()
+                    .filter(|route| route.host.matches(host))
+                    .map(|route| Candidate {
+                        route,
+                        params: HashMap::new(),
+                        wildcard: None,
+                    });
+                return Self::select(candidates, path, policy);
+            }
+        }
+
         let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
 
-        let mut matches = Vec::new();
-        Self::match_recursive(&self.root, host, &segments, 0, &mut matches);
+        let mut candidates = Vec::new();
+        Self::match_recursive(&self.root, host, &segments, 0, &mut candidates);
+        Self::select(candidates, path, policy)
+    }
 
-        // Most specific host first, then highest priority.
-        matches.sort_by(|a, b| {
-            b.route
-                .host
-                .specificity()
-                .cmp(&a.route.host.specificity())
-                .then(b.route.priority.cmp(&a.route.priority))
-        });
-        matches.into_iter().next()
+    /// Walk `path` through static children only, stopping at the first node
+    /// with a parameter or wildcard branch.
+    fn static_walk(&self, path: &str) -> StaticWalk<'_> {
+        let mut node = &self.root;
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            if node.param_child.is_some() || node.wildcard_child.is_some() {
+                return StaticWalk::Dynamic;
+            }
+            match node.children.get(segment) {
+                Some(child) => node = child,
+                None => return StaticWalk::NoMatch,
+            }
+        }
+        StaticWalk::Terminal(node)
     }
 
-    fn match_recursive(
-        node: &TrieNode,
+    /// Pick the best of `candidates` for `path` under `policy`.
+    fn select<'a>(
+        candidates: impl IntoIterator<Item = Candidate<'a>>,
+        path: &str,
+        policy: TrailingSlashPolicy,
+    ) -> Option<Match> {
+        // Wildcard routes absorb the trailing slash into their tail, so only
+        // routes ending at a terminal node care about the request's form.
+        let slash = has_trailing_slash(path);
+        let same_form =
+            |c: &Candidate<'_>| c.wildcard.is_none() && has_trailing_slash(&c.route.path) == slash;
+
+        // Most specific host first, then most specific path, then highest
+        // priority, then the route registered in the request's trailing-slash
+        // form. Ties go to the first candidate found.
+        let best = candidates
+            .into_iter()
+            .filter(|c| {
+                policy != TrailingSlashPolicy::Strict || c.wildcard.is_some() || same_form(c)
+            })
+            .min_by(|a, b| {
+                b.route
+                    .host
+                    .specificity()
+                    .cmp(&a.route.host.specificity())
+                    .then_with(|| path_specificity(&b.route.path, &a.route.path))
+                    .then(b.route.priority.cmp(&a.route.priority))
+                    .then(same_form(b).cmp(&same_form(a)))
+            })?;
+
+        let redirect_to = (policy == TrailingSlashPolicy::RedirectToCanonical
+            && best.wildcard.is_none()
+            && has_trailing_slash(&best.route.path) != slash)
+            .then(|| with_trailing_slash(path, has_trailing_slash(&best.route.path)));
+        Some(Match {
+            route: Arc::clone(best.route),
+            params: best.params,
+            wildcard: best.wildcard,
+            redirect_to,
+        })
+    }
+
+    fn match_recursive<'a>(
+        node: &'a TrieNode,
         host: &str,
         segments: &[&str],
         index: usize,
-        matches: &mut Vec<Match>,
+        candidates: &mut Vec<Candidate<'a>>,
     ) {
         if index == segments.len() {
             // Reached end of path — collect every host-matching route here.
@@ -180,8 +333,8 @@ impl RouteTrie {
                 if let Some(params) = matcher.matches(&path) {
                     for route in &node.routes {
                         if route.host.matches(host) {
-                            matches.push(Match {
-                                route: route.clone(),
+                            candidates.push(Candidate {
+                                route,
                                 params: params.clone(),
                                 wildcard: None,
                             });
@@ -196,12 +349,12 @@ impl RouteTrie {
 
         // Try static match first (highest priority)
         if let Some(child) = node.children.get(segment) {
-            Self::match_recursive(child, host, segments, index + 1, matches);
+            Self::match_recursive(child, host, segments, index + 1, candidates);
         }
 
         // Try parameter match
         if let Some(ref child) = node.param_child {
-            Self::match_recursive(child, host, segments, index + 1, matches);
+            Self::match_recursive(child, host, segments, index + 1, candidates);
         }
 
         // Try wildcard match (lowest priority)
@@ -211,8 +364,8 @@ impl RouteTrie {
                 if let Some(params) = matcher.matches(&path) {
                     for route in &child.routes {
                         if route.host.matches(host) {
-                            matches.push(Match {
-                                route: route.clone(),
+                            candidates.push(Candidate {
+                                route,
                                 params: params.clone(),
                                 wildcard: Some(segments[index..].join("/")),
                             });
@@ -242,7 +395,7 @@ impl RouteTrie {
 
     fn collect_routes(node: &TrieNode, routes: &mut Vec<Route>) {
         for route in &node.routes {
-            routes.push(route.clone());
+            routes.push(Route::clone(route));
         }
 
         for child in node.children.values() {
@@ -380,6 +533,268 @@ mod tests {
         assert!(dup.is_err(), "same (path, host) must be rejected");
     }
 
+    fn route(path: &str, upstream: &str) -> Route {
+        route_h(path, upstream, HostMatch::Any)
+    }
+
+    #[test]
+    fn from_routes_and_lookup() {
+        let trie = RouteTrie::from_routes([
+            route("/users/:id", "users"),
+            route_h(
+                "/admin",
+                "admin",
+                HostMatch::Exact("admin.example.com".into()),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.lookup("/users/7").unwrap().params["id"], "7");
+        // Host-scoped routes need a host.
+        assert!(trie.lookup("/admin").is_none());
+
+        let err = RouteTrie::from_routes([route("/a", "x"), route("/a", "y")]).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+    }
+
+    #[test]
+    fn strict_policy_distinguishes_trailing_slash() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users", "no-slash")).unwrap();
+        trie.insert(route("/orders/", "slash")).unwrap();
+
+        let strict = TrailingSlashPolicy::Strict;
+        assert_eq!(
+            trie.match_path_with_policy("", "/users", strict)
+                .unwrap()
+                .route
+                .upstream_name,
+            "no-slash"
+        );
+        assert!(trie.match_path_with_policy("", "/users/", strict).is_none());
+        assert_eq!(
+            trie.match_path_with_policy("", "/orders/", strict)
+                .unwrap()
+                .route
+                .upstream_name,
+            "slash"
+        );
+        assert!(trie.match_path_with_policy("", "/orders", strict).is_none());
+    }
+
+    #[test]
+    fn strict_policy_allows_both_forms_as_distinct_routes() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users", "collection")).unwrap();
+        trie.insert(route("/users/", "index")).unwrap();
+        assert_eq!(trie.len(), 2);
+
+        let strict = TrailingSlashPolicy::Strict;
+        let up = |p: &str| {
+            trie.match_path_with_policy("", p, strict)
+                .unwrap()
+                .route
+                .upstream_name
+                .clone()
+        };
+        assert_eq!(up("/users"), "collection");
+        assert_eq!(up("/users/"), "index");
+
+        // Removing one form leaves the other in place.
+        trie.remove("/users/").unwrap();
+        assert_eq!(trie.len(), 1);
+        assert!(trie.match_path_with_policy("", "/users/", strict).is_none());
+        assert!(trie.match_path_with_policy("", "/users", strict).is_some());
+    }
+
+    #[test]
+    fn merge_policy_matches_either_form() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users", "no-slash")).unwrap();
+        trie.insert(route("/orders/", "slash")).unwrap();
+
+        let merge = TrailingSlashPolicy::Merge;
+        for path in ["/users", "/users/"] {
+            let m = trie.match_path_with_policy("", path, merge).unwrap();
+            assert_eq!(m.route.upstream_name, "no-slash");
+            assert!(m.redirect_to.is_none());
+        }
+        for path in ["/orders", "/orders/"] {
+            let m = trie.match_path_with_policy("", path, merge).unwrap();
+            assert_eq!(m.route.upstream_name, "slash");
+            assert!(m.redirect_to.is_none());
+        }
+    }
+
+    #[test]
+    fn merge_policy_still_matches_parameterized_routes() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users/:id", "user")).unwrap();
+        trie.insert(route("/teams/:team/members/", "members"))
+            .unwrap();
+
+        let merge = TrailingSlashPolicy::Merge;
+        let m = trie
+            .match_path_with_policy("", "/users/42/", merge)
+            .unwrap();
+        assert_eq!(m.route.upstream_name, "user");
+        assert_eq!(m.params.get("id"), Some(&"42".to_string()));
+
+        let m = trie
+            .match_path_with_policy("", "/teams/core/members", merge)
+            .unwrap();
+        assert_eq!(m.route.upstream_name, "members");
+        assert_eq!(m.params.get("team"), Some(&"core".to_string()));
+    }
+
+    #[test]
+    fn merge_policy_prefers_exact_form_when_both_registered() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users", "collection")).unwrap();
+        trie.insert(route("/users/", "index")).unwrap();
+
+        let merge = TrailingSlashPolicy::Merge;
+        let up = |p: &str| {
+            trie.match_path_with_policy("", p, merge)
+                .unwrap()
+                .route
+                .upstream_name
+                .clone()
+        };
+        assert_eq!(up("/users"), "collection");
+        assert_eq!(up("/users/"), "index");
+    }
+
+    #[test]
+    fn redirect_policy_reports_canonical_form() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users", "no-slash")).unwrap();
+        trie.insert(route("/orders/:id/", "slash")).unwrap();
+
+        let redirect = TrailingSlashPolicy::RedirectToCanonical;
+        let m = trie
+            .match_path_with_policy("", "/users/", redirect)
+            .unwrap();
+        assert_eq!(m.redirect_to.as_deref(), Some("/users"));
+
+        let m = trie
+            .match_path_with_policy("", "/orders/7", redirect)
+            .unwrap();
+        assert_eq!(m.redirect_to.as_deref(), Some("/orders/7/"));
+
+        // Already canonical: no redirect.
+        let m = trie.match_path_with_policy("", "/users", redirect).unwrap();
+        assert!(m.redirect_to.is_none());
+    }
+
+    #[test]
+    fn wildcard_routes_ignore_trailing_slash_policy() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/static/*filepath", "static")).unwrap();
+
+        for policy in [
+            TrailingSlashPolicy::Strict,
+            TrailingSlashPolicy::Merge,
+            TrailingSlashPolicy::RedirectToCanonical,
+        ] {
+            let m = trie
+                .match_path_with_policy("", "/static/css/", policy)
+                .unwrap();
+            assert_eq!(m.route.upstream_name, "static");
+            assert_eq!(m.wildcard.as_deref(), Some("css"));
+            assert!(m.redirect_to.is_none());
+        }
+    }
+
+    #[test]
+    fn static_matches_share_the_stored_route() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/api/health", "health")).unwrap();
+        trie.insert(route("/api/users/:id", "user")).unwrap();
+
+        let first = trie.match_path("", "/api/health").unwrap();
+        let second = trie.match_path("", "/api/health/").unwrap();
+        assert!(Arc::ptr_eq(&first.route, &second.route));
+        assert!(first.params.is_empty());
+        assert!(first.wildcard.is_none());
+
+        // A parameterized sibling takes the full walk and still binds params.
+        let m = trie.match_path("", "/api/users/7").unwrap();
+        assert_eq!(m.route.upstream_name, "user");
+        assert_eq!(m.params.get("id"), Some(&"7".to_string()));
+    }
+
+    #[test]
+    fn static_beats_param_beats_wildcard_regardless_of_order_or_priority() {
+        let mut trie = RouteTrie::new();
+        let mut catch_all = route("/users/*rest", "catch-all");
+        catch_all.priority = 50;
+        trie.insert(catch_all).unwrap();
+        let mut user = route("/users/:id", "user");
+        user.priority = 10;
+        trie.insert(user).unwrap();
+        trie.insert(route("/users/me", "me")).unwrap();
+
+        let up = |p: &str| {
+            trie.match_path("", p)
+                .map(|m| m.route.upstream_name.clone())
+        };
+        assert_eq!(up("/users/me").as_deref(), Some("me"));
+        assert_eq!(up("/users/42").as_deref(), Some("user"));
+        assert_eq!(up("/users/42/posts").as_deref(), Some("catch-all"));
+        assert_eq!(up("/nowhere"), None);
+    }
+
+    #[test]
+    fn specificity_is_decided_at_the_first_differing_segment() {
+        let mut trie = RouteTrie::new();
+        trie.insert(route("/users/:id/profile", "by-id")).unwrap();
+        trie.insert(route("/users/me/:tab", "me-tab")).unwrap();
+        trie.insert(route("/:section/me/profile", "section"))
+            .unwrap();
+
+        let m = trie.match_path("", "/users/me/profile").unwrap();
+        assert_eq!(m.route.upstream_name, "me-tab");
+        assert_eq!(m.params.get("tab"), Some(&"profile".to_string()));
+    }
+
+    #[test]
+    fn priority_breaks_a_genuine_tie() {
+        let mut trie = RouteTrie::new();
+        let mut low = route_h(
+            "/reports/:id",
+            "low",
+            HostMatch::Wildcard(".example.com".into()),
+        );
+        low.priority = 1;
+        let mut high = route_h(
+            "/reports/:id",
+            "high",
+            HostMatch::Wildcard(".eu.example.com".into()),
+        );
+        high.priority = 9;
+        trie.insert(low).unwrap();
+        trie.insert(high).unwrap();
+
+        // Same host specificity and path shape: priority decides.
+        let m = trie
+            .match_path("acme.eu.example.com", "/reports/7")
+            .unwrap();
+        assert_eq!(m.route.upstream_name, "high");
+        assert_eq!(m.params.get("id"), Some(&"7".to_string()));
+        // Only one candidate matches this host.
+        let m = trie.match_path("acme.example.com", "/reports/7").unwrap();
+        assert_eq!(m.route.upstream_name, "low");
+    }
+
+    #[test]
+    fn path_specificity_ranks_segments_left_to_right() {
+        assert_eq!(path_specificity("/a/b", "/a/:x"), Ordering::Greater);
+        assert_eq!(path_specificity("/a/:x", "/a/*rest"), Ordering::Greater);
+        assert_eq!(path_specificity("/:x/b", "/a/:y"), Ordering::Less);
+        assert_eq!(path_specificity("/a/:x/", "/a/:y"), Ordering::Equal);
+    }
+
     #[test]
     fn test_insert_and_match_static() {
         let mut trie = RouteTrie::new();
@@ -0,0 +1,208 @@
+//! Property tests for path matching
+//!
+//! Route tables and request paths are generated at random and checked against
+//! invariants that must hold for any table: a static route matches its own
+//! path, parameters capture the segment at their position, and the route a
+//! path selects does not depend on the order routes were registered in.
+
+use http::Method;
+use octopus_router::{Match, PathMatcher, Route, RouteBuilder, RouteTrie};
+use proptest::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// One segment of a generated route pattern
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Segment {
+    Static(String),
+    Param,
+    Wildcard,
+}
+
+/// Literal path segment. The small alphabet makes generated routes share
+/// prefixes, so lookups have to choose between static, parameter and
+/// wildcard branches.
+fn literal() -> impl Strategy<Value = String> {
+    "[ab][a-c0-9]{0,2}"
+}
+
+/// Value a request puts where a parameter or wildcard sits
+fn value() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9_.~-]{1,8}"
+}
+
+/// Pattern segments, a wildcard only ever in last place
+fn segments() -> impl Strategy<Value = Vec<Segment>> {
+    let segment = prop_oneof![
+        3 => literal().prop_map(Segment::Static),
+        1 => Just(Segment::Param),
+    ];
+    (
+        prop::collection::vec(segment, 0..5),
+        prop::bool::weighted(0.2),
+    )
+        .prop_map(|(mut segments, wildcard)| {
+            if wildcard {
+                segments.push(Segment::Wildcard);
+            }
+            segments
+        })
+}
+
+/// Route pattern for `segments`. Parameters are named by position, so two
+/// routes of the same shape get the same pattern.
+fn pattern(segments: &[Segment]) -> String {
+    let mut pattern = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        pattern.push('/');
+        match segment {
+            Segment::Static(s) => pattern.push_str(s),
+            Segment::Param => pattern.push_str(&format!(":p{i}")),
+            Segment::Wildcard => pattern.push_str("*rest"),
+        }
+    }
+    if pattern.is_empty() {
+        pattern.push('/');
+    }
+    pattern
+}
+
+/// Request path for `segments`, filling parameters and the wildcard from
+/// `values` in turn
+fn instantiate(segments: &[Segment], values: &[String]) -> String {
+    let mut values = values.iter().cycle();
+    let mut path = String::new();
+    for segment in segments {
+        path.push('/');
+        match segment {
+            Segment::Static(s) => path.push_str(s),
+            Segment::Param | Segment::Wildcard => path.push_str(values.next().unwrap()),
+        }
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+fn route(path: &str) -> Route {
+    RouteBuilder::new()
+        .method(Method::GET)
+        .path(path)
+        .upstream_name(path)
+        .build()
+        .unwrap()
+}
+
+/// Route path, sorted parameters and wildcard tail of a match
+type Selection = (String, Vec<(String, String)>, Option<String>);
+
+/// What a lookup selected, in a form that compares across tries
+fn selection(found: Option<Match>) -> Option<Selection> {
+    found.map(|m| {
+        let mut params: Vec<_> = m.params.into_iter().collect();
+        params.sort();
+        (m.route.path.clone(), params, m.wildcard)
+    })
+}
+
+/// A set of distinct route shapes together with two registration orders
+fn route_table() -> impl Strategy<Value = (Vec<Vec<Segment>>, Vec<Vec<Segment>>)> {
+    prop::collection::btree_set(segments(), 1..12).prop_flat_map(|shapes: BTreeSet<_>| {
+        let shapes: Vec<_> = shapes.into_iter().collect();
+        (
+            Just(shapes.clone()).prop_shuffle(),
+            Just(shapes).prop_shuffle(),
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn static_pattern_matches_itself(segments in prop::collection::vec(literal(), 0..6)) {
+        let path = format!("/{}", segments.join("/"));
+        let matcher = PathMatcher::new(path.as_str());
+
+        prop_assert!(matcher.is_static());
+        prop_assert!(matcher.is_match(&path));
+        prop_assert_eq!(matcher.matches(&path), Some(HashMap::new()));
+    }
+
+    #[test]
+    fn registered_static_routes_match_themselves(
+        paths in prop::collection::btree_set(prop::collection::vec(literal(), 1..5), 1..16),
+        dynamic in prop::collection::vec(segments(), 0..6),
+    ) {
+        let paths: Vec<String> = paths.iter().map(|s| format!("/{}", s.join("/"))).collect();
+        let mut trie = RouteTrie::from_routes(paths.iter().map(|p| route(p))).unwrap();
+        // Dynamic routes alongside must not shadow the static ones.
+        for shape in &dynamic {
+            let _ = trie.insert(route(&pattern(shape)));
+        }
+
+        for path in &paths {
+            let found = trie.lookup(path);
+            prop_assert!(found.is_some(), "{} did not match", path);
+            let found = found.unwrap();
+            prop_assert_eq!(&found.route.path, path);
+            prop_assert!(found.params.is_empty());
+            prop_assert_eq!(found.wildcard, None);
+        }
+    }
+
+    #[test]
+    fn params_capture_their_segment(
+        shape in segments(),
+        values in prop::collection::vec(value(), 1..6),
+    ) {
+        let pattern = pattern(&shape);
+        let path = instantiate(&shape, &values);
+        let matcher = PathMatcher::new(pattern.as_str());
+        prop_assert!(matcher.is_match(&path), "{} !~ {}", path, pattern);
+
+        let expected: HashMap<String, String> = shape
+            .iter()
+            .zip(path.split('/').skip(1))
+            .enumerate()
+            .filter_map(|(i, (segment, value))| match segment {
+                Segment::Static(_) => None,
+                Segment::Param => Some((format!("p{i}"), value.to_string())),
+                Segment::Wildcard => Some(("rest".to_string(), value.to_string())),
+            })
+            .collect();
+        for (name, value) in &expected {
+            prop_assert_eq!(matcher.capture(&path, name), Some(value.clone()));
+        }
+        prop_assert_eq!(matcher.matches(&path), Some(expected.clone()));
+
+        // The trie extracts the same parameters as the matcher.
+        let trie = RouteTrie::from_routes([route(&pattern)]).unwrap();
+        let found = trie.lookup(&path);
+        prop_assert!(found.is_some(), "{} did not match in the trie", path);
+        prop_assert_eq!(found.unwrap().params, expected);
+    }
+
+    #[test]
+    fn matching_ignores_insertion_order(
+        (first, second) in route_table(),
+        values in prop::collection::vec(value(), 1..4),
+        extra in prop::collection::vec(segments(), 0..8),
+    ) {
+        let build = |shapes: &[Vec<Segment>]| {
+            RouteTrie::from_routes(shapes.iter().map(|s| route(&pattern(s)))).unwrap()
+        };
+        let (a, b) = (build(&first), build(&second));
+        prop_assert_eq!(a.len(), b.len());
+
+        // Probe with every route's own shape, filled in, plus unrelated paths.
+        for shape in first.iter().chain(&extra) {
+            for path in [instantiate(shape, &values), pattern(shape)] {
+                prop_assert_eq!(
+                    selection(a.lookup(&path)),
+                    selection(b.lookup(&path)),
+                    "{} selected differently",
+                    path
+                );
+            }
+        }
+    }
+}
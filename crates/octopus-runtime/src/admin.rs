@@ -8,6 +8,7 @@ use axum::Router as AxumRouter;
 use bytes::Bytes;
 use http::{HeaderMap, Method, Request, Response, StatusCode};
 use http_body_util::Full;
+use octopus_admin::auth::AdminOperator;
 use octopus_admin::{AppState, DashboardRouter};
 use octopus_core::{Error, Result};
 use octopus_health::{CircuitBreaker, HealthTracker};
@@ -38,8 +39,6 @@ pub struct AdminHandler {
     #[allow(dead_code)]
     health_tracker: Option<Arc<HealthTracker>>,
     #[allow(dead_code)]
-    circuit_breaker: Option<Arc<CircuitBreaker>>,
-    #[allow(dead_code)]
     plugin_manager: Option<Arc<PluginManager>>,
     metrics_collector: Option<Arc<MetricsCollector>>,
     #[allow(dead_code)]
@@ -104,7 +103,6 @@ impl AdminHandler {
             admin_router,
             app_state,
             health_tracker: None,
-            circuit_breaker: None,
             plugin_manager: None,
             metrics_collector: None,
             activity_log: None,
@@ -119,7 +117,7 @@ impl AdminHandler {
         circuit_breaker: Arc<CircuitBreaker>,
     ) -> Self {
         let ht = Some(health_tracker.clone());
-        let cb = Some(circuit_breaker.clone());
+        let cb = Some(circuit_breaker);
         let app_state =
             Self::build_app_state(&router, &None, &None, &ht, &cb, &None, &None, &None, &None);
         let admin_router = DashboardRouter::build(Arc::clone(&app_state));
@@ -130,7 +128,6 @@ impl AdminHandler {
             admin_router,
             app_state,
             health_tracker: Some(health_tracker),
-            circuit_breaker: Some(circuit_breaker),
             plugin_manager: None,
             metrics_collector: None,
             activity_log: None,
@@ -170,24 +167,62 @@ impl AdminHandler {
             admin_router,
             app_state,
             health_tracker,
-            circuit_breaker,
             plugin_manager,
             metrics_collector,
             activity_log,
         }
     }
 
+    /// Maintenance mode switch, toggled by `/admin/api/maintenance`
+    pub fn maintenance(&self) -> &Arc<octopus_core::MaintenanceMode> {
+        &self.app_state.maintenance
+    }
+
+    /// Serve `log` at `/admin/api/debug/captures`
+    pub fn set_debug_tap(&self, log: Arc<octopus_metrics::DebugTapLog>) {
+        if let Ok(mut slot) = self.app_state.debug_tap.write() {
+            *slot = Some(log);
+        }
+    }
+
+    /// Serve `keys` at `/admin/api/rate-limits/:key`
+    pub fn set_rate_limits(&self, keys: Arc<dyn octopus_core::RateLimitKeys>) {
+        if let Ok(mut slot) = self.app_state.rate_limits.write() {
+            *slot = Some(keys);
+        }
+    }
+
+    /// List `backends` in `/admin/api/health`
+    pub fn set_state_backends(&self, backends: Vec<crate::lifecycle::StateBackendCheck>) {
+        if let Ok(mut slot) = self.app_state.state_backends.write() {
+            *slot = backends
+                .into_iter()
+                .map(|(name, backend)| (name.to_string(), backend))
+                .collect();
+        }
+    }
+
+    /// Publish the gateway middleware chain for `/admin/api/explain`
+    pub fn set_middleware(&self, chain: &[Arc<dyn octopus_core::Middleware>]) {
+        let names = chain.iter().map(|m| m.name().to_string()).collect();
+        if let Ok(mut slot) = self.app_state.middleware.write() {
+            *slot = names;
+        }
+    }
+
     /// Handle admin routes using the Axum router
     ///
     /// This method now delegates to the DashboardRouter from octopus-admin,
     /// which handles all /admin routes properly. Special routes like /metrics
-    /// are handled separately.
+    /// are handled separately. `operator` is the identity the gateway's admin
+    /// auth provider authenticated, used for audit logging.
     pub async fn handle(
         &self,
         method: &Method,
         path: &str,
         headers: HeaderMap,
         body: Bytes,
+        operator: Option<String>,
     ) -> Result<Response<Full<Bytes>>> {
         debug!(method = %method, path = %path, "Handling admin route via Axum router");
 
@@ -204,7 +239,7 @@ impl AdminHandler {
 
         // Bridge the Hyper request into an Axum request, forwarding the method,
         // path (+query), headers and body so write endpoints can read their body.
-        let req = build_admin_request(method, path, headers, body)?;
+        let req = build_admin_request(method, path, headers, body, operator)?;
 
         // Call the Axum router
         let router = self.admin_router.clone();
@@ -253,12 +288,14 @@ impl AdminHandler {
 
 /// Build the Axum request fed to the dashboard router, forwarding the caller's
 /// method, path (+query), headers and request body so write endpoints (JSON
-/// CRUD) can read their body — historically this dropped the body.
+/// CRUD) can read their body — historically this dropped the body. An
+/// authenticated `operator` rides along as an [`AdminOperator`] extension.
 fn build_admin_request(
     method: &Method,
     path: &str,
     headers: HeaderMap,
     body: Bytes,
+    operator: Option<String>,
 ) -> Result<Request<Body>> {
     let mut builder = Request::builder().method(method.clone()).uri(path);
     // Forward the caller's headers (notably Content-Type, so Json<T> extractors
@@ -266,6 +303,9 @@ fn build_admin_request(
     if let Some(dst) = builder.headers_mut() {
         *dst = headers;
     }
+    if let Some(operator) = operator {
+        builder = builder.extension(AdminOperator(operator));
+    }
     builder
         .body(Body::from(body))
         .map_err(|e| Error::InvalidRequest(format!("Failed to build admin request: {e}")))
@@ -288,8 +328,14 @@ mod tests {
         headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
         let body = Bytes::from_static(b"{\"name\":\"orders\"}");
 
-        let req = build_admin_request(&Method::POST, "/admin/api/upstreams", headers, body.clone())
-            .unwrap();
+        let req = build_admin_request(
+            &Method::POST,
+            "/admin/api/upstreams",
+            headers,
+            body.clone(),
+            Some("ops@example.com".to_string()),
+        )
+        .unwrap();
 
         assert_eq!(req.method(), Method::POST);
         assert_eq!(
@@ -297,6 +343,10 @@ mod tests {
             Some(&b"application/json"[..]),
             "content-type must be forwarded so Json<T> extractors run"
         );
+        assert_eq!(
+            req.extensions().get::<AdminOperator>(),
+            Some(&AdminOperator("ops@example.com".to_string()))
+        );
         let collected = axum::body::to_bytes(req.into_body(), usize::MAX)
             .await
             .unwrap();
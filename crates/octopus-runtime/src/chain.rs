@@ -1,43 +1,85 @@
 //! Construction of the request-processing middleware chain from configuration.
 //!
-//! These middleware run *before* authentication and are assembled here (rather
-//! than inline in [`crate::server`]) so the wiring can be unit-tested directly
-//! against configuration.
+//! These middleware, most of which run *before* authentication, are assembled
+//! here (rather than inline in [`crate::server`]) so the wiring can be
+//! unit-tested directly against configuration.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
 use octopus_config::types::{
-    CompressionConfig, CorsGlobalConfig, PluginConfig, SecurityHeadersConfig,
+    CompressionConfig, CorsGlobalConfig, LogLineFormat, LogOverflow, LoggingConfig, PluginConfig,
+    RequestIdConfig, RequestIdGenerator, SecurityHeadersConfig,
 };
 use octopus_core::middleware::Middleware;
+use octopus_metrics::MetricsCollector;
+use octopus_middleware::{AccessLogWriter, LogFormat, LogWriterConfig, OverflowPolicy, WriterSink};
+use octopus_scripting::ScriptKind;
+
+/// Build the response compression middleware, if `compression.enabled`.
+///
+/// The caller registers it after auth, as the last post-auth middleware: auth
+/// rejections and other responses the gateway turns away early stay
+/// uncompressed, and body transforms closer to the proxy see plain bodies.
+pub(crate) fn build_compression(compression: &CompressionConfig) -> Option<Arc<dyn Middleware>> {
+    if !compression.enabled {
+        return None;
+    }
+    let cfg = octopus_compression::CompressionConfig {
+        enabled: compression.enabled,
+        level: compression.level,
+        min_size: compression.min_size,
+        algorithms: compression.algorithms.clone(),
+        adaptive: octopus_compression::AdaptiveConfig {
+            enabled: compression.adaptive.enabled,
+            sample_size: compression.adaptive.sample_size,
+            min_savings: compression.adaptive.min_savings,
+            resample_after: compression.adaptive.resample_after,
+            cpu_high_watermark: compression.adaptive.cpu_high_watermark,
+            high_load_level: compression.adaptive.high_load_level,
+        },
+    };
+    Some(Arc::new(octopus_compression::CompressionMiddleware::new(
+        cfg,
+    )))
+}
+
+/// Build the request ID middleware for `gateway.request_id`.
+pub(crate) fn build_request_id(
+    request_id: &RequestIdConfig,
+) -> octopus_core::Result<Arc<dyn Middleware>> {
+    let generator = match request_id.generator {
+        RequestIdGenerator::UuidV4 => octopus_middleware::IdGenerator::UuidV4,
+        RequestIdGenerator::Ulid => octopus_middleware::IdGenerator::Ulid,
+        RequestIdGenerator::UuidV7 => octopus_middleware::IdGenerator::UuidV7,
+        RequestIdGenerator::Snowflake => {
+            octopus_middleware::IdGenerator::snowflake(request_id.worker_id)?
+        }
+    };
+    Ok(Arc::new(octopus_middleware::RequestId::with_config(
+        octopus_middleware::RequestIdConfig {
+            header_name: request_id.header.clone(),
+            generator,
+            add_to_response: request_id.add_to_response,
+        },
+    )))
+}
 
 /// Build the pre-auth request middleware from configuration.
 ///
-/// Currently: response compression, CORS (global policy; per-route overrides are
-/// applied from request extensions by the CORS middleware itself), and security
-/// response headers (when `security_headers.enabled`). Returned in execution
-/// order (outermost first); the caller appends the auth gateway middleware after
-/// these.
+/// Currently: CORS (global policy; per-route overrides are applied from
+/// request extensions by the CORS middleware itself) and security response
+/// headers (when `security_headers.enabled`). Returned in execution order
+/// (outermost first); the caller appends the auth gateway middleware after
+/// these. An invalid CORS policy (see [`octopus_middleware::CorsConfig::validate`])
+/// is an error.
 pub(crate) fn build_request_middleware(
-    compression: &CompressionConfig,
     cors: Option<&CorsGlobalConfig>,
     security_headers: &SecurityHeadersConfig,
-) -> Vec<Arc<dyn Middleware>> {
+) -> octopus_core::Result<Vec<Arc<dyn Middleware>>> {
     let mut mws: Vec<Arc<dyn Middleware>> = Vec::new();
 
-    if compression.enabled {
-        let cfg = octopus_compression::CompressionConfig {
-            enabled: compression.enabled,
-            level: compression.level,
-            min_size: compression.min_size,
-            algorithms: compression.algorithms.clone(),
-        };
-        mws.push(Arc::new(octopus_compression::CompressionMiddleware::new(
-            cfg,
-        )));
-    }
-
     if let Some(c) = cors {
         let cfg = octopus_middleware::CorsConfig {
             allowed_origins: c.allowed_origins.clone(),
@@ -51,7 +93,7 @@ pub(crate) fn build_request_middleware(
             max_age: Duration::from_secs(c.max_age),
             allow_credentials: c.allow_credentials,
         };
-        mws.push(Arc::new(octopus_middleware::Cors::with_config(cfg)));
+        mws.push(Arc::new(octopus_middleware::Cors::try_with_config(cfg)?));
     }
 
     if security_headers.enabled {
@@ -69,17 +111,101 @@ pub(crate) fn build_request_middleware(
         )));
     }
 
-    mws
+    Ok(mws)
+}
+
+/// Build the request logger for `observability.logging.categories.access`,
+/// if configured.
+///
+/// Without a `buffer` the logger emits its lines as events on the access log
+/// target, which the access sink picks up: structured events, or combined
+/// access-log lines for the `message` format. With one, lines go through an
+/// [`AccessLogWriter`] straight to the sink's output, as JSON lines for the
+/// `json` format and combined access-log lines otherwise; lines dropped on a
+/// full buffer are counted in `metrics`.
+pub(crate) fn build_access_logger(
+    logging: &LoggingConfig,
+    metrics: &MetricsCollector,
+) -> octopus_core::Result<Option<Arc<dyn Middleware>>> {
+    let Some(access) = &logging.categories.access else {
+        return Ok(None);
+    };
+    let format = access.format.unwrap_or(if logging.format == "json" {
+        LogLineFormat::Json
+    } else {
+        LogLineFormat::Text
+    });
+    let combined = || LogFormat::AccessLog(octopus_middleware::COMBINED_LOG_FORMAT.to_string());
+
+    let mut config = octopus_middleware::LoggingConfig::default();
+    match &access.buffer {
+        None => {
+            config.format = match format {
+                LogLineFormat::Message => combined(),
+                LogLineFormat::Text | LogLineFormat::Json => LogFormat::Tracing,
+            };
+        }
+        Some(buffer) => {
+            config.format = match format {
+                LogLineFormat::Json => LogFormat::JsonLines,
+                LogLineFormat::Text | LogLineFormat::Message => combined(),
+            };
+            let output: Box<dyn tokio::io::AsyncWrite + Unpin + Send> = match access.output.as_str()
+            {
+                "stdout" => Box::new(tokio::io::stdout()),
+                "stderr" => Box::new(tokio::io::stderr()),
+                path => {
+                    let file = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .map_err(|e| {
+                            octopus_core::Error::Config(format!(
+                                "failed to open access log {path}: {e}"
+                            ))
+                        })?;
+                    Box::new(tokio::fs::File::from_std(file))
+                }
+            };
+            let writer = AccessLogWriter::spawn(
+                WriterSink::new(output),
+                LogWriterConfig {
+                    capacity: buffer.capacity,
+                    batch_size: buffer.batch_size,
+                    flush_interval: buffer.flush_interval,
+                    overflow: match buffer.overflow {
+                        LogOverflow::Drop => OverflowPolicy::Drop,
+                        LogOverflow::Block => OverflowPolicy::Block,
+                    },
+                },
+            )
+            .count_drops_in(metrics.access_log_dropped());
+            config.writer = Some(writer);
+        }
+    }
+    Ok(Some(Arc::new(
+        octopus_middleware::RequestLogger::with_config(config),
+    )))
 }
 
-/// Build middleware from the `plugins` config. Currently supports **script**
-/// plugins (`plugin_type: "script"`): each enabled entry's `config` is
-/// deserialized into a [`octopus_scripting::ScriptMiddlewareConfig`] (inline
-/// `code` or file `path`, `language`, `on_request`/`on_response`, `timeout_ms`)
-/// and run as a [`octopus_scripting::ScriptMiddleware`], ordered by descending
-/// `priority`. Other plugin types (`static`/`dynamic`) are not yet loaded and are
-/// skipped with a warning.
-pub(crate) fn build_plugin_middleware(plugins: &[PluginConfig]) -> Vec<Arc<dyn Middleware>> {
+/// Build middleware from the `plugins` config, ordered by descending
+/// `priority`. Supports **script** plugins (`plugin_type: "script"`): each
+/// enabled entry's `config` is deserialized into a
+/// [`octopus_scripting::ScriptMiddlewareConfig`] (inline `code` or file
+/// `path`, `language`, `kind`, `on_request`/`on_response`, `timeout_ms`) and
+/// run as a [`octopus_scripting::ScriptMiddleware`]. Only scripts of the given
+/// `kind` are returned: transform scripts run before auth, authorize scripts
+/// after it. **Static** plugins (`plugin_type: "static"`) are taken from
+/// `static_plugins`, already initialized by name (see [`crate::plugins`]), and
+/// run with the transform scripts, as are **WASM** plugins (`plugin_type:
+/// "wasm"`, `wasm` feature). Entries without a registered factory, WASM
+/// entries without the feature and other plugin types (`dynamic`) are skipped
+/// with a warning.
+pub(crate) fn build_plugin_middleware(
+    plugins: &[PluginConfig],
+    kind: ScriptKind,
+    static_plugins: &HashMap<String, Arc<dyn Middleware>>,
+) -> Vec<Arc<dyn Middleware>> {
     let mut enabled: Vec<&PluginConfig> = plugins.iter().filter(|p| p.enabled).collect();
     enabled.sort_by_key(|p| std::cmp::Reverse(p.priority));
 
@@ -89,20 +215,39 @@ pub(crate) fn build_plugin_middleware(plugins: &[PluginConfig]) -> Vec<Arc<dyn M
             "script" => {
                 let value = serde_json::Value::Object(p.config.clone().into_iter().collect());
                 match serde_json::from_value::<octopus_scripting::ScriptMiddlewareConfig>(value) {
+                    Ok(cfg) if cfg.kind != kind => {}
                     Ok(cfg) => {
                         mws.push(Arc::new(octopus_scripting::ScriptMiddleware::new(cfg)));
                         tracing::info!(plugin = %p.name, "Script plugin middleware loaded");
                     }
+                    // Problems are reported once, by the transform pass.
+                    Err(_) if kind != ScriptKind::Transform => {}
                     Err(e) => {
                         tracing::warn!(plugin = %p.name, error = %e, "Invalid script plugin config; skipping");
                     }
                 }
             }
+            _ if kind != ScriptKind::Transform => {}
+            "static" | "wasm" => match static_plugins.get(&p.name) {
+                Some(mw) => mws.push(Arc::clone(mw)),
+                None if p.plugin_type == "wasm" => {
+                    tracing::warn!(
+                        plugin = %p.name,
+                        "WASM plugins need the 'wasm' feature; skipping"
+                    );
+                }
+                None => {
+                    tracing::warn!(
+                        plugin = %p.name,
+                        "No plugin factory registered for static plugin; skipping"
+                    );
+                }
+            },
             other => {
                 tracing::warn!(
                     plugin = %p.name,
                     plugin_type = %other,
-                    "Plugin type not yet loaded (only 'script' is wired); skipping"
+                    "Plugin type not yet loaded (only 'script', 'static' and 'wasm' are wired); skipping"
                 );
             }
         }
@@ -143,13 +288,6 @@ mod tests {
         }
     }
 
-    fn compression_off() -> CompressionConfig {
-        CompressionConfig {
-            enabled: false,
-            ..Default::default()
-        }
-    }
-
     fn cors_allow_all() -> CorsGlobalConfig {
         CorsGlobalConfig {
             allowed_origins: vec!["*".to_string()],
@@ -184,10 +322,48 @@ mod tests {
             .unwrap()
     }
 
+    #[tokio::test]
+    async fn request_id_is_generated_with_the_configured_strategy() {
+        let request_id = build_request_id(&RequestIdConfig {
+            header: "x-trace-id".to_string(),
+            generator: RequestIdGenerator::Snowflake,
+            worker_id: 5,
+            add_to_response: true,
+        })
+        .unwrap();
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::from(vec![request_id, Arc::new(TerminalOk) as _]);
+
+        let resp = Next::new(stack)
+            .run(req_with_origin(Method::GET))
+            .await
+            .unwrap();
+        let id: u64 = resp.headers()["x-trace-id"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!((id >> 12) & 0x3ff, 5);
+    }
+
+    #[test]
+    fn compression_is_built_only_when_enabled() {
+        let off = CompressionConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(build_compression(&off).is_none());
+        let on = CompressionConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(build_compression(&on).is_some());
+    }
+
     #[tokio::test]
     async fn global_cors_applies_allow_origin_header() {
         let cors = cors_allow_all();
-        let mut mws = build_request_middleware(&compression_off(), Some(&cors), &sh_off());
+        let mut mws = build_request_middleware(Some(&cors), &sh_off()).unwrap();
         mws.push(Arc::new(TerminalOk));
         let stack: Arc<[Arc<dyn Middleware>]> = Arc::from(mws);
 
@@ -204,9 +380,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn wildcard_cors_with_credentials_is_rejected() {
+        let cors = CorsGlobalConfig {
+            allow_credentials: true,
+            ..cors_allow_all()
+        };
+        assert!(build_request_middleware(Some(&cors), &sh_off()).is_err());
+    }
+
     #[tokio::test]
     async fn no_cors_header_without_global_config() {
-        let mut mws = build_request_middleware(&compression_off(), None, &sh_off());
+        let mut mws = build_request_middleware(None, &sh_off()).unwrap();
         mws.push(Arc::new(TerminalOk));
         let stack: Arc<[Arc<dyn Middleware>]> = Arc::from(mws);
 
@@ -223,7 +408,7 @@ mod tests {
 
     #[tokio::test]
     async fn security_headers_added_when_enabled() {
-        let mut mws = build_request_middleware(&compression_off(), None, &sh_on());
+        let mut mws = build_request_middleware(None, &sh_on()).unwrap();
         mws.push(Arc::new(TerminalOk));
         let stack: Arc<[Arc<dyn Middleware>]> = Arc::from(mws);
 
@@ -244,7 +429,7 @@ mod tests {
 
     #[tokio::test]
     async fn no_security_headers_when_disabled() {
-        let mut mws = build_request_middleware(&compression_off(), None, &sh_off());
+        let mut mws = build_request_middleware(None, &sh_off()).unwrap();
         mws.push(Arc::new(TerminalOk));
         let stack: Arc<[Arc<dyn Middleware>]> = Arc::from(mws);
 
@@ -260,7 +445,7 @@ mod tests {
     #[tokio::test]
     async fn preflight_short_circuits_with_204() {
         let cors = cors_allow_all();
-        let mut mws = build_request_middleware(&compression_off(), Some(&cors), &sh_off());
+        let mut mws = build_request_middleware(Some(&cors), &sh_off()).unwrap();
         mws.push(Arc::new(PanicTerminal));
         let stack: Arc<[Arc<dyn Middleware>]> = Arc::from(mws);
 
@@ -287,12 +472,17 @@ mod tests {
             enabled,
             priority,
             config,
+            sandbox: Default::default(),
         }
     }
 
     #[test]
     fn script_plugin_produces_middleware() {
-        let mws = build_plugin_middleware(&[script_plugin("s", true, 0)]);
+        let mws = build_plugin_middleware(
+            &[script_plugin("s", true, 0)],
+            ScriptKind::Transform,
+            &HashMap::new(),
+        );
         assert_eq!(mws.len(), 1);
         assert!(format!("{:?}", mws[0]).contains("ScriptMiddleware"));
     }
@@ -302,6 +492,90 @@ mod tests {
         let disabled = script_plugin("d", false, 0);
         let mut static_plugin = script_plugin("st", true, 0);
         static_plugin.plugin_type = "static".to_string();
-        assert!(build_plugin_middleware(&[disabled, static_plugin]).is_empty());
+        assert!(build_plugin_middleware(
+            &[disabled, static_plugin],
+            ScriptKind::Transform,
+            &HashMap::new()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn static_plugins_are_ordered_with_scripts_by_priority() {
+        let mut static_plugin = script_plugin("st", true, 5);
+        static_plugin.plugin_type = "static".to_string();
+        let plugins = [
+            script_plugin("low", true, 0),
+            static_plugin,
+            script_plugin("high", true, 10),
+        ];
+        let loaded: HashMap<String, Arc<dyn Middleware>> = HashMap::from([(
+            "st".to_string(),
+            Arc::new(TerminalOk) as Arc<dyn Middleware>,
+        )]);
+
+        let transform = build_plugin_middleware(&plugins, ScriptKind::Transform, &loaded);
+        let names: Vec<String> = transform.iter().map(|m| format!("{m:?}")).collect();
+        assert_eq!(names.len(), 3);
+        assert!(names[0].contains("ScriptMiddleware"));
+        assert_eq!(names[1], "TerminalOk");
+        assert!(names[2].contains("ScriptMiddleware"));
+
+        // Static plugins run once, with the transform scripts.
+        assert!(build_plugin_middleware(&plugins, ScriptKind::Authorize, &loaded).is_empty());
+    }
+
+    #[test]
+    fn authorize_scripts_are_built_separately() {
+        let mut authorize = script_plugin("authz", true, 0);
+        authorize
+            .config
+            .insert("kind".to_string(), serde_json::json!("authorize"));
+        let plugins = [script_plugin("transform", true, 0), authorize];
+
+        let transform = build_plugin_middleware(&plugins, ScriptKind::Transform, &HashMap::new());
+        let authz = build_plugin_middleware(&plugins, ScriptKind::Authorize, &HashMap::new());
+
+        assert_eq!(transform.len(), 1);
+        assert_eq!(authz.len(), 1);
+        assert!(format!("{:?}", authz[0]).contains("Authorize"));
+    }
+
+    #[tokio::test]
+    async fn buffered_access_log_is_written_to_its_output() {
+        let path = std::env::temp_dir().join(format!("octopus-access-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let logging: LoggingConfig = serde_json::from_value(serde_json::json!({
+            "level": "info",
+            "format": "text",
+            "categories": {"access": {
+                "output": path.to_str().unwrap(),
+                "format": "json",
+                "buffer": {"flush_interval": "10ms"},
+            }},
+        }))
+        .unwrap();
+        let metrics = MetricsCollector::new();
+
+        let logger = build_access_logger(&logging, &metrics).unwrap().unwrap();
+        let stack: Arc<[Arc<dyn Middleware>]> = Arc::new([logger, Arc::new(TerminalOk)]);
+        Next::new(stack)
+            .run(req_with_origin(Method::GET))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let line: serde_json::Value = serde_json::from_str(written.trim()).unwrap();
+        assert_eq!(line["status"], 200, "{written}");
+
+        let unconfigured = LoggingConfig {
+            categories: Default::default(),
+            ..logging
+        };
+        assert!(build_access_logger(&unconfigured, &metrics)
+            .unwrap()
+            .is_none());
     }
 }
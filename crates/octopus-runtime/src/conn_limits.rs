@@ -0,0 +1,529 @@
+//! Frontend connection limits (`gateway.connection_limits`).
+//!
+//! Each accepted connection gets a [`Watchdog`] that closes it when it
+//! stalls: the first byte of a request doesn't arrive in time, an HTTP/1.1
+//! request head trickles in past the header read timeout, or the connection
+//! sits idle between requests for too long. Once it has carried the maximum
+//! number of requests it is shut down gracefully after the last response.
+//! None of this applies while a request is in progress; the request timeout
+//! bounds that.
+
+use http::{Request, Response, Version};
+use hyper::body::{Body, Frame, Incoming, SizeHint};
+use hyper::service::Service;
+use octopus_config::types::ConnectionLimitsConfig;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Timeouts and request cap applied to frontend connections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ConnectionLimits {
+    first_byte_timeout: Option<Duration>,
+    header_read_timeout: Option<Duration>,
+    keep_alive_idle_timeout: Option<Duration>,
+    max_requests: Option<u32>,
+}
+
+impl ConnectionLimits {
+    /// From `gateway.connection_limits`; a zero timeout or request cap
+    /// disables it
+    pub(crate) fn from_config(config: &ConnectionLimitsConfig) -> Self {
+        let enabled = |timeout: Duration| (!timeout.is_zero()).then_some(timeout);
+        Self {
+            first_byte_timeout: enabled(config.first_byte_timeout),
+            header_read_timeout: enabled(config.header_read_timeout),
+            keep_alive_idle_timeout: enabled(config.keep_alive_idle_timeout),
+            max_requests: config.max_requests_per_connection.filter(|&n| n > 0),
+        }
+    }
+
+    /// Start watching a connection accepted now
+    pub(crate) fn watch(self) -> Watchdog {
+        Watchdog {
+            limits: self,
+            state: Mutex::new(State {
+                phase: Phase::Waiting {
+                    stall: Stall::FirstByte,
+                    until: deadline(self.first_byte_timeout),
+                },
+                served: 0,
+                http2: false,
+                exhausted: false,
+            }),
+            changed: Notify::new(),
+        }
+    }
+}
+
+fn deadline(timeout: Option<Duration>) -> Option<Instant> {
+    timeout.map(|timeout| Instant::now() + timeout)
+}
+
+/// Deadline a waiting connection can miss
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stall {
+    /// Nothing received since the connection was accepted
+    FirstByte,
+    /// An HTTP/1.1 request head started but not finished
+    HeaderRead,
+    /// Nothing received since the last response
+    Idle,
+}
+
+impl Stall {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::FirstByte => "first_byte",
+            Self::HeaderRead => "header_read",
+            Self::Idle => "keep_alive_idle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    /// Waiting for a request, closed at `until`
+    Waiting {
+        stall: Stall,
+        until: Option<Instant>,
+    },
+    /// This many requests in progress
+    Serving(usize),
+}
+
+#[derive(Debug)]
+struct State {
+    phase: Phase,
+    /// Requests received so far
+    served: u32,
+    /// Whether the connection speaks HTTP/2, where reads between requests
+    /// (pings, settings) don't start a request head
+    http2: bool,
+    /// The request cap was reached and the connection not yet told to stop
+    exhausted: bool,
+}
+
+/// Why a connection should end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expiry {
+    /// It missed a deadline; close it
+    Stalled(Stall),
+    /// It carried its last request; shut it down gracefully
+    Exhausted,
+}
+
+/// Tracks one connection's progress against its [`ConnectionLimits`]
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    limits: ConnectionLimits,
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+impl Watchdog {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run `fut` (the TLS handshake) within the first-byte deadline; `None`
+    /// if the deadline passes first
+    pub(crate) async fn before_first_byte<F: Future>(&self, fut: F) -> Option<F::Output> {
+        let until = match self.state().phase {
+            Phase::Waiting { until, .. } => until,
+            Phase::Serving(_) => None,
+        };
+        match until {
+            Some(until) => tokio::time::timeout_at(until, fut).await.ok(),
+            None => Some(fut.await),
+        }
+    }
+
+    /// The connection was negotiated as HTTP/2
+    pub(crate) fn expect_http2(&self) {
+        self.state().http2 = true;
+    }
+
+    /// Bytes arrived: for HTTP/1.1 a waiting connection starts a request head
+    fn on_read(&self) {
+        let mut state = self.state();
+        if state.http2 {
+            return;
+        }
+        if let Phase::Waiting {
+            stall: Stall::FirstByte | Stall::Idle,
+            ..
+        } = state.phase
+        {
+            state.phase = Phase::Waiting {
+                stall: Stall::HeaderRead,
+                until: deadline(self.limits.header_read_timeout),
+            };
+            drop(state);
+            self.changed.notify_one();
+        }
+    }
+
+    /// A request's head arrived; it is in progress until the returned guard
+    /// is dropped with its response body
+    fn request_started(self: &Arc<Self>, version: Version) -> InFlight {
+        let mut state = self.state();
+        state.http2 = version == Version::HTTP_2;
+        state.phase = match state.phase {
+            Phase::Serving(n) => Phase::Serving(n + 1),
+            Phase::Waiting { .. } => Phase::Serving(1),
+        };
+        state.served = state.served.saturating_add(1);
+        if self.limits.max_requests == Some(state.served) {
+            state.exhausted = true;
+        }
+        drop(state);
+        self.changed.notify_one();
+        InFlight(Arc::clone(self))
+    }
+
+    fn request_finished(&self) {
+        let mut state = self.state();
+        if let Phase::Serving(n) = state.phase {
+            state.phase = if n > 1 {
+                Phase::Serving(n - 1)
+            } else {
+                Phase::Waiting {
+                    stall: Stall::Idle,
+                    until: deadline(self.limits.keep_alive_idle_timeout),
+                }
+            };
+        }
+        drop(state);
+        self.changed.notify_one();
+    }
+
+    /// Resolves when the connection should end. The request cap is reported
+    /// once.
+    async fn expired(&self) -> Expiry {
+        loop {
+            let changed = self.changed.notified();
+            let waiting = {
+                let mut state = self.state();
+                if std::mem::take(&mut state.exhausted) {
+                    return Expiry::Exhausted;
+                }
+                match state.phase {
+                    Phase::Waiting {
+                        stall,
+                        until: Some(until),
+                    } => Some((stall, until)),
+                    _ => None,
+                }
+            };
+            match waiting {
+                Some((stall, until)) => tokio::select! {
+                    biased;
+                    () = changed => {}
+                    () = tokio::time::sleep_until(until) => return Expiry::Stalled(stall),
+                },
+                None => changed.await,
+            }
+        }
+    }
+}
+
+/// Drive the connection `conn` until it finishes or its watchdog ends it: a
+/// stalled connection is dropped, closing it, and one that reached the
+/// request cap is shut down gracefully through `shutdown`.
+pub(crate) async fn supervise<C, E>(
+    conn: C,
+    watchdog: &Watchdog,
+    shutdown: impl FnOnce(Pin<&mut C>),
+) -> Result<(), E>
+where
+    C: Future<Output = Result<(), E>>,
+{
+    tokio::pin!(conn);
+    let mut shutdown = Some(shutdown);
+    loop {
+        tokio::select! {
+            result = conn.as_mut() => return result,
+            expiry = watchdog.expired() => match expiry {
+                Expiry::Stalled(stall) => {
+                    tracing::debug!(timeout = stall.as_str(), "Closing stalled connection");
+                    return Ok(());
+                }
+                Expiry::Exhausted => {
+                    if let Some(shutdown) = shutdown.take() {
+                        shutdown(conn.as_mut());
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Marks a request in progress until dropped
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<Watchdog>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.request_finished();
+    }
+}
+
+/// A connection's IO, reporting reads to its watchdog
+#[derive(Debug)]
+pub(crate) struct WatchedIo<IO> {
+    io: IO,
+    watchdog: Arc<Watchdog>,
+}
+
+impl<IO> WatchedIo<IO> {
+    pub(crate) fn new(io: IO, watchdog: Arc<Watchdog>) -> Self {
+        Self { io, watchdog }
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for WatchedIo<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut self.io).poll_read(cx, buf))?;
+        if buf.filled().len() > filled {
+            self.watchdog.on_read();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for WatchedIo<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// A connection's service, reporting requests to its watchdog
+#[derive(Debug)]
+pub(crate) struct WatchedService<S> {
+    inner: S,
+    watchdog: Arc<Watchdog>,
+}
+
+impl<S> WatchedService<S> {
+    pub(crate) fn new(inner: S, watchdog: Arc<Watchdog>) -> Self {
+        Self { inner, watchdog }
+    }
+}
+
+impl<S, B> Service<Request<Incoming>> for WatchedService<S>
+where
+    S: Service<Request<Incoming>, Response = Response<B>>,
+{
+    type Response = Response<WatchedBody<B>>;
+    type Error = S::Error;
+    type Future = WatchedFuture<S::Future>;
+
+    fn call(&self, req: Request<Incoming>) -> Self::Future {
+        let in_flight = self.watchdog.request_started(req.version());
+        WatchedFuture {
+            inner: Box::pin(self.inner.call(req)),
+            in_flight: Some(in_flight),
+        }
+    }
+}
+
+/// Response future of a [`WatchedService`]
+pub(crate) struct WatchedFuture<F> {
+    inner: Pin<Box<F>>,
+    in_flight: Option<InFlight>,
+}
+
+impl<F, B, E> Future for WatchedFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<WatchedBody<B>>, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = ready!(self.inner.as_mut().poll(cx));
+        let in_flight = self
+            .in_flight
+            .take()
+            .expect("WatchedFuture polled after completion");
+        Poll::Ready(result.map(|res| {
+            res.map(|body| WatchedBody {
+                body,
+                _in_flight: in_flight,
+            })
+        }))
+    }
+}
+
+/// Response body that keeps its request in progress until it is dropped
+pub(crate) struct WatchedBody<B> {
+    body: B,
+    _in_flight: InFlight,
+}
+
+impl<B: Body + Unpin> Body for WatchedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::listener::{serve_connection, HttpVersions};
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Empty, Full};
+    use hyper_util::rt::TokioIo;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio::task::JoinHandle;
+
+    type Served = JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>>;
+
+    /// Serve HTTP/1.1 on `io`, answering each request after `delay`
+    fn serve(io: DuplexStream, limits: ConnectionLimits, delay: Duration) -> Served {
+        let service = hyper::service::service_fn(move |_req: Request<Incoming>| async move {
+            tokio::time::sleep(delay).await;
+            Ok::<_, std::convert::Infallible>(Response::new(Full::new(Bytes::from("ok"))))
+        });
+        tokio::spawn(serve_connection(
+            io,
+            service,
+            HttpVersions::Http1,
+            limits.watch(),
+        ))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn trickled_headers_are_cut_off_at_the_header_read_timeout() {
+        let limits = ConnectionLimits {
+            header_read_timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let (mut client, server) = tokio::io::duplex(1024);
+        let served = serve(server, limits, Duration::ZERO);
+
+        // Every line arrives well within the timeout, the whole head doesn't
+        let started = Instant::now();
+        for line in [
+            "GET / HTTP/1.1\r\n",
+            "Host: gateway.test\r\n",
+            "X-Slow: 1\r\n",
+        ] {
+            client.write_all(line.as_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(400)).await;
+        }
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+        assert!(received.is_empty());
+        assert!(started.elapsed() < Duration::from_millis(1300));
+        served.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_keep_alive_connections_are_closed_but_slow_requests_are_not() {
+        let limits = ConnectionLimits {
+            keep_alive_idle_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let (mut client, server) = tokio::io::duplex(1024);
+        let served = serve(server, limits, Duration::from_secs(10));
+
+        let started = Instant::now();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: gateway.test\r\n\r\n")
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+
+        assert!(received.starts_with(b"HTTP/1.1 200 OK"));
+        assert!(received.ends_with(b"ok"));
+        assert!(started.elapsed() >= Duration::from_secs(15));
+        served.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn keep_alive_is_capped_at_the_request_limit() {
+        let limits = ConnectionLimits {
+            max_requests: Some(2),
+            ..Default::default()
+        };
+        let (client, server) = tokio::io::duplex(1024);
+        let served = serve(server, limits, Duration::ZERO);
+
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(client))
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+        let request = || {
+            Request::builder()
+                .uri("http://gateway.test/")
+                .body(Empty::<Bytes>::new())
+                .unwrap()
+        };
+
+        for _ in 0..2 {
+            sender.ready().await.unwrap();
+            let res = sender.send_request(request()).await.unwrap();
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "ok");
+        }
+        served.await.unwrap().unwrap();
+
+        let third = async {
+            sender.ready().await?;
+            sender.send_request(request()).await
+        };
+        assert!(third.await.is_err());
+    }
+}
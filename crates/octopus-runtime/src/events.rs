@@ -0,0 +1,688 @@
+//! Gateway lifecycle and security events
+//!
+//! Subsystems publish typed [`GatewayEvent`]s on the [`EventBus`], which fans
+//! them out to every registered [`EventSink`]. The built-in [`WebhookSink`]
+//! POSTs each event as JSON with retry and exponential backoff; custom sinks
+//! (queues, chat ops, audit stores) implement [`EventSink`] and are added with
+//! [`EventBus::add_sink`].
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::{header, Request, Uri};
+use http_body_util::{BodyExt, Full};
+use octopus_config::types::{EventsConfig, WebhookConfig};
+use octopus_core::{Error, Result, UpstreamInstance};
+use octopus_health::{CircuitBreaker, CircuitState};
+use octopus_proxy::HttpClient;
+use octopus_router::Router;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// A gateway lifecycle or security event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GatewayEvent {
+    /// Configuration was hot-reloaded and applied.
+    ConfigReloaded {
+        /// Routes after the reload
+        routes: usize,
+        /// Upstreams after the reload
+        upstreams: usize,
+    },
+    /// A reloaded configuration was rejected; the previous one stays active.
+    ConfigReloadFailed {
+        /// Why the configuration was rejected
+        error: String,
+    },
+    /// Every instance of an upstream has its circuit open.
+    UpstreamDown {
+        /// Upstream name
+        upstream: String,
+    },
+    /// An upstream instance's circuit breaker opened.
+    CircuitOpened {
+        /// Instance id
+        instance: String,
+        /// Upstream the instance belongs to, if known
+        upstream: Option<String>,
+    },
+    /// An upstream instance's circuit breaker changed state.
+    CircuitStateChanged {
+        /// Instance id
+        instance: String,
+        /// Upstream the instance belongs to, if known
+        upstream: Option<String>,
+        /// New state: `closed`, `open` or `half-open`
+        state: String,
+    },
+    /// Authentication/authorization failures crossed the configured rate.
+    AuthFailureSpike {
+        /// Failures counted in the window
+        failures: u64,
+        /// Window length in seconds
+        window_secs: u64,
+    },
+    /// A plugin failed while handling a request.
+    PluginCrashed {
+        /// Plugin name
+        plugin: String,
+        /// Failure message
+        message: String,
+    },
+}
+
+impl GatewayEvent {
+    /// The event's `type` tag, e.g. `circuit_opened`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ConfigReloaded { .. } => "config_reloaded",
+            Self::ConfigReloadFailed { .. } => "config_reload_failed",
+            Self::UpstreamDown { .. } => "upstream_down",
+            Self::CircuitOpened { .. } => "circuit_opened",
+            Self::CircuitStateChanged { .. } => "circuit_state_changed",
+            Self::AuthFailureSpike { .. } => "auth_failure_spike",
+            Self::PluginCrashed { .. } => "plugin_crashed",
+        }
+    }
+}
+
+/// An event as delivered to sinks: the event plus a unique id and the time
+/// it was emitted. Serializes flat, e.g.
+/// `{"id": "...", "timestamp": "...", "type": "upstream_down", "upstream": "users"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventEnvelope {
+    /// Unique event id, stable across delivery retries
+    pub id: String,
+    /// When the event was emitted
+    pub timestamp: DateTime<Utc>,
+    /// The event
+    #[serde(flatten)]
+    pub event: GatewayEvent,
+}
+
+impl EventEnvelope {
+    /// Wrap `event` with a fresh id and the current time.
+    pub fn new(event: GatewayEvent) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            event,
+        }
+    }
+}
+
+/// A destination for gateway events.
+#[async_trait]
+pub trait EventSink: Send + Sync + fmt::Debug {
+    /// Whether this sink wants `event`; defaults to every event.
+    fn accepts(&self, _event: &GatewayEvent) -> bool {
+        true
+    }
+
+    /// Deliver one event. Called on a background task; errors are logged.
+    async fn deliver(&self, envelope: &EventEnvelope) -> Result<()>;
+}
+
+/// Fans events out to registered sinks without blocking the emitter.
+///
+/// Clones share the same sinks, so a sink added after the bus was handed to
+/// the subsystems still receives their events.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    sinks: Arc<RwLock<Vec<Arc<dyn EventSink>>>>,
+    deliveries: Arc<Deliveries>,
+}
+
+/// Deliveries in progress, so shutdown can wait for them
+#[derive(Debug, Default)]
+struct Deliveries {
+    pending: AtomicUsize,
+    idle: Notify,
+}
+
+impl EventBus {
+    /// Create a bus with no sinks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a bus with a [`WebhookSink`] per configured webhook. Webhooks
+    /// with an unusable URL are skipped with a warning.
+    pub fn from_config(config: &EventsConfig) -> Self {
+        let bus = Self::new();
+        for webhook in &config.webhooks {
+            match WebhookSink::new(webhook.clone()) {
+                Ok(sink) => bus.add_sink(Arc::new(sink)),
+                Err(e) => {
+                    tracing::warn!(url = %webhook.url, error = %e, "Skipping event webhook")
+                }
+            }
+        }
+        bus
+    }
+
+    /// Register a sink.
+    pub fn add_sink(&self, sink: Arc<dyn EventSink>) {
+        self.sinks.write().push(sink);
+    }
+
+    /// Whether any sink is registered.
+    pub fn has_sinks(&self) -> bool {
+        !self.sinks.read().is_empty()
+    }
+
+    /// Publish `event` to every interested sink, each on its own task.
+    ///
+    /// Outside a Tokio runtime the event is logged and dropped.
+    pub fn emit(&self, event: GatewayEvent) {
+        let sinks: Vec<_> = self
+            .sinks
+            .read()
+            .iter()
+            .filter(|sink| sink.accepts(&event))
+            .cloned()
+            .collect();
+        if sinks.is_empty() {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!(event = event.kind(), "No runtime to deliver gateway event");
+            return;
+        };
+
+        let envelope = Arc::new(EventEnvelope::new(event));
+        tracing::debug!(event = envelope.event.kind(), id = %envelope.id, "Emitting gateway event");
+        for sink in sinks {
+            let envelope = Arc::clone(&envelope);
+            let deliveries = Arc::clone(&self.deliveries);
+            deliveries.pending.fetch_add(1, Ordering::AcqRel);
+            runtime.spawn(async move {
+                let result = sink.deliver(&envelope).await;
+                if deliveries.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    deliveries.idle.notify_waiters();
+                }
+                if let Err(e) = result {
+                    tracing::warn!(
+                        event = envelope.event.kind(),
+                        id = %envelope.id,
+                        sink = ?sink,
+                        error = %e,
+                        "Gateway event delivery failed"
+                    );
+                }
+            });
+        }
+    }
+
+    /// Wait until every event emitted so far has been delivered, or has
+    /// failed for good.
+    pub async fn flush(&self) {
+        loop {
+            let idle = self.deliveries.idle.notified();
+            if self.deliveries.pending.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+/// Emit `circuit_state_changed` on every transition of `breaker`,
+/// `circuit_opened` when an instance's breaker opens, and `upstream_down`
+/// once every instance of its upstream is open.
+pub fn watch_circuits(events: &EventBus, router: &Arc<Router>, breaker: &Arc<CircuitBreaker>) {
+    let events = events.clone();
+    let router = Arc::clone(router);
+    // Weak: the listener is owned by the breaker it queries.
+    let weak = Arc::downgrade(breaker);
+    breaker.on_transition(Arc::new(move |instance, state| {
+        let upstream = router.upstream_of(instance);
+        events.emit(GatewayEvent::CircuitStateChanged {
+            instance: instance.to_string(),
+            upstream: upstream.clone(),
+            state: state.to_string(),
+        });
+        if state != CircuitState::Open {
+            return;
+        }
+        events.emit(GatewayEvent::CircuitOpened {
+            instance: instance.to_string(),
+            upstream: upstream.clone(),
+        });
+
+        // Only an opening circuit needs the cluster, to check its siblings.
+        let (Some(upstream), Some(breaker)) = (
+            upstream.and_then(|name| router.get_upstream(&name)),
+            weak.upgrade(),
+        ) else {
+            return;
+        };
+        let all_open = upstream
+            .instances
+            .iter()
+            .all(|i| i.id == instance || breaker.get_state(&i.id) == CircuitState::Open);
+        if all_open {
+            tracing::error!(upstream = %upstream.name, "All upstream circuits open");
+            events.emit(GatewayEvent::UpstreamDown {
+                upstream: upstream.name,
+            });
+        }
+    }));
+}
+
+/// POSTs events as JSON to a URL, retrying errors and non-2xx responses
+/// with exponential backoff.
+///
+/// Each request carries `X-Octopus-Event` (the event type) and
+/// `X-Octopus-Event-Id` so receivers can route and deduplicate.
+pub struct WebhookSink {
+    config: WebhookConfig,
+    instance: UpstreamInstance,
+    authority: String,
+    path: String,
+    /// Created on first delivery; the pool needs a running Tokio runtime.
+    client: OnceLock<HttpClient>,
+}
+
+impl WebhookSink {
+    /// Create a sink for `config.url`.
+    pub fn new(config: WebhookConfig) -> Result<Self> {
+        let uri: Uri = config
+            .url
+            .parse()
+            .map_err(|e| Error::Config(format!("Invalid webhook url {}: {e}", config.url)))?;
+        let tls = match uri.scheme_str() {
+            Some("https") => true,
+            Some("http") => false,
+            _ => {
+                return Err(Error::Config(format!(
+                    "Webhook url must be http(s): {}",
+                    config.url
+                )))
+            }
+        };
+        let authority = uri
+            .authority()
+            .ok_or_else(|| Error::Config(format!("Webhook url has no host: {}", config.url)))?;
+        let port = authority.port_u16().unwrap_or(if tls { 443 } else { 80 });
+
+        let mut instance =
+            UpstreamInstance::new(format!("webhook:{}", config.url), authority.host(), port);
+        instance.set_tls(tls, None, true);
+
+        Ok(Self {
+            instance,
+            authority: authority.to_string(),
+            path: uri
+                .path_and_query()
+                .map_or_else(|| "/".to_string(), |p| p.to_string()),
+            client: OnceLock::new(),
+            config,
+        })
+    }
+
+    async fn post(&self, envelope: &EventEnvelope, body: Bytes) -> Result<()> {
+        let mut builder = Request::post(&self.path)
+            .header(header::HOST, &self.authority)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-octopus-event", envelope.event.kind())
+            .header("x-octopus-event-id", &envelope.id);
+        for (name, value) in &self.config.headers {
+            builder = builder.header(name, value);
+        }
+        let req = builder
+            .body(Full::new(body))
+            .map_err(|e| Error::Internal(format!("Invalid webhook request: {e}")))?;
+
+        let client = self
+            .client
+            .get_or_init(|| HttpClient::with_timeout(self.config.timeout));
+        let res = client.send(req, &self.instance).await?;
+        let status = res.status();
+        // Drain the body so the pooled connection can be reused.
+        let _ = res.into_body().collect().await;
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(Error::UpstreamConnection(format!(
+                "Webhook {} responded {status}",
+                self.config.url
+            )))
+        }
+    }
+}
+
+impl fmt::Debug for WebhookSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookSink")
+            .field("url", &self.config.url)
+            .field("events", &self.config.events)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    fn accepts(&self, event: &GatewayEvent) -> bool {
+        self.config.events.is_empty() || self.config.events.iter().any(|e| e == event.kind())
+    }
+
+    async fn deliver(&self, envelope: &EventEnvelope) -> Result<()> {
+        let body = Bytes::from(
+            serde_json::to_vec(envelope)
+                .map_err(|e| Error::Internal(format!("Failed to encode event: {e}")))?,
+        );
+
+        let mut backoff = self.config.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            match self.post(envelope, body.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < self.config.max_retries => {
+                    attempt += 1;
+                    tracing::debug!(
+                        url = %self.config.url,
+                        attempt,
+                        error = %e,
+                        "Webhook delivery failed; retrying"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Counts auth failures in fixed windows and reports when a window's count
+/// reaches the threshold (once per window).
+#[derive(Debug)]
+pub struct AuthFailureMonitor {
+    threshold: u64,
+    window: Duration,
+    state: Mutex<(Instant, u64)>,
+}
+
+impl AuthFailureMonitor {
+    /// Create a monitor; a `threshold` of 0 never reports.
+    pub fn new(threshold: u64, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Create a monitor from the events config.
+    pub fn from_config(config: &EventsConfig) -> Self {
+        Self::new(config.auth_failure_threshold, config.auth_failure_window)
+    }
+
+    /// Record one failure, returning the spike event when this failure
+    /// reaches the threshold.
+    pub fn record(&self) -> Option<GatewayEvent> {
+        if self.threshold == 0 {
+            return None;
+        }
+        let mut state = self.state.lock();
+        if state.0.elapsed() >= self.window {
+            *state = (Instant::now(), 0);
+        }
+        state.1 += 1;
+        (state.1 == self.threshold).then(|| GatewayEvent::AuthFailureSpike {
+            failures: state.1,
+            window_secs: self.window.as_secs(),
+        })
+    }
+}
+
+impl Default for AuthFailureMonitor {
+    fn default() -> Self {
+        Self::from_config(&EventsConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{HeaderMap, Response};
+    use hyper::body::Incoming;
+    use std::collections::HashMap;
+    use tokio::sync::mpsc;
+
+    /// Mock receiver answering with `statuses` in turn (then 200), reporting
+    /// each received request's headers and JSON body.
+    async fn mock_receiver(
+        statuses: Vec<u16>,
+    ) -> (
+        String,
+        mpsc::UnboundedReceiver<(HeaderMap, serde_json::Value)>,
+    ) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let statuses = Arc::new(statuses);
+        let served = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let (tx, statuses, served) =
+                    (tx.clone(), Arc::clone(&statuses), Arc::clone(&served));
+                let service = hyper::service::service_fn(move |req: Request<Incoming>| {
+                    let (tx, statuses, served) =
+                        (tx.clone(), Arc::clone(&statuses), Arc::clone(&served));
+                    async move {
+                        let (parts, body) = req.into_parts();
+                        let body = body.collect().await.unwrap().to_bytes();
+                        let _ = tx.send((parts.headers, serde_json::from_slice(&body).unwrap()));
+                        let n = served.fetch_add(1, Ordering::SeqCst);
+                        let status = statuses.get(n).copied().unwrap_or(200);
+                        Response::builder()
+                            .status(status)
+                            .body(Full::new(Bytes::new()))
+                    }
+                });
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+                );
+            }
+        });
+
+        (format!("http://{addr}/hooks/octopus"), rx)
+    }
+
+    fn webhook(url: String) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            events: vec![],
+            headers: HashMap::from([("authorization".to_string(), "Bearer t0ken".to_string())]),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn emit_posts_event_json_to_webhook() {
+        let (url, mut received) = mock_receiver(vec![]).await;
+        let bus = EventBus::from_config(&EventsConfig {
+            webhooks: vec![webhook(url)],
+            ..Default::default()
+        });
+
+        bus.emit(GatewayEvent::UpstreamDown {
+            upstream: "users".to_string(),
+        });
+
+        let (headers, body) = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers["x-octopus-event"], "upstream_down");
+        assert_eq!(headers["authorization"], "Bearer t0ken");
+        assert_eq!(body["type"], "upstream_down");
+        assert_eq!(body["upstream"], "users");
+        assert_eq!(headers["x-octopus-event-id"], body["id"].as_str().unwrap());
+        assert!(body["timestamp"]
+            .as_str()
+            .unwrap()
+            .parse::<DateTime<Utc>>()
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn webhook_retries_failed_deliveries_with_same_id() {
+        let (url, mut received) = mock_receiver(vec![500, 503]).await;
+        let sink = WebhookSink::new(webhook(url)).unwrap();
+        let envelope = EventEnvelope::new(GatewayEvent::PluginCrashed {
+            plugin: "tenant-guard".to_string(),
+            message: "boom".to_string(),
+        });
+
+        sink.deliver(&envelope).await.unwrap();
+
+        let mut ids = Vec::new();
+        while let Ok((_, body)) = received.try_recv() {
+            assert_eq!(body["plugin"], "tenant-guard");
+            ids.push(body["id"].as_str().unwrap().to_string());
+        }
+        assert_eq!(ids, vec![envelope.id.clone(); 3]);
+    }
+
+    #[tokio::test]
+    async fn webhook_gives_up_after_max_retries() {
+        let (url, _received) = mock_receiver(vec![500; 10]).await;
+        let mut config = webhook(url);
+        config.max_retries = 1;
+        let sink = WebhookSink::new(config).unwrap();
+
+        let envelope = EventEnvelope::new(GatewayEvent::ConfigReloaded {
+            routes: 1,
+            upstreams: 1,
+        });
+        assert!(sink.deliver(&envelope).await.is_err());
+    }
+
+    /// Forwards every event to a channel
+    #[derive(Debug)]
+    struct ChannelSink(mpsc::UnboundedSender<GatewayEvent>);
+
+    #[async_trait]
+    impl EventSink for ChannelSink {
+        async fn deliver(&self, envelope: &EventEnvelope) -> Result<()> {
+            let _ = self.0.send(envelope.event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn flush_waits_for_pending_deliveries() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let bus = EventBus::new();
+        bus.add_sink(Arc::new(ChannelSink(tx)));
+
+        bus.emit(GatewayEvent::ConfigReloaded {
+            routes: 1,
+            upstreams: 1,
+        });
+        bus.flush().await;
+
+        assert!(rx.try_recv().is_ok());
+        // Nothing pending: returns straight away.
+        bus.flush().await;
+    }
+
+    #[tokio::test]
+    async fn circuit_transitions_emit_events() {
+        use octopus_core::UpstreamCluster;
+        use octopus_health::CircuitBreakerConfig;
+
+        let router = Arc::new(Router::new());
+        let mut cluster = UpstreamCluster::new("users");
+        cluster.add_instance(UpstreamInstance::new("users-1", "127.0.0.1", 8001));
+        router.register_upstream(cluster);
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 1,
+            ..Default::default()
+        }));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let bus = EventBus::new();
+        bus.add_sink(Arc::new(ChannelSink(tx)));
+        watch_circuits(&bus, &router, &breaker);
+
+        breaker.record_failure("users-1");
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            received.push(event);
+        }
+        assert!(received.contains(&GatewayEvent::CircuitStateChanged {
+            instance: "users-1".to_string(),
+            upstream: Some("users".to_string()),
+            state: "open".to_string(),
+        }));
+        assert!(received.contains(&GatewayEvent::CircuitOpened {
+            instance: "users-1".to_string(),
+            upstream: Some("users".to_string()),
+        }));
+        assert!(received.contains(&GatewayEvent::UpstreamDown {
+            upstream: "users".to_string(),
+        }));
+
+        breaker.reset("users-1");
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event,
+            GatewayEvent::CircuitStateChanged {
+                instance: "users-1".to_string(),
+                upstream: Some("users".to_string()),
+                state: "closed".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn webhook_event_filter_and_auth_spike() {
+        let mut config = webhook("http://127.0.0.1:9/".to_string());
+        config.events = vec!["circuit_opened".to_string()];
+        let sink = WebhookSink::new(config).unwrap();
+        assert!(!sink.accepts(&GatewayEvent::UpstreamDown {
+            upstream: "users".to_string()
+        }));
+        assert!(sink.accepts(&GatewayEvent::CircuitOpened {
+            instance: "users-1".to_string(),
+            upstream: None,
+        }));
+
+        let monitor = AuthFailureMonitor::new(3, Duration::from_secs(60));
+        assert_eq!(monitor.record(), None);
+        assert_eq!(monitor.record(), None);
+        assert_eq!(
+            monitor.record(),
+            Some(GatewayEvent::AuthFailureSpike {
+                failures: 3,
+                window_secs: 60
+            })
+        );
+        assert_eq!(monitor.record(), None);
+    }
+}
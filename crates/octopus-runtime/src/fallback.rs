@@ -0,0 +1,302 @@
+//! Graceful-degradation fallbacks for routes whose upstream is unavailable.
+//!
+//! When upstream selection finds no healthy instance, or every retry fails to
+//! get an answer, a route with a [`RouteFallback`] answers with it instead of
+//! the gateway's 502/503/504. An upstream's own error response is never
+//! replaced. Fallback responses carry an `X-Octopus-Fallback: <kind>` header
+//! so clients and logs can tell them apart from real upstream responses.
+//!
+//! [`RouteFallback::LastGoodCache`] replays the last successful anonymous
+//! `GET` response for the same URL, captured by [`LastGoodCache::capture`] on
+//! the way out. Requests carrying credentials, and responses marked
+//! `private` or `no-store`, are never captured, and `Set-Cookie` is not kept.
+
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderValue, Method, Response, StatusCode, Uri};
+use http_body_util::{BodyExt, Full};
+use octopus_core::{ErrorResponse, ResponseBuilder, StreamedResponse};
+use octopus_router::RouteFallback;
+use std::time::{Duration, Instant};
+
+/// Header marking a response as a route fallback.
+pub const FALLBACK_HEADER: &str = "x-octopus-fallback";
+
+/// Bodies larger than this are not captured for [`RouteFallback::LastGoodCache`].
+const MAX_CAPTURED_BODY: usize = 1024 * 1024;
+
+#[derive(Clone)]
+struct Snapshot {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    captured_at: Instant,
+}
+
+/// Last successful anonymous `GET` response per URL, for
+/// [`RouteFallback::LastGoodCache`].
+#[derive(Clone)]
+pub struct LastGoodCache {
+    entries: moka::sync::Cache<String, Snapshot>,
+}
+
+impl std::fmt::Debug for LastGoodCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LastGoodCache")
+            .field("entries", &self.entries.entry_count())
+            .finish()
+    }
+}
+
+impl Default for LastGoodCache {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+impl LastGoodCache {
+    /// Create a cache holding at most `capacity` responses.
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            entries: moka::sync::Cache::builder().max_capacity(capacity).build(),
+        }
+    }
+
+    /// Cache key of a request to `host`, or `None` if it is not cacheable:
+    /// not a `GET`, or carrying `Authorization` or `Cookie`, whose response
+    /// may be personal to the caller.
+    pub fn key(method: &Method, host: &str, uri: &Uri, headers: &HeaderMap) -> Option<String> {
+        let anonymous =
+            !headers.contains_key(header::AUTHORIZATION) && !headers.contains_key(header::COOKIE);
+        (method == Method::GET && anonymous).then(|| {
+            let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+            format!("{host}{path}")
+        })
+    }
+
+    /// Remember `response` under `key` if it is a shareable 2xx, then return
+    /// it unchanged. `Set-Cookie` is left out of the captured copy, and a
+    /// streamed response is never captured.
+    pub async fn capture(
+        &self,
+        key: String,
+        response: Response<Full<Bytes>>,
+    ) -> Response<Full<Bytes>> {
+        if !response.status().is_success()
+            || !shareable(response.headers())
+            || StreamedResponse::is_streamed(&response)
+        {
+            return response;
+        }
+        let (parts, body) = response.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(never) => match never {},
+        };
+        if body.len() <= MAX_CAPTURED_BODY {
+            let mut headers = parts.headers.clone();
+            headers.remove(header::SET_COOKIE);
+            self.entries.insert(
+                key,
+                Snapshot {
+                    status: parts.status,
+                    headers,
+                    body: body.clone(),
+                    captured_at: Instant::now(),
+                },
+            );
+        }
+        Response::from_parts(parts, Full::new(body))
+    }
+
+    /// The response captured under `key`, if it is younger than `max_age`.
+    pub fn get(&self, key: &str, max_age: Duration) -> Option<Response<Full<Bytes>>> {
+        let snapshot = self.entries.get(key)?;
+        if snapshot.captured_at.elapsed() > max_age {
+            self.entries.invalidate(key);
+            return None;
+        }
+        let mut response = Response::new(Full::new(snapshot.body));
+        *response.status_mut() = snapshot.status;
+        *response.headers_mut() = snapshot.headers;
+        Some(response)
+    }
+}
+
+/// Whether a response may be replayed to other clients: not marked
+/// `Cache-Control: private` or `no-store`.
+fn shareable(headers: &HeaderMap) -> bool {
+    !headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.split('=').next().unwrap_or("").trim())
+        .any(|directive| {
+            directive.eq_ignore_ascii_case("private") || directive.eq_ignore_ascii_case("no-store")
+        })
+}
+
+/// Build the fallback response for `fallback`.
+///
+/// `last_good` is consulted only for [`RouteFallback::LastGoodCache`]; `None`
+/// from it (nothing captured yet) yields `None`, so the caller returns the
+/// usual error.
+pub fn fallback_response(
+    fallback: &RouteFallback,
+    last_good: impl FnOnce(Duration) -> Option<Response<Full<Bytes>>>,
+) -> Option<Response<Full<Bytes>>> {
+    let mut response = match fallback {
+        RouteFallback::StaticJson { status, body } => Response::builder()
+            .status(*status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body.clone())))
+            .ok()?,
+        RouteFallback::Status(status) if status.is_client_error() || status.is_server_error() => {
+            ErrorResponse::new(*status, "upstream_unavailable")
+                .detail("Upstream unavailable")
+                .into_response()
+        }
+        RouteFallback::Status(status) => {
+            let mut response = Response::new(Full::new(Bytes::new()));
+            *response.status_mut() = *status;
+            response
+        }
+        RouteFallback::Redirect(url) => ResponseBuilder::redirect(StatusCode::FOUND, url).ok()?,
+        RouteFallback::LastGoodCache { max_age } => last_good(*max_age)?,
+    };
+    response
+        .headers_mut()
+        .insert(FALLBACK_HEADER, HeaderValue::from_static(fallback.kind()));
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_string(response: Response<Full<Bytes>>) -> String {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn ok_response(body: &'static str) -> Response<Full<Bytes>> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from_static(body.as_bytes())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn static_json_fallback_replaces_unavailable_upstream() {
+        let fallback = RouteFallback::StaticJson {
+            status: StatusCode::OK,
+            body: r#"{"items":[],"degraded":true}"#.to_string(),
+        };
+
+        let response = fallback_response(&fallback, |_| unreachable!()).unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[FALLBACK_HEADER], "static_json");
+        assert_eq!(
+            body_string(response).await,
+            r#"{"items":[],"degraded":true}"#
+        );
+    }
+
+    #[test]
+    fn status_and_redirect_fallbacks() {
+        let response =
+            fallback_response(&RouteFallback::Status(StatusCode::NO_CONTENT), |_| None).unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[FALLBACK_HEADER], "status");
+
+        let response = fallback_response(
+            &RouteFallback::Redirect("https://status.example.com".to_string()),
+            |_| None,
+        )
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://status.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn last_good_cache_replays_captured_get() {
+        let cache = LastGoodCache::default();
+        let uri: Uri = "/api/catalog?page=1".parse().unwrap();
+        let key =
+            LastGoodCache::key(&Method::GET, "shop.example.com", &uri, &HeaderMap::new()).unwrap();
+        let fallback = RouteFallback::LastGoodCache {
+            max_age: Duration::from_secs(60),
+        };
+
+        // Nothing captured yet: no fallback, the caller returns the error.
+        assert!(fallback_response(&fallback, |age| cache.get(&key, age)).is_none());
+
+        let passed_through = cache.capture(key.clone(), ok_response(r#"{"v":1}"#)).await;
+        assert_eq!(body_string(passed_through).await, r#"{"v":1}"#);
+
+        let response = fallback_response(&fallback, |age| cache.get(&key, age)).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_HEADER], "last_good_cache");
+        assert_eq!(body_string(response).await, r#"{"v":1}"#);
+
+        // Expired entries are not served.
+        assert!(cache.get(&key, Duration::ZERO).is_none());
+    }
+
+    #[tokio::test]
+    async fn last_good_cache_ignores_errors_and_non_get() {
+        let cache = LastGoodCache::default();
+        let uri: Uri = "/api/catalog".parse().unwrap();
+        let anonymous = HeaderMap::new();
+        assert!(LastGoodCache::key(&Method::POST, "shop.example.com", &uri, &anonymous).is_none());
+
+        let key = LastGoodCache::key(&Method::GET, "shop.example.com", &uri, &anonymous).unwrap();
+        let mut error = ok_response("oops");
+        *error.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        cache.capture(key.clone(), error).await;
+        assert!(cache.get(&key, Duration::from_secs(60)).is_none());
+    }
+
+    #[test]
+    fn last_good_cache_skips_requests_with_credentials() {
+        let uri: Uri = "/api/me".parse().unwrap();
+        for name in [header::AUTHORIZATION, header::COOKIE] {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static("secret"));
+            assert!(LastGoodCache::key(&Method::GET, "shop.example.com", &uri, &headers).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn last_good_cache_keeps_only_shareable_responses() {
+        let cache = LastGoodCache::default();
+        let max_age = Duration::from_secs(60);
+
+        for directive in ["private", "no-store", "max-age=0, Private"] {
+            let mut response = ok_response("mine");
+            response
+                .headers_mut()
+                .insert(header::CACHE_CONTROL, HeaderValue::from_static(directive));
+            cache.capture(directive.to_string(), response).await;
+            assert!(cache.get(directive, max_age).is_none(), "{directive}");
+        }
+
+        let mut response = ok_response("public");
+        response
+            .headers_mut()
+            .insert(header::SET_COOKIE, HeaderValue::from_static("session=abc"));
+        let passed_through = cache.capture("public".to_string(), response).await;
+        assert_eq!(passed_through.headers()[header::SET_COOKIE], "session=abc");
+        let replayed = cache.get("public", max_age).unwrap();
+        assert!(!replayed.headers().contains_key(header::SET_COOKIE));
+    }
+}
@@ -0,0 +1,107 @@
+//! Body schemas from the FARP-federated OpenAPI spec.
+//!
+//! [`FarpSchemaResolver`] backs `request_validation` rules with
+//! `from_farp: true` and dev-mode `response_validation`: it parses the
+//! federated OpenAPI document once per federation update and hands the body
+//! schemas of the matching operation to the validation middleware.
+//! [`ActivityLogSink`] records the response schema violations in the admin
+//! activity log.
+
+use octopus_farp::{SchemaFederation, SchemaFormat};
+use octopus_metrics::{ActivityEntry, ActivityLog};
+use octopus_middleware::{
+    AuditEvent, AuditHandler, DeclaredResponse, OpenApiSchemas, ResolvedSchema, SchemaResolver,
+};
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// [`SchemaResolver`] over the federated OpenAPI spec
+#[derive(Debug)]
+pub struct FarpSchemaResolver {
+    federation: Arc<SchemaFederation>,
+    /// Operations of the last parsed spec
+    parsed: RwLock<Option<Arc<OpenApiSchemas>>>,
+}
+
+impl FarpSchemaResolver {
+    /// Create a resolver reading from `federation`
+    pub fn new(federation: Arc<SchemaFederation>) -> Self {
+        Self {
+            federation,
+            parsed: RwLock::new(None),
+        }
+    }
+
+    /// The operations of the current federated spec, re-parsing only when
+    /// the federation has been rebuilt
+    fn schemas(&self) -> Option<Arc<OpenApiSchemas>> {
+        let version = version_of(self.federation.updated_at(&SchemaFormat::OpenApi)?);
+        if let Some(schemas) = &*self.parsed.read() {
+            if schemas.version() == version {
+                return Some(Arc::clone(schemas));
+            }
+        }
+
+        let federated = self.federation.get_federated(&SchemaFormat::OpenApi).ok()?;
+        let version = version_of(federated.updated_at);
+        let schemas = match serde_json::from_str(&federated.content) {
+            Ok(doc) => Arc::new(OpenApiSchemas::from_document(&doc, version)),
+            Err(e) => {
+                tracing::warn!(error = %e, "Federated OpenAPI spec is not JSON; schema validation skipped");
+                Arc::new(OpenApiSchemas::from_document(
+                    &serde_json::Value::Null,
+                    version,
+                ))
+            }
+        };
+        tracing::debug!(
+            operations = schemas.len(),
+            "Loaded body schemas from federated OpenAPI spec"
+        );
+        *self.parsed.write() = Some(Arc::clone(&schemas));
+        Some(schemas)
+    }
+}
+
+impl SchemaResolver for FarpSchemaResolver {
+    fn resolve(&self, method: &http::Method, path: &str) -> Option<ResolvedSchema> {
+        self.schemas()?.find(method, path)
+    }
+
+    fn resolve_response(
+        &self,
+        method: &http::Method,
+        path: &str,
+        status: http::StatusCode,
+    ) -> Option<DeclaredResponse> {
+        self.schemas()?.find_response(method, path, status)
+    }
+}
+
+fn version_of(updated_at: SystemTime) -> u64 {
+    updated_at
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Records schema violations as activity log entries
+#[derive(Debug)]
+pub struct ActivityLogSink(pub Arc<ActivityLog>);
+
+impl AuditHandler for ActivityLogSink {
+    fn log(&self, event: &AuditEvent) {
+        let method = http::Method::from_bytes(event.method.as_bytes()).unwrap_or_default();
+        let status = http::StatusCode::from_u16(event.status_code).unwrap_or_default();
+        let entry = ActivityEntry::new(
+            method,
+            event.path.clone(),
+            status,
+            Duration::ZERO,
+            "schema-violation".to_string(),
+        )
+        .with_note(event.details.clone().unwrap_or_default());
+        self.0.add_entry(entry);
+    }
+}
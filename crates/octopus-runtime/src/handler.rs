@@ -1,41 +1,63 @@
 //! HTTP request handler
 
 use crate::admin::AdminHandler;
+use crate::events::{AuthFailureMonitor, EventBus, GatewayEvent};
+use crate::fallback::{self, LastGoodCache};
+use crate::intake::{self, IntakeError};
+use crate::internal_redirect::{self, InternalRedirect};
 use crate::lifecycle::LifecycleState;
+use crate::multipart::{self, MultipartBody, MultipartLimits, StreamedBody};
 use crate::probes::{self, ProbeRoutes};
 use crate::redirect::RedirectRewrite;
+use crate::unmatched::UnmatchedPolicy;
 use arc_swap::ArcSwap;
 use bytes::Bytes;
 use http::{Request, Response, StatusCode};
+use http_body_util::combinators::BoxBody;
 use http_body_util::{BodyExt, Either, Full};
-use hyper::body::Incoming;
-use octopus_core::{middleware::Middleware, Error, Result, UpstreamCluster, UpstreamInstance};
+use hyper::body::{Body as _, Incoming};
+use octopus_core::{
+    middleware::Middleware, Error, ErrorResponse, Result, StreamEvents, StreamedResponse,
+    UpstreamCluster, UpstreamInstance,
+};
 use octopus_farp::FarpApiHandler;
 use octopus_health::{CircuitBreaker, HealthTracker};
 use octopus_metrics::{ActivityLog, MetricsCollector, RequestOutcome};
 use octopus_plugin_runtime::PluginManager;
-use octopus_protocols::ProtocolHandler;
+use octopus_protocols::{
+    EventTransform, ProtocolDispatcher, ProtocolType, RestGraphQLMapper, RestGraphQLMatch,
+    StreamFormat, TransformedStream,
+};
 use octopus_proxy::HttpProxy;
 use octopus_router::{
-    gateway_scoped_upstream, BackendStrategy, Convention, ConventionTarget, PathRewrite, Route,
-    Router, VirtualGatewayIndex,
+    gateway_scoped_upstream, normalize_path, BackendStrategy, Convention, ConventionTarget,
+    EncodedSlash, PathRewrite, Route, RouteFallback, Router, VirtualGatewayIndex,
 };
+use std::borrow::Cow;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tracing::{debug, error, info, warn};
 
-/// Body type — Left for buffered, Right for streaming (SSE / chunked)
-pub type Body = Either<Full<Bytes>, Incoming>;
+/// Body type — Left for buffered, Right for streaming (SSE, event-stream
+/// responses and chunked)
+///
+/// The Right variant is a boxed body rather than hyper's `Incoming`, so a
+/// streamed response can be transformed on its way to the client; code that
+/// matched on it as `Incoming` needs to treat it as any `http_body::Body`.
+pub type Body = Either<Full<Bytes>, BoxBody<Bytes, hyper::Error>>;
 
 /// Create a buffered body from data
 fn buffered(data: impl Into<Bytes>) -> Body {
     Either::Left(Full::new(data.into()))
 }
 
-/// Create a streaming body from an Incoming response
-fn streaming(incoming: Incoming) -> Body {
-    Either::Right(incoming)
+/// Create a streaming body from an upstream response body
+fn streaming<B>(body: B) -> Body
+where
+    B: hyper::body::Body<Data = Bytes, Error = hyper::Error> + Send + Sync + 'static,
+{
+    Either::Right(BoxBody::new(body))
 }
 
 /// Process-wide Rhai engine for convention host-resolution scripts. Shared so
@@ -60,6 +82,32 @@ fn new_resolve_cache() -> moka::sync::Cache<String, ConventionTarget> {
 #[derive(Debug, Clone, Copy)]
 pub struct ClientAddr(pub std::net::SocketAddr);
 
+/// Whether the inbound connection was accepted over TLS, injected into request
+/// extensions alongside [`ClientAddr`].
+#[derive(Debug, Clone, Copy)]
+pub struct ClientTls(pub bool);
+
+/// Which endpoints a handler serves, for a gateway with separate data and
+/// admin listeners (`admin.listen`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Plane {
+    /// Everything on one listener
+    #[default]
+    All,
+    /// Proxied traffic only: admin, metrics and FARP paths are routed like
+    /// any other request
+    Data,
+    /// Admin, metrics and FARP only; anything else is `404`
+    Admin,
+}
+
+impl Plane {
+    /// Whether the admin dashboard, metrics and FARP endpoints are served
+    fn serves_control_plane(self) -> bool {
+        self != Self::Data
+    }
+}
+
 /// Whether a client IP may reach the admin surface. An empty allowlist permits
 /// all; otherwise the client IP must be known and match one of the patterns.
 fn admin_ip_allowed(
@@ -84,7 +132,7 @@ pub struct RequestHandler {
     admin_handler: AdminHandler,
     middleware_chain: Arc<[Arc<dyn Middleware>]>,
     farp_handler: Option<Arc<FarpApiHandler>>,
-    protocol_handlers: Arc<[Arc<dyn ProtocolHandler>]>,
+    protocols: ProtocolDispatcher,
     metrics_collector: Arc<MetricsCollector>,
     activity_log: Arc<ActivityLog>,
     /// Active WebSocket connection count for graceful shutdown coordination
@@ -97,10 +145,32 @@ pub struct RequestHandler {
     admin_auth_provider: Option<String>,
     /// Admin IP allowlist (empty = all allowed); parsed IP/CIDR/range patterns.
     admin_allowed_ips: Vec<octopus_middleware::IpPattern>,
+    /// Forwarded-header rewriting applied on ingress.
+    forwarded: octopus_middleware::ForwardedHeaders,
+    /// gRPC service/method mapping and reflection routing.
+    grpc: Arc<octopus_protocols::GrpcHandler>,
+    /// REST routes served by a GraphQL upstream (`graphql.rest_mappings`).
+    rest_graphql: Arc<RestGraphQLMapper>,
+    /// Per-route body and header transforms, keyed by route method and path.
+    route_transforms: Arc<
+        std::collections::HashMap<
+            (http::Method, String),
+            (
+                octopus_middleware::MatchedRouteTransform,
+                Option<octopus_middleware::MatchedRouteHeaders>,
+            ),
+        >,
+    >,
+    /// Gateway event bus (plugin crashes, auth failure spikes).
+    events: EventBus,
+    /// Counts 401/403 responses toward `auth_failure_spike` events.
+    auth_failures: Arc<AuthFailureMonitor>,
     /// Lifecycle state backing the health probes (None = probes disabled).
     lifecycle: Option<LifecycleState>,
     /// Resolved probe endpoint paths.
     probe_routes: ProbeRoutes,
+    /// Last successful responses for routes with a `last_good_cache` fallback.
+    last_good: LastGoodCache,
     /// Whether to reject requests where `Host`/`:authority` disagrees with the
     /// negotiated TLS SNI (anti host-spoofing). Default `true`.
     enforce_sni_check: bool,
@@ -109,10 +179,30 @@ pub struct RequestHandler {
     /// Keeps EndpointSlice-backed convention upstreams' pod instances live
     /// (`None` = no Kubernetes watcher; convention falls back to Service DNS).
     backend_watcher: Option<Arc<dyn octopus_core::BackendWatcher>>,
+    /// Resolves the instances of routes naming a `service` (`None` = no
+    /// discovery; such routes fail with no healthy upstream).
+    upstream_resolver: Option<Arc<dyn octopus_core::UpstreamResolver>>,
     /// Shared virtual gateway index (lock-free `load`), used to resolve a request's
     /// gateway by host — e.g. to answer gateway-level CORS preflight when no route
     /// matches. Empty unless wired from the k8s operator via [`Self::set_gateway_index`].
     gateway_index: Arc<ArcSwap<VirtualGatewayIndex>>,
+    /// Request path normalization before routing (`None` = disabled); the value
+    /// decides how an encoded slash (`%2F`) is handled.
+    path_normalization: Option<EncodedSlash>,
+    /// Largest request body buffered, in bytes (`None` = unlimited). Larger
+    /// bodies, declared or streamed, are rejected with `413`.
+    max_body_size: Option<usize>,
+    /// Limits on `multipart/form-data` bodies, which replace `max_body_size`
+    /// for them (`None` = treated like any other body)
+    multipart: Option<MultipartLimits>,
+    /// Internal redirects upstreams may request (`None` = disabled)
+    internal_redirect: Option<InternalRedirect>,
+    /// Handling of requests no route matches
+    unmatched: UnmatchedPolicy,
+    /// Tenant resolution ahead of routing (`None` = single-tenant).
+    tenancy: Option<Arc<octopus_middleware::TenantResolver>>,
+    /// Endpoints served on the listener this handler belongs to.
+    plane: Plane,
 }
 
 /// Join a rewrite `prefix` onto the already prefix-stripped `rest` of a request
@@ -145,7 +235,7 @@ impl std::fmt::Debug for RequestHandler {
         f.debug_struct("RequestHandler")
             .field("request_count", &self.request_count)
             .field("middleware_count", &self.middleware_chain.len())
-            .field("protocol_handlers_count", &self.protocol_handlers.len())
+            .field("protocol_handlers_count", &self.protocols.len())
             .finish()
     }
 }
@@ -169,7 +259,7 @@ impl RequestHandler {
             admin_handler,
             middleware_chain: Arc::new([]), // Empty chain by default
             farp_handler: None,
-            protocol_handlers: Arc::new([]),
+            protocols: ProtocolDispatcher::default(),
             metrics_collector,
             activity_log,
             ws_active_count: Arc::new(AtomicUsize::new(0)),
@@ -177,12 +267,27 @@ impl RequestHandler {
             auth_registry: None,
             admin_auth_provider: None,
             admin_allowed_ips: Vec::new(),
+            forwarded: octopus_middleware::ForwardedHeaders::default(),
+            grpc: Arc::new(octopus_protocols::GrpcHandler::new()),
+            rest_graphql: Arc::new(RestGraphQLMapper::default()),
+            route_transforms: Arc::default(),
+            events: EventBus::default(),
+            auth_failures: Arc::default(),
             lifecycle: None,
             probe_routes: ProbeRoutes::default(),
+            last_good: LastGoodCache::default(),
             enforce_sni_check: true,
             resolve_cache: new_resolve_cache(),
             gateway_index: Arc::new(ArcSwap::from_pointee(VirtualGatewayIndex::default())),
             backend_watcher: None,
+            upstream_resolver: None,
+            path_normalization: Some(EncodedSlash::default()),
+            max_body_size: None,
+            multipart: None,
+            internal_redirect: None,
+            unmatched: UnmatchedPolicy::NotFound,
+            tenancy: None,
+            plane: Plane::All,
         }
     }
 
@@ -193,7 +298,7 @@ impl RequestHandler {
         request_count: Arc<AtomicUsize>,
         middleware_chain: Arc<[Arc<dyn Middleware>]>,
         farp_handler: Option<Arc<FarpApiHandler>>,
-        protocol_handlers: Arc<[Arc<dyn ProtocolHandler>]>,
+        protocols: ProtocolDispatcher,
     ) -> Self {
         let metrics_collector = Arc::new(MetricsCollector::new());
         let activity_log = Arc::new(ActivityLog::default());
@@ -212,6 +317,7 @@ impl RequestHandler {
             farp_federation,
             None, // config
         );
+        admin_handler.set_middleware(&middleware_chain);
 
         Self {
             router,
@@ -220,7 +326,7 @@ impl RequestHandler {
             admin_handler,
             middleware_chain,
             farp_handler,
-            protocol_handlers,
+            protocols,
             metrics_collector,
             activity_log,
             ws_active_count: Arc::new(AtomicUsize::new(0)),
@@ -228,12 +334,27 @@ impl RequestHandler {
             auth_registry: None,
             admin_auth_provider: None,
             admin_allowed_ips: Vec::new(),
+            forwarded: octopus_middleware::ForwardedHeaders::default(),
+            grpc: Arc::new(octopus_protocols::GrpcHandler::new()),
+            rest_graphql: Arc::new(RestGraphQLMapper::default()),
+            route_transforms: Arc::default(),
+            events: EventBus::default(),
+            auth_failures: Arc::default(),
             lifecycle: None,
             probe_routes: ProbeRoutes::default(),
+            last_good: LastGoodCache::default(),
             enforce_sni_check: true,
             resolve_cache: new_resolve_cache(),
             gateway_index: Arc::new(ArcSwap::from_pointee(VirtualGatewayIndex::default())),
             backend_watcher: None,
+            upstream_resolver: None,
+            path_normalization: Some(EncodedSlash::default()),
+            max_body_size: None,
+            multipart: None,
+            internal_redirect: None,
+            unmatched: UnmatchedPolicy::NotFound,
+            tenancy: None,
+            plane: Plane::All,
         }
     }
 
@@ -245,7 +366,7 @@ impl RequestHandler {
         request_count: Arc<AtomicUsize>,
         middleware_chain: Arc<[Arc<dyn Middleware>]>,
         farp_handler: Option<Arc<FarpApiHandler>>,
-        protocol_handlers: Arc<[Arc<dyn ProtocolHandler>]>,
+        protocols: ProtocolDispatcher,
         health_tracker: Option<Arc<HealthTracker>>,
         circuit_breaker: Option<Arc<CircuitBreaker>>,
         plugin_manager: Option<Arc<PluginManager>>,
@@ -267,6 +388,7 @@ impl RequestHandler {
             farp_federation,
             config,
         );
+        admin_handler.set_middleware(&middleware_chain);
 
         Self {
             router,
@@ -275,7 +397,7 @@ impl RequestHandler {
             admin_handler,
             middleware_chain,
             farp_handler,
-            protocol_handlers,
+            protocols,
             metrics_collector,
             activity_log,
             ws_active_count: Arc::new(AtomicUsize::new(0)),
@@ -283,12 +405,27 @@ impl RequestHandler {
             auth_registry: None,
             admin_auth_provider: None,
             admin_allowed_ips: Vec::new(),
+            forwarded: octopus_middleware::ForwardedHeaders::default(),
+            grpc: Arc::new(octopus_protocols::GrpcHandler::new()),
+            rest_graphql: Arc::new(RestGraphQLMapper::default()),
+            route_transforms: Arc::default(),
+            events: EventBus::default(),
+            auth_failures: Arc::default(),
             lifecycle: None,
             probe_routes: ProbeRoutes::default(),
+            last_good: LastGoodCache::default(),
             enforce_sni_check: true,
             resolve_cache: new_resolve_cache(),
             gateway_index: Arc::new(ArcSwap::from_pointee(VirtualGatewayIndex::default())),
             backend_watcher: None,
+            upstream_resolver: None,
+            path_normalization: Some(EncodedSlash::default()),
+            max_body_size: None,
+            multipart: None,
+            internal_redirect: None,
+            unmatched: UnmatchedPolicy::NotFound,
+            tenancy: None,
+            plane: Plane::All,
         }
     }
 
@@ -303,6 +440,7 @@ impl RequestHandler {
         let activity_log = Arc::new(ActivityLog::default());
 
         let admin_handler = AdminHandler::new(Arc::clone(&router), Arc::clone(&request_count));
+        admin_handler.set_middleware(&middleware_chain);
 
         Self {
             router,
@@ -311,7 +449,7 @@ impl RequestHandler {
             admin_handler,
             middleware_chain,
             farp_handler: None,
-            protocol_handlers: Arc::new([]),
+            protocols: ProtocolDispatcher::default(),
             metrics_collector,
             activity_log,
             ws_active_count: Arc::new(AtomicUsize::new(0)),
@@ -319,12 +457,27 @@ impl RequestHandler {
             auth_registry: None,
             admin_auth_provider: None,
             admin_allowed_ips: Vec::new(),
+            forwarded: octopus_middleware::ForwardedHeaders::default(),
+            grpc: Arc::new(octopus_protocols::GrpcHandler::new()),
+            rest_graphql: Arc::new(RestGraphQLMapper::default()),
+            route_transforms: Arc::default(),
+            events: EventBus::default(),
+            auth_failures: Arc::default(),
             lifecycle: None,
             probe_routes: ProbeRoutes::default(),
+            last_good: LastGoodCache::default(),
             enforce_sni_check: true,
             resolve_cache: new_resolve_cache(),
             gateway_index: Arc::new(ArcSwap::from_pointee(VirtualGatewayIndex::default())),
             backend_watcher: None,
+            upstream_resolver: None,
+            path_normalization: Some(EncodedSlash::default()),
+            max_body_size: None,
+            multipart: None,
+            internal_redirect: None,
+            unmatched: UnmatchedPolicy::NotFound,
+            tenancy: None,
+            plane: Plane::All,
         }
     }
 
@@ -354,6 +507,152 @@ impl RequestHandler {
             .collect();
     }
 
+    /// Configure forwarded-header handling; invalid `trusted_proxies` entries
+    /// are logged and ignored.
+    pub fn set_forwarded(&mut self, config: &octopus_config::types::ForwardedHeadersConfig) {
+        let trusted_proxies = config
+            .trusted_proxies
+            .iter()
+            .filter_map(|s| match octopus_middleware::IpPattern::parse(s) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    tracing::warn!(pattern = %s, error = %e, "Ignoring invalid trusted_proxies entry");
+                    None
+                }
+            })
+            .collect();
+        self.forwarded =
+            octopus_middleware::ForwardedHeaders::new(octopus_middleware::ForwardedConfig {
+                enabled: config.enabled,
+                trusted_proxies,
+                emit_forwarded: config.emit_forwarded,
+            });
+    }
+
+    /// Configure gRPC routing from the `grpc` config section.
+    pub fn set_grpc(&mut self, config: &octopus_config::types::GrpcConfig) {
+        self.grpc = Arc::new(octopus_protocols::GrpcHandler::from_config(config));
+    }
+
+    /// Configure per-route JSON body, header, cookie and query transforms from
+    /// `routes[].transform`.
+    pub fn set_route_transforms(&mut self, routes: &[octopus_config::types::RouteConfig]) {
+        let transforms = routes
+            .iter()
+            .filter_map(|route| route.transform.as_ref().map(|t| (route, t)))
+            .flat_map(|(route, transform)| {
+                let headers = transform
+                    .has_non_body_rules()
+                    .then(|| octopus_middleware::MatchedRouteHeaders::from(transform));
+                let transform = (
+                    octopus_middleware::MatchedRouteTransform::from(transform),
+                    headers,
+                );
+                route.methods.iter().filter_map(move |method| {
+                    let method = method.parse().ok()?;
+                    Some(((method, route.path.clone()), transform.clone()))
+                })
+            })
+            .collect();
+        self.route_transforms = Arc::new(transforms);
+    }
+
+    /// Publish plugin crashes and auth failure spikes on `events`.
+    pub fn set_events(&mut self, events: EventBus, config: &octopus_config::types::EventsConfig) {
+        self.events = events;
+        self.auth_failures = Arc::new(AuthFailureMonitor::from_config(config));
+    }
+
+    /// Emit events for what the middleware chain reported: plugin failures
+    /// and auth rejections (counted toward a spike).
+    fn observe_chain_result(&self, result: &Result<Response<octopus_core::middleware::Body>>) {
+        let auth_failure = match result {
+            Ok(res) => matches!(
+                res.status(),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+            ),
+            Err(Error::Authentication(_) | Error::Authorization(_)) => true,
+            Err(Error::Plugin { plugin, message }) => {
+                self.events.emit(GatewayEvent::PluginCrashed {
+                    plugin: plugin.clone(),
+                    message: message.clone(),
+                });
+                false
+            }
+            Err(_) => false,
+        };
+        if auth_failure {
+            if let Some(spike) = self.auth_failures.record() {
+                warn!("Authentication failure spike detected");
+                self.events.emit(spike);
+            }
+        }
+    }
+
+    /// Configure REST routes that are translated into GraphQL operations.
+    pub fn set_rest_graphql(&mut self, config: &octopus_config::types::GraphQLConfig) {
+        self.rest_graphql = Arc::new(RestGraphQLMapper::from_config(&config.rest_mappings));
+    }
+
+    /// Serve the debug tap's captures from the admin API
+    pub fn set_debug_tap(&self, log: Arc<octopus_metrics::DebugTapLog>) {
+        self.admin_handler.set_debug_tap(log);
+    }
+
+    /// Report shared state stores in the admin health view
+    pub fn set_state_backends(&self, backends: Vec<crate::lifecycle::StateBackendCheck>) {
+        self.admin_handler.set_state_backends(backends);
+    }
+
+    /// Let the admin API inspect and override a rate limiter's per-key state:
+    /// the server installs its `routes[].rate_limit` limiter; embedders can
+    /// pass `octopus_middleware::RateLimit::keys` instead
+    pub fn set_rate_limits(&self, keys: Arc<dyn octopus_core::RateLimitKeys>) {
+        self.admin_handler.set_rate_limits(keys);
+    }
+
+    /// Apply the configured maintenance mode settings, failing on an invalid
+    /// bypass IP. The admin API can change them afterwards at runtime.
+    pub fn set_maintenance(&self, settings: &octopus_core::MaintenanceSettings) -> Result<()> {
+        self.admin_handler.maintenance().set(settings.clone())
+    }
+
+    /// The maintenance 503 for a request to `path` from `client_ip`, or `None`
+    /// if the request proceeds. Probe, metrics, admin and FARP endpoints are
+    /// never put in maintenance.
+    fn maintenance_response(
+        &self,
+        path: &str,
+        client_ip: Option<std::net::IpAddr>,
+    ) -> Option<Response<Full<Bytes>>> {
+        let maintenance = self.admin_handler.maintenance();
+        if !maintenance.is_enabled() {
+            return None;
+        }
+        let control_plane = self.probe_routes.matches(path)
+            || (self.plane.serves_control_plane()
+                && (path == "/metrics"
+                    || path == "/__metrics"
+                    || path.starts_with("/admin")
+                    || path.starts_with("/__admin")
+                    || path.starts_with("/_farp/v1")
+                    || path.starts_with("/__/farp")
+                    || path.starts_with("/__farp")));
+        if control_plane {
+            return None;
+        }
+
+        let settings = maintenance.settings();
+        if !settings.covers(path) {
+            return None;
+        }
+        if client_ip.is_some_and(|ip| maintenance.bypasses(&ip)) {
+            debug!(path = %path, client = ?client_ip, "Maintenance bypassed by client IP");
+            return None;
+        }
+        Some(settings.response())
+    }
+
     /// Enable Kubernetes-style health probe endpoints (`/livez`, `/readyz`,
     /// `/startupz`) backed by the given lifecycle state.
     pub fn set_lifecycle(&mut self, lifecycle: LifecycleState, probe_routes: ProbeRoutes) {
@@ -416,6 +715,181 @@ impl RequestHandler {
         self.backend_watcher = Some(watcher);
     }
 
+    /// Install the resolver for routes that name a discovery `service`
+    /// instead of an upstream.
+    pub fn set_upstream_resolver(&mut self, resolver: Arc<dyn octopus_core::UpstreamResolver>) {
+        self.upstream_resolver = Some(resolver);
+    }
+
+    /// Configure request path normalization (`None` disables it). Normalization
+    /// runs before the admin/internal prefix checks, auth and routing.
+    pub fn set_path_normalization(&mut self, encoded_slash: Option<EncodedSlash>) {
+        self.path_normalization = encoded_slash;
+    }
+
+    /// Limit buffered request bodies to `limit` bytes (`None` = unlimited).
+    pub fn set_max_body_size(&mut self, limit: Option<usize>) {
+        self.max_body_size = limit;
+    }
+
+    /// Configure `multipart/form-data` limits (`None` disables them).
+    pub fn set_multipart(&mut self, config: Option<&octopus_config::types::MultipartConfig>) {
+        self.multipart = config.map(MultipartLimits::new);
+    }
+
+    /// Configure internal redirects (`None` disables them).
+    pub fn set_internal_redirect(
+        &mut self,
+        config: Option<&octopus_config::types::InternalRedirectConfig>,
+    ) {
+        self.internal_redirect = config.and_then(InternalRedirect::new);
+    }
+
+    /// Set how requests that match no route are answered.
+    pub fn set_unmatched(&mut self, policy: UnmatchedPolicy) {
+        self.unmatched = policy;
+    }
+
+    /// Serve only the endpoints of `plane` (default: everything).
+    pub fn set_plane(&mut self, plane: Plane) {
+        self.plane = plane;
+    }
+
+    /// The FARP handler, if this listener serves FARP.
+    fn farp(&self) -> Option<&Arc<FarpApiHandler>> {
+        self.farp_handler
+            .as_ref()
+            .filter(|_| self.plane.serves_control_plane())
+    }
+
+    /// Paths the gateway answers itself on the admin listener: the admin
+    /// dashboard and API, metrics and FARP.
+    fn is_control_plane_path(path: &str) -> bool {
+        matches!(path, "/metrics" | "/__metrics")
+            || path.starts_with("/admin")
+            || path.starts_with("/__admin")
+            || Self::is_farp_path(path)
+    }
+
+    /// Resolve each data-plane request's tenant from `gateway.tenancy`.
+    pub fn set_tenancy(&mut self, config: &octopus_config::types::TenancyConfig) -> Result<()> {
+        self.tenancy = Some(Arc::new(octopus_middleware::TenantResolver::new(config)?));
+        Ok(())
+    }
+
+    /// Apply the tenancy to a data-plane request, returning the rejection for
+    /// an unknown or missing tenant. The gateway's own FARP and docs
+    /// endpoints belong to no tenant.
+    fn resolve_tenant<B>(&self, req: &mut Request<B>) -> Option<Response<Full<Bytes>>> {
+        let tenancy = self.tenancy.as_ref()?;
+        if self.farp().is_some() && Self::is_farp_path(req.uri().path()) {
+            return None;
+        }
+        let host = Self::request_host(req);
+        tenancy.apply(req, &host)
+    }
+
+    /// Fails when `req`'s body was parked as a [`StreamedBody`], which only
+    /// the proxy can send: `target` would see an empty body.
+    fn ensure_buffered<B>(req: &Request<B>, target: &str) -> Result<()> {
+        if req.extensions().get::<StreamedBody>().is_some() {
+            return Err(Error::InvalidRequest(format!(
+                "{target} does not accept streamed multipart uploads"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Resolve a claim tenant from the claims auth verified for the request
+    /// (see [`TenantResolver::apply_verified`]), returning the rejection for
+    /// an unknown or missing tenant.
+    ///
+    /// [`TenantResolver::apply_verified`]: octopus_middleware::TenantResolver::apply_verified
+    fn resolve_verified_tenant<B>(&self, req: &mut Request<B>) -> Option<Response<Full<Bytes>>> {
+        self.tenancy.as_ref()?.apply_verified(req)
+    }
+
+    /// Paths answered by the FARP handler rather than proxied.
+    fn is_farp_path(path: &str) -> bool {
+        ["/_farp/v1", "/__/farp", "/__farp", "/farp"]
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+            || matches!(path, "/swagger" | "/docs" | "/redoc")
+    }
+
+    /// The upstream of the request's tenant when it has its own, else
+    /// `upstream_key`.
+    fn tenant_upstream<B>(req: &Request<B>, upstream_key: String) -> String {
+        match req
+            .extensions()
+            .get::<octopus_middleware::ResolvedTenant>()
+            .and_then(|tenant| tenant.upstream.clone())
+        {
+            Some(upstream) => {
+                debug!(upstream = %upstream, "Routing to tenant upstream");
+                upstream
+            }
+            None => upstream_key,
+        }
+    }
+
+    /// Whether `upstream` has a healthy instance whose circuit breaker would
+    /// let a request through
+    fn upstream_available(&self, upstream: &str) -> bool {
+        let breaker = self.proxy.circuit_breaker();
+        self.router
+            .has_healthy_instance(upstream, |instance| breaker.admits(&instance.id))
+    }
+
+    /// Log and count a failover from `upstream` to `fallback`
+    fn record_failover(&self, upstream: &str, fallback: &str) {
+        warn!(
+            upstream = %upstream,
+            fallback = %fallback,
+            "Upstream unavailable, failing over"
+        );
+        self.metrics_collector.record_failover(upstream, fallback);
+    }
+
+    /// Pick an instance of `upstream`, passing over instances that answered
+    /// with a `Retry-After` pause or whose circuit breaker turns requests
+    /// away while others are left
+    fn select_instance(&self, upstream: &str) -> Result<UpstreamInstance> {
+        let throttle = self.proxy.throttle();
+        let breaker = self.proxy.circuit_breaker();
+        self.router.select_instance_avoiding(upstream, "", |i| {
+            throttle.is_throttled(&i.id) || !breaker.admits(&i.id)
+        })
+    }
+
+    /// A copy of `req`, to send to a fallback upstream if the one it was
+    /// sent to turns it away
+    fn copy_request(req: &Request<Full<Bytes>>) -> Request<Full<Bytes>> {
+        let mut copy = Request::new(req.body().clone());
+        *copy.method_mut() = req.method().clone();
+        *copy.uri_mut() = req.uri().clone();
+        *copy.version_mut() = req.version();
+        *copy.headers_mut() = req.headers().clone();
+        *copy.extensions_mut() = req.extensions().clone();
+        copy
+    }
+
+    /// Replace the request path, keeping the scheme, authority and query.
+    fn set_request_path<B>(req: &mut Request<B>, path: &str) {
+        let query = req
+            .uri()
+            .query()
+            .map(|q| format!("?{q}"))
+            .unwrap_or_default();
+        let mut parts = req.uri().clone().into_parts();
+        if let Ok(path_and_query) = format!("{path}{query}").parse() {
+            parts.path_and_query = Some(path_and_query);
+            if let Ok(uri) = http::Uri::from_parts(parts) {
+                *req.uri_mut() = uri;
+            }
+        }
+    }
+
     /// Wire the shared virtual gateway index (from the k8s operator) so the handler
     /// can resolve a request's gateway by host for gateway-level behavior.
     pub fn set_gateway_index(&mut self, index: Arc<ArcSwap<VirtualGatewayIndex>>) {
@@ -504,6 +978,26 @@ impl RequestHandler {
         key.to_string()
     }
 
+    /// Resolve a route's discovery `service` to its current instances and
+    /// sync them into the upstream cluster for it, returning the cluster key.
+    /// The resolver caches lookups; the cluster is only rebuilt when the
+    /// instance set changes.
+    async fn resolve_service_upstream(&self, service: &str) -> Result<String> {
+        let Some(resolver) = &self.upstream_resolver else {
+            warn!(service, "No upstream resolver for route service");
+            return Err(Error::NoHealthyUpstream);
+        };
+        let instances = resolver.resolve(service).await.map_err(|e| {
+            warn!(service, error = %e, "Service resolution failed");
+            Error::NoHealthyUpstream
+        })?;
+        let key = format!("__service__:{service}");
+        if self.router.sync_upstream(&key, instances) {
+            debug!(service, upstream = %key, "Service instances changed");
+        }
+        Ok(key)
+    }
+
     /// Resolve the upstream cluster name and any path rewrite for a matched route.
     ///
     /// For convention routes the `{namespace, service}` target is derived from the
@@ -526,6 +1020,10 @@ impl RequestHandler {
             return Ok((key, None));
         }
 
+        if let Some(service) = &route.service {
+            return Ok((self.resolve_service_upstream(service).await?, None));
+        }
+
         let Some(conv) = &route.convention else {
             return Ok((route.upstream_name.clone(), None));
         };
@@ -645,14 +1143,21 @@ impl RequestHandler {
     }
 
     /// Handle an incoming HTTP request (from Hyper with Incoming body)
-    pub async fn handle(&self, req: Request<Incoming>) -> Result<Response<Body>> {
+    pub async fn handle(&self, mut req: Request<Incoming>) -> Result<Response<Body>> {
         // Health probes are answered before request accounting so a readiness
         // poll during drain never inflates the in-flight counter or holds up
         // graceful shutdown.
         if let Some(ref lifecycle) = self.lifecycle {
-            if let Some(resp) =
-                probes::handle_probe(lifecycle, &self.probe_routes, req.uri().path())
-            {
+            // With no upstreams registered (e.g. FARP-only) there is nothing
+            // to require, so the upstream check passes.
+            let healthy_upstream =
+                || self.router.upstream_count() == 0 || self.router.has_healthy_upstream();
+            if let Some(resp) = probes::handle_probe(
+                lifecycle,
+                &self.probe_routes,
+                req.uri().path(),
+                healthy_upstream,
+            ) {
                 return Ok(resp.map(Either::Left));
             }
         }
@@ -662,13 +1167,15 @@ impl RequestHandler {
         // request accounting so scrapes don't skew gateway request metrics.
         {
             let req_path = req.uri().path();
-            if req_path == "/metrics" || req_path == "/__metrics" {
+            if self.plane.serves_control_plane()
+                && (req_path == "/metrics" || req_path == "/__metrics")
+            {
                 let method = req.method().clone();
                 let headers = req.headers().clone();
                 let req_path = req_path.to_string();
                 return self
                     .admin_handler
-                    .handle(&method, &req_path, headers, Bytes::new())
+                    .handle(&method, &req_path, headers, Bytes::new(), None)
                     .await
                     .map(|r| r.map(Either::Left));
             }
@@ -676,6 +1183,59 @@ impl RequestHandler {
 
         // Increment request counter
         self.request_count.fetch_add(1, Ordering::Relaxed);
+        let received = Instant::now();
+
+        // Canonicalize the path before anything inspects it, so the internal
+        // prefix checks, auth and routing all see the same path (no `//admin`
+        // or `/x/../admin` spellings slipping past path-based rules).
+        if let Some(encoded_slash) = self.path_normalization {
+            match normalize_path(req.uri().path(), encoded_slash) {
+                Ok(Cow::Borrowed(_)) => {}
+                Ok(Cow::Owned(normalized)) => Self::set_request_path(&mut req, &normalized),
+                Err(e) => {
+                    warn!(path = %req.uri().path(), error = %e, "Rejecting request with invalid path");
+                    return Ok(ErrorResponse::from(&e).into_response().map(Either::Left));
+                }
+            }
+        }
+
+        // The admin listener serves nothing but the gateway's own endpoints.
+        if self.plane == Plane::Admin && !Self::is_control_plane_path(req.uri().path()) {
+            let e = Error::RouteNotFound(format!(
+                "{} is not served on the admin listener",
+                req.uri().path()
+            ));
+            return Ok(ErrorResponse::from(&e).into_response().map(Either::Left));
+        }
+
+        // Drop client-supplied headers the gateway owns (e.g. `X-Auth-*`)
+        // before auth and middleware can trust them.
+        self.proxy.strip_client_headers(req.headers_mut());
+
+        // Set X-Forwarded-* from the connection, keeping the incoming chain
+        // only when the peer is a trusted proxy.
+        if let Some(ClientAddr(peer)) = req.extensions().get::<ClientAddr>().copied() {
+            let tls = req.extensions().get::<ClientTls>().is_some_and(|t| t.0);
+            self.forwarded.apply(req.headers_mut(), peer.ip(), tls);
+            let client = self.forwarded.client_ip(req.headers(), peer.ip());
+            req.extensions_mut()
+                .insert(octopus_middleware::ClientIp(client));
+        }
+
+        // Answer unsupported expectations and oversized declared bodies
+        // before reading, so a client waiting on `100 Continue` never sends
+        // a body we would reject.
+        let multipart = self
+            .multipart
+            .as_ref()
+            .and_then(|limits| Some((limits, multipart::boundary(req.headers())?)));
+        let body_limit = match &multipart {
+            Some((limits, _)) => Some(limits.max_total()),
+            None => self.max_body_size,
+        };
+        if let Some(resp) = intake::check_request(&req, body_limit) {
+            return Ok(resp.map(Either::Left));
+        }
 
         let method = req.method().clone();
         let path = req.uri().path().to_string();
@@ -688,7 +1248,9 @@ impl RequestHandler {
 
         // Handle internal API routes (built-in, not proxied)
         // Internal routes use __ prefix by default
-        if path.starts_with("/__admin") || path.starts_with("/admin") {
+        if self.plane.serves_control_plane()
+            && (path.starts_with("/__admin") || path.starts_with("/admin"))
+        {
             // Enforce the admin IP allowlist (empty = all allowed) before auth.
             if !self.admin_allowed_ips.is_empty() {
                 let client_ip = req.extensions().get::<ClientAddr>().map(|c| c.0.ip());
@@ -702,6 +1264,7 @@ impl RequestHandler {
             }
 
             // Check admin auth if configured
+            let mut admin_operator = None;
             if let (Some(ref registry), Some(ref provider_name)) =
                 (&self.auth_registry, &self.admin_auth_provider)
             {
@@ -719,21 +1282,19 @@ impl RequestHandler {
                         tls_client_cn: tls_cn.as_deref(),
                     };
                     match registry.authenticate(provider_name, &auth_req).await {
-                        Ok(octopus_auth::AuthResult::Authenticated(_)) => {
-                            // Proceed
+                        Ok(octopus_auth::AuthResult::Authenticated(principal)) => {
+                            admin_operator = Some(principal.id);
                         }
                         Ok(octopus_auth::AuthResult::Unauthenticated)
                         | Ok(octopus_auth::AuthResult::Failed(_)) => {
-                            let body = serde_json::json!({
-                                "error": "unauthorized",
-                                "message": "Admin authentication required"
-                            });
-                            return Ok(Response::builder()
-                                .status(StatusCode::UNAUTHORIZED)
-                                .header("Content-Type", "application/json")
-                                .header("WWW-Authenticate", "Bearer")
-                                .body(buffered(serde_json::to_vec(&body).unwrap_or_default()))
-                                .unwrap());
+                            return Ok(ErrorResponse::new(
+                                StatusCode::UNAUTHORIZED,
+                                "unauthorized",
+                            )
+                            .detail("Admin authentication required")
+                            .header(http::header::WWW_AUTHENTICATE, "Bearer")
+                            .into_response()
+                            .map(Either::Left));
                         }
                         Err(_) => {
                             return Ok(Response::builder()
@@ -758,61 +1319,115 @@ impl RequestHandler {
                 Some(q) => format!("{admin_path}?{q}"),
                 None => admin_path,
             };
-            let admin_body = match req.into_body().collect().await {
-                Ok(collected) => collected.to_bytes(),
-                Err(e) => {
-                    return Ok(Response::builder()
-                        .status(StatusCode::BAD_REQUEST)
-                        .body(buffered(format!("Failed to read admin request body: {e}")))
-                        .unwrap());
+            let admin_body = match intake::read_body(req.into_body(), self.max_body_size).await {
+                Ok(body) => body,
+                Err(IntakeError::TooLarge(limit)) => {
+                    return Ok(intake::too_large(limit).map(Either::Left));
+                }
+                Err(IntakeError::Read(e)) => {
+                    warn!(error = %e, "Failed to read admin request body");
+                    return Ok(ErrorResponse::from(&Error::InvalidRequest(format!(
+                        "Failed to read request body: {e}"
+                    )))
+                    .into_response()
+                    .map(Either::Left));
                 }
             };
             return self
                 .admin_handler
-                .handle(&method, &admin_target, admin_headers, admin_body)
+                .handle(
+                    &method,
+                    &admin_target,
+                    admin_headers,
+                    admin_body,
+                    admin_operator,
+                )
                 .await
                 .map(|r| r.map(Either::Left));
         }
 
-        // ── WebSocket upgrade ─────────────────────────────────────────
-        // Must intercept BEFORE body buffering so the hyper OnUpgrade
-        // extension is still in the request.
-        if octopus_protocols::is_websocket_upgrade(&req) {
-            return self.handle_websocket_upgrade(req).await;
+        // ── Maintenance mode ──────────────────────────────────────────
+        // Short-circuits every data-plane protocol before proxying.
+        let client_ip = req.extensions().get::<ClientAddr>().map(|c| c.0.ip());
+        if let Some(resp) = self.maintenance_response(&path, client_ip) {
+            return Ok(resp.map(Either::Left));
         }
 
-        // ── SSE streaming proxy ──────────────────────────────────────
-        // Must intercept BEFORE body buffering so we can stream the response.
-        if req
-            .headers()
-            .get(http::header::ACCEPT)
-            .and_then(|v| v.to_str().ok())
-            .is_some_and(|v| v.contains("text/event-stream"))
-        {
-            return self.handle_sse_proxy(req).await;
+        // ── Tenant resolution ─────────────────────────────────────────
+        // Ahead of protocol dispatch and routing, so every protocol sees the
+        // tenant and routes match the path without a tenant prefix.
+        if let Some(resp) = self.resolve_tenant(&mut req) {
+            return Ok(resp.map(Either::Left));
         }
+        let path = req.uri().path().to_string();
 
-        // ── gRPC proxy ────────────────────────────────────────────────
-        // Must intercept BEFORE body buffering to support streaming RPCs.
-        // gRPC over HTTP/2 streams request/response bodies.
-        if octopus_protocols::GrpcHandler::is_grpc_request_raw(&req) {
-            return self.handle_grpc_proxy(req).await;
+        // ── Streaming protocol dispatch ───────────────────────────────
+        // Must intercept BEFORE body buffering: a WebSocket upgrade needs the
+        // hyper OnUpgrade extension still in the request, and SSE and gRPC
+        // stream their request/response bodies.
+        let streaming = self
+            .protocols
+            .select(&req)
+            .filter(|handler| handler.streaming())
+            .map(|handler| handler.protocol_type());
+        // Streamed protocols skip the middleware chain, so no auth verifies
+        // a claim tenant for them.
+        if streaming.is_some() {
+            if let Some(resp) = self.resolve_verified_tenant(&mut req) {
+                return Ok(resp.map(Either::Left));
+            }
+        }
+        match streaming {
+            Some(ProtocolType::WebSocket) => return self.handle_websocket_upgrade(req).await,
+            Some(ProtocolType::Sse) => return self.handle_sse_proxy(req).await,
+            Some(ProtocolType::Grpc) => return self.handle_grpc_proxy(req).await,
+            _ => {}
         }
 
-        // Convert Incoming body to Full<Bytes>
-        let (parts, body) = req.into_parts();
-        let body_bytes = body
-            .collect()
-            .await
-            .map_err(|e| Error::InvalidRequest(format!("Failed to read request body: {e}")))?
-            .to_bytes();
+        // Multipart uploads are checked as they are read. A streamed one
+        // runs the chain with an empty body and is sent by the proxy.
+        let (mut parts, body) = req.into_parts();
+        let body_bytes = if let Some((limits, boundary)) = multipart {
+            let body = MultipartBody::new(body, &boundary, limits.clone());
+            if limits.stream {
+                parts.extensions.insert(StreamedBody::new(body));
+                Bytes::new()
+            } else {
+                let violation = body.violation();
+                match body.collect().await {
+                    Ok(collected) => collected.to_bytes(),
+                    Err(e) => match violation.get() {
+                        Some(violation) => return Ok(violation.response().map(Either::Left)),
+                        None => {
+                            return Err(Error::InvalidRequest(format!(
+                                "Failed to read request body: {e}"
+                            )));
+                        }
+                    },
+                }
+            }
+        } else {
+            // Convert Incoming body to Full<Bytes>
+            match intake::read_body(body, self.max_body_size).await {
+                Ok(body) => body,
+                Err(IntakeError::TooLarge(limit)) => {
+                    return Ok(intake::too_large(limit).map(Either::Left));
+                }
+                Err(IntakeError::Read(e)) => {
+                    return Err(Error::InvalidRequest(format!(
+                        "Failed to read request body: {e}"
+                    )));
+                }
+            }
+        };
         let mut req = Request::from_parts(parts, Full::new(body_bytes));
 
         // Handle FARP v1 push protocol routes (/_farp/v1/*)
         // Per FARP spec: /_farp/v1/register, /_farp/v1/heartbeat/{id}, etc.
         if path.starts_with("/_farp/v1") {
-            if let Some(farp_handler) = &self.farp_handler {
+            if let Some(farp_handler) = self.farp() {
                 debug!("Routing to FARP handler (v1 push protocol)");
+                Self::ensure_buffered(&req, "FARP")?;
                 let internal_path = path.replacen("/_farp/v1", "/farp", 1);
                 let (parts, body) = req.into_parts();
                 let mut builder = http::Request::builder()
@@ -838,8 +1453,9 @@ impl RequestHandler {
         // Handle internal FARP API routes (with __ prefix)
         // Support both /__farp and /__/farp patterns
         if path.starts_with("/__/farp") || path.starts_with("/__farp") {
-            if let Some(farp_handler) = &self.farp_handler {
+            if let Some(farp_handler) = self.farp() {
                 debug!("Routing to FARP handler (internal)");
+                Self::ensure_buffered(&req, "FARP")?;
                 // Remove __ prefix before passing to FARP handler
                 let internal_path = if path.starts_with("/__/farp") {
                     path.replacen("/__/farp", "/farp", 1)
@@ -870,16 +1486,18 @@ impl RequestHandler {
 
         // Also support legacy /farp paths for backwards compatibility
         if path.starts_with("/farp") {
-            if let Some(farp_handler) = &self.farp_handler {
+            if let Some(farp_handler) = self.farp() {
                 debug!("Routing to FARP handler");
+                Self::ensure_buffered(&req, "FARP")?;
                 return farp_handler.handle(req).await.map(|r| r.map(Either::Left));
             }
         }
 
         // Root-level documentation routes (/swagger, /docs, /redoc)
         if path == "/swagger" || path == "/docs" || path == "/redoc" {
-            if let Some(farp_handler) = &self.farp_handler {
+            if let Some(farp_handler) = self.farp() {
                 debug!("Routing to FARP docs handler");
+                Self::ensure_buffered(&req, "FARP")?;
                 let internal_path = if path == "/redoc" {
                     "/farp/redoc".to_string()
                 } else {
@@ -906,15 +1524,14 @@ impl RequestHandler {
             }
         }
 
-        // Check protocol handlers (WebSocket, gRPC, GraphQL)
-        for handler in self.protocol_handlers.iter() {
-            if handler.can_handle(&req) {
-                debug!(
-                    protocol = %handler.protocol_type(),
-                    "Routing to protocol handler"
-                );
-                return handler.handle(req).await.map(|r| r.map(Either::Left));
-            }
+        // Buffered protocol handlers; unmatched requests fall back to HTTP proxying
+        if let Some(handler) = self.protocols.select(&req).filter(|h| !h.streaming()) {
+            debug!(
+                protocol = %handler.protocol_type(),
+                "Routing to protocol handler"
+            );
+            Self::ensure_buffered(&req, "This protocol")?;
+            return handler.handle(req).await.map(|r| r.map(Either::Left));
         }
 
         let host = Self::request_host(&req);
@@ -938,10 +1555,41 @@ impl RequestHandler {
         }
 
         // Pre-match route to inject auth context into extensions for auth middleware
-        if let Ok(route) = self
+        if let Ok(matched) = self
             .router
-            .find_route(&host, req.method(), req.uri().path())
+            .match_route(&host, req.method(), req.uri().path())
         {
+            // Trailing-slash canonicalization: send the client to the route's
+            // canonical form before any middleware (auth included) runs.
+            if let Some(ref canonical) = matched.redirect_to {
+                debug!(path = %path, location = %canonical, "Redirecting to canonical path");
+                return Self::canonical_redirect(canonical, req.uri().query());
+            }
+            req.extensions_mut()
+                .insert(octopus_core::PathParams(matched.params));
+            let route = matched.route;
+
+            // One upstream time budget for the whole request, counted from
+            // receipt and shared by every retry.
+            let budget = route
+                .timeout
+                .unwrap_or_else(|| self.proxy.client().timeout());
+            req.extensions_mut()
+                .insert(octopus_core::Deadline::at(received + budget));
+            // Routes without a limit get the proxy's `max_response_body_size`.
+            if let Some(limit) = route.response_body_limit {
+                req.extensions_mut().insert(limit);
+            }
+
+            // Route identity for logging and other route-aware layers.
+            req.extensions_mut()
+                .insert(octopus_core::request::RouteInfo {
+                    path: route.path.clone(),
+                    method: route.method.to_string(),
+                    operation_id: None,
+                    tags: Vec::new(),
+                });
+
             req.extensions_mut()
                 .insert(octopus_middleware::MatchedRouteAuth {
                     auth_provider: route.auth_provider.clone(),
@@ -972,15 +1620,39 @@ impl RequestHandler {
                     });
             }
 
-            // Inject the per-route rate limit (keyed by the route's path pattern)
-            // so the route-aware rate limiter can enforce it.
+            // Inject the route's body transforms for the BodyTransform layer
+            // and its header, cookie and query rules for the HeaderTransform layer.
+            if !self.route_transforms.is_empty() {
+                if let Some((transform, headers)) = self
+                    .route_transforms
+                    .get(&(route.method.clone(), route.path.clone()))
+                {
+                    req.extensions_mut().insert(transform.clone());
+                    if let Some(headers) = headers {
+                        req.extensions_mut().insert(headers.clone());
+                    }
+                }
+            }
+
+            // Inject the per-route rate limit (keyed by the route's path pattern
+            // and the client behind any trusted proxies) so the route-aware rate
+            // limiter can enforce it.
             if let Some((requests_per_window, window_size)) = route.rate_limit {
+                let client = req.extensions().get::<ClientAddr>().map_or_else(
+                    || "unknown".to_string(),
+                    |ClientAddr(peer)| {
+                        self.forwarded
+                            .client_ip(req.headers(), peer.ip())
+                            .to_string()
+                    },
+                );
                 req.extensions_mut()
                     .insert(octopus_middleware::MatchedRouteRateLimit {
                         key: Self::gateway_scoped_rate_limit_key(
                             route.gateway_id.as_deref(),
                             &route.path,
                         ),
+                        client,
                         requests_per_window,
                         window_size,
                     });
@@ -1005,6 +1677,44 @@ impl RequestHandler {
             }
         }
 
+        // An event-stream answer streams to the client whatever was asked for
+        req.extensions_mut().insert(StreamEvents);
+        self.handle_buffered(req).await.map(Self::client_response)
+    }
+
+    /// The client response for one from the buffered path: its upstream
+    /// event stream if the proxy left one unread, else its buffered body
+    fn client_response(response: Response<Full<Bytes>>) -> Response<Body> {
+        let (mut parts, body) = response.into_parts();
+        match parts
+            .extensions
+            .remove::<StreamedResponse>()
+            .and_then(|streamed| streamed.take())
+        {
+            Some(stream) => Response::from_parts(parts, Either::Right(stream)),
+            None => Response::from_parts(parts, Either::Left(body)),
+        }
+    }
+
+    /// Run a buffered request through the middleware chain (if any) and the
+    /// proxy, counting its request and response body bytes for the route.
+    ///
+    /// The response size is taken after the chain, so a compressed response
+    /// is counted at its compressed size.
+    async fn handle_buffered(&self, req: Request<Full<Bytes>>) -> Result<Response<Full<Bytes>>> {
+        let path = req.uri().path().to_string();
+        let request_bytes = req.body().size_hint().exact().unwrap_or(0);
+
+        let result = self.run_chain(req).await;
+        if let Ok(response) = &result {
+            let response_bytes = response.body().size_hint().exact().unwrap_or(0);
+            self.metrics_collector
+                .record_bytes(&path, request_bytes, response_bytes);
+        }
+        result
+    }
+
+    async fn run_chain(&self, req: Request<Full<Bytes>>) -> Result<Response<Full<Bytes>>> {
         // Execute middleware chain if configured
         if !self.middleware_chain.is_empty() {
             debug!(
@@ -1032,13 +1742,13 @@ impl RequestHandler {
                 Arc::clone(&self.middleware_chain),
                 final_handler,
             );
-            return next.run(req).await.map(|r| r.map(Either::Left));
+            let result = next.run(req).await;
+            self.observe_chain_result(&result);
+            return result;
         }
 
         // No middleware, handle directly
-        self.handle_proxy_request(req)
-            .await
-            .map(|r| r.map(Either::Left))
+        self.handle_proxy_request(req).await
     }
 
     /// Handle WebSocket upgrade requests.
@@ -1048,7 +1758,8 @@ impl RequestHandler {
     /// Flow:
     /// 1. Route match → select upstream instance
     /// 2. Build forwarded headers (X-Forwarded-For, Origin, Cookie, etc.)
-    /// 3. **Connect to upstream WS first** (with timeout) — fail fast with 502 if unreachable
+    /// 3. **Connect to upstream WS first** (with timeout) — fail fast with 502 if unreachable,
+    ///    or pass on the upstream's response if it refuses the upgrade
     /// 4. Only on success → build 101 response, extract OnUpgrade
     /// 5. Spawn background proxy task with already-connected upstream
     /// 6. Return 101 to client
@@ -1069,6 +1780,7 @@ impl RequestHandler {
         let (upstream_key, conv_rewrite) = self
             .resolve_upstream_with_path(&route, &host, &path)
             .await?;
+        let upstream_key = Self::tenant_upstream(&req, upstream_key);
         let instance = self.router.select_instance(&upstream_key).map_err(|e| {
             tracing::error!(upstream = %upstream_key, error = %e, "No upstream for WebSocket");
             Error::NoHealthyUpstream
@@ -1085,18 +1797,27 @@ impl RequestHandler {
         // 2. Build forwarded headers from client request
         let forwarded_headers = octopus_protocols::build_forwarded_headers(&req);
 
-        // 3. Connect to upstream FIRST — fail fast with 502 if unreachable
+        // 3. Connect to upstream FIRST — fail fast with 502 if unreachable;
+        // an upstream that answers without upgrading (401, 404, ...) is
+        // passed through as it answered
         let config = octopus_protocols::WebSocketConfig::default();
-        let upstream_stream = octopus_protocols::connect_upstream(
+        let upstream_stream = match octopus_protocols::connect_upstream(
             &upstream_url,
             &forwarded_headers,
             &config,
         )
         .await
-        .map_err(|e| {
-            tracing::error!(upstream = %upstream_url, error = %e, "Upstream WebSocket connect failed");
-            Error::UpstreamConnection(e)
-        })?;
+        {
+            Ok(stream) => stream,
+            Err(e) => {
+                if let Some(response) = e.upstream_response() {
+                    tracing::warn!(upstream = %upstream_url, status = %response.status(), "Upstream refused the WebSocket upgrade");
+                    return Ok(response.map(Either::Left));
+                }
+                tracing::error!(upstream = %upstream_url, error = %e, "Upstream WebSocket connect failed");
+                return Err(e);
+            }
+        };
 
         // 4. Upstream connected — now build 101 response (validates handshake)
         let response =
@@ -1211,6 +1932,7 @@ impl RequestHandler {
         let (upstream_key, conv_rewrite) = self
             .resolve_upstream_with_path(&route, &host, &path)
             .await?;
+        let upstream_key = Self::tenant_upstream(&req, upstream_key);
         let instance = self.router.select_instance(&upstream_key).map_err(|e| {
             tracing::error!(upstream = %upstream_key, error = %e, "No upstream for SSE");
             Error::NoHealthyUpstream
@@ -1227,11 +1949,17 @@ impl RequestHandler {
         let (parts, body) = req.into_parts();
 
         // Collect the incoming body for forwarding (SSE request bodies are typically small)
-        let body_bytes = body
-            .collect()
-            .await
-            .map_err(|e| Error::Internal(format!("Failed to read SSE request body: {e}")))?
-            .to_bytes();
+        let body_bytes = match intake::read_body(body, self.max_body_size).await {
+            Ok(body) => body,
+            Err(IntakeError::TooLarge(limit)) => {
+                return Ok(intake::too_large(limit).map(Either::Left));
+            }
+            Err(IntakeError::Read(e)) => {
+                return Err(Error::Internal(format!(
+                    "Failed to read SSE request body: {e}"
+                )));
+            }
+        };
 
         let mut upstream_builder = http::Request::builder()
             .method(&parts.method)
@@ -1296,7 +2024,22 @@ impl RequestHandler {
         let _start = Instant::now();
 
         // Build response — forward upstream headers including Retry
-        let (resp_parts, upstream_body) = upstream_resp.into_parts();
+        let (mut resp_parts, upstream_body) = upstream_resp.into_parts();
+
+        // The route's JSON response rules apply to each event as it streams
+        let body = match (
+            self.event_transform(&route),
+            StreamFormat::from_headers(&resp_parts.headers),
+        ) {
+            (Some((events, max_event_size)), Some(format)) => {
+                resp_parts.headers.remove(http::header::CONTENT_LENGTH);
+                streaming(
+                    TransformedStream::new(upstream_body, format, events)
+                        .max_event_size(max_event_size),
+                )
+            }
+            _ => streaming(upstream_body),
+        };
 
         // Spawn cleanup task that fires when the streaming body is dropped
         // (i.e., when client disconnects or upstream ends)
@@ -1318,7 +2061,7 @@ impl RequestHandler {
         });
 
         // Return response with streaming body and SSE-appropriate headers
-        let mut response = Response::from_parts(resp_parts, streaming(upstream_body));
+        let mut response = Response::from_parts(resp_parts, body);
 
         // Ensure SSE headers are set even if upstream didn't set them
         let headers = response.headers_mut();
@@ -1406,15 +2149,24 @@ impl RequestHandler {
 
         debug!(service = %service, method = %rpc_method, "Routing gRPC request");
 
-        // Route to upstream — first check explicit gRPC services map, then fall back to router
-        let route = self.router.find_route(&host, &method, &path).map_err(|e| {
-            warn!(service = %service, error = %e, "No route for gRPC service");
-            Error::RouteNotFound(format!("No route for gRPC service: {service}"))
-        })?;
-
-        let (upstream_key, conv_rewrite) = self
-            .resolve_upstream_with_path(&route, &host, &path)
-            .await?;
+        // Route to upstream: the `grpc.services` map (method entries, then
+        // service entries, then reflection) wins over the HTTP router. Mapped
+        // calls keep their path verbatim.
+        let (route, upstream_key, upstream_path) = match self.grpc.resolve_path(&path) {
+            Some(upstream) => (None, upstream.to_string(), path.clone()),
+            None => {
+                let route = self.router.find_route(&host, &method, &path).map_err(|e| {
+                    warn!(service = %service, error = %e, "No route for gRPC service");
+                    Error::RouteNotFound(format!("No route for gRPC service: {service}"))
+                })?;
+                let (upstream_key, conv_rewrite) = self
+                    .resolve_upstream_with_path(&route, &host, &path)
+                    .await?;
+                let upstream_key = Self::tenant_upstream(&req, upstream_key);
+                let upstream_path = Self::compute_upstream_path(&route, &path, &conv_rewrite);
+                (Some(route), upstream_key, upstream_path)
+            }
+        };
         let instance = self.router.select_instance(&upstream_key).map_err(|e| {
             error!(upstream = %upstream_key, error = %e, "No upstream for gRPC");
             Error::NoHealthyUpstream
@@ -1422,7 +2174,6 @@ impl RequestHandler {
 
         // Build upstream URL
         let upstream_base = instance.base_url();
-        let upstream_path = Self::compute_upstream_path(&route, &path, &conv_rewrite);
 
         // Parse deadline from grpc-timeout header
         let deadline = req
@@ -1432,18 +2183,37 @@ impl RequestHandler {
             .and_then(octopus_protocols::GrpcHandler::parse_grpc_timeout);
 
         // Build upstream gRPC headers
-        let upstream_headers = octopus_protocols::grpc::build_grpc_upstream_headers(req.headers());
+        let mut upstream_headers =
+            octopus_protocols::grpc::build_grpc_upstream_headers(req.headers());
 
         // Decompose the request — keep the streaming body
         let (_parts, body) = req.into_parts();
 
         // Collect body for HTTP/2 send (gRPC unary messages are small; streaming will need
         // a different approach but this works for the common unary case)
-        let body_bytes = body
-            .collect()
-            .await
-            .map_err(|e| Error::InvalidRequest(format!("Failed to read gRPC body: {e}")))?
-            .to_bytes();
+        let body_bytes = match intake::read_body(body, self.max_body_size).await {
+            Ok(body) => body,
+            Err(IntakeError::TooLarge(limit)) => {
+                return Ok(intake::too_large(limit).map(Either::Left));
+            }
+            Err(IntakeError::Read(e)) => {
+                return Err(Error::InvalidRequest(format!(
+                    "Failed to read gRPC body: {e}"
+                )));
+            }
+        };
+
+        // Messages are forwarded as they are (compressed ones included);
+        // only their encoding, framing and size are checked.
+        let checked = octopus_protocols::grpc::negotiate_grpc_encoding(&mut upstream_headers)
+            .and_then(|()| {
+                self.grpc
+                    .check_request_messages(&upstream_headers, &body_bytes)
+            });
+        if let Err(status) = checked {
+            warn!(service = %service, message = %status.message, "Rejected gRPC request");
+            return Ok(status.into_response()?.map(Either::Left));
+        }
 
         // Build the upstream request
         let upstream_uri: http::Uri = format!("{upstream_base}{upstream_path}")
@@ -1496,12 +2266,14 @@ impl RequestHandler {
                 let (mut parts, body) = resp.into_parts();
 
                 // Rewrite redirect headers for proxy-mode routes.
-                Self::apply_redirect_rewrite(
-                    &route,
-                    &host,
-                    Some(&format!("{}:{}", instance.address, instance.port)),
-                    &mut parts.headers,
-                );
+                if let Some(route) = &route {
+                    Self::apply_redirect_rewrite(
+                        route,
+                        &host,
+                        Some(&format!("{}:{}", instance.address, instance.port)),
+                        &mut parts.headers,
+                    );
+                }
 
                 let response = Response::from_parts(parts, streaming(body));
                 Ok(response)
@@ -1531,27 +2303,122 @@ impl RequestHandler {
     /// streaming (SSE) is handled separately before reaching here.
     async fn handle_proxy_request(
         &self,
-        mut req: Request<Full<Bytes>>,
+        req: Request<Full<Bytes>>,
     ) -> Result<Response<Full<Bytes>>> {
-        let start_time = Instant::now();
-        let method = req.method().clone();
-        let path = req.uri().path().to_string();
-        let host = Self::request_host(&req);
-
-        // Track active connections
-        self.metrics_collector.increment_active_connections();
+        let Some(redirect) = &self.internal_redirect else {
+            return self.proxy_routed(req).await;
+        };
 
-        // Find matching route
-        let route = match self.router.find_route(&host, &method, &path) {
-            Ok(route) => route,
-            Err(e) => {
-                let latency = start_time.elapsed();
+        // An upstream may answer with a path to fetch and return instead;
+        // the intermediate response never reaches the client.
+        let template = internal_redirect::template(&req);
+        let mut response = self.proxy_routed(req).await?;
+        for hop in 0..=redirect.max_hops {
+            let Some(target) = redirect.target(&response) else {
+                return Ok(response);
+            };
+            if hop == redirect.max_hops {
                 warn!(
-                    method = %method,
-                    path = %path,
-                    error = %e,
-                    "No route found"
+                    path = %template.uri().path(),
+                    max_hops = redirect.max_hops,
+                    "Too many internal redirects"
                 );
+                break;
+            }
+            let Some(req) = InternalRedirect::request(&template, target) else {
+                warn!(location = ?target, "Upstream requested an invalid internal redirect");
+                break;
+            };
+            debug!(location = %req.uri(), "Following internal redirect");
+            response = self.proxy_routed(req).await?;
+        }
+        self.error_response(
+            ErrorResponse::new(StatusCode::BAD_GATEWAY, "invalid_internal_redirect")
+                .detail("Upstream requested an internal redirect that could not be followed"),
+        )
+    }
+
+    /// The route's JSON response rules as a per-event transform, with the
+    /// largest event they apply to, if it has any
+    fn event_transform(&self, route: &Route) -> Option<(Arc<dyn EventTransform>, usize)> {
+        let (transform, _) = self
+            .route_transforms
+            .get(&(route.method.clone(), route.path.clone()))?;
+        if transform.response_rules.is_empty() {
+            return None;
+        }
+        let rules = transform.clone();
+        let events: Arc<dyn EventTransform> = Arc::new(move |event: &mut serde_json::Value| {
+            *event = rules.transform_response(event.take());
+            true
+        });
+        Some((events, transform.max_body_size))
+    }
+
+    /// Apply the route's response rules to each event of a streamed response
+    fn transform_streamed(&self, route: &Route, response: &mut Response<Full<Bytes>>) {
+        let Some(streamed) = response.extensions().get::<StreamedResponse>().cloned() else {
+            return;
+        };
+        let (Some((events, max_event_size)), Some(format)) = (
+            self.event_transform(route),
+            StreamFormat::from_headers(response.headers()),
+        ) else {
+            return;
+        };
+        response.headers_mut().remove(http::header::CONTENT_LENGTH);
+        streamed.map(|body| {
+            BoxBody::new(
+                TransformedStream::new(body, format, events).max_event_size(max_event_size),
+            )
+        });
+    }
+
+    /// Route `req` and proxy it to the route's upstream
+    async fn proxy_routed(&self, mut req: Request<Full<Bytes>>) -> Result<Response<Full<Bytes>>> {
+        // Auth has run: a claim tenant can be read now.
+        if let Some(response) = self.resolve_verified_tenant(&mut req) {
+            return Ok(response);
+        }
+        let start_time = Instant::now();
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        let host = Self::request_host(&req);
+
+        // REST routes mapped onto a GraphQL upstream bypass the router.
+        if let Some(matched) = self.rest_graphql.find(&method, &path) {
+            Self::ensure_buffered(&req, "A REST-to-GraphQL mapping")?;
+            return self.handle_rest_graphql(req, matched).await;
+        }
+
+        // Track active connections
+        self.metrics_collector.increment_active_connections();
+
+        // Find matching route; unmatched requests may go to a catch-all upstream
+        let found = self.router.find_route(&host, &method, &path);
+        let route = match found.or_else(|e| {
+            self.unmatched
+                .catch_all_route(&method)
+                .map(Arc::new)
+                .ok_or(e)
+        }) {
+            Ok(route) => route,
+            Err(e) => {
+                let latency = start_time.elapsed();
+                warn!(
+                    method = %method,
+                    path = %path,
+                    error = %e,
+                    "No route found"
+                );
+
+                let response = match self.unmatched.response(req.uri()) {
+                    Some(response) => response,
+                    None => self.error_response(
+                        ErrorResponse::from(&Error::RouteNotFound(path.clone()))
+                            .instance(path.clone()),
+                    )?,
+                };
 
                 // Record failed request
                 self.metrics_collector
@@ -1559,13 +2426,13 @@ impl RequestHandler {
                 self.activity_log.record(
                     method.clone(),
                     path.clone(),
-                    StatusCode::NOT_FOUND,
+                    response.status(),
                     latency,
                     "none".to_string(),
                 );
                 self.metrics_collector.decrement_active_connections();
 
-                return self.error_response(StatusCode::NOT_FOUND, "Route not found");
+                return Ok(response);
             }
         };
 
@@ -1574,11 +2441,47 @@ impl RequestHandler {
             "Route matched"
         );
 
+        // Key for replaying this response if the route falls back to its
+        // last good one (taken before path rewriting).
+        let last_good_key = match route.fallback {
+            Some(RouteFallback::LastGoodCache { .. }) => {
+                LastGoodCache::key(&method, &host, req.uri(), req.headers())
+            }
+            _ => None,
+        };
+
         // Get upstream instance (convention routes derive it from the host)
         let (upstream_key, conv_rewrite) = self
             .resolve_upstream_with_path(&route, &host, &path)
             .await?;
-        let instance = match self.router.select_instance(&upstream_key) {
+        // A region-specific upstream for the client's location (set by GeoIp)
+        // takes precedence.
+        let upstream_key = match req
+            .extensions()
+            .get::<octopus_middleware::ClientGeo>()
+            .and_then(|geo| route.geo_upstream(geo.keys()))
+        {
+            Some(geo_upstream) => {
+                debug!(upstream = %geo_upstream, "Routing to region-specific upstream");
+                geo_upstream.to_string()
+            }
+            None => upstream_key,
+        };
+        // A tenant with its own upstream gets all its traffic there.
+        let upstream_key = Self::tenant_upstream(&req, upstream_key);
+        // Fail over while the upstream has no instance to send to.
+        let failover = route
+            .fallback_upstream
+            .as_deref()
+            .filter(|_| !self.upstream_available(&upstream_key));
+        let (mut upstream_key, mut failover_from) = match failover {
+            Some(fallback) => {
+                self.record_failover(&upstream_key, fallback);
+                (fallback.to_string(), Some(upstream_key))
+            }
+            None => (upstream_key, None),
+        };
+        let mut instance = match self.select_instance(&upstream_key) {
             Ok(instance) => instance,
             Err(e) => {
                 let latency = start_time.elapsed();
@@ -1600,10 +2503,11 @@ impl RequestHandler {
                 );
                 self.metrics_collector.decrement_active_connections();
 
-                return self.error_response(
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    "No healthy upstream available",
-                );
+                if let Some(response) = self.route_fallback(&route, last_good_key.as_deref()) {
+                    return Ok(response);
+                }
+                return self
+                    .error_response(ErrorResponse::from(&Error::NoHealthyUpstream).instance(path));
             }
         };
 
@@ -1634,8 +2538,42 @@ impl RequestHandler {
             }
         }
 
-        // Proxy the request with retry support
-        let result = self.proxy.proxy_with_retry(req, &instance).await;
+        // Proxy the request with retry support; a streamed upload can only
+        // be sent once, and a body middleware put in its place would be lost.
+        let result = match req.extensions_mut().remove::<StreamedBody>() {
+            Some(_) if req.body().size_hint().exact() != Some(0) => Err(Error::Internal(
+                "Middleware replaced the body of a streamed upload".to_string(),
+            )),
+            Some(streamed) => streamed.proxy(&self.proxy, req, &instance).await,
+            None => {
+                // The breaker may still turn the request away, having let
+                // other requests take its last probe slots; keep a copy to
+                // fail over with.
+                let spare = route
+                    .fallback_upstream
+                    .as_deref()
+                    .filter(|_| failover_from.is_none())
+                    .map(|fallback| (fallback, Self::copy_request(&req)));
+                let result = self.proxy.proxy_with_retry(req, &instance).await;
+                match (result, spare) {
+                    (Err(Error::CircuitBreakerOpen(id)), Some((fallback, req))) => {
+                        match self.select_instance(fallback) {
+                            Ok(fallback_instance) => {
+                                self.record_failover(&upstream_key, fallback);
+                                failover_from = Some(std::mem::replace(
+                                    &mut upstream_key,
+                                    fallback.to_string(),
+                                ));
+                                instance = fallback_instance;
+                                self.proxy.proxy_with_retry(req, &instance).await
+                            }
+                            Err(_) => Err(Error::CircuitBreakerOpen(id)),
+                        }
+                    }
+                    (result, _) => result,
+                }
+            }
+        };
         let latency = start_time.elapsed();
 
         // Decrement active connections
@@ -1671,23 +2609,56 @@ impl RequestHandler {
 
                 // Rewrite redirect headers for proxy-mode routes before returning.
                 let mut response = response;
+                if let Some(selection) = response
+                    .extensions_mut()
+                    .get_mut::<octopus_core::UpstreamSelection>()
+                {
+                    selection.upstream = upstream_key.clone();
+                    selection.failover_from = failover_from;
+                }
                 Self::apply_redirect_rewrite(
                     &route,
                     &host,
                     Some(&format!("{}:{}", instance.address, instance.port)),
                     response.headers_mut(),
                 );
+                self.transform_streamed(&route, &mut response);
+
+                if let Some(key) = last_good_key {
+                    response = self.last_good.capture(key, response).await;
+                }
 
                 Ok(response)
             }
             Err(e) => {
+                // Transport failures answer from the error table (502 for
+                // refused/reset/DNS/TLS/truncated, 504 for a timeout); the
+                // details only go to the log.
+                let response = match &e {
+                    Error::DeadlineExceeded => {
+                        ErrorResponse::new(StatusCode::GATEWAY_TIMEOUT, "gateway_timeout")
+                            .detail("Upstream did not respond within the request deadline")
+                    }
+                    e if e.is_upstream_failure() => ErrorResponse::from(e),
+                    _ => ErrorResponse::new(StatusCode::BAD_GATEWAY, "upstream_error")
+                        .detail("Upstream error"),
+                };
+                let status = response.status();
+                let outcome = match e {
+                    Error::DeadlineExceeded | Error::UpstreamTimeout => RequestOutcome::Timeout,
+                    _ => RequestOutcome::Error,
+                };
+
                 // Record failed request
+                if e.is_upstream_failure() {
+                    self.metrics_collector.record_upstream_error(e.code());
+                }
                 self.metrics_collector
-                    .record_request(&path, latency, RequestOutcome::Error);
+                    .record_request(&path, latency, outcome);
                 self.activity_log.record(
                     method.clone(),
                     path.clone(),
-                    StatusCode::BAD_GATEWAY,
+                    status,
                     latency,
                     route.upstream_name.clone(),
                 );
@@ -1699,11 +2670,33 @@ impl RequestHandler {
                     latency_ms = %latency.as_millis(),
                     "Proxy error"
                 );
-                self.error_response(StatusCode::BAD_GATEWAY, "Upstream error")
+                if let Some(response) = self.route_fallback(&route, last_good_key.as_deref()) {
+                    return Ok(response);
+                }
+                self.error_response(response.instance(path.as_str()))
             }
         }
     }
 
+    /// The route's fallback response, if it has one (and, for
+    /// `last_good_cache`, a response was captured for `last_good_key`).
+    fn route_fallback(
+        &self,
+        route: &Route,
+        last_good_key: Option<&str>,
+    ) -> Option<Response<Full<Bytes>>> {
+        let kind = route.fallback.as_ref()?;
+        let response = fallback::fallback_response(kind, |max_age| {
+            self.last_good.get(last_good_key?, max_age)
+        })?;
+        warn!(
+            upstream = %route.upstream_name,
+            fallback = kind.kind(),
+            "Upstream unavailable, serving route fallback"
+        );
+        Some(response)
+    }
+
     /// Rewrite redirect-bearing response headers for proxy-mode routes.
     ///
     /// For routes with `proxy.rewrite_redirects == true` this re-adds the
@@ -1801,23 +2794,129 @@ impl RequestHandler {
         }
     }
 
-    /// Create a buffered error response
-    fn error_response(&self, status: StatusCode, message: &str) -> Result<Response<Full<Bytes>>> {
+    /// Build the `308 Permanent Redirect` for a request whose path differs from
+    /// its route's canonical trailing-slash form. The query string is carried
+    /// over, and 308 keeps the method and body, so the retried request is
+    /// otherwise identical.
+    fn canonical_redirect(path: &str, query: Option<&str>) -> Result<Response<Body>> {
+        let location = match query {
+            Some(q) => format!("{path}?{q}"),
+            None => path.to_string(),
+        };
         Response::builder()
-            .status(status)
-            .header("content-type", "text/plain")
-            .body(Full::new(Bytes::from(message.to_string())))
-            .map_err(|e| Error::Internal(format!("Failed to build error response: {e}")))
+            .status(StatusCode::PERMANENT_REDIRECT)
+            .header(http::header::LOCATION, location)
+            .body(buffered(Bytes::new()))
+            .map_err(|e| Error::Internal(format!("Failed to build redirect response: {e}")))
+    }
+
+    /// Serve a REST request from a GraphQL upstream: translate it into the
+    /// mapped operation, POST it to the upstream's GraphQL endpoint and
+    /// return the unwrapped `data`.
+    async fn handle_rest_graphql(
+        &self,
+        req: Request<Full<Bytes>>,
+        matched: RestGraphQLMatch<'_>,
+    ) -> Result<Response<Full<Bytes>>> {
+        let start_time = Instant::now();
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        let upstream = &matched.mapping.upstream;
+
+        let (mut parts, body) = req.into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(|e| Error::InvalidRequest(format!("Failed to read body: {e}")))?
+            .to_bytes();
+        let operation = match matched.build_request(parts.uri.query(), &body) {
+            Ok(operation) => operation,
+            Err(e) => return self.error_response(ErrorResponse::from(&e).instance(path)),
+        };
+        let payload = serde_json::to_vec(&operation)
+            .map_err(|e| Error::Internal(format!("Failed to serialize GraphQL request: {e}")))?;
+
+        let instance = match self.router.select_instance(upstream) {
+            Ok(instance) => instance,
+            Err(e) => {
+                error!(upstream = %upstream, error = %e, "No upstream for REST-to-GraphQL mapping");
+                return self
+                    .error_response(ErrorResponse::from(&Error::NoHealthyUpstream).instance(path));
+            }
+        };
+
+        parts.method = http::Method::POST;
+        parts.uri = matched
+            .mapping
+            .endpoint
+            .parse()
+            .map_err(|e| Error::Config(format!("Invalid GraphQL endpoint: {e}")))?;
+        parts.headers.remove(http::header::CONTENT_LENGTH);
+        parts.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/json"),
+        );
+        parts.headers.insert(
+            http::header::ACCEPT,
+            http::HeaderValue::from_static("application/json"),
+        );
+        let upstream_req = Request::from_parts(parts, Full::new(Bytes::from(payload)));
+
+        debug!(path = %path, upstream = %upstream, "Proxying REST request as GraphQL");
+        self.metrics_collector.increment_active_connections();
+        let result = self.proxy.proxy_with_retry(upstream_req, &instance).await;
+        self.metrics_collector.decrement_active_connections();
+
+        let response = match result {
+            Ok(response) => {
+                let (mut parts, body) = response.into_parts();
+                let body = body
+                    .collect()
+                    .await
+                    .map_err(|e| Error::Internal(format!("Failed to read upstream body: {e}")))?
+                    .to_bytes();
+                let (status, body) = matched.unwrap_response(parts.status, &body);
+                parts.status = status;
+                parts.headers.remove(http::header::CONTENT_LENGTH);
+                parts.headers.remove(http::header::CONTENT_ENCODING);
+                parts.headers.insert(
+                    http::header::CONTENT_TYPE,
+                    http::HeaderValue::from_static("application/json"),
+                );
+                Response::from_parts(parts, Full::new(body))
+            }
+            Err(e) => {
+                warn!(upstream = %upstream, error = %e, "GraphQL upstream request failed");
+                ErrorResponse::from(&e)
+                    .instance(path.clone())
+                    .into_response()
+            }
+        };
+
+        let latency = start_time.elapsed();
+        let status = response.status();
+        let outcome = if status.is_success() {
+            RequestOutcome::Success
+        } else {
+            RequestOutcome::Error
+        };
+        self.metrics_collector
+            .record_request(&path, latency, outcome);
+        self.activity_log
+            .record(method, path, status, latency, upstream.clone());
+
+        Ok(response)
+    }
+
+    /// Create a buffered problem+json (or legacy, per `gateway.error_format`) error response
+    fn error_response(&self, problem: ErrorResponse) -> Result<Response<Full<Bytes>>> {
+        Ok(problem.into_response())
     }
 
     /// Create a streaming-typed error response (for use in contexts returning `Body`)
     #[allow(dead_code)]
-    fn error_body_response(&self, status: StatusCode, message: &str) -> Result<Response<Body>> {
-        Response::builder()
-            .status(status)
-            .header("content-type", "text/plain")
-            .body(buffered(message.to_string()))
-            .map_err(|e| Error::Internal(format!("Failed to build error response: {e}")))
+    fn error_body_response(&self, problem: ErrorResponse) -> Result<Response<Body>> {
+        Ok(problem.into_response().map(Either::Left))
     }
 }
 
@@ -1842,6 +2941,51 @@ mod tests {
         assert_eq!(handler.request_count.load(Ordering::Relaxed), 0);
     }
 
+    #[test]
+    fn trailing_slash_redirect_preserves_query_string() {
+        let router = Router::new()
+            .with_trailing_slash(octopus_router::TrailingSlashPolicy::RedirectToCanonical);
+        router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/users")
+                    .upstream_name("users")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let matched = router
+            .match_route("example.com", &http::Method::GET, "/users/")
+            .unwrap();
+        let canonical = matched.redirect_to.expect("non-canonical form redirects");
+
+        let resp =
+            RequestHandler::canonical_redirect(&canonical, Some("page=2&sort=name")).unwrap();
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers()[http::header::LOCATION],
+            "/users?page=2&sort=name"
+        );
+
+        let resp = RequestHandler::canonical_redirect(&canonical, None).unwrap();
+        assert_eq!(resp.headers()[http::header::LOCATION], "/users");
+    }
+
+    #[test]
+    fn normalized_path_replaces_request_path_and_keeps_query() {
+        let mut req = Request::builder()
+            .uri("/public/../admin//users?page=2")
+            .body(())
+            .unwrap();
+        let normalized = normalize_path(req.uri().path(), EncodedSlash::Reject).unwrap();
+        let normalized = normalized.into_owned();
+        RequestHandler::set_request_path(&mut req, &normalized);
+        assert_eq!(req.uri().path(), "/admin/users");
+        assert_eq!(req.uri().query(), Some("page=2"));
+    }
+
     #[test]
     fn admin_allowlist_empty_allows_all() {
         assert!(admin_ip_allowed(&[], None));
@@ -1863,6 +3007,154 @@ mod tests {
         assert!(!admin_ip_allowed(&allowed, None));
     }
 
+    fn handler_in_maintenance() -> RequestHandler {
+        let handler = create_test_handler();
+        handler
+            .set_maintenance(&octopus_core::MaintenanceSettings {
+                enabled: true,
+                bypass_ips: vec!["10.1.2.0/24".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        handler
+    }
+
+    #[test]
+    fn maintenance_returns_503_for_data_routes() {
+        let handler = handler_in_maintenance();
+        let client = Some("203.0.113.9".parse().unwrap());
+
+        let resp = handler.maintenance_response("/catalog", client).unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers()[http::header::RETRY_AFTER], "300");
+
+        handler.admin_handler.maintenance().set_enabled(false);
+        assert!(handler.maintenance_response("/catalog", client).is_none());
+    }
+
+    #[test]
+    fn maintenance_keeps_health_and_admin_endpoints() {
+        let handler = handler_in_maintenance();
+        let client = Some("203.0.113.9".parse().unwrap());
+
+        for path in [
+            "/healthz",
+            "/readyz",
+            "/livez",
+            "/metrics",
+            "/admin/api/maintenance",
+        ] {
+            assert!(
+                handler.maintenance_response(path, client).is_none(),
+                "{path} should bypass maintenance"
+            );
+        }
+    }
+
+    #[test]
+    fn maintenance_covers_admin_paths_on_a_data_listener() {
+        let mut handler = handler_in_maintenance();
+        handler.set_plane(Plane::Data);
+        let client = Some("203.0.113.9".parse().unwrap());
+
+        // On the data listener these are ordinary routes; probes still bypass.
+        for path in ["/metrics", "/admin/api/maintenance", "/__farp/services"] {
+            assert!(
+                handler.maintenance_response(path, client).is_some(),
+                "{path} should be in maintenance"
+            );
+        }
+        assert!(handler.maintenance_response("/readyz", client).is_none());
+    }
+
+    #[test]
+    fn maintenance_lets_allowlisted_ips_through() {
+        let handler = handler_in_maintenance();
+
+        let smoke_test = Some("10.1.2.3".parse().unwrap());
+        assert!(handler
+            .maintenance_response("/catalog", smoke_test)
+            .is_none());
+        // Without a known client address there is nothing to match.
+        assert!(handler.maintenance_response("/catalog", None).is_some());
+    }
+
+    fn handler_with_unhealthy_upstream(fallback: Option<RouteFallback>) -> RequestHandler {
+        let handler = create_test_handler();
+        let mut instance = octopus_core::UpstreamInstance::new("catalog-1", "127.0.0.1", 9);
+        instance.mark_unhealthy();
+        let mut cluster = octopus_core::UpstreamCluster::new("catalog");
+        cluster.add_instance(instance);
+        handler.router.register_upstream(cluster);
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/catalog")
+                    .upstream_name("catalog")
+                    .fallback(fallback)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        handler
+    }
+
+    fn catalog_request() -> Request<Full<Bytes>> {
+        Request::builder()
+            .uri("/catalog")
+            .header(http::header::HOST, "shop.example.com")
+            .body(Full::new(Bytes::new()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn no_healthy_upstream_serves_static_fallback() {
+        let handler = handler_with_unhealthy_upstream(Some(RouteFallback::StaticJson {
+            status: StatusCode::OK,
+            body: r#"{"items":[]}"#.to_string(),
+        }));
+
+        let resp = handler
+            .handle_proxy_request(catalog_request())
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[fallback::FALLBACK_HEADER], "static_json");
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], br#"{"items":[]}"#);
+    }
+
+    #[tokio::test]
+    async fn no_healthy_upstream_without_fallback_is_503() {
+        let handler = handler_with_unhealthy_upstream(None);
+
+        let resp = handler
+            .handle_proxy_request(catalog_request())
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!resp.headers().contains_key(fallback::FALLBACK_HEADER));
+    }
+
+    #[tokio::test]
+    async fn empty_last_good_cache_falls_through_to_503() {
+        let handler = handler_with_unhealthy_upstream(Some(RouteFallback::LastGoodCache {
+            max_age: Duration::from_secs(60),
+        }));
+
+        let resp = handler
+            .handle_proxy_request(catalog_request())
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[tokio::test]
     async fn sni_check_respects_enforce_flag() {
         let mut handler = create_test_handler();
@@ -2521,4 +3813,892 @@ mod tests {
             "synthetic origin upstream registered"
         );
     }
+
+    /// Upstream answering every request with `len` bytes of `text/plain`.
+    async fn fixed_size_upstream(len: usize) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service =
+                    hyper::service::service_fn(move |req: Request<Incoming>| async move {
+                        req.into_body().collect().await?;
+                        Ok::<_, hyper::Error>(
+                            Response::builder()
+                                .header(http::header::CONTENT_TYPE, "text/plain")
+                                .body(Full::new(Bytes::from(vec![b'a'; len])))
+                                .unwrap(),
+                        )
+                    });
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+                );
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn proxied_body_bytes_are_counted_per_route_after_compression() {
+        let port = fixed_size_upstream(4096).await;
+        let mut handler = create_test_handler();
+        let mut cluster = octopus_core::UpstreamCluster::new("files");
+        cluster.add_instance(octopus_core::UpstreamInstance::new(
+            "files-1",
+            "127.0.0.1",
+            port,
+        ));
+        handler.router.register_upstream(cluster);
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::POST)
+                    .path("/upload")
+                    .upstream_name("files")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        handler.middleware_chain = Arc::new([Arc::new(
+            octopus_compression::CompressionMiddleware::new(Default::default()),
+        ) as Arc<dyn Middleware>]);
+
+        let upload = |size: usize, accept_encoding: Option<&str>| {
+            let mut builder = Request::builder().method("POST").uri("/upload");
+            if let Some(encoding) = accept_encoding {
+                builder = builder.header(http::header::ACCEPT_ENCODING, encoding);
+            }
+            builder
+                .body(Full::new(Bytes::from(vec![b'x'; size])))
+                .unwrap()
+        };
+
+        // Uncompressed: the full upstream body is sent.
+        let resp = handler.handle_buffered(upload(1000, None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key(http::header::CONTENT_ENCODING));
+
+        // Compressed: only the encoded bytes are sent and counted.
+        let resp = handler
+            .handle_buffered(upload(500, Some("gzip")))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[http::header::CONTENT_ENCODING], "gzip");
+        let compressed = resp.into_body().collect().await.unwrap().to_bytes().len() as u64;
+        assert!(compressed < 4096);
+
+        let stats = handler.metrics_collector.route_stats("/upload").unwrap();
+        assert_eq!(stats.request_bytes.load(Ordering::Relaxed), 1500);
+        assert_eq!(
+            stats.response_bytes.load(Ordering::Relaxed),
+            4096 + compressed
+        );
+        assert_eq!(handler.metrics_collector.total_request_bytes(), 1500);
+        assert_eq!(
+            handler.metrics_collector.total_response_bytes(),
+            4096 + compressed
+        );
+    }
+
+    /// Upstream that resets every connection once the request has arrived.
+    async fn resetting_upstream() -> u16 {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                stream.set_linger(Some(Duration::ZERO)).unwrap();
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn upstream_reset_is_502_and_counted_by_code() {
+        let port = resetting_upstream().await;
+        let mut handler = create_test_handler();
+        let mut cluster = octopus_core::UpstreamCluster::new("orders");
+        cluster.add_instance(octopus_core::UpstreamInstance::new(
+            "orders-1",
+            "127.0.0.1",
+            port,
+        ));
+        handler.router.register_upstream(cluster);
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders")
+                    .upstream_name("orders")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/orders")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let resp = handler.handle_buffered(req).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("UPSTREAM_RESET"), "{body}");
+        assert!(!body.contains("127.0.0.1"), "{body}");
+        assert_eq!(
+            handler
+                .metrics_collector
+                .upstream_error_count(octopus_core::ErrorCode::UpstreamReset),
+            1
+        );
+    }
+
+    /// Handler routing `GET /orders` to an `orders` upstream on `port`
+    fn orders_handler(port: u16) -> RequestHandler {
+        let handler = create_test_handler();
+        let mut cluster = octopus_core::UpstreamCluster::new("orders");
+        cluster.add_instance(octopus_core::UpstreamInstance::new(
+            "orders-1",
+            "127.0.0.1",
+            port,
+        ));
+        handler.router.register_upstream(cluster);
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders")
+                    .upstream_name("orders")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        handler
+    }
+
+    #[tokio::test]
+    async fn upstream_error_status_is_passed_through_verbatim() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = hyper::service::service_fn(|_req: Request<Incoming>| async {
+                    Ok::<_, hyper::Error>(
+                        Response::builder()
+                            .status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .header(http::header::CONTENT_TYPE, "application/json")
+                            .header("x-upstream-trace", "abc123")
+                            .body(Full::new(Bytes::from(r#"{"error":"db down"}"#)))
+                            .unwrap(),
+                    )
+                });
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+                );
+            }
+        });
+        let handler = orders_handler(port);
+
+        let req = Request::builder()
+            .uri("/orders")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let resp = handler.handle_buffered(req).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            resp.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert_eq!(resp.headers()["x-upstream-trace"], "abc123");
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, r#"{"error":"db down"}"#);
+    }
+
+    #[tokio::test]
+    async fn unreachable_upstream_is_a_gateway_502() {
+        // A port nothing listens on
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let handler = orders_handler(port);
+
+        let req = Request::builder()
+            .uri("/orders")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let resp = handler.handle_buffered(req).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            resp.headers()[http::header::CONTENT_TYPE],
+            octopus_core::PROBLEM_JSON
+        );
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["code"], "UPSTREAM_REFUSED");
+        assert_eq!(problem["status"], 502);
+    }
+
+    #[tokio::test]
+    async fn ndjson_response_streams_with_route_redaction() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = hyper::service::service_fn(|_req: Request<Incoming>| async {
+                    Ok::<_, hyper::Error>(
+                        Response::builder()
+                            .header(http::header::CONTENT_TYPE, "application/x-ndjson")
+                            .body(Full::new(Bytes::from_static(
+                                b"{\"email\":\"a@example.com\",\"id\":1}\n{\"id\":2}\n",
+                            )))
+                            .unwrap(),
+                    )
+                });
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+                );
+            }
+        });
+        let mut handler = orders_handler(port);
+        let route: octopus_config::types::RouteConfig = serde_json::from_value(serde_json::json!({
+            "path": "/orders",
+            "upstream": "orders",
+            "methods": ["GET"],
+            "transform": {"response": [{"op": "redact", "path": "$.email"}]}
+        }))
+        .unwrap();
+        handler.set_route_transforms(&[route]);
+
+        // The client asked for JSON; the upstream's content type decides
+        let mut req = Request::builder()
+            .uri("/orders")
+            .header(http::header::ACCEPT, "application/json")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        req.extensions_mut().insert(StreamEvents);
+        let resp = RequestHandler::client_response(handler.handle_buffered(req).await.unwrap());
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(matches!(resp.body(), Either::Right(_)));
+        assert!(resp.headers().get(http::header::CONTENT_LENGTH).is_none());
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "{\"email\":\"***REDACTED***\",\"id\":1}\n{\"id\":2}\n"
+        );
+    }
+
+    fn unmatched_request(path: &'static str) -> Request<Full<Bytes>> {
+        Request::builder()
+            .uri(path)
+            .header(http::header::HOST, "shop.example.com")
+            .body(Full::new(Bytes::new()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unmatched_request_gets_custom_body() {
+        let mut handler = create_test_handler();
+        handler.set_unmatched(UnmatchedPolicy::Respond {
+            status: StatusCode::NOT_FOUND,
+            content_type: http::HeaderValue::from_static("text/html"),
+            body: Bytes::from_static(b"<h1>Not here</h1>"),
+        });
+
+        let resp = handler
+            .handle_proxy_request(unmatched_request("/nowhere"))
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[http::header::CONTENT_TYPE], "text/html");
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"<h1>Not here</h1>");
+    }
+
+    #[tokio::test]
+    async fn unmatched_request_is_redirected() {
+        let mut handler = create_test_handler();
+        handler.set_unmatched(UnmatchedPolicy::Redirect {
+            url: "https://legacy.example.com".to_string(),
+            status: StatusCode::FOUND,
+            preserve_path: true,
+        });
+
+        let resp = handler
+            .handle_proxy_request(unmatched_request("/old/cart?id=3"))
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers()[http::header::LOCATION],
+            "https://legacy.example.com/old/cart?id=3"
+        );
+    }
+
+    #[tokio::test]
+    async fn unmatched_request_is_forwarded_to_catch_all_upstream() {
+        let port = fixed_size_upstream(64).await;
+        let mut handler = create_test_handler();
+        let mut cluster = octopus_core::UpstreamCluster::new("legacy");
+        cluster.add_instance(octopus_core::UpstreamInstance::new(
+            "legacy-1",
+            "127.0.0.1",
+            port,
+        ));
+        handler.router.register_upstream(cluster);
+
+        // Without a policy the request is a plain 404.
+        let resp = handler
+            .handle_proxy_request(unmatched_request("/not/migrated"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        handler.set_unmatched(UnmatchedPolicy::Upstream("legacy".to_string()));
+        let resp = handler
+            .handle_proxy_request(unmatched_request("/not/migrated"))
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), 64);
+    }
+
+    /// Upstream redirecting `/downloads/{name}` internally to
+    /// `/storage/{name}` (or, for `loop`, to itself), and serving
+    /// `/storage/...` as `stored <path>`.
+    async fn accel_redirect_upstream() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = hyper::service::service_fn(|req: Request<Incoming>| async move {
+                    let path = req.uri().path().to_string();
+                    let auth = req.headers().get("authorization").cloned();
+                    let response = match path.strip_prefix("/downloads/") {
+                        Some("loop") => Response::builder()
+                            .header("x-accel-redirect", "/downloads/loop")
+                            .body(Full::new(Bytes::new())),
+                        Some(name) => Response::builder()
+                            .header("x-accel-redirect", format!("/storage/{name}"))
+                            .header("set-cookie", "internal=1")
+                            .body(Full::new(Bytes::from_static(b"intermediate"))),
+                        None => {
+                            let mut builder = Response::builder();
+                            if let Some(auth) = auth {
+                                builder = builder.header("x-seen-authorization", auth);
+                            }
+                            builder.body(Full::new(Bytes::from(format!("stored {path}"))))
+                        }
+                    };
+                    Ok::<_, hyper::Error>(response.unwrap())
+                });
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+                );
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn internal_redirect_returns_the_redirected_resource() {
+        let port = accel_redirect_upstream().await;
+        let mut handler = create_test_handler();
+        for name in ["app", "storage"] {
+            let mut cluster = octopus_core::UpstreamCluster::new(name);
+            cluster.add_instance(octopus_core::UpstreamInstance::new(
+                format!("{name}-1"),
+                "127.0.0.1",
+                port,
+            ));
+            handler.router.register_upstream(cluster);
+        }
+        for (path, upstream) in [("/downloads/*name", "app"), ("/storage/*path", "storage")] {
+            handler
+                .router
+                .add_route(
+                    octopus_router::RouteBuilder::new()
+                        .method(http::Method::GET)
+                        .path(path)
+                        .upstream_name(upstream)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+        }
+        let download = |path: &'static str| {
+            Request::builder()
+                .uri(path)
+                .header(http::header::HOST, "files.example.com")
+                .header(http::header::AUTHORIZATION, "Bearer abc")
+                .body(Full::new(Bytes::new()))
+                .unwrap()
+        };
+
+        // Disabled: the header goes back to the client untouched.
+        let resp = handler
+            .handle_proxy_request(download("/downloads/report.pdf"))
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["x-accel-redirect"], "/storage/report.pdf");
+
+        handler.set_internal_redirect(Some(&octopus_config::types::InternalRedirectConfig {
+            header: "X-Accel-Redirect".to_string(),
+            max_hops: 2,
+        }));
+        let resp = handler
+            .handle_proxy_request(download("/downloads/report.pdf"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key("x-accel-redirect"));
+        assert!(!resp.headers().contains_key("set-cookie"));
+        assert_eq!(resp.headers()["x-seen-authorization"], "Bearer abc");
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "stored /storage/report.pdf");
+
+        // A redirect that keeps redirecting gives up after max_hops.
+        let resp = handler
+            .handle_proxy_request(download("/downloads/loop"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn unavailable_upstream_fails_over_to_the_fallback_upstream() {
+        let handler = create_test_handler();
+        let mut clusters = Vec::new();
+        for (cluster, len) in [("orders", 16), ("orders-dr", 32)] {
+            let port = fixed_size_upstream(len).await;
+            let mut upstream = octopus_core::UpstreamCluster::new(cluster);
+            upstream.add_instance(octopus_core::UpstreamInstance::new(
+                format!("{cluster}-1"),
+                "127.0.0.1",
+                port,
+            ));
+            handler.router.register_upstream(upstream.clone());
+            clusters.push(upstream);
+        }
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders")
+                    .upstream_name("orders")
+                    .fallback_upstream(Some("orders-dr".to_string()))
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        let get = |handler: &RequestHandler| {
+            let req = Request::builder()
+                .uri("/orders")
+                .header(http::header::HOST, "shop.example.com")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            let handler = handler.clone();
+            async move {
+                let resp = handler.handle_proxy_request(req).await.unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                // Only debug headers name upstreams to the client.
+                assert!(!resp.headers().contains_key("x-octopus-failover"));
+                let failover = resp
+                    .extensions()
+                    .get::<octopus_core::UpstreamSelection>()
+                    .and_then(|selection| selection.failover_from.clone());
+                let len = resp.into_body().collect().await.unwrap().to_bytes().len();
+                (len, failover)
+            }
+        };
+
+        assert_eq!(get(&handler).await, (16, None));
+
+        // The primary's breaker opens: traffic moves to the fallback.
+        let breaker = handler.proxy.circuit_breaker().clone();
+        breaker.force_open("orders-1");
+        let (len, failover) = get(&handler).await;
+        assert_eq!(len, 32);
+        assert_eq!(failover.unwrap(), "orders");
+        assert_eq!(
+            handler.metrics_collector.failover_counts(),
+            vec![(("orders".to_string(), "orders-dr".to_string()), 1)]
+        );
+
+        // Closed again: back to the primary.
+        breaker.reset("orders-1");
+        assert_eq!(get(&handler).await, (16, None));
+
+        // Half-open with its one probe in flight, the primary can't take
+        // the request either.
+        breaker.set_instance_configs(std::collections::HashMap::from([(
+            "orders-1".to_string(),
+            octopus_health::CircuitBreakerConfig {
+                min_requests: 1,
+                open_timeout: Duration::ZERO,
+                half_open_max_requests: 1,
+                ..Default::default()
+            },
+        )]));
+        breaker.record_failure("orders-1");
+        assert!(breaker.allow_request("orders-1"));
+        assert_eq!(
+            breaker.get_state("orders-1"),
+            octopus_health::CircuitState::HalfOpen
+        );
+        assert_eq!(get(&handler).await.0, 32);
+
+        // The probe's outcome is ignored, which frees its slot: the primary
+        // takes the next request, and its success closes the circuit.
+        breaker.record("orders-1", octopus_health::Outcome::Local);
+        assert_eq!(get(&handler).await, (16, None));
+        assert_eq!(
+            breaker.get_state("orders-1"),
+            octopus_health::CircuitState::Closed
+        );
+
+        // Open and due to probe, the primary gets the request, and a
+        // successful probe keeps it there.
+        breaker.record_failure("orders-1");
+        assert_eq!(get(&handler).await, (16, None));
+        assert_eq!(
+            breaker.get_state("orders-1"),
+            octopus_health::CircuitState::Closed
+        );
+        breaker.reset("orders-1");
+
+        // No healthy instance fails over too.
+        let mut primary = clusters.swap_remove(0);
+        primary.instances[0].mark_unhealthy();
+        handler.router.register_upstream(primary);
+        assert_eq!(get(&handler).await.0, 32);
+    }
+
+    /// Resolves every service to instances on `ports`, which tests change
+    #[derive(Debug, Default)]
+    struct MockResolver {
+        ports: std::sync::Mutex<Vec<u16>>,
+    }
+
+    #[async_trait::async_trait]
+    impl octopus_core::UpstreamResolver for MockResolver {
+        async fn resolve(&self, service: &str) -> Result<Vec<octopus_core::UpstreamInstance>> {
+            Ok(self
+                .ports
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|port| {
+                    octopus_core::UpstreamInstance::new(
+                        format!("{service}-{port}"),
+                        "127.0.0.1",
+                        *port,
+                    )
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn service_routes_follow_the_resolved_instances() {
+        let mut handler = create_test_handler();
+        let resolver = Arc::new(MockResolver::default());
+        handler.set_upstream_resolver(resolver.clone());
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders")
+                    .upstream_name("")
+                    .service(Some("orders".to_string()))
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        let get = |handler: &RequestHandler| {
+            let req = Request::builder()
+                .uri("/orders")
+                .header(http::header::HOST, "shop.example.com")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            let handler = handler.clone();
+            async move {
+                let resp = handler.handle_proxy_request(req).await.unwrap();
+                let status = resp.status();
+                let len = resp.into_body().collect().await.unwrap().to_bytes().len();
+                (status, len)
+            }
+        };
+
+        // No instances yet: nothing to proxy to.
+        assert_eq!(get(&handler).await.0, StatusCode::SERVICE_UNAVAILABLE);
+
+        // The service scales up, then moves: each request sees the change.
+        *resolver.ports.lock().unwrap() = vec![fixed_size_upstream(16).await];
+        assert_eq!(get(&handler).await, (StatusCode::OK, 16));
+        *resolver.ports.lock().unwrap() = vec![fixed_size_upstream(32).await];
+        assert_eq!(get(&handler).await, (StatusCode::OK, 32));
+        assert_eq!(
+            handler
+                .router
+                .get_upstream("__service__:orders")
+                .unwrap()
+                .instances
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn tenant_requests_are_routed_to_the_tenant_upstream() {
+        let mut handler = create_test_handler();
+        for (name, len) in [("shared", 16), ("acme-api", 32)] {
+            let port = fixed_size_upstream(len).await;
+            let mut cluster = octopus_core::UpstreamCluster::new(name);
+            cluster.add_instance(octopus_core::UpstreamInstance::new(
+                format!("{name}-1"),
+                "127.0.0.1",
+                port,
+            ));
+            handler.router.register_upstream(cluster);
+        }
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders")
+                    .upstream_name("shared")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        handler
+            .set_tenancy(&octopus_config::types::TenancyConfig {
+                source: octopus_config::types::TenantSourceConfig::Path {
+                    prefix: "/t".to_string(),
+                },
+                header: "X-Tenant-Id".to_string(),
+                unknown_status: 404,
+                required: true,
+                tenants: [
+                    (
+                        "acme".to_string(),
+                        octopus_config::types::TenantConfig {
+                            upstream: Some("acme-api".to_string()),
+                            headers: Default::default(),
+                        },
+                    ),
+                    ("globex".to_string(), Default::default()),
+                ]
+                .into(),
+            })
+            .unwrap();
+
+        let proxied_len = |path: &'static str| {
+            let handler = &handler;
+            async move {
+                let mut req = Request::builder()
+                    .uri(path)
+                    .header(http::header::HOST, "api.example.com")
+                    .body(Full::new(Bytes::new()))
+                    .unwrap();
+                if let Some(rejection) = handler.resolve_tenant(&mut req) {
+                    return Err(rejection.status());
+                }
+                let resp = handler.handle_proxy_request(req).await.unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                Ok(resp.into_body().collect().await.unwrap().to_bytes().len())
+            }
+        };
+
+        // acme has its own upstream; globex shares the route's
+        assert_eq!(proxied_len("/t/acme/orders").await, Ok(32));
+        assert_eq!(proxied_len("/t/globex/orders").await, Ok(16));
+        assert_eq!(
+            proxied_len("/t/initech/orders").await,
+            Err(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(proxied_len("/orders").await, Err(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn claim_tenant_is_read_from_verified_claims_only() {
+        let mut handler = create_test_handler();
+        for (name, len) in [("shared", 16), ("acme-api", 32)] {
+            let port = fixed_size_upstream(len).await;
+            let mut cluster = octopus_core::UpstreamCluster::new(name);
+            cluster.add_instance(octopus_core::UpstreamInstance::new(
+                format!("{name}-1"),
+                "127.0.0.1",
+                port,
+            ));
+            handler.router.register_upstream(cluster);
+        }
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders")
+                    .upstream_name("shared")
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        handler
+            .set_tenancy(&octopus_config::types::TenancyConfig {
+                source: octopus_config::types::TenantSourceConfig::Claim {
+                    claim: "tenant".to_string(),
+                    token_header: "Authorization".to_string(),
+                },
+                header: "X-Tenant-Id".to_string(),
+                unknown_status: 404,
+                required: true,
+                tenants: [(
+                    "acme".to_string(),
+                    octopus_config::types::TenantConfig {
+                        upstream: Some("acme-api".to_string()),
+                        headers: Default::default(),
+                    },
+                )]
+                .into(),
+            })
+            .unwrap();
+
+        let request = || {
+            let mut req = Request::builder()
+                .uri("/orders")
+                .header(http::header::HOST, "api.example.com")
+                // Unsigned token claiming the tenant
+                .header(
+                    http::header::AUTHORIZATION,
+                    "Bearer eyJhbGciOiJub25lIn0.eyJ0ZW5hbnQiOiJhY21lIn0.",
+                )
+                .header("x-tenant-id", "acme")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            assert!(handler.resolve_tenant(&mut req).is_none());
+            req
+        };
+
+        // Nothing verified the token: no tenant
+        let resp = handler.handle_proxy_request(request()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // As the JWT middleware leaves a verified token
+        let mut req = request();
+        req.extensions_mut()
+            .insert(octopus_middleware::jwt::Claims {
+                sub: "user-1".to_string(),
+                exp: 0,
+                iat: None,
+                iss: None,
+                aud: None,
+                custom: serde_json::json!({ "tenant": "acme" }),
+            });
+        let resp = handler.handle_proxy_request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.into_body().collect().await.unwrap().to_bytes().len(),
+            32
+        );
+    }
+
+    #[tokio::test]
+    async fn route_rate_limit_is_kept_per_client_and_reset_from_the_admin_api() {
+        let port = fixed_size_upstream(16).await;
+        let mut handler = create_test_handler();
+        let mut cluster = octopus_core::UpstreamCluster::new("orders");
+        cluster.add_instance(octopus_core::UpstreamInstance::new(
+            "orders-1",
+            "127.0.0.1",
+            port,
+        ));
+        handler.router.register_upstream(cluster);
+        handler
+            .router
+            .add_route(
+                octopus_router::RouteBuilder::new()
+                    .method(http::Method::GET)
+                    .path("/orders/*")
+                    .upstream_name("orders")
+                    .rate_limit(1, Duration::from_secs(60))
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        let limiter = Arc::new(octopus_middleware::RouteRateLimiter::new(
+            octopus_state::InMemoryBackend::new(),
+        ));
+        handler.middleware_chain = Arc::new([Arc::clone(&limiter) as Arc<dyn Middleware>]);
+        handler.set_rate_limits(limiter);
+
+        let request = |client: &str| {
+            let mut req = Request::builder()
+                .uri("/orders/42")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            req.extensions_mut()
+                .insert(ClientAddr(format!("{client}:40000").parse().unwrap()));
+            req
+        };
+        let admin = |method: http::Method, target: &'static str| {
+            let admin = &handler.admin_handler;
+            async move {
+                let resp = admin
+                    .handle(&method, target, http::HeaderMap::new(), Bytes::new(), None)
+                    .await
+                    .unwrap();
+                let body = resp.into_body().collect().await.unwrap().to_bytes();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let status = |resp: Result<Response<Full<Bytes>>>| resp.unwrap().status();
+        assert_eq!(
+            status(handler.handle_buffered(request("10.0.0.7")).await),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(handler.handle_buffered(request("10.0.0.7")).await),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        // Another client has its own window
+        assert_eq!(
+            status(handler.handle_buffered(request("10.0.0.8")).await),
+            StatusCode::OK
+        );
+
+        // The route's key (with `/`) is reported per client
+        let state = admin(http::Method::GET, "/admin/api/rate-limits?key=10.0.0.7").await;
+        assert_eq!(state["buckets"][0]["route"], "/orders/*");
+        assert_eq!(state["buckets"][0]["remaining"], 0);
+
+        let reset = admin(http::Method::DELETE, "/admin/api/rate-limits?key=10.0.0.7").await;
+        assert_eq!(reset["reset"], 1);
+        assert_eq!(
+            status(handler.handle_buffered(request("10.0.0.7")).await),
+            StatusCode::OK
+        );
+    }
 }
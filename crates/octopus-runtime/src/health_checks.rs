@@ -0,0 +1,214 @@
+//! Active health checks of configured upstreams
+//!
+//! Upstreams with a `health_check` are probed by a [`HealthCheckScheduler`],
+//! one per distinct check interval, each running as a `health` background
+//! task. Results are counted per instance: `unhealthy_threshold` consecutive
+//! failures take an instance out of rotation and `healthy_threshold`
+//! consecutive passes bring it back, through
+//! [`Router::set_instance_health`], which also restarts its slow-start ramp.
+//! Instances added later by discovery are not probed.
+
+use crate::shutdown::BackgroundTasks;
+use octopus_config::types::{HealthCheckConfig, UpstreamConfig};
+use octopus_health::{
+    CheckTarget, HealthCheck, HealthCheckResult, HealthCheckScheduler, HealthCheckType,
+    HealthChecker, HealthStatus, ResultSink, ScheduleConfig,
+};
+use octopus_router::Router;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Consecutive passes and failures needed to change an instance's health
+#[derive(Debug, Clone, Copy)]
+struct Thresholds {
+    healthy: u32,
+    unhealthy: u32,
+}
+
+/// Turns check results into health transitions on the router
+#[derive(Debug)]
+struct Transitions {
+    router: Arc<Router>,
+    thresholds: HashMap<String, Thresholds>,
+    /// Current run per (upstream, instance): passes when positive, failures
+    /// when negative
+    streaks: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl Transitions {
+    fn new(router: Arc<Router>) -> Self {
+        Self {
+            router,
+            thresholds: HashMap::new(),
+            streaks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count `status` for an instance, changing its health once a threshold
+    /// is reached
+    fn record(&self, upstream: &str, instance_id: &str, status: HealthStatus) {
+        let Some(thresholds) = self.thresholds.get(upstream) else {
+            return;
+        };
+        let streak = {
+            let mut streaks = self.streaks.lock();
+            let streak = streaks
+                .entry((upstream.to_string(), instance_id.to_string()))
+                .or_default();
+            *streak = match status {
+                HealthStatus::Healthy => (*streak).max(0) + 1,
+                HealthStatus::Unhealthy => (*streak).min(0) - 1,
+                HealthStatus::Unknown => return,
+            };
+            *streak
+        };
+
+        let healthy = if streak >= i64::from(thresholds.healthy) {
+            true
+        } else if -streak >= i64::from(thresholds.unhealthy) {
+            false
+        } else {
+            return;
+        };
+        if self
+            .router
+            .set_instance_health(upstream, instance_id, healthy)
+        {
+            if healthy {
+                tracing::info!(upstream = %upstream, instance = %instance_id, "Instance passed health checks; back in rotation");
+            } else {
+                tracing::warn!(upstream = %upstream, instance = %instance_id, "Instance failed health checks; out of rotation");
+            }
+        }
+    }
+}
+
+/// The checker `check` describes
+fn checker(check: &HealthCheckConfig) -> Arc<dyn HealthCheck> {
+    let check_type = match check.check_type.as_str() {
+        "tcp" => HealthCheckType::Tcp,
+        "grpc" => HealthCheckType::Grpc {
+            service: String::new(),
+        },
+        _ => HealthCheckType::Http {
+            path: check.path.clone().unwrap_or_else(|| "/health".to_string()),
+            expected_status: vec![http::StatusCode::OK],
+            method: http::Method::GET,
+            headers: HashMap::new(),
+        },
+    };
+    Arc::new(HealthChecker::new(octopus_health::HealthCheckConfig {
+        check_type,
+        timeout: check.timeout,
+        healthy_threshold: check.healthy_threshold,
+        unhealthy_threshold: check.unhealthy_threshold,
+    }))
+}
+
+/// Start health checking every upstream in `upstreams` that configures it,
+/// on `tasks` under the `health` subsystem. Returns how many upstreams are
+/// checked.
+pub(crate) fn start(
+    router: Arc<Router>,
+    upstreams: &[UpstreamConfig],
+    tasks: &BackgroundTasks,
+) -> usize {
+    let mut transitions = Transitions::new(router);
+    let mut schedulers: BTreeMap<Duration, HealthCheckScheduler> = BTreeMap::new();
+    for upstream in upstreams {
+        let Some(check) = &upstream.health_check else {
+            continue;
+        };
+        transitions.thresholds.insert(
+            upstream.name.clone(),
+            Thresholds {
+                healthy: check.healthy_threshold,
+                unhealthy: check.unhealthy_threshold,
+            },
+        );
+        let targets = upstream
+            .instances
+            .iter()
+            .map(|i| CheckTarget::new(&i.id, &i.host, i.port))
+            .collect();
+        schedulers
+            .entry(check.interval)
+            .or_insert_with(|| {
+                HealthCheckScheduler::new(ScheduleConfig {
+                    interval: check.interval,
+                    ..Default::default()
+                })
+            })
+            .set_cluster(&upstream.name, checker(check), targets);
+    }
+
+    let checked = transitions.thresholds.len();
+    let transitions = Arc::new(transitions);
+    let sink: ResultSink = Arc::new(
+        move |upstream: &str, target: &CheckTarget, result: &HealthCheckResult| {
+            transitions.record(upstream, &target.instance_id, result.status);
+        },
+    );
+    for (interval, scheduler) in schedulers {
+        tracing::debug!(interval = ?interval, "Starting health checks");
+        let sink = Arc::clone(&sink);
+        tasks.spawn("health", async move { scheduler.run(sink).await });
+    }
+    checked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_core::{UpstreamCluster, UpstreamInstance};
+
+    fn transitions() -> Transitions {
+        let router = Arc::new(Router::new());
+        let mut cluster = UpstreamCluster::new("orders");
+        cluster.add_instance(UpstreamInstance::new("orders-1", "10.0.0.1", 8080));
+        router.register_upstream(cluster);
+
+        let mut transitions = Transitions::new(router);
+        transitions.thresholds.insert(
+            "orders".to_string(),
+            Thresholds {
+                healthy: 2,
+                unhealthy: 3,
+            },
+        );
+        transitions
+    }
+
+    fn healthy(transitions: &Transitions) -> bool {
+        transitions
+            .router
+            .get_upstream("orders")
+            .unwrap()
+            .healthy_count()
+            == 1
+    }
+
+    #[test]
+    fn thresholds_gate_health_transitions() {
+        let transitions = transitions();
+        let record = |status| transitions.record("orders", "orders-1", status);
+
+        record(HealthStatus::Unhealthy);
+        record(HealthStatus::Unhealthy);
+        // A pass resets the run of failures.
+        record(HealthStatus::Healthy);
+        record(HealthStatus::Unhealthy);
+        record(HealthStatus::Unhealthy);
+        assert!(healthy(&transitions));
+        record(HealthStatus::Unhealthy);
+        assert!(!healthy(&transitions));
+
+        record(HealthStatus::Healthy);
+        record(HealthStatus::Unknown);
+        assert!(!healthy(&transitions));
+        record(HealthStatus::Healthy);
+        assert!(healthy(&transitions));
+    }
+}
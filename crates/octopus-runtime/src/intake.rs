@@ -0,0 +1,222 @@
+//! Request body intake: `Expect: 100-continue` and the buffered body limit.
+//!
+//! hyper sends the interim `100 Continue` the first time a request body is
+//! polled, so the gateway only decides, before reading, whether to read at
+//! all: an unsupported expectation is answered with `417`, and a declared
+//! `Content-Length` above the limit with `413`, without the client ever
+//! sending the body. Bodies without a declared length are read through
+//! [`Limited`] and rejected with `413` as soon as they exceed the limit.
+
+use bytes::Bytes;
+use http::header::{CONTENT_LENGTH, EXPECT};
+use http::{Request, Response, StatusCode, Version};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use octopus_core::ErrorResponse;
+
+/// Why a request body was not read
+#[derive(Debug)]
+pub(crate) enum IntakeError {
+    /// The body exceeded the limit while being read
+    TooLarge(usize),
+    /// The body could not be read
+    Read(String),
+}
+
+/// Reject a request before its body is read: `417` for an `Expect` other
+/// than `100-continue`, `413` for a declared `Content-Length` above `limit`.
+pub(crate) fn check_request<B>(
+    req: &Request<B>,
+    limit: Option<usize>,
+) -> Option<Response<Full<Bytes>>> {
+    // HTTP/1.0 has no interim responses, so expectations don't apply.
+    if req.version() != Version::HTTP_10 {
+        if let Some(expect) = req.headers().get(EXPECT) {
+            let continue_ = expect
+                .to_str()
+                .is_ok_and(|v| v.trim().eq_ignore_ascii_case("100-continue"));
+            if !continue_ {
+                return Some(
+                    ErrorResponse::new(StatusCode::EXPECTATION_FAILED, "expectation_failed")
+                        .detail("Only `Expect: 100-continue` is supported")
+                        .into_response(),
+                );
+            }
+        }
+    }
+
+    let limit = limit?;
+    let declared = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())?;
+    (declared > limit as u64).then(|| too_large(limit))
+}
+
+/// Buffer `body`, failing with [`IntakeError::TooLarge`] once it exceeds
+/// `limit` bytes (`None` = unlimited).
+pub(crate) async fn read_body<B>(body: B, limit: Option<usize>) -> Result<Bytes, IntakeError>
+where
+    B: hyper::body::Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let Some(limit) = limit else {
+        return body
+            .collect()
+            .await
+            .map(|c| c.to_bytes())
+            .map_err(|e| IntakeError::Read(e.into().to_string()));
+    };
+    match Limited::new(body, limit).collect().await {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(e) if e.is::<LengthLimitError>() => Err(IntakeError::TooLarge(limit)),
+        Err(e) => Err(IntakeError::Read(e.to_string())),
+    }
+}
+
+/// `413 Payload Too Large` for a body above `limit` bytes
+pub(crate) fn too_large(limit: usize) -> Response<Full<Bytes>> {
+    ErrorResponse::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large")
+        .detail(format!("Request body exceeds {limit} bytes"))
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Serve connections the way the handler does: check, then buffer.
+    async fn serve(limit: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = hyper::service::service_fn(
+                    move |req: Request<hyper::body::Incoming>| async move {
+                        if let Some(rejection) = check_request(&req, Some(limit)) {
+                            return Ok::<_, Infallible>(rejection);
+                        }
+                        Ok(match read_body(req.into_body(), Some(limit)).await {
+                            Ok(body) => Response::new(Full::new(Bytes::from(format!(
+                                "read {}",
+                                body.len()
+                            )))),
+                            Err(IntakeError::TooLarge(limit)) => too_large(limit),
+                            Err(IntakeError::Read(e)) => Response::new(Full::new(e.into())),
+                        })
+                    },
+                );
+                tokio::spawn(
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+                );
+            }
+        });
+        addr
+    }
+
+    /// Read from `stream` until a complete head (and `body_len` body bytes)
+    /// has arrived.
+    async fn read_response(stream: &mut TcpStream, body_len: usize) -> String {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                if buf.len() >= end + 4 + body_len {
+                    return String::from_utf8(buf).unwrap();
+                }
+            }
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert!(
+                n > 0,
+                "connection closed: {:?}",
+                String::from_utf8_lossy(&buf)
+            );
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_sends_interim_response_before_body() {
+        let addr = serve(1024).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST /upload HTTP/1.1\r\nhost: gw\r\ncontent-length: 5\r\n\
+                  expect: 100-continue\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let interim = read_response(&mut stream, 0).await;
+        assert!(interim.starts_with("HTTP/1.1 100 Continue"), "{interim}");
+
+        stream.write_all(b"hello").await.unwrap();
+        let response = read_response(&mut stream, "read 5".len()).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("read 5"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_declared_length_rejected_before_continue() {
+        let addr = serve(1024).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST /upload HTTP/1.1\r\nhost: gw\r\ncontent-length: 4096\r\n\
+                  expect: 100-continue\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        // The final 413 arrives instead of `100 Continue`; no body was sent.
+        let response = read_response(&mut stream, 0).await;
+        assert!(
+            response.starts_with("HTTP/1.1 413 Payload Too Large"),
+            "{response}"
+        );
+    }
+
+    #[test]
+    fn test_check_request() {
+        let req = |headers: &[(&str, &str)]| {
+            let mut builder = Request::builder().method("POST").uri("/");
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+            builder.body(()).unwrap()
+        };
+
+        assert!(check_request(&req(&[("content-length", "1024")]), Some(1024)).is_none());
+        assert!(check_request(&req(&[("content-length", "4096")]), None).is_none());
+        assert_eq!(
+            check_request(&req(&[("content-length", "1025")]), Some(1024))
+                .unwrap()
+                .status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            check_request(&req(&[("expect", "something-else")]), None)
+                .unwrap()
+                .status(),
+            StatusCode::EXPECTATION_FAILED
+        );
+        assert!(check_request(&req(&[("expect", "100-Continue")]), None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_body_enforces_limit_without_declared_length() {
+        let body = Full::new(Bytes::from(vec![b'x'; 2048]));
+        assert!(matches!(
+            read_body(body, Some(1024)).await,
+            Err(IntakeError::TooLarge(1024))
+        ));
+
+        let body = Full::new(Bytes::from_static(b"small"));
+        assert_eq!(read_body(body, Some(1024)).await.unwrap(), "small");
+    }
+}
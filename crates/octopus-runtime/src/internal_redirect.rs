@@ -0,0 +1,138 @@
+//! Internal redirects requested by upstreams (`X-Accel-Redirect`).
+//!
+//! An upstream answers with a header naming another resource, typically a
+//! file on a storage backend it has authorized the client for. The gateway
+//! discards that response, fetches the named path through its own routes and
+//! returns the result, so the client never sees the intermediate response or
+//! where the resource lives.
+
+use bytes::Bytes;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE, EXPECT, TRANSFER_ENCODING};
+use http::uri::PathAndQuery;
+use http::{HeaderName, HeaderValue, Method, Request, Response, Uri};
+use http_body_util::Full;
+use octopus_config::types::InternalRedirectConfig;
+
+use crate::multipart::StreamedBody;
+
+/// Internal redirect handling from `gateway.internal_redirect`
+#[derive(Debug, Clone)]
+pub(crate) struct InternalRedirect {
+    header: HeaderName,
+    /// Most redirects followed for one request
+    pub(crate) max_hops: u32,
+}
+
+impl InternalRedirect {
+    /// `None` when the configured header isn't a valid name (rejected by
+    /// config validation)
+    pub(crate) fn new(config: &InternalRedirectConfig) -> Option<Self> {
+        Some(Self {
+            header: HeaderName::try_from(config.header.as_str()).ok()?,
+            max_hops: config.max_hops,
+        })
+    }
+
+    /// The redirect `response` asks for, if any
+    pub(crate) fn target<'a, B>(&self, response: &'a Response<B>) -> Option<&'a HeaderValue> {
+        response.headers().get(&self.header)
+    }
+
+    /// The request fetching `target` on behalf of `original`: a `GET` (or
+    /// `HEAD` for a `HEAD`) without a body, carrying the client's headers
+    /// and request context. `None` if `target` isn't a path.
+    pub(crate) fn request(
+        original: &Request<()>,
+        target: &HeaderValue,
+    ) -> Option<Request<Full<Bytes>>> {
+        let target = target.to_str().ok()?.trim();
+        if !target.starts_with('/') || target.starts_with("//") {
+            return None;
+        }
+        let mut uri = original.uri().clone().into_parts();
+        uri.path_and_query = Some(PathAndQuery::try_from(target).ok()?);
+        let method = match *original.method() {
+            Method::HEAD => Method::HEAD,
+            _ => Method::GET,
+        };
+
+        let mut req = Request::new(Full::new(Bytes::new()));
+        *req.method_mut() = method;
+        *req.uri_mut() = Uri::from_parts(uri).ok()?;
+        *req.version_mut() = original.version();
+        *req.headers_mut() = original.headers().clone();
+        for name in [CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING, EXPECT] {
+            req.headers_mut().remove(name);
+        }
+        *req.extensions_mut() = original.extensions().clone();
+        req.extensions_mut().remove::<StreamedBody>();
+        Some(req)
+    }
+}
+
+/// Everything of `req` but its body, to derive redirected requests from
+pub(crate) fn template<B>(req: &Request<B>) -> Request<()> {
+    let mut template = Request::new(());
+    *template.method_mut() = req.method().clone();
+    *template.uri_mut() = req.uri().clone();
+    *template.version_mut() = req.version();
+    *template.headers_mut() = req.headers().clone();
+    *template.extensions_mut() = req.extensions().clone();
+    template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect() -> InternalRedirect {
+        InternalRedirect::new(&InternalRedirectConfig {
+            header: "x-accel-redirect".to_string(),
+            max_hops: 3,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn redirected_request_is_a_bodyless_get_for_the_target() {
+        let original = template(
+            &Request::builder()
+                .method(Method::POST)
+                .uri("http://api.example.com/downloads/7?token=x")
+                .header(CONTENT_TYPE, "application/json")
+                .header(CONTENT_LENGTH, "2")
+                .header("authorization", "Bearer abc")
+                .body(Full::new(Bytes::from_static(b"{}")))
+                .unwrap(),
+        );
+        let response = Response::builder()
+            .header("X-Accel-Redirect", "/storage/files/7.pdf?sig=abc")
+            .body(())
+            .unwrap();
+
+        let target = redirect().target(&response).unwrap();
+        let req = InternalRedirect::request(&original, target).unwrap();
+        assert_eq!(req.method(), Method::GET);
+        assert_eq!(
+            req.uri(),
+            "http://api.example.com/storage/files/7.pdf?sig=abc"
+        );
+        assert_eq!(req.headers()["authorization"], "Bearer abc");
+        assert!(!req.headers().contains_key(CONTENT_TYPE));
+        assert!(!req.headers().contains_key(CONTENT_LENGTH));
+    }
+
+    #[test]
+    fn only_paths_are_followed() {
+        let original = template(&Request::builder().uri("/a").body(()).unwrap());
+        for target in ["https://evil.example/x", "//evil.example/x", "files/7"] {
+            let value = HeaderValue::from_static(target);
+            assert!(
+                InternalRedirect::request(&original, &value).is_none(),
+                "{target}"
+            );
+        }
+        let response = Response::new(());
+        assert!(redirect().target(&response).is_none());
+    }
+}
@@ -6,6 +6,7 @@
 //! - Worker thread management
 //! - Hot reload support
 //! - Health monitoring
+//! - Gateway event webhooks
 
 #![forbid(unsafe_code)]
 #![warn(
@@ -17,20 +18,42 @@
 
 pub mod admin;
 mod chain;
+mod conn_limits;
+pub mod events;
+pub mod fallback;
+pub mod farp_schemas;
 pub mod handler;
+mod health_checks;
+mod intake;
+mod internal_redirect;
+mod multipart;
 pub mod lifecycle;
+mod listener;
+pub mod plugins;
 pub mod probes;
+mod proxy_protocol;
 pub mod redirect;
+mod reload;
+mod reuseport;
 pub mod server;
 pub mod shutdown;
+mod startup_check;
+#[cfg(unix)]
+mod unix_socket;
+pub mod unmatched;
+mod warmup;
 pub mod worker;
 
 pub use admin::AdminHandler;
-pub use handler::RequestHandler;
+pub use events::{EventBus, EventSink, GatewayEvent, WebhookSink};
+pub use handler::{Plane, RequestHandler};
 pub use lifecycle::LifecycleState;
+pub use plugins::PluginFactory;
 pub use probes::ProbeRoutes;
+pub use reload::build_router;
 pub use server::{Server, ServerBuilder};
-pub use shutdown::{ShutdownSignal, SignalHandler};
+pub use shutdown::{BackgroundTasks, ShutdownSequence, ShutdownSignal, SignalHandler};
+pub use worker::{WorkerConfig, WorkerPool};
 
 /// Runtime state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
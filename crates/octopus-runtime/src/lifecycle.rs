@@ -6,8 +6,12 @@
 //! and (via [`LifecycleState::discovery_synced_flag`]) the service-discovery
 //! watcher, so every component observes the same lifecycle.
 
+use octopus_state::{BackendHealthSource, HealthStatus};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+
+/// A named shared state store readiness depends on
+pub type StateBackendCheck = (&'static str, Arc<dyn BackendHealthSource>);
 
 /// Shared, lock-free liveness/readiness/startup state.
 ///
@@ -37,6 +41,9 @@ struct Inner {
     discovery_synced: Arc<AtomicBool>,
     /// Whether readiness must wait for discovery to sync.
     discovery_required: bool,
+    /// Shared state stores the middleware chain uses; readiness fails while
+    /// one is degraded. Set once the chain is built.
+    state_backends: OnceLock<Vec<StateBackendCheck>>,
 }
 
 impl LifecycleState {
@@ -56,6 +63,7 @@ impl LifecycleState {
             // depends on running + config.
             discovery_synced: Arc::new(AtomicBool::new(!discovery_required)),
             discovery_required,
+            state_backends: OnceLock::new(),
         };
         Self {
             inner: Arc::new(inner),
@@ -92,6 +100,12 @@ impl LifecycleState {
         self.inner.discovery_synced.store(true, Ordering::Release);
     }
 
+    /// Make readiness depend on `backends` reaching their stores. Only the
+    /// first call takes effect.
+    pub fn set_state_backends(&self, backends: Vec<StateBackendCheck>) {
+        let _ = self.inner.state_backends.set(backends);
+    }
+
     /// Begin draining: readiness flips to NotReady immediately.
     pub fn begin_draining(&self) {
         self.inner.draining.store(true, Ordering::Release);
@@ -115,11 +129,30 @@ impl LifecycleState {
 
     /// Readiness: ready to receive new traffic.
     pub fn is_ready(&self) -> bool {
-        self.inner.running.load(Ordering::Acquire)
-            && !self.inner.draining.load(Ordering::Acquire)
-            && self.inner.config_loaded.load(Ordering::Acquire)
-            && (!self.inner.discovery_required
-                || self.inner.discovery_synced.load(Ordering::Acquire))
+        self.readiness_checks().iter().all(|(_, ok)| *ok)
+    }
+
+    /// The individual readiness conditions as `(name, passed)` pairs, in the
+    /// order the readiness probe reports them.
+    pub fn readiness_checks(&self) -> Vec<(&'static str, bool)> {
+        let mut checks = vec![
+            ("running", self.inner.running.load(Ordering::Acquire)),
+            (
+                "config_loaded",
+                self.inner.config_loaded.load(Ordering::Acquire),
+            ),
+            ("not_draining", !self.inner.draining.load(Ordering::Acquire)),
+        ];
+        if self.inner.discovery_required {
+            checks.push((
+                "discovery_synced",
+                self.inner.discovery_synced.load(Ordering::Acquire),
+            ));
+        }
+        for (name, backend) in self.inner.state_backends.get().into_iter().flatten() {
+            checks.push((*name, backend.health().status == HealthStatus::Connected));
+        }
+        checks
     }
 
     /// Startup: the listener has bound.
@@ -182,6 +215,39 @@ mod tests {
         assert!(lc.is_started(), "still started while draining");
     }
 
+    #[test]
+    fn readiness_fails_while_a_state_backend_is_degraded() {
+        #[derive(Debug)]
+        struct Store(AtomicBool);
+        impl BackendHealthSource for Store {
+            fn health(&self) -> octopus_state::BackendHealth {
+                let connected = self.0.load(Ordering::Acquire);
+                octopus_state::BackendHealth {
+                    status: if connected {
+                        HealthStatus::Connected
+                    } else {
+                        HealthStatus::Degraded
+                    },
+                    consecutive_failures: u64::from(!connected),
+                    last_error: None,
+                }
+            }
+        }
+
+        let lc = LifecycleState::new(false);
+        lc.mark_config_loaded();
+        lc.mark_running();
+        let store = Arc::new(Store(AtomicBool::new(false)));
+        lc.set_state_backends(vec![("idempotency_store", Arc::clone(&store) as _)]);
+        assert!(!lc.is_ready());
+        assert!(lc
+            .readiness_checks()
+            .contains(&("idempotency_store", false)));
+
+        store.0.store(true, Ordering::Release);
+        assert!(lc.is_ready());
+    }
+
     #[test]
     fn stopped_reports_dead() {
         let lc = LifecycleState::new(false);
@@ -0,0 +1,246 @@
+//! Frontend connection protocol selection.
+//!
+//! Over TLS a connection speaks the HTTP version negotiated through ALPN
+//! (`h2` or `http/1.1`); a client that offers no ALPN is detected from its
+//! first bytes. On a plaintext listener HTTP/2 needs prior knowledge (h2c),
+//! which `gateway.h2c` allows; without it only HTTP/1.1 is served.
+
+use crate::conn_limits::{
+    supervise, Watchdog, WatchedBody, WatchedFuture, WatchedIo, WatchedService,
+};
+use http::{Request, Response};
+use hyper::body::{Body, Incoming};
+use hyper::rt::bounds::Http2ServerConnExec;
+use hyper::server::conn::{http1, http2};
+use hyper::service::Service;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// HTTP versions a frontend connection may speak
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HttpVersions {
+    /// HTTP/1.1 only
+    Http1,
+    /// HTTP/2 only
+    Http2,
+    /// Either, detected from the connection preface
+    Auto,
+}
+
+impl HttpVersions {
+    /// For a TLS connection, from the protocol its handshake negotiated
+    pub(crate) fn for_tls(alpn: Option<&[u8]>) -> Self {
+        match alpn {
+            Some(b"h2") => Self::Http2,
+            Some(b"http/1.1") => Self::Http1,
+            _ => Self::Auto,
+        }
+    }
+
+    /// For a plaintext connection
+    pub(crate) fn for_plaintext(h2c: bool) -> Self {
+        if h2c {
+            Self::Auto
+        } else {
+            Self::Http1
+        }
+    }
+}
+
+/// Serve `io` with `service` over the HTTP `versions` it may speak, within
+/// the connection limits `watchdog` enforces. HTTP/1.1 connections keep
+/// supporting upgrades (WebSocket).
+pub(crate) async fn serve_connection<IO, S, B>(
+    io: IO,
+    service: S,
+    versions: HttpVersions,
+    watchdog: Watchdog,
+) -> Result<(), BoxError>
+where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Service<Request<Incoming>, Response = Response<B>>,
+    S::Future: 'static,
+    S::Error: Into<BoxError>,
+    B: Body + Unpin + 'static,
+    B::Error: Into<BoxError>,
+    TokioExecutor: Http2ServerConnExec<WatchedFuture<S::Future>, WatchedBody<B>>,
+{
+    if versions == HttpVersions::Http2 {
+        watchdog.expect_http2();
+    }
+    let watchdog = Arc::new(watchdog);
+    let io = TokioIo::new(WatchedIo::new(io, Arc::clone(&watchdog)));
+    let service = WatchedService::new(service, Arc::clone(&watchdog));
+
+    // `auto::Builder::http1_only`/`http2_only` have no effect on
+    // connections served with upgrades, so pick the protocol builder here.
+    match versions {
+        HttpVersions::Http1 => {
+            let conn = http1::Builder::new()
+                .serve_connection(io, service)
+                .with_upgrades();
+            supervise(conn, &watchdog, |conn| conn.graceful_shutdown())
+                .await
+                .map_err(Into::into)
+        }
+        HttpVersions::Http2 => {
+            let conn = http2::Builder::new(TokioExecutor::new()).serve_connection(io, service);
+            supervise(conn, &watchdog, |conn| conn.graceful_shutdown())
+                .await
+                .map_err(Into::into)
+        }
+        HttpVersions::Auto => {
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let conn = builder.serve_connection_with_upgrades(io, service);
+            supervise(conn, &watchdog, |conn| conn.graceful_shutdown()).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn_limits::ConnectionLimits;
+    use bytes::Bytes;
+    use http::Version;
+    use http_body_util::{BodyExt, Empty, Full};
+
+    /// Serve one connection, answering every request with its HTTP version
+    fn serve<IO>(io: IO, versions: HttpVersions)
+    where
+        IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let service =
+            hyper::service::service_fn(|req: Request<hyper::body::Incoming>| async move {
+                let body = format!("{:?}", req.version());
+                Ok::<_, std::convert::Infallible>(Response::new(Full::new(Bytes::from(body))))
+            });
+        let watchdog = ConnectionLimits::default().watch();
+        tokio::spawn(serve_connection(io, service, versions, watchdog));
+    }
+
+    /// The version a request over `io` was served with, or `None` if the
+    /// server refused the protocol
+    async fn request<IO>(io: IO, version: Version) -> Option<String>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let io = TokioIo::new(io);
+        let req = Request::builder()
+            .uri("https://gateway.test/")
+            .body(Empty::<Bytes>::new())
+            .unwrap();
+        let res = if version == Version::HTTP_2 {
+            let (mut sender, conn) =
+                hyper::client::conn::http2::handshake(TokioExecutor::new(), io)
+                    .await
+                    .ok()?;
+            tokio::spawn(conn);
+            sender.send_request(req).await.ok()?
+        } else {
+            let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await.ok()?;
+            tokio::spawn(conn);
+            sender.send_request(req).await.ok()?
+        };
+        let body = res.into_body().collect().await.ok()?.to_bytes();
+        Some(String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    /// A TLS connection negotiated with the client offering `alpn`; returns
+    /// the client stream and the server's protocol choice
+    async fn tls_connection(
+        alpn: &[&[u8]],
+    ) -> (
+        tokio_rustls::client::TlsStream<tokio::io::DuplexStream>,
+        tokio_rustls::server::TlsStream<tokio::io::DuplexStream>,
+    ) {
+        let cert = rcgen::generate_simple_self_signed(vec!["gateway.test".to_string()]).unwrap();
+        let server_config = octopus_tls::build_server_config_from_pem(
+            cert.cert.pem().as_bytes(),
+            cert.key_pair.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert.cert.der().clone()).unwrap();
+        let mut client_config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client_config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+        let name = rustls::pki_types::ServerName::try_from("gateway.test").unwrap();
+        let (client, server) = tokio::join!(
+            connector.connect(name, client_io),
+            acceptor.accept(server_io)
+        );
+        (client.unwrap(), server.unwrap())
+    }
+
+    #[tokio::test]
+    async fn h2_negotiated_over_tls_is_served_as_http2() {
+        let (client, server) = tls_connection(&[b"h2", b"http/1.1"]).await;
+        let alpn = octopus_tls::extract_alpn_protocol(&server);
+        assert_eq!(alpn.as_deref(), Some(&b"h2"[..]));
+
+        let versions = HttpVersions::for_tls(alpn.as_deref());
+        assert_eq!(versions, HttpVersions::Http2);
+        serve(server, versions);
+        assert_eq!(
+            request(client, Version::HTTP_2).await.as_deref(),
+            Some("HTTP/2.0")
+        );
+    }
+
+    #[tokio::test]
+    async fn http1_over_tls_still_works() {
+        let (client, server) = tls_connection(&[b"http/1.1"]).await;
+        let alpn = octopus_tls::extract_alpn_protocol(&server);
+        assert_eq!(alpn.as_deref(), Some(&b"http/1.1"[..]));
+
+        serve(server, HttpVersions::for_tls(alpn.as_deref()));
+        assert_eq!(
+            request(client, Version::HTTP_11).await.as_deref(),
+            Some("HTTP/1.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn tls_without_alpn_detects_the_version() {
+        for version in [Version::HTTP_11, Version::HTTP_2] {
+            let (client, server) = tls_connection(&[]).await;
+            let alpn = octopus_tls::extract_alpn_protocol(&server);
+            assert_eq!(alpn, None);
+
+            serve(server, HttpVersions::for_tls(None));
+            assert_eq!(request(client, version).await, Some(format!("{version:?}")));
+        }
+    }
+
+    #[tokio::test]
+    async fn h2c_prior_knowledge_is_optional_on_plaintext() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        serve(server, HttpVersions::for_plaintext(true));
+        assert_eq!(
+            request(client, Version::HTTP_2).await.as_deref(),
+            Some("HTTP/2.0")
+        );
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        serve(server, HttpVersions::for_plaintext(true));
+        assert_eq!(
+            request(client, Version::HTTP_11).await.as_deref(),
+            Some("HTTP/1.1")
+        );
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        serve(server, HttpVersions::for_plaintext(false));
+        assert_eq!(request(client, Version::HTTP_2).await, None);
+    }
+}
@@ -0,0 +1,642 @@
+//! `multipart/form-data` request bodies: part and total size limits and a
+//! field allowlist, enforced as the body streams through.
+//!
+//! [`MultipartBody`] wraps a request body and follows its multipart framing
+//! frame by frame, holding no more than a possible partial boundary (or one
+//! part's headers) at a time, so a body can be checked while it is being
+//! forwarded. The first limit broken ends the body with an error and records
+//! the [`Violation`] for the handler to answer with.
+//!
+//! A body that is streamed rather than buffered is parked in request
+//! extensions as a [`StreamedBody`] while the middleware chain runs on an
+//! empty one, and sent by the proxy once the chain lets the request through.
+
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, Request, Response, StatusCode};
+use http_body_util::Full;
+use hyper::body::{Body, Frame, Incoming, SizeHint};
+use octopus_config::types::MultipartConfig;
+use octopus_core::{Error, ErrorResponse, Result, UpstreamInstance};
+use octopus_proxy::HttpProxy;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{ready, Context, Poll};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Longest header block a part may have
+const MAX_PART_HEADER_BYTES: usize = 16 * 1024;
+
+/// Limits from `gateway.multipart`
+#[derive(Debug, Clone)]
+pub(crate) struct MultipartLimits {
+    max_part: u64,
+    max_total: u64,
+    /// Empty allows any field
+    allowed_fields: Arc<HashSet<String>>,
+    /// Forward the body as it arrives instead of buffering it
+    pub(crate) stream: bool,
+}
+
+impl MultipartLimits {
+    pub(crate) fn new(config: &MultipartConfig) -> Self {
+        Self {
+            max_part: config.max_part_size as u64,
+            max_total: config.max_total_size as u64,
+            allowed_fields: Arc::new(config.allowed_fields.iter().cloned().collect()),
+            stream: config.stream,
+        }
+    }
+
+    /// Largest body accepted, for the declared `Content-Length` check
+    pub(crate) fn max_total(&self) -> usize {
+        self.max_total as usize
+    }
+}
+
+/// The boundary of a `multipart/form-data` request, or `None` for any other
+/// content type
+pub(crate) fn boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    let boundary = parameters(params)
+        .find_map(|(name, value)| name.eq_ignore_ascii_case("boundary").then_some(value))?;
+    (!boundary.is_empty() && boundary.len() <= 70).then_some(boundary)
+}
+
+/// `name=value` pairs of a `;`-separated parameter list, quoted values
+/// unquoted. A `;` inside quotes doesn't separate.
+fn parameters(list: &str) -> impl Iterator<Item = (&str, String)> {
+    let mut rest = list;
+    std::iter::from_fn(move || loop {
+        let trimmed = rest.trim_start_matches([';', ' ', '\t']);
+        if trimmed.is_empty() {
+            return None;
+        }
+        let (name, after) = trimmed.split_once('=').unwrap_or((trimmed, ""));
+        if name.contains(';') {
+            // A bare token without a value: skip it.
+            rest = &trimmed[name.find(';').unwrap_or(name.len())..];
+            continue;
+        }
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        rest = remaining;
+        return Some((name.trim(), value));
+    })
+}
+
+/// Why a multipart body was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Violation {
+    /// A part exceeded this many bytes
+    PartTooLarge(u64),
+    /// The body exceeded this many bytes
+    TooLarge(u64),
+    /// A part named a field not in the allowlist
+    FieldNotAllowed(String),
+    /// The body isn't valid multipart
+    Malformed(&'static str),
+}
+
+impl Violation {
+    /// The response the client gets
+    pub(crate) fn response(&self) -> Response<Full<Bytes>> {
+        let problem = match self {
+            Self::PartTooLarge(limit) => {
+                ErrorResponse::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large")
+                    .detail(format!("A multipart part exceeds {limit} bytes"))
+            }
+            Self::TooLarge(limit) => return crate::intake::too_large(*limit as usize),
+            Self::FieldNotAllowed(name) => {
+                ErrorResponse::new(StatusCode::BAD_REQUEST, "multipart_field_not_allowed")
+                    .detail(format!("Multipart field {name:?} is not allowed"))
+            }
+            Self::Malformed(reason) => {
+                ErrorResponse::new(StatusCode::BAD_REQUEST, "malformed_multipart").detail(*reason)
+            }
+        };
+        problem.into_response()
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PartTooLarge(limit) => write!(f, "multipart part exceeds {limit} bytes"),
+            Self::TooLarge(limit) => write!(f, "multipart body exceeds {limit} bytes"),
+            Self::FieldNotAllowed(name) => write!(f, "multipart field {name:?} is not allowed"),
+            Self::Malformed(reason) => write!(f, "malformed multipart body: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the first boundary
+    Preamble,
+    /// Inside a part's content
+    Content,
+    /// Just past a boundary: `--` closes the body, a line break opens a part
+    Delimiter,
+    /// Inside a part's headers
+    Headers,
+    /// Past the closing boundary
+    Done,
+}
+
+/// Follows the multipart framing of a body fed to it chunk by chunk
+#[derive(Debug)]
+struct Scanner {
+    /// `\r\n--boundary`, which ends the preamble and every part
+    delimiter: Vec<u8>,
+    limits: MultipartLimits,
+    state: State,
+    /// Bytes not consumed yet: a possible start of the delimiter, or part
+    /// headers still missing their end
+    pending: Vec<u8>,
+    part: u64,
+    total: u64,
+}
+
+impl Scanner {
+    fn new(boundary: &str, limits: MultipartLimits) -> Self {
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+        Self {
+            delimiter,
+            limits,
+            state: State::Preamble,
+            // The body may open with the boundary itself, without a line
+            // break before it.
+            pending: b"\r\n".to_vec(),
+            part: 0,
+            total: 0,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> std::result::Result<(), Violation> {
+        self.total += chunk.len() as u64;
+        if self.total > self.limits.max_total {
+            return Err(Violation::TooLarge(self.limits.max_total));
+        }
+        if self.state == State::Done {
+            return Ok(());
+        }
+        self.pending.extend_from_slice(chunk);
+
+        let mut at = 0;
+        loop {
+            let rest = &self.pending[at..];
+            match self.state {
+                State::Preamble | State::Content => {
+                    let (end, found) = find_delimiter(rest, &self.delimiter);
+                    if self.state == State::Content {
+                        self.part += end as u64;
+                        if self.part > self.limits.max_part {
+                            return Err(Violation::PartTooLarge(self.limits.max_part));
+                        }
+                    }
+                    at += end;
+                    if !found {
+                        break;
+                    }
+                    at += self.delimiter.len();
+                    self.state = State::Delimiter;
+                }
+                State::Delimiter => {
+                    if rest.starts_with(b"--") {
+                        self.state = State::Done;
+                        at = self.pending.len();
+                        break;
+                    }
+                    // Transport padding may follow the boundary.
+                    let padding = rest
+                        .iter()
+                        .take_while(|b| matches!(b, b' ' | b'\t'))
+                        .count();
+                    if rest.len() < padding + 2 {
+                        break;
+                    }
+                    if &rest[padding..padding + 2] != b"\r\n" {
+                        return Err(Violation::Malformed("invalid boundary line"));
+                    }
+                    at += padding + 2;
+                    self.state = State::Headers;
+                }
+                State::Headers => {
+                    let (block, consumed) = if rest.starts_with(b"\r\n") {
+                        (&rest[..0], 2)
+                    } else {
+                        match rest.windows(4).position(|w| w == b"\r\n\r\n") {
+                            Some(end) => (&rest[..end], end + 4),
+                            None if rest.len() > MAX_PART_HEADER_BYTES => {
+                                return Err(Violation::Malformed("part headers too large"));
+                            }
+                            None => break,
+                        }
+                    };
+                    self.check_headers(block)?;
+                    at += consumed;
+                    self.part = 0;
+                    self.state = State::Content;
+                }
+                State::Done => break,
+            }
+        }
+        self.pending.drain(..at);
+        Ok(())
+    }
+
+    /// The body ended: it must have been closed
+    fn finish(&self) -> std::result::Result<(), Violation> {
+        match self.state {
+            State::Done => Ok(()),
+            _ => Err(Violation::Malformed(
+                "body ends before the closing boundary",
+            )),
+        }
+    }
+
+    /// Check a part's field name against the allowlist
+    fn check_headers(&self, block: &[u8]) -> std::result::Result<(), Violation> {
+        if self.limits.allowed_fields.is_empty() {
+            return Ok(());
+        }
+        let block = std::str::from_utf8(block)
+            .map_err(|_| Violation::Malformed("part headers are not UTF-8"))?;
+        let mut names = block
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("content-disposition"))
+            .flat_map(|(_, value)| {
+                let params = value.split_once(';').map_or("", |(_, params)| params);
+                parameters(params)
+                    .filter(|(name, _)| name.eq_ignore_ascii_case("name"))
+                    .map(|(_, value)| value)
+            });
+        let name = names.next().unwrap_or_default();
+        if names.next().is_some() {
+            // Parsers disagree on which of several names wins.
+            return Err(Violation::Malformed("part names more than one field"));
+        }
+        if self.limits.allowed_fields.contains(&name) {
+            Ok(())
+        } else {
+            Err(Violation::FieldNotAllowed(name))
+        }
+    }
+}
+
+/// Where `needle` starts in `hay` and whether all of it is there: a match
+/// cut off by the end of `hay` is reported as not found, at its start, so
+/// the bytes from there on are kept for the next chunk
+fn find_delimiter(hay: &[u8], needle: &[u8]) -> (usize, bool) {
+    let mut from = 0;
+    while let Some(offset) = hay[from..].iter().position(|&b| b == needle[0]) {
+        let start = from + offset;
+        let candidate = &hay[start..];
+        if candidate.len() >= needle.len() {
+            if candidate.starts_with(needle) {
+                return (start, true);
+            }
+        } else if needle.starts_with(candidate) {
+            return (start, false);
+        }
+        from = start + 1;
+    }
+    (hay.len(), false)
+}
+
+/// A request body checked against [`MultipartLimits`] as it is read
+#[derive(Debug)]
+pub(crate) struct MultipartBody<B> {
+    inner: B,
+    scanner: Scanner,
+    violation: Arc<OnceLock<Violation>>,
+    failed: bool,
+}
+
+impl<B> MultipartBody<B> {
+    pub(crate) fn new(inner: B, boundary: &str, limits: MultipartLimits) -> Self {
+        Self {
+            inner,
+            scanner: Scanner::new(boundary, limits),
+            violation: Arc::new(OnceLock::new()),
+            failed: false,
+        }
+    }
+
+    /// Where the first violation is recorded, still readable once the body
+    /// has been handed off
+    pub(crate) fn violation(&self) -> Arc<OnceLock<Violation>> {
+        Arc::clone(&self.violation)
+    }
+
+    fn fail(&mut self, violation: Violation) -> BoxError {
+        self.failed = true;
+        let _ = self.violation.set(violation.clone());
+        Box::new(violation)
+    }
+}
+
+impl<B> Body for MultipartBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, BoxError>>> {
+        let this = &mut *self;
+        if this.failed {
+            return Poll::Ready(None);
+        }
+        match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    if let Err(violation) = this.scanner.feed(data) {
+                        return Poll::Ready(Some(Err(this.fail(violation))));
+                    }
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Some(Err(e)) => Poll::Ready(Some(Err(e.into()))),
+            None => match this.scanner.finish() {
+                Ok(()) => Poll::Ready(None),
+                Err(violation) => Poll::Ready(Some(Err(this.fail(violation)))),
+            },
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A multipart body left unread for the proxy to stream, carried in request
+/// extensions past the middleware chain
+#[derive(Debug, Clone)]
+pub(crate) struct StreamedBody {
+    body: Arc<Mutex<Option<MultipartBody<Incoming>>>>,
+    violation: Arc<OnceLock<Violation>>,
+}
+
+impl StreamedBody {
+    pub(crate) fn new(body: MultipartBody<Incoming>) -> Self {
+        Self {
+            violation: body.violation(),
+            body: Arc::new(Mutex::new(Some(body))),
+        }
+    }
+
+    /// Proxy `req` to `instance` with the parked body in place of its own.
+    /// A body stopped for breaking a limit is answered with that violation
+    /// rather than the upstream failure it caused.
+    pub(crate) async fn proxy(
+        self,
+        proxy: &HttpProxy,
+        req: Request<Full<Bytes>>,
+        instance: &UpstreamInstance,
+    ) -> Result<Response<Full<Bytes>>> {
+        let Some(body) = self.body.lock().take() else {
+            return Err(Error::Internal(
+                "Streamed request body was already sent".to_string(),
+            ));
+        };
+        let (parts, _) = req.into_parts();
+        let result = proxy
+            .proxy_streaming(Request::from_parts(parts, body), instance)
+            .await;
+        match self.violation.get() {
+            Some(violation) => Ok(violation.response()),
+            None => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::SinkExt;
+    use http_body_util::{BodyExt, StreamBody};
+    use std::convert::Infallible;
+
+    const BOUNDARY: &str = "XyZ123";
+
+    fn limits(max_part: usize, max_total: usize, allowed: &[&str]) -> MultipartLimits {
+        MultipartLimits::new(&MultipartConfig {
+            max_part_size: max_part,
+            max_total_size: max_total,
+            stream: true,
+            allowed_fields: allowed.iter().map(|f| f.to_string()).collect(),
+        })
+    }
+
+    /// A multipart body with one part per `(field, content)`
+    fn form(parts: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (field, content) in parts {
+            body.extend_from_slice(
+                format!(
+                    "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{field}\"; \
+                     filename=\"{field}.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+        body
+    }
+
+    /// Feed `body` to a scanner in chunks of `size` bytes
+    fn scan(
+        body: &[u8],
+        size: usize,
+        limits: MultipartLimits,
+    ) -> std::result::Result<(), Violation> {
+        let mut scanner = Scanner::new(BOUNDARY, limits);
+        for chunk in body.chunks(size) {
+            scanner.feed(chunk)?;
+        }
+        scanner.finish()
+    }
+
+    #[test]
+    fn boundary_comes_from_a_form_data_content_type() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            "multipart/form-data; charset=utf-8; boundary=\"a b;c\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(boundary(&headers).as_deref(), Some("a b;c"));
+
+        headers.insert(CONTENT_TYPE, "multipart/mixed; boundary=x".parse().unwrap());
+        assert_eq!(boundary(&headers), None);
+        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        assert_eq!(boundary(&headers), None);
+    }
+
+    #[test]
+    fn limits_hold_however_the_body_is_chunked() {
+        let body = form(&[("avatar", &[b'a'; 100]), ("bio", b"hello")]);
+        for size in [1, 2, 7, 64, body.len()] {
+            assert_eq!(scan(&body, size, limits(100, 4096, &[])), Ok(()), "{size}");
+            assert_eq!(
+                scan(&body, size, limits(99, 4096, &[])),
+                Err(Violation::PartTooLarge(99)),
+                "{size}"
+            );
+            assert_eq!(
+                scan(&body, size, limits(100, 200, &[])),
+                Err(Violation::TooLarge(200)),
+                "{size}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_allowed_fields_pass() {
+        let body = form(&[("avatar", b"png"), ("bio", b"hello")]);
+        assert_eq!(
+            scan(&body, 16, limits(100, 4096, &["avatar", "bio"])),
+            Ok(())
+        );
+        assert_eq!(
+            scan(&body, 16, limits(100, 4096, &["avatar"])),
+            Err(Violation::FieldNotAllowed("bio".to_string()))
+        );
+
+        let twice = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"x\"; name=\"avatar\"\r\n\r\n\
+             png\r\n--{BOUNDARY}--\r\n"
+        );
+        assert!(matches!(
+            scan(twice.as_bytes(), 16, limits(100, 4096, &["avatar"])),
+            Err(Violation::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn unterminated_bodies_are_malformed() {
+        let body = form(&[("avatar", b"png")]);
+        let cut = &body[..body.len() - 10];
+        assert!(matches!(
+            scan(cut, 8, limits(100, 4096, &[])),
+            Err(Violation::Malformed(_))
+        ));
+    }
+
+    type Chunk = std::result::Result<Frame<Bytes>, Infallible>;
+
+    /// A body fed through a channel, one frame per message
+    fn channel_body() -> (
+        mpsc::Sender<Chunk>,
+        MultipartBody<StreamBody<mpsc::Receiver<Chunk>>>,
+    ) {
+        let (tx, rx) = mpsc::channel(16);
+        let limits = limits(1024, 64 * 1024, &["avatar", "bio"]);
+        (
+            tx,
+            MultipartBody::new(StreamBody::new(rx), BOUNDARY, limits),
+        )
+    }
+
+    #[tokio::test]
+    async fn part_over_the_limit_stops_the_body() {
+        let (mut tx, mut body) = channel_body();
+        let violation = body.violation();
+        let big = form(&[("bio", b"hi"), ("avatar", &[b'a'; 2048])]);
+        let (head, tail) = big.split_at(600);
+        tx.send(Ok(Frame::data(Bytes::copy_from_slice(head))))
+            .await
+            .unwrap();
+        tx.send(Ok(Frame::data(Bytes::copy_from_slice(tail))))
+            .await
+            .unwrap();
+
+        // The first chunk is within limits and passes; the second takes the
+        // avatar part past 1024 bytes and ends the body, whatever follows.
+        let first = body.frame().await.unwrap().unwrap();
+        assert_eq!(first.into_data().unwrap(), head);
+        let err = body.frame().await.unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "multipart part exceeds 1024 bytes");
+        assert_eq!(violation.get(), Some(&Violation::PartTooLarge(1024)));
+        assert!(body.frame().await.is_none());
+
+        let response = violation.get().unwrap().response();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn body_within_limits_passes_through_as_it_arrives() {
+        let (mut tx, mut body) = channel_body();
+        let upload = form(&[("bio", b"hello"), ("avatar", &[b'a'; 1000])]);
+        let chunks: Vec<&[u8]> = upload.chunks(100).collect();
+
+        // Each frame comes out unchanged as soon as it goes in, before the
+        // rest of the body has been sent.
+        for chunk in &chunks {
+            tx.send(Ok(Frame::data(Bytes::copy_from_slice(chunk))))
+                .await
+                .unwrap();
+            let frame = body.frame().await.unwrap().unwrap();
+            assert_eq!(frame.into_data().unwrap(), *chunk);
+        }
+        tx.close_channel();
+        assert!(body.frame().await.is_none());
+        assert!(body.violation().get().is_none());
+    }
+
+    #[tokio::test]
+    async fn buffered_bodies_are_checked_too() {
+        let (mut tx, body) = channel_body();
+        let violation = body.violation();
+        let upload = form(&[("resume", b"pdf")]);
+        tx.send(Ok(Frame::data(Bytes::from(upload)))).await.unwrap();
+        tx.close_channel();
+
+        assert!(body.collect().await.is_err());
+        let response = violation.get().unwrap().response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,830 @@
+//! Static (in-process) plugins enabled by name from config.
+//!
+//! Plugins compiled into the binary are registered on the
+//! [`ServerBuilder`](crate::ServerBuilder) with a [`PluginFactory`]. An
+//! enabled `plugins` entry with `plugin_type: static` whose `name` matches a
+//! registered factory gets a fresh instance, initialized with the entry's
+//! `config` and started, which then runs in the middleware chain: its request
+//! interceptor before the rest of the chain, its response interceptor after.
+//!
+//! With the `wasm` feature, `plugin_type: wasm` entries need no factory: each
+//! loads the module named by its `config.path` into a sandboxed
+//! [`WasmPlugin`](octopus_plugin_runtime::WasmPlugin) (`max_fuel` and
+//! `max_memory` bound each call) and runs the same way.
+//!
+//! Every plugin records the time spent in its interceptors, and whether they
+//! failed, into the gateway's [`MetricsCollector`] once per request.
+//!
+//! Interceptor calls run in a [`PluginSandbox`] configured by the entry's
+//! `sandbox` section: a call that panics or outlives its timeout fails the
+//! request, or with `on_failure: open` is skipped, and a plugin failing that
+//! way `max_failures` times in a row is not called for `disable_for`.
+//!
+//! At shutdown each instance is stopped through its [`PluginHandle`], and
+//! gets five seconds to stop before it is skipped.
+
+use async_trait::async_trait;
+use http::{Request, Response};
+use octopus_config::types::{PluginConfig, PluginFailurePolicy};
+use octopus_core::middleware::{Body, Middleware, Next};
+use octopus_core::{Error, Result};
+use octopus_metrics::MetricsCollector;
+use octopus_plugin_runtime::context::{RequestContext, ResponseContext};
+use octopus_plugin_runtime::interceptor::{
+    InterceptorAction, RequestInterceptor, ResponseInterceptor,
+};
+use octopus_plugin_runtime::{Plugin, PluginError, PluginSandbox, SandboxConfig, SandboxError};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// An instance built by a factory: the plugin and whichever interceptor
+/// roles it implements.
+trait Interceptors: Send + Sync + fmt::Debug {
+    fn plugin_mut(&mut self) -> &mut dyn Plugin;
+    fn request(&self) -> Option<&dyn RequestInterceptor>;
+    fn response(&self) -> Option<&dyn ResponseInterceptor>;
+}
+
+#[derive(Debug)]
+struct RequestOnly<P>(P);
+
+impl<P: RequestInterceptor + 'static> Interceptors for RequestOnly<P> {
+    fn plugin_mut(&mut self) -> &mut dyn Plugin {
+        &mut self.0
+    }
+    fn request(&self) -> Option<&dyn RequestInterceptor> {
+        Some(&self.0)
+    }
+    fn response(&self) -> Option<&dyn ResponseInterceptor> {
+        None
+    }
+}
+
+#[derive(Debug)]
+struct ResponseOnly<P>(P);
+
+impl<P: ResponseInterceptor + 'static> Interceptors for ResponseOnly<P> {
+    fn plugin_mut(&mut self) -> &mut dyn Plugin {
+        &mut self.0
+    }
+    fn request(&self) -> Option<&dyn RequestInterceptor> {
+        None
+    }
+    fn response(&self) -> Option<&dyn ResponseInterceptor> {
+        Some(&self.0)
+    }
+}
+
+#[derive(Debug)]
+struct Both<P>(P);
+
+impl<P: RequestInterceptor + ResponseInterceptor + 'static> Interceptors for Both<P> {
+    fn plugin_mut(&mut self) -> &mut dyn Plugin {
+        &mut self.0
+    }
+    fn request(&self) -> Option<&dyn RequestInterceptor> {
+        Some(&self.0)
+    }
+    fn response(&self) -> Option<&dyn ResponseInterceptor> {
+        Some(&self.0)
+    }
+}
+
+/// Builds fresh, uninitialized instances of a static plugin.
+#[derive(Clone)]
+pub struct PluginFactory {
+    build: Arc<dyn Fn() -> Box<dyn Interceptors> + Send + Sync>,
+}
+
+impl PluginFactory {
+    /// A plugin that intercepts requests.
+    pub fn request<P, F>(make: F) -> Self
+    where
+        P: RequestInterceptor + 'static,
+        F: Fn() -> P + Send + Sync + 'static,
+    {
+        Self {
+            build: Arc::new(move || Box::new(RequestOnly(make()))),
+        }
+    }
+
+    /// A plugin that intercepts responses.
+    pub fn response<P, F>(make: F) -> Self
+    where
+        P: ResponseInterceptor + 'static,
+        F: Fn() -> P + Send + Sync + 'static,
+    {
+        Self {
+            build: Arc::new(move || Box::new(ResponseOnly(make()))),
+        }
+    }
+
+    /// A plugin that intercepts both requests and responses.
+    pub fn interceptor<P, F>(make: F) -> Self
+    where
+        P: RequestInterceptor + ResponseInterceptor + 'static,
+        F: Fn() -> P + Send + Sync + 'static,
+    {
+        Self {
+            build: Arc::new(move || Box::new(Both(make()))),
+        }
+    }
+
+    /// Build an instance, then initialize it with `config.config` and start it.
+    async fn instantiate(
+        &self,
+        config: &PluginConfig,
+        metrics: Arc<MetricsCollector>,
+    ) -> Result<PluginMiddleware> {
+        let mut instance = (self.build)();
+        let value = serde_json::Value::Object(config.config.clone().into_iter().collect());
+        let plugin = instance.plugin_mut();
+        plugin
+            .init(value)
+            .await
+            .map_err(|e| Error::plugin(&config.name, e.to_string()))?;
+        plugin
+            .start()
+            .await
+            .map_err(|e| Error::plugin(&config.name, e.to_string()))?;
+
+        Ok(PluginMiddleware {
+            name: config.name.clone(),
+            plugin: Arc::new(RwLock::new(instance)),
+            metrics,
+            sandbox: PluginSandbox::new(SandboxConfig {
+                timeout: config.sandbox.timeout,
+                max_failures: config.sandbox.max_failures,
+                disable_for: config.sandbox.disable_for,
+            }),
+            on_failure: config.sandbox.on_failure,
+        })
+    }
+}
+
+impl fmt::Debug for PluginFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginFactory").finish_non_exhaustive()
+    }
+}
+
+/// Static plugins instantiated at startup
+#[derive(Debug, Default)]
+pub(crate) struct LoadedPlugins {
+    /// Middleware running each plugin, by name
+    pub(crate) middleware: HashMap<String, Arc<dyn Middleware>>,
+    /// Handles to stop each instance, in load order
+    pub(crate) handles: Vec<PluginHandle>,
+}
+
+/// Stops a loaded plugin instance at shutdown.
+#[derive(Clone)]
+pub(crate) struct PluginHandle {
+    name: String,
+    plugin: Arc<RwLock<Box<dyn Interceptors>>>,
+}
+
+impl PluginHandle {
+    /// Call the plugin's `stop()`, once interceptor calls in progress finish.
+    pub(crate) async fn stop(&self) -> Result<()> {
+        self.plugin
+            .write()
+            .await
+            .plugin_mut()
+            .stop()
+            .await
+            .map_err(|e| Error::plugin(&self.name, e.to_string()))
+    }
+}
+
+impl fmt::Debug for PluginHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginHandle")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How long one plugin gets to stop at shutdown
+pub(crate) const PLUGIN_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stop every plugin in `handles`, in reverse load order, each within
+/// `timeout`; a plugin that fails or overruns is logged and skipped, so it
+/// cannot keep the others from stopping.
+pub(crate) async fn stop_plugins(handles: &[PluginHandle], timeout: Duration) {
+    for handle in handles.iter().rev() {
+        match tokio::time::timeout(timeout, handle.stop()).await {
+            Ok(Ok(())) => tracing::info!(plugin = %handle.name, "Plugin stopped"),
+            Ok(Err(e)) => {
+                tracing::warn!(plugin = %handle.name, error = %e, "Plugin failed to stop")
+            }
+            Err(_) => tracing::warn!(
+                plugin = %handle.name,
+                timeout_ms = timeout.as_millis(),
+                "Plugin did not stop in time, skipping"
+            ),
+        }
+    }
+}
+
+/// Instantiate every enabled `static` plugin in `plugins` that has a factory
+/// in `factories`, and every enabled `wasm` plugin when the `wasm` feature is
+/// on, keyed by plugin name. Entries without a factory are left
+/// to [`build_plugin_middleware`](crate::chain::build_plugin_middleware),
+/// which skips them with a warning; an instance that fails to initialize or
+/// start fails the whole load. Executions are recorded into `metrics`.
+pub(crate) async fn load_static_plugins(
+    factories: &HashMap<String, PluginFactory>,
+    plugins: &[PluginConfig],
+    metrics: Arc<MetricsCollector>,
+) -> Result<LoadedPlugins> {
+    let mut loaded = LoadedPlugins::default();
+    for p in plugins.iter().filter(|p| p.enabled) {
+        let factory = match p.plugin_type.as_str() {
+            "static" => factories.get(&p.name).cloned(),
+            #[cfg(feature = "wasm")]
+            "wasm" => {
+                let name = p.name.clone();
+                Some(PluginFactory::interceptor(move || {
+                    octopus_plugin_runtime::WasmPlugin::new(name.clone())
+                }))
+            }
+            _ => None,
+        };
+        let Some(factory) = factory else {
+            continue;
+        };
+        let middleware = factory.instantiate(p, Arc::clone(&metrics)).await?;
+        tracing::info!(plugin = %p.name, plugin_type = %p.plugin_type, "Plugin initialized");
+        loaded.handles.push(PluginHandle {
+            name: p.name.clone(),
+            plugin: Arc::clone(&middleware.plugin),
+        });
+        loaded
+            .middleware
+            .insert(p.name.clone(), Arc::new(middleware));
+    }
+    Ok(loaded)
+}
+
+/// Runs an initialized static plugin's interceptors around the rest of the
+/// chain.
+#[derive(Debug)]
+struct PluginMiddleware {
+    name: String,
+    /// Shared with the plugin's [`PluginHandle`], which takes it exclusively
+    /// to stop the instance
+    plugin: Arc<RwLock<Box<dyn Interceptors>>>,
+    metrics: Arc<MetricsCollector>,
+    sandbox: PluginSandbox,
+    on_failure: PluginFailurePolicy,
+}
+
+impl PluginMiddleware {
+    /// Record `spent` inside the interceptors for one request.
+    fn record(&self, spent: Duration, ok: bool) {
+        self.metrics.record_plugin(&self.name, spent, ok);
+    }
+
+    /// The output of a sandboxed interceptor call, or `None` when the call
+    /// panicked, timed out or was refused and the plugin fails open.
+    fn contain<T>(&self, result: std::result::Result<T, SandboxError>) -> Result<Option<T>> {
+        let error = match result {
+            Ok(output) => return Ok(Some(output)),
+            Err(e) => e,
+        };
+        tracing::warn!(
+            plugin = %self.name,
+            error = %error,
+            on_failure = ?self.on_failure,
+            "Plugin step failed"
+        );
+        match self.on_failure {
+            PluginFailurePolicy::Open => Ok(None),
+            PluginFailurePolicy::Closed => Err(Error::plugin(&self.name, error.to_string())),
+        }
+    }
+
+    fn error(&self, error: PluginError) -> Error {
+        match error {
+            PluginError::AuthError(msg) => Error::Authentication(msg),
+            PluginError::AuthzError(msg) => Error::Authorization(msg),
+            other => Error::plugin(&self.name, other.to_string()),
+        }
+    }
+}
+
+/// The plugin API's view of `req`.
+pub(crate) fn request_context(req: &Request<Body>) -> RequestContext {
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let remote_addr = req
+        .extensions()
+        .get::<crate::handler::ClientAddr>()
+        .map(|addr| addr.0)
+        .unwrap_or_else(|| ([0, 0, 0, 0], 0).into());
+
+    let mut ctx = RequestContext::new(request_id, remote_addr);
+    ctx.route = req
+        .extensions()
+        .get::<octopus_core::request::RouteInfo>()
+        .map(|r| r.path.clone());
+    ctx.upstream = req
+        .extensions()
+        .get::<octopus_middleware::MatchedRouteAuth>()
+        .map(|r| r.upstream.clone());
+    ctx
+}
+
+#[async_trait]
+impl Middleware for PluginMiddleware {
+    async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        let started = Instant::now();
+        let ctx = request_context(&req);
+        // Time inside the interceptors only; the rest of the chain is not the
+        // plugin's latency.
+        let mut spent = Duration::ZERO;
+        // A step skipped after a panic or timeout still counts as an error.
+        let mut failed = false;
+
+        if let Some(interceptor) = self.plugin.read().await.request() {
+            let result = self
+                .sandbox
+                .call(interceptor.intercept_request(&mut req, &ctx))
+                .await;
+            spent = started.elapsed();
+            match self.contain(result) {
+                Ok(Some(Ok(InterceptorAction::Continue))) => {}
+                Ok(Some(Ok(InterceptorAction::Return(response)))) => {
+                    self.record(spent, true);
+                    return Ok(response);
+                }
+                Ok(Some(Ok(InterceptorAction::Abort(e)) | Err(e))) => {
+                    self.record(spent, false);
+                    return Err(self.error(e));
+                }
+                Ok(None) => failed = true,
+                Err(e) => {
+                    self.record(spent, false);
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut response = match next.run(req).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.record(spent, !failed);
+                return Err(e);
+            }
+        };
+
+        if let Some(interceptor) = self.plugin.read().await.response() {
+            let mut response_ctx = ResponseContext::new(
+                ctx.request_id,
+                started.elapsed(),
+                response.status().as_u16(),
+            );
+            response_ctx.upstream = ctx.upstream;
+            let began = Instant::now();
+            let result = self
+                .sandbox
+                .call(interceptor.intercept_response(&mut response, &response_ctx))
+                .await;
+            spent += began.elapsed();
+            match self.contain(result) {
+                Ok(Some(Ok(InterceptorAction::Continue))) => {}
+                Ok(Some(Ok(InterceptorAction::Return(replacement)))) => response = replacement,
+                Ok(Some(Ok(InterceptorAction::Abort(e)) | Err(e))) => {
+                    self.record(spent, false);
+                    return Err(self.error(e));
+                }
+                Ok(None) => failed = true,
+                Err(e) => {
+                    self.record(spent, false);
+                    return Err(e);
+                }
+            }
+        }
+
+        self.record(spent, !failed);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use http::StatusCode;
+    use http_body_util::Full;
+    use octopus_config::types::PluginSandboxConfig;
+    use octopus_core::middleware::HandlerFn;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Tags requests and responses with a configured header value.
+    #[derive(Debug, Default)]
+    struct Tagger {
+        tag: String,
+        started: bool,
+    }
+
+    #[async_trait]
+    impl Plugin for Tagger {
+        fn name(&self) -> &str {
+            "tagger"
+        }
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+        async fn init(
+            &mut self,
+            config: serde_json::Value,
+        ) -> std::result::Result<(), PluginError> {
+            self.tag = config["tag"]
+                .as_str()
+                .ok_or_else(|| PluginError::config("missing 'tag'"))?
+                .to_string();
+            Ok(())
+        }
+        async fn start(&mut self) -> std::result::Result<(), PluginError> {
+            self.started = true;
+            Ok(())
+        }
+        async fn stop(&mut self) -> std::result::Result<(), PluginError> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl RequestInterceptor for Tagger {
+        async fn intercept_request(
+            &self,
+            req: &mut Request<Body>,
+            _ctx: &RequestContext,
+        ) -> std::result::Result<InterceptorAction, PluginError> {
+            assert!(self.started);
+            if req.uri().path() == "/slow" {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            if req.uri().path() == "/blocked" {
+                return Err(PluginError::AuthzError("blocked".to_string()));
+            }
+            if req.uri().path() == "/short" {
+                let response = Response::builder()
+                    .status(StatusCode::IM_A_TEAPOT)
+                    .body(Full::new(Bytes::new()))
+                    .unwrap();
+                return Ok(InterceptorAction::Return(response));
+            }
+            req.headers_mut()
+                .insert("x-plugin-tag", self.tag.parse().unwrap());
+            Ok(InterceptorAction::Continue)
+        }
+    }
+
+    #[async_trait]
+    impl ResponseInterceptor for Tagger {
+        async fn intercept_response(
+            &self,
+            res: &mut Response<Body>,
+            ctx: &ResponseContext,
+        ) -> std::result::Result<InterceptorAction, PluginError> {
+            res.headers_mut()
+                .insert("x-plugin-status", ctx.status_code.into());
+            Ok(InterceptorAction::Continue)
+        }
+    }
+
+    /// Panics on `/panic` and hangs on `/hang`, counting its calls.
+    #[derive(Debug, Default)]
+    struct Crasher {
+        calls: Arc<AtomicUsize>,
+        stopped: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Plugin for Crasher {
+        fn name(&self) -> &str {
+            "crasher"
+        }
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+        async fn init(
+            &mut self,
+            _config: serde_json::Value,
+        ) -> std::result::Result<(), PluginError> {
+            Ok(())
+        }
+        async fn start(&mut self) -> std::result::Result<(), PluginError> {
+            Ok(())
+        }
+        async fn stop(&mut self) -> std::result::Result<(), PluginError> {
+            self.stopped.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl RequestInterceptor for Crasher {
+        async fn intercept_request(
+            &self,
+            req: &mut Request<Body>,
+            _ctx: &RequestContext,
+        ) -> std::result::Result<InterceptorAction, PluginError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            match req.uri().path() {
+                "/panic" => panic!("crasher bug"),
+                "/hang" => std::future::pending().await,
+                _ => Ok(InterceptorAction::Continue),
+            }
+        }
+    }
+
+    fn plugin_config(name: &str, config: serde_json::Value) -> PluginConfig {
+        PluginConfig {
+            name: name.to_string(),
+            plugin_type: "static".to_string(),
+            enabled: true,
+            priority: 0,
+            config: serde_json::from_value(config).unwrap(),
+            sandbox: Default::default(),
+        }
+    }
+
+    /// The crasher plugin, loaded with `sandbox`, and its call counter.
+    async fn crasher(
+        sandbox: PluginSandboxConfig,
+        metrics: Arc<MetricsCollector>,
+    ) -> (Arc<dyn Middleware>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let factories = HashMap::from([(
+            "crasher".to_string(),
+            PluginFactory::request(move || Crasher {
+                calls: Arc::clone(&counter),
+                ..Default::default()
+            }),
+        )]);
+        let mut config = plugin_config("crasher", serde_json::json!({}));
+        config.sandbox = sandbox;
+        let mut loaded = load_static_plugins(&factories, &[config], metrics)
+            .await
+            .unwrap();
+        (loaded.middleware.remove("crasher").unwrap(), calls)
+    }
+
+    fn metrics() -> Arc<MetricsCollector> {
+        Arc::new(MetricsCollector::new())
+    }
+
+    fn factories() -> HashMap<String, PluginFactory> {
+        HashMap::from([(
+            "tagger".to_string(),
+            PluginFactory::interceptor(Tagger::default),
+        )])
+    }
+
+    /// Run `req` through `middleware`, echoing the request's tag header in
+    /// the response body.
+    async fn run(middleware: Arc<dyn Middleware>, req: Request<Body>) -> Result<Response<Body>> {
+        let handler: HandlerFn = Box::new(|req: Request<Body>| {
+            Box::pin(async move {
+                let tag = req
+                    .headers()
+                    .get("x-plugin-tag")
+                    .map(|v| Bytes::copy_from_slice(v.as_bytes()))
+                    .unwrap_or_default();
+                Ok(Response::new(Full::new(tag)))
+            })
+        });
+        Next::with_handler(Arc::from(vec![middleware]), handler)
+            .run(req)
+            .await
+    }
+
+    fn request(path: &str) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .body(Full::new(Bytes::new()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn enabled_static_plugin_runs_its_interceptors() {
+        let plugins = [plugin_config("tagger", serde_json::json!({"tag": "blue"}))];
+        let loaded = load_static_plugins(&factories(), &plugins, metrics())
+            .await
+            .unwrap();
+        let middleware = loaded.middleware["tagger"].clone();
+
+        let response = run(middleware.clone(), request("/ok")).await.unwrap();
+        assert_eq!(response.headers()["x-plugin-status"], "200");
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "blue");
+
+        let short = run(middleware.clone(), request("/short")).await.unwrap();
+        assert_eq!(short.status(), StatusCode::IM_A_TEAPOT);
+
+        let blocked = run(middleware, request("/blocked")).await.unwrap_err();
+        assert!(matches!(blocked, Error::Authorization(_)));
+    }
+
+    #[tokio::test]
+    async fn slow_plugin_records_elevated_latency() {
+        let metrics = metrics();
+        let plugins = [
+            plugin_config("tagger", serde_json::json!({"tag": "slow"})),
+            plugin_config("fast", serde_json::json!({"tag": "fast"})),
+        ];
+        let mut factories = factories();
+        factories.insert(
+            "fast".to_string(),
+            PluginFactory::interceptor(Tagger::default),
+        );
+        let loaded = load_static_plugins(&factories, &plugins, Arc::clone(&metrics))
+            .await
+            .unwrap();
+
+        run(loaded.middleware["tagger"].clone(), request("/slow"))
+            .await
+            .unwrap();
+        run(loaded.middleware["fast"].clone(), request("/ok"))
+            .await
+            .unwrap();
+
+        let slow = metrics.plugin_stats("tagger").unwrap();
+        let fast = metrics.plugin_stats("fast").unwrap();
+        assert_eq!(slow.invocations.load(Ordering::Relaxed), 1);
+        assert!(slow.avg_latency_ms() >= 50.0, "{}", slow.avg_latency_ms());
+        assert!(fast.avg_latency_ms() < 50.0, "{}", fast.avg_latency_ms());
+    }
+
+    #[tokio::test]
+    async fn plugin_errors_are_counted() {
+        let metrics = metrics();
+        let plugins = [plugin_config("tagger", serde_json::json!({"tag": "x"}))];
+        let loaded = load_static_plugins(&factories(), &plugins, Arc::clone(&metrics))
+            .await
+            .unwrap();
+        let middleware = loaded.middleware["tagger"].clone();
+
+        run(middleware.clone(), request("/ok")).await.unwrap();
+        run(middleware.clone(), request("/blocked"))
+            .await
+            .unwrap_err();
+        run(middleware, request("/blocked")).await.unwrap_err();
+
+        let stats = metrics.plugin_stats("tagger").unwrap();
+        assert_eq!(stats.invocations.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.errors.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn panicking_plugin_fails_closed_without_taking_down_the_worker() {
+        let metrics = metrics();
+        let (middleware, _) = crasher(PluginSandboxConfig::default(), Arc::clone(&metrics)).await;
+
+        let err = run(middleware.clone(), request("/panic"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Plugin { ref plugin, .. } if plugin == "crasher"));
+        assert!(err.to_string().contains("crasher bug"), "{err}");
+
+        // The same instance keeps serving requests.
+        let ok = run(middleware, request("/ok")).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+        let stats = metrics.plugin_stats("crasher").unwrap();
+        assert_eq!(stats.invocations.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.errors.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failing_open_skips_panicking_and_hanging_steps() {
+        let metrics = metrics();
+        let sandbox = PluginSandboxConfig {
+            timeout: Duration::from_millis(100),
+            on_failure: PluginFailurePolicy::Open,
+            ..Default::default()
+        };
+        let (middleware, _) = crasher(sandbox, Arc::clone(&metrics)).await;
+
+        let panicked = run(middleware.clone(), request("/panic")).await.unwrap();
+        assert_eq!(panicked.status(), StatusCode::OK);
+        let hung = run(middleware, request("/hang")).await.unwrap();
+        assert_eq!(hung.status(), StatusCode::OK);
+
+        let stats = metrics.plugin_stats("crasher").unwrap();
+        assert_eq!(stats.errors.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hanging_plugin_fails_closed_after_its_timeout() {
+        let sandbox = PluginSandboxConfig {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let (middleware, _) = crasher(sandbox, metrics()).await;
+
+        let err = run(middleware, request("/hang")).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repeatedly_crashing_plugin_is_disabled() {
+        let sandbox = PluginSandboxConfig {
+            on_failure: PluginFailurePolicy::Open,
+            max_failures: 2,
+            disable_for: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let (middleware, calls) = crasher(sandbox, metrics()).await;
+
+        for _ in 0..2 {
+            run(middleware.clone(), request("/panic")).await.unwrap();
+        }
+        // Disabled: requests pass without calling the plugin.
+        run(middleware.clone(), request("/ok")).await.unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        run(middleware, request("/ok")).await.unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_loaded_plugins() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stopped);
+        let factories = HashMap::from([(
+            "crasher".to_string(),
+            PluginFactory::request(move || Crasher {
+                stopped: Arc::clone(&flag),
+                ..Default::default()
+            }),
+        )]);
+        let plugins = [plugin_config("crasher", serde_json::json!({}))];
+        let loaded = load_static_plugins(&factories, &plugins, metrics())
+            .await
+            .unwrap();
+        assert_eq!(loaded.handles.len(), 1);
+
+        stop_plugins(&loaded.handles, PLUGIN_STOP_TIMEOUT).await;
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn only_enabled_plugins_with_a_factory_are_loaded() {
+        let mut disabled = plugin_config("tagger", serde_json::json!({"tag": "x"}));
+        disabled.enabled = false;
+        let unknown = plugin_config("unknown", serde_json::json!({}));
+        let loaded = load_static_plugins(&factories(), &[disabled, unknown], metrics())
+            .await
+            .unwrap();
+        assert!(loaded.middleware.is_empty());
+        assert!(loaded.handles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn init_failure_names_the_plugin() {
+        let plugins = [plugin_config("tagger", serde_json::json!({}))];
+        let err = load_static_plugins(&factories(), &plugins, metrics())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Plugin { ref plugin, .. } if plugin == "tagger"));
+    }
+
+    #[cfg(feature = "wasm")]
+    #[tokio::test]
+    async fn wasm_plugins_load_without_a_factory() {
+        let mut wasm = plugin_config(
+            "wasm-tagger",
+            serde_json::json!({
+                "path": concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../octopus-plugin-runtime/tests/fixtures/add_header.wat"
+                ),
+            }),
+        );
+        wasm.plugin_type = "wasm".to_string();
+        let loaded = load_static_plugins(&HashMap::new(), &[wasm], metrics())
+            .await
+            .unwrap();
+
+        let response = run(loaded.middleware["wasm-tagger"].clone(), request("/ok"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-wasm"], "hello");
+    }
+}
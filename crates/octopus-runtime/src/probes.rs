@@ -5,7 +5,11 @@
 //! drain never inflates the in-flight counter or blocks graceful shutdown.
 //!
 //! - `/livez` — liveness; 200 while the process is alive (even while draining).
-//! - `/readyz` — readiness; 200 only when ready to receive new traffic.
+//! - `/readyz` — readiness; 200 only when ready to receive new traffic:
+//!   running, config loaded, not draining, discovery synced (if required),
+//!   shared state stores reachable and at least one upstream healthy (if
+//!   required and any are registered). The body lists each check so a 503
+//!   says which one failed.
 //! - `/startupz` — startup; 200 once the listener has bound.
 //!
 //! `/healthz` and `/health` are accepted as back-compat aliases for readiness.
@@ -33,6 +37,8 @@ pub struct ProbeRoutes {
     pub readiness: String,
     /// Path for the startup probe.
     pub startup: String,
+    /// Whether readiness requires at least one healthy upstream instance.
+    pub require_healthy_upstream: bool,
 }
 
 impl Default for ProbeRoutes {
@@ -42,10 +48,23 @@ impl Default for ProbeRoutes {
             liveness: DEFAULT_LIVENESS_PATH.to_string(),
             readiness: DEFAULT_READINESS_PATH.to_string(),
             startup: DEFAULT_STARTUP_PATH.to_string(),
+            require_healthy_upstream: true,
         }
     }
 }
 
+impl ProbeRoutes {
+    /// Whether `path` is one of the probe endpoints (including the `/healthz`
+    /// and `/health` aliases), whether or not probes are enabled.
+    pub fn matches(&self, path: &str) -> bool {
+        path == self.liveness
+            || path == self.readiness
+            || path == self.startup
+            || path == "/healthz"
+            || path == "/health"
+    }
+}
+
 enum ProbeKind {
     Liveness,
     Readiness,
@@ -56,11 +75,13 @@ enum ProbeKind {
 ///
 /// Returns `Some(response)` for liveness/readiness/startup paths (and the
 /// `/healthz` / `/health` readiness aliases), and `None` otherwise so the
-/// caller continues normal request dispatch.
+/// caller continues normal request dispatch. `healthy_upstream` is only
+/// evaluated for readiness when [`ProbeRoutes::require_healthy_upstream`] is set.
 pub fn handle_probe(
     lifecycle: &LifecycleState,
     routes: &ProbeRoutes,
     path: &str,
+    healthy_upstream: impl FnOnce() -> bool,
 ) -> Option<Response<Full<Bytes>>> {
     if !routes.enabled {
         return None;
@@ -76,22 +97,37 @@ pub fn handle_probe(
         return None;
     };
 
-    let (ok, name) = match kind {
-        ProbeKind::Liveness => (lifecycle.is_live(), "live"),
-        ProbeKind::Readiness => (lifecycle.is_ready(), "ready"),
-        ProbeKind::Startup => (lifecycle.is_started(), "started"),
+    let response = match kind {
+        ProbeKind::Liveness => build_response(lifecycle.is_live(), "live", &[]),
+        ProbeKind::Readiness => {
+            let mut checks = lifecycle.readiness_checks();
+            if routes.require_healthy_upstream {
+                checks.push(("healthy_upstream", healthy_upstream()));
+            }
+            let ok = checks.iter().all(|(_, ok)| *ok);
+            build_response(ok, "ready", &checks)
+        }
+        ProbeKind::Startup => build_response(lifecycle.is_started(), "started", &[]),
     };
 
-    Some(build_response(ok, name))
+    Some(response)
 }
 
-fn build_response(ok: bool, name: &str) -> Response<Full<Bytes>> {
+fn build_response(ok: bool, name: &str, checks: &[(&str, bool)]) -> Response<Full<Bytes>> {
     let (status, status_str) = if ok {
         (StatusCode::OK, "ok")
     } else {
         (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
     };
-    let body = format!("{{\"status\":\"{status_str}\",\"probe\":\"{name}\"}}");
+    let mut body = serde_json::json!({ "status": status_str, "probe": name });
+    if !checks.is_empty() {
+        body["checks"] = checks
+            .iter()
+            .map(|(check, ok)| (check.to_string(), serde_json::Value::Bool(*ok)))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    let body = body.to_string();
     Response::builder()
         .status(status)
         .header("Content-Type", "application/json")
@@ -105,6 +141,12 @@ fn build_response(ok: bool, name: &str) -> Response<Full<Bytes>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use http_body_util::BodyExt;
+
+    async fn body_json(response: Response<Full<Bytes>>) -> serde_json::Value {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
 
     fn ready_lifecycle() -> LifecycleState {
         let lc = LifecycleState::new(false);
@@ -117,7 +159,7 @@ mod tests {
     #[test]
     fn unknown_path_returns_none() {
         let lc = LifecycleState::new(false);
-        assert!(handle_probe(&lc, &ProbeRoutes::default(), "/api/users").is_none());
+        assert!(handle_probe(&lc, &ProbeRoutes::default(), "/api/users", || true).is_none());
     }
 
     #[test]
@@ -127,7 +169,7 @@ mod tests {
             enabled: false,
             ..Default::default()
         };
-        assert!(handle_probe(&lc, &routes, "/livez").is_none());
+        assert!(handle_probe(&lc, &routes, "/livez", || true).is_none());
     }
 
     #[test]
@@ -136,10 +178,10 @@ mod tests {
         let routes = ProbeRoutes::default();
         lc.begin_draining();
 
-        let live = handle_probe(&lc, &routes, "/livez").unwrap();
+        let live = handle_probe(&lc, &routes, "/livez", || true).unwrap();
         assert_eq!(live.status(), StatusCode::OK);
 
-        let ready = handle_probe(&lc, &routes, "/readyz").unwrap();
+        let ready = handle_probe(&lc, &routes, "/readyz", || true).unwrap();
         assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
     }
 
@@ -148,7 +190,9 @@ mod tests {
         let lc = ready_lifecycle();
         let routes = ProbeRoutes::default();
         assert_eq!(
-            handle_probe(&lc, &routes, "/healthz").unwrap().status(),
+            handle_probe(&lc, &routes, "/healthz", || true)
+                .unwrap()
+                .status(),
             StatusCode::OK
         );
     }
@@ -158,12 +202,95 @@ mod tests {
         let lc = LifecycleState::new(false);
         let routes = ProbeRoutes::default();
         assert_eq!(
-            handle_probe(&lc, &routes, "/startupz").unwrap().status(),
+            handle_probe(&lc, &routes, "/startupz", || true)
+                .unwrap()
+                .status(),
             StatusCode::SERVICE_UNAVAILABLE
         );
         lc.mark_bind_complete();
         assert_eq!(
-            handle_probe(&lc, &routes, "/startupz").unwrap().status(),
+            handle_probe(&lc, &routes, "/startupz", || true)
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn readiness_503_during_startup_lists_failed_checks() {
+        let lc = LifecycleState::new(false);
+        lc.mark_bind_complete();
+        let routes = ProbeRoutes::default();
+
+        let resp = handle_probe(&lc, &routes, "/readyz", || true).unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = body_json(resp).await;
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["checks"]["running"], false);
+        assert_eq!(body["checks"]["config_loaded"], false);
+        assert_eq!(body["checks"]["healthy_upstream"], true);
+    }
+
+    #[tokio::test]
+    async fn readiness_503_while_draining_and_200_when_healthy() {
+        let lc = ready_lifecycle();
+        let routes = ProbeRoutes::default();
+
+        let resp = handle_probe(&lc, &routes, "/readyz", || true).unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body_json(resp).await;
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["probe"], "ready");
+        assert_eq!(body["checks"]["not_draining"], true);
+
+        lc.begin_draining();
+        let resp = handle_probe(&lc, &routes, "/readyz", || true).unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body_json(resp).await["checks"]["not_draining"], false);
+    }
+
+    #[test]
+    fn readiness_requires_healthy_upstream_unless_disabled() {
+        let lc = ready_lifecycle();
+        let routes = ProbeRoutes::default();
+        assert_eq!(
+            handle_probe(&lc, &routes, "/readyz", || false)
+                .unwrap()
+                .status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let routes = ProbeRoutes {
+            require_healthy_upstream: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            handle_probe(&lc, &routes, "/readyz", || panic!("not evaluated"))
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn custom_paths_are_honored() {
+        let lc = ready_lifecycle();
+        let routes = ProbeRoutes {
+            liveness: "/_gw/live".to_string(),
+            readiness: "/_gw/ready".to_string(),
+            ..Default::default()
+        };
+        assert!(handle_probe(&lc, &routes, "/livez", || true).is_none());
+        assert_eq!(
+            handle_probe(&lc, &routes, "/_gw/live", || true)
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            handle_probe(&lc, &routes, "/_gw/ready", || true)
+                .unwrap()
+                .status(),
             StatusCode::OK
         );
     }
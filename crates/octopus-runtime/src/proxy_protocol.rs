@@ -0,0 +1,334 @@
+//! PROXY protocol (v1 and v2) on accepted connections.
+//!
+//! An L4 load balancer in front of the gateway (AWS NLB, HAProxy in TCP mode)
+//! can prefix each connection with a PROXY protocol header naming the client
+//! it accepted the connection from. With `gateway.proxy_protocol` enabled the
+//! header is read before TLS or HTTP, and the address it carries replaces the
+//! balancer's as the connection's client address.
+
+use octopus_config::types::ProxyProtocolConfig;
+use octopus_middleware::IpPattern;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Start of a v1 header
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// Longest v1 header, CRLF included
+const V1_MAX_LEN: usize = 107;
+
+/// Signature a v2 header starts with
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Shortest header of either version (v1 `PROXY UNKNOWN\r\n`), so reading it
+/// never consumes bytes of the connection that follows
+const MIN_HEADER_LEN: usize = 15;
+
+/// PROXY protocol handling for the connections of trusted peers
+#[derive(Debug)]
+pub(crate) struct ProxyProtocol {
+    trusted: Vec<IpPattern>,
+    header_timeout: Duration,
+}
+
+impl ProxyProtocol {
+    /// From `gateway.proxy_protocol`; `None` when disabled. Invalid
+    /// `trusted_sources` entries (rejected by config validation) are logged
+    /// and ignored.
+    pub(crate) fn from_config(config: &ProxyProtocolConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let trusted = config
+            .trusted_sources
+            .iter()
+            .filter_map(|s| match IpPattern::parse(s) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    tracing::warn!(pattern = %s, error = %e, "Ignoring invalid proxy_protocol.trusted_sources entry");
+                    None
+                }
+            })
+            .collect();
+        Some(Self {
+            trusted,
+            header_timeout: config.header_timeout,
+        })
+    }
+
+    /// Whether headers from `peer` are expected and honoured. With no
+    /// trusted sources, no peer is.
+    fn trusts(&self, peer: IpAddr) -> bool {
+        self.trusted.iter().any(|p| p.matches(&peer))
+    }
+
+    /// Client address of a connection accepted from `peer`.
+    ///
+    /// For a trusted peer this reads the PROXY header off `io`, failing when
+    /// it is missing, malformed or late; a header that names no client (a
+    /// balancer health check) leaves `peer`. Untrusted peers are `peer`.
+    pub(crate) async fn client_addr<IO>(
+        &self,
+        io: &mut IO,
+        peer: SocketAddr,
+    ) -> io::Result<SocketAddr>
+    where
+        IO: AsyncRead + Unpin,
+    {
+        if !self.trusts(peer.ip()) {
+            return Ok(peer);
+        }
+        match tokio::time::timeout(self.header_timeout, read_header(io)).await {
+            Ok(source) => Ok(source?.unwrap_or(peer)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no PROXY protocol header within the timeout",
+            )),
+        }
+    }
+}
+
+/// Read a v1 or v2 header off `io`, leaving the bytes after it unread.
+///
+/// Returns the source address it carries, or `None` for headers without one
+/// (v1 `UNKNOWN`, v2 `LOCAL`, or a non-IP address family).
+pub(crate) async fn read_header<IO>(io: &mut IO) -> io::Result<Option<SocketAddr>>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut header = vec![0; MIN_HEADER_LEN];
+    io.read_exact(&mut header).await?;
+
+    if header.starts_with(V2_SIGNATURE) {
+        let mut rest = [0; 16 - MIN_HEADER_LEN];
+        io.read_exact(&mut rest).await?;
+        header.extend_from_slice(&rest);
+        let len = u16::from_be_bytes([header[14], header[15]]);
+        let mut addresses = vec![0; usize::from(len)];
+        io.read_exact(&mut addresses).await?;
+        parse_v2(header[12], header[13], &addresses)
+    } else if header.starts_with(V1_PREFIX) {
+        while !header.ends_with(b"\r\n") {
+            if header.len() == V1_MAX_LEN {
+                return Err(invalid("PROXY v1 header too long"));
+            }
+            header.push(io.read_u8().await?);
+        }
+        parse_v1(&header[..header.len() - 2])
+    } else {
+        Err(invalid("missing PROXY protocol header"))
+    }
+}
+
+/// `PROXY <TCP4|TCP6|UNKNOWN> <src> <dst> <sport> <dport>`, without the CRLF
+fn parse_v1(line: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid("PROXY v1 header is not ASCII"))?;
+    let mut fields = line.split(' ').skip(1);
+    let ipv4 = match fields.next() {
+        Some("TCP4") => true,
+        Some("TCP6") => false,
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid("unsupported PROXY v1 protocol")),
+    };
+
+    let (Some(source), Some(_), Some(port), Some(_), None) = (
+        fields.next(),
+        fields.next(),
+        fields.next(),
+        fields.next(),
+        fields.next(),
+    ) else {
+        return Err(invalid("malformed PROXY v1 header"));
+    };
+    let ip: IpAddr = source
+        .parse()
+        .map_err(|_| invalid("invalid PROXY v1 source address"))?;
+    if ip.is_ipv4() != ipv4 {
+        return Err(invalid(
+            "PROXY v1 source address does not match its protocol",
+        ));
+    }
+    let port: u16 = port
+        .parse()
+        .map_err(|_| invalid("invalid PROXY v1 source port"))?;
+    Ok(Some(SocketAddr::new(ip, port)))
+}
+
+/// The version/command and family bytes of a v2 header and the address
+/// block that follows them
+fn parse_v2(version_command: u8, family: u8, addresses: &[u8]) -> io::Result<Option<SocketAddr>> {
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    match version_command & 0x0F {
+        // LOCAL: the balancer's own connection, e.g. a health check
+        0x0 => return Ok(None),
+        0x1 => {}
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+
+    // Source address, destination address, source port, destination port;
+    // any TLVs after them are ignored.
+    let (ip, port) = match family >> 4 {
+        0x1 if addresses.len() >= 12 => {
+            let ip: [u8; 4] = addresses[..4].try_into().unwrap();
+            (
+                IpAddr::from(Ipv4Addr::from(ip)),
+                [addresses[8], addresses[9]],
+            )
+        }
+        0x2 if addresses.len() >= 36 => {
+            let ip: [u8; 16] = addresses[..16].try_into().unwrap();
+            (
+                IpAddr::from(Ipv6Addr::from(ip)),
+                [addresses[32], addresses[33]],
+            )
+        }
+        0x1 | 0x2 => return Err(invalid("truncated PROXY v2 address block")),
+        // UNSPEC or AF_UNIX: no IP address to report
+        _ => return Ok(None),
+    };
+    Ok(Some(SocketAddr::new(ip, u16::from_be_bytes(port))))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &[u8] = b"GET / HTTP/1.1\r\n\r\n";
+
+    /// Parse the header at the start of `input`, checking that the request
+    /// after it is left unread.
+    async fn parse(header: &[u8]) -> io::Result<Option<SocketAddr>> {
+        let input = [header, REQUEST].concat();
+        let mut rest = input.as_slice();
+        let source = read_header(&mut rest).await?;
+        assert_eq!(rest, REQUEST);
+        Ok(source)
+    }
+
+    fn v2(command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x20 | command, family]);
+        header.extend_from_slice(&(addresses.len() as u16).to_be_bytes());
+        header.extend_from_slice(addresses);
+        header
+    }
+
+    #[tokio::test]
+    async fn test_v1_headers() {
+        let source = parse(b"PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\n").await;
+        assert_eq!(source.unwrap(), Some("203.0.113.7:56324".parse().unwrap()));
+
+        let source = parse(b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 443\r\n").await;
+        assert_eq!(source.unwrap(), Some("[2001:db8::1]:4000".parse().unwrap()));
+
+        assert_eq!(parse(b"PROXY UNKNOWN\r\n").await.unwrap(), None);
+        let source = parse(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n").await;
+        assert_eq!(source.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_v2_headers() {
+        let mut inet = vec![203, 0, 113, 7, 10, 0, 0, 1];
+        inet.extend_from_slice(&56324u16.to_be_bytes());
+        inet.extend_from_slice(&443u16.to_be_bytes());
+        let source = parse(&v2(0x1, 0x11, &inet)).await;
+        assert_eq!(source.unwrap(), Some("203.0.113.7:56324".parse().unwrap()));
+
+        // TLVs after the addresses are skipped.
+        let mut inet6 = "2001:db8::1".parse::<Ipv6Addr>().unwrap().octets().to_vec();
+        inet6.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        inet6.extend_from_slice(&4000u16.to_be_bytes());
+        inet6.extend_from_slice(&443u16.to_be_bytes());
+        inet6.extend_from_slice(&[0x04, 0x00, 0x01, 0x00]);
+        let source = parse(&v2(0x1, 0x21, &inet6)).await;
+        assert_eq!(source.unwrap(), Some("[2001:db8::1]:4000".parse().unwrap()));
+
+        // LOCAL (balancer health checks) names no client.
+        assert_eq!(parse(&v2(0x0, 0x00, &[])).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_headers() {
+        for header in [
+            &b"GET / HTTP/1.1\r\nHost: example.com\r\n"[..],
+            b"PROXY TCP4 203.0.113.7 10.0.0.1 56324\r\n",
+            b"PROXY TCP4 2001:db8::1 2001:db8::2 4000 443\r\n",
+            b"PROXY UDP4 203.0.113.7 10.0.0.1 56324 443\r\n",
+            &v2(0x1, 0x11, &[203, 0, 113, 7])[..],
+            &[&V2_SIGNATURE[..], &[0x11, 0x11, 0, 0]].concat()[..],
+        ] {
+            let mut input = [header, REQUEST].concat();
+            input.resize(input.len().max(128), b'x');
+            let err = read_header(&mut input.as_slice()).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{header:?}");
+        }
+
+        let endless = [b"PROXY TCP4 ".as_slice(), &[b'1'; 200]].concat();
+        let err = read_header(&mut endless.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_only_trusted_peers_send_headers() {
+        let proxy_protocol = ProxyProtocol::from_config(&ProxyProtocolConfig {
+            enabled: true,
+            trusted_sources: vec!["10.0.0.0/8".to_string()],
+            header_timeout: Duration::from_secs(1),
+        })
+        .unwrap();
+        let header = b"PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\n";
+
+        let balancer = "10.1.2.3:40000".parse().unwrap();
+        let client = proxy_protocol
+            .client_addr(&mut header.as_slice(), balancer)
+            .await
+            .unwrap();
+        assert_eq!(client, "203.0.113.7:56324".parse().unwrap());
+
+        // An untrusted peer's bytes are left alone, header or not.
+        let stranger = "198.51.100.9:40000".parse().unwrap();
+        let mut input = header.as_slice();
+        let client = proxy_protocol
+            .client_addr(&mut input, stranger)
+            .await
+            .unwrap();
+        assert_eq!(client, stranger);
+        assert_eq!(input, header);
+
+        let disabled = ProxyProtocolConfig::default();
+        assert!(ProxyProtocol::from_config(&disabled).is_none());
+
+        // Without trusted sources no peer may choose its address.
+        let untrusting = ProxyProtocol::from_config(&ProxyProtocolConfig {
+            enabled: true,
+            trusted_sources: vec!["not-an-ip".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!untrusting.trusts(balancer.ip()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_silent_trusted_peer_times_out() {
+        let proxy_protocol = ProxyProtocol::from_config(&ProxyProtocolConfig {
+            enabled: true,
+            trusted_sources: vec!["10.0.0.0/8".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let (_client, mut server) = tokio::io::duplex(64);
+        let err = proxy_protocol
+            .client_addr(&mut server, "10.1.2.3:40000".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
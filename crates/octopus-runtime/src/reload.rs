@@ -0,0 +1,406 @@
+//! Staged configuration hot-reload.
+//!
+//! A reloaded [`Config`] is applied in two steps: everything it describes is
+//! first built into a fresh, unpublished [`Router`] (validation, upstream
+//! references, every route, and script plugin compilation), and only when all
+//! of that succeeds is the staged router swapped into the live one. Any
+//! failure leaves the running configuration untouched.
+
+use octopus_config::types::{
+    ConcurrencyConfig, PluginConfig, SloConfig, SloObjectiveConfig, UpstreamOpenApiConfig,
+};
+use octopus_config::{validate_config, Config};
+use octopus_core::{Error, Result, UpstreamCluster, UpstreamInstance};
+use octopus_farp::RouteGenerator;
+use octopus_health::CircuitBreakerConfig;
+use octopus_metrics::{SloObjective, SloTracker};
+use octopus_router::{RouteBuilder, RouteCorsOverride, Router};
+use std::collections::HashMap;
+
+/// Register `config`'s upstreams and routes on `router`, failing on the first
+/// route that can't be built or inserted.
+pub(crate) fn register_config(router: &Router, config: &Config) -> Result<()> {
+    for upstream_config in &config.upstreams {
+        let mut cluster = UpstreamCluster::new(&upstream_config.name);
+        cluster.slow_start = upstream_config.slow_start;
+        for instance_config in &upstream_config.instances {
+            let mut instance = UpstreamInstance::new(
+                &instance_config.id,
+                &instance_config.host,
+                instance_config.port,
+            );
+            instance.weight = instance_config.weight;
+            cluster.add_instance(instance);
+        }
+        router.register_upstream(cluster);
+
+        if let Some(openapi) = &upstream_config.openapi {
+            register_openapi_routes(router, &upstream_config.name, openapi)?;
+        }
+    }
+
+    for route_config in &config.routes {
+        for method_str in &route_config.methods {
+            let method = method_str
+                .parse()
+                .map_err(|_| Error::Config(format!("Invalid HTTP method: {method_str}")))?;
+
+            let mut builder = RouteBuilder::new()
+                .path(&route_config.path)
+                .method(method)
+                .upstream_name(&route_config.upstream)
+                .priority(route_config.priority)
+                .auth_provider(route_config.auth_provider.as_deref())
+                .skip_auth(route_config.skip_auth)
+                .require_roles(&route_config.require_roles)
+                .require_scopes(&route_config.require_scopes)
+                .authz_rule(route_config.authz_rule.as_deref());
+
+            if let Some(ref pfx) = route_config.strip_prefix {
+                builder = builder.strip_prefix(pfx);
+            }
+            if let Some(ref pfx) = route_config.add_prefix {
+                builder = builder.add_prefix(pfx);
+            }
+            if let Some(ref rl) = route_config.rate_limit {
+                builder = builder.rate_limit(rl.requests_per_window, rl.window_size);
+            }
+            if let Some(timeout) = route_config.timeout {
+                builder = builder.timeout(Some(timeout));
+            }
+            if let Some(ref cors_cfg) = route_config.cors {
+                builder = builder.cors(Some(RouteCorsOverride {
+                    allowed_origins: cors_cfg.allowed_origins.clone(),
+                    allowed_methods: cors_cfg.allowed_methods.clone(),
+                    allowed_headers: cors_cfg.allowed_headers.clone(),
+                    allow_credentials: cors_cfg.allow_credentials,
+                    max_age: cors_cfg.max_age,
+                }));
+            }
+            if let Some(spec) = route_config.proxy_spec() {
+                builder = builder.proxy(Some(spec));
+            }
+            builder = builder.fallback(route_config.fallback_spec());
+            builder = builder.fallback_upstream(route_config.fallback_upstream.clone());
+            builder = builder.service(route_config.service.clone());
+            builder = builder.geo_upstreams(route_config.geo_upstreams.clone());
+            builder = builder
+                .response_body_limit(route_config.response_body_limit.map(|limit| limit.limit()));
+
+            router.add_route(builder.build()?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Register the routes generated from `upstream`'s local OpenAPI file,
+/// mounted under its prefix, which is stripped before proxying.
+fn register_openapi_routes(
+    router: &Router,
+    upstream: &str,
+    openapi: &UpstreamOpenApiConfig,
+) -> Result<()> {
+    let mut routes = RouteGenerator::new().generate_from_openapi_file(&openapi.path, upstream)?;
+    if !openapi.tags.is_empty() {
+        routes = RouteGenerator::filter_by_tags(routes, &openapi.tags);
+    }
+
+    let prefix = openapi.prefix_for(upstream);
+    for route in RouteGenerator::apply_prefix(routes, &prefix) {
+        // A partly templated segment (`/{id}.json`) is kept literally by the
+        // generator and would never match; such requests are served by the
+        // operation matching the whole segment, if any.
+        if route.path.contains('{') {
+            tracing::debug!(upstream, path = %route.path, "Skipping OpenAPI path the router can't match");
+            continue;
+        }
+        let method = route
+            .method
+            .parse()
+            .map_err(|_| Error::Config(format!("Invalid HTTP method: {}", route.method)))?;
+
+        let mut builder = RouteBuilder::new()
+            .path(route.path)
+            .method(method)
+            .upstream_name(upstream)
+            .priority(openapi.priority);
+        if !prefix.is_empty() {
+            builder = builder.strip_prefix(&prefix);
+        }
+        router.add_route(builder.build()?)?;
+    }
+    Ok(())
+}
+
+/// Compile every enabled script plugin so a broken script rejects the reload
+/// instead of failing requests later.
+async fn check_plugins(plugins: &[PluginConfig]) -> Result<()> {
+    for p in plugins
+        .iter()
+        .filter(|p| p.enabled && p.plugin_type == "script")
+    {
+        let value = serde_json::Value::Object(p.config.clone().into_iter().collect());
+        let cfg = serde_json::from_value::<octopus_scripting::ScriptMiddlewareConfig>(value)
+            .map_err(|e| Error::Config(format!("plugin '{}': {e}", p.name)))?;
+        octopus_scripting::ScriptMiddleware::new(cfg)
+            .prepare()
+            .await
+            .map_err(|e| Error::Config(format!("plugin '{}': {e}", p.name)))?;
+    }
+    Ok(())
+}
+
+/// Build `config` into a staged router without touching the live one.
+pub(crate) async fn stage(config: &Config) -> Result<Router> {
+    validate_config(config)?;
+    check_plugins(&config.plugins).await?;
+
+    build_router(config)
+}
+
+/// Concurrency limits of the routes in `config`, by method and path
+pub(crate) fn concurrency_limits(
+    config: &Config,
+) -> Vec<(http::Method, String, ConcurrencyConfig)> {
+    config
+        .routes
+        .iter()
+        .filter_map(|route| Some((route, route.concurrency.as_ref()?)))
+        .flat_map(|(route, limit)| {
+            route.methods.iter().filter_map(move |method| {
+                Some((method.parse().ok()?, route.path.clone(), limit.clone()))
+            })
+        })
+        .collect()
+}
+
+/// Circuit breaker configs of the instances of every upstream in `config`
+/// that sets `circuit_breaker`, for the proxy's breaker
+pub(crate) fn circuit_breaker_configs(config: &Config) -> HashMap<String, CircuitBreakerConfig> {
+    let mut configs = HashMap::new();
+    for upstream in &config.upstreams {
+        let Some(breaker) = &upstream.circuit_breaker else {
+            continue;
+        };
+        let breaker = CircuitBreakerConfig {
+            failure_threshold: f64::from(breaker.error_threshold) / 100.0,
+            min_requests: u64::from(breaker.min_requests),
+            open_timeout: breaker.timeout,
+            failure_status_codes: breaker.failure_status_codes.clone(),
+            count_connection_errors: breaker.count_connection_errors,
+            count_timeouts: breaker.count_timeouts,
+            ..Default::default()
+        };
+        for instance in &upstream.instances {
+            configs.insert(instance.id.clone(), breaker.clone());
+        }
+    }
+    configs
+}
+
+/// The SLO tracker `slo` describes; objectives are only read at startup
+pub(crate) fn slo_tracker(slo: &SloConfig) -> SloTracker {
+    let objective = |o: &SloObjectiveConfig| SloObjective {
+        success_target: o.success_target,
+        latency_threshold: o.latency_threshold,
+        latency_target: o.latency_target,
+        window: o.window,
+        burn_rate_threshold: o.burn_rate_threshold,
+        min_requests: o.min_requests,
+    };
+    let mut tracker = SloTracker::new();
+    if let Some(default) = &slo.default {
+        tracker = tracker.with_default_objective(objective(default));
+    }
+    for (route, o) in &slo.routes {
+        tracker = tracker.with_objective(route.clone(), objective(o));
+    }
+    tracker
+}
+
+/// Build the router `config` describes, as the server does at startup,
+/// without starting anything. Used to resolve requests offline, e.g. by
+/// `octopus test-route`.
+pub fn build_router(config: &Config) -> Result<Router> {
+    let router = Router::new().with_trailing_slash(config.gateway.trailing_slash.into());
+    register_config(&router, config)?;
+    Ok(router)
+}
+
+/// Stage `config` and, if it is fully valid, swap it into `router`. Returns
+/// the applied route and upstream counts; on error `router` is unchanged.
+pub(crate) async fn apply(router: &Router, config: &Config) -> Result<(usize, usize)> {
+    let staged = stage(config).await?;
+    router.replace_with(staged);
+    Ok((config.routes.len(), config.upstreams.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Method;
+    use octopus_config::ConfigFormat;
+
+    fn config(routes: &str) -> Config {
+        let yaml = format!(
+            r#"
+gateway:
+  listen: "127.0.0.1:8080"
+upstreams:
+  - name: users
+    instances:
+      - id: users-1
+        host: 127.0.0.1
+        port: 9001
+  - name: orders
+    instances:
+      - id: orders-1
+        host: 127.0.0.1
+        port: 9002
+routes:
+{routes}
+"#
+        );
+        octopus_config::load_str(&yaml, ConfigFormat::Yaml).unwrap()
+    }
+
+    fn live_router() -> Router {
+        let router = Router::new();
+        register_config(
+            &router,
+            &config("  - path: /users\n    methods: [GET]\n    upstream: users"),
+        )
+        .unwrap();
+        router
+    }
+
+    #[tokio::test]
+    async fn valid_reload_replaces_routes() {
+        let router = live_router();
+
+        let counts = apply(
+            &router,
+            &config("  - path: /orders/:id\n    methods: [GET, DELETE]\n    upstream: orders"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(counts, (1, 2));
+        assert!(router.find_route("", &Method::GET, "/users").is_err());
+        let route = router.find_route("", &Method::DELETE, "/orders/7").unwrap();
+        assert_eq!(route.upstream_name, "orders");
+        assert!(router.get_upstream("orders").is_some());
+    }
+
+    #[tokio::test]
+    async fn invalid_reload_keeps_serving_old_config() {
+        let router = live_router();
+
+        let unknown_upstream = apply(
+            &router,
+            &config("  - path: /orders\n    methods: [GET]\n    upstream: missing"),
+        )
+        .await;
+        assert!(unknown_upstream.is_err());
+
+        let mut broken_plugin =
+            config("  - path: /orders\n    methods: [GET]\n    upstream: orders");
+        broken_plugin.plugins = vec![PluginConfig {
+            name: "bad".to_string(),
+            plugin_type: "script".to_string(),
+            enabled: true,
+            priority: 0,
+            config: [("code".to_string(), serde_json::json!("let x = ;"))]
+                .into_iter()
+                .collect(),
+            sandbox: Default::default(),
+        }];
+        let err = apply(&router, &broken_plugin).await.unwrap_err();
+        assert!(err.to_string().contains("plugin 'bad'"));
+
+        let route = router.find_route("", &Method::GET, "/users").unwrap();
+        assert_eq!(route.upstream_name, "users");
+        assert!(router.find_route("", &Method::GET, "/orders").is_err());
+        assert_eq!(router.total_route_count(), 1);
+    }
+
+    #[test]
+    fn openapi_file_routes_are_bound_to_their_upstream() {
+        let spec = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../octopus-farp/tests/fixtures/petstore.yaml"
+        );
+        let yaml = format!(
+            r#"
+gateway:
+  listen: "127.0.0.1:8080"
+upstreams:
+  - name: Pets
+    instances:
+      - id: pets-1
+        host: 127.0.0.1
+        port: 9003
+    openapi:
+      path: {spec}
+      tags: [pets]
+"#
+        );
+        let router = Router::new();
+        register_config(
+            &router,
+            &octopus_config::load_str(&yaml, ConfigFormat::Yaml).unwrap(),
+        )
+        .unwrap();
+
+        // GET /pets and GET /pets/{petId} carry the tag; so does
+        // GET /pets/{petId}.json, which the router can't match.
+        assert_eq!(router.total_route_count(), 2);
+        let route = router.find_route("", &Method::GET, "/pets/pets/7").unwrap();
+        assert_eq!(route.upstream_name, "Pets");
+        assert_eq!(route.strip_prefix.as_deref(), Some("/pets"));
+        assert_eq!(route.priority, 100);
+        assert!(router.find_route("", &Method::POST, "/pets/pets").is_err());
+    }
+
+    #[test]
+    fn openapi_routes_are_mounted_under_a_configured_prefix() {
+        let spec = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../octopus-farp/tests/fixtures/petstore.yaml"
+        );
+        let yaml = format!(
+            r#"
+gateway:
+  listen: "127.0.0.1:8080"
+upstreams:
+  - name: Pets
+    instances:
+      - id: pets-1
+        host: 127.0.0.1
+        port: 9003
+    openapi:
+      path: {spec}
+      prefix: /api/v2/
+"#
+        );
+        let router = Router::new();
+        register_config(
+            &router,
+            &octopus_config::load_str(&yaml, ConfigFormat::Yaml).unwrap(),
+        )
+        .unwrap();
+
+        let route = router
+            .find_route("", &Method::DELETE, "/api/v2/pets/7")
+            .unwrap();
+        assert_eq!(route.path, "/api/v2/pets/:petId");
+        assert_eq!(route.strip_prefix.as_deref(), Some("/api/v2"));
+        // The `.json` variant reaches the upstream through `/pets/{petId}`.
+        let route = router
+            .find_route("", &Method::GET, "/api/v2/pets/7.json")
+            .unwrap();
+        assert_eq!(route.path, "/api/v2/pets/:petId");
+        assert!(router.find_route("", &Method::GET, "/pets/7").is_err());
+    }
+}
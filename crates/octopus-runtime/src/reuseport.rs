@@ -0,0 +1,153 @@
+//! Listener sockets.
+//!
+//! On Linux the gateway binds several sockets to its listen address with
+//! `SO_REUSEPORT`, each drained by its own accept loop, and the kernel spreads
+//! incoming connections across them. Where the option is unavailable a single
+//! socket is bound instead.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+
+/// Pending-connection backlog of each listener socket
+const BACKLOG: u32 = 1024;
+
+/// A bound listener socket that an accept loop drains
+pub(crate) trait Accept: Send + Sync + 'static {
+    /// Stream of an accepted connection
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Wait for the next connection and the address of its peer
+    fn accept(&self) -> impl Future<Output = io::Result<(Self::Stream, SocketAddr)>> + Send;
+}
+
+impl Accept for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        TcpListener::accept(self).await
+    }
+}
+
+/// Number of listener sockets for `gateway.acceptors`: the configured count,
+/// or with 0 one per worker thread on Linux and a single socket elsewhere.
+pub(crate) fn listener_count(acceptors: usize, workers: usize) -> usize {
+    match acceptors {
+        0 if cfg!(target_os = "linux") => workers.max(1),
+        0 => 1,
+        n => n,
+    }
+}
+
+/// Bind `count` listeners sharing `addr` through `SO_REUSEPORT`.
+///
+/// Falls back to one listener when `count` is 1 or the platform refuses the
+/// option. With port 0 all sockets share the port the first one was given.
+pub(crate) fn bind_listeners(addr: SocketAddr, count: usize) -> io::Result<Vec<TcpListener>> {
+    if count <= 1 {
+        return Ok(vec![listen(plain_socket(addr)?, addr)?]);
+    }
+
+    let mut listeners = Vec::with_capacity(count);
+    let mut addr = addr;
+    for _ in 0..count {
+        let socket = match reuseport_socket(addr) {
+            Ok(socket) => socket,
+            Err(e) if listeners.is_empty() => {
+                tracing::warn!(
+                    error = %e,
+                    requested = count,
+                    "SO_REUSEPORT unavailable; binding a single listener"
+                );
+                return Ok(vec![listen(plain_socket(addr)?, addr)?]);
+            }
+            Err(e) => return Err(e),
+        };
+        let listener = listen(socket, addr)?;
+        addr = listener.local_addr()?;
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+fn listen(socket: TcpSocket, addr: SocketAddr) -> io::Result<TcpListener> {
+    socket.bind(addr)?;
+    socket.listen(BACKLOG)
+}
+
+/// A socket set up like `TcpListener::bind` does it
+fn plain_socket(addr: SocketAddr) -> io::Result<TcpSocket> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    #[cfg(unix)]
+    socket.set_reuseaddr(true)?;
+    Ok(socket)
+}
+
+#[cfg(target_os = "linux")]
+fn reuseport_socket(addr: SocketAddr) -> io::Result<TcpSocket> {
+    let socket = plain_socket(addr)?;
+    socket.set_reuseport(true)?;
+    Ok(socket)
+}
+
+/// Other platforms either lack `SO_REUSEPORT` or do not balance connections
+/// across the sockets sharing a port.
+#[cfg(not(target_os = "linux"))]
+fn reuseport_socket(_addr: SocketAddr) -> io::Result<TcpSocket> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "SO_REUSEPORT load balancing is only supported on Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listener_count() {
+        assert_eq!(listener_count(3, 8), 3);
+        if cfg!(target_os = "linux") {
+            assert_eq!(listener_count(0, 8), 8);
+        } else {
+            assert_eq!(listener_count(0, 8), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_single_listener() {
+        let listeners = bind_listeners("127.0.0.1:0".parse().unwrap(), 1).unwrap();
+        assert_eq!(listeners.len(), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_reuseport_listeners_share_a_port_and_accept() {
+        use futures::future::select_all;
+
+        let listeners = bind_listeners("127.0.0.1:0".parse().unwrap(), 4).unwrap();
+        assert_eq!(listeners.len(), 4);
+        let addr = listeners[0].local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+        assert!(listeners.iter().all(|l| l.local_addr().unwrap() == addr));
+
+        let mut clients = Vec::new();
+        for _ in 0..16 {
+            clients.push(tokio::net::TcpStream::connect(addr).await.unwrap());
+        }
+
+        let mut accepted = 0;
+        while accepted < clients.len() {
+            let (result, _, _) = select_all(listeners.iter().map(|l| Box::pin(l.accept()))).await;
+            let (_, peer) = result.unwrap();
+            assert!(clients.iter().any(|c| c.local_addr().unwrap() == peer));
+            accepted += 1;
+        }
+    }
+}
@@ -1,22 +1,38 @@
 //! HTTP server implementation
 
+use crate::conn_limits::{ConnectionLimits, Watchdog};
+use crate::events::{EventBus, EventSink, GatewayEvent};
+use crate::handler::Plane;
 use crate::lifecycle::LifecycleState;
-use crate::shutdown::ShutdownSignal;
+use crate::listener::HttpVersions;
+use crate::plugins::{LoadedPlugins, PluginFactory, PluginHandle};
+use crate::proxy_protocol::ProxyProtocol;
+use crate::shutdown::{BackgroundTasks, ShutdownSequence, ShutdownSignal};
 use crate::worker::{WorkerConfig, WorkerPool};
 use crate::RuntimeState;
 use octopus_config::{Config, ConfigWatcher};
 use octopus_core::{Error, Result};
 use octopus_farp::FarpApiHandler;
 use octopus_plugin_runtime::PluginManager;
-use octopus_protocols::{GrpcHandler, ProtocolHandler};
-use octopus_proxy::{HttpClient, HttpProxy, ProxyConfig};
+use octopus_protocols::{
+    GrpcHandler, ProtocolDispatcher, ProtocolHandler, SseHandler, WebSocketHandler,
+};
+use octopus_proxy::{
+    HeaderFilter, HeaderStripPolicy, HttpClient, HttpProxy, ProxyConfig, ProxyLimits, RetryPolicy,
+};
 use octopus_router::Router;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Cap on the per-route rate limiter's in-process window counters. Live
+/// windows are never evicted: once it is reached, new clients are limited
+/// until windows expire.
+const RATE_LIMIT_MAX_KEYS: usize = 100_000;
+
 /// How a connection's transport is handled.
 #[derive(Clone)]
 enum TlsMode {
@@ -28,14 +44,18 @@ enum TlsMode {
     Operator(octopus_tls::SwappableTlsAcceptor),
 }
 
-/// Serve a single connection (HTTP/1.1 or HTTP/2 auto-detected), injecting the
-/// optional client-certificate CN (mTLS) into request extensions.
+/// Serve a single connection over the HTTP `versions` it may speak, injecting
+/// the optional client-certificate CN (mTLS) into request extensions.
+#[allow(clippy::too_many_arguments)]
 async fn serve_io<IO>(
     io: IO,
     handler: crate::RequestHandler,
     client_cn: Option<String>,
     sni: Option<String>,
     peer_addr: SocketAddr,
+    tls: bool,
+    versions: HttpVersions,
+    watchdog: Watchdog,
 ) where
     IO: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
 {
@@ -50,40 +70,106 @@ async fn serve_io<IO>(
                 req.extensions_mut().insert(octopus_tls::TlsSniName(sni));
                 req.extensions_mut()
                     .insert(crate::handler::ClientAddr(addr));
+                req.extensions_mut().insert(crate::handler::ClientTls(tls));
                 handler.handle(req).await.or_else(|e| {
-                    tracing::error!("Request handler error: {}", e);
-                    let status = e.to_status_code();
-                    http::Response::builder()
-                        .status(status)
-                        .body(http_body_util::Either::Left(http_body_util::Full::new(
-                            bytes::Bytes::from(format!("Error: {e}")),
-                        )))
-                        .map_err(|e| {
-                            tracing::error!("Failed to build error response: {}", e);
-                            e
-                        })
+                    // Full details go to the log; the client only sees the
+                    // error code and its client-safe message.
+                    tracing::error!(code = %e.code(), error = %e, "Request handler error");
+                    Ok::<_, http::Error>(
+                        octopus_core::ErrorResponse::from(&e)
+                            .into_response()
+                            .map(http_body_util::Either::Left),
+                    )
                 })
             }
         });
-    let io = hyper_util::rt::TokioIo::new(io);
-    if let Err(e) =
-        hyper_util::server::conn::auto::Builder::new(hyper_util::rt::TokioExecutor::new())
-            .serve_connection_with_upgrades(io, service)
-            .await
-    {
+    if let Err(e) = crate::listener::serve_connection(io, service, versions, watchdog).await {
         tracing::error!("Connection error: {}", e);
     }
 }
 
+/// Accept connections on `listener` until `stop` is cancelled, serving each
+/// on its own task.
+async fn accept_loop<L: crate::reuseport::Accept>(
+    listener: L,
+    handler: crate::RequestHandler,
+    tls_mode: TlsMode,
+    h2c: bool,
+    proxy_protocol: Option<Arc<ProxyProtocol>>,
+    limits: ConnectionLimits,
+    stop: tokio_util::sync::CancellationToken,
+) {
+    loop {
+        let (mut stream, peer) = tokio::select! {
+            _ = stop.cancelled() => break,
+            result = listener.accept() => match result {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::error!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+        };
+        tracing::trace!("Accepted connection from {}", peer);
+        let watchdog = limits.watch();
+
+        let handler = handler.clone();
+        let tls_mode = tls_mode.clone();
+        let proxy_protocol = proxy_protocol.clone();
+
+        // Spawn a task to handle this connection
+        tokio::spawn(async move {
+            // Behind an L4 load balancer the client is named by the PROXY
+            // protocol header in front of the connection.
+            let addr = match proxy_protocol {
+                Some(proxy_protocol) => match proxy_protocol.client_addr(&mut stream, peer).await {
+                    Ok(client) => client,
+                    Err(e) => {
+                        tracing::warn!(
+                            peer = %peer,
+                            error = %e,
+                            "Rejected connection without a valid PROXY protocol header"
+                        );
+                        return;
+                    }
+                },
+                None => peer,
+            };
+
+            match tls_mode {
+                TlsMode::Plain => {
+                    let versions = HttpVersions::for_plaintext(h2c);
+                    serve_io(stream, handler, None, None, addr, false, versions, watchdog).await;
+                }
+                TlsMode::Static(acceptor) | TlsMode::Operator(acceptor) => {
+                    match watchdog.before_first_byte(acceptor.accept(stream)).await {
+                        Some(Ok(tls_stream)) => {
+                            let cn = octopus_tls::extract_client_cn(&tls_stream);
+                            let sni = octopus_tls::extract_server_name(&tls_stream);
+                            let alpn = octopus_tls::extract_alpn_protocol(&tls_stream);
+                            let versions = HttpVersions::for_tls(alpn.as_deref());
+                            serve_io(tls_stream, handler, cn, sni, addr, true, versions, watchdog)
+                                .await;
+                        }
+                        Some(Err(e)) => tracing::error!("TLS handshake failed: {}", e),
+                        None => tracing::debug!(peer = %peer, "TLS handshake timed out"),
+                    }
+                }
+            }
+        });
+    }
+}
+
 /// Spawn a background task that reloads the file-based TLS certificate when the
 /// cert file's modification time changes, rebuilding the config (preserving mTLS
 /// and ALPN) and swapping it into the live acceptor with no downtime.
 fn spawn_cert_reload(
+    tasks: &BackgroundTasks,
     acceptor: octopus_tls::SwappableTlsAcceptor,
     tls_cfg: octopus_tls::TlsConfig,
     interval: Duration,
 ) {
-    tokio::spawn(async move {
+    tasks.spawn("tls", async move {
         let cert_path = tls_cfg.cert_file.clone();
         let mut last = std::fs::metadata(&cert_path)
             .and_then(|m| m.modified())
@@ -137,6 +223,122 @@ fn convention_for_auth(
     }
 }
 
+/// Build the idempotency middleware over its configured backend, with the
+/// backend's health when it is a shared store.
+///
+/// Keys live in-process unless `redis_url` names a shared store. In-process
+/// in-flight keys get an uncapped backend of their own, bounded by the
+/// requests in flight and `lock_timeout`, so a full response store never
+/// turns away a key being taken.
+async fn idempotency_middleware(
+    cfg: &octopus_config::types::IdempotencyConfig,
+) -> Result<(
+    Arc<dyn octopus_core::middleware::Middleware>,
+    Option<Arc<dyn octopus_state::BackendHealthSource>>,
+)> {
+    if let Some(url) = &cfg.redis_url {
+        #[cfg(feature = "redis")]
+        {
+            let backend = octopus_state::RedisBackend::new(url)
+                .await
+                .map_err(|e| Error::Config(format!("idempotency.redis_url {url}: {e}")))?;
+            let health: Arc<dyn octopus_state::BackendHealthSource> = Arc::new(backend.clone());
+            return Ok((
+                Arc::new(octopus_middleware::Idempotency::new(cfg, backend)?),
+                Some(health),
+            ));
+        }
+        #[cfg(not(feature = "redis"))]
+        return Err(Error::Config(format!(
+            "idempotency.redis_url {url} needs octopus built with the redis feature"
+        )));
+    }
+
+    let backend = octopus_state::InMemoryBackend::from_config(&octopus_state::StateConfig {
+        max_entries: Some(cfg.max_entries),
+        ..Default::default()
+    });
+    let locks = octopus_state::InMemoryBackend::from_config(&octopus_state::StateConfig::default());
+    Ok((
+        Arc::new(octopus_middleware::Idempotency::new(cfg, backend)?.with_locks(locks)),
+        None,
+    ))
+}
+
+/// Build the request signing middleware over its nonce store, with the
+/// store's health when it is shared.
+///
+/// Nonces live in-process unless `redis_url` names a shared store. The
+/// in-process store is swept but never capped: evicting a nonce before
+/// `max_skew` passes would let its request be replayed.
+async fn request_signing_middleware(
+    cfg: &octopus_config::types::RequestSigningConfig,
+) -> Result<(
+    Arc<dyn octopus_core::middleware::Middleware>,
+    Option<Arc<dyn octopus_state::BackendHealthSource>>,
+)> {
+    if let Some(url) = &cfg.redis_url {
+        #[cfg(feature = "redis")]
+        {
+            let backend = octopus_state::RedisBackend::new(url)
+                .await
+                .map_err(|e| Error::Config(format!("request_signing.redis_url {url}: {e}")))?;
+            let health: Arc<dyn octopus_state::BackendHealthSource> = Arc::new(backend.clone());
+            return Ok((
+                Arc::new(octopus_middleware::RequestSigning::new(cfg, backend)?),
+                Some(health),
+            ));
+        }
+        #[cfg(not(feature = "redis"))]
+        return Err(Error::Config(format!(
+            "request_signing.redis_url {url} needs octopus built with the redis feature"
+        )));
+    }
+
+    let backend =
+        octopus_state::InMemoryBackend::from_config(&octopus_state::StateConfig::default());
+    Ok((
+        Arc::new(octopus_middleware::RequestSigning::new(cfg, backend)?),
+        None,
+    ))
+}
+
+/// Map the `gateway.unmatched` config onto the handler's policy.
+fn unmatched_policy(
+    cfg: &octopus_config::types::UnmatchedConfig,
+) -> Result<crate::unmatched::UnmatchedPolicy> {
+    use crate::unmatched::UnmatchedPolicy;
+    use octopus_config::types::UnmatchedConfig;
+
+    let status = |code: u16| {
+        http::StatusCode::from_u16(code)
+            .map_err(|_| Error::Config(format!("invalid unmatched status: {code}")))
+    };
+    Ok(match cfg {
+        UnmatchedConfig::Respond {
+            status: code,
+            content_type,
+            body,
+        } => UnmatchedPolicy::Respond {
+            status: status(*code)?,
+            content_type: http::HeaderValue::from_str(content_type).map_err(|_| {
+                Error::Config(format!("invalid unmatched content_type: {content_type}"))
+            })?,
+            body: bytes::Bytes::from(body.clone()),
+        },
+        UnmatchedConfig::Redirect {
+            url,
+            status: code,
+            preserve_path,
+        } => UnmatchedPolicy::Redirect {
+            url: url.clone(),
+            status: status(*code)?,
+            preserve_path: *preserve_path,
+        },
+        UnmatchedConfig::Upstream { upstream } => UnmatchedPolicy::Upstream(upstream.clone()),
+    })
+}
+
 /// Shared, lock-free handle to the operator's virtual gateway index.
 type GatewayIndexHandle = std::sync::Arc<arc_swap::ArcSwap<octopus_router::VirtualGatewayIndex>>;
 
@@ -162,6 +364,18 @@ pub struct Server {
     /// Shared virtual gateway index from the operator, handed to the request
     /// handler so it can resolve a request's gateway by host. `None` without k8s.
     gateway_index: Option<GatewayIndexHandle>,
+    /// Gateway lifecycle and security events.
+    events: EventBus,
+    /// Initialized static plugins, by name, for the middleware chain.
+    static_plugins: HashMap<String, Arc<dyn octopus_core::middleware::Middleware>>,
+    /// Request, upstream and plugin metrics, shared with the static plugins.
+    metrics: Arc<octopus_metrics::MetricsCollector>,
+    /// Handles to stop the static plugins at shutdown.
+    plugin_handles: Vec<PluginHandle>,
+    /// How long each plugin gets to stop at shutdown.
+    plugin_stop_timeout: Duration,
+    /// Long-running background tasks, cancelled at shutdown.
+    tasks: BackgroundTasks,
 }
 
 impl std::fmt::Debug for Server {
@@ -210,8 +424,54 @@ impl Server {
         self.lifecycle.clone()
     }
 
+    /// Get the gateway event bus; sinks added here receive all later events.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Resolver for routes naming a discovery `service`, caching lookups
+    /// for `farp.discovery.resolve_cache_ttl` (`None` without discovery).
+    ///
+    /// Installed whenever discovery is configured, not only when a startup
+    /// route names a service, so service routes added by a reload resolve.
+    async fn upstream_resolver(&self) -> Option<Arc<dyn octopus_core::UpstreamResolver>> {
+        let discovery = self.config.farp.discovery.as_ref()?;
+        let providers = ServerBuilder::discovery_providers(discovery).await;
+        if providers.is_empty() {
+            if self
+                .config
+                .routes
+                .iter()
+                .any(|route| route.service.is_some())
+            {
+                tracing::warn!("Routes name a service but no discovery backend is enabled");
+            }
+            return None;
+        }
+        Some(Arc::new(octopus_core::CachedResolver::new(
+            octopus_discovery::DiscoveryResolver::new(providers),
+            discovery.resolve_cache_ttl,
+        )))
+    }
+
     /// Run the server
     pub async fn run(&self) -> Result<()> {
+        // Probe the upstreams before anything can report ready; a strict
+        // check that fails ends startup here.
+        if let Some(check) = &self.config.gateway.startup_check {
+            crate::startup_check::run(&self.router, check).await?;
+        }
+
+        // Active health checks take failing instances out of rotation.
+        let checked = crate::health_checks::start(
+            Arc::clone(&self.router),
+            &self.config.upstreams,
+            &self.tasks,
+        );
+        if checked > 0 {
+            tracing::info!(upstreams = checked, "Active health checks started");
+        }
+
         // Set state to running
         {
             let mut state = self.state.write().await;
@@ -225,13 +485,59 @@ impl Server {
             "Server starting"
         );
 
-        // Create TCP listener
-        let listener = tokio::net::TcpListener::bind(self.listen_addr())
-            .await
-            .map_err(|e| {
-                Error::Runtime(format!("Failed to bind to {}: {}", self.listen_addr(), e))
-            })?;
-        // Listener is bound — startup probe can now pass.
+        // Bind the Unix domain socket when configured; it replaces the TCP
+        // listeners. The socket file is removed when `_socket_file` drops at
+        // the end of this function.
+        #[cfg(unix)]
+        let (unix_listener, _socket_file) = match &self.config.gateway.unix_socket {
+            Some(socket) => {
+                let (listener, file) = crate::unix_socket::bind(socket).await.map_err(|e| {
+                    Error::Runtime(format!(
+                        "Failed to bind to {}: {}",
+                        socket.path.display(),
+                        e
+                    ))
+                })?;
+                tracing::info!(path = %socket.path.display(), "Unix domain socket bound");
+                (Some(listener), Some(file))
+            }
+            None => (None, None),
+        };
+        #[cfg(not(unix))]
+        if self.config.gateway.unix_socket.is_some() {
+            return Err(Error::Runtime(
+                "gateway.unix_socket is only supported on Unix".to_string(),
+            ));
+        }
+
+        // Create the TCP listeners (several SO_REUSEPORT sockets where supported)
+        let listeners = if self.config.gateway.unix_socket.is_some() {
+            Vec::new()
+        } else {
+            let listener_count = crate::reuseport::listener_count(
+                self.config.gateway.acceptors,
+                self.worker_pool.worker_count(),
+            );
+            let listeners = crate::reuseport::bind_listeners(self.listen_addr(), listener_count)
+                .map_err(|e| {
+                    Error::Runtime(format!("Failed to bind to {}: {}", self.listen_addr(), e))
+                })?;
+            tracing::info!(listeners = listeners.len(), "Listener sockets bound");
+            listeners
+        };
+        // A separate admin listener takes the admin dashboard, metrics and
+        // FARP off the data-plane address. It serves plain HTTP.
+        let admin_listener = match self.config.admin.listen {
+            Some(addr) => {
+                let listener = tokio::net::TcpListener::bind(addr).await.map_err(|e| {
+                    Error::Runtime(format!("Failed to bind admin listener to {addr}: {e}"))
+                })?;
+                tracing::info!(listen = %addr, "Admin listener bound");
+                Some(listener)
+            }
+            None => None,
+        };
+        // Listeners are bound — startup probe can now pass.
         self.lifecycle.mark_bind_complete();
 
         // Create TLS acceptor if configured
@@ -251,6 +557,7 @@ impl Server {
                     let acceptor = octopus_tls::SwappableTlsAcceptor::new(Arc::new(server_config));
                     if tls_config.enable_cert_reload {
                         spawn_cert_reload(
+                            &self.tasks,
                             acceptor.clone(),
                             tls_cfg.clone(),
                             Duration::from_secs(tls_config.reload_interval_secs),
@@ -279,36 +586,195 @@ impl Server {
         } else if let Some(swappable) = self.operator_tls.clone() {
             TlsMode::Operator(swappable)
         } else {
-            tracing::info!("Server listening on {} (HTTP only)", self.listen_addr());
+            tracing::info!("Server listening (HTTP only)");
             TlsMode::Plain
         };
 
-        // Build the pre-auth request middleware (compression, CORS) from config.
-        let mut middlewares: Vec<Arc<dyn octopus_core::middleware::Middleware>> =
+        // Assemble the chain by phase (pre-auth, auth, post-auth, pre-proxy);
+        // the builder keeps declaration order within each phase. The pre-auth
+        // request middleware (CORS, security headers) comes from config.
+        use octopus_middleware::{MiddlewareBuilder, Phase};
+        let mut pipeline = MiddlewareBuilder::new();
+        // Request IDs are assigned before anything else runs, so every later
+        // middleware, plugin and the upstream see the same ID.
+        if let Some(request_id) = &self.config.gateway.request_id {
+            pipeline = pipeline
+                .with_middleware_in(Phase::PreAuth, crate::chain::build_request_id(request_id)?);
+            tracing::info!(header = %request_id.header, generator = ?request_id.generator, "Request IDs enabled");
+        }
+        // The access log wraps everything else, so it records the final
+        // response and the time the whole chain took.
+        if let Some(logger) =
+            crate::chain::build_access_logger(&self.config.observability.logging, &self.metrics)?
+        {
+            pipeline = pipeline.with_middleware_in(Phase::PreAuth, logger);
+            tracing::info!("Access log enabled");
+        }
+        // Debug headers come next so `total` covers the rest of the chain and
+        // the headers are added to the final response.
+        let debug_headers = self.config.gateway.debug_headers.as_ref();
+        if let Some(config) = debug_headers {
+            pipeline = pipeline.with_middleware_in(
+                Phase::PreAuth,
+                Arc::new(octopus_middleware::DebugHeaders::new(config)?)
+                    as Arc<dyn octopus_core::middleware::Middleware>,
+            );
+            if config.always {
+                tracing::warn!(
+                    "Debug headers enabled for every response; they expose upstream addresses"
+                );
+            } else {
+                tracing::info!(header = %config.header, "Debug headers enabled for triggered requests");
+            }
+        }
+        pipeline = pipeline.with_middlewares_in(
+            Phase::PreAuth,
             crate::chain::build_request_middleware(
-                &self.config.gateway.compression,
                 self.config.cors.as_ref(),
                 &self.config.gateway.security_headers,
-            );
+            )?,
+        );
         tracing::info!(
-            compression = self.config.gateway.compression.enabled,
             cors = self.config.cors.is_some(),
             "Request middleware chain built"
         );
 
+        // GeoIP runs first so blocked countries are rejected before any
+        // other work, and routes see the client's location.
+        let geoip = &self.config.gateway.geoip;
+        if geoip.enabled {
+            #[cfg(feature = "geoip")]
+            {
+                let database = octopus_middleware::GeoIpDatabase::open(&geoip.database)?;
+                let database = Arc::new(database);
+                self.tasks.spawn(
+                    "geoip",
+                    Arc::clone(&database).run_auto_reload(geoip.reload_interval),
+                );
+                let cfg = octopus_middleware::GeoIpConfig {
+                    blocked_countries: geoip.blocked_countries.iter().cloned().collect(),
+                    block_status: http::StatusCode::from_u16(geoip.block_status)
+                        .unwrap_or(http::StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS),
+                    // Invalid entries are reported when forwarded headers are set up
+                    trusted_proxies: self
+                        .config
+                        .gateway
+                        .forwarded
+                        .trusted_proxies
+                        .iter()
+                        .filter_map(|p| octopus_middleware::IpPattern::parse(p).ok())
+                        .collect(),
+                };
+                pipeline = pipeline.with_middleware_in(
+                    Phase::PreAuth,
+                    Arc::new(octopus_middleware::GeoIp::new(database, cfg))
+                        as Arc<dyn octopus_core::middleware::Middleware>,
+                );
+                tracing::info!(
+                    database = %geoip.database,
+                    blocked_countries = geoip.blocked_countries.len(),
+                    "GeoIP enabled"
+                );
+            }
+            #[cfg(not(feature = "geoip"))]
+            tracing::warn!("geoip is configured but octopus was built without the geoip feature");
+        }
+
+        // The debug tap sits outside limits, auth and body rewriting, so a
+        // capture shows what the client sent and got back.
+        let debug_tap = match &self.config.gateway.debug_tap {
+            Some(tap_config) => {
+                let log = Arc::new(octopus_metrics::DebugTapLog::new(tap_config.capacity));
+                pipeline = pipeline.with_middleware_in(
+                    Phase::PreAuth,
+                    Arc::new(octopus_middleware::DebugTap::new(
+                        tap_config,
+                        Arc::clone(&log),
+                    )?) as Arc<dyn octopus_core::middleware::Middleware>,
+                );
+                tracing::warn!(
+                    header = %tap_config.header,
+                    "Debug tap enabled; signed requests are captured with their bodies"
+                );
+                Some(log)
+            }
+            None => None,
+        };
+
         // Add the route-aware rate limiter when any route declares a `rate_limit`.
         // It reads the per-route `MatchedRouteRateLimit` extension injected by the
-        // handler and enforces a fixed window. Uses an in-process state backend;
-        // swap for a shared backend (e.g. Redis) for cross-replica limits.
+        // handler and enforces a fixed window per client. Uses an in-process state
+        // backend, capped so that many distinct clients cannot grow it without
+        // bound; swap for a shared backend (e.g. Redis) for cross-replica limits.
+        // The admin API inspects and resets a client's windows by its address.
+        let mut rate_limits: Option<Arc<dyn octopus_core::RateLimitKeys>> = None;
         if self.config.routes.iter().any(|r| r.rate_limit.is_some()) {
-            let backend = octopus_state::InMemoryBackend::new();
-            middlewares.push(Arc::new(octopus_middleware::RouteRateLimiter::new(backend))
-                as Arc<dyn octopus_core::middleware::Middleware>);
+            let backend =
+                octopus_state::InMemoryBackend::from_config(&octopus_state::StateConfig {
+                    max_entries: Some(RATE_LIMIT_MAX_KEYS),
+                    ..Default::default()
+                });
+            let limiter = Arc::new(octopus_middleware::RouteRateLimiter::new(backend));
+            rate_limits = Some(Arc::clone(&limiter) as Arc<dyn octopus_core::RateLimitKeys>);
+            pipeline = pipeline.with_middleware_in(
+                Phase::PreAuth,
+                limiter as Arc<dyn octopus_core::middleware::Middleware>,
+            );
             tracing::info!("Per-route rate limiting enabled");
         }
 
+        // Concurrency limits run after rate limiting, so requests that are
+        // turned away anyway never take or wait for a slot. The middleware
+        // is always installed, as a reload may add route limits.
+        let mut concurrency = octopus_middleware::ConcurrencyLimit::new(Arc::clone(&self.metrics));
+        if let Some(global) = &self.config.gateway.concurrency {
+            concurrency = concurrency.global(global.clone());
+        }
+        concurrency.set_routes(crate::reload::concurrency_limits(&self.config));
+        // QoS rules on the principal's roles need auth to have run first.
+        let mut concurrency_phase = Phase::PreAuth;
+        if let Some(qos) = &self.config.gateway.qos {
+            let classifier = octopus_middleware::QosClassifier::new(qos.clone());
+            if classifier.needs_auth() {
+                concurrency_phase = Phase::PostAuth;
+            }
+            concurrency = concurrency.qos(classifier);
+        }
+        if !concurrency.is_empty() {
+            tracing::info!(phase = %concurrency_phase, "Concurrency limiting enabled");
+        }
+        pipeline = pipeline.with_middleware_in(
+            concurrency_phase,
+            Arc::new(concurrency.clone()) as Arc<dyn octopus_core::middleware::Middleware>,
+        );
+
         // Load plugin middleware (script plugins) from `config.plugins`.
-        middlewares.extend(crate::chain::build_plugin_middleware(&self.config.plugins));
+        pipeline = pipeline.with_middlewares_in(
+            Phase::PreAuth,
+            crate::chain::build_plugin_middleware(
+                &self.config.plugins,
+                octopus_scripting::ScriptKind::Transform,
+                &self.static_plugins,
+            ),
+        );
+
+        // Shared stores the chain depends on, reported by readiness and the
+        // admin health view.
+        let mut state_backends: Vec<crate::lifecycle::StateBackendCheck> = Vec::new();
+
+        // Signed requests are verified before the auth providers run, so a
+        // replayed or forged request never reaches them.
+        if let Some(signing) = &self.config.gateway.request_signing {
+            let (middleware, health) = request_signing_middleware(signing).await?;
+            pipeline = pipeline.with_middleware_in(Phase::Auth, middleware);
+            state_backends.extend(health.map(|health| ("nonce_store", health)));
+            tracing::info!(
+                keys = signing.keys.len(),
+                max_skew = ?signing.max_skew,
+                require_nonce = signing.require_nonce,
+                "Request signing enabled"
+            );
+        }
 
         // Initialize auth providers from config and add auth middleware
         let mut auth_registry: Option<Arc<octopus_auth::AuthProviderRegistry>> = None;
@@ -417,7 +883,7 @@ impl Server {
 
             // Spawn cache cleanup task
             let registry_clone = Arc::clone(&registry);
-            tokio::spawn(async move {
+            self.tasks.spawn("auth", async move {
                 loop {
                     tokio::time::sleep(std::time::Duration::from_secs(120)).await;
                     registry_clone.cleanup_cache();
@@ -430,7 +896,19 @@ impl Server {
                 authz,
                 self.config.auth.clone(),
             )) as Arc<dyn octopus_core::middleware::Middleware>;
-            middlewares.push(auth_middleware);
+            if debug_headers.is_some() {
+                pipeline = pipeline.with_middleware_in(
+                    Phase::Auth,
+                    Arc::new(octopus_middleware::DebugTimingMark::auth_start()),
+                );
+            }
+            pipeline = pipeline.with_middleware_in(Phase::Auth, auth_middleware);
+            if debug_headers.is_some() {
+                pipeline = pipeline.with_middleware_in(
+                    Phase::Auth,
+                    Arc::new(octopus_middleware::DebugTimingMark::auth_end()),
+                );
+            }
 
             tracing::info!(
                 providers = self.config.auth_providers.len(),
@@ -442,36 +920,210 @@ impl Server {
             auth_registry = Some(registry);
         }
 
+        // Authorization scripts (`kind: authorize`) decide after auth, with the
+        // principal and path params in request extensions, before proxying.
+        pipeline = pipeline.with_middlewares_in(
+            Phase::PostAuth,
+            crate::chain::build_plugin_middleware(
+                &self.config.plugins,
+                octopus_scripting::ScriptKind::Authorize,
+                &self.static_plugins,
+            ),
+        );
+
+        // A/B experiment assignment runs after auth so the authenticated
+        // principal header can serve as the stable user key.
+        if !self.config.gateway.experiments.experiments.is_empty() {
+            let experiments = &self.config.gateway.experiments;
+            let cfg = octopus_middleware::ExperimentsConfig {
+                experiments: experiments
+                    .experiments
+                    .iter()
+                    .map(|e| octopus_middleware::Experiment {
+                        name: e.name.clone(),
+                        variants: e
+                            .variants
+                            .iter()
+                            .map(|v| octopus_middleware::ExperimentVariant {
+                                name: v.name.clone(),
+                                weight: v.weight,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                key_headers: experiments.key_headers.clone(),
+                key_cookies: experiments.key_cookies.clone(),
+                cookie_prefix: experiments.cookie_prefix.clone(),
+                cookie_max_age: experiments.cookie_max_age,
+            };
+            pipeline = pipeline.with_middleware_in(
+                Phase::PostAuth,
+                Arc::new(octopus_middleware::Experiments::new(cfg))
+                    as Arc<dyn octopus_core::middleware::Middleware>,
+            );
+            tracing::info!(
+                experiments = experiments.experiments.len(),
+                "Experiment assignment enabled"
+            );
+        }
+
+        // Request body validation runs after auth so unauthenticated clients
+        // cannot probe the schemas, and just before the body is proxied.
+        let validation = &self.config.gateway.request_validation;
+        if !validation.rules.is_empty() {
+            let cfg = octopus_middleware::RequestValidationConfig {
+                rules: validation
+                    .rules
+                    .iter()
+                    .map(|r| octopus_middleware::ValidationRule {
+                        path: r.path.clone(),
+                        methods: r.methods.clone(),
+                        schema: match &r.schema {
+                            Some(schema) => octopus_middleware::RuleSchema::Inline(schema.clone()),
+                            None => octopus_middleware::RuleSchema::Resolved,
+                        },
+                    })
+                    .collect(),
+                max_body_size: validation.max_body_size,
+            };
+            let mut middleware = octopus_middleware::RequestValidation::new(cfg);
+            if let Some(farp) = &self.farp_handler {
+                middleware = middleware.with_resolver(Arc::new(
+                    crate::farp_schemas::FarpSchemaResolver::new(Arc::clone(farp.federation())),
+                ));
+            } else if validation.rules.iter().any(|r| r.from_farp) {
+                tracing::warn!("request_validation rules with from_farp need FARP enabled; they are not enforced");
+            }
+            pipeline = pipeline.with_middleware_in(
+                Phase::PostAuth,
+                Arc::new(middleware) as Arc<dyn octopus_core::middleware::Middleware>,
+            );
+            tracing::info!(
+                rules = validation.rules.len(),
+                max_body_size = validation.max_body_size,
+                "Request body validation enabled"
+            );
+        }
+
+        // Idempotency keys are taken after auth and validation, so only
+        // requests that would reach the upstream hold one.
+        if let Some(idempotency) = &self.config.gateway.idempotency {
+            let (middleware, health) = idempotency_middleware(idempotency).await?;
+            pipeline = pipeline.with_middleware_in(Phase::PostAuth, middleware);
+            state_backends.extend(health.map(|health| ("idempotency_store", health)));
+            tracing::info!(
+                header = %idempotency.header,
+                ttl = ?idempotency.ttl,
+                "Idempotency keys enabled"
+            );
+        }
+
+        // Compression is the last post-auth layer: auth rejections aren't
+        // compressed, and the pre-proxy transforms below see plain bodies.
+        if let Some(compression) = crate::chain::build_compression(&self.config.gateway.compression)
+        {
+            pipeline = pipeline.with_middleware_in(Phase::PostAuth, compression);
+            tracing::info!("Response compression enabled");
+        }
+
+        // Declarative JSON body transforms run after request validation (which
+        // checks the client's body) and outside response validation (which
+        // checks the upstream's). Rules come from the matched route.
+        if self.config.routes.iter().any(|r| r.transform.is_some()) {
+            pipeline = pipeline.with_middleware_in(
+                Phase::PreProxy,
+                Arc::new(octopus_middleware::BodyTransform::new())
+                    as Arc<dyn octopus_core::middleware::Middleware>,
+            );
+            tracing::info!("Per-route body transforms enabled");
+        }
+
+        // Route header templates, cookie and query rules run after auth, so
+        // `{claim.*}` sees the authenticated caller.
+        if self
+            .config
+            .routes
+            .iter()
+            .any(|r| r.transform.as_ref().is_some_and(|t| t.has_non_body_rules()))
+        {
+            pipeline = pipeline.with_middleware_in(
+                Phase::PreProxy,
+                Arc::new(octopus_middleware::HeaderTransform::default())
+                    as Arc<dyn octopus_core::middleware::Middleware>,
+            );
+            tracing::info!("Per-route header, cookie and query transforms enabled");
+        }
+
+        // Shared by the request handler and response validation.
+        let activity_log = Arc::new(octopus_metrics::ActivityLog::default());
+
+        // Dev-mode response validation sits closest to the proxy so it sees
+        // the upstream response as-is. Off by default: no layer, no cost.
+        let response_validation = &self.config.gateway.response_validation;
+        if response_validation.enabled {
+            match &self.farp_handler {
+                Some(farp) => {
+                    let resolver = Arc::new(crate::farp_schemas::FarpSchemaResolver::new(
+                        Arc::clone(farp.federation()),
+                    ));
+                    let cfg = octopus_middleware::ResponseValidationConfig {
+                        violation_header: response_validation.violation_header,
+                        max_body_size: response_validation.max_body_size,
+                    };
+                    pipeline = pipeline.with_middleware_in(
+                        Phase::PreProxy,
+                        Arc::new(
+                            octopus_middleware::ResponseValidation::new(cfg, resolver).with_sink(
+                                Arc::new(crate::farp_schemas::ActivityLogSink(Arc::clone(
+                                    &activity_log,
+                                ))),
+                            ),
+                        ) as Arc<dyn octopus_core::middleware::Middleware>,
+                    );
+                    tracing::warn!(
+                        "Response validation enabled; intended for dev/staging, not production"
+                    );
+                }
+                None => {
+                    tracing::warn!("response_validation needs FARP enabled; it is not active");
+                }
+            }
+        }
+
         // GraphQL-aware layer runs last (after auth/rate-limit), then delegates
         // to the proxy for valid operations.
         if self.config.graphql.enabled {
-            middlewares.push(Arc::new(octopus_graphql::GraphQlMiddleware::from_config(
-                &self.config.graphql,
-            ))
-                as Arc<dyn octopus_core::middleware::Middleware>);
+            pipeline = pipeline.with_middleware_in(
+                Phase::PreProxy,
+                Arc::new(octopus_graphql::GraphQlMiddleware::from_config(
+                    &self.config.graphql,
+                )) as Arc<dyn octopus_core::middleware::Middleware>,
+            );
             tracing::info!(
                 endpoint = %self.config.graphql.endpoint,
                 "GraphQL gateway layer enabled"
             );
         }
 
-        let middleware_chain: Arc<[Arc<dyn octopus_core::middleware::Middleware>]> =
-            Arc::from(middlewares);
+        let middleware_chain = pipeline.build()?;
 
-        let protocol_handlers: Arc<[Arc<dyn ProtocolHandler>]> = Arc::from(
-            self.protocol_handlers
-                .iter()
-                .map(Arc::clone)
-                .collect::<Vec<_>>(),
-        );
+        // Compile scripts and fetch signing keys before the first request
+        // needs them.
+        crate::warmup::run(
+            &middleware_chain,
+            auth_registry.as_deref(),
+            &self.config.gateway.warmup,
+        )
+        .await?;
 
-        // Create metrics collector and activity log
-        let metrics_collector = Arc::new(octopus_metrics::MetricsCollector::new());
-        let activity_log = Arc::new(octopus_metrics::ActivityLog::default());
+        let protocols = ProtocolDispatcher::new(self.protocol_handlers.clone());
 
-        // Create health tracker and circuit breaker for monitoring
+        // Create health tracker for monitoring; circuit state comes from the
+        // proxy's breaker so the admin view reflects live upstream traffic
         let health_tracker = Arc::new(octopus_health::HealthTracker::default_config());
-        let circuit_breaker = Arc::new(octopus_health::CircuitBreaker::default_config());
+        self.tasks
+            .spawn("health", Arc::clone(&health_tracker).run_cleanup());
+        let circuit_breaker = Arc::clone(self.proxy.circuit_breaker());
 
         let mut handler = crate::RequestHandler::with_all_features(
             Arc::clone(&self.router),
@@ -479,11 +1131,11 @@ impl Server {
             Arc::clone(&self.request_count),
             middleware_chain,
             self.farp_handler.clone(),
-            protocol_handlers,
+            protocols,
             Some(health_tracker),
             Some(circuit_breaker),
             self.plugin_manager.clone(),
-            metrics_collector,
+            Arc::clone(&self.metrics),
             activity_log,
             Some(Arc::new(self.config.clone())),
         );
@@ -491,6 +1143,43 @@ impl Server {
         // Wire the admin IP allowlist (independent of admin auth).
         handler.set_admin_allowed_ips(&self.config.admin.allowed_ips);
 
+        // Serve debug tap captures at /admin/api/debug/captures.
+        if let Some(log) = debug_tap {
+            handler.set_debug_tap(log);
+        }
+
+        // Serve per-route rate-limit windows at /admin/api/rate-limits/:key.
+        if let Some(keys) = rate_limits {
+            handler.set_rate_limits(keys);
+        }
+
+        // Shared state stores feed readiness and /admin/api/health.
+        self.lifecycle.set_state_backends(state_backends.clone());
+        handler.set_state_backends(state_backends);
+
+        // X-Forwarded-* / Forwarded handling, trusting only configured proxies.
+        handler.set_forwarded(&self.config.gateway.forwarded);
+
+        // gRPC service/method mapping and reflection upstream.
+        handler.set_grpc(&self.config.grpc);
+
+        // REST routes served by GraphQL upstreams.
+        handler.set_rest_graphql(&self.config.graphql);
+
+        // Per-route JSON body transforms.
+        handler.set_route_transforms(&self.config.routes);
+
+        // Routes naming a discovery `service` resolve it per request.
+        if let Some(resolver) = self.upstream_resolver().await {
+            handler.set_upstream_resolver(resolver);
+        }
+
+        // Plugin crash and auth failure spike events.
+        handler.set_events(self.events.clone(), &self.config.events);
+
+        // Maintenance mode from config; the admin API toggles it at runtime.
+        handler.set_maintenance(&self.config.gateway.maintenance)?;
+
         // Wire admin auth if configured
         if let Some(ref registry) = auth_registry {
             handler.set_admin_auth(
@@ -502,6 +1191,34 @@ impl Server {
         // Anti host-spoofing (Host == TLS SNI), gated by config.
         handler.set_enforce_sni_check(self.config.gateway.enforce_sni_check);
 
+        // Error body format (RFC 7807 problem+json or legacy JSON), process-wide
+        // so middleware and FARP responses follow the same setting.
+        octopus_core::set_error_format(self.config.gateway.error_format);
+
+        // Buffered request body limit (413 beyond it, checked up front for a
+        // declared Content-Length / `Expect: 100-continue`).
+        handler.set_max_body_size(Some(self.config.gateway.max_body_size));
+        handler.set_multipart(self.config.gateway.multipart.as_ref());
+        handler.set_internal_redirect(self.config.gateway.internal_redirect.as_ref());
+
+        // Requests no route matches: custom body, redirect or catch-all upstream.
+        if let Some(unmatched) = &self.config.gateway.unmatched {
+            handler.set_unmatched(unmatched_policy(unmatched)?);
+        }
+
+        // Tenant resolution (subdomain, path prefix, header or token claim).
+        if let Some(tenancy) = &self.config.gateway.tenancy {
+            handler.set_tenancy(tenancy)?;
+        }
+
+        // Path normalization before routing/auth, gated by config.
+        let normalization = &self.config.gateway.path_normalization;
+        handler.set_path_normalization(
+            normalization
+                .enabled
+                .then(|| normalization.encoded_slash.into()),
+        );
+
         // Share the operator's virtual gateway index so the handler can resolve a
         // request's gateway by host (e.g. gateway-level CORS preflight).
         if let Some(ref gateway_index) = self.gateway_index {
@@ -516,6 +1233,7 @@ impl Server {
                 liveness: probes_cfg.liveness_path.clone(),
                 readiness: probes_cfg.readiness_path.clone(),
                 startup: probes_cfg.startup_path.clone(),
+                require_healthy_upstream: probes_cfg.require_healthy_upstream,
             };
             handler.set_lifecycle(self.lifecycle.clone(), probe_routes);
         }
@@ -560,46 +1278,52 @@ impl Server {
         tokio::pin!(drain_deadline);
         let mut draining = false;
 
+        // One accept loop per listener socket; they run until the loop below
+        // decides to stop accepting.
+        let stop_accepting = tokio_util::sync::CancellationToken::new();
+        let proxy_protocol =
+            ProxyProtocol::from_config(&self.config.gateway.proxy_protocol).map(Arc::new);
+        let limits = ConnectionLimits::from_config(&self.config.gateway.connection_limits);
+        if let Some(listener) = admin_listener {
+            let mut admin = handler.clone();
+            admin.set_plane(Plane::Admin);
+            handler.set_plane(Plane::Data);
+            tokio::spawn(accept_loop(
+                listener,
+                admin,
+                TlsMode::Plain,
+                self.config.gateway.h2c,
+                None,
+                limits,
+                stop_accepting.clone(),
+            ));
+        }
+        for listener in listeners {
+            tokio::spawn(accept_loop(
+                listener,
+                handler.clone(),
+                tls_mode.clone(),
+                self.config.gateway.h2c,
+                proxy_protocol.clone(),
+                limits,
+                stop_accepting.clone(),
+            ));
+        }
+        #[cfg(unix)]
+        if let Some(listener) = unix_listener {
+            tokio::spawn(accept_loop(
+                listener,
+                handler.clone(),
+                tls_mode.clone(),
+                self.config.gateway.h2c,
+                proxy_protocol.clone(),
+                limits,
+                stop_accepting.clone(),
+            ));
+        }
+
         loop {
             tokio::select! {
-                // Accept new connections
-                result = listener.accept() => {
-                    match result {
-                        Ok((stream, addr)) => {
-                            tracing::trace!("Accepted connection from {}", addr);
-
-                            let handler = handler.clone();
-                            let tls_mode = tls_mode.clone();
-
-                            // Spawn a task to handle this connection
-                            tokio::spawn(async move {
-                                match tls_mode {
-                                    TlsMode::Plain => serve_io(stream, handler, None, None, addr).await,
-                                    TlsMode::Static(acceptor) => match acceptor.accept(stream).await {
-                                        Ok(tls_stream) => {
-                                            let cn = octopus_tls::extract_client_cn(&tls_stream);
-                                            let sni = octopus_tls::extract_server_name(&tls_stream);
-                                            serve_io(tls_stream, handler, cn, sni, addr).await;
-                                        }
-                                        Err(e) => tracing::error!("TLS handshake failed: {}", e),
-                                    },
-                                    TlsMode::Operator(acceptor) => match acceptor.accept(stream).await {
-                                        Ok(tls_stream) => {
-                                            let cn = octopus_tls::extract_client_cn(&tls_stream);
-                                            let sni = octopus_tls::extract_server_name(&tls_stream);
-                                            serve_io(tls_stream, handler, cn, sni, addr).await;
-                                        }
-                                        Err(e) => tracing::error!("TLS handshake failed: {}", e),
-                                    },
-                                }
-                            });
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to accept connection: {}", e);
-                        }
-                    }
-                }
-
                 // Handle config hot-reload
                 Some(new_config) = async {
                     match config_reload_rx.as_mut() {
@@ -609,87 +1333,27 @@ impl Server {
                 } => {
                     tracing::info!("Applying hot-reloaded configuration");
 
-                    // 1. Clear existing routes and re-register from new config
-                    self.router.clear();
-
-                    for route_config in &new_config.routes {
-                        for method_str in &route_config.methods {
-                            let method: http::Method = match method_str.parse() {
-                                Ok(m) => m,
-                                Err(_) => {
-                                    tracing::error!(method = %method_str, "Invalid HTTP method in reloaded config, skipping");
-                                    continue;
-                                }
-                            };
-
-                            let mut builder = octopus_router::RouteBuilder::new()
-                                .path(&route_config.path)
-                                .method(method)
-                                .upstream_name(&route_config.upstream)
-                                .priority(route_config.priority)
-                                .auth_provider(route_config.auth_provider.as_deref())
-                                .skip_auth(route_config.skip_auth)
-                                .require_roles(&route_config.require_roles)
-                                .require_scopes(&route_config.require_scopes)
-                                .authz_rule(route_config.authz_rule.as_deref());
-
-                            if let Some(ref pfx) = route_config.strip_prefix {
-                                builder = builder.strip_prefix(pfx);
-                            }
-                            if let Some(ref pfx) = route_config.add_prefix {
-                                builder = builder.add_prefix(pfx);
-                            }
-                            if let Some(ref rl) = route_config.rate_limit {
-                                builder = builder.rate_limit(rl.requests_per_window, rl.window_size);
-                            }
-                            if let Some(timeout) = route_config.timeout {
-                                builder = builder.timeout(Some(timeout));
-                            }
-                            if let Some(ref cors_cfg) = route_config.cors {
-                                builder = builder.cors(Some(octopus_router::RouteCorsOverride {
-                                    allowed_origins: cors_cfg.allowed_origins.clone(),
-                                    allowed_methods: cors_cfg.allowed_methods.clone(),
-                                    allowed_headers: cors_cfg.allowed_headers.clone(),
-                                    allow_credentials: cors_cfg.allow_credentials,
-                                    max_age: cors_cfg.max_age,
-                                }));
-                            }
-                            if let Some(spec) = route_config.proxy_spec() {
-                                builder = builder.proxy(Some(spec));
-                            }
-
-                            match builder.build() {
-                                Ok(route) => {
-                                    if let Err(e) = self.router.add_route(route) {
-                                        tracing::error!(error = %e, "Failed to add route during reload");
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::error!(error = %e, "Failed to build route during reload");
-                                }
-                            }
+                    // Stage the whole config first; the live router is only
+                    // touched once every route, upstream and plugin checks out.
+                    match crate::reload::apply(&self.router, &new_config).await {
+                        Ok((routes, upstreams)) => {
+                            self.proxy.circuit_breaker().set_instance_configs(
+                                crate::reload::circuit_breaker_configs(&new_config),
+                            );
+                            concurrency.set_routes(crate::reload::concurrency_limits(&new_config));
+                            tracing::info!(routes, upstreams, "Configuration reloaded successfully");
+                            self.events.emit(GatewayEvent::ConfigReloaded { routes, upstreams });
                         }
-                    }
-
-                    // 2. Re-register upstreams
-                    for upstream_config in &new_config.upstreams {
-                        let mut cluster = octopus_core::UpstreamCluster::new(&upstream_config.name);
-                        for instance_config in &upstream_config.instances {
-                            let instance = octopus_core::UpstreamInstance::new(
-                                &instance_config.id,
-                                &instance_config.host,
-                                instance_config.port,
+                        Err(e) => {
+                            tracing::error!(
+                                error = %e,
+                                "Reloaded configuration rejected, keeping previous config"
                             );
-                            cluster.add_instance(instance);
+                            self.events.emit(GatewayEvent::ConfigReloadFailed {
+                                error: e.to_string(),
+                            });
                         }
-                        self.router.register_upstream(cluster);
                     }
-
-                    tracing::info!(
-                        routes = new_config.routes.len(),
-                        upstreams = new_config.upstreams.len(),
-                        "Configuration reloaded successfully"
-                    );
                 }
 
                 // Handle shutdown signal — begin draining but KEEP accepting for
@@ -726,47 +1390,73 @@ impl Server {
             }
         }
 
-        // Readiness is NotReady, state is ShuttingDown, and we have stopped
-        // accepting (the accept loop exited after the pre-stop drain window).
-        // Now wait for in-flight requests to drain.
+        // Readiness is NotReady and state is ShuttingDown. Tear the
+        // subsystems down in order, each step bounded by its own timeout.
         tracing::info!("Server shutting down gracefully");
-
-        let shutdown_timeout = self.config.gateway.shutdown_timeout;
         let start = std::time::Instant::now();
 
-        tracing::info!(
-            timeout_secs = shutdown_timeout.as_secs(),
-            "Waiting for in-flight requests to complete"
-        );
-
-        // Poll active connections count until zero or timeout
-        loop {
-            let active = self
-                .request_count
-                .load(std::sync::atomic::Ordering::Relaxed);
-
-            if active == 0 {
-                tracing::info!("All requests completed, shutting down cleanly");
-                break;
-            }
-
-            if start.elapsed() >= shutdown_timeout {
-                tracing::warn!(
-                    active_requests = active,
-                    "Shutdown timeout reached, forcing shutdown"
-                );
-                break;
-            }
-
-            tracing::debug!(
-                active_requests = active,
-                elapsed_ms = start.elapsed().as_millis(),
-                "Waiting for active requests to complete"
-            );
-
-            // Sleep briefly before checking again
-            tokio::time::sleep(Duration::from_millis(100)).await;
-        }
+        let shutdown_timeout = self.config.gateway.shutdown_timeout;
+        let request_count = Arc::clone(&self.request_count);
+        let plugin_handles = self.plugin_handles.clone();
+        let plugin_stop_timeout = self.plugin_stop_timeout;
+        // Every plugin may use its full stop timeout, plus the dynamic ones.
+        let plugins_step_timeout = plugin_stop_timeout * (plugin_handles.len() as u32 + 1);
+        let plugin_manager = self.plugin_manager.clone();
+        let discovery_tasks = self.tasks.clone();
+        let tasks = self.tasks.clone();
+        let events = self.events.clone();
+        let metrics = Arc::clone(&self.metrics);
+        ShutdownSequence::new()
+            .step(
+                "stop accepting",
+                SHUTDOWN_STEP_TIMEOUT,
+                move || async move {
+                    stop_accepting.cancel();
+                },
+            )
+            .step("drain requests", shutdown_timeout, move || {
+                drain_requests(request_count, shutdown_timeout)
+            })
+            .step("stop plugins", plugins_step_timeout, move || async move {
+                crate::plugins::stop_plugins(&plugin_handles, plugin_stop_timeout).await;
+                if let Some(manager) = plugin_manager {
+                    match tokio::time::timeout(plugin_stop_timeout, manager.stop_all()).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => tracing::warn!(error = %e, "Failed to stop plugins"),
+                        Err(_) => tracing::warn!("Dynamic plugins did not stop in time"),
+                    }
+                }
+            })
+            .step(
+                "cancel discovery",
+                SHUTDOWN_STEP_TIMEOUT,
+                move || async move {
+                    let cancelled = discovery_tasks.cancel("discovery").await;
+                    tracing::debug!(cancelled, "Discovery watchers cancelled");
+                },
+            )
+            .step(
+                "cancel background tasks",
+                SHUTDOWN_STEP_TIMEOUT,
+                move || async move {
+                    let cancelled = tasks.cancel_all().await;
+                    tracing::debug!(cancelled, "Background tasks cancelled");
+                },
+            )
+            .step(
+                "flush events and metrics",
+                SHUTDOWN_STEP_TIMEOUT,
+                move || async move {
+                    events.flush().await;
+                    tracing::info!(
+                        total_requests = metrics.total_requests(),
+                        total_errors = metrics.total_errors(),
+                        "Final request totals"
+                    );
+                },
+            )
+            .run()
+            .await;
 
         // Set state to stopped
         {
@@ -784,15 +1474,46 @@ impl Server {
     }
 }
 
+/// Timeout for each shutdown step other than draining requests, which is
+/// bounded by `gateway.shutdown_timeout`.
+const SHUTDOWN_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait for in-flight requests to finish. Never returns while requests are
+/// still active; the caller bounds the wait.
+async fn drain_requests(request_count: Arc<AtomicUsize>, timeout: Duration) {
+    tracing::info!(
+        timeout_secs = timeout.as_secs(),
+        "Waiting for in-flight requests to complete"
+    );
+    let start = std::time::Instant::now();
+    loop {
+        let active = request_count.load(Ordering::Relaxed);
+        if active == 0 {
+            tracing::info!("All requests completed");
+            return;
+        }
+        tracing::debug!(
+            active_requests = active,
+            elapsed_ms = start.elapsed().as_millis(),
+            "Waiting for active requests to complete"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
 /// Server builder
 #[derive(Debug)]
 pub struct ServerBuilder {
     config: Option<Config>,
-    worker_config: WorkerConfig,
+    worker_config: Option<WorkerConfig>,
+    worker_pool: Option<Arc<WorkerPool>>,
     enable_farp: bool,
     enable_plugins: bool,
     enable_protocols: bool,
+    protocol_handlers: Vec<Arc<dyn ProtocolHandler>>,
+    event_sinks: Vec<Arc<dyn EventSink>>,
     config_paths: Option<Vec<std::path::PathBuf>>,
+    plugin_factories: HashMap<String, PluginFactory>,
 }
 
 impl ServerBuilder {
@@ -800,11 +1521,15 @@ impl ServerBuilder {
     pub fn new() -> Self {
         Self {
             config: None,
-            worker_config: WorkerConfig::default(),
+            worker_config: None,
+            worker_pool: None,
             enable_farp: true,
             enable_plugins: true,
             enable_protocols: true,
+            protocol_handlers: Vec::new(),
+            event_sinks: Vec::new(),
             config_paths: None,
+            plugin_factories: HashMap::new(),
         }
     }
 
@@ -814,9 +1539,18 @@ impl ServerBuilder {
         self
     }
 
-    /// Set worker configuration
+    /// Set worker configuration (default: derived from `gateway.workers`,
+    /// `gateway.worker_stack_size` and `gateway.worker_cpus`)
     pub fn worker_config(mut self, config: WorkerConfig) -> Self {
-        self.worker_config = config;
+        self.worker_config = Some(config);
+        self
+    }
+
+    /// Use the worker pool whose runtime the server will run on, typically
+    /// the one the entry point built it from. Takes precedence over
+    /// [`worker_config`](Self::worker_config).
+    pub fn worker_pool(mut self, pool: Arc<WorkerPool>) -> Self {
+        self.worker_pool = Some(pool);
         self
     }
 
@@ -838,6 +1572,29 @@ impl ServerBuilder {
         self
     }
 
+    /// Register an additional protocol handler, dispatched after the
+    /// built-in ones (first match wins)
+    pub fn protocol_handler(mut self, handler: Arc<dyn ProtocolHandler>) -> Self {
+        self.protocol_handlers.push(handler);
+        self
+    }
+
+    /// Register an additional gateway event sink, alongside the webhooks
+    /// from `events.webhooks`
+    pub fn event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.event_sinks.push(sink);
+        self
+    }
+
+    /// Register a static (compiled-in) plugin under `name`. An enabled
+    /// `plugins` entry with `plugin_type: static` and this name is built by
+    /// `factory`, initialized with its `config` and run in the middleware
+    /// chain.
+    pub fn with_plugin_factory(mut self, name: impl Into<String>, factory: PluginFactory) -> Self {
+        self.plugin_factories.insert(name.into(), factory);
+        self
+    }
+
     /// Set config file paths to enable hot-reload support.
     ///
     /// When set, the server will poll these files for changes and
@@ -854,8 +1611,17 @@ impl ServerBuilder {
             .config
             .ok_or_else(|| Error::Config("config is required".to_string()))?;
 
-        // Create worker pool (only if not in test mode)
-        let worker_pool = Arc::new(WorkerPool::new(self.worker_config)?);
+        // The pool describes the runtime this server is expected to run on;
+        // the entry point builds that runtime via `WorkerPool::build_runtime`.
+        let worker_pool = match self.worker_pool {
+            Some(pool) => pool,
+            None => {
+                let worker_config = self
+                    .worker_config
+                    .unwrap_or_else(|| WorkerConfig::from_gateway(&config.gateway));
+                Arc::new(WorkerPool::new(worker_config)?)
+            }
+        };
 
         // Lifecycle state backing the health probes. Readiness waits for the
         // first discovery sync only when discovery is configured and the
@@ -866,77 +1632,42 @@ impl ServerBuilder {
             && config.gateway.probes.require_discovery_sync;
         let lifecycle = LifecycleState::new(discovery_required);
 
-        // Create router
-        let router = Arc::new(Router::new());
-
-        // Register upstreams
-        for upstream_config in &config.upstreams {
-            let mut cluster = octopus_core::UpstreamCluster::new(&upstream_config.name);
-
-            for instance_config in &upstream_config.instances {
-                let instance = octopus_core::UpstreamInstance::new(
-                    &instance_config.id,
-                    &instance_config.host,
-                    instance_config.port,
-                );
-                cluster.add_instance(instance);
-            }
-
-            router.register_upstream(cluster);
-        }
-
-        // Register routes (with auth config)
-        for route_config in &config.routes {
-            for method_str in &route_config.methods {
-                let method = method_str
-                    .parse()
-                    .map_err(|_| Error::Config(format!("Invalid HTTP method: {method_str}")))?;
-
-                let mut builder = octopus_router::RouteBuilder::new()
-                    .path(&route_config.path)
-                    .method(method)
-                    .upstream_name(&route_config.upstream)
-                    .priority(route_config.priority)
-                    .auth_provider(route_config.auth_provider.as_deref())
-                    .skip_auth(route_config.skip_auth)
-                    .require_roles(&route_config.require_roles)
-                    .require_scopes(&route_config.require_scopes)
-                    .authz_rule(route_config.authz_rule.as_deref());
-
-                if let Some(ref pfx) = route_config.strip_prefix {
-                    builder = builder.strip_prefix(pfx);
-                }
-                if let Some(ref pfx) = route_config.add_prefix {
-                    builder = builder.add_prefix(pfx);
-                }
-                if let Some(ref rl) = route_config.rate_limit {
-                    builder = builder.rate_limit(rl.requests_per_window, rl.window_size);
-                }
-                if let Some(timeout) = route_config.timeout {
-                    builder = builder.timeout(Some(timeout));
-                }
-                if let Some(ref cors_cfg) = route_config.cors {
-                    builder = builder.cors(Some(octopus_router::RouteCorsOverride {
-                        allowed_origins: cors_cfg.allowed_origins.clone(),
-                        allowed_methods: cors_cfg.allowed_methods.clone(),
-                        allowed_headers: cors_cfg.allowed_headers.clone(),
-                        allow_credentials: cors_cfg.allow_credentials,
-                        max_age: cors_cfg.max_age,
-                    }));
-                }
-                if let Some(spec) = route_config.proxy_spec() {
-                    builder = builder.proxy(Some(spec));
-                }
-
-                router.add_route(builder.build()?)?;
-            }
-        }
+        // Create router with the configured upstreams and routes
+        let router = Arc::new(crate::reload::build_router(&config)?);
 
         // Create HTTP client (connection pool is managed internally)
         let client = HttpClient::with_timeout(config.gateway.request_timeout);
 
         // Create proxy
-        let proxy = Arc::new(HttpProxy::new(client, ProxyConfig::default()));
+        let policy = &config.gateway.header_policy;
+        let retry_after = &config.gateway.upstream_retry_after;
+        let mut limits = ProxyLimits::default();
+        if let Some(body_limit) = &config.gateway.response_body_limit {
+            limits = limits.with_max_response_body_size(body_limit.max_bytes);
+        }
+        let proxy_config = ProxyConfig {
+            header_policy: HeaderStripPolicy {
+                request: HeaderFilter::new(&policy.request.deny, &policy.request.allow),
+                response: HeaderFilter::new(&policy.response.deny, &policy.response.allow),
+            },
+            propagate_retry_after: retry_after.propagate,
+            limits,
+            truncate_large_responses: config
+                .gateway
+                .response_body_limit
+                .is_some_and(|limit| limit.limit().truncate),
+            ..Default::default()
+        };
+        let retry_policy = RetryPolicy::default().with_max_retry_after(retry_after.max_wait);
+        let proxy = Arc::new(
+            HttpProxy::new(client, proxy_config).with_retry_policy(Arc::new(retry_policy)),
+        );
+        proxy
+            .circuit_breaker()
+            .set_instance_configs(crate::reload::circuit_breaker_configs(&config));
+
+        // Spawned through this so shutdown can cancel them.
+        let tasks = BackgroundTasks::new();
 
         // Initialize FARP (if enabled in config AND builder)
         let farp_enabled = config.farp.enabled && self.enable_farp;
@@ -981,6 +1712,7 @@ impl ServerBuilder {
                     config.farp.watch_interval,
                     lifecycle.discovery_synced_flag(),
                     binding_cell,
+                    &tasks,
                 )
                 .await;
             }
@@ -1002,15 +1734,46 @@ impl ServerBuilder {
             None
         };
 
+        // Created here so the static plugins can record their execution time.
+        let mut metrics = octopus_metrics::MetricsCollector::new().with_circuit_breaker(
+            Arc::clone(proxy.circuit_breaker()),
+            Arc::new({
+                let router = Arc::clone(&router);
+                move |instance: &str| router.upstream_of(instance)
+            }),
+        );
+        if let Some(slo) = &config.observability.metrics.slo {
+            metrics = metrics.with_slo(Arc::new(crate::reload::slo_tracker(slo)));
+        }
+        let metrics = Arc::new(metrics);
+
+        // Instantiate the static plugins enabled in config; one that fails to
+        // initialize fails startup.
+        let static_plugins = if self.enable_plugins {
+            crate::plugins::load_static_plugins(
+                &self.plugin_factories,
+                &config.plugins,
+                Arc::clone(&metrics),
+            )
+            .await?
+        } else {
+            LoadedPlugins::default()
+        };
+
         // Initialize Protocol Handlers (if enabled)
-        let protocol_handlers: Vec<Arc<dyn ProtocolHandler>> = if self.enable_protocols {
+        // Dispatch order: first handler whose matchers accept a request wins;
+        // anything unmatched is proxied as plain HTTP.
+        let mut protocol_handlers: Vec<Arc<dyn ProtocolHandler>> = if self.enable_protocols {
             tracing::info!("Initializing protocol handlers");
-            // Note: WebSocket is handled via HTTP upgrade in handler.rs,
-            // not through the ProtocolHandler trait (which requires Full<Bytes>)
-            vec![Arc::new(GrpcHandler::new())]
+            vec![
+                Arc::new(WebSocketHandler),
+                Arc::new(SseHandler),
+                Arc::new(GrpcHandler::from_config(&config.grpc)),
+            ]
         } else {
             Vec::new()
         };
+        protocol_handlers.extend(self.protocol_handlers);
 
         tracing::info!(
             upstreams = config.upstreams.len(),
@@ -1034,6 +1797,12 @@ impl ServerBuilder {
             Option<GatewayIndexHandle>,
         ) = (None, None);
 
+        let events = EventBus::from_config(&config.events);
+        for sink in self.event_sinks {
+            events.add_sink(sink);
+        }
+        crate::events::watch_circuits(&events, &router, proxy.circuit_breaker());
+
         // Configuration is fully loaded and applied.
         lifecycle.mark_config_loaded();
 
@@ -1052,37 +1821,23 @@ impl ServerBuilder {
             lifecycle,
             operator_tls,
             gateway_index,
+            events,
+            static_plugins: static_plugins.middleware,
+            metrics,
+            plugin_handles: static_plugins.handles,
+            plugin_stop_timeout: crate::plugins::PLUGIN_STOP_TIMEOUT,
+            tasks,
         })
     }
 
-    /// Initialize FARP discovery providers
-    async fn initialize_farp_discovery(
-        registry: Arc<octopus_farp::SchemaRegistry>,
-        federation: Arc<octopus_farp::SchemaFederation>,
-        router: Arc<octopus_router::Router>,
+    /// Providers for the enabled discovery backends, used by the FARP
+    /// discovery watcher and to resolve route `service`s
+    async fn discovery_providers(
         discovery_config: &octopus_config::types::FarpDiscoveryConfig,
-        watch_interval: std::time::Duration,
-        discovery_synced: Arc<AtomicBool>,
-        binding_cell: octopus_farp::BindingCell,
-    ) {
+    ) -> Vec<Arc<dyn octopus_discovery::DiscoveryProvider>> {
         use octopus_config::types::DiscoveryBackendConfig;
 
-        tracing::info!(
-            backends = discovery_config.backends.len(),
-            "Initializing FARP discovery watcher"
-        );
-
-        let mut watcher = octopus_farp::DiscoveryWatcher::with_federation(
-            registry,
-            watch_interval,
-            3, // max_missed_discoveries
-            federation,
-        )
-        .with_router(router)
-        .with_readiness_flag(Arc::clone(&discovery_synced))
-        .with_binding_cell(binding_cell);
-
-        let mut enabled_backends = 0;
+        let mut providers: Vec<Arc<dyn octopus_discovery::DiscoveryProvider>> = Vec::new();
 
         for backend in &discovery_config.backends {
             match backend {
@@ -1106,8 +1861,7 @@ impl ServerBuilder {
                         };
 
                         let discovery = MdnsDiscovery::new(mdns_config);
-                        watcher.add_provider(Arc::new(discovery));
-                        enabled_backends += 1;
+                        providers.push(Arc::new(discovery));
                     }
 
                     #[cfg(not(feature = "mdns"))]
@@ -1136,8 +1890,7 @@ impl ServerBuilder {
 
                         match DnsDiscovery::new(dns_config).await {
                             Ok(discovery) => {
-                                watcher.add_provider(Arc::new(discovery));
-                                enabled_backends += 1;
+                                providers.push(Arc::new(discovery));
                             }
                             Err(e) => {
                                 tracing::error!(error = %e, "Failed to initialize DNS discovery")
@@ -1174,8 +1927,7 @@ impl ServerBuilder {
                         };
 
                         let discovery = ConsulDiscovery::new(consul_config);
-                        watcher.add_provider(Arc::new(discovery));
-                        enabled_backends += 1;
+                        providers.push(Arc::new(discovery));
                     }
 
                     #[cfg(not(feature = "consul"))]
@@ -1209,8 +1961,7 @@ impl ServerBuilder {
 
                         match K8sDiscovery::new(k8s_config).await {
                             Ok(discovery) => {
-                                watcher.add_provider(Arc::new(discovery));
-                                enabled_backends += 1;
+                                providers.push(Arc::new(discovery));
                             }
                             Err(e) => {
                                 tracing::error!(error = %e, "Failed to initialize Kubernetes discovery")
@@ -1232,6 +1983,41 @@ impl ServerBuilder {
             }
         }
 
+        providers
+    }
+
+    /// Initialize FARP discovery providers
+    async fn initialize_farp_discovery(
+        registry: Arc<octopus_farp::SchemaRegistry>,
+        federation: Arc<octopus_farp::SchemaFederation>,
+        router: Arc<octopus_router::Router>,
+        discovery_config: &octopus_config::types::FarpDiscoveryConfig,
+        watch_interval: std::time::Duration,
+        discovery_synced: Arc<AtomicBool>,
+        binding_cell: octopus_farp::BindingCell,
+        tasks: &BackgroundTasks,
+    ) {
+        tracing::info!(
+            backends = discovery_config.backends.len(),
+            "Initializing FARP discovery watcher"
+        );
+
+        let mut watcher = octopus_farp::DiscoveryWatcher::with_federation(
+            registry,
+            watch_interval,
+            3, // max_missed_discoveries
+            federation,
+        )
+        .with_router(router)
+        .with_readiness_flag(Arc::clone(&discovery_synced))
+        .with_binding_cell(binding_cell);
+
+        let providers = Self::discovery_providers(discovery_config).await;
+        let enabled_backends = providers.len();
+        for provider in providers {
+            watcher.add_provider(provider);
+        }
+
         if enabled_backends > 0 {
             tracing::info!(
                 enabled_backends,
@@ -1241,7 +2027,7 @@ impl ServerBuilder {
 
             // Spawn the watcher as a background task
             let watcher = Arc::new(watcher);
-            tokio::spawn(async move {
+            tasks.spawn("discovery", async move {
                 if let Err(e) = watcher.watch().await {
                     tracing::error!(error = %e, "FARP discovery watcher terminated with error");
                 }
@@ -1390,25 +2176,15 @@ mod tests {
     use octopus_config::{ConfigBuilder, GatewayConfig};
     use std::time::Duration;
 
+    /// A gateway on 127.0.0.1:8080 with 4 workers; every other setting is
+    /// its serde default, so new gateway fields need no change here.
     fn test_config() -> Config {
-        use octopus_config::types::{CompressionConfig, ProbeConfig};
-        ConfigBuilder::new()
-            .gateway(GatewayConfig {
-                listen: "127.0.0.1:8080".parse().unwrap(),
-                workers: 4,
-                request_timeout: Duration::from_secs(30),
-                shutdown_timeout: Duration::from_secs(30),
-                pre_stop_delay: Duration::from_secs(5),
-                max_body_size: 10 * 1024 * 1024,
-                tls: None,
-                compression: CompressionConfig::default(),
-                internal_route_prefix: Some("__".to_string()),
-                probes: ProbeConfig::default(),
-                enforce_sni_check: true,
-                security_headers: Default::default(),
-            })
-            .build()
-            .unwrap()
+        let gateway: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "listen": "127.0.0.1:8080",
+            "workers": 4,
+        }))
+        .unwrap();
+        ConfigBuilder::new().gateway(gateway).build().unwrap()
     }
 
     #[tokio::test]
@@ -1420,6 +2196,421 @@ mod tests {
         assert_eq!(server.request_count(), 0);
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_serves_http_over_unix_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path = std::env::temp_dir().join(format!("octopus-server-{}.sock", std::process::id()));
+        let mut config = test_config();
+        config.gateway.unix_socket = Some(octopus_config::types::UnixSocketConfig {
+            path: path.clone(),
+            mode: None,
+            uid: None,
+            gid: None,
+        });
+        config.gateway.pre_stop_delay = Duration::ZERO;
+        let server = ServerBuilder::new()
+            .config(config)
+            .enable_farp(false)
+            .build()
+            .await
+            .unwrap();
+
+        let client = async {
+            let mut stream = tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    match tokio::net::UnixStream::connect(&path).await {
+                        Ok(stream) => break stream,
+                        Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                    }
+                }
+            })
+            .await
+            .expect("socket never accepted connections");
+            stream
+                .write_all(b"GET /livez HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            server.shutdown_signal().trigger();
+            response
+        };
+        let (result, response) = tokio::join!(server.run(), client);
+
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(!path.exists(), "socket file left behind");
+    }
+
+    /// A config with one upstream cluster that nothing listens for and a
+    /// startup check under `policy`.
+    async fn unreachable_upstream_config(
+        policy: octopus_config::types::StartupCheckPolicy,
+    ) -> Config {
+        use octopus_config::types::{InstanceConfig, StartupCheckConfig};
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = test_config();
+        config.upstreams.push(octopus_config::UpstreamConfig {
+            name: "orders".to_string(),
+            instances: vec![InstanceConfig {
+                id: "orders-1".to_string(),
+                host: "127.0.0.1".to_string(),
+                port,
+                weight: 1,
+                metadata: HashMap::new(),
+            }],
+            lb_policy: "round_robin".to_string(),
+            health_check: None,
+            circuit_breaker: None,
+            openapi: None,
+            slow_start: None,
+        });
+        config.gateway.startup_check = Some(StartupCheckConfig {
+            policy,
+            timeout: Duration::from_millis(500),
+        });
+        config.gateway.pre_stop_delay = Duration::ZERO;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_strict_startup_check_blocks_readiness() {
+        use octopus_config::types::StartupCheckPolicy;
+
+        let config = unreachable_upstream_config(StartupCheckPolicy::Strict).await;
+        let server = ServerBuilder::new()
+            .config(config)
+            .enable_farp(false)
+            .build()
+            .await
+            .unwrap();
+
+        let err = server.run().await.unwrap_err().to_string();
+        assert!(err.contains("orders"), "{err}");
+        assert!(!server.lifecycle().is_ready());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_warn_startup_check_lets_the_server_become_ready() {
+        use octopus_config::types::StartupCheckPolicy;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path =
+            std::env::temp_dir().join(format!("octopus-startup-check-{}.sock", std::process::id()));
+        let mut config = unreachable_upstream_config(StartupCheckPolicy::Warn).await;
+        config.gateway.unix_socket = Some(octopus_config::types::UnixSocketConfig {
+            path: path.clone(),
+            mode: None,
+            uid: None,
+            gid: None,
+        });
+        let server = ServerBuilder::new()
+            .config(config)
+            .enable_farp(false)
+            .build()
+            .await
+            .unwrap();
+
+        let client = async {
+            let mut stream = tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    match tokio::net::UnixStream::connect(&path).await {
+                        Ok(stream) => break stream,
+                        Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                    }
+                }
+            })
+            .await
+            .expect("socket never accepted connections");
+            stream
+                .write_all(b"GET /readyz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            server.shutdown_signal().trigger();
+            response
+        };
+        let (result, response) = tokio::join!(server.run(), client);
+
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    }
+
+    /// A local port nothing is bound to
+    async fn free_port() -> u16 {
+        tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Send `GET path` to `port` and return the status line, retrying the
+    /// connection until the server is listening
+    async fn get_status(port: u16, path: &str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let mut stream = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                    Ok(stream) => break stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .expect("listener never accepted connections");
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_admin_listener_splits_admin_from_data_traffic() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // An upstream answering every request with 200.
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = upstream.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                        .await;
+                });
+            }
+        });
+
+        let (data, admin) = (free_port().await, free_port().await);
+        let yaml = format!(
+            "gateway:\n  listen: \"127.0.0.1:{data}\"\n  acceptors: 1\n  pre_stop_delay: 0s\n\
+             admin:\n  listen: \"127.0.0.1:{admin}\"\n\
+             upstreams:\n  - name: orders\n    instances:\n      - id: orders-1\n        \
+             host: 127.0.0.1\n        port: {upstream_port}\n\
+             routes:\n  - path: /orders\n    methods: [GET]\n    upstream: orders\n"
+        );
+        let config =
+            octopus_config::load_from_str(&yaml, octopus_config::ConfigFormat::Yaml).unwrap();
+        let server = ServerBuilder::new()
+            .config(config)
+            .enable_farp(false)
+            .build()
+            .await
+            .unwrap();
+
+        let client = async {
+            let statuses = [
+                get_status(data, "/orders").await,
+                get_status(data, "/metrics").await,
+                get_status(data, "/admin/api/upstreams").await,
+                get_status(admin, "/metrics").await,
+                get_status(admin, "/admin/api/upstreams").await,
+                get_status(admin, "/orders").await,
+                // Probes answer on both.
+                get_status(data, "/livez").await,
+                get_status(admin, "/livez").await,
+            ];
+            server.shutdown_signal().trigger();
+            statuses
+        };
+        let (result, statuses) = tokio::join!(server.run(), client);
+        result.unwrap();
+
+        let codes: Vec<&str> = statuses
+            .iter()
+            .map(|line| line.split(' ').nth(1).unwrap_or_default())
+            .collect();
+        assert_eq!(
+            codes,
+            ["200", "404", "404", "200", "200", "404", "200", "200"],
+            "{statuses:?}"
+        );
+    }
+
+    /// Answers every request with the client address the plugin API sees.
+    #[derive(Debug)]
+    struct EchoClientAddr;
+
+    #[async_trait::async_trait]
+    impl octopus_core::middleware::Middleware for EchoClientAddr {
+        async fn call(
+            &self,
+            req: http::Request<octopus_core::middleware::Body>,
+            _next: octopus_core::middleware::Next,
+        ) -> Result<http::Response<octopus_core::middleware::Body>> {
+            let ctx = crate::plugins::request_context(&req);
+            Ok(http::Response::new(http_body_util::Full::new(
+                bytes::Bytes::from(ctx.remote_addr.to_string()),
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol_client_reaches_request_context() {
+        use octopus_core::middleware::Middleware;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = crate::RequestHandler::with_middleware(
+            Arc::new(Router::new()),
+            Arc::new(HttpProxy::new(HttpClient::new(), ProxyConfig::default())),
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new([Arc::new(EchoClientAddr) as Arc<dyn Middleware>]),
+        );
+        let proxy_protocol =
+            ProxyProtocol::from_config(&octopus_config::types::ProxyProtocolConfig {
+                enabled: true,
+                trusted_sources: vec!["127.0.0.1".to_string()],
+                ..Default::default()
+            })
+            .map(Arc::new);
+        let stop = tokio_util::sync::CancellationToken::new();
+        tokio::spawn(accept_loop(
+            listener,
+            handler,
+            TlsMode::Plain,
+            false,
+            proxy_protocol,
+            ConnectionLimits::default(),
+            stop.clone(),
+        ));
+
+        let mut v2 = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c".to_vec();
+        v2.extend_from_slice(&[198, 51, 100, 20, 127, 0, 0, 1]);
+        v2.extend_from_slice(&40000u16.to_be_bytes());
+        v2.extend_from_slice(&8080u16.to_be_bytes());
+        let v1 = b"PROXY TCP4 203.0.113.7 127.0.0.1 56324 8080\r\n".to_vec();
+
+        for (header, client) in [(v1, "203.0.113.7:56324"), (v2, "198.51.100.20:40000")] {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&header).await.unwrap();
+            stream
+                .write_all(b"GET /whoami HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+            assert!(response.ends_with(client), "{response}");
+        }
+        stop.cancel();
+    }
+
+    /// A static plugin that records its stop, or never finishes stopping
+    #[derive(Debug)]
+    struct Stopper {
+        stopped: Arc<AtomicBool>,
+        hang: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl octopus_plugin_runtime::Plugin for Stopper {
+        fn name(&self) -> &str {
+            "stopper"
+        }
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+        async fn init(
+            &mut self,
+            _config: serde_json::Value,
+        ) -> std::result::Result<(), octopus_plugin_runtime::PluginError> {
+            Ok(())
+        }
+        async fn start(&mut self) -> std::result::Result<(), octopus_plugin_runtime::PluginError> {
+            Ok(())
+        }
+        async fn stop(&mut self) -> std::result::Result<(), octopus_plugin_runtime::PluginError> {
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            self.stopped.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl octopus_plugin_runtime::interceptor::RequestInterceptor for Stopper {
+        async fn intercept_request(
+            &self,
+            _req: &mut http::Request<octopus_core::middleware::Body>,
+            _ctx: &octopus_plugin_runtime::context::RequestContext,
+        ) -> std::result::Result<
+            octopus_plugin_runtime::interceptor::InterceptorAction,
+            octopus_plugin_runtime::PluginError,
+        > {
+            Ok(octopus_plugin_runtime::interceptor::InterceptorAction::Continue)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_skips_stuck_plugins_and_cancels_background_tasks() {
+        let port = free_port().await;
+        let yaml = format!(
+            "gateway:\n  listen: \"127.0.0.1:{port}\"\n  acceptors: 1\n  pre_stop_delay: 0s\n\
+             plugins:\n  - name: stuck\n  - name: polite\n"
+        );
+        let config =
+            octopus_config::load_from_str(&yaml, octopus_config::ConfigFormat::Yaml).unwrap();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stopped);
+        let mut server = ServerBuilder::new()
+            .config(config)
+            .enable_farp(false)
+            .with_plugin_factory(
+                "stuck",
+                PluginFactory::request(|| Stopper {
+                    stopped: Arc::new(AtomicBool::new(false)),
+                    hang: true,
+                }),
+            )
+            .with_plugin_factory(
+                "polite",
+                PluginFactory::request(move || Stopper {
+                    stopped: Arc::clone(&flag),
+                    hang: false,
+                }),
+            )
+            .build()
+            .await
+            .unwrap();
+        server.plugin_stop_timeout = Duration::from_millis(100);
+
+        let client = async {
+            assert!(get_status(port, "/livez").await.contains("200"));
+            assert_eq!(server.tasks.running("health"), 1);
+            server.shutdown_signal().trigger();
+        };
+        let (result, ()) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(server.run(), client)
+        })
+        .await
+        .expect("shutdown waited on the stuck plugin");
+
+        result.unwrap();
+        assert!(
+            stopped.load(Ordering::SeqCst),
+            "polite plugin was not stopped"
+        );
+        assert_eq!(server.tasks.running("health"), 0);
+    }
+
     // Note: test_server_state removed due to runtime-in-runtime complications
     // The server state is tested via integration tests
 
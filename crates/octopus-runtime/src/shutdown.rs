@@ -1,8 +1,21 @@
 //! Graceful shutdown with signal handling
+//!
+//! Once the server stops accepting and in-flight requests drain, subsystems
+//! are torn down by a [`ShutdownSequence`]: each step runs to completion or
+//! its timeout before the next one starts, so plugins stop before the tasks
+//! they may depend on are cancelled, and events are flushed last. Long-running
+//! tasks are spawned through [`BackgroundTasks`] so the sequence can cancel
+//! them by subsystem.
 
+use parking_lot::Mutex;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::signal;
 use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 
 /// Shutdown signal broadcaster
 #[derive(Debug, Clone)]
@@ -42,6 +55,145 @@ impl Default for ShutdownSignal {
     }
 }
 
+/// A background task and the subsystem it belongs to
+type Task = (&'static str, JoinHandle<()>);
+
+/// Long-running tasks owned by the server, tagged with their subsystem
+/// (e.g. `discovery`, `health`) so shutdown can cancel them in order.
+///
+/// Clones share the same tasks.
+#[derive(Debug, Clone, Default)]
+pub struct BackgroundTasks {
+    tasks: Arc<Mutex<Vec<Task>>>,
+}
+
+impl BackgroundTasks {
+    /// Create an empty task set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn `task` on the current runtime as part of `subsystem`
+    pub fn spawn<F>(&self, subsystem: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task);
+        let mut tasks = self.tasks.lock();
+        tasks.retain(|(_, handle)| !handle.is_finished());
+        tasks.push((subsystem, handle));
+    }
+
+    /// Tasks of `subsystem` that are still running
+    pub fn running(&self, subsystem: &str) -> usize {
+        self.tasks
+            .lock()
+            .iter()
+            .filter(|(s, handle)| *s == subsystem && !handle.is_finished())
+            .count()
+    }
+
+    /// Abort every task of `subsystem` and wait for them to stop. Returns
+    /// how many were still running.
+    pub async fn cancel(&self, subsystem: &str) -> usize {
+        let cancelled: Vec<_> = {
+            let mut tasks = self.tasks.lock();
+            let (cancelled, kept) = std::mem::take(&mut *tasks)
+                .into_iter()
+                .partition(|(s, _)| *s == subsystem);
+            *tasks = kept;
+            cancelled
+        };
+        Self::abort(cancelled).await
+    }
+
+    /// Abort every remaining task and wait for them to stop. Returns how many
+    /// were still running.
+    pub async fn cancel_all(&self) -> usize {
+        let cancelled = std::mem::take(&mut *self.tasks.lock());
+        Self::abort(cancelled).await
+    }
+
+    async fn abort(tasks: Vec<Task>) -> usize {
+        let mut running = 0;
+        for (_, handle) in &tasks {
+            if !handle.is_finished() {
+                running += 1;
+                handle.abort();
+            }
+        }
+        for (_, handle) in tasks {
+            let _ = handle.await;
+        }
+        running
+    }
+}
+
+type Step = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// Ordered teardown steps, each bounded by its own timeout.
+///
+/// A step that overruns its timeout is abandoned (logged as timed out) and
+/// the sequence moves on, so one stuck subsystem cannot hold up the rest.
+#[derive(Default)]
+pub struct ShutdownSequence {
+    steps: Vec<(String, Duration, Step)>,
+}
+
+impl ShutdownSequence {
+    /// Create an empty sequence
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a step, run after every step added before it
+    pub fn step<F, Fut>(mut self, name: impl Into<String>, timeout: Duration, step: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.steps
+            .push((name.into(), timeout, Box::new(move || Box::pin(step()))));
+        self
+    }
+
+    /// Run the steps in order. Returns the names of the steps that timed out.
+    pub async fn run(self) -> Vec<String> {
+        let mut timed_out = Vec::new();
+        for (name, timeout, step) in self.steps {
+            tracing::info!(step = %name, timeout_ms = timeout.as_millis(), "Shutdown step started");
+            let started = Instant::now();
+            match tokio::time::timeout(timeout, step()).await {
+                Ok(()) => tracing::info!(
+                    step = %name,
+                    elapsed_ms = started.elapsed().as_millis(),
+                    "Shutdown step completed"
+                ),
+                Err(_) => {
+                    tracing::warn!(
+                        step = %name,
+                        timeout_ms = timeout.as_millis(),
+                        "Shutdown step timed out, continuing"
+                    );
+                    timed_out.push(name);
+                }
+            }
+        }
+        timed_out
+    }
+}
+
+impl fmt::Debug for ShutdownSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownSequence")
+            .field(
+                "steps",
+                &self.steps.iter().map(|(name, ..)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
 /// Signal handler for OS signals
 #[derive(Debug)]
 pub struct SignalHandler {
@@ -127,4 +279,53 @@ mod tests {
         assert!(rx1.try_recv().is_ok());
         assert!(rx2.try_recv().is_ok());
     }
+
+    /// Sets its flag when dropped, i.e. when the task owning it is cancelled
+    struct DropFlag(Arc<std::sync::atomic::AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_only_the_subsystems_tasks() {
+        let tasks = BackgroundTasks::new();
+        let dropped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = DropFlag(Arc::clone(&dropped));
+        tasks.spawn("discovery", async move {
+            let _flag = flag;
+            std::future::pending::<()>().await;
+        });
+        tasks.spawn("health", std::future::pending());
+        assert_eq!(tasks.running("discovery"), 1);
+
+        assert_eq!(tasks.cancel("discovery").await, 1);
+        assert!(dropped.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(tasks.running("discovery"), 0);
+        assert_eq!(tasks.running("health"), 1);
+
+        assert_eq!(tasks.cancel_all().await, 1);
+        assert_eq!(tasks.running("health"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sequence_runs_steps_in_order_and_skips_past_timeouts() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let record = |name: &'static str| {
+            let order = Arc::clone(&order);
+            move || async move { order.lock().push(name) }
+        };
+
+        let timed_out = ShutdownSequence::new()
+            .step("plugins", Duration::from_secs(1), record("plugins"))
+            .step("stuck", Duration::from_secs(1), std::future::pending)
+            .step("flush", Duration::from_secs(1), record("flush"))
+            .run()
+            .await;
+
+        assert_eq!(timed_out, vec!["stuck".to_string()]);
+        assert_eq!(*order.lock(), vec!["plugins", "flush"]);
+    }
 }
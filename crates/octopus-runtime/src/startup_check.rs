@@ -0,0 +1,133 @@
+//! Startup self-check of upstream reachability
+//!
+//! Before the server reports ready, [`run`] opens one TCP connection per
+//! upstream cluster, trying its instances in turn until one accepts. Under
+//! [`StartupCheckPolicy::Strict`] any unreachable cluster fails startup, so
+//! readiness never flips; under [`StartupCheckPolicy::Warn`] each one is
+//! logged and startup carries on. Clusters without instances are skipped:
+//! discovery may not have populated them yet.
+
+use octopus_config::types::{StartupCheckConfig, StartupCheckPolicy};
+use octopus_core::{Error, Result};
+use octopus_health::{HealthCheck, HealthStatus, TcpHealthCheck};
+use octopus_router::Router;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Probe every cluster of `router` and apply `config.policy` to the result
+pub(crate) async fn run(router: &Router, config: &StartupCheckConfig) -> Result<()> {
+    let unreachable = unreachable_clusters(router, config.timeout).await;
+    if unreachable.is_empty() {
+        tracing::info!("Startup check passed: every upstream cluster is reachable");
+        return Ok(());
+    }
+    match config.policy {
+        StartupCheckPolicy::Strict => Err(Error::Runtime(format!(
+            "Startup check failed: unreachable upstream clusters: {}",
+            unreachable.join(", ")
+        ))),
+        StartupCheckPolicy::Warn => {
+            for cluster in &unreachable {
+                tracing::warn!(
+                    upstream = %cluster,
+                    "STARTUP CHECK: upstream cluster is unreachable; starting anyway"
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Names of the clusters none of whose instances accept a connection within
+/// `timeout`, sorted
+pub(crate) async fn unreachable_clusters(router: &Router, timeout: Duration) -> Vec<String> {
+    let checker = Arc::new(TcpHealthCheck::new(timeout));
+    let mut probes = JoinSet::new();
+    for cluster in router.get_all_upstreams() {
+        if cluster.instances.is_empty() {
+            tracing::debug!(upstream = %cluster.name, "No instances, skipping startup check");
+            continue;
+        }
+        let checker = Arc::clone(&checker);
+        probes.spawn(async move {
+            for instance in &cluster.instances {
+                let result = checker.check(&instance.address, instance.port).await;
+                if result.status == HealthStatus::Healthy {
+                    return None;
+                }
+            }
+            Some(cluster.name)
+        });
+    }
+
+    let mut unreachable = Vec::new();
+    while let Some(probe) = probes.join_next().await {
+        if let Ok(Some(name)) = probe {
+            unreachable.push(name);
+        }
+    }
+    unreachable.sort();
+    unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_core::{UpstreamCluster, UpstreamInstance};
+    use tokio::net::TcpListener;
+
+    /// A local port nothing listens on
+    async fn closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    fn cluster(name: &str, ports: &[u16]) -> UpstreamCluster {
+        let mut cluster = UpstreamCluster::new(name);
+        for (i, port) in ports.iter().enumerate() {
+            cluster.add_instance(UpstreamInstance::new(
+                format!("{name}-{i}"),
+                "127.0.0.1",
+                *port,
+            ));
+        }
+        cluster
+    }
+
+    #[tokio::test]
+    async fn reports_clusters_with_no_reachable_instance() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let closed = closed_port().await;
+
+        let router = Router::new();
+        router.register_upstream(cluster("up", &[open]));
+        // One reachable instance is enough.
+        router.register_upstream(cluster("partial", &[closed, open]));
+        router.register_upstream(cluster("down", &[closed]));
+        router.register_upstream(cluster("empty", &[]));
+
+        let unreachable = unreachable_clusters(&router, Duration::from_secs(1)).await;
+        assert_eq!(unreachable, ["down"]);
+    }
+
+    #[tokio::test]
+    async fn policy_decides_whether_startup_fails() {
+        let router = Router::new();
+        router.register_upstream(cluster("down", &[closed_port().await]));
+
+        let strict = StartupCheckConfig {
+            policy: StartupCheckPolicy::Strict,
+            timeout: Duration::from_secs(1),
+        };
+        let err = run(&router, &strict).await.unwrap_err().to_string();
+        assert!(err.contains("down"), "{err}");
+
+        let warn = StartupCheckConfig {
+            policy: StartupCheckPolicy::Warn,
+            ..strict
+        };
+        assert!(run(&router, &warn).await.is_ok());
+    }
+}
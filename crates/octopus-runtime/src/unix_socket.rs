@@ -0,0 +1,184 @@
+//! Unix domain socket listener.
+//!
+//! With `gateway.unix_socket` the gateway serves HTTP on a socket file instead
+//! of a TCP port, e.g. behind a sidecar proxy on the same host. Connections
+//! are accepted and served exactly like TCP ones. Their peer address is the
+//! unspecified address `0.0.0.0`, so IP rules written for localhost clients
+//! don't match them.
+
+use crate::reuseport::Accept;
+use octopus_config::types::UnixSocketConfig;
+use rustix::fs::Mode;
+use std::fs::Permissions;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::net::{UnixListener, UnixStream};
+
+/// Peer address reported for connections accepted on a Unix socket
+const UNIX_PEER: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+/// Umask the socket file is created under when a mode is configured: owner
+/// only, until its ownership and mode are applied
+const BIND_UMASK: Mode = Mode::XUSR.union(Mode::RWXG).union(Mode::RWXO);
+
+impl Accept for UnixListener {
+    type Stream = UnixStream;
+
+    async fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
+        let (stream, _) = UnixListener::accept(self).await?;
+        Ok((stream, UNIX_PEER))
+    }
+}
+
+/// A bound socket file, removed when dropped.
+#[derive(Debug)]
+pub(crate) struct SocketFile {
+    path: PathBuf,
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => tracing::debug!(path = %self.path.display(), "Removed socket file"),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!(
+                path = %self.path.display(),
+                error = %e,
+                "Failed to remove socket file"
+            ),
+        }
+    }
+}
+
+/// Bind a listener on `config.path` with the configured permissions and
+/// ownership, replacing a socket file left behind by a previous run.
+///
+/// With a mode configured the file is created owner-only, so it never
+/// accepts connections the configured mode would refuse.
+pub(crate) async fn bind(config: &UnixSocketConfig) -> io::Result<(UnixListener, SocketFile)> {
+    remove_stale(&config.path).await?;
+
+    let mode = config.mode_bits();
+    let listener = match mode {
+        Some(_) => bind_with_umask(&config.path, BIND_UMASK)?,
+        None => UnixListener::bind(&config.path)?,
+    };
+    // From here on the file is removed again if anything fails.
+    let file = SocketFile {
+        path: config.path.clone(),
+    };
+    if config.uid.is_some() || config.gid.is_some() {
+        std::os::unix::fs::chown(&config.path, config.uid, config.gid)?;
+    }
+    if let Some(mode) = mode {
+        std::fs::set_permissions(&config.path, Permissions::from_mode(mode))?;
+    }
+    Ok((listener, file))
+}
+
+/// Bind `path` with the process umask set to `umask`, restoring it after.
+///
+/// The umask is process-wide, so files other threads create meanwhile get it
+/// too; it only ever narrows their permissions.
+fn bind_with_umask(path: &Path, umask: Mode) -> io::Result<UnixListener> {
+    let previous = rustix::process::umask(umask);
+    let listener = UnixListener::bind(path);
+    rustix::process::umask(previous);
+    listener
+}
+
+/// Remove the socket file at `path` if nothing accepts connections on it.
+///
+/// Fails when `path` is not a socket, or when another process still serves
+/// on it.
+async fn remove_stale(path: &Path) -> io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+
+    match UnixStream::connect(path).await {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another process", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            tracing::info!(path = %path.display(), "Removing stale socket file");
+            std::fs::remove_file(path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn socket_config(name: &str) -> UnixSocketConfig {
+        UnixSocketConfig {
+            path: std::env::temp_dir().join(format!("octopus-{name}-{}.sock", std::process::id())),
+            mode: Some("0600".to_string()),
+            uid: None,
+            gid: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bind_applies_mode_and_removes_file_on_drop() {
+        let config = socket_config("mode");
+        let (listener, file) = bind(&config).await.unwrap();
+
+        let mode = std::fs::metadata(&config.path)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        drop(listener);
+        drop(file);
+        assert!(!config.path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_accepted_peer_is_not_loopback() {
+        let config = socket_config("peer");
+        let (listener, _file) = bind(&config).await.unwrap();
+
+        let _client = UnixStream::connect(&config.path).await.unwrap();
+        let (_stream, peer) = Accept::accept(&listener).await.unwrap();
+        assert!(peer.ip().is_unspecified());
+    }
+
+    #[tokio::test]
+    async fn test_bind_replaces_stale_socket() {
+        let config = socket_config("stale");
+        // A listener dropped without cleanup leaves its file behind.
+        drop(std::os::unix::net::UnixListener::bind(&config.path).unwrap());
+        assert!(config.path.exists());
+
+        let (_listener, _file) = bind(&config).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bind_refuses_live_socket_and_other_files() {
+        let config = socket_config("live");
+        let (_listener, _file) = bind(&config).await.unwrap();
+        let err = bind(&config).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+        let config = socket_config("regular");
+        std::fs::write(&config.path, b"not a socket").unwrap();
+        let err = bind(&config).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        std::fs::remove_file(&config.path).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! What the gateway does with requests no route matches.
+//!
+//! By default they get the problem-details `404`. An [`UnmatchedPolicy`] can
+//! instead serve a custom body, redirect elsewhere, or forward everything to
+//! a catch-all upstream (e.g. a legacy gateway during a gradual migration).
+
+use bytes::Bytes;
+use http::{header, HeaderValue, Method, Response, StatusCode, Uri};
+use http_body_util::Full;
+use octopus_core::ResponseBuilder;
+use octopus_router::{Route, RouteBuilder};
+
+/// Handling of requests that match no route.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum UnmatchedPolicy {
+    /// The standard `404` problem response
+    #[default]
+    NotFound,
+    /// A custom body
+    Respond {
+        /// Response status
+        status: StatusCode,
+        /// `Content-Type` of `body`
+        content_type: HeaderValue,
+        /// Response body, served verbatim
+        body: Bytes,
+    },
+    /// A redirect to `url`
+    Redirect {
+        /// Redirect target
+        url: String,
+        /// Redirect status (3xx)
+        status: StatusCode,
+        /// Append the request path and query to `url`
+        preserve_path: bool,
+    },
+    /// Proxy the request to this upstream as if a catch-all route matched
+    Upstream(String),
+}
+
+impl UnmatchedPolicy {
+    /// A catch-all route to the [`Upstream`](Self::Upstream), used in place of
+    /// the missing match so the request is proxied like any other.
+    pub fn catch_all_route(&self, method: &Method) -> Option<Route> {
+        let Self::Upstream(upstream) = self else {
+            return None;
+        };
+        RouteBuilder::new()
+            .method(method.clone())
+            .path("/*path")
+            .upstream_name(upstream)
+            .build()
+            .ok()
+    }
+
+    /// The response for an unmatched request to `uri`, or `None` to use the
+    /// default `404` (or the catch-all upstream).
+    pub fn response(&self, uri: &Uri) -> Option<Response<Full<Bytes>>> {
+        match self {
+            Self::NotFound | Self::Upstream(_) => None,
+            Self::Respond {
+                status,
+                content_type,
+                body,
+            } => {
+                let mut response = Response::new(Full::new(body.clone()));
+                *response.status_mut() = *status;
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_TYPE, content_type.clone());
+                Some(response)
+            }
+            Self::Redirect {
+                url,
+                status,
+                preserve_path,
+            } => {
+                let location = if *preserve_path {
+                    let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+                    format!("{}{path}", url.trim_end_matches('/'))
+                } else {
+                    url.clone()
+                };
+                ResponseBuilder::redirect(*status, location).ok()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[tokio::test]
+    async fn custom_body_keeps_status_and_content_type() {
+        let policy = UnmatchedPolicy::Respond {
+            status: StatusCode::NOT_FOUND,
+            content_type: HeaderValue::from_static("text/html"),
+            body: Bytes::from_static(b"<h1>Nothing here</h1>"),
+        };
+
+        let response = policy.response(&Uri::from_static("/missing")).unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"<h1>Nothing here</h1>");
+    }
+
+    #[test]
+    fn redirect_optionally_preserves_path_and_query() {
+        let uri = Uri::from_static("/old/page?id=7");
+        let policy = |preserve_path| UnmatchedPolicy::Redirect {
+            url: "https://legacy.example.com/".to_string(),
+            status: StatusCode::FOUND,
+            preserve_path,
+        };
+
+        let response = policy(true).response(&uri).unwrap();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://legacy.example.com/old/page?id=7"
+        );
+
+        let response = policy(false).response(&uri).unwrap();
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://legacy.example.com/"
+        );
+    }
+
+    #[test]
+    fn only_upstream_policy_has_a_catch_all_route() {
+        let route = UnmatchedPolicy::Upstream("legacy".to_string())
+            .catch_all_route(&Method::POST)
+            .unwrap();
+        assert_eq!(route.upstream_name, "legacy");
+        assert_eq!(route.method, Method::POST);
+
+        assert!(UnmatchedPolicy::NotFound
+            .catch_all_route(&Method::GET)
+            .is_none());
+        assert!(UnmatchedPolicy::NotFound
+            .response(&Uri::from_static("/"))
+            .is_none());
+    }
+}
@@ -0,0 +1,99 @@
+//! Startup warmup
+//!
+//! One-off work that would otherwise land on the first requests: letting
+//! each middleware in the global chain prepare itself (script middleware compiles its script), and, with
+//! `fetch_jwks`, having auth providers fetch signing keys they don't hold
+//! yet. Failures are logged and the work is left to happen lazily, unless
+//! `fail_on_error` turns them into a startup error.
+
+use octopus_auth::AuthProviderRegistry;
+use octopus_config::types::WarmupConfig;
+use octopus_core::middleware::Middleware;
+use octopus_core::{Error, Result};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Warm up `chain` and the auth providers as `config` says
+pub(crate) async fn run(
+    chain: &[Arc<dyn Middleware>],
+    auth: Option<&AuthProviderRegistry>,
+    config: &WarmupConfig,
+) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let started = Instant::now();
+
+    let mut failures = Vec::new();
+    for middleware in chain {
+        if let Err(e) = middleware.warm_up().await {
+            tracing::warn!(middleware = middleware.name(), error = %e, "Warmup failed");
+            failures.push(middleware.name().to_string());
+        }
+    }
+    if let Some(registry) = auth.filter(|_| config.fetch_jwks) {
+        for (provider, e) in registry.warm_up().await {
+            tracing::warn!(provider = %provider, error = %e, "Auth provider warmup failed");
+            failures.push(format!("auth provider {provider}"));
+        }
+    }
+
+    tracing::info!(
+        middleware = chain.len(),
+        failed = failures.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "Warmup complete"
+    );
+    if config.fail_on_error && !failures.is_empty() {
+        return Err(Error::Runtime(format!(
+            "Warmup failed: {}",
+            failures.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Request, Response};
+    use octopus_core::middleware::{Body, Next};
+
+    #[derive(Debug)]
+    struct FailingWarmup;
+
+    #[async_trait::async_trait]
+    impl Middleware for FailingWarmup {
+        async fn call(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+            next.run(req).await
+        }
+
+        async fn warm_up(&self) -> Result<()> {
+            Err(Error::Runtime("script does not compile".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn disabled_skips_every_step() {
+        let chain: Vec<Arc<dyn Middleware>> = vec![Arc::new(FailingWarmup)];
+        let disabled = WarmupConfig {
+            enabled: false,
+            fail_on_error: true,
+            ..Default::default()
+        };
+        assert!(run(&chain, None, &disabled).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn failures_are_fatal_only_when_configured() {
+        let chain: Vec<Arc<dyn Middleware>> = vec![Arc::new(FailingWarmup)];
+        assert!(run(&chain, None, &WarmupConfig::default()).await.is_ok());
+
+        let strict = WarmupConfig {
+            fail_on_error: true,
+            ..Default::default()
+        };
+        let err = run(&chain, None, &strict).await.unwrap_err();
+        assert!(err.to_string().contains("FailingWarmup"), "{err}");
+    }
+}
@@ -1,5 +1,10 @@
 //! Worker thread management
 
+use octopus_config::GatewayConfig;
+use octopus_core::{Error, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 /// Worker pool configuration
 #[derive(Debug, Clone)]
 pub struct WorkerConfig {
@@ -11,6 +16,9 @@ pub struct WorkerConfig {
 
     /// Thread name prefix
     pub thread_name: String,
+
+    /// CPUs to pin worker threads to, round-robin (empty = no pinning)
+    pub cpu_affinity: Vec<usize>,
 }
 
 impl Default for WorkerConfig {
@@ -19,16 +27,31 @@ impl Default for WorkerConfig {
             threads: 0, // Auto-detect
             stack_size: None,
             thread_name: "octopus-worker".to_string(),
+            cpu_affinity: Vec::new(),
+        }
+    }
+}
+
+impl WorkerConfig {
+    /// Worker configuration from `gateway.workers`, `gateway.worker_stack_size`
+    /// and `gateway.worker_cpus`.
+    pub fn from_gateway(gateway: &GatewayConfig) -> Self {
+        Self {
+            threads: gateway.workers,
+            stack_size: gateway.worker_stack_size,
+            cpu_affinity: gateway.worker_cpus.clone(),
+            ..Default::default()
         }
     }
 }
 
 /// Worker pool manager
 ///
-/// Note: This struct tracks worker configuration but does not create a separate runtime.
-/// The server uses the runtime provided by #[tokio::main] in the CLI entry point.
-/// Creating a nested runtime would cause "Cannot drop a runtime in a context where
-/// blocking is not allowed" panics on shutdown.
+/// Holds the worker configuration and builds the multi-threaded Tokio runtime
+/// the gateway runs on ([`WorkerPool::build_runtime`]). The runtime has to be
+/// built by the entry point before any async code runs: building (and later
+/// dropping) a runtime from inside another one panics with "Cannot drop a
+/// runtime in a context where blocking is not allowed".
 #[derive(Debug)]
 pub struct WorkerPool {
     config: WorkerConfig,
@@ -36,20 +59,38 @@ pub struct WorkerPool {
 
 impl WorkerPool {
     /// Create a new worker pool
-    pub fn new(config: WorkerConfig) -> octopus_core::Result<Self> {
-        let threads = if config.threads == 0 {
-            num_cpus::get()
-        } else {
-            config.threads
-        };
+    ///
+    /// Fails if `cpu_affinity` names a CPU this process may not run on.
+    pub fn new(config: WorkerConfig) -> Result<Self> {
+        if !config.cpu_affinity.is_empty() {
+            let allowed: Vec<usize> = core_affinity::get_core_ids()
+                .ok_or_else(|| {
+                    Error::Config("worker CPU pinning is not supported on this platform".into())
+                })?
+                .into_iter()
+                .map(|core| core.id)
+                .collect();
+            if let Some(cpu) = config
+                .cpu_affinity
+                .iter()
+                .find(|cpu| !allowed.contains(cpu))
+            {
+                return Err(Error::Config(format!(
+                    "worker CPU {cpu} is not available to this process (allowed: {allowed:?})"
+                )));
+            }
+        }
 
+        let pool = Self { config };
         tracing::info!(
-            threads = threads,
-            thread_name = %config.thread_name,
-            "Worker pool configuration loaded (using existing runtime)"
+            threads = pool.worker_count(),
+            stack_size = ?pool.config.stack_size,
+            cpus = ?pool.config.cpu_affinity,
+            thread_name = %pool.config.thread_name,
+            "Worker pool configuration loaded"
         );
 
-        Ok(Self { config })
+        Ok(pool)
     }
 
     /// Get worker count
@@ -60,6 +101,46 @@ impl WorkerPool {
             self.config.threads
         }
     }
+
+    /// Build the multi-threaded runtime described by this pool.
+    ///
+    /// When CPUs are configured, the first [`worker_count`](Self::worker_count)
+    /// threads the runtime starts — its workers — are pinned to them in turn.
+    /// Threads started later belong to the blocking pool and are left alone.
+    pub fn build_runtime(&self) -> Result<tokio::runtime::Runtime> {
+        let workers = self.worker_count();
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder
+            .worker_threads(workers)
+            .thread_name(self.config.thread_name.clone())
+            .enable_all();
+
+        if let Some(stack_size) = self.config.stack_size {
+            builder.thread_stack_size(stack_size);
+        }
+
+        if !self.config.cpu_affinity.is_empty() {
+            let cpus = self.config.cpu_affinity.clone();
+            let started = Arc::new(AtomicUsize::new(0));
+            builder.on_thread_start(move || {
+                let index = started.fetch_add(1, Ordering::Relaxed);
+                if index < workers {
+                    pin_current_thread(cpus[index % cpus.len()]);
+                }
+            });
+        }
+
+        builder
+            .build()
+            .map_err(|e| Error::Config(format!("failed to build worker runtime: {e}")))
+    }
+}
+
+/// Pin the calling thread to `cpu`. Failures are logged, not fatal.
+fn pin_current_thread(cpu: usize) {
+    if !core_affinity::set_for_current(core_affinity::CoreId { id: cpu }) {
+        tracing::warn!(cpu, "Failed to pin worker thread");
+    }
 }
 
 #[cfg(test)]
@@ -71,6 +152,7 @@ mod tests {
         let config = WorkerConfig::default();
         assert_eq!(config.threads, 0); // Auto
         assert_eq!(config.thread_name, "octopus-worker");
+        assert!(config.cpu_affinity.is_empty());
     }
 
     #[test]
@@ -92,4 +174,81 @@ mod tests {
 
         assert_eq!(pool.worker_count(), 4);
     }
+
+    #[test]
+    fn test_worker_pool_rejects_unknown_cpu() {
+        let config = WorkerConfig {
+            cpu_affinity: vec![usize::MAX],
+            ..Default::default()
+        };
+        let err = WorkerPool::new(config).unwrap_err().to_string();
+        assert!(err.contains("not available"), "{err}");
+    }
+
+    #[test]
+    fn test_runtime_has_configured_workers() {
+        let pool = WorkerPool::new(WorkerConfig {
+            threads: 3,
+            stack_size: Some(1024 * 1024),
+            cpu_affinity: vec![first_allowed_cpu()],
+            ..Default::default()
+        })
+        .unwrap();
+        let runtime = pool.build_runtime().unwrap();
+
+        assert_eq!(runtime.metrics().num_workers(), 3);
+        let name = runtime.block_on(async {
+            tokio::spawn(async { std::thread::current().name().map(str::to_string) })
+                .await
+                .unwrap()
+        });
+        assert_eq!(name.as_deref(), Some("octopus-worker"));
+    }
+
+    fn first_allowed_cpu() -> usize {
+        core_affinity::get_core_ids().unwrap()[0].id
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_runtime_pins_workers() {
+        let cpu = first_allowed_cpu();
+        let pool = WorkerPool::new(WorkerConfig {
+            threads: 2,
+            cpu_affinity: vec![cpu],
+            ..Default::default()
+        })
+        .unwrap();
+        let runtime = pool.build_runtime().unwrap();
+
+        // On Linux the ids a thread may use are its affinity mask
+        let allowed = runtime.block_on(async {
+            tokio::spawn(async {
+                core_affinity::get_core_ids()
+                    .unwrap()
+                    .into_iter()
+                    .map(|core| core.id)
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap()
+        });
+        assert_eq!(allowed, vec![cpu]);
+    }
+
+    #[test]
+    fn test_worker_config_from_gateway() {
+        let gateway: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "listen": "0.0.0.0:8080",
+            "workers": 6,
+            "worker_stack_size": 4194304,
+            "worker_cpus": [0, 2],
+        }))
+        .unwrap();
+        let config = WorkerConfig::from_gateway(&gateway);
+
+        assert_eq!(config.threads, 6);
+        assert_eq!(config.stack_size, Some(4 * 1024 * 1024));
+        assert_eq!(config.cpu_affinity, vec![0, 2]);
+    }
 }
@@ -21,6 +21,8 @@ pub struct RequestContext {
     pub query: HashMap<String, String>,
     /// Path parameters (from router)
     pub path_params: HashMap<String, String>,
+    /// Authenticated principal (set by the auth gateway), if any
+    pub auth: Option<Box<octopus_core::AuthContext>>,
     /// Custom metadata
     pub metadata: HashMap<String, serde_json::Value>,
 }
@@ -49,7 +51,16 @@ impl RequestContext {
                 .collect(),
             body: None, // Will be populated if needed
             query,
-            path_params: HashMap::new(),
+            path_params: req
+                .extensions()
+                .get::<octopus_core::PathParams>()
+                .map(|p| p.0.clone())
+                .unwrap_or_default(),
+            auth: req
+                .extensions()
+                .get::<octopus_core::AuthContext>()
+                .cloned()
+                .map(Box::new),
             metadata: HashMap::new(),
         }
     }
@@ -1,6 +1,6 @@
 //! Script engine trait and abstractions
 
-use crate::context::ScriptContext;
+use crate::context::{RequestContext, ScriptContext};
 use crate::error::{Result, ScriptError};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -55,6 +55,29 @@ impl fmt::Display for ScriptLanguage {
     }
 }
 
+/// What a script is run for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptKind {
+    /// Request/response transformation, run before authentication
+    #[default]
+    Transform,
+    /// Authorization decision, run after authentication and before proxying
+    Authorize,
+}
+
+/// Result of an authorization script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthzDecision {
+    /// Let the request through
+    Allow,
+    /// Reject the request with a 403
+    Deny {
+        /// Optional reason, returned in the 403 body
+        reason: Option<String>,
+    },
+}
+
 /// Script source (inline or file-based)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -153,6 +176,22 @@ pub trait ScriptEngine: Send + Sync + fmt::Debug {
         ctx: &mut ScriptContext,
     ) -> Result<bool>;
 
+    /// Execute an authorization script (see [`ScriptKind::Authorize`])
+    ///
+    /// The request context is read-only: authorization scripts decide, they
+    /// do not transform.
+    async fn execute_authorize(
+        &self,
+        source: &ScriptSource,
+        ctx: &RequestContext,
+    ) -> Result<AuthzDecision> {
+        let _ = (source, ctx);
+        Err(ScriptError::runtime(format!(
+            "{} engine does not support authorization scripts",
+            self.language()
+        )))
+    }
+
     /// Clear cached ASTs/compiled scripts
     async fn clear_cache(&self) -> Result<()> {
         Ok(())
@@ -7,7 +7,7 @@
 //! - **Rhai** - Fast, Rust-native scripting (5-50μs execution)
 //! - **Lua** - Coming soon
 //! - **JavaScript (Deno)** - Coming soon
-//! - **WebAssembly** - Coming soon
+//! - **WebAssembly** - Runs as sandboxed plugins instead (`octopus_plugin_runtime::wasm`)
 //!
 //! ## Features
 //!
@@ -33,7 +33,7 @@ pub mod middleware;
 pub mod rhai_engine;
 
 pub use context::{RequestContext, ResponseContext, ScriptContext};
-pub use engine::{ScriptEngine, ScriptLanguage, ScriptSource};
+pub use engine::{AuthzDecision, ScriptEngine, ScriptKind, ScriptLanguage, ScriptSource};
 pub use error::{Result, ScriptError};
 pub use middleware::{ScriptMiddleware, ScriptMiddlewareConfig};
 pub use rhai_engine::{HostResolution, RhaiEngine};
@@ -41,7 +41,9 @@ pub use rhai_engine::{HostResolution, RhaiEngine};
 /// Prelude with commonly used types
 pub mod prelude {
     pub use crate::context::{RequestContext, ResponseContext, ScriptContext};
-    pub use crate::engine::{ScriptEngine, ScriptLanguage, ScriptSource};
+    pub use crate::engine::{
+        AuthzDecision, ScriptEngine, ScriptKind, ScriptLanguage, ScriptSource,
+    };
     pub use crate::error::{Result, ScriptError};
     pub use crate::middleware::{ScriptMiddleware, ScriptMiddlewareConfig};
 }
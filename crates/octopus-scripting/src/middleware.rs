@@ -1,13 +1,13 @@
 //! Script middleware for request/response interception
 
 use crate::context::{RequestContext, ResponseContext, ScriptContext};
-use crate::engine::{ScriptEngine, ScriptLanguage, ScriptSource};
+use crate::engine::{AuthzDecision, ScriptEngine, ScriptKind, ScriptLanguage, ScriptSource};
 use crate::error::{Result as ScriptResult, ScriptError};
 use crate::rhai_engine::RhaiEngine;
 use async_trait::async_trait;
 use http::{Request, Response};
 use octopus_core::middleware::{Body, Middleware, Next};
-use octopus_core::{Error, Result};
+use octopus_core::{Error, ErrorResponse, Result, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::sync::Arc;
@@ -24,6 +24,11 @@ pub struct ScriptMiddlewareConfig {
     #[serde(flatten)]
     pub source: ScriptSource,
 
+    /// What the script is for (default: transform). `authorize` scripts
+    /// return an allow/deny decision and ignore `on_request`/`on_response`.
+    #[serde(default)]
+    pub kind: ScriptKind,
+
     /// Whether to run on requests (default: true)
     #[serde(default = "default_true")]
     pub on_request: bool,
@@ -59,6 +64,7 @@ impl ScriptMiddlewareConfig {
         Self {
             language: ScriptLanguage::Rhai,
             source: ScriptSource::inline(code),
+            kind: ScriptKind::Transform,
             on_request: true,
             on_response: false,
             continue_on_error: false,
@@ -71,6 +77,7 @@ impl ScriptMiddlewareConfig {
         Self {
             language: ScriptLanguage::Rhai,
             source: ScriptSource::file(path),
+            kind: ScriptKind::Transform,
             on_request: true,
             on_response: false,
             continue_on_error: false,
@@ -78,6 +85,12 @@ impl ScriptMiddlewareConfig {
         }
     }
 
+    /// Run as an authorization script ([`ScriptKind::Authorize`])
+    pub fn authorize(mut self) -> Self {
+        self.kind = ScriptKind::Authorize;
+        self
+    }
+
     /// Enable response interception
     pub fn with_response(mut self) -> Self {
         self.on_response = true;
@@ -190,6 +203,56 @@ impl ScriptMiddleware {
         result
     }
 
+    /// Execute an authorization script against the request
+    async fn execute_authorize(&self, req: &Request<Body>) -> ScriptResult<AuthzDecision> {
+        let ctx = RequestContext::from_request(req);
+        let timeout = tokio::time::Duration::from_millis(self.config.timeout_ms);
+        tokio::time::timeout(
+            timeout,
+            self.engine.execute_authorize(&self.config.source, &ctx),
+        )
+        .await
+        .map_err(|_| ScriptError::timeout(self.config.timeout_ms))?
+    }
+
+    /// Run as an authorization hook: a denial becomes a 403 whose detail is
+    /// the script's reason. Script errors fail closed unless
+    /// `continue_on_error` is set.
+    async fn authorize(&self, req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        match self.execute_authorize(&req).await {
+            Ok(AuthzDecision::Allow) => next.run(req).await,
+            Ok(AuthzDecision::Deny { reason }) => {
+                debug!(
+                    script = %self.config.source.name(),
+                    reason = ?reason,
+                    "Authorization script denied request"
+                );
+                Ok(ErrorResponse::new(StatusCode::FORBIDDEN, "forbidden")
+                    .detail(reason.unwrap_or_else(|| "Denied by authorization policy".to_string()))
+                    .instance(req.uri().path())
+                    .into_response())
+            }
+            Err(e) => {
+                error!(
+                    script = %self.config.source.name(),
+                    error = %e,
+                    "Authorization script failed"
+                );
+                if self.config.continue_on_error {
+                    next.run(req).await
+                } else {
+                    Err(self.plugin_error(&e))
+                }
+            }
+        }
+    }
+
+    /// A failing script surfaces as a plugin error named after the script,
+    /// so callers can attribute the crash.
+    fn plugin_error(&self, e: &ScriptError) -> Error {
+        Error::plugin(self.config.source.name(), e.to_string())
+    }
+
     /// Execute script on response
     async fn execute_on_response(&self, res: &mut Response<Body>) -> ScriptResult<bool> {
         let start = std::time::Instant::now();
@@ -231,6 +294,7 @@ impl fmt::Debug for ScriptMiddleware {
         f.debug_struct("ScriptMiddleware")
             .field("language", &self.config.language)
             .field("script", &self.config.source.name())
+            .field("kind", &self.config.kind)
             .field("on_request", &self.config.on_request)
             .field("on_response", &self.config.on_response)
             .finish()
@@ -240,6 +304,10 @@ impl fmt::Debug for ScriptMiddleware {
 #[async_trait]
 impl Middleware for ScriptMiddleware {
     async fn call(&self, mut req: Request<Body>, next: Next) -> Result<Response<Body>> {
+        if self.config.kind == ScriptKind::Authorize {
+            return self.authorize(req, next).await;
+        }
+
         // Execute on request if enabled
         if self.config.on_request {
             match self.execute_on_request(&mut req).await {
@@ -263,7 +331,7 @@ impl Middleware for ScriptMiddleware {
                         "Script execution failed on request"
                     );
                     if !self.config.continue_on_error {
-                        return Err(Error::Internal(format!("Script error: {e}")));
+                        return Err(self.plugin_error(&e));
                     }
                 }
             }
@@ -285,7 +353,7 @@ impl Middleware for ScriptMiddleware {
                         "Script execution failed on response"
                     );
                     if !self.config.continue_on_error {
-                        return Err(Error::Internal(format!("Script error: {e}")));
+                        return Err(self.plugin_error(&e));
                     }
                 }
             }
@@ -293,6 +361,10 @@ impl Middleware for ScriptMiddleware {
 
         Ok(res)
     }
+
+    async fn warm_up(&self) -> Result<()> {
+        self.prepare().await.map_err(|e| self.plugin_error(&e))
+    }
 }
 
 #[cfg(test)]
@@ -318,4 +390,134 @@ mod tests {
         assert!(config.continue_on_error);
         assert_eq!(config.timeout_ms, 200);
     }
+
+    /// Terminal middleware standing in for the proxy.
+    #[derive(Debug)]
+    struct Upstream;
+
+    #[async_trait]
+    impl Middleware for Upstream {
+        async fn call(&self, _req: Request<Body>, _next: Next) -> Result<Response<Body>> {
+            Ok(Response::new(Body::from("proxied")))
+        }
+    }
+
+    /// Tenant isolation: the `:tenant` path param must match the principal's
+    /// `tenant` claim; admins may access any tenant.
+    const TENANT_SCRIPT: &str = r#"
+        if principal == () { return deny("authentication required"); }
+        if principal.roles.contains("admin") { return allow(); }
+        if path_params.tenant != principal.claims.tenant {
+            return deny(`tenant ${path_params.tenant} is not yours`);
+        }
+        true
+    "#;
+
+    fn tenant_request(tenant: &str, auth: Option<octopus_core::AuthContext>) -> Request<Body> {
+        let mut req = Request::builder()
+            .uri(format!("/tenants/{tenant}/orders"))
+            .body(Body::from(""))
+            .unwrap();
+        let mut params = std::collections::HashMap::new();
+        params.insert("tenant".to_string(), tenant.to_string());
+        req.extensions_mut()
+            .insert(octopus_core::PathParams(params));
+        if let Some(auth) = auth {
+            req.extensions_mut().insert(auth);
+        }
+        req
+    }
+
+    fn principal(tenant: &str, roles: &[&str]) -> octopus_core::AuthContext {
+        let mut claims = std::collections::HashMap::new();
+        claims.insert("tenant".to_string(), serde_json::json!(tenant));
+        octopus_core::AuthContext {
+            subject: "user-1".to_string(),
+            provider: "jwt".to_string(),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            scopes: Vec::new(),
+            claims,
+        }
+    }
+
+    async fn run_tenant_check(req: Request<Body>) -> Response<Body> {
+        let authz =
+            ScriptMiddleware::new(ScriptMiddlewareConfig::inline(TENANT_SCRIPT).authorize());
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(authz) as Arc<dyn Middleware>, Arc::new(Upstream)]);
+        Next::new(stack).run(req).await.unwrap()
+    }
+
+    async fn body_string(res: Response<Body>) -> String {
+        let body = http_body_util::BodyExt::collect(res.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn authorize_denies_path_param_not_matching_claim() {
+        let res = run_tenant_check(tenant_request("globex", Some(principal("acme", &[])))).await;
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(body_string(res)
+            .await
+            .contains("tenant globex is not yours"));
+    }
+
+    #[tokio::test]
+    async fn authorize_allows_matching_claim_and_admin() {
+        let res = run_tenant_check(tenant_request("acme", Some(principal("acme", &[])))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_string(res).await, "proxied");
+
+        let res = run_tenant_check(tenant_request(
+            "globex",
+            Some(principal("acme", &["admin"])),
+        ))
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn authorize_denies_unauthenticated_request() {
+        let res = run_tenant_check(tenant_request("acme", None)).await;
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(body_string(res).await.contains("authentication required"));
+    }
+
+    #[tokio::test]
+    async fn failing_script_is_reported_as_plugin_error() {
+        let script = ScriptMiddleware::new(ScriptMiddlewareConfig::inline(r#"throw "boom""#));
+        let stack: Arc<[Arc<dyn Middleware>]> =
+            Arc::new([Arc::new(script) as Arc<dyn Middleware>, Arc::new(Upstream)]);
+        let req = Request::builder().uri("/").body(Body::from("")).unwrap();
+
+        let err = Next::new(stack).run(req).await.unwrap_err();
+        assert!(matches!(err, Error::Plugin { ref plugin, .. } if plugin == "inline"));
+    }
+
+    #[tokio::test]
+    async fn warm_up_compiles_the_script_ahead_of_the_first_request() {
+        let script = ScriptMiddleware::new(ScriptMiddlewareConfig::inline("let x = 1; true"));
+        assert!(script.warm_up().await.is_ok());
+
+        let broken = ScriptMiddleware::new(ScriptMiddlewareConfig::inline("let x = ;"));
+        let err = broken.warm_up().await.unwrap_err();
+        assert!(matches!(err, Error::Plugin { ref plugin, .. } if plugin == "inline"));
+    }
+
+    #[test]
+    fn authorize_kind_parses_from_config() {
+        let config: ScriptMiddlewareConfig =
+            serde_json::from_value(serde_json::json!({"code": "true", "kind": "authorize"}))
+                .unwrap();
+        assert_eq!(config.kind, ScriptKind::Authorize);
+
+        let config: ScriptMiddlewareConfig =
+            serde_json::from_value(serde_json::json!({"code": "true"})).unwrap();
+        assert_eq!(config.kind, ScriptKind::Transform);
+    }
 }
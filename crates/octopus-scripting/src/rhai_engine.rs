@@ -1,7 +1,7 @@
 //! Rhai script engine implementation
 
 use crate::context::{RequestContext, ResponseContext, ScriptContext};
-use crate::engine::{CacheStats, ScriptEngine, ScriptLanguage, ScriptSource};
+use crate::engine::{AuthzDecision, CacheStats, ScriptEngine, ScriptLanguage, ScriptSource};
 use crate::error::{Result, ScriptError};
 use async_trait::async_trait;
 use rhai::{Dynamic, Engine, Scope, AST};
@@ -100,6 +100,20 @@ impl RhaiEngine {
 
         engine.register_fn("uuid", || -> String { uuid::Uuid::new_v4().to_string() });
 
+        // Authorization decisions: `allow()`, `deny("reason")`
+        engine.register_fn("allow", || -> rhai::Map {
+            let mut decision = rhai::Map::new();
+            decision.insert("allow".into(), Dynamic::from(true));
+            decision
+        });
+
+        engine.register_fn("deny", |reason: &str| -> rhai::Map {
+            let mut decision = rhai::Map::new();
+            decision.insert("allow".into(), Dynamic::from(false));
+            decision.insert("reason".into(), Dynamic::from(reason.to_string()));
+            decision
+        });
+
         // Logging (for debugging scripts)
         engine.register_fn("log_debug", |msg: &str| {
             debug!(script_log = msg);
@@ -283,6 +297,129 @@ impl RhaiEngine {
         }
     }
 
+    /// Execute an authorization script. The scope holds `method`, `uri`,
+    /// `path`, `headers`, `query`, `path_params` and `principal` (a map of
+    /// `id`, `provider`, `roles`, `scopes` and `claims`, or `()` when
+    /// unauthenticated).
+    async fn execute_with_authorize(
+        &self,
+        ast: &AST,
+        ctx: &RequestContext,
+    ) -> Result<AuthzDecision> {
+        let mut scope = Scope::new();
+
+        scope.push("method", ctx.method.clone());
+        scope.push("uri", ctx.uri.clone());
+        let path = ctx
+            .uri
+            .parse::<http::Uri>()
+            .map(|u| u.path().to_string())
+            .unwrap_or_else(|_| ctx.uri.clone());
+        scope.push("path", path);
+        scope.push("headers", Self::string_map(&ctx.headers));
+        scope.push("query", Self::string_map(&ctx.query));
+        scope.push("path_params", Self::string_map(&ctx.path_params));
+
+        let principal = match &ctx.auth {
+            Some(auth) => {
+                let mut principal = rhai::Map::new();
+                principal.insert("id".into(), Dynamic::from(auth.subject.clone()));
+                principal.insert("provider".into(), Dynamic::from(auth.provider.clone()));
+                principal.insert("roles".into(), Self::string_array(&auth.roles));
+                principal.insert("scopes".into(), Self::string_array(&auth.scopes));
+                let claims: rhai::Map = auth
+                    .claims
+                    .iter()
+                    .map(|(k, v)| (k.clone().into(), Self::json_to_dynamic(v)))
+                    .collect();
+                principal.insert("claims".into(), Dynamic::from(claims));
+                Dynamic::from(principal)
+            }
+            None => Dynamic::UNIT,
+        };
+        scope.push("principal", principal);
+
+        let result: Dynamic = self
+            .engine
+            .eval_ast_with_scope(&mut scope, ast)
+            .map_err(|e| ScriptError::RuntimeError {
+                message: e.to_string(),
+                line: None,
+            })?;
+
+        Self::extract_authz_decision(result)
+    }
+
+    /// Interpret an authorization script's return value: `true`/`false`, or
+    /// a map `#{ allow: bool, reason: "..." }` (as built by `allow()` /
+    /// `deny(reason)`).
+    fn extract_authz_decision(result: Dynamic) -> Result<AuthzDecision> {
+        if let Some(allow) = result.clone().try_cast::<bool>() {
+            return Ok(if allow {
+                AuthzDecision::Allow
+            } else {
+                AuthzDecision::Deny { reason: None }
+            });
+        }
+        let map = result.try_cast::<rhai::Map>().ok_or_else(|| {
+            ScriptError::runtime(
+                "Authorization script must return a bool or #{ allow: bool, reason: string }",
+            )
+        })?;
+        let allow = map
+            .get("allow")
+            .and_then(|v| v.clone().try_cast::<bool>())
+            .ok_or_else(|| ScriptError::runtime("Authorization result is missing `allow`"))?;
+        if allow {
+            return Ok(AuthzDecision::Allow);
+        }
+        let reason = map
+            .get("reason")
+            .and_then(|v| v.clone().try_cast::<String>());
+        Ok(AuthzDecision::Deny { reason })
+    }
+
+    fn string_map(values: &HashMap<String, String>) -> rhai::Map {
+        values
+            .iter()
+            .map(|(k, v)| (k.clone().into(), Dynamic::from(v.clone())))
+            .collect()
+    }
+
+    fn string_array(values: &[String]) -> Dynamic {
+        Dynamic::from(
+            values
+                .iter()
+                .cloned()
+                .map(Dynamic::from)
+                .collect::<rhai::Array>(),
+        )
+    }
+
+    fn json_to_dynamic(value: &serde_json::Value) -> Dynamic {
+        match value {
+            serde_json::Value::Null => Dynamic::UNIT,
+            serde_json::Value::Bool(b) => Dynamic::from(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Dynamic::from(i),
+                None => Dynamic::from(n.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(s) => Dynamic::from(s.clone()),
+            serde_json::Value::Array(items) => Dynamic::from(
+                items
+                    .iter()
+                    .map(Self::json_to_dynamic)
+                    .collect::<rhai::Array>(),
+            ),
+            serde_json::Value::Object(fields) => Dynamic::from(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone().into(), Self::json_to_dynamic(v)))
+                    .collect::<rhai::Map>(),
+            ),
+        }
+    }
+
     /// Evaluate a host-resolution script: the script is given the request `host`
     /// (as a string variable `host`) and returns a map
     /// `#{ namespace: "...", service: "...", port: 8080 }` (port optional) to map
@@ -386,6 +523,15 @@ impl ScriptEngine for RhaiEngine {
         }
     }
 
+    async fn execute_authorize(
+        &self,
+        source: &ScriptSource,
+        ctx: &RequestContext,
+    ) -> Result<AuthzDecision> {
+        let ast = self.get_ast(source).await?;
+        self.execute_with_authorize(&ast, ctx).await
+    }
+
     async fn clear_cache(&self) -> Result<()> {
         let mut cache = self.ast_cache.write().await;
         cache.clear();
@@ -489,6 +635,7 @@ mod tests {
             body: None,
             query: HashMap::new(),
             path_params: HashMap::new(),
+            auth: None,
             metadata: HashMap::new(),
         };
 
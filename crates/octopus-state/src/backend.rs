@@ -2,8 +2,39 @@
 
 use crate::{Error, Result};
 use async_trait::async_trait;
+use serde::Serialize;
+use std::fmt;
 use std::time::Duration;
 
+/// Reachability of a remote backend, for health endpoints and the admin UI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackendHealth {
+    /// Overall status
+    pub status: HealthStatus,
+    /// Failed operations since the last successful one
+    pub consecutive_failures: u64,
+    /// Most recent connection error or timeout
+    pub last_error: Option<String>,
+}
+
+/// Status in a [`BackendHealth`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// The last operation reached the backend
+    Connected,
+    /// Recent operations timed out or lost their connection; they are being
+    /// retried on a reconnecting connection
+    Degraded,
+}
+
+/// A remote backend that tracks its own reachability, so readiness probes
+/// and the admin health view can report it
+pub trait BackendHealthSource: Send + Sync + fmt::Debug {
+    /// Current reachability
+    fn health(&self) -> BackendHealth;
+}
+
 /// State backend trait
 ///
 /// Defines the interface for pluggable state storage backends.
@@ -21,6 +52,13 @@ pub trait StateBackend: Send + Sync + Clone + 'static {
     /// If TTL is Some, the value expires after the duration.
     async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()>;
 
+    /// Set a value only if the key is absent (or expired)
+    ///
+    /// Returns true if the value was set. An existing key keeps its value
+    /// and TTL, which makes this suitable for locks that must expire.
+    async fn set_if_absent(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>)
+        -> Result<bool>;
+
     /// Atomic increment operation
     ///
     /// Increments the value at key by delta.
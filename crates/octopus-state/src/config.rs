@@ -13,6 +13,14 @@ pub struct StateConfig {
     /// Cleanup interval for expired keys (in-memory backend)
     #[serde(default = "default_cleanup_interval")]
     pub cleanup_interval: Duration,
+
+    /// Maximum number of keys the in-memory backend holds (None = unbounded)
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+
+    /// Which key the in-memory backend evicts once `max_entries` is reached
+    #[serde(default)]
+    pub eviction: EvictionPolicy,
 }
 
 impl Default for StateConfig {
@@ -20,10 +28,23 @@ impl Default for StateConfig {
         Self {
             backend: BackendConfig::InMemory,
             cleanup_interval: default_cleanup_interval(),
+            max_entries: None,
+            eviction: EvictionPolicy::default(),
         }
     }
 }
 
+/// Eviction policy of a size-capped in-memory backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicy {
+    /// Evict the least recently used key
+    #[default]
+    Lru,
+    /// Evict the least frequently used key (ties go to the least recent)
+    Lfu,
+}
+
 /// Backend configuration
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -46,6 +67,10 @@ pub enum BackendConfig {
         #[serde(default = "default_timeout")]
         timeout: Duration,
 
+        /// Deadline for each operation, so a stalled Redis fails fast
+        #[serde(default = "default_op_timeout")]
+        op_timeout: Duration,
+
         /// Key prefix for namespacing
         #[serde(default)]
         prefix: Option<String>,
@@ -76,10 +101,20 @@ pub enum BackendConfig {
         /// Redis configuration
         redis_url: String,
 
-        /// Local cache TTL
+        /// Local cache TTL: how long a value may be served from this
+        /// instance after another instance changed it (staleness window)
         #[serde(default = "default_cache_ttl")]
         cache_ttl: Duration,
 
+        /// Maximum number of keys held in the local cache
+        #[serde(default = "default_max_cached")]
+        max_cached: usize,
+
+        /// Redis pub/sub channel used to invalidate other instances' local
+        /// caches on writes (None = rely on `cache_ttl` alone)
+        #[serde(default)]
+        invalidation_channel: Option<String>,
+
         /// Connection pool size
         #[serde(default = "default_pool_size")]
         pool_size: u32,
@@ -104,11 +139,21 @@ fn default_timeout() -> Duration {
     Duration::from_secs(5)
 }
 
+#[allow(dead_code)]
+fn default_op_timeout() -> Duration {
+    Duration::from_secs(1)
+}
+
 #[allow(dead_code)]
 fn default_cache_ttl() -> Duration {
     Duration::from_secs(60)
 }
 
+#[allow(dead_code)]
+fn default_max_cached() -> usize {
+    10_000
+}
+
 #[allow(dead_code)]
 fn default_table_name() -> String {
     "octopus_state".to_string()
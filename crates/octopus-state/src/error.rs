@@ -30,6 +30,10 @@ pub enum Error {
     #[error("Backend error: {0}")]
     Backend(String),
 
+    /// A capped store is full of entries it may not evict
+    #[error("Store full: {0}")]
+    CapacityExceeded(String),
+
     /// Invalid configuration
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
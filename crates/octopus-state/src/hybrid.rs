@@ -1,9 +1,16 @@
 //! Hybrid backend (local cache + Redis)
 
-use crate::{InMemoryBackend, RedisBackend, Result, StateBackend};
+use crate::{BackendConfig, Error, EvictionPolicy, InMemoryBackend, InMemoryStats};
+use crate::{RedisBackend, RedisOptions, Result, StateBackend};
 use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, trace};
+use tokio::task::JoinHandle;
+use tracing::{debug, trace, warn};
+
+/// Default cap on the number of locally cached keys
+const DEFAULT_MAX_CACHED: usize = 10_000;
 
 /// Hybrid backend combining local cache with Redis
 ///
@@ -13,38 +20,67 @@ use tracing::{debug, trace};
 /// ## Strategy
 /// - **Reads**: Local cache first (μs), Redis on miss (1-2ms)
 /// - **Writes**: Write-through to Redis, update local cache
-/// - **Invalidation**: TTL-based expiration in local cache
+/// - **Invalidation**: TTL-based expiration in local cache, optionally
+///   pushed to other instances over Redis pub/sub
+///
+/// ## Consistency
+/// A value cached here is served for at most `cache_ttl` after another
+/// instance changed it; that is the staleness window. With
+/// [`with_invalidation`](HybridBackend::with_invalidation) other instances
+/// drop their copy as soon as the write is published, and the window only
+/// covers a lost message. Atomic operations (increment, CAS) always go to
+/// Redis.
+///
+/// The remote is generic so the caching layer can run over any backend; it
+/// defaults to [`RedisBackend`].
 #[derive(Clone)]
-pub struct HybridBackend {
+pub struct HybridBackend<R = RedisBackend> {
     local: InMemoryBackend,
-    remote: RedisBackend,
+    remote: R,
     cache_ttl: Duration,
+    invalidation: Option<Arc<Invalidation>>,
 }
 
-impl std::fmt::Debug for HybridBackend {
+impl<R> std::fmt::Debug for HybridBackend<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HybridBackend")
             .field("cache_ttl", &self.cache_ttl)
+            .field(
+                "invalidation_channel",
+                &self.invalidation.as_ref().map(|i| &i.channel),
+            )
             .finish()
     }
 }
 
+/// Cross-instance invalidation over Redis pub/sub
+///
+/// Messages are `"<instance> k:<key>"` for a changed key and `"<instance> f"`
+/// for a flush; an instance ignores its own.
+struct Invalidation {
+    publisher: RedisBackend,
+    channel: String,
+    instance: String,
+    subscriber: JoinHandle<()>,
+}
+
+impl Drop for Invalidation {
+    fn drop(&mut self) {
+        self.subscriber.abort();
+    }
+}
+
 impl HybridBackend {
     /// Create a new hybrid backend
     pub async fn new(redis_url: &str, cache_ttl: Duration) -> Result<Self> {
         let remote = RedisBackend::new(redis_url).await?;
-        let local = InMemoryBackend::with_cleanup(cache_ttl);
 
         debug!(
             cache_ttl_secs = cache_ttl.as_secs(),
             "Hybrid backend initialized"
         );
 
-        Ok(Self {
-            local,
-            remote,
-            cache_ttl,
-        })
+        Self::with_remote(remote, cache_ttl)
     }
 
     /// Create with key prefix for Redis namespacing
@@ -54,15 +90,115 @@ impl HybridBackend {
         cache_ttl: Duration,
     ) -> Result<Self> {
         let remote = RedisBackend::with_prefix(redis_url, prefix).await?;
-        let local = InMemoryBackend::with_cleanup(cache_ttl);
+        Self::with_remote(remote, cache_ttl)
+    }
+
+    /// Create from a [`BackendConfig::Hybrid`]
+    pub async fn from_config(config: &BackendConfig) -> Result<Self> {
+        let BackendConfig::Hybrid {
+            redis_url,
+            cache_ttl,
+            max_cached,
+            invalidation_channel,
+            pool_size,
+            prefix,
+        } = config
+        else {
+            return Err(Error::InvalidConfig(
+                "expected a hybrid backend configuration".to_string(),
+            ));
+        };
+
+        let options = RedisOptions {
+            pool_size: *pool_size as usize,
+            ..Default::default()
+        };
+        let mut remote = RedisBackend::with_options(redis_url, options).await?;
+        remote.prefix = prefix.clone();
+        let backend = Self::with_remote(remote, *cache_ttl)?.with_max_cached(*max_cached);
+
+        match invalidation_channel {
+            Some(channel) => backend.with_invalidation(channel).await,
+            None => Ok(backend),
+        }
+    }
+
+    /// Invalidate other instances' local caches over Redis pub/sub
+    ///
+    /// Every write is published on `channel`, and a background subscriber
+    /// drops the keys other instances publish. Publishing is best effort: a
+    /// failure is logged and the write still succeeds, leaving peers stale
+    /// for at most the cache TTL.
+    pub async fn with_invalidation(mut self, channel: impl Into<String>) -> Result<Self> {
+        let channel = channel.into();
+        let instance = uuid::Uuid::new_v4().to_string();
+        let pubsub = self.remote.subscribe(&channel).await?;
+
+        let local = self.local.clone();
+        let own = instance.clone();
+        let subscriber = tokio::spawn(async move {
+            let mut messages = pubsub.into_on_message();
+            while let Some(msg) = messages.next().await {
+                let Ok(payload) = msg.get_payload::<String>() else {
+                    continue;
+                };
+                match payload.split_once(' ') {
+                    Some((sender, _)) if sender == own => {}
+                    Some((_, "f")) => {
+                        let _ = local.flush().await;
+                    }
+                    Some((_, op)) => {
+                        if let Some(key) = op.strip_prefix("k:") {
+                            trace!(key, "Hybrid remote invalidation");
+                            let _ = local.delete(key).await;
+                        }
+                    }
+                    None => {}
+                }
+            }
+            warn!("Hybrid invalidation subscription closed");
+        });
+
+        debug!(%channel, "Hybrid cache invalidation enabled");
+        self.invalidation = Some(Arc::new(Invalidation {
+            publisher: self.remote.clone(),
+            channel,
+            instance,
+            subscriber,
+        }));
+        Ok(self)
+    }
+}
+
+impl<R: StateBackend> HybridBackend<R> {
+    /// Cache `remote` locally, serving cached values for up to `cache_ttl`
+    ///
+    /// The local cache sweeps expired entries on a tokio task, so this fails
+    /// outside a tokio runtime.
+    pub fn with_remote(remote: R, cache_ttl: Duration) -> Result<Self> {
+        if tokio::runtime::Handle::try_current().is_err() {
+            return Err(Error::Backend(
+                "hybrid backend must be created inside a tokio runtime".to_string(),
+            ));
+        }
+        let local = InMemoryBackend::with_cleanup(cache_ttl)
+            .with_max_entries(DEFAULT_MAX_CACHED, EvictionPolicy::Lru)
+            .with_ttl_eviction();
 
         Ok(Self {
             local,
             remote,
             cache_ttl,
+            invalidation: None,
         })
     }
 
+    /// Cap the local cache at `max_cached` keys (least recently used evicted)
+    pub fn with_max_cached(mut self, max_cached: usize) -> Self {
+        self.local = self.local.with_max_entries(max_cached, EvictionPolicy::Lru);
+        self
+    }
+
     /// Invalidate local cache for a key
     pub async fn invalidate_cache(&self, key: &str) -> Result<()> {
         self.local.delete(key).await
@@ -72,10 +208,39 @@ impl HybridBackend {
     pub fn cache_size(&self) -> usize {
         self.local.len()
     }
+
+    /// Size and eviction counters of the local cache
+    pub fn cache_stats(&self) -> InMemoryStats {
+        self.local.stats()
+    }
+
+    /// Local TTL for a value whose remote TTL is `ttl`
+    fn local_ttl(&self, ttl: Option<Duration>) -> Option<Duration> {
+        Some(ttl.unwrap_or(self.cache_ttl).min(self.cache_ttl))
+    }
+
+    /// Tell other instances that `key` changed
+    async fn publish_change(&self, key: &str) {
+        self.publish(format!("k:{key}")).await;
+    }
+
+    async fn publish(&self, op: String) {
+        let Some(invalidation) = &self.invalidation else {
+            return;
+        };
+        let message = format!("{} {op}", invalidation.instance);
+        if let Err(e) = invalidation
+            .publisher
+            .publish(&invalidation.channel, message)
+            .await
+        {
+            warn!(error = %e, "Failed to publish hybrid cache invalidation");
+        }
+    }
 }
 
 #[async_trait]
-impl StateBackend for HybridBackend {
+impl<R: StateBackend> StateBackend for HybridBackend<R> {
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
         trace!(key, "Hybrid GET");
 
@@ -105,12 +270,30 @@ impl StateBackend for HybridBackend {
         self.remote.set(key, value.clone(), ttl).await?;
 
         // Update local cache with shorter TTL
-        let cache_ttl = Some(ttl.unwrap_or(self.cache_ttl).min(self.cache_ttl));
-        self.local.set(key, value, cache_ttl).await?;
+        self.local.set(key, value, self.local_ttl(ttl)).await?;
+        self.publish_change(key).await;
 
         Ok(())
     }
 
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool> {
+        trace!(key, ttl_secs = ?ttl.map(|d| d.as_secs()), "Hybrid SETNX");
+
+        // Only Redis can decide whether the key is absent across instances
+        let set = self.remote.set_if_absent(key, value, ttl).await?;
+        if set {
+            self.local.delete(key).await?;
+            self.publish_change(key).await;
+        }
+
+        Ok(set)
+    }
+
     async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64> {
         trace!(key, delta, "Hybrid INCREMENT");
 
@@ -119,6 +302,7 @@ impl StateBackend for HybridBackend {
 
         // Invalidate local cache (stale after increment)
         self.local.delete(key).await?;
+        self.publish_change(key).await;
 
         Ok(new_value)
     }
@@ -129,6 +313,7 @@ impl StateBackend for HybridBackend {
         // Delete from both
         self.remote.delete(key).await?;
         self.local.delete(key).await?;
+        self.publish_change(key).await;
 
         Ok(())
     }
@@ -151,6 +336,7 @@ impl StateBackend for HybridBackend {
             // Update local cache on successful CAS
             let cache_ttl = Some(self.cache_ttl);
             self.local.set(key, new_value, cache_ttl).await?;
+            self.publish_change(key).await;
         }
 
         Ok(success)
@@ -168,6 +354,7 @@ impl StateBackend for HybridBackend {
             if let Some(value) = self.local.get(key).await? {
                 self.local.set(key, value, cache_ttl).await?;
             }
+            self.publish_change(key).await;
         }
 
         Ok(success)
@@ -216,8 +403,8 @@ impl StateBackend for HybridBackend {
 
         // Update local cache
         for (key, value, ttl) in items {
-            let cache_ttl = Some(ttl.unwrap_or(self.cache_ttl).min(self.cache_ttl));
-            self.local.set(&key, value, cache_ttl).await?;
+            self.local.set(&key, value, self.local_ttl(ttl)).await?;
+            self.publish_change(&key).await;
         }
 
         Ok(())
@@ -229,6 +416,9 @@ impl StateBackend for HybridBackend {
         // Delete from both
         self.remote.mdel(keys).await?;
         self.local.mdel(keys).await?;
+        for key in keys {
+            self.publish_change(key).await;
+        }
 
         Ok(())
     }
@@ -245,6 +435,7 @@ impl StateBackend for HybridBackend {
 
         self.remote.flush().await?;
         self.local.flush().await?;
+        self.publish("f".to_string()).await;
 
         Ok(())
     }
@@ -333,4 +524,115 @@ mod tests {
 
         assert!(backend.health_check().await.is_ok());
     }
+
+    /// Hybrid cache over an in-process remote, which stands in for Redis and
+    /// for writes made by other instances.
+    fn over_memory(cache_ttl: Duration) -> (HybridBackend<InMemoryBackend>, InMemoryBackend) {
+        let remote = InMemoryBackend::new();
+        (
+            HybridBackend::with_remote(remote.clone(), cache_ttl).unwrap(),
+            remote,
+        )
+    }
+
+    #[test]
+    fn test_with_remote_outside_runtime_is_an_error() {
+        let result = HybridBackend::with_remote(InMemoryBackend::new(), Duration::from_secs(5));
+        assert!(matches!(result, Err(Error::Backend(_))));
+    }
+
+    #[tokio::test]
+    async fn test_read_through_populates_cache() {
+        let (backend, remote) = over_memory(Duration::from_secs(5));
+        remote.set("k", b"v".to_vec(), None).await.unwrap();
+
+        assert_eq!(backend.get("k").await.unwrap(), Some(b"v".to_vec()));
+        assert_eq!(backend.cache_size(), 1);
+
+        // Served locally while cached
+        remote.delete("k").await.unwrap();
+        assert_eq!(backend.get("k").await.unwrap(), Some(b"v".to_vec()));
+        assert!(backend.get("missing").await.unwrap().is_none());
+        assert_eq!(backend.cache_size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_through() {
+        let (backend, remote) = over_memory(Duration::from_secs(5));
+
+        backend.set("k", b"v".to_vec(), None).await.unwrap();
+        assert_eq!(remote.get("k").await.unwrap(), Some(b"v".to_vec()));
+        assert_eq!(backend.cache_size(), 1);
+
+        backend.delete("k").await.unwrap();
+        assert!(remote.get("k").await.unwrap().is_none());
+        assert_eq!(backend.cache_size(), 0);
+
+        assert_eq!(backend.increment("n", 2, None).await.unwrap(), 2);
+        assert_eq!(remote.get("n").await.unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_staleness_is_bounded_by_cache_ttl() {
+        let (backend, remote) = over_memory(Duration::from_millis(50));
+        remote.set("k", b"old".to_vec(), None).await.unwrap();
+        backend.get("k").await.unwrap();
+
+        // Another instance writes: stale within the window...
+        remote.set("k", b"new".to_vec(), None).await.unwrap();
+        assert_eq!(backend.get("k").await.unwrap(), Some(b"old".to_vec()));
+
+        // ...and fresh once it has passed
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert_eq!(backend.get("k").await.unwrap(), Some(b"new".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_local_ttl_never_outlives_remote_ttl() {
+        let (backend, _remote) = over_memory(Duration::from_secs(5));
+
+        backend
+            .set("k", b"v".to_vec(), Some(Duration::from_millis(30)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        assert!(backend.get("k").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_local_cache_is_bounded() {
+        let (backend, remote) = over_memory(Duration::from_secs(5));
+        let backend = backend.with_max_cached(2);
+        for key in ["a", "b", "c"] {
+            remote.set(key, b"v".to_vec(), None).await.unwrap();
+            backend.get(key).await.unwrap();
+        }
+
+        assert_eq!(backend.cache_size(), 2);
+        assert_eq!(backend.cache_stats().evictions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pubsub_invalidates_other_instances() {
+        let (Some(a), Some(b)) = (setup().await, setup().await) else {
+            return;
+        };
+        let (Ok(a), Ok(b)) = (
+            a.with_invalidation("invalidate").await,
+            b.with_invalidation("invalidate").await,
+        ) else {
+            return;
+        };
+
+        b.set("shared", b"v1".to_vec(), None).await.unwrap();
+        assert_eq!(a.get("shared").await.unwrap(), Some(b"v1".to_vec()));
+
+        b.set("shared", b"v2".to_vec(), None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Well within a's 5s cache TTL
+        assert_eq!(a.get("shared").await.unwrap(), Some(b"v2".to_vec()));
+        b.delete("shared").await.unwrap();
+    }
 }
@@ -1,25 +1,35 @@
 //! In-memory state backend implementation
 
-use crate::{Error, Result, StateBackend};
+use crate::{Error, EvictionPolicy, Result, StateBackend, StateConfig};
 use async_trait::async_trait;
 use dashmap::DashMap;
-use std::sync::Arc;
+use parking_lot::Mutex;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 use tokio::time::interval;
 use tracing::{debug, trace};
 
 /// Entry in the in-memory store
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct Entry {
     value: Vec<u8>,
     expires_at: Option<Instant>,
+    /// Logical time of the last read or write (LRU)
+    last_access: AtomicU64,
+    /// Reads and writes since insertion (LFU)
+    hits: AtomicU64,
 }
 
 impl Entry {
-    fn new(value: Vec<u8>, ttl: Option<Duration>) -> Self {
+    fn new(value: Vec<u8>, ttl: Option<Duration>, now: u64) -> Self {
         Self {
             value,
             expires_at: ttl.map(|d| Instant::now() + d),
+            last_access: AtomicU64::new(now),
+            hits: AtomicU64::new(1),
         }
     }
 
@@ -28,15 +38,97 @@ impl Entry {
             .map(|exp| Instant::now() > exp)
             .unwrap_or(false)
     }
+
+    fn touch(&self, now: u64) {
+        self.last_access.store(now, Ordering::Relaxed);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Eviction rank under `policy`; the lowest rank goes first.
+    fn rank(&self, policy: EvictionPolicy) -> (u64, u64) {
+        let last_access = self.last_access.load(Ordering::Relaxed);
+        match policy {
+            EvictionPolicy::Lru => (last_access, 0),
+            EvictionPolicy::Lfu => (self.hits.load(Ordering::Relaxed), last_access),
+        }
+    }
+}
+
+/// Size and eviction counters of an [`InMemoryBackend`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InMemoryStats {
+    /// Keys currently stored, including expired ones not yet removed
+    pub entries: usize,
+    /// Configured cap (None = unbounded)
+    pub max_entries: Option<usize>,
+    /// Live keys evicted to stay within the cap
+    pub evictions: u64,
+    /// Expired keys removed, by the sweep or on access
+    pub expired: u64,
+}
+
+/// Eviction order of a capped store, kept beside it so making room doesn't
+/// scan the store.
+///
+/// Records are pushed when an entry is written, and when an entry without a
+/// TTL is touched. They go stale when the entry changes or goes away; stale
+/// records are skipped when popped and dropped when the heaps are rebuilt.
+#[derive(Debug, Default)]
+struct EvictionIndex {
+    /// Entries with a TTL, soonest expiry first
+    expiries: BinaryHeap<Reverse<(Instant, String)>>,
+    /// Entries without a TTL, lowest eviction rank first
+    ranks: BinaryHeap<Reverse<((u64, u64), String)>>,
+}
+
+impl EvictionIndex {
+    fn len(&self) -> usize {
+        self.expiries.len() + self.ranks.len()
+    }
+
+    fn push(&mut self, key: String, entry: &Entry, policy: EvictionPolicy) {
+        match entry.expires_at {
+            Some(expires_at) => self.expiries.push(Reverse((expires_at, key))),
+            None => self.ranks.push(Reverse((entry.rank(policy), key))),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.expiries.clear();
+        self.ranks.clear();
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// Logical clock ordering accesses for LRU
+    clock: AtomicU64,
+    evictions: AtomicU64,
+    expired: AtomicU64,
 }
 
 /// In-memory state backend
 ///
 /// Fast, zero dependencies, but single-instance only.
 /// Perfect for development, testing, and single-node deployments.
+///
+/// Unbounded by default. With [`with_max_entries`](Self::with_max_entries)
+/// inserting a new key into a full store first drops expired keys, then
+/// evicts a key without a TTL by the configured [`EvictionPolicy`]. Keys
+/// with a TTL, such as rate-limit counters, are never evicted before they
+/// expire: when only those are left the write fails with
+/// [`Error::CapacityExceeded`], unless
+/// [`with_ttl_eviction`](Self::with_ttl_eviction) is set. Concurrent inserts may overshoot the cap
+/// briefly; the next insert makes up for it.
 #[derive(Debug, Clone)]
 pub struct InMemoryBackend {
     store: Arc<DashMap<String, Entry>>,
+    index: Arc<Mutex<EvictionIndex>>,
+    counters: Arc<Counters>,
+    max_entries: Option<usize>,
+    eviction: EvictionPolicy,
+    /// Evict live keys with a TTL once no key without one is left
+    evict_ttl_keys: bool,
 }
 
 impl InMemoryBackend {
@@ -44,6 +136,11 @@ impl InMemoryBackend {
     pub fn new() -> Self {
         Self {
             store: Arc::new(DashMap::new()),
+            index: Arc::default(),
+            counters: Arc::new(Counters::default()),
+            max_entries: None,
+            eviction: EvictionPolicy::default(),
+            evict_ttl_keys: false,
         }
     }
 
@@ -52,26 +149,61 @@ impl InMemoryBackend {
     /// Spawns a tokio task that periodically removes expired entries.
     pub fn with_cleanup(cleanup_interval: Duration) -> Self {
         let backend = Self::new();
-        let store = backend.store.clone();
+        backend.spawn_cleanup(cleanup_interval);
+        backend
+    }
+
+    /// Create from a [`StateConfig`]: capped per `max_entries`/`eviction`,
+    /// with expired entries swept every `cleanup_interval`.
+    pub fn from_config(config: &StateConfig) -> Self {
+        let mut backend = Self::new();
+        if let Some(max_entries) = config.max_entries {
+            backend = backend.with_max_entries(max_entries, config.eviction);
+        }
+        backend.spawn_cleanup(config.cleanup_interval);
+        backend
+    }
+
+    /// Cap the store at `max_entries` keys, evicting by `policy` when full.
+    pub fn with_max_entries(mut self, max_entries: usize, policy: EvictionPolicy) -> Self {
+        self.max_entries = Some(max_entries.max(1));
+        self.eviction = policy;
+        self
+    }
+
+    /// When full, also evict live keys with a TTL, soonest expiry first, once
+    /// no key without a TTL is left. For caches, whose entries can be fetched
+    /// again.
+    pub fn with_ttl_eviction(mut self) -> Self {
+        self.evict_ttl_keys = true;
+        self
+    }
+
+    /// Sweep expired entries every `cleanup_interval`, until the backend
+    /// (and all its clones) is dropped.
+    fn spawn_cleanup(&self, cleanup_interval: Duration) {
+        let store = Arc::downgrade(&self.store);
+        let counters = self.counters.clone();
 
         tokio::spawn(async move {
             let mut ticker = interval(cleanup_interval);
             loop {
                 ticker.tick().await;
-                Self::cleanup_expired(&store);
+                let Some(store) = Weak::upgrade(&store) else {
+                    break;
+                };
+                Self::cleanup_expired(&store, &counters);
             }
         });
-
-        backend
     }
 
     /// Manually trigger cleanup of expired entries
     pub fn cleanup(&self) {
-        Self::cleanup_expired(&self.store);
+        Self::cleanup_expired(&self.store, &self.counters);
     }
 
     /// Internal cleanup implementation
-    fn cleanup_expired(store: &DashMap<String, Entry>) {
+    fn cleanup_expired(store: &DashMap<String, Entry>, counters: &Counters) {
         let mut removed = 0;
         store.retain(|_, entry| {
             if entry.is_expired() {
@@ -83,6 +215,7 @@ impl InMemoryBackend {
         });
 
         if removed > 0 {
+            counters.expired.fetch_add(removed, Ordering::Relaxed);
             debug!(removed, "Cleaned up expired entries");
         }
     }
@@ -96,6 +229,102 @@ impl InMemoryBackend {
     pub fn is_empty(&self) -> bool {
         self.store.is_empty()
     }
+
+    /// Current size and eviction counters
+    pub fn stats(&self) -> InMemoryStats {
+        InMemoryStats {
+            entries: self.store.len(),
+            max_entries: self.max_entries,
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            expired: self.counters.expired.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Next tick of the logical access clock
+    fn tick(&self) -> u64 {
+        self.counters.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Record `key`'s current place in the eviction order. Call it without
+    /// holding a guard on the store.
+    fn track(&self, key: &str) {
+        if self.max_entries.is_none() {
+            return;
+        }
+        let mut index = self.index.lock();
+        if let Some(entry) = self.store.get(key) {
+            index.push(key.to_string(), &entry, self.eviction);
+        }
+        // Rebuild once stale records outnumber live ones.
+        if index.len() > 2 * self.store.len() + 64 {
+            index.clear();
+            for entry in self.store.iter() {
+                index.push(entry.key().clone(), entry.value(), self.eviction);
+            }
+        }
+    }
+
+    /// Make room for `key` if it is new and the store is full: drop expired
+    /// keys, then evict keys without a TTL.
+    fn admit(&self, key: &str) -> Result<()> {
+        let Some(max_entries) = self.max_entries else {
+            return Ok(());
+        };
+        if self.store.len() < max_entries || self.store.contains_key(key) {
+            return Ok(());
+        }
+
+        let mut index = self.index.lock();
+        let now = Instant::now();
+        while self.store.len() >= max_entries {
+            if let Some(Reverse((expires_at, _))) = index.expiries.peek() {
+                if *expires_at <= now {
+                    let Some(Reverse((_, victim))) = index.expiries.pop() else {
+                        break;
+                    };
+                    if self
+                        .store
+                        .remove_if(&victim, |_, entry| entry.is_expired())
+                        .is_some()
+                    {
+                        self.counters.expired.fetch_add(1, Ordering::Relaxed);
+                    } else if let Some(expires_at) =
+                        self.store.get(&victim).and_then(|entry| entry.expires_at)
+                    {
+                        // Extended since it was recorded
+                        index.expiries.push(Reverse((expires_at, victim)));
+                    }
+                    continue;
+                }
+            }
+
+            let Some(Reverse((rank, victim))) = index.ranks.pop() else {
+                if self.evict_ttl_keys {
+                    if let Some(Reverse((expires_at, victim))) = index.expiries.pop() {
+                        let evicted = self
+                            .store
+                            .remove_if(&victim, |_, entry| entry.expires_at == Some(expires_at));
+                        if evicted.is_some() {
+                            self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+                            trace!(key = %victim, "InMemory EVICT");
+                        }
+                        continue;
+                    }
+                }
+                return Err(Error::CapacityExceeded(format!(
+                    "{max_entries} keys, none of them evictable"
+                )));
+            };
+            let evicted = self.store.remove_if(&victim, |_, entry| {
+                entry.expires_at.is_none() && entry.rank(self.eviction) == rank
+            });
+            if evicted.is_some() {
+                self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+                trace!(key = %victim, "InMemory EVICT");
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for InMemoryBackend {
@@ -112,10 +341,19 @@ impl StateBackend for InMemoryBackend {
         if let Some(entry) = self.store.get(key) {
             if entry.is_expired() {
                 drop(entry); // Release read lock
-                self.store.remove(key);
+                if self.store.remove(key).is_some() {
+                    self.counters.expired.fetch_add(1, Ordering::Relaxed);
+                }
                 return Ok(None);
             }
-            return Ok(Some(entry.value.clone()));
+            entry.touch(self.tick());
+            let value = entry.value.clone();
+            let untimed = entry.expires_at.is_none();
+            drop(entry);
+            if untimed {
+                self.track(key);
+            }
+            return Ok(Some(value));
         }
 
         Ok(None)
@@ -124,20 +362,55 @@ impl StateBackend for InMemoryBackend {
     async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
         trace!(key, ttl_secs = ?ttl.map(|d| d.as_secs()), "InMemory SET");
 
-        let entry = Entry::new(value, ttl);
+        self.admit(key)?;
+        let entry = Entry::new(value, ttl, self.tick());
         self.store.insert(key.to_string(), entry);
+        self.track(key);
 
         Ok(())
     }
 
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool> {
+        trace!(key, ttl_secs = ?ttl.map(|d| d.as_secs()), "InMemory SETNX");
+
+        self.admit(key)?;
+        let entry = Entry::new(value, ttl, self.tick());
+        // The shard lock is released before tracking the entry.
+        match self.store.entry(key.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(mut held) => {
+                if !held.get().is_expired() {
+                    return Ok(false);
+                }
+                held.insert(entry);
+            }
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                vacant.insert(entry);
+            }
+        }
+        self.track(key);
+
+        Ok(true)
+    }
+
     async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64> {
         trace!(key, delta, "InMemory INCREMENT");
 
+        self.admit(key)?;
         let mut new_value = delta;
+        // A counter still counting within its TTL keeps its eviction record;
+        // an extended TTL is picked up when the record comes due.
+        let mut track = true;
+        let now = self.tick();
 
         self.store
             .entry(key.to_string())
             .and_modify(|entry| {
+                entry.touch(now);
                 if !entry.is_expired() {
                     // Parse existing value and increment
                     if let Ok(current) = std::str::from_utf8(&entry.value) {
@@ -149,6 +422,7 @@ impl StateBackend for InMemoryBackend {
                             if let Some(ttl) = ttl {
                                 entry.expires_at = Some(Instant::now() + ttl);
                             }
+                            track = entry.expires_at.is_none();
                             return;
                         }
                     }
@@ -158,7 +432,11 @@ impl StateBackend for InMemoryBackend {
                 entry.value = delta.to_string().into_bytes();
                 entry.expires_at = ttl.map(|d| Instant::now() + d);
             })
-            .or_insert_with(|| Entry::new(delta.to_string().into_bytes(), ttl));
+            .or_insert_with(|| Entry::new(delta.to_string().into_bytes(), ttl, now));
+
+        if track {
+            self.track(key);
+        }
 
         Ok(new_value)
     }
@@ -183,7 +461,10 @@ impl StateBackend for InMemoryBackend {
             }
 
             if entry.value == expected {
+                entry.touch(self.tick());
                 entry.value = new_value;
+                drop(entry);
+                self.track(key);
                 return Ok(true);
             }
             return Ok(false);
@@ -198,6 +479,8 @@ impl StateBackend for InMemoryBackend {
         if let Some(mut entry) = self.store.get_mut(key) {
             if !entry.is_expired() {
                 entry.expires_at = Some(Instant::now() + ttl);
+                drop(entry);
+                self.track(key);
                 return Ok(true);
             }
         }
@@ -227,6 +510,7 @@ impl StateBackend for InMemoryBackend {
     async fn flush(&self) -> Result<()> {
         debug!("InMemory FLUSH - clearing all keys");
         self.store.clear();
+        self.index.lock().clear();
         Ok(())
     }
 
@@ -307,6 +591,29 @@ mod tests {
         assert_eq!(val3, 4);
     }
 
+    #[tokio::test]
+    async fn test_set_if_absent() {
+        let backend = InMemoryBackend::new();
+
+        assert!(backend
+            .set_if_absent("lock", b"a".to_vec(), Some(Duration::from_millis(50)))
+            .await
+            .unwrap());
+        // A held key keeps its value and TTL.
+        assert!(!backend
+            .set_if_absent("lock", b"b".to_vec(), Some(Duration::from_secs(60)))
+            .await
+            .unwrap());
+        assert_eq!(backend.get("lock").await.unwrap(), Some(b"a".to_vec()));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend
+            .set_if_absent("lock", b"c".to_vec(), None)
+            .await
+            .unwrap());
+        assert_eq!(backend.get("lock").await.unwrap(), Some(b"c".to_vec()));
+    }
+
     #[tokio::test]
     async fn test_delete() {
         let backend = InMemoryBackend::new();
@@ -436,4 +743,141 @@ mod tests {
         let backend = InMemoryBackend::new();
         assert!(backend.health_check().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        let backend = InMemoryBackend::new().with_max_entries(2, EvictionPolicy::Lru);
+
+        backend.set("a", b"1".to_vec(), None).await.unwrap();
+        backend.set("b", b"2".to_vec(), None).await.unwrap();
+        backend.get("a").await.unwrap();
+        backend.set("c", b"3".to_vec(), None).await.unwrap();
+
+        // "b" was least recently used
+        assert_eq!(backend.len(), 2);
+        assert!(backend.get("b").await.unwrap().is_none());
+        assert!(backend.get("a").await.unwrap().is_some());
+        assert!(backend.get("c").await.unwrap().is_some());
+        assert_eq!(backend.stats().evictions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lfu_eviction() {
+        let backend = InMemoryBackend::new().with_max_entries(2, EvictionPolicy::Lfu);
+
+        backend.set("a", b"1".to_vec(), None).await.unwrap();
+        backend.set("b", b"2".to_vec(), None).await.unwrap();
+        backend.get("a").await.unwrap();
+        backend.get("a").await.unwrap();
+        backend.get("b").await.unwrap();
+        backend.increment("n", 1, None).await.unwrap();
+
+        // "b" was used less often than "a"
+        assert!(backend.get("b").await.unwrap().is_none());
+        assert!(backend.get("a").await.unwrap().is_some());
+        assert_eq!(backend.get("n").await.unwrap(), Some(b"1".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_cap_drops_expired_before_evicting() {
+        let backend = InMemoryBackend::new().with_max_entries(2, EvictionPolicy::Lru);
+
+        backend
+            .set("short", b"1".to_vec(), Some(Duration::from_millis(10)))
+            .await
+            .unwrap();
+        backend.set("b", b"2".to_vec(), None).await.unwrap();
+        sleep(Duration::from_millis(30)).await;
+        backend.set("c", b"3".to_vec(), None).await.unwrap();
+
+        assert!(backend.get("b").await.unwrap().is_some());
+        let stats = backend.stats();
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.expired, 1);
+    }
+
+    #[tokio::test]
+    async fn test_live_ttl_keys_are_not_evicted() {
+        let backend = InMemoryBackend::new().with_max_entries(2, EvictionPolicy::Lru);
+        let ttl = Some(Duration::from_secs(60));
+
+        backend.increment("a", 1, ttl).await.unwrap();
+        backend.increment("b", 1, ttl).await.unwrap();
+        let err = backend.increment("c", 1, ttl).await.unwrap_err();
+        assert!(matches!(err, Error::CapacityExceeded(_)), "{err}");
+
+        // Existing counters keep counting.
+        assert_eq!(backend.increment("a", 1, ttl).await.unwrap(), 2);
+        assert_eq!(backend.increment("b", 1, ttl).await.unwrap(), 2);
+        assert_eq!(backend.stats().evictions, 0);
+    }
+
+    #[tokio::test]
+    async fn test_untimed_keys_make_room_for_counters() {
+        let backend = InMemoryBackend::new().with_max_entries(2, EvictionPolicy::Lru);
+        let ttl = Some(Duration::from_secs(60));
+
+        backend.increment("counter", 1, ttl).await.unwrap();
+        backend.set("plain", b"1".to_vec(), None).await.unwrap();
+        // Many touches leave one live record per key.
+        for _ in 0..200 {
+            backend.get("plain").await.unwrap();
+            backend.increment("counter", 1, ttl).await.unwrap();
+        }
+        backend.increment("new", 1, ttl).await.unwrap();
+
+        assert!(backend.get("plain").await.unwrap().is_none());
+        assert_eq!(backend.increment("counter", 1, ttl).await.unwrap(), 202);
+        assert_eq!(backend.stats().evictions, 1);
+        assert!(backend.index.lock().len() <= 2 * backend.len() + 64);
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_does_not_evict() {
+        let backend = InMemoryBackend::new().with_max_entries(2, EvictionPolicy::Lru);
+
+        backend.set("a", b"1".to_vec(), None).await.unwrap();
+        backend.set("b", b"2".to_vec(), None).await.unwrap();
+        backend.set("a", b"3".to_vec(), None).await.unwrap();
+
+        assert_eq!(backend.len(), 2);
+        assert_eq!(backend.stats().evictions, 0);
+    }
+
+    #[tokio::test]
+    async fn test_background_sweep() {
+        let backend = InMemoryBackend::with_cleanup(Duration::from_millis(20));
+
+        backend
+            .set("key1", b"val1".to_vec(), Some(Duration::from_millis(30)))
+            .await
+            .unwrap();
+        backend.set("key2", b"val2".to_vec(), None).await.unwrap();
+
+        sleep(Duration::from_millis(100)).await;
+
+        // Swept without being read
+        assert_eq!(backend.len(), 1);
+        assert_eq!(backend.stats().expired, 1);
+    }
+
+    #[tokio::test]
+    async fn test_from_config() {
+        let config = StateConfig {
+            max_entries: Some(10),
+            eviction: EvictionPolicy::Lfu,
+            ..Default::default()
+        };
+        let backend = InMemoryBackend::from_config(&config);
+
+        assert_eq!(
+            backend.stats(),
+            InMemoryStats {
+                entries: 0,
+                max_entries: Some(10),
+                evictions: 0,
+                expired: 0,
+            }
+        );
+    }
 }
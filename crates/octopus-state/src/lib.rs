@@ -5,6 +5,7 @@
 //! - Session storage
 //! - Circuit breaker state
 //! - Distributed locks
+//! - Replay-protection nonces
 //!
 //! ## Backends
 //!
@@ -46,6 +47,7 @@ mod backend;
 mod config;
 mod error;
 mod inmemory;
+mod nonce;
 
 #[cfg(feature = "redis-backend")]
 mod redis_backend;
@@ -56,13 +58,14 @@ mod postgres_backend;
 #[cfg(feature = "hybrid")]
 mod hybrid;
 
-pub use backend::StateBackend;
-pub use config::{BackendConfig, StateConfig};
+pub use backend::{BackendHealth, BackendHealthSource, HealthStatus, StateBackend};
+pub use config::{BackendConfig, EvictionPolicy, StateConfig};
 pub use error::{Error, Result};
-pub use inmemory::InMemoryBackend;
+pub use inmemory::{InMemoryBackend, InMemoryStats};
+pub use nonce::{NonceCache, MAX_NONCE_LEN};
 
 #[cfg(feature = "redis-backend")]
-pub use redis_backend::RedisBackend;
+pub use redis_backend::{RedisBackend, RedisOptions};
 
 #[cfg(feature = "postgres-backend")]
 pub use postgres_backend::PostgresBackend;
@@ -73,9 +76,10 @@ pub use hybrid::HybridBackend;
 /// Re-export commonly used types
 pub mod prelude {
     pub use crate::backend::StateBackend;
-    pub use crate::config::{BackendConfig, StateConfig};
+    pub use crate::config::{BackendConfig, EvictionPolicy, StateConfig};
     pub use crate::error::{Error, Result};
     pub use crate::inmemory::InMemoryBackend;
+    pub use crate::nonce::NonceCache;
 
     #[cfg(feature = "redis-backend")]
     pub use crate::redis_backend::RedisBackend;
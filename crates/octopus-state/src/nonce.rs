@@ -0,0 +1,85 @@
+//! Replay protection for single-use request nonces
+//!
+//! A [`NonceCache`] records each nonce it accepts for a fixed TTL (the
+//! signature timestamp window) and rejects any reuse within it. Entries are
+//! written with the backend's own TTL, so they expire on their own; with
+//! [`InMemoryBackend::with_cleanup`](crate::InMemoryBackend::with_cleanup)
+//! or Redis nothing accumulates past the window.
+
+use crate::{Result, StateBackend};
+use std::time::Duration;
+
+/// Longest nonce accepted, bounding the memory a client can pin per request.
+pub const MAX_NONCE_LEN: usize = 128;
+
+/// Records single-use nonces in a [`StateBackend`].
+#[derive(Debug, Clone)]
+pub struct NonceCache<B> {
+    backend: B,
+    ttl: Duration,
+}
+
+impl<B: StateBackend> NonceCache<B> {
+    /// Create a cache remembering nonces for `ttl`, which should cover every
+    /// time a signed request's timestamp is accepted: twice the maximum
+    /// clock skew, as a request may be dated ahead by that much.
+    pub fn new(backend: B, ttl: Duration) -> Self {
+        Self { backend, ttl }
+    }
+
+    /// How long accepted nonces are remembered.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Record `nonce` for `scope` (e.g. the signing key id). Returns `true`
+    /// on first use and `false` if it was already used within the TTL. An
+    /// empty nonce or one over [`MAX_NONCE_LEN`] is never accepted; errors
+    /// are the backend's own.
+    ///
+    /// Uses the backend's atomic increment, so concurrent replays of the
+    /// same nonce admit exactly one request, also across gateway instances
+    /// sharing a distributed backend.
+    pub async fn check_and_record(&self, scope: &str, nonce: &str) -> Result<bool> {
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Ok(false);
+        }
+        let key = format!("nonce:{scope}:{nonce}");
+        let uses = self.backend.increment(&key, 1, Some(self.ttl)).await?;
+        Ok(uses == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryBackend;
+
+    #[tokio::test]
+    async fn test_nonce_is_single_use_within_ttl() {
+        let cache = NonceCache::new(InMemoryBackend::new(), Duration::from_secs(300));
+
+        assert!(cache.check_and_record("key-1", "n-abc").await.unwrap());
+        assert!(!cache.check_and_record("key-1", "n-abc").await.unwrap());
+        // Nonces are scoped: another key may use the same value.
+        assert!(cache.check_and_record("key-2", "n-abc").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_expires_after_ttl() {
+        let cache = NonceCache::new(InMemoryBackend::new(), Duration::from_millis(50));
+
+        assert!(cache.check_and_record("key-1", "n-abc").await.unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(cache.check_and_record("key-1", "n-abc").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_length_is_bounded() {
+        let cache = NonceCache::new(InMemoryBackend::new(), Duration::from_secs(300));
+
+        assert!(!cache.check_and_record("key-1", "").await.unwrap());
+        let long = "x".repeat(MAX_NONCE_LEN + 1);
+        assert!(!cache.check_and_record("key-1", &long).await.unwrap());
+    }
+}
@@ -132,6 +132,40 @@ impl StateBackend for PostgresBackend {
         Ok(())
     }
 
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool> {
+        trace!(key, ttl_secs = ?ttl.map(|d| d.as_secs()), "PostgreSQL SET IF ABSENT");
+
+        let expires_at = ttl.map(|d| chrono::Utc::now() + chrono::Duration::from_std(d).unwrap());
+
+        // An expired row counts as absent and is overwritten
+        let query = format!(
+            r#"
+            INSERT INTO {0} (key, value, expires_at, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (key) DO UPDATE
+            SET value = EXCLUDED.value,
+                expires_at = EXCLUDED.expires_at,
+                updated_at = NOW()
+            WHERE {0}.expires_at IS NOT NULL AND {0}.expires_at <= NOW()
+            "#,
+            self.table_name
+        );
+
+        let result = sqlx::query(&query)
+            .bind(key)
+            .bind(value)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
     async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64> {
         trace!(key, delta, "PostgreSQL INCREMENT");
 
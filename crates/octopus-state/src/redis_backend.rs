@@ -1,53 +1,203 @@
 //! Redis state backend implementation
 
-use crate::{Error, Result, StateBackend};
+use crate::{
+    BackendConfig, BackendHealth, BackendHealthSource, Error, HealthStatus, Result, StateBackend,
+};
 use async_trait::async_trait;
-use redis::{aio::ConnectionManager, AsyncCommands};
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{AsyncCommands, RedisError, RedisResult};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
+
+/// Connection, reconnect and timeout settings of a [`RedisBackend`]
+#[derive(Debug, Clone)]
+pub struct RedisOptions {
+    /// Multiplexed connections to open; operations are spread across them
+    pub pool_size: usize,
+
+    /// Deadline for a single operation, reconnect included
+    pub op_timeout: Duration,
+
+    /// Deadline for establishing a connection
+    pub connect_timeout: Duration,
+
+    /// Reconnect attempts, with exponential backoff, before an operation fails
+    pub reconnect_retries: usize,
+
+    /// Upper bound on the delay between reconnect attempts
+    pub reconnect_max_delay: Duration,
+}
+
+impl Default for RedisOptions {
+    fn default() -> Self {
+        Self {
+            pool_size: 4,
+            op_timeout: Duration::from_secs(1),
+            connect_timeout: Duration::from_secs(5),
+            reconnect_retries: 6,
+            reconnect_max_delay: Duration::from_secs(2),
+        }
+    }
+}
 
 /// Redis state backend
 ///
 /// Distributed, persistent, production-ready backend using Redis.
 /// Perfect for multi-instance deployments and high-traffic production.
+///
+/// Commands are multiplexed over a small pool of connections that reconnect
+/// with backoff when dropped. Every operation runs under
+/// [`RedisOptions::op_timeout`], so a stalled Redis fails requests with
+/// [`Error::Timeout`] instead of hanging them; [`health`](Self::health)
+/// reports whether recent operations reached Redis.
 #[derive(Clone)]
 pub struct RedisBackend {
-    client: ConnectionManager,
-    prefix: Option<String>,
+    connections: Arc<[ConnectionManager]>,
+    next: Arc<AtomicUsize>,
+    health: Arc<HealthState>,
+    op_timeout: Duration,
+    /// Kept to open dedicated (pub/sub) connections; may carry credentials
+    #[cfg(feature = "hybrid")]
+    url: String,
+    pub(crate) prefix: Option<String>,
 }
 
 impl std::fmt::Debug for RedisBackend {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("RedisBackend")
             .field("prefix", &self.prefix)
+            .field("pool_size", &self.connections.len())
+            .field("op_timeout", &self.op_timeout)
             .finish()
     }
 }
 
+impl BackendHealthSource for RedisBackend {
+    fn health(&self) -> BackendHealth {
+        RedisBackend::health(self)
+    }
+}
+
 impl RedisBackend {
     /// Create a new Redis backend
     pub async fn new(url: &str) -> Result<Self> {
-        let client = redis::Client::open(url).map_err(|e| Error::Connection(e.to_string()))?;
+        Self::with_options(url, RedisOptions::default()).await
+    }
 
-        let connection_manager = ConnectionManager::new(client)
-            .await
-            .map_err(|e| Error::Connection(e.to_string()))?;
+    /// Create with key prefix for namespacing
+    pub async fn with_prefix(url: &str, prefix: impl Into<String>) -> Result<Self> {
+        let mut backend = Self::new(url).await?;
+        backend.prefix = Some(prefix.into());
+        Ok(backend)
+    }
+
+    /// Create with explicit connection settings
+    pub async fn with_options(url: &str, options: RedisOptions) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(|e| Error::Connection(e.to_string()))?;
+        let config = ConnectionManagerConfig::new()
+            .set_connection_timeout(options.connect_timeout)
+            .set_number_of_retries(options.reconnect_retries)
+            .set_max_delay(options.reconnect_max_delay.as_millis() as u64);
+
+        let mut connections = Vec::with_capacity(options.pool_size.max(1));
+        for _ in 0..options.pool_size.max(1) {
+            let connection = ConnectionManager::new_with_config(client.clone(), config.clone())
+                .await
+                .map_err(|e| Error::Connection(e.to_string()))?;
+            connections.push(connection);
+        }
 
-        debug!(url, "Redis backend connected");
+        debug!(pool_size = connections.len(), "Redis backend connected");
 
         Ok(Self {
-            client: connection_manager,
+            connections: connections.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+            health: Arc::new(HealthState::default()),
+            op_timeout: options.op_timeout,
+            #[cfg(feature = "hybrid")]
+            url: url.to_string(),
             prefix: None,
         })
     }
 
-    /// Create with key prefix for namespacing
-    pub async fn with_prefix(url: &str, prefix: impl Into<String>) -> Result<Self> {
-        let mut backend = Self::new(url).await?;
-        backend.prefix = Some(prefix.into());
+    /// Create from a [`BackendConfig::Redis`]
+    pub async fn from_config(config: &BackendConfig) -> Result<Self> {
+        let BackendConfig::Redis {
+            url,
+            pool_size,
+            timeout,
+            op_timeout,
+            prefix,
+        } = config
+        else {
+            return Err(Error::InvalidConfig(
+                "expected a redis backend configuration".to_string(),
+            ));
+        };
+
+        let options = RedisOptions {
+            pool_size: *pool_size as usize,
+            op_timeout: *op_timeout,
+            connect_timeout: *timeout,
+            ..Default::default()
+        };
+        let mut backend = Self::with_options(url, options).await?;
+        backend.prefix = prefix.clone();
         Ok(backend)
     }
 
+    /// Whether recent operations reached Redis
+    pub fn health(&self) -> BackendHealth {
+        self.health.snapshot()
+    }
+
+    /// Increment several counters in one atomic round trip
+    ///
+    /// Each entry is `(key, delta, ttl)` as for [`StateBackend::increment`];
+    /// the new values are returned in the same order.
+    pub async fn increment_many(
+        &self,
+        ops: &[(String, i64, Option<Duration>)],
+    ) -> Result<Vec<i64>> {
+        trace!(count = ops.len(), "Redis INCRBY (pipelined)");
+
+        if ops.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (key, delta, ttl) in ops {
+            let key = self.key(key);
+            pipe.incr(&key, *delta);
+            if let Some(ttl) = ttl {
+                pipe.expire(&key, ttl.as_secs() as i64).ignore();
+            }
+        }
+
+        let mut conn = self.conn();
+        self.run("INCRBY", async move { pipe.query_async(&mut conn).await })
+            .await
+    }
+
+    /// Next connection of the pool
+    fn conn(&self) -> ConnectionManager {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        self.connections[i].clone()
+    }
+
+    /// Run `op` under the operation timeout, recording its outcome
+    async fn run<T>(
+        &self,
+        op: &'static str,
+        fut: impl Future<Output = RedisResult<T>>,
+    ) -> Result<T> {
+        guarded(&self.health, self.op_timeout, op, fut).await
+    }
+
     /// Add prefix to key if configured
     fn key(&self, key: &str) -> String {
         match &self.prefix {
@@ -56,6 +206,31 @@ impl RedisBackend {
         }
     }
 
+    /// Publish `message` on `channel` (namespaced like keys)
+    #[cfg(feature = "hybrid")]
+    pub(crate) async fn publish(&self, channel: &str, message: String) -> Result<()> {
+        let channel = self.key(channel);
+        let mut conn = self.conn();
+        self.run(
+            "PUBLISH",
+            async move { conn.publish(&channel, message).await },
+        )
+        .await
+    }
+
+    /// Open a dedicated connection subscribed to `channel` (namespaced like keys)
+    #[cfg(feature = "hybrid")]
+    pub(crate) async fn subscribe(&self, channel: &str) -> Result<redis::aio::PubSub> {
+        let client =
+            redis::Client::open(self.url.as_str()).map_err(|e| Error::Connection(e.to_string()))?;
+        let mut pubsub = client
+            .get_async_pubsub()
+            .await
+            .map_err(|e| Error::Connection(e.to_string()))?;
+        pubsub.subscribe(self.key(channel)).await?;
+        Ok(pubsub)
+    }
+
     /// Remove prefix from key if configured
     fn unprefix(&self, key: &str) -> String {
         match &self.prefix {
@@ -70,59 +245,152 @@ impl RedisBackend {
     }
 }
 
+/// Connection failures since the last successful operation
+#[derive(Debug, Default)]
+struct HealthState {
+    consecutive_failures: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl HealthState {
+    fn record_success(&self) {
+        if self.consecutive_failures.swap(0, Ordering::Relaxed) > 0 {
+            debug!("Redis connection recovered");
+        }
+    }
+
+    fn record_failure(&self, error: String) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures == 1 {
+            warn!(%error, "Redis connection degraded");
+        }
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    }
+
+    fn snapshot(&self) -> BackendHealth {
+        let consecutive_failures = self.consecutive_failures.load(Ordering::Relaxed);
+        BackendHealth {
+            status: if consecutive_failures == 0 {
+                HealthStatus::Connected
+            } else {
+                HealthStatus::Degraded
+            },
+            consecutive_failures,
+            last_error: self
+                .last_error
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        }
+    }
+}
+
+/// Await `fut` for at most `timeout`. Timeouts and connection errors mark the
+/// backend degraded; any reply from Redis, errors included, means it is reachable.
+async fn guarded<T>(
+    health: &HealthState,
+    timeout: Duration,
+    op: &'static str,
+    fut: impl Future<Output = RedisResult<T>>,
+) -> Result<T> {
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(Ok(value)) => {
+            health.record_success();
+            Ok(value)
+        }
+        Ok(Err(e)) if is_connection_error(&e) => {
+            health.record_failure(e.to_string());
+            Err(Error::Connection(e.to_string()))
+        }
+        Ok(Err(e)) => {
+            health.record_success();
+            Err(e.into())
+        }
+        Err(_) => {
+            let error = format!("Redis {op} timed out after {timeout:?}");
+            health.record_failure(error.clone());
+            Err(Error::Timeout(error))
+        }
+    }
+}
+
+fn is_connection_error(e: &RedisError) -> bool {
+    e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout()
+}
+
 #[async_trait]
 impl StateBackend for RedisBackend {
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
         trace!(key, "Redis GET");
 
         let key = self.key(key);
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
 
-        let result: Option<Vec<u8>> = conn.get(&key).await?;
-
-        Ok(result)
+        self.run("GET", async move { conn.get(&key).await }).await
     }
 
     async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
         trace!(key, ttl_secs = ?ttl.map(|d| d.as_secs()), "Redis SET");
 
         let key = self.key(key);
-        let mut conn = self.client.clone();
-
-        if let Some(ttl) = ttl {
-            conn.set_ex::<_, _, ()>(&key, value, ttl.as_secs()).await?;
-        } else {
-            conn.set::<_, _, ()>(&key, value).await?;
-        }
+        let mut conn = self.conn();
 
-        Ok(())
+        self.run("SET", async move {
+            match ttl {
+                Some(ttl) => conn.set_ex(&key, value, ttl.as_secs()).await,
+                None => conn.set(&key, value).await,
+            }
+        })
+        .await
     }
 
-    async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64> {
-        trace!(key, delta, "Redis INCRBY");
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool> {
+        trace!(key, ttl_secs = ?ttl.map(|d| d.as_secs()), "Redis SET NX");
 
         let key = self.key(key);
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
+
+        // SET NX replies OK when set and nil when the key exists
+        let reply: Option<String> = self
+            .run("SET NX", async move {
+                let mut cmd = redis::cmd("SET");
+                cmd.arg(&key).arg(value).arg("NX");
+                if let Some(ttl) = ttl {
+                    cmd.arg("PX").arg(ttl.as_millis().max(1) as u64);
+                }
+                cmd.query_async(&mut conn).await
+            })
+            .await?;
 
-        let new_value: i64 = conn.incr(&key, delta).await?;
+        Ok(reply.is_some())
+    }
 
-        // Set TTL if provided and this is a new key
-        if let Some(ttl) = ttl {
-            conn.expire::<_, ()>(&key, ttl.as_secs() as i64).await?;
-        }
+    async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64> {
+        trace!(key, delta, "Redis INCRBY");
 
-        Ok(new_value)
+        // INCRBY and EXPIRE in one atomic round trip
+        let values = self
+            .increment_many(&[(key.to_string(), delta, ttl)])
+            .await?;
+
+        values
+            .first()
+            .copied()
+            .ok_or_else(|| Error::Backend("empty INCRBY reply".to_string()))
     }
 
     async fn delete(&self, key: &str) -> Result<()> {
         trace!(key, "Redis DEL");
 
         let key = self.key(key);
-        let mut conn = self.client.clone();
-
-        let _: () = conn.del(&key).await?;
+        let mut conn = self.conn();
 
-        Ok(())
+        self.run("DEL", async move { conn.del(&key).await }).await
     }
 
     async fn compare_and_swap(
@@ -134,7 +402,7 @@ impl StateBackend for RedisBackend {
         trace!(key, "Redis CAS (using Lua script)");
 
         let key = self.key(key);
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
 
         // Lua script for atomic CAS
         let script = redis::Script::new(
@@ -149,11 +417,15 @@ impl StateBackend for RedisBackend {
             "#,
         );
 
-        let result: i32 = script
-            .key(&key)
-            .arg(expected)
-            .arg(new_value)
-            .invoke_async(&mut conn)
+        let result: i32 = self
+            .run("CAS", async move {
+                script
+                    .key(&key)
+                    .arg(expected)
+                    .arg(new_value)
+                    .invoke_async(&mut conn)
+                    .await
+            })
             .await?;
 
         Ok(result == 1)
@@ -163,62 +435,74 @@ impl StateBackend for RedisBackend {
         trace!(key, ttl_secs = ttl.as_secs(), "Redis EXPIRE");
 
         let key = self.key(key);
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
 
-        let result: bool = conn.expire(&key, ttl.as_secs() as i64).await?;
-
-        Ok(result)
+        self.run("EXPIRE", async move {
+            conn.expire(&key, ttl.as_secs() as i64).await
+        })
+        .await
     }
 
     async fn mget(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>> {
         trace!(count = keys.len(), "Redis MGET");
 
-        let prefixed_keys: Vec<String> = keys.iter().map(|k| self.key(k)).collect();
-        let mut conn = self.client.clone();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let values: Vec<Option<Vec<u8>>> = conn.get(&prefixed_keys).await?;
+        let prefixed_keys: Vec<String> = keys.iter().map(|k| self.key(k)).collect();
+        let mut conn = self.conn();
 
-        Ok(values)
+        self.run("MGET", async move { conn.mget(&prefixed_keys).await })
+            .await
     }
 
     async fn mset(&self, items: Vec<(String, Vec<u8>, Option<Duration>)>) -> Result<()> {
         trace!(count = items.len(), "Redis MSET (pipelined)");
 
-        let mut conn = self.client.clone();
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.conn();
         let mut pipe = redis::pipe();
 
         for (key, value, ttl) in items {
             let key = self.key(&key);
             if let Some(ttl) = ttl {
-                pipe.set_ex(&key, value, ttl.as_secs());
+                pipe.set_ex(&key, value, ttl.as_secs()).ignore();
             } else {
-                pipe.set(&key, value);
+                pipe.set(&key, value).ignore();
             }
         }
 
-        let _: () = pipe.query_async(&mut conn).await?;
-
-        Ok(())
+        self.run("MSET", async move { pipe.query_async(&mut conn).await })
+            .await
     }
 
     async fn mdel(&self, keys: &[String]) -> Result<()> {
         trace!(count = keys.len(), "Redis DEL (multiple)");
 
-        let prefixed_keys: Vec<String> = keys.iter().map(|k| self.key(k)).collect();
-        let mut conn = self.client.clone();
+        if keys.is_empty() {
+            return Ok(());
+        }
 
-        let _: () = conn.del(&prefixed_keys).await?;
+        let prefixed_keys: Vec<String> = keys.iter().map(|k| self.key(k)).collect();
+        let mut conn = self.conn();
 
-        Ok(())
+        self.run("DEL", async move { conn.del(&prefixed_keys).await })
+            .await
     }
 
     async fn keys(&self, pattern: &str) -> Result<Vec<String>> {
         trace!(pattern, "Redis KEYS");
 
         let pattern = self.key(pattern);
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
 
-        let keys: Vec<String> = conn.keys(&pattern).await?;
+        let keys: Vec<String> = self
+            .run("KEYS", async move { conn.keys(&pattern).await })
+            .await?;
 
         // Remove prefix from results
         let keys = keys.into_iter().map(|k| self.unprefix(&k)).collect();
@@ -229,31 +513,38 @@ impl StateBackend for RedisBackend {
     async fn flush(&self) -> Result<()> {
         debug!("Redis FLUSHDB");
 
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
 
         // Use SCAN and DEL with prefix to avoid flushing the entire DB
         if let Some(ref prefix) = self.prefix {
             let pattern = format!("{prefix}:*");
-            let keys: Vec<String> = conn.keys(&pattern).await?;
+            let keys: Vec<String> = self
+                .run("KEYS", async { conn.keys(&pattern).await })
+                .await?;
             if !keys.is_empty() {
-                let _: () = conn.del(&keys).await?;
+                self.run::<()>("DEL", async { conn.del(&keys).await })
+                    .await?;
             }
         } else {
             // No prefix - flush entire DB (dangerous!)
-            let _: () = redis::cmd("FLUSHDB").query_async(&mut conn).await?;
+            self.run::<()>("FLUSHDB", async {
+                redis::cmd("FLUSHDB").query_async(&mut conn).await
+            })
+            .await?;
         }
 
         Ok(())
     }
 
     async fn health_check(&self) -> Result<()> {
-        let mut conn = self.client.clone();
+        let mut conn = self.conn();
 
         // PING command
-        let response: String = redis::cmd("PING")
-            .query_async(&mut conn)
-            .await
-            .map_err(|e| Error::Backend(e.to_string()))?;
+        let response: String = self
+            .run("PING", async move {
+                redis::cmd("PING").query_async(&mut conn).await
+            })
+            .await?;
 
         if response == "PONG" {
             Ok(())
@@ -361,4 +652,109 @@ mod tests {
 
         assert!(backend.health_check().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_stalled_operation_times_out_and_degrades() {
+        let health = HealthState::default();
+
+        let err = guarded(
+            &health,
+            Duration::from_millis(20),
+            "GET",
+            std::future::pending::<RedisResult<()>>(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)), "{err}");
+
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot.status, HealthStatus::Degraded);
+        assert_eq!(snapshot.consecutive_failures, 1);
+
+        guarded(&health, Duration::from_millis(20), "GET", async { Ok(()) })
+            .await
+            .unwrap();
+        assert_eq!(health.snapshot().status, HealthStatus::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_command_errors_do_not_degrade() {
+        let health = HealthState::default();
+
+        let err = guarded(&health, Duration::from_secs(1), "INCRBY", async {
+            Err::<(), _>(RedisError::from((redis::ErrorKind::TypeError, "WRONGTYPE")))
+        })
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, Error::Backend(_)), "{err}");
+        assert_eq!(health.snapshot().status, HealthStatus::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_redis_reconnects_after_dropped_connection() {
+        let Some(backend) = setup().await else {
+            return;
+        };
+        backend.set("reconnect", b"v".to_vec(), None).await.unwrap();
+
+        // Drop every client connection, ours included
+        let mut admin = redis::Client::open("redis://127.0.0.1:6379")
+            .unwrap()
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let _: RedisResult<()> = redis::cmd("CLIENT")
+            .arg(&["KILL", "TYPE", "normal", "SKIPME", "no"])
+            .query_async(&mut admin)
+            .await;
+
+        // In-flight commands may fail once; the pool reconnects behind them
+        let mut value = None;
+        for _ in 0..20 {
+            if let Ok(v) = backend.get("reconnect").await {
+                value = v;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(value, Some(b"v".to_vec()));
+        assert_eq!(backend.health().status, HealthStatus::Connected);
+        backend.delete("reconnect").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_redis_pipelined_batches() {
+        let Some(backend) = setup().await else {
+            return;
+        };
+
+        let items: Vec<_> = (0..50)
+            .map(|i| (format!("batch:{i}"), i.to_string().into_bytes(), None))
+            .collect();
+        let keys: Vec<String> = items.iter().map(|(k, _, _)| k.clone()).collect();
+        backend.mset(items).await.unwrap();
+
+        let values = backend.mget(&keys).await.unwrap();
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(value.as_deref(), Some(i.to_string().as_bytes()));
+        }
+
+        let counts = backend
+            .increment_many(&[
+                ("batch:c".to_string(), 1, None),
+                ("batch:c".to_string(), 2, Some(Duration::from_secs(60))),
+                ("batch:d".to_string(), 5, None),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(counts, vec![1, 3, 5]);
+
+        backend.mdel(&keys).await.unwrap();
+        backend
+            .mdel(&["batch:c".to_string(), "batch:d".to_string()])
+            .await
+            .unwrap();
+    }
 }
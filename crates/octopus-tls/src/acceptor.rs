@@ -145,6 +145,18 @@ where
     server_conn.server_name().map(String::from)
 }
 
+/// The application protocol negotiated through ALPN (`h2`, `http/1.1`), if
+/// the client offered one the server supports.
+pub fn extract_alpn_protocol<IO>(
+    tls_stream: &tokio_rustls::server::TlsStream<IO>,
+) -> Option<Vec<u8>>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    let (_, server_conn) = tls_stream.get_ref();
+    server_conn.alpn_protocol().map(<[u8]>::to_vec)
+}
+
 /// Parse a DER-encoded X.509 certificate and extract the subject CN
 fn extract_cn_from_der(der: &[u8]) -> Option<String> {
     let (_, cert) = X509Certificate::from_der(der).ok()?;
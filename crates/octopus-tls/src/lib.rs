@@ -24,8 +24,8 @@ pub mod reloadable;
 pub mod sni;
 
 pub use acceptor::{
-    build_server_config, extract_client_cn, extract_server_name, TlsAcceptor, TlsClientCn,
-    TlsSniName,
+    build_server_config, extract_alpn_protocol, extract_client_cn, extract_server_name,
+    TlsAcceptor, TlsClientCn, TlsSniName,
 };
 pub use config::TlsConfig;
 pub use loader::{load_certificates, load_private_key, CertificateReloader};
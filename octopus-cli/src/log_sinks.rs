@@ -0,0 +1,358 @@
+//! Per-category log sinks
+//!
+//! Access logs (events on [`ACCESS_LOG_TARGET`]), audit logs (events on
+//! [`AUDIT_LOG_TARGET`]) and the remaining application logs can each go to
+//! their own output in their own format, as configured under
+//! `observability.logging.categories`. Every configured category becomes one
+//! `fmt` layer filtered to its events; the app layer takes whatever no other
+//! layer claims. Audit events arrive already encoded as JSON, so a JSON audit
+//! sink embeds them as objects rather than as escaped strings.
+
+use anyhow::{Context, Result};
+use octopus_config::types::{LogLineFormat, LogSinkConfig, LoggingConfig};
+use octopus_middleware::{ACCESS_LOG_TARGET, AUDIT_LOG_TARGET};
+use std::fmt::Write as _;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// A type-erased layer, so sinks with different formats share a `Vec`.
+pub(crate) type BoxedLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+/// Build one layer per log category.
+///
+/// Sinks without a format of their own use `default_format`, as does the
+/// stdout app sink used when `app` isn't configured. `open` turns a sink's
+/// `output` into a writer.
+pub(crate) fn layers<S>(
+    logging: &LoggingConfig,
+    default_format: LogLineFormat,
+    open: impl Fn(&str) -> Result<BoxMakeWriter>,
+) -> Result<Vec<BoxedLayer<S>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + 'static,
+{
+    let categories = &logging.categories;
+    let mut claimed = Vec::new();
+    let mut layers = Vec::new();
+    for (target, sink, embed_json) in [
+        (ACCESS_LOG_TARGET, &categories.access, false),
+        (AUDIT_LOG_TARGET, &categories.audit, true),
+    ] {
+        if let Some(sink) = sink {
+            let layer = sink_layer(sink, default_format, &open, embed_json)?;
+            layers.push(
+                layer
+                    .with_filter(filter_fn(move |meta| meta.target() == target))
+                    .boxed(),
+            );
+            claimed.push(target);
+        }
+    }
+
+    let app = categories.app.clone().unwrap_or_default();
+    let layer = sink_layer(&app, default_format, &open, false)?;
+    layers.push(
+        layer
+            .with_filter(filter_fn(move |meta| !claimed.contains(&meta.target())))
+            .boxed(),
+    );
+    Ok(layers)
+}
+
+/// Open a sink's output: `stdout`, `stderr`, or a file appended to.
+pub(crate) fn open_output(output: &str) -> Result<BoxMakeWriter> {
+    Ok(match output {
+        "stdout" => BoxMakeWriter::new(std::io::stdout),
+        "stderr" => BoxMakeWriter::new(std::io::stderr),
+        path => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open log file {path}"))?;
+            BoxMakeWriter::new(std::sync::Mutex::new(file))
+        }
+    })
+}
+
+/// Build a sink's layer; with `embed_json`, JSON lines embed fields that
+/// hold encoded JSON objects instead of quoting them.
+fn sink_layer<S>(
+    sink: &LogSinkConfig,
+    default_format: LogLineFormat,
+    open: &impl Fn(&str) -> Result<BoxMakeWriter>,
+    embed_json: bool,
+) -> Result<BoxedLayer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + 'static,
+{
+    let writer = open(&sink.output)?;
+    // Colour only for terminals, never in files.
+    let ansi = matches!(sink.output.as_str(), "stdout" | "stderr");
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_target(false);
+    Ok(match sink.format.unwrap_or(default_format) {
+        LogLineFormat::Text => layer.with_level(true).boxed(),
+        LogLineFormat::Json if embed_json => layer.event_format(EmbeddedJson).boxed(),
+        LogLineFormat::Json => layer.json().boxed(),
+        LogLineFormat::Message => layer.event_format(MessageOnly).boxed(),
+    })
+}
+
+/// Writes an event's message and nothing else, for lines that were already
+/// rendered (access log templates, JSON audit events).
+struct MessageOnly;
+
+impl<S, N> FormatEvent<S, N> for MessageOnly
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut message = Message::default();
+        event.record(&mut message);
+        writeln!(writer, "{}", message.0)
+    }
+}
+
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.push_str(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        }
+    }
+}
+
+/// JSON lines shaped like `tracing`'s own (`timestamp`, `level`, `fields`),
+/// except that a field holding an encoded JSON object is embedded as that
+/// object.
+struct EmbeddedJson;
+
+impl<S, N> FormatEvent<S, N> for EmbeddedJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let line = serde_json::json!({
+            "timestamp": timestamp,
+            "level": event.metadata().level().to_string(),
+            "fields": fields.0,
+        });
+        writeln!(writer, "{line}")
+    }
+}
+
+#[derive(Default)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl JsonFields {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        self.0.insert(field.name().to_string(), value);
+    }
+
+    fn insert_text(&mut self, field: &Field, text: String) {
+        let value = match serde_json::from_str(&text) {
+            Ok(object @ serde_json::Value::Object(_)) => object,
+            _ => serde_json::Value::String(text),
+        };
+        self.insert(field, value);
+    }
+}
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert_text(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert_text(field, format!("{value:?}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octopus_config::types::LogCategories;
+    use std::collections::HashMap;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// An in-memory log output
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn sink(output: &str, format: Option<LogLineFormat>) -> Option<LogSinkConfig> {
+        Some(LogSinkConfig {
+            output: output.to_string(),
+            format,
+            buffer: None,
+        })
+    }
+
+    /// Emit one event per category through sinks built from `categories`,
+    /// returning what each named output received.
+    fn capture(categories: LogCategories) -> HashMap<String, String> {
+        let logging = LoggingConfig {
+            level: "info".to_string(),
+            format: "text".to_string(),
+            categories,
+        };
+        let buffers: Arc<Mutex<HashMap<String, Buffer>>> = Arc::default();
+        let open = |output: &str| {
+            let buffer = buffers
+                .lock()
+                .unwrap()
+                .entry(output.to_string())
+                .or_default()
+                .clone();
+            Ok(BoxMakeWriter::new(move || buffer.clone()))
+        };
+        let layers = layers(&logging, LogLineFormat::Text, open).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                target: ACCESS_LOG_TARGET,
+                "127.0.0.1 - - \"GET /orders HTTP/1.1\" 200"
+            );
+            tracing::info!(target: AUDIT_LOG_TARGET, "{{\"event_type\":\"auth_failure\"}}");
+            tracing::warn!(
+                target: AUDIT_LOG_TARGET,
+                audit_event = %"{\"event_type\":\"security_violation\"}",
+                status = 403,
+                "Security-critical audit event"
+            );
+            tracing::info!(upstream = "orders", "Upstream registered");
+        });
+
+        let buffers = buffers.lock().unwrap();
+        buffers
+            .iter()
+            .map(|(name, buffer)| (name.clone(), buffer.contents()))
+            .collect()
+    }
+
+    #[test]
+    fn categories_go_to_their_own_sinks_in_their_own_formats() {
+        let outputs = capture(LogCategories {
+            app: sink("app.log", Some(LogLineFormat::Text)),
+            access: sink("access.log", Some(LogLineFormat::Message)),
+            audit: sink("audit.log", Some(LogLineFormat::Json)),
+        });
+
+        assert_eq!(
+            outputs["access.log"],
+            "127.0.0.1 - - \"GET /orders HTTP/1.1\" 200\n"
+        );
+
+        // Audit events already encoded as JSON are embedded, not re-quoted.
+        let audit: Vec<serde_json::Value> = outputs["audit.log"]
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(audit.len(), 2, "{audit:?}");
+        assert_eq!(audit[0]["level"], "INFO");
+        assert_eq!(audit[0]["fields"]["message"]["event_type"], "auth_failure");
+        assert_eq!(audit[1]["level"], "WARN");
+        assert_eq!(
+            audit[1]["fields"]["audit_event"]["event_type"],
+            "security_violation"
+        );
+        assert_eq!(audit[1]["fields"]["status"], 403);
+        assert_eq!(
+            audit[1]["fields"]["message"],
+            "Security-critical audit event"
+        );
+
+        let app = &outputs["app.log"];
+        assert_eq!(app.lines().count(), 1, "{app}");
+        assert!(app.contains(" INFO "), "{app}");
+        assert!(
+            app.contains("Upstream registered upstream=\"orders\""),
+            "{app}"
+        );
+    }
+
+    #[test]
+    fn unconfigured_categories_fall_back_to_the_app_sink() {
+        let outputs = capture(LogCategories {
+            app: sink("app.log", None),
+            access: sink("access.log", None),
+            audit: None,
+        });
+
+        // The default format applies where a sink sets none.
+        assert!(outputs["access.log"].contains(" INFO "));
+        let app = &outputs["app.log"];
+        assert!(app.contains("auth_failure"), "{app}");
+        assert!(app.contains("Upstream registered"), "{app}");
+        assert!(!app.contains("GET /orders"), "{app}");
+    }
+}
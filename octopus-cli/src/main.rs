@@ -1,13 +1,17 @@
 //! Octopus CLI
 
 mod gen;
+mod log_sinks;
+mod test_route;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use octopus_config::types::LogLineFormat;
 use octopus_config::{load_and_merge, load_config};
-use octopus_runtime::{ServerBuilder, SignalHandler};
+use octopus_runtime::{ServerBuilder, SignalHandler, WorkerConfig, WorkerPool};
 use opentelemetry_otlp::WithExportConfig;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
@@ -41,6 +45,26 @@ enum Commands {
         config: Vec<PathBuf>,
     },
 
+    /// Show how a request would be routed, without starting the server.
+    /// Exits non-zero when no route matches.
+    TestRoute {
+        /// Config file(s) or directory
+        #[arg(short, long, default_value = "config.yaml")]
+        config: Vec<PathBuf>,
+
+        /// Request method
+        #[arg(short, long, default_value = "GET")]
+        method: String,
+
+        /// Request path, e.g. `/users/123`
+        #[arg(short, long)]
+        path: String,
+
+        /// Request host, for host-scoped routes
+        #[arg(long, default_value = "")]
+        host: String,
+    },
+
     /// Generate config, schema, and TypeScript client from API specs
     Gen {
         /// Path to octopus-gen.yaml configuration file
@@ -55,8 +79,7 @@ enum Commands {
     Version,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     // rustls 0.23 is compiled here with multiple crypto backends (aws-lc-rs from
     // the rustls default + ring via kube's `rustls-tls`), so the process-level
     // CryptoProvider is ambiguous. Install one explicitly before any TLS client
@@ -71,38 +94,69 @@ async fn main() -> Result<()> {
     match cli.command {
         Commands::Serve { config, log_level } => {
             // Load configuration first so logging can honor observability.logging
-            // (level/format). The CLI --log-level still overrides the config level.
+            // (level/format) and the runtime can honor gateway.workers & co.
+            // The CLI --log-level still overrides the config level.
             let config = load_config_paths(&config)?;
-            init_tracing(log_level.as_deref(), Some(&config.observability))?;
-
-            tracing::info!("Starting Octopus API Gateway");
-            tracing::info!(
-                listen = %config.gateway.listen,
-                workers = config.gateway.workers,
-                "Configuration loaded"
-            );
-
-            // Build server
-            let server = ServerBuilder::new().config(config).build().await?;
-
-            // Setup signal handler
-            let shutdown_signal = server.shutdown_signal();
-            tokio::spawn(async move {
-                let handler = SignalHandler::new(shutdown_signal);
-                handler.run().await;
-            });
+            let worker_pool = Arc::new(WorkerPool::new(WorkerConfig::from_gateway(
+                &config.gateway,
+            ))?);
+            worker_pool
+                .build_runtime()?
+                .block_on(serve(config, log_level, worker_pool))
+        }
 
-            // Run server
-            tracing::info!("Server starting...");
-            server.run().await?;
+        command => tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?
+            .block_on(run(command)),
+    }
+}
 
-            // Flush any pending OTLP trace batches before exit (no-op if tracing
-            // is disabled).
-            opentelemetry::global::shutdown_tracer_provider();
+/// Run the gateway until it is shut down by a signal.
+async fn serve(
+    config: octopus_config::Config,
+    log_level: Option<String>,
+    worker_pool: Arc<WorkerPool>,
+) -> Result<()> {
+    init_tracing(log_level.as_deref(), Some(&config.observability))?;
+
+    tracing::info!("Starting Octopus API Gateway");
+    tracing::info!(
+        listen = %config.gateway.listen,
+        workers = config.gateway.workers,
+        "Configuration loaded"
+    );
+
+    // Build server
+    let server = ServerBuilder::new()
+        .config(config)
+        .worker_pool(worker_pool)
+        .build()
+        .await?;
+
+    // Setup signal handler
+    let shutdown_signal = server.shutdown_signal();
+    tokio::spawn(async move {
+        let handler = SignalHandler::new(shutdown_signal);
+        handler.run().await;
+    });
+
+    // Run server
+    tracing::info!("Server starting...");
+    server.run().await?;
+
+    // Flush any pending OTLP trace batches before exit (no-op if tracing
+    // is disabled).
+    opentelemetry::global::shutdown_tracer_provider();
+
+    tracing::info!("Server stopped");
+    Ok(())
+}
 
-            tracing::info!("Server stopped");
-            Ok(())
-        }
+/// Run any command other than `serve`.
+async fn run(command: Commands) -> Result<()> {
+    match command {
+        Commands::Serve { .. } => unreachable!("serve builds its own runtime"),
 
         Commands::Validate { config } => {
             tracing_subscriber::fmt().with_target(false).init();
@@ -125,6 +179,27 @@ async fn main() -> Result<()> {
             }
         }
 
+        Commands::TestRoute {
+            config,
+            method,
+            path,
+            host,
+        } => {
+            let cfg = match load_config_paths(&config) {
+                Ok(cfg) => cfg,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    std::process::exit(2);
+                }
+            };
+            let (report, matched) = test_route::report(&cfg, &method, &path, &host);
+            print!("{report}");
+            if !matched {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+
         Commands::Gen { config } => {
             init_tracing(Some("info"), None)?;
 
@@ -224,13 +299,6 @@ fn load_config_paths(paths: &[PathBuf]) -> octopus_core::Result<octopus_config::
     load_and_merge(all_files)
 }
 
-/// Log output format.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum LogFormat {
-    Text,
-    Json,
-}
-
 /// Resolve the effective log level and output format.
 ///
 /// Level precedence: explicit `--log-level` > `observability.logging.level` >
@@ -239,7 +307,7 @@ enum LogFormat {
 fn resolve_logging(
     cli_level: Option<&str>,
     obs: Option<&octopus_config::types::ObservabilityConfig>,
-) -> (tracing::Level, LogFormat) {
+) -> (tracing::Level, LogLineFormat) {
     let level_str = cli_level
         .or_else(|| obs.map(|o| o.logging.level.as_str()))
         .unwrap_or("info");
@@ -252,8 +320,8 @@ fn resolve_logging(
         _ => tracing::Level::INFO,
     };
     let format = match obs.map(|o| o.logging.format.to_lowercase()).as_deref() {
-        Some("json") => LogFormat::Json,
-        _ => LogFormat::Text,
+        Some("json") => LogLineFormat::Json,
+        _ => LogLineFormat::Text,
     };
     (level, format)
 }
@@ -301,19 +369,17 @@ fn init_tracing(
         None => None,
     };
 
-    let registry = tracing_subscriber::registry().with(filter).with(otel_layer);
-    match format {
-        LogFormat::Json => registry
-            .with(tracing_subscriber::fmt::layer().with_target(false).json())
-            .init(),
-        LogFormat::Text => registry
-            .with(
-                tracing_subscriber::fmt::layer()
-                    .with_target(false)
-                    .with_level(true),
-            )
-            .init(),
-    }
+    // One output per log category (access, audit, app); without
+    // `observability.logging.categories` everything goes to stdout.
+    let default_logging = octopus_config::types::ObservabilityConfig::default().logging;
+    let logging = obs.map_or(&default_logging, |o| &o.logging);
+    let sinks = log_sinks::layers(logging, format, log_sinks::open_output)?;
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(otel_layer)
+        .with(sinks)
+        .init();
 
     Ok(())
 }
@@ -328,6 +394,7 @@ mod tests {
             logging: LoggingConfig {
                 level: level.to_string(),
                 format: format.to_string(),
+                categories: Default::default(),
             },
             ..Default::default()
         }
@@ -351,14 +418,14 @@ mod tests {
     fn defaults_to_info_text() {
         let (level, format) = resolve_logging(None, None);
         assert_eq!(level, tracing::Level::INFO);
-        assert_eq!(format, LogFormat::Text);
+        assert_eq!(format, LogLineFormat::Text);
     }
 
     #[test]
     fn json_format_from_config() {
         let obs = obs_with("info", "json");
         let (_, format) = resolve_logging(None, Some(&obs));
-        assert_eq!(format, LogFormat::Json);
+        assert_eq!(format, LogLineFormat::Json);
     }
 
     #[test]
@@ -0,0 +1,83 @@
+//! `octopus test-route`: resolve a request against a config offline
+//!
+//! Builds the router the config describes and runs the same match the
+//! gateway would, printing the route, its path params, the upstream and the
+//! instance the load balancer picks, or why nothing matched. Nothing is
+//! started and no upstream is contacted.
+
+use http::Method;
+use octopus_config::Config;
+use octopus_router::{HostMatch, Router};
+use std::fmt::Write as _;
+
+/// The report for one request and whether a route matched.
+pub(crate) fn report(config: &Config, method: &str, path: &str, host: &str) -> (String, bool) {
+    let router = match octopus_runtime::build_router(config) {
+        Ok(router) => router,
+        Err(e) => return (format!("error: failed to build router: {e}\n"), false),
+    };
+    let Ok(method) = Method::from_bytes(method.trim().to_ascii_uppercase().as_bytes()) else {
+        return (format!("error: invalid method '{method}'\n"), false);
+    };
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    };
+    describe(&router, &method, &path, host)
+}
+
+fn describe(router: &Router, method: &Method, path: &str, host: &str) -> (String, bool) {
+    let mut out = String::new();
+    let explanation = router.explain(host, method, path);
+    let Some(matched) = explanation.matched else {
+        let reason = explanation.reason.unwrap_or_default();
+        let _ = writeln!(out, "no match: {reason}");
+        return (out, false);
+    };
+
+    let route = &matched.route;
+    let _ = writeln!(out, "route: {} {}", route.method, route.path);
+    if !matches!(route.host, HostMatch::Any) {
+        let _ = writeln!(out, "host: {}", host_pattern(&route.host));
+    }
+    let mut params: Vec<_> = matched.params.iter().collect();
+    params.sort();
+    for (name, value) in params {
+        let _ = writeln!(out, "param: {name}={value}");
+    }
+    if let Some(wildcard) = &matched.wildcard {
+        let _ = writeln!(out, "wildcard: {wildcard}");
+    }
+    if let Some(to) = &matched.redirect_to {
+        let _ = writeln!(out, "redirect: {to}");
+        return (out, true);
+    }
+
+    if route.convention.is_some() {
+        let _ = writeln!(out, "upstream: derived from the host at request time");
+        return (out, true);
+    }
+    let _ = writeln!(out, "upstream: {}", route.upstream_name);
+    match router.select_instance(&route.upstream_name) {
+        Ok(instance) => {
+            let _ = writeln!(
+                out,
+                "instance: {} ({}:{})",
+                instance.id, instance.address, instance.port
+            );
+        }
+        Err(e) => {
+            let _ = writeln!(out, "instance: none ({e})");
+        }
+    }
+    (out, true)
+}
+
+fn host_pattern(host: &HostMatch) -> String {
+    match host {
+        HostMatch::Any => "*".to_string(),
+        HostMatch::Wildcard(suffix) => format!("*{suffix}"),
+        HostMatch::Exact(host) => host.clone(),
+    }
+}
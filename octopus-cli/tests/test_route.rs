@@ -0,0 +1,82 @@
+//! `octopus test-route` run against a config file
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const CONFIG: &str = r#"
+gateway:
+  listen: "127.0.0.1:8080"
+upstreams:
+  - name: users
+    instances:
+      - id: users-1
+        host: 10.0.0.5
+        port: 9001
+routes:
+  - path: /users/:id
+    methods: [GET]
+    upstream: users
+"#;
+
+fn write_config(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "octopus-test-route-{name}-{}.yaml",
+        std::process::id()
+    ));
+    std::fs::write(&path, CONFIG).unwrap();
+    path
+}
+
+fn test_route(name: &str, method: &str, path: &str) -> Output {
+    let config = write_config(name);
+    let output = Command::new(env!("CARGO_BIN_EXE_octopus"))
+        .args(["test-route", "--config"])
+        .arg(&config)
+        .args([
+            "--method",
+            method,
+            "--path",
+            path,
+            "--host",
+            "api.example.com",
+        ])
+        .output()
+        .unwrap();
+    std::fs::remove_file(config).unwrap();
+    output
+}
+
+#[test]
+fn matching_path_prints_route_params_upstream_and_instance() {
+    let output = test_route("match", "GET", "/users/123");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(0), "{stdout}");
+    assert_eq!(
+        stdout,
+        "route: GET /users/:id\n\
+         param: id=123\n\
+         upstream: users\n\
+         instance: users-1 (10.0.0.5:9001)\n"
+    );
+}
+
+#[test]
+fn unmatched_path_prints_the_reason_and_fails() {
+    let output = test_route("no-match", "GET", "/orders/7");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert_eq!(
+        stdout,
+        "no match: no route pattern matches path /orders/7\n"
+    );
+
+    let output = test_route("wrong-method", "DELETE", "/users/123");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert_eq!(
+        stdout,
+        "no match: path /users/123 is routed for GET but not DELETE\n"
+    );
+}
@@ -7,6 +7,8 @@
 //! - JWT token validation
 //! - Configurable secret key
 //! - Optional routes (can skip auth for certain paths)
+//! - Configurable claim-to-header forwarding, with nested claims and arrays
+//! - Optional removal of the Authorization header before forwarding
 //! - Custom error responses
 //!
 //! ## Example
@@ -19,7 +21,12 @@
 //! let mut plugin = JwtAuthPlugin::new();
 //! plugin.init(serde_json::json!({
 //!     "secret": "my-secret-key",
-//!     "skip_routes": ["/health", "/metrics"]
+//!     "skip_routes": ["/health", "/metrics"],
+//!     "claim_headers": {
+//!         "sub": "x-user-id",
+//!         "realm_access.roles": "x-user-roles"
+//!     },
+//!     "strip_authorization": true
 //! })).await?;
 //! # Ok(())
 //! # }
@@ -27,11 +34,14 @@
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use http::{Request, Response, StatusCode};
+use http::{HeaderName, HeaderValue, Request, Response, StatusCode};
 use http_body_util::Full;
 use jsonwebtoken::{decode, DecodingKey, Validation};
+use octopus_core::json_path::claim_header_value;
 use octopus_plugin_api::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use tracing::{debug, warn};
 
 /// JWT Authentication Plugin
@@ -60,6 +70,20 @@ pub struct JwtAuthConfig {
     /// Whether to require authentication (if false, only validates if present)
     #[serde(default = "default_require_auth")]
     pub require_auth: bool,
+
+    /// Claims forwarded to the upstream: claim path (`realm_access.roles`,
+    /// `$['https://example.com/tenant']`) → header name. Array claims are
+    /// joined with `array_separator`, as by the gateway's JWT middleware.
+    #[serde(default = "default_claim_headers")]
+    pub claim_headers: BTreeMap<String, String>,
+
+    /// Separator joining the elements of an array claim
+    #[serde(default = "default_array_separator")]
+    pub array_separator: String,
+
+    /// Remove the Authorization header before forwarding
+    #[serde(default)]
+    pub strip_authorization: bool,
 }
 
 fn default_algorithm() -> String {
@@ -70,6 +94,17 @@ fn default_require_auth() -> bool {
     true
 }
 
+fn default_claim_headers() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("sub".to_string(), "x-auth-user".to_string()),
+        ("roles".to_string(), "x-auth-roles".to_string()),
+    ])
+}
+
+fn default_array_separator() -> String {
+    ",".to_string()
+}
+
 impl Default for JwtAuthConfig {
     fn default() -> Self {
         Self {
@@ -77,6 +112,9 @@ impl Default for JwtAuthConfig {
             skip_routes: vec![],
             algorithm: default_algorithm(),
             require_auth: true,
+            claim_headers: default_claim_headers(),
+            array_separator: default_array_separator(),
+            strip_authorization: false,
         }
     }
 }
@@ -88,6 +126,9 @@ pub struct Claims {
     pub exp: usize,
     #[serde(default)]
     pub roles: Vec<String>,
+    /// Any other claims
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
 }
 
 impl JwtAuthPlugin {
@@ -128,6 +169,32 @@ impl JwtAuthPlugin {
             .map_err(|e| PluginError::auth(format!("Invalid JWT token: {}", e)))
     }
 
+    /// Remove the configured claim headers, which only this plugin may set
+    fn remove_claim_headers(&self, req: &mut Request<Full<Bytes>>) {
+        for header in self.config.claim_headers.values() {
+            req.headers_mut().remove(header.as_str());
+        }
+    }
+
+    /// Set the configured headers from `claims`
+    fn forward_claims(&self, claims: &Claims, req: &mut Request<Full<Bytes>>) {
+        let claims = serde_json::to_value(claims).unwrap_or_default();
+        for (claim, header) in &self.config.claim_headers {
+            // Checked in `init`
+            let Ok(header) = HeaderName::from_bytes(header.as_bytes()) else {
+                continue;
+            };
+            let value = claim_header_value(&claims, claim, &self.config.array_separator)
+                .and_then(|value| HeaderValue::from_str(&value).ok());
+            if let Some(value) = value {
+                req.headers_mut().insert(header, value);
+            }
+        }
+        if self.config.strip_authorization {
+            req.headers_mut().remove(http::header::AUTHORIZATION);
+        }
+    }
+
     /// Create unauthorized response
     fn unauthorized_response(&self, message: &str) -> Response<Full<Bytes>> {
         Response::builder()
@@ -177,6 +244,15 @@ impl Plugin for JwtAuthPlugin {
             return Err(PluginError::config("JWT secret is required"));
         }
 
+        for (claim, header) in &self.config.claim_headers {
+            if HeaderName::from_bytes(header.as_bytes()).is_err() {
+                return Err(PluginError::config(format!(
+                    "Invalid header name {:?} for claim {:?}",
+                    header, claim
+                )));
+            }
+        }
+
         debug!(
             skip_routes = ?self.config.skip_routes,
             require_auth = self.config.require_auth,
@@ -204,6 +280,9 @@ impl RequestInterceptor for JwtAuthPlugin {
         req: &mut Request<Full<Bytes>>,
         ctx: &RequestContext,
     ) -> Result<InterceptorAction, PluginError> {
+        // Never pass on claim headers the client sent itself
+        self.remove_claim_headers(req);
+
         let path = req.uri().path();
 
         // Skip authentication for configured routes
@@ -242,17 +321,7 @@ impl RequestInterceptor for JwtAuthPlugin {
                 );
 
                 // Inject user info into request headers for upstream services
-                req.headers_mut().insert(
-                    "x-auth-user",
-                    claims.sub.parse().unwrap(),
-                );
-
-                if !claims.roles.is_empty() {
-                    req.headers_mut().insert(
-                        "x-auth-roles",
-                        claims.roles.join(",").parse().unwrap(),
-                    );
-                }
+                self.forward_claims(&claims, req);
 
                 Ok(InterceptorAction::Continue)
             }
@@ -294,8 +363,7 @@ mod tests {
             config: JwtAuthConfig {
                 secret: "test".to_string(),
                 skip_routes: vec!["/health".to_string(), "/api/public/*".to_string()],
-                algorithm: "HS256".to_string(),
-                require_auth: true,
+                ..Default::default()
             },
         };
 
@@ -305,6 +373,53 @@ mod tests {
         assert!(!plugin.should_skip("/api/private"));
     }
 
+    #[tokio::test]
+    async fn test_claims_mapped_to_headers() {
+        let mut plugin = JwtAuthPlugin::new();
+        plugin
+            .init(serde_json::json!({
+                "secret": "test-secret",
+                "claim_headers": {
+                    "sub": "X-User-Id",
+                    "org.tenant": "X-Tenant",
+                    "realm_access.roles": "X-Roles"
+                },
+                "strip_authorization": true
+            }))
+            .await
+            .unwrap();
+
+        let claims = serde_json::json!({
+            "sub": "user-42",
+            "exp": 4_102_444_800u64,
+            "org": { "tenant": "acme" },
+            "realm_access": { "roles": ["admin", "billing"] }
+        });
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"test-secret"),
+        )
+        .unwrap();
+
+        let mut req = Request::builder()
+            .uri("/orders")
+            .header("authorization", format!("Bearer {}", token))
+            .header("x-tenant", "spoofed")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let ctx = RequestContext::new("req-1".to_string(), "127.0.0.1:9000".parse().unwrap());
+        let action = plugin.intercept_request(&mut req, &ctx).await.unwrap();
+        assert!(matches!(action, InterceptorAction::Continue));
+
+        let headers = req.headers();
+        assert_eq!(headers["x-user-id"], "user-42");
+        assert_eq!(headers["x-tenant"], "acme");
+        assert_eq!(headers["x-roles"], "admin,billing");
+        assert!(!headers.contains_key("authorization"));
+        assert!(!headers.contains_key("x-auth-user"));
+    }
+
     #[test]
     fn test_extract_token() {
         let plugin = JwtAuthPlugin::new();